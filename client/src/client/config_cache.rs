@@ -0,0 +1,149 @@
+//! 代理配置缓存
+//!
+//! 每次收到 Controller 推送的代理配置后写入本地缓存文件；启动时连不上 Controller 则先按缓存
+//! 建立隧道，同时在后台继续重连，连上后以 Controller 推送的配置为准。
+//!
+//! 缓存用 AES-256-GCM 加密，密钥由客户端 token 派生：文件被篡改、或 token 更换后旧缓存
+//! 都无法解密，直接忽略。路径由 `OXIPROXY_CONFIG_CACHE` 指定（默认工作目录下的
+//! `oxiproxy-client.cache`），设为 `off` 时不缓存。
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+use common::protocol::client_config::{ServerProxyGroup, VisitorInfo};
+
+const DEFAULT_PATH: &str = "oxiproxy-client.cache";
+/// 文件头：魔数 + 格式版本，同时作为 AEAD 的附加数据
+const HEADER: &[u8; 5] = b"OXCC\x01";
+/// 密钥派生的上下文前缀，避免与其他用途的 token 派生值混淆
+const KEY_CONTEXT: &[u8] = b"oxiproxy-client-config-cache";
+
+/// 缓存的代理配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedConfig {
+    pub client_id: i64,
+    pub client_name: String,
+    pub server_groups: Vec<ServerProxyGroup>,
+    pub visitors: Vec<VisitorInfo>,
+    pub saved_at: DateTime<Utc>,
+}
+
+/// 缓存文件路径，未启用时为 None
+fn cache_path() -> Option<&'static PathBuf> {
+    static PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+    PATH.get_or_init(|| match common::env::var("OXIPROXY_CONFIG_CACHE") {
+        Some(v) if v.eq_ignore_ascii_case("off") => None,
+        Some(v) => Some(PathBuf::from(v)),
+        None => Some(PathBuf::from(DEFAULT_PATH)),
+    })
+    .as_ref()
+}
+
+fn cache_key(token: &str) -> LessSafeKey {
+    let mut material = KEY_CONTEXT.to_vec();
+    material.extend_from_slice(token.as_bytes());
+    let hash = digest(&SHA256, &material);
+    LessSafeKey::new(UnboundKey::new(&AES_256_GCM, hash.as_ref()).expect("SHA-256 输出长度与 AES-256 密钥一致"))
+}
+
+fn encrypt(token: &str, config: &CachedConfig) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new().fill(&mut nonce).map_err(|_| anyhow!("系统随机数不可用"))?;
+
+    let mut sealed = serde_json::to_vec(config)?;
+    cache_key(token)
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(HEADER), &mut sealed)
+        .map_err(|_| anyhow!("加密配置缓存失败"))?;
+
+    let mut data = Vec::with_capacity(HEADER.len() + NONCE_LEN + sealed.len());
+    data.extend_from_slice(HEADER);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&sealed);
+    Ok(data)
+}
+
+fn decrypt(token: &str, data: &[u8]) -> Result<CachedConfig> {
+    let Some(rest) = data.strip_prefix(HEADER.as_slice()) else {
+        bail!("不是有效的配置缓存文件");
+    };
+    if rest.len() < NONCE_LEN {
+        bail!("配置缓存文件不完整");
+    }
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("配置缓存文件不完整"))?;
+
+    let mut sealed = sealed.to_vec();
+    let plain = cache_key(token)
+        .open_in_place(nonce, Aad::from(HEADER), &mut sealed)
+        .map_err(|_| anyhow!("配置缓存校验失败（文件被修改或 token 已更换）"))?;
+    Ok(serde_json::from_slice(plain)?)
+}
+
+/// 保存配置缓存（先写临时文件再替换，避免写到一半时退出留下损坏的缓存）
+pub fn save(token: &str, config: &CachedConfig) -> Result<()> {
+    let Some(path) = cache_path() else {
+        return Ok(());
+    };
+    let data = encrypt(token, config)?;
+
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, data)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))?;
+    }
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// 读取配置缓存，未启用或没有缓存时返回 `Ok(None)`
+pub fn load(token: &str) -> Result<Option<CachedConfig>> {
+    let Some(path) = cache_path() else {
+        return Ok(None);
+    };
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(anyhow!("读取配置缓存 {} 失败: {}", path.display(), e)),
+    };
+    decrypt(token, &data).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let config = CachedConfig {
+            client_id: 7,
+            client_name: "office".to_string(),
+            server_groups: Vec::new(),
+            visitors: Vec::new(),
+            saved_at: Utc::now(),
+        };
+        let data = encrypt("token-a", &config).unwrap();
+
+        let loaded = decrypt("token-a", &data).unwrap();
+        assert_eq!(loaded.client_id, 7);
+        assert_eq!(loaded.client_name, "office");
+
+        // token 更换后无法解密
+        assert!(decrypt("token-b", &data).is_err());
+
+        // 篡改任意字节都会校验失败
+        let mut tampered = data.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(decrypt("token-a", &tampered).is_err());
+        assert!(decrypt("token-a", &data[..HEADER.len() + 4]).is_err());
+    }
+}
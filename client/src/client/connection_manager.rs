@@ -2,24 +2,45 @@
 //!
 //! 管理到多个 Agent Server 的隧道连接。
 //! 根据 Controller 返回的代理列表，动态建立和断开连接。
+//! 每个节点的隧道参数（地址、协议、KCP/QUIC 配置）计算一个配置哈希，
+//! 调和时只重连哈希发生变化的节点，其余隧道保持不动。
+//! 访客代理（本机监听端口）同样按 Controller 推送的列表调和，配置变化时重新监听。
 
 use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
 use tracing::{info, error, warn, debug};
 
 use common::{TunnelConnector, QuicConnector, KcpConnector, TcpTunnelConnector, TunnelProtocol};
-use common::protocol::client_config::ServerProxyGroup;
+use common::protocol::client_config::{ServerProxyGroup, VisitorInfo};
 
 use crate::client::connector;
 use crate::client::log_collector::LogCollector;
+use crate::client::local_pool::LocalPools;
+use crate::client::target_policy::TargetPolicy;
+use crate::client::visitor;
 
 /// 单个 Server 连接的状态
 struct ServerConnection {
     node_id: i64,
+    /// 建立连接时使用的隧道参数哈希
+    config_hash: u64,
     proxy_ids: HashSet<i64>,
+    /// 本地目标策略（白名单 / 端口黑名单），变更时直接替换，无需重连
+    target_policy: Arc<std::sync::RwLock<TargetPolicy>>,
+    /// 本地服务预连接池，随代理列表更新
+    local_pools: Arc<LocalPools>,
+    cancel_token: tokio_util::sync::CancellationToken,
+    handle: JoinHandle<()>,
+}
+
+/// 运行中的访客代理监听
+struct VisitorListener {
+    info: VisitorInfo,
     cancel_token: tokio_util::sync::CancellationToken,
     handle: JoinHandle<()>,
 }
@@ -27,6 +48,12 @@ struct ServerConnection {
 /// 连接管理器
 pub struct ConnectionManager {
     connections: Arc<RwLock<HashMap<i64, ServerConnection>>>,
+    /// visitor_id -> 访客监听
+    visitors: RwLock<HashMap<i64, VisitorListener>>,
+    /// 上一次调和的完整代理分组哈希，Controller 重连后推送相同配置时直接跳过
+    last_applied: RwLock<Option<u64>>,
+    /// 客户端 ID（连接控制器后获得），隧道认证握手时使用
+    client_id: AtomicI64,
     token: String,
     log_collector: LogCollector,
 }
@@ -35,13 +62,27 @@ impl ConnectionManager {
     pub fn new(token: String, log_collector: LogCollector) -> Self {
         Self {
             connections: Arc::new(RwLock::new(HashMap::new())),
+            visitors: RwLock::new(HashMap::new()),
+            last_applied: RwLock::new(None),
+            client_id: AtomicI64::new(0),
             token,
             log_collector,
         }
     }
 
+    /// 设置客户端 ID（每次连接控制器后调用）
+    pub fn set_client_id(&self, client_id: i64) {
+        self.client_id.store(client_id, Ordering::Relaxed);
+    }
+
     /// 根据新的代理分组列表，调和（reconcile）连接状态
     pub async fn reconcile(&self, server_groups: Vec<ServerProxyGroup>) {
+        let groups_hash = hash_json(&server_groups);
+        if *self.last_applied.read().await == Some(groups_hash) && self.all_running().await {
+            debug!("代理配置未变化，保持现有隧道");
+            return;
+        }
+
         let new_node_ids: HashSet<i64> = server_groups.iter().map(|g| g.node_id).collect();
 
         // 1. 断开不再需要的连接
@@ -61,6 +102,8 @@ impl ConnectionManager {
         // 2. 建立新连接或更新已有连接的代理列表
         for group in server_groups {
             let new_proxy_ids: HashSet<i64> = group.proxies.iter().map(|p| p.proxy_id).collect();
+            let target_policy = TargetPolicy::from_group(&group);
+            let config_hash = tunnel_config_hash(&group);
 
             let needs_connect = {
                 let conns = self.connections.read().await;
@@ -73,6 +116,9 @@ impl ConnectionManager {
                                 group.node_id
                             );
                             true
+                        } else if conn.config_hash != config_hash {
+                            info!("节点 #{} 隧道参数已变更，重新连接", group.node_id);
+                            true
                         } else {
                             // 已有连接且 task 仍在运行，更新代理列表
                             if conn.proxy_ids != new_proxy_ids {
@@ -96,23 +142,69 @@ impl ConnectionManager {
                     let mut conns = self.connections.write().await;
                     if let Some(old_conn) = conns.remove(&group.node_id) {
                         old_conn.cancel_token.cancel();
+                        old_conn.local_pools.clear();
                     }
                 }
-                self.connect(group, new_proxy_ids).await;
+                self.connect(group, config_hash, new_proxy_ids, target_policy).await;
             } else {
-                // 更新代理列表
+                // 更新代理列表和目标策略
                 let mut conns = self.connections.write().await;
                 if let Some(conn) = conns.get_mut(&group.node_id) {
                     conn.proxy_ids = new_proxy_ids;
+                    *conn.target_policy.write().unwrap_or_else(|e| e.into_inner()) = target_policy;
+                    conn.local_pools.update(&group.proxies);
+                }
+            }
+        }
+
+        *self.last_applied.write().await = Some(groups_hash);
+    }
+
+    /// 根据新的访客代理列表调和本机监听：移除已删除或配置变化的，再启动新增的
+    pub async fn reconcile_visitors(&self, visitors: Vec<VisitorInfo>) {
+        let mut listeners = self.visitors.write().await;
+
+        let stale: Vec<i64> = listeners
+            .iter()
+            .filter(|(id, l)| !visitors.iter().any(|v| v.visitor_id == **id && v == &l.info))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in stale {
+            if let Some(listener) = listeners.remove(&id) {
+                info!("停止访客代理 {}", listener.info.name);
+                listener.cancel_token.cancel();
+                // 等待旧监听释放端口，新配置可能仍使用同一端口
+                if tokio::time::timeout(std::time::Duration::from_secs(5), listener.handle).await.is_err() {
+                    warn!("访客代理 {} 未能在 5 秒内停止", listener.info.name);
                 }
             }
         }
+
+        for info in visitors {
+            if listeners.contains_key(&info.visitor_id) {
+                continue;
+            }
+            let cancel_token = tokio_util::sync::CancellationToken::new();
+            let handle = tokio::spawn(visitor::run(info.clone(), cancel_token.clone()));
+            listeners.insert(info.visitor_id, VisitorListener { info, cancel_token, handle });
+        }
+    }
+
+    /// 所有节点的连接 task 是否仍在运行
+    async fn all_running(&self) -> bool {
+        self.connections.read().await.values().all(|c| !c.handle.is_finished())
     }
 
     /// 建立到指定 Server 的连接
-    async fn connect(&self, group: ServerProxyGroup, proxy_ids: HashSet<i64>) {
+    async fn connect(
+        &self,
+        group: ServerProxyGroup,
+        config_hash: u64,
+        proxy_ids: HashSet<i64>,
+        target_policy: TargetPolicy,
+    ) {
         let node_id = group.node_id;
-        let server_addr_str = format!("{}:{}", group.server_addr, group.server_port);
+        let server_addr_str = common::utils::join_host_port(&group.server_addr, group.server_port);
         let server_addr: SocketAddr = match server_addr_str.parse() {
             Ok(addr) => addr,
             Err(e) => {
@@ -126,19 +218,26 @@ impl ConnectionManager {
             node_id, server_addr, group.protocol, proxy_ids.len()
         );
 
+        let client_id = self.client_id.load(Ordering::Relaxed);
         let token = self.token.clone();
         let log_collector = self.log_collector.clone();
         let cancel_token = tokio_util::sync::CancellationToken::new();
         let cancel_clone = cancel_token.clone();
         let protocol = group.protocol.clone();
         let kcp_config = group.kcp.clone();
+        let quic_config = group.quic.clone();
+        let target_policy = Arc::new(std::sync::RwLock::new(target_policy));
+        let policy_clone = target_policy.clone();
+        let local_pools = Arc::new(LocalPools::new(target_policy.clone()));
+        local_pools.update(&group.proxies);
+        let pools_clone = local_pools.clone();
 
         let handle = tokio::spawn(async move {
             loop {
                 // 创建连接器
                 let connector: Arc<dyn TunnelConnector> = match protocol {
                     TunnelProtocol::Quic => {
-                        match QuicConnector::new() {
+                        match QuicConnector::new(quic_config.clone()) {
                             Ok(c) => Arc::new(c),
                             Err(e) => {
                                 error!("节点 #{} 创建 QUIC 连接器失败: {}", node_id, e);
@@ -160,8 +259,11 @@ impl ConnectionManager {
                     result = connector::connect_once(
                         connector,
                         server_addr,
+                        client_id,
                         &token,
                         log_collector.clone(),
+                        policy_clone.clone(),
+                        pools_clone.clone(),
                     ) => {
                         match result {
                             Ok(_) => info!("节点 #{} 连接已关闭", node_id),
@@ -192,7 +294,10 @@ impl ConnectionManager {
 
         let conn = ServerConnection {
             node_id,
+            config_hash,
             proxy_ids,
+            target_policy,
+            local_pools,
             cancel_token,
             handle,
         };
@@ -202,6 +307,23 @@ impl ConnectionManager {
     }
 
     /// 断开指定节点的连接
+    /// 断开所有节点连接并等待连接 task 退出（进程退出前调用）
+    pub async fn shutdown(&self) {
+        let conns: Vec<ServerConnection> = self.connections.write().await.drain().map(|(_, c)| c).collect();
+        for conn in &conns {
+            conn.cancel_token.cancel();
+            conn.local_pools.clear();
+        }
+        for (_, listener) in self.visitors.write().await.drain() {
+            listener.cancel_token.cancel();
+        }
+        for conn in conns {
+            if tokio::time::timeout(std::time::Duration::from_secs(5), conn.handle).await.is_err() {
+                warn!("节点 #{} 连接未能在 5 秒内关闭", conn.node_id);
+            }
+        }
+    }
+
     async fn disconnect(&self, node_id: i64) {
         let conn = {
             let mut conns = self.connections.write().await;
@@ -211,7 +333,72 @@ impl ConnectionManager {
         if let Some(conn) = conn {
             info!("断开节点 #{} 连接", node_id);
             conn.cancel_token.cancel();
+            conn.local_pools.clear();
             // 不等待 handle 完成，让它自行退出
         }
     }
 }
+
+/// 计算节点隧道参数的哈希（代理列表由节点侧监听，变化时无需重连隧道）
+fn tunnel_config_hash(group: &ServerProxyGroup) -> u64 {
+    hash_json(&(
+        &group.server_addr,
+        group.server_port,
+        group.protocol,
+        &group.kcp,
+        &group.quic,
+    ))
+}
+
+/// 以 JSON 序列化结果计算哈希（KCP/QUIC 配置未实现 Hash）
+fn hash_json<T: serde::Serialize>(value: &T) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    serde_json::to_string(value).unwrap_or_default().hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::protocol::client_config::ProxyInfo;
+
+    fn group(protocol: TunnelProtocol, proxies: Vec<i64>) -> ServerProxyGroup {
+        ServerProxyGroup {
+            node_id: 1,
+            server_addr: "203.0.113.1".to_string(),
+            server_port: 7000,
+            protocol,
+            kcp: None,
+            quic: None,
+            nat_probe_port: None,
+            proxies: proxies
+                .into_iter()
+                .map(|id| ProxyInfo {
+                    proxy_id: id,
+                    name: format!("p{}", id),
+                    proxy_type: "tcp".to_string(),
+                    local_ip: "127.0.0.1".to_string(),
+                    local_port: 22,
+                    remote_port: 2200 + id as i32,
+                    enabled: true,
+                    local_pool_size: 0,
+                    local_source: None,
+                })
+                .collect(),
+            blocked_targets: Vec::new(),
+            allowed_targets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_tunnel_config_hash() {
+        let base = group(TunnelProtocol::Quic, vec![1]);
+        // 代理列表变化不影响隧道参数
+        assert_eq!(tunnel_config_hash(&base), tunnel_config_hash(&group(TunnelProtocol::Quic, vec![1, 2])));
+        assert_ne!(tunnel_config_hash(&base), tunnel_config_hash(&group(TunnelProtocol::Kcp, vec![1])));
+
+        let mut moved = base.clone();
+        moved.server_port = 7001;
+        assert_ne!(tunnel_config_hash(&base), tunnel_config_hash(&moved));
+    }
+}
@@ -1,50 +1,82 @@
 use anyhow::Result;
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
-use tokio::net::TcpStream;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tracing::{info, error, warn, debug};
 use crate::client::log_collector::LogCollector;
+use crate::client::local_pool::LocalPools;
+use crate::client::target_policy::TargetPolicy;
 
 // 从共享库导入隧道模块
 use common::{TunnelConnection, TunnelConnector, TunnelRecvStream, TunnelSendStream};
-use common::utils::create_configured_udp_socket;
+use common::tunnel::{read_datagram, write_datagram, MAX_DATAGRAM_SIZE};
+use common::source_binding::{self, SourceBinding};
+use common::protocol::handshake::{self, ClientHello, ClientMetadata, HANDSHAKE_TIMEOUT};
 
 // Heartbeat configuration
 const HEARTBEAT_INTERVAL_SECS: u64 = 10;
 const HEARTBEAT_TIMEOUT_SECS: u64 = 15;
 
+/// 旧版（不分帧）UDP 代理流无数据多久后关闭
+const UDP_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// 单次连接尝试（供 controller 模式使用，不含重试循环）
 pub async fn connect_once(
     connector: Arc<dyn TunnelConnector>,
     server_addr: SocketAddr,
+    client_id: i64,
     token: &str,
     log_collector: LogCollector,
+    target_policy: Arc<RwLock<TargetPolicy>>,
+    local_pools: Arc<LocalPools>,
 ) -> Result<()> {
     info!("连接节点: {}", server_addr);
-    connect_to_server(connector, server_addr, token, log_collector).await
+    connect_to_server(connector, server_addr, client_id, token, log_collector, target_policy, local_pools).await
 }
 
 async fn connect_to_server(
     connector: Arc<dyn TunnelConnector>,
     server_addr: SocketAddr,
+    client_id: i64,
     token: &str,
     log_collector: LogCollector,
+    target_policy: Arc<RwLock<TargetPolicy>>,
+    local_pools: Arc<LocalPools>,
 ) -> Result<()> {
     // Connect to server
     let conn = connector.connect(server_addr).await?;
     let conn = Arc::new(conn);
 
-    // Send token for authentication
-    debug!("发送认证令牌");
-    let mut uni_stream = conn.open_uni().await?;
-    let token_bytes = token.as_bytes();
-    let len = token_bytes.len() as u16;
-    uni_stream.write_all(&len.to_be_bytes()).await?;
-    uni_stream.write_all(token_bytes).await?;
-    uni_stream.finish().await?;
+    // Authenticate on the first stream (challenge-response, the token itself is never sent)
+    debug!("认证握手");
+    let metadata = ClientMetadata {
+        instance_id: Some(crate::client::instance_id::get().to_string()),
+        ..ClientMetadata::current(env!("CARGO_PKG_VERSION"))
+    };
+    let hello = ClientHello::new(client_id, &metadata);
+    // 问候可以安全重放，使用 0-RTT 发送；0-RTT 被拒绝时在握手完成后的新流上重新认证
+    let (mut send, mut recv) = conn.open_bi_early().await?;
+    let result = tokio::time::timeout(
+        HANDSHAKE_TIMEOUT,
+        handshake::client_handshake(send.as_mut(), recv.as_mut(), token, &hello),
+    )
+    .await
+    .map_err(|_| anyhow::anyhow!("认证握手超时"))?;
+    if let Err(e) = result {
+        if conn.early_data_accepted().await {
+            return Err(e);
+        }
+        debug!("0-RTT 被拒绝，重新认证握手");
+        let (mut send, mut recv) = conn.open_bi().await?;
+        tokio::time::timeout(
+            HANDSHAKE_TIMEOUT,
+            handshake::client_handshake(send.as_mut(), recv.as_mut(), token, &hello),
+        )
+        .await
+        .map_err(|_| anyhow::anyhow!("认证握手超时"))??;
+    }
 
     info!("节点认证成功: {}", server_addr);
 
@@ -107,6 +139,8 @@ async fn connect_to_server(
                 match result {
                     Ok((quic_send, mut quic_recv)) => {
                         let collector = log_collector.clone();
+                        let policy = target_policy.clone();
+                        let pools = local_pools.clone();
 
                         tokio::spawn(async move {
                             // Read message type (1 byte)
@@ -119,7 +153,7 @@ async fn connect_to_server(
                                 b'p' => {
                                     // 'p' = proxy request
                                     debug!("收到代理请求");
-                                    if let Err(e) = handle_proxy_stream(quic_send, quic_recv).await {
+                                    if let Err(e) = handle_proxy_stream(quic_send, quic_recv, policy, pools).await {
                                         error!("代理流处理错误: {}", e);
                                     }
                                 }
@@ -149,6 +183,8 @@ async fn connect_to_server(
 async fn handle_proxy_stream(
     quic_send: Box<dyn TunnelSendStream>,
     mut quic_recv: Box<dyn TunnelRecvStream>,
+    target_policy: Arc<RwLock<TargetPolicy>>,
+    local_pools: Arc<LocalPools>,
 ) -> Result<()> {
     // Read protocol type (1 byte)
     let mut proto_buf = [0u8; 1];
@@ -165,17 +201,23 @@ async fn handle_proxy_stream(
     let target_addr = String::from_utf8(addr_buf)?;
 
     debug!("目标地址: {}, 协议: {}", target_addr,
-          if protocol_type == b'u' { "UDP" } else { "TCP" });
+          if protocol_type == b'u' || protocol_type == b'U' { "UDP" } else { "TCP" });
+
+    let target = resolve_allowed_target(&target_addr, &target_policy).await?;
 
     // Connect to target service based on protocol type
     match protocol_type {
         b't' => {
             // TCP connection
-            handle_tcp_proxy(quic_send, quic_recv, &target_addr).await?;
+            handle_tcp_proxy(quic_send, quic_recv, &target_addr, target, &local_pools).await?;
         }
         b'u' => {
             // UDP connection
-            handle_udp_proxy(quic_send, quic_recv, &target_addr).await?;
+            handle_udp_proxy(quic_send, quic_recv, target, local_pools.source_for(&target_addr)).await?;
+        }
+        b'U' => {
+            // UDP connection with datagram framing (KCP / TCP tunnels)
+            handle_framed_udp_proxy(quic_send, quic_recv, target, local_pools.source_for(&target_addr)).await?;
         }
         _ => {
             error!("未知协议类型: {}", protocol_type);
@@ -186,15 +228,46 @@ async fn handle_proxy_stream(
     Ok(())
 }
 
+/// 解析目标地址并检查目标策略，按解析后的实际地址匹配，防止通过域名绕过
+pub(crate) async fn resolve_allowed_target(
+    target_addr: &str,
+    target_policy: &RwLock<TargetPolicy>,
+) -> Result<SocketAddr> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host(target_addr).await?.collect();
+    let policy = target_policy.read().unwrap_or_else(|e| e.into_inner());
+    let mut denied = None;
+    for addr in addrs {
+        match policy.check(addr) {
+            Ok(()) => return Ok(addr),
+            Err(reason) => denied = Some((addr, reason)),
+        }
+    }
+    match denied {
+        Some((addr, reason)) => {
+            warn!("拒绝转发到 {} ({}): {}", target_addr, addr, reason);
+            Err(anyhow::anyhow!("目标地址 {} 已被禁止", target_addr))
+        }
+        None => Err(anyhow::anyhow!("无法解析目标地址: {}", target_addr)),
+    }
+}
+
 async fn handle_tcp_proxy(
     mut quic_send: Box<dyn TunnelSendStream>,
     mut quic_recv: Box<dyn TunnelRecvStream>,
     target_addr: &str,
+    target: SocketAddr,
+    local_pools: &LocalPools,
 ) -> Result<()> {
-    // Connect to target service
-    let mut tcp_stream = TcpStream::connect(target_addr).await?;
+    // Connect to target service（优先使用预连接）
+    let mut tcp_stream = match local_pools.take(target_addr, target).await {
+        Some(stream) => {
+            debug!("使用本地预连接: {}", target);
+            stream
+        }
+        None => source_binding::connect_tcp(target, local_pools.source_for(target_addr).as_ref()).await?,
+    };
 
-    debug!("已连接目标服务: {}", target_addr);
+    debug!("已连接目标服务: {}", target);
 
     let (mut tcp_read, mut tcp_write) = tcp_stream.split();
 
@@ -247,64 +320,46 @@ async fn handle_tcp_proxy(
     Ok(())
 }
 
+/// 旧版节点的 UDP 代理：每个数据报一条流、不分帧，节点读到流结束才释放，
+/// 因此空闲超过 [`UDP_IDLE_TIMEOUT`] 后主动结束
 async fn handle_udp_proxy(
     mut quic_send: Box<dyn TunnelSendStream>,
     mut quic_recv: Box<dyn TunnelRecvStream>,
-    target_addr: &str,
+    target: SocketAddr,
+    source: Option<SourceBinding>,
 ) -> Result<()> {
-    // Bind a UDP socket
-    let socket = create_configured_udp_socket("0.0.0.0:0".parse()?).await?;
-    debug!("UDP 代理已启动: {}", target_addr);
-
-    // Read initial UDP data from server
-    let mut recv_buf = vec![0u8; 65535];
-    let initial_len = match quic_recv.read(&mut recv_buf).await? {
-        Some(n) => n,
-        None => {
-            debug!("未收到初始 UDP 数据");
-            return Ok(());
-        }
-    };
-
-    // Send data to target address
-    socket.send_to(&recv_buf[..initial_len], target_addr).await?;
-    debug!("Sent {} bytes UDP data to {}", initial_len, target_addr);
+    // 按目标的协议族（IPv4/IPv6）绑定本地 UDP 套接字，只接收目标地址的响应
+    let socket = source_binding::udp_socket_for(target, source.as_ref()).await?;
+    socket.connect(target).await?;
+    debug!("UDP 代理已启动: {}", target);
 
-    // Set TTL
-    socket.set_ttl(64)?;
-
-    // Loop to receive responses from target and forward back to server
+    let mut recv_buf = vec![0u8; MAX_DATAGRAM_SIZE];
+    let mut response_buf = vec![0u8; MAX_DATAGRAM_SIZE];
     loop {
-        let mut response_buf = vec![0u8; 65535];
         tokio::select! {
             // Read data from QUIC (more UDP packets from server)
             result = quic_recv.read(&mut recv_buf) => {
                 match result? {
-                    Some(n) => {
-                        if n > 0 {
-                            // Forward to target
-                            socket.send_to(&recv_buf[..n], target_addr).await?;
-                            debug!("Forwarded UDP packet: {} bytes", n);
-                        } else {
-                            break;
+                    Some(n) if n > 0 => {
+                        if let Err(e) = socket.send(&recv_buf[..n]).await {
+                            debug!("UDP 发送错误: {}", e);
                         }
                     }
-                    None => break,
+                    _ => break,
                 }
             }
             // Read UDP response from target
-            result = socket.recv_from(&mut response_buf) => {
+            result = socket.recv(&mut response_buf) => {
                 match result {
-                    Ok((len, _from)) => {
-                        // Send back to server
-                        quic_send.write_all(&response_buf[..len]).await?;
-                    }
-                    Err(e) => {
-                        error!("UDP 接收错误: {}", e);
-                        break;
-                    }
+                    Ok(len) => quic_send.write_all(&response_buf[..len]).await?,
+                    // 目标端口不可达等 ICMP 错误不影响后续数据报
+                    Err(e) => debug!("UDP 接收错误: {}", e),
                 }
             }
+            _ = tokio::time::sleep(UDP_IDLE_TIMEOUT) => {
+                debug!("UDP 代理空闲超时: {}", target);
+                break;
+            }
         }
     }
 
@@ -314,6 +369,57 @@ async fn handle_udp_proxy(
     Ok(())
 }
 
+/// 分帧 UDP 代理：隧道流是字节流，每个数据报带 2 字节长度前缀，
+/// 同一来源的数据报复用这条流和同一个本地 UDP 套接字，节点关闭流时结束
+async fn handle_framed_udp_proxy(
+    mut tunnel_send: Box<dyn TunnelSendStream>,
+    mut tunnel_recv: Box<dyn TunnelRecvStream>,
+    target: SocketAddr,
+    source: Option<SourceBinding>,
+) -> Result<()> {
+    let socket = source_binding::udp_socket_for(target, source.as_ref()).await?;
+    // 只接收目标地址的响应
+    socket.connect(target).await?;
+    debug!("分帧 UDP 代理已启动: {}", target);
+
+    // 分帧读取不能被中途取消，两个方向各自独立循环
+    let tunnel_to_target = async {
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+        while let Some(n) = read_datagram(tunnel_recv.as_mut(), &mut buf).await? {
+            // 本地服务重启时发送会短暂失败（ICMP 端口不可达），丢弃该数据报即可
+            if let Err(e) = socket.send(&buf[..n]).await {
+                debug!("UDP 发送错误: {}", e);
+            }
+        }
+        Ok::<_, anyhow::Error>(())
+    };
+
+    let target_to_tunnel = async {
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+        loop {
+            match socket.recv(&mut buf).await {
+                Ok(len) => {
+                    if let Err(e) = write_datagram(tunnel_send.as_mut(), &buf[..len]).await {
+                        break Err::<(), anyhow::Error>(e);
+                    }
+                }
+                Err(e) => {
+                    // 目标端口不可达等 ICMP 错误不影响后续数据报
+                    debug!("UDP 接收错误: {}", e);
+                }
+            }
+        }
+    };
+
+    tokio::select! {
+        res = tunnel_to_target => res?,
+        res = target_to_tunnel => res?,
+    }
+
+    tunnel_send.finish().await?;
+    Ok(())
+}
+
 /// Send application-level heartbeat
 /// Heartbeat protocol: client sends 'h' (heartbeat), server replies 'h'
 async fn send_heartbeat(conn: &Arc<Box<dyn TunnelConnection>>) -> Result<()> {
@@ -380,3 +486,149 @@ async fn handle_log_request(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use tokio::io::{DuplexStream, ReadHalf, WriteHalf};
+    use tokio::net::UdpSocket;
+
+    struct PipeSend(WriteHalf<DuplexStream>);
+
+    #[async_trait]
+    impl TunnelSendStream for PipeSend {
+        async fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            Ok(self.0.write_all(buf).await?)
+        }
+        async fn flush(&mut self) -> Result<()> {
+            Ok(self.0.flush().await?)
+        }
+        async fn finish(&mut self) -> Result<()> {
+            Ok(self.0.shutdown().await?)
+        }
+    }
+
+    struct PipeRecv(ReadHalf<DuplexStream>);
+
+    #[async_trait]
+    impl TunnelRecvStream for PipeRecv {
+        async fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+            self.0.read_exact(buf).await?;
+            Ok(())
+        }
+        async fn read(&mut self, buf: &mut [u8]) -> Result<Option<usize>> {
+            match self.0.read(buf).await? {
+                0 => Ok(None),
+                n => Ok(Some(n)),
+            }
+        }
+    }
+
+    /// 启动分帧 UDP 代理，返回节点一侧的流
+    fn start_relay(target: SocketAddr) -> (PipeSend, PipeRecv, tokio::task::JoinHandle<Result<()>>) {
+        let (node, client) = tokio::io::duplex(64 * 1024);
+        let (node_recv, node_send) = tokio::io::split(node);
+        let (client_recv, client_send) = tokio::io::split(client);
+        let handle = tokio::spawn(handle_framed_udp_proxy(
+            Box::new(PipeSend(client_send)),
+            Box::new(PipeRecv(client_recv)),
+            target,
+            None,
+        ));
+        (PipeSend(node_send), PipeRecv(node_recv), handle)
+    }
+
+    async fn recv_frame(recv: &mut PipeRecv) -> Vec<u8> {
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+        let n = tokio::time::timeout(Duration::from_secs(5), read_datagram(recv, &mut buf))
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        buf.truncate(n);
+        buf
+    }
+
+    #[tokio::test]
+    async fn test_framed_udp_dns() {
+        // 模拟 DNS 服务：回复原查询并置位 QR
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let target = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            loop {
+                let (n, from) = server.recv_from(&mut buf).await.unwrap();
+                buf[2] |= 0x80;
+                server.send_to(&buf[..n], from).await.unwrap();
+            }
+        });
+
+        let (mut send, mut recv, handle) = start_relay(target);
+        // 连续写入的两个查询必须作为两个数据报到达
+        let queries: Vec<Vec<u8>> = (0u8..2)
+            .map(|id| [&[0x12, id, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0][..], b"\x07example\x03com\x00\x00\x01\x00\x01"].concat())
+            .collect();
+        for q in &queries {
+            write_datagram(&mut send, q).await.unwrap();
+        }
+        let mut answers = vec![recv_frame(&mut recv).await, recv_frame(&mut recv).await];
+        answers.sort();
+        for (q, a) in queries.iter().zip(&answers) {
+            assert_eq!(a.len(), q.len());
+            assert_eq!(a[1], q[1]);
+            assert_eq!(a[2], 0x81);
+            assert_eq!(a[3..], q[3..]);
+        }
+
+        // 节点关闭流后会话结束
+        send.finish().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), handle).await.unwrap().unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_framed_udp_wireguard() {
+        // 模拟 WireGuard 对端：记录来源地址，回显数据包，并主动发送 keepalive
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let target = server.local_addr().unwrap();
+        let (peers_tx, mut peers_rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 2048];
+            loop {
+                let (n, from) = server.recv_from(&mut buf).await.unwrap();
+                peers_tx.send(from).unwrap();
+                server.send_to(&buf[..n], from).await.unwrap();
+                if buf[0] == 1 {
+                    // 握手发起后对端主动发送 32 字节 keepalive
+                    server.send_to(&[4u8; 32], from).await.unwrap();
+                }
+            }
+        });
+
+        let (mut send, mut recv, handle) = start_relay(target);
+        // 握手发起（148 字节）与 1420 MTU 下的最大数据包（1452 字节）
+        let mut packets = vec![vec![1u8; 148]];
+        for i in 0..10u8 {
+            let mut p = vec![i; 1452];
+            p[0] = 4;
+            packets.push(p);
+        }
+
+        write_datagram(&mut send, &packets[0]).await.unwrap();
+        assert_eq!(recv_frame(&mut recv).await, packets[0]);
+        assert_eq!(recv_frame(&mut recv).await, vec![4u8; 32]);
+        for p in &packets[1..] {
+            write_datagram(&mut send, p).await.unwrap();
+            assert_eq!(&recv_frame(&mut recv).await, p);
+        }
+
+        // 同一会话的所有数据包都来自同一个本地地址，对端无需重新握手
+        let first = peers_rx.recv().await.unwrap();
+        for _ in 1..packets.len() {
+            assert_eq!(peers_rx.recv().await.unwrap(), first);
+        }
+
+        send.finish().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), handle).await.unwrap().unwrap().unwrap();
+    }
+}
@@ -0,0 +1,513 @@
+//! 诊断包
+//!
+//! `client diagnose` 收集排查问题所需的信息并打包为 tar.gz：本次诊断的日志和日志目录中
+//! 最近的日志、当前配置、Controller gRPC 和各节点隧道的连通性测试、DNS 解析结果、
+//! NAT 情况以及路径 MTU（大包黑洞）检测。token 等敏感信息在写入前脱敏，诊断包可以直接附加到 issue 中。
+//!
+//! 诊断通过只读的 `ClientDiagnose` 接口获取分配的节点，节点测试只建立隧道连接而不发送
+//! token，因此可以在客户端正常运行时执行，不会顶替正在运行的连接。
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+use common::grpc::oxiproxy;
+use common::grpc::AgentClientServiceClient;
+use common::grpc::tuning::Tuned;
+use common::protocol::client_config::ServerProxyGroup;
+use common::{KcpConnector, QuicConnector, TcpTunnelConnector, TunnelConnector, TunnelProtocol};
+
+use super::grpc_client;
+use super::mtu_probe::{self, MtuProbeResult};
+use super::nat_detect;
+use super::log_collector::{LogCollector, LogCollectorLayer, LogEntry};
+
+/// 单项连通性测试的超时
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+/// 每个日志文件最多保留的行数
+const LOG_TAIL_LINES: usize = 2000;
+/// 最多收集的日志文件数（按文件名倒序，即最近的几天）
+const MAX_LOG_FILES: usize = 3;
+
+/// 诊断参数
+pub struct DiagnoseOptions {
+    pub controller_url: String,
+    pub token: String,
+    pub tls_ca_cert: Option<Vec<u8>>,
+    pub tls_ca_cert_path: Option<String>,
+    pub log_dir: Option<String>,
+    /// 输出文件路径，未指定时写入当前目录
+    pub output: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Report {
+    generated_at: String,
+    version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    controller: ControllerCheck,
+    dns: Vec<DnsResult>,
+    nodes: Vec<NodeCheck>,
+    nat: NatInfo,
+    /// 节点启用 NAT 探测时的路径 MTU 检测结果
+    mtu: Option<MtuProbeResult>,
+}
+
+#[derive(Serialize)]
+struct ControllerCheck {
+    url: String,
+    connected: bool,
+    connect_ms: Option<f64>,
+    authenticated: bool,
+    client_id: Option<i64>,
+    client_name: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DnsResult {
+    host: String,
+    addresses: Vec<String>,
+    elapsed_ms: f64,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct NodeCheck {
+    node_id: i64,
+    address: String,
+    protocol: TunnelProtocol,
+    proxy_count: usize,
+    handshake_ok: bool,
+    handshake_ms: Option<f64>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct NatInfo {
+    /// Controller 看到的来源地址
+    observed_ip: Option<String>,
+    /// 连接 Controller 时使用的本地地址
+    local_ip: Option<String>,
+    /// 探测得到的外部映射地址
+    mapped_addr: Option<String>,
+    /// 用于探测的节点
+    probe_node_id: Option<i64>,
+    /// 节点启用 NAT 探测时为检测到的 NAT 类型，否则根据来源地址粗略判断为 direct / nat / unknown
+    nat_type: String,
+}
+
+#[derive(Serialize)]
+struct RedactedConfig {
+    controller_url: String,
+    token: String,
+    tls_ca_cert: Option<String>,
+    log_dir: Option<String>,
+    env: Vec<(String, String)>,
+    server_groups: Vec<ServerProxyGroup>,
+}
+
+/// 执行诊断并写入诊断包，返回诊断包路径
+pub async fn run_diagnose(opts: DiagnoseOptions) -> Result<PathBuf> {
+    let log_collector = LogCollector::new(1000);
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(fmt::layer())
+        .with(LogCollectorLayer::new(log_collector.clone()))
+        .init();
+
+    info!("开始诊断，版本 {}", env!("CARGO_PKG_VERSION"));
+
+    // 1. DNS 解析 Controller
+    let mut dns = Vec::new();
+    let controller_host = controller_host(&opts.controller_url);
+    if let Some((host, _)) = &controller_host {
+        dns.push(resolve(host).await);
+    }
+
+    // 2. Controller gRPC 连通性和 token 校验
+    let (controller, diagnose_resp) = check_controller(&opts).await;
+    let server_groups = diagnose_resp
+        .as_ref()
+        .map(|r| grpc_client::convert_server_groups(r.server_groups.clone()))
+        .unwrap_or_default();
+
+    // 3. 各节点的 DNS 解析和隧道握手
+    let mut nodes = Vec::new();
+    for group in &server_groups {
+        if group.server_addr.parse::<IpAddr>().is_err() && !dns.iter().any(|d| d.host == group.server_addr) {
+            dns.push(resolve(&group.server_addr).await);
+        }
+        nodes.push(check_node(group).await);
+    }
+
+    // 4. NAT 情况：优先向节点的 NAT 探测端口检测，否则比较本地地址与 Controller 看到的来源地址
+    let local_ip = match &controller_host {
+        Some((host, port)) => local_ip_towards(host, *port).await,
+        None => None,
+    };
+    let observed_ip = diagnose_resp.as_ref().and_then(|r| r.observed_ip.clone());
+    let detection = match nat_detect::probe_target(&server_groups) {
+        Some(group) => nat_detect::detect(group)
+            .await
+            .map_err(|e| warn!("NAT 类型检测失败: {}", e))
+            .ok(),
+        None => None,
+    };
+    let nat_type = match (&detection, &local_ip, &observed_ip) {
+        (Some(d), _, _) => d.nat_type.to_string(),
+        (None, Some(local), Some(observed)) if local == observed => "direct".to_string(),
+        (None, Some(_), Some(_)) => "nat".to_string(),
+        _ => "unknown".to_string(),
+    };
+    info!("NAT 检测: {}，本地地址 {:?}，Controller 看到的地址 {:?}", nat_type, local_ip, observed_ip);
+
+    // 5. 路径 MTU：同样使用节点的 NAT 探测端口
+    let mtu = match nat_detect::probe_target(&server_groups) {
+        Some(group) => mtu_probe::probe(group)
+            .await
+            .map_err(|e| warn!("路径 MTU 检测失败: {}", e))
+            .ok(),
+        None => None,
+    };
+
+    let report = Report {
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        controller,
+        dns,
+        nodes,
+        nat: NatInfo {
+            observed_ip,
+            local_ip,
+            mapped_addr: detection.as_ref().and_then(|d| d.mapped_addr).map(|a| a.to_string()),
+            probe_node_id: detection.as_ref().map(|d| d.node_id),
+            nat_type,
+        },
+        mtu,
+    };
+    print_summary(&report);
+
+    let config = RedactedConfig {
+        controller_url: opts.controller_url.clone(),
+        token: mask(&opts.token),
+        tls_ca_cert: opts.tls_ca_cert_path.clone(),
+        log_dir: opts.log_dir.clone(),
+        env: redacted_env(),
+        server_groups,
+    };
+
+    let output = opts.output.clone().map(PathBuf::from).unwrap_or_else(|| {
+        PathBuf::from(format!("oxiproxy-diagnose-{}.tar.gz", chrono::Local::now().format("%Y%m%d-%H%M%S")))
+    });
+    let logs = log_collector.get_all_logs();
+    let token = opts.token.clone();
+    let log_dir = opts.log_dir.clone();
+    let path = output.clone();
+    tokio::task::spawn_blocking(move || write_bundle(&path, &report, &config, &logs, log_dir.as_deref(), &token))
+        .await
+        .map_err(|e| anyhow!("写入诊断包失败: {}", e))??;
+
+    Ok(output)
+}
+
+/// 测试 Controller gRPC 连接，并通过只读诊断接口校验 token、获取分配的节点
+async fn check_controller(opts: &DiagnoseOptions) -> (ControllerCheck, Option<oxiproxy::ClientDiagnoseResponse>) {
+    let mut check = ControllerCheck {
+        url: opts.controller_url.clone(),
+        connected: false,
+        connect_ms: None,
+        authenticated: false,
+        client_id: None,
+        client_name: None,
+        error: None,
+    };
+
+    let start = Instant::now();
+    let channel = match grpc_client::connect_channel(&opts.controller_url, opts.tls_ca_cert.as_deref()).await {
+        Ok(channel) => channel,
+        Err(e) => {
+            warn!("Controller 连接失败: {}", e);
+            check.error = Some(e.to_string());
+            return (check, None);
+        }
+    };
+    check.connected = true;
+    check.connect_ms = Some(start.elapsed().as_secs_f64() * 1000.0);
+    info!("Controller gRPC 已连接，耗时 {:.1} ms", check.connect_ms.unwrap_or_default());
+
+    let mut client = AgentClientServiceClient::new(channel).tuned();
+    let request = oxiproxy::ClientAuthRequest {
+        token: opts.token.clone(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        session_token: None,
+        // 诊断不占用连接，无需上报实例 ID
+        instance_id: None,
+    };
+    match tokio::time::timeout(CHECK_TIMEOUT, client.client_diagnose(request)).await {
+        Ok(Ok(resp)) => {
+            let resp = resp.into_inner();
+            if resp.success {
+                info!("token 校验通过: {} (ID: {})，分配了 {} 个节点", resp.client_name, resp.client_id, resp.server_groups.len());
+                check.authenticated = true;
+                check.client_id = Some(resp.client_id);
+                check.client_name = Some(resp.client_name.clone());
+            } else {
+                let message = resp.error_message.clone().unwrap_or_default();
+                warn!("token 校验失败: {}", message);
+                check.error = Some(message);
+            }
+            (check, Some(resp))
+        }
+        Ok(Err(status)) if status.code() == tonic::Code::Unimplemented => {
+            warn!("Controller 版本过旧，不支持诊断接口，跳过 token 校验和节点测试");
+            check.error = Some("Controller 不支持诊断接口".to_string());
+            (check, None)
+        }
+        Ok(Err(status)) => {
+            warn!("诊断请求失败: {}", status.message());
+            check.error = Some(status.message().to_string());
+            (check, None)
+        }
+        Err(_) => {
+            warn!("诊断请求超时");
+            check.error = Some(format!("请求超时（{} 秒）", CHECK_TIMEOUT.as_secs()));
+            (check, None)
+        }
+    }
+}
+
+/// 测试与节点的隧道握手（QUIC / KCP / TCP）
+///
+/// 只建立连接，不发送 token，节点会在认证超时后关闭该连接。
+async fn check_node(group: &ServerProxyGroup) -> NodeCheck {
+    let address = common::utils::join_host_port(&group.server_addr, group.server_port);
+    let mut check = NodeCheck {
+        node_id: group.node_id,
+        address: address.clone(),
+        protocol: group.protocol,
+        proxy_count: group.proxies.len(),
+        handshake_ok: false,
+        handshake_ms: None,
+        error: None,
+    };
+
+    let addr = match tokio::net::lookup_host(&address).await.map(|mut addrs| addrs.next()) {
+        Ok(Some(addr)) => addr,
+        Ok(None) => {
+            check.error = Some("地址解析结果为空".to_string());
+            return check;
+        }
+        Err(e) => {
+            check.error = Some(format!("地址解析失败: {}", e));
+            return check;
+        }
+    };
+
+    let connector: Arc<dyn TunnelConnector> = match group.protocol {
+        TunnelProtocol::Quic => match QuicConnector::new(group.quic.clone()) {
+            Ok(c) => Arc::new(c),
+            Err(e) => {
+                check.error = Some(format!("创建 QUIC 连接器失败: {}", e));
+                return check;
+            }
+        },
+        TunnelProtocol::Kcp => Arc::new(KcpConnector::new(group.kcp.clone())),
+        TunnelProtocol::Tcp => Arc::new(TcpTunnelConnector::new()),
+    };
+
+    let start = Instant::now();
+    match tokio::time::timeout(CHECK_TIMEOUT, connector.connect(addr)).await {
+        Ok(Ok(_conn)) => {
+            let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+            info!("节点 #{} ({:?} {}) 握手成功，耗时 {:.1} ms", group.node_id, group.protocol, addr, elapsed);
+            check.handshake_ok = true;
+            check.handshake_ms = Some(elapsed);
+        }
+        Ok(Err(e)) => {
+            warn!("节点 #{} ({:?} {}) 握手失败: {}", group.node_id, group.protocol, addr, e);
+            check.error = Some(e.to_string());
+        }
+        Err(_) => {
+            warn!("节点 #{} ({:?} {}) 握手超时", group.node_id, group.protocol, addr);
+            check.error = Some(format!("握手超时（{} 秒）", CHECK_TIMEOUT.as_secs()));
+        }
+    }
+    check
+}
+
+/// 解析主机名
+async fn resolve(host: &str) -> DnsResult {
+    let start = Instant::now();
+    let result = tokio::time::timeout(CHECK_TIMEOUT, tokio::net::lookup_host((host, 0))).await;
+    let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let (addresses, error) = match result {
+        Ok(Ok(addrs)) => (addrs.map(|a| a.ip().to_string()).collect::<Vec<_>>(), None),
+        Ok(Err(e)) => (Vec::new(), Some(e.to_string())),
+        Err(_) => (Vec::new(), Some("解析超时".to_string())),
+    };
+    match &error {
+        None => info!("DNS 解析 {}: {:?}（{:.1} ms）", host, addresses, elapsed_ms),
+        Some(e) => warn!("DNS 解析 {} 失败: {}", host, e),
+    }
+    DnsResult {
+        host: host.to_string(),
+        addresses,
+        elapsed_ms,
+        error,
+    }
+}
+
+/// 从 Controller 地址中取出主机名和端口
+fn controller_host(controller_url: &str) -> Option<(String, u16)> {
+    let uri: tonic::transport::Uri = controller_url.parse().ok()?;
+    let host = uri.host()?.trim_start_matches('[').trim_end_matches(']').to_string();
+    let default_port = if uri.scheme_str() == Some("https") { 443 } else { 80 };
+    Some((host, uri.port_u16().unwrap_or(default_port)))
+}
+
+/// 访问 Controller 时操作系统选择的本地地址（UDP connect 不发送数据）
+async fn local_ip_towards(host: &str, port: u16) -> Option<String> {
+    let remote: SocketAddr = tokio::net::lookup_host((host, port)).await.ok()?.next()?;
+    let bind: SocketAddr = if remote.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+    let socket = tokio::net::UdpSocket::bind(bind).await.ok()?;
+    socket.connect(remote).await.ok()?;
+    Some(socket.local_addr().ok()?.ip().to_string())
+}
+
+/// 脱敏：只保留前 4 个字符
+fn mask(value: &str) -> String {
+    let prefix: String = value.chars().take(4).collect();
+    format!("{}***", prefix)
+}
+
+/// 收集 OXIPROXY_* 环境变量，敏感项脱敏
+fn redacted_env() -> Vec<(String, String)> {
+    let mut vars: Vec<(String, String)> = std::env::vars()
+        .filter(|(k, _)| k.starts_with("OXIPROXY_"))
+        .map(|(k, v)| {
+            let sensitive = ["TOKEN", "PASSWORD", "SECRET", "KEY"].iter().any(|s| k.contains(s));
+            let v = if sensitive { mask(&v) } else { v };
+            (k, v)
+        })
+        .collect();
+    vars.sort();
+    vars
+}
+
+fn print_summary(report: &Report) {
+    let c = &report.controller;
+    println!();
+    println!("Controller {}: {}", c.url, if c.authenticated { "正常" } else if c.connected { "已连接，token 校验未通过" } else { "无法连接" });
+    if let Some(e) = &c.error {
+        println!("  错误: {}", e);
+    }
+    for d in &report.dns {
+        match &d.error {
+            None => println!("DNS {}: {}", d.host, d.addresses.join(", ")),
+            Some(e) => println!("DNS {}: 失败 ({})", d.host, e),
+        }
+    }
+    for n in &report.nodes {
+        match n.handshake_ms {
+            Some(ms) => println!("节点 #{} {:?} {}: 握手成功 ({:.1} ms)", n.node_id, n.protocol, n.address, ms),
+            None => println!("节点 #{} {:?} {}: 握手失败 ({})", n.node_id, n.protocol, n.address, n.error.as_deref().unwrap_or("")),
+        }
+    }
+    println!("NAT: {}", report.nat.nat_type);
+    if let Some(mtu) = &report.mtu {
+        match (mtu.max_ip_mtu, mtu.blackhole) {
+            (Some(max), true) => println!(
+                "路径 MTU: {} 字节，更大的包被静默丢弃（大包黑洞），建议 KCP mtu {}、QUIC max_mtu {}",
+                max,
+                mtu.recommended_kcp_mtu.unwrap_or_default(),
+                mtu.recommended_quic_max_mtu.unwrap_or_default()
+            ),
+            (Some(max), false) => println!("路径 MTU: {} 字节", max),
+            (None, _) => println!("路径 MTU: 探测包没有回复"),
+        }
+    }
+    println!();
+}
+
+/// 将诊断结果、配置和日志写入 tar.gz，日志中出现的 token 替换为脱敏值
+fn write_bundle(
+    path: &Path,
+    report: &Report,
+    config: &RedactedConfig,
+    logs: &[LogEntry],
+    log_dir: Option<&str>,
+    token: &str,
+) -> Result<()> {
+    let file = std::fs::File::create(path).map_err(|e| anyhow!("创建 {} 失败: {}", path.display(), e))?;
+    let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+    let mut tar = tar::Builder::new(encoder);
+    let redact = |text: String| if token.is_empty() { text } else { text.replace(token, &mask(token)) };
+
+    append(&mut tar, "report.json", serde_json::to_vec_pretty(report)?)?;
+    append(&mut tar, "config.json", redact(serde_json::to_string_pretty(config)?).into_bytes())?;
+
+    let diagnose_log: String = logs
+        .iter()
+        .map(|l| format!("{} {} {}\n", l.timestamp.to_rfc3339(), l.level, l.message))
+        .collect();
+    append(&mut tar, "logs/diagnose.log", redact(diagnose_log).into_bytes())?;
+
+    if let Some(dir) = log_dir {
+        for file in recent_log_files(dir) {
+            let Some(name) = file.file_name().map(|n| n.to_string_lossy().to_string()) else {
+                continue;
+            };
+            match std::fs::read(&file) {
+                Ok(content) => {
+                    let content = String::from_utf8_lossy(&content);
+                    let lines: Vec<&str> = content.lines().collect();
+                    let tail = lines[lines.len().saturating_sub(LOG_TAIL_LINES)..].join("\n");
+                    append(&mut tar, &format!("logs/{}", name), redact(tail).into_bytes())?;
+                }
+                Err(e) => warn!("读取日志文件 {} 失败: {}", file.display(), e),
+            }
+        }
+    }
+
+    tar.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// 日志目录中最近的客户端日志文件（client.log.YYYY-MM-DD 以及守护进程输出）
+fn recent_log_files(dir: &str) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut daily = Vec::new();
+    let mut files = Vec::new();
+    for path in entries.flatten().map(|e| e.path()) {
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        if name.starts_with("client.log") {
+            daily.push(path);
+        } else if name == "daemon.log" || name == "daemon.err" {
+            files.push(path);
+        }
+    }
+    daily.sort();
+    files.extend(daily.into_iter().rev().take(MAX_LOG_FILES));
+    files
+}
+
+fn append<W: std::io::Write>(tar: &mut tar::Builder<W>, name: &str, data: Vec<u8>) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+    tar.append_data(&mut header, name, data.as_slice())?;
+    Ok(())
+}
@@ -3,76 +3,87 @@
 //! 连接 Controller 的 gRPC 双向流，处理认证、接收代理列表推送。
 
 use anyhow::{anyhow, Result};
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tokio_stream::StreamExt;
 use tonic::transport::{Channel, ClientTlsConfig};
 use tracing::{error, info, warn, debug};
 
-use common::config::KcpConfig;
+use common::config::{KcpConfig, QuicConfig};
 use common::grpc::oxiproxy;
 use common::grpc::oxiproxy::agent_client_message::Payload as ClientPayload;
 use common::grpc::oxiproxy::controller_to_client_message::Payload as ControllerPayload;
+use common::grpc::reconnect;
+use common::grpc::tuning::{self, Tuned};
 use common::grpc::AgentClientServiceClient;
 use common::protocol::client_config::{
-    ProxyInfo as ClientProxyInfo, ServerProxyGroup as ClientServerProxyGroup,
+    ProxyInfo as ClientProxyInfo, ServerProxyGroup as ClientServerProxyGroup, VisitorInfo as ClientVisitorInfo,
 };
 use common::TunnelProtocol;
 
 use super::log_collector::LogCollector;
 
-/// 连接 Controller 并认证，返回代理列表更新的接收器
-pub async fn connect_and_run(
-    controller_url: &str,
-    token: &str,
-    tls_ca_cert: Option<&[u8]>,
-    log_collector: LogCollector,
-) -> Result<(i64, String, mpsc::Receiver<Vec<ClientServerProxyGroup>>)> {
-    let mut endpoint = Channel::from_shared(controller_url.to_string())?
-        .timeout(Duration::from_secs(30))
-        .connect_timeout(Duration::from_secs(10))
-        .tcp_keepalive(Some(Duration::from_secs(60)))
-        .http2_keep_alive_interval(Duration::from_secs(30))
-        .keep_alive_timeout(Duration::from_secs(10));
+/// Controller 推送的代理配置
+pub struct ProxyConfigUpdate {
+    pub server_groups: Vec<ClientServerProxyGroup>,
+    pub visitors: Vec<ClientVisitorInfo>,
+}
 
-    if controller_url.starts_with("https://") {
-        // 从 URL 中提取域名用于 SNI
-        let domain = controller_url
-            .trim_start_matches("https://")
-            .split(':')
-            .next()
-            .ok_or_else(|| anyhow!("无法从 URL 提取域名"))?;
+/// 进程启动时间（首次连接时记录），用于上报运行时长
+static STARTED_AT: LazyLock<Instant> = LazyLock::new(Instant::now);
 
-        let mut tls_config = ClientTlsConfig::new()
-            .domain_name(domain)
-            .with_webpki_roots();
+/// 同一 token 的另一个实例在线，Controller 按重复登录策略拒绝本实例连接
+pub const DUPLICATE_INSTANCE: &str = "duplicate_instance";
+/// 本实例被同一 token 的另一个实例踢下线
+const KICKED: &str = "kicked";
 
-        if let Some(ca_pem) = tls_ca_cert {
-            info!("使用自定义 CA 证书进行 TLS 验证");
-            tls_config = tls_config.ca_certificate(
-                tonic::transport::Certificate::from_pem(ca_pem)
-            );
-        }
+/// 是否已被踢下线，被踢下线后不再重连
+static KICKED_OUT: AtomicBool = AtomicBool::new(false);
 
-        endpoint = endpoint.tls_config(tls_config)
-            .map_err(|e| anyhow!("TLS 配置失败: {}", e))?;
+/// Controller 以错误通知拒绝认证
+#[derive(Debug)]
+pub struct AuthRejected {
+    pub code: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for AuthRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "认证被拒绝: [{}] {}", self.code, self.message)
     }
+}
 
-    let channel = endpoint.connect()
-        .await
-        .map_err(|e| anyhow!("连接 Controller gRPC 失败: {}", e))?;
+impl std::error::Error for AuthRejected {}
+
+/// 是否已被同一 token 的另一个实例踢下线
+pub fn kicked_out() -> bool {
+    KICKED_OUT.load(Ordering::Relaxed)
+}
 
-    let mut client = AgentClientServiceClient::new(channel);
+/// 连接 Controller 并认证，返回代理列表更新的接收器
+pub async fn connect_and_run(
+    controller_url: &str,
+    token: &str,
+    tls_ca_cert: Option<&[u8]>,
+    log_collector: LogCollector,
+) -> Result<(i64, String, mpsc::Receiver<ProxyConfigUpdate>)> {
+    LazyLock::force(&STARTED_AT);
+    let channel = connect_channel(controller_url, tls_ca_cert).await?;
+    let mut client = AgentClientServiceClient::new(channel).tuned();
 
     // 创建双向流
     let (tx, rx) = mpsc::channel::<oxiproxy::AgentClientMessage>(64);
-    let (update_tx, update_rx) = mpsc::channel::<Vec<ClientServerProxyGroup>>(16);
+    let (update_tx, update_rx) = mpsc::channel::<ProxyConfigUpdate>(16);
 
     // 发送认证请求作为首条消息
     let auth_msg = oxiproxy::AgentClientMessage {
         payload: Some(ClientPayload::Auth(oxiproxy::ClientAuthRequest {
             token: token.to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
+            session_token: reconnect::session_token(),
+            instance_id: Some(super::instance_id::get().to_string()),
         })),
     };
     tx.send(auth_msg)
@@ -98,7 +109,7 @@ pub async fn connect_and_run(
     let auth_resp = match first_msg.payload {
         Some(ControllerPayload::AuthResponse(resp)) => resp,
         Some(ControllerPayload::Error(err)) => {
-            return Err(anyhow!("认证被拒绝: [{}] {}", err.code, err.message));
+            return Err(AuthRejected { code: err.code, message: err.message }.into());
         }
         _ => return Err(anyhow!("首条响应不是认证响应")),
     };
@@ -112,8 +123,12 @@ pub async fn connect_and_run(
 
     let client_id = auth_resp.client_id;
     let client_name = auth_resp.client_name.clone();
+    reconnect::set_session_token(auth_resp.session_token.clone());
     info!("客户端认证成功: {} (ID: {})", client_name, client_id);
 
+    // 启动日志上传
+    tokio::spawn(super::log_shipper::run(log_collector.clone(), tx.clone()));
+
     // 启动消息接收循环
     let response_tx = tx.clone();
     tokio::spawn(async move {
@@ -129,13 +144,55 @@ pub async fn connect_and_run(
     Ok((client_id, client_name, update_rx))
 }
 
+/// 建立到 Controller 的 gRPC 通道（https 地址启用 TLS）
+pub async fn connect_channel(controller_url: &str, tls_ca_cert: Option<&[u8]>) -> Result<Channel> {
+    let mut endpoint = tuning::configure_endpoint(
+        Channel::from_shared(controller_url.to_string())?
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(10))
+            .tcp_keepalive(Some(Duration::from_secs(60))),
+    );
+
+    if controller_url.starts_with("https://") {
+        // 从 URL 中提取域名用于 SNI
+        let domain = controller_url
+            .trim_start_matches("https://")
+            .split(':')
+            .next()
+            .ok_or_else(|| anyhow!("无法从 URL 提取域名"))?;
+
+        let mut tls_config = ClientTlsConfig::new()
+            .domain_name(domain)
+            .with_webpki_roots();
+
+        if let Some(ca_pem) = tls_ca_cert {
+            info!("使用自定义 CA 证书进行 TLS 验证");
+            tls_config = tls_config.ca_certificate(
+                tonic::transport::Certificate::from_pem(ca_pem)
+            );
+        }
+
+        endpoint = endpoint.tls_config(tls_config)
+            .map_err(|e| anyhow!("TLS 配置失败: {}", e))?;
+    }
+
+    let channel = endpoint.connect()
+        .await
+        .map_err(|e| anyhow!("连接 Controller gRPC 失败: {}", e))?;
+
+    Ok(channel)
+}
+
 /// 消息接收循环
 async fn message_loop(
     mut inbound: tonic::Streaming<oxiproxy::ControllerToClientMessage>,
-    update_tx: mpsc::Sender<Vec<ClientServerProxyGroup>>,
+    update_tx: mpsc::Sender<ProxyConfigUpdate>,
     response_tx: mpsc::Sender<oxiproxy::AgentClientMessage>,
     log_collector: LogCollector,
 ) {
+    // 每次连接检测一次 NAT 类型（重连可能意味着网络已变化）
+    let mut nat_detected = false;
+
     while let Some(result) = inbound.next().await {
         let msg = match result {
             Ok(m) => m,
@@ -158,7 +215,14 @@ async fn message_loop(
             ControllerPayload::ProxyUpdate(update) => {
                 debug!("收到代理配置更新: {} 个节点", update.server_groups.len());
                 let groups = convert_server_groups(update.server_groups);
-                if update_tx.send(groups).await.is_err() {
+                if !nat_detected {
+                    if let Some(group) = super::nat_detect::probe_target(&groups) {
+                        nat_detected = true;
+                        tokio::spawn(super::nat_detect::detect_and_report(group.clone(), response_tx.clone()));
+                    }
+                }
+                let visitors = convert_visitors(update.visitors);
+                if update_tx.send(ProxyConfigUpdate { server_groups: groups, visitors }).await.is_err() {
                     warn!("代理列表更新通道已关闭");
                     break;
                 }
@@ -166,6 +230,15 @@ async fn message_loop(
 
             ControllerPayload::Error(err) => {
                 error!("收到 Controller 错误通知: [{}] {}", err.code, err.message);
+                if err.code == KICKED {
+                    KICKED_OUT.store(true, Ordering::Relaxed);
+                    break;
+                }
+            }
+
+            ControllerPayload::ReconnectHint(hint) => {
+                info!("Controller 即将关闭，断线后等待 {} ms 再重连", hint.delay_ms);
+                reconnect::set_hint(Duration::from_millis(hint.delay_ms as u64));
             }
 
             ControllerPayload::GetLogs(cmd) => {
@@ -199,14 +272,37 @@ async fn message_loop(
             }
 
             ControllerPayload::SoftwareUpdate(cmd) => {
-                info!("收到远程软件更新指令，开始更新...");
-                let update_result = tokio::task::spawn_blocking(perform_client_self_update).await;
-                let (success, error_msg, new_ver) = match update_result {
-                    Ok(Ok(v)) => (true, None, Some(v)),
-                    Ok(Err(e)) => (false, Some(e.to_string()), None),
-                    Err(e) => (false, Some(e.to_string()), None),
+                let current = env!("CARGO_PKG_VERSION");
+                let target_version = cmd.target_version;
+                let already_current = target_version.as_deref() == Some(current);
+
+                let (success, error_msg, new_ver) = if already_current {
+                    info!("当前已是目标版本 {}，跳过更新", current);
+                    (true, None, Some(current.to_string()))
+                } else {
+                    info!(
+                        "收到远程软件更新指令，开始更新到 {}...",
+                        target_version.as_deref().unwrap_or("最新版本")
+                    );
+                    send_update_progress(&response_tx, &cmd.request_id, "downloading", target_version.clone(), None).await;
+
+                    let tv = target_version.clone();
+                    let update_result = tokio::task::spawn_blocking(move || perform_client_self_update(tv.as_deref())).await;
+                    match update_result {
+                        Ok(Ok(v)) => (true, None, Some(v)),
+                        Ok(Err(e)) => (false, Some(e.to_string()), None),
+                        Err(e) => (false, Some(e.to_string()), None),
+                    }
                 };
 
+                if !already_current {
+                    if success {
+                        send_update_progress(&response_tx, &cmd.request_id, "restarting", new_ver.clone(), None).await;
+                    } else {
+                        send_update_progress(&response_tx, &cmd.request_id, "failed", target_version, error_msg.clone()).await;
+                    }
+                }
+
                 let resp_msg = oxiproxy::AgentClientMessage {
                     payload: Some(ClientPayload::Response(oxiproxy::AgentClientResponse {
                         request_id: cmd.request_id,
@@ -221,7 +317,7 @@ async fn message_loop(
                 };
 
                 let _ = response_tx.send(resp_msg).await;
-                if success {
+                if success && !already_current {
                     info!("软件更新成功，3秒后重启...");
                     tokio::time::sleep(std::time::Duration::from_secs(3)).await;
                     std::process::exit(0);
@@ -237,6 +333,37 @@ async fn message_loop(
     warn!("gRPC 连接断开");
 }
 
+/// 上报软件更新进度到 Controller
+async fn send_update_progress(
+    sender: &mpsc::Sender<oxiproxy::AgentClientMessage>,
+    request_id: &str,
+    stage: &str,
+    target_version: Option<String>,
+    message: Option<String>,
+) {
+    let msg = oxiproxy::AgentClientMessage {
+        payload: Some(ClientPayload::UpdateProgress(oxiproxy::UpdateProgress {
+            request_id: request_id.to_string(),
+            stage: stage.to_string(),
+            target_version,
+            message,
+        })),
+    };
+    if sender.send(msg).await.is_err() {
+        warn!("上报更新进度失败: {}", stage);
+    }
+}
+
+/// 心跳中上报的系统信息
+fn system_info() -> oxiproxy::AgentSystemInfo {
+    oxiproxy::AgentSystemInfo {
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        uptime_secs: STARTED_AT.elapsed().as_secs(),
+    }
+}
+
 /// 心跳循环
 async fn heartbeat_loop(sender: mpsc::Sender<oxiproxy::AgentClientMessage>) {
     let mut interval = tokio::time::interval(Duration::from_secs(15));
@@ -248,6 +375,8 @@ async fn heartbeat_loop(sender: mpsc::Sender<oxiproxy::AgentClientMessage>) {
         let msg = oxiproxy::AgentClientMessage {
             payload: Some(ClientPayload::Heartbeat(oxiproxy::Heartbeat {
                 timestamp: chrono::Utc::now().timestamp(),
+                proxy_speeds: Vec::new(),
+                system_info: Some(system_info()),
             })),
         };
 
@@ -259,7 +388,7 @@ async fn heartbeat_loop(sender: mpsc::Sender<oxiproxy::AgentClientMessage>) {
 }
 
 /// 将 gRPC ServerProxyGroup 转换为 client_config::ServerProxyGroup
-fn convert_server_groups(
+pub fn convert_server_groups(
     grpc_groups: Vec<oxiproxy::ServerProxyGroup>,
 ) -> Vec<ClientServerProxyGroup> {
     grpc_groups
@@ -271,12 +400,8 @@ fn convert_server_groups(
                 _ => TunnelProtocol::Quic,
             };
 
-            let kcp = g.kcp.map(|k| KcpConfig {
-                nodelay: k.nodelay,
-                interval: k.interval,
-                resend: k.resend,
-                nc: k.nc,
-            });
+            let kcp = g.kcp.map(KcpConfig::from);
+            let quic = g.quic.map(QuicConfig::from);
 
             let proxies = g
                 .proxies
@@ -289,6 +414,8 @@ fn convert_server_groups(
                     local_port: p.local_port,
                     remote_port: p.remote_port,
                     enabled: p.enabled,
+                    local_pool_size: p.local_pool_size,
+                    local_source: p.local_source,
                 })
                 .collect();
 
@@ -298,20 +425,50 @@ fn convert_server_groups(
                 server_port: g.server_port as u16,
                 protocol,
                 kcp,
+                quic,
                 proxies,
+                blocked_targets: g.blocked_targets,
+                allowed_targets: g.allowed_targets,
+                nat_probe_port: g.nat_probe_port.and_then(|p| u16::try_from(p).ok()),
             }
         })
         .collect()
 }
 
+/// 将 gRPC VisitorInfo 转换为 client_config::VisitorInfo，端口无效的忽略
+fn convert_visitors(grpc_visitors: Vec<oxiproxy::VisitorInfo>) -> Vec<ClientVisitorInfo> {
+    grpc_visitors
+        .into_iter()
+        .filter_map(|v| {
+            Some(ClientVisitorInfo {
+                visitor_id: v.visitor_id,
+                name: v.name,
+                proxy_type: v.proxy_type,
+                bind_ip: v.bind_ip,
+                bind_port: u16::try_from(v.bind_port).ok()?,
+                target_addr: v.target_addr,
+                target_port: u16::try_from(v.target_port).ok()?,
+            })
+        })
+        .collect()
+}
+
 /// 执行客户端自更新（阻塞操作，需在 spawn_blocking 中调用）
-fn perform_client_self_update() -> anyhow::Result<String> {
-    let status = self_update::backends::github::Update::configure()
+///
+/// `target_version` 为空时更新到最新版本
+fn perform_client_self_update(target_version: Option<&str>) -> anyhow::Result<String> {
+    let mut builder = self_update::backends::github::Update::configure();
+    if let Some(version) = target_version {
+        builder.target_version_tag(&format!("v{}", version.trim_start_matches('v')));
+    }
+
+    let status = builder
         .repo_owner("oxiproxy")
         .repo_name("oxiproxy")
         .bin_name("client")
         .identifier("client")
         .bin_path_in_archive("{bin}{bin_ext}")
+        .verifying_keys(common::update::verifying_keys()?)
         .show_download_progress(false)
         .current_version(env!("CARGO_PKG_VERSION"))
         .no_confirm(true)
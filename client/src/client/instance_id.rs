@@ -0,0 +1,73 @@
+//! 客户端实例 ID
+//!
+//! 首次启动时生成一个随机 UUID 写入本地文件，之后每次认证都上报给 Controller。实例 ID 与 token
+//! 无关：同一 token 被复制到多台主机时，Controller 据此区分是同一实例重连还是另一个实例在使用该
+//! token。路径由 `OXIPROXY_INSTANCE_ID_FILE` 指定（默认工作目录下的 `oxiproxy-client.id`），
+//! 容器部署时应放在持久化卷中，否则每次重建容器都会被当作新实例。
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use ring::rand::{SecureRandom, SystemRandom};
+use tracing::{info, warn};
+
+const DEFAULT_PATH: &str = "oxiproxy-client.id";
+
+/// 本机的实例 ID，首次调用时读取或生成
+pub fn get() -> &'static str {
+    static ID: OnceLock<String> = OnceLock::new();
+    ID.get_or_init(|| {
+        let path = common::env::var("OXIPROXY_INSTANCE_ID_FILE").map(PathBuf::from).unwrap_or_else(|| PathBuf::from(DEFAULT_PATH));
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            let id = content.trim();
+            if is_valid(id) {
+                return id.to_ascii_lowercase();
+            }
+            warn!("实例 ID 文件 {} 内容无效，重新生成", path.display());
+        }
+        let id = generate();
+        match std::fs::write(&path, format!("{}\n", id)) {
+            Ok(()) => info!("已生成客户端实例 ID {}，保存在 {}", id, path.display()),
+            Err(e) => warn!("保存实例 ID 到 {} 失败: {}，重启后将被视为新实例", path.display(), e),
+        }
+        id
+    })
+}
+
+/// 随机生成 UUID v4
+fn generate() -> String {
+    let mut bytes = [0u8; 16];
+    SystemRandom::new().fill(&mut bytes).expect("系统随机数不可用");
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+/// 是否为 8-4-4-4-12 格式的 UUID
+fn is_valid(id: &str) -> bool {
+    let groups: Vec<&str> = id.split('-').collect();
+    groups.len() == 5
+        && groups.iter().zip([8, 4, 4, 4, 12]).all(|(g, len)| g.len() == len && g.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_id_is_valid_v4() {
+        let id = generate();
+        assert!(is_valid(&id));
+        assert_eq!(&id[14..15], "4");
+        assert_ne!(id, generate());
+    }
+
+    #[test]
+    fn rejects_malformed_ids() {
+        assert!(!is_valid(""));
+        assert!(!is_valid("not-a-uuid"));
+        assert!(!is_valid("0123456789abcdef0123456789abcdef"));
+        assert!(is_valid("123e4567-e89b-42d3-a456-426614174000"));
+    }
+}
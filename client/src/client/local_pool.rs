@@ -0,0 +1,234 @@
+//! 本地服务预连接池
+//!
+//! 代理设置了 `local_pool_size` 时，客户端预先建立并保持若干条到本地服务的 TCP 连接，
+//! 新的隧道流直接取用，省去每个访客请求的本地握手延迟（适合 HTTP 等短连接后端）。
+//! 每条预连接只交给一个隧道流使用，用完即关闭，取走后在后台补充。
+//!
+//! 预连接同样经过目标策略检查；空闲超过 30 秒的连接会被替换，避免后端已关闭空闲连接。
+//!
+//! 这里同时记录各代理单独设置的本地连接源地址 / 网卡（`local_source`），
+//! 预连接和隧道流新建的本地连接都从该源发出，未设置时使用全局的 `--local-source`。
+
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use tokio::net::TcpStream;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use common::protocol::client_config::ProxyInfo;
+use common::source_binding::{self, SourceBinding};
+
+use crate::client::connector::resolve_allowed_target;
+use crate::client::target_policy::TargetPolicy;
+
+/// 预连接的最长空闲时间
+const MAX_IDLE: Duration = Duration::from_secs(30);
+/// 补充失败后的重试间隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+/// 建立预连接的超时时间
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+struct Pool {
+    target_addr: String,
+    size: AtomicUsize,
+    source: Option<SourceBinding>,
+    idle: Mutex<VecDeque<(TcpStream, Instant)>>,
+    refill: Notify,
+    cancel: CancellationToken,
+}
+
+impl Pool {
+    /// 保持空闲连接数等于池大小，直到池被移除
+    async fn run(self: Arc<Self>, policy: Arc<RwLock<TargetPolicy>>) {
+        loop {
+            self.idle
+                .lock()
+                .unwrap()
+                .retain(|(_, since)| since.elapsed() < MAX_IDLE);
+
+            let mut wait = MAX_IDLE;
+            while self.idle.lock().unwrap().len() < self.size.load(Ordering::Relaxed) {
+                match self.connect(&policy).await {
+                    Ok(stream) => self.idle.lock().unwrap().push_back((stream, Instant::now())),
+                    Err(e) => {
+                        debug!("建立本地预连接失败 {}: {}", self.target_addr, e);
+                        wait = RETRY_INTERVAL;
+                        break;
+                    }
+                }
+            }
+
+            tokio::select! {
+                _ = self.refill.notified() => {}
+                _ = tokio::time::sleep(wait) => {}
+                _ = self.cancel.cancelled() => return,
+            }
+        }
+    }
+
+    async fn connect(&self, policy: &RwLock<TargetPolicy>) -> anyhow::Result<TcpStream> {
+        let target = resolve_allowed_target(&self.target_addr, policy).await?;
+        let connect = source_binding::connect_tcp(target, self.source.as_ref());
+        tokio::time::timeout(CONNECT_TIMEOUT, connect).await?
+    }
+
+    /// 取出一条仍然可用、且连向 `target` 的预连接
+    async fn take(&self, target: SocketAddr) -> Option<TcpStream> {
+        loop {
+            let (stream, since) = self.idle.lock().unwrap().pop_back()?;
+            self.refill.notify_one();
+            if since.elapsed() >= MAX_IDLE || stream.peer_addr().ok() != Some(target) {
+                continue;
+            }
+            if is_alive(&stream).await {
+                return Some(stream);
+            }
+        }
+    }
+}
+
+/// 对端是否已关闭连接（后端主动发送的欢迎信息不算关闭，也不会被读走）
+async fn is_alive(stream: &TcpStream) -> bool {
+    let mut buf = [0u8; 1];
+    match tokio::time::timeout(Duration::ZERO, stream.peek(&mut buf)).await {
+        Ok(Ok(n)) => n > 0,
+        Ok(Err(_)) => false,
+        Err(_) => true,
+    }
+}
+
+/// 单个节点隧道上所有代理的预连接池
+pub struct LocalPools {
+    policy: Arc<RwLock<TargetPolicy>>,
+    pools: Mutex<HashMap<String, Arc<Pool>>>,
+    /// 本地地址 → 代理单独设置的连接源
+    sources: RwLock<HashMap<String, SourceBinding>>,
+}
+
+impl LocalPools {
+    pub fn new(policy: Arc<RwLock<TargetPolicy>>) -> Self {
+        Self { policy, pools: Mutex::new(HashMap::new()), sources: RwLock::new(HashMap::new()) }
+    }
+
+    /// 连接本地地址 `target_addr` 时使用的源，代理未单独设置时为全局默认值
+    pub fn source_for(&self, target_addr: &str) -> Option<SourceBinding> {
+        let sources = self.sources.read().unwrap();
+        sources.get(target_addr).cloned().or_else(|| source_binding::local_source().cloned())
+    }
+
+    /// 按代理列表调整连接池（同一本地地址取最大的池大小）和各本地地址的连接源
+    pub fn update(&self, proxies: &[ProxyInfo]) {
+        let mut sources = HashMap::new();
+        for p in proxies.iter().filter(|p| p.enabled) {
+            let Some(source) = p.local_source.as_deref().filter(|s| !s.is_empty()) else {
+                continue;
+            };
+            match source.parse::<SourceBinding>() {
+                Ok(source) => {
+                    sources.insert(common::utils::join_host_port(&p.local_ip, p.local_port as u16), source);
+                }
+                Err(e) => warn!("代理 {} 的本地连接源无效，使用默认值: {}", p.name, e),
+            }
+        }
+        *self.sources.write().unwrap() = sources;
+
+        let mut desired: HashMap<String, usize> = HashMap::new();
+        for p in proxies.iter().filter(|p| p.enabled && p.proxy_type == "tcp" && p.local_pool_size > 0) {
+            let target_addr = common::utils::join_host_port(&p.local_ip, p.local_port as u16);
+            let size = desired.entry(target_addr).or_default();
+            *size = (*size).max(p.local_pool_size as usize);
+        }
+
+        let mut pools = self.pools.lock().unwrap();
+        pools.retain(|target_addr, pool| {
+            let keep = desired.contains_key(target_addr);
+            if !keep {
+                pool.cancel.cancel();
+            }
+            keep
+        });
+        // 连接源变化时重建连接池，已有的预连接来自旧的源
+        pools.retain(|target_addr, pool| {
+            let keep = pool.source == self.source_for(target_addr);
+            if !keep {
+                pool.cancel.cancel();
+            }
+            keep
+        });
+        for (target_addr, size) in desired {
+            if let Some(pool) = pools.get(&target_addr) {
+                if pool.size.swap(size, Ordering::Relaxed) != size {
+                    pool.idle.lock().unwrap().truncate(size);
+                    pool.refill.notify_one();
+                }
+                continue;
+            }
+            info!("本地预连接池: {} ({} 条)", target_addr, size);
+            let pool = Arc::new(Pool {
+                target_addr: target_addr.clone(),
+                size: AtomicUsize::new(size),
+                source: self.source_for(&target_addr),
+                idle: Mutex::new(VecDeque::new()),
+                refill: Notify::new(),
+                cancel: CancellationToken::new(),
+            });
+            tokio::spawn(pool.clone().run(self.policy.clone()));
+            pools.insert(target_addr, pool);
+        }
+    }
+
+    /// 取一条到 `target_addr` 的预连接，`target` 为已通过策略检查的地址
+    pub async fn take(&self, target_addr: &str, target: SocketAddr) -> Option<TcpStream> {
+        let pool = self.pools.lock().unwrap().get(target_addr).cloned()?;
+        pool.take(target).await
+    }
+
+    /// 停止所有连接池（隧道断开时调用）
+    pub fn clear(&self) {
+        for (_, pool) in self.pools.lock().unwrap().drain() {
+            pool.cancel.cancel();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_take_skips_closed_connections() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = listener.local_addr().unwrap();
+        let pools = LocalPools::new(Arc::new(RwLock::new(TargetPolicy::default())));
+        pools.update(&[ProxyInfo {
+            proxy_id: 1,
+            name: "web".to_string(),
+            proxy_type: "tcp".to_string(),
+            local_ip: "127.0.0.1".to_string(),
+            local_port: target.port() as i32,
+            remote_port: 8080,
+            enabled: true,
+            local_pool_size: 2,
+            local_source: None,
+        }]);
+        let target_addr = target.to_string();
+
+        let (first, closed) = listener.accept().await.unwrap();
+        let (_second, _) = listener.accept().await.unwrap();
+        drop(first);
+        // 等待补充任务收到关闭事件
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // 已关闭的一条被跳过；取走后会在后台补充新的预连接，第二次可能取到补充的连接
+        let stream = pools.take(&target_addr, target).await;
+        assert!(stream.is_some_and(|s| s.local_addr().unwrap() != closed));
+        let stream = pools.take(&target_addr, target).await;
+        assert!(stream.is_none_or(|s| s.local_addr().unwrap() != closed));
+        pools.clear();
+    }
+}
@@ -16,14 +16,26 @@ pub struct LogEntry {
 /// 日志收集器 - 保存最近的日志到内存
 #[derive(Clone)]
 pub struct LogCollector {
-    logs: Arc<Mutex<VecDeque<LogEntry>>>,
+    logs: Arc<Mutex<LogBuffer>>,
     max_entries: usize,
 }
 
+struct LogBuffer {
+    entries: VecDeque<LogEntry>,
+    /// 已写入的日志总数，最旧一条的序号为 `total - entries.len()`
+    total: u64,
+    /// 下一条待上传日志的序号
+    upload_cursor: u64,
+}
+
 impl LogCollector {
     pub fn new(max_entries: usize) -> Self {
         Self {
-            logs: Arc::new(Mutex::new(VecDeque::with_capacity(max_entries))),
+            logs: Arc::new(Mutex::new(LogBuffer {
+                entries: VecDeque::with_capacity(max_entries),
+                total: 0,
+                upload_cursor: 0,
+            })),
             max_entries,
         }
     }
@@ -32,28 +44,51 @@ impl LogCollector {
         let mut logs = self.logs.lock().unwrap();
 
         // 如果达到最大容量，移除最旧的日志
-        if logs.len() >= self.max_entries {
-            logs.pop_front();
+        if logs.entries.len() >= self.max_entries {
+            logs.entries.pop_front();
         }
 
-        logs.push_back(LogEntry {
+        logs.entries.push_back(LogEntry {
             timestamp: chrono::Utc::now(),
             level,
             message,
         });
+        logs.total += 1;
     }
 
     /// 获取最近的N条日志
     pub fn get_recent_logs(&self, count: usize) -> Vec<LogEntry> {
         let logs = self.logs.lock().unwrap();
-        let start = logs.len().saturating_sub(count);
-        logs.iter().skip(start).cloned().collect()
+        let start = logs.entries.len().saturating_sub(count);
+        logs.entries.iter().skip(start).cloned().collect()
     }
 
     /// 获取所有日志
     pub fn get_all_logs(&self) -> Vec<LogEntry> {
         let logs = self.logs.lock().unwrap();
-        logs.iter().cloned().collect()
+        logs.entries.iter().cloned().collect()
+    }
+
+    /// 取出尚未上传、级别不低于 `min_level` 的日志，最多 `max` 条
+    ///
+    /// 返回的日志视为已上传；第二个值为上次调用后在上传前就被新日志挤出缓冲区的条数（不区分级别）。
+    /// 超出 `max` 的日志留到下次再取。
+    pub fn take_for_upload(&self, min_level: Level, max: usize) -> (Vec<LogEntry>, u64) {
+        let mut logs = self.logs.lock().unwrap();
+        let oldest = logs.total - logs.entries.len() as u64;
+        let overflowed = oldest.saturating_sub(logs.upload_cursor);
+        let mut cursor = logs.upload_cursor.max(oldest);
+
+        let mut batch = Vec::new();
+        while cursor < logs.total && batch.len() < max {
+            let entry = &logs.entries[(cursor - oldest) as usize];
+            if entry.level.parse::<Level>().is_ok_and(|level| level <= min_level) {
+                batch.push(entry.clone());
+            }
+            cursor += 1;
+        }
+        logs.upload_cursor = cursor;
+        (batch, overflowed)
     }
 }
 
@@ -115,3 +150,30 @@ impl tracing::field::Visit for LogVisitor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_for_upload() {
+        let collector = LogCollector::new(3);
+        collector.add_log("INFO".to_string(), "a".to_string());
+        collector.add_log("WARN".to_string(), "b".to_string());
+        collector.add_log("ERROR".to_string(), "c".to_string());
+
+        let (batch, overflowed) = collector.take_for_upload(Level::WARN, 1);
+        assert_eq!(batch.iter().map(|e| e.message.as_str()).collect::<Vec<_>>(), vec!["b"]);
+        assert_eq!(overflowed, 0);
+
+        // 未取走的 "c" 在被挤出前仍可上传，挤出的条数单独返回
+        collector.add_log("ERROR".to_string(), "d".to_string());
+        collector.add_log("ERROR".to_string(), "e".to_string());
+        collector.add_log("ERROR".to_string(), "f".to_string());
+        let (batch, overflowed) = collector.take_for_upload(Level::WARN, 10);
+        assert_eq!(batch.iter().map(|e| e.message.as_str()).collect::<Vec<_>>(), vec!["d", "e", "f"]);
+        assert_eq!(overflowed, 1);
+
+        assert!(collector.take_for_upload(Level::TRACE, 10).0.is_empty());
+    }
+}
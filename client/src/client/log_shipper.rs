@@ -0,0 +1,126 @@
+//! 日志上传
+//!
+//! 连接 Controller 期间每 5 秒把 `LogCollector` 中新增的日志批量上传，Controller 保存在内存中，
+//! 客户端离线后仍可在管理界面查看。只上传级别不低于 `OXIPROXY_LOG_UPLOAD_LEVEL` 的日志，
+//! 并按 `OXIPROXY_LOG_UPLOAD_RATE`（条/分钟）限速，超出的日志留在本地缓冲区，
+//! 被新日志挤出时计入下一批的丢弃条数。
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tracing::{warn, Level};
+
+use common::grpc::oxiproxy;
+use common::grpc::oxiproxy::agent_client_message::Payload as ClientPayload;
+
+use super::log_collector::LogCollector;
+
+const UPLOAD_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_LEVEL: Level = Level::WARN;
+const DEFAULT_RATE_PER_MINUTE: u32 = 120;
+
+struct UploadConfig {
+    min_level: Level,
+    rate_per_minute: u32,
+}
+
+/// 上传配置，`OXIPROXY_LOG_UPLOAD_LEVEL=off` 或 `OXIPROXY_LOG_UPLOAD_RATE=0` 时不上传
+fn config() -> Option<&'static UploadConfig> {
+    static CONFIG: OnceLock<Option<UploadConfig>> = OnceLock::new();
+    CONFIG
+        .get_or_init(|| {
+            let min_level = match common::env::var("OXIPROXY_LOG_UPLOAD_LEVEL") {
+                Some(v) if v.eq_ignore_ascii_case("off") => return None,
+                Some(v) => v.parse::<Level>().unwrap_or_else(|_| {
+                    warn!("OXIPROXY_LOG_UPLOAD_LEVEL 无效: {}，使用默认值 {}", v, DEFAULT_LEVEL);
+                    DEFAULT_LEVEL
+                }),
+                None => DEFAULT_LEVEL,
+            };
+            let rate_per_minute =
+                common::env::parse::<u32>("OXIPROXY_LOG_UPLOAD_RATE").unwrap_or(DEFAULT_RATE_PER_MINUTE);
+            (rate_per_minute > 0).then_some(UploadConfig { min_level, rate_per_minute })
+        })
+        .as_ref()
+}
+
+/// 令牌桶：每分钟补充 `rate` 个，最多积累一分钟的量
+struct RateLimiter {
+    rate: f64,
+    tokens: f64,
+}
+
+impl RateLimiter {
+    fn new(rate_per_minute: u32) -> Self {
+        let rate = rate_per_minute as f64;
+        Self { rate, tokens: rate }
+    }
+
+    fn refill(&mut self, elapsed: Duration) {
+        self.tokens = (self.tokens + self.rate * elapsed.as_secs_f64() / 60.0).min(self.rate);
+    }
+
+    /// 当前可发送的条数
+    fn available(&self) -> usize {
+        self.tokens as usize
+    }
+
+    fn consume(&mut self, count: usize) {
+        self.tokens = (self.tokens - count as f64).max(0.0);
+    }
+}
+
+/// 上传循环，连接断开（发送失败）时退出
+pub async fn run(collector: LogCollector, sender: mpsc::Sender<oxiproxy::AgentClientMessage>) {
+    let Some(config) = config() else {
+        return;
+    };
+    let mut limiter = RateLimiter::new(config.rate_per_minute);
+    let mut interval = tokio::time::interval(UPLOAD_INTERVAL);
+    interval.tick().await; // 跳过首次
+
+    loop {
+        interval.tick().await;
+        limiter.refill(UPLOAD_INTERVAL);
+
+        let (logs, dropped) = collector.take_for_upload(config.min_level, limiter.available());
+        if logs.is_empty() && dropped == 0 {
+            continue;
+        }
+        limiter.consume(logs.len());
+
+        let msg = oxiproxy::AgentClientMessage {
+            payload: Some(ClientPayload::LogUpload(oxiproxy::ClientLogUpload {
+                logs: logs
+                    .into_iter()
+                    .map(|l| oxiproxy::LogEntry {
+                        timestamp: l.timestamp.to_rfc3339(),
+                        level: l.level,
+                        message: l.message,
+                    })
+                    .collect(),
+                dropped,
+            })),
+        };
+        if sender.send(msg).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::new(60);
+        assert_eq!(limiter.available(), 60);
+        limiter.consume(60);
+        limiter.refill(Duration::from_secs(5));
+        assert_eq!(limiter.available(), 5);
+        limiter.refill(Duration::from_secs(600));
+        assert_eq!(limiter.available(), 60);
+    }
+}
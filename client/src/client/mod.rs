@@ -1,7 +1,17 @@
 pub mod connector;
 pub mod log_collector;
+pub mod log_shipper;
 pub mod connection_manager;
 pub mod grpc_client;
+pub mod target_policy;
+pub mod local_pool;
+pub mod diagnose;
+pub mod nat_detect;
+pub mod mtu_probe;
+pub mod health;
+pub mod visitor;
+pub mod config_cache;
+pub mod instance_id;
 
 use anyhow::Result;
 use std::time::Duration;
@@ -9,11 +19,15 @@ use tracing::{info, error, warn};
 use tracing_subscriber::{EnvFilter, fmt, prelude::*, layer::SubscriberExt};
 use log_collector::{LogCollector, LogCollectorLayer};
 
+/// 同一 token 的另一个实例在线而被拒绝时的重试间隔
+const DUPLICATE_RETRY_DELAY: Duration = Duration::from_secs(60);
+
 pub async fn run_client(
     controller_url: String,
     token: String,
     tls_ca_cert: Option<Vec<u8>>,
     log_dir: Option<String>,
+    health_port: Option<u16>,
 ) -> Result<()> {
     // 初始化日志收集器（保留最近 1000 条日志）
     let log_collector = LogCollector::new(1000);
@@ -39,34 +53,118 @@ pub async fn run_client(
     }
 
     info!("OxiProxy 客户端启动");
+    target_policy::init_local_policy()?;
     info!("控制器地址: {}", controller_url);
 
+    // 健康检查服务（容器 HEALTHCHECK 通过 `client health` 访问）
+    let health = health::HealthState::new();
+    if let Some(port) = health_port {
+        health::start_health_server(port, health.clone());
+    }
+
     // Controller 模式：通过 gRPC 双向流接收代理列表推送
     let conn_manager = connection_manager::ConnectionManager::new(
         token.clone(),
         log_collector.clone(),
     );
 
-    // 断线重连循环
-    loop {
-        match grpc_client::connect_and_run(&controller_url, &token, tls_ca_cert.as_deref(), log_collector.clone()).await {
-            Ok((_client_id, client_name, mut update_rx)) => {
-                info!("已连接控制器: {}", client_name);
+    // 断线重连循环，收到终止信号（容器停止时发送 SIGTERM）后断开所有隧道再退出
+    tokio::select! {
+        _ = async {
+            // 尚未从 Controller 或缓存获得过配置
+            let mut configured = false;
+            loop {
+                match grpc_client::connect_and_run(&controller_url, &token, tls_ca_cert.as_deref(), log_collector.clone()).await {
+                    Ok((client_id, client_name, mut update_rx)) => {
+                        info!("已连接控制器: {}", client_name);
+                        conn_manager.set_client_id(client_id);
+                        health.set_controller_connected(true);
 
-                // 接收代理列表推送并调和连接
-                while let Some(server_groups) = update_rx.recv().await {
-                    info!("代理配置已更新: {} 个节点", server_groups.len());
-                    conn_manager.reconcile(server_groups).await;
+                        // 接收代理列表推送并调和连接
+                        while let Some(update) = update_rx.recv().await {
+                            info!(
+                                "代理配置已更新: {} 个节点, {} 个访客代理",
+                                update.server_groups.len(),
+                                update.visitors.len()
+                            );
+                            configured = true;
+                            let cached = config_cache::CachedConfig {
+                                client_id,
+                                client_name: client_name.clone(),
+                                server_groups: update.server_groups.clone(),
+                                visitors: update.visitors.clone(),
+                                saved_at: chrono::Utc::now(),
+                            };
+                            if let Err(e) = config_cache::save(&token, &cached) {
+                                warn!("保存配置缓存失败: {}", e);
+                            }
+                            conn_manager.reconcile(update.server_groups).await;
+                            conn_manager.reconcile_visitors(update.visitors).await;
+                        }
+
+                        health.set_controller_connected(false);
+                        if grpc_client::kicked_out() {
+                            // 重复登录策略为踢下线：让出给新实例，不再重连
+                            error!("已被同一 token 的另一个客户端实例踢下线，断开所有隧道并停止重连");
+                            conn_manager.reconcile(Vec::new()).await;
+                            conn_manager.reconcile_visitors(Vec::new()).await;
+                            std::future::pending::<()>().await;
+                        }
+                        warn!("控制器连接断开");
+                    }
+                    Err(e) => {
+                        error!("连接控制器失败: {}", e);
+                        let duplicate = e
+                            .downcast_ref::<grpc_client::AuthRejected>()
+                            .is_some_and(|r| r.code == grpc_client::DUPLICATE_INSTANCE);
+                        if duplicate {
+                            // 另一个实例在线：不按缓存启动，已按缓存建立的隧道也断开，放慢重试
+                            configured = true;
+                            conn_manager.reconcile(Vec::new()).await;
+                            conn_manager.reconcile_visitors(Vec::new()).await;
+                            common::grpc::reconnect::set_hint(DUPLICATE_RETRY_DELAY);
+                        } else if !configured {
+                            // 启动时连不上 Controller，先按缓存的配置建立隧道
+                            configured = true;
+                            start_from_cache(&conn_manager, &token).await;
+                        }
+                    }
                 }
 
-                warn!("控制器连接断开");
-            }
-            Err(e) => {
-                error!("连接控制器失败: {}", e);
+                // 有 Controller 下发的重连延迟时按延迟等待，否则在 5 秒基础上加随机抖动
+                let delay = common::grpc::reconnect::next_delay(Duration::from_secs(5));
+                warn!("{} ms 后重连...", delay.as_millis());
+                tokio::time::sleep(delay).await;
             }
+        } => {}
+        signal = common::health::shutdown_signal() => {
+            info!("收到 {} 信号，正在关闭客户端...", signal);
         }
-
-        warn!("5 秒后重连...");
-        tokio::time::sleep(Duration::from_secs(5)).await;
     }
+
+    health.set_controller_connected(false);
+    conn_manager.shutdown().await;
+    info!("客户端已停止");
+    Ok(())
+}
+
+/// 按本地缓存的配置建立隧道，Controller 恢复后推送的配置会覆盖缓存
+async fn start_from_cache(conn_manager: &connection_manager::ConnectionManager, token: &str) {
+    let cached = match config_cache::load(token) {
+        Ok(Some(cached)) => cached,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("忽略配置缓存: {}", e);
+            return;
+        }
+    };
+    warn!(
+        "使用 {} 缓存的配置启动: {} 个节点, {} 个访客代理，后台继续重连控制器",
+        cached.saved_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"),
+        cached.server_groups.len(),
+        cached.visitors.len()
+    );
+    conn_manager.set_client_id(cached.client_id);
+    conn_manager.reconcile(cached.server_groups).await;
+    conn_manager.reconcile_visitors(cached.visitors).await;
 }
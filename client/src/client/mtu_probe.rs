@@ -0,0 +1,174 @@
+//! 大包黑洞检测
+//!
+//! PPPoE、VPN 等额外封装会降低链路 MTU，部分链路既不转发超过 MTU 的包、也不回 ICMP
+//! （MTU 黑洞），表现为握手正常、传输大量数据时卡住。这里向节点的 NAT 探测端口依次发送
+//! 从小到大、要求原样大小回复的探测包（协议见 `common::nat_probe`），找出往返都能通过的
+//! 最大包，并据此给出 KCP `mtu` 和 QUIC `max_mtu` 的建议值。
+//!
+//! Linux 上探测包设置 DF 标志，其他平台可能由操作系统分片发送，结果只能作为参考。
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+use tracing::{info, warn};
+
+use common::config::{KCP_MTU_RANGE, QUIC_MTU_RANGE};
+use common::nat_probe::{ProbeRequest, ProbeResponse, FLAG_PAD_REPLY, REQUEST_LEN};
+use common::protocol::client_config::ServerProxyGroup;
+
+/// 依次探测的 IP 包大小（字节）
+const PROBE_MTUS: [u16; 9] = [576, 1280, 1380, 1400, 1420, 1452, 1480, 1492, 1500];
+/// 每次探测等待回复的时间
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+/// 每个大小的发送次数（UDP 可能丢包）
+const PROBE_ATTEMPTS: u32 = 3;
+
+#[derive(Debug, Serialize)]
+pub struct MtuStep {
+    /// IP 包大小
+    pub ip_mtu: u16,
+    /// UDP 载荷大小
+    pub payload: usize,
+    pub ok: bool,
+    /// 发送失败的原因（本机或已知路径 MTU 不允许），超时不算
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MtuProbeResult {
+    pub node_id: i64,
+    pub steps: Vec<MtuStep>,
+    /// 往返都能通过的最大 IP 包
+    pub max_ip_mtu: Option<u16>,
+    /// 更大的包被静默丢弃（没有发送错误，也没有回复）
+    pub blackhole: bool,
+    /// 节点是否按请求大小回复（旧版本节点只回复小包，只检测了上行）
+    pub padded_reply: bool,
+    /// 建议的 KCP `mtu`
+    pub recommended_kcp_mtu: Option<u32>,
+    /// 建议的 QUIC `max_mtu`，小于 1200 时 QUIC 无法正常工作
+    pub recommended_quic_max_mtu: Option<u16>,
+}
+
+/// 向节点的探测端口检测路径 MTU
+pub async fn probe(group: &ServerProxyGroup) -> Result<MtuProbeResult> {
+    let port = group.nat_probe_port.ok_or_else(|| anyhow!("节点 #{} 未启用 NAT 探测", group.node_id))?;
+    let server = tokio::net::lookup_host((group.server_addr.as_str(), port))
+        .await?
+        .next()
+        .ok_or_else(|| anyhow!("无法解析节点地址: {}", group.server_addr))?;
+
+    let bind: SocketAddr = if server.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+    let socket = UdpSocket::bind(bind).await?;
+    set_dont_fragment(&socket, server.is_ipv4());
+    // IPv4 / IPv6 头部加 UDP 头部
+    let overhead = if server.is_ipv4() { 28 } else { 48 };
+
+    let mut txn = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+
+    let mut steps = Vec::new();
+    let mut padded_reply = true;
+    let mut blackhole = false;
+    for ip_mtu in PROBE_MTUS {
+        let payload = (ip_mtu as usize - overhead).max(REQUEST_LEN);
+        txn = txn.wrapping_add(1);
+        let step = match send_probe(&socket, server, payload, txn).await {
+            Ok(Some(reply_len)) => {
+                padded_reply &= reply_len >= payload;
+                MtuStep { ip_mtu, payload, ok: true, error: None }
+            }
+            Ok(None) => {
+                // 小包能通过、大包没有任何回应
+                blackhole = steps.iter().any(|s: &MtuStep| s.ok);
+                MtuStep { ip_mtu, payload, ok: false, error: None }
+            }
+            Err(e) => MtuStep { ip_mtu, payload, ok: false, error: Some(e.to_string()) },
+        };
+        let ok = step.ok;
+        steps.push(step);
+        if !ok {
+            break;
+        }
+    }
+
+    let best = steps.iter().rfind(|s| s.ok);
+    let max_ip_mtu = best.map(|s| s.ip_mtu);
+    let max_payload = best.map(|s| s.payload);
+    let result = MtuProbeResult {
+        node_id: group.node_id,
+        max_ip_mtu,
+        blackhole,
+        padded_reply: padded_reply && best.is_some(),
+        recommended_kcp_mtu: max_payload
+            .map(|p| (p as u32).clamp(*KCP_MTU_RANGE.start(), *KCP_MTU_RANGE.end())),
+        recommended_quic_max_mtu: max_payload
+            .map(|p| p.min(*QUIC_MTU_RANGE.end() as usize) as u16),
+        steps,
+    };
+
+    match result.max_ip_mtu {
+        Some(mtu) if result.blackhole => warn!(
+            "节点 #{} 路径存在大包黑洞：{} 字节以内的包可以通过，更大的包被静默丢弃；建议 KCP mtu ≤ {}，QUIC max_mtu ≤ {}",
+            group.node_id,
+            mtu,
+            result.recommended_kcp_mtu.unwrap_or_default(),
+            result.recommended_quic_max_mtu.unwrap_or_default()
+        ),
+        Some(mtu) => info!("节点 #{} 路径 MTU 检测: 最大 {} 字节", group.node_id, mtu),
+        None => warn!("节点 #{} 路径 MTU 检测: 探测包没有回复", group.node_id),
+    }
+    if !result.padded_reply && result.max_ip_mtu.is_some() {
+        info!("节点 #{} 不支持按请求大小回复，只检测了上行方向", group.node_id);
+    }
+    Ok(result)
+}
+
+/// 发送一个 `payload` 字节的探测，返回回复长度；超时返回 `None`
+async fn send_probe(socket: &UdpSocket, target: SocketAddr, payload: usize, txn: u64) -> Result<Option<usize>> {
+    let request = ProbeRequest { flags: FLAG_PAD_REPLY, txn }.encode_padded(payload);
+    let mut buf = vec![0u8; payload.max(REQUEST_LEN) + 64];
+    for _ in 0..PROBE_ATTEMPTS {
+        socket.send_to(&request, target).await?;
+        let deadline = tokio::time::Instant::now() + PROBE_TIMEOUT;
+        // 丢弃之前探测的迟到回复
+        while let Ok(result) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+            let Ok((len, _)) = result else { continue };
+            if ProbeResponse::decode(&buf[..len]).is_some_and(|r| r.txn == txn) {
+                return Ok(Some(len));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// 设置 DF 标志并忽略内核缓存的路径 MTU，过大的包直接被丢弃而不是分片
+#[cfg(target_os = "linux")]
+fn set_dont_fragment(socket: &UdpSocket, ipv4: bool) {
+    use std::os::fd::AsRawFd;
+
+    let (level, name, value) = if ipv4 {
+        (libc::IPPROTO_IP, libc::IP_MTU_DISCOVER, libc::IP_PMTUDISC_PROBE)
+    } else {
+        (libc::IPPROTO_IPV6, libc::IPV6_MTU_DISCOVER, libc::IPV6_PMTUDISC_PROBE)
+    };
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if ret != 0 {
+        warn!("设置 DF 标志失败: {}", std::io::Error::last_os_error());
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_dont_fragment(_socket: &UdpSocket, _ipv4: bool) {}
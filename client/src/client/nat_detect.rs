@@ -0,0 +1,129 @@
+//! NAT 类型检测
+//!
+//! 向节点的 NAT 探测端口发送探测（协议见 `common::nat_probe`），判断本机所在网络的 NAT 类型，
+//! 结果通过 gRPC 上报给 Controller。对称型 NAT 下 KCP / QUIC 连接在映射变化后容易中断，
+//! 也无法进行 P2P 打洞。
+
+use anyhow::{anyhow, Result};
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use common::grpc::oxiproxy;
+use common::grpc::oxiproxy::agent_client_message::Payload as ClientPayload;
+use common::nat_probe::{self, NatType, ProbeRequest, ProbeResponse, FLAG_ALT_PORT};
+use common::protocol::client_config::ServerProxyGroup;
+
+/// 每次探测等待回复的时间
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+/// 每次探测的发送次数（UDP 可能丢包）
+const PROBE_ATTEMPTS: u32 = 3;
+
+/// NAT 检测结果
+pub struct NatDetection {
+    pub node_id: i64,
+    pub nat_type: NatType,
+    pub local_addr: SocketAddr,
+    pub mapped_addr: Option<SocketAddr>,
+}
+
+/// 选取第一个启用了 NAT 探测的节点
+pub fn probe_target(groups: &[ServerProxyGroup]) -> Option<&ServerProxyGroup> {
+    groups.iter().find(|g| g.nat_probe_port.is_some())
+}
+
+/// 向节点的探测端口发送探测并判断 NAT 类型
+pub async fn detect(group: &ServerProxyGroup) -> Result<NatDetection> {
+    let port = group.nat_probe_port.ok_or_else(|| anyhow!("节点 #{} 未启用 NAT 探测", group.node_id))?;
+    let alt_port = port.checked_add(1).ok_or_else(|| anyhow!("NAT 探测端口无效: {}", port))?;
+    let server = tokio::net::lookup_host((group.server_addr.as_str(), port))
+        .await?
+        .next()
+        .ok_or_else(|| anyhow!("无法解析节点地址: {}", group.server_addr))?;
+
+    let bind: SocketAddr = if server.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+    let socket = UdpSocket::bind(bind).await?;
+    let local_addr = SocketAddr::new(local_ip_towards(bind, server).await?, socket.local_addr()?.port());
+
+    let mut txn = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+    let mut next_txn = || {
+        txn = txn.wrapping_add(1);
+        txn
+    };
+
+    let mapped1 = probe(&socket, server, 0, next_txn()).await;
+    let (mapped2, alt_reply) = if mapped1.is_some() {
+        let alt = SocketAddr::new(server.ip(), alt_port);
+        let mapped2 = probe(&socket, alt, 0, next_txn()).await;
+        let alt_reply = probe(&socket, server, FLAG_ALT_PORT, next_txn()).await.is_some();
+        (mapped2, alt_reply)
+    } else {
+        (None, false)
+    };
+
+    Ok(NatDetection {
+        node_id: group.node_id,
+        nat_type: nat_probe::classify(local_addr, mapped1, mapped2, alt_reply),
+        local_addr,
+        mapped_addr: mapped1,
+    })
+}
+
+/// 检测 NAT 类型并通过 gRPC 上报
+pub async fn detect_and_report(group: ServerProxyGroup, sender: mpsc::Sender<oxiproxy::AgentClientMessage>) {
+    let detection = match detect(&group).await {
+        Ok(d) => d,
+        Err(e) => {
+            warn!("NAT 类型检测失败: {}", e);
+            return;
+        }
+    };
+    info!(
+        "NAT 类型: {}（本地地址 {}，映射地址 {}）",
+        detection.nat_type,
+        detection.local_addr,
+        detection.mapped_addr.map(|a| a.to_string()).unwrap_or_else(|| "-".to_string())
+    );
+
+    let msg = oxiproxy::AgentClientMessage {
+        payload: Some(ClientPayload::NatReport(oxiproxy::NatReport {
+            nat_type: detection.nat_type.to_string(),
+            mapped_addr: detection.mapped_addr.map(|a| a.to_string()).unwrap_or_default(),
+            local_addr: detection.local_addr.to_string(),
+            node_id: detection.node_id,
+        })),
+    };
+    if sender.send(msg).await.is_err() {
+        warn!("上报 NAT 类型失败，连接可能已断开");
+    }
+}
+
+/// 发送一次探测，返回节点看到的映射地址；`flags` 带 `FLAG_ALT_PORT` 时回复来自另一个端口
+async fn probe(socket: &UdpSocket, target: SocketAddr, flags: u8, txn: u64) -> Option<SocketAddr> {
+    let request = ProbeRequest { flags, txn }.encode();
+    let mut buf = [0u8; 128];
+    for _ in 0..PROBE_ATTEMPTS {
+        socket.send_to(&request, target).await.ok()?;
+        let deadline = tokio::time::Instant::now() + PROBE_TIMEOUT;
+        // 丢弃之前探测的迟到回复
+        while let Ok(result) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+            let Ok((len, _)) = result else { continue };
+            if let Some(resp) = ProbeResponse::decode(&buf[..len]).filter(|r| r.txn == txn) {
+                return Some(resp.mapped);
+            }
+        }
+    }
+    None
+}
+
+/// 访问 `server` 时操作系统选择的本地地址（UDP connect 不发送数据）
+async fn local_ip_towards(bind: SocketAddr, server: SocketAddr) -> Result<std::net::IpAddr> {
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(server).await?;
+    Ok(socket.local_addr()?.ip())
+}
@@ -0,0 +1,103 @@
+//! 本地目标地址策略
+//!
+//! 客户端连接本地服务前检查目标地址，防止被 Controller 或其他用户当作跳板访问任意内网：
+//!
+//! - 本机白名单：环境变量 `OXIPROXY_ALLOWED_TARGETS`（逗号分隔，例如 `127.0.0.1,192.168.1.0/24`），
+//!   由客户端运维人员配置，Controller 无法修改；
+//! - Controller 下发的白名单：客户端所有者在管理界面设置，只能进一步收紧；
+//! - Controller 下发的端口黑名单。
+//!
+//! 目标必须同时通过两份白名单（未配置的白名单不限制），且不命中黑名单。
+
+use anyhow::{anyhow, Result};
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use tracing::error;
+
+use common::protocol::client_config::ServerProxyGroup;
+use common::target_rule::TargetRules;
+
+static LOCAL_ALLOWED: OnceLock<Option<TargetRules>> = OnceLock::new();
+
+/// 读取本机白名单，配置无效时返回错误（客户端拒绝启动，避免白名单意外失效）
+pub fn init_local_policy() -> Result<()> {
+    let rules = match common::env::var("OXIPROXY_ALLOWED_TARGETS") {
+        Some(value) => {
+            let rules: Vec<&str> = value.split(',').map(str::trim).filter(|r| !r.is_empty()).collect();
+            Some(TargetRules::parse(&rules).map_err(|e| anyhow!("OXIPROXY_ALLOWED_TARGETS: {}", e))?)
+        }
+        None => None,
+    };
+    let _ = LOCAL_ALLOWED.set(rules);
+    Ok(())
+}
+
+fn local_allowed() -> Option<&'static TargetRules> {
+    LOCAL_ALLOWED.get().and_then(|r| r.as_ref())
+}
+
+/// 单个节点隧道的目标策略（随 Controller 推送更新）
+#[derive(Debug, Clone, Default)]
+pub struct TargetPolicy {
+    /// Controller 下发的白名单，`None` 表示不限制
+    allowed: Option<TargetRules>,
+    blocked: TargetRules,
+}
+
+impl TargetPolicy {
+    pub fn from_group(group: &ServerProxyGroup) -> Self {
+        let allowed = if group.allowed_targets.is_empty() {
+            None
+        } else {
+            // 白名单无效时拒绝所有目标，而不是放开限制
+            Some(TargetRules::parse(&group.allowed_targets).unwrap_or_else(|e| {
+                error!("节点 #{} 的本地目标白名单无效，拒绝所有转发: {}", group.node_id, e);
+                TargetRules::default()
+            }))
+        };
+        Self {
+            allowed,
+            blocked: TargetRules::parse_lenient(&group.blocked_targets),
+        }
+    }
+
+    /// 检查目标地址，不允许时返回原因
+    pub fn check(&self, target: SocketAddr) -> Result<(), String> {
+        if let Some(local) = local_allowed() {
+            if local.find_match(target).is_none() {
+                return Err("不在本机白名单 OXIPROXY_ALLOWED_TARGETS 内".to_string());
+            }
+        }
+        if let Some(allowed) = &self.allowed {
+            if allowed.find_match(target).is_none() {
+                return Err("不在客户端的本地目标白名单内".to_string());
+            }
+        }
+        if let Some(rule) = self.blocked.find_match(target) {
+            return Err(format!("命中端口黑名单规则 {}", rule));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pushed_policy() {
+        let policy = TargetPolicy {
+            allowed: Some(TargetRules::parse(&["127.0.0.1", "192.168.1.0/24"]).unwrap()),
+            blocked: TargetRules::parse(&["192.168.1.1:22"]).unwrap(),
+        };
+        assert!(policy.check("127.0.0.1:8080".parse().unwrap()).is_ok());
+        assert!(policy.check("192.168.1.20:80".parse().unwrap()).is_ok());
+        assert!(policy.check("192.168.1.1:22".parse().unwrap()).is_err());
+        assert!(policy.check("10.0.0.1:80".parse().unwrap()).is_err());
+
+        // 白名单无效时拒绝所有目标
+        let deny_all = TargetPolicy { allowed: Some(TargetRules::default()), ..Default::default() };
+        assert!(deny_all.check("127.0.0.1:80".parse().unwrap()).is_err());
+        assert!(TargetPolicy::default().check("10.0.0.1:80".parse().unwrap()).is_ok());
+    }
+}
@@ -0,0 +1,234 @@
+//! 访客代理
+//!
+//! 在客户端本机监听端口，把每个连接转发到目标代理在节点上的地址，
+//! 从而在本机通过 `127.0.0.1:<端口>` 访问另一个客户端暴露的服务。
+//! 监听失败（如端口被占用）时每 5 秒重试，直到被取消。
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use tokio::net::{TcpListener, UdpSocket};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use common::protocol::client_config::VisitorInfo;
+
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// UDP 会话无数据多久后关闭
+const UDP_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 运行访客监听，直到被取消
+pub async fn run(visitor: VisitorInfo, cancel: CancellationToken) {
+    let bind_addr = match visitor.bind_ip.parse::<IpAddr>() {
+        Ok(ip) => SocketAddr::new(ip, visitor.bind_port),
+        Err(_) => {
+            error!("访客代理 {} 监听地址无效: {}", visitor.name, visitor.bind_ip);
+            return;
+        }
+    };
+    let udp = visitor.proxy_type.eq_ignore_ascii_case("udp");
+
+    loop {
+        let result = if udp {
+            run_udp(&visitor, bind_addr, &cancel).await
+        } else {
+            run_tcp(&visitor, bind_addr, &cancel).await
+        };
+        match result {
+            Ok(()) => return,
+            Err(e) => error!("访客代理 {} 监听 {} 失败: {}，{} 秒后重试", visitor.name, bind_addr, e, RETRY_INTERVAL.as_secs()),
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(RETRY_INTERVAL) => {}
+            _ = cancel.cancelled() => return,
+        }
+    }
+}
+
+/// 解析目标代理地址（可能是域名）
+async fn resolve_target(visitor: &VisitorInfo) -> Result<SocketAddr> {
+    tokio::net::lookup_host((visitor.target_addr.as_str(), visitor.target_port))
+        .await?
+        .next()
+        .ok_or_else(|| anyhow!("无法解析 {}", visitor.target_addr))
+}
+
+async fn run_tcp(visitor: &VisitorInfo, bind_addr: SocketAddr, cancel: &CancellationToken) -> Result<()> {
+    let listener = TcpListener::bind(bind_addr).await?;
+    info!(
+        "访客代理 {} 已监听 {} -> {}:{}",
+        visitor.name, bind_addr, visitor.target_addr, visitor.target_port
+    );
+
+    loop {
+        let (mut inbound, peer) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = cancel.cancelled() => {
+                info!("访客代理 {} 已停止", visitor.name);
+                return Ok(());
+            }
+        };
+
+        let visitor = visitor.clone();
+        let cancel = cancel.clone();
+        tokio::spawn(async move {
+            let connect = async {
+                let target = resolve_target(&visitor).await?;
+                // 访客连接的是节点的公网地址，与隧道使用同一出口
+                common::source_binding::connect_tcp(target, common::source_binding::tunnel_source()).await
+            };
+            let mut outbound = match tokio::time::timeout(CONNECT_TIMEOUT, connect).await {
+                Ok(Ok(stream)) => stream,
+                Ok(Err(e)) => {
+                    warn!("访客代理 {} 连接目标失败: {}", visitor.name, e);
+                    return;
+                }
+                Err(_) => {
+                    warn!("访客代理 {} 连接目标超时", visitor.name);
+                    return;
+                }
+            };
+            let _ = inbound.set_nodelay(true);
+            let _ = outbound.set_nodelay(true);
+
+            tokio::select! {
+                result = tokio::io::copy_bidirectional(&mut inbound, &mut outbound) => match result {
+                    Ok((sent, received)) => {
+                        debug!("访客代理 {} 连接 {} 结束: 发送 {} 字节，接收 {} 字节", visitor.name, peer, sent, received)
+                    }
+                    Err(e) => debug!("访客代理 {} 连接 {} 中断: {}", visitor.name, peer, e),
+                },
+                _ = cancel.cancelled() => {}
+            }
+        });
+    }
+}
+
+async fn run_udp(visitor: &VisitorInfo, bind_addr: SocketAddr, cancel: &CancellationToken) -> Result<()> {
+    let socket = Arc::new(UdpSocket::bind(bind_addr).await?);
+    info!(
+        "访客代理 {} 已监听 UDP {} -> {}:{}",
+        visitor.name, bind_addr, visitor.target_addr, visitor.target_port
+    );
+
+    // 每个本地来源地址对应一个到目标的 UDP 会话
+    let sessions: Arc<Mutex<HashMap<SocketAddr, Arc<UdpSocket>>>> = Arc::new(Mutex::new(HashMap::new()));
+    let mut buf = vec![0u8; 65536];
+    loop {
+        let (len, peer) = tokio::select! {
+            received = socket.recv_from(&mut buf) => received?,
+            _ = cancel.cancelled() => {
+                info!("访客代理 {} 已停止", visitor.name);
+                return Ok(());
+            }
+        };
+
+        let existing = sessions.lock().unwrap_or_else(|e| e.into_inner()).get(&peer).cloned();
+        let upstream = match existing {
+            Some(upstream) => upstream,
+            None => match open_udp_session(visitor).await {
+                Ok(upstream) => {
+                    let upstream = Arc::new(upstream);
+                    sessions.lock().unwrap_or_else(|e| e.into_inner()).insert(peer, upstream.clone());
+                    tokio::spawn(relay_udp_replies(
+                        socket.clone(),
+                        upstream.clone(),
+                        peer,
+                        sessions.clone(),
+                        cancel.clone(),
+                    ));
+                    upstream
+                }
+                Err(e) => {
+                    warn!("访客代理 {} 连接目标失败: {}", visitor.name, e);
+                    continue;
+                }
+            },
+        };
+        if let Err(e) = upstream.send(&buf[..len]).await {
+            debug!("访客代理 {} 发送 UDP 数据失败: {}", visitor.name, e);
+        }
+    }
+}
+
+async fn open_udp_session(visitor: &VisitorInfo) -> Result<UdpSocket> {
+    let target = resolve_target(visitor).await?;
+    let upstream = common::source_binding::udp_socket_for(target, common::source_binding::tunnel_source()).await?;
+    upstream.connect(target).await?;
+    Ok(upstream)
+}
+
+/// 把目标的回包转发给本地来源地址，会话空闲超时后移除
+async fn relay_udp_replies(
+    socket: Arc<UdpSocket>,
+    upstream: Arc<UdpSocket>,
+    peer: SocketAddr,
+    sessions: Arc<Mutex<HashMap<SocketAddr, Arc<UdpSocket>>>>,
+    cancel: CancellationToken,
+) {
+    let mut buf = vec![0u8; 65536];
+    loop {
+        let len = tokio::select! {
+            received = tokio::time::timeout(UDP_IDLE_TIMEOUT, upstream.recv(&mut buf)) => match received {
+                Ok(Ok(len)) => len,
+                _ => break,
+            },
+            _ = cancel.cancelled() => break,
+        };
+        if socket.send_to(&buf[..len], peer).await.is_err() {
+            break;
+        }
+    }
+    sessions.lock().unwrap_or_else(|e| e.into_inner()).remove(&peer);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    #[tokio::test]
+    async fn test_tcp_forward() {
+        // 模拟节点上的目标代理：回显服务
+        let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target_port = target.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = target.accept().await.unwrap();
+            let mut buf = [0u8; 5];
+            stream.read_exact(&mut buf).await.unwrap();
+            stream.write_all(&buf).await.unwrap();
+        });
+
+        let bind_port = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+        let visitor = VisitorInfo {
+            visitor_id: 1,
+            name: "test".to_string(),
+            proxy_type: "tcp".to_string(),
+            bind_ip: "127.0.0.1".to_string(),
+            bind_port,
+            target_addr: "127.0.0.1".to_string(),
+            target_port,
+        };
+        let cancel = CancellationToken::new();
+        let handle = tokio::spawn(run(visitor, cancel.clone()));
+
+        let mut stream = loop {
+            match TcpStream::connect(("127.0.0.1", bind_port)).await {
+                Ok(s) => break s,
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        };
+        stream.write_all(b"hello").await.unwrap();
+        let mut buf = [0u8; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+
+        cancel.cancel();
+        handle.await.unwrap();
+    }
+}
@@ -4,6 +4,8 @@ mod client;
 mod windows_service;
 
 use clap::{Parser, Subcommand};
+use common::grpc::tuning::GrpcTuning;
+use common::source_binding::SourceArgs;
 use std::fs;
 
 #[cfg(unix)]
@@ -23,20 +25,30 @@ enum Command {
     /// 前台运行客户端
     Start {
         /// Controller 地址（例如 http://controller:3100）
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_CONTROLLER_URL")]
         controller_url: String,
 
         /// 客户端 Token
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_TOKEN", hide_env_values = true)]
         token: String,
 
         /// 自定义 CA 证书文件路径（PEM 格式，用于验证 Controller 的 TLS 证书）
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_TLS_CA_CERT")]
         tls_ca_cert: Option<String>,
 
         /// 日志目录路径（按天自动分割，不指定则输出到控制台）
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_LOG_DIR")]
         log_dir: Option<String>,
+
+        /// 健康检查 HTTP 端口（提供 /healthz 和 /readyz，不指定则不启动）
+        #[arg(long, env = "OXIPROXY_HEALTH_PORT")]
+        health_port: Option<u16>,
+
+        #[command(flatten)]
+        grpc: GrpcTuning,
+
+        #[command(flatten)]
+        source: SourceArgs,
     },
 
     /// 停止运行中的守护进程
@@ -55,17 +67,27 @@ enum Command {
     /// 以守护进程模式运行
     Daemon {
         /// Controller 地址（例如 http://controller:3100）
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_CONTROLLER_URL")]
         controller_url: String,
 
         /// 客户端 Token
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_TOKEN", hide_env_values = true)]
         token: String,
 
         /// 自定义 CA 证书文件路径（PEM 格式，用于验证 Controller 的 TLS 证书）
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_TLS_CA_CERT")]
         tls_ca_cert: Option<String>,
 
+        /// 健康检查 HTTP 端口（提供 /healthz 和 /readyz，不指定则不启动）
+        #[arg(long, env = "OXIPROXY_HEALTH_PORT")]
+        health_port: Option<u16>,
+
+        #[command(flatten)]
+        grpc: GrpcTuning,
+
+        #[command(flatten)]
+        source: SourceArgs,
+
         /// PID 文件路径
         #[cfg(unix)]
         #[arg(long, default_value = "/var/run/oxiproxy-client.pid")]
@@ -73,7 +95,7 @@ enum Command {
 
         /// 日志目录路径（按天自动分割）
         #[cfg(unix)]
-        #[arg(long, default_value = "./logs")]
+        #[arg(long, env = "OXIPROXY_LOG_DIR", default_value = "./logs")]
         log_dir: String,
 
         /// PID 文件路径
@@ -83,7 +105,7 @@ enum Command {
 
         /// 日志目录路径（按天自动分割）
         #[cfg(windows)]
-        #[arg(long, default_value = "./logs")]
+        #[arg(long, env = "OXIPROXY_LOG_DIR", default_value = "./logs")]
         log_dir: String,
     },
 
@@ -91,15 +113,15 @@ enum Command {
     #[cfg(windows)]
     InstallService {
         /// Controller 地址（例如 http://controller:3100）
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_CONTROLLER_URL")]
         controller_url: String,
 
         /// 客户端 Token
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_TOKEN", hide_env_values = true)]
         token: String,
 
         /// 自定义 CA 证书文件路径（PEM 格式，用于验证 Controller 的 TLS 证书）
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_TLS_CA_CERT")]
         tls_ca_cert: Option<String>,
     },
 
@@ -112,16 +134,127 @@ enum Command {
     #[command(hide = true)]
     Service {
         /// Controller 地址
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_CONTROLLER_URL")]
         controller_url: Option<String>,
 
         /// 客户端 Token
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_TOKEN", hide_env_values = true)]
         token: Option<String>,
     },
 
     /// 更新到最新版本
     Update,
+
+    /// 检查运行中客户端的就绪状态（请求健康检查服务的 /readyz），未连接 Controller 时以非零状态退出，可用作 Docker HEALTHCHECK
+    Health {
+        /// 健康检查 HTTP 端口（与运行中客户端的 --health-port 相同）
+        #[arg(long, env = "OXIPROXY_HEALTH_PORT")]
+        health_port: u16,
+
+        /// 健康检查服务地址
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+    },
+
+    /// 收集诊断信息并打包（日志、配置、连通性测试、DNS 和 NAT 情况），敏感信息已脱敏
+    Diagnose {
+        /// Controller 地址（例如 http://controller:3100）
+        #[arg(long, env = "OXIPROXY_CONTROLLER_URL")]
+        controller_url: String,
+
+        /// 客户端 Token
+        #[arg(long, env = "OXIPROXY_TOKEN", hide_env_values = true)]
+        token: String,
+
+        /// 自定义 CA 证书文件路径（PEM 格式，用于验证 Controller 的 TLS 证书）
+        #[arg(long, env = "OXIPROXY_TLS_CA_CERT")]
+        tls_ca_cert: Option<String>,
+
+        /// 客户端日志目录（收集其中最近的日志）
+        #[arg(long, env = "OXIPROXY_LOG_DIR")]
+        log_dir: Option<String>,
+
+        /// 诊断包输出路径（默认 oxiproxy-diagnose-<时间>.tar.gz）
+        #[arg(long, short)]
+        output: Option<String>,
+
+        #[command(flatten)]
+        grpc: GrpcTuning,
+
+        #[command(flatten)]
+        source: SourceArgs,
+    },
+
+    /// 检查配置（Controller 地址、token、CA 证书等），有问题时以非零状态退出，可用于 CI 或 systemd ExecStartPre
+    Check {
+        /// Controller 地址（例如 http://controller:3100）
+        #[arg(long, env = "OXIPROXY_CONTROLLER_URL")]
+        controller_url: String,
+
+        /// 客户端 Token
+        #[arg(long, env = "OXIPROXY_TOKEN", hide_env_values = true)]
+        token: String,
+
+        /// 自定义 CA 证书文件路径（PEM 格式，用于验证 Controller 的 TLS 证书）
+        #[arg(long, env = "OXIPROXY_TLS_CA_CERT")]
+        tls_ca_cert: Option<String>,
+
+        /// 健康检查 HTTP 端口
+        #[arg(long, env = "OXIPROXY_HEALTH_PORT")]
+        health_port: Option<u16>,
+    },
+}
+
+/// 执行诊断并输出诊断包路径
+fn run_diagnose(
+    controller_url: String,
+    token: String,
+    tls_ca_cert: Option<String>,
+    log_dir: Option<String>,
+    output: Option<String>,
+) -> anyhow::Result<()> {
+    let opts = client::diagnose::DiagnoseOptions {
+        controller_url,
+        token,
+        tls_ca_cert: load_tls_ca_cert(&tls_ca_cert)?,
+        tls_ca_cert_path: tls_ca_cert,
+        log_dir,
+        output,
+    };
+    let runtime = tokio::runtime::Runtime::new()?;
+    let path = runtime.block_on(client::diagnose::run_diagnose(opts))?;
+    println!("诊断包已生成: {}", path.display());
+    Ok(())
+}
+
+/// 启动前的配置自检，有失败项时返回错误（进程以非零状态退出）
+fn run_config_check(
+    controller_url: &str,
+    token: &str,
+    tls_ca_cert: Option<&str>,
+    health_port: Option<u16>,
+) -> anyhow::Result<()> {
+    use common::check::{self, Report, Transport};
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(async {
+        let mut report = Report::default();
+        check::check_token(&mut report, token);
+        check::check_controller_url(&mut report, controller_url).await;
+        check::check_ca_cert(&mut report, tls_ca_cert, controller_url);
+        if let Some(port) = health_port {
+            check::check_port(&mut report, "健康检查端口", port, Transport::Tcp);
+        }
+        report.finish()
+    })
+}
+
+/// 请求健康检查服务，未就绪时返回错误（进程以非零状态退出）
+fn run_health_check(host: &str, port: u16) -> anyhow::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let body = runtime.block_on(common::health::check_ready(host, port))?;
+    println!("{}", body);
+    Ok(())
 }
 
 /// 加载 CA 证书文件内容
@@ -146,6 +279,8 @@ fn main() -> anyhow::Result<()> {
         .install_default()
         .expect("Failed to install rustls crypto provider");
 
+    // 展开 OXIPROXY_*_FILE 环境变量（Kubernetes Secret 挂载），需在解析参数前执行
+    common::env::expand_file_vars();
     let cli = Cli::parse();
 
     match cli.command {
@@ -154,13 +289,18 @@ fn main() -> anyhow::Result<()> {
             token,
             tls_ca_cert,
             log_dir,
+            health_port,
+            grpc,
+            source,
         } => {
+            common::grpc::tuning::set(grpc);
+            source.apply();
             let ca_cert = load_tls_ca_cert(&tls_ca_cert)?;
             if let Some(ref dir) = log_dir {
                 fs::create_dir_all(dir).expect("无法创建日志目录");
             }
             let runtime = tokio::runtime::Runtime::new()?;
-            runtime.block_on(client::run_client(controller_url, token, ca_cert, log_dir))?;
+            runtime.block_on(client::run_client(controller_url, token, ca_cert, log_dir, health_port))?;
         }
 
         Command::Stop { pid_file } => {
@@ -171,9 +311,14 @@ fn main() -> anyhow::Result<()> {
             controller_url,
             token,
             tls_ca_cert,
+            health_port,
+            grpc,
+            source,
             pid_file,
             log_dir,
         } => {
+            common::grpc::tuning::set(grpc);
+            source.apply();
             // 确保日志目录存在
             fs::create_dir_all(&log_dir).expect("无法创建日志目录");
 
@@ -204,12 +349,39 @@ fn main() -> anyhow::Result<()> {
             // fork 完成后再创建 tokio runtime，确保 epoll fd 和线程池状态正确
             let ca_cert = load_tls_ca_cert(&tls_ca_cert)?;
             let runtime = tokio::runtime::Runtime::new()?;
-            runtime.block_on(client::run_client(controller_url, token, ca_cert, Some(log_dir)))?;
+            runtime.block_on(client::run_client(controller_url, token, ca_cert, Some(log_dir), health_port))?;
         }
 
         Command::Update => {
             update_binary()?;
         }
+
+        Command::Diagnose {
+            controller_url,
+            token,
+            tls_ca_cert,
+            log_dir,
+            output,
+            grpc,
+            source,
+        } => {
+            common::grpc::tuning::set(grpc);
+            source.apply();
+            run_diagnose(controller_url, token, tls_ca_cert, log_dir, output)?;
+        }
+
+        Command::Health { health_port, host } => {
+            run_health_check(&host, health_port)?;
+        }
+
+        Command::Check {
+            controller_url,
+            token,
+            tls_ca_cert,
+            health_port,
+        } => {
+            run_config_check(&controller_url, &token, tls_ca_cert.as_deref(), health_port)?;
+        }
     }
 
     Ok(())
@@ -249,6 +421,8 @@ fn main() -> anyhow::Result<()> {
         .install_default()
         .expect("Failed to install rustls crypto provider");
 
+    // 展开 OXIPROXY_*_FILE 环境变量（Kubernetes Secret 挂载），需在解析参数前执行
+    common::env::expand_file_vars();
     let cli = Cli::parse();
 
     match cli.command {
@@ -257,13 +431,18 @@ fn main() -> anyhow::Result<()> {
             token,
             tls_ca_cert,
             log_dir,
+            health_port,
+            grpc,
+            source,
         } => {
+            common::grpc::tuning::set(grpc);
+            source.apply();
             let ca_cert = load_tls_ca_cert(&tls_ca_cert)?;
             if let Some(ref dir) = log_dir {
                 fs::create_dir_all(dir).expect("无法创建日志目录");
             }
             let runtime = tokio::runtime::Runtime::new()?;
-            runtime.block_on(async { client::run_client(controller_url, token, ca_cert, log_dir).await })
+            runtime.block_on(async { client::run_client(controller_url, token, ca_cert, log_dir, health_port).await })
         }
 
         Command::Stop { pid_file } => stop_daemon_windows(&pid_file),
@@ -272,9 +451,12 @@ fn main() -> anyhow::Result<()> {
             controller_url,
             token,
             tls_ca_cert,
+            health_port,
+            grpc,
+            source,
             pid_file,
             log_dir,
-        } => start_daemon_windows(&controller_url, &token, &tls_ca_cert, &pid_file, &log_dir),
+        } => start_daemon_windows(&controller_url, &token, &tls_ca_cert, health_port, &grpc, &source, &pid_file, &log_dir),
 
         Command::InstallService {
             controller_url,
@@ -287,6 +469,29 @@ fn main() -> anyhow::Result<()> {
         Command::Service { .. } => windows_service::run_service(),
 
         Command::Update => update_binary(),
+
+        Command::Diagnose {
+            controller_url,
+            token,
+            tls_ca_cert,
+            log_dir,
+            output,
+            grpc,
+            source,
+        } => {
+            common::grpc::tuning::set(grpc);
+            source.apply();
+            run_diagnose(controller_url, token, tls_ca_cert, log_dir, output)
+        }
+
+        Command::Health { health_port, host } => run_health_check(&host, health_port),
+
+        Command::Check {
+            controller_url,
+            token,
+            tls_ca_cert,
+            health_port,
+        } => run_config_check(&controller_url, &token, tls_ca_cert.as_deref(), health_port),
     }
 }
 
@@ -295,6 +500,9 @@ fn start_daemon_windows(
     controller_url: &str,
     token: &str,
     tls_ca_cert: &Option<String>,
+    health_port: Option<u16>,
+    grpc: &GrpcTuning,
+    source: &SourceArgs,
     pid_file: &str,
     log_dir: &str,
 ) -> anyhow::Result<()> {
@@ -328,6 +536,13 @@ fn start_daemon_windows(
         args.push(ca_path.to_string());
     }
 
+    if let Some(port) = health_port {
+        args.push("--health-port".to_string());
+        args.push(port.to_string());
+    }
+    args.extend(grpc.to_args());
+    args.extend(source.to_args());
+
     let child = std::process::Command::new(&exe)
         .args(&args)
         .stdout(stdout)
@@ -396,6 +611,7 @@ fn update_binary() -> anyhow::Result<()> {
         .bin_name("client")
         .identifier("client")
         .bin_path_in_archive("{bin}{bin_ext}")
+        .verifying_keys(common::update::verifying_keys()?)
         .show_download_progress(true)
         .current_version(env!("CARGO_PKG_VERSION"))
         .no_confirm(true)
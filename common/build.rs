@@ -1,4 +1,10 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("proto/oxiproxy.proto")?;
+    // 同时生成文件描述符集，供 Controller 的 gRPC 服务反射使用
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR")?);
+    tonic_build::configure()
+        .file_descriptor_set_path(out_dir.join("oxiproxy_descriptor.bin"))
+        .compile_protos(&["proto/oxiproxy.proto"], &["proto"])?;
+    // 更新签名公钥在编译时内置，变更后需要重新编译
+    println!("cargo:rerun-if-env-changed=OXIPROXY_UPDATE_PUBLIC_KEYS");
     Ok(())
 }
@@ -0,0 +1,197 @@
+//! 配置自检
+//!
+//! `controller check`、`node check` 和 `client check` 共用的检查项与结果输出：每项检查输出一行
+//! ✓ / ⚠ / ✗，失败项附带修复建议。有失败项时 [`Report::finish`] 返回错误，进程以非零状态退出，
+//! 因此可以放在 CI 或 systemd 的 `ExecStartPre` 中，在服务启动前发现配置问题。
+
+use std::fmt::Display;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+
+/// DNS 解析等网络检查的超时时间
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// token 建议的最短长度
+const MIN_TOKEN_LEN: usize = 16;
+
+/// 检查结果汇总
+#[derive(Default)]
+pub struct Report {
+    warnings: usize,
+    failures: usize,
+}
+
+impl Report {
+    pub fn ok(&mut self, item: &str, detail: impl Display) {
+        println!("✓ {}: {}", item, detail);
+    }
+
+    pub fn warn(&mut self, item: &str, detail: impl Display) {
+        self.warnings += 1;
+        println!("⚠ {}: {}", item, detail);
+    }
+
+    /// 记录失败项，`hint` 为修复建议
+    pub fn fail(&mut self, item: &str, error: impl Display, hint: impl Display) {
+        self.failures += 1;
+        println!("✗ {}: {}", item, error);
+        println!("    → {}", hint);
+    }
+
+    /// 输出汇总，有失败项时返回错误
+    pub fn finish(self) -> Result<()> {
+        println!();
+        if self.failures > 0 {
+            bail!("配置检查未通过：{} 项失败，{} 项警告", self.failures, self.warnings);
+        }
+        println!("配置检查通过（{} 项警告）", self.warnings);
+        Ok(())
+    }
+}
+
+/// 监听端口的协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Tcp,
+    Udp,
+}
+
+/// 无权监听 1024 以下端口时的修复建议
+pub const LOW_PORT_HINT: &str =
+    "监听 1024 以下的端口需要 root 权限或 CAP_NET_BIND_SERVICE 能力（也可以 root 启动并用 --user 降权）";
+
+/// 检查端口能否监听（与服务实际使用相同的通配地址），需在 tokio runtime 中调用
+pub fn check_port(report: &mut Report, item: &str, port: u16, transport: Transport) {
+    let addr = crate::utils::wildcard_addr(port);
+    let (result, proto) = match transport {
+        Transport::Tcp => (crate::utils::bind_tcp_listener(addr).map(drop), "tcp"),
+        Transport::Udp => (crate::utils::bind_std_udp_socket(addr).map(drop), "udp"),
+    };
+    let Err(e) = result else {
+        report.ok(item, format!("{}/{} 可以监听", port, proto));
+        return;
+    };
+    let hint = match e.downcast_ref::<std::io::Error>().map(|e| e.kind()) {
+        Some(std::io::ErrorKind::AddrInUse) => format!(
+            "端口已被占用（服务是否已在运行？），可用 `ss -lnp | grep :{}` 查看占用的进程，或换一个端口",
+            port
+        ),
+        Some(std::io::ErrorKind::PermissionDenied) => LOW_PORT_HINT.to_string(),
+        _ => "检查端口配置和系统网络设置".to_string(),
+    };
+    report.fail(item, format!("无法监听 {}/{}: {}", addr, proto, e), hint);
+}
+
+/// token 的格式问题，格式正确时返回 None
+pub fn token_problem(token: &str) -> Option<&'static str> {
+    if token.is_empty() {
+        return Some("token 为空");
+    }
+    if token.trim() != token {
+        return Some("token 首尾包含空白字符（常见于复制时带上的换行）");
+    }
+    if !token.bytes().all(|b| b.is_ascii_graphic()) {
+        return Some("token 只能包含可见的 ASCII 字符");
+    }
+    None
+}
+
+/// 检查 token 格式
+pub fn check_token(report: &mut Report, token: &str) {
+    match token_problem(token) {
+        Some(problem) => report.fail(
+            "Token",
+            problem,
+            "从管理界面重新复制 token，并检查 --token / OXIPROXY_TOKEN / OXIPROXY_TOKEN_FILE",
+        ),
+        None if token.len() < MIN_TOKEN_LEN => {
+            report.warn("Token", format!("长度只有 {} 个字符，容易被猜到，建议使用自动生成的 token", token.len()))
+        }
+        None => report.ok("Token", format!("格式正确（{} 个字符）", token.len())),
+    }
+}
+
+/// 检查 Controller 地址的格式并解析主机名
+pub async fn check_controller_url(report: &mut Report, url: &str) {
+    const ITEM: &str = "Controller 地址";
+    const HINT: &str = "格式为 http://host:port 或 https://host:port，例如 https://controller.example.com:3100";
+
+    let uri: tonic::transport::Uri = match url.parse() {
+        Ok(uri) => uri,
+        Err(e) => return report.fail(ITEM, format!("{} 不是有效的地址: {}", url, e), HINT),
+    };
+    let https = match uri.scheme_str() {
+        Some("https") => true,
+        Some("http") => false,
+        _ => return report.fail(ITEM, format!("{} 缺少 http:// 或 https:// 前缀", url), HINT),
+    };
+    let Some(host) = uri.host().map(crate::utils::normalize_host) else {
+        return report.fail(ITEM, format!("{} 中没有主机名", url), HINT);
+    };
+    let port = uri.port_u16().unwrap_or(if https { 443 } else { 80 });
+
+    match tokio::time::timeout(CHECK_TIMEOUT, tokio::net::lookup_host((host, port))).await {
+        Ok(Ok(addrs)) => {
+            let addrs: Vec<String> = addrs.map(|a: SocketAddr| a.ip().to_string()).collect();
+            report.ok(ITEM, format!("{} → {}", url, addrs.join(", ")));
+        }
+        Ok(Err(e)) => {
+            return report.fail(ITEM, format!("无法解析 {}: {}", host, e), "检查主机名拼写和本机 DNS 配置")
+        }
+        Err(_) => {
+            return report.fail(
+                ITEM,
+                format!("解析 {} 超时（{} 秒）", host, CHECK_TIMEOUT.as_secs()),
+                "检查本机 DNS 服务器是否可达",
+            )
+        }
+    }
+
+    let loopback = host.eq_ignore_ascii_case("localhost") || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback());
+    if !https && !loopback {
+        report.warn(ITEM, "使用 http:// 连接，token 将以明文传输，建议为 Controller 启用 gRPC TLS 并改用 https://");
+    }
+}
+
+/// 检查自定义 CA 证书能否解析
+pub fn check_ca_cert(report: &mut Report, path: Option<&str>, controller_url: &str) {
+    const ITEM: &str = "CA 证书";
+    const HINT: &str = "CA 证书需为 PEM 格式（-----BEGIN CERTIFICATE-----），DER 格式可用 `openssl x509 -inform der -in ca.der -out ca.pem` 转换";
+
+    let Some(path) = path else {
+        return;
+    };
+    let pem = match std::fs::read(path) {
+        Ok(pem) => pem,
+        Err(e) => return report.fail(ITEM, format!("读取 {} 失败: {}", path, e), "检查 --tls-ca-cert 的路径和文件读取权限"),
+    };
+    match CertificateDer::pem_slice_iter(&pem).collect::<Result<Vec<_>, _>>() {
+        Ok(certs) if certs.is_empty() => report.fail(ITEM, format!("{} 中没有证书", path), HINT),
+        Ok(certs) => {
+            report.ok(ITEM, format!("{}（{} 个证书）", path, certs.len()));
+            if !controller_url.starts_with("https://") {
+                report.warn(ITEM, "Controller 地址不是 https://，CA 证书不会被使用");
+            }
+        }
+        Err(e) => report.fail(ITEM, format!("解析 {} 失败: {:?}", path, e), HINT),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_problem() {
+        assert!(token_problem("6f1c2a9e-3b7d-4f0e-9a51-2c8d7e4b1f30").is_none());
+        assert!(token_problem("").is_some());
+        assert!(token_problem("abc\n").is_some());
+        assert!(token_problem(" abc").is_some());
+        assert!(token_problem("ab c").is_some());
+        assert!(token_problem("令牌").is_some());
+    }
+}
@@ -1,6 +1,6 @@
-//! KCP 配置定义
+//! 隧道传输配置定义
 //!
-//! 此模块包含 KCP 协议的配置参数，用于客户端和服务端。
+//! 此模块包含 KCP 和 QUIC 协议的配置参数，用于客户端和服务端。
 
 use serde::{Deserialize, Serialize};
 
@@ -8,7 +8,7 @@ use serde::{Deserialize, Serialize};
 ///
 /// 用于配置 KCP 隧道的各项参数，包括延迟模式、发送间隔、
 /// 快速重传和拥塞控制等。
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct KcpConfig {
     /// 是否启用 nodelay 模式
     /// - true: 低延迟模式，适合实时性要求高的场景
@@ -32,6 +32,23 @@ pub struct KcpConfig {
     /// - false: 启用拥塞控制，更稳定但延迟更高
     #[serde(default = "default_true")]
     pub nc: bool,
+
+    /// 发送窗口大小（包数）
+    /// 丢包较多的链路（如移动网络）适当调大可以提高吞吐量
+    /// 默认值: 256
+    #[serde(default = "default_wnd")]
+    pub snd_wnd: u16,
+
+    /// 接收窗口大小（包数），应不小于对端的发送窗口
+    /// 默认值: 256
+    #[serde(default = "default_wnd")]
+    pub rcv_wnd: u16,
+
+    /// 最大传输单元（字节），包含 KCP 头部
+    /// 链路存在额外封装（PPPoE、VPN）时应调小以避免 IP 分片
+    /// 默认值: 1400，范围 576-1500
+    #[serde(default = "default_mtu")]
+    pub mtu: u32,
 }
 
 fn default_true() -> bool {
@@ -46,6 +63,17 @@ fn default_resend() -> u32 {
     2
 }
 
+fn default_wnd() -> u16 {
+    256
+}
+
+fn default_mtu() -> u32 {
+    1400
+}
+
+/// MTU 允许范围
+pub const KCP_MTU_RANGE: std::ops::RangeInclusive<u32> = 576..=1500;
+
 impl Default for KcpConfig {
     fn default() -> Self {
         Self {
@@ -53,6 +81,225 @@ impl Default for KcpConfig {
             interval: 10,
             resend: 2,
             nc: true,
+            snd_wnd: default_wnd(),
+            rcv_wnd: default_wnd(),
+            mtu: default_mtu(),
+        }
+    }
+}
+
+impl KcpConfig {
+    /// 校验参数是否在合理范围内
+    pub fn validate(&self) -> Result<(), String> {
+        if !KCP_MTU_RANGE.contains(&self.mtu) {
+            return Err(format!(
+                "KCP MTU 必须在 {}-{} 之间",
+                KCP_MTU_RANGE.start(),
+                KCP_MTU_RANGE.end()
+            ));
+        }
+        if self.snd_wnd == 0 || self.rcv_wnd == 0 {
+            return Err("KCP 窗口大小必须大于 0".to_string());
+        }
+        if self.interval == 0 || self.interval > 5000 {
+            return Err("KCP 刷新间隔必须在 1-5000 毫秒之间".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// QUIC 拥塞控制算法
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CongestionController {
+    /// CUBIC（quinn 默认）
+    #[default]
+    Cubic,
+    /// BBR，适合高带宽、存在随机丢包的链路
+    Bbr,
+    /// NewReno
+    NewReno,
+}
+
+impl CongestionController {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CongestionController::Cubic => "cubic",
+            CongestionController::Bbr => "bbr",
+            CongestionController::NewReno => "newreno",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "cubic" => Some(CongestionController::Cubic),
+            "bbr" => Some(CongestionController::Bbr),
+            "newreno" => Some(CongestionController::NewReno),
+            _ => None,
         }
     }
 }
+
+/// QUIC 传输参数
+///
+/// 窗口类参数未设置时使用 quinn 的默认值。
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct QuicConfig {
+    /// 拥塞控制算法
+    #[serde(default)]
+    pub congestion_controller: CongestionController,
+
+    /// 初始拥塞窗口（字节）
+    #[serde(default)]
+    pub initial_window: Option<u64>,
+
+    /// 最大发送窗口（字节），限制未确认数据总量，即拥塞窗口的上限
+    #[serde(default)]
+    pub send_window: Option<u64>,
+
+    /// 单个流的接收窗口（字节）
+    #[serde(default)]
+    pub stream_receive_window: Option<u32>,
+
+    /// 整个连接的接收窗口（字节）
+    #[serde(default)]
+    pub receive_window: Option<u32>,
+
+    /// 最大空闲超时（秒）
+    /// 默认值: 60
+    #[serde(default = "default_idle_timeout")]
+    pub idle_timeout: u64,
+
+    /// 心跳间隔（秒）
+    /// 默认值: 5
+    #[serde(default = "default_keep_alive_interval")]
+    pub keep_alive_interval: u64,
+
+    /// 最大并发流数
+    /// 默认值: 100
+    #[serde(default = "default_max_streams")]
+    pub max_concurrent_streams: u32,
+
+    /// 是否启用路径 MTU 探测（PMTUD），探测到的包过大时 quinn 会自动回退到 1200
+    /// 默认值: true
+    #[serde(default = "default_mtu_discovery")]
+    pub mtu_discovery: bool,
+
+    /// 探测前使用的 UDP 载荷上限（字节），不小于 1200；未设置时为 1200
+    #[serde(default)]
+    pub initial_mtu: Option<u16>,
+
+    /// MTU 探测的上限（字节）；未设置时为 quinn 默认的 1452
+    #[serde(default)]
+    pub max_mtu: Option<u16>,
+}
+
+fn default_idle_timeout() -> u64 {
+    60
+}
+
+fn default_keep_alive_interval() -> u64 {
+    5
+}
+
+fn default_max_streams() -> u32 {
+    100
+}
+
+fn default_mtu_discovery() -> bool {
+    true
+}
+
+/// QUIC UDP 载荷大小允许范围（QUIC 要求至少 1200）
+pub const QUIC_MTU_RANGE: std::ops::RangeInclusive<u16> = 1200..=65527;
+
+impl Default for QuicConfig {
+    fn default() -> Self {
+        Self {
+            congestion_controller: CongestionController::default(),
+            initial_window: None,
+            send_window: None,
+            stream_receive_window: None,
+            receive_window: None,
+            idle_timeout: default_idle_timeout(),
+            keep_alive_interval: default_keep_alive_interval(),
+            max_concurrent_streams: default_max_streams(),
+            mtu_discovery: default_mtu_discovery(),
+            initial_mtu: None,
+            max_mtu: None,
+        }
+    }
+}
+
+impl QuicConfig {
+    /// 校验参数是否在合理范围内
+    pub fn validate(&self) -> Result<(), String> {
+        if self.idle_timeout == 0 {
+            return Err("QUIC 空闲超时必须大于 0".to_string());
+        }
+        if self.keep_alive_interval == 0 || self.keep_alive_interval >= self.idle_timeout {
+            return Err("QUIC 心跳间隔必须大于 0 且小于空闲超时".to_string());
+        }
+        if self.max_concurrent_streams == 0 {
+            return Err("QUIC 最大并发流数必须大于 0".to_string());
+        }
+        if let (Some(initial), Some(send)) = (self.initial_window, self.send_window) {
+            if initial > send {
+                return Err("QUIC 初始拥塞窗口不能大于最大发送窗口".to_string());
+            }
+        }
+        for mtu in [self.initial_mtu, self.max_mtu].into_iter().flatten() {
+            if !QUIC_MTU_RANGE.contains(&mtu) {
+                return Err(format!(
+                    "QUIC MTU 必须在 {}-{} 之间",
+                    QUIC_MTU_RANGE.start(),
+                    QUIC_MTU_RANGE.end()
+                ));
+            }
+        }
+        if let (Some(initial), Some(max)) = (self.initial_mtu, self.max_mtu) {
+            if initial > max {
+                return Err("QUIC 初始 MTU 不能大于 MTU 探测上限".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let config: KcpConfig = serde_json::from_str(r#"{"nodelay":false,"interval":20}"#).unwrap();
+        assert!(!config.nodelay);
+        assert_eq!(config.interval, 20);
+        assert_eq!(config.snd_wnd, 256);
+        assert_eq!(config.mtu, 1400);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_quic_config_defaults() {
+        let config: QuicConfig = serde_json::from_str(r#"{"congestion_controller":"bbr"}"#).unwrap();
+        assert_eq!(config.congestion_controller, CongestionController::Bbr);
+        assert_eq!(config.idle_timeout, 60);
+        assert!(config.validate().is_ok());
+
+        let invalid = QuicConfig { keep_alive_interval: 60, ..Default::default() };
+        assert!(invalid.validate().is_err());
+
+        assert!(config.mtu_discovery);
+        let invalid = QuicConfig { initial_mtu: Some(1000), ..Default::default() };
+        assert!(invalid.validate().is_err());
+        let invalid = QuicConfig { initial_mtu: Some(1400), max_mtu: Some(1300), ..Default::default() };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_mtu() {
+        let config = KcpConfig { mtu: 9000, ..Default::default() };
+        assert!(config.validate().is_err());
+    }
+}
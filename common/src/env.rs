@@ -0,0 +1,98 @@
+//! 环境变量配置工具
+//!
+//! 所有组件统一使用 `OXIPROXY_` 前缀的环境变量覆盖配置。
+//! 任意变量都可以通过 `<NAME>_FILE` 指向一个文件，从文件内容读取取值，
+//! 便于挂载 Kubernetes Secret / Docker Secret。
+
+use std::fs;
+
+/// 环境变量统一前缀
+pub const ENV_PREFIX: &str = "OXIPROXY_";
+
+/// 文件引用后缀
+const FILE_SUFFIX: &str = "_FILE";
+
+/// 读取环境变量
+///
+/// 优先读取 `name`，未设置（或为空）时读取 `<name>_FILE` 指向的文件内容（去除首尾空白）。
+pub fn var(name: &str) -> Option<String> {
+    if let Ok(value) = std::env::var(name) {
+        if !value.is_empty() {
+            return Some(value);
+        }
+    }
+
+    let path = std::env::var(format!("{}{}", name, FILE_SUFFIX)).ok()?;
+    match fs::read_to_string(&path) {
+        Ok(content) => Some(content.trim().to_string()),
+        Err(e) => {
+            tracing::warn!("读取 {}{} 指向的文件 {} 失败: {}", name, FILE_SUFFIX, path, e);
+            None
+        }
+    }
+}
+
+/// 读取并解析环境变量，解析失败时记录警告并返回 None
+pub fn parse<T: std::str::FromStr>(name: &str) -> Option<T> {
+    let value = var(name)?;
+    match value.parse::<T>() {
+        Ok(parsed) => Some(parsed),
+        Err(_) => {
+            tracing::warn!("环境变量 {} 的值无效: {}", name, value);
+            None
+        }
+    }
+}
+
+/// 系统配置键对应的环境变量名，例如 `grpc_tls_enabled` → `OXIPROXY_GRPC_TLS_ENABLED`
+pub fn key_to_var(key: &str) -> String {
+    format!("{}{}", ENV_PREFIX, key.to_ascii_uppercase())
+}
+
+/// 将所有 `OXIPROXY_*_FILE` 变量展开为对应的 `OXIPROXY_*` 变量
+///
+/// 已显式设置的变量优先。供使用 clap `env` 属性解析参数的程序在解析前调用，
+/// 必须在创建任何线程（包括 tokio runtime）之前调用。
+pub fn expand_file_vars() {
+    let file_vars: Vec<(String, String)> = std::env::vars()
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX) && name.ends_with(FILE_SUFFIX))
+        .collect();
+
+    for (file_var, path) in file_vars {
+        let name = &file_var[..file_var.len() - FILE_SUFFIX.len()];
+        if std::env::var(name).map(|v| !v.is_empty()).unwrap_or(false) {
+            continue;
+        }
+        match fs::read_to_string(&path) {
+            Ok(content) => std::env::set_var(name, content.trim()),
+            Err(e) => eprintln!("读取 {} 指向的文件 {} 失败: {}", file_var, path, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_to_var() {
+        assert_eq!(key_to_var("grpc_tls_enabled"), "OXIPROXY_GRPC_TLS_ENABLED");
+        assert_eq!(key_to_var("web_port"), "OXIPROXY_WEB_PORT");
+    }
+
+    #[test]
+    fn test_var_reads_file_fallback() {
+        let path = std::env::temp_dir().join("oxiproxy_env_test_secret");
+        fs::write(&path, "s3cret\n").unwrap();
+        std::env::set_var("OXIPROXY_TEST_SECRET_FILE", &path);
+
+        assert_eq!(var("OXIPROXY_TEST_SECRET"), Some("s3cret".to_string()));
+
+        std::env::set_var("OXIPROXY_TEST_SECRET", "direct");
+        assert_eq!(var("OXIPROXY_TEST_SECRET"), Some("direct".to_string()));
+
+        std::env::remove_var("OXIPROXY_TEST_SECRET");
+        std::env::remove_var("OXIPROXY_TEST_SECRET_FILE");
+        let _ = fs::remove_file(path);
+    }
+}
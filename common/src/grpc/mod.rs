@@ -1,13 +1,277 @@
 pub mod pending_requests;
+pub mod reconnect;
+pub mod tuning;
+
+/// 流量上报流中携带节点 token 的 metadata 键
+pub const NODE_TOKEN_METADATA: &str = "x-node-token";
+
+/// 流量上报 ID：由节点会话和会话内序号组成，同一批次重发时保持不变
+pub fn traffic_report_id(session: u64, seq: u64) -> String {
+    format!("{:016x}-{}", session, seq)
+}
 
 // 导出 proto 生成的代码
 pub mod oxiproxy {
     tonic::include_proto!("oxiproxy");
 }
 
+/// proto 文件描述符集（gRPC 服务反射使用）
+pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("oxiproxy_descriptor");
+
 // 重新导出常用类型
 pub use oxiproxy::*;
 pub use oxiproxy::agent_server_service_client::AgentServerServiceClient;
 pub use oxiproxy::agent_server_service_server::{AgentServerService, AgentServerServiceServer};
 pub use oxiproxy::agent_client_service_client::AgentClientServiceClient;
 pub use oxiproxy::agent_client_service_server::{AgentClientService, AgentClientServiceServer};
+
+impl From<&crate::config::KcpConfig> for GrpcKcpConfig {
+    fn from(k: &crate::config::KcpConfig) -> Self {
+        Self {
+            nodelay: k.nodelay,
+            interval: k.interval,
+            resend: k.resend,
+            nc: k.nc,
+            snd_wnd: Some(k.snd_wnd as u32),
+            rcv_wnd: Some(k.rcv_wnd as u32),
+            mtu: Some(k.mtu),
+        }
+    }
+}
+
+impl From<GrpcKcpConfig> for crate::config::KcpConfig {
+    /// 旧版本 Controller 不下发窗口和 MTU，缺省时使用默认值
+    fn from(k: GrpcKcpConfig) -> Self {
+        let defaults = Self::default();
+        Self {
+            nodelay: k.nodelay,
+            interval: k.interval,
+            resend: k.resend,
+            nc: k.nc,
+            snd_wnd: k.snd_wnd.map_or(defaults.snd_wnd, |w| w.min(u16::MAX as u32) as u16),
+            rcv_wnd: k.rcv_wnd.map_or(defaults.rcv_wnd, |w| w.min(u16::MAX as u32) as u16),
+            mtu: k.mtu.unwrap_or(defaults.mtu),
+        }
+    }
+}
+
+impl From<&crate::tunnel::KcpStats> for GrpcKcpStats {
+    fn from(s: &crate::tunnel::KcpStats) -> Self {
+        Self {
+            connections: s.connections,
+            streams_open: s.streams_open,
+            streams_opened: s.streams_opened,
+            bytes_sent: s.bytes_sent,
+            bytes_received: s.bytes_received,
+            send_stalls: s.send_stalls,
+            send_stall_ms: s.send_stall_ms,
+            retransmissions: s.retransmissions,
+            segments_sent: s.segments_sent,
+            rtt_connections: s.rtt_connections,
+            rtt_ms_sum: s.rtt_ms_sum,
+            inflight_segments: s.inflight_segments,
+            window_segments: s.window_segments,
+        }
+    }
+}
+
+impl From<GrpcKcpStats> for crate::tunnel::KcpStats {
+    fn from(s: GrpcKcpStats) -> Self {
+        Self {
+            connections: s.connections,
+            streams_open: s.streams_open,
+            streams_opened: s.streams_opened,
+            bytes_sent: s.bytes_sent,
+            bytes_received: s.bytes_received,
+            send_stalls: s.send_stalls,
+            send_stall_ms: s.send_stall_ms,
+            retransmissions: s.retransmissions,
+            segments_sent: s.segments_sent,
+            rtt_connections: s.rtt_connections,
+            rtt_ms_sum: s.rtt_ms_sum,
+            inflight_segments: s.inflight_segments,
+            window_segments: s.window_segments,
+        }
+    }
+}
+
+impl From<&crate::config::QuicConfig> for GrpcQuicConfig {
+    fn from(q: &crate::config::QuicConfig) -> Self {
+        Self {
+            congestion_controller: q.congestion_controller.as_str().to_string(),
+            initial_window: q.initial_window,
+            send_window: q.send_window,
+            stream_receive_window: q.stream_receive_window,
+            receive_window: q.receive_window,
+            idle_timeout: q.idle_timeout,
+            keep_alive_interval: q.keep_alive_interval,
+            max_concurrent_streams: q.max_concurrent_streams,
+            mtu_discovery: Some(q.mtu_discovery),
+            initial_mtu: q.initial_mtu.map(u32::from),
+            max_mtu: q.max_mtu.map(u32::from),
+        }
+    }
+}
+
+impl From<GrpcQuicConfig> for crate::config::QuicConfig {
+    /// 旧版本 Controller 不下发 MTU 参数，缺省时启用 MTU 探测并使用 quinn 默认值
+    fn from(q: GrpcQuicConfig) -> Self {
+        Self {
+            congestion_controller: crate::config::CongestionController::parse(&q.congestion_controller)
+                .unwrap_or_default(),
+            initial_window: q.initial_window,
+            send_window: q.send_window,
+            stream_receive_window: q.stream_receive_window,
+            receive_window: q.receive_window,
+            idle_timeout: q.idle_timeout,
+            keep_alive_interval: q.keep_alive_interval,
+            max_concurrent_streams: q.max_concurrent_streams,
+            mtu_discovery: q.mtu_discovery.unwrap_or(true),
+            initial_mtu: q.initial_mtu.map(|m| m.min(u16::MAX as u32) as u16),
+            max_mtu: q.max_mtu.map(|m| m.min(u16::MAX as u32) as u16),
+        }
+    }
+}
+
+impl From<&crate::mitigation::MitigationRule> for GrpcMitigationRule {
+    fn from(r: &crate::mitigation::MitigationRule) -> Self {
+        Self {
+            max_mbps: r.max_mbps,
+            max_connections: r.max_connections,
+            sustain_secs: r.sustain_secs,
+            action: r.action.as_str().to_string(),
+            throttle_mbps: r.throttle_mbps,
+            penalty_secs: r.penalty_secs,
+        }
+    }
+}
+
+impl TryFrom<GrpcMitigationRule> for crate::mitigation::MitigationRule {
+    type Error = anyhow::Error;
+
+    /// 新版本 Controller 可能下发节点不认识的处置方式，此时忽略该规则
+    fn try_from(r: GrpcMitigationRule) -> anyhow::Result<Self> {
+        let action = crate::mitigation::MitigationAction::parse(&r.action)
+            .ok_or_else(|| anyhow::anyhow!("未知的处置方式: {}", r.action))?;
+        let rule = Self {
+            max_mbps: r.max_mbps,
+            max_connections: r.max_connections,
+            sustain_secs: r.sustain_secs,
+            action,
+            throttle_mbps: r.throttle_mbps,
+            penalty_secs: r.penalty_secs,
+        };
+        rule.validate()?;
+        Ok(rule)
+    }
+}
+
+impl From<&crate::protocol::control::PortLease> for GrpcPortLease {
+    fn from(l: &crate::protocol::control::PortLease) -> Self {
+        Self {
+            client_id: l.client_id.clone(),
+            proxy_type: l.proxy_type.clone(),
+            remote_port: l.remote_port as u32,
+        }
+    }
+}
+
+impl From<GrpcPortLease> for crate::protocol::control::PortLease {
+    fn from(l: GrpcPortLease) -> Self {
+        Self {
+            node_id: None,
+            client_id: l.client_id,
+            proxy_type: l.proxy_type,
+            remote_port: l.remote_port.min(u16::MAX as u32) as u16,
+        }
+    }
+}
+
+impl From<&crate::protocol::node_register::NodeCapabilities> for GrpcNodeCapabilities {
+    fn from(c: &crate::protocol::node_register::NodeCapabilities) -> Self {
+        Self {
+            public_ips: c.public_ips.clone(),
+            tunnel_protocols: c.tunnel_protocols.clone(),
+            port_range_start: c.port_range_start.map(u32::from),
+            port_range_end: c.port_range_end.map(u32::from),
+            max_throughput: c.max_throughput,
+            privileged_ports: c.privileged_ports,
+        }
+    }
+}
+
+impl From<GrpcNodeCapabilities> for crate::protocol::node_register::NodeCapabilities {
+    fn from(c: GrpcNodeCapabilities) -> Self {
+        Self {
+            public_ips: c.public_ips,
+            tunnel_protocols: c.tunnel_protocols,
+            port_range_start: c.port_range_start.and_then(|p| u16::try_from(p).ok()),
+            port_range_end: c.port_range_end.and_then(|p| u16::try_from(p).ok()),
+            max_throughput: c.max_throughput,
+            privileged_ports: c.privileged_ports,
+        }
+    }
+}
+
+/// 版本偏差兼容性：新旧版本的 Agent 与 Controller 之间的消息须能互相解码，
+/// 新增字段缺省时取默认值，不认识的字段被忽略
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+
+    /// 早期节点的注册请求（只有前 4 个字段）
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct OldNodeRegisterRequest {
+        #[prost(string, tag = "1")]
+        token: String,
+        #[prost(uint32, tag = "2")]
+        tunnel_port: u32,
+        #[prost(string, tag = "3")]
+        tunnel_protocol: String,
+        #[prost(string, tag = "4")]
+        version: String,
+    }
+
+    /// 早期的心跳（只有时间戳）
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct OldHeartbeat {
+        #[prost(int64, tag = "1")]
+        timestamp: i64,
+    }
+
+    #[test]
+    fn test_old_agent_register_decodes() {
+        let old = OldNodeRegisterRequest {
+            token: "secret".to_string(),
+            tunnel_port: 7000,
+            tunnel_protocol: "quic".to_string(),
+            version: "0.9.0".to_string(),
+        };
+        let req = NodeRegisterRequest::decode(old.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(req.version, "0.9.0");
+        assert_eq!(req.tunnel_port, 7000);
+        assert!(req.capabilities.is_none());
+        assert!(req.session_token.is_none());
+    }
+
+    #[test]
+    fn test_new_fields_ignored_by_old_agent() {
+        let heartbeat = Heartbeat {
+            timestamp: 42,
+            proxy_speeds: vec![ProxySpeed { proxy_id: 1, client_id: 2, ..Default::default() }],
+            system_info: Some(AgentSystemInfo {
+                os: "linux".to_string(),
+                arch: "x86_64".to_string(),
+                version: "1.0.0".to_string(),
+                uptime_secs: 60,
+            }),
+        };
+        let old = OldHeartbeat::decode(heartbeat.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(old.timestamp, 42);
+
+        let back = Heartbeat::decode(old.encode_to_vec().as_slice()).unwrap();
+        assert!(back.system_info.is_none());
+        assert!(back.proxy_speeds.is_empty());
+    }
+}
@@ -0,0 +1,82 @@
+//! Agent 重连状态
+//!
+//! 节点和客户端进程各自只维护一条到 Controller 的连接，重连相关的状态保存在进程级全局变量中：
+//!
+//! - Controller 关闭前下发的重连延迟（`ReconnectHint`），断线后先等待该延迟再重连，
+//!   使 Controller 重启后各 Agent 错开重连
+//! - Controller 认证成功后下发的会话令牌，重连时带上，Controller 据此跳过重复工作
+//!
+//! 没有延迟提示时，重连间隔在基础间隔上增加随机抖动，避免所有 Agent 以相同节奏重试。
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+#[derive(Default)]
+struct State {
+    hint: Option<Duration>,
+    session_token: Option<String>,
+}
+
+fn state() -> &'static Mutex<State> {
+    static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+    STATE.get_or_init(|| Mutex::new(State::default()))
+}
+
+/// 记录 Controller 下发的重连延迟
+pub fn set_hint(delay: Duration) {
+    state().lock().unwrap().hint = Some(delay);
+}
+
+/// 下一次重连前的等待时间：有延迟提示时使用提示（只生效一次），否则为 `base` 加上 0 ~ `base` 的随机抖动
+pub fn next_delay(base: Duration) -> Duration {
+    if let Some(hint) = state().lock().unwrap().hint.take() {
+        return hint;
+    }
+    base + jitter(base)
+}
+
+/// 0 ~ `max` 之间的随机时长
+pub fn jitter(max: Duration) -> Duration {
+    let millis = max.as_millis() as u64;
+    if millis == 0 {
+        return Duration::ZERO;
+    }
+    // RandomState 每次创建使用不同的随机种子，足以用于错开重连
+    let random = RandomState::new().build_hasher().finish();
+    Duration::from_millis(random % (millis + 1))
+}
+
+/// 当前会话令牌
+pub fn session_token() -> Option<String> {
+    state().lock().unwrap().session_token.clone()
+}
+
+/// 保存 Controller 下发的会话令牌（`None` 时保留原令牌）
+pub fn set_session_token(token: Option<String>) {
+    if let Some(token) = token.filter(|t| !t.is_empty()) {
+        state().lock().unwrap().session_token = Some(token);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jitter_bounds() {
+        assert_eq!(jitter(Duration::ZERO), Duration::ZERO);
+        for _ in 0..100 {
+            assert!(jitter(Duration::from_secs(5)) <= Duration::from_secs(5));
+        }
+    }
+
+    #[test]
+    fn test_hint_used_once() {
+        set_hint(Duration::from_millis(1234));
+        assert_eq!(next_delay(Duration::from_secs(5)), Duration::from_millis(1234));
+        let delay = next_delay(Duration::from_secs(5));
+        assert!(delay >= Duration::from_secs(5) && delay <= Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,212 @@
+//! gRPC 传输参数
+//!
+//! 单条消息大小上限、消息压缩和 HTTP/2 keepalive 在进程级全局变量中设置一次：
+//! Controller 从配置加载，Node / Client 从命令行参数（或对应的环境变量）加载，
+//! 之后建立的 Channel、生成的 gRPC 客户端和服务端都按此配置。
+//!
+//! 压缩只影响本端发送的消息，两种压缩格式始终都可以接收，因此两端可以分别配置。
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tonic::codec::CompressionEncoding;
+use tonic::transport::{Channel, Endpoint, Server};
+
+use super::{
+    AgentClientService, AgentClientServiceClient, AgentClientServiceServer, AgentServerService,
+    AgentServerServiceClient, AgentServerServiceServer,
+};
+
+/// 默认单条消息大小上限（tonic 默认接收上限为 4 MiB，代理列表和日志较多时可能超出）
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+pub const DEFAULT_KEEPALIVE_INTERVAL_SECS: u64 = 30;
+pub const DEFAULT_KEEPALIVE_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    fn encoding(self) -> Option<CompressionEncoding> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some(CompressionEncoding::Gzip),
+            Compression::Zstd => Some(CompressionEncoding::Zstd),
+        }
+    }
+}
+
+impl std::str::FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "none" => Ok(Compression::None),
+            "gzip" => Ok(Compression::Gzip),
+            "zstd" => Ok(Compression::Zstd),
+            other => Err(format!("未知的压缩方式: {}（可选 none、gzip、zstd）", other)),
+        }
+    }
+}
+
+/// gRPC 传输参数，Node / Client 通过 `#[command(flatten)]` 作为命令行参数使用
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, clap::Args)]
+#[serde(default)]
+pub struct GrpcTuning {
+    /// gRPC 单条消息大小上限（字节）
+    #[arg(long = "grpc-max-message-size", env = "OXIPROXY_GRPC_MAX_MESSAGE_SIZE", default_value_t = DEFAULT_MAX_MESSAGE_SIZE)]
+    pub max_message_size: usize,
+
+    /// 发送 gRPC 消息时使用的压缩方式
+    #[arg(long = "grpc-compression", env = "OXIPROXY_GRPC_COMPRESSION", value_enum, default_value_t = Compression::None)]
+    pub compression: Compression,
+
+    /// HTTP/2 keepalive ping 间隔（秒），0 表示不发送；应小于中间 NAT / 负载均衡器的空闲超时
+    #[arg(long = "grpc-keepalive-interval", env = "OXIPROXY_GRPC_KEEPALIVE_INTERVAL_SECS", default_value_t = DEFAULT_KEEPALIVE_INTERVAL_SECS)]
+    pub keepalive_interval_secs: u64,
+
+    /// keepalive ping 的响应超时（秒），超时后断开连接并重连
+    #[arg(long = "grpc-keepalive-timeout", env = "OXIPROXY_GRPC_KEEPALIVE_TIMEOUT_SECS", default_value_t = DEFAULT_KEEPALIVE_TIMEOUT_SECS)]
+    pub keepalive_timeout_secs: u64,
+}
+
+impl Default for GrpcTuning {
+    fn default() -> Self {
+        Self {
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            compression: Compression::None,
+            keepalive_interval_secs: DEFAULT_KEEPALIVE_INTERVAL_SECS,
+            keepalive_timeout_secs: DEFAULT_KEEPALIVE_TIMEOUT_SECS,
+        }
+    }
+}
+
+impl GrpcTuning {
+    /// 转为命令行参数（启动子进程时传递）
+    pub fn to_args(&self) -> Vec<String> {
+        let compression = match self.compression {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        };
+        vec![
+            format!("--grpc-max-message-size={}", self.max_message_size),
+            format!("--grpc-compression={}", compression),
+            format!("--grpc-keepalive-interval={}", self.keepalive_interval_secs),
+            format!("--grpc-keepalive-timeout={}", self.keepalive_timeout_secs),
+        ]
+    }
+
+    fn keepalive_interval(&self) -> Option<Duration> {
+        Some(Duration::from_secs(self.keepalive_interval_secs)).filter(|d| !d.is_zero())
+    }
+
+    fn keepalive_timeout(&self) -> Duration {
+        Duration::from_secs(self.keepalive_timeout_secs.max(1))
+    }
+}
+
+fn global() -> &'static OnceLock<GrpcTuning> {
+    static TUNING: OnceLock<GrpcTuning> = OnceLock::new();
+    &TUNING
+}
+
+/// 设置进程的 gRPC 传输参数（启动时调用一次，之后的调用被忽略）
+pub fn set(tuning: GrpcTuning) {
+    let _ = global().set(tuning);
+}
+
+/// 当前的 gRPC 传输参数，未设置时为默认值
+pub fn get() -> &'static GrpcTuning {
+    global().get_or_init(GrpcTuning::default)
+}
+
+/// 为连接 Controller 的 Channel 设置 keepalive（空闲时同样发送 ping，保持 NAT 映射）
+pub fn configure_endpoint(endpoint: Endpoint) -> Endpoint {
+    let tuning = get();
+    match tuning.keepalive_interval() {
+        Some(interval) => endpoint
+            .http2_keep_alive_interval(interval)
+            .keep_alive_timeout(tuning.keepalive_timeout())
+            .keep_alive_while_idle(true),
+        None => endpoint,
+    }
+}
+
+/// 为 gRPC Server 设置 keepalive
+pub fn configure_server(builder: Server) -> Server {
+    let tuning = get();
+    builder
+        .http2_keepalive_interval(tuning.keepalive_interval())
+        .http2_keepalive_timeout(Some(tuning.keepalive_timeout()))
+}
+
+/// 按当前参数设置生成的 gRPC 客户端 / 服务端的消息大小上限和压缩
+pub trait Tuned {
+    fn tuned(self) -> Self;
+}
+
+macro_rules! tune {
+    ($service:expr) => {{
+        let tuning = get();
+        let service = $service
+            .max_decoding_message_size(tuning.max_message_size)
+            .max_encoding_message_size(tuning.max_message_size)
+            .accept_compressed(CompressionEncoding::Gzip)
+            .accept_compressed(CompressionEncoding::Zstd);
+        match tuning.compression.encoding() {
+            Some(encoding) => service.send_compressed(encoding),
+            None => service,
+        }
+    }};
+}
+
+impl Tuned for AgentServerServiceClient<Channel> {
+    fn tuned(self) -> Self {
+        tune!(self)
+    }
+}
+
+impl Tuned for AgentClientServiceClient<Channel> {
+    fn tuned(self) -> Self {
+        tune!(self)
+    }
+}
+
+impl<T: AgentServerService> Tuned for AgentServerServiceServer<T> {
+    fn tuned(self) -> Self {
+        tune!(self)
+    }
+}
+
+impl<T: AgentClientService> Tuned for AgentClientServiceServer<T> {
+    fn tuned(self) -> Self {
+        tune!(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_compression() {
+        assert_eq!(" GZIP ".parse::<Compression>(), Ok(Compression::Gzip));
+        assert_eq!("".parse::<Compression>(), Ok(Compression::None));
+        assert!("brotli".parse::<Compression>().is_err());
+    }
+
+    #[test]
+    fn test_keepalive() {
+        let tuning = GrpcTuning { keepalive_interval_secs: 0, keepalive_timeout_secs: 0, ..Default::default() };
+        assert_eq!(tuning.keepalive_interval(), None);
+        assert_eq!(tuning.keepalive_timeout(), Duration::from_secs(1));
+        assert_eq!(GrpcTuning::default().keepalive_interval(), Some(Duration::from_secs(30)));
+    }
+}
@@ -0,0 +1,65 @@
+//! 容器健康检查与终止信号
+//!
+//! `node health` / `client health` 子命令通过 [`check_ready`] 请求运行中进程的 `/readyz`，
+//! 未就绪时以非零状态退出，可直接用作 Docker `HEALTHCHECK`。镜像中没有 curl / wget，
+//! 这里直接用 TCP 发送最小的 HTTP/1.0 请求。
+
+use anyhow::{anyhow, Result};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// 健康检查请求的超时时间
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 请求 `http://host:port/readyz`，返回 200 时成功，否则返回包含响应内容的错误
+pub async fn check_ready(host: &str, port: u16) -> Result<String> {
+    tokio::time::timeout(CHECK_TIMEOUT, request_readyz(host, port))
+        .await
+        .map_err(|_| anyhow!("健康检查超时"))?
+}
+
+async fn request_readyz(host: &str, port: u16) -> Result<String> {
+    let addr = crate::utils::join_host_port(host, port);
+    let mut stream = TcpStream::connect(&addr)
+        .await
+        .map_err(|e| anyhow!("无法连接健康检查服务 {}: {}", addr, e))?;
+    let request = format!("GET /readyz HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n", addr);
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    stream.take(64 * 1024).read_to_end(&mut response).await?;
+    let response = String::from_utf8_lossy(&response);
+
+    let status = response
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| anyhow!("无效的健康检查响应"))?;
+    let body = response.split_once("\r\n\r\n").map(|(_, b)| b.trim().to_string()).unwrap_or_default();
+
+    if status == 200 {
+        Ok(body)
+    } else {
+        Err(anyhow!("未就绪 (HTTP {}): {}", status, body))
+    }
+}
+
+/// 等待 Ctrl+C 或 SIGTERM（容器停止时发送），返回信号名称
+pub async fn shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut sigterm = signal(SignalKind::terminate()).expect("failed to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => "Ctrl+C",
+            _ = sigterm.recv() => "SIGTERM",
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "Ctrl+C"
+    }
+}
@@ -8,6 +8,15 @@ pub mod config;
 pub mod utils;
 pub mod protocol;
 pub mod grpc;
+pub mod env;
+pub mod update;
+pub mod target_rule;
+pub mod mitigation;
+pub mod nat_probe;
+pub mod health;
+pub mod check;
+pub mod socket_activation;
+pub mod source_binding;
 
 
 pub use tunnel::{
@@ -31,4 +40,4 @@ pub use tunnel::{
     TcpTunnelListener,
 };
 
-pub use config::KcpConfig;
+pub use config::{CongestionController, KcpConfig, QuicConfig};
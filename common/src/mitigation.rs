@@ -0,0 +1,138 @@
+//! 来源 IP 自动处置规则
+//!
+//! 单个来源 IP 在某个代理上的速率或并发连接数持续超过阈值时，节点在一段时间内对该 IP 限速或拒绝连接。
+//! 规则可以设置在代理上，也可以设置为节点默认值（代理未设置时使用）。
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// 触发后的处置方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MitigationAction {
+    /// 限制该 IP 在此代理上的总速率
+    Throttle,
+    /// 拒绝新连接并断开已有连接
+    Block,
+}
+
+impl MitigationAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MitigationAction::Throttle => "throttle",
+            MitigationAction::Block => "block",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "throttle" => Some(MitigationAction::Throttle),
+            "block" => Some(MitigationAction::Block),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MitigationRule {
+    /// 单个 IP 的速率上限（Mbps，收发合计）
+    #[serde(default)]
+    pub max_mbps: Option<f64>,
+    /// 单个 IP 的并发连接上限
+    #[serde(default)]
+    pub max_connections: Option<u32>,
+    /// 持续超限多少秒后触发
+    pub sustain_secs: u32,
+    pub action: MitigationAction,
+    /// 限速时的速率（Mbps），仅 `throttle` 使用
+    #[serde(default)]
+    pub throttle_mbps: Option<f64>,
+    /// 处置持续时间（秒）
+    pub penalty_secs: u32,
+}
+
+impl MitigationRule {
+    pub fn validate(&self) -> Result<()> {
+        if self.max_mbps.is_none() && self.max_connections.is_none() {
+            return Err(anyhow!("至少需要设置速率上限或并发连接上限"));
+        }
+        if self.max_mbps.is_some_and(|m| !m.is_finite() || m <= 0.0) {
+            return Err(anyhow!("速率上限必须大于 0"));
+        }
+        if self.max_connections == Some(0) {
+            return Err(anyhow!("并发连接上限必须大于 0"));
+        }
+        if !(1..=3600).contains(&self.sustain_secs) {
+            return Err(anyhow!("持续时间必须在 1 到 3600 秒之间"));
+        }
+        if !(1..=86400).contains(&self.penalty_secs) {
+            return Err(anyhow!("处置时长必须在 1 到 86400 秒之间"));
+        }
+        match (self.action, self.throttle_mbps) {
+            (MitigationAction::Throttle, Some(m)) if m.is_finite() && m > 0.0 => Ok(()),
+            (MitigationAction::Throttle, _) => Err(anyhow!("限速处置需要设置大于 0 的限速速率")),
+            (MitigationAction::Block, _) => Ok(()),
+        }
+    }
+
+    /// 当前速率（字节/秒）和并发连接数是否超过阈值
+    pub fn exceeded_by(&self, bytes_per_sec: f64, connections: u32) -> bool {
+        self.max_mbps.is_some_and(|m| bytes_per_sec > mbps_to_bytes(m))
+            || self.max_connections.is_some_and(|c| connections > c)
+    }
+
+    /// 限速处置的速率（字节/秒）
+    pub fn throttle_rate(&self) -> u64 {
+        self.throttle_mbps.map_or(0, |m| mbps_to_bytes(m) as u64)
+    }
+}
+
+/// Mbps 转换为字节/秒
+pub fn mbps_to_bytes(mbps: f64) -> f64 {
+    mbps * 1_000_000.0 / 8.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule() -> MitigationRule {
+        MitigationRule {
+            max_mbps: Some(8.0),
+            max_connections: Some(10),
+            sustain_secs: 5,
+            action: MitigationAction::Throttle,
+            throttle_mbps: Some(1.0),
+            penalty_secs: 60,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(rule().validate().is_ok());
+        assert!(MitigationRule { max_mbps: None, max_connections: None, ..rule() }.validate().is_err());
+        assert!(MitigationRule { throttle_mbps: None, ..rule() }.validate().is_err());
+        assert!(MitigationRule { action: MitigationAction::Block, throttle_mbps: None, ..rule() }.validate().is_ok());
+        assert!(MitigationRule { sustain_secs: 0, ..rule() }.validate().is_err());
+        assert!(MitigationRule { max_connections: Some(0), ..rule() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_exceeded_by() {
+        let rule = rule();
+        assert!(!rule.exceeded_by(1_000_000.0, 10));
+        assert!(rule.exceeded_by(1_000_001.0, 1));
+        assert!(rule.exceeded_by(0.0, 11));
+        assert_eq!(rule.throttle_rate(), 125_000);
+    }
+
+    #[test]
+    fn test_json_format() {
+        let json = r#"{"maxConnections":20,"sustainSecs":10,"action":"block","penaltySecs":300}"#;
+        let rule: MitigationRule = serde_json::from_str(json).unwrap();
+        assert_eq!(rule.max_connections, Some(20));
+        assert_eq!(rule.max_mbps, None);
+        assert_eq!(rule.action, MitigationAction::Block);
+    }
+}
@@ -0,0 +1,234 @@
+//! NAT 探测协议
+//!
+//! 节点在探测端口 `P` 和 `P + 1` 上各监听一个 UDP 套接字，收到探测请求后回复请求的来源地址
+//! （即客户端的外部映射地址）。客户端从同一个本地套接字依次：
+//!
+//! 1. 向 `P` 发送请求，得到映射地址 M1；收不到回复说明 UDP 被阻断，M1 等于本地地址说明没有 NAT
+//! 2. 向 `P + 1` 发送请求，得到映射地址 M2；M2 与 M1 不同说明映射随目标变化（对称型 NAT）
+//! 3. 向 `P` 发送请求并要求从 `P + 1` 回复；能收到说明 NAT 不按端口过滤入站包（完全锥形），
+//!    否则为端口受限锥形
+//!
+//! 节点只有一个地址，无法区分完全锥形和地址受限锥形，两者都报告为完全锥形。
+//!
+//! 请求可以用零填充到不同的长度，带 `FLAG_PAD_REPLY` 时回复也填充到与请求相同的长度，
+//! 客户端据此检测链路是否会丢弃大包（MTU 黑洞）。回复从不比请求大，探测端口不会被用于流量放大。
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+const MAGIC: &[u8; 4] = b"OXNP";
+const VERSION: u8 = 1;
+/// 请求包长度（不足的部分填零）
+pub const REQUEST_LEN: usize = 64;
+/// 要求节点从另一个探测端口回复
+pub const FLAG_ALT_PORT: u8 = 0x01;
+/// 要求节点把回复填充到与请求相同的长度
+pub const FLAG_PAD_REPLY: u8 = 0x02;
+/// 请求包的最大长度（节点只读取这么多字节）
+pub const MAX_REQUEST_LEN: usize = 1500;
+
+/// 探测请求
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeRequest {
+    pub flags: u8,
+    pub txn: u64,
+}
+
+/// 探测回复
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeResponse {
+    pub flags: u8,
+    pub txn: u64,
+    /// 节点看到的请求来源地址
+    pub mapped: SocketAddr,
+}
+
+impl ProbeRequest {
+    pub fn encode(&self) -> [u8; REQUEST_LEN] {
+        let mut buf = [0u8; REQUEST_LEN];
+        buf[..4].copy_from_slice(MAGIC);
+        buf[4] = VERSION;
+        buf[5] = self.flags;
+        buf[6..14].copy_from_slice(&self.txn.to_be_bytes());
+        buf
+    }
+
+    /// 编码并用零填充到 `len` 字节（限制在 `REQUEST_LEN..=MAX_REQUEST_LEN` 内）
+    pub fn encode_padded(&self, len: usize) -> Vec<u8> {
+        let mut buf = self.encode().to_vec();
+        buf.resize(len.clamp(REQUEST_LEN, MAX_REQUEST_LEN), 0);
+        buf
+    }
+
+    pub fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < REQUEST_LEN || &buf[..4] != MAGIC || buf[4] != VERSION {
+            return None;
+        }
+        Some(Self {
+            flags: buf[5],
+            txn: u64::from_be_bytes(buf[6..14].try_into().ok()?),
+        })
+    }
+}
+
+impl ProbeResponse {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(33);
+        buf.extend_from_slice(MAGIC);
+        buf.push(VERSION);
+        buf.push(self.flags);
+        buf.extend_from_slice(&self.txn.to_be_bytes());
+        match self.mapped.ip() {
+            IpAddr::V4(ip) => {
+                buf.push(4);
+                buf.extend_from_slice(&self.mapped.port().to_be_bytes());
+                buf.extend_from_slice(&ip.octets());
+            }
+            IpAddr::V6(ip) => {
+                buf.push(6);
+                buf.extend_from_slice(&self.mapped.port().to_be_bytes());
+                buf.extend_from_slice(&ip.octets());
+            }
+        }
+        buf
+    }
+
+    /// 编码并用零填充到 `len` 字节（`len` 为请求长度）
+    pub fn encode_padded(&self, len: usize) -> Vec<u8> {
+        let mut buf = self.encode();
+        if buf.len() < len {
+            buf.resize(len.min(MAX_REQUEST_LEN), 0);
+        }
+        buf
+    }
+
+    pub fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < 17 || &buf[..4] != MAGIC || buf[4] != VERSION {
+            return None;
+        }
+        let flags = buf[5];
+        let txn = u64::from_be_bytes(buf[6..14].try_into().ok()?);
+        let port = u16::from_be_bytes([buf[15], buf[16]]);
+        let ip = match buf[14] {
+            4 => IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(buf.get(17..21)?).ok()?)),
+            6 => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(buf.get(17..33)?).ok()?)),
+            _ => return None,
+        };
+        Some(Self {
+            flags,
+            txn,
+            mapped: SocketAddr::new(ip, port),
+        })
+    }
+}
+
+/// NAT 类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NatType {
+    /// 没有 NAT（外部地址与本地地址相同）
+    Open,
+    /// 映射与目标无关，且不按端口过滤入站包
+    FullCone,
+    /// 映射与目标无关，只接收曾发送过的地址和端口的回包
+    PortRestrictedCone,
+    /// 不同目标使用不同的映射，P2P 打洞基本无法成功
+    Symmetric,
+    /// 收不到任何回复
+    UdpBlocked,
+}
+
+impl NatType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NatType::Open => "open",
+            NatType::FullCone => "full_cone",
+            NatType::PortRestrictedCone => "port_restricted_cone",
+            NatType::Symmetric => "symmetric",
+            NatType::UdpBlocked => "udp_blocked",
+        }
+    }
+}
+
+impl fmt::Display for NatType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 根据三次探测的结果判断 NAT 类型
+///
+/// `mapped1` / `mapped2` 为向两个探测端口请求得到的映射地址，`alt_reply` 表示是否收到了
+/// 从另一端口发出的回复。第二个端口没有回复时按映射不变处理。
+pub fn classify(local: SocketAddr, mapped1: Option<SocketAddr>, mapped2: Option<SocketAddr>, alt_reply: bool) -> NatType {
+    let Some(mapped1) = mapped1 else {
+        return NatType::UdpBlocked;
+    };
+    if mapped1 == local {
+        return NatType::Open;
+    }
+    if mapped2.is_some_and(|m| m != mapped1) {
+        return NatType::Symmetric;
+    }
+    if alt_reply {
+        NatType::FullCone
+    } else {
+        NatType::PortRestrictedCone
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_request_roundtrip() {
+        let req = ProbeRequest { flags: FLAG_ALT_PORT, txn: 0x0102_0304_0506_0708 };
+        let buf = req.encode();
+        assert_eq!(ProbeRequest::decode(&buf), Some(req));
+        // 短包和错误的魔数被拒绝
+        assert_eq!(ProbeRequest::decode(&buf[..20]), None);
+        let mut bad = buf;
+        bad[0] = b'X';
+        assert_eq!(ProbeRequest::decode(&bad), None);
+    }
+
+    #[test]
+    fn test_response_roundtrip() {
+        for mapped in [addr("203.0.113.7:40000"), addr("[2001:db8::1]:5353")] {
+            let resp = ProbeResponse { flags: 0, txn: 42, mapped };
+            let buf = resp.encode();
+            assert!(buf.len() < REQUEST_LEN);
+            assert_eq!(ProbeResponse::decode(&buf), Some(resp));
+        }
+        assert_eq!(ProbeResponse::decode(b"OXNP"), None);
+    }
+
+    #[test]
+    fn test_padded_roundtrip() {
+        let req = ProbeRequest { flags: FLAG_PAD_REPLY, txn: 7 };
+        let buf = req.encode_padded(1400);
+        assert_eq!(buf.len(), 1400);
+        assert_eq!(ProbeRequest::decode(&buf), Some(req));
+        assert_eq!(req.encode_padded(9000).len(), MAX_REQUEST_LEN);
+
+        let resp = ProbeResponse { flags: FLAG_PAD_REPLY, txn: 7, mapped: addr("203.0.113.7:40000") };
+        let buf = resp.encode_padded(1400);
+        assert_eq!(buf.len(), 1400);
+        assert_eq!(ProbeResponse::decode(&buf), Some(resp));
+    }
+
+    #[test]
+    fn test_classify() {
+        let local = addr("192.168.1.10:50000");
+        let m1 = addr("203.0.113.7:40000");
+        assert_eq!(classify(local, None, None, false), NatType::UdpBlocked);
+        assert_eq!(classify(m1, Some(m1), Some(m1), true), NatType::Open);
+        assert_eq!(classify(local, Some(m1), Some(addr("203.0.113.7:40001")), true), NatType::Symmetric);
+        assert_eq!(classify(local, Some(m1), Some(m1), true), NatType::FullCone);
+        assert_eq!(classify(local, Some(m1), None, false), NatType::PortRestrictedCone);
+    }
+}
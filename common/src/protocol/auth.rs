@@ -8,6 +8,7 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
 use super::control::ProxyConfig;
+use super::handshake::AuthProof;
 
 /// Token 验证请求
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +23,9 @@ pub struct ValidateTokenResponse {
     pub client_name: String,
     pub allowed: bool,
     pub reject_reason: Option<String>,
+    /// 允许同一客户端的多个实例同时连接（重复登录策略为副本模式），节点在各实例的隧道间分配访客连接
+    #[serde(default)]
+    pub allow_replicas: bool,
 }
 
 /// 客户端上下线通知
@@ -48,6 +52,11 @@ pub trait ClientAuthProvider: Send + Sync {
     /// 返回客户端信息和是否允许连接
     async fn validate_token(&self, token: &str) -> Result<ValidateTokenResponse>;
 
+    /// 校验隧道握手的认证应答
+    ///
+    /// 按 `proof.client_id` 查找客户端，用其 token 校验 HMAC，其余检查与 `validate_token` 相同
+    async fn validate_proof(&self, proof: &AuthProof) -> Result<ValidateTokenResponse>;
+
     /// 通知客户端上下线状态
     async fn set_client_online(&self, client_id: i64, online: bool) -> Result<()>;
 
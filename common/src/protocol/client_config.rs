@@ -3,7 +3,7 @@
 //! 定义了 Agent Client 从 Controller 获取连接配置的请求/响应结构体。
 
 use serde::{Deserialize, Serialize};
-use crate::config::KcpConfig;
+use crate::config::{KcpConfig, QuicConfig};
 use crate::tunnel::TunnelProtocol;
 
 /// 客户端连接配置请求
@@ -23,6 +23,9 @@ pub struct ClientConnectConfig {
     pub protocol: TunnelProtocol,
     /// KCP 配置（可选）
     pub kcp: Option<KcpConfig>,
+    /// QUIC 配置（可选）
+    #[serde(default)]
+    pub quic: Option<QuicConfig>,
     /// 客户端 ID
     pub client_id: i64,
     /// 客户端名称
@@ -57,8 +60,20 @@ pub struct ServerProxyGroup {
     pub protocol: TunnelProtocol,
     /// KCP 配置（可选）
     pub kcp: Option<KcpConfig>,
+    /// QUIC 配置（可选）
+    #[serde(default)]
+    pub quic: Option<QuicConfig>,
     /// 该 Server 上的代理列表
     pub proxies: Vec<ProxyInfo>,
+    /// 禁止转发的本地目标规则（格式见 `target_rule`）
+    #[serde(default)]
+    pub blocked_targets: Vec<String>,
+    /// 允许转发的本地目标白名单，为空表示不限制
+    #[serde(default)]
+    pub allowed_targets: Vec<String>,
+    /// 节点的 NAT 探测端口，未启用时为空
+    #[serde(default)]
+    pub nat_probe_port: Option<u16>,
 }
 
 /// 轮询响应中的代理信息
@@ -71,4 +86,24 @@ pub struct ProxyInfo {
     pub local_port: i32,
     pub remote_port: i32,
     pub enabled: bool,
+    /// 客户端到本地服务的预连接数，0 表示不启用
+    #[serde(default)]
+    pub local_pool_size: u32,
+    /// 连接本地服务使用的源 IP 或网卡，为空时使用客户端的全局设置
+    #[serde(default)]
+    pub local_source: Option<String>,
+}
+
+/// 访客代理：在客户端本机监听端口，连接经节点转发到目标代理
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VisitorInfo {
+    pub visitor_id: i64,
+    pub name: String,
+    /// 与目标代理相同（tcp / udp）
+    pub proxy_type: String,
+    pub bind_ip: String,
+    pub bind_port: u16,
+    /// 目标代理在节点上的访问地址
+    pub target_addr: String,
+    pub target_port: u16,
 }
@@ -7,7 +7,7 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
 /// 代理配置信息
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProxyConfig {
     pub proxy_id: i64,
     pub client_id: String,
@@ -17,6 +17,71 @@ pub struct ProxyConfig {
     pub local_port: u16,
     pub remote_port: u16,
     pub enabled: bool,
+    /// TCP 连接空闲超时（秒），0 表示不限制，`None` 使用节点默认值
+    #[serde(default)]
+    pub idle_timeout: Option<u32>,
+    /// 来源 IP 处置规则，`None` 使用节点默认规则
+    #[serde(default)]
+    pub mitigation: Option<crate::mitigation::MitigationRule>,
+    /// 节点上监听的 IP，`None` 使用节点默认的监听地址
+    #[serde(default)]
+    pub bind_ip: Option<String>,
+    /// 节点公平分配带宽时的权重，`None` 为 1
+    #[serde(default)]
+    pub bandwidth_weight: Option<u32>,
+    /// 优先级，`None` 为普通
+    #[serde(default)]
+    pub priority: Option<ProxyPriority>,
+}
+
+/// 代理优先级（QoS）
+///
+/// 节点公平分配带宽时优先级与带宽权重相乘作为调度权重，QUIC 隧道上同时设置流的发送优先级，
+/// 使 SSH、RDP 等交互式隧道在备份等大流量隧道旁保持响应。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyPriority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl ProxyPriority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProxyPriority::High => "high",
+            ProxyPriority::Normal => "normal",
+            ProxyPriority::Low => "low",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "high" => Some(ProxyPriority::High),
+            "normal" => Some(ProxyPriority::Normal),
+            "low" => Some(ProxyPriority::Low),
+            _ => None,
+        }
+    }
+
+    /// 公平分配时与带宽权重相乘的系数
+    pub fn weight_factor(&self) -> u32 {
+        match self {
+            ProxyPriority::High => 4,
+            ProxyPriority::Normal => 2,
+            ProxyPriority::Low => 1,
+        }
+    }
+
+    /// QUIC 流的发送优先级，数值越大越先发送
+    pub fn stream_priority(&self) -> i32 {
+        match self {
+            ProxyPriority::High => 1,
+            ProxyPriority::Normal => 0,
+            ProxyPriority::Low => -1,
+        }
+    }
 }
 
 /// 启动代理请求
@@ -33,6 +98,19 @@ pub struct StopProxyRequest {
     pub proxy_id: i64,
 }
 
+/// 两阶段创建代理时在节点上预留的端口
+///
+/// 创建代理时先预留端口（节点绑定并持有），写入数据库后再激活监听器，写入失败则释放，
+/// 端口冲突在写入数据库前即可发现，无需事后删除记录回滚。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortLease {
+    /// 预留所在的节点，Controller 据此路由；节点本地实现忽略
+    pub node_id: Option<i64>,
+    pub client_id: String,
+    pub proxy_type: String,
+    pub remote_port: u16,
+}
+
 /// 连接的客户端信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectedClient {
@@ -46,6 +124,33 @@ pub struct ConnectedClient {
 pub struct ServerStatus {
     pub connected_clients: Vec<ConnectedClient>,
     pub active_proxy_count: usize,
+    /// 因空闲超时被回收的连接累计数
+    #[serde(default)]
+    pub reaped_connections: u64,
+    /// 代理监听器因超过接入速率关闭的连接累计数
+    #[serde(default)]
+    pub accept_rate_limited: u64,
+    /// 代理监听器因待处理连接达到上限关闭的连接累计数
+    #[serde(default)]
+    pub accept_pending_rejected: u64,
+    /// 正在进行的转发任务数（TCP 连接和 UDP 会话）
+    #[serde(default)]
+    pub active_relays: u64,
+    /// 转发任务上限，0 表示不限制
+    #[serde(default)]
+    pub relay_limit: u64,
+    /// 因转发任务数达到上限被拒绝的连接累计数
+    #[serde(default)]
+    pub relay_rejected: u64,
+    /// 打开的文件描述符数，无法获取时为 0
+    #[serde(default)]
+    pub open_fds: u64,
+    /// 文件描述符软限制，无法获取时为 0
+    #[serde(default)]
+    pub fd_limit: u64,
+    /// KCP 多路复用统计
+    #[serde(default)]
+    pub kcp: crate::tunnel::KcpStats,
 }
 
 /// 日志条目
@@ -68,6 +173,15 @@ pub trait ProxyControl: Send + Sync {
     /// 停止指定客户端的指定代理监听器
     async fn stop_proxy(&self, client_id: &str, proxy_id: i64) -> Result<()>;
 
+    /// 预留代理端口：节点绑定并持有该端口，直到激活、释放或超时
+    async fn reserve_port(&self, lease: &PortLease) -> Result<()>;
+
+    /// 关闭预留的端口并立即启动代理监听器
+    async fn activate_proxy(&self, lease: &PortLease, proxy_id: i64) -> Result<()>;
+
+    /// 释放未激活的预留端口
+    async fn release_port(&self, lease: &PortLease) -> Result<()>;
+
     /// 获取当前连接的客户端列表
     async fn get_connected_clients(&self) -> Result<Vec<ConnectedClient>>;
 
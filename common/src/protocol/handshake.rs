@@ -0,0 +1,312 @@
+//! 隧道认证握手
+//!
+//! 客户端连上节点后，在第一个双向流上完成挑战-应答认证，token 本身不在隧道上传输：
+//!
+//! 1. 客户端 → 节点：`ClientHello`（魔数、版本、客户端 ID、客户端随机数、客户端信息）；
+//! 2. 节点 → 客户端：`ServerChallenge`（魔数、版本、节点随机数）；
+//! 3. 客户端 → 节点：HMAC-SHA256(token, 上下文 ‖ 握手内容 ‖ 两个随机数)；
+//! 4. 节点 → 客户端：`AuthResult`（是否通过、拒绝原因）。
+//!
+//! 节点随机数每个连接重新生成，抓包得到的应答无法在另一个连接上重放；
+//! 各字段有固定长度或长度上限，魔数、版本或长度不符的连接直接断开，整个握手须在
+//! [`HANDSHAKE_TIMEOUT`] 内完成。节点没有 token，应答交给 Controller 校验（见 [`AuthProof`]）。
+
+use anyhow::{anyhow, bail, Result};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::tunnel::{TunnelRecvStream, TunnelSendStream};
+
+/// 握手魔数
+pub const MAGIC: [u8; 4] = *b"OXTA";
+/// 当前握手版本
+pub const VERSION: u8 = 1;
+/// 随机数长度
+pub const NONCE_LEN: usize = 16;
+/// HMAC-SHA256 长度
+pub const MAC_LEN: usize = 32;
+/// 客户端信息的最大长度
+pub const MAX_METADATA_LEN: usize = 512;
+/// 拒绝原因的最大长度
+const MAX_REASON_LEN: usize = 512;
+/// 整个握手的超时时间
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// HMAC 的上下文前缀，避免与其他用途的签名混淆
+const MAC_CONTEXT: &[u8] = b"oxiproxy-tunnel-auth";
+
+/// 生成随机数
+pub fn random_nonce() -> [u8; NONCE_LEN] {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new().fill(&mut nonce).expect("系统随机数不可用");
+    nonce
+}
+
+/// 客户端信息（握手时上报，节点记录日志）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClientMetadata {
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    /// 客户端实例 ID，节点据此区分同一实例重连和另一个实例的连接（旧版客户端不上报）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<String>,
+}
+
+impl ClientMetadata {
+    /// 当前平台的客户端信息
+    pub fn current(version: &str) -> Self {
+        Self {
+            version: version.to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            instance_id: None,
+        }
+    }
+}
+
+/// 客户端问候
+#[derive(Debug, Clone)]
+pub struct ClientHello {
+    pub client_id: i64,
+    pub client_nonce: [u8; NONCE_LEN],
+    /// JSON 编码的 [`ClientMetadata`]，原样参与 HMAC 计算
+    pub metadata: Vec<u8>,
+}
+
+impl ClientHello {
+    pub fn new(client_id: i64, metadata: &ClientMetadata) -> Self {
+        let mut metadata = serde_json::to_vec(metadata).unwrap_or_default();
+        metadata.truncate(MAX_METADATA_LEN);
+        Self { client_id, client_nonce: random_nonce(), metadata }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(4 + 1 + 8 + NONCE_LEN + 2 + self.metadata.len());
+        buf.extend_from_slice(&MAGIC);
+        buf.push(VERSION);
+        buf.extend_from_slice(&self.client_id.to_be_bytes());
+        buf.extend_from_slice(&self.client_nonce);
+        buf.extend_from_slice(&(self.metadata.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&self.metadata);
+        buf
+    }
+
+    /// 读取问候，魔数、版本或长度不符时返回错误
+    pub async fn read(recv: &mut dyn TunnelRecvStream) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        recv.read_exact(&mut magic).await?;
+        if magic != MAGIC {
+            bail!("握手魔数不符");
+        }
+        Self::read_after_magic(recv).await
+    }
+
+    /// 读取魔数之后的部分（调用方已读取并核对魔数）
+    pub async fn read_after_magic(recv: &mut dyn TunnelRecvStream) -> Result<Self> {
+        let mut header = [0u8; 1 + 8 + NONCE_LEN + 2];
+        recv.read_exact(&mut header).await?;
+        check_version(header[0])?;
+        let client_id = i64::from_be_bytes(header[1..9].try_into().unwrap());
+        let client_nonce: [u8; NONCE_LEN] = header[9..9 + NONCE_LEN].try_into().unwrap();
+        let len = u16::from_be_bytes(header[9 + NONCE_LEN..].try_into().unwrap()) as usize;
+        if len > MAX_METADATA_LEN {
+            bail!("客户端信息过长: {} 字节", len);
+        }
+        let mut metadata = vec![0u8; len];
+        recv.read_exact(&mut metadata).await?;
+        Ok(Self { client_id, client_nonce, metadata })
+    }
+
+    /// 解析客户端信息，格式不符时返回默认值
+    pub fn metadata(&self) -> ClientMetadata {
+        serde_json::from_slice(&self.metadata).unwrap_or_default()
+    }
+}
+
+/// 节点挑战
+#[derive(Debug, Clone)]
+pub struct ServerChallenge {
+    pub server_nonce: [u8; NONCE_LEN],
+}
+
+impl ServerChallenge {
+    pub fn new() -> Self {
+        Self { server_nonce: random_nonce() }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(4 + 1 + NONCE_LEN);
+        buf.extend_from_slice(&MAGIC);
+        buf.push(VERSION);
+        buf.extend_from_slice(&self.server_nonce);
+        buf
+    }
+
+    pub async fn read(recv: &mut dyn TunnelRecvStream) -> Result<Self> {
+        let mut buf = [0u8; 4 + 1 + NONCE_LEN];
+        recv.read_exact(&mut buf).await?;
+        if buf[..4] != MAGIC {
+            bail!("握手魔数不符");
+        }
+        check_version(buf[4])?;
+        Ok(Self { server_nonce: buf[5..].try_into().unwrap() })
+    }
+}
+
+impl Default for ServerChallenge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn check_version(version: u8) -> Result<()> {
+    if version != VERSION {
+        bail!("不支持的握手版本: {}（当前 {}）", version, VERSION);
+    }
+    Ok(())
+}
+
+/// 待校验的认证应答，节点转交 Controller 校验
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthProof {
+    pub client_id: i64,
+    pub client_nonce: Vec<u8>,
+    pub server_nonce: Vec<u8>,
+    pub metadata: Vec<u8>,
+    pub mac: Vec<u8>,
+}
+
+impl AuthProof {
+    /// 客户端计算应答
+    pub fn sign(token: &str, hello: &ClientHello, challenge: &ServerChallenge) -> [u8; MAC_LEN] {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, token.as_bytes());
+        let msg = signed_message(hello.client_id, &hello.client_nonce, &challenge.server_nonce, &hello.metadata);
+        hmac::sign(&key, &msg).as_ref().try_into().unwrap()
+    }
+
+    /// 节点组装收到的问候、挑战和应答
+    pub fn new(hello: ClientHello, challenge: &ServerChallenge, mac: [u8; MAC_LEN]) -> Self {
+        Self {
+            client_id: hello.client_id,
+            client_nonce: hello.client_nonce.to_vec(),
+            server_nonce: challenge.server_nonce.to_vec(),
+            metadata: hello.metadata,
+            mac: mac.to_vec(),
+        }
+    }
+
+    /// 用客户端 token 校验应答（常量时间比较）
+    pub fn verify(&self, token: &str) -> bool {
+        if self.client_nonce.len() != NONCE_LEN || self.server_nonce.len() != NONCE_LEN {
+            return false;
+        }
+        let key = hmac::Key::new(hmac::HMAC_SHA256, token.as_bytes());
+        let msg = signed_message(self.client_id, &self.client_nonce, &self.server_nonce, &self.metadata);
+        hmac::verify(&key, &msg, &self.mac).is_ok()
+    }
+
+    /// 读取客户端应答
+    pub async fn read_mac(recv: &mut dyn TunnelRecvStream) -> Result<[u8; MAC_LEN]> {
+        let mut mac = [0u8; MAC_LEN];
+        recv.read_exact(&mut mac).await?;
+        Ok(mac)
+    }
+}
+
+fn signed_message(client_id: i64, client_nonce: &[u8], server_nonce: &[u8], metadata: &[u8]) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(MAC_CONTEXT.len() + 1 + 8 + NONCE_LEN * 2 + metadata.len());
+    msg.extend_from_slice(MAC_CONTEXT);
+    msg.push(VERSION);
+    msg.extend_from_slice(&client_id.to_be_bytes());
+    msg.extend_from_slice(client_nonce);
+    msg.extend_from_slice(server_nonce);
+    msg.extend_from_slice(metadata);
+    msg
+}
+
+/// 认证结果
+#[derive(Debug, Clone)]
+pub struct AuthResult {
+    pub allowed: bool,
+    pub reason: Option<String>,
+}
+
+impl AuthResult {
+    pub fn encode(&self) -> Vec<u8> {
+        let reason = self.reason.as_deref().unwrap_or_default().as_bytes();
+        let reason = &reason[..reason.len().min(MAX_REASON_LEN)];
+        let mut buf = Vec::with_capacity(1 + 2 + reason.len());
+        buf.push(self.allowed as u8);
+        buf.extend_from_slice(&(reason.len() as u16).to_be_bytes());
+        buf.extend_from_slice(reason);
+        buf
+    }
+
+    pub async fn read(recv: &mut dyn TunnelRecvStream) -> Result<Self> {
+        let mut header = [0u8; 3];
+        recv.read_exact(&mut header).await?;
+        let len = u16::from_be_bytes([header[1], header[2]]) as usize;
+        if len > MAX_REASON_LEN {
+            bail!("拒绝原因过长: {} 字节", len);
+        }
+        let mut reason = vec![0u8; len];
+        recv.read_exact(&mut reason).await?;
+        Ok(Self {
+            allowed: header[0] == 1,
+            reason: (!reason.is_empty()).then(|| String::from_utf8_lossy(&reason).into_owned()),
+        })
+    }
+}
+
+/// 客户端完成握手（须在 [`HANDSHAKE_TIMEOUT`] 内调用完毕）
+pub async fn client_handshake(
+    send: &mut dyn TunnelSendStream,
+    recv: &mut dyn TunnelRecvStream,
+    token: &str,
+    hello: &ClientHello,
+) -> Result<()> {
+    send.write_all(&hello.encode()).await?;
+    send.flush().await?;
+    let challenge = ServerChallenge::read(recv).await?;
+    send.write_all(&AuthProof::sign(token, hello, &challenge)).await?;
+    send.flush().await?;
+    let result = AuthResult::read(recv).await?;
+    if !result.allowed {
+        return Err(anyhow!("节点拒绝认证: {}", result.reason.unwrap_or_default()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proof_verify() {
+        let hello = ClientHello::new(42, &ClientMetadata::current("1.0.0"));
+        let challenge = ServerChallenge::new();
+        let mac = AuthProof::sign("secret", &hello, &challenge);
+        let proof = AuthProof::new(hello.clone(), &challenge, mac);
+        assert!(proof.verify("secret"));
+        assert!(!proof.verify("other"));
+
+        // 同一应答换一个节点随机数（重放到新连接）无法通过
+        let replayed = AuthProof::new(hello, &ServerChallenge::new(), mac);
+        assert!(!replayed.verify("secret"));
+
+        let mut tampered = proof.clone();
+        tampered.client_id = 43;
+        assert!(!tampered.verify("secret"));
+    }
+
+    #[test]
+    fn test_version_check() {
+        let hello = ClientHello::new(1, &ClientMetadata::default()).encode();
+        assert_eq!(hello[..4], MAGIC);
+        assert!(check_version(hello[4]).is_ok());
+        assert!(check_version(VERSION + 1).is_err());
+    }
+}
@@ -5,6 +5,7 @@
 
 pub mod control;
 pub mod auth;
+pub mod handshake;
 pub mod traffic;
 pub mod client_config;
 pub mod node_register;
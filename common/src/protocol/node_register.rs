@@ -29,3 +29,79 @@ pub struct NodeRegisterResponse {
     /// Controller 内部 API 地址（agent server 用于回调 controller）
     pub controller_internal_url: String,
 }
+
+/// 节点能力，节点注册时上报，Controller 以 JSON 保存在 `node.capabilities`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeCapabilities {
+    /// 节点本机的公网 IP（IPv4 / IPv6）
+    #[serde(default)]
+    pub public_ips: Vec<String>,
+    /// 支持的隧道协议
+    #[serde(default)]
+    pub tunnel_protocols: Vec<String>,
+    /// 可用于代理监听的端口范围
+    pub port_range_start: Option<u16>,
+    pub port_range_end: Option<u16>,
+    /// 最大吞吐量提示（字节/秒）
+    pub max_throughput: Option<u64>,
+    /// 能否监听 1024 以下的端口（root、CAP_NET_BIND_SERVICE 或放宽了 ip_unprivileged_port_start）
+    pub privileged_ports: Option<bool>,
+}
+
+impl NodeCapabilities {
+    /// 是否支持该隧道协议（未上报时视为支持）
+    pub fn supports_protocol(&self, protocol: &str) -> bool {
+        self.tunnel_protocols.is_empty() || self.tunnel_protocols.iter().any(|p| p == protocol)
+    }
+
+    /// 端口是否在可用范围内（未上报时视为可用）
+    pub fn allows_port(&self, port: u16) -> bool {
+        self.port_range_start.is_none_or(|start| port >= start) && self.port_range_end.is_none_or(|end| port <= end)
+    }
+
+    /// 节点是否明确上报了没有监听该端口的权限（未上报时视为有权限）
+    pub fn lacks_permission_for(&self, port: u16) -> bool {
+        port < 1024 && self.privileged_ports == Some(false)
+    }
+
+    /// 访客连接使用的地址：优先 IPv4
+    pub fn preferred_public_ip(&self) -> Option<&str> {
+        self.public_ips
+            .iter()
+            .find(|ip| !ip.contains(':'))
+            .or_else(|| self.public_ips.first())
+            .map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_checks() {
+        let caps = NodeCapabilities {
+            public_ips: vec!["2001:db8::1".to_string(), "203.0.113.5".to_string()],
+            tunnel_protocols: vec!["quic".to_string(), "tcp".to_string()],
+            port_range_start: Some(1024),
+            port_range_end: Some(60000),
+            max_throughput: None,
+            privileged_ports: Some(false),
+        };
+        assert!(caps.supports_protocol("quic"));
+        assert!(!caps.supports_protocol("kcp"));
+        assert!(caps.allows_port(1024));
+        assert!(!caps.allows_port(80));
+        assert!(!caps.allows_port(60001));
+        assert!(caps.lacks_permission_for(443));
+        assert!(!caps.lacks_permission_for(8443));
+        assert_eq!(caps.preferred_public_ip(), Some("203.0.113.5"));
+
+        let unknown = NodeCapabilities::default();
+        assert!(unknown.supports_protocol("kcp"));
+        assert!(unknown.allows_port(80));
+        assert!(!unknown.lacks_permission_for(80));
+        assert_eq!(unknown.preferred_public_ip(), None);
+    }
+}
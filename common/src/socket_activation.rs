@@ -0,0 +1,184 @@
+//! systemd 套接字激活（`sd_listen_fds`）
+//!
+//! 由 systemd 的 `.socket` 单元预先监听端口时，服务启动时通过 `LISTEN_PID` / `LISTEN_FDS`
+//! 环境变量收到从 3 开始的文件描述符。Controller 的 Web / gRPC 端口和节点的隧道端口会优先
+//! 使用端口相同的传入套接字（指定了监听 IP 时 IP 也需相同），没有时再自行监听。
+//!
+//! 套接字始终由 systemd 持有，服务重启期间到达的连接和数据包在内核中排队，重启后继续处理，
+//! 也可以按需启动服务。每次取用时复制一份文件描述符，隧道协议切换等场景下重新监听同一端口
+//! 仍会拿到同一个套接字。
+
+use std::net::SocketAddr;
+
+use anyhow::Result;
+
+/// 监听 `addr` 的 TCP 监听器：优先 systemd 传入的套接字，否则自行监听
+pub fn tcp_listener(addr: SocketAddr) -> Result<tokio::net::TcpListener> {
+    match imp::take(addr, socket2::Type::STREAM)? {
+        Some(socket) => Ok(tokio::net::TcpListener::from_std(socket.into())?),
+        None => crate::utils::bind_tcp_listener(addr),
+    }
+}
+
+/// 监听 `addr` 的 UDP 套接字（非阻塞）：优先 systemd 传入的套接字，否则自行监听
+pub fn std_udp_socket(addr: SocketAddr) -> Result<std::net::UdpSocket> {
+    match imp::take(addr, socket2::Type::DGRAM)? {
+        Some(socket) => Ok(socket.into()),
+        None => crate::utils::bind_std_udp_socket(addr),
+    }
+}
+
+/// [`std_udp_socket`] 的 tokio 版本
+pub async fn udp_socket(addr: SocketAddr) -> Result<tokio::net::UdpSocket> {
+    Ok(tokio::net::UdpSocket::from_std(std_udp_socket(addr)?)?)
+}
+
+/// 原地重新执行自身（exec）前调用：清除 systemd 传入套接字的 CLOEXEC 标志
+///
+/// exec 不改变进程 ID，新的进程映像通过相同的 `LISTEN_PID` / `LISTEN_FDS` 继续使用这些套接字，
+/// 重启期间到达的连接仍在内核中排队。
+pub fn keep_across_exec() -> Result<()> {
+    imp::keep_across_exec()
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::net::SocketAddr;
+    use std::ops::Range;
+    use std::os::fd::{BorrowedFd, FromRawFd, RawFd};
+    use std::sync::OnceLock;
+
+    use anyhow::Result;
+    use socket2::{SockRef, Socket, Type};
+    use tracing::{info, warn};
+
+    /// systemd 传入的第一个文件描述符（`SD_LISTEN_FDS_START`）
+    const LISTEN_FDS_START: i32 = 3;
+
+    /// 根据 `LISTEN_PID` / `LISTEN_FDS` 计算传入的文件描述符范围
+    ///
+    /// LISTEN_PID 与当前进程不符时，环境变量是从父进程继承来的，不属于本进程。
+    fn listen_fds(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> Range<i32> {
+        if listen_pid != Some(pid.to_string().as_str()) {
+            return LISTEN_FDS_START..LISTEN_FDS_START;
+        }
+        let count = listen_fds.and_then(|v| v.parse::<i32>().ok()).unwrap_or(0).max(0);
+        LISTEN_FDS_START..LISTEN_FDS_START + count
+    }
+
+    fn current_listen_fds() -> Range<i32> {
+        listen_fds(
+            std::env::var("LISTEN_PID").ok().as_deref(),
+            std::env::var("LISTEN_FDS").ok().as_deref(),
+            std::process::id(),
+        )
+    }
+
+    /// systemd 传入的套接字：(监听地址, 类型, 套接字)
+    fn inherited() -> &'static [(SocketAddr, Type, Socket)] {
+        static SOCKETS: OnceLock<Vec<(SocketAddr, Type, Socket)>> = OnceLock::new();
+        SOCKETS.get_or_init(|| {
+            let mut sockets = Vec::new();
+            for fd in current_listen_fds() {
+                let socket = unsafe { Socket::from_raw_fd(fd) };
+                // 避免泄漏给执行的子进程（如 nft / iptables）
+                let _ = socket.set_cloexec(true);
+                let addr = socket.local_addr().ok().and_then(|a| a.as_socket());
+                match (addr, socket.r#type()) {
+                    (Some(addr), Ok(ty)) if ty == Type::STREAM || ty == Type::DGRAM => {
+                        info!(
+                            "使用 systemd 传入的 {} 套接字: {}",
+                            if ty == Type::STREAM { "TCP" } else { "UDP" },
+                            addr
+                        );
+                        sockets.push((addr, ty, socket));
+                    }
+                    _ => {
+                        warn!("忽略 systemd 传入的文件描述符 {}：不是 TCP / UDP 套接字", fd);
+                        // 不是本模块能用的套接字，不关闭也不接管
+                        std::mem::forget(socket);
+                    }
+                }
+            }
+            sockets
+        })
+    }
+
+    fn set_cloexec(fd: RawFd, close_on_exec: bool) -> Result<()> {
+        // 只修改标志，不接管文件描述符
+        let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+        SockRef::from(&fd).set_cloexec(close_on_exec)?;
+        Ok(())
+    }
+
+    pub fn keep_across_exec() -> Result<()> {
+        for fd in current_listen_fds() {
+            set_cloexec(fd, false)?;
+        }
+        Ok(())
+    }
+
+    pub fn take(addr: SocketAddr, ty: Type) -> Result<Option<Socket>> {
+        let matches = |local: &SocketAddr| {
+            local.port() == addr.port() && (addr.ip().is_unspecified() || local.ip() == addr.ip())
+        };
+        let Some((_, _, socket)) = inherited().iter().find(|(local, t, _)| matches(local) && *t == ty) else {
+            return Ok(None);
+        };
+        let socket = socket.try_clone()?;
+        socket.set_nonblocking(true)?;
+        Ok(Some(socket))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::os::fd::AsRawFd;
+        use std::process::Command;
+
+        #[test]
+        fn test_listen_fds() {
+            assert_eq!(listen_fds(Some("42"), Some("2"), 42), 3..5);
+            assert!(listen_fds(Some("41"), Some("2"), 42).is_empty());
+            assert!(listen_fds(None, Some("2"), 42).is_empty());
+            assert!(listen_fds(Some("42"), Some("-1"), 42).is_empty());
+        }
+
+        /// 子进程 exec 后能否看到文件描述符
+        fn visible_after_exec(fd: RawFd) -> bool {
+            Command::new("sh")
+                .arg("-c")
+                .arg(format!("[ -e /dev/fd/{} ]", fd))
+                .status()
+                .unwrap()
+                .success()
+        }
+
+        #[test]
+        fn test_keep_across_exec() {
+            let socket = Socket::new(socket2::Domain::IPV4, Type::STREAM, None).unwrap();
+            let fd = socket.as_raw_fd();
+            // 与 inherited() 一致，接管后设置了 CLOEXEC
+            set_cloexec(fd, true).unwrap();
+            assert!(!visible_after_exec(fd));
+            set_cloexec(fd, false).unwrap();
+            assert!(visible_after_exec(fd));
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use std::net::SocketAddr;
+
+    use anyhow::Result;
+    use socket2::{Socket, Type};
+
+    pub fn take(_addr: SocketAddr, _ty: Type) -> Result<Option<Socket>> {
+        Ok(None)
+    }
+
+    pub fn keep_across_exec() -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+//! 出站连接的源地址 / 网卡绑定
+//!
+//! 多出口（多 WAN）的客户端机器或按源地址做策略路由的环境中，可以让客户端到节点的隧道连接、
+//! 到本地服务的连接固定从某个源 IP（先 bind 再 connect）或某块网卡（Linux 的
+//! `SO_BINDTODEVICE`）发出。隧道源在启动时全局设置一次；本地服务连接可以全局设置，
+//! 也可以按代理覆盖。
+//!
+//! 按网卡绑定仅支持 Linux，5.7 之前的内核需要 `CAP_NET_RAW`。
+
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, bail, Result};
+use socket2::SockRef;
+use tokio::net::{TcpSocket, TcpStream};
+
+/// 出站连接的源：源 IP 或网卡名
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceBinding {
+    Ip(IpAddr),
+    Interface(String),
+}
+
+impl FromStr for SourceBinding {
+    type Err = anyhow::Error;
+
+    /// IP 地址解析为源 IP，其余按网卡名处理
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Ok(ip) = crate::utils::normalize_host(s).parse::<IpAddr>() {
+            return Ok(Self::Ip(ip));
+        }
+        // 网卡名最长 15 字节（IFNAMSIZ - 1）
+        if s.is_empty() || s.len() > 15 || s.contains(|c: char| c.is_whitespace() || c == '/' || c == ':') {
+            bail!("无效的源地址或网卡名: {}", s);
+        }
+        Ok(Self::Interface(s.to_string()))
+    }
+}
+
+impl fmt::Display for SourceBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ip(ip) => write!(f, "{}", ip),
+            Self::Interface(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl SourceBinding {
+    /// 连接 `target` 时的本地绑定地址（端口为 0）
+    fn local_addr_for(&self, target: &SocketAddr) -> Result<SocketAddr> {
+        match self {
+            Self::Ip(ip) if ip.is_ipv4() != target.is_ipv4() => {
+                bail!("源地址 {} 与目标 {} 的地址族不同", ip, target)
+            }
+            Self::Ip(ip) => Ok(SocketAddr::new(*ip, 0)),
+            Self::Interface(_) => Ok(crate::utils::local_wildcard_for(target)),
+        }
+    }
+
+    fn bind_device(&self, socket: SockRef<'_>) -> Result<()> {
+        match self {
+            Self::Ip(_) => Ok(()),
+            Self::Interface(name) => bind_device(socket, name),
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn bind_device(socket: SockRef<'_>, name: &str) -> Result<()> {
+    socket
+        .bind_device(Some(name.as_bytes()))
+        .map_err(|e| anyhow!("绑定网卡 {} 失败: {}", name, e))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn bind_device(_socket: SockRef<'_>, name: &str) -> Result<()> {
+    Err(anyhow!("按网卡绑定（{}）仅支持 Linux，请改用源 IP", name))
+}
+
+/// 出站源参数，Client 通过 `#[command(flatten)]` 作为命令行参数使用
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct SourceArgs {
+    /// 隧道连接（到节点）使用的源 IP 或网卡名，多出口机器上用于选择出口
+    #[arg(long, env = "OXIPROXY_TUNNEL_SOURCE")]
+    pub tunnel_source: Option<SourceBinding>,
+
+    /// 连接本地服务使用的源 IP 或网卡名，可在代理上单独覆盖
+    #[arg(long, env = "OXIPROXY_LOCAL_SOURCE")]
+    pub local_source: Option<SourceBinding>,
+}
+
+impl SourceArgs {
+    /// 设置为进程的全局出站源（启动时调用一次）
+    pub fn apply(self) {
+        set_tunnel_source(self.tunnel_source);
+        set_local_source(self.local_source);
+    }
+
+    /// 转换为命令行参数（用于启动子进程）
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(source) = &self.tunnel_source {
+            args.extend(["--tunnel-source".to_string(), source.to_string()]);
+        }
+        if let Some(source) = &self.local_source {
+            args.extend(["--local-source".to_string(), source.to_string()]);
+        }
+        args
+    }
+}
+
+static TUNNEL_SOURCE: OnceLock<Option<SourceBinding>> = OnceLock::new();
+static LOCAL_SOURCE: OnceLock<Option<SourceBinding>> = OnceLock::new();
+
+/// 设置隧道连接的源（启动时调用一次，之后的调用被忽略）
+pub fn set_tunnel_source(source: Option<SourceBinding>) {
+    let _ = TUNNEL_SOURCE.set(source);
+}
+
+/// 隧道连接的源，未设置时由系统路由决定
+pub fn tunnel_source() -> Option<&'static SourceBinding> {
+    TUNNEL_SOURCE.get().and_then(Option::as_ref)
+}
+
+/// 设置本地服务连接的默认源（启动时调用一次，之后的调用被忽略）
+pub fn set_local_source(source: Option<SourceBinding>) {
+    let _ = LOCAL_SOURCE.set(source);
+}
+
+/// 本地服务连接的默认源，代理未单独设置时使用
+pub fn local_source() -> Option<&'static SourceBinding> {
+    LOCAL_SOURCE.get().and_then(Option::as_ref)
+}
+
+/// 从 `source` 连接 TCP 目标，`source` 为空时与 `TcpStream::connect` 相同
+pub async fn connect_tcp(target: SocketAddr, source: Option<&SourceBinding>) -> Result<TcpStream> {
+    let Some(source) = source else {
+        return Ok(TcpStream::connect(target).await?);
+    };
+    let socket = if target.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+    source.bind_device(SockRef::from(&socket))?;
+    socket.bind(source.local_addr_for(&target)?)?;
+    Ok(socket.connect(target).await?)
+}
+
+/// 创建用于与 `target` 通信的 UDP 套接字（非阻塞），绑定到 `source`
+pub fn std_udp_socket_for(target: SocketAddr, source: Option<&SourceBinding>) -> Result<std::net::UdpSocket> {
+    let Some(source) = source else {
+        return crate::utils::bind_std_udp_socket(crate::utils::local_wildcard_for(&target));
+    };
+    let socket = crate::utils::bind_std_udp_socket(source.local_addr_for(&target)?)?;
+    source.bind_device(SockRef::from(&socket))?;
+    Ok(socket)
+}
+
+/// [`std_udp_socket_for`] 的 tokio 版本
+pub async fn udp_socket_for(target: SocketAddr, source: Option<&SourceBinding>) -> Result<tokio::net::UdpSocket> {
+    Ok(tokio::net::UdpSocket::from_std(std_udp_socket_for(target, source)?)?)
+}
+
+/// 创建不限定目标地址族的 UDP 套接字（如 QUIC 端点），优先使用双栈套接字
+///
+/// 源为 IP 时只能与同地址族的目标通信。
+pub fn std_udp_socket(source: Option<&SourceBinding>) -> Result<std::net::UdpSocket> {
+    if let Some(SourceBinding::Ip(ip)) = source {
+        return crate::utils::bind_std_udp_socket(SocketAddr::new(*ip, 0));
+    }
+    let socket = match crate::utils::bind_std_udp_socket("[::]:0".parse()?) {
+        Ok(socket) => socket,
+        Err(_) => crate::utils::bind_std_udp_socket("0.0.0.0:0".parse()?)?,
+    };
+    if let Some(source) = source {
+        source.bind_device(SockRef::from(&socket))?;
+    }
+    Ok(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_source_binding() {
+        assert_eq!("192.0.2.1".parse::<SourceBinding>().unwrap(), SourceBinding::Ip("192.0.2.1".parse().unwrap()));
+        assert_eq!("[2001:db8::1]".parse::<SourceBinding>().unwrap(), SourceBinding::Ip("2001:db8::1".parse().unwrap()));
+        assert_eq!("wan1".parse::<SourceBinding>().unwrap(), SourceBinding::Interface("wan1".to_string()));
+        assert!("".parse::<SourceBinding>().is_err());
+        assert!("eth0 eth1".parse::<SourceBinding>().is_err());
+        assert!("a-very-long-interface".parse::<SourceBinding>().is_err());
+    }
+
+    #[test]
+    fn test_address_family_mismatch() {
+        let source = SourceBinding::Ip("192.0.2.1".parse().unwrap());
+        assert!(source.local_addr_for(&"[2001:db8::2]:80".parse().unwrap()).is_err());
+        assert_eq!(
+            source.local_addr_for(&"198.51.100.1:80".parse().unwrap()).unwrap(),
+            "192.0.2.1:0".parse::<SocketAddr>().unwrap()
+        );
+    }
+}
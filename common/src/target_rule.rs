@@ -0,0 +1,257 @@
+//! 目标地址规则
+//!
+//! Controller 与客户端共用的地址 / 端口匹配规则，用于端口黑名单、客户端本地目标白名单等策略。规则格式：
+//!
+//! - `25`、`6000-6100`：端口或端口范围（任意地址）
+//! - `10.0.0.1`、`169.254.0.0/16`、`fd00::/8`：地址或网段（任意端口）
+//! - `10.0.0.0/8:22`、`[fd00::/8]:80-90`：网段 + 端口范围
+
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+/// 端口范围（闭区间）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    pub fn contains(&self, port: u16) -> bool {
+        port >= self.start && port <= self.end
+    }
+}
+
+impl FromStr for PortRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (start, end) = match s.split_once('-') {
+            Some((a, b)) => (a.trim(), b.trim()),
+            None => (s, s),
+        };
+        let start: u16 = start.parse().map_err(|_| anyhow!("无效的端口: {}", s))?;
+        let end: u16 = end.parse().map_err(|_| anyhow!("无效的端口: {}", s))?;
+        if start > end {
+            return Err(anyhow!("起始端口不能大于结束端口: {}", s));
+        }
+        Ok(Self { start, end })
+    }
+}
+
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+/// IP 网段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNet {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // IPv4 映射地址（::ffff:a.b.c.d）按 IPv4 匹配，避免绕过 IPv4 规则
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                prefix_matches(u32::from(net) as u128, u32::from(ip) as u128, self.prefix, 32)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_matches(u128::from(net), u128::from(ip), self.prefix, 128)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(net: u128, ip: u128, prefix: u8, bits: u8) -> bool {
+    if prefix == 0 {
+        return true;
+    }
+    let shift = bits - prefix;
+    (net >> shift) == (ip >> shift)
+}
+
+impl FromStr for IpNet {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (addr, prefix) = match s.split_once('/') {
+            Some((a, p)) => (a, Some(p)),
+            None => (s, None),
+        };
+        let addr: IpAddr = crate::utils::normalize_host(addr)
+            .parse()
+            .map_err(|_| anyhow!("无效的 IP 地址: {}", addr))?;
+        let addr = addr.to_canonical();
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p.trim().parse::<u8>().ok().filter(|p| *p <= max)
+                .ok_or_else(|| anyhow!("无效的网段前缀: {}", s))?,
+            None => max,
+        };
+        Ok(Self { addr, prefix })
+    }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let max = if self.addr.is_ipv4() { 32 } else { 128 };
+        if self.prefix == max {
+            write!(f, "{}", self.addr)
+        } else {
+            write!(f, "{}/{}", self.addr, self.prefix)
+        }
+    }
+}
+
+/// 单条目标规则：网段和端口范围均为空时匹配任意目标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetRule {
+    pub net: Option<IpNet>,
+    pub ports: Option<PortRange>,
+}
+
+impl TargetRule {
+    pub fn matches(&self, target: SocketAddr) -> bool {
+        self.net.is_none_or(|net| net.contains(target.ip()))
+            && self.ports.is_none_or(|ports| ports.contains(target.port()))
+    }
+}
+
+impl FromStr for TargetRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(anyhow!("规则不能为空"));
+        }
+
+        // [IPv6 网段]:端口
+        if let Some(rest) = s.strip_prefix('[') {
+            let (net, ports) = rest.split_once(']').ok_or_else(|| anyhow!("无效的规则: {}", s))?;
+            let ports = match ports.strip_prefix(':') {
+                Some(p) => Some(p.parse()?),
+                None if ports.is_empty() => None,
+                None => return Err(anyhow!("无效的规则: {}", s)),
+            };
+            return Ok(Self { net: Some(net.parse()?), ports });
+        }
+
+        // 纯端口或端口范围
+        if let Ok(ports) = s.parse::<PortRange>() {
+            return Ok(Self { net: None, ports: Some(ports) });
+        }
+
+        // IPv4 网段:端口（IPv6 地址包含多个冒号，不带方括号时视为不限端口）
+        if let Some((net, ports)) = s.rsplit_once(':') {
+            if !net.contains(':') {
+                return Ok(Self { net: Some(net.parse()?), ports: Some(ports.parse()?) });
+            }
+        }
+
+        Ok(Self { net: Some(s.parse()?), ports: None })
+    }
+}
+
+impl fmt::Display for TargetRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.net, self.ports) {
+            (Some(net), Some(ports)) if matches!(net.addr, IpAddr::V6(_)) => write!(f, "[{}]:{}", net, ports),
+            (Some(net), Some(ports)) => write!(f, "{}:{}", net, ports),
+            (Some(net), None) => write!(f, "{}", net),
+            (None, Some(ports)) => write!(f, "{}", ports),
+            (None, None) => write!(f, "*"),
+        }
+    }
+}
+
+/// 一组目标规则
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetRules(Vec<TargetRule>);
+
+impl TargetRules {
+    /// 解析规则列表，任意一条无效即返回错误
+    pub fn parse<S: AsRef<str>>(rules: &[S]) -> Result<Self> {
+        rules
+            .iter()
+            .map(|r| r.as_ref().parse().map_err(|e| anyhow!("无效的目标规则 {}: {}", r.as_ref(), e)))
+            .collect::<Result<Vec<_>>>()
+            .map(Self)
+    }
+
+    /// 解析规则列表，无效的规则记录警告后跳过
+    pub fn parse_lenient<S: AsRef<str>>(rules: &[S]) -> Self {
+        Self(
+            rules
+                .iter()
+                .filter_map(|r| match r.as_ref().parse() {
+                    Ok(rule) => Some(rule),
+                    Err(e) => {
+                        tracing::warn!("忽略无效的目标规则 {}: {}", r.as_ref(), e);
+                        None
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 返回第一条匹配目标地址的规则
+    pub fn find_match(&self, target: SocketAddr) -> Option<&TargetRule> {
+        self.0.iter().find(|rule| rule.matches(target))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_match() {
+        let rule: TargetRule = "25".parse().unwrap();
+        assert!(rule.matches(addr("1.2.3.4:25")));
+        assert!(!rule.matches(addr("1.2.3.4:26")));
+
+        let rule: TargetRule = "169.254.0.0/16".parse().unwrap();
+        assert!(rule.matches(addr("169.254.169.254:80")));
+        assert!(rule.matches(addr("[::ffff:169.254.169.254]:80")));
+        assert!(!rule.matches(addr("169.255.0.1:80")));
+
+        let rule: TargetRule = "10.0.0.0/8:22-23".parse().unwrap();
+        assert!(rule.matches(addr("10.1.2.3:23")));
+        assert!(!rule.matches(addr("10.1.2.3:80")));
+
+        let rule: TargetRule = "fd00::/8".parse().unwrap();
+        assert!(rule.matches(addr("[fd12::1]:443")));
+        assert_eq!(rule.to_string(), "fd00::/8");
+
+        let rule: TargetRule = "[fd00::/8]:80".parse().unwrap();
+        assert!(rule.matches(addr("[fd12::1]:80")));
+        assert!(!rule.matches(addr("[fd12::1]:81")));
+        assert_eq!(rule.to_string(), "[fd00::/8]:80");
+
+        assert!("10.0.0.0/33".parse::<TargetRule>().is_err());
+        assert!("100-20".parse::<TargetRule>().is_err());
+        assert!("example.com".parse::<TargetRule>().is_err());
+    }
+}
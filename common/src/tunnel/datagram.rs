@@ -0,0 +1,107 @@
+//! 隧道流上的 UDP 数据报分帧
+//!
+//! KCP / TCP 隧道的流由 yamux 多路复用，是纯字节流，连续写入的多个数据报可能被合并或拆分。
+//! UDP 代理在这些隧道上为每个数据报加上 2 字节长度前缀（大端），接收端按帧还原数据报边界。
+
+use anyhow::{anyhow, Result};
+
+use super::traits::{TunnelRecvStream, TunnelSendStream};
+
+/// 单个数据报的最大长度
+pub const MAX_DATAGRAM_SIZE: usize = u16::MAX as usize;
+
+/// 写入一个数据报帧
+pub async fn write_datagram(send: &mut (dyn TunnelSendStream + '_), data: &[u8]) -> Result<()> {
+    if data.len() > MAX_DATAGRAM_SIZE {
+        return Err(anyhow!("数据报过大: {} 字节", data.len()));
+    }
+    // 长度和数据合并为一次写入，避免产生额外的小包
+    let mut frame = Vec::with_capacity(2 + data.len());
+    frame.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    frame.extend_from_slice(data);
+    send.write_all(&frame).await
+}
+
+/// 读取一个数据报帧到 `buf`，返回数据报长度；流正常结束时返回 `None`
+///
+/// `buf` 长度应不小于 [`MAX_DATAGRAM_SIZE`]。
+pub async fn read_datagram(recv: &mut (dyn TunnelRecvStream + '_), buf: &mut [u8]) -> Result<Option<usize>> {
+    let mut header = [0u8; 2];
+    match recv.read(&mut header[..1]).await? {
+        Some(1) => {}
+        _ => return Ok(None),
+    }
+    recv.read_exact(&mut header[1..]).await?;
+
+    let len = u16::from_be_bytes(header) as usize;
+    if len > buf.len() {
+        return Err(anyhow!("数据报长度 {} 超出缓冲区大小 {}", len, buf.len()));
+    }
+    recv.read_exact(&mut buf[..len]).await?;
+    Ok(Some(len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    struct VecSend(Vec<u8>);
+
+    #[async_trait]
+    impl TunnelSendStream for VecSend {
+        async fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            self.0.extend_from_slice(buf);
+            Ok(())
+        }
+        async fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+        async fn finish(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// 每次最多返回 3 字节，模拟字节流对数据报的拆分
+    struct ChunkedRecv(std::collections::VecDeque<u8>);
+
+    #[async_trait]
+    impl TunnelRecvStream for ChunkedRecv {
+        async fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+            if self.0.len() < buf.len() {
+                return Err(anyhow!("unexpected eof"));
+            }
+            for b in buf.iter_mut() {
+                *b = self.0.pop_front().unwrap();
+            }
+            Ok(())
+        }
+        async fn read(&mut self, buf: &mut [u8]) -> Result<Option<usize>> {
+            if self.0.is_empty() {
+                return Ok(None);
+            }
+            let n = buf.len().min(3).min(self.0.len());
+            for b in buf[..n].iter_mut() {
+                *b = self.0.pop_front().unwrap();
+            }
+            Ok(Some(n))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_datagram_roundtrip() {
+        let mut send = VecSend(Vec::new());
+        write_datagram(&mut send, b"hello").await.unwrap();
+        write_datagram(&mut send, b"").await.unwrap();
+        write_datagram(&mut send, &[7u8; 1500]).await.unwrap();
+
+        let mut recv = ChunkedRecv(send.0.into_iter().collect());
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+        assert_eq!(read_datagram(&mut recv, &mut buf).await.unwrap(), Some(5));
+        assert_eq!(&buf[..5], b"hello");
+        assert_eq!(read_datagram(&mut recv, &mut buf).await.unwrap(), Some(0));
+        assert_eq!(read_datagram(&mut recv, &mut buf).await.unwrap(), Some(1500));
+        assert!(buf[..1500].iter().all(|&b| b == 7));
+        assert_eq!(read_datagram(&mut recv, &mut buf).await.unwrap(), None);
+    }
+}
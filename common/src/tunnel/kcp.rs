@@ -5,12 +5,15 @@
 //! - `KcpConnection`: 连接包装器（基于 yamux 多路复用）
 //! - `KcpConnector`: 客户端连接器
 //! - `KcpListener`: 服务端监听器
+//!
+//! 连接、流、收发和控制块状态的统计见 `kcp_stats` 模块。
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use futures::{AsyncReadExt, AsyncWriteExt};
 use futures::io::{ReadHalf, WriteHalf};
 use std::net::SocketAddr;
+use std::sync::Arc;
 use std::task::Poll;
 use tokio::sync::{mpsc, oneshot, watch, Mutex};
 use tokio_kcp::{KcpConfig as TokioKcpConfig, KcpListener as TokioKcpListener, KcpStream};
@@ -18,20 +21,21 @@ use tokio_util::compat::TokioAsyncReadCompatExt;
 use tracing::{debug, warn};
 use yamux::{Config as YamuxConfig, Connection as YamuxConnection, Mode, Stream as YamuxStream};
 
+use super::kcp_stats::{ConnectionGuard, ControlBlock, Inspect, Metered, StreamGuard};
 use super::traits::{TunnelConnection, TunnelConnector, TunnelListener, TunnelRecvStream, TunnelSendStream};
 use crate::config::KcpConfig;
-use crate::utils::create_configured_udp_socket;
 
 /// KCP 发送流
 ///
 /// 基于 yamux Stream 拆分后的写半流，与 KcpRecvStream 互不阻塞。
 pub struct KcpSendStream {
     writer: Mutex<WriteHalf<YamuxStream>>,
+    _guard: Arc<StreamGuard>,
 }
 
 impl KcpSendStream {
-    fn new(writer: WriteHalf<YamuxStream>) -> Self {
-        Self { writer: Mutex::new(writer) }
+    fn new(writer: WriteHalf<YamuxStream>, guard: Arc<StreamGuard>) -> Self {
+        Self { writer: Mutex::new(writer), _guard: guard }
     }
 }
 
@@ -61,11 +65,12 @@ impl TunnelSendStream for KcpSendStream {
 /// 基于 yamux Stream 拆分后的读半流，与 KcpSendStream 互不阻塞。
 pub struct KcpRecvStream {
     reader: Mutex<ReadHalf<YamuxStream>>,
+    _guard: Arc<StreamGuard>,
 }
 
 impl KcpRecvStream {
-    fn new(reader: ReadHalf<YamuxStream>) -> Self {
-        Self { reader: Mutex::new(reader) }
+    fn new(reader: ReadHalf<YamuxStream>, guard: Arc<StreamGuard>) -> Self {
+        Self { reader: Mutex::new(reader), _guard: guard }
     }
 }
 
@@ -87,8 +92,15 @@ impl TunnelRecvStream for KcpRecvStream {
     }
 }
 
-/// Compat 包装器，将 tokio KcpStream 转换为 futures AsyncRead/AsyncWrite
-type CompatKcpStream = tokio_util::compat::Compat<KcpStream>;
+/// Compat 包装器，将 tokio KcpStream 转换为 futures AsyncRead/AsyncWrite，并统计收发
+type CompatKcpStream = Metered<tokio_util::compat::Compat<KcpStream>>;
+
+impl Inspect for tokio_util::compat::Compat<KcpStream> {
+    fn control_block(&self) -> Option<ControlBlock> {
+        let socket = self.get_ref().session().kcp_socket().lock();
+        ControlBlock::parse(&format!("{:?}", *socket))
+    }
+}
 
 /// 出站流请求，通过 channel 发送给后台驱动任务
 struct OutboundRequest {
@@ -111,12 +123,13 @@ pub struct KcpConnection {
     _driver_handle: tokio::task::JoinHandle<()>,
     /// 远端地址
     remote_addr: SocketAddr,
+    _guard: ConnectionGuard,
 }
 
 impl KcpConnection {
     /// 创建新的 KCP 连接
     pub fn new(stream: KcpStream, remote_addr: SocketAddr, is_client: bool) -> Self {
-        let compat_stream = stream.compat();
+        let compat_stream = Metered::new(stream.compat());
         let mode = if is_client { Mode::Client } else { Mode::Server };
         let config = YamuxConfig::default();
         let connection = YamuxConnection::new(compat_stream, config, mode);
@@ -138,6 +151,7 @@ impl KcpConnection {
             close_reason_rx,
             _driver_handle: driver_handle,
             remote_addr,
+            _guard: ConnectionGuard::new(),
         }
     }
 }
@@ -246,9 +260,10 @@ impl TunnelConnection for KcpConnection {
             .map_err(|_| anyhow!("connection driver closed"))??;
 
         let (reader, writer) = stream.split();
+        let guard = Arc::new(StreamGuard::new());
         Ok((
-            Box::new(KcpSendStream::new(writer)),
-            Box::new(KcpRecvStream::new(reader)),
+            Box::new(KcpSendStream::new(writer, guard.clone())),
+            Box::new(KcpRecvStream::new(reader, guard)),
         ))
     }
 
@@ -259,9 +274,10 @@ impl TunnelConnection for KcpConnection {
         };
 
         let (reader, writer) = stream.split();
+        let guard = Arc::new(StreamGuard::new());
         Ok((
-            Box::new(KcpSendStream::new(writer)),
-            Box::new(KcpRecvStream::new(reader)),
+            Box::new(KcpSendStream::new(writer, guard.clone())),
+            Box::new(KcpRecvStream::new(reader, guard)),
         ))
     }
 
@@ -278,7 +294,7 @@ impl TunnelConnection for KcpConnection {
             .map_err(|_| anyhow!("connection driver closed"))??;
 
         let (_reader, writer) = stream.split();
-        Ok(Box::new(KcpSendStream::new(writer)))
+        Ok(Box::new(KcpSendStream::new(writer, Arc::new(StreamGuard::new()))))
     }
 
     async fn accept_uni(&self) -> Result<Box<dyn TunnelRecvStream>> {
@@ -288,7 +304,7 @@ impl TunnelConnection for KcpConnection {
         };
 
         let (reader, _writer) = stream.split();
-        Ok(Box::new(KcpRecvStream::new(reader)))
+        Ok(Box::new(KcpRecvStream::new(reader, Arc::new(StreamGuard::new()))))
     }
 
     fn remote_address(&self) -> SocketAddr {
@@ -313,29 +329,14 @@ impl KcpConnector {
         }
     }
 
-    fn build_kcp_config(&self) -> TokioKcpConfig {
-        let mut config = TokioKcpConfig::default();
-        config.nodelay = tokio_kcp::KcpNoDelayConfig {
-            nodelay: self.config.nodelay,
-            interval: self.config.interval as i32,
-            resend: self.config.resend as i32,
-            nc: self.config.nc,
-        };
-        config
-    }
 }
 
 #[async_trait]
 impl TunnelConnector for KcpConnector {
     async fn connect(&self, addr: SocketAddr) -> Result<Box<dyn TunnelConnection>> {
-        let kcp_config = self.build_kcp_config();
+        let kcp_config = build_kcp_config(&self.config);
 
-        let local_addr: SocketAddr = if addr.is_ipv4() {
-            "0.0.0.0:0".parse().unwrap()
-        } else {
-            "[::]:0".parse().unwrap()
-        };
-        let socket = create_configured_udp_socket(local_addr).await?;
+        let socket = crate::source_binding::udp_socket_for(addr, crate::source_binding::tunnel_source()).await?;
 
         let stream = KcpStream::connect_with_socket(&kcp_config, socket, addr).await?;
         Ok(Box::new(KcpConnection::new(stream, addr, true)))
@@ -350,15 +351,19 @@ pub struct KcpListener {
 impl KcpListener {
     /// 创建新的 KCP 监听器
     pub async fn new(bind_addr: SocketAddr, config: Option<KcpConfig>) -> Result<Self> {
-        let kcp_config = build_kcp_config(config);
-        let socket = create_configured_udp_socket(bind_addr).await?;
+        let kcp_config = build_kcp_config(&config.unwrap_or_default());
+        let socket = crate::socket_activation::udp_socket(bind_addr).await?;
         let listener = TokioKcpListener::from_socket(kcp_config, socket).await?;
         Ok(Self { listener: Mutex::new(listener) })
     }
 }
 
-fn build_kcp_config(config: Option<KcpConfig>) -> TokioKcpConfig {
-    let config = config.unwrap_or_default();
+fn build_kcp_config(config: &KcpConfig) -> TokioKcpConfig {
+    if let Err(e) = config.validate() {
+        tracing::warn!("KCP 参数无效（{}），使用默认参数", e);
+        return build_kcp_config(&KcpConfig::default());
+    }
+
     let mut kcp_config = TokioKcpConfig::default();
     kcp_config.nodelay = tokio_kcp::KcpNoDelayConfig {
         nodelay: config.nodelay,
@@ -366,6 +371,8 @@ fn build_kcp_config(config: Option<KcpConfig>) -> TokioKcpConfig {
         resend: config.resend as i32,
         nc: config.nc,
     };
+    kcp_config.wnd_size = (config.snd_wnd, config.rcv_wnd);
+    kcp_config.mtu = config.mtu as usize;
     kcp_config
 }
 
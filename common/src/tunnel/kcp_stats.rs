@@ -0,0 +1,431 @@
+//! KCP 多路复用统计
+//!
+//! 进程内所有 KCP 连接的累计指标，节点通过状态接口上报，便于按数据调整 `KcpConfig`：
+//! - 连接数、当前打开的流和累计打开的流（yamux 多路复用）
+//! - 经多路复用层收发的字节数
+//! - 写入等待：底层 KCP 流暂不接受写入（发送队列已达窗口、等待对端确认）的次数和累计时长
+//! - KCP 控制块状态：超时重传次数、发送的数据分片数、平滑 RTT、已发送未确认的分片数和有效发送窗口
+//!
+//! kcp 库不提供控制块字段的访问方法，只能从 `Kcp` 的 `Debug` 输出中读取，每个连接在收发时最多
+//! 每秒采样一次。RTT 和窗口按连接求和上报（节点之间也直接相加），平均 RTT 和窗口利用率由
+//! [`KcpStats::avg_rtt_ms`] 和 [`KcpStats::window_utilization`] 计算。
+
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use serde::{Deserialize, Serialize};
+
+/// KCP 多路复用统计快照
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KcpStats {
+    /// 当前 KCP 连接数
+    pub connections: u64,
+    /// 当前打开的多路复用流
+    pub streams_open: u64,
+    /// 累计打开的多路复用流
+    pub streams_opened: u64,
+    /// 经多路复用层发送的字节数
+    pub bytes_sent: u64,
+    /// 经多路复用层接收的字节数
+    pub bytes_received: u64,
+    /// 写入因 KCP 发送窗口已满而等待的次数
+    pub send_stalls: u64,
+    /// 写入等待的累计时长（毫秒）
+    pub send_stall_ms: u64,
+    /// 累计超时重传的分片数（不含快速重传）
+    #[serde(default)]
+    pub retransmissions: u64,
+    /// 累计发送的数据分片数（不含重传）
+    #[serde(default)]
+    pub segments_sent: u64,
+    /// 已测得 RTT 的连接数
+    #[serde(default)]
+    pub rtt_connections: u64,
+    /// 这些连接的平滑 RTT 之和（毫秒）
+    #[serde(default)]
+    pub rtt_ms_sum: u64,
+    /// 当前已发送未确认的分片数
+    #[serde(default)]
+    pub inflight_segments: u64,
+    /// 当前有效发送窗口之和（分片数，取 `snd_wnd`、对端接收窗口和拥塞窗口的最小值）
+    #[serde(default)]
+    pub window_segments: u64,
+}
+
+impl KcpStats {
+    /// 各连接平滑 RTT 的平均值（毫秒），还没有测得 RTT 时返回 None
+    pub fn avg_rtt_ms(&self) -> Option<u64> {
+        (self.rtt_connections > 0).then(|| self.rtt_ms_sum / self.rtt_connections)
+    }
+
+    /// 发送窗口利用率（0-1）：已发送未确认的分片数占有效发送窗口的比例
+    pub fn window_utilization(&self) -> Option<f64> {
+        (self.window_segments > 0).then(|| self.inflight_segments as f64 / self.window_segments as f64)
+    }
+
+    /// 超时重传率：重传分片数占发送分片数的比例
+    pub fn retransmission_rate(&self) -> Option<f64> {
+        (self.segments_sent > 0).then(|| self.retransmissions as f64 / self.segments_sent as f64)
+    }
+}
+
+impl std::ops::AddAssign for KcpStats {
+    fn add_assign(&mut self, other: Self) {
+        self.connections += other.connections;
+        self.streams_open += other.streams_open;
+        self.streams_opened += other.streams_opened;
+        self.bytes_sent += other.bytes_sent;
+        self.bytes_received += other.bytes_received;
+        self.send_stalls += other.send_stalls;
+        self.send_stall_ms += other.send_stall_ms;
+        self.retransmissions += other.retransmissions;
+        self.segments_sent += other.segments_sent;
+        self.rtt_connections += other.rtt_connections;
+        self.rtt_ms_sum += other.rtt_ms_sum;
+        self.inflight_segments += other.inflight_segments;
+        self.window_segments += other.window_segments;
+    }
+}
+
+struct Counters {
+    connections: AtomicU64,
+    streams_open: AtomicU64,
+    streams_opened: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    send_stalls: AtomicU64,
+    send_stall_ms: AtomicU64,
+    retransmissions: AtomicU64,
+    segments_sent: AtomicU64,
+    rtt_connections: AtomicU64,
+    rtt_ms_sum: AtomicU64,
+    inflight_segments: AtomicU64,
+    window_segments: AtomicU64,
+}
+
+static COUNTERS: Counters = Counters {
+    connections: AtomicU64::new(0),
+    streams_open: AtomicU64::new(0),
+    streams_opened: AtomicU64::new(0),
+    bytes_sent: AtomicU64::new(0),
+    bytes_received: AtomicU64::new(0),
+    send_stalls: AtomicU64::new(0),
+    send_stall_ms: AtomicU64::new(0),
+    retransmissions: AtomicU64::new(0),
+    segments_sent: AtomicU64::new(0),
+    rtt_connections: AtomicU64::new(0),
+    rtt_ms_sum: AtomicU64::new(0),
+    inflight_segments: AtomicU64::new(0),
+    window_segments: AtomicU64::new(0),
+};
+
+/// 每个连接采样控制块状态的最小间隔
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 进程内所有 KCP 连接的统计
+pub fn kcp_stats() -> KcpStats {
+    KcpStats {
+        connections: COUNTERS.connections.load(Ordering::Relaxed),
+        streams_open: COUNTERS.streams_open.load(Ordering::Relaxed),
+        streams_opened: COUNTERS.streams_opened.load(Ordering::Relaxed),
+        bytes_sent: COUNTERS.bytes_sent.load(Ordering::Relaxed),
+        bytes_received: COUNTERS.bytes_received.load(Ordering::Relaxed),
+        send_stalls: COUNTERS.send_stalls.load(Ordering::Relaxed),
+        send_stall_ms: COUNTERS.send_stall_ms.load(Ordering::Relaxed),
+        retransmissions: COUNTERS.retransmissions.load(Ordering::Relaxed),
+        segments_sent: COUNTERS.segments_sent.load(Ordering::Relaxed),
+        rtt_connections: COUNTERS.rtt_connections.load(Ordering::Relaxed),
+        rtt_ms_sum: COUNTERS.rtt_ms_sum.load(Ordering::Relaxed),
+        inflight_segments: COUNTERS.inflight_segments.load(Ordering::Relaxed),
+        window_segments: COUNTERS.window_segments.load(Ordering::Relaxed),
+    }
+}
+
+/// 一个 KCP 连接的控制块状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct ControlBlock {
+    /// 下一个数据分片的序号，即已发送的数据分片数
+    snd_nxt: u32,
+    /// 最早未确认的分片序号
+    snd_una: u32,
+    /// 超时重传次数
+    xmit: u32,
+    /// 平滑 RTT（毫秒），还没有测得时为 0
+    srtt: u32,
+    /// 有效发送窗口（分片数）
+    window: u32,
+}
+
+impl ControlBlock {
+    /// 从 `Kcp`（或包含它的 `KcpSocket`）的 `Debug` 输出中读取控制块状态
+    pub(super) fn parse(debug: &str) -> Option<Self> {
+        let kcp = &debug[debug.find("Kcp {")?..];
+        let value = |name: &str| {
+            let pattern = format!(" {}: ", name);
+            let rest = &kcp[kcp.find(&pattern)? + pattern.len()..];
+            let end = rest.find([',', ' ', '}']).unwrap_or(rest.len());
+            Some(&rest[..end])
+        };
+        let number = |name: &str| value(name)?.parse::<u32>().ok();
+
+        let mut window = number("snd_wnd")?.min(number("rmt_wnd")?);
+        if value("nocwnd")? == "false" {
+            window = window.min(number("cwnd")?);
+        }
+        Some(Self {
+            snd_nxt: number("snd_nxt")?,
+            snd_una: number("snd_una")?,
+            xmit: number("xmit")?,
+            srtt: number("rx_srtt")?,
+            window,
+        })
+    }
+
+    fn inflight(&self) -> u32 {
+        self.snd_nxt.wrapping_sub(self.snd_una)
+    }
+}
+
+/// 可以读取 KCP 控制块状态的底层流
+pub(super) trait Inspect {
+    fn control_block(&self) -> Option<ControlBlock>;
+}
+
+/// 连接存活期间计入连接数
+pub(super) struct ConnectionGuard;
+
+impl ConnectionGuard {
+    pub(super) fn new() -> Self {
+        COUNTERS.connections.fetch_add(1, Ordering::Relaxed);
+        Self
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        COUNTERS.connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 由流的读写两半共享，两半都释放后计为关闭
+pub(super) struct StreamGuard;
+
+impl StreamGuard {
+    pub(super) fn new() -> Self {
+        COUNTERS.streams_open.fetch_add(1, Ordering::Relaxed);
+        COUNTERS.streams_opened.fetch_add(1, Ordering::Relaxed);
+        Self
+    }
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        COUNTERS.streams_open.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 统计收发字节数、写入等待和控制块状态的 KCP 流包装
+pub(super) struct Metered<T> {
+    inner: T,
+    /// 当前这次写入等待的开始时间
+    stalled_since: Option<Instant>,
+    /// 上次采样的时间和控制块状态，连接关闭时从汇总中减去它贡献的 RTT 和窗口
+    sampled: Option<(Instant, ControlBlock)>,
+}
+
+impl<T> Metered<T> {
+    pub(super) fn new(inner: T) -> Self {
+        Self { inner, stalled_since: None, sampled: None }
+    }
+}
+
+/// 连接对 RTT 和窗口汇总的贡献
+fn gauges(cb: &ControlBlock) -> [(&'static AtomicU64, u64); 4] {
+    [
+        (&COUNTERS.rtt_connections, (cb.srtt > 0) as u64),
+        (&COUNTERS.rtt_ms_sum, cb.srtt as u64),
+        (&COUNTERS.inflight_segments, cb.inflight() as u64),
+        (&COUNTERS.window_segments, cb.window as u64),
+    ]
+}
+
+impl<T: Inspect> Metered<T> {
+    fn sample(&mut self) {
+        if self.sampled.is_some_and(|(at, _)| at.elapsed() < SAMPLE_INTERVAL) {
+            return;
+        }
+        let Some(cb) = self.inner.control_block() else {
+            return;
+        };
+        let last = self.sampled.map(|(_, last)| last).unwrap_or_default();
+        COUNTERS.retransmissions.fetch_add(cb.xmit.wrapping_sub(last.xmit) as u64, Ordering::Relaxed);
+        COUNTERS.segments_sent.fetch_add(cb.snd_nxt.wrapping_sub(last.snd_nxt) as u64, Ordering::Relaxed);
+        for ((counter, old), (_, new)) in gauges(&last).into_iter().zip(gauges(&cb)) {
+            counter.fetch_add(new, Ordering::Relaxed);
+            counter.fetch_sub(old, Ordering::Relaxed);
+        }
+        self.sampled = Some((Instant::now(), cb));
+    }
+}
+
+impl<T> Drop for Metered<T> {
+    fn drop(&mut self) {
+        if let Some((_, cb)) = self.sampled.take() {
+            for (counter, value) in gauges(&cb) {
+                counter.fetch_sub(value, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl<T: AsyncRead + Inspect + Unpin> AsyncRead for Metered<T> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(n)) = poll {
+            COUNTERS.bytes_received.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        this.sample();
+        poll
+    }
+}
+
+impl<T: AsyncWrite + Inspect + Unpin> AsyncWrite for Metered<T> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_write(cx, buf);
+        match &poll {
+            Poll::Pending => {
+                if this.stalled_since.is_none() {
+                    this.stalled_since = Some(Instant::now());
+                    COUNTERS.send_stalls.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            Poll::Ready(result) => {
+                if let Some(since) = this.stalled_since.take() {
+                    COUNTERS.send_stall_ms.fetch_add(since.elapsed().as_millis() as u64, Ordering::Relaxed);
+                }
+                if let Ok(n) = result {
+                    COUNTERS.bytes_sent.fetch_add(*n as u64, Ordering::Relaxed);
+                }
+            }
+        }
+        this.sample();
+        poll
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{AsyncReadExt, AsyncWriteExt};
+
+    impl<T> Inspect for futures::io::Cursor<T> {
+        fn control_block(&self) -> Option<ControlBlock> {
+            None
+        }
+    }
+
+    #[test]
+    fn parses_control_block() {
+        let debug = "KcpSocket { kcp: Kcp { conv: 1, mtu: 1400, mss: 1376, state: 0, snd_una: 90, \
+            snd_nxt: 100, rcv_nxt: 7, ssthresh: 2, rx_rttval: 3, rx_srtt: 42, rx_rto: 100, rx_minrto: 30, \
+            snd_wnd: 256, rcv_wnd: 256, rmt_wnd: 128, cwnd: 32, probe: 0, current: 1, interval: 10, \
+            ts_flush: 1, xmit: 5, nodelay: true, updated: true, ts_probe: 0, probe_wait: 0, dead_link: 20, \
+            incr: 0, snd_queue.len: 0, rcv_queue.len: 0, snd_buf.len: 10, rcv_buf.len: 0, acklist.len: 0, \
+            buf.len: 0, fastresend: 2, fastlimit: 5, nocwnd: false, stream: true, input_conv: false }, \
+            last_update: Instant { .. }, closed: false }";
+        let cb = ControlBlock::parse(debug).unwrap();
+        assert_eq!(
+            cb,
+            ControlBlock { snd_nxt: 100, snd_una: 90, xmit: 5, srtt: 42, window: 32 }
+        );
+        assert_eq!(cb.inflight(), 10);
+
+        // 关闭拥塞控制时不受拥塞窗口限制
+        let cb = ControlBlock::parse(&debug.replace("nocwnd: false", "nocwnd: true")).unwrap();
+        assert_eq!(cb.window, 128);
+
+        assert_eq!(ControlBlock::parse("KcpSocket { closed: false }"), None);
+    }
+
+    #[tokio::test]
+    async fn samples_real_kcp_stream() {
+        use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+        let config = tokio_kcp::KcpConfig::default();
+        let mut listener = tokio_kcp::KcpListener::bind(config, "127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 5];
+            stream.read_exact(&mut buf).await.unwrap();
+            stream.write_all(&buf).await.unwrap();
+            stream.flush().await.unwrap();
+            // 等待客户端读取完毕后再关闭
+            let _ = stream.read(&mut buf).await;
+        });
+
+        let mut stream = tokio_kcp::KcpStream::connect(&config, addr).await.unwrap();
+        stream.write_all(b"hello").await.unwrap();
+        stream.flush().await.unwrap();
+        let mut buf = [0u8; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+
+        // 依赖 kcp 库的 Debug 输出格式，升级 kcp / tokio_kcp 后由此测试发现格式变化
+        let compat = tokio_util::compat::TokioAsyncReadCompatExt::compat(stream);
+        let cb = compat.control_block().expect("无法从 KcpSocket 的 Debug 输出中读取控制块");
+        assert!(cb.snd_nxt >= 1);
+        assert!(cb.window > 0);
+        drop(compat);
+        server.abort();
+    }
+
+    #[test]
+    fn derived_stats() {
+        let stats = KcpStats {
+            retransmissions: 5,
+            segments_sent: 100,
+            rtt_connections: 2,
+            rtt_ms_sum: 90,
+            inflight_segments: 16,
+            window_segments: 64,
+            ..Default::default()
+        };
+        assert_eq!(stats.avg_rtt_ms(), Some(45));
+        assert_eq!(stats.window_utilization(), Some(0.25));
+        assert_eq!(stats.retransmission_rate(), Some(0.05));
+        assert_eq!(KcpStats::default().avg_rtt_ms(), None);
+    }
+
+    #[tokio::test]
+    async fn counts_bytes_and_streams() {
+        let before = kcp_stats();
+        let mut stream = Metered::new(futures::io::Cursor::new(Vec::new()));
+        stream.write_all(b"hello").await.unwrap();
+        stream.inner.set_position(0);
+        let mut buf = [0u8; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+
+        let guard = StreamGuard::new();
+        let during = kcp_stats();
+        drop(guard);
+
+        // 计数器为进程内共享，其他测试可能同时计数
+        assert!(during.bytes_sent >= before.bytes_sent + 5);
+        assert!(during.bytes_received >= before.bytes_received + 5);
+        assert!(during.streams_opened > before.streams_opened);
+    }
+}
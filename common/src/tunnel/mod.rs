@@ -7,10 +7,14 @@ mod traits;
 mod protocol;
 mod quic;
 mod kcp;
+mod kcp_stats;
 mod tcp;
+mod datagram;
 
 pub use traits::*;
 pub use protocol::*;
 pub use quic::*;
 pub use kcp::*;
+pub use kcp_stats::{kcp_stats, KcpStats};
 pub use tcp::*;
+pub use datagram::*;
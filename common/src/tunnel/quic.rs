@@ -5,6 +5,12 @@
 //! - `QuicConnection`: 连接包装器
 //! - `QuicConnector`: 客户端连接器
 //! - `QuicListener`: 服务端监听器
+//!
+//! 客户端连接器在进程内共享 TLS 会话票据，断线重连时使用 0-RTT 恢复会话。
+//! 0-RTT 数据可能被重放，因此握手完成前只有 `open_bi_early` 打开的认证流可以发送数据
+//! （认证问候不含凭据，节点在握手完成后才接受连接并下发新的挑战，重放的数据包无法完成握手），
+//! 心跳等 `open_bi` 打开的双向流以及节点发起的代理流都在握手完成后才传输数据。
+//! 节点拒绝 0-RTT 时连接继续以 1-RTT 使用，客户端在新的流上重新认证。
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -14,11 +20,69 @@ use quinn::{
 };
 use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 use tokio::io::AsyncWriteExt;
+use tokio::sync::watch;
+use tracing::{debug, warn};
 
 use super::traits::{TunnelConnection, TunnelConnector, TunnelListener, TunnelRecvStream, TunnelSendStream};
+use crate::config::{CongestionController, QuicConfig};
+
+/// 根据 QUIC 传输参数构建 quinn 传输配置（客户端和服务端共用）
+pub fn build_transport_config(config: &QuicConfig) -> Result<TransportConfig> {
+    let mut transport = TransportConfig::default();
+    transport.max_concurrent_bidi_streams(VarInt::from_u32(config.max_concurrent_streams));
+    transport.keep_alive_interval(Some(Duration::from_secs(config.keep_alive_interval)));
+    transport.max_idle_timeout(Some(Duration::from_secs(config.idle_timeout).try_into()?));
+
+    if let Some(window) = config.stream_receive_window {
+        transport.stream_receive_window(VarInt::from_u32(window));
+    }
+    if let Some(window) = config.receive_window {
+        transport.receive_window(VarInt::from_u32(window));
+    }
+    if let Some(window) = config.send_window {
+        transport.send_window(window);
+    }
+
+    if let Some(mtu) = config.initial_mtu {
+        transport.initial_mtu(mtu);
+    }
+    if !config.mtu_discovery {
+        transport.mtu_discovery_config(None);
+    } else if let Some(mtu) = config.max_mtu {
+        let mut discovery = quinn::MtuDiscoveryConfig::default();
+        discovery.upper_bound(mtu);
+        transport.mtu_discovery_config(Some(discovery));
+    }
+
+    match config.congestion_controller {
+        CongestionController::Cubic => {
+            let mut cc = quinn::congestion::CubicConfig::default();
+            if let Some(window) = config.initial_window {
+                cc.initial_window(window);
+            }
+            transport.congestion_controller_factory(Arc::new(cc));
+        }
+        CongestionController::Bbr => {
+            let mut cc = quinn::congestion::BbrConfig::default();
+            if let Some(window) = config.initial_window {
+                cc.initial_window(window);
+            }
+            transport.congestion_controller_factory(Arc::new(cc));
+        }
+        CongestionController::NewReno => {
+            let mut cc = quinn::congestion::NewRenoConfig::default();
+            if let Some(window) = config.initial_window {
+                cc.initial_window(window);
+            }
+            transport.congestion_controller_factory(Arc::new(cc));
+        }
+    }
+
+    Ok(transport)
+}
 
 /// QUIC 发送流包装器
 pub struct QuicSendStream {
@@ -77,12 +141,46 @@ impl TunnelRecvStream for QuicRecvStream {
 /// QUIC 连接包装器
 pub struct QuicConnection {
     inner: quinn::Connection,
+    /// 0-RTT 连接的握手状态：`None` 表示握手未完成，`Some(accepted)` 表示已完成
+    handshake: Option<watch::Receiver<Option<bool>>>,
 }
 
 impl QuicConnection {
-    /// 创建新的 QUIC 连接包装器
+    /// 创建新的 QUIC 连接包装器（握手已完成）
     pub fn new(inner: quinn::Connection) -> Self {
-        Self { inner }
+        Self { inner, handshake: None }
+    }
+
+    /// 创建 0-RTT 连接包装器
+    ///
+    /// 服务端拒绝 0-RTT 时，握手完成前发送的数据不会送达对端，但连接在握手完成后仍然可用。
+    pub fn with_zero_rtt(inner: quinn::Connection, accepted: quinn::ZeroRttAccepted) -> Self {
+        let (tx, rx) = watch::channel(None);
+        let remote = inner.remote_address();
+        tokio::spawn(async move {
+            let accepted = accepted.await;
+            if accepted {
+                debug!("0-RTT 数据已被 {} 接受", remote);
+            } else {
+                warn!("0-RTT 数据被 {} 拒绝，握手完成后以 1-RTT 继续", remote);
+            }
+            let _ = tx.send(Some(accepted));
+        });
+        Self { inner, handshake: Some(rx) }
+    }
+
+    /// 等待握手完成，返回 0-RTT 数据是否被接受；之后发送的数据不再是可重放的 0-RTT 数据
+    async fn wait_handshake(&self) -> Result<bool> {
+        let Some(handshake) = &self.handshake else {
+            return Ok(true);
+        };
+        let mut handshake = handshake.clone();
+        let accepted = handshake
+            .wait_for(|state| state.is_some())
+            .await
+            .map_err(|_| anyhow::anyhow!("QUIC 握手未完成，连接已关闭"))?
+            .unwrap_or(false);
+        Ok(accepted)
     }
 
     /// 获取内部 quinn::Connection 引用
@@ -94,6 +192,16 @@ impl QuicConnection {
 #[async_trait]
 impl TunnelConnection for QuicConnection {
     async fn open_bi(&self) -> Result<(Box<dyn TunnelSendStream>, Box<dyn TunnelRecvStream>)> {
+        // 双向流可能承载非幂等的请求，不允许在 0-RTT 阶段发送
+        self.wait_handshake().await?;
+        let (send, recv) = self.inner.open_bi().await?;
+        Ok((
+            Box::new(QuicSendStream::new(send)),
+            Box::new(QuicRecvStream::new(recv)),
+        ))
+    }
+
+    async fn open_bi_early(&self) -> Result<(Box<dyn TunnelSendStream>, Box<dyn TunnelRecvStream>)> {
         let (send, recv) = self.inner.open_bi().await?;
         Ok((
             Box::new(QuicSendStream::new(send)),
@@ -101,6 +209,11 @@ impl TunnelConnection for QuicConnection {
         ))
     }
 
+    async fn early_data_accepted(&self) -> bool {
+        // 握手失败时连接已关闭，后续操作会返回错误
+        self.wait_handshake().await.unwrap_or(true)
+    }
+
     async fn accept_bi(&self) -> Result<(Box<dyn TunnelSendStream>, Box<dyn TunnelRecvStream>)> {
         let (send, recv) = self.inner.accept_bi().await?;
         Ok((
@@ -128,6 +241,14 @@ impl TunnelConnection for QuicConnection {
     }
 }
 
+/// 进程内共享的 TLS 会话票据缓存
+fn session_cache() -> Arc<rustls::client::ClientSessionMemoryCache> {
+    static CACHE: OnceLock<Arc<rustls::client::ClientSessionMemoryCache>> = OnceLock::new();
+    CACHE
+        .get_or_init(|| Arc::new(rustls::client::ClientSessionMemoryCache::new(256)))
+        .clone()
+}
+
 /// QUIC 客户端连接器
 ///
 /// 用于客户端连接到 QUIC 服务器，支持自签名证书（跳过验证）。
@@ -138,25 +259,28 @@ pub struct QuicConnector {
 impl QuicConnector {
     /// 创建新的 QUIC 连接器
     ///
-    /// 配置了默认的传输参数和证书验证（跳过验证用于开发环境）。
-    pub fn new() -> Result<Self> {
+    /// 使用节点下发的传输参数（未设置时使用默认参数），跳过证书验证（用于开发环境）。
+    pub fn new(config: Option<QuicConfig>) -> Result<Self> {
         // 创建传输配置
-        let mut transport_config = TransportConfig::default();
+        let mut transport_config = build_transport_config(&config.unwrap_or_default())?;
         transport_config.max_concurrent_uni_streams(0u32.into());
-        transport_config.keep_alive_interval(Some(Duration::from_secs(5)));
-        transport_config.max_idle_timeout(Some(Duration::from_secs(60).try_into()?));
 
         // 创建客户端配置（跳过证书验证）
-        let crypto = rustls::ClientConfig::builder()
+        let mut crypto = rustls::ClientConfig::builder()
             .dangerous()
             .with_custom_certificate_verifier(Arc::new(SkipVerification))
             .with_no_client_auth();
+        // 共享会话票据缓存，重新创建连接器后仍可使用 0-RTT
+        crypto.resumption = rustls::client::Resumption::store(session_cache());
+        crypto.enable_early_data = true;
 
         let mut client_config = ClientConfig::new(Arc::new(QuicClientConfig::try_from(crypto)?));
         client_config.transport_config(Arc::new(transport_config));
 
-        // 创建 QUIC 端点
-        let mut endpoint = Endpoint::client("0.0.0.0:0".parse()?)?;
+        // 创建 QUIC 端点：优先使用双栈套接字，以便同时连接 IPv4 和 IPv6 节点
+        let socket = crate::source_binding::std_udp_socket(crate::source_binding::tunnel_source())?;
+        let runtime = quinn::default_runtime().ok_or_else(|| anyhow::anyhow!("未找到 QUIC 异步运行时"))?;
+        let mut endpoint = Endpoint::new(quinn::EndpointConfig::default(), None, socket, runtime)?;
         endpoint.set_default_client_config(client_config);
 
         Ok(Self { endpoint })
@@ -166,8 +290,17 @@ impl QuicConnector {
 #[async_trait]
 impl TunnelConnector for QuicConnector {
     async fn connect(&self, addr: SocketAddr) -> Result<Box<dyn TunnelConnection>> {
-        let conn = self.endpoint.connect(addr, "oxiproxy")?.await?;
-        Ok(Box::new(QuicConnection::new(conn)))
+        let connecting = self.endpoint.connect(addr, "oxiproxy")?;
+        match connecting.into_0rtt() {
+            Ok((conn, accepted)) => {
+                debug!("使用 0-RTT 恢复到 {} 的会话", addr);
+                Ok(Box::new(QuicConnection::with_zero_rtt(conn, accepted)))
+            }
+            Err(connecting) => {
+                let conn = connecting.await?;
+                Ok(Box::new(QuicConnection::new(conn)))
+            }
+        }
     }
 }
 
@@ -185,21 +318,15 @@ impl QuicListener {
     /// * `bind_addr` - 绑定地址
     /// * `cert` - TLS 证书
     /// * `key` - TLS 私钥
-    /// * `idle_timeout` - 空闲超时时间（秒）
-    /// * `max_streams` - 最大并发流数
-    /// * `keep_alive_interval` - 心跳间隔（秒）
+    /// * `config` - 传输参数
     pub fn new(
         bind_addr: SocketAddr,
         cert: CertificateDer<'static>,
         key: PrivateKeyDer<'static>,
-        idle_timeout: u64,
-        max_streams: u32,
-        keep_alive_interval: u64,
+        config: &QuicConfig,
     ) -> Result<Self> {
-        let mut transport_config = TransportConfig::default();
-        transport_config.max_concurrent_uni_streams(VarInt::from_u32(max_streams));
-        transport_config.keep_alive_interval(Some(Duration::from_secs(keep_alive_interval)));
-        transport_config.max_idle_timeout(Some(Duration::from_secs(idle_timeout).try_into()?));
+        let mut transport_config = build_transport_config(config)?;
+        transport_config.max_concurrent_uni_streams(VarInt::from_u32(config.max_concurrent_streams));
 
         let mut server_config = ServerConfig::with_single_cert(
             vec![cert],
@@ -207,7 +334,9 @@ impl QuicListener {
         )?;
         server_config.transport_config(Arc::new(transport_config));
 
-        let endpoint = Endpoint::server(server_config, bind_addr)?;
+        let socket = crate::utils::bind_std_udp_socket(bind_addr)?;
+        let runtime = quinn::default_runtime().ok_or_else(|| anyhow::anyhow!("未找到 QUIC 异步运行时"))?;
+        let endpoint = Endpoint::new(quinn::EndpointConfig::default(), Some(server_config), socket, runtime)?;
 
         Ok(Self { endpoint })
     }
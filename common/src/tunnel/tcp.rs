@@ -291,7 +291,7 @@ impl TcpTunnelConnector {
 #[async_trait]
 impl TunnelConnector for TcpTunnelConnector {
     async fn connect(&self, addr: SocketAddr) -> Result<Box<dyn TunnelConnection>> {
-        let stream = tokio::net::TcpStream::connect(addr).await?;
+        let stream = crate::source_binding::connect_tcp(addr, crate::source_binding::tunnel_source()).await?;
         stream.set_nodelay(true)?;
         Ok(Box::new(TcpTunnelConnection::new(stream, addr, true)))
     }
@@ -304,7 +304,7 @@ pub struct TcpTunnelListener {
 
 impl TcpTunnelListener {
     pub async fn new(bind_addr: SocketAddr) -> Result<Self> {
-        let listener = tokio::net::TcpListener::bind(bind_addr).await?;
+        let listener = crate::socket_activation::tcp_listener(bind_addr)?;
         Ok(Self { listener })
     }
 }
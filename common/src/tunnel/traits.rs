@@ -65,6 +65,22 @@ pub trait TunnelConnection: Send + Sync {
     /// 返回 (发送流, 接收流) 元组
     async fn open_bi(&self) -> Result<(Box<dyn TunnelSendStream>, Box<dyn TunnelRecvStream>)>;
 
+    /// 打开一个可以在 0-RTT 阶段发送数据的双向流
+    ///
+    /// 数据可能被重放，只能用于可安全重放的请求（如认证握手的问候）。
+    /// 不支持 0-RTT 的协议等同于 [`open_bi`](Self::open_bi)。
+    async fn open_bi_early(&self) -> Result<(Box<dyn TunnelSendStream>, Box<dyn TunnelRecvStream>)> {
+        self.open_bi().await
+    }
+
+    /// 等待握手完成，返回 0-RTT 数据是否被对端接受
+    ///
+    /// 被拒绝时 [`open_bi_early`](Self::open_bi_early) 打开的流已失效，连接本身仍可用，
+    /// 需要在新的流上重新发送请求。未使用 0-RTT 的连接返回 `true`。
+    async fn early_data_accepted(&self) -> bool {
+        true
+    }
+
     /// 接受一个双向流
     ///
     /// 等待对端打开一个双向流。
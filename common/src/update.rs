@@ -0,0 +1,81 @@
+//! 自更新签名校验
+//!
+//! 发布产物使用 [zipsign](https://github.com/Kijewski/zipsign)（ed25519ph）签名，
+//! 校验公钥在编译时通过环境变量 `OXIPROXY_UPDATE_PUBLIC_KEYS` 内置
+//! （十六进制编码，多个公钥以逗号分隔，便于轮换密钥）。
+//! 未内置公钥的构建拒绝自更新，避免下载未经验证的二进制替换自身。
+
+use anyhow::{anyhow, Result};
+
+/// ed25519 公钥长度
+pub const PUBLIC_KEY_LENGTH: usize = 32;
+
+/// 编译时内置的更新签名公钥
+const BAKED_PUBLIC_KEYS: Option<&str> = option_env!("OXIPROXY_UPDATE_PUBLIC_KEYS");
+
+/// 获取用于校验更新包签名的公钥
+pub fn verifying_keys() -> Result<Vec<[u8; PUBLIC_KEY_LENGTH]>> {
+    let raw = BAKED_PUBLIC_KEYS
+        .ok_or_else(|| anyhow!("此构建未内置更新签名公钥，拒绝下载未经签名验证的二进制"))?;
+    parse_public_keys(raw)
+}
+
+/// 解析以逗号分隔的十六进制公钥列表
+pub fn parse_public_keys(raw: &str) -> Result<Vec<[u8; PUBLIC_KEY_LENGTH]>> {
+    let keys = raw
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(parse_public_key)
+        .collect::<Result<Vec<_>>>()?;
+
+    if keys.is_empty() {
+        return Err(anyhow!("更新签名公钥列表为空"));
+    }
+    Ok(keys)
+}
+
+fn parse_public_key(hex: &str) -> Result<[u8; PUBLIC_KEY_LENGTH]> {
+    if hex.len() != PUBLIC_KEY_LENGTH * 2 {
+        return Err(anyhow!("更新签名公钥长度无效: {}", hex));
+    }
+    // 先校验全部为 ASCII 十六进制字符，多字节字符会让下面按字节切片时越过字符边界
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(anyhow!("更新签名公钥不是有效的十六进制: {}", hex));
+    }
+
+    let mut key = [0u8; PUBLIC_KEY_LENGTH];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| anyhow!("更新签名公钥不是有效的十六进制: {}", hex))?;
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_public_keys() {
+        let a = "00".repeat(32);
+        let b = "ff".repeat(32);
+        let keys = parse_public_keys(&format!("{}, {}", a, b)).unwrap();
+        assert_eq!(keys, vec![[0u8; 32], [0xffu8; 32]]);
+    }
+
+    #[test]
+    fn test_parse_public_keys_rejects_invalid() {
+        assert!(parse_public_keys("").is_err());
+        assert!(parse_public_keys("abcd").is_err());
+        assert!(parse_public_keys(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_parse_public_keys_rejects_multibyte() {
+        // 「é」占两个字节，总字节数仍为 64
+        let key = format!("{}é{}", "0".repeat(31), "0".repeat(31));
+        assert_eq!(key.len(), 64);
+        assert!(parse_public_keys(&key).is_err());
+    }
+}
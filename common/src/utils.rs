@@ -1,7 +1,81 @@
 use anyhow::Result;
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::OnceLock;
 use socket2::{Socket, Domain, Type, Protocol};
 
+/// 是否启用 IPv6 双栈监听（环境变量 `OXIPROXY_IPV6`，默认关闭）
+pub fn ipv6_enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| crate::env::parse::<bool>("OXIPROXY_IPV6").unwrap_or(false))
+}
+
+/// 通配监听地址：启用 IPv6 时为 `[::]:port`（双栈，同时接受 IPv4），否则为 `0.0.0.0:port`
+pub fn wildcard_addr(port: u16) -> SocketAddr {
+    if ipv6_enabled() {
+        SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), port)
+    } else {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port)
+    }
+}
+
+/// 与目标地址同协议族的本地通配地址（端口由系统分配）
+pub fn local_wildcard_for(target: &SocketAddr) -> SocketAddr {
+    if target.is_ipv4() {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0)
+    } else {
+        SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0)
+    }
+}
+
+/// 去掉 IPv6 地址两侧的方括号，例如 `[::1]` → `::1`
+pub fn normalize_host(host: &str) -> &str {
+    let host = host.trim();
+    host.strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host)
+}
+
+/// 拼接主机和端口，IPv6 地址自动加方括号，例如 (`::1`, 80) → `[::1]:80`
+pub fn join_host_port(host: &str, port: u16) -> String {
+    let host = normalize_host(host);
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// 用于展示的访客地址：双栈监听收到的 IPv4 映射地址（`::ffff:a.b.c.d`）还原为 IPv4
+pub fn display_addr(addr: SocketAddr) -> SocketAddr {
+    SocketAddr::new(addr.ip().to_canonical(), addr.port())
+}
+
+/// 通配 IPv6 地址关闭 IPV6_V6ONLY，使同一个套接字同时接受 IPv4 连接
+fn enable_dual_stack(socket: &Socket, addr: &SocketAddr) -> Result<()> {
+    if let SocketAddr::V6(v6) = addr {
+        if v6.ip().is_unspecified() {
+            socket.set_only_v6(false)?;
+        }
+    }
+    Ok(())
+}
+
+/// 创建 TCP 监听器，通配 IPv6 地址自动启用双栈
+pub fn bind_tcp_listener(addr: SocketAddr) -> Result<tokio::net::TcpListener> {
+    let domain = if addr.is_ipv4() { Domain::IPV4 } else { Domain::IPV6 };
+    let socket = Socket::new(domain, Type::STREAM, Some(Protocol::TCP))?;
+
+    enable_dual_stack(&socket, &addr)?;
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+
+    let std_listener: std::net::TcpListener = socket.into();
+    Ok(tokio::net::TcpListener::from_std(std_listener)?)
+}
+
 #[cfg(windows)]
 fn apply_windows_udp_fix(socket: &Socket) -> Result<()> {
     use std::os::windows::io::AsRawSocket;
@@ -32,10 +106,12 @@ fn apply_windows_udp_fix(socket: &Socket) -> Result<()> {
     Ok(())
 }
 
-pub async fn create_configured_udp_socket(addr: SocketAddr) -> Result<tokio::net::UdpSocket> {
+/// 创建标准库 UDP 套接字（非阻塞），通配 IPv6 地址自动启用双栈
+pub fn bind_std_udp_socket(addr: SocketAddr) -> Result<std::net::UdpSocket> {
     let domain = if addr.is_ipv4() { Domain::IPV4 } else { Domain::IPV6 };
     let socket = Socket::new(domain, Type::DGRAM, Some(Protocol::UDP))?;
 
+    enable_dual_stack(&socket, &addr)?;
     socket.set_nonblocking(true)?;
 
     #[cfg(windows)]
@@ -49,7 +125,33 @@ pub async fn create_configured_udp_socket(addr: SocketAddr) -> Result<tokio::net
 
     socket.bind(&addr.into())?;
 
-    let std_socket: std::net::UdpSocket = socket.into();
+    Ok(socket.into())
+}
+
+pub async fn create_configured_udp_socket(addr: SocketAddr) -> Result<tokio::net::UdpSocket> {
+    let std_socket = bind_std_udp_socket(addr)?;
     let tokio_socket = tokio::net::UdpSocket::from_std(std_socket)?;
     Ok(tokio_socket)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_host_port() {
+        assert_eq!(join_host_port("127.0.0.1", 80), "127.0.0.1:80");
+        assert_eq!(join_host_port("::1", 80), "[::1]:80");
+        assert_eq!(join_host_port("[2001:db8::1]", 8080), "[2001:db8::1]:8080");
+        assert_eq!(join_host_port("example.com", 443), "example.com:443");
+        assert!(join_host_port("fe80::1", 22).parse::<SocketAddr>().is_ok());
+    }
+
+    #[test]
+    fn test_display_addr() {
+        let mapped: SocketAddr = "[::ffff:192.0.2.1]:1234".parse().unwrap();
+        assert_eq!(display_addr(mapped).to_string(), "192.0.2.1:1234");
+        let v6: SocketAddr = "[2001:db8::1]:1234".parse().unwrap();
+        assert_eq!(display_addr(v6), v6);
+    }
+}
@@ -0,0 +1,117 @@
+//! Agent 连接接入限速
+//!
+//! Controller 重启后所有节点和客户端会在短时间内重连，每个连接都要查询数据库、推送配置。
+//! 这里按 GCRA（通用信元速率算法）为新的 Agent 连接分配接入时间：突发范围内的连接立即处理，
+//! 超出的连接按速率排队等待，需要等待的时间超过上限时直接拒绝，由 Agent 稍后重试。
+//!
+//! 速率由 `OXIPROXY_AGENT_ACCEPT_RATE`（每秒连接数，默认 50，0 表示不限速）控制，突发量与速率相同。
+
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// 默认每秒接入的 Agent 连接数
+const DEFAULT_RATE: u32 = 50;
+/// 单个连接最长排队时间
+const MAX_WAIT: Duration = Duration::from_secs(10);
+
+pub struct AcceptLimiter {
+    /// 相邻两个连接的间隔
+    interval: Duration,
+    /// 允许的突发量对应的时间
+    tolerance: Duration,
+    max_wait: Duration,
+    /// 理论上下一个连接的到达时间
+    tat: Mutex<Option<Instant>>,
+}
+
+impl AcceptLimiter {
+    pub fn new(rate: u32, burst: u32, max_wait: Duration) -> Self {
+        let interval = Duration::from_secs(1) / rate.max(1);
+        Self {
+            interval,
+            tolerance: interval * burst.max(1),
+            max_wait,
+            tat: Mutex::new(None),
+        }
+    }
+
+    /// 为 `now` 到达的连接预留接入时间，返回需要等待的时长；超过等待上限时返回 `None`（不占用额度）
+    pub fn reserve_at(&self, now: Instant) -> Option<Duration> {
+        let mut tat = self.tat.lock().unwrap();
+        let start = tat.map_or(now, |t| t.max(now));
+        let next = start + self.interval;
+        let wait = next.saturating_duration_since(now).saturating_sub(self.tolerance);
+        if wait > self.max_wait {
+            return None;
+        }
+        *tat = Some(next);
+        Some(wait)
+    }
+}
+
+/// 全局限速器，速率为 0 时返回 `None`
+pub fn global() -> Option<&'static AcceptLimiter> {
+    static LIMITER: OnceLock<Option<AcceptLimiter>> = OnceLock::new();
+    LIMITER
+        .get_or_init(|| {
+            let rate = common::env::parse::<u32>("OXIPROXY_AGENT_ACCEPT_RATE").unwrap_or(DEFAULT_RATE);
+            (rate > 0).then(|| AcceptLimiter::new(rate, rate, MAX_WAIT))
+        })
+        .as_ref()
+}
+
+/// 等待轮到当前连接接入；排队时间超过上限时返回 false
+pub async fn acquire() -> bool {
+    let Some(limiter) = global() else {
+        return true;
+    };
+    match limiter.reserve_at(Instant::now()) {
+        Some(wait) => {
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_then_paced() {
+        let limiter = AcceptLimiter::new(10, 3, Duration::from_secs(1));
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(limiter.reserve_at(now), Some(Duration::ZERO));
+        }
+        assert_eq!(limiter.reserve_at(now), Some(Duration::from_millis(100)));
+        assert_eq!(limiter.reserve_at(now), Some(Duration::from_millis(200)));
+    }
+
+    #[test]
+    fn test_rejects_beyond_max_wait() {
+        let limiter = AcceptLimiter::new(10, 1, Duration::from_millis(250));
+        let now = Instant::now();
+        assert_eq!(limiter.reserve_at(now), Some(Duration::ZERO));
+        assert_eq!(limiter.reserve_at(now), Some(Duration::from_millis(100)));
+        assert_eq!(limiter.reserve_at(now), Some(Duration::from_millis(200)));
+        assert_eq!(limiter.reserve_at(now), None);
+        // 被拒绝的连接不占用额度
+        assert_eq!(limiter.reserve_at(now + Duration::from_millis(100)), Some(Duration::from_millis(200)));
+    }
+
+    #[test]
+    fn test_recovers_after_idle() {
+        let limiter = AcceptLimiter::new(10, 2, Duration::from_secs(1));
+        let now = Instant::now();
+        for _ in 0..4 {
+            limiter.reserve_at(now);
+        }
+        let later = now + Duration::from_secs(5);
+        assert_eq!(limiter.reserve_at(later), Some(Duration::ZERO));
+        assert_eq!(limiter.reserve_at(later), Some(Duration::ZERO));
+    }
+}
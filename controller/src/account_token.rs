@@ -0,0 +1,158 @@
+//! 邮箱验证和找回密码链接中的签名令牌
+//!
+//! 令牌为 `base64url(载荷).base64url(HMAC-SHA256)`，以 JWT 密钥签名，载荷包含用途、用户 ID、
+//! 过期时间和绑定值。绑定值取自令牌签发时用户的邮箱（邮箱验证）或密码哈希（找回密码），
+//! 使用时与用户当前状态比对：修改邮箱后旧的验证链接失效，密码重置成功后同一链接无法再次使用，
+//! 无需在数据库中记录已签发的令牌。
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::entity::user;
+
+/// 令牌用途，不同用途的令牌不能互换
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Purpose {
+    VerifyEmail,
+    ResetPassword,
+}
+
+impl Purpose {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Purpose::VerifyEmail => "verify-email",
+            Purpose::ResetPassword => "reset-password",
+        }
+    }
+}
+
+/// 校验通过的令牌
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountToken {
+    pub user_id: i64,
+    binding: String,
+}
+
+impl AccountToken {
+    /// 令牌是否仍与用户当前的邮箱或密码对应
+    pub fn matches(&self, purpose: Purpose, user: &user::Model) -> bool {
+        self.user_id == user.id && binding(purpose, user).is_some_and(|b| b == self.binding)
+    }
+}
+
+/// 令牌绑定的用户状态摘要，邮箱验证时用户没有邮箱则为 `None`
+fn binding(purpose: Purpose, user: &user::Model) -> Option<String> {
+    let source = match purpose {
+        Purpose::VerifyEmail => user.email.as_deref()?,
+        Purpose::ResetPassword => user.password_hash.as_str(),
+    };
+    Some(hex::encode(&Sha256::digest(source.as_bytes())[..8]))
+}
+
+fn sign(payload: &str, secret: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC 接受任意长度的密钥");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+/// 签发令牌，`expires_at` 为 Unix 时间戳（秒）
+pub fn issue(purpose: Purpose, user: &user::Model, secret: &str, expires_at: i64) -> Option<String> {
+    let payload = format!("{}:{}:{}:{}", purpose.as_str(), user.id, expires_at, binding(purpose, user)?);
+    let mac = sign(&payload, secret).finalize().into_bytes();
+    Some(format!("{}.{}", URL_SAFE_NO_PAD.encode(payload), URL_SAFE_NO_PAD.encode(mac)))
+}
+
+/// 校验令牌的签名、用途和有效期，`now` 为 Unix 时间戳（秒）
+pub fn verify(purpose: Purpose, token: &str, secret: &str, now: i64) -> Option<AccountToken> {
+    let (payload, mac) = token.trim().split_once('.')?;
+    let payload = String::from_utf8(URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+    let mac = URL_SAFE_NO_PAD.decode(mac).ok()?;
+    sign(&payload, secret).verify_slice(&mac).ok()?;
+
+    let mut parts = payload.splitn(4, ':');
+    if parts.next()? != purpose.as_str() {
+        return None;
+    }
+    let user_id = parts.next()?.parse().ok()?;
+    let expires_at: i64 = parts.next()?.parse().ok()?;
+    if expires_at <= now {
+        return None;
+    }
+    Some(AccountToken { user_id, binding: parts.next()?.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "test-secret";
+
+    fn user() -> user::Model {
+        let now = chrono::Utc::now().naive_utc();
+        user::Model {
+            id: 7,
+            username: "alice".into(),
+            password_hash: "$2b$12$old".into(),
+            is_admin: false,
+            total_bytes_sent: 0,
+            total_bytes_received: 0,
+            traffic_reset_cycle: "none".into(),
+            last_reset_at: None,
+            is_traffic_exceeded: false,
+            traffic_quota_gb: None,
+            max_port_count: None,
+            allowed_port_range: None,
+            max_node_count: None,
+            max_client_count: None,
+            speed_limit: None,
+            speed_limit_schedule: None,
+            tenant_id: None,
+            is_tenant_admin: false,
+            display_name: None,
+            email: Some("alice@example.com".into()),
+            email_verified_at: None,
+            lock_version: 0,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn roundtrip_and_purpose() {
+        let u = user();
+        let token = issue(Purpose::ResetPassword, &u, SECRET, 2000).unwrap();
+        let parsed = verify(Purpose::ResetPassword, &token, SECRET, 1000).unwrap();
+        assert!(parsed.matches(Purpose::ResetPassword, &u));
+        assert!(verify(Purpose::VerifyEmail, &token, SECRET, 1000).is_none());
+        assert!(verify(Purpose::ResetPassword, &token, "other", 1000).is_none());
+        assert!(verify(Purpose::ResetPassword, &token, SECRET, 2000).is_none());
+    }
+
+    #[test]
+    fn rejects_tampered_payload() {
+        let token = issue(Purpose::ResetPassword, &user(), SECRET, 2000).unwrap();
+        let (_, mac) = token.split_once('.').unwrap();
+        let forged = URL_SAFE_NO_PAD.encode("reset-password:1:2000:0000000000000000");
+        assert!(verify(Purpose::ResetPassword, &format!("{}.{}", forged, mac), SECRET, 1000).is_none());
+    }
+
+    #[test]
+    fn binding_follows_user_state() {
+        let mut u = user();
+        let reset = issue(Purpose::ResetPassword, &u, SECRET, 2000).unwrap();
+        let verify_email = issue(Purpose::VerifyEmail, &u, SECRET, 2000).unwrap();
+
+        u.password_hash = "$2b$12$new".into();
+        let parsed = verify(Purpose::ResetPassword, &reset, SECRET, 1000).unwrap();
+        assert!(!parsed.matches(Purpose::ResetPassword, &u));
+
+        u.email = Some("bob@example.com".into());
+        let parsed = verify(Purpose::VerifyEmail, &verify_email, SECRET, 1000).unwrap();
+        assert!(!parsed.matches(Purpose::VerifyEmail, &u));
+
+        u.email = None;
+        assert!(issue(Purpose::VerifyEmail, &u, SECRET, 2000).is_none());
+    }
+}
@@ -0,0 +1,350 @@
+//! Controller 运维命令行
+//!
+//! 直接操作本地数据库，用于 Web 管理界面不可用或 admin 密码丢失时通过 SSH 管理系统。
+//! 通过命令行修改的隧道配置不会实时推送给运行中的 controller，
+//! 客户端重新连接（或重启 controller）后生效。
+
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use clap::Subcommand;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, QueryFilter, QueryOrder, Set};
+use sea_orm_migration::MigratorTrait;
+
+use crate::entity::{Client, Node, Proxy, User};
+use crate::migration::{self, get_connection};
+
+#[derive(Subcommand)]
+pub enum AdminCommand {
+    /// 重置用户密码（未指定 --password 时随机生成）
+    ResetPassword {
+        /// 用户名
+        username: String,
+
+        /// 新密码
+        #[arg(long)]
+        password: Option<String>,
+    },
+
+    /// 授予或撤销管理员权限
+    SetAdmin {
+        /// 用户名
+        username: String,
+
+        /// 撤销管理员权限
+        #[arg(long)]
+        revoke: bool,
+    },
+
+    /// 列出所有用户
+    ListUsers,
+
+    /// 列出所有节点
+    ListNodes,
+
+    /// 列出所有客户端
+    ListClients,
+}
+
+#[derive(Subcommand)]
+pub enum ProxyCommand {
+    /// 列出隧道
+    List {
+        /// 仅显示指定客户端的隧道
+        #[arg(long)]
+        client_id: Option<i64>,
+    },
+
+    /// 创建隧道
+    Create {
+        /// 客户端 ID
+        #[arg(long)]
+        client_id: i64,
+
+        /// 隧道名称
+        #[arg(long)]
+        name: String,
+
+        /// 隧道类型（tcp / udp）
+        #[arg(long = "type", default_value = "tcp")]
+        proxy_type: String,
+
+        /// 本地地址
+        #[arg(long, default_value = "127.0.0.1")]
+        local_ip: String,
+
+        /// 本地端口
+        #[arg(long)]
+        local_port: u16,
+
+        /// 远程端口
+        #[arg(long)]
+        remote_port: u16,
+
+        /// 节点 ID
+        #[arg(long)]
+        node_id: Option<i64>,
+    },
+
+    /// 启用隧道
+    Enable {
+        /// 隧道 ID
+        id: i64,
+    },
+
+    /// 禁用隧道
+    Disable {
+        /// 隧道 ID
+        id: i64,
+    },
+
+    /// 删除隧道
+    Delete {
+        /// 隧道 ID
+        id: i64,
+    },
+}
+
+/// 连接数据库并确保迁移已执行
+async fn prepare_db() -> Result<&'static sea_orm::DatabaseConnection> {
+    let db = get_connection().await;
+    migration::Migrator::up(db, None).await?;
+    Ok(db)
+}
+
+/// 执行管理员命令
+pub async fn run_admin_command(command: AdminCommand) -> Result<()> {
+    let db = prepare_db().await?;
+
+    match command {
+        AdminCommand::ResetPassword { username, password } => {
+            let user = find_user(&username).await?;
+            let password = password.unwrap_or_else(|| crate::auth::generate_random_password(16));
+            let password_hash = crate::auth::hash_password(&password)?;
+
+            let mut active: crate::entity::user::ActiveModel = user.into();
+            active.password_hash = Set(password_hash);
+            active.updated_at = Set(Utc::now().naive_utc());
+            active.update(db).await?;
+
+            println!("✓ 用户 {} 的密码已重置", username);
+            println!("新密码: {}", password);
+        }
+
+        AdminCommand::SetAdmin { username, revoke } => {
+            let user = find_user(&username).await?;
+
+            let mut active: crate::entity::user::ActiveModel = user.into();
+            active.is_admin = Set(!revoke);
+            active.updated_at = Set(Utc::now().naive_utc());
+            active.update(db).await?;
+
+            if revoke {
+                println!("✓ 已撤销用户 {} 的管理员权限", username);
+            } else {
+                println!("✓ 已授予用户 {} 管理员权限", username);
+            }
+        }
+
+        AdminCommand::ListUsers => {
+            let users = User::find()
+                .order_by_asc(crate::entity::user::Column::Id)
+                .all(db)
+                .await?;
+
+            println!("{:<6} {:<24} {:<6} {:>14}", "ID", "USERNAME", "ADMIN", "TRAFFIC(GB)");
+            for user in users {
+                let used_gb = crate::traffic_limiter::bytes_to_gb(
+                    user.total_bytes_sent + user.total_bytes_received,
+                );
+                println!(
+                    "{:<6} {:<24} {:<6} {:>14.2}",
+                    user.id,
+                    user.username,
+                    if user.is_admin { "yes" } else { "no" },
+                    used_gb
+                );
+            }
+        }
+
+        AdminCommand::ListNodes => {
+            let nodes = Node::find()
+                .order_by_asc(crate::entity::node::Column::Id)
+                .all(db)
+                .await?;
+
+            println!(
+                "{:<6} {:<20} {:<8} {:<10} {:<28} {:<10}",
+                "ID", "NAME", "STATUS", "TYPE", "TUNNEL", "PROTOCOL"
+            );
+            for node in nodes {
+                println!(
+                    "{:<6} {:<20} {:<8} {:<10} {:<28} {:<10}",
+                    node.id,
+                    node.name,
+                    if node.is_online { "online" } else { "offline" },
+                    node.node_type,
+                    common::utils::join_host_port(&node.tunnel_addr, node.tunnel_port as u16),
+                    node.tunnel_protocol
+                );
+            }
+        }
+
+        AdminCommand::ListClients => {
+            let clients = Client::find()
+                .order_by_asc(crate::entity::client::Column::Id)
+                .all(db)
+                .await?;
+
+            println!(
+                "{:<6} {:<20} {:<8} {:<8} {:<38}",
+                "ID", "NAME", "STATUS", "USER", "TOKEN"
+            );
+            for client in clients {
+                println!(
+                    "{:<6} {:<20} {:<8} {:<8} {:<38}",
+                    client.id,
+                    client.name,
+                    if client.is_online { "online" } else { "offline" },
+                    client.user_id.map(|id| id.to_string()).unwrap_or_else(|| "-".to_string()),
+                    client.token
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 执行隧道管理命令
+pub async fn run_proxy_command(command: ProxyCommand) -> Result<()> {
+    let db = prepare_db().await?;
+
+    match command {
+        ProxyCommand::List { client_id } => {
+            let mut select = Proxy::find().order_by_asc(crate::entity::proxy::Column::Id);
+            if let Some(client_id) = client_id {
+                select = select.filter(crate::entity::proxy::Column::ClientId.eq(client_id.to_string()));
+            }
+            let proxies = select.all(db).await?;
+
+            println!(
+                "{:<6} {:<20} {:<8} {:<6} {:<22} {:<8} {:<6} {:<8}",
+                "ID", "NAME", "CLIENT", "TYPE", "LOCAL", "REMOTE", "NODE", "ENABLED"
+            );
+            for proxy in proxies {
+                println!(
+                    "{:<6} {:<20} {:<8} {:<6} {:<22} {:<8} {:<6} {:<8}",
+                    proxy.id,
+                    proxy.name,
+                    proxy.client_id,
+                    proxy.proxy_type,
+                    common::utils::join_host_port(&proxy.local_ip, proxy.local_port),
+                    proxy.remote_port,
+                    proxy.node_id.map(|id| id.to_string()).unwrap_or_else(|| "-".to_string()),
+                    if proxy.enabled { "yes" } else { "no" }
+                );
+            }
+        }
+
+        ProxyCommand::Create {
+            client_id,
+            name,
+            proxy_type,
+            local_ip,
+            local_port,
+            remote_port,
+            node_id,
+        } => {
+            if Client::find_by_id(client_id).one(db).await?.is_none() {
+                bail!("客户端 #{} 不存在", client_id);
+            }
+            if let Some(node_id) = node_id {
+                if Node::find_by_id(node_id).one(db).await?.is_none() {
+                    bail!("节点 #{} 不存在", node_id);
+                }
+            }
+
+            // 同一节点上的 remote_port 必须唯一
+            if let Some(existing) = crate::anycast::port_owner(node_id, remote_port, None, db).await? {
+                bail!("远程端口 {} 已被代理「{}」占用", remote_port, existing.name);
+            }
+
+            let now = Utc::now().naive_utc();
+            let proxy = crate::entity::proxy::ActiveModel {
+                id: NotSet,
+                client_id: Set(client_id.to_string()),
+                name: Set(name),
+                proxy_type: Set(proxy_type),
+                local_ip: Set(common::utils::normalize_host(&local_ip).to_string()),
+                local_port: Set(local_port),
+                remote_port: Set(remote_port),
+                bind_ip: Set(None),
+                enabled: Set(true),
+                node_id: Set(node_id),
+                group_id: Set(None),
+                idle_timeout: Set(None),
+                mitigation_config: Set(None),
+                bandwidth_weight: Set(None),
+                priority: Set(None),
+                local_pool_size: Set(None),
+                local_source: Set(None),
+                anycast_node_ids: Set(None),
+                dns_name: Set(None),
+                service: Set(None),
+                schedule: Set(None),
+                expires_at: Set(None),
+                stale_at: Set(None),
+                total_bytes_sent: Set(0),
+                total_bytes_received: Set(0),
+                lock_version: Set(0),
+                created_at: Set(now),
+                updated_at: Set(now),
+            }
+            .insert(db)
+            .await?;
+
+            println!("✓ 隧道已创建: {} (ID: {})", proxy.name, proxy.id);
+            println!("提示: 客户端重新连接后生效");
+        }
+
+        ProxyCommand::Enable { id } => set_proxy_enabled(id, true).await?,
+
+        ProxyCommand::Disable { id } => set_proxy_enabled(id, false).await?,
+
+        ProxyCommand::Delete { id } => {
+            let result = Proxy::delete_by_id(id).exec(db).await?;
+            if result.rows_affected == 0 {
+                bail!("隧道 #{} 不存在", id);
+            }
+            println!("✓ 隧道 #{} 已删除", id);
+        }
+    }
+
+    Ok(())
+}
+
+async fn set_proxy_enabled(id: i64, enabled: bool) -> Result<()> {
+    let db = get_connection().await;
+    let proxy = Proxy::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| anyhow!("隧道 #{} 不存在", id))?;
+
+    let mut active: crate::entity::proxy::ActiveModel = proxy.into();
+    active.enabled = Set(enabled);
+    active.updated_at = Set(Utc::now().naive_utc());
+    active.update(db).await?;
+
+    println!("✓ 隧道 #{} 已{}", id, if enabled { "启用" } else { "禁用" });
+    println!("提示: 客户端重新连接后生效");
+    Ok(())
+}
+
+async fn find_user(username: &str) -> Result<crate::entity::user::Model> {
+    let db = get_connection().await;
+    User::find()
+        .filter(crate::entity::user::Column::Username.eq(username))
+        .one(db)
+        .await?
+        .ok_or_else(|| anyhow!("用户 {} 不存在", username))
+}
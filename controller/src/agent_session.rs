@@ -0,0 +1,85 @@
+//! Agent 会话令牌
+//!
+//! 节点 / 客户端认证成功后，Controller 下发一个短期有效的会话令牌，记录本次认证时的来源 IP
+//! 和版本。Agent 重连时带上该令牌，Controller 验证通过且来源未变化时跳过地理位置查询、
+//! 未变化的数据库更新等开销较大的步骤。Controller 重启后大量 Agent 同时重连时，
+//! 这能显著缩短每次认证的耗时。
+//!
+//! 会话令牌不能代替节点密钥 / 客户端 token：认证仍以后者为准，令牌只用于判断能否复用上次的结果。
+
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+
+/// 会话令牌的 audience，与管理后台登录令牌区分
+const AUDIENCE: &str = "oxiproxy-agent";
+/// 会话令牌有效期
+const SESSION_HOURS: i64 = 24;
+
+pub const KIND_NODE: &str = "node";
+pub const KIND_CLIENT: &str = "client";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionClaims {
+    /// 节点或客户端 ID
+    sub: i64,
+    /// `node` / `client`
+    kind: String,
+    /// 认证时的来源 IP
+    #[serde(default)]
+    ip: Option<String>,
+    /// 认证时上报的版本
+    #[serde(default)]
+    version: Option<String>,
+    aud: String,
+    iat: i64,
+    exp: i64,
+}
+
+async fn secret() -> &'static str {
+    static SECRET: OnceCell<String> = OnceCell::const_new();
+    SECRET
+        .get_or_init(|| async { crate::config::get_config().await.get_jwt_secret().unwrap_or_default() })
+        .await
+}
+
+/// 签发会话令牌，失败时返回 `None`（Agent 下次重连时走完整认证）
+pub async fn issue(kind: &str, id: i64, ip: Option<&str>, version: Option<&str>) -> Option<String> {
+    let now = Utc::now();
+    let claims = SessionClaims {
+        sub: id,
+        kind: kind.to_string(),
+        ip: ip.map(str::to_string),
+        version: version.map(str::to_string),
+        aud: AUDIENCE.to_string(),
+        iat: now.timestamp(),
+        exp: (now + Duration::hours(SESSION_HOURS)).timestamp(),
+    };
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(secret().await.as_bytes())).ok()
+}
+
+/// 验证会话令牌，要求类型和 ID 与本次认证的 Agent 一致
+async fn verify(token: &str, kind: &str, id: i64) -> Result<SessionClaims> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[AUDIENCE]);
+    let claims = decode::<SessionClaims>(token, &DecodingKey::from_secret(secret().await.as_bytes()), &validation)
+        .map_err(|e| anyhow!("会话令牌无效: {}", e))?
+        .claims;
+    if claims.kind != kind || claims.sub != id {
+        return Err(anyhow!("会话令牌不属于该 Agent"));
+    }
+    Ok(claims)
+}
+
+/// 会话令牌有效且来源 IP、版本与本次连接一致时返回 true
+pub async fn is_resumable(token: Option<&str>, kind: &str, id: i64, ip: Option<&str>, version: Option<&str>) -> bool {
+    let Some(token) = token.filter(|t| !t.is_empty()) else {
+        return false;
+    };
+    match verify(token, kind, id).await {
+        Ok(claims) => claims.ip.as_deref() == ip && claims.version.as_deref() == version,
+        Err(_) => false,
+    }
+}
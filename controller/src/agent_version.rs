@@ -0,0 +1,115 @@
+//! Agent 版本比较与兼容性检查
+//!
+//! 版本号按 `主.次.修订` 逐段比较（可带 `v` 前缀，`-` / `+` 之后的预发布和构建信息忽略），
+//! 无法解析的版本视为未知，不参与比较。
+//!
+//! 设置 `OXIPROXY_MIN_AGENT_VERSION` 后，版本低于该值的节点和客户端在注册时即被拒绝，并收到
+//! 说明所需版本的错误，而不是连接成功后因协议不兼容以难以理解的方式失败。同时设置
+//! `OXIPROXY_AUTO_UPDATE_OUTDATED_AGENTS=true` 时，拒绝前先让 Agent 完成注册并下发自更新指令，
+//! 等待其更新重启（最多 [`UPDATE_WAIT`]），期间不下发任何代理。
+
+use std::cmp::Ordering;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tracing::warn;
+
+/// 等待过旧 Agent 完成自更新的最长时间
+pub const UPDATE_WAIT: Duration = Duration::from_secs(300);
+
+/// Controller 自身的版本
+pub const CONTROLLER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// 解析版本号的数字部分
+fn parse(version: &str) -> Option<Vec<u64>> {
+    let version = version.trim().trim_start_matches('v');
+    let core = version.split(['-', '+']).next()?;
+    core.split('.').map(|part| part.parse().ok()).collect()
+}
+
+/// 比较两个版本号，任一无法解析时返回 None
+pub fn compare(a: &str, b: &str) -> Option<Ordering> {
+    let (mut a, mut b) = (parse(a)?, parse(b)?);
+    let len = a.len().max(b.len());
+    a.resize(len, 0);
+    b.resize(len, 0);
+    Some(a.cmp(&b))
+}
+
+/// Agent 版本是否低于 Controller 版本（版本未知时不视为过旧）
+pub fn is_outdated(version: Option<&str>) -> bool {
+    version.and_then(|v| compare(v, CONTROLLER_VERSION)) == Some(Ordering::Less)
+}
+
+/// 最低支持的 Agent 版本（`OXIPROXY_MIN_AGENT_VERSION`，不设置时不检查）
+pub fn min_agent_version() -> Option<&'static str> {
+    static MIN: OnceLock<Option<String>> = OnceLock::new();
+    MIN.get_or_init(|| {
+        let version = common::env::var("OXIPROXY_MIN_AGENT_VERSION")?.trim().to_string();
+        if version.is_empty() {
+            return None;
+        }
+        if parse(&version).is_none() {
+            warn!("OXIPROXY_MIN_AGENT_VERSION 不是有效的版本号，已忽略: {}", version);
+            return None;
+        }
+        Some(version)
+    })
+    .as_deref()
+}
+
+/// 拒绝过旧 Agent 前是否先下发自更新指令（`OXIPROXY_AUTO_UPDATE_OUTDATED_AGENTS`，默认 false）
+pub fn auto_update_outdated() -> bool {
+    common::env::var("OXIPROXY_AUTO_UPDATE_OUTDATED_AGENTS").is_some_and(|v| v == "true" || v == "1")
+}
+
+fn check_against(version: Option<&str>, min: &str) -> Result<(), String> {
+    let version = version.unwrap_or("");
+    match compare(version, min) {
+        Some(Ordering::Less) => Err(format!(
+            "Agent 版本 {} 低于 Controller（{}）支持的最低版本 {}，请升级到 {} 或更高版本后重新连接",
+            version, CONTROLLER_VERSION, min, min
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// 检查 Agent 版本是否受支持，不支持时返回说明（版本未知时放行）
+pub fn check_supported(version: Option<&str>) -> Result<(), String> {
+    match min_agent_version() {
+        Some(min) => check_against(version, min),
+        None => Ok(()),
+    }
+}
+
+/// 过旧 Agent 自更新的目标版本：Controller 自身版本满足最低版本时与其一致，否则为最新版本
+pub fn update_target() -> Option<String> {
+    let min = min_agent_version()?;
+    matches!(compare(CONTROLLER_VERSION, min), Some(Ordering::Equal | Ordering::Greater))
+        .then(|| CONTROLLER_VERSION.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(compare("1.2.3", "v1.2.3"), Some(Ordering::Equal));
+        assert_eq!(compare("1.2", "1.2.0"), Some(Ordering::Equal));
+        assert_eq!(compare("1.10.0", "1.9.9"), Some(Ordering::Greater));
+        assert_eq!(compare("0.9.0-beta.1", "1.0.0"), Some(Ordering::Less));
+        assert_eq!(compare("dev", "1.0.0"), None);
+        assert_eq!(compare("", "1.0.0"), None);
+    }
+
+    #[test]
+    fn test_check_against_min_version() {
+        assert!(check_against(Some("1.4.9"), "1.5.0").is_err());
+        assert!(check_against(Some("v1.5.0"), "1.5.0").is_ok());
+        assert!(check_against(Some("1.6.0-rc.1"), "1.5.0").is_ok());
+        // 无法判断的版本（开发构建、未上报）放行
+        assert!(check_against(Some("dev"), "1.5.0").is_ok());
+        assert!(check_against(None, "1.5.0").is_ok());
+    }
+}
@@ -0,0 +1,442 @@
+//! 告警规则
+//!
+//! 管理员定义的规则每 30 秒按 Controller 内部状态求值一次，支持以下内置类型：
+//!
+//! - `node_offline` / `client_offline`：节点 / 客户端离线；
+//! - `user_traffic`：用户已用流量达到配额的 `threshold`%；
+//! - `proxy_error_rate`：代理的访客连接错误率超过每分钟 `threshold` 次（节点心跳上报）。
+//!
+//! 条件对某个对象持续满足 `duration_secs` 秒后触发（firing），条件不再满足时恢复（resolved），
+//! 两次状态变化都写入 `alert_event` 并发送通知。处于维护窗口中的对象不求值。通知渠道为日志和 `OXIPROXY_ALERT_WEBHOOK_URL`
+//! （逗号分隔多个地址，以 JSON POST 事件）。规则被删除或停用时，其未恢复的告警随即恢复。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, NotSet, QueryFilter, Set};
+use tracing::{error, info, warn};
+
+use crate::entity::alert_event::{self, STATE_FIRING, STATE_RESOLVED};
+use crate::entity::{alert_rule, AlertEvent, AlertRule, Client, Node, Proxy, User};
+use crate::migration::get_connection;
+
+pub const KIND_NODE_OFFLINE: &str = "node_offline";
+pub const KIND_CLIENT_OFFLINE: &str = "client_offline";
+pub const KIND_USER_TRAFFIC: &str = "user_traffic";
+pub const KIND_PROXY_ERROR_RATE: &str = "proxy_error_rate";
+
+const KINDS: [&str; 4] = [KIND_NODE_OFFLINE, KIND_CLIENT_OFFLINE, KIND_USER_TRAFFIC, KIND_PROXY_ERROR_RATE];
+const SEVERITIES: [&str; 3] = ["info", "warning", "critical"];
+
+/// 求值间隔
+const EVAL_INTERVAL: Duration = Duration::from_secs(30);
+/// 通知请求超时
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 校验规则参数
+pub fn validate_rule(kind: &str, threshold: f64, duration_secs: i64, severity: &str) -> Result<(), String> {
+    if !KINDS.contains(&kind) {
+        return Err(format!("未知的告警类型: {}（可选 {}）", kind, KINDS.join(" / ")));
+    }
+    if !SEVERITIES.contains(&severity) {
+        return Err(format!("未知的告警级别: {}（可选 {}）", severity, SEVERITIES.join(" / ")));
+    }
+    if duration_secs < 0 {
+        return Err("持续时间不能为负数".to_string());
+    }
+    match kind {
+        KIND_USER_TRAFFIC if !(threshold > 0.0 && threshold <= 100.0) => {
+            Err("流量阈值须为 0-100 之间的百分比".to_string())
+        }
+        KIND_PROXY_ERROR_RATE if threshold <= 0.0 || !threshold.is_finite() => {
+            Err("错误率阈值须大于 0".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 某个对象在一次求值中的指标
+struct Observation {
+    target_type: &'static str,
+    target_id: i64,
+    target_name: String,
+    value: f64,
+    active: bool,
+    /// 处于维护窗口中，本轮跳过
+    in_maintenance: bool,
+}
+
+/// 单个（规则, 对象）的状态
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Track {
+    /// 条件开始满足的时间
+    pending_since: Option<Instant>,
+    /// 正在触发的告警事件
+    firing: Option<i64>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Transition {
+    Fire,
+    Resolve(i64),
+}
+
+impl Track {
+    /// 按本次求值结果推进状态机
+    fn step(&mut self, active: bool, now: Instant, hold: Duration) -> Option<Transition> {
+        if !active {
+            self.pending_since = None;
+            return self.firing.take().map(Transition::Resolve);
+        }
+        if self.firing.is_some() {
+            return None;
+        }
+        let since = *self.pending_since.get_or_insert(now);
+        (now.duration_since(since) >= hold).then_some(Transition::Fire)
+    }
+}
+
+/// 所有规则的求值状态
+#[derive(Default)]
+struct Engine {
+    tracks: HashMap<(i64, i64), Track>,
+}
+
+impl Engine {
+    /// 载入未恢复的告警，Controller 重启后不会重复触发
+    async fn load(db: &DatabaseConnection) -> Self {
+        let mut engine = Self::default();
+        match AlertEvent::find()
+            .filter(alert_event::Column::State.eq(STATE_FIRING))
+            .all(db)
+            .await
+        {
+            Ok(events) => {
+                for event in events {
+                    engine.tracks.insert(
+                        (event.rule_id, event.target_id),
+                        Track { pending_since: None, firing: Some(event.id) },
+                    );
+                }
+            }
+            Err(e) => error!("加载未恢复的告警失败: {}", e),
+        }
+        engine
+    }
+
+    async fn evaluate(&mut self, db: &DatabaseConnection) {
+        let rules = match AlertRule::find()
+            .filter(alert_rule::Column::Enabled.eq(true))
+            .all(db)
+            .await
+        {
+            Ok(rules) => rules,
+            Err(e) => {
+                error!("查询告警规则失败: {}", e);
+                return;
+            }
+        };
+
+        // 已删除或停用的规则：恢复其告警
+        let rule_ids: HashSet<i64> = rules.iter().map(|r| r.id).collect();
+        let stale: Vec<(i64, i64)> = self.tracks.keys().filter(|(rule_id, _)| !rule_ids.contains(rule_id)).copied().collect();
+        for key in stale {
+            if let Some(event_id) = self.tracks.remove(&key).and_then(|t| t.firing) {
+                resolve(db, event_id, "规则已删除或停用").await;
+            }
+        }
+
+        let now = Instant::now();
+        for rule in rules {
+            let observations = match observe(db, &rule).await {
+                Ok(observations) => observations,
+                Err(e) => {
+                    error!("告警规则 #{} 求值失败: {}", rule.id, e);
+                    continue;
+                }
+            };
+            let hold = Duration::from_secs(rule.duration_secs.max(0) as u64);
+            let mut seen = HashSet::new();
+            for obs in observations {
+                seen.insert(obs.target_id);
+                if obs.in_maintenance {
+                    // 维护结束后重新计时
+                    if let Some(track) = self.tracks.get_mut(&(rule.id, obs.target_id)) {
+                        track.pending_since = None;
+                    }
+                    continue;
+                }
+                let track = self.tracks.entry((rule.id, obs.target_id)).or_default();
+                match track.step(obs.active, now, hold) {
+                    Some(Transition::Fire) => track.firing = fire(db, &rule, &obs).await,
+                    Some(Transition::Resolve(event_id)) => resolve(db, event_id, "条件已恢复").await,
+                    None => {}
+                }
+            }
+            // 对象已被删除
+            let gone: Vec<(i64, i64)> = self
+                .tracks
+                .keys()
+                .filter(|(rule_id, target_id)| *rule_id == rule.id && !seen.contains(target_id))
+                .copied()
+                .collect();
+            for key in gone {
+                if let Some(event_id) = self.tracks.remove(&key).and_then(|t| t.firing) {
+                    resolve(db, event_id, "对象已删除").await;
+                }
+            }
+        }
+        self.tracks.retain(|_, t| t.pending_since.is_some() || t.firing.is_some());
+    }
+}
+
+/// 求出规则涉及的所有对象的指标
+async fn observe(db: &DatabaseConnection, rule: &alert_rule::Model) -> anyhow::Result<Vec<Observation>> {
+    let wanted = |id: i64| rule.target_id.is_none_or(|t| t == id);
+    let observations = match rule.kind.as_str() {
+        KIND_NODE_OFFLINE => Node::find()
+            .all(db)
+            .await?
+            .into_iter()
+            .filter(|n| wanted(n.id))
+            .map(|n| {
+                let online = crate::online_status::nodes().get(n.id).unwrap_or(n.is_online);
+                Observation {
+                    target_type: "node",
+                    target_id: n.id,
+                    in_maintenance: crate::maintenance::global().node(n.id),
+                    target_name: n.name,
+                    value: 0.0,
+                    active: !online,
+                }
+            })
+            .collect(),
+        KIND_CLIENT_OFFLINE => Client::find()
+            .all(db)
+            .await?
+            .into_iter()
+            .filter(|c| wanted(c.id))
+            .map(|c| {
+                let online = crate::online_status::clients().get(c.id).unwrap_or(c.is_online);
+                Observation {
+                    target_type: "client",
+                    target_id: c.id,
+                    in_maintenance: crate::maintenance::global().client(c.id),
+                    target_name: c.name,
+                    value: 0.0,
+                    active: !online,
+                }
+            })
+            .collect(),
+        KIND_USER_TRAFFIC => User::find()
+            .all(db)
+            .await?
+            .into_iter()
+            .filter(|u| wanted(u.id))
+            .filter_map(|u| {
+                let quota = crate::traffic_limiter::gb_to_bytes(u.traffic_quota_gb.filter(|q| *q > 0.0)?);
+                let percent = (u.total_bytes_sent + u.total_bytes_received) as f64 / quota as f64 * 100.0;
+                Some(Observation {
+                    target_type: "user",
+                    target_id: u.id,
+                    target_name: u.username,
+                    value: percent,
+                    active: percent >= rule.threshold,
+                    in_maintenance: false,
+                })
+            })
+            .collect(),
+        KIND_PROXY_ERROR_RATE => {
+            let rates = crate::live_speed::proxy_error_rates();
+            Proxy::find()
+                .all(db)
+                .await?
+                .into_iter()
+                .filter(|p| wanted(p.id))
+                .map(|p| {
+                    let rate = rates.get(&p.id).copied().unwrap_or(0.0);
+                    Observation {
+                        target_type: "proxy",
+                        target_id: p.id,
+                        in_maintenance: crate::maintenance::global().proxy(&p),
+                        target_name: p.name,
+                        value: rate,
+                        active: rate > rule.threshold,
+                    }
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+    Ok(observations)
+}
+
+fn describe(rule: &alert_rule::Model, obs: &Observation) -> String {
+    match rule.kind.as_str() {
+        KIND_NODE_OFFLINE => format!("节点 {} 离线超过 {} 秒", obs.target_name, rule.duration_secs),
+        KIND_CLIENT_OFFLINE => format!("客户端 {} 离线超过 {} 秒", obs.target_name, rule.duration_secs),
+        KIND_USER_TRAFFIC => format!("用户 {} 已用流量达到配额的 {:.1}%（阈值 {}%）", obs.target_name, obs.value, rule.threshold),
+        KIND_PROXY_ERROR_RATE => format!("代理 {} 错误率 {:.1} 次/分钟（阈值 {}）", obs.target_name, obs.value, rule.threshold),
+        _ => rule.name.clone(),
+    }
+}
+
+async fn fire(db: &DatabaseConnection, rule: &alert_rule::Model, obs: &Observation) -> Option<i64> {
+    let event = alert_event::ActiveModel {
+        id: NotSet,
+        rule_id: Set(rule.id),
+        rule_name: Set(rule.name.clone()),
+        kind: Set(rule.kind.clone()),
+        severity: Set(rule.severity.clone()),
+        target_type: Set(obs.target_type.to_string()),
+        target_id: Set(obs.target_id),
+        target_name: Set(obs.target_name.clone()),
+        value: Set(obs.value),
+        message: Set(describe(rule, obs)),
+        state: Set(STATE_FIRING.to_string()),
+        fired_at: Set(Utc::now().naive_utc()),
+        resolved_at: Set(None),
+    };
+    match event.insert(db).await {
+        Ok(event) => {
+            warn!("🚨 告警 [{}] {}: {}", event.severity, event.rule_name, event.message);
+            notify(&event);
+            Some(event.id)
+        }
+        Err(e) => {
+            error!("记录告警事件失败: {}", e);
+            None
+        }
+    }
+}
+
+async fn resolve(db: &DatabaseConnection, event_id: i64, reason: &str) {
+    let event = match AlertEvent::find_by_id(event_id).one(db).await {
+        Ok(Some(event)) if event.state == STATE_FIRING => event,
+        Ok(_) => return,
+        Err(e) => {
+            error!("查询告警事件 #{} 失败: {}", event_id, e);
+            return;
+        }
+    };
+    let mut active: alert_event::ActiveModel = event.into();
+    active.state = Set(STATE_RESOLVED.to_string());
+    active.resolved_at = Set(Some(Utc::now().naive_utc()));
+    match active.update(db).await {
+        Ok(event) => {
+            info!("✅ 告警恢复 [{}] {}: {}（{}）", event.severity, event.rule_name, event.message, reason);
+            notify(&event);
+        }
+        Err(e) => error!("更新告警事件 #{} 失败: {}", event_id, e),
+    }
+}
+
+/// 通知渠道（Webhook）地址
+fn webhooks() -> &'static [String] {
+    static URLS: OnceLock<Vec<String>> = OnceLock::new();
+    URLS.get_or_init(|| {
+        common::env::var("OXIPROXY_ALERT_WEBHOOK_URL")
+            .map(|v| {
+                v.split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .filter(|url| match crate::outbound::validate_url(url) {
+                        Ok(_) => true,
+                        Err(e) => {
+                            warn!("告警 Webhook 地址不可用，已忽略: {}", e);
+                            false
+                        }
+                    })
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    })
+}
+
+/// 把告警事件发送到通知渠道（后台发送，不阻塞求值）
+fn notify(event: &alert_event::Model) {
+    post_webhooks(event.clone());
+}
+
+/// 把事件以 JSON POST 到告警 Webhook（后台发送），故障转移等系统事件也经此通知
+pub fn post_webhooks<T: serde::Serialize + Send + 'static>(event: T) {
+    let urls = webhooks();
+    if urls.is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        let http = match crate::outbound::client(WEBHOOK_TIMEOUT) {
+            Ok(http) => http,
+            Err(e) => {
+                error!("创建告警通知 HTTP 客户端失败: {}", e);
+                return;
+            }
+        };
+        for url in urls {
+            match http.post(url).json(&event).send().await {
+                Ok(resp) if resp.status().is_success() => {}
+                Ok(resp) => warn!("告警通知 {} 返回状态 {}", url, resp.status()),
+                Err(e) => warn!("发送告警通知到 {} 失败: {}", url, e),
+            }
+        }
+    });
+}
+
+/// 启动告警求值任务
+pub fn start_alert_evaluator() {
+    tokio::spawn(async move {
+        let db = get_connection().await;
+        let mut engine = Engine::load(db).await;
+        let mut interval = tokio::time::interval(EVAL_INTERVAL);
+        loop {
+            interval.tick().await;
+            engine.evaluate(db).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fires_after_hold_and_resolves() {
+        let hold = Duration::from_secs(120);
+        let start = Instant::now();
+        let mut track = Track::default();
+
+        assert_eq!(track.step(true, start, hold), None);
+        assert_eq!(track.step(true, start + Duration::from_secs(60), hold), None);
+        assert_eq!(track.step(true, start + hold, hold), Some(Transition::Fire));
+        track.firing = Some(7);
+        // 触发后不重复触发
+        assert_eq!(track.step(true, start + hold * 2, hold), None);
+        assert_eq!(track.step(false, start + hold * 3, hold), Some(Transition::Resolve(7)));
+        assert_eq!(track, Track::default());
+    }
+
+    #[test]
+    fn test_flapping_resets_pending() {
+        let hold = Duration::from_secs(60);
+        let start = Instant::now();
+        let mut track = Track::default();
+
+        assert_eq!(track.step(true, start, hold), None);
+        assert_eq!(track.step(false, start + Duration::from_secs(30), hold), None);
+        assert_eq!(track.step(true, start + Duration::from_secs(61), hold), None);
+        assert_eq!(track.step(true, start + Duration::from_secs(121), hold), Some(Transition::Fire));
+    }
+
+    #[test]
+    fn test_validate_rule() {
+        assert!(validate_rule(KIND_NODE_OFFLINE, 0.0, 120, "critical").is_ok());
+        assert!(validate_rule(KIND_USER_TRAFFIC, 90.0, 0, "warning").is_ok());
+        assert!(validate_rule(KIND_USER_TRAFFIC, 120.0, 0, "warning").is_err());
+        assert!(validate_rule(KIND_PROXY_ERROR_RATE, 0.0, 0, "info").is_err());
+        assert!(validate_rule("disk_full", 1.0, 0, "info").is_err());
+        assert!(validate_rule(KIND_NODE_OFFLINE, 0.0, 0, "page").is_err());
+    }
+}
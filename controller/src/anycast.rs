@@ -0,0 +1,91 @@
+//! 代理多节点发布
+//!
+//! 代理除 `node_id` 指定的节点外，还可以同时发布在其他节点上（`anycast_node_ids`），各节点监听相同的
+//! 远程端口，用户可以把多个地区的入口地址分发给访客。客户端与每个发布节点都建立隧道；各节点上报的流量
+//! 都计入同一个代理及其客户端，节点自身的流量按实际上报的节点统计。远程端口在每个发布节点上都必须空闲。
+
+use sea_orm::{ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+
+use common::protocol::control::ProxyControl;
+
+use crate::config_manager::ConfigManager;
+use crate::entity::{proxy, Proxy};
+use crate::node_manager::NodeManager;
+
+/// 可能发布在该节点上的代理（主节点是它，或设置了多节点发布），查询结果还需用
+/// [`proxy::Model::is_published_on`] 过滤
+pub fn maybe_on_node(node_id: i64) -> Condition {
+    Condition::any()
+        .add(proxy::Column::NodeId.eq(node_id))
+        .add(proxy::Column::AnycastNodeIds.is_not_null())
+}
+
+/// 在节点上占用该远程端口的已启用代理（`node_id` 为空时只看未指定节点的代理），`exclude` 为正在修改的代理
+pub async fn port_owner<C: ConnectionTrait>(
+    node_id: Option<i64>,
+    remote_port: u16,
+    exclude: Option<i64>,
+    db: &C,
+) -> Result<Option<proxy::Model>, DbErr> {
+    let mut query = Proxy::find()
+        .filter(proxy::Column::RemotePort.eq(remote_port))
+        .filter(proxy::Column::Enabled.eq(true));
+    if let Some(id) = exclude {
+        query = query.filter(proxy::Column::Id.ne(id));
+    }
+    let Some(node_id) = node_id else {
+        return query.filter(proxy::Column::NodeId.is_null()).one(db).await;
+    };
+    let candidates = query.filter(maybe_on_node(node_id)).all(db).await?;
+    Ok(candidates.into_iter().find(|p| p.is_published_on(node_id)))
+}
+
+/// 检查代理能否发布在节点上（节点限制、端口黑名单、端口预留、端口占用），不能时返回原因
+pub async fn check_node(
+    p: &proxy::Model,
+    node_id: i64,
+    config_manager: &ConfigManager,
+    db: &DatabaseConnection,
+) -> anyhow::Result<Option<String>> {
+    let (allowed, reason) = crate::node_limiter::validate_node_proxy_limit(node_id, p.remote_port, config_manager, db).await?;
+    if !allowed {
+        return Ok(Some(reason));
+    }
+    let tenant_id = crate::port_blocklist::client_tenant_id(&p.client_id, db).await?;
+    let (allowed, reason) = crate::port_blocklist::validate_proxy_target(
+        Some(node_id),
+        tenant_id,
+        p.remote_port,
+        &p.local_ip,
+        p.local_port,
+        db,
+    )
+    .await?;
+    if !allowed {
+        return Ok(Some(reason));
+    }
+    let (allowed, reason) =
+        crate::port_reservation::validate_proxy_port(Some(node_id), &p.client_id, p.remote_port, db).await?;
+    if !allowed {
+        return Ok(Some(reason));
+    }
+    if let Some(existing) = port_owner(Some(node_id), p.remote_port, Some(p.id), db).await? {
+        return Ok(Some(format!("远程端口 {} 已被代理「{}」占用", p.remote_port, existing.name)));
+    }
+    Ok(None)
+}
+
+/// 停止已删除代理在所有发布节点上的监听器（记录已删除，无法再按代理查询节点）
+pub async fn stop_deleted(node_manager: &NodeManager, p: &proxy::Model) -> anyhow::Result<()> {
+    let node_ids = p.node_ids();
+    if node_ids.is_empty() {
+        return node_manager.stop_proxy(&p.client_id, p.id).await;
+    }
+    let mut result = Ok(());
+    for node_id in node_ids {
+        if let Err(e) = node_manager.stop_proxy_on_node(node_id, &p.client_id, p.id).await {
+            result = Err(e);
+        }
+    }
+    result
+}
@@ -0,0 +1,182 @@
+//! 资源访问检查
+//!
+//! 修改或查看客户端、代理的接口统一通过这里检查当前用户能否访问目标资源：平台管理员可访问全部，
+//! 租户管理员可访问本租户用户的资源，普通用户只能访问自己的资源。无权访问的资源与不存在的资源
+//! 一样返回 404，不暴露其他用户的资源是否存在。
+
+use std::collections::{HashMap, HashSet};
+
+use axum::http::StatusCode;
+use sea_orm::sea_query::SimpleExpr;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect};
+
+use crate::entity::{client, proxy, Client, Proxy};
+use crate::middleware::AuthUser;
+use crate::tenant::UserScope;
+
+/// 检查失败时返回的状态码和错误信息
+pub type AccessError = (StatusCode, String);
+
+/// 资源所属用户是否在可访问的用户范围内（`None` 表示不限制），不属于任何用户的资源只有不受限时可访问
+pub fn owner_allowed(allowed_user_ids: Option<&[i64]>, owner: Option<i64>) -> bool {
+    match allowed_user_ids {
+        None => true,
+        Some(ids) => owner.is_some_and(|id| ids.contains(&id)),
+    }
+}
+
+/// 要求已登录
+pub fn require_user(auth_user: Option<AuthUser>) -> Result<AuthUser, AccessError> {
+    auth_user.ok_or_else(|| (StatusCode::UNAUTHORIZED, "未认证".to_string()))
+}
+
+/// 当前用户可访问的用户 ID，`None` 表示不限制
+pub async fn allowed_user_ids(auth_user: &AuthUser, db: &DatabaseConnection) -> Result<Option<Vec<i64>>, AccessError> {
+    UserScope::of(auth_user)
+        .user_ids(db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询用户失败: {}", e)))
+}
+
+/// 客户端查询条件：只包含可访问用户的客户端，`None` 表示不限制
+pub fn client_owner_condition(allowed_user_ids: Option<&[i64]>) -> Option<SimpleExpr> {
+    allowed_user_ids.map(|ids| client::Column::UserId.is_in(ids.iter().copied()))
+}
+
+/// 当前用户可访问的客户端 ID，`None` 表示不限制
+pub async fn accessible_client_ids(auth_user: &AuthUser, db: &DatabaseConnection) -> Result<Option<Vec<i64>>, AccessError> {
+    let allowed = allowed_user_ids(auth_user, db).await?;
+    let Some(condition) = client_owner_condition(allowed.as_deref()) else {
+        return Ok(None);
+    };
+    let ids = Client::find()
+        .select_only()
+        .column(client::Column::Id)
+        .filter(condition)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询客户端失败: {}", e)))?;
+    Ok(Some(ids))
+}
+
+/// 当前用户可访问的客户端
+pub async fn accessible_client(
+    auth_user: &AuthUser,
+    client_id: i64,
+    db: &DatabaseConnection,
+) -> Result<client::Model, AccessError> {
+    let client = match Client::find_by_id(client_id).one(db).await {
+        Ok(Some(c)) => c,
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "客户端不存在".to_string())),
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("查询客户端失败: {}", e))),
+    };
+    let allowed = allowed_user_ids(auth_user, db).await?;
+    if !owner_allowed(allowed.as_deref(), client.user_id) {
+        return Err((StatusCode::NOT_FOUND, "客户端不存在".to_string()));
+    }
+    Ok(client)
+}
+
+/// 当前用户可访问的代理（按代理所属客户端的用户判断）
+pub async fn accessible_proxy(
+    auth_user: &AuthUser,
+    proxy_id: i64,
+    db: &DatabaseConnection,
+) -> Result<proxy::Model, AccessError> {
+    let proxy = match Proxy::find_by_id(proxy_id).one(db).await {
+        Ok(Some(p)) => p,
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "代理不存在".to_string())),
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("查询代理失败: {}", e))),
+    };
+    let allowed = allowed_user_ids(auth_user, db).await?;
+    if allowed.is_some() {
+        let owners = client_owners(std::slice::from_ref(&proxy), db).await?;
+        if !owner_allowed(allowed.as_deref(), owners.get(&proxy.client_id).copied().flatten()) {
+            return Err((StatusCode::NOT_FOUND, "代理不存在".to_string()));
+        }
+    }
+    Ok(proxy)
+}
+
+/// 当前用户可访问的代理组，组内任一代理无权访问时整组按不存在处理
+pub async fn accessible_proxy_group(
+    auth_user: &AuthUser,
+    group_id: &str,
+    db: &DatabaseConnection,
+) -> Result<Vec<proxy::Model>, AccessError> {
+    let proxies = Proxy::find()
+        .filter(proxy::Column::GroupId.eq(group_id))
+        .all(db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询代理组失败: {}", e)))?;
+    if proxies.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "代理组不存在".to_string()));
+    }
+    let allowed = allowed_user_ids(auth_user, db).await?;
+    if allowed.is_some() {
+        let owners = client_owners(&proxies, db).await?;
+        if !proxies.iter().all(|p| owner_allowed(allowed.as_deref(), owners.get(&p.client_id).copied().flatten())) {
+            return Err((StatusCode::NOT_FOUND, "代理组不存在".to_string()));
+        }
+    }
+    Ok(proxies)
+}
+
+/// 代理所属客户端的用户（键为代理的 `client_id`）
+async fn client_owners(
+    proxies: &[proxy::Model],
+    db: &DatabaseConnection,
+) -> Result<HashMap<String, Option<i64>>, AccessError> {
+    let client_ids: HashSet<i64> = proxies.iter().filter_map(|p| p.client_id.parse().ok()).collect();
+    let clients = Client::find()
+        .filter(client::Column::Id.is_in(client_ids))
+        .all(db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询客户端失败: {}", e)))?;
+    Ok(clients.into_iter().map(|c| (c.id.to_string(), c.user_id)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: i64, is_admin: bool, tenant_id: Option<i64>, is_tenant_admin: bool) -> AuthUser {
+        AuthUser { id, username: format!("user{}", id), is_admin, tenant_id, is_tenant_admin, impersonator: None }
+    }
+
+    #[test]
+    fn test_owner_allowed() {
+        // 平台管理员不受限制，包括不属于任何用户的客户端
+        assert_eq!(UserScope::of(&user(1, true, None, false)), UserScope::All);
+        assert!(owner_allowed(None, Some(2)));
+        assert!(owner_allowed(None, None));
+
+        // 普通用户只能访问自己的资源
+        assert_eq!(UserScope::of(&user(2, false, None, false)), UserScope::Own(2));
+        assert!(owner_allowed(Some(&[2]), Some(2)));
+        assert!(!owner_allowed(Some(&[2]), Some(3)));
+        assert!(!owner_allowed(Some(&[2]), None));
+
+        // 租户管理员可以访问本租户用户的资源
+        assert_eq!(UserScope::of(&user(4, false, Some(9), true)), UserScope::Tenant(9));
+        assert!(owner_allowed(Some(&[4, 5]), Some(5)));
+        assert!(!owner_allowed(Some(&[4, 5]), Some(6)));
+
+        // 租户内的普通成员不因 is_admin 获得全部权限
+        assert_eq!(UserScope::of(&user(5, true, Some(9), false)), UserScope::Own(5));
+    }
+
+    #[test]
+    fn test_client_owner_condition() {
+        use sea_orm::{DbBackend, QueryTrait};
+
+        // 平台管理员不加条件
+        assert!(client_owner_condition(None).is_none());
+
+        // 租户管理员的代理列表、流量统计按本租户所有用户的客户端过滤，而不只是自己的
+        let condition = client_owner_condition(Some(&[4, 5])).unwrap();
+        let sql = Client::find().filter(condition).build(DbBackend::Sqlite).to_string();
+        assert!(sql.contains(r#""client"."user_id" IN (4, 5)"#), "{}", sql);
+    }
+}
@@ -0,0 +1,262 @@
+//! 邮箱验证和自助找回密码
+//!
+//! 链接中的令牌见 [`crate::account_token`]，邮件经 [`crate::mailer`] 发送，链接指向
+//! `public_url` 配置的 Web 界面地址。找回密码只向已验证的邮箱发送链接，并且无论账户是否存在
+//! 都返回相同的结果，避免被用来探测用户名和邮箱。
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::Extension,
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use chrono::Utc;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, EntityTrait, QueryFilter, Set};
+use serde::Deserialize;
+
+use crate::{
+    account_token::{self, Purpose},
+    auth::hash_password,
+    entity::{user, User},
+    middleware::AuthUser,
+    migration::get_connection,
+    password_policy::PasswordPolicy,
+    AppState,
+};
+
+use super::ApiResponse;
+
+/// 同一用户同一用途的邮件最短发送间隔，防止被用来轰炸邮箱
+const RESEND_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize)]
+pub struct PasswordResetRequest {
+    /// 用户名或已验证的邮箱
+    pub account: String,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetConfirm {
+    pub token: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+/// 记录一次发送，距上次同类邮件不足 [`RESEND_INTERVAL`] 时返回 false
+fn allow_send(user_id: i64, purpose: Purpose) -> bool {
+    static SENT: OnceLock<Mutex<HashMap<(i64, Purpose), Instant>>> = OnceLock::new();
+    let now = Instant::now();
+    let mut sent = SENT.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    sent.retain(|_, at| now.duration_since(*at) < RESEND_INTERVAL);
+    if sent.contains_key(&(user_id, purpose)) {
+        return false;
+    }
+    sent.insert((user_id, purpose), now);
+    true
+}
+
+/// 签发令牌并生成指向 Web 界面 `path` 页面的链接
+async fn signed_link(app_state: &AppState, purpose: Purpose, user: &user::Model, path: &str) -> Result<String, String> {
+    if !crate::mailer::enabled() {
+        return Err("未配置邮件发送（OXIPROXY_MAIL_WEBHOOK_URL）".to_string());
+    }
+    let public_url = app_state.config_manager.get_string("public_url", "").await;
+    let public_url = public_url.trim().trim_end_matches('/');
+    if public_url.is_empty() {
+        return Err("未配置 Web 界面外部地址（public_url）".to_string());
+    }
+    let secret = app_state.config.get_jwt_secret().map_err(|e| format!("JWT 配置错误: {}", e))?;
+    let minutes = app_state.config_manager.get_number("password_reset_token_minutes", 30).await.max(5);
+    let expires_at = Utc::now().timestamp() + minutes * 60;
+    let token = account_token::issue(purpose, user, &secret, expires_at).ok_or_else(|| "用户未设置邮箱".to_string())?;
+    Ok(format!("{}/{}?token={}", public_url, path, token))
+}
+
+/// 向用户当前的邮箱发送验证链接
+pub(crate) async fn send_verification_email(app_state: &AppState, user: &user::Model) -> Result<(), String> {
+    let Some(email) = user.email.as_deref() else {
+        return Err("用户未设置邮箱".to_string());
+    };
+    let link = signed_link(app_state, Purpose::VerifyEmail, user, "verify-email").await?;
+    if !allow_send(user.id, Purpose::VerifyEmail) {
+        return Err("验证邮件发送过于频繁，请稍后再试".to_string());
+    }
+    crate::mailer::send(
+        email,
+        "验证您的 OxiProxy 邮箱",
+        format!("您好 {}：\n\n请打开以下链接验证邮箱：\n{}\n\n如果这不是您的操作，请忽略本邮件。", user.username, link),
+    );
+    tracing::info!("已向用户 '{}' 发送邮箱验证链接", user.username);
+    Ok(())
+}
+
+/// 向用户已验证的邮箱发送密码重置链接
+pub(crate) async fn send_password_reset_email(app_state: &AppState, user: &user::Model) -> Result<(), String> {
+    let Some(email) = user.email.as_deref().filter(|_| user.email_verified_at.is_some()) else {
+        return Err("用户没有已验证的邮箱".to_string());
+    };
+    let link = signed_link(app_state, Purpose::ResetPassword, user, "reset-password").await?;
+    if !allow_send(user.id, Purpose::ResetPassword) {
+        return Err("重置邮件发送过于频繁，请稍后再试".to_string());
+    }
+    crate::mailer::send(
+        email,
+        "重置您的 OxiProxy 密码",
+        format!(
+            "您好 {}：\n\n请打开以下链接设置新密码，链接只能使用一次：\n{}\n\n如果这不是您的操作，请忽略本邮件，您的密码不会改变。",
+            user.username, link
+        ),
+    );
+    tracing::info!("已向用户 '{}' 发送密码重置链接", user.username);
+    Ok(())
+}
+
+/// 校验令牌并取出对应的用户，令牌与用户当前状态不符时视为无效
+async fn token_user(app_state: &AppState, purpose: Purpose, token: &str) -> Result<user::Model, (StatusCode, String)> {
+    let invalid = || (StatusCode::BAD_REQUEST, "链接无效或已过期".to_string());
+    let secret = app_state
+        .config
+        .get_jwt_secret()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("JWT 配置错误: {}", e)))?;
+    let parsed = account_token::verify(purpose, token, &secret, Utc::now().timestamp()).ok_or_else(invalid)?;
+    let db = get_connection().await;
+    match User::find_by_id(parsed.user_id).one(db).await {
+        Ok(Some(user)) if parsed.matches(purpose, &user) => Ok(user),
+        Ok(_) => Err(invalid()),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("查询用户失败: {}", e))),
+    }
+}
+
+/// POST /api/auth/password-reset/request - 向账户已验证的邮箱发送密码重置链接
+pub async fn request_password_reset(
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<PasswordResetRequest>,
+) -> impl IntoResponse {
+    const ACCEPTED: &str = "如果账户存在且绑定了已验证的邮箱，重置链接已发送";
+
+    let account = req.account.trim();
+    if account.is_empty() {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<&str>::error("请输入用户名或邮箱".to_string()));
+    }
+    if !crate::mailer::enabled() {
+        return (StatusCode::SERVICE_UNAVAILABLE, ApiResponse::<&str>::error("系统未开启邮件找回密码，请联系管理员".to_string()));
+    }
+
+    let db = get_connection().await;
+    let users = match User::find()
+        .filter(
+            Condition::any()
+                .add(user::Column::Username.eq(account))
+                .add(Condition::all().add(user::Column::Email.eq(account)).add(user::Column::EmailVerifiedAt.is_not_null())),
+        )
+        .all(db)
+        .await
+    {
+        Ok(users) => users,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<&str>::error(format!("查询用户失败: {}", e))),
+    };
+
+    for user in &users {
+        if let Err(reason) = send_password_reset_email(&app_state, user).await {
+            tracing::warn!("未向用户 '{}' 发送密码重置链接: {}", user.username, reason);
+        }
+    }
+    (StatusCode::OK, ApiResponse::success(ACCEPTED))
+}
+
+/// POST /api/auth/password-reset/confirm - 使用重置链接中的令牌设置新密码
+pub async fn confirm_password_reset(
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<PasswordResetConfirm>,
+) -> impl IntoResponse {
+    let user = match token_user(&app_state, Purpose::ResetPassword, &req.token).await {
+        Ok(user) => user,
+        Err((status, msg)) => return (status, ApiResponse::<&str>::error(msg)),
+    };
+
+    let policy = PasswordPolicy::load(&app_state.config_manager).await;
+    if let Err(reason) = policy.check(&req.new_password, &user.username).await {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<&str>::error(reason));
+    }
+    let password_hash = match hash_password(&req.new_password) {
+        Ok(hash) => hash,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<&str>::error(format!("密码加密失败: {}", e))),
+    };
+
+    // 仅在密码哈希仍与令牌绑定时更新，同一链接并发提交也只有一次生效
+    let db = get_connection().await;
+    let result = User::update_many()
+        .col_expr(user::Column::PasswordHash, Expr::value(password_hash))
+        .col_expr(user::Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+        .filter(user::Column::Id.eq(user.id))
+        .filter(user::Column::PasswordHash.eq(user.password_hash.as_str()))
+        .exec(db)
+        .await;
+    match result {
+        Ok(r) if r.rows_affected == 1 => {
+            tracing::info!("用户 '{}' 通过邮件链接重置了密码", user.username);
+            (StatusCode::OK, ApiResponse::success("密码已重置，请使用新密码登录"))
+        }
+        Ok(_) => (StatusCode::BAD_REQUEST, ApiResponse::<&str>::error("链接无效或已过期".to_string())),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<&str>::error(format!("重置密码失败: {}", e))),
+    }
+}
+
+/// POST /api/auth/verify-email - 使用验证链接中的令牌确认邮箱
+pub async fn verify_email(
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<VerifyEmailRequest>,
+) -> impl IntoResponse {
+    let user = match token_user(&app_state, Purpose::VerifyEmail, &req.token).await {
+        Ok(user) => user,
+        Err((status, msg)) => return (status, ApiResponse::<&str>::error(msg)),
+    };
+    if user.email_verified_at.is_some() {
+        return (StatusCode::OK, ApiResponse::success("邮箱已验证"));
+    }
+
+    let username = user.username.clone();
+    let mut active: user::ActiveModel = user.into();
+    active.email_verified_at = Set(Some(Utc::now().naive_utc()));
+    match active.update(get_connection().await).await {
+        Ok(_) => {
+            tracing::info!("用户 '{}' 验证了邮箱", username);
+            (StatusCode::OK, ApiResponse::success("邮箱已验证"))
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<&str>::error(format!("更新用户失败: {}", e))),
+    }
+}
+
+/// POST /api/auth/me/email/verify - 重新发送当前用户的邮箱验证链接
+pub async fn resend_email_verification(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<&str>::error("未认证".to_string()));
+    };
+
+    let db = get_connection().await;
+    let user = match User::find_by_id(auth_user.id).one(db).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<&str>::error("用户不存在".to_string())),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<&str>::error(format!("查询用户失败: {}", e))),
+    };
+    if user.email_verified_at.is_some() {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<&str>::error("邮箱已验证".to_string()));
+    }
+    match send_verification_email(&app_state, &user).await {
+        Ok(()) => (StatusCode::OK, ApiResponse::success("验证邮件已发送")),
+        Err(reason) => (StatusCode::BAD_REQUEST, ApiResponse::<&str>::error(reason)),
+    }
+}
+
@@ -0,0 +1,226 @@
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, QueryFilter, QueryOrder, QuerySelect, Set};
+use serde::Deserialize;
+
+use crate::alerting::validate_rule;
+use crate::entity::{alert_event, alert_rule, AlertEvent, AlertRule};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use super::ApiResponse;
+
+/// 告警规则只能由平台管理员管理
+fn require_platform_admin(auth_user: Option<AuthUser>) -> Result<AuthUser, (StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+    match auth_user {
+        Some(user) if user.is_admin && user.tenant_id.is_none() => Ok(user),
+        Some(_) => Err((StatusCode::FORBIDDEN, ApiResponse::error("仅平台管理员".to_string()))),
+        None => Err((StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()))),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateAlertRuleRequest {
+    pub name: String,
+    /// node_offline / client_offline / user_traffic / proxy_error_rate
+    pub kind: String,
+    #[serde(default)]
+    pub threshold: f64,
+    #[serde(rename = "durationSecs", default)]
+    pub duration_secs: i64,
+    pub severity: String,
+    #[serde(rename = "targetId")]
+    pub target_id: Option<i64>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateAlertRuleRequest {
+    pub name: Option<String>,
+    pub threshold: Option<f64>,
+    #[serde(rename = "durationSecs")]
+    pub duration_secs: Option<i64>,
+    pub severity: Option<String>,
+    #[serde(rename = "targetId")]
+    pub target_id: Option<Option<i64>>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct AlertEventListQuery {
+    /// firing / resolved
+    pub state: Option<String>,
+    #[serde(rename = "ruleId")]
+    pub rule_id: Option<i64>,
+}
+
+/// GET /api/alerts/rules - 获取告警规则
+pub async fn list_alert_rules(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    match AlertRule::find().order_by_asc(alert_rule::Column::Id).all(db).await {
+        Ok(rules) => (StatusCode::OK, ApiResponse::success(serde_json::json!(rules))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("查询告警规则失败: {}", e)),
+        ),
+    }
+}
+
+/// POST /api/alerts/rules - 添加告警规则
+pub async fn create_alert_rule(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Json(req): Json<CreateAlertRuleRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let name = req.name.trim().to_string();
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("规则名称不能为空".to_string()));
+    }
+    if let Err(e) = validate_rule(&req.kind, req.threshold, req.duration_secs, &req.severity) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(e));
+    }
+
+    let now = Utc::now().naive_utc();
+    let rule = alert_rule::ActiveModel {
+        id: NotSet,
+        name: Set(name),
+        kind: Set(req.kind),
+        threshold: Set(req.threshold),
+        duration_secs: Set(req.duration_secs),
+        severity: Set(req.severity),
+        target_id: Set(req.target_id),
+        enabled: Set(req.enabled.unwrap_or(true)),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    let db = get_connection().await;
+    match rule.insert(db).await {
+        Ok(rule) => (StatusCode::OK, ApiResponse::success(serde_json::json!(rule))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("添加告警规则失败: {}", e)),
+        ),
+    }
+}
+
+/// PUT /api/alerts/rules/{id} - 修改告警规则（类型不可修改）
+pub async fn update_alert_rule(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Json(req): Json<UpdateAlertRuleRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    let rule = match AlertRule::find_by_id(id).one(db).await {
+        Ok(Some(r)) => r,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("规则不存在".to_string())),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询告警规则失败: {}", e)),
+            )
+        }
+    };
+
+    let threshold = req.threshold.unwrap_or(rule.threshold);
+    let duration_secs = req.duration_secs.unwrap_or(rule.duration_secs);
+    let severity = req.severity.unwrap_or_else(|| rule.severity.clone());
+    if let Err(e) = validate_rule(&rule.kind, threshold, duration_secs, &severity) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(e));
+    }
+
+    let mut active: alert_rule::ActiveModel = rule.into();
+    if let Some(name) = req.name {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return (StatusCode::BAD_REQUEST, ApiResponse::error("规则名称不能为空".to_string()));
+        }
+        active.name = Set(name);
+    }
+    active.threshold = Set(threshold);
+    active.duration_secs = Set(duration_secs);
+    active.severity = Set(severity);
+    if let Some(target_id) = req.target_id {
+        active.target_id = Set(target_id);
+    }
+    if let Some(enabled) = req.enabled {
+        active.enabled = Set(enabled);
+    }
+    active.updated_at = Set(Utc::now().naive_utc());
+
+    match active.update(db).await {
+        Ok(rule) => (StatusCode::OK, ApiResponse::success(serde_json::json!(rule))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("修改告警规则失败: {}", e)),
+        ),
+    }
+}
+
+/// DELETE /api/alerts/rules/{id} - 删除告警规则（其未恢复的告警在下次求值时恢复）
+pub async fn delete_alert_rule(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    match AlertRule::delete_by_id(id).exec(db).await {
+        Ok(res) if res.rows_affected == 0 => (StatusCode::NOT_FOUND, ApiResponse::error("规则不存在".to_string())),
+        Ok(_) => (StatusCode::OK, ApiResponse::success(serde_json::json!(null))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("删除告警规则失败: {}", e)),
+        ),
+    }
+}
+
+/// GET /api/alerts/events - 告警事件（最近 200 条）
+pub async fn list_alert_events(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Query(query): Query<AlertEventListQuery>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let mut select = AlertEvent::find();
+    if let Some(state) = query.state {
+        select = select.filter(alert_event::Column::State.eq(state));
+    }
+    if let Some(rule_id) = query.rule_id {
+        select = select.filter(alert_event::Column::RuleId.eq(rule_id));
+    }
+
+    let db = get_connection().await;
+    match select
+        .order_by_desc(alert_event::Column::Id)
+        .limit(200)
+        .all(db)
+        .await
+    {
+        Ok(events) => (StatusCode::OK, ApiResponse::success(serde_json::json!(events))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("查询告警事件失败: {}", e)),
+        ),
+    }
+}
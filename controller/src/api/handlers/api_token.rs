@@ -0,0 +1,128 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, QueryFilter, QueryOrder, Set};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::entity::{api_token, ApiToken};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use super::ApiResponse;
+
+/// 每个用户最多保留的 API 令牌数
+const MAX_TOKENS_PER_USER: usize = 50;
+
+#[derive(Deserialize)]
+pub struct CreateApiTokenRequest {
+    pub name: String,
+    /// 有效天数，不指定表示不过期
+    #[serde(rename = "expiresInDays")]
+    pub expires_in_days: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct CreateApiTokenResponse {
+    #[serde(flatten)]
+    pub record: api_token::Model,
+    /// 令牌原文，只在创建时返回一次
+    pub token: String,
+}
+
+/// GET /api/auth/me/api-tokens - 当前用户的 API 令牌
+pub async fn list_api_tokens(Extension(auth_user): Extension<Option<AuthUser>>) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<Vec<api_token::Model>>::error("未认证".to_string()));
+    };
+    let db = get_connection().await;
+    match ApiToken::find()
+        .filter(api_token::Column::UserId.eq(auth_user.id))
+        .order_by_desc(api_token::Column::Id)
+        .all(db)
+        .await
+    {
+        Ok(tokens) => (StatusCode::OK, ApiResponse::success(tokens)),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("查询 API 令牌失败: {}", e))),
+    }
+}
+
+/// POST /api/auth/me/api-tokens - 创建 API 令牌（需要二次验证）
+pub async fn create_api_token(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Json(req): Json<CreateApiTokenRequest>,
+) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<CreateApiTokenResponse>::error("未认证".to_string()));
+    };
+    // 模拟登录的管理员不能以被模拟用户的身份签发令牌（模拟会话为只读，这里再明确拒绝一次）
+    if auth_user.impersonator.is_some() {
+        return (StatusCode::FORBIDDEN, ApiResponse::error("模拟登录期间不能创建 API 令牌".to_string()));
+    }
+    let name = req.name.trim();
+    if name.is_empty() || name.chars().count() > 64 {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("令牌名称不能为空且不超过 64 个字符".to_string()));
+    }
+    if req.expires_in_days == Some(0) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("有效天数必须大于 0".to_string()));
+    }
+
+    let db = get_connection().await;
+    match ApiToken::find().filter(api_token::Column::UserId.eq(auth_user.id)).all(db).await {
+        Ok(tokens) if tokens.len() >= MAX_TOKENS_PER_USER => {
+            return (
+                StatusCode::CONFLICT,
+                ApiResponse::error(format!("API 令牌已达上限（{} 个），请先吊销不再使用的令牌", MAX_TOKENS_PER_USER)),
+            )
+        }
+        Ok(_) => {}
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("查询 API 令牌失败: {}", e))),
+    }
+
+    let token = crate::api_token::generate();
+    let now = Utc::now().naive_utc();
+    let record = api_token::ActiveModel {
+        id: NotSet,
+        user_id: Set(auth_user.id),
+        name: Set(name.to_string()),
+        token_hash: Set(crate::api_token::hash(&token)),
+        prefix: Set(crate::api_token::display_prefix(&token)),
+        expires_at: Set(req.expires_in_days.map(|days| now + chrono::Duration::days(days as i64))),
+        last_used_at: Set(None),
+        created_at: Set(now),
+    };
+    match record.insert(db).await {
+        Ok(record) => {
+            info!("用户 {} 创建了 API 令牌「{}」(#{})", auth_user.username, record.name, record.id);
+            (StatusCode::OK, ApiResponse::success(CreateApiTokenResponse { record, token }))
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("创建 API 令牌失败: {}", e))),
+    }
+}
+
+/// DELETE /api/auth/me/api-tokens/{id} - 吊销 API 令牌
+pub async fn revoke_api_token(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<&str>::error("未认证".to_string()));
+    };
+    let db = get_connection().await;
+    match ApiToken::delete_many()
+        .filter(api_token::Column::Id.eq(id))
+        .filter(api_token::Column::UserId.eq(auth_user.id))
+        .exec(db)
+        .await
+    {
+        Ok(res) if res.rows_affected > 0 => {
+            info!("用户 {} 吊销了 API 令牌 #{}", auth_user.username, id);
+            (StatusCode::OK, ApiResponse::success("API 令牌已吊销"))
+        }
+        Ok(_) => (StatusCode::NOT_FOUND, ApiResponse::error("API 令牌不存在".to_string())),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("吊销 API 令牌失败: {}", e))),
+    }
+}
@@ -1,18 +1,21 @@
 use axum::{
     extract::Extension,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Json},
 };
 
 use crate::{
-    auth::{hash_password, verify_password},
+    auth::{hash_password, needs_rehash, verify_password},
     entity::User,
     jwt::generate_token,
     middleware::AuthUser,
     migration::get_connection,
+    password_policy::PasswordPolicy,
+    session_guard,
     AppState,
 };
 use chrono::Utc;
+use std::time::Duration;
 use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, QueryFilter, Set};
 use serde::{Deserialize, Serialize};
 
@@ -29,6 +32,11 @@ pub struct UserInfo {
     pub id: i64,
     pub username: String,
     pub is_admin: bool,
+    pub tenant_id: Option<i64>,
+    pub is_tenant_admin: bool,
+    /// 模拟登录时为发起的管理员用户名
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub impersonator: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -86,6 +94,21 @@ pub async fn login(
         }
     };
 
+    // 旧的 bcrypt 哈希在登录成功时透明升级为 Argon2id
+    if needs_rehash(&user.password_hash) {
+        match hash_password(&req.password) {
+            Ok(password_hash) => {
+                let mut active: crate::entity::user::ActiveModel = user.clone().into();
+                active.password_hash = Set(password_hash);
+                match active.update(db).await {
+                    Ok(_) => tracing::info!("用户 '{}' 的密码哈希已升级为 Argon2id", user.username),
+                    Err(e) => tracing::warn!("升级用户 '{}' 的密码哈希失败: {}", user.username, e),
+                }
+            }
+            Err(e) => tracing::warn!("升级用户 '{}' 的密码哈希失败: {}", user.username, e),
+        }
+    }
+
     // Get JWT secret from config
     let jwt_secret = match app_state.config.get_jwt_secret() {
         Ok(secret) => secret,
@@ -99,9 +122,7 @@ pub async fn login(
 
     // Generate JWT token
     let token = match generate_token(
-        user.id,
-        &user.username,
-        user.is_admin,
+        &user,
         &jwt_secret,
         app_state.config.jwt_expiration_hours,
     ) {
@@ -120,6 +141,9 @@ pub async fn login(
             id: user.id,
             username: user.username,
             is_admin: user.is_admin,
+            tenant_id: user.tenant_id,
+            is_tenant_admin: user.is_tenant_admin,
+            impersonator: None,
         },
     };
 
@@ -136,11 +160,60 @@ pub async fn me(Extension(auth_user): Extension<Option<AuthUser>>) -> impl IntoR
         id: auth_user.id,
         username: auth_user.username,
         is_admin: auth_user.is_admin,
+        tenant_id: auth_user.tenant_id,
+        is_tenant_admin: auth_user.is_tenant_admin,
+        impersonator: auth_user.impersonator.map(|i| i.username),
     };
 
     (StatusCode::OK, ApiResponse::success(user_info))
 }
 
+#[derive(Deserialize)]
+pub struct ReauthRequest {
+    pub password: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReauthResponse {
+    /// 二次验证的有效时间（秒）
+    pub expires_in_secs: u64,
+}
+
+/// POST /api/auth/reauth - 重新输入密码，允许当前会话在一段时间内执行敏感操作
+pub async fn reauth(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<ReauthRequest>,
+) -> impl IntoResponse {
+    let (Some(auth_user), Some(key)) = (auth_user, session_guard::session_key(&headers)) else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<ReauthResponse>::error("未认证".to_string()));
+    };
+
+    let db = get_connection().await;
+    let user = match User::find_by_id(auth_user.id).one(db).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("用户不存在".to_string())),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("查询用户失败: {}", e))),
+    };
+    match verify_password(&req.password, &user.password_hash) {
+        Ok(true) => {}
+        Ok(false) => {
+            tracing::warn!("用户 '{}' 二次验证密码错误", user.username);
+            return (StatusCode::FORBIDDEN, ApiResponse::error("密码不正确".to_string()));
+        }
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("验证密码失败: {}", e))),
+    }
+
+    let minutes = app_state.config_manager.get_number("elevated_action_window_minutes", 5).await.max(1) as u64;
+    if !session_guard::elevate(key, Duration::from_secs(minutes * 60)).await {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::error("会话已失效，请重新登录".to_string()));
+    }
+    tracing::info!("用户 '{}' 通过二次验证，{} 分钟内可执行敏感操作", user.username, minutes);
+    (StatusCode::OK, ApiResponse::success(ReauthResponse { expires_in_secs: minutes * 60 }))
+}
+
 #[derive(Deserialize)]
 pub struct RegisterRequest {
     pub username: String,
@@ -184,11 +257,9 @@ pub async fn register(
     }
 
     // 校验密码
-    if req.password.len() < 6 {
-        return (
-            StatusCode::BAD_REQUEST,
-            ApiResponse::<LoginResponse>::error("密码长度不能少于 6 个字符".to_string()),
-        );
+    let policy = PasswordPolicy::load(&app_state.config_manager).await;
+    if let Err(reason) = policy.check(&req.password, &username).await {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<LoginResponse>::error(reason));
     }
 
     let db = get_connection().await;
@@ -242,6 +313,14 @@ pub async fn register(
         allowed_port_range: Set(None),
         max_node_count: Set(None),
         max_client_count: Set(None),
+        speed_limit: Set(None),
+        speed_limit_schedule: Set(None),
+        tenant_id: Set(None),
+        is_tenant_admin: Set(false),
+        display_name: Set(None),
+        email: Set(None),
+        email_verified_at: Set(None),
+        lock_version: Set(0),
         created_at: Set(now),
         updated_at: Set(now),
     };
@@ -268,9 +347,7 @@ pub async fn register(
     };
 
     let token = match generate_token(
-        user.id,
-        &user.username,
-        user.is_admin,
+        &user,
         &jwt_secret,
         app_state.config.jwt_expiration_hours,
     ) {
@@ -289,6 +366,9 @@ pub async fn register(
             id: user.id,
             username: user.username,
             is_admin: user.is_admin,
+            tenant_id: user.tenant_id,
+            is_tenant_admin: user.is_tenant_admin,
+            impersonator: None,
         },
     };
 
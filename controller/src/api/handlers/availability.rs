@@ -0,0 +1,42 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+use crate::api::access;
+use crate::entity::maintenance_window::{SCOPE_CLIENT, SCOPE_NODE, SCOPE_PROXY};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use super::ApiResponse;
+
+/// GET /api/availability/{scope}/{id} - 节点 / 客户端 / 代理最近 24 小时、7 天、30 天的可用率
+///
+/// 节点对所有登录用户可见；客户端和代理只能查看自己（租户管理员：本租户内用户）的。
+pub async fn get_availability(
+    Path((scope, id)): Path<(String, i64)>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("未认证".to_string()));
+    };
+
+    let db = get_connection().await;
+    let checked = match scope.as_str() {
+        SCOPE_NODE => Ok(()),
+        SCOPE_CLIENT => access::accessible_client(&auth_user, id, db).await.map(|_| ()),
+        SCOPE_PROXY => access::accessible_proxy(&auth_user, id, db).await.map(|_| ()),
+        _ => return (StatusCode::BAD_REQUEST, ApiResponse::error(format!("无效的统计对象: {}", scope))),
+    };
+    if let Err((status, e)) = checked {
+        return (status, ApiResponse::error(e));
+    }
+
+    match crate::availability::report(db, &scope, id).await {
+        Ok(periods) => (StatusCode::OK, ApiResponse::success(serde_json::json!(periods))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("计算可用率失败: {}", e)),
+        ),
+    }
+}
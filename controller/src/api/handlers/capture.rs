@@ -0,0 +1,257 @@
+//! 代理按需抓包：发起、查询、下载和删除
+
+use axum::{
+    body::Body,
+    extract::{Extension, Path, Query},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, NotSet, QueryFilter, QueryOrder, QuerySelect, Set};
+use serde::Deserialize;
+use tokio_util::io::ReaderStream;
+use tracing::{info, warn};
+
+use crate::entity::proxy_capture::{self, STATUS_COMPLETED, STATUS_DELETED, STATUS_FAILED, STATUS_RUNNING};
+use crate::entity::{Proxy, ProxyCapture};
+use crate::migration::get_connection;
+use crate::{middleware::AuthUser, AppState};
+
+use super::ApiResponse;
+
+const DEFAULT_DURATION_SECS: u32 = 60;
+
+fn require_platform_admin(auth_user: Option<AuthUser>) -> Result<AuthUser, (StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+    match auth_user {
+        Some(user) if user.is_admin && user.tenant_id.is_none() => Ok(user),
+        Some(_) => Err((StatusCode::FORBIDDEN, ApiResponse::error("仅平台管理员".to_string()))),
+        None => Err((StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()))),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct StartCaptureRequest {
+    /// 抓包时长（秒），默认 60，不超过 `capture_max_duration_secs`
+    #[serde(rename = "durationSecs")]
+    pub duration_secs: Option<u32>,
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ListCapturesQuery {
+    #[serde(rename = "proxyId")]
+    pub proxy_id: Option<i64>,
+}
+
+/// POST /api/proxies/{id}/captures - 在代理所在节点上开始抓包
+pub async fn start_proxy_capture(
+    Path(proxy_id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<StartCaptureRequest>,
+) -> impl IntoResponse {
+    let auth_user = match require_platform_admin(auth_user) {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+    let db = get_connection().await;
+
+    let proxy = match Proxy::find_by_id(proxy_id).one(db).await {
+        Ok(Some(p)) => p,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("代理不存在".to_string())),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("查询代理失败: {}", e))),
+    };
+    if proxy.proxy_type.eq_ignore_ascii_case("udp") {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("只支持抓取 TCP 代理".to_string()));
+    }
+    let Some(node_id) = proxy.node_id else {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("代理未分配节点".to_string()));
+    };
+
+    let config = &app_state.config_manager;
+    let max_duration = config.get_number("capture_max_duration_secs", 600).await.max(1) as u32;
+    let duration = req.duration_secs.unwrap_or(DEFAULT_DURATION_SECS);
+    if duration == 0 || duration > max_duration {
+        return (
+            StatusCode::BAD_REQUEST,
+            ApiResponse::error(format!("抓包时长需在 1 ~ {} 秒之间", max_duration)),
+        );
+    }
+    let max_bytes = config.get_number("capture_max_size_mb", 100).await.max(1) as u64 * 1024 * 1024;
+    let retention_hours = config.get_number("capture_retention_hours", 24).await.max(1);
+    let reason = req.reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+
+    let now = Utc::now().naive_utc();
+    let record = proxy_capture::ActiveModel {
+        id: NotSet,
+        proxy_id: Set(proxy_id),
+        proxy_name: Set(proxy.name.clone()),
+        node_id: Set(node_id),
+        user_id: Set(auth_user.id),
+        username: Set(auth_user.username.clone()),
+        reason: Set(reason.clone()),
+        duration_secs: Set(duration as i32),
+        status: Set(STATUS_RUNNING.to_string()),
+        size_bytes: Set(0),
+        dropped_packets: Set(0),
+        error: Set(None),
+        download_count: Set(0),
+        created_at: Set(now),
+        completed_at: Set(None),
+        expires_at: Set(now + chrono::Duration::hours(retention_hours)),
+    };
+    let record = match record.insert(db).await {
+        Ok(r) => r,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("记录抓包审计日志失败: {}", e))),
+    };
+
+    if let Err(e) = app_state
+        .node_manager
+        .start_capture(node_id, &record.id.to_string(), proxy_id, duration, max_bytes)
+        .await
+    {
+        let message = e.to_string();
+        let mut active = record.into_active_model();
+        active.status = Set(STATUS_FAILED.to_string());
+        active.error = Set(Some(message.clone()));
+        active.completed_at = Set(Some(Utc::now().naive_utc()));
+        let _ = active.update(db).await;
+        return (StatusCode::BAD_GATEWAY, ApiResponse::error(message));
+    }
+
+    warn!(
+        "管理员 {} 开始抓包 #{}: 代理 #{} ({})，节点 #{}，{} 秒（原因: {}）",
+        auth_user.username,
+        record.id,
+        proxy_id,
+        proxy.name,
+        node_id,
+        duration,
+        reason.as_deref().unwrap_or("-")
+    );
+    (StatusCode::OK, ApiResponse::success(serde_json::json!(record)))
+}
+
+/// GET /api/captures - 抓包记录（可按 proxyId 过滤）
+pub async fn list_captures(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Query(query): Query<ListCapturesQuery>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    let mut select = ProxyCapture::find();
+    if let Some(proxy_id) = query.proxy_id {
+        select = select.filter(proxy_capture::Column::ProxyId.eq(proxy_id));
+    }
+    match select.order_by_desc(proxy_capture::Column::Id).limit(200).all(db).await {
+        Ok(records) => (StatusCode::OK, ApiResponse::success(serde_json::json!(records))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("查询抓包记录失败: {}", e))),
+    }
+}
+
+/// POST /api/captures/{id}/stop - 提前结束抓包，已抓到的数据照常上传
+pub async fn stop_capture(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    let record = match ProxyCapture::find_by_id(id).one(db).await {
+        Ok(Some(r)) => r,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("抓包记录不存在".to_string())),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("查询抓包记录失败: {}", e))),
+    };
+    if record.status != STATUS_RUNNING {
+        return (StatusCode::CONFLICT, ApiResponse::error("抓包已结束".to_string()));
+    }
+    match app_state.node_manager.stop_capture(record.node_id, &id.to_string()).await {
+        Ok(()) => (StatusCode::OK, ApiResponse::success(serde_json::json!(null))),
+        Err(e) => (StatusCode::BAD_GATEWAY, ApiResponse::error(e.to_string())),
+    }
+}
+
+/// GET /api/captures/{id}/download - 下载 pcapng 文件
+pub async fn download_capture(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> Response {
+    let auth_user = match require_platform_admin(auth_user) {
+        Ok(user) => user,
+        Err(resp) => return resp.into_response(),
+    };
+
+    let db = get_connection().await;
+    let record = match ProxyCapture::find_by_id(id).one(db).await {
+        Ok(Some(r)) => r,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<()>::error("抓包记录不存在".to_string())).into_response(),
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<()>::error(format!("查询抓包记录失败: {}", e)))
+                .into_response()
+        }
+    };
+    if record.status != STATUS_COMPLETED {
+        return (StatusCode::CONFLICT, ApiResponse::<()>::error("抓包文件不可下载（未完成、失败或已删除）".to_string()))
+            .into_response();
+    }
+    let file = match tokio::fs::File::open(crate::capture::file_path(id)).await {
+        Ok(f) => f,
+        Err(e) => {
+            return (StatusCode::NOT_FOUND, ApiResponse::<()>::error(format!("读取抓包文件失败: {}", e))).into_response()
+        }
+    };
+
+    let download_count = record.download_count + 1;
+    let filename = format!("capture-{}-proxy-{}.pcapng", id, record.proxy_id);
+    let mut active = record.into_active_model();
+    active.download_count = Set(download_count);
+    let _ = active.update(db).await;
+    info!("管理员 {} 下载了抓包 #{}（第 {} 次）", auth_user.username, id, download_count);
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/x-pcapng".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response()
+}
+
+/// DELETE /api/captures/{id} - 提前删除抓包文件（记录保留）
+pub async fn delete_capture(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    let auth_user = match require_platform_admin(auth_user) {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+
+    let db = get_connection().await;
+    let record = match ProxyCapture::find_by_id(id).one(db).await {
+        Ok(Some(r)) => r,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("抓包记录不存在".to_string())),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("查询抓包记录失败: {}", e))),
+    };
+    if record.status != STATUS_COMPLETED && record.status != STATUS_FAILED {
+        return (StatusCode::CONFLICT, ApiResponse::error("抓包进行中或文件已删除".to_string()));
+    }
+
+    crate::capture::remove_file(id).await;
+    let mut active = record.into_active_model();
+    active.status = Set(STATUS_DELETED.to_string());
+    active.expires_at = Set(Utc::now().naive_utc());
+    if let Err(e) = active.update(db).await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("更新抓包记录失败: {}", e)));
+    }
+    info!("管理员 {} 删除了抓包 #{}", auth_user.username, id);
+    (StatusCode::OK, ApiResponse::success(serde_json::json!(null)))
+}
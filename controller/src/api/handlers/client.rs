@@ -1,16 +1,18 @@
 use axum::{
-    extract::{Extension, Path},
+    extract::{Extension, Path, Query},
     http::StatusCode,
     response::{IntoResponse, Json},
 };
 use chrono::Utc;
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, PaginatorTrait, QueryFilter, Set};
-use serde::Deserialize;
+use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, EntityTrait, NotSet, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{entity::Client, migration::get_connection, middleware::AuthUser};
+use crate::{entity::Client, migration::get_connection, middleware::AuthUser, tenant::UserScope, AppState};
 
 use super::ApiResponse;
+use crate::api::access;
+use crate::api::pagination::{apply_cursor, apply_sort, fetch_page, ListQuery};
 
 #[derive(Deserialize)]
 pub struct CreateClientRequest {
@@ -19,51 +21,133 @@ pub struct CreateClientRequest {
     pub region: Option<String>,
     pub traffic_reset_cycle: Option<String>,
     pub traffic_quota_gb: Option<f64>,
+    /// 到期时间，到期后禁用该客户端的所有代理并拒绝连接
+    pub expires_at: Option<chrono::DateTime<Utc>>,
 }
 
-pub async fn list_clients(Extension(auth_user_opt): Extension<Option<AuthUser>>) -> impl IntoResponse {
+/// 客户端列表过滤参数
+#[derive(Debug, Default, Deserialize)]
+pub struct ClientListFilter {
+    pub online: Option<bool>,
+    pub user_id: Option<i64>,
+    pub region: Option<String>,
+}
+
+/// 客户端列表项：客户端信息 + 名下代理的当前速率之和 + 是否处于维护窗口 + 版本与运行时长
+#[derive(Serialize)]
+pub struct ClientWithSpeed {
+    #[serde(flatten)]
+    pub client: crate::entity::client::Model,
+    #[serde(flatten)]
+    pub speed: crate::live_speed::Speed,
+    #[serde(rename = "inMaintenance")]
+    pub in_maintenance: bool,
+    /// 版本低于 Controller
+    pub outdated: bool,
+    /// 在线客户端的运行时长（秒）
+    #[serde(rename = "uptimeSecs")]
+    pub uptime_secs: Option<i64>,
+}
+
+pub async fn list_clients(
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
+    Query(list_query): Query<ListQuery>,
+    Query(filter): Query<ClientListFilter>,
+) -> impl IntoResponse {
     let auth_user = match auth_user_opt {
         Some(user) => user,
-        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<Vec<crate::entity::client::Model>>::error("Not authenticated".to_string())),
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<Vec<ClientWithSpeed>>::error("Not authenticated".to_string())),
     };
 
     let db = get_connection().await;
 
-    let clients = if auth_user.is_admin {
-        // Admin can see all clients
-        match Client::find().all(db).await {
-            Ok(clients) => clients,
-            Err(e) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    ApiResponse::<Vec<crate::entity::client::Model>>::error(format!(
-                        "Failed to list clients: {}",
-                        e
-                    )),
-                )
+    let mut select = Client::find();
+
+    match UserScope::of(&auth_user) {
+        UserScope::All => {
+            // Admin can see all clients, optionally narrowed to a single user
+            if let Some(user_id) = filter.user_id {
+                select = select.filter(crate::entity::client::Column::UserId.eq(user_id));
             }
         }
-    } else {
-        // Regular users can only see their own clients (based on client.user_id)
-        match Client::find()
-            .filter(crate::entity::client::Column::UserId.eq(auth_user.id))
-            .all(db)
-            .await
-        {
-            Ok(clients) => clients,
-            Err(e) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    ApiResponse::<Vec<crate::entity::client::Model>>::error(format!(
-                        "Failed to list clients: {}",
-                        e
-                    )),
-                )
+        UserScope::Tenant(tenant_id) => {
+            // 租户管理员可以看到本租户所有用户的客户端
+            let user_ids = match crate::tenant::tenant_user_ids(tenant_id, db).await {
+                Ok(ids) => ids,
+                Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<Vec<ClientWithSpeed>>::error(format!("Failed to list clients: {}", e))),
+            };
+            select = select.filter(crate::entity::client::Column::UserId.is_in(user_ids));
+            if let Some(user_id) = filter.user_id {
+                select = select.filter(crate::entity::client::Column::UserId.eq(user_id));
             }
         }
-    };
+        UserScope::Own(user_id) => {
+            // Regular users can only see their own clients (based on client.user_id)
+            select = select.filter(crate::entity::client::Column::UserId.eq(user_id));
+        }
+    }
 
-    (StatusCode::OK, ApiResponse::success(clients))
+    if let Some(online) = filter.online {
+        select = select.filter(crate::entity::client::Column::IsOnline.eq(online));
+    }
+    if let Some(region) = filter.region.filter(|r| !r.is_empty()) {
+        select = select.filter(crate::entity::client::Column::Region.eq(region));
+    }
+    if let Some(keyword) = list_query.keyword() {
+        select = select.filter(
+            Condition::any()
+                .add(crate::entity::client::Column::Name.contains(keyword))
+                .add(crate::entity::client::Column::PublicIp.contains(keyword))
+                .add(crate::entity::client::Column::Region.contains(keyword)),
+        );
+    }
+
+    let select = apply_sort(
+        select,
+        &list_query,
+        &[
+            ("id", crate::entity::client::Column::Id),
+            ("name", crate::entity::client::Column::Name),
+            ("online", crate::entity::client::Column::IsOnline),
+            ("region", crate::entity::client::Column::Region),
+            ("version", crate::entity::client::Column::Version),
+            ("user_id", crate::entity::client::Column::UserId),
+            ("total_bytes_sent", crate::entity::client::Column::TotalBytesSent),
+            ("total_bytes_received", crate::entity::client::Column::TotalBytesReceived),
+            ("created_at", crate::entity::client::Column::CreatedAt),
+            ("updated_at", crate::entity::client::Column::UpdatedAt),
+        ],
+        crate::entity::client::Column::Id,
+    );
+    let select = apply_cursor(select, &list_query, crate::entity::client::Column::Id);
+
+    match fetch_page(select, &list_query, db).await {
+        Ok((clients, total)) => {
+            let speeds = crate::live_speed::client_speeds();
+            let now = Utc::now().naive_utc();
+            let clients = clients
+                .into_iter()
+                .map(|client| ClientWithSpeed {
+                    speed: speeds.get(&client.id).copied().unwrap_or_default(),
+                    in_maintenance: crate::maintenance::global().client(client.id),
+                    outdated: crate::agent_version::is_outdated(client.version.as_deref()),
+                    uptime_secs: client
+                        .started_at
+                        .filter(|_| client.is_online)
+                        .map(|t| (now - t).num_seconds().max(0)),
+                    client,
+                })
+                .collect();
+            (StatusCode::OK, ApiResponse::paginated(clients, total))
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::<Vec<ClientWithSpeed>>::error(format!(
+                "Failed to list clients: {}",
+                e
+            )),
+        ),
+    }
 }
 
 pub async fn create_client(
@@ -77,6 +161,13 @@ pub async fn create_client(
 
     let db = get_connection().await;
 
+    // 检查租户客户端数量限制
+    match crate::tenant::validate_tenant_client_limit(auth_user.id, db).await {
+        Ok((true, _)) => {}
+        Ok((false, reason)) => return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::client::Model>::error(reason)),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<crate::entity::client::Model>::error(format!("查询客户端数量失败: {}", e))),
+    }
+
     // 检查客户端数量限制
     if let Ok(Some(user_model)) = crate::entity::User::find_by_id(auth_user.id).one(db).await {
         let (_, _, _, final_max_client_count) = match crate::subscription_quota::get_user_final_quota(
@@ -120,6 +211,16 @@ pub async fn create_client(
         region: Set(req.region),
         user_id: Set(Some(auth_user.id)),
         version: Set(None),
+        allowed_targets: Set(None),
+        expires_at: Set(req.expires_at.map(|t| t.naive_utc())),
+        nat_type: Set(None),
+        nat_mapped_addr: Set(None),
+        nat_detected_at: Set(None),
+        os: Set(None),
+        arch: Set(None),
+        started_at: Set(None),
+        instance_id: Set(None),
+        duplicate_login_policy: Set(crate::duplicate_login::Policy::default().as_str().to_string()),
         total_bytes_sent: Set(0),
         total_bytes_received: Set(0),
         traffic_quota_gb: Set(req.traffic_quota_gb),
@@ -144,31 +245,46 @@ pub async fn create_client(
     }
 }
 
-pub async fn get_client(Path(id): Path<i64>, Extension(_auth_user): Extension<Option<AuthUser>>) -> impl IntoResponse {
+pub async fn get_client(Path(id): Path<i64>, Extension(auth_user_opt): Extension<Option<AuthUser>>) -> impl IntoResponse {
+    let auth_user = match access::require_user(auth_user_opt) {
+        Ok(user) => user,
+        Err((status, e)) => return (status, ApiResponse::<crate::entity::client::Model>::error(e)),
+    };
     let db = get_connection().await;
-    match Client::find_by_id(id).one(db).await {
-        Ok(Some(client)) => (StatusCode::OK, ApiResponse::success(client)),
-        Ok(None) => (
-            StatusCode::NOT_FOUND,
-            ApiResponse::<crate::entity::client::Model>::error("Client not found".to_string()),
-        ),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            ApiResponse::<crate::entity::client::Model>::error(format!(
-                "Failed to get client: {}",
-                e
-            )),
-        ),
+    match access::accessible_client(&auth_user, id, db).await {
+        Ok(client) => (StatusCode::OK, ApiResponse::success(client)),
+        Err((status, e)) => (status, ApiResponse::<crate::entity::client::Model>::error(e)),
     }
 }
 
 pub async fn delete_client(
     Path(id): Path<i64>,
-    Extension(_auth_user): Extension<Option<AuthUser>>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
 ) -> impl IntoResponse {
+    let auth_user = match access::require_user(auth_user_opt) {
+        Ok(user) => user,
+        Err((status, e)) => return (status, ApiResponse::<&str>::error(e)),
+    };
     let db = get_connection().await;
+    if let Err((status, e)) = access::accessible_client(&auth_user, id, db).await {
+        return (status, ApiResponse::<&str>::error(e));
+    }
+    // proxy.client_id 只在 SQLite 上有外键级联，其他数据库需要手动删除客户端的代理
+    if let Err(e) = crate::entity::Proxy::delete_many()
+        .filter(crate::entity::proxy::Column::ClientId.eq(id.to_string()))
+        .exec(db)
+        .await
+    {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::<&str>::error(format!("Failed to delete client proxies: {}", e)),
+        );
+    }
     match Client::delete_by_id(id).exec(db).await {
-        Ok(_) => (StatusCode::OK, ApiResponse::success("Client deleted successfully")),
+        Ok(_) => {
+            crate::client_log_store::remove(id);
+            (StatusCode::OK, ApiResponse::success("Client deleted successfully"))
+        }
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             ApiResponse::<&str>::error(format!("Failed to delete client: {}", e)),
@@ -183,6 +299,7 @@ pub struct UpdateClientRequest {
     pub traffic_quota_gb: Option<f64>,
     pub traffic_reset_cycle: Option<String>,
     pub is_traffic_exceeded: Option<bool>,
+    pub expires_at: Option<Option<chrono::DateTime<Utc>>>,
 }
 
 pub async fn update_client(
@@ -224,6 +341,9 @@ pub async fn update_client(
     if let Some(exceeded) = req.is_traffic_exceeded {
         client_active.is_traffic_exceeded = Set(exceeded);
     }
+    if let Some(expires_at) = req.expires_at {
+        client_active.expires_at = Set(expires_at.map(|t| t.naive_utc()));
+    }
 
     client_active.updated_at = Set(Utc::now().naive_utc());
 
@@ -233,81 +353,177 @@ pub async fn update_client(
     }
 }
 
-/// 为客户端分配流量配额
 #[derive(Deserialize)]
-pub struct AllocateQuotaRequest {
-    pub quota_gb: f64,
+pub struct UpdateTargetPolicyRequest {
+    /// 允许转发的本地目标（例如 `127.0.0.1`、`192.168.1.0/24`），为空表示不限制
+    #[serde(rename = "allowedTargets", default)]
+    pub allowed_targets: Vec<String>,
 }
 
-pub async fn allocate_client_quota(
-    Path(client_id): Path<i64>,
+/// PUT /api/clients/{id}/target-policy - 设置客户端允许转发的本地目标白名单
+pub async fn update_client_target_policy(
+    Path(id): Path<i64>,
     Extension(auth_user_opt): Extension<Option<AuthUser>>,
-    Json(req): Json<AllocateQuotaRequest>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<UpdateTargetPolicyRequest>,
 ) -> impl IntoResponse {
     let auth_user = match auth_user_opt {
         Some(user) => user,
-        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<String>::error("未认证".to_string())),
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<crate::entity::client::Model>::error("未认证".to_string())),
     };
 
-    if req.quota_gb < 0.0 {
-        return (StatusCode::BAD_REQUEST, ApiResponse::<String>::error("配额不能为负数".to_string()));
+    let rules: Vec<String> = req
+        .allowed_targets
+        .iter()
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .collect();
+    if let Err(e) = common::target_rule::TargetRules::parse(&rules) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::client::Model>::error(e.to_string()));
     }
 
     let db = get_connection().await;
-
-    // 检查客户端是否存在
-    let client = match Client::find_by_id(client_id).one(db).await {
-        Ok(Some(c)) => c,
-        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<String>::error("客户端不存在".to_string())),
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<String>::error(format!("查询客户端失败: {}", e))),
+    // 只能修改自己（租户管理员：本租户内用户）的客户端
+    let client = match access::accessible_client(&auth_user, id, db).await {
+        Ok(c) => c,
+        Err((status, e)) => return (status, ApiResponse::<crate::entity::client::Model>::error(e)),
     };
 
-    // 获取客户端当前配额
-    let current_quota = client.traffic_quota_gb.unwrap_or(0.0);
-    let quota_diff = req.quota_gb - current_quota;
-
-    // 如果不是管理员，需要检查用户配额
-    if !auth_user.is_admin {
-        // 检查用户是否有权限访问此客户端
-        let has_access = match crate::entity::UserClient::find()
-            .filter(crate::entity::user_client::Column::UserId.eq(auth_user.id))
-            .filter(crate::entity::user_client::Column::ClientId.eq(client_id))
-            .one(db)
-            .await
-        {
-            Ok(Some(_)) => true,
-            Ok(None) => false,
-            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<String>::error(format!("检查权限失败: {}", e))),
-        };
-
-        if !has_access {
-            return (StatusCode::FORBIDDEN, ApiResponse::<String>::error("无权限访问此客户端".to_string()));
-        }
+    let mut client_active: crate::entity::client::ActiveModel = client.into();
+    client_active.allowed_targets = Set(if rules.is_empty() { None } else { Some(rules.join(",")) });
+    client_active.updated_at = Set(Utc::now().naive_utc());
 
-        // 检查用户配额是否足够（仅在增加配额时检查）
-        if quota_diff > 0.0 {
-            match crate::traffic_limiter::check_user_quota_allocation(auth_user.id, quota_diff, db).await {
-                Ok((true, _)) => {},
-                Ok((false, reason)) => return (StatusCode::BAD_REQUEST, ApiResponse::<String>::error(reason)),
-                Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<String>::error(format!("检查配额失败: {}", e))),
-            }
+    match client_active.update(db).await {
+        Ok(updated) => {
+            let csm = app_state.client_stream_manager.clone();
+            let client_id_notify = updated.id.to_string();
+            tokio::spawn(async move {
+                csm.notify_proxy_change(&client_id_notify).await;
+            });
+            (StatusCode::OK, ApiResponse::success(updated))
         }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<crate::entity::client::Model>::error(format!("更新客户端失败: {}", e))),
     }
+}
+
+#[derive(Deserialize)]
+pub struct UpdateLoginPolicyRequest {
+    /// `reject_new`、`kick_old` 或 `replicas`
+    #[serde(rename = "duplicateLoginPolicy")]
+    pub duplicate_login_policy: String,
+}
+
+/// PUT /api/clients/{id}/login-policy - 设置同一 token 多个实例同时连接时的处理策略
+///
+/// 只影响之后的连接，已在线的实例不受影响
+pub async fn update_client_login_policy(
+    Path(id): Path<i64>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
+    Json(req): Json<UpdateLoginPolicyRequest>,
+) -> impl IntoResponse {
+    let auth_user = match auth_user_opt {
+        Some(user) => user,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<crate::entity::client::Model>::error("未认证".to_string())),
+    };
+
+    let Some(policy) = crate::duplicate_login::Policy::parse(req.duplicate_login_policy.trim()) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            ApiResponse::<crate::entity::client::Model>::error("重复登录策略必须是 reject_new、kick_old 或 replicas".to_string()),
+        );
+    };
+
+    let db = get_connection().await;
+    let client = match access::accessible_client(&auth_user, id, db).await {
+        Ok(c) => c,
+        Err((status, e)) => return (status, ApiResponse::<crate::entity::client::Model>::error(e)),
+    };
 
-    // 更新客户端配额
     let mut client_active: crate::entity::client::ActiveModel = client.into();
-    client_active.traffic_quota_gb = Set(Some(req.quota_gb));
+    client_active.duplicate_login_policy = Set(policy.as_str().to_string());
     client_active.updated_at = Set(Utc::now().naive_utc());
 
     match client_active.update(db).await {
-        Ok(_) => (StatusCode::OK, ApiResponse::success(format!("配额分配成功: {:.2} GB", req.quota_gb))),
+        Ok(updated) => (StatusCode::OK, ApiResponse::success(updated)),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<crate::entity::client::Model>::error(format!("更新客户端失败: {}", e))),
+    }
+}
+
+/// GET /api/clients/{id}/login-events - 客户端最近的重复登录审计事件
+pub async fn list_client_login_events(
+    Path(id): Path<i64>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    let auth_user = match auth_user_opt {
+        Some(user) => user,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<Vec<crate::entity::client_login_event::Model>>::error("未认证".to_string())),
+    };
+
+    let db = get_connection().await;
+    if let Err((status, e)) = access::accessible_client(&auth_user, id, db).await {
+        return (status, ApiResponse::<Vec<crate::entity::client_login_event::Model>>::error(e));
+    }
+
+    match crate::entity::ClientLoginEvent::find()
+        .filter(crate::entity::client_login_event::Column::ClientId.eq(id))
+        .order_by_desc(crate::entity::client_login_event::Column::Id)
+        .limit(200)
+        .all(db)
+        .await
+    {
+        Ok(events) => (StatusCode::OK, ApiResponse::success(events)),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::<Vec<crate::entity::client_login_event::Model>>::error(format!("查询重复登录事件失败: {}", e)),
+        ),
+    }
+}
+
+/// 为客户端分配流量配额
+#[derive(Deserialize)]
+pub struct AllocateQuotaRequest {
+    /// 分配给客户端的配额（GB），为空时回收分配
+    pub quota_gb: Option<f64>,
+}
+
+pub async fn allocate_client_quota(
+    Path(client_id): Path<i64>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
+    Json(req): Json<AllocateQuotaRequest>,
+) -> impl IntoResponse {
+    let auth_user = match auth_user_opt {
+        Some(user) => user,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<String>::error("未认证".to_string())),
+    };
+
+    let db = get_connection().await;
+
+    // 只能为自己（租户管理员：本租户内用户）的客户端分配配额，平台管理员不受所属用户可用配额限制
+    if let Err((status, e)) = access::accessible_client(&auth_user, client_id, db).await {
+        return (status, ApiResponse::<String>::error(e));
+    }
+    let scope = UserScope::of(&auth_user);
+
+    let result = match req.quota_gb {
+        Some(quota_gb) => crate::quota::allocate(db, client_id, quota_gb, scope != UserScope::All).await,
+        None => crate::quota::release(db, client_id).await,
+    };
+
+    match result {
+        Ok(updated) => {
+            let message = match updated.traffic_quota_gb {
+                Some(quota_gb) => format!("配额分配成功: {:.2} GB", quota_gb),
+                None => "配额分配已回收".to_string(),
+            };
+            (StatusCode::OK, ApiResponse::success(message))
+        }
+        Err(e @ crate::quota::QuotaError::NotFound(_)) => (StatusCode::NOT_FOUND, ApiResponse::<String>::error(e.to_string())),
+        Err(e @ crate::quota::QuotaError::Rejected(_)) => (StatusCode::BAD_REQUEST, ApiResponse::<String>::error(e.to_string())),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<String>::error(format!("更新配额失败: {}", e))),
     }
 }
 
 /// 获取客户端流量详情（包含剩余配额）
-use serde::Serialize;
-
 #[derive(Serialize)]
 pub struct ClientTrafficInfo {
     pub client_id: i64,
@@ -323,14 +539,17 @@ pub struct ClientTrafficInfo {
 
 pub async fn get_client_traffic(
     Path(client_id): Path<i64>,
-    Extension(_auth_user): Extension<Option<AuthUser>>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
 ) -> impl IntoResponse {
+    let auth_user = match access::require_user(auth_user_opt) {
+        Ok(user) => user,
+        Err((status, e)) => return (status, ApiResponse::<ClientTrafficInfo>::error(e)),
+    };
     let db = get_connection().await;
 
-    let client = match Client::find_by_id(client_id).one(db).await {
-        Ok(Some(c)) => c,
-        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<ClientTrafficInfo>::error("客户端不存在".to_string())),
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<ClientTrafficInfo>::error(format!("查询失败: {}", e))),
+    let client = match access::accessible_client(&auth_user, client_id, db).await {
+        Ok(c) => c,
+        Err((status, e)) => return (status, ApiResponse::<ClientTrafficInfo>::error(e)),
     };
 
     let remaining_quota_gb = crate::traffic_limiter::calculate_client_remaining_quota(&client);
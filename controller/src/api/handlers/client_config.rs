@@ -9,7 +9,7 @@ use tracing::debug;
 use common::protocol::client_config::{
     ClientConnectConfig, ClientConnectConfigRequest,
 };
-use common::KcpConfig;
+use common::{KcpConfig, QuicConfig};
 use common::TunnelProtocol;
 
 use crate::{
@@ -61,6 +61,17 @@ pub async fn get_client_connect_config(
         );
     }
 
+    // 检查是否到期
+    if crate::expiration::is_expired(client_model.expires_at) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": "client_expired",
+                "message": format!("客户端 '{}' 已到期", client_model.name)
+            })),
+        );
+    }
+
     // 3. 查找客户端的第一个启用的代理，并获取其节点配置
     let proxies = match crate::entity::Proxy::find()
         .filter(crate::entity::proxy::Column::ClientId.eq(client_model.id.to_string()))
@@ -130,12 +141,15 @@ pub async fn get_client_connect_config(
 
     let kcp = node_model.kcp_config
         .and_then(|s| serde_json::from_str::<KcpConfig>(&s).ok());
+    let quic = node_model.quic_config
+        .and_then(|s| serde_json::from_str::<QuicConfig>(&s).ok());
 
     let config = ClientConnectConfig {
         server_addr: node_model.tunnel_addr,
         server_port: node_model.tunnel_port as u16,
         protocol,
         kcp,
+        quic,
         client_id: client_model.id,
         client_name: client_model.name,
     };
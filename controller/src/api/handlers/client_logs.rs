@@ -1,38 +1,81 @@
 use axum::{
-    extract::{Extension, Path},
+    extract::{Extension, Path, Query},
     http::StatusCode,
     response::IntoResponse,
 };
-use tracing::{error, info};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tracing::{info, warn};
 
+use crate::api::access;
+use crate::client_log_store::{self, LogFilter};
+use crate::migration::get_connection;
 use crate::{middleware::AuthUser, AppState};
 use common::protocol::control::LogEntry;
 
 use super::ApiResponse;
 
+const DEFAULT_LIMIT: usize = 200;
+const MAX_LIMIT: usize = 2000;
+
+#[derive(Deserialize)]
+pub struct ClientLogsQuery {
+    /// 最低级别（error / warn / info / debug / trace）
+    pub level: Option<String>,
+    /// 起止时间（RFC 3339）
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// 最多返回最近的 N 条，默认 200，最大 2000
+    pub limit: Option<usize>,
+}
+
 /// GET /api/clients/{id}/logs - 获取客户端日志
+///
+/// 合并客户端已上传到 Controller 的日志和在线时实时获取的日志，客户端离线时只返回已上传的部分。
 pub async fn get_client_logs(
     Path(client_id): Path<i64>,
-    Extension(_auth_user): Extension<Option<AuthUser>>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
     Extension(app_state): Extension<AppState>,
+    Query(query): Query<ClientLogsQuery>,
 ) -> impl IntoResponse {
-    info!("请求客户端 {} 的日志", client_id);
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<Vec<LogEntry>>::error("未认证".to_string()));
+    };
+    let min_level = match query.level.as_deref().filter(|l| !l.is_empty()) {
+        Some(level) => match level.parse::<tracing::Level>() {
+            Ok(level) => Some(level),
+            Err(_) => {
+                return (StatusCode::BAD_REQUEST, ApiResponse::error(format!("无效的日志级别: {}", level)));
+            }
+        },
+        None => None,
+    };
 
-    // 直接通过 ClientStreamManager 向客户端请求日志
-    match app_state.client_stream_manager.fetch_client_logs(client_id, 200).await {
-        Ok(logs) => {
-            info!("成功获取客户端 {} 的 {} 条日志", client_id, logs.len());
-            (StatusCode::OK, ApiResponse::success(logs))
-        }
-        Err(e) => {
-            error!("获取客户端日志失败: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                ApiResponse::<Vec<LogEntry>>::error(format!(
-                    "获取日志失败: {}",
-                    e
-                )),
-            )
-        }
+    let db = get_connection().await;
+    if let Err((status, e)) = access::accessible_client(&auth_user, client_id, db).await {
+        return (status, ApiResponse::error(e));
     }
+
+    info!("请求客户端 {} 的日志", client_id);
+
+    // 客户端在线时直接通过 ClientStreamManager 获取其内存中的全部日志，失败时只返回已上传的部分
+    let csm = &app_state.client_stream_manager;
+    let live = if csm.is_connected(client_id).await {
+        csm.fetch_client_logs(client_id, 0).await.unwrap_or_else(|e| {
+            warn!("实时获取客户端 {} 的日志失败: {}", client_id, e);
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+
+    let filter = LogFilter {
+        min_level,
+        since: query.since,
+        until: query.until,
+        limit: query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT),
+    };
+    let logs = client_log_store::merge(client_log_store::get(client_id), live, &filter);
+    info!("成功获取客户端 {} 的 {} 条日志", client_id, logs.len());
+    (StatusCode::OK, ApiResponse::success(logs))
 }
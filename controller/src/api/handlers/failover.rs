@@ -0,0 +1,256 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, NotSet, QueryFilter, QueryOrder, Set};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::entity::failover_group::{self, format_ids};
+use crate::entity::{node, FailoverGroup, Node};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use super::ApiResponse;
+
+/// 默认离线阈值（秒）
+const DEFAULT_OFFLINE_THRESHOLD_SECS: i32 = 120;
+
+fn require_admin(auth_user: Option<AuthUser>) -> Result<AuthUser, (StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+    match auth_user {
+        Some(user) if user.is_admin => Ok(user),
+        Some(_) => Err((StatusCode::FORBIDDEN, ApiResponse::error("仅管理员".to_string()))),
+        None => Err((StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()))),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateFailoverGroupRequest {
+    pub name: String,
+    #[serde(rename = "primaryNodeId")]
+    pub primary_node_id: i64,
+    /// 备用节点，按优先级排列
+    #[serde(rename = "standbyNodeIds")]
+    pub standby_node_ids: Vec<i64>,
+    #[serde(rename = "offlineThresholdSecs")]
+    pub offline_threshold_secs: Option<i32>,
+    pub failback: Option<bool>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateFailoverGroupRequest {
+    pub name: Option<String>,
+    #[serde(rename = "standbyNodeIds")]
+    pub standby_node_ids: Option<Vec<i64>>,
+    #[serde(rename = "offlineThresholdSecs")]
+    pub offline_threshold_secs: Option<i32>,
+    pub failback: Option<bool>,
+    pub enabled: Option<bool>,
+}
+
+/// 校验组的节点配置，返回错误原因
+async fn validate_nodes(
+    group_id: Option<i64>,
+    primary: i64,
+    standbys: &[i64],
+    db: &DatabaseConnection,
+) -> Result<Option<String>, sea_orm::DbErr> {
+    if standbys.is_empty() {
+        return Ok(Some("至少需要一个备用节点".to_string()));
+    }
+    if standbys.contains(&primary) {
+        return Ok(Some("主节点不能同时作为备用节点".to_string()));
+    }
+    let mut all: Vec<i64> = standbys.to_vec();
+    all.push(primary);
+    all.sort_unstable();
+    all.dedup();
+    if all.len() != standbys.len() + 1 {
+        return Ok(Some("备用节点不能重复".to_string()));
+    }
+    let found = Node::find().filter(node::Column::Id.is_in(all.clone())).all(db).await?;
+    if found.len() != all.len() {
+        return Ok(Some("节点不存在".to_string()));
+    }
+
+    // 主节点上的代理只能归属一个组
+    let mut others = FailoverGroup::find().filter(failover_group::Column::PrimaryNodeId.eq(primary));
+    if let Some(id) = group_id {
+        others = others.filter(failover_group::Column::Id.ne(id));
+    }
+    if others.one(db).await?.is_some() {
+        return Ok(Some("该主节点已属于其他故障转移组".to_string()));
+    }
+    Ok(None)
+}
+
+/// GET /api/failover-groups - 故障转移组列表
+pub async fn list_failover_groups(Extension(auth_user): Extension<Option<AuthUser>>) -> impl IntoResponse {
+    if let Err(resp) = require_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    match FailoverGroup::find().order_by_asc(failover_group::Column::Id).all(db).await {
+        Ok(groups) => (StatusCode::OK, ApiResponse::success(serde_json::json!(groups))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("查询故障转移组失败: {}", e)),
+        ),
+    }
+}
+
+/// POST /api/failover-groups - 创建故障转移组
+pub async fn create_failover_group(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Json(req): Json<CreateFailoverGroupRequest>,
+) -> impl IntoResponse {
+    let auth_user = match require_admin(auth_user) {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let name = req.name.trim().to_string();
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("名称不能为空".to_string()));
+    }
+    let threshold = req.offline_threshold_secs.unwrap_or(DEFAULT_OFFLINE_THRESHOLD_SECS);
+    if threshold <= 0 {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("离线阈值必须大于 0".to_string()));
+    }
+
+    let db = get_connection().await;
+    match validate_nodes(None, req.primary_node_id, &req.standby_node_ids, db).await {
+        Ok(None) => {}
+        Ok(Some(reason)) => return (StatusCode::BAD_REQUEST, ApiResponse::error(reason)),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询节点失败: {}", e)),
+            )
+        }
+    }
+
+    let now = Utc::now().naive_utc();
+    let group = failover_group::ActiveModel {
+        id: NotSet,
+        name: Set(name),
+        primary_node_id: Set(req.primary_node_id),
+        standby_node_ids: Set(format_ids(&req.standby_node_ids)),
+        offline_threshold_secs: Set(threshold),
+        failback: Set(req.failback.unwrap_or(true)),
+        enabled: Set(req.enabled.unwrap_or(true)),
+        active_node_id: Set(req.primary_node_id),
+        moved_proxy_ids: Set(None),
+        failed_over_at: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    match group.insert(db).await {
+        Ok(group) => {
+            info!(
+                "管理员 {} 创建故障转移组 #{} {}: 主节点 #{}，备用节点 {}",
+                auth_user.username, group.id, group.name, group.primary_node_id, group.standby_node_ids
+            );
+            (StatusCode::OK, ApiResponse::success(serde_json::json!(group)))
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("创建故障转移组失败: {}", e)),
+        ),
+    }
+}
+
+/// PUT /api/failover-groups/{id} - 修改故障转移组（主节点不可修改）
+pub async fn update_failover_group(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Json(req): Json<UpdateFailoverGroupRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    let group = match FailoverGroup::find_by_id(id).one(db).await {
+        Ok(Some(g)) => g,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("故障转移组不存在".to_string())),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询故障转移组失败: {}", e)),
+            )
+        }
+    };
+
+    if let Some(standbys) = &req.standby_node_ids {
+        match validate_nodes(Some(id), group.primary_node_id, standbys, db).await {
+            Ok(None) => {}
+            Ok(Some(reason)) => return (StatusCode::BAD_REQUEST, ApiResponse::error(reason)),
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ApiResponse::error(format!("查询节点失败: {}", e)),
+                )
+            }
+        }
+    }
+
+    let mut active: failover_group::ActiveModel = group.into();
+    if let Some(name) = req.name {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return (StatusCode::BAD_REQUEST, ApiResponse::error("名称不能为空".to_string()));
+        }
+        active.name = Set(name);
+    }
+    if let Some(standbys) = req.standby_node_ids {
+        active.standby_node_ids = Set(format_ids(&standbys));
+    }
+    if let Some(threshold) = req.offline_threshold_secs {
+        if threshold <= 0 {
+            return (StatusCode::BAD_REQUEST, ApiResponse::error("离线阈值必须大于 0".to_string()));
+        }
+        active.offline_threshold_secs = Set(threshold);
+    }
+    if let Some(failback) = req.failback {
+        active.failback = Set(failback);
+    }
+    if let Some(enabled) = req.enabled {
+        active.enabled = Set(enabled);
+    }
+    active.updated_at = Set(Utc::now().naive_utc());
+
+    match active.update(db).await {
+        Ok(group) => (StatusCode::OK, ApiResponse::success(serde_json::json!(group))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("修改故障转移组失败: {}", e)),
+        ),
+    }
+}
+
+/// DELETE /api/failover-groups/{id} - 删除故障转移组（已迁移的代理留在当前节点）
+pub async fn delete_failover_group(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    match FailoverGroup::delete_by_id(id).exec(db).await {
+        Ok(res) if res.rows_affected == 0 => {
+            (StatusCode::NOT_FOUND, ApiResponse::error("故障转移组不存在".to_string()))
+        }
+        Ok(_) => (StatusCode::OK, ApiResponse::success(serde_json::json!(null))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("删除故障转移组失败: {}", e)),
+        ),
+    }
+}
@@ -0,0 +1,49 @@
+use axum::{
+    extract::Extension,
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use serde::Serialize;
+
+use crate::{migration::get_connection, AppState};
+
+#[derive(Serialize)]
+pub struct ReadinessChecks {
+    pub database: bool,
+    pub grpc: bool,
+    pub config: bool,
+    /// 正在排空准备重启
+    pub draining: bool,
+}
+
+#[derive(Serialize)]
+pub struct HealthStatus {
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checks: Option<ReadinessChecks>,
+}
+
+/// GET /healthz - 存活探针，进程能响应请求即视为存活
+pub async fn healthz() -> impl IntoResponse {
+    (StatusCode::OK, Json(HealthStatus { status: "ok", checks: None }))
+}
+
+/// GET /readyz - 就绪探针：数据库可访问、gRPC 端口已绑定、系统配置已加载，且不在重启前的排空阶段
+pub async fn readyz(Extension(app_state): Extension<AppState>) -> impl IntoResponse {
+    let db = get_connection().await;
+    let checks = ReadinessChecks {
+        database: db.ping().await.is_ok(),
+        grpc: app_state.health.is_grpc_bound(),
+        config: app_state.health.is_config_loaded(),
+        draining: app_state.health.is_draining(),
+    };
+
+    if checks.database && checks.grpc && checks.config && !checks.draining {
+        (StatusCode::OK, Json(HealthStatus { status: "ok", checks: Some(checks) }))
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(HealthStatus { status: "unavailable", checks: Some(checks) }),
+        )
+    }
+}
@@ -0,0 +1,181 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use chrono::{Duration, Utc};
+use sea_orm::{ActiveModelTrait, EntityTrait, NotSet, QueryOrder, QuerySelect, Set};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::entity::{impersonation_log, ImpersonationLog, User};
+use crate::jwt::{generate_impersonation_token, Impersonator};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use crate::AppState;
+use super::{ApiResponse, UserInfo};
+
+/// 模拟登录令牌的默认有效期（分钟）
+const DEFAULT_TTL_MINUTES: i64 = 15;
+/// 模拟登录令牌的最长有效期（分钟）
+const MAX_TTL_MINUTES: i64 = 60;
+
+#[derive(Deserialize)]
+pub struct ImpersonateRequest {
+    /// 模拟原因，记录在审计日志中
+    pub reason: Option<String>,
+    /// 有效期（分钟），默认 15 分钟
+    #[serde(rename = "ttlMinutes")]
+    pub ttl_minutes: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ImpersonateResponse {
+    pub token: String,
+    pub user: UserInfo,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+/// 模拟登录可以查看任意用户的数据，仅平台管理员可用；模拟令牌本身不能再发起模拟
+fn require_platform_admin(auth_user: Option<AuthUser>) -> Result<AuthUser, (StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+    match auth_user {
+        Some(user) if user.is_admin && user.tenant_id.is_none() && user.impersonator.is_none() => Ok(user),
+        Some(_) => Err((StatusCode::FORBIDDEN, ApiResponse::error("仅平台管理员".to_string()))),
+        None => Err((StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()))),
+    }
+}
+
+/// POST /api/users/{id}/impersonate - 生成以该用户身份只读访问的短期令牌
+pub async fn impersonate_user(
+    Path(user_id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<ImpersonateRequest>,
+) -> impl IntoResponse {
+    let auth_user = match require_platform_admin(auth_user) {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let ttl_minutes = req.ttl_minutes.unwrap_or(DEFAULT_TTL_MINUTES);
+    if !(1..=MAX_TTL_MINUTES).contains(&ttl_minutes) {
+        return (
+            StatusCode::BAD_REQUEST,
+            ApiResponse::error(format!("有效期必须在 1 到 {} 分钟之间", MAX_TTL_MINUTES)),
+        );
+    }
+    if user_id == auth_user.id {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("不能模拟自己".to_string()));
+    }
+
+    let db = get_connection().await;
+    let user = match User::find_by_id(user_id).one(db).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("用户不存在".to_string())),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询用户失败: {}", e)),
+            )
+        }
+    };
+    if user.is_admin && user.tenant_id.is_none() {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("不能模拟平台管理员".to_string()));
+    }
+
+    let jwt_secret = match app_state.config.get_jwt_secret() {
+        Ok(secret) => secret,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("JWT 配置错误: {}", e)),
+            )
+        }
+    };
+
+    // 先写审计记录，令牌中携带记录 ID
+    let now = Utc::now();
+    let expires_at = now + Duration::minutes(ttl_minutes);
+    let reason = req.reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+    let log = impersonation_log::ActiveModel {
+        id: NotSet,
+        admin_id: Set(auth_user.id),
+        admin_username: Set(auth_user.username.clone()),
+        user_id: Set(user.id),
+        username: Set(user.username.clone()),
+        reason: Set(reason.clone()),
+        expires_at: Set(expires_at.naive_utc()),
+        created_at: Set(now.naive_utc()),
+    };
+    let log = match log.insert(db).await {
+        Ok(log) => log,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("写入审计记录失败: {}", e)),
+            )
+        }
+    };
+
+    let impersonator = Impersonator {
+        id: auth_user.id,
+        username: auth_user.username.clone(),
+        log_id: log.id,
+    };
+    let token = match generate_impersonation_token(&user, impersonator, &jwt_secret, expires_at) {
+        Ok(token) => token,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("生成令牌失败: {}", e)),
+            )
+        }
+    };
+
+    info!(
+        "管理员 {} 开始模拟用户 {} (#{})，有效期 {} 分钟，原因: {}",
+        auth_user.username,
+        user.username,
+        user.id,
+        ttl_minutes,
+        reason.as_deref().unwrap_or("-")
+    );
+
+    let response = ImpersonateResponse {
+        token,
+        user: UserInfo {
+            id: user.id,
+            username: user.username,
+            is_admin: user.is_admin,
+            tenant_id: user.tenant_id,
+            is_tenant_admin: user.is_tenant_admin,
+            impersonator: Some(auth_user.username),
+        },
+        expires_at: expires_at.to_rfc3339(),
+    };
+    (StatusCode::OK, ApiResponse::success(serde_json::json!(response)))
+}
+
+/// GET /api/impersonations - 模拟登录审计记录
+pub async fn list_impersonations(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    match ImpersonationLog::find()
+        .order_by_desc(impersonation_log::Column::Id)
+        .limit(200)
+        .all(db)
+        .await
+    {
+        Ok(logs) => (StatusCode::OK, ApiResponse::success(serde_json::json!(logs))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("查询模拟登录记录失败: {}", e)),
+        ),
+    }
+}
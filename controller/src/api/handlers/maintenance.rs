@@ -0,0 +1,249 @@
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, NotSet, QueryFilter, QueryOrder, QuerySelect, Set};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::entity::maintenance_window::{self, SCOPE_CLIENT, SCOPE_NODE};
+use crate::entity::{Client, MaintenanceWindow, Node, Proxy};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use super::ApiResponse;
+
+fn require_admin(auth_user: Option<AuthUser>) -> Result<AuthUser, (StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+    match auth_user {
+        Some(user) if user.is_admin => Ok(user),
+        Some(_) => Err((StatusCode::FORBIDDEN, ApiResponse::error("仅管理员".to_string()))),
+        None => Err((StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()))),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateMaintenanceWindowRequest {
+    pub name: String,
+    /// node / client / proxy
+    #[serde(rename = "scopeType")]
+    pub scope_type: String,
+    #[serde(rename = "scopeId")]
+    pub scope_id: i64,
+    #[serde(rename = "startsAt")]
+    pub starts_at: DateTime<Utc>,
+    #[serde(rename = "endsAt")]
+    pub ends_at: DateTime<Utc>,
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateMaintenanceWindowRequest {
+    pub name: Option<String>,
+    #[serde(rename = "startsAt")]
+    pub starts_at: Option<DateTime<Utc>>,
+    #[serde(rename = "endsAt")]
+    pub ends_at: Option<DateTime<Utc>>,
+    pub description: Option<Option<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct MaintenanceWindowListQuery {
+    /// 只返回未结束的窗口
+    #[serde(default)]
+    pub upcoming: bool,
+    #[serde(rename = "scopeType")]
+    pub scope_type: Option<String>,
+    #[serde(rename = "scopeId")]
+    pub scope_id: Option<i64>,
+}
+
+/// 维护对象是否存在
+async fn scope_exists(scope_type: &str, scope_id: i64, db: &DatabaseConnection) -> Result<bool, sea_orm::DbErr> {
+    Ok(match scope_type {
+        SCOPE_NODE => Node::find_by_id(scope_id).one(db).await?.is_some(),
+        SCOPE_CLIENT => Client::find_by_id(scope_id).one(db).await?.is_some(),
+        _ => Proxy::find_by_id(scope_id).one(db).await?.is_some(),
+    })
+}
+
+/// 窗口变更后刷新内存中的缓存
+async fn reload_windows(db: &DatabaseConnection) {
+    if let Err(e) = crate::maintenance::reload(db).await {
+        warn!("重新加载维护窗口失败: {}", e);
+    }
+}
+
+/// GET /api/maintenance-windows - 维护窗口列表（最近 200 条）
+pub async fn list_maintenance_windows(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Query(query): Query<MaintenanceWindowListQuery>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_admin(auth_user) {
+        return resp;
+    }
+
+    let mut select = MaintenanceWindow::find();
+    if query.upcoming {
+        select = select.filter(maintenance_window::Column::EndsAt.gt(Utc::now().naive_utc()));
+    }
+    if let Some(scope_type) = query.scope_type {
+        select = select.filter(maintenance_window::Column::ScopeType.eq(scope_type));
+    }
+    if let Some(scope_id) = query.scope_id {
+        select = select.filter(maintenance_window::Column::ScopeId.eq(scope_id));
+    }
+
+    let db = get_connection().await;
+    match select
+        .order_by_desc(maintenance_window::Column::StartsAt)
+        .limit(200)
+        .all(db)
+        .await
+    {
+        Ok(windows) => (StatusCode::OK, ApiResponse::success(serde_json::json!(windows))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("查询维护窗口失败: {}", e)),
+        ),
+    }
+}
+
+/// POST /api/maintenance-windows - 登记维护窗口
+pub async fn create_maintenance_window(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Json(req): Json<CreateMaintenanceWindowRequest>,
+) -> impl IntoResponse {
+    let auth_user = match require_admin(auth_user) {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let name = req.name.trim().to_string();
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("名称不能为空".to_string()));
+    }
+    let starts_at = req.starts_at.naive_utc();
+    let ends_at = req.ends_at.naive_utc();
+    if let Err(e) = crate::maintenance::validate_window(&req.scope_type, starts_at, ends_at) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(e));
+    }
+
+    let db = get_connection().await;
+    match scope_exists(&req.scope_type, req.scope_id, db).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::BAD_REQUEST, ApiResponse::error("维护对象不存在".to_string())),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询维护对象失败: {}", e)),
+            )
+        }
+    }
+
+    let window = maintenance_window::ActiveModel {
+        id: NotSet,
+        name: Set(name),
+        scope_type: Set(req.scope_type),
+        scope_id: Set(req.scope_id),
+        starts_at: Set(starts_at),
+        ends_at: Set(ends_at),
+        description: Set(req.description),
+        created_by: Set(auth_user.username.clone()),
+        created_at: Set(Utc::now().naive_utc()),
+    };
+
+    match window.insert(db).await {
+        Ok(window) => {
+            reload_windows(db).await;
+            info!(
+                "管理员 {} 登记维护窗口 #{}: {} #{} {} ~ {}",
+                auth_user.username, window.id, window.scope_type, window.scope_id, window.starts_at, window.ends_at
+            );
+            (StatusCode::OK, ApiResponse::success(serde_json::json!(window)))
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("登记维护窗口失败: {}", e)),
+        ),
+    }
+}
+
+/// PUT /api/maintenance-windows/{id} - 修改维护窗口（如提前结束）
+pub async fn update_maintenance_window(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Json(req): Json<UpdateMaintenanceWindowRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    let window = match MaintenanceWindow::find_by_id(id).one(db).await {
+        Ok(Some(w)) => w,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("维护窗口不存在".to_string())),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询维护窗口失败: {}", e)),
+            )
+        }
+    };
+
+    let starts_at = req.starts_at.map_or(window.starts_at, |t| t.naive_utc());
+    let ends_at = req.ends_at.map_or(window.ends_at, |t| t.naive_utc());
+    if let Err(e) = crate::maintenance::validate_window(&window.scope_type, starts_at, ends_at) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(e));
+    }
+
+    let mut active: maintenance_window::ActiveModel = window.into();
+    if let Some(name) = req.name {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return (StatusCode::BAD_REQUEST, ApiResponse::error("名称不能为空".to_string()));
+        }
+        active.name = Set(name);
+    }
+    active.starts_at = Set(starts_at);
+    active.ends_at = Set(ends_at);
+    if let Some(description) = req.description {
+        active.description = Set(description);
+    }
+
+    match active.update(db).await {
+        Ok(window) => {
+            reload_windows(db).await;
+            (StatusCode::OK, ApiResponse::success(serde_json::json!(window)))
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("修改维护窗口失败: {}", e)),
+        ),
+    }
+}
+
+/// DELETE /api/maintenance-windows/{id} - 删除维护窗口
+pub async fn delete_maintenance_window(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    match MaintenanceWindow::delete_by_id(id).exec(db).await {
+        Ok(res) if res.rows_affected == 0 => {
+            (StatusCode::NOT_FOUND, ApiResponse::error("维护窗口不存在".to_string()))
+        }
+        Ok(_) => {
+            reload_windows(db).await;
+            (StatusCode::OK, ApiResponse::success(serde_json::json!(null)))
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("删除维护窗口失败: {}", e)),
+        ),
+    }
+}
@@ -0,0 +1,114 @@
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::entity::{mitigation_event, MitigationEvent};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use crate::AppState;
+use super::ApiResponse;
+
+fn require_admin(auth_user: Option<AuthUser>) -> Result<AuthUser, (StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+    match auth_user {
+        Some(user) if user.is_admin => Ok(user),
+        Some(_) => Err((StatusCode::FORBIDDEN, ApiResponse::error("仅管理员".to_string()))),
+        None => Err((StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()))),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MitigationListQuery {
+    /// 只返回仍在生效的处置
+    #[serde(default)]
+    pub active: bool,
+    #[serde(rename = "nodeId")]
+    pub node_id: Option<i64>,
+}
+
+/// GET /api/mitigations - 节点上报的来源 IP 处置记录（最近 200 条）
+pub async fn list_mitigations(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Query(query): Query<MitigationListQuery>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_admin(auth_user) {
+        return resp;
+    }
+
+    let mut select = MitigationEvent::find();
+    if query.active {
+        select = select
+            .filter(mitigation_event::Column::ExpiresAt.gt(Utc::now().naive_utc()))
+            .filter(mitigation_event::Column::ReleasedAt.is_null());
+    }
+    if let Some(node_id) = query.node_id {
+        select = select.filter(mitigation_event::Column::NodeId.eq(node_id));
+    }
+
+    let db = get_connection().await;
+    match select
+        .order_by_desc(mitigation_event::Column::Id)
+        .limit(200)
+        .all(db)
+        .await
+    {
+        Ok(events) => (StatusCode::OK, ApiResponse::success(serde_json::json!(events))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("查询处置记录失败: {}", e)),
+        ),
+    }
+}
+
+/// POST /api/mitigations/{id}/release - 提前解除节点对来源 IP 的处置
+pub async fn release_mitigation(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+) -> impl IntoResponse {
+    let auth_user = match require_admin(auth_user) {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let db = get_connection().await;
+    let event = match MitigationEvent::find_by_id(id).one(db).await {
+        Ok(Some(e)) => e,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("处置记录不存在".to_string())),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("查询处置记录失败: {}", e))),
+    };
+
+    let now = Utc::now().naive_utc();
+    if event.released_at.is_some() || event.expires_at <= now {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("该处置已解除或已到期".to_string()));
+    }
+
+    // 节点离线时处置仍保留在节点内存中，需要节点在线才能解除
+    if let Err(e) = app_state
+        .node_manager
+        .send_release_mitigation(event.node_id, event.proxy_id, &event.source_ip)
+        .await
+    {
+        return (StatusCode::BAD_GATEWAY, ApiResponse::error(format!("通知节点解除处置失败: {}", e)));
+    }
+
+    info!(
+        "管理员 {} 解除了节点 #{} 对来源 {} 在代理 #{} 上的处置",
+        auth_user.username, event.node_id, event.source_ip, event.proxy_id
+    );
+    let mut active: mitigation_event::ActiveModel = event.into();
+    active.released_at = Set(Some(now));
+    active.released_by = Set(Some(auth_user.username));
+    match active.update(db).await {
+        Ok(event) => (StatusCode::OK, ApiResponse::success(serde_json::json!(event))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("更新处置记录失败: {}", e)),
+        ),
+    }
+}
@@ -11,6 +11,31 @@ pub mod client_config;
 pub mod subscription;
 pub mod user_subscription;
 pub mod version;
+pub mod health;
+pub mod update_rollout;
+pub mod tls_apply;
+pub mod tenant;
+pub mod port_blocklist;
+pub mod temporary_tunnel;
+pub mod online_status;
+pub mod profile;
+pub mod account_recovery;
+pub mod node_probe;
+pub mod node_log_stream;
+pub mod impersonation;
+pub mod mitigation;
+pub mod alert;
+pub mod maintenance;
+pub mod failover;
+pub mod availability;
+pub mod port_reservation;
+pub mod visitor;
+pub mod proxy_events;
+pub mod capture;
+pub mod port_probe;
+pub mod proxy_anycast;
+pub mod api_token;
+pub mod node_drain;
 
 // Re-export common handler modules
 pub use auth::*;
@@ -26,6 +51,31 @@ pub use client_config::*;
 pub use subscription::*;
 pub use user_subscription::*;
 pub use version::*;
+pub use health::*;
+pub use update_rollout::*;
+pub use tls_apply::*;
+pub use tenant::*;
+pub use port_blocklist::*;
+pub use temporary_tunnel::*;
+pub use online_status::*;
+pub use profile::*;
+pub use account_recovery::*;
+pub use node_probe::*;
+pub use node_log_stream::*;
+pub use impersonation::*;
+pub use mitigation::*;
+pub use alert::*;
+pub use maintenance::*;
+pub use failover::*;
+pub use availability::*;
+pub use port_reservation::*;
+pub use visitor::*;
+pub use proxy_events::*;
+pub use capture::*;
+pub use port_probe::*;
+pub use proxy_anycast::*;
+pub use api_token::*;
+pub use node_drain::*;
 
 use serde::Serialize;
 
@@ -34,6 +84,9 @@ pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
     pub message: String,
+    /// 列表接口的总条数（用于分页）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
 }
 
 impl<T> ApiResponse<T> {
@@ -42,6 +95,17 @@ impl<T> ApiResponse<T> {
             success: true,
             data: Some(data),
             message: "Success".to_string(),
+            total: None,
+        })
+    }
+
+    /// 带总条数的列表响应
+    pub fn paginated(data: T, total: u64) -> axum::response::Json<Self> {
+        axum::response::Json(Self {
+            success: true,
+            data: Some(data),
+            message: "Success".to_string(),
+            total: Some(total),
         })
     }
 
@@ -50,6 +114,7 @@ impl<T> ApiResponse<T> {
             success: false,
             data: None,
             message,
+            total: None,
         })
     }
 }
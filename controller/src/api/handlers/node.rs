@@ -1,11 +1,11 @@
 use axum::{
-    extract::{Extension, Path},
-    http::StatusCode,
+    extract::{Extension, Path, Query},
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Json},
 };
 use chrono::Utc;
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, PaginatorTrait, QueryFilter, Set};
-use serde::Deserialize;
+use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, EntityTrait, NotSet, PaginatorTrait, QueryFilter, Set};
+use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 use uuid::Uuid;
 
@@ -17,6 +17,51 @@ use crate::{
 };
 
 use super::ApiResponse;
+use crate::api::pagination::{apply_cursor, apply_sort, fetch_page, ListQuery};
+
+/// 校验节点的 KCP 参数（JSON），空字符串表示使用默认参数
+fn validate_kcp_config(raw: Option<&str>) -> Result<(), String> {
+    match raw {
+        Some(raw) if !raw.trim().is_empty() => serde_json::from_str::<common::KcpConfig>(raw)
+            .map_err(|e| format!("KCP 参数格式错误: {}", e))?
+            .validate(),
+        _ => Ok(()),
+    }
+}
+
+/// 将节点保存的 KCP 参数转换为 gRPC 下发格式
+fn node_kcp_grpc(raw: Option<&str>) -> Option<common::grpc::oxiproxy::GrpcKcpConfig> {
+    raw.and_then(|s| serde_json::from_str::<common::KcpConfig>(s).ok())
+        .map(|k| common::grpc::oxiproxy::GrpcKcpConfig::from(&k))
+}
+
+/// 校验节点的 QUIC 参数（JSON），空字符串表示使用默认参数
+fn validate_quic_config(raw: Option<&str>) -> Result<(), String> {
+    match raw {
+        Some(raw) if !raw.trim().is_empty() => serde_json::from_str::<common::QuicConfig>(raw)
+            .map_err(|e| format!("QUIC 参数格式错误: {}", e))?
+            .validate(),
+        _ => Ok(()),
+    }
+}
+
+/// 将节点保存的 QUIC 参数转换为 gRPC 下发格式
+fn node_quic_grpc(raw: Option<&str>) -> Option<common::grpc::oxiproxy::GrpcQuicConfig> {
+    raw.and_then(|s| serde_json::from_str::<common::QuicConfig>(s).ok())
+        .map(|q| common::grpc::oxiproxy::GrpcQuicConfig::from(&q))
+}
+
+/// 校验节点要分配的租户是否存在
+async fn validate_tenant(tenant_id: Option<i64>, db: &sea_orm::DatabaseConnection) -> Result<(), String> {
+    let Some(tenant_id) = tenant_id else {
+        return Ok(());
+    };
+    match crate::entity::Tenant::find_by_id(tenant_id).one(db).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(format!("租户 #{} 不存在", tenant_id)),
+        Err(e) => Err(format!("查询租户失败: {}", e)),
+    }
+}
 
 #[derive(Deserialize)]
 pub struct CreateNodeRequest {
@@ -33,6 +78,8 @@ pub struct CreateNodeRequest {
     pub tunnel_protocol: Option<String>,
     #[serde(rename = "kcpConfig")]
     pub kcp_config: Option<String>,
+    #[serde(rename = "quicConfig")]
+    pub quic_config: Option<String>,
     #[serde(rename = "nodeType")]
     pub node_type: Option<String>,
     #[serde(rename = "maxProxyCount")]
@@ -45,6 +92,21 @@ pub struct CreateNodeRequest {
     pub traffic_reset_cycle: Option<String>,
     #[serde(rename = "speedLimit")]
     pub speed_limit: Option<i64>,
+    /// 带宽时间表，如 `00:00-08:00=12.5M`
+    #[serde(rename = "speedLimitSchedule")]
+    pub speed_limit_schedule: Option<String>,
+    /// 带宽饱和时按代理权重公平分配
+    #[serde(rename = "fairShare")]
+    pub fair_share: Option<bool>,
+    /// 分配给租户后仅该租户的用户可见
+    #[serde(rename = "tenantId")]
+    pub tenant_id: Option<i64>,
+    /// 默认的来源 IP 处置规则（JSON）
+    #[serde(rename = "mitigationConfig")]
+    pub mitigation_config: Option<String>,
+    /// 云服务商防火墙配置（JSON）
+    #[serde(rename = "firewallConfig")]
+    pub firewall_config: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -62,6 +124,8 @@ pub struct UpdateNodeRequest {
     pub tunnel_protocol: Option<String>,
     #[serde(rename = "kcpConfig")]
     pub kcp_config: Option<String>,
+    #[serde(rename = "quicConfig")]
+    pub quic_config: Option<String>,
     #[serde(rename = "nodeType")]
     pub node_type: Option<String>,
     #[serde(rename = "maxProxyCount")]
@@ -74,55 +138,129 @@ pub struct UpdateNodeRequest {
     pub traffic_reset_cycle: Option<String>,
     #[serde(rename = "speedLimit")]
     pub speed_limit: Option<Option<i64>>,
+    /// 空字符串表示取消时间表
+    #[serde(rename = "speedLimitSchedule")]
+    pub speed_limit_schedule: Option<String>,
+    #[serde(rename = "fairShare")]
+    pub fair_share: Option<bool>,
+    #[serde(rename = "tenantId")]
+    pub tenant_id: Option<Option<i64>>,
+    /// 空字符串表示取消默认规则
+    #[serde(rename = "mitigationConfig")]
+    pub mitigation_config: Option<String>,
+    /// 空字符串表示取消防火墙联动，凭据为 `******` 时沿用原值
+    #[serde(rename = "firewallConfig")]
+    pub firewall_config: Option<String>,
+    /// 读取时的版本号，也可以通过 If-Match 请求头提供
+    #[serde(rename = "lockVersion")]
+    pub lock_version: Option<i32>,
 }
 
 /// GET /api/nodes — 列出节点（管理员看全部，普通用户看可用的）
+/// 节点列表过滤参数
+#[derive(Debug, Default, Deserialize)]
+pub struct NodeListFilter {
+    pub online: Option<bool>,
+    pub node_type: Option<String>,
+    pub region: Option<String>,
+    pub protocol: Option<String>,
+}
+
+/// 节点列表项：节点信息 + 是否处于维护窗口
+#[derive(Serialize)]
+pub struct NodeListItem {
+    #[serde(flatten)]
+    pub node: node::Model,
+    #[serde(rename = "inMaintenance")]
+    pub in_maintenance: bool,
+}
+
 pub async fn list_nodes(
     Extension(auth_user_opt): Extension<Option<AuthUser>>,
+    Query(list_query): Query<ListQuery>,
+    Query(filter): Query<NodeListFilter>,
 ) -> impl IntoResponse {
     let auth_user = match auth_user_opt {
         Some(user) => user,
-        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<Vec<node::Model>>::error("Not authenticated".to_string())),
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<Vec<NodeListItem>>::error("Not authenticated".to_string())),
     };
 
     let db = get_connection().await;
 
-    if auth_user.is_admin {
-        // 管理员可以看到所有节点
-        match Node::find().all(db).await {
-            Ok(nodes) => (StatusCode::OK, ApiResponse::success(nodes)),
-            Err(e) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                ApiResponse::<Vec<node::Model>>::error(format!("Failed to list nodes: {}", e)),
-            ),
-        }
-    } else {
+    let mut select = Node::find();
+
+    if !auth_user.is_admin {
         // 普通用户只能看到共享节点 + 自己的独享节点
-        match Node::find().all(db).await {
-            Ok(all_nodes) => {
-                // 获取用户的独享节点
-                let user_node_ids = match crate::entity::UserNode::find()
-                    .filter(crate::entity::user_node::Column::UserId.eq(auth_user.id))
-                    .all(db)
-                    .await
-                {
-                    Ok(user_nodes) => user_nodes.into_iter().map(|un| un.node_id).collect::<Vec<_>>(),
-                    Err(_) => vec![],
-                };
-
-                // 过滤出共享节点 + 用户的独享节点
-                let available_nodes: Vec<node::Model> = all_nodes
-                    .into_iter()
-                    .filter(|node| node.node_type == "shared" || user_node_ids.contains(&node.id))
-                    .collect();
-
-                (StatusCode::OK, ApiResponse::success(available_nodes))
-            }
-            Err(e) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                ApiResponse::<Vec<node::Model>>::error(format!("Failed to list nodes: {}", e)),
-            ),
+        let user_node_ids = match crate::entity::UserNode::find()
+            .filter(crate::entity::user_node::Column::UserId.eq(auth_user.id))
+            .all(db)
+            .await
+        {
+            Ok(user_nodes) => user_nodes.into_iter().map(|un| un.node_id).collect::<Vec<_>>(),
+            Err(_) => vec![],
+        };
+
+        select = select
+            .filter(
+                Condition::any()
+                    .add(node::Column::NodeType.eq("shared"))
+                    .add(node::Column::Id.is_in(user_node_ids)),
+            )
+            // 其他租户的节点不可见
+            .filter(crate::tenant::node_tenant_condition(auth_user.tenant_id));
+    }
+
+    if let Some(online) = filter.online {
+        select = select.filter(node::Column::IsOnline.eq(online));
+    }
+    if let Some(node_type) = filter.node_type.filter(|t| !t.is_empty()) {
+        select = select.filter(node::Column::NodeType.eq(node_type));
+    }
+    if let Some(region) = filter.region.filter(|r| !r.is_empty()) {
+        select = select.filter(node::Column::Region.eq(region));
+    }
+    if let Some(protocol) = filter.protocol.filter(|p| !p.is_empty()) {
+        select = select.filter(node::Column::TunnelProtocol.eq(protocol));
+    }
+    if let Some(keyword) = list_query.keyword() {
+        select = select.filter(
+            Condition::any()
+                .add(node::Column::Name.contains(keyword))
+                .add(node::Column::TunnelAddr.contains(keyword))
+                .add(node::Column::PublicIp.contains(keyword))
+                .add(node::Column::Region.contains(keyword))
+                .add(node::Column::Description.contains(keyword)),
+        );
+    }
+
+    let select = apply_sort(
+        select,
+        &list_query,
+        &[
+            ("id", node::Column::Id),
+            ("name", node::Column::Name),
+            ("online", node::Column::IsOnline),
+            ("region", node::Column::Region),
+            ("node_type", node::Column::NodeType),
+            ("created_at", node::Column::CreatedAt),
+            ("updated_at", node::Column::UpdatedAt),
+        ],
+        node::Column::Id,
+    );
+    let select = apply_cursor(select, &list_query, node::Column::Id);
+
+    match fetch_page(select, &list_query, db).await {
+        Ok((nodes, total)) => {
+            let nodes = nodes
+                .into_iter()
+                .map(|node| NodeListItem { in_maintenance: crate::maintenance::global().node(node.id), node })
+                .collect();
+            (StatusCode::OK, ApiResponse::paginated(nodes, total))
         }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::<Vec<NodeListItem>>::error(format!("Failed to list nodes: {}", e)),
+        ),
     }
 }
 
@@ -141,6 +279,28 @@ pub async fn create_node(
         return (StatusCode::FORBIDDEN, ApiResponse::<node::Model>::error("Only admin can manage nodes".to_string()));
     }
 
+    if let Err(e) = validate_kcp_config(req.kcp_config.as_deref()) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<node::Model>::error(e));
+    }
+    if let Err(e) = validate_quic_config(req.quic_config.as_deref()) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<node::Model>::error(e));
+    }
+    if let Err(e) = validate_tenant(req.tenant_id, get_connection().await).await {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<node::Model>::error(e));
+    }
+    let mitigation_config = match crate::mitigation::normalize_config(req.mitigation_config) {
+        Ok(c) => c,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<node::Model>::error(e)),
+    };
+    let firewall_config = match crate::cloud_firewall::normalize_config(req.firewall_config, None) {
+        Ok(c) => c,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<node::Model>::error(e)),
+    };
+    let speed_limit_schedule = match crate::bandwidth_schedule::normalize(req.speed_limit_schedule) {
+        Ok(s) => s,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<node::Model>::error(e)),
+    };
+
     let now = Utc::now().naive_utc();
     let new_node = node::ActiveModel {
         id: NotSet,
@@ -155,6 +315,7 @@ pub async fn create_node(
         tunnel_port: Set(req.tunnel_port.unwrap_or(7000)),
         tunnel_protocol: Set(req.tunnel_protocol.unwrap_or_else(|| "quic".to_string())),
         kcp_config: Set(req.kcp_config),
+        quic_config: Set(req.quic_config),
         node_type: Set(req.node_type.unwrap_or_else(|| "shared".to_string())),
         max_proxy_count: Set(req.max_proxy_count),
         allowed_port_range: Set(req.allowed_port_range),
@@ -165,7 +326,16 @@ pub async fn create_node(
         last_reset_at: Set(None),
         is_traffic_exceeded: Set(false),
         speed_limit: Set(req.speed_limit),
+        speed_limit_schedule: Set(speed_limit_schedule),
+        fair_share: Set(req.fair_share.unwrap_or(false)),
         version: Set(None),
+        tenant_id: Set(req.tenant_id),
+        nat_probe_port: Set(None),
+        mitigation_config: Set(mitigation_config),
+        firewall_config: Set(firewall_config),
+        capabilities: Set(None),
+        drained_proxy_ids: Set(None),
+        lock_version: Set(0),
         created_at: Set(now),
         updated_at: Set(now),
     };
@@ -213,6 +383,7 @@ pub async fn update_node(
     Path(id): Path<i64>,
     Extension(auth_user_opt): Extension<Option<AuthUser>>,
     Extension(app_state): Extension<AppState>,
+    headers: HeaderMap,
     Json(req): Json<UpdateNodeRequest>,
 ) -> impl IntoResponse {
     let auth_user = match auth_user_opt {
@@ -224,16 +395,77 @@ pub async fn update_node(
         return (StatusCode::FORBIDDEN, ApiResponse::<node::Model>::error("Only admin can manage nodes".to_string()));
     }
 
+    let if_match = match crate::optimistic_lock::expected_version(&headers, req.lock_version) {
+        Ok(v) => v,
+        Err((status, e)) => return (status, ApiResponse::<node::Model>::error(e)),
+    };
+
+    if let Err(e) = validate_kcp_config(req.kcp_config.as_deref()) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<node::Model>::error(e));
+    }
+    if let Err(e) = validate_quic_config(req.quic_config.as_deref()) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<node::Model>::error(e));
+    }
+
+    let mitigation_config = match req.mitigation_config.map(|c| crate::mitigation::normalize_config(Some(c))).transpose() {
+        Ok(c) => c,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<node::Model>::error(e)),
+    };
+    let speed_limit_schedule = match req.speed_limit_schedule.map(|s| crate::bandwidth_schedule::normalize(Some(s))).transpose() {
+        Ok(s) => s,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<node::Model>::error(e)),
+    };
+
     let db = get_connection().await;
+    if let Some(tenant_id) = req.tenant_id {
+        if let Err(e) = validate_tenant(tenant_id, db).await {
+            return (StatusCode::BAD_REQUEST, ApiResponse::<node::Model>::error(e));
+        }
+    }
     let node_model = match Node::find_by_id(id).one(db).await {
         Ok(Some(n)) => n,
         Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<node::Model>::error("Node not found".to_string())),
         Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<node::Model>::error(format!("Failed to find node: {}", e))),
     };
+    let expected_version = if_match.unwrap_or(node_model.lock_version);
+    if node_model.lock_version != expected_version {
+        return (
+            StatusCode::CONFLICT,
+            ApiResponse::<node::Model>::error(crate::optimistic_lock::conflict_message(expected_version, node_model.lock_version)),
+        );
+    }
+    let firewall_config = match req
+        .firewall_config
+        .map(|c| crate::cloud_firewall::normalize_config(Some(c), node_model.firewall_config.as_deref()))
+        .transpose()
+    {
+        Ok(c) => c,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<node::Model>::error(e)),
+    };
+
+    // 节点上报了支持的隧道协议时，不允许切换到不支持的协议
+    if let (Some(protocol), Some(caps)) = (&req.tunnel_protocol, node_model.capabilities()) {
+        if *protocol != node_model.tunnel_protocol && !caps.supports_protocol(protocol) {
+            return (
+                StatusCode::BAD_REQUEST,
+                ApiResponse::<node::Model>::error(format!(
+                    "节点不支持隧道协议 {}（支持: {}）",
+                    protocol,
+                    caps.tunnel_protocols.join(", ")
+                )),
+            );
+        }
+    }
 
     // 保存旧的协议值，用于检测变更
     let old_protocol = node_model.tunnel_protocol.clone();
     let old_speed_limit = node_model.speed_limit;
+    let old_speed_limit_schedule = node_model.speed_limit_schedule.clone();
+    let old_fair_share = node_model.fair_share;
+    let old_kcp_config = node_model.kcp_config.clone();
+    let old_quic_config = node_model.quic_config.clone();
+    let old_mitigation_config = node_model.mitigation_config.clone();
+    let old_firewall_config = node_model.firewall_config.clone();
     let new_protocol_opt = req.tunnel_protocol.clone();
 
     let mut active: node::ActiveModel = node_model.into();
@@ -265,6 +497,9 @@ pub async fn update_node(
     if req.kcp_config.is_some() {
         active.kcp_config = Set(req.kcp_config);
     }
+    if req.quic_config.is_some() {
+        active.quic_config = Set(req.quic_config);
+    }
     if let Some(node_type) = req.node_type {
         active.node_type = Set(node_type);
     }
@@ -283,26 +518,50 @@ pub async fn update_node(
     if let Some(speed_limit) = req.speed_limit {
         active.speed_limit = Set(speed_limit);
     }
+    if let Some(speed_limit_schedule) = speed_limit_schedule {
+        active.speed_limit_schedule = Set(speed_limit_schedule);
+    }
+    if let Some(fair_share) = req.fair_share {
+        active.fair_share = Set(fair_share);
+    }
+    if let Some(tenant_id) = req.tenant_id {
+        active.tenant_id = Set(tenant_id);
+    }
+    if let Some(mitigation_config) = mitigation_config {
+        active.mitigation_config = Set(mitigation_config);
+    }
+    if let Some(firewall_config) = firewall_config {
+        active.firewall_config = Set(firewall_config);
+    }
+    active.lock_version = Set(expected_version + 1);
     active.updated_at = Set(Utc::now().naive_utc());
 
-    match active.update(db).await {
+    match Node::update(active).filter(node::Column::LockVersion.eq(expected_version)).exec(db).await {
         Ok(updated) => {
-            // 检查协议是否变更
-            if let Some(ref new_protocol) = new_protocol_opt {
-                if new_protocol != &old_protocol {
-                    info!("节点 #{} 协议变更: {} -> {}", id, old_protocol, new_protocol);
-
-                    // 检查节点是否在线
-                    let connected_ids = app_state.node_manager.get_loaded_node_ids().await;
-                    if connected_ids.contains(&id) {
-                        // 推送协议更新到在线节点
-                        if let Err(e) = app_state.node_manager.send_update_protocol(id, new_protocol).await {
-                            warn!("推送协议更新到节点 #{} 失败: {}", id, e);
-                        } else {
-                            info!("已推送协议更新到节点 #{}", id);
-                        }
-                    }
+            // 检查协议或传输参数是否变更（KCP / QUIC 参数仅在使用对应协议时生效）
+            let protocol_changed = updated.tunnel_protocol != old_protocol;
+            let kcp_changed = updated.tunnel_protocol == "kcp" && updated.kcp_config != old_kcp_config;
+            let quic_changed = updated.tunnel_protocol == "quic" && updated.quic_config != old_quic_config;
+            if protocol_changed || kcp_changed || quic_changed {
+                if protocol_changed {
+                    info!("节点 #{} 协议变更: {} -> {}", id, old_protocol, updated.tunnel_protocol);
+                } else {
+                    info!("节点 #{} {} 参数变更", id, updated.tunnel_protocol.to_uppercase());
+                }
 
+                // 检查节点是否在线
+                let connected_ids = app_state.node_manager.get_loaded_node_ids().await;
+                if connected_ids.contains(&id) {
+                    // 在线节点分阶段切换，客户端由后台任务逐个迁移
+                    crate::protocol_switch::spawn(
+                        app_state.node_manager.clone(),
+                        app_state.client_stream_manager.clone(),
+                        id,
+                        updated.tunnel_protocol.clone(),
+                        node_kcp_grpc(updated.kcp_config.as_deref()),
+                        node_quic_grpc(updated.quic_config.as_deref()),
+                    );
+                } else {
                     // 通知该节点上的所有客户端刷新配置
                     app_state.client_stream_manager.notify_clients_for_node(id).await;
                 }
@@ -310,21 +569,37 @@ pub async fn update_node(
 
             // gRPC 模式下节点会主动重连，无需手动更新连接
 
-            // 如果 speed_limit 变更，推送到在线节点
-            if updated.speed_limit != old_speed_limit {
+            // 速度限制、带宽时间表或公平分配变更，按当前时间推送到在线节点
+            if updated.speed_limit != old_speed_limit
+                || updated.speed_limit_schedule != old_speed_limit_schedule
+                || updated.fair_share != old_fair_share
+            {
+                crate::bandwidth_schedule::push_now(&app_state.node_manager, Some(id)).await;
+            }
+
+            // 默认处置规则变更，推送到在线节点
+            if updated.mitigation_config != old_mitigation_config {
                 let connected_ids = app_state.node_manager.get_loaded_node_ids().await;
                 if connected_ids.contains(&id) {
-                    let new_limit = updated.speed_limit.unwrap_or(0);
-                    if let Err(e) = app_state.node_manager.send_update_speed_limit(id, new_limit).await {
-                        warn!("推送速度限制到节点 #{} 失败: {}", id, e);
+                    let rule = crate::mitigation::to_grpc(updated.mitigation_config.as_deref());
+                    if let Err(e) = app_state.node_manager.send_update_mitigation(id, rule).await {
+                        warn!("推送来源 IP 处置规则到节点 #{} 失败: {}", id, e);
                     } else {
-                        info!("已推送速度限制到节点 #{}: {} bytes/s", id, new_limit);
+                        info!("已推送来源 IP 处置规则到节点 #{}", id);
                     }
                 }
             }
 
+            // 防火墙配置变更，旧位置放行的端口先关闭再按新配置放行
+            if updated.firewall_config != old_firewall_config {
+                crate::cloud_firewall::config_changed(id, old_firewall_config, updated.firewall_config.as_deref());
+            }
+
             (StatusCode::OK, ApiResponse::success(updated))
         }
+        Err(e) if crate::optimistic_lock::is_conflict(&e) => {
+            (StatusCode::CONFLICT, ApiResponse::<node::Model>::error("数据已被他人修改，请刷新后重试".to_string()))
+        }
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<node::Model>::error(format!("Failed to update node: {}", e))),
     }
 }
@@ -360,8 +635,14 @@ pub async fn delete_node(
         );
     }
 
+    let firewall_config = Node::find_by_id(id).one(db).await.ok().flatten().and_then(|n| n.firewall_config);
+
     match Node::delete_by_id(id).exec(db).await {
         Ok(_) => {
+            // 用删除前的配置关闭仍未关闭的端口
+            if firewall_config.is_some() {
+                crate::cloud_firewall::config_changed(id, firewall_config, None);
+            }
             // gRPC 模式下节点断开后会自动清理
             (StatusCode::OK, ApiResponse::success("Node deleted successfully"))
         }
@@ -372,6 +653,53 @@ pub async fn delete_node(
     }
 }
 
+#[derive(Deserialize)]
+pub struct CloneNodeQuery {
+    /// 是否同时禁用源节点上已克隆的代理（默认是）
+    #[serde(default = "default_disable_source")]
+    disable_source: bool,
+}
+
+fn default_disable_source() -> bool {
+    true
+}
+
+/// POST /api/nodes/{id}/clone-to/{target_id} — 把节点上的代理克隆到备用节点（灾难恢复）
+pub async fn clone_node_to(
+    Path((id, target_id)): Path<(i64, i64)>,
+    Query(query): Query<CloneNodeQuery>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+) -> impl IntoResponse {
+    let auth_user = match auth_user_opt {
+        Some(user) => user,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<crate::node_clone::CloneReport>::error("Not authenticated".to_string())),
+    };
+
+    if !auth_user.is_admin {
+        return (StatusCode::FORBIDDEN, ApiResponse::<crate::node_clone::CloneReport>::error("Only admin can manage nodes".to_string()));
+    }
+
+    let db = get_connection().await;
+    match crate::node_clone::clone_node_proxies(
+        id,
+        target_id,
+        query.disable_source,
+        app_state.proxy_control.as_ref(),
+        &app_state.client_stream_manager,
+        &app_state.config_manager,
+        db,
+    )
+    .await
+    {
+        Ok(report) => (StatusCode::OK, ApiResponse::success(report)),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            ApiResponse::<crate::node_clone::CloneReport>::error(format!("克隆节点失败: {}", e)),
+        ),
+    }
+}
+
 /// POST /api/nodes/{id}/test — 测试节点连接
 pub async fn test_node_connection(
     Path(id): Path<i64>,
@@ -426,12 +754,21 @@ pub async fn get_node_status(
         return (StatusCode::NOT_FOUND, ApiResponse::<serde_json::Value>::error("Node not connected via gRPC".to_string()));
     }
 
-    // 通过 ProxyControl 获取状态（会通过 gRPC 流发送命令）
-    match app_state.proxy_control.get_server_status().await {
+    // 通过 gRPC 流向该节点发送状态查询命令
+    match app_state.node_manager.get_node_server_status(id).await {
         Ok(status) => {
             let result = serde_json::json!({
                 "connected_clients": status.connected_clients,
                 "active_proxy_count": status.active_proxy_count,
+                "reaped_connections": status.reaped_connections,
+                "accept_rate_limited": status.accept_rate_limited,
+                "accept_pending_rejected": status.accept_pending_rejected,
+                "active_relays": status.active_relays,
+                "relay_limit": status.relay_limit,
+                "relay_rejected": status.relay_rejected,
+                "open_fds": status.open_fds,
+                "fd_limit": status.fd_limit,
+                "kcp": status.kcp,
             });
             (StatusCode::OK, ApiResponse::success(result))
         }
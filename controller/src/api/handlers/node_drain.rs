@@ -0,0 +1,194 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+use crate::entity::failover_group::{format_ids, parse_ids};
+use crate::entity::{node, proxy, Node, Proxy};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use crate::AppState;
+use super::ApiResponse;
+
+#[derive(Serialize, Default)]
+pub struct DrainResult {
+    /// 本次禁用（排空）或重新启用（恢复）的代理
+    pub changed: Vec<i64>,
+    /// 恢复时跳过的代理：已删除、已被手动启用或已迁移到其他节点
+    pub skipped: Vec<i64>,
+    /// 更新失败的代理，恢复时仍保留在排空记录中，可以重试
+    pub failed: Vec<i64>,
+}
+
+async fn find_node(auth_user: Option<AuthUser>, id: i64) -> Result<node::Model, (StatusCode, String)> {
+    let Some(auth_user) = auth_user else {
+        return Err((StatusCode::UNAUTHORIZED, "未认证".to_string()));
+    };
+    if !auth_user.is_admin {
+        return Err((StatusCode::FORBIDDEN, "只有管理员可以管理节点".to_string()));
+    }
+    match Node::find_by_id(id).one(get_connection().await).await {
+        Ok(Some(n)) => Ok(n),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "节点不存在".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("查询节点失败: {}", e))),
+    }
+}
+
+/// 保存节点的排空记录，`None` 表示节点不再处于排空状态
+async fn save_drained(node: node::Model, drained: Option<Vec<i64>>) -> Result<node::Model, sea_orm::DbErr> {
+    let lock_version = node.lock_version;
+    let mut active: node::ActiveModel = node.into();
+    active.drained_proxy_ids = Set(drained.map(|ids| format_ids(&ids)));
+    active.lock_version = Set(lock_version + 1);
+    active.updated_at = Set(chrono::Utc::now().naive_utc());
+    active.update(get_connection().await).await
+}
+
+async fn set_enabled(app_state: &AppState, p: &proxy::Model, enabled: bool) -> Result<(), sea_orm::DbErr> {
+    let mut active: proxy::ActiveModel = p.clone().into();
+    active.enabled = Set(enabled);
+    active.lock_version = Set(p.lock_version + 1);
+    active.updated_at = Set(chrono::Utc::now().naive_utc());
+    active.update(get_connection().await).await?;
+
+    let result = if enabled {
+        app_state.proxy_control.start_proxy(&p.client_id, p.id).await
+    } else {
+        app_state.proxy_control.stop_proxy(&p.client_id, p.id).await
+    };
+    if let Err(e) = result {
+        warn!("{}代理监听器失败 (ID: {}): {}", if enabled { "启动" } else { "停止" }, p.id, e);
+    }
+    Ok(())
+}
+
+fn notify_clients(app_state: &AppState, client_ids: HashSet<String>) {
+    crate::cloud_firewall::request_sync();
+    let csm = app_state.client_stream_manager.clone();
+    tokio::spawn(async move {
+        for client_id in client_ids {
+            csm.notify_proxy_change(&client_id).await;
+        }
+    });
+}
+
+/// POST /api/nodes/{id}/drain - 排空节点：禁用节点上所有启用的代理，并记录被禁用的代理
+///
+/// 已处于排空状态时再次排空会把之后新启用的代理追加到记录中。
+pub async fn drain_node(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+) -> impl IntoResponse {
+    let node = match find_node(auth_user, id).await {
+        Ok(n) => n,
+        Err((status, e)) => return (status, ApiResponse::<DrainResult>::error(e)),
+    };
+    let db = get_connection().await;
+    let proxies = match Proxy::find()
+        .filter(proxy::Column::NodeId.eq(id))
+        .filter(proxy::Column::Enabled.eq(true))
+        .all(db)
+        .await
+    {
+        Ok(p) => p,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("查询代理失败: {}", e))),
+    };
+
+    let mut drained = node.drained_proxy_ids.as_deref().map(parse_ids).unwrap_or_default();
+    let mut result = DrainResult::default();
+    let mut client_ids = HashSet::new();
+    for p in &proxies {
+        match set_enabled(&app_state, p, false).await {
+            Ok(()) => {
+                result.changed.push(p.id);
+                if !drained.contains(&p.id) {
+                    drained.push(p.id);
+                }
+                client_ids.insert(p.client_id.clone());
+            }
+            Err(e) => {
+                error!("排空节点 #{} 时禁用代理 {} 失败: {}", id, p.id, e);
+                result.failed.push(p.id);
+            }
+        }
+    }
+
+    let name = node.name.clone();
+    if let Err(e) = save_drained(node, Some(drained)).await {
+        // 代理已经禁用，记录保存失败时返回被禁用的代理 ID，便于手动恢复
+        error!("保存节点 #{} 的排空记录失败，已禁用的代理: {:?}: {}", id, result.changed, e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("保存排空记录失败: {}", e)));
+    }
+    info!("节点 {} (#{}) 已排空: 禁用 {} 个代理，失败 {} 个", name, id, result.changed.len(), result.failed.len());
+    notify_clients(&app_state, client_ids);
+
+    (StatusCode::OK, ApiResponse::success(result))
+}
+
+/// POST /api/nodes/{id}/undrain - 结束排空：只重新启用排空时禁用的代理
+pub async fn undrain_node(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+) -> impl IntoResponse {
+    let node = match find_node(auth_user, id).await {
+        Ok(n) => n,
+        Err((status, e)) => return (status, ApiResponse::<DrainResult>::error(e)),
+    };
+    let Some(drained) = node.drained_proxy_ids.as_deref().map(parse_ids) else {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("节点未处于排空状态".to_string()));
+    };
+    let db = get_connection().await;
+    let proxies = match Proxy::find().filter(proxy::Column::Id.is_in(drained.clone())).all(db).await {
+        Ok(p) => p,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("查询代理失败: {}", e))),
+    };
+
+    let mut result = DrainResult::default();
+    let mut client_ids = HashSet::new();
+    for &proxy_id in &drained {
+        let Some(p) = proxies.iter().find(|p| p.id == proxy_id) else {
+            result.skipped.push(proxy_id);
+            continue;
+        };
+        if p.enabled || p.node_id != Some(id) {
+            result.skipped.push(proxy_id);
+            continue;
+        }
+        match set_enabled(&app_state, p, true).await {
+            Ok(()) => {
+                result.changed.push(proxy_id);
+                client_ids.insert(p.client_id.clone());
+            }
+            Err(e) => {
+                error!("恢复节点 #{} 时启用代理 {} 失败: {}", id, proxy_id, e);
+                result.failed.push(proxy_id);
+            }
+        }
+    }
+
+    let name = node.name.clone();
+    let remaining = (!result.failed.is_empty()).then(|| result.failed.clone());
+    if let Err(e) = save_drained(node, remaining).await {
+        error!("更新节点 #{} 的排空记录失败: {}", id, e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("更新排空记录失败: {}", e)));
+    }
+    info!(
+        "节点 {} (#{}) 已结束排空: 启用 {} 个代理，跳过 {} 个，失败 {} 个",
+        name,
+        id,
+        result.changed.len(),
+        result.skipped.len(),
+        result.failed.len()
+    );
+    notify_clients(&app_state, client_ids);
+
+    (StatusCode::OK, ApiResponse::success(result))
+}
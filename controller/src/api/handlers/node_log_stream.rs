@@ -0,0 +1,94 @@
+//! 实时跟随节点日志（Server-Sent Events），用于故障排查时持续查看节点的新日志
+
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+};
+use sea_orm::EntityTrait;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use common::protocol::control::LogEntry;
+
+use crate::{entity::Node, middleware::AuthUser, migration::get_connection, AppState};
+
+use super::ApiResponse;
+
+#[derive(Deserialize)]
+pub struct NodeLogStreamQuery {
+    /// 最低日志级别（error / warn / info），默认不过滤
+    pub level: Option<String>,
+}
+
+/// GET /api/nodes/{id}/logs/stream - 实时跟随节点日志（仅管理员）
+///
+/// 每条日志为一个 `log` 事件（JSON），节点上报不及时跳过的条数以 `skipped` 事件通知；
+/// 节点断开时事件流结束，关闭连接后节点停止上报。
+pub async fn stream_node_logs(
+    Path(id): Path<i64>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Query(query): Query<NodeLogStreamQuery>,
+) -> Response {
+    let Some(auth_user) = auth_user_opt else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<()>::error("未认证".to_string())).into_response();
+    };
+    if !auth_user.is_admin {
+        return (StatusCode::FORBIDDEN, ApiResponse::<()>::error("只有管理员可以查看节点日志".to_string())).into_response();
+    }
+    let level = query.level.unwrap_or_default().trim().to_ascii_lowercase();
+    if !level.is_empty() && level.parse::<tracing::Level>().is_err() {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<()>::error(format!("无效的日志级别: {}", level))).into_response();
+    }
+
+    let db = get_connection().await;
+    match Node::find_by_id(id).one(db).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<()>::error("节点不存在".to_string())).into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<()>::error(format!("查询节点失败: {}", e))).into_response(),
+    }
+
+    let node_manager = app_state.node_manager.clone();
+    let (request_id, mut chunks) = match node_manager.follow_logs(id, &level).await {
+        Ok(follow) => follow,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<()>::error(format!("跟随节点日志失败: {}", e))).into_response(),
+    };
+    tracing::info!("管理员 '{}' 开始实时查看节点 #{} 的日志", auth_user.username, id);
+
+    let (event_tx, event_rx) = mpsc::channel::<Result<Event, Infallible>>(256);
+    tokio::spawn(async move {
+        'follow: loop {
+            let chunk = tokio::select! {
+                chunk = chunks.recv() => match chunk {
+                    Some(chunk) => chunk,
+                    None => break,
+                },
+                // 管理员关闭了页面
+                _ = event_tx.closed() => break,
+            };
+            if chunk.skipped > 0 {
+                let event = Event::default().event("skipped").data(chunk.skipped.to_string());
+                if event_tx.send(Ok(event)).await.is_err() {
+                    break;
+                }
+            }
+            for log in chunk.logs {
+                let entry = LogEntry { timestamp: log.timestamp, level: log.level, message: log.message };
+                let event = Event::default().event("log").json_data(entry).unwrap_or_default();
+                if event_tx.send(Ok(event)).await.is_err() {
+                    break 'follow;
+                }
+            }
+        }
+        node_manager.stop_follow_logs(id, &request_id).await;
+    });
+
+    Sse::new(ReceiverStream::new(event_rx)).keep_alive(KeepAlive::default()).into_response()
+}
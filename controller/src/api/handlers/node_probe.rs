@@ -0,0 +1,170 @@
+//! 从节点发起连通性探测（TCP 连接 / ping / traceroute），用于判断故障出在访客→节点还是节点→目标之间
+
+use std::convert::Infallible;
+use std::time::Duration;
+
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json, Response,
+    },
+};
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+
+use common::grpc::oxiproxy;
+
+use crate::{entity::Node, middleware::AuthUser, migration::get_connection, AppState};
+
+use super::ApiResponse;
+
+/// 非流式请求等待探测完成的时间上限（节点端 90 秒超时，另留出余量）
+const PROBE_WAIT: Duration = Duration::from_secs(100);
+
+#[derive(Deserialize)]
+pub struct ProbeRequest {
+    /// tcp / ping / traceroute
+    pub kind: String,
+    pub host: String,
+    /// tcp 探测必填
+    pub port: Option<u16>,
+    /// tcp / ping 的次数，默认 4 次，最多 10 次
+    pub count: Option<u32>,
+    #[serde(rename = "timeoutMs")]
+    pub timeout_ms: Option<u32>,
+    /// 为 true 时以 Server-Sent Events 逐条返回结果
+    #[serde(default)]
+    pub stream: bool,
+}
+
+#[derive(Serialize)]
+pub struct ProbeStepView {
+    pub seq: u32,
+    pub success: bool,
+    #[serde(rename = "rttMs")]
+    pub rtt_ms: Option<f64>,
+    pub message: String,
+    pub done: bool,
+}
+
+impl From<oxiproxy::ProbeStep> for ProbeStepView {
+    fn from(step: oxiproxy::ProbeStep) -> Self {
+        Self {
+            seq: step.seq,
+            success: step.success,
+            rtt_ms: step.rtt_ms,
+            message: step.message,
+            done: step.done,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ProbeResult {
+    #[serde(rename = "nodeId")]
+    pub node_id: i64,
+    pub kind: String,
+    pub host: String,
+    pub port: Option<u16>,
+    /// 汇总结果是否成功（未收到汇总时为 false）
+    pub success: bool,
+    pub summary: String,
+    pub steps: Vec<ProbeStepView>,
+}
+
+/// 校验探测参数，返回错误信息
+fn validate(req: &ProbeRequest) -> Result<(), String> {
+    match req.kind.as_str() {
+        "tcp" if req.port.unwrap_or(0) == 0 => return Err("TCP 探测需要指定目标端口".to_string()),
+        "tcp" | "ping" | "traceroute" => {}
+        other => return Err(format!("不支持的探测类型: {}（可选 tcp / ping / traceroute）", other)),
+    }
+    let host = req.host.trim();
+    if host.is_empty()
+        || host.len() > 253
+        || host.starts_with('-')
+        || !host.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '_'))
+    {
+        return Err("目标主机格式不正确".to_string());
+    }
+    Ok(())
+}
+
+/// POST /api/nodes/{id}/probe - 让节点向指定目标发起连通性探测（仅管理员）
+pub async fn probe_from_node(
+    Path(id): Path<i64>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<ProbeRequest>,
+) -> Response {
+    let Some(auth_user) = auth_user_opt else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<ProbeResult>::error("未认证".to_string())).into_response();
+    };
+    if !auth_user.is_admin {
+        return (StatusCode::FORBIDDEN, ApiResponse::<ProbeResult>::error("只有管理员可以从节点发起探测".to_string())).into_response();
+    }
+    if let Err(msg) = validate(&req) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<ProbeResult>::error(msg)).into_response();
+    }
+
+    let db = get_connection().await;
+    match Node::find_by_id(id).one(db).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<ProbeResult>::error("节点不存在".to_string())).into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<ProbeResult>::error(format!("查询节点失败: {}", e))).into_response(),
+    }
+
+    let host = req.host.trim().to_string();
+    let cmd = oxiproxy::ProbeCommand {
+        request_id: String::new(),
+        kind: req.kind.clone(),
+        host: host.clone(),
+        port: req.port.unwrap_or(0) as u32,
+        count: req.count.unwrap_or(4),
+        timeout_ms: req.timeout_ms.unwrap_or(0),
+    };
+    let mut steps = match app_state.node_manager.start_probe(id, cmd).await {
+        Ok(rx) => rx,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<ProbeResult>::error(format!("发起探测失败: {}", e))).into_response(),
+    };
+
+    tracing::info!("管理员 '{}' 从节点 #{} 发起 {} 探测: {}", auth_user.username, id, req.kind, host);
+
+    if req.stream {
+        // 节点上报汇总结果或断开后结果通道关闭，事件流随之结束
+        let events = ReceiverStream::new(steps)
+            .map(|step| Ok::<_, Infallible>(Event::default().json_data(ProbeStepView::from(step)).unwrap_or_default()));
+        return Sse::new(events).keep_alive(KeepAlive::default()).into_response();
+    }
+
+    let mut result = ProbeResult {
+        node_id: id,
+        kind: req.kind,
+        host,
+        port: req.port,
+        success: false,
+        summary: "节点未返回汇总结果".to_string(),
+        steps: Vec::new(),
+    };
+    let collect = async {
+        while let Some(step) = steps.recv().await {
+            if step.done {
+                result.success = step.success;
+                result.summary = step.message.clone();
+            }
+            let done = step.done;
+            result.steps.push(step.into());
+            if done {
+                break;
+            }
+        }
+    };
+    if tokio::time::timeout(PROBE_WAIT, collect).await.is_err() {
+        result.summary = format!("等待节点返回结果超时（{} 秒）", PROBE_WAIT.as_secs());
+    }
+
+    (StatusCode::OK, ApiResponse::success(result)).into_response()
+}
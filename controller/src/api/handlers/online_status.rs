@@ -0,0 +1,66 @@
+use axum::{extract::Extension, http::StatusCode, response::IntoResponse};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QuerySelect};
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::entity::{client, Client};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use crate::online_status;
+use crate::tenant::UserScope;
+use super::ApiResponse;
+
+#[derive(Serialize)]
+pub struct OnlineStatus {
+    /// 在线的客户端 ID（仅限当前用户可见的客户端）
+    #[serde(rename = "clientIds")]
+    pub client_ids: Vec<i64>,
+    /// 在线的节点 ID
+    #[serde(rename = "nodeIds")]
+    pub node_ids: Vec<i64>,
+}
+
+/// GET /api/status/online - 从内存缓存读取实时在线状态，供前端轮询
+pub async fn get_online_status(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<OnlineStatus>::error("未认证".to_string()));
+    };
+
+    let mut client_ids = online_status::clients().online_ids();
+    let db = get_connection().await;
+    match UserScope::of(&auth_user).user_ids(db).await {
+        Ok(None) => {}
+        Ok(Some(user_ids)) => {
+            let visible: HashSet<i64> = match Client::find()
+                .select_only()
+                .column(client::Column::Id)
+                .filter(client::Column::UserId.is_in(user_ids))
+                .into_tuple::<i64>()
+                .all(db)
+                .await
+            {
+                Ok(ids) => ids.into_iter().collect(),
+                Err(e) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        ApiResponse::error(format!("查询客户端失败: {}", e)),
+                    )
+                }
+            };
+            client_ids.retain(|id| visible.contains(id));
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询用户范围失败: {}", e)),
+            )
+        }
+    }
+
+    (
+        StatusCode::OK,
+        ApiResponse::success(OnlineStatus { client_ids, node_ids: online_status::nodes().online_ids() }),
+    )
+}
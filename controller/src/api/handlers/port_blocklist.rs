@@ -0,0 +1,191 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, QueryFilter, QueryOrder, Set};
+use serde::Deserialize;
+
+use crate::entity::port_blocklist::{self, KIND_LOCAL_TARGET};
+use crate::entity::{Node, PortBlocklist, Tenant};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use crate::AppState;
+use super::ApiResponse;
+
+#[derive(Deserialize)]
+pub struct CreateBlocklistRuleRequest {
+    /// remote_port / local_target
+    pub kind: String,
+    pub pattern: String,
+    #[serde(rename = "nodeId")]
+    pub node_id: Option<i64>,
+    #[serde(rename = "tenantId")]
+    pub tenant_id: Option<i64>,
+    pub description: Option<String>,
+}
+
+/// 端口黑名单只能由平台管理员管理
+fn require_platform_admin(auth_user: Option<AuthUser>) -> Result<AuthUser, (StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+    match auth_user {
+        Some(user) if user.is_admin && user.tenant_id.is_none() => Ok(user),
+        Some(_) => Err((StatusCode::FORBIDDEN, ApiResponse::error("仅平台管理员".to_string()))),
+        None => Err((StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()))),
+    }
+}
+
+/// 本地目标规则变更后重新下发给所有在线客户端
+fn notify_clients(app_state: &AppState, kind: &str) {
+    if kind != KIND_LOCAL_TARGET {
+        return;
+    }
+    let csm = app_state.client_stream_manager.clone();
+    tokio::spawn(async move {
+        csm.notify_all_clients().await;
+    });
+}
+
+/// GET /api/port-blocklist - 获取端口黑名单规则
+pub async fn list_blocklist_rules(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    match PortBlocklist::find()
+        .order_by_asc(port_blocklist::Column::Kind)
+        .order_by_asc(port_blocklist::Column::Id)
+        .all(db)
+        .await
+    {
+        Ok(rules) => (StatusCode::OK, ApiResponse::success(serde_json::json!(rules))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("查询端口黑名单失败: {}", e)),
+        ),
+    }
+}
+
+/// POST /api/port-blocklist - 添加端口黑名单规则
+pub async fn create_blocklist_rule(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<CreateBlocklistRuleRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let pattern = req.pattern.trim().to_string();
+    if let Err(e) = crate::port_blocklist::validate_pattern(&req.kind, &pattern) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(e));
+    }
+
+    let db = get_connection().await;
+    if let Some(node_id) = req.node_id {
+        match Node::find_by_id(node_id).one(db).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return (StatusCode::BAD_REQUEST, ApiResponse::error("节点不存在".to_string())),
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ApiResponse::error(format!("查询节点失败: {}", e)),
+                )
+            }
+        }
+    }
+    if let Some(tenant_id) = req.tenant_id {
+        match Tenant::find_by_id(tenant_id).one(db).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return (StatusCode::BAD_REQUEST, ApiResponse::error("租户不存在".to_string())),
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ApiResponse::error(format!("查询租户失败: {}", e)),
+                )
+            }
+        }
+    }
+
+    match PortBlocklist::find()
+        .filter(port_blocklist::Column::Kind.eq(&req.kind))
+        .filter(port_blocklist::Column::Pattern.eq(&pattern))
+        .filter(match req.node_id {
+            Some(id) => port_blocklist::Column::NodeId.eq(id),
+            None => port_blocklist::Column::NodeId.is_null(),
+        })
+        .filter(match req.tenant_id {
+            Some(id) => port_blocklist::Column::TenantId.eq(id),
+            None => port_blocklist::Column::TenantId.is_null(),
+        })
+        .one(db)
+        .await
+    {
+        Ok(Some(_)) => return (StatusCode::CONFLICT, ApiResponse::error("规则已存在".to_string())),
+        Ok(None) => {}
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询端口黑名单失败: {}", e)),
+            )
+        }
+    }
+
+    let rule = port_blocklist::ActiveModel {
+        id: NotSet,
+        kind: Set(req.kind),
+        pattern: Set(pattern),
+        node_id: Set(req.node_id),
+        tenant_id: Set(req.tenant_id),
+        description: Set(req.description),
+        created_at: Set(Utc::now().naive_utc()),
+    };
+
+    match rule.insert(db).await {
+        Ok(rule) => {
+            notify_clients(&app_state, &rule.kind);
+            (StatusCode::OK, ApiResponse::success(serde_json::json!(rule)))
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("添加端口黑名单规则失败: {}", e)),
+        ),
+    }
+}
+
+/// DELETE /api/port-blocklist/{id} - 删除端口黑名单规则
+pub async fn delete_blocklist_rule(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    let rule = match PortBlocklist::find_by_id(id).one(db).await {
+        Ok(Some(r)) => r,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("规则不存在".to_string())),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询端口黑名单失败: {}", e)),
+            )
+        }
+    };
+
+    match PortBlocklist::delete_by_id(id).exec(db).await {
+        Ok(_) => {
+            notify_clients(&app_state, &rule.kind);
+            (StatusCode::OK, ApiResponse::success(serde_json::json!(null)))
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("删除端口黑名单规则失败: {}", e)),
+        ),
+    }
+}
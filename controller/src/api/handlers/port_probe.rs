@@ -0,0 +1,57 @@
+//! 代理公网端口探测：从外部检查代理的远程端口是否可达
+
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+use crate::api::access;
+use crate::entity::{proxy_port_probe, ProxyPortProbe};
+use crate::migration::get_connection;
+use crate::{middleware::AuthUser, AppState};
+
+use super::ApiResponse;
+
+/// GET /api/proxies/{id}/probe - 获取代理最近一次的端口探测结果（从未探测时为 null）
+pub async fn get_proxy_port_probe(
+    Path(id): Path<i64>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    let auth_user = match access::require_user(auth_user_opt) {
+        Ok(user) => user,
+        Err((status, e)) => return (status, ApiResponse::<Option<proxy_port_probe::Model>>::error(e)),
+    };
+    let db = get_connection().await;
+    if let Err((status, e)) = access::accessible_proxy(&auth_user, id, db).await {
+        return (status, ApiResponse::error(e));
+    }
+
+    match ProxyPortProbe::find().filter(proxy_port_probe::Column::ProxyId.eq(id)).one(db).await {
+        Ok(result) => (StatusCode::OK, ApiResponse::success(result)),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("查询探测结果失败: {}", e))),
+    }
+}
+
+/// POST /api/proxies/{id}/probe - 立即从外部探测代理的远程端口并保存结果
+pub async fn probe_proxy_port(
+    Path(id): Path<i64>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+) -> impl IntoResponse {
+    let auth_user = match access::require_user(auth_user_opt) {
+        Ok(user) => user,
+        Err((status, e)) => return (status, ApiResponse::<proxy_port_probe::Model>::error(e)),
+    };
+    let db = get_connection().await;
+    let proxy = match access::accessible_proxy(&auth_user, id, db).await {
+        Ok(p) => p,
+        Err((status, e)) => return (status, ApiResponse::error(e)),
+    };
+
+    match crate::port_probe::probe_proxy(&proxy, &app_state.node_manager, &app_state.config_manager, db).await {
+        Ok(result) => (StatusCode::OK, ApiResponse::success(result)),
+        Err(e) => (StatusCode::BAD_REQUEST, ApiResponse::error(format!("探测失败: {}", e))),
+    }
+}
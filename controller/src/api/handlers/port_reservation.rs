@@ -0,0 +1,223 @@
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, QueryFilter, QueryOrder, Set};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::entity::{port_reservation, proxy, Node, PortReservation, Proxy, Tenant, User};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use super::ApiResponse;
+
+/// 端口预留只能由平台管理员管理
+fn require_platform_admin(auth_user: Option<AuthUser>) -> Result<AuthUser, (StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+    match auth_user {
+        Some(user) if user.is_admin && user.tenant_id.is_none() => Ok(user),
+        Some(_) => Err((StatusCode::FORBIDDEN, ApiResponse::error("仅平台管理员".to_string()))),
+        None => Err((StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()))),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreatePortReservationRequest {
+    #[serde(rename = "nodeId")]
+    pub node_id: i64,
+    #[serde(rename = "portStart")]
+    pub port_start: i32,
+    /// 为空时只预留 `portStart` 一个端口
+    #[serde(rename = "portEnd")]
+    pub port_end: Option<i32>,
+    #[serde(rename = "userId")]
+    pub user_id: Option<i64>,
+    #[serde(rename = "tenantId")]
+    pub tenant_id: Option<i64>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<DateTime<Utc>>,
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PortReservationListQuery {
+    #[serde(rename = "nodeId")]
+    pub node_id: Option<i64>,
+}
+
+/// GET /api/port-reservations - 端口预留列表
+pub async fn list_port_reservations(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Query(query): Query<PortReservationListQuery>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let mut select = PortReservation::find();
+    if let Some(node_id) = query.node_id {
+        select = select.filter(port_reservation::Column::NodeId.eq(node_id));
+    }
+
+    let db = get_connection().await;
+    match select
+        .order_by_asc(port_reservation::Column::NodeId)
+        .order_by_asc(port_reservation::Column::PortStart)
+        .all(db)
+        .await
+    {
+        Ok(reservations) => (StatusCode::OK, ApiResponse::success(serde_json::json!(reservations))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("查询端口预留失败: {}", e)),
+        ),
+    }
+}
+
+/// POST /api/port-reservations - 为用户或租户预留节点端口
+pub async fn create_port_reservation(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Json(req): Json<CreatePortReservationRequest>,
+) -> impl IntoResponse {
+    let auth_user = match require_platform_admin(auth_user) {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let port_end = req.port_end.unwrap_or(req.port_start);
+    if let Err(e) = crate::port_reservation::validate_range(req.port_start, port_end) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(e));
+    }
+    if req.user_id.is_some() == req.tenant_id.is_some() {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("须指定用户或租户之一".to_string()));
+    }
+    if req.expires_at.is_some_and(|t| t <= Utc::now()) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("过期时间须晚于当前时间".to_string()));
+    }
+
+    let db = get_connection().await;
+    let exists = async {
+        let node = Node::find_by_id(req.node_id).one(db).await?.map(|_| ());
+        let owner = match (req.user_id, req.tenant_id) {
+            (Some(user_id), _) => User::find_by_id(user_id).one(db).await?.map(|_| ()),
+            (_, Some(tenant_id)) => Tenant::find_by_id(tenant_id).one(db).await?.map(|_| ()),
+            _ => None,
+        };
+        Ok::<_, sea_orm::DbErr>((node.is_some(), owner.is_some()))
+    };
+    match exists.await {
+        Ok((false, _)) => return (StatusCode::BAD_REQUEST, ApiResponse::error("节点不存在".to_string())),
+        Ok((_, false)) => return (StatusCode::BAD_REQUEST, ApiResponse::error("用户或租户不存在".to_string())),
+        Ok(_) => {}
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询节点或用户失败: {}", e)),
+            )
+        }
+    }
+
+    // 同一节点上的预留不能重叠
+    match crate::port_reservation::active_reservations(req.node_id, db).await {
+        Ok(reservations) => {
+            if let Some(r) = reservations.iter().find(|r| r.port_start <= port_end && req.port_start <= r.port_end) {
+                return (
+                    StatusCode::CONFLICT,
+                    ApiResponse::error(format!("与已有预留 #{}（{}-{}）重叠", r.id, r.port_start, r.port_end)),
+                );
+            }
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询端口预留失败: {}", e)),
+            )
+        }
+    }
+
+    // 范围内已有的代理须属于预留对象
+    let proxies = match Proxy::find()
+        .filter(proxy::Column::NodeId.eq(req.node_id))
+        .filter(proxy::Column::Enabled.eq(true))
+        .filter(proxy::Column::RemotePort.between(req.port_start, port_end))
+        .all(db)
+        .await
+    {
+        Ok(p) => p,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询代理失败: {}", e)),
+            )
+        }
+    };
+    for proxy in proxies {
+        let (user_id, tenant_id) = match crate::port_reservation::client_owner(&proxy.client_id, db).await {
+            Ok(owner) => owner,
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ApiResponse::error(format!("查询代理所属用户失败: {}", e)),
+                )
+            }
+        };
+        let owned = (req.user_id.is_some() && req.user_id == user_id)
+            || (req.tenant_id.is_some() && req.tenant_id == tenant_id);
+        if !owned {
+            return (
+                StatusCode::CONFLICT,
+                ApiResponse::error(format!("端口 {} 已被其他用户的代理「{}」占用", proxy.remote_port, proxy.name)),
+            );
+        }
+    }
+
+    let reservation = port_reservation::ActiveModel {
+        id: NotSet,
+        node_id: Set(req.node_id),
+        port_start: Set(req.port_start),
+        port_end: Set(port_end),
+        user_id: Set(req.user_id),
+        tenant_id: Set(req.tenant_id),
+        expires_at: Set(req.expires_at.map(|t| t.naive_utc())),
+        description: Set(req.description),
+        created_by: Set(auth_user.username.clone()),
+        created_at: Set(Utc::now().naive_utc()),
+    };
+
+    match reservation.insert(db).await {
+        Ok(reservation) => {
+            info!(
+                "管理员 {} 在节点 #{} 预留端口 {}-{}",
+                auth_user.username, reservation.node_id, reservation.port_start, reservation.port_end
+            );
+            (StatusCode::OK, ApiResponse::success(serde_json::json!(reservation)))
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("预留端口失败: {}", e)),
+        ),
+    }
+}
+
+/// DELETE /api/port-reservations/{id} - 取消端口预留
+pub async fn delete_port_reservation(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    match PortReservation::delete_by_id(id).exec(db).await {
+        Ok(res) if res.rows_affected == 0 => {
+            (StatusCode::NOT_FOUND, ApiResponse::error("预留不存在".to_string()))
+        }
+        Ok(_) => (StatusCode::OK, ApiResponse::success(serde_json::json!(null))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("取消端口预留失败: {}", e)),
+        ),
+    }
+}
@@ -0,0 +1,292 @@
+//! 当前用户的自助接口：查看 / 修改个人资料、修改密码、轮换自己客户端的 token
+//!
+//! 邮箱验证和找回密码见 [`super::account_recovery`]。
+
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, Set};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    auth::{hash_password, verify_password},
+    entity::{client, user_node, Client, User, UserNode},
+    middleware::AuthUser,
+    migration::get_connection,
+    password_policy::PasswordPolicy,
+    AppState,
+};
+
+use super::ApiResponse;
+
+/// 当前用户的资料和用量
+#[derive(Serialize)]
+pub struct Profile {
+    pub id: i64,
+    pub username: String,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    pub email: Option<String>,
+    #[serde(rename = "emailVerified")]
+    pub email_verified: bool,
+    pub is_admin: bool,
+    #[serde(rename = "tenantId")]
+    pub tenant_id: Option<i64>,
+    #[serde(rename = "isTenantAdmin")]
+    pub is_tenant_admin: bool,
+    pub created_at: String,
+    #[serde(rename = "totalBytesSent")]
+    pub total_bytes_sent: i64,
+    #[serde(rename = "totalBytesReceived")]
+    pub total_bytes_received: i64,
+    #[serde(rename = "trafficQuotaGb")]
+    pub traffic_quota_gb: Option<f64>,
+    #[serde(rename = "isTrafficExceeded")]
+    pub is_traffic_exceeded: bool,
+    #[serde(rename = "maxPortCount")]
+    pub max_port_count: Option<i32>,
+    #[serde(rename = "currentPortCount")]
+    pub current_port_count: u64,
+    #[serde(rename = "maxNodeCount")]
+    pub max_node_count: Option<i32>,
+    #[serde(rename = "currentNodeCount")]
+    pub current_node_count: u64,
+    #[serde(rename = "maxClientCount")]
+    pub max_client_count: Option<i32>,
+    #[serde(rename = "currentClientCount")]
+    pub current_client_count: u64,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateProfileRequest {
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    pub email: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
+
+#[derive(Serialize)]
+pub struct RotateTokenResponse {
+    pub token: String,
+}
+
+/// 空字符串视为清空
+fn normalize(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// GET /api/auth/me/profile - 当前用户的资料和用量
+pub async fn get_profile(Extension(auth_user): Extension<Option<AuthUser>>) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<Profile>::error("未认证".to_string()));
+    };
+
+    let db = get_connection().await;
+    let user = match User::find_by_id(auth_user.id).one(db).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<Profile>::error("用户不存在".to_string())),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<Profile>::error(format!("查询用户失败: {}", e))),
+    };
+
+    let (traffic_quota_gb, max_port_count, max_node_count, max_client_count) =
+        crate::subscription_quota::get_user_final_quota(
+            user.id,
+            user.traffic_quota_gb,
+            user.max_port_count,
+            user.max_node_count,
+            user.max_client_count,
+            db,
+        )
+        .await
+        .unwrap_or((user.traffic_quota_gb, user.max_port_count, user.max_node_count, user.max_client_count));
+
+    let current_port_count = crate::port_limiter::get_user_port_counts(&[user.id], db)
+        .await
+        .ok()
+        .and_then(|counts| counts.get(&user.id).copied())
+        .unwrap_or(0);
+    let current_node_count = UserNode::find()
+        .filter(user_node::Column::UserId.eq(user.id))
+        .count(db)
+        .await
+        .unwrap_or(0);
+    let current_client_count = Client::find()
+        .filter(client::Column::UserId.eq(user.id))
+        .count(db)
+        .await
+        .unwrap_or(0);
+
+    let profile = Profile {
+        id: user.id,
+        username: user.username,
+        display_name: user.display_name,
+        email_verified: user.email.is_some() && user.email_verified_at.is_some(),
+        email: user.email,
+        is_admin: user.is_admin,
+        tenant_id: user.tenant_id,
+        is_tenant_admin: user.is_tenant_admin,
+        created_at: user.created_at.to_string(),
+        total_bytes_sent: user.total_bytes_sent,
+        total_bytes_received: user.total_bytes_received,
+        traffic_quota_gb,
+        is_traffic_exceeded: user.is_traffic_exceeded,
+        max_port_count,
+        current_port_count,
+        max_node_count,
+        current_node_count,
+        max_client_count,
+        current_client_count,
+    };
+
+    (StatusCode::OK, ApiResponse::success(profile))
+}
+
+/// PUT /api/auth/me/profile - 修改当前用户的资料
+///
+/// 修改邮箱后需要重新验证，并向新邮箱发送验证链接。
+pub async fn update_profile(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<UpdateProfileRequest>,
+) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<&str>::error("未认证".to_string()));
+    };
+
+    let display_name = normalize(req.display_name);
+    if display_name.as_ref().is_some_and(|n| n.chars().count() > 50) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<&str>::error("显示名称不能超过 50 个字符".to_string()));
+    }
+    let email = normalize(req.email);
+    if email.as_ref().is_some_and(|e| e.len() > 254 || !e.contains('@') || e.contains(char::is_whitespace)) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<&str>::error("邮箱格式不正确".to_string()));
+    }
+
+    let db = get_connection().await;
+    let user = match User::find_by_id(auth_user.id).one(db).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<&str>::error("用户不存在".to_string())),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<&str>::error(format!("查询用户失败: {}", e))),
+    };
+
+    let email_changed = email != user.email;
+    let mut active: crate::entity::user::ActiveModel = user.into();
+    active.display_name = Set(display_name);
+    if email_changed {
+        active.email = Set(email);
+        active.email_verified_at = Set(None);
+    }
+    active.updated_at = Set(Utc::now().naive_utc());
+
+    let user = match active.update(db).await {
+        Ok(user) => user,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<&str>::error(format!("更新资料失败: {}", e))),
+    };
+    if email_changed && user.email.is_some() && crate::mailer::enabled() {
+        return match super::account_recovery::send_verification_email(&app_state, &user).await {
+            Ok(()) => (StatusCode::OK, ApiResponse::success("资料已更新，验证邮件已发送到新邮箱")),
+            Err(reason) => {
+                tracing::warn!("未向用户 '{}' 发送邮箱验证链接: {}", user.username, reason);
+                (StatusCode::OK, ApiResponse::success("资料已更新"))
+            }
+        };
+    }
+    (StatusCode::OK, ApiResponse::success("资料已更新"))
+}
+
+/// PUT /api/auth/me/password - 修改当前用户的密码（需要验证当前密码）
+pub async fn change_password(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<ChangePasswordRequest>,
+) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<&str>::error("未认证".to_string()));
+    };
+
+    let policy = PasswordPolicy::load(&app_state.config_manager).await;
+    if let Err(reason) = policy.check(&req.new_password, &auth_user.username).await {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<&str>::error(reason));
+    }
+
+    let db = get_connection().await;
+    let user = match User::find_by_id(auth_user.id).one(db).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<&str>::error("用户不存在".to_string())),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<&str>::error(format!("查询用户失败: {}", e))),
+    };
+
+    match verify_password(&req.current_password, &user.password_hash) {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::FORBIDDEN, ApiResponse::<&str>::error("当前密码不正确".to_string())),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<&str>::error(format!("验证密码失败: {}", e))),
+    }
+
+    let password_hash = match hash_password(&req.new_password) {
+        Ok(hash) => hash,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<&str>::error(format!("密码加密失败: {}", e))),
+    };
+
+    let username = user.username.clone();
+    let mut active: crate::entity::user::ActiveModel = user.into();
+    active.password_hash = Set(password_hash);
+    active.updated_at = Set(Utc::now().naive_utc());
+
+    match active.update(db).await {
+        Ok(_) => {
+            tracing::info!("用户 '{}' 修改了密码", username);
+            (StatusCode::OK, ApiResponse::success("密码已修改"))
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<&str>::error(format!("修改密码失败: {}", e))),
+    }
+}
+
+/// POST /api/auth/me/clients/{id}/rotate-token - 为自己的客户端生成新 token
+///
+/// 只能轮换属于当前用户的客户端，旧 token 立即失效，已连接的客户端会被断开。
+pub async fn rotate_own_client_token(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Path(client_id): Path<i64>,
+) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<RotateTokenResponse>::error("未认证".to_string()));
+    };
+
+    let db = get_connection().await;
+    let client = match Client::find_by_id(client_id).one(db).await {
+        Ok(Some(c)) if c.user_id == Some(auth_user.id) => c,
+        // 不区分不存在和无权访问，避免暴露其他用户的客户端
+        Ok(_) => return (StatusCode::NOT_FOUND, ApiResponse::<RotateTokenResponse>::error("客户端不存在".to_string())),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<RotateTokenResponse>::error(format!("查询客户端失败: {}", e))),
+    };
+
+    let token = Uuid::new_v4().to_string();
+    let name = client.name.clone();
+    let mut active: client::ActiveModel = client.into();
+    active.token = Set(token.clone());
+    active.updated_at = Set(Utc::now().naive_utc());
+
+    if let Err(e) = active.update(db).await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<RotateTokenResponse>::error(format!("更新客户端失败: {}", e)));
+    }
+
+    tracing::info!("用户 '{}' 轮换了客户端 #{} ({}) 的 token", auth_user.username, client_id, name);
+    app_state
+        .client_stream_manager
+        .disconnect(client_id, "token_rotated", format!("客户端 '{}' 的 token 已轮换", name))
+        .await;
+
+    (StatusCode::OK, ApiResponse::success(RotateTokenResponse { token }))
+}
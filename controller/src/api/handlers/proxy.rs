@@ -1,16 +1,24 @@
 use axum::{
-    extract::{Extension, Path},
-    http::StatusCode,
+    extract::{Extension, Path, Query},
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Json},
 };
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, QueryFilter, Set};
-use serde::Deserialize;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, NotSet, QueryFilter, Set,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use tracing::info;
 use uuid::Uuid;
 
-use crate::{entity::Proxy, migration::get_connection, middleware::AuthUser, AppState};
+use common::protocol::control::PortLease;
+
+use crate::{entity::Proxy, migration::get_connection, middleware::AuthUser, proxy_schedule::Schedule, AppState};
 
 use super::ApiResponse;
+use crate::api::access;
+use crate::api::pagination::{apply_cursor, apply_sort, fetch_page, ListQuery};
 
 #[derive(Deserialize)]
 pub struct CreateProxyRequest {
@@ -24,8 +32,37 @@ pub struct CreateProxyRequest {
     pub local_port: u16,
     #[serde(rename = "remotePort")]
     pub remote_port: u16,
+    /// 节点上监听的 IP（多网卡服务器），为空时使用节点默认的监听地址
+    #[serde(rename = "bindIp")]
+    pub bind_ip: Option<String>,
     #[serde(rename = "nodeId")]
     pub node_id: Option<i64>,
+    #[serde(rename = "idleTimeout")]
+    pub idle_timeout: Option<i32>,
+    /// 启用时间表，如 `mon-fri 09:00-18:00`
+    pub schedule: Option<String>,
+    /// 到期时间，到期后自动禁用
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// 来源 IP 处置规则（JSON），为空时使用节点默认规则
+    #[serde(rename = "mitigationConfig")]
+    pub mitigation_config: Option<String>,
+    /// 节点公平分配带宽时的权重（1-100）
+    #[serde(rename = "bandwidthWeight")]
+    pub bandwidth_weight: Option<i32>,
+    /// 优先级：high、normal、low
+    pub priority: Option<String>,
+    /// 客户端到本地服务的预连接数，0 表示不启用
+    #[serde(rename = "localPoolSize")]
+    pub local_pool_size: Option<i32>,
+    /// 客户端连接本地服务使用的源 IP 或网卡
+    #[serde(rename = "localSource")]
+    pub local_source: Option<String>,
+    /// 自动发布的 DNS 记录名，如 `ssh` 或 `_minecraft._tcp.mc`
+    #[serde(rename = "dnsName")]
+    pub dns_name: Option<String>,
+    /// 访客侧服务类型（ssh、mysql 等），用于生成连接字符串
+    pub service: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -39,73 +76,276 @@ pub struct UpdateProxyRequest {
     pub local_port: Option<u16>,
     #[serde(rename = "remotePort")]
     pub remote_port: Option<u16>,
+    #[serde(rename = "bindIp")]
+    pub bind_ip: Option<Option<String>>,
     pub enabled: Option<bool>,
+    #[serde(rename = "idleTimeout")]
+    pub idle_timeout: Option<Option<i32>>,
+    pub schedule: Option<Option<String>>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<Option<chrono::DateTime<chrono::Utc>>>,
+    #[serde(rename = "mitigationConfig")]
+    pub mitigation_config: Option<Option<String>>,
+    #[serde(rename = "bandwidthWeight")]
+    pub bandwidth_weight: Option<Option<i32>>,
+    pub priority: Option<Option<String>>,
+    #[serde(rename = "localPoolSize")]
+    pub local_pool_size: Option<Option<i32>>,
+    #[serde(rename = "localSource")]
+    pub local_source: Option<Option<String>>,
+    #[serde(rename = "dnsName")]
+    pub dns_name: Option<Option<String>>,
+    pub service: Option<Option<String>>,
+    /// 读取时的版本号，也可以通过 If-Match 请求头提供
+    #[serde(rename = "lockVersion")]
+    pub lock_version: Option<i32>,
+}
+
+/// TCP 连接空闲超时上限（秒）
+const MAX_IDLE_TIMEOUT_SECS: i32 = 7 * 24 * 3600;
+
+/// 校验代理的空闲超时设置：0 表示不限制，为空使用节点默认值
+fn validate_idle_timeout(idle_timeout: Option<i32>) -> Result<(), String> {
+    match idle_timeout {
+        Some(t) if !(0..=MAX_IDLE_TIMEOUT_SECS).contains(&t) => {
+            Err(format!("空闲超时必须在 0 到 {} 秒之间", MAX_IDLE_TIMEOUT_SECS))
+        }
+        _ => Ok(()),
+    }
 }
 
-pub async fn list_proxies(Extension(auth_user_opt): Extension<Option<AuthUser>>) -> impl IntoResponse {
+/// 带宽权重上限
+const MAX_BANDWIDTH_WEIGHT: i32 = 100;
+
+/// 校验代理的带宽权重，为空时为 1
+fn validate_bandwidth_weight(weight: Option<i32>) -> Result<(), String> {
+    match weight {
+        Some(w) if !(1..=MAX_BANDWIDTH_WEIGHT).contains(&w) => {
+            Err(format!("带宽权重必须在 1 到 {} 之间", MAX_BANDWIDTH_WEIGHT))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 规范化代理的优先级，空字符串视为不设置（普通优先级）
+fn normalize_priority(priority: Option<String>) -> Result<Option<String>, String> {
+    let Some(priority) = priority.filter(|s| !s.trim().is_empty()) else {
+        return Ok(None);
+    };
+    match common::protocol::control::ProxyPriority::parse(&priority.trim().to_ascii_lowercase()) {
+        Some(p) => Ok(Some(p.as_str().to_string())),
+        None => Err("优先级只能是 high、normal 或 low".to_string()),
+    }
+}
+
+/// 规范化代理的监听 IP，空字符串或通配地址视为不设置（使用节点默认的监听地址）
+fn normalize_bind_ip(bind_ip: Option<String>) -> Result<Option<String>, String> {
+    let Some(bind_ip) = bind_ip.filter(|s| !s.trim().is_empty()) else {
+        return Ok(None);
+    };
+    match common::utils::normalize_host(&bind_ip).parse::<std::net::IpAddr>() {
+        Ok(ip) if ip.is_unspecified() => Ok(None),
+        Ok(ip) => Ok(Some(ip.to_string())),
+        Err(_) => Err(format!("监听地址必须是 IP 地址: {}", bind_ip.trim())),
+    }
+}
+
+/// 客户端本地预连接数上限
+const MAX_LOCAL_POOL_SIZE: i32 = 16;
+
+/// 校验本地预连接数：0 或为空表示不启用
+fn validate_local_pool_size(size: Option<i32>) -> Result<(), String> {
+    match size {
+        Some(n) if !(0..=MAX_LOCAL_POOL_SIZE).contains(&n) => {
+            Err(format!("本地预连接数必须在 0 到 {} 之间", MAX_LOCAL_POOL_SIZE))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 规范化客户端本地连接源（源 IP 或网卡名），空字符串视为不设置
+fn normalize_local_source(source: Option<String>) -> Result<Option<String>, String> {
+    let Some(source) = source.filter(|s| !s.trim().is_empty()) else {
+        return Ok(None);
+    };
+    source
+        .parse::<common::source_binding::SourceBinding>()
+        .map(|s| Some(s.to_string()))
+        .map_err(|e| e.to_string())
+}
+
+/// 校验到期时间：必须晚于当前时间
+fn validate_expires_at(expires_at: Option<chrono::DateTime<chrono::Utc>>) -> Result<(), String> {
+    match expires_at {
+        Some(t) if t <= chrono::Utc::now() => Err("到期时间必须晚于当前时间".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// 校验代理的启用时间表，空字符串视为不设置
+fn validate_schedule(schedule: Option<String>) -> Result<Option<(String, Schedule)>, String> {
+    match schedule.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(s) => s
+            .parse::<Schedule>()
+            .map(|parsed| Some((s.to_string(), parsed)))
+            .map_err(|e| format!("无效的时间表: {}", e)),
+    }
+}
+
+/// 规范化代理的 DNS 名称并检查是否与其他代理冲突，空字符串视为不设置
+async fn validate_dns_name(
+    dns_name: Option<String>,
+    node_id: Option<i64>,
+    client_id: &str,
+    proxy_id: Option<i64>,
+    db: &DatabaseConnection,
+) -> Result<Option<String>, (StatusCode, String)> {
+    let Some(name) = dns_name.filter(|n| !n.trim().is_empty()) else {
+        return Ok(None);
+    };
+    if node_id.is_none() {
+        return Err((StatusCode::BAD_REQUEST, "未指定节点的代理不能发布 DNS 记录".to_string()));
+    }
+    let name = crate::dns::normalize_name(&name).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    crate::dns::check_conflict(&name, node_id, client_id, proxy_id, db)
+        .await
+        .map_err(|e| (StatusCode::CONFLICT, e))?;
+    Ok(Some(name))
+}
+
+/// 隧道列表过滤参数
+#[derive(Debug, Default, Deserialize)]
+pub struct ProxyListFilter {
+    pub node_id: Option<i64>,
+    pub client_id: Option<i64>,
+    pub enabled: Option<bool>,
+    #[serde(rename = "type")]
+    pub proxy_type: Option<String>,
+}
+
+/// 代理列表项：代理信息 + 当前速率 + 是否处于维护窗口（含所属客户端和节点的窗口）+ 访客连接字符串
+#[derive(Serialize)]
+pub struct ProxyWithSpeed {
+    #[serde(flatten)]
+    pub proxy: crate::entity::proxy::Model,
+    #[serde(flatten)]
+    pub speed: crate::live_speed::Speed,
+    #[serde(rename = "inMaintenance")]
+    pub in_maintenance: bool,
+    pub endpoints: Vec<crate::endpoint::Endpoint>,
+    /// 最近一次公网端口探测结果
+    #[serde(rename = "portProbe")]
+    pub port_probe: Option<crate::entity::proxy_port_probe::Model>,
+}
+
+pub async fn list_proxies(
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
+    Query(list_query): Query<ListQuery>,
+    Query(filter): Query<ProxyListFilter>,
+) -> impl IntoResponse {
     let auth_user = match auth_user_opt {
         Some(user) => user,
-        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<Vec<crate::entity::proxy::Model>>::error("Not authenticated".to_string())),
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<Vec<ProxyWithSpeed>>::error("Not authenticated".to_string())),
     };
     let db = get_connection().await;
 
-    let proxies = if auth_user.is_admin {
-        // Admin can see all proxies
-        match Proxy::find().all(db).await {
-            Ok(proxies) => proxies,
-            Err(e) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    ApiResponse::<Vec<crate::entity::proxy::Model>>::error(format!(
-                        "Failed to list proxies: {}",
-                        e
-                    )),
-                )
+    let mut select = Proxy::find();
+
+    // 平台管理员可以看到所有代理，租户管理员可以看到本租户用户的代理，普通用户只能看到自己客户端的代理
+    match access::accessible_client_ids(&auth_user, db).await {
+        Ok(None) => {}
+        Ok(Some(client_ids)) => {
+            if client_ids.is_empty() {
+                return (StatusCode::OK, ApiResponse::paginated(vec![], 0));
             }
+            select = select.filter(crate::entity::proxy::Column::ClientId.is_in(client_ids.into_iter().map(|id| id.to_string())));
         }
-    } else {
-        // Regular users can only see proxies for their own clients
-        let client_ids = match crate::entity::Client::find()
-            .filter(crate::entity::client::Column::UserId.eq(auth_user.id))
-            .all(db)
-            .await
-        {
-            Ok(clients) => clients.into_iter().map(|c| c.id.to_string()).collect::<Vec<_>>(),
-            Err(e) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    ApiResponse::<Vec<crate::entity::proxy::Model>>::error(format!(
-                        "Failed to get clients: {}",
-                        e
-                    )),
-                )
-            }
-        };
+        Err((status, e)) => return (status, ApiResponse::<Vec<ProxyWithSpeed>>::error(e)),
+    }
 
-        if client_ids.is_empty() {
-            vec![]
-        } else {
-            // Get proxies for those clients
-            match Proxy::find()
-                .filter(crate::entity::proxy::Column::ClientId.is_in(client_ids))
+    if let Some(node_id) = filter.node_id {
+        select = select.filter(crate::entity::proxy::Column::NodeId.eq(node_id));
+    }
+    if let Some(client_id) = filter.client_id {
+        select = select.filter(crate::entity::proxy::Column::ClientId.eq(client_id.to_string()));
+    }
+    if let Some(enabled) = filter.enabled {
+        select = select.filter(crate::entity::proxy::Column::Enabled.eq(enabled));
+    }
+    if let Some(proxy_type) = filter.proxy_type.filter(|t| !t.is_empty()) {
+        select = select.filter(crate::entity::proxy::Column::ProxyType.eq(proxy_type));
+    }
+    if let Some(keyword) = list_query.keyword() {
+        let mut condition = Condition::any()
+            .add(crate::entity::proxy::Column::Name.contains(keyword))
+            .add(crate::entity::proxy::Column::LocalIp.contains(keyword));
+        if let Ok(port) = keyword.parse::<u16>() {
+            condition = condition
+                .add(crate::entity::proxy::Column::RemotePort.eq(port))
+                .add(crate::entity::proxy::Column::LocalPort.eq(port));
+        }
+        select = select.filter(condition);
+    }
+
+    let select = apply_sort(
+        select,
+        &list_query,
+        &[
+            ("id", crate::entity::proxy::Column::Id),
+            ("name", crate::entity::proxy::Column::Name),
+            ("type", crate::entity::proxy::Column::ProxyType),
+            ("remote_port", crate::entity::proxy::Column::RemotePort),
+            ("local_port", crate::entity::proxy::Column::LocalPort),
+            ("node_id", crate::entity::proxy::Column::NodeId),
+            ("enabled", crate::entity::proxy::Column::Enabled),
+            ("created_at", crate::entity::proxy::Column::CreatedAt),
+            ("updated_at", crate::entity::proxy::Column::UpdatedAt),
+        ],
+        crate::entity::proxy::Column::Id,
+    );
+    let select = apply_cursor(select, &list_query, crate::entity::proxy::Column::Id);
+
+    match fetch_page(select, &list_query, db).await {
+        Ok((proxies, total)) => {
+            let speeds = crate::live_speed::proxy_speeds();
+            let node_ids: Vec<i64> = proxies.iter().flat_map(|p| p.node_ids()).collect();
+            let nodes: HashMap<i64, crate::entity::node::Model> = crate::entity::Node::find()
+                .filter(crate::entity::node::Column::Id.is_in(node_ids))
                 .all(db)
                 .await
-            {
-                Ok(proxies) => proxies,
-                Err(e) => {
-                    return (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        ApiResponse::<Vec<crate::entity::proxy::Model>>::error(format!(
-                            "Failed to list proxies: {}",
-                            e
-                        )),
-                    )
-                }
-            }
+                .unwrap_or_default()
+                .into_iter()
+                .map(|n| (n.id, n))
+                .collect();
+            let mut probes: HashMap<i64, crate::entity::proxy_port_probe::Model> = crate::entity::ProxyPortProbe::find()
+                .filter(crate::entity::proxy_port_probe::Column::ProxyId.is_in(proxies.iter().map(|p| p.id)))
+                .all(db)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|r| (r.proxy_id, r))
+                .collect();
+            let proxies = proxies
+                .into_iter()
+                .map(|proxy| ProxyWithSpeed {
+                    port_probe: probes.remove(&proxy.id),
+                    speed: speeds.get(&proxy.id).copied().unwrap_or_default(),
+                    in_maintenance: crate::maintenance::global().proxy(&proxy),
+                    endpoints: crate::endpoint::for_published(&proxy, &nodes),
+                    proxy,
+                })
+                .collect();
+            (StatusCode::OK, ApiResponse::paginated(proxies, total))
         }
-    };
-
-    (StatusCode::OK, ApiResponse::success(proxies))
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::<Vec<ProxyWithSpeed>>::error(format!(
+                "Failed to list proxies: {}",
+                e
+            )),
+        ),
+    }
 }
 
 pub async fn list_proxies_by_client(
@@ -118,39 +358,8 @@ pub async fn list_proxies_by_client(
     };
     let db = get_connection().await;
 
-    // Check if user has access to this client (via node binding)
-    if !auth_user.is_admin {
-        // First get the client's node_id
-        let client = match crate::entity::Client::find_by_id(client_id).one(db).await {
-            Ok(Some(c)) => c,
-            Ok(None) => {
-                return (
-                    StatusCode::NOT_FOUND,
-                    ApiResponse::<Vec<crate::entity::proxy::Model>>::error(
-                        "Client not found".to_string(),
-                    ),
-                )
-            }
-            Err(e) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    ApiResponse::<Vec<crate::entity::proxy::Model>>::error(format!(
-                        "Failed to check access: {}",
-                        e
-                    )),
-                )
-            }
-        };
-
-        // Check if user owns the client
-        if client.user_id != Some(auth_user.id) {
-            return (
-                StatusCode::FORBIDDEN,
-                ApiResponse::<Vec<crate::entity::proxy::Model>>::error(
-                    "Access denied to this client".to_string(),
-                ),
-            )
-        }
+    if let Err((status, e)) = access::accessible_client(&auth_user, client_id, db).await {
+        return (status, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(e));
     }
 
     match Proxy::find()
@@ -169,38 +378,89 @@ pub async fn list_proxies_by_client(
     }
 }
 
-pub async fn create_proxy(
+/// GET /api/proxies/{id}/endpoints - 代理的访客连接字符串
+pub async fn get_proxy_endpoints(
+    Path(id): Path<i64>,
     Extension(auth_user_opt): Extension<Option<AuthUser>>,
-    Extension(app_state): Extension<AppState>,
-    Json(req): Json<CreateProxyRequest>,
 ) -> impl IntoResponse {
     let auth_user = match auth_user_opt {
         Some(user) => user,
-        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<crate::entity::proxy::Model>::error("未认证".to_string())),
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<Vec<crate::endpoint::Endpoint>>::error("未认证".to_string())),
     };
-
     let db = get_connection().await;
 
-    // 获取客户端信息以验证端口限制
-    let client = match crate::entity::Client::find()
-        .filter(crate::entity::client::Column::Id.eq(req.client_id.parse::<i64>().unwrap_or(0)))
-        .one(db)
+    let proxy = match access::accessible_proxy(&auth_user, id, db).await {
+        Ok(p) => p,
+        Err((status, e)) => return (status, ApiResponse::<Vec<crate::endpoint::Endpoint>>::error(e)),
+    };
+
+    let nodes: HashMap<i64, crate::entity::node::Model> = match crate::entity::Node::find()
+        .filter(crate::entity::node::Column::Id.is_in(proxy.node_ids()))
+        .all(db)
         .await
     {
-        Ok(Some(c)) => c,
-        Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                ApiResponse::<crate::entity::proxy::Model>::error("客户端不存在".to_string()),
-            )
-        }
+        Ok(nodes) => nodes.into_iter().map(|n| (n.id, n)).collect(),
         Err(e) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                ApiResponse::<crate::entity::proxy::Model>::error(format!("查询客户端失败: {}", e)),
+                ApiResponse::<Vec<crate::endpoint::Endpoint>>::error(format!("查询节点失败: {}", e)),
             )
         }
     };
+    (StatusCode::OK, ApiResponse::success(crate::endpoint::for_published(&proxy, &nodes)))
+}
+
+pub async fn create_proxy(
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<CreateProxyRequest>,
+) -> impl IntoResponse {
+    let auth_user = match auth_user_opt {
+        Some(user) => user,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<crate::entity::proxy::Model>::error("未认证".to_string())),
+    };
+
+    if let Err(e) = validate_idle_timeout(req.idle_timeout)
+        .and_then(|_| validate_bandwidth_weight(req.bandwidth_weight))
+        .and_then(|_| validate_local_pool_size(req.local_pool_size))
+        .and_then(|_| validate_expires_at(req.expires_at))
+    {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::proxy::Model>::error(e));
+    }
+    let schedule = match validate_schedule(req.schedule) {
+        Ok(s) => s,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
+    let priority = match normalize_priority(req.priority) {
+        Ok(p) => p,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
+    let mitigation_config = match crate::mitigation::normalize_config(req.mitigation_config) {
+        Ok(c) => c,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
+    let service = match crate::endpoint::normalize_service(req.service) {
+        Ok(s) => s,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
+    let bind_ip = match normalize_bind_ip(req.bind_ip) {
+        Ok(ip) => ip,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
+    let local_source = match normalize_local_source(req.local_source) {
+        Ok(s) => s,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
+    // 设置了时间表的代理在窗口外创建时先保持禁用，由调度器在窗口开始时启用
+    let enabled = schedule.as_ref().is_none_or(|(_, s)| s.is_active_now());
+
+    let db = get_connection().await;
+
+    // 获取客户端信息以验证归属和端口限制
+    let client = match access::accessible_client(&auth_user, req.client_id.parse::<i64>().unwrap_or(0), db).await {
+        Ok(c) => c,
+        Err((status, e)) => return (status, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
 
     // 验证端口限制（仅对非管理员用户）
     if !auth_user.is_admin {
@@ -243,6 +503,14 @@ pub async fn create_proxy(
             }
         };
 
+        // 其他租户的节点不可用
+        if !auth_user.is_admin && !crate::tenant::node_visible_to(&node, auth_user.tenant_id) {
+            return (
+                StatusCode::NOT_FOUND,
+                ApiResponse::<crate::entity::proxy::Model>::error("节点不存在".to_string()),
+            );
+        }
+
         // 如果是独享节点，需要检查用户是否有权限
         if node.node_type == "dedicated" && !auth_user.is_admin {
             // 获取客户端所属用户
@@ -304,7 +572,7 @@ pub async fn create_proxy(
 
     // 验证节点限制（代理数量、端口范围、流量）
     if let Some(node_id) = req.node_id {
-        match crate::node_limiter::validate_node_proxy_limit(node_id, req.remote_port, db).await {
+        match crate::node_limiter::validate_node_proxy_limit(node_id, req.remote_port, &app_state.config_manager, db).await {
             Ok((allowed, reason)) => {
                 if !allowed {
                     return (
@@ -325,41 +593,80 @@ pub async fn create_proxy(
         }
     }
 
-    // 检查端口是否已被占用（同一节点上的 remote_port 必须唯一）
-    {
-        let mut port_query = Proxy::find()
-            .filter(crate::entity::proxy::Column::RemotePort.eq(req.remote_port))
-            .filter(crate::entity::proxy::Column::Enabled.eq(true));
-
-        if let Some(node_id) = req.node_id {
-            port_query =
-                port_query.filter(crate::entity::proxy::Column::NodeId.eq(node_id));
-        } else {
-            port_query =
-                port_query.filter(crate::entity::proxy::Column::NodeId.is_null());
+    // 验证端口黑名单（远程端口 + 本地目标）
+    let tenant_id = match crate::port_blocklist::client_tenant_id(&req.client_id, db).await {
+        Ok(t) => t,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::<crate::entity::proxy::Model>::error(format!("查询客户端租户失败: {}", e)),
+            );
         }
-
-        match port_query.one(db).await {
-            Ok(Some(existing)) => {
+    };
+    match crate::port_blocklist::validate_proxy_target(
+        req.node_id,
+        tenant_id,
+        req.remote_port,
+        &req.local_ip,
+        req.local_port,
+        db,
+    )
+    .await
+    {
+        Ok((allowed, reason)) => {
+            if !allowed {
                 return (
-                    StatusCode::CONFLICT,
-                    ApiResponse::<crate::entity::proxy::Model>::error(format!(
-                        "远程端口 {} 已被代理「{}」占用",
-                        req.remote_port, existing.name
-                    )),
+                    StatusCode::FORBIDDEN,
+                    ApiResponse::<crate::entity::proxy::Model>::error(reason),
                 );
             }
-            Ok(None) => {} // 端口未被占用
-            Err(e) => {
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::<crate::entity::proxy::Model>::error(format!("验证端口黑名单失败: {}", e)),
+            );
+        }
+    }
+
+    // 验证端口预留（预留给其他用户 / 租户的端口不可用）
+    match crate::port_reservation::validate_proxy_port(req.node_id, &req.client_id, req.remote_port, db).await {
+        Ok((allowed, reason)) => {
+            if !allowed {
                 return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    ApiResponse::<crate::entity::proxy::Model>::error(format!(
-                        "检查端口占用失败: {}",
-                        e
-                    )),
+                    StatusCode::CONFLICT,
+                    ApiResponse::<crate::entity::proxy::Model>::error(reason),
                 );
             }
         }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::<crate::entity::proxy::Model>::error(format!("验证端口预留失败: {}", e)),
+            );
+        }
+    }
+
+    let dns_name = match validate_dns_name(req.dns_name, req.node_id, &req.client_id, None, db).await {
+        Ok(n) => n,
+        Err((status, e)) => return (status, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
+
+    // 两阶段创建：先在节点上预留端口（节点绑定并持有 socket），事务写入数据库后再激活监听器。
+    // 并发创建同一端口时只有一个能预留成功，写入后也不会因端口被占用而启动失败。
+    let lease = PortLease {
+        node_id: req.node_id,
+        client_id: req.client_id.clone(),
+        proxy_type: req.proxy_type.clone(),
+        remote_port: req.remote_port,
+    };
+    if enabled {
+        if let Err(e) = app_state.proxy_control.reserve_port(&lease).await {
+            return (
+                StatusCode::CONFLICT,
+                ApiResponse::<crate::entity::proxy::Model>::error(format!("预留端口失败: {}", e)),
+            );
+        }
     }
 
     let now = chrono::Utc::now().naive_utc();
@@ -369,80 +676,192 @@ pub async fn create_proxy(
         client_id: Set(req.client_id.clone()),
         name: Set(req.name),
         proxy_type: Set(req.proxy_type),
-        local_ip: Set(req.local_ip),
+        local_ip: Set(common::utils::normalize_host(&req.local_ip).to_string()),
         local_port: Set(req.local_port),
         remote_port: Set(req.remote_port),
-        enabled: Set(true),
+        bind_ip: Set(bind_ip),
+        enabled: Set(enabled),
         node_id: Set(req.node_id),
         group_id: Set(None),
+        idle_timeout: Set(req.idle_timeout),
+        mitigation_config: Set(mitigation_config),
+        bandwidth_weight: Set(req.bandwidth_weight),
+        priority: Set(priority),
+        local_pool_size: Set(req.local_pool_size),
+        local_source: Set(local_source),
+        anycast_node_ids: Set(None),
+        dns_name: Set(dns_name),
+        service: Set(service),
+        schedule: Set(schedule.map(|(s, _)| s)),
+        expires_at: Set(req.expires_at.map(|t| t.naive_utc())),
+        stale_at: Set(None),
         total_bytes_sent: Set(0),
         total_bytes_received: Set(0),
+        lock_version: Set(0),
         created_at: Set(now),
         updated_at: Set(now),
     };
-    let db = get_connection().await;
-    match new_proxy.insert(db).await {
-        Ok(proxy) => {
-            info!("代理已创建: {} (ID: {}, 客户端: {})", proxy.name, proxy.id, proxy.client_id);
-
-            // 通过 ProxyControl trait 动态启动代理监听器（同步等待，检测端口占用）
-            if let Err(e) = app_state.proxy_control.start_proxy(&req.client_id, proxy.id).await {
-                // 启动失败（可能端口被占用），回滚删除数据库记录
-                tracing::warn!("启动代理监听器失败，回滚创建: {}", e);
-                let _ = Proxy::delete_by_id(proxy.id).exec(db).await;
-                return (
-                    StatusCode::CONFLICT,
-                    ApiResponse::<crate::entity::proxy::Model>::error(format!(
-                        "启动代理监听器失败: {}",
-                        e
-                    )),
-                );
+    let proxy = match insert_proxies_checked(db, req.node_id, vec![(req.remote_port, new_proxy)]).await {
+        Ok(mut proxies) => proxies.remove(0),
+        Err((status, e)) => {
+            if enabled {
+                if let Err(e) = app_state.proxy_control.release_port(&lease).await {
+                    tracing::warn!("释放预留端口 {} 失败: {}", lease.remote_port, e);
+                }
             }
+            return (status, ApiResponse::<crate::entity::proxy::Model>::error(e));
+        }
+    };
+    info!("代理已创建: {} (ID: {}, 客户端: {})", proxy.name, proxy.id, proxy.client_id);
+    if proxy.dns_name.is_some() {
+        crate::dns::request_sync();
+    }
+    crate::cloud_firewall::request_sync();
 
-            info!("代理监听器已动态启动: {}", proxy.name);
+    if !proxy.enabled {
+        info!("代理 {} 不在启用时间窗口内，等待定时启用", proxy.name);
+        return (StatusCode::OK, ApiResponse::success(proxy));
+    }
 
-            // 通知 Agent Client 代理配置已变更
-            let csm = app_state.client_stream_manager.clone();
-            let client_id_notify = req.client_id.clone();
-            tokio::spawn(async move {
-                csm.notify_proxy_change(&client_id_notify).await;
-            });
+    // 激活：节点关闭预留的 socket 并启动监听器（同步等待）
+    if let Err(e) = app_state.proxy_control.activate_proxy(&lease, proxy.id).await {
+        // 端口已预留，激活失败只可能是节点断开等异常情况，删除记录兜底
+        tracing::warn!("激活代理 {} 失败，删除记录: {}", proxy.name, e);
+        let _ = Proxy::delete_by_id(proxy.id).exec(db).await;
+        let _ = app_state.proxy_control.release_port(&lease).await;
+        return (
+            StatusCode::CONFLICT,
+            ApiResponse::<crate::entity::proxy::Model>::error(format!("启动代理监听器失败: {}", e)),
+        );
+    }
+
+    info!("代理监听器已动态启动: {}", proxy.name);
+    crate::port_probe::spawn_probe(proxy.clone(), app_state.node_manager.clone(), app_state.config_manager.clone(), db);
 
-            (StatusCode::OK, ApiResponse::success(proxy))
+    // 通知 Agent Client 代理配置已变更
+    let csm = app_state.client_stream_manager.clone();
+    let client_id_notify = req.client_id.clone();
+    tokio::spawn(async move {
+        csm.notify_proxy_change(&client_id_notify).await;
+    });
+
+    (StatusCode::OK, ApiResponse::success(proxy))
+}
+
+/// 在一个事务中检查远程端口占用并写入代理（同一节点上启用的代理 remote_port 必须唯一）
+async fn insert_proxies_checked(
+    db: &DatabaseConnection,
+    node_id: Option<i64>,
+    new_proxies: Vec<(u16, crate::entity::proxy::ActiveModel)>,
+) -> Result<Vec<crate::entity::proxy::Model>, (StatusCode, String)> {
+    let db_err = |e: sea_orm::DbErr| (StatusCode::INTERNAL_SERVER_ERROR, format!("创建代理失败: {}", e));
+    let txn = db.begin().await.map_err(db_err)?;
+
+    let mut proxies = Vec::with_capacity(new_proxies.len());
+    for (remote_port, new_proxy) in new_proxies {
+        let existing = crate::anycast::port_owner(node_id, remote_port, None, &txn)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("检查端口占用失败: {}", e)))?;
+        if let Some(existing) = existing {
+            return Err((
+                StatusCode::CONFLICT,
+                format!("远程端口 {} 已被代理「{}」占用", remote_port, existing.name),
+            ));
         }
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            ApiResponse::<crate::entity::proxy::Model>::error(format!(
-                "Failed to create proxy: {}",
-                e
-            )),
-        ),
+        proxies.push(new_proxy.insert(&txn).await.map_err(db_err)?);
     }
+
+    txn.commit().await.map_err(db_err)?;
+    Ok(proxies)
 }
 
 pub async fn update_proxy(
     Path(id): Path<i64>,
-    Extension(_auth_user): Extension<Option<AuthUser>>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
     Extension(app_state): Extension<AppState>,
+    headers: HeaderMap,
     Json(req): Json<UpdateProxyRequest>,
 ) -> impl IntoResponse {
+    let auth_user = match access::require_user(auth_user_opt) {
+        Ok(user) => user,
+        Err((status, e)) => return (status, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
+    let if_match = match crate::optimistic_lock::expected_version(&headers, req.lock_version) {
+        Ok(v) => v,
+        Err((status, e)) => return (status, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
+    if let Err(e) = validate_idle_timeout(req.idle_timeout.flatten())
+        .and_then(|_| validate_bandwidth_weight(req.bandwidth_weight.flatten()))
+        .and_then(|_| validate_local_pool_size(req.local_pool_size.flatten()))
+        .and_then(|_| validate_expires_at(req.expires_at.flatten()))
+    {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::proxy::Model>::error(e));
+    }
+    let schedule = match req.schedule.map(validate_schedule).transpose() {
+        Ok(s) => s,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
+    let priority = match req.priority.map(normalize_priority).transpose() {
+        Ok(p) => p,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
+    let mitigation_config = match req.mitigation_config.map(crate::mitigation::normalize_config).transpose() {
+        Ok(c) => c,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
+    let service = match req.service.map(crate::endpoint::normalize_service).transpose() {
+        Ok(s) => s,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
+    let bind_ip = match req.bind_ip.map(normalize_bind_ip).transpose() {
+        Ok(ip) => ip,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
+    let local_source = match req.local_source.map(normalize_local_source).transpose() {
+        Ok(s) => s,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+    };
+
     let db = get_connection().await;
-    match Proxy::find_by_id(id).one(db).await {
-        Ok(Some(proxy)) => {
+    match access::accessible_proxy(&auth_user, id, db).await {
+        Ok(proxy) => {
+            let expected_version = if_match.unwrap_or(proxy.lock_version);
+            if proxy.lock_version != expected_version {
+                return (
+                    StatusCode::CONFLICT,
+                    ApiResponse::<crate::entity::proxy::Model>::error(
+                        crate::optimistic_lock::conflict_message(expected_version, proxy.lock_version),
+                    ),
+                );
+            }
             let old_enabled = proxy.enabled;
+            let old_expires_at = proxy.expires_at;
+            let old_idle_timeout = proxy.idle_timeout;
+            let old_bind_ip = proxy.bind_ip.clone();
+            let old_mitigation_config = proxy.mitigation_config.clone();
+            let old_bandwidth_weight = proxy.bandwidth_weight;
+            let old_priority = proxy.priority.clone();
+            let old_local_pool_size = proxy.local_pool_size;
+            let old_local_source = proxy.local_source.clone();
             let old_proxy_type = proxy.proxy_type.clone();
             let old_local_ip = proxy.local_ip.clone();
             let old_local_port = proxy.local_port;
             let old_remote_port = proxy.remote_port;
             let proxy_node_id = proxy.node_id;
+            let original = proxy.clone();
             let client_id = proxy.client_id.clone();
             let mut proxy: crate::entity::proxy::ActiveModel = proxy.into();
 
             let mut config_changed = false;
+            let target_changed =
+                req.remote_port.is_some() || req.local_ip.is_some() || req.local_port.is_some();
 
             if let Some(name) = req.name {
                 proxy.name = Set(name);
             }
+            if let Some(service) = service {
+                proxy.service = Set(service);
+            }
             if let Some(proxy_type) = req.proxy_type {
                 if proxy_type != old_proxy_type {
                     config_changed = true;
@@ -450,6 +869,7 @@ pub async fn update_proxy(
                 proxy.proxy_type = Set(proxy_type);
             }
             if let Some(local_ip) = req.local_ip {
+                let local_ip = common::utils::normalize_host(&local_ip).to_string();
                 if local_ip != old_local_ip {
                     config_changed = true;
                 }
@@ -468,6 +888,7 @@ pub async fn update_proxy(
                         match crate::node_limiter::validate_node_proxy_limit(
                             node_id,
                             remote_port,
+                            &app_state.config_manager,
                             db,
                         )
                         .await
@@ -493,21 +914,36 @@ pub async fn update_proxy(
                         }
                     }
 
-                    // 检查新端口是否已被占用（排除当前代理自身）
-                    let mut port_query = Proxy::find()
-                        .filter(crate::entity::proxy::Column::RemotePort.eq(remote_port))
-                        .filter(crate::entity::proxy::Column::Enabled.eq(true))
-                        .filter(crate::entity::proxy::Column::Id.ne(id));
-
-                    if let Some(node_id) = proxy_node_id {
-                        port_query = port_query
-                            .filter(crate::entity::proxy::Column::NodeId.eq(node_id));
-                    } else {
-                        port_query = port_query
-                            .filter(crate::entity::proxy::Column::NodeId.is_null());
+                    // 验证端口预留
+                    match crate::port_reservation::validate_proxy_port(
+                        proxy_node_id,
+                        &client_id,
+                        remote_port,
+                        db,
+                    )
+                    .await
+                    {
+                        Ok((allowed, reason)) => {
+                            if !allowed {
+                                return (
+                                    StatusCode::CONFLICT,
+                                    ApiResponse::<crate::entity::proxy::Model>::error(reason),
+                                );
+                            }
+                        }
+                        Err(e) => {
+                            return (
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                ApiResponse::<crate::entity::proxy::Model>::error(format!(
+                                    "验证端口预留失败: {}",
+                                    e
+                                )),
+                            );
+                        }
                     }
 
-                    match port_query.one(db).await {
+                    // 检查新端口是否已被占用（排除当前代理自身）
+                    match crate::anycast::port_owner(proxy_node_id, remote_port, Some(id), db).await {
                         Ok(Some(existing)) => {
                             return (
                                 StatusCode::CONFLICT,
@@ -535,18 +971,201 @@ pub async fn update_proxy(
                 proxy.remote_port = Set(remote_port);
             }
 
-            let enabled_changed = if let Some(enabled) = req.enabled {
+            // 远程端口或本地目标变更后重新检查端口黑名单
+            if target_changed {
+                let tenant_id = match crate::port_blocklist::client_tenant_id(&client_id, db).await {
+                    Ok(t) => t,
+                    Err(e) => {
+                        return (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            ApiResponse::<crate::entity::proxy::Model>::error(format!(
+                                "查询客户端租户失败: {}",
+                                e
+                            )),
+                        );
+                    }
+                };
+                let remote_port = req.remote_port.unwrap_or(old_remote_port);
+                let local_port = req.local_port.unwrap_or(old_local_port);
+                let local_ip = match &proxy.local_ip {
+                    sea_orm::ActiveValue::Set(ip) => ip.clone(),
+                    _ => old_local_ip.clone(),
+                };
+                match crate::port_blocklist::validate_proxy_target(
+                    proxy_node_id,
+                    tenant_id,
+                    remote_port,
+                    &local_ip,
+                    local_port,
+                    db,
+                )
+                .await
+                {
+                    Ok((allowed, reason)) => {
+                        if !allowed {
+                            return (
+                                StatusCode::FORBIDDEN,
+                                ApiResponse::<crate::entity::proxy::Model>::error(reason),
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        return (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            ApiResponse::<crate::entity::proxy::Model>::error(format!(
+                                "验证端口黑名单失败: {}",
+                                e
+                            )),
+                        );
+                    }
+                }
+
+                // 多节点发布的其他节点同样需要检查
+                let candidate = crate::entity::proxy::Model {
+                    remote_port,
+                    local_ip,
+                    local_port,
+                    ..original.clone()
+                };
+                for node_id in original.anycast_ids() {
+                    match crate::anycast::check_node(&candidate, node_id, &app_state.config_manager, db).await {
+                        Ok(None) => {}
+                        Ok(Some(reason)) => {
+                            return (
+                                StatusCode::CONFLICT,
+                                ApiResponse::<crate::entity::proxy::Model>::error(format!(
+                                    "节点 #{}: {}",
+                                    node_id, reason
+                                )),
+                            );
+                        }
+                        Err(e) => {
+                            return (
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                ApiResponse::<crate::entity::proxy::Model>::error(format!(
+                                    "验证多节点发布失败: {}",
+                                    e
+                                )),
+                            );
+                        }
+                    }
+                }
+            }
+
+            if let Some(idle_timeout) = req.idle_timeout {
+                // 空闲超时由节点监听器使用，变更后需要重启监听器
+                if idle_timeout != old_idle_timeout {
+                    config_changed = true;
+                }
+                proxy.idle_timeout = Set(idle_timeout);
+            }
+
+            if let Some(bind_ip) = bind_ip {
+                // 监听 IP 只存在于单个节点上
+                if bind_ip.is_some() && !original.anycast_ids().is_empty() {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        ApiResponse::<crate::entity::proxy::Model>::error("多节点发布的代理不能指定监听地址".to_string()),
+                    );
+                }
+                // 监听地址变更后需要重启监听器
+                if bind_ip != old_bind_ip {
+                    config_changed = true;
+                }
+                proxy.bind_ip = Set(bind_ip);
+            }
+
+            if let Some(mitigation_config) = mitigation_config {
+                // 处置规则随代理配置下发，变更后需要重启监听器
+                if mitigation_config != old_mitigation_config {
+                    config_changed = true;
+                }
+                proxy.mitigation_config = Set(mitigation_config);
+            }
+
+            if let Some(bandwidth_weight) = req.bandwidth_weight {
+                // 权重由节点监听器使用，变更后需要重启监听器
+                if bandwidth_weight != old_bandwidth_weight {
+                    config_changed = true;
+                }
+                proxy.bandwidth_weight = Set(bandwidth_weight);
+            }
+
+            if let Some(priority) = priority {
+                // 优先级决定调度权重和隧道流优先级，变更后需要重启监听器
+                if priority != old_priority {
+                    config_changed = true;
+                }
+                proxy.priority = Set(priority);
+            }
+
+            // DNS 记录由后台任务同步，不影响监听器
+            let dns_changed = req.dns_name.is_some();
+            if let Some(dns_name) = req.dns_name {
+                match validate_dns_name(dns_name, proxy_node_id, &client_id, Some(id), db).await {
+                    Ok(n) => proxy.dns_name = Set(n),
+                    Err((status, e)) => return (status, ApiResponse::<crate::entity::proxy::Model>::error(e)),
+                }
+            }
+
+            // 本地预连接和本地连接源只在客户端生效，变更后通知客户端即可，无需重启监听器
+            let mut client_config_changed = false;
+            if let Some(local_pool_size) = req.local_pool_size {
+                client_config_changed = local_pool_size != old_local_pool_size;
+                proxy.local_pool_size = Set(local_pool_size);
+            }
+            if let Some(local_source) = local_source {
+                client_config_changed |= local_source != old_local_source;
+                proxy.local_source = Set(local_source);
+            }
+
+            // 设置时间表时按当前是否处于窗口内同步启用状态（请求中显式指定 enabled 时以请求为准）
+            let mut req_enabled = req.enabled;
+            if let Some(schedule) = schedule {
+                if let Some((_, s)) = &schedule {
+                    req_enabled = req_enabled.or(Some(s.is_active_now()));
+                }
+                proxy.schedule = Set(schedule.map(|(s, _)| s));
+            }
+
+            let expires_at = match req.expires_at {
+                Some(t) => {
+                    let t = t.map(|t| t.naive_utc());
+                    proxy.expires_at = Set(t);
+                    t
+                }
+                None => old_expires_at,
+            };
+            if req_enabled == Some(true) && crate::expiration::is_expired(expires_at) {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    ApiResponse::<crate::entity::proxy::Model>::error("代理已到期，请先延长到期时间".to_string()),
+                );
+            }
+
+            let enabled_changed = if let Some(enabled) = req_enabled {
                 proxy.enabled = Set(enabled);
                 old_enabled != enabled
             } else {
                 false
             };
 
+            proxy.lock_version = Set(expected_version + 1);
             proxy.updated_at = Set(chrono::Utc::now().naive_utc());
 
-            match proxy.update(&*db).await {
+            // 校验期间可能已被他人修改，更新时再按版本号过滤一次
+            match Proxy::update(proxy)
+                .filter(crate::entity::proxy::Column::LockVersion.eq(expected_version))
+                .exec(&*db)
+                .await
+            {
                 Ok(updated) => {
                     info!("代理已更新: {} (ID: {})", updated.name, updated.id);
+                    // SRV 记录包含远程端口
+                    if dns_changed || (updated.dns_name.is_some() && req.remote_port.is_some()) {
+                        crate::dns::request_sync();
+                    }
+                    crate::cloud_firewall::request_sync();
 
                     let need_restart = enabled_changed || (config_changed && updated.enabled);
 
@@ -584,7 +1203,7 @@ pub async fn update_proxy(
                     }
 
                     // 通知 Agent Client 代理配置已变更
-                    if enabled_changed || config_changed {
+                    if enabled_changed || config_changed || client_config_changed {
                         let csm = app_state.client_stream_manager.clone();
                         let client_id_notify = client_id.clone();
                         tokio::spawn(async move {
@@ -594,6 +1213,10 @@ pub async fn update_proxy(
 
                     (StatusCode::OK, ApiResponse::success(updated))
                 }
+                Err(e) if crate::optimistic_lock::is_conflict(&e) => (
+                    StatusCode::CONFLICT,
+                    ApiResponse::<crate::entity::proxy::Model>::error("数据已被他人修改，请刷新后重试".to_string()),
+                ),
                 Err(e) => (
                     StatusCode::INTERNAL_SERVER_ERROR,
                     ApiResponse::<crate::entity::proxy::Model>::error(format!(
@@ -603,56 +1226,44 @@ pub async fn update_proxy(
                 ),
             }
         }
-        Ok(None) => (
-            StatusCode::NOT_FOUND,
-            ApiResponse::<crate::entity::proxy::Model>::error("Proxy not found".to_string()),
-        ),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            ApiResponse::<crate::entity::proxy::Model>::error(format!(
-                "Failed to get proxy: {}",
-                e
-            )),
-        ),
+        Err((status, e)) => (status, ApiResponse::<crate::entity::proxy::Model>::error(e)),
     }
 }
 
 pub async fn delete_proxy(
     Path(id): Path<i64>,
-    Extension(_auth_user): Extension<Option<AuthUser>>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
     Extension(app_state): Extension<AppState>,
 ) -> impl IntoResponse {
+    let auth_user = match access::require_user(auth_user_opt) {
+        Ok(user) => user,
+        Err((status, e)) => return (status, ApiResponse::<&str>::error(e)),
+    };
     let db = get_connection().await;
 
     // 先获取代理信息，用于停止监听器
-    let proxy = match Proxy::find_by_id(id).one(db).await {
-        Ok(Some(p)) => p,
-        Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                ApiResponse::<&str>::error("Proxy not found".to_string()),
-            )
-        }
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                ApiResponse::<&str>::error(format!("Failed to get proxy: {}", e)),
-            )
-        }
+    let proxy = match access::accessible_proxy(&auth_user, id, db).await {
+        Ok(p) => p,
+        Err((status, e)) => return (status, ApiResponse::<&str>::error(e)),
     };
 
-    let client_id = proxy.client_id.clone();
     let proxy_name = proxy.name.clone();
 
     // 删除代理
     match Proxy::delete_by_id(id).exec(db).await {
         Ok(_) => {
             info!("代理已删除: {} (ID: {})", proxy_name, id);
+            crate::port_probe::forget(&[id], db).await;
+            if proxy.dns_name.is_some() {
+                crate::dns::request_sync();
+            }
+            crate::cloud_firewall::request_sync();
 
-            // 通过 ProxyControl trait 停止代理监听器
-            let proxy_control = app_state.proxy_control.clone();
+            // 停止所有发布节点上的代理监听器
+            let node_manager = app_state.node_manager.clone();
+            let deleted = proxy.clone();
             tokio::spawn(async move {
-                if let Err(e) = proxy_control.stop_proxy(&client_id, id).await {
+                if let Err(e) = crate::anycast::stop_deleted(&node_manager, &deleted).await {
                     tracing::error!("停止代理监听器失败: {}", e);
                 } else {
                     info!("代理监听器已停止: {}", proxy_name);
@@ -691,6 +1302,10 @@ pub struct BatchCreateProxyRequest {
     pub remote_ports: Vec<u16>,
     #[serde(rename = "nodeId")]
     pub node_id: Option<i64>,
+    #[serde(rename = "idleTimeout")]
+    pub idle_timeout: Option<i32>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 pub async fn batch_create_proxies(
@@ -707,6 +1322,10 @@ pub async fn batch_create_proxies(
         return (StatusCode::BAD_REQUEST, ApiResponse::<Vec<crate::entity::proxy::Model>>::error("远程端口列表不能为空".to_string()));
     }
 
+    if let Err(e) = validate_idle_timeout(req.idle_timeout).and_then(|_| validate_expires_at(req.expires_at)) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(e));
+    }
+
     if req.local_ports.len() != 1 && req.local_ports.len() != req.remote_ports.len() {
         return (StatusCode::BAD_REQUEST, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(
             format!("本地端口数量（{}）必须为 1 或与远程端口数量（{}）一致", req.local_ports.len(), req.remote_ports.len()),
@@ -716,14 +1335,9 @@ pub async fn batch_create_proxies(
     let db = get_connection().await;
 
     // 验证客户端
-    let client = match crate::entity::Client::find()
-        .filter(crate::entity::client::Column::Id.eq(req.client_id.parse::<i64>().unwrap_or(0)))
-        .one(db)
-        .await
-    {
-        Ok(Some(c)) => c,
-        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<Vec<crate::entity::proxy::Model>>::error("客户端不存在".to_string())),
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(format!("查询客户端失败: {}", e))),
+    let client = match access::accessible_client(&auth_user, req.client_id.parse::<i64>().unwrap_or(0), db).await {
+        Ok(c) => c,
+        Err((status, e)) => return (status, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(e)),
     };
 
     // 验证端口限制（仅对非管理员）
@@ -750,6 +1364,10 @@ pub async fn batch_create_proxies(
             Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(format!("查询节点失败: {}", e))),
         };
 
+        if !auth_user.is_admin && !crate::tenant::node_visible_to(&node, auth_user.tenant_id) {
+            return (StatusCode::NOT_FOUND, ApiResponse::<Vec<crate::entity::proxy::Model>>::error("节点不存在".to_string()));
+        }
+
         if node.node_type == "dedicated" && !auth_user.is_admin {
             if client.user_id != Some(auth_user.id) {
                 return (StatusCode::FORBIDDEN, ApiResponse::<Vec<crate::entity::proxy::Model>>::error("无权访问此客户端".to_string()));
@@ -769,10 +1387,25 @@ pub async fn batch_create_proxies(
         }
     }
 
-    // 验证所有端口（节点限制 + 端口唯一性）
-    for &remote_port in &req.remote_ports {
+    let tenant_id = match crate::port_blocklist::client_tenant_id(&req.client_id, db).await {
+        Ok(t) => t,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(format!("查询客户端租户失败: {}", e))),
+    };
+
+    // 验证所有端口（节点限制 + 端口黑名单 + 端口预留），端口唯一性在写入事务中检查
+    for (i, &remote_port) in req.remote_ports.iter().enumerate() {
+        let local_port = if req.local_ports.len() == 1 { req.local_ports[0] } else { req.local_ports[i] };
+        match crate::port_blocklist::validate_proxy_target(req.node_id, tenant_id, remote_port, &req.local_ip, local_port, db).await {
+            Ok((allowed, reason)) => {
+                if !allowed {
+                    return (StatusCode::FORBIDDEN, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(reason));
+                }
+            }
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(format!("验证端口黑名单失败: {}", e))),
+        }
+
         if let Some(node_id) = req.node_id {
-            match crate::node_limiter::validate_node_proxy_limit(node_id, remote_port, db).await {
+            match crate::node_limiter::validate_node_proxy_limit(node_id, remote_port, &app_state.config_manager, db).await {
                 Ok((allowed, reason)) => {
                     if !allowed {
                         return (StatusCode::FORBIDDEN, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(reason));
@@ -782,25 +1415,13 @@ pub async fn batch_create_proxies(
             }
         }
 
-        // 检查端口唯一性
-        let mut port_query = Proxy::find()
-            .filter(crate::entity::proxy::Column::RemotePort.eq(remote_port))
-            .filter(crate::entity::proxy::Column::Enabled.eq(true));
-
-        if let Some(node_id) = req.node_id {
-            port_query = port_query.filter(crate::entity::proxy::Column::NodeId.eq(node_id));
-        } else {
-            port_query = port_query.filter(crate::entity::proxy::Column::NodeId.is_null());
-        }
-
-        match port_query.one(db).await {
-            Ok(Some(existing)) => {
-                return (StatusCode::CONFLICT, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(
-                    format!("远程端口 {} 已被代理「{}」占用", remote_port, existing.name),
-                ));
+        match crate::port_reservation::validate_proxy_port(req.node_id, &req.client_id, remote_port, db).await {
+            Ok((allowed, reason)) => {
+                if !allowed {
+                    return (StatusCode::CONFLICT, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(reason));
+                }
             }
-            Ok(None) => {}
-            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(format!("检查端口占用失败: {}", e))),
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(format!("验证端口预留失败: {}", e))),
         }
     }
 
@@ -811,8 +1432,30 @@ pub async fn batch_create_proxies(
         None
     };
 
+    // 先在节点上预留全部端口，任一失败则释放已预留的
+    let leases: Vec<PortLease> = req
+        .remote_ports
+        .iter()
+        .map(|&remote_port| PortLease {
+            node_id: req.node_id,
+            client_id: req.client_id.clone(),
+            proxy_type: req.proxy_type.clone(),
+            remote_port,
+        })
+        .collect();
+    for (i, lease) in leases.iter().enumerate() {
+        if let Err(e) = app_state.proxy_control.reserve_port(lease).await {
+            for reserved in &leases[..i] {
+                let _ = app_state.proxy_control.release_port(reserved).await;
+            }
+            return (StatusCode::CONFLICT, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(
+                format!("预留端口 {} 失败: {}", lease.remote_port, e),
+            ));
+        }
+    }
+
     let now = chrono::Utc::now().naive_utc();
-    let mut created_proxies: Vec<crate::entity::proxy::Model> = Vec::new();
+    let mut new_proxies = Vec::with_capacity(req.remote_ports.len());
 
     for (i, &remote_port) in req.remote_ports.iter().enumerate() {
         let local_port = if req.local_ports.len() == 1 { req.local_ports[0] } else { req.local_ports[i] };
@@ -827,49 +1470,70 @@ pub async fn batch_create_proxies(
             client_id: Set(req.client_id.clone()),
             name: Set(proxy_name),
             proxy_type: Set(req.proxy_type.clone()),
-            local_ip: Set(req.local_ip.clone()),
+            local_ip: Set(common::utils::normalize_host(&req.local_ip).to_string()),
             local_port: Set(local_port),
             remote_port: Set(remote_port),
+            bind_ip: Set(None),
             enabled: Set(true),
             node_id: Set(req.node_id),
             group_id: Set(group_id.clone()),
+            idle_timeout: Set(req.idle_timeout),
+            mitigation_config: Set(None),
+            bandwidth_weight: Set(None),
+            priority: Set(None),
+            local_pool_size: Set(None),
+            local_source: Set(None),
+            anycast_node_ids: Set(None),
+            dns_name: Set(None),
+            service: Set(None),
+            schedule: Set(None),
+            expires_at: Set(req.expires_at.map(|t| t.naive_utc())),
+            stale_at: Set(None),
             total_bytes_sent: Set(0),
             total_bytes_received: Set(0),
+            lock_version: Set(0),
             created_at: Set(now),
             updated_at: Set(now),
         };
 
-        match new_proxy.insert(db).await {
-            Ok(proxy) => {
-                // 启动代理监听器
-                if let Err(e) = app_state.proxy_control.start_proxy(&req.client_id, proxy.id).await {
-                    tracing::warn!("批量创建：启动代理监听器失败，回滚全部: {}", e);
-                    // 回滚：删除已创建的所有代理并停止监听器
-                    for p in &created_proxies {
-                        let _ = app_state.proxy_control.stop_proxy(&req.client_id, p.id).await;
-                        let _ = Proxy::delete_by_id(p.id).exec(db).await;
-                    }
-                    let _ = Proxy::delete_by_id(proxy.id).exec(db).await;
-                    return (StatusCode::CONFLICT, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(
-                        format!("端口 {} 启动代理监听器失败: {}", remote_port, e),
-                    ));
-                }
-                created_proxies.push(proxy);
+        new_proxies.push((remote_port, new_proxy));
+    }
+
+    let created_proxies = match insert_proxies_checked(db, req.node_id, new_proxies).await {
+        Ok(proxies) => proxies,
+        Err((status, e)) => {
+            for lease in &leases {
+                let _ = app_state.proxy_control.release_port(lease).await;
             }
-            Err(e) => {
-                // 回滚已创建的代理
-                for p in &created_proxies {
-                    let _ = app_state.proxy_control.stop_proxy(&req.client_id, p.id).await;
-                    let _ = Proxy::delete_by_id(p.id).exec(db).await;
-                }
-                return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(
-                    format!("创建代理失败: {}", e),
-                ));
+            return (status, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(e));
+        }
+    };
+
+    // 激活全部监听器；任一失败则停止已激活的并删除全部记录兜底
+    for (i, (proxy, lease)) in created_proxies.iter().zip(&leases).enumerate() {
+        if let Err(e) = app_state.proxy_control.activate_proxy(lease, proxy.id).await {
+            tracing::warn!("批量创建：激活代理监听器失败，回滚全部: {}", e);
+            for p in &created_proxies[..i] {
+                let _ = app_state.proxy_control.stop_proxy(&req.client_id, p.id).await;
+            }
+            for lease in &leases[i..] {
+                let _ = app_state.proxy_control.release_port(lease).await;
             }
+            let _ = Proxy::delete_many()
+                .filter(crate::entity::proxy::Column::Id.is_in(created_proxies.iter().map(|p| p.id)))
+                .exec(db)
+                .await;
+            return (StatusCode::CONFLICT, ApiResponse::<Vec<crate::entity::proxy::Model>>::error(
+                format!("端口 {} 启动代理监听器失败: {}", proxy.remote_port, e),
+            ));
         }
     }
 
     info!("批量创建 {} 个代理 (group_id: {:?}, 客户端: {})", created_proxies.len(), group_id, req.client_id);
+    for proxy in &created_proxies {
+        crate::port_probe::spawn_probe(proxy.clone(), app_state.node_manager.clone(), app_state.config_manager.clone(), db);
+    }
+    crate::cloud_firewall::request_sync();
 
     // 通知客户端（只通知一次）
     let csm = app_state.client_stream_manager.clone();
@@ -888,25 +1552,21 @@ pub struct ToggleGroupRequest {
 
 pub async fn toggle_proxy_group(
     Path(group_id): Path<String>,
-    Extension(_auth_user): Extension<Option<AuthUser>>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
     Extension(app_state): Extension<AppState>,
     Json(req): Json<ToggleGroupRequest>,
 ) -> impl IntoResponse {
+    let auth_user = match access::require_user(auth_user_opt) {
+        Ok(user) => user,
+        Err((status, e)) => return (status, ApiResponse::<&str>::error(e)),
+    };
     let db = get_connection().await;
 
-    let proxies = match Proxy::find()
-        .filter(crate::entity::proxy::Column::GroupId.eq(&group_id))
-        .all(db)
-        .await
-    {
+    let proxies = match access::accessible_proxy_group(&auth_user, &group_id, db).await {
         Ok(p) => p,
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<&str>::error(format!("查询代理组失败: {}", e))),
+        Err((status, e)) => return (status, ApiResponse::<&str>::error(e)),
     };
 
-    if proxies.is_empty() {
-        return (StatusCode::NOT_FOUND, ApiResponse::<&str>::error("代理组不存在".to_string()));
-    }
-
     let client_id = proxies[0].client_id.clone();
     let now = chrono::Utc::now().naive_utc();
 
@@ -918,6 +1578,7 @@ pub async fn toggle_proxy_group(
 
         let mut active: crate::entity::proxy::ActiveModel = proxy.clone().into();
         active.enabled = Set(req.enabled);
+        active.lock_version = Set(proxy.lock_version + 1);
         active.updated_at = Set(now);
 
         if let Err(e) = active.update(db).await {
@@ -935,6 +1596,7 @@ pub async fn toggle_proxy_group(
     }
 
     info!("代理组 {} 已{}", group_id, if req.enabled { "启用" } else { "禁用" });
+    crate::cloud_firewall::request_sync();
 
     // 通知客户端
     let csm = app_state.client_stream_manager.clone();
@@ -948,41 +1610,41 @@ pub async fn toggle_proxy_group(
 
 pub async fn delete_proxy_group(
     Path(group_id): Path<String>,
-    Extension(_auth_user): Extension<Option<AuthUser>>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
     Extension(app_state): Extension<AppState>,
 ) -> impl IntoResponse {
+    let auth_user = match access::require_user(auth_user_opt) {
+        Ok(user) => user,
+        Err((status, e)) => return (status, ApiResponse::<&str>::error(e)),
+    };
     let db = get_connection().await;
 
-    let proxies = match Proxy::find()
-        .filter(crate::entity::proxy::Column::GroupId.eq(&group_id))
-        .all(db)
-        .await
-    {
+    let proxies = match access::accessible_proxy_group(&auth_user, &group_id, db).await {
         Ok(p) => p,
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<&str>::error(format!("查询代理组失败: {}", e))),
+        Err((status, e)) => return (status, ApiResponse::<&str>::error(e)),
     };
 
-    if proxies.is_empty() {
-        return (StatusCode::NOT_FOUND, ApiResponse::<&str>::error("代理组不存在".to_string()));
-    }
-
     let client_id = proxies[0].client_id.clone();
     let count = proxies.len();
 
     for proxy in &proxies {
         let _ = Proxy::delete_by_id(proxy.id).exec(db).await;
 
-        let proxy_control = app_state.proxy_control.clone();
-        let cid = client_id.clone();
-        let pid = proxy.id;
+        let node_manager = app_state.node_manager.clone();
+        let deleted = proxy.clone();
         tokio::spawn(async move {
-            if let Err(e) = proxy_control.stop_proxy(&cid, pid).await {
-                tracing::error!("停止代理监听器失败 (ID: {}): {}", pid, e);
+            if let Err(e) = crate::anycast::stop_deleted(&node_manager, &deleted).await {
+                tracing::error!("停止代理监听器失败 (ID: {}): {}", deleted.id, e);
             }
         });
     }
 
     info!("代理组 {} 已删除（共 {} 个）", group_id, count);
+    crate::port_probe::forget(&proxies.iter().map(|p| p.id).collect::<Vec<_>>(), db).await;
+    if proxies.iter().any(|p| p.dns_name.is_some()) {
+        crate::dns::request_sync();
+    }
+    crate::cloud_firewall::request_sync();
 
     // 通知客户端
     let csm = app_state.client_stream_manager.clone();
@@ -1007,25 +1669,21 @@ pub struct UpdateGroupRequest {
 
 pub async fn update_proxy_group(
     Path(group_id): Path<String>,
-    Extension(_auth_user): Extension<Option<AuthUser>>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
     Extension(app_state): Extension<AppState>,
     Json(req): Json<UpdateGroupRequest>,
 ) -> impl IntoResponse {
+    let auth_user = match access::require_user(auth_user_opt) {
+        Ok(user) => user,
+        Err((status, e)) => return (status, ApiResponse::<&str>::error(e)),
+    };
     let db = get_connection().await;
 
-    let proxies = match Proxy::find()
-        .filter(crate::entity::proxy::Column::GroupId.eq(&group_id))
-        .all(db)
-        .await
-    {
+    let proxies = match access::accessible_proxy_group(&auth_user, &group_id, db).await {
         Ok(p) => p,
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<&str>::error(format!("查询代理组失败: {}", e))),
+        Err((status, e)) => return (status, ApiResponse::<&str>::error(e)),
     };
 
-    if proxies.is_empty() {
-        return (StatusCode::NOT_FOUND, ApiResponse::<&str>::error("代理组不存在".to_string()));
-    }
-
     let client_id = proxies[0].client_id.clone();
     let now = chrono::Utc::now().naive_utc();
     let mut config_changed = false;
@@ -1052,6 +1710,7 @@ pub async fn update_proxy_group(
             changed = true;
         }
         if let Some(ref local_ip) = req.local_ip {
+            let local_ip = &common::utils::normalize_host(local_ip).to_string();
             if local_ip != &proxy.local_ip {
                 config_changed = true;
             }
@@ -1067,6 +1726,7 @@ pub async fn update_proxy_group(
         }
 
         if changed {
+            active.lock_version = Set(proxy.lock_version + 1);
             active.updated_at = Set(now);
             if let Err(e) = active.update(db).await {
                 tracing::error!("更新代理 {} 失败: {}", proxy.id, e);
@@ -1094,5 +1754,6 @@ pub async fn update_proxy_group(
     }
 
     info!("代理组 {} 已更新", group_id);
+    crate::cloud_firewall::request_sync();
     (StatusCode::OK, ApiResponse::success("代理组更新成功"))
 }
@@ -0,0 +1,205 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use common::protocol::control::PortLease;
+
+use crate::entity::failover_group::format_ids;
+use crate::entity::{proxy, Client, Node, UserNode};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use crate::AppState;
+use super::ApiResponse;
+use crate::api::access;
+
+#[derive(Deserialize)]
+pub struct UpdateAnycastNodesRequest {
+    /// 除代理所属节点外同时发布的节点，为空表示取消多节点发布
+    #[serde(rename = "nodeIds")]
+    pub node_ids: Vec<i64>,
+}
+
+/// 检查用户能否使用该节点发布代理（与创建代理时的节点权限检查一致）
+async fn check_node_access(
+    auth_user: &AuthUser,
+    p: &proxy::Model,
+    node_id: i64,
+    db: &DatabaseConnection,
+) -> Result<(), (StatusCode, String)> {
+    let node = match Node::find_by_id(node_id).one(db).await {
+        Ok(Some(n)) => n,
+        Ok(None) => return Err((StatusCode::NOT_FOUND, format!("节点 #{} 不存在", node_id))),
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("查询节点失败: {}", e))),
+    };
+    if auth_user.is_admin {
+        return Ok(());
+    }
+    if !crate::tenant::node_visible_to(&node, auth_user.tenant_id) {
+        return Err((StatusCode::NOT_FOUND, format!("节点 #{} 不存在", node_id)));
+    }
+    if node.node_type != "dedicated" {
+        return Ok(());
+    }
+    let client = Client::find_by_id(p.client_id.parse::<i64>().unwrap_or(0))
+        .one(db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询客户端失败: {}", e)))?;
+    if client.and_then(|c| c.user_id) != Some(auth_user.id) {
+        return Err((StatusCode::FORBIDDEN, "无权访问此客户端".to_string()));
+    }
+    let user_node = UserNode::find()
+        .filter(crate::entity::user_node::Column::UserId.eq(auth_user.id))
+        .filter(crate::entity::user_node::Column::NodeId.eq(node_id))
+        .one(db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("检查节点权限失败: {}", e)))?;
+    if user_node.is_none() {
+        return Err((StatusCode::FORBIDDEN, format!("独享节点「{}」未分配给您，无法使用", node.name)));
+    }
+    Ok(())
+}
+
+/// PUT /api/proxies/{id}/anycast-nodes - 设置代理同时发布的其他节点
+///
+/// 新增的节点先预留端口，写入数据库后再激活监听器；任一节点失败时整体回滚。被移除的节点停止监听器。
+pub async fn update_proxy_anycast_nodes(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<UpdateAnycastNodesRequest>,
+) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<proxy::Model>::error("未认证".to_string()));
+    };
+    let db = get_connection().await;
+    let p = match access::accessible_proxy(&auth_user, id, db).await {
+        Ok(p) => p,
+        Err((status, e)) => return (status, ApiResponse::<proxy::Model>::error(e)),
+    };
+    let Some(primary) = p.node_id else {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<proxy::Model>::error("未指定节点的代理不能多节点发布".to_string()));
+    };
+
+    let mut node_ids: Vec<i64> = Vec::new();
+    for node_id in req.node_ids {
+        if node_id != primary && !node_ids.contains(&node_id) {
+            node_ids.push(node_id);
+        }
+    }
+    if !node_ids.is_empty() && p.bind_ip.is_some() {
+        // 监听 IP 只存在于单个节点上
+        return (StatusCode::BAD_REQUEST, ApiResponse::<proxy::Model>::error("指定了监听地址的代理不能多节点发布".to_string()));
+    }
+    let current = p.anycast_ids();
+    let added: Vec<i64> = node_ids.iter().copied().filter(|n| !current.contains(n)).collect();
+    let removed: Vec<i64> = current.iter().copied().filter(|n| !node_ids.contains(n)).collect();
+
+    for &node_id in &added {
+        if let Err((status, e)) = check_node_access(&auth_user, &p, node_id, db).await {
+            return (status, ApiResponse::<proxy::Model>::error(e));
+        }
+        match crate::anycast::check_node(&p, node_id, &app_state.config_manager, db).await {
+            Ok(None) => {}
+            Ok(Some(reason)) => {
+                return (StatusCode::CONFLICT, ApiResponse::<proxy::Model>::error(format!("节点 #{}: {}", node_id, reason)))
+            }
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ApiResponse::<proxy::Model>::error(format!("验证多节点发布失败: {}", e)),
+                )
+            }
+        }
+    }
+
+    // 与创建代理相同的两阶段流程：先在新增节点上预留端口
+    let leases: Vec<PortLease> = added
+        .iter()
+        .map(|&node_id| PortLease {
+            node_id: Some(node_id),
+            client_id: p.client_id.clone(),
+            proxy_type: p.proxy_type.clone(),
+            remote_port: p.remote_port,
+        })
+        .collect();
+    let release_all = |leases: &[PortLease]| {
+        let proxy_control = app_state.proxy_control.clone();
+        let leases = leases.to_vec();
+        async move {
+            for lease in &leases {
+                if let Err(e) = proxy_control.release_port(lease).await {
+                    warn!("释放节点 #{:?} 上预留的端口 {} 失败: {}", lease.node_id, lease.remote_port, e);
+                }
+            }
+        }
+    };
+    if p.enabled {
+        for (i, lease) in leases.iter().enumerate() {
+            if let Err(e) = app_state.proxy_control.reserve_port(lease).await {
+                release_all(&leases[..i]).await;
+                return (
+                    StatusCode::CONFLICT,
+                    ApiResponse::<proxy::Model>::error(format!("在节点 #{} 上预留端口失败: {}", added[i], e)),
+                );
+            }
+        }
+    }
+
+    let mut active: proxy::ActiveModel = p.clone().into();
+    active.anycast_node_ids = Set((!node_ids.is_empty()).then(|| format_ids(&node_ids)));
+    active.lock_version = Set(p.lock_version + 1);
+    active.updated_at = Set(chrono::Utc::now().naive_utc());
+    let updated = match active.update(db).await {
+        Ok(updated) => updated,
+        Err(e) => {
+            if p.enabled {
+                release_all(&leases).await;
+            }
+            return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<proxy::Model>::error(format!("更新代理失败: {}", e)));
+        }
+    };
+
+    if p.enabled {
+        for (lease, &node_id) in leases.iter().zip(&added) {
+            if let Err(e) = app_state.proxy_control.activate_proxy(lease, p.id).await {
+                // 端口已预留，激活失败只可能是节点断开等异常情况，恢复原来的节点列表
+                warn!("在节点 #{} 上启动代理 {} 失败，恢复多节点发布设置: {}", node_id, p.name, e);
+                let mut revert: proxy::ActiveModel = updated.clone().into();
+                revert.anycast_node_ids = Set(p.anycast_node_ids.clone());
+                revert.lock_version = Set(updated.lock_version + 1);
+                let _ = revert.update(db).await;
+                for &started in added.iter().take_while(|&&n| n != node_id) {
+                    let _ = app_state.node_manager.stop_proxy_on_node(started, &p.client_id, p.id).await;
+                }
+                release_all(&leases).await;
+                return (
+                    StatusCode::CONFLICT,
+                    ApiResponse::<proxy::Model>::error(format!("在节点 #{} 上启动代理监听器失败: {}", node_id, e)),
+                );
+            }
+        }
+        for &node_id in &removed {
+            if let Err(e) = app_state.node_manager.stop_proxy_on_node(node_id, &p.client_id, p.id).await {
+                warn!("停止节点 #{} 上的代理 {} 失败: {}", node_id, p.name, e);
+            }
+        }
+    }
+
+    info!("代理 {} (ID: {}) 的多节点发布已更新: {:?}", p.name, p.id, node_ids);
+    crate::cloud_firewall::request_sync();
+
+    // 客户端需要与新增节点建立隧道
+    let csm = app_state.client_stream_manager.clone();
+    let client_id_notify = p.client_id.clone();
+    tokio::spawn(async move {
+        csm.notify_proxy_change(&client_id_notify).await;
+    });
+
+    (StatusCode::OK, ApiResponse::success(updated))
+}
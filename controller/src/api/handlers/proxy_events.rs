@@ -0,0 +1,81 @@
+//! 代理事件时间线：汇总各节点记录的代理生命周期事件
+
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::api::access;
+use crate::migration::get_connection;
+use crate::{middleware::AuthUser, AppState};
+
+use super::ApiResponse;
+
+const DEFAULT_LIMIT: u32 = 200;
+const MAX_LIMIT: u32 = 2000;
+
+#[derive(Deserialize)]
+pub struct ProxyEventsQuery {
+    /// 每个节点最多返回最近的 N 条，默认 200，最大 2000
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyEventView {
+    pub timestamp: String,
+    /// listener_started / listener_stopped / bind_failed / client_attached / client_detached / protocol_switched
+    pub kind: String,
+    pub node_id: i64,
+    pub proxy_id: Option<i64>,
+    pub client_id: Option<String>,
+    pub port: Option<u32>,
+    pub detail: String,
+}
+
+/// GET /api/proxies/{id}/events - 获取代理的事件时间线
+///
+/// 包含代理自身的监听器事件、所属客户端的接入 / 断开以及节点隧道协议切换，按时间排序。
+/// 未连接或查询失败的节点会被跳过。
+pub async fn get_proxy_events(
+    Path(id): Path<i64>,
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Query(query): Query<ProxyEventsQuery>,
+) -> impl IntoResponse {
+    let Some(auth_user) = auth_user_opt else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<Vec<ProxyEventView>>::error("未认证".to_string()));
+    };
+    let db = get_connection().await;
+
+    let proxy = match access::accessible_proxy(&auth_user, id, db).await {
+        Ok(p) => p,
+        Err((status, e)) => return (status, ApiResponse::error(e)),
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let node_manager = &app_state.node_manager;
+    let mut events = Vec::new();
+    // 代理可能因故障转移等原因在多个节点上运行过，汇总所有已连接节点的记录
+    for node_id in node_manager.get_loaded_node_ids().await {
+        match node_manager.get_proxy_events(node_id, Some(proxy.id), Some(proxy.client_id.clone()), limit).await {
+            Ok(node_events) => events.extend(node_events.into_iter().map(|e| ProxyEventView {
+                timestamp: e.timestamp,
+                kind: e.kind,
+                node_id,
+                proxy_id: e.proxy_id,
+                client_id: e.client_id,
+                port: e.port,
+                detail: e.detail,
+            })),
+            Err(e) => warn!("从节点 #{} 获取代理 #{} 的事件失败: {}", node_id, proxy.id, e),
+        }
+    }
+    events.sort_by_cached_key(|e| DateTime::parse_from_rfc3339(&e.timestamp).ok());
+
+    (StatusCode::OK, ApiResponse::success(events))
+}
@@ -1,11 +1,15 @@
 use axum::{
-    extract::Extension,
+    extract::{Extension, Path, Query},
+    http::StatusCode,
     response::Json,
 };
-use sea_orm::{EntityTrait, Set, ActiveModelTrait, ColumnTrait, QueryFilter};
+use sea_orm::{EntityTrait, Set, ActiveModelTrait, ColumnTrait, QueryFilter, QueryOrder};
 use serde::{Deserialize, Serialize};
-use crate::entity::{SystemConfig, system_config};
+use crate::api::pagination::{fetch_page, ListQuery};
+use crate::config_revision::{self, ConfigChange, ConfigSnapshot};
+use crate::entity::{config_revision as config_revision_entity, system_restart, ConfigRevision, SystemConfig, SystemRestart, system_config};
 use crate::migration::get_connection;
+use crate::tls_apply;
 use crate::AppState;
 use super::ApiResponse;
 use crate::middleware::AuthUser;
@@ -31,37 +35,102 @@ pub struct UpdateConfigRequest {
     pub value: serde_json::Value,
 }
 
-/// 获取所有系统配置
-pub async fn get_configs() -> Json<ApiResponse<ConfigListResponse>> {
+impl From<system_config::Model> for ConfigItem {
+    /// 敏感配置项（证书 / 私钥内容、密钥、密码）只返回占位值
+    fn from(c: system_config::Model) -> Self {
+        let raw = config_revision::redact_value(&c.key, &c.value);
+        let value = if config_revision::is_redacted(&raw) {
+            serde_json::Value::String(raw)
+        } else {
+            serde_json::from_str(&raw).unwrap_or(serde_json::Value::Null)
+        };
+        Self {
+            id: c.id,
+            key: c.key,
+            value,
+            description: c.description,
+            value_type: c.value_type,
+        }
+    }
+}
+
+/// 校验并转换配置值为数据库中保存的形式
+fn convert_value(config: &system_config::Model, value: &serde_json::Value) -> Result<String, String> {
+    match config.value_type.as_str() {
+        "number" => {
+            if let Some(n) = value.as_i64() {
+                Ok(n.to_string())
+            } else if let Some(f) = value.as_f64() {
+                Ok(f.to_string())
+            } else {
+                Err("配置值类型错误：需要数字类型".to_string())
+            }
+        }
+        "boolean" => value
+            .as_bool()
+            .map(|b| b.to_string())
+            .ok_or_else(|| "配置值类型错误：需要布尔类型".to_string()),
+        "string" => value
+            .as_str()
+            .map(|s| serde_json::to_string(s).unwrap_or_else(|_| s.to_string()))
+            .ok_or_else(|| "配置值类型错误：需要字符串类型".to_string()),
+        _ => Ok(value.to_string()),
+    }
+}
+
+/// 客户端把读取到的敏感配置占位值原样提交时视为未修改
+fn is_unchanged_secret(key: &str, value: &serde_json::Value) -> bool {
+    config_revision::is_sensitive_key(key) && value.as_str().is_some_and(config_revision::is_redacted)
+}
+
+/// 写入 TLS 证书 / 私钥内容前与 `/system/tls/apply` 一样校验证书链与私钥，
+/// 避免不匹配的证书写入后被热加载
+async fn check_tls_contents(updates: ConfigSnapshot) -> Result<(), (StatusCode, String)> {
+    if updates.is_empty() {
+        return Ok(());
+    }
+    let current = config_revision::current_snapshot(get_connection().await)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询配置失败: {}", e)))?;
+    tls_apply::validate_content_updates(&current, &updates).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+/// 获取所有系统配置（仅管理员，敏感值已隐藏）
+pub async fn get_configs(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> (StatusCode, Json<ApiResponse<ConfigListResponse>>) {
+    match auth_user {
+        Some(user) if user.is_admin => {}
+        Some(_) => return (StatusCode::FORBIDDEN, ApiResponse::error("权限不足".to_string())),
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::error("未登录，请先登录".to_string())),
+    }
+
     let db = get_connection().await;
 
     match SystemConfig::find().all(db).await {
         Ok(configs) => {
-            let items: Vec<ConfigItem> = configs
-                .into_iter()
-                .map(|c| {
-                    let value = serde_json::from_str(&c.value).unwrap_or(serde_json::Value::Null);
-                    ConfigItem {
-                        id: c.id,
-                        key: c.key,
-                        value,
-                        description: c.description,
-                        value_type: c.value_type,
-                    }
-                })
-                .collect();
-
-            ApiResponse::success(ConfigListResponse { configs: items })
+            let items = configs.into_iter().map(ConfigItem::from).collect();
+            (StatusCode::OK, ApiResponse::success(ConfigListResponse { configs: items }))
         }
-        Err(e) => ApiResponse::error(format!("获取配置失败: {}", e)),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("获取配置失败: {}", e)),
+        ),
     }
 }
 
-/// 更新系统配置
+/// 更新系统配置（仅管理员）
 pub async fn update_config(
+    Extension(auth_user): Extension<Option<AuthUser>>,
     Extension(app_state): Extension<AppState>,
     Json(payload): Json<UpdateConfigRequest>,
-) -> Json<ApiResponse<ConfigItem>> {
+) -> (StatusCode, Json<ApiResponse<ConfigItem>>) {
+    let auth_user = match auth_user {
+        Some(user) if user.is_admin => user,
+        Some(_) => return (StatusCode::FORBIDDEN, ApiResponse::error("权限不足".to_string())),
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::error("未登录，请先登录".to_string())),
+    };
+
     let config_manager = &app_state.config_manager;
     let db = get_connection().await;
 
@@ -73,63 +142,61 @@ pub async fn update_config(
     {
         Ok(Some(c)) => c,
         Ok(None) => {
-            return ApiResponse::error(format!("配置项不存在: {}", payload.key));
+            return (StatusCode::NOT_FOUND, ApiResponse::error(format!("配置项不存在: {}", payload.key)));
         }
         Err(e) => {
-            return ApiResponse::error(format!("查询配置失败: {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("查询配置失败: {}", e)));
         }
     };
 
+    if is_unchanged_secret(&config.key, &payload.value) {
+        return (StatusCode::OK, ApiResponse::success(ConfigItem::from(config)));
+    }
+
     // 验证并转换值
-    let value_str = match config.value_type.as_str() {
-        "number" => {
-            if let Some(n) = payload.value.as_i64() {
-                n.to_string()
-            } else if let Some(f) = payload.value.as_f64() {
-                f.to_string()
-            } else {
-                return ApiResponse::error("配置值类型错误：需要数字类型".to_string());
-            }
-        }
-        "boolean" => {
-            if let Some(b) = payload.value.as_bool() {
-                b.to_string()
-            } else {
-                return ApiResponse::error("配置值类型错误：需要布尔类型".to_string());
-            }
-        }
-        "string" => {
-            if let Some(s) = payload.value.as_str() {
-                serde_json::to_string(s).unwrap_or_else(|_| s.to_string())
-            } else {
-                return ApiResponse::error("配置值类型错误：需要字符串类型".to_string());
-            }
-        }
-        _ => payload.value.to_string(),
+    let value_str = match convert_value(&config, &payload.value) {
+        Ok(v) => v,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::error(e)),
     };
+    if tls_apply::is_content_key(&config.key) {
+        let updates = ConfigSnapshot::from([(config.key.clone(), value_str.clone())]);
+        if let Err((status, e)) = check_tls_contents(updates).await {
+            return (status, ApiResponse::error(e));
+        }
+    }
 
     // 更新数据库
+    let old_value = config.value.clone();
     let mut active_model: system_config::ActiveModel = config.clone().into();
     active_model.value = Set(value_str);
     active_model.updated_at = Set(chrono::Utc::now().naive_utc());
 
     match active_model.update(db).await {
         Ok(updated) => {
+            // 记录配置修订
+            let changes = vec![ConfigChange {
+                key: updated.key.clone(),
+                old: old_value,
+                new: updated.value.clone(),
+            }]
+            .into_iter()
+            .filter(|c| c.old != c.new)
+            .collect();
+            if let Err(e) = config_revision::record_revision(db, changes, None, Some(auth_user.username)).await {
+                tracing::error!("记录配置修订失败: {}", e);
+            }
+
             // 重新加载配置缓存
             if let Err(e) = config_manager.reload().await {
                 tracing::error!("重新加载配置缓存失败: {}", e);
             }
 
-            let value = serde_json::from_str(&updated.value).unwrap_or(serde_json::Value::Null);
-            ApiResponse::success(ConfigItem {
-                id: updated.id,
-                key: updated.key,
-                value,
-                description: updated.description,
-                value_type: updated.value_type,
-            })
+            (StatusCode::OK, ApiResponse::success(ConfigItem::from(updated)))
         }
-        Err(e) => ApiResponse::error(format!("更新配置失败: {}", e)),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("更新配置失败: {}", e)),
+        ),
     }
 }
 
@@ -139,15 +206,42 @@ pub struct BatchUpdateConfigRequest {
     pub configs: Vec<UpdateConfigRequest>,
 }
 
+/// 批量更新系统配置（仅管理员）
 pub async fn batch_update_configs(
+    Extension(auth_user): Extension<Option<AuthUser>>,
     Extension(app_state): Extension<AppState>,
     Json(payload): Json<BatchUpdateConfigRequest>,
-) -> Json<ApiResponse<ConfigListResponse>> {
+) -> (StatusCode, Json<ApiResponse<ConfigListResponse>>) {
+    let auth_user = match auth_user {
+        Some(user) if user.is_admin => user,
+        Some(_) => return (StatusCode::FORBIDDEN, ApiResponse::error("权限不足".to_string())),
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::error("未登录，请先登录".to_string())),
+    };
+
     let config_manager = &app_state.config_manager;
     let db = get_connection().await;
     let mut updated_items = Vec::new();
+    let mut changes = Vec::new();
+
+    // 证书和私钥通常在同一批中修改，需要整体校验
+    let tls_updates = payload
+        .configs
+        .iter()
+        .filter(|req| tls_apply::is_content_key(&req.key) && !is_unchanged_secret(&req.key, &req.value))
+        .filter_map(|req| {
+            let value = req.value.as_str()?;
+            Some((req.key.clone(), serde_json::to_string(value).ok()?))
+        })
+        .collect();
+    if let Err((status, e)) = check_tls_contents(tls_updates).await {
+        return (status, ApiResponse::error(e));
+    }
 
     for update_req in payload.configs {
+        if is_unchanged_secret(&update_req.key, &update_req.value) {
+            continue;
+        }
+
         // 查找配置
         let config = match SystemConfig::find()
             .filter(system_config::Column::Key.eq(&update_req.key))
@@ -160,145 +254,295 @@ pub async fn batch_update_configs(
         };
 
         // 验证并转换值
-        let value_str = match config.value_type.as_str() {
-            "number" => {
-                if let Some(n) = update_req.value.as_i64() {
-                    n.to_string()
-                } else if let Some(f) = update_req.value.as_f64() {
-                    f.to_string()
-                } else {
-                    continue;
-                }
-            }
-            "boolean" => {
-                if let Some(b) = update_req.value.as_bool() {
-                    b.to_string()
-                } else {
-                    continue;
-                }
-            }
-            "string" => {
-                if let Some(s) = update_req.value.as_str() {
-                    serde_json::to_string(s).unwrap_or_else(|_| s.to_string())
-                } else {
-                    continue;
-                }
-            }
-            _ => update_req.value.to_string(),
+        let Ok(value_str) = convert_value(&config, &update_req.value) else {
+            continue;
         };
 
         // 更新数据库
+        let old_value = config.value.clone();
         let mut active_model: system_config::ActiveModel = config.into();
         active_model.value = Set(value_str);
         active_model.updated_at = Set(chrono::Utc::now().naive_utc());
 
         if let Ok(updated) = active_model.update(db).await {
-            let value = serde_json::from_str(&updated.value).unwrap_or(serde_json::Value::Null);
-            updated_items.push(ConfigItem {
-                id: updated.id,
-                key: updated.key,
-                value,
-                description: updated.description,
-                value_type: updated.value_type,
-            });
+            if old_value != updated.value {
+                changes.push(ConfigChange {
+                    key: updated.key.clone(),
+                    old: old_value,
+                    new: updated.value.clone(),
+                });
+            }
+            updated_items.push(ConfigItem::from(updated));
         }
     }
 
+    // 记录配置修订
+    if let Err(e) = config_revision::record_revision(db, changes, None, Some(auth_user.username)).await {
+        tracing::error!("记录配置修订失败: {}", e);
+    }
+
     // 重新加载配置缓存
     if let Err(e) = config_manager.reload().await {
         tracing::error!("重新加载配置缓存失败: {}", e);
     }
 
-    ApiResponse::success(ConfigListResponse { configs: updated_items })
+    (StatusCode::OK, ApiResponse::success(ConfigListResponse { configs: updated_items }))
 }
 
-/// 重启系统响应
+/// gRPC TLS 状态，客户端 / 节点安装命令需要据此选择 `https://` 地址
 #[derive(Debug, Serialize)]
-pub struct RestartResponse {
-    pub message: String,
+pub struct GrpcTlsStatus {
+    pub enabled: bool,
+    pub domain: String,
 }
 
-/// 重启系统（仅管理员可用）
-pub async fn restart_system(
+/// GET /api/system/grpc-tls - 获取 gRPC TLS 状态（登录用户均可访问）
+pub async fn get_grpc_tls_status(
     Extension(auth_user): Extension<Option<AuthUser>>,
-) -> Json<ApiResponse<RestartResponse>> {
-    // 检查是否已登录
-    let auth_user = match auth_user {
-        Some(user) => user,
-        None => {
-            return ApiResponse::error("未登录，请先登录".to_string());
-        }
+    Extension(app_state): Extension<AppState>,
+) -> (StatusCode, Json<ApiResponse<GrpcTlsStatus>>) {
+    if auth_user.is_none() {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::error("未登录，请先登录".to_string()));
+    }
+    let config_manager = &app_state.config_manager;
+    let status = GrpcTlsStatus {
+        enabled: config_manager.get_bool("grpc_tls_enabled", false).await,
+        domain: config_manager.get_string("grpc_domain", "").await,
     };
+    (StatusCode::OK, ApiResponse::success(status))
+}
 
-    // 检查是否为管理员
-    if !auth_user.is_admin {
-        return ApiResponse::error("权限不足，仅管理员可以重启系统".to_string());
+/// 配置修订（展示用，敏感值已隐藏）
+#[derive(Debug, Serialize)]
+pub struct ConfigRevisionItem {
+    pub id: i64,
+    pub description: Option<String>,
+    #[serde(rename = "createdBy")]
+    pub created_by: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    pub changes: Vec<ConfigChange>,
+}
+
+impl From<config_revision_entity::Model> for ConfigRevisionItem {
+    fn from(revision: config_revision_entity::Model) -> Self {
+        let changes = config_revision::parse_changes(&revision)
+            .iter()
+            .map(ConfigChange::redacted)
+            .collect();
+        Self {
+            id: revision.id,
+            description: revision.description,
+            created_by: revision.created_by,
+            created_at: revision.created_at.to_string(),
+            changes,
+        }
     }
+}
 
-    tracing::info!("管理员 {} 请求重启系统", auth_user.username);
+/// 配置回滚结果
+#[derive(Debug, Serialize)]
+pub struct ConfigRollbackResponse {
+    pub revision: ConfigRevisionItem,
+    /// 回滚应用的变更
+    pub changes: Vec<ConfigChange>,
+}
 
-    // 获取当前可执行文件路径
-    let exe_path = match std::env::current_exe() {
-        Ok(path) => path,
-        Err(e) => {
-            tracing::error!("无法获取可执行文件路径: {}", e);
-            return ApiResponse::error("无法获取可执行文件路径".to_string());
+/// GET /api/system/configs/revisions - 获取配置修订历史（仅管理员）
+pub async fn list_config_revisions(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Query(list_query): Query<ListQuery>,
+) -> (StatusCode, Json<ApiResponse<Vec<ConfigRevisionItem>>>) {
+    match auth_user {
+        Some(user) if user.is_admin => {}
+        Some(_) => return (StatusCode::FORBIDDEN, ApiResponse::error("权限不足".to_string())),
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::error("未登录，请先登录".to_string())),
+    }
+
+    let db = get_connection().await;
+    let select = ConfigRevision::find().order_by_desc(config_revision_entity::Column::Id);
+    // 修订历史按 ID 降序，不支持游标分页
+    let list_query = ListQuery { after: None, ..list_query };
+
+    match fetch_page(select, &list_query, db).await {
+        Ok((revisions, total)) => {
+            let items = revisions.into_iter().map(ConfigRevisionItem::from).collect();
+            (StatusCode::OK, ApiResponse::paginated(items, total))
         }
-    };
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("获取配置修订失败: {}", e)),
+        ),
+    }
+}
 
-    // 获取当前工作目录
-    let current_dir = std::env::current_dir().ok();
+/// POST /api/system/configs/rollback/{rev} - 将系统配置回滚到指定修订（仅管理员）
+pub async fn rollback_configs(
+    Path(revision_id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+) -> (StatusCode, Json<ApiResponse<ConfigRollbackResponse>>) {
+    let auth_user = match auth_user {
+        Some(user) if user.is_admin => user,
+        Some(_) => return (StatusCode::FORBIDDEN, ApiResponse::error("权限不足".to_string())),
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::error("未登录，请先登录".to_string())),
+    };
 
-    // 在后台线程中延迟重启
-    tokio::spawn(async move {
-        // 等待 300ms 让响应先发送
-        tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
-        tracing::info!("系统正在重启...");
+    let db = get_connection().await;
+    match config_revision::rollback(db, revision_id, Some(auth_user.username.clone())).await {
+        Ok((revision, changes)) => {
+            tracing::info!(
+                "管理员 {} 将系统配置回滚到修订 #{}（{} 项变更）",
+                auth_user.username,
+                revision_id,
+                changes.len()
+            );
 
-        // 构建启动命令，使用延迟启动让当前进程有时间退出并释放端口
-        #[cfg(windows)]
-        {
-            // Windows: 使用 cmd /c 延迟启动
-            let exe_path_str = exe_path.to_string_lossy().to_string();
-            let mut cmd = std::process::Command::new("cmd");
-            cmd.args(["/c", "timeout", "/t", "2", "/nobreak", ">nul", "&&", &exe_path_str]);
-            if let Some(dir) = current_dir {
-                cmd.current_dir(dir);
-            }
-            match cmd.spawn() {
-                Ok(_) => {
-                    tracing::info!("重启命令已发送，当前进程即将退出");
-                }
-                Err(e) => {
-                    tracing::error!("启动重启命令失败: {}", e);
-                }
+            if let Err(e) = app_state.config_manager.reload().await {
+                tracing::error!("重新加载配置缓存失败: {}", e);
             }
+
+            let changes = changes.iter().map(ConfigChange::redacted).collect();
+            (
+                StatusCode::OK,
+                ApiResponse::success(ConfigRollbackResponse {
+                    revision: revision.into(),
+                    changes,
+                }),
+            )
         }
+        Err(e) => (StatusCode::BAD_REQUEST, ApiResponse::error(e.to_string())),
+    }
+}
 
-        #[cfg(not(windows))]
-        {
-            // Linux/Unix: 使用 sh -c 延迟启动
-            let exe_path_str = exe_path.to_string_lossy().to_string();
-            let mut cmd = std::process::Command::new("sh");
-            cmd.args(["-c", &format!("sleep 2 && {}", exe_path_str)]);
-            if let Some(dir) = current_dir {
-                cmd.current_dir(dir);
-            }
-            match cmd.spawn() {
-                Ok(_) => {
-                    tracing::info!("重启命令已发送，当前进程即将退出");
-                }
-                Err(e) => {
-                    tracing::error!("启动重启命令失败: {}", e);
-                }
-            }
+#[derive(Debug, Deserialize, Default)]
+pub struct RestartRequest {
+    /// 重启原因，记录在审计日志中
+    pub reason: Option<String>,
+}
+
+/// 重启系统响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestartResponse {
+    pub message: String,
+    /// 重启后生效的配置项
+    pub pending_changes: Vec<String>,
+    /// 收到重启通知的节点 / 客户端数
+    pub notified_nodes: usize,
+    pub notified_clients: usize,
+}
+
+/// 重启状态
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestartStatusResponse {
+    /// 本次启动的标识，重启完成后变化
+    pub boot_id: String,
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// 已请求重启，正在排空
+    pub restarting: bool,
+    /// 修改后尚未生效、需要重启的配置项
+    pub pending_changes: Vec<String>,
+    /// 最近一次通过 API 发起的重启
+    pub last_restart: Option<crate::entity::system_restart::Model>,
+}
+
+/// GET /api/system/restart - 查看重启状态和待生效的配置（仅管理员）
+pub async fn get_restart_status(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+) -> (StatusCode, Json<ApiResponse<RestartStatusResponse>>) {
+    match auth_user {
+        Some(user) if user.is_admin => {}
+        Some(_) => return (StatusCode::FORBIDDEN, ApiResponse::error("权限不足".to_string())),
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::error("未登录，请先登录".to_string())),
+    }
+
+    let db = get_connection().await;
+    let last_restart = match SystemRestart::find().order_by_desc(system_restart::Column::Id).one(db).await {
+        Ok(record) => record,
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("查询重启记录失败: {}", e)));
         }
+    };
+
+    (
+        StatusCode::OK,
+        ApiResponse::success(RestartStatusResponse {
+            boot_id: crate::restart::boot_id().to_string(),
+            started_at: crate::restart::started_at(),
+            restarting: crate::restart::is_requested(),
+            pending_changes: crate::restart::pending_changes(&app_state.config_manager).await,
+            last_restart,
+        }),
+    )
+}
 
-        // 退出当前进程
-        std::process::exit(0);
-    });
+/// POST /api/system/restart - 重启系统（仅管理员，需二次验证）
+///
+/// 先通知已连接的节点和客户端错开重连并记录审计日志，然后排空并原地重启，
+/// 见 [`crate::restart`]。
+pub async fn restart_system(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    req: Option<Json<RestartRequest>>,
+) -> (StatusCode, Json<ApiResponse<RestartResponse>>) {
+    let auth_user = match auth_user {
+        Some(user) => user,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::error("未登录，请先登录".to_string())),
+    };
+    if !auth_user.is_admin {
+        return (StatusCode::FORBIDDEN, ApiResponse::error("权限不足，仅管理员可以重启系统".to_string()));
+    }
+    if crate::restart::is_requested() {
+        return (StatusCode::CONFLICT, ApiResponse::error("系统正在重启".to_string()));
+    }
+
+    let reason = req.and_then(|Json(r)| r.reason).map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+    let pending_changes = crate::restart::pending_changes(&app_state.config_manager).await;
+
+    // 先通知节点和客户端：断线后错开重连，避免新进程启动时同时涌入
+    let spread = crate::restart::reconnect_spread();
+    let notified_nodes = app_state.node_manager.broadcast_reconnect_hint(spread).await;
+    let notified_clients = app_state.client_stream_manager.broadcast_reconnect_hint(spread).await;
+
+    let db = get_connection().await;
+    let record = system_restart::ActiveModel {
+        id: sea_orm::NotSet,
+        user_id: Set(auth_user.id),
+        username: Set(auth_user.username.clone()),
+        reason: Set(reason.clone()),
+        pending_changes: Set(serde_json::to_string(&pending_changes).unwrap_or_default()),
+        connected_nodes: Set(notified_nodes as i32),
+        connected_clients: Set(notified_clients as i32),
+        requested_at: Set(chrono::Utc::now().naive_utc()),
+        completed_at: Set(None),
+    };
+    if let Err(e) = record.insert(db).await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("记录重启审计日志失败: {}", e)));
+    }
+
+    if !crate::restart::request() {
+        return (StatusCode::CONFLICT, ApiResponse::error("系统正在重启".to_string()));
+    }
+    app_state.health.set_draining(true);
+    tracing::warn!(
+        "管理员 {} 请求重启系统（原因: {}，待生效配置: {:?}，已通知 {} 个节点、{} 个客户端）",
+        auth_user.username,
+        reason.as_deref().unwrap_or("-"),
+        pending_changes,
+        notified_nodes,
+        notified_clients
+    );
 
-    ApiResponse::success(RestartResponse {
-        message: "系统将在 2 秒后重启".to_string(),
-    })
+    (
+        StatusCode::OK,
+        ApiResponse::success(RestartResponse {
+            message: "系统正在排空，即将重启".to_string(),
+            pending_changes,
+            notified_nodes,
+            notified_clients,
+        }),
+    )
 }
@@ -0,0 +1,210 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::Deserialize;
+
+use crate::entity::{proxy, temporary_tunnel, Client, Node, Proxy, TemporaryTunnel};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use crate::temporary_tunnel::{self as tunnel, OpenRequest, DEFAULT_TTL_SECS, MAX_TTL_SECS, MIN_TTL_SECS};
+use crate::AppState;
+use super::ApiResponse;
+
+#[derive(Deserialize)]
+pub struct OpenTemporaryTunnelRequest {
+    #[serde(rename = "localIP")]
+    pub local_ip: Option<String>,
+    #[serde(rename = "localPort")]
+    pub local_port: u16,
+    /// 有效期（秒），默认 1 小时
+    #[serde(rename = "ttlSecs")]
+    pub ttl_secs: Option<u64>,
+    /// 为空时使用客户端已有代理所在的节点
+    #[serde(rename = "nodeId")]
+    pub node_id: Option<i64>,
+    pub reason: Option<String>,
+}
+
+/// 临时隧道可以访问任意客户端的内网端口，仅平台管理员可用
+fn require_platform_admin(auth_user: Option<AuthUser>) -> Result<AuthUser, (StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+    match auth_user {
+        Some(user) if user.is_admin && user.tenant_id.is_none() => Ok(user),
+        Some(_) => Err((StatusCode::FORBIDDEN, ApiResponse::error("仅平台管理员".to_string()))),
+        None => Err((StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()))),
+    }
+}
+
+/// POST /api/clients/{id}/temporary-tunnel - 为客户端的本地端口开启临时隧道
+pub async fn open_temporary_tunnel(
+    Path(client_id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<OpenTemporaryTunnelRequest>,
+) -> impl IntoResponse {
+    let auth_user = match require_platform_admin(auth_user) {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let ttl_secs = req.ttl_secs.unwrap_or(DEFAULT_TTL_SECS);
+    if !(MIN_TTL_SECS..=MAX_TTL_SECS).contains(&ttl_secs) {
+        return (
+            StatusCode::BAD_REQUEST,
+            ApiResponse::error(format!("有效期必须在 {} 到 {} 秒之间", MIN_TTL_SECS, MAX_TTL_SECS)),
+        );
+    }
+    if req.local_port == 0 {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("本地端口无效".to_string()));
+    }
+    let local_ip = common::utils::normalize_host(req.local_ip.as_deref().unwrap_or("127.0.0.1")).to_string();
+
+    let db = get_connection().await;
+    match Client::find_by_id(client_id).one(db).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("客户端不存在".to_string())),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询客户端失败: {}", e)),
+            )
+        }
+    }
+
+    // 未指定节点时使用客户端已有代理所在的节点
+    let node_id = match req.node_id {
+        Some(id) => id,
+        None => {
+            let node_id: Option<Option<i64>> = match Proxy::find()
+                .select_only()
+                .column(proxy::Column::NodeId)
+                .filter(proxy::Column::ClientId.eq(client_id.to_string()))
+                .filter(proxy::Column::Enabled.eq(true))
+                .filter(proxy::Column::NodeId.is_not_null())
+                .into_tuple()
+                .one(db)
+                .await
+            {
+                Ok(n) => n,
+                Err(e) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        ApiResponse::error(format!("查询客户端代理失败: {}", e)),
+                    )
+                }
+            };
+            match node_id.flatten() {
+                Some(id) => id,
+                None => return (StatusCode::BAD_REQUEST, ApiResponse::error("客户端没有可用的节点，请指定 nodeId".to_string())),
+            }
+        }
+    };
+    match Node::find_by_id(node_id).one(db).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("节点不存在".to_string())),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询节点失败: {}", e)),
+            )
+        }
+    }
+
+    // 本地目标同样受端口黑名单约束（远程端口随机分配，仅检查本地目标）
+    let tenant_id = match crate::port_blocklist::client_tenant_id(&client_id.to_string(), db).await {
+        Ok(t) => t,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询客户端租户失败: {}", e)),
+            )
+        }
+    };
+    match crate::port_blocklist::validate_local_target(Some(node_id), tenant_id, &local_ip, req.local_port, db).await {
+        Ok((true, _)) => {}
+        Ok((false, reason)) => return (StatusCode::FORBIDDEN, ApiResponse::error(reason)),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("验证端口黑名单失败: {}", e)),
+            )
+        }
+    }
+
+    let open_req = OpenRequest {
+        client_id,
+        node_id,
+        local_ip,
+        local_port: req.local_port,
+        ttl_secs,
+        reason: req.reason,
+        created_by: auth_user.username,
+    };
+    match tunnel::open(&app_state.node_manager, &app_state.client_stream_manager, open_req).await {
+        Ok(t) => (StatusCode::OK, ApiResponse::success(serde_json::json!(t))),
+        Err(e) => (
+            StatusCode::CONFLICT,
+            ApiResponse::error(format!("开启临时隧道失败: {}", e)),
+        ),
+    }
+}
+
+/// GET /api/temporary-tunnels - 临时隧道记录（含已关闭的，用于审计）
+pub async fn list_temporary_tunnels(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    match TemporaryTunnel::find()
+        .order_by_desc(temporary_tunnel::Column::Id)
+        .limit(200)
+        .all(db)
+        .await
+    {
+        Ok(tunnels) => (StatusCode::OK, ApiResponse::success(serde_json::json!(tunnels))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("查询临时隧道失败: {}", e)),
+        ),
+    }
+}
+
+/// DELETE /api/temporary-tunnels/{id} - 提前关闭临时隧道
+pub async fn close_temporary_tunnel(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+) -> impl IntoResponse {
+    let auth_user = match require_platform_admin(auth_user) {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let db = get_connection().await;
+    let t = match TemporaryTunnel::find_by_id(id).one(db).await {
+        Ok(Some(t)) => t,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("临时隧道不存在".to_string())),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询临时隧道失败: {}", e)),
+            )
+        }
+    };
+    if t.closed_at.is_some() {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("临时隧道已关闭".to_string()));
+    }
+
+    match tunnel::close(&app_state.node_manager, &app_state.client_stream_manager, t, Some(auth_user.username)).await {
+        Ok(t) => (StatusCode::OK, ApiResponse::success(serde_json::json!(t))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("关闭临时隧道失败: {}", e)),
+        ),
+    }
+}
@@ -0,0 +1,256 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, PaginatorTrait, QueryFilter, QueryOrder, Set};
+use serde::{Deserialize, Serialize};
+
+use crate::entity::{node, tenant, user, Node, Tenant, User};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use super::ApiResponse;
+
+#[derive(Deserialize)]
+pub struct CreateTenantRequest {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(rename = "maxUserCount")]
+    pub max_user_count: Option<i32>,
+    #[serde(rename = "maxClientCount")]
+    pub max_client_count: Option<i32>,
+    #[serde(rename = "trafficQuotaGb")]
+    pub traffic_quota_gb: Option<f64>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateTenantRequest {
+    pub name: Option<String>,
+    pub description: Option<Option<String>>,
+    #[serde(rename = "maxUserCount")]
+    pub max_user_count: Option<Option<i32>>,
+    #[serde(rename = "maxClientCount")]
+    pub max_client_count: Option<Option<i32>>,
+    #[serde(rename = "trafficQuotaGb")]
+    pub traffic_quota_gb: Option<Option<f64>>,
+}
+
+#[derive(Serialize)]
+pub struct TenantWithUsage {
+    #[serde(flatten)]
+    pub tenant: tenant::Model,
+    #[serde(rename = "userCount")]
+    pub user_count: u64,
+    #[serde(rename = "nodeCount")]
+    pub node_count: u64,
+}
+
+/// 租户只能由平台管理员管理
+fn require_platform_admin(auth_user: Option<AuthUser>) -> Result<AuthUser, (StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+    match auth_user {
+        Some(user) if user.is_admin && user.tenant_id.is_none() => Ok(user),
+        Some(_) => Err((StatusCode::FORBIDDEN, ApiResponse::error("仅平台管理员".to_string()))),
+        None => Err((StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()))),
+    }
+}
+
+/// GET /api/tenants - 获取租户列表及用户数、节点数
+pub async fn list_tenants(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    let tenants = match Tenant::find().order_by_asc(tenant::Column::Id).all(db).await {
+        Ok(t) => t,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询租户失败: {}", e)),
+            )
+        }
+    };
+
+    let mut result = Vec::with_capacity(tenants.len());
+    for tenant in tenants {
+        let user_count = User::find()
+            .filter(user::Column::TenantId.eq(tenant.id))
+            .count(db)
+            .await
+            .unwrap_or(0);
+        let node_count = Node::find()
+            .filter(node::Column::TenantId.eq(tenant.id))
+            .count(db)
+            .await
+            .unwrap_or(0);
+        result.push(TenantWithUsage { tenant, user_count, node_count });
+    }
+
+    (StatusCode::OK, ApiResponse::success(serde_json::json!(result)))
+}
+
+/// POST /api/tenants - 创建租户
+pub async fn create_tenant(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Json(req): Json<CreateTenantRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let name = req.name.trim().to_string();
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("租户名称不能为空".to_string()));
+    }
+
+    let db = get_connection().await;
+    match Tenant::find().filter(tenant::Column::Name.eq(&name)).one(db).await {
+        Ok(Some(_)) => return (StatusCode::CONFLICT, ApiResponse::error("租户名称已存在".to_string())),
+        Ok(None) => {}
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询租户失败: {}", e)),
+            )
+        }
+    }
+
+    let now = Utc::now().naive_utc();
+    let new_tenant = tenant::ActiveModel {
+        id: NotSet,
+        name: Set(name),
+        description: Set(req.description),
+        max_user_count: Set(req.max_user_count),
+        max_client_count: Set(req.max_client_count),
+        traffic_quota_gb: Set(req.traffic_quota_gb),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    match new_tenant.insert(db).await {
+        Ok(tenant) => (StatusCode::OK, ApiResponse::success(serde_json::json!(tenant))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("创建租户失败: {}", e)),
+        ),
+    }
+}
+
+/// PUT /api/tenants/{id} - 更新租户
+pub async fn update_tenant(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Json(req): Json<UpdateTenantRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    let tenant = match Tenant::find_by_id(id).one(db).await {
+        Ok(Some(t)) => t,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("租户不存在".to_string())),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询租户失败: {}", e)),
+            )
+        }
+    };
+
+    let mut active: tenant::ActiveModel = tenant.into();
+    if let Some(name) = req.name {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return (StatusCode::BAD_REQUEST, ApiResponse::error("租户名称不能为空".to_string()));
+        }
+        match Tenant::find()
+            .filter(tenant::Column::Name.eq(&name))
+            .filter(tenant::Column::Id.ne(id))
+            .one(db)
+            .await
+        {
+            Ok(Some(_)) => return (StatusCode::CONFLICT, ApiResponse::error("租户名称已存在".to_string())),
+            Ok(None) => {}
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ApiResponse::error(format!("查询租户失败: {}", e)),
+                )
+            }
+        }
+        active.name = Set(name);
+    }
+    if let Some(description) = req.description {
+        active.description = Set(description);
+    }
+    if let Some(max_user_count) = req.max_user_count {
+        active.max_user_count = Set(max_user_count);
+    }
+    if let Some(max_client_count) = req.max_client_count {
+        active.max_client_count = Set(max_client_count);
+    }
+    if let Some(traffic_quota_gb) = req.traffic_quota_gb {
+        active.traffic_quota_gb = Set(traffic_quota_gb);
+    }
+    active.updated_at = Set(Utc::now().naive_utc());
+
+    match active.update(db).await {
+        Ok(tenant) => (StatusCode::OK, ApiResponse::success(serde_json::json!(tenant))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("更新租户失败: {}", e)),
+        ),
+    }
+}
+
+/// DELETE /api/tenants/{id} - 删除租户（租户内仍有用户时拒绝删除，分配给租户的节点恢复为公共节点）
+pub async fn delete_tenant(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_platform_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    match User::find().filter(user::Column::TenantId.eq(id)).count(db).await {
+        Ok(0) => {}
+        Ok(count) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                ApiResponse::error(format!("租户内还有 {} 个用户，请先移除或删除这些用户", count)),
+            )
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询租户用户失败: {}", e)),
+            )
+        }
+    }
+
+    if let Err(e) = Node::update_many()
+        .col_expr(node::Column::TenantId, sea_orm::sea_query::Expr::value(Option::<i64>::None))
+        .filter(node::Column::TenantId.eq(id))
+        .exec(db)
+        .await
+    {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("释放租户节点失败: {}", e)),
+        );
+    }
+
+    match Tenant::delete_by_id(id).exec(db).await {
+        Ok(result) if result.rows_affected > 0 => (StatusCode::OK, ApiResponse::success(serde_json::json!(null))),
+        Ok(_) => (StatusCode::NOT_FOUND, ApiResponse::error("租户不存在".to_string())),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("删除租户失败: {}", e)),
+        ),
+    }
+}
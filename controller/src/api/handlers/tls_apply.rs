@@ -0,0 +1,114 @@
+use axum::{
+    extract::Extension,
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use serde::Deserialize;
+
+use crate::middleware::AuthUser;
+use crate::tls_apply::{TlsTarget, DEFAULT_CONFIRM_TIMEOUT_SECS, DEFAULT_PROBE_PORT};
+use crate::AppState;
+use super::ApiResponse;
+
+#[derive(Deserialize)]
+pub struct ApplyTlsRequest {
+    pub target: TlsTarget, // web, grpc
+    /// base64 编码的 PEM 证书链
+    pub cert_content: String,
+    /// base64 编码的 PEM 私钥
+    pub key_content: String,
+    pub confirm_timeout_secs: Option<u64>,
+    pub probe_port: Option<u16>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmTlsRequest {
+    pub token: Option<String>,
+}
+
+fn require_admin(auth_user: Option<AuthUser>) -> Result<AuthUser, (StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+    match auth_user {
+        Some(user) if user.is_admin => Ok(user),
+        Some(_) => Err((StatusCode::FORBIDDEN, ApiResponse::error("仅管理员".to_string()))),
+        None => Err((StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()))),
+    }
+}
+
+/// POST /api/system/tls/apply - 校验并试用新的 TLS 证书，超时未确认自动恢复
+pub async fn apply_tls(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<ApplyTlsRequest>,
+) -> impl IntoResponse {
+    let user = match require_admin(auth_user) {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let timeout = req.confirm_timeout_secs.unwrap_or(DEFAULT_CONFIRM_TIMEOUT_SECS).clamp(30, 3600);
+    match app_state
+        .tls_apply
+        .apply(
+            req.target,
+            &req.cert_content,
+            &req.key_content,
+            req.probe_port.unwrap_or(DEFAULT_PROBE_PORT),
+            timeout,
+            Some(user.username),
+        )
+        .await
+    {
+        Ok(pending) => (
+            StatusCode::OK,
+            ApiResponse::success(serde_json::json!({
+                "pending": pending,
+                "confirmPath": format!("/tls-confirm/{}", pending.token),
+            })),
+        ),
+        Err(e) => (StatusCode::BAD_REQUEST, ApiResponse::error(e.to_string())),
+    }
+}
+
+/// GET /api/system/tls/pending - 获取待确认的 TLS 变更
+pub async fn get_pending_tls(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_admin(auth_user) {
+        return resp;
+    }
+
+    let pending = app_state.tls_apply.pending().await;
+    (StatusCode::OK, ApiResponse::success(serde_json::json!(pending)))
+}
+
+/// POST /api/system/tls/confirm - 确认 TLS 变更
+pub async fn confirm_tls(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<ConfirmTlsRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_admin(auth_user) {
+        return resp;
+    }
+
+    match app_state.tls_apply.confirm(req.token.as_deref()).await {
+        Ok(pending) => (StatusCode::OK, ApiResponse::success(serde_json::json!(pending))),
+        Err(e) => (StatusCode::BAD_REQUEST, ApiResponse::error(e.to_string())),
+    }
+}
+
+/// POST /api/system/tls/revert - 撤销 TLS 变更并恢复之前的配置
+pub async fn revert_tls(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_admin(auth_user) {
+        return resp;
+    }
+
+    match app_state.tls_apply.revert(None).await {
+        Ok(pending) => (StatusCode::OK, ApiResponse::success(serde_json::json!(pending))),
+        Err(e) => (StatusCode::BAD_REQUEST, ApiResponse::error(e.to_string())),
+    }
+}
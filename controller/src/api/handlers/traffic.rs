@@ -3,15 +3,22 @@ use axum::{
     http::StatusCode,
     response::IntoResponse,
 };
+use sea_orm::EntityTrait;
 use serde::Deserialize;
 
 use super::ApiResponse;
+use crate::api::access;
+use crate::entity::{user, User};
 use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use crate::tenant::UserScope;
 use crate::traffic::{get_traffic_overview, TrafficOverview};
 
 #[derive(Debug, Deserialize)]
 pub struct TrafficQuery {
     pub days: Option<i64>,
+    /// 客户端和代理只返回流量最高的前 N 个
+    pub top: Option<u64>,
 }
 
 /// 获取流量总览
@@ -32,8 +39,13 @@ pub async fn get_traffic_overview_handler(
 
     let days = params.days.unwrap_or(30);
 
-    // 始终传入 user_id，在 get_traffic_overview 内部判断管理员权限
-    match get_traffic_overview(Some(auth_user.id), days).await {
+    // 平台管理员统计全部，租户管理员统计本租户用户，普通用户只统计自己
+    let user_ids = match access::allowed_user_ids(&auth_user, get_connection().await).await {
+        Ok(ids) => ids,
+        Err((status, e)) => return (status, ApiResponse::error(e)),
+    };
+
+    match get_traffic_overview(user_ids.as_deref(), days, params.top).await {
         Ok(overview) => (StatusCode::OK, ApiResponse::success(overview)),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -59,8 +71,19 @@ pub async fn get_user_traffic_handler(
         }
     };
 
-    // 权限检查：只有管理员或用户本人可以查看
-    if !auth_user.is_admin && auth_user.id != user_id {
+    let target = match User::find_by_id(user_id).one(get_connection().await).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<TrafficOverview>::error("用户不存在".to_string())),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::<TrafficOverview>::error(format!("查询用户失败: {}", e)),
+            )
+        }
+    };
+
+    // 权限检查：平台管理员、本租户的租户管理员或用户本人可以查看
+    if !can_view_user_traffic(&auth_user, &target) {
         return (
             StatusCode::FORBIDDEN,
             ApiResponse::<TrafficOverview>::error("无权查看其他用户的流量统计".to_string()),
@@ -69,7 +92,7 @@ pub async fn get_user_traffic_handler(
 
     let days = params.days.unwrap_or(30);
 
-    match get_traffic_overview(Some(user_id), days).await {
+    match get_traffic_overview(Some(std::slice::from_ref(&user_id)), days, params.top).await {
         Ok(overview) => (StatusCode::OK, ApiResponse::success(overview)),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -77,3 +100,59 @@ pub async fn get_user_traffic_handler(
         ),
     }
 }
+
+fn can_view_user_traffic(auth_user: &AuthUser, target: &user::Model) -> bool {
+    UserScope::of(auth_user).contains(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth(id: i64, is_admin: bool, tenant_id: Option<i64>, is_tenant_admin: bool) -> AuthUser {
+        AuthUser { id, username: format!("user{}", id), is_admin, tenant_id, is_tenant_admin, impersonator: None }
+    }
+
+    fn target(id: i64, tenant_id: Option<i64>) -> user::Model {
+        let now = chrono::Utc::now().naive_utc();
+        user::Model {
+            id,
+            username: format!("user{}", id),
+            password_hash: String::new(),
+            is_admin: false,
+            total_bytes_sent: 0,
+            total_bytes_received: 0,
+            traffic_reset_cycle: "none".into(),
+            last_reset_at: None,
+            is_traffic_exceeded: false,
+            traffic_quota_gb: None,
+            max_port_count: None,
+            allowed_port_range: None,
+            max_node_count: None,
+            max_client_count: None,
+            speed_limit: None,
+            speed_limit_schedule: None,
+            tenant_id,
+            is_tenant_admin: false,
+            display_name: None,
+            email: None,
+            email_verified_at: None,
+            lock_version: 0,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn test_tenant_admin_can_view_tenant_user_traffic() {
+        let tenant_admin = auth(4, false, Some(9), true);
+        assert!(can_view_user_traffic(&tenant_admin, &target(5, Some(9))));
+        assert!(!can_view_user_traffic(&tenant_admin, &target(6, Some(10))));
+        assert!(!can_view_user_traffic(&tenant_admin, &target(7, None)));
+
+        // 普通用户只能查看自己
+        let member = auth(5, false, Some(9), false);
+        assert!(can_view_user_traffic(&member, &target(5, Some(9))));
+        assert!(!can_view_user_traffic(&member, &target(4, Some(9))));
+    }
+}
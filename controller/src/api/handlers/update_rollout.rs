@@ -0,0 +1,171 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::Deserialize;
+
+use crate::entity::{agent_update, update_rollout, AgentUpdate, UpdateRollout};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use crate::AppState;
+use super::ApiResponse;
+
+#[derive(Deserialize)]
+pub struct CreateRolloutRequest {
+    pub agent_type: String, // node, client
+    pub target_version: String,
+    pub percentage: Option<i32>,
+    pub canary_ids: Option<Vec<i64>>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateRolloutRequest {
+    pub percentage: Option<i32>,
+    pub status: Option<String>, // running, paused, cancelled
+}
+
+fn require_admin(auth_user: Option<AuthUser>) -> Result<AuthUser, (StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+    match auth_user {
+        Some(user) if user.is_admin => Ok(user),
+        Some(_) => Err((StatusCode::FORBIDDEN, ApiResponse::error("仅管理员".to_string()))),
+        None => Err((StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()))),
+    }
+}
+
+/// 在后台立即执行一次调和，无需等待下一个周期
+fn trigger_reconcile(app_state: &AppState) {
+    let manager = app_state.update_rollout.clone();
+    tokio::spawn(async move {
+        manager.reconcile_all().await;
+    });
+}
+
+/// GET /api/updates/rollouts - 获取发布计划列表
+pub async fn list_update_rollouts(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    match UpdateRollout::find()
+        .order_by_desc(update_rollout::Column::Id)
+        .all(db)
+        .await
+    {
+        Ok(rollouts) => (StatusCode::OK, ApiResponse::success(serde_json::json!(rollouts))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("查询发布计划失败: {}", e)),
+        ),
+    }
+}
+
+/// GET /api/updates/rollouts/{id} - 获取发布计划详情及各 Agent 更新进度
+pub async fn get_update_rollout(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_admin(auth_user) {
+        return resp;
+    }
+
+    let db = get_connection().await;
+    let rollout = match UpdateRollout::find_by_id(id).one(db).await {
+        Ok(Some(r)) => r,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("发布计划不存在".to_string())),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询发布计划失败: {}", e)),
+            )
+        }
+    };
+
+    let agents = match AgentUpdate::find()
+        .filter(agent_update::Column::RolloutId.eq(id))
+        .order_by_asc(agent_update::Column::AgentId)
+        .all(db)
+        .await
+    {
+        Ok(a) => a,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询更新进度失败: {}", e)),
+            )
+        }
+    };
+
+    let count = |status: &str| agents.iter().filter(|a| a.status == status).count();
+    let summary = serde_json::json!({
+        "succeeded": count("succeeded"),
+        "failed": count("failed"),
+        "inProgress": agents.len() - count("succeeded") - count("failed"),
+    });
+
+    (
+        StatusCode::OK,
+        ApiResponse::success(serde_json::json!({
+            "rollout": rollout,
+            "agents": agents,
+            "summary": summary,
+        })),
+    )
+}
+
+/// POST /api/updates/rollouts - 创建发布计划（先更新金丝雀，再按百分比扩大范围）
+pub async fn create_update_rollout(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<CreateRolloutRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_admin(auth_user) {
+        return resp;
+    }
+
+    let canary_ids = req.canary_ids.unwrap_or_default();
+    match app_state
+        .update_rollout
+        .create_rollout(
+            &req.agent_type,
+            &req.target_version,
+            req.percentage.unwrap_or(0),
+            &canary_ids,
+        )
+        .await
+    {
+        Ok(rollout) => {
+            trigger_reconcile(&app_state);
+            (StatusCode::OK, ApiResponse::success(serde_json::json!(rollout)))
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, ApiResponse::error(e.to_string())),
+    }
+}
+
+/// PUT /api/updates/rollouts/{id} - 扩大发布百分比、暂停/恢复或取消发布计划
+pub async fn update_update_rollout(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<UpdateRolloutRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_admin(auth_user) {
+        return resp;
+    }
+
+    match app_state
+        .update_rollout
+        .update_rollout(id, req.percentage, req.status.as_deref())
+        .await
+    {
+        Ok(rollout) => {
+            trigger_reconcile(&app_state);
+            (StatusCode::OK, ApiResponse::success(serde_json::json!(rollout)))
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, ApiResponse::error(e.to_string())),
+    }
+}
@@ -1,10 +1,11 @@
 use axum::{
-    extract::{Extension, Path},
-    http::StatusCode,
+    extract::{Extension, Path, Query},
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Json},
 };
 use chrono::Utc;
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, PaginatorTrait, QueryFilter, Set};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, PaginatorTrait, QueryFilter, QuerySelect, Set};
+use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -12,15 +13,23 @@ use crate::{
     entity::{User, UserNode, Node},
     migration::get_connection,
     middleware::AuthUser,
+    password_policy::PasswordPolicy,
+    tenant::UserScope,
+    AppState,
 };
 
 use super::ApiResponse;
+use crate::api::pagination::{apply_cursor, apply_sort, fetch_page, ListQuery};
 
 #[derive(Serialize)]
 pub struct UserWithNodeCount {
     pub id: i64,
     pub username: String,
     pub is_admin: bool,
+    #[serde(rename = "tenantId")]
+    pub tenant_id: Option<i64>,
+    #[serde(rename = "isTenantAdmin")]
+    pub is_tenant_admin: bool,
     pub created_at: String,
     pub updated_at: String,
     pub node_count: u64,
@@ -50,6 +59,12 @@ pub struct UserWithNodeCount {
     pub max_client_count: Option<i32>,
     #[serde(rename = "currentClientCount")]
     pub current_client_count: u64,
+    #[serde(rename = "speedLimit")]
+    pub speed_limit: Option<i64>,
+    #[serde(rename = "speedLimitSchedule")]
+    pub speed_limit_schedule: Option<String>,
+    #[serde(rename = "lockVersion")]
+    pub lock_version: i32,
 }
 
 #[derive(Deserialize)]
@@ -62,6 +77,9 @@ pub struct CreateUserRequest {
     pub max_port_count: Option<i32>,
     pub max_node_count: Option<i32>,
     pub max_client_count: Option<i32>,
+    /// 所属租户（仅平台管理员可指定，租户管理员创建的用户固定属于本租户）
+    pub tenant_id: Option<i64>,
+    pub is_tenant_admin: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -76,29 +94,146 @@ pub struct UpdateUserRequest {
     pub allowed_port_range: Option<String>,
     pub max_node_count: Option<i32>,
     pub max_client_count: Option<i32>,
+    /// 每个节点上的总带宽（字节/秒），null 或 0 表示不限速
+    pub speed_limit: Option<Option<i64>>,
+    /// 带宽时间表，空字符串表示取消
+    pub speed_limit_schedule: Option<String>,
+    /// 调整所属租户（仅平台管理员）
+    pub tenant_id: Option<Option<i64>>,
+    pub is_tenant_admin: Option<bool>,
+    /// 读取时的版本号，也可以通过 If-Match 请求头提供
+    #[serde(rename = "lockVersion")]
+    pub lock_version: Option<i32>,
+}
+
+/// 用户列表过滤参数
+#[derive(Debug, Default, Deserialize)]
+pub struct UserListFilter {
+    pub is_admin: Option<bool>,
+    pub traffic_exceeded: Option<bool>,
+    pub tenant_id: Option<i64>,
+}
+
+/// 查找当前用户有权管理的用户，不在范围内时按不存在处理，避免泄露其他租户的用户
+async fn find_managed_user(
+    auth_user: &AuthUser,
+    user_id: i64,
+    db: &sea_orm::DatabaseConnection,
+) -> Result<crate::entity::user::Model, (StatusCode, String)> {
+    let scope = UserScope::of(auth_user);
+    if !scope.can_manage_users() {
+        return Err((StatusCode::FORBIDDEN, "无权限管理用户".to_string()));
+    }
+    match User::find_by_id(user_id).one(db).await {
+        Ok(Some(user)) if scope.contains(&user) => Ok(user),
+        Ok(_) => Err((StatusCode::NOT_FOUND, "User not found".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to find user: {}", e))),
+    }
+}
+
+/// 按 user_id 分组计数，查询失败时视为全部为 0
+async fn count_by_user<E>(
+    select: sea_orm::Select<E>,
+    user_id: E::Column,
+    user_ids: &[i64],
+    db: &sea_orm::DatabaseConnection,
+) -> HashMap<i64, u64>
+where
+    E: EntityTrait,
+{
+    if user_ids.is_empty() {
+        return HashMap::new();
+    }
+    select
+        .select_only()
+        .column(user_id)
+        .column_as(sea_orm::sea_query::Expr::col(user_id).count(), "count")
+        .filter(user_id.is_in(user_ids.iter().copied()))
+        .group_by(user_id)
+        .into_tuple::<(i64, i64)>()
+        .all(db)
+        .await
+        .map(|rows| rows.into_iter().map(|(id, count)| (id, count as u64)).collect())
+        .unwrap_or_default()
 }
 
 /// GET /api/users - Get all users (admin only)
-pub async fn list_users(Extension(auth_user_opt): Extension<Option<AuthUser>>) -> impl IntoResponse {
-    let _auth_user = match auth_user_opt {
+pub async fn list_users(
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
+    Query(list_query): Query<ListQuery>,
+    Query(filter): Query<UserListFilter>,
+) -> impl IntoResponse {
+    let auth_user = match auth_user_opt {
         Some(user) => user,
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<Vec<UserWithNodeCount>>::error("Not authenticated".to_string())),
     };
     let db = get_connection().await;
 
-    match User::find().all(db).await {
-        Ok(users) => {
-            // Count nodes for each user
+    let mut select = User::find();
+    match UserScope::of(&auth_user) {
+        UserScope::All => {
+            if let Some(tenant_id) = filter.tenant_id {
+                select = select.filter(crate::entity::user::Column::TenantId.eq(tenant_id));
+            }
+        }
+        UserScope::Tenant(tenant_id) => {
+            select = select.filter(crate::entity::user::Column::TenantId.eq(tenant_id));
+        }
+        UserScope::Own(id) => {
+            select = select.filter(crate::entity::user::Column::Id.eq(id));
+        }
+    }
+    if let Some(is_admin) = filter.is_admin {
+        select = select.filter(crate::entity::user::Column::IsAdmin.eq(is_admin));
+    }
+    if let Some(exceeded) = filter.traffic_exceeded {
+        select = select.filter(crate::entity::user::Column::IsTrafficExceeded.eq(exceeded));
+    }
+    if let Some(keyword) = list_query.keyword() {
+        select = select.filter(crate::entity::user::Column::Username.contains(keyword));
+    }
+
+    let select = apply_sort(
+        select,
+        &list_query,
+        &[
+            ("id", crate::entity::user::Column::Id),
+            ("username", crate::entity::user::Column::Username),
+            ("is_admin", crate::entity::user::Column::IsAdmin),
+            ("total_bytes_sent", crate::entity::user::Column::TotalBytesSent),
+            ("total_bytes_received", crate::entity::user::Column::TotalBytesReceived),
+            ("created_at", crate::entity::user::Column::CreatedAt),
+            ("updated_at", crate::entity::user::Column::UpdatedAt),
+        ],
+        crate::entity::user::Column::Id,
+    );
+    let select = apply_cursor(select, &list_query, crate::entity::user::Column::Id);
+
+    match fetch_page(select, &list_query, db).await {
+        Ok((users, total)) => {
+            // 当前页用户的节点数、端口数、客户端数各用一次分组查询批量获取
+            let user_ids: Vec<i64> = users.iter().map(|u| u.id).collect();
+            let node_counts = count_by_user(
+                UserNode::find(),
+                crate::entity::user_node::Column::UserId,
+                &user_ids,
+                db,
+            )
+            .await;
+            let client_counts = count_by_user(
+                crate::entity::Client::find(),
+                crate::entity::client::Column::UserId,
+                &user_ids,
+                db,
+            )
+            .await;
+            let port_counts = crate::port_limiter::get_user_port_counts(&user_ids, db)
+                .await
+                .unwrap_or_default();
+
             let mut users_with_count = Vec::new();
             for user in users {
-                let node_count = match UserNode::find()
-                    .filter(crate::entity::user_node::Column::UserId.eq(user.id))
-                    .count(db)
-                    .await
-                {
-                    Ok(count) => count,
-                    Err(_) => 0,
-                };
+                let node_count = node_counts.get(&user.id).copied().unwrap_or(0);
 
                 // 获取最终配额（套餐配额 + 用户直接配额）
                 let (final_traffic_quota_gb, final_max_port_count, final_max_node_count, final_max_client_count) = match crate::subscription_quota::get_user_final_quota(
@@ -122,21 +257,15 @@ pub async fn list_users(Extension(auth_user_opt): Extension<Option<AuthUser>>) -
                     None
                 };
 
-                let current_port_count = crate::port_limiter::get_user_port_count(user.id, db).await.unwrap_or(0);
-
-                let current_client_count = match crate::entity::Client::find()
-                    .filter(crate::entity::client::Column::UserId.eq(user.id))
-                    .count(db)
-                    .await
-                {
-                    Ok(count) => count,
-                    Err(_) => 0,
-                };
+                let current_port_count = port_counts.get(&user.id).copied().unwrap_or(0);
+                let current_client_count = client_counts.get(&user.id).copied().unwrap_or(0);
 
                 users_with_count.push(UserWithNodeCount {
                     id: user.id,
                     username: user.username.clone(),
                     is_admin: user.is_admin,
+                    tenant_id: user.tenant_id,
+                    is_tenant_admin: user.is_tenant_admin,
                     created_at: user.created_at.to_string(),
                     updated_at: user.updated_at.to_string(),
                     node_count,
@@ -153,10 +282,13 @@ pub async fn list_users(Extension(auth_user_opt): Extension<Option<AuthUser>>) -
                     max_node_count: final_max_node_count,
                     max_client_count: final_max_client_count,
                     current_client_count,
+                    speed_limit: user.speed_limit,
+                    speed_limit_schedule: user.speed_limit_schedule.clone(),
+                    lock_version: user.lock_version,
                 });
             }
 
-            (StatusCode::OK, ApiResponse::success(users_with_count))
+            (StatusCode::OK, ApiResponse::paginated(users_with_count, total))
         }
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -168,14 +300,47 @@ pub async fn list_users(Extension(auth_user_opt): Extension<Option<AuthUser>>) -
 /// POST /api/users - Create a new user (admin only)
 pub async fn create_user(
     Extension(auth_user_opt): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
     Json(req): Json<CreateUserRequest>,
 ) -> impl IntoResponse {
-    let _auth_user = match auth_user_opt {
+    let auth_user = match auth_user_opt {
         Some(user) => user,
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Not authenticated".to_string())),
     };
+
+    // 租户管理员创建的用户固定属于本租户，且不能是平台管理员
+    let tenant_id = match UserScope::of(&auth_user) {
+        UserScope::All => req.tenant_id,
+        UserScope::Tenant(tenant_id) => {
+            if req.is_admin.unwrap_or(false) {
+                return (StatusCode::FORBIDDEN, ApiResponse::<serde_json::Value>::error("租户管理员不能创建平台管理员".to_string()));
+            }
+            Some(tenant_id)
+        }
+        UserScope::Own(_) => {
+            return (StatusCode::FORBIDDEN, ApiResponse::<serde_json::Value>::error("无权限管理用户".to_string()));
+        }
+    };
+    if tenant_id.is_some() && req.is_admin.unwrap_or(false) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<serde_json::Value>::error("租户内的用户不能设为平台管理员".to_string()));
+    }
+
     // Check if username already exists
     let db = get_connection().await;
+    if let Some(tenant_id) = tenant_id {
+        match crate::tenant::validate_tenant_user_limit(tenant_id, db).await {
+            Ok((true, _)) => {}
+            Ok((false, reason)) => return (StatusCode::BAD_REQUEST, ApiResponse::<serde_json::Value>::error(reason)),
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<serde_json::Value>::error(format!("检查租户限制失败: {}", e))),
+        }
+        if let Some(quota) = req.traffic_quota_gb.filter(|q| *q > 0.0) {
+            match crate::tenant::validate_tenant_traffic_quota(tenant_id, None, quota, db).await {
+                Ok((true, _)) => {}
+                Ok((false, reason)) => return (StatusCode::BAD_REQUEST, ApiResponse::<serde_json::Value>::error(reason)),
+                Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<serde_json::Value>::error(format!("检查租户配额失败: {}", e))),
+            }
+        }
+    }
     match User::find()
         .filter(crate::entity::user::Column::Username.eq(&req.username))
         .one(db)
@@ -196,7 +361,13 @@ pub async fn create_user(
         }
     };
 
-    // Hash password or generate random one
+    // Hash password or generate random one (generated passwords skip the policy)
+    if let Some(password) = &req.password {
+        let policy = PasswordPolicy::load(&app_state.config_manager).await;
+        if let Err(reason) = policy.check(password, &req.username).await {
+            return (StatusCode::BAD_REQUEST, ApiResponse::<serde_json::Value>::error(reason));
+        }
+    }
     let password = req.password.clone().unwrap_or_else(|| generate_random_password(16));
     let password_hash = match hash_password(&password) {
         Ok(hash) => hash,
@@ -225,6 +396,14 @@ pub async fn create_user(
         allowed_port_range: Set(None),
         max_node_count: Set(Some(req.max_node_count.unwrap_or(0))),
         max_client_count: Set(Some(req.max_client_count.unwrap_or(0))),
+        speed_limit: Set(None),
+        speed_limit_schedule: Set(None),
+        tenant_id: Set(tenant_id),
+        is_tenant_admin: Set(tenant_id.is_some() && req.is_tenant_admin.unwrap_or(false)),
+        display_name: Set(None),
+        email: Set(None),
+        email_verified_at: Set(None),
+        lock_version: Set(0),
         created_at: Set(now),
         updated_at: Set(now),
     };
@@ -241,6 +420,9 @@ pub async fn create_user(
                 "id": user.id,
                 "username": user.username,
                 "is_admin": user.is_admin,
+                "tenantId": user.tenant_id,
+                "isTenantAdmin": user.is_tenant_admin,
+                "lockVersion": user.lock_version,
                 "created_at": user.created_at,
                 "updated_at": user.updated_at,
                 "generated_password": if req.password.is_none() { Some(password) } else { None },
@@ -258,31 +440,67 @@ pub async fn create_user(
 /// PUT /api/users/:id - Update a user (admin only)
 pub async fn update_user(
     Extension(auth_user_opt): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
     Path(id): Path<i64>,
+    headers: HeaderMap,
     Json(req): Json<UpdateUserRequest>,
 ) -> impl IntoResponse {
-    let _auth_user = match auth_user_opt {
+    let auth_user = match auth_user_opt {
         Some(user) => user,
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Not authenticated".to_string())),
     };
+    let if_match = match crate::optimistic_lock::expected_version(&headers, req.lock_version) {
+        Ok(v) => v,
+        Err((status, e)) => return (status, ApiResponse::<serde_json::Value>::error(e)),
+    };
     let db = get_connection().await;
 
     // Find user
-    let user = match User::find_by_id(id).one(db).await {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                ApiResponse::<serde_json::Value>::error("User not found".to_string()),
-            )
+    let user = match find_managed_user(&auth_user, id, db).await {
+        Ok(user) => user,
+        Err((status, msg)) => return (status, ApiResponse::<serde_json::Value>::error(msg)),
+    };
+    let expected_version = if_match.unwrap_or(user.lock_version);
+    if user.lock_version != expected_version {
+        return (
+            StatusCode::CONFLICT,
+            ApiResponse::<serde_json::Value>::error(crate::optimistic_lock::conflict_message(expected_version, user.lock_version)),
+        );
+    }
+
+    // 租户归属和平台管理员标记只能由平台管理员调整
+    let is_platform_admin = UserScope::of(&auth_user) == UserScope::All;
+    if !is_platform_admin && (req.tenant_id.is_some() || req.is_admin.is_some()) {
+        return (StatusCode::FORBIDDEN, ApiResponse::<serde_json::Value>::error("只有平台管理员可以调整租户归属和管理员权限".to_string()));
+    }
+    let tenant_id = req.tenant_id.unwrap_or(user.tenant_id);
+    if tenant_id.is_some() && req.is_admin.unwrap_or(user.is_admin) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<serde_json::Value>::error("租户内的用户不能设为平台管理员".to_string()));
+    }
+    if let Some(tenant_id) = tenant_id {
+        if user.tenant_id != Some(tenant_id) {
+            match crate::tenant::validate_tenant_user_limit(tenant_id, db).await {
+                Ok((true, _)) => {}
+                Ok((false, reason)) => return (StatusCode::BAD_REQUEST, ApiResponse::<serde_json::Value>::error(reason)),
+                Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<serde_json::Value>::error(format!("检查租户限制失败: {}", e))),
+            }
         }
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                ApiResponse::<serde_json::Value>::error(format!("Failed to find user: {}", e)),
-            )
+        if let Some(quota) = req.traffic_quota_gb.filter(|q| *q > 0.0) {
+            match crate::tenant::validate_tenant_traffic_quota(tenant_id, Some(user.id), quota, db).await {
+                Ok((true, _)) => {}
+                Ok((false, reason)) => return (StatusCode::BAD_REQUEST, ApiResponse::<serde_json::Value>::error(reason)),
+                Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<serde_json::Value>::error(format!("检查租户配额失败: {}", e))),
+            }
         }
+    }
+    let is_tenant_admin = tenant_id.is_some() && req.is_tenant_admin.unwrap_or(user.is_tenant_admin);
+    let current_username = user.username.clone();
+    let speed_limit_schedule = match req.speed_limit_schedule.map(|s| crate::bandwidth_schedule::normalize(Some(s))).transpose() {
+        Ok(s) => s,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<serde_json::Value>::error(e)),
     };
+    let speed_limit_changed = req.speed_limit.is_some_and(|l| l != user.speed_limit)
+        || speed_limit_schedule.as_ref().is_some_and(|s| *s != user.speed_limit_schedule);
 
     let mut user: crate::entity::user::ActiveModel = user.into();
 
@@ -319,6 +537,11 @@ pub async fn update_user(
 
     // Update password if provided
     if let Some(password) = &req.password {
+        let policy = PasswordPolicy::load(&app_state.config_manager).await;
+        let username = req.username.as_deref().unwrap_or(&current_username);
+        if let Err(reason) = policy.check(password, username).await {
+            return (StatusCode::BAD_REQUEST, ApiResponse::<serde_json::Value>::error(reason));
+        }
         let password_hash = match hash_password(password) {
             Ok(hash) => hash,
             Err(e) => {
@@ -338,6 +561,8 @@ pub async fn update_user(
     if let Some(is_admin) = req.is_admin {
         user.is_admin = Set(is_admin);
     }
+    user.tenant_id = Set(tenant_id);
+    user.is_tenant_admin = Set(is_tenant_admin);
 
     // Update traffic limits if provided
     if req.traffic_quota_gb.is_some() || req.traffic_quota_gb.is_none() {
@@ -384,20 +609,41 @@ pub async fn update_user(
         user.max_client_count = Set(Some(max_count));
     }
 
+    // 带宽限制
+    if let Some(speed_limit) = req.speed_limit {
+        user.speed_limit = Set(speed_limit);
+    }
+    if let Some(speed_limit_schedule) = speed_limit_schedule {
+        user.speed_limit_schedule = Set(speed_limit_schedule);
+    }
+
+    user.lock_version = Set(expected_version + 1);
     user.updated_at = Set(Utc::now().naive_utc());
 
-    match user.update(db).await {
+    match User::update(user).filter(crate::entity::user::Column::LockVersion.eq(expected_version)).exec(db).await {
         Ok(updated) => {
+            // 用户的客户端可能连接任意节点，推送到所有在线节点
+            if speed_limit_changed {
+                crate::bandwidth_schedule::push_now(&app_state.node_manager, None).await;
+            }
+
             let user_response = serde_json::json!({
                 "id": updated.id,
                 "username": updated.username,
                 "is_admin": updated.is_admin,
+                "tenantId": updated.tenant_id,
+                "isTenantAdmin": updated.is_tenant_admin,
+                "lockVersion": updated.lock_version,
                 "created_at": updated.created_at,
                 "updated_at": updated.updated_at,
             });
 
             (StatusCode::OK, ApiResponse::<serde_json::Value>::success(user_response))
         }
+        Err(e) if crate::optimistic_lock::is_conflict(&e) => (
+            StatusCode::CONFLICT,
+            ApiResponse::<serde_json::Value>::error("数据已被他人修改，请刷新后重试".to_string()),
+        ),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             ApiResponse::<serde_json::Value>::error(format!("Failed to update user: {}", e)),
@@ -407,12 +653,16 @@ pub async fn update_user(
 
 /// DELETE /api/users/:id - Delete a user (admin only)
 pub async fn delete_user(Extension(auth_user_opt): Extension<Option<AuthUser>>, Path(id): Path<i64>) -> impl IntoResponse {
-    let _auth_user = match auth_user_opt {
+    let auth_user = match auth_user_opt {
         Some(user) => user,
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<&str>::error("Not authenticated".to_string())),
     };
     let db = get_connection().await;
 
+    if let Err((status, msg)) = find_managed_user(&auth_user, id, db).await {
+        return (status, ApiResponse::<&str>::error(msg));
+    }
+
     match User::delete_by_id(id).exec(db).await {
         Ok(_) => (StatusCode::OK, ApiResponse::success("User deleted successfully")),
         Err(e) => (
@@ -424,12 +674,18 @@ pub async fn delete_user(Extension(auth_user_opt): Extension<Option<AuthUser>>,
 
 /// GET /api/users/:id/nodes - Get user's node list (admin only)
 pub async fn get_user_nodes(Extension(auth_user_opt): Extension<Option<AuthUser>>, Path(user_id): Path<i64>) -> impl IntoResponse {
-    let _auth_user = match auth_user_opt {
+    let auth_user = match auth_user_opt {
         Some(user) => user,
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<Vec<crate::entity::node::Model>>::error("Not authenticated".to_string())),
     };
     let db = get_connection().await;
 
+    match User::find_by_id(user_id).one(db).await {
+        Ok(Some(user)) if UserScope::of(&auth_user).contains(&user) => {}
+        Ok(_) => return (StatusCode::NOT_FOUND, ApiResponse::<Vec<crate::entity::node::Model>>::error("User not found".to_string())),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<Vec<crate::entity::node::Model>>::error(format!("Failed to find user: {}", e))),
+    }
+
     match UserNode::find()
         .filter(crate::entity::user_node::Column::UserId.eq(user_id))
         .find_also_related(crate::entity::Node)
@@ -459,27 +715,16 @@ pub async fn assign_node_to_user(
     Extension(auth_user_opt): Extension<Option<AuthUser>>,
     Path((user_id, node_id)): Path<(i64, i64)>,
 ) -> impl IntoResponse {
-    let _auth_user = match auth_user_opt {
+    let auth_user = match auth_user_opt {
         Some(user) => user,
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<&str>::error("Not authenticated".to_string())),
     };
     let db = get_connection().await;
 
     // Check if user exists
-    let user_model = match User::find_by_id(user_id).one(db).await {
-        Ok(Some(u)) => u,
-        Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                ApiResponse::<&str>::error("User not found".to_string()),
-            )
-        }
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                ApiResponse::<&str>::error(format!("Failed to find user: {}", e)),
-            )
-        }
+    let user_model = match find_managed_user(&auth_user, user_id, db).await {
+        Ok(u) => u,
+        Err((status, msg)) => return (status, ApiResponse::<&str>::error(msg)),
     };
 
     // Check if node exists and is dedicated
@@ -499,6 +744,14 @@ pub async fn assign_node_to_user(
         }
     };
 
+    // 分配给其他租户的节点不可见
+    if !crate::tenant::node_visible_to(&node, user_model.tenant_id) {
+        return (
+            StatusCode::NOT_FOUND,
+            ApiResponse::<&str>::error("Node not found".to_string()),
+        );
+    }
+
     // Only dedicated nodes can be assigned to users
     if node.node_type == "shared" {
         return (
@@ -582,12 +835,16 @@ pub async fn remove_node_from_user(
     Extension(auth_user_opt): Extension<Option<AuthUser>>,
     Path((user_id, node_id)): Path<(i64, i64)>,
 ) -> impl IntoResponse {
-    let _auth_user = match auth_user_opt {
+    let auth_user = match auth_user_opt {
         Some(user) => user,
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<&str>::error("Not authenticated".to_string())),
     };
     let db = get_connection().await;
 
+    if let Err((status, msg)) = find_managed_user(&auth_user, user_id, db).await {
+        return (status, ApiResponse::<&str>::error(msg));
+    }
+
     match UserNode::delete_many()
         .filter(crate::entity::user_node::Column::UserId.eq(user_id))
         .filter(crate::entity::user_node::Column::NodeId.eq(node_id))
@@ -630,73 +887,31 @@ pub async fn adjust_user_quota(
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<String>::error("未认证".to_string())),
     };
 
-    if !auth_user.is_admin {
+    if !UserScope::of(&auth_user).can_manage_users() {
         return (StatusCode::FORBIDDEN, ApiResponse::<String>::error("只有管理员可以调整用户配额".to_string()));
     }
 
     let db = get_connection().await;
 
     // 查找用户
-    let user = match User::find_by_id(user_id).one(db).await {
-        Ok(Some(u)) => u,
-        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<String>::error("用户不存在".to_string())),
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<String>::error(format!("查询用户失败: {}", e))),
+    let user = match find_managed_user(&auth_user, user_id, db).await {
+        Ok(u) => u,
+        Err((status, msg)) => return (status, ApiResponse::<String>::error(msg)),
     };
 
-    // 计算新配额
-    let current_quota = user.traffic_quota_gb.unwrap_or(0.0);
-    let new_quota = current_quota + req.quota_change_gb;
-
-    if new_quota < 0.0 {
-        return (StatusCode::BAD_REQUEST, ApiResponse::<String>::error("配额不能为负数".to_string()));
-    }
-
-    // 如果是减少配额，需要检查是否会影响已分配的客户端配额
-    if req.quota_change_gb < 0.0 {
-        use crate::entity::{user_client, UserClient, Client};
-
-        // 计算用户已使用的流量
-        let user_used_gb = crate::traffic_limiter::bytes_to_gb(user.total_bytes_sent + user.total_bytes_received);
-
-        // 查询用户所有客户端已分配的配额总和
-        let user_clients = match UserClient::find()
-            .filter(user_client::Column::UserId.eq(user_id))
-            .all(db)
-            .await
-        {
-            Ok(uc) => uc,
-            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<String>::error(format!("查询客户端失败: {}", e))),
-        };
-
-        let mut total_allocated_gb = 0.0;
-        for uc in user_clients {
-            if let Ok(Some(client)) = Client::find_by_id(uc.client_id).one(db).await {
-                if let Some(quota) = client.traffic_quota_gb {
-                    total_allocated_gb += quota;
-                }
-            }
-        }
-
-        // 检查新配额是否足够覆盖已使用和已分配的配额
-        if new_quota < user_used_gb + total_allocated_gb {
-            let reason = format!(
-                "配额不足: 新配额 {:.2} GB 小于已使用 {:.2} GB + 已分配 {:.2} GB = {:.2} GB",
-                new_quota,
-                user_used_gb,
-                total_allocated_gb,
-                user_used_gb + total_allocated_gb
-            );
-            return (StatusCode::BAD_REQUEST, ApiResponse::<String>::error(reason));
+    // 增加配额时检查租户流量总配额
+    if let (Some(tenant_id), true) = (user.tenant_id, req.quota_change_gb > 0.0) {
+        let new_quota = user.traffic_quota_gb.unwrap_or(0.0) + req.quota_change_gb;
+        match crate::tenant::validate_tenant_traffic_quota(tenant_id, Some(user.id), new_quota, db).await {
+            Ok((true, _)) => {}
+            Ok((false, reason)) => return (StatusCode::BAD_REQUEST, ApiResponse::<String>::error(reason)),
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<String>::error(format!("检查租户配额失败: {}", e))),
         }
     }
 
-    // 更新用户配额
-    let mut user_active: crate::entity::user::ActiveModel = user.into();
-    user_active.traffic_quota_gb = Set(Some(new_quota));
-    user_active.updated_at = Set(Utc::now().naive_utc());
-
-    match user_active.update(db).await {
-        Ok(_) => {
+    // 减少配额时新配额需覆盖已使用和已分配给客户端的配额
+    match crate::quota::adjust_user_quota(db, user.id, req.quota_change_gb).await {
+        Ok(new_quota) => {
             let message = if req.quota_change_gb > 0.0 {
                 format!("配额增加成功: +{:.2} GB，当前配额: {:.2} GB", req.quota_change_gb, new_quota)
             } else {
@@ -704,6 +919,8 @@ pub async fn adjust_user_quota(
             };
             (StatusCode::OK, ApiResponse::success(message))
         }
+        Err(e @ crate::quota::QuotaError::NotFound(_)) => (StatusCode::NOT_FOUND, ApiResponse::<String>::error(e.to_string())),
+        Err(e @ crate::quota::QuotaError::Rejected(_)) => (StatusCode::BAD_REQUEST, ApiResponse::<String>::error(e.to_string())),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<String>::error(format!("更新配额失败: {}", e))),
     }
 }
@@ -729,77 +946,61 @@ pub async fn get_user_quota_info(
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<UserQuotaInfo>::error("未认证".to_string())),
     };
 
-    // 非管理员只能查看自己的配额信息
-    if !auth_user.is_admin && auth_user.id != user_id {
-        return (StatusCode::FORBIDDEN, ApiResponse::<UserQuotaInfo>::error("无权限查看此用户配额".to_string()));
-    }
-
     let db = get_connection().await;
 
+    // 普通用户只能查看自己的配额信息，租户管理员可以查看本租户用户
     let user = match User::find_by_id(user_id).one(db).await {
-        Ok(Some(u)) => u,
+        Ok(Some(u)) if UserScope::of(&auth_user).contains(&u) => u,
+        Ok(Some(_)) => return (StatusCode::FORBIDDEN, ApiResponse::<UserQuotaInfo>::error("无权限查看此用户配额".to_string())),
         Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<UserQuotaInfo>::error("用户不存在".to_string())),
         Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<UserQuotaInfo>::error(format!("查询失败: {}", e))),
     };
 
-    let used_gb = crate::traffic_limiter::bytes_to_gb(user.total_bytes_sent + user.total_bytes_received);
-
-    // 计算已分配给客户端的配额
-    use crate::entity::{user_client, UserClient, Client};
-
-    let user_clients = match UserClient::find()
-        .filter(user_client::Column::UserId.eq(user_id))
-        .all(db)
-        .await
-    {
-        Ok(uc) => uc,
+    // 已使用流量和已分配给客户端的配额
+    let ledger = match crate::quota::ledger(db, &user).await {
+        Ok(ledger) => ledger,
         Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<UserQuotaInfo>::error(format!("查询客户端失败: {}", e))),
     };
 
-    let mut allocated_to_clients_gb = 0.0;
-    for uc in user_clients {
-        if let Ok(Some(client)) = Client::find_by_id(uc.client_id).one(db).await {
-            if let Some(quota) = client.traffic_quota_gb {
-                allocated_to_clients_gb += quota;
-            }
-        }
-    }
-
-    // 获取最终配额（套餐配额 + 用户直接配额）
-    let (final_traffic_quota_gb, _, _, _) = match crate::subscription_quota::get_user_final_quota(
-        user_id,
-        user.traffic_quota_gb,
-        user.max_port_count,
-        user.max_node_count,
-        user.max_client_count,
-        db,
-    ).await {
-        Ok(quota) => quota,
-        Err(_) => (user.traffic_quota_gb, user.max_port_count, user.max_node_count, user.max_client_count),
-    };
-
-    let total_quota_gb = final_traffic_quota_gb;
-    let available_gb = if let Some(quota) = total_quota_gb {
-        (quota - used_gb - allocated_to_clients_gb).max(0.0)
-    } else {
-        f64::INFINITY
-    };
-
-    let quota_usage_percent = if let Some(quota) = total_quota_gb {
-        Some(((used_gb + allocated_to_clients_gb) / quota * 100.0).min(100.0))
-    } else {
-        None
-    };
+    let quota_usage_percent = ledger
+        .quota_gb
+        .map(|quota| ((ledger.used_gb + ledger.allocated_gb) / quota * 100.0).min(100.0));
 
     let info = UserQuotaInfo {
         user_id: user.id,
         username: user.username,
-        total_quota_gb,
-        used_gb,
-        allocated_to_clients_gb,
-        available_gb,
+        total_quota_gb: ledger.quota_gb,
+        used_gb: ledger.used_gb,
+        allocated_to_clients_gb: ledger.allocated_gb,
+        available_gb: ledger.available_gb().unwrap_or(f64::INFINITY),
         quota_usage_percent,
     };
 
     (StatusCode::OK, ApiResponse::success(info))
 }
+
+/// POST /api/users/{id}/password-reset - 管理员向用户已验证的邮箱发送密码重置链接
+///
+/// 管理员无需再为用户设置临时密码，新密码只有用户本人知道。
+pub async fn send_user_password_reset(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<&str>::error("未认证".to_string()));
+    };
+
+    let db = get_connection().await;
+    let user = match find_managed_user(&auth_user, id, db).await {
+        Ok(user) => user,
+        Err((status, msg)) => return (status, ApiResponse::<&str>::error(msg)),
+    };
+    match super::account_recovery::send_password_reset_email(&app_state, &user).await {
+        Ok(()) => {
+            tracing::info!("管理员 '{}' 为用户 '{}' 发送了密码重置链接", auth_user.username, user.username);
+            (StatusCode::OK, ApiResponse::success("重置链接已发送"))
+        }
+        Err(reason) => (StatusCode::BAD_REQUEST, ApiResponse::<&str>::error(reason)),
+    }
+}
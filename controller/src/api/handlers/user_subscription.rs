@@ -11,6 +11,7 @@ use crate::{
     entity::{Subscription, UserSubscription, User},
     migration::get_connection,
     middleware::AuthUser,
+    quota_simulation::{QuotaChange, SimulationReport},
 };
 
 use super::ApiResponse;
@@ -244,6 +245,60 @@ pub async fn get_user_active_subscription(
     }
 }
 
+/// POST /api/users/{user_id}/quota-simulation - 模拟分配套餐或修改配额的影响（管理员）
+///
+/// 只计算不修改：返回变更前后的配额、当前用量、会被超出的限制以及不再符合配额的代理。
+pub async fn simulate_user_quota(
+    Extension(auth_user_opt): Extension<Option<AuthUser>>,
+    Path(user_id): Path<i64>,
+    Json(req): Json<QuotaChange>,
+) -> impl IntoResponse {
+    let auth_user = match auth_user_opt {
+        Some(user) => user,
+        None => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                ApiResponse::<SimulationReport>::error("未认证".to_string()),
+            )
+        }
+    };
+
+    if !auth_user.is_admin {
+        return (
+            StatusCode::FORBIDDEN,
+            ApiResponse::error("需要管理员权限".to_string()),
+        );
+    }
+
+    let db = get_connection().await;
+
+    let user = match User::find_by_id(user_id).one(db).await {
+        Ok(Some(u)) => u,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                ApiResponse::error("用户不存在".to_string()),
+            )
+        }
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiResponse::error(format!("查询用户失败: {}", err)),
+            )
+        }
+    };
+
+    match crate::quota_simulation::simulate(&user, &req, db).await {
+        Ok(report) => (StatusCode::OK, ApiResponse::success(report)),
+        Err(e @ crate::quota::QuotaError::NotFound(_)) => (StatusCode::NOT_FOUND, ApiResponse::error(e.to_string())),
+        Err(e @ crate::quota::QuotaError::Rejected(_)) => (StatusCode::BAD_REQUEST, ApiResponse::error(e.to_string())),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiResponse::error(format!("模拟配额变更失败: {}", e)),
+        ),
+    }
+}
+
 /// POST /api/user-subscriptions - 创建用户订阅（管理员）
 pub async fn create_user_subscription(
     Extension(auth_user_opt): Extension<Option<AuthUser>>,
@@ -36,7 +36,7 @@ struct GitHubRelease {
 }
 
 async fn fetch_latest_release() -> anyhow::Result<CachedRelease> {
-    let client = reqwest::Client::builder()
+    let client = crate::outbound::builder(std::time::Duration::from_secs(10))
         .user_agent("OxiProxy-Controller")
         .build()?;
 
@@ -126,7 +126,7 @@ pub async fn trigger_node_update(
         return (StatusCode::BAD_REQUEST, ApiResponse::<serde_json::Value>::error("节点不在线".to_string()));
     }
 
-    match app_state.node_manager.send_software_update(id).await {
+    match app_state.node_manager.send_software_update(id, None).await {
         Ok(resp) => {
             let result = serde_json::json!({
                 "success": resp.success,
@@ -157,7 +157,7 @@ pub async fn trigger_client_update(
         return (StatusCode::FORBIDDEN, ApiResponse::<serde_json::Value>::error("仅管理员".to_string()));
     }
 
-    match app_state.client_stream_manager.send_software_update(id).await {
+    match app_state.client_stream_manager.send_software_update(id, None).await {
         Ok(resp) => {
             let result = serde_json::json!({
                 "success": resp.success,
@@ -212,7 +212,7 @@ pub async fn batch_update_nodes(
         let nm = app_state.node_manager.clone();
         let name = name.clone();
         handles.push(tokio::spawn(async move {
-            let result = nm.send_software_update(node_id).await;
+            let result = nm.send_software_update(node_id, None).await;
             (node_id, name, result)
         }));
     }
@@ -288,7 +288,7 @@ pub async fn batch_update_clients(
         let client_id = client.id;
         let name = client.name.clone();
         handles.push(tokio::spawn(async move {
-            let result = csm.send_software_update(client_id).await;
+            let result = csm.send_software_update(client_id, None).await;
             (client_id, name, result)
         }));
     }
@@ -0,0 +1,269 @@
+use std::net::IpAddr;
+
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, NotSet, QueryFilter, QueryOrder, Set};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::entity::{client, visitor, Client, Proxy, Visitor};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use crate::api::access;
+use crate::AppState;
+use super::ApiResponse;
+
+#[derive(Deserialize)]
+pub struct CreateVisitorRequest {
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    pub name: String,
+    #[serde(rename = "proxyId")]
+    pub proxy_id: i64,
+    /// 为空时只监听本机回环地址
+    #[serde(rename = "bindIP")]
+    pub bind_ip: Option<String>,
+    #[serde(rename = "bindPort")]
+    pub bind_port: u16,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateVisitorRequest {
+    pub name: Option<String>,
+    #[serde(rename = "bindIP")]
+    pub bind_ip: Option<String>,
+    #[serde(rename = "bindPort")]
+    pub bind_port: Option<u16>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct VisitorListQuery {
+    #[serde(rename = "clientId")]
+    pub client_id: Option<String>,
+}
+
+type ErrorResponse = (StatusCode, Json<ApiResponse<serde_json::Value>>);
+
+fn db_error(action: &str, e: sea_orm::DbErr) -> ErrorResponse {
+    (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("{}失败: {}", action, e)))
+}
+
+fn validate_bind(bind_ip: &str, bind_port: u16) -> Result<(), String> {
+    if bind_ip.parse::<IpAddr>().is_err() {
+        return Err(format!("无效的监听地址: {}", bind_ip));
+    }
+    if bind_port == 0 {
+        return Err("监听端口不能为 0".to_string());
+    }
+    Ok(())
+}
+
+/// 只能操作自己（租户管理员：本租户内用户）的客户端
+async fn check_client_owner(auth_user: &AuthUser, client_id: &str, db: &DatabaseConnection) -> Result<(), ErrorResponse> {
+    access::accessible_client(auth_user, client_id.parse::<i64>().unwrap_or(0), db)
+        .await
+        .map(|_| ())
+        .map_err(|(status, e)| (status, ApiResponse::error(e)))
+}
+
+/// 同一客户端上的访客不能监听相同端口
+async fn check_port_conflict(
+    client_id: &str,
+    bind_port: u16,
+    exclude_id: Option<i64>,
+    db: &DatabaseConnection,
+) -> Result<(), ErrorResponse> {
+    let mut select = Visitor::find()
+        .filter(visitor::Column::ClientId.eq(client_id))
+        .filter(visitor::Column::BindPort.eq(bind_port));
+    if let Some(id) = exclude_id {
+        select = select.filter(visitor::Column::Id.ne(id));
+    }
+    match select.one(db).await.map_err(|e| db_error("查询访客代理", e))? {
+        Some(v) => Err((
+            StatusCode::CONFLICT,
+            ApiResponse::error(format!("本地端口 {} 已被访客代理「{}」使用", bind_port, v.name)),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn notify_client(app_state: &AppState, client_id: String) {
+    let csm = app_state.client_stream_manager.clone();
+    tokio::spawn(async move {
+        csm.notify_proxy_change(&client_id).await;
+    });
+}
+
+/// GET /api/visitors - 访客代理列表
+pub async fn list_visitors(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Query(query): Query<VisitorListQuery>,
+) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()));
+    };
+    let db = get_connection().await;
+
+    let mut select = Visitor::find();
+    if let Some(client_id) = &query.client_id {
+        select = select.filter(visitor::Column::ClientId.eq(client_id.as_str()));
+    }
+    if !auth_user.is_admin {
+        let client_ids: Vec<String> = match Client::find().filter(client::Column::UserId.eq(auth_user.id)).all(db).await {
+            Ok(clients) => clients.into_iter().map(|c| c.id.to_string()).collect(),
+            Err(e) => return db_error("查询客户端", e),
+        };
+        select = select.filter(visitor::Column::ClientId.is_in(client_ids));
+    }
+
+    match select.order_by_asc(visitor::Column::Id).all(db).await {
+        Ok(visitors) => (StatusCode::OK, ApiResponse::success(serde_json::json!(visitors))),
+        Err(e) => db_error("查询访客代理", e),
+    }
+}
+
+/// POST /api/visitors - 在客户端本机创建访客代理
+pub async fn create_visitor(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<CreateVisitorRequest>,
+) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()));
+    };
+    let bind_ip = req.bind_ip.filter(|ip| !ip.trim().is_empty()).unwrap_or_else(|| "127.0.0.1".to_string());
+    if let Err(e) = validate_bind(&bind_ip, req.bind_port) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(e));
+    }
+
+    let db = get_connection().await;
+    if let Err(resp) = check_client_owner(&auth_user, &req.client_id, db).await {
+        return resp;
+    }
+
+    // 目标代理须由同一用户所有
+    let target = match Proxy::find_by_id(req.proxy_id).one(db).await {
+        Ok(Some(p)) => p,
+        Ok(None) => return (StatusCode::BAD_REQUEST, ApiResponse::error("目标代理不存在".to_string())),
+        Err(e) => return db_error("查询代理", e),
+    };
+    if check_client_owner(&auth_user, &target.client_id, db).await.is_err() {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("目标代理不存在".to_string()));
+    }
+    if let Err(resp) = check_port_conflict(&req.client_id, req.bind_port, None, db).await {
+        return resp;
+    }
+
+    let now = Utc::now().naive_utc();
+    let visitor = visitor::ActiveModel {
+        id: NotSet,
+        client_id: Set(req.client_id.clone()),
+        name: Set(req.name),
+        proxy_id: Set(req.proxy_id),
+        bind_ip: Set(bind_ip),
+        bind_port: Set(req.bind_port),
+        enabled: Set(req.enabled.unwrap_or(true)),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    match visitor.insert(db).await {
+        Ok(visitor) => {
+            info!(
+                "访客代理已创建: {} (ID: {})，客户端 #{} 监听 {}:{} -> 代理 #{}",
+                visitor.name, visitor.id, visitor.client_id, visitor.bind_ip, visitor.bind_port, visitor.proxy_id
+            );
+            notify_client(&app_state, visitor.client_id.clone());
+            (StatusCode::OK, ApiResponse::success(serde_json::json!(visitor)))
+        }
+        Err(e) => db_error("创建访客代理", e),
+    }
+}
+
+/// PUT /api/visitors/{id} - 修改访客代理
+pub async fn update_visitor(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+    Json(req): Json<UpdateVisitorRequest>,
+) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()));
+    };
+    let db = get_connection().await;
+
+    let existing = match Visitor::find_by_id(id).one(db).await {
+        Ok(Some(v)) => v,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("访客代理不存在".to_string())),
+        Err(e) => return db_error("查询访客代理", e),
+    };
+    if check_client_owner(&auth_user, &existing.client_id, db).await.is_err() {
+        return (StatusCode::NOT_FOUND, ApiResponse::error("访客代理不存在".to_string()));
+    }
+
+    let bind_ip = req.bind_ip.unwrap_or_else(|| existing.bind_ip.clone());
+    let bind_port = req.bind_port.unwrap_or(existing.bind_port);
+    if let Err(e) = validate_bind(&bind_ip, bind_port) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(e));
+    }
+    if let Err(resp) = check_port_conflict(&existing.client_id, bind_port, Some(id), db).await {
+        return resp;
+    }
+
+    let mut visitor: visitor::ActiveModel = existing.into();
+    if let Some(name) = req.name {
+        visitor.name = Set(name);
+    }
+    if let Some(enabled) = req.enabled {
+        visitor.enabled = Set(enabled);
+    }
+    visitor.bind_ip = Set(bind_ip);
+    visitor.bind_port = Set(bind_port);
+    visitor.updated_at = Set(Utc::now().naive_utc());
+
+    match visitor.update(db).await {
+        Ok(visitor) => {
+            info!("访客代理已更新: {} (ID: {})", visitor.name, visitor.id);
+            notify_client(&app_state, visitor.client_id.clone());
+            (StatusCode::OK, ApiResponse::success(serde_json::json!(visitor)))
+        }
+        Err(e) => db_error("更新访客代理", e),
+    }
+}
+
+/// DELETE /api/visitors/{id} - 删除访客代理
+pub async fn delete_visitor(
+    Path(id): Path<i64>,
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    Extension(app_state): Extension<AppState>,
+) -> impl IntoResponse {
+    let Some(auth_user) = auth_user else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::error("未认证".to_string()));
+    };
+    let db = get_connection().await;
+
+    let existing = match Visitor::find_by_id(id).one(db).await {
+        Ok(Some(v)) => v,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("访客代理不存在".to_string())),
+        Err(e) => return db_error("查询访客代理", e),
+    };
+    if check_client_owner(&auth_user, &existing.client_id, db).await.is_err() {
+        return (StatusCode::NOT_FOUND, ApiResponse::error("访客代理不存在".to_string()));
+    }
+
+    match Visitor::delete_by_id(id).exec(db).await {
+        Ok(_) => {
+            info!("访客代理已删除: {} (ID: {})", existing.name, id);
+            notify_client(&app_state, existing.client_id);
+            (StatusCode::OK, ApiResponse::success(serde_json::json!(null)))
+        }
+        Err(e) => db_error("删除访客代理", e),
+    }
+}
@@ -1,20 +1,26 @@
+use axum::handler::Handler;
 use axum::middleware::from_fn;
 use axum::{Extension, Router};
 use axum::routing::{get, post, put, delete};
 use tower_http::cors::CorsLayer;
-use tower_http::services::{ServeDir, ServeFile};
 use tracing::{info, error, warn};
 use crate::AppState;
-use crate::middleware::auth_middleware;
+use crate::middleware::{auth_middleware, require_reauth};
 use std::sync::Arc;
 use axum_server::tls_rustls::RustlsConfig;
 use axum_server_dual_protocol::ServerExt;
 use base64::Engine;
 
+pub mod access;
 pub mod handlers;
+pub mod pagination;
+mod ui;
 
-/// 从 ConfigManager 加载 Web TLS 证书和私钥
-async fn load_web_tls_config(config_manager: &crate::config_manager::ConfigManager) -> Option<RustlsConfig> {
+/// 证书热加载检查间隔（秒）
+const WEB_TLS_WATCH_INTERVAL_SECS: u64 = 30;
+
+/// 从 ConfigManager 读取 Web TLS 证书和私钥（PEM），返回 (证书, 私钥, 来源描述)
+async fn read_web_tls_pem(config_manager: &crate::config_manager::ConfigManager) -> Option<(Vec<u8>, Vec<u8>, String)> {
     let tls_enabled = config_manager.get_bool("web_tls_enabled", false).await;
     if !tls_enabled {
         return None;
@@ -29,17 +35,7 @@ async fn load_web_tls_config(config_manager: &crate::config_manager::ConfigManag
             base64::engine::general_purpose::STANDARD.decode(&cert_content),
             base64::engine::general_purpose::STANDARD.decode(&key_content),
         ) {
-            (Ok(cert_pem), Ok(key_pem)) => {
-                match RustlsConfig::from_pem(cert_pem, key_pem).await {
-                    Ok(config) => {
-                        info!("从数据库加载 Web TLS 证书");
-                        return Some(config);
-                    }
-                    Err(e) => {
-                        error!("Web TLS 证书加载失败: {}", e);
-                    }
-                }
-            }
+            (Ok(cert_pem), Ok(key_pem)) => return Some((cert_pem, key_pem, "数据库".to_string())),
             _ => {
                 error!("Web TLS 证书 base64 解码失败");
             }
@@ -51,75 +47,197 @@ async fn load_web_tls_config(config_manager: &crate::config_manager::ConfigManag
     let key_path = config_manager.get_string("web_tls_key_path", "").await;
 
     if !cert_path.is_empty() && !key_path.is_empty() {
-        match RustlsConfig::from_pem_file(&cert_path, &key_path).await {
-            Ok(config) => {
-                info!("从文件加载 Web TLS 证书: {}", cert_path);
-                return Some(config);
+        match (tokio::fs::read(&cert_path).await, tokio::fs::read(&key_path).await) {
+            (Ok(cert_pem), Ok(key_pem)) => return Some((cert_pem, key_pem, format!("文件 {}", cert_path))),
+            (Err(e), _) | (_, Err(e)) => {
+                error!("读取 Web TLS 证书文件失败: {}", e);
+            }
+        }
+    }
+
+    None
+}
+
+/// 从 ConfigManager 加载 Web TLS 证书和私钥，返回 (TLS 配置, 当前证书内容)
+async fn load_web_tls_config(
+    config_manager: &crate::config_manager::ConfigManager,
+) -> Option<(RustlsConfig, (Vec<u8>, Vec<u8>))> {
+    if let Some((cert_pem, key_pem, source)) = read_web_tls_pem(config_manager).await {
+        match crate::tls_apply::validate_pem(&cert_pem, &key_pem) {
+            Ok(server_config) => {
+                info!("从{}加载 Web TLS 证书", source);
+                return Some((RustlsConfig::from_config(Arc::new(server_config)), (cert_pem, key_pem)));
             }
             Err(e) => {
-                error!("从文件加载 Web TLS 证书失败: {}", e);
+                error!("Web TLS 证书加载失败: {}", e);
             }
         }
     }
 
-    warn!("Web TLS 已启用但未配置有效证书，回退到 HTTP 模式");
+    if config_manager.get_bool("web_tls_enabled", false).await {
+        warn!("Web TLS 已启用但未配置有效证书，回退到 HTTP 模式");
+    }
     None
 }
 
+/// 定期检查 Web TLS 证书是否变化（数据库配置更新或证书文件续期），
+/// 变化时直接替换运行中监听器的 rustls 配置，无需重启
+fn watch_web_tls_config(
+    config_manager: Arc<crate::config_manager::ConfigManager>,
+    tls_config: RustlsConfig,
+    mut current: (Vec<u8>, Vec<u8>),
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(WEB_TLS_WATCH_INTERVAL_SECS));
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+
+            let Some((cert_pem, key_pem, source)) = read_web_tls_pem(&config_manager).await else {
+                continue;
+            };
+            if current.0 == cert_pem && current.1 == key_pem {
+                continue;
+            }
+
+            match crate::tls_apply::validate_pem(&cert_pem, &key_pem) {
+                Ok(server_config) => {
+                    tls_config.reload_from_config(Arc::new(server_config));
+                    info!("🔐 检测到 Web TLS 证书变化，已从{}热加载新证书", source);
+                }
+                Err(e) => {
+                    error!("新的 Web TLS 证书无效，继续使用当前证书: {}", e);
+                }
+            }
+            // 无效证书同样记录，避免每个周期重复报错
+            current = (cert_pem, key_pem);
+        }
+    });
+}
+
 /// 启动 Web API 服务
 pub fn start_web_server(app_state: AppState) -> tokio::task::JoinHandle<()> {
     let web_port = app_state.config.web_port;
     let config_manager = app_state.config_manager.clone();
 
     tokio::spawn(async move {
+        // 敏感操作需要当前会话近期通过二次验证
+        let reauth = from_fn(require_reauth);
+
         // 构建 Web 应用
         let api_routes = Router::new()
             // 公开路由（无需认证）
             .route("/auth/login", post(handlers::login))
             .route("/auth/register", post(handlers::register))
             .route("/auth/register-status", get(handlers::get_register_status))
+            .route("/auth/password-reset/request", post(handlers::request_password_reset))
+            .route("/auth/password-reset/confirm", post(handlers::confirm_password_reset))
+            .route("/auth/verify-email", post(handlers::verify_email))
             .route("/client/connect-config", post(handlers::get_client_connect_config))
             // 认证路由（需要登录）
             .route("/auth/me", get(handlers::me))
+            .route("/auth/me/profile", get(handlers::get_profile).put(handlers::update_profile))
+            .route("/auth/me/password", put(handlers::change_password))
+            .route("/auth/me/email/verify", post(handlers::resend_email_verification))
+            .route("/auth/reauth", post(handlers::reauth))
+            .route("/auth/me/clients/{id}/rotate-token", post(handlers::rotate_own_client_token.layer(reauth.clone())))
+            .route("/auth/me/api-tokens", get(handlers::list_api_tokens).post(handlers::create_api_token.layer(reauth.clone())))
+            .route("/auth/me/api-tokens/{id}", delete(handlers::revoke_api_token))
             // 仪表板路由
             .route("/dashboard/stats/{user_id}", get(handlers::get_user_dashboard_stats))
+            .route("/status/online", get(handlers::get_online_status))
+            .route("/availability/{scope}/{id}", get(handlers::get_availability))
             .route("/clients", get(handlers::list_clients).post(handlers::create_client))
             .route("/clients/batch-update", post(handlers::batch_update_clients))
-            .route("/clients/{id}", get(handlers::get_client).delete(handlers::delete_client))
+            .route("/clients/{id}", get(handlers::get_client).delete(handlers::delete_client.layer(reauth.clone())))
             .route("/clients/{id}/logs", get(handlers::get_client_logs))
             .route("/clients/{id}/traffic", get(handlers::get_client_traffic))
             .route("/clients/{id}/allocate-quota", post(handlers::allocate_client_quota))
             .route("/clients/{id}/update", post(handlers::trigger_client_update))
+            .route("/clients/{id}/target-policy", put(handlers::update_client_target_policy))
+            .route("/clients/{id}/login-policy", put(handlers::update_client_login_policy))
+            .route("/clients/{id}/login-events", get(handlers::list_client_login_events))
+            .route("/clients/{id}/temporary-tunnel", post(handlers::open_temporary_tunnel))
+            .route("/temporary-tunnels", get(handlers::list_temporary_tunnels))
+            .route("/temporary-tunnels/{id}", delete(handlers::close_temporary_tunnel))
             .route("/proxies", get(handlers::list_proxies).post(handlers::create_proxy))
             .route("/proxies/batch", post(handlers::batch_create_proxies))
             .route("/proxies/group/{group_id}", put(handlers::update_proxy_group).delete(handlers::delete_proxy_group))
             .route("/proxies/group/{group_id}/toggle", post(handlers::toggle_proxy_group))
             .route("/proxies/{id}", put(handlers::update_proxy).delete(handlers::delete_proxy))
+            .route("/proxies/{id}/endpoints", get(handlers::get_proxy_endpoints))
+            .route("/proxies/{id}/anycast-nodes", put(handlers::update_proxy_anycast_nodes))
+            .route("/proxies/{id}/events", get(handlers::get_proxy_events))
+            .route("/proxies/{id}/probe", get(handlers::get_proxy_port_probe).post(handlers::probe_proxy_port))
+            .route("/proxies/{id}/captures", post(handlers::start_proxy_capture.layer(reauth.clone())))
+            .route("/captures", get(handlers::list_captures))
+            .route("/captures/{id}", delete(handlers::delete_capture))
+            .route("/captures/{id}/stop", post(handlers::stop_capture))
+            .route("/captures/{id}/download", get(handlers::download_capture))
+            .route("/visitors", get(handlers::list_visitors).post(handlers::create_visitor))
+            .route("/visitors/{id}", put(handlers::update_visitor).delete(handlers::delete_visitor))
             .route("/clients/{id}/proxies", get(handlers::list_proxies_by_client))
             // 流量统计路由
             .route("/traffic/overview", get(handlers::get_traffic_overview_handler))
             .route("/traffic/users/{id}", get(handlers::get_user_traffic_handler))
             // 系统配置路由
             .route("/system/configs", get(handlers::get_configs))
-            .route("/system/configs/update", post(handlers::update_config))
-            .route("/system/configs/batch", post(handlers::batch_update_configs))
-            .route("/system/restart", post(handlers::restart_system))
+            .route("/system/configs/update", post(handlers::update_config.layer(reauth.clone())))
+            .route("/system/configs/batch", post(handlers::batch_update_configs.layer(reauth.clone())))
+            .route("/system/grpc-tls", get(handlers::get_grpc_tls_status))
+            .route("/system/configs/revisions", get(handlers::list_config_revisions))
+            .route("/system/configs/rollback/{rev}", post(handlers::rollback_configs.layer(reauth.clone())))
+            .route("/system/tls/apply", post(handlers::apply_tls.layer(reauth.clone())))
+            .route("/system/tls/pending", get(handlers::get_pending_tls))
+            .route("/system/tls/confirm", post(handlers::confirm_tls))
+            .route("/system/tls/revert", post(handlers::revert_tls))
+            .route("/system/restart", get(handlers::get_restart_status).post(handlers::restart_system.layer(reauth.clone())))
             .route("/system/latest-version", get(handlers::get_latest_version))
             // 管理员路由（需要管理员权限）
             .route("/users", get(handlers::list_users).post(handlers::create_user))
-            .route("/users/{id}", put(handlers::update_user).delete(handlers::delete_user))
+            .route("/users/{id}", put(handlers::update_user.layer(reauth.clone())).delete(handlers::delete_user.layer(reauth.clone())))
             .route("/users/{id}/nodes", get(handlers::get_user_nodes))
             .route("/users/{id}/nodes/{node_id}", post(handlers::assign_node_to_user).delete(handlers::remove_node_from_user))
             .route("/users/{id}/adjust-quota", post(handlers::adjust_user_quota))
             .route("/users/{id}/quota-info", get(handlers::get_user_quota_info))
+            .route("/users/{id}/password-reset", post(handlers::send_user_password_reset.layer(reauth.clone())))
+            .route("/users/{id}/impersonate", post(handlers::impersonate_user.layer(reauth.clone())))
+            .route("/impersonations", get(handlers::list_impersonations))
+            // 租户管理路由（平台管理员权限）
+            .route("/tenants", get(handlers::list_tenants).post(handlers::create_tenant))
+            .route("/tenants/{id}", put(handlers::update_tenant).delete(handlers::delete_tenant.layer(reauth.clone())))
+            .route("/port-blocklist", get(handlers::list_blocklist_rules).post(handlers::create_blocklist_rule))
+            .route("/port-blocklist/{id}", delete(handlers::delete_blocklist_rule))
+            .route("/port-reservations", get(handlers::list_port_reservations).post(handlers::create_port_reservation))
+            .route("/port-reservations/{id}", delete(handlers::delete_port_reservation))
             // 节点管理路由（管理员权限）
             .route("/nodes", get(handlers::list_nodes).post(handlers::create_node))
             .route("/nodes/batch-update", post(handlers::batch_update_nodes))
-            .route("/nodes/{id}", get(handlers::get_node).put(handlers::update_node).delete(handlers::delete_node))
+            .route("/nodes/{id}", get(handlers::get_node).put(handlers::update_node).delete(handlers::delete_node.layer(reauth.clone())))
             .route("/nodes/{id}/test", post(handlers::test_node_connection))
             .route("/nodes/{id}/status", get(handlers::get_node_status))
             .route("/nodes/{id}/logs", get(handlers::get_node_logs))
+            .route("/nodes/{id}/logs/stream", get(handlers::stream_node_logs))
+            .route("/nodes/{id}/probe", post(handlers::probe_from_node))
             .route("/nodes/{id}/update", post(handlers::trigger_node_update))
+            .route("/nodes/{id}/clone-to/{target_id}", post(handlers::clone_node_to))
+            .route("/nodes/{id}/drain", post(handlers::drain_node.layer(reauth.clone())))
+            .route("/nodes/{id}/undrain", post(handlers::undrain_node.layer(reauth)))
+            .route("/mitigations", get(handlers::list_mitigations))
+            .route("/mitigations/{id}/release", post(handlers::release_mitigation))
+            // 告警路由（平台管理员权限）
+            .route("/alerts/rules", get(handlers::list_alert_rules).post(handlers::create_alert_rule))
+            .route("/alerts/rules/{id}", put(handlers::update_alert_rule).delete(handlers::delete_alert_rule))
+            .route("/alerts/events", get(handlers::list_alert_events))
+            // 维护窗口路由（管理员权限）
+            .route("/maintenance-windows", get(handlers::list_maintenance_windows).post(handlers::create_maintenance_window))
+            .route("/maintenance-windows/{id}", put(handlers::update_maintenance_window).delete(handlers::delete_maintenance_window))
+            // 故障转移组路由（管理员权限）
+            .route("/failover-groups", get(handlers::list_failover_groups).post(handlers::create_failover_group))
+            .route("/failover-groups/{id}", put(handlers::update_failover_group).delete(handlers::delete_failover_group))
+            // 软件更新发布计划路由（管理员权限）
+            .route("/updates/rollouts", get(handlers::list_update_rollouts).post(handlers::create_update_rollout))
+            .route("/updates/rollouts/{id}", get(handlers::get_update_rollout).put(handlers::update_update_rollout))
             // 订阅管理路由
             .route("/subscriptions", get(handlers::list_subscriptions).post(handlers::create_subscription))
             .route("/subscriptions/active", get(handlers::list_active_subscriptions))
@@ -129,28 +247,50 @@ pub fn start_web_server(app_state: AppState) -> tokio::task::JoinHandle<()> {
             .route("/user-subscriptions/{id}", put(handlers::update_user_subscription).delete(handlers::delete_user_subscription))
             .route("/users/{user_id}/subscriptions", get(handlers::get_user_subscriptions))
             .route("/users/{user_id}/subscriptions/active", get(handlers::get_user_active_subscription))
+            .route("/users/{user_id}/quota-simulation", post(handlers::simulate_user_quota));
+
+        // GraphQL 查询接口（可选功能 `graphql`）
+        #[cfg(feature = "graphql")]
+        let api_routes = api_routes.route("/graphql", post(crate::graphql::graphql_handler));
+
+        let api_routes = api_routes
             // 应用认证中间件
             .layer(from_fn(auth_middleware))
             // 添加应用状态
-            .layer(Extension(app_state));
+            .layer(Extension(app_state.clone()));
+
+        // 健康检查路由（供 Kubernetes / 负载均衡器探测，不经过认证）
+        let health_routes = Router::new()
+            .route("/healthz", get(handlers::healthz))
+            .route("/readyz", get(handlers::readyz))
+            .layer(Extension(app_state.clone()));
 
         let app = Router::new()
             // API 路由
             .nest("/api", api_routes)
-            // 静态文件服务，带 SPA fallback
-            .fallback_service(
-                ServeDir::new("dist")
-                    .fallback(ServeFile::new("dist/index.html"))
-            )
+            .merge(health_routes);
+        // 静态文件服务，带 SPA fallback
+        let app = ui::attach(app, app_state.config.ui_dir.as_deref())
             .layer(CorsLayer::permissive());
 
-        let web_addr = format!("0.0.0.0:{}", web_port);
+        let web_addr = common::utils::wildcard_addr(web_port).to_string();
 
         // 尝试加载 TLS 配置
-        if let Some(tls_config) = load_web_tls_config(&config_manager).await {
+        if let Some((tls_config, current_pem)) = load_web_tls_config(&config_manager).await {
+            watch_web_tls_config(config_manager.clone(), tls_config.clone(), current_pem);
+
             // 使用 HTTPS（同时支持 HTTP 自动重定向到 HTTPS）
+            let listener = match common::socket_activation::tcp_listener(web_addr.parse().unwrap())
+                .and_then(|listener| Ok(listener.into_std()?))
+            {
+                Ok(listener) => listener,
+                Err(err) => {
+                    error!("Web服务启动失败：{}", err);
+                    return;
+                }
+            };
             info!("🌐 Web管理界面: https://{}", web_addr);
-            match axum_server_dual_protocol::bind_dual_protocol(web_addr.parse().unwrap(), tls_config)
+            match axum_server_dual_protocol::from_tcp_dual_protocol(listener, tls_config)
                 .set_upgrade(true)
                 .serve(app.into_make_service())
                 .await
@@ -162,7 +302,7 @@ pub fn start_web_server(app_state: AppState) -> tokio::task::JoinHandle<()> {
             }
         } else {
             // 使用 HTTP
-            match tokio::net::TcpListener::bind(web_addr.clone()).await {
+            match common::socket_activation::tcp_listener(web_addr.parse().unwrap()) {
                 Ok(listener) => {
                     info!("🌐 Web管理界面: http://{}", web_addr);
                     if let Err(err) = axum::serve(listener, app).await {
@@ -0,0 +1,179 @@
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DbErr, EntityTrait, FromQueryResult, Order, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, Select,
+};
+use serde::Deserialize;
+
+/// 默认每页条数
+pub const DEFAULT_PAGE_SIZE: u64 = 20;
+/// 每页条数上限
+pub const MAX_PAGE_SIZE: u64 = 200;
+
+/// 列表接口通用查询参数
+///
+/// - `page`：页码（从 1 开始），未指定时返回全部数据（兼容旧版前端）
+/// - `page_size`：每页条数，默认 20，最大 200
+/// - `sort`：排序字段，前缀 `-` 表示降序，例如 `-created_at`
+/// - `q`：关键字搜索
+/// - `after`：游标分页，只返回 ID 大于该值的记录（取上一页最后一条的 ID），
+///   走主键索引，不受偏移量影响；仅在按 ID 升序（未指定排序）时生效，优先于 `page`
+#[derive(Debug, Default, Deserialize)]
+pub struct ListQuery {
+    pub page: Option<u64>,
+    pub page_size: Option<u64>,
+    pub sort: Option<String>,
+    pub q: Option<String>,
+    pub after: Option<i64>,
+}
+
+impl ListQuery {
+    pub fn page_size(&self) -> u64 {
+        self.page_size
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+
+    /// 去除首尾空白后的搜索关键字，空字符串视为未指定
+    pub fn keyword(&self) -> Option<&str> {
+        self.q.as_deref().map(str::trim).filter(|q| !q.is_empty())
+    }
+
+    /// 解析排序参数，返回 (字段名, 排序方向)
+    pub fn sort_order(&self) -> Option<(&str, Order)> {
+        let sort = self.sort.as_deref().map(str::trim).filter(|s| !s.is_empty())?;
+        match sort.strip_prefix('-') {
+            Some(field) => Some((field, Order::Desc)),
+            None => Some((sort.trim_start_matches('+'), Order::Asc)),
+        }
+    }
+
+    /// 有效的游标：指定了按 ID 升序以外的排序时忽略
+    pub fn cursor(&self) -> Option<i64> {
+        match self.sort_order() {
+            None | Some(("id", Order::Asc)) => self.after,
+            _ => None,
+        }
+    }
+}
+
+/// 按白名单应用排序，未知字段回退到默认排序列
+pub fn apply_sort<E>(
+    select: Select<E>,
+    query: &ListQuery,
+    sortable: &[(&str, E::Column)],
+    default: E::Column,
+) -> Select<E>
+where
+    E: EntityTrait,
+{
+    let resolved = query.sort_order().and_then(|(field, order)| {
+        sortable
+            .iter()
+            .find(|(name, _)| *name == field)
+            .map(|(_, column)| (*column, order))
+    });
+
+    match resolved {
+        Some((column, order)) => select.order_by(column, order),
+        None => select.order_by(default, Order::Asc),
+    }
+}
+
+/// 应用游标条件，只保留 ID 大于游标的记录
+pub fn apply_cursor<E>(select: Select<E>, query: &ListQuery, id: E::Column) -> Select<E>
+where
+    E: EntityTrait,
+{
+    match query.cursor() {
+        Some(after) => select.filter(id.gt(after)),
+        None => select,
+    }
+}
+
+/// 执行分页查询，返回 (当前页数据, 总条数)
+///
+/// 未指定 `page` 时返回全部数据，总条数即结果条数；
+/// 使用游标时返回游标之后的一页，总条数为游标之后的剩余条数。
+pub async fn fetch_page<E, C>(
+    select: Select<E>,
+    query: &ListQuery,
+    db: &C,
+) -> Result<(Vec<E::Model>, u64), DbErr>
+where
+    E: EntityTrait,
+    E::Model: FromQueryResult + Sized + Send + Sync + 'static,
+    C: ConnectionTrait,
+{
+    if query.cursor().is_some() {
+        let total = select.clone().count(db).await?;
+        let items = select.limit(query.page_size()).all(db).await?;
+        return Ok((items, total));
+    }
+
+    match query.page {
+        Some(page) => {
+            let paginator = select.paginate(db, query.page_size());
+            let total = paginator.num_items().await?;
+            let items = paginator.fetch_page(page.max(1) - 1).await?;
+            Ok((items, total))
+        }
+        None => {
+            let items = select.all(db).await?;
+            let total = items.len() as u64;
+            Ok((items, total))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_order_parsing() {
+        let query = ListQuery { sort: Some("-created_at".to_string()), ..Default::default() };
+        assert_eq!(query.sort_order(), Some(("created_at", Order::Desc)));
+
+        let query = ListQuery { sort: Some("name".to_string()), ..Default::default() };
+        assert_eq!(query.sort_order(), Some(("name", Order::Asc)));
+
+        let query = ListQuery { sort: Some("  ".to_string()), ..Default::default() };
+        assert_eq!(query.sort_order(), None);
+    }
+
+    #[test]
+    fn test_page_size_clamped() {
+        let query = ListQuery::default();
+        assert_eq!(query.page_size(), DEFAULT_PAGE_SIZE);
+
+        let query = ListQuery { page_size: Some(0), ..Default::default() };
+        assert_eq!(query.page_size(), 1);
+
+        let query = ListQuery { page_size: Some(10_000), ..Default::default() };
+        assert_eq!(query.page_size(), MAX_PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_cursor_requires_id_order() {
+        let query = ListQuery { after: Some(42), ..Default::default() };
+        assert_eq!(query.cursor(), Some(42));
+
+        let query = ListQuery { after: Some(42), sort: Some("id".to_string()), ..Default::default() };
+        assert_eq!(query.cursor(), Some(42));
+
+        let query = ListQuery { after: Some(42), sort: Some("-id".to_string()), ..Default::default() };
+        assert_eq!(query.cursor(), None);
+
+        let query = ListQuery { after: Some(42), sort: Some("name".to_string()), ..Default::default() };
+        assert_eq!(query.cursor(), None);
+    }
+
+    #[test]
+    fn test_keyword_trimmed() {
+        let query = ListQuery { q: Some("  web ".to_string()), ..Default::default() };
+        assert_eq!(query.keyword(), Some("web"));
+
+        let query = ListQuery { q: Some("   ".to_string()), ..Default::default() };
+        assert_eq!(query.keyword(), None);
+    }
+}
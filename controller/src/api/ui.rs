@@ -0,0 +1,91 @@
+//! Web 管理界面静态文件
+//!
+//! 查找顺序：
+//! 1. `--ui-dir` / `OXIPROXY_UI_DIR` / 配置项 `ui_dir` 指定的目录
+//! 2. 启用 `embed-ui` 特性编译时内嵌到二进制中的资源
+//! 3. 当前工作目录下的 `dist`，不存在时使用程序所在目录下的 `dist`
+//!
+//! 以上均为 SPA：找不到的路径返回 `index.html`，由前端路由处理。
+
+use axum::Router;
+use std::path::PathBuf;
+use tower_http::services::{ServeDir, ServeFile};
+use tracing::{info, warn};
+
+/// 为路由挂载静态文件服务
+pub fn attach(router: Router, ui_dir: Option<&str>) -> Router {
+    if let Some(dir) = ui_dir {
+        return serve_dir(router, PathBuf::from(dir));
+    }
+
+    #[cfg(feature = "embed-ui")]
+    if embedded::available() {
+        info!("Web 管理界面: 使用内嵌资源");
+        return router.fallback(embedded::serve);
+    }
+
+    serve_dir(router, default_dir())
+}
+
+fn serve_dir(router: Router, dir: PathBuf) -> Router {
+    let index = dir.join("index.html");
+    if index.is_file() {
+        info!("Web 管理界面目录: {}", dir.display());
+    } else {
+        warn!("Web 管理界面目录 {} 中没有 index.html，请检查 --ui-dir 或构建前端", dir.display());
+    }
+    router.fallback_service(ServeDir::new(&dir).fallback(ServeFile::new(index)))
+}
+
+/// 当前工作目录下的 dist，作为服务运行（工作目录不是安装目录）时回退到程序所在目录下的 dist
+fn default_dir() -> PathBuf {
+    let cwd_dist = PathBuf::from("dist");
+    if cwd_dist.join("index.html").is_file() {
+        return cwd_dist;
+    }
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|p| p.join("dist")))
+        .filter(|dir| dir.join("index.html").is_file())
+        .unwrap_or(cwd_dist)
+}
+
+#[cfg(feature = "embed-ui")]
+mod embedded {
+    use axum::http::{header, StatusCode, Uri};
+    use axum::response::{IntoResponse, Response};
+
+    #[derive(rust_embed::RustEmbed)]
+    #[folder = "../dist/"]
+    struct Assets;
+
+    /// 编译时 dist 目录中存在 index.html
+    pub fn available() -> bool {
+        Assets::get("index.html").is_some()
+    }
+
+    pub async fn serve(uri: Uri) -> Response {
+        let path = uri.path().trim_start_matches('/');
+        let path = if path.is_empty() { "index.html" } else { path };
+
+        let (path, file) = match Assets::get(path) {
+            Some(file) => (path, file),
+            None => match Assets::get("index.html") {
+                Some(file) => ("index.html", file),
+                None => return StatusCode::NOT_FOUND.into_response(),
+            },
+        };
+
+        let mime = mime_guess::from_path(path).first_or_octet_stream();
+        // assets/ 下是带哈希的构建产物，可长期缓存；其余文件（index.html 等）每次重新验证
+        let cache = if path.starts_with("assets/") { "public, max-age=31536000, immutable" } else { "no-cache" };
+        (
+            [
+                (header::CONTENT_TYPE, mime.as_ref().to_string()),
+                (header::CACHE_CONTROL, cache.to_string()),
+            ],
+            file.data,
+        )
+            .into_response()
+    }
+}
@@ -0,0 +1,105 @@
+//! 长期有效的 API 令牌
+//!
+//! 登录令牌（JWT）会过期，也会因会话空闲超时失效，不适合 rfrpctl 和脚本长期保存。API 令牌由用户在
+//! 登录会话中创建，请求时通过 `X-API-Key` 头携带，权限与创建者本人相同，可随时吊销。
+//! 数据库只保存令牌的 SHA-256，原文只在创建时返回一次。
+//!
+//! API 令牌无法通过二次验证，删除节点、用户等需要二次验证的操作仍需使用登录会话。
+
+use axum::http::HeaderMap;
+use chrono::Utc;
+use rand::Rng;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use sha2::{Digest, Sha256};
+
+use crate::entity::{api_token, ApiToken, User};
+use crate::middleware::AuthUser;
+
+/// 携带 API 令牌的请求头
+pub const HEADER: &str = "x-api-key";
+/// 令牌原文的前缀，便于在配置文件和日志中辨认
+const TOKEN_PREFIX: &str = "oxp_";
+/// 列表中展示的令牌原文长度（含前缀）
+const DISPLAY_PREFIX_LEN: usize = 12;
+/// 最近使用时间的最小更新间隔，避免每个请求都写数据库
+const TOUCH_INTERVAL_SECS: i64 = 60;
+
+/// 生成新的令牌原文
+pub fn generate() -> String {
+    let bytes: [u8; 24] = rand::rng().random();
+    format!("{}{}", TOKEN_PREFIX, hex::encode(bytes))
+}
+
+/// 令牌原文的摘要（数据库中保存的值）
+pub fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// 列表中展示的令牌前缀
+pub fn display_prefix(token: &str) -> String {
+    token.chars().take(DISPLAY_PREFIX_LEN).collect()
+}
+
+/// 请求头中的 API 令牌
+pub fn from_headers(headers: &HeaderMap) -> Option<&str> {
+    headers.get(HEADER)?.to_str().ok().map(str::trim).filter(|t| !t.is_empty())
+}
+
+/// 校验 API 令牌，返回令牌所属用户；令牌不存在、已过期或用户已删除时返回 None
+pub async fn authenticate(token: &str, db: &DatabaseConnection) -> Option<AuthUser> {
+    let record = ApiToken::find()
+        .filter(api_token::Column::TokenHash.eq(hash(token)))
+        .one(db)
+        .await
+        .ok()??;
+    let now = Utc::now().naive_utc();
+    if record.expires_at.is_some_and(|at| at <= now) {
+        return None;
+    }
+    let user = User::find_by_id(record.user_id).one(db).await.ok()??;
+
+    if record.last_used_at.is_none_or(|at| (now - at).num_seconds() >= TOUCH_INTERVAL_SECS) {
+        let mut active: api_token::ActiveModel = record.into();
+        active.last_used_at = Set(Some(now));
+        if let Err(e) = active.update(db).await {
+            tracing::warn!("更新 API 令牌使用时间失败: {}", e);
+        }
+    }
+
+    Some(AuthUser {
+        id: user.id,
+        username: user.username,
+        // 与登录令牌一致：租户内的用户不具备平台管理员权限
+        is_admin: user.is_admin && user.tenant_id.is_none(),
+        tenant_id: user.tenant_id,
+        is_tenant_admin: user.is_tenant_admin,
+        impersonator: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_and_hash() {
+        let token = generate();
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert_eq!(token.len(), TOKEN_PREFIX.len() + 48);
+        assert_ne!(token, generate());
+
+        assert_eq!(hash(&token), hash(&token));
+        assert_eq!(hash(&token).len(), 64);
+        assert_eq!(display_prefix(&token), token[..DISPLAY_PREFIX_LEN]);
+    }
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(from_headers(&headers), None);
+        headers.insert(HEADER, " oxp_abc ".parse().unwrap());
+        assert_eq!(from_headers(&headers), Some("oxp_abc"));
+        headers.insert(HEADER, "".parse().unwrap());
+        assert_eq!(from_headers(&headers), None);
+    }
+}
@@ -1,16 +1,45 @@
 use anyhow::Result;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
 
-/// Hash a password using bcrypt with cost 12
+/// Argon2id with the library's recommended parameters (19 MiB, 2 iterations, 1 lane)
+fn argon2() -> Argon2<'static> {
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::default())
+}
+
+/// Hash a password using Argon2id
 pub fn hash_password(password: &str) -> Result<String> {
-    let cost = 12;
-    bcrypt::hash(password, cost).map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))
+    let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>())
+        .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))?;
+    argon2()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))
 }
 
-/// Verify a password against a hash
+/// Verify a password against a hash (Argon2 or legacy bcrypt)
 pub fn verify_password(password: &str, hash: &str) -> Result<bool> {
+    if hash.starts_with("$argon2") {
+        let parsed = PasswordHash::new(hash).map_err(|e| anyhow::anyhow!("Failed to verify password: {}", e))?;
+        return Ok(argon2().verify_password(password.as_bytes(), &parsed).is_ok());
+    }
     bcrypt::verify(password, hash).map_err(|e| anyhow::anyhow!("Failed to verify password: {}", e))
 }
 
+/// Whether a hash should be upgraded on next successful login
+/// (legacy bcrypt, or Argon2 with different algorithm/parameters)
+pub fn needs_rehash(hash: &str) -> bool {
+    let Ok(parsed) = PasswordHash::new(hash) else {
+        return true;
+    };
+    if parsed.algorithm != argon2::ARGON2ID_IDENT {
+        return true;
+    }
+    let current = Params::default();
+    !Params::try_from(&parsed)
+        .is_ok_and(|p| (p.m_cost(), p.t_cost(), p.p_cost()) == (current.m_cost(), current.t_cost(), current.p_cost()))
+}
+
 /// Generate a random password of specified length
 pub fn generate_random_password(length: usize) -> String {
     use rand::Rng;
@@ -23,3 +52,21 @@ pub fn generate_random_password(length: usize) -> String {
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_bcrypt_rehash() {
+        let legacy = bcrypt::hash("secret-pass", 4).unwrap();
+        assert!(verify_password("secret-pass", &legacy).unwrap());
+        assert!(needs_rehash(&legacy));
+
+        let hash = hash_password("secret-pass").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify_password("secret-pass", &hash).unwrap());
+        assert!(!verify_password("wrong-pass", &hash).unwrap());
+        assert!(!needs_rehash(&hash));
+    }
+}
@@ -0,0 +1,313 @@
+//! 可用率（SLA）统计
+//!
+//! 节点和客户端的每次上线 / 离线都追加到 `status_history`（由 `online_status` 写入）。
+//! 可用率 = 在线时长 / 有记录的时长，按最近 24 小时、7 天、30 天分别计算：
+//!
+//! - 统计区间开始前的最后一条记录决定起始状态；对象在区间内才第一次出现时，从第一条记录开始统计；
+//! - 代理在所属客户端和所在节点同时在线时视为可用；
+//! - 维护窗口内的时间（含客户端和节点的窗口）不计入统计，计划内停机不影响可用率。
+//!
+//! Controller 停止期间没有记录，按停止前的最后状态计算。
+
+use anyhow::{anyhow, Result};
+use chrono::{Duration, NaiveDateTime, Utc};
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set};
+use serde::Serialize;
+use tracing::error;
+
+use crate::entity::maintenance_window::{self, SCOPE_CLIENT, SCOPE_NODE, SCOPE_PROXY};
+use crate::entity::status_history::{self, ENTITY_CLIENT, ENTITY_NODE};
+use crate::entity::{MaintenanceWindow, Proxy, StatusHistory};
+use crate::migration::get_connection;
+
+/// 统计区间（名称, 小时数）
+const PERIODS: [(&str, i64); 3] = [("24h", 24), ("7d", 24 * 7), ("30d", 24 * 30)];
+
+/// 时间段 [开始, 结束)
+type Span = (NaiveDateTime, NaiveDateTime);
+
+/// 写入状态变化记录
+pub async fn record(entity_type: &str, ids: &[i64], online: bool) {
+    if ids.is_empty() {
+        return;
+    }
+    let now = Utc::now().naive_utc();
+    let rows = ids.iter().map(|&id| status_history::ActiveModel {
+        entity_type: Set(entity_type.to_string()),
+        entity_id: Set(id),
+        online: Set(online),
+        changed_at: Set(now),
+        ..Default::default()
+    });
+    if let Err(e) = StatusHistory::insert_many(rows).exec(get_connection().await).await {
+        error!("写入在线状态历史失败: {}", e);
+    }
+}
+
+/// 一段时间内的状态：有记录的时间段和在线的时间段
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Timeline {
+    known: Vec<Span>,
+    up: Vec<Span>,
+}
+
+impl Timeline {
+    /// 由起始状态和区间内的状态变化得出时间线
+    fn from_changes(initial: Option<bool>, changes: &[(NaiveDateTime, bool)], start: NaiveDateTime, end: NaiveDateTime) -> Self {
+        let mut timeline = Timeline::default();
+        let mut state = initial;
+        let mut since = start;
+        for &(at, online) in changes {
+            let at = at.clamp(start, end);
+            if let Some(was_online) = state {
+                timeline.push(since, at, was_online);
+            }
+            state = Some(online);
+            since = at;
+        }
+        if let Some(online) = state {
+            timeline.push(since, end, online);
+        }
+        timeline.known = merge(timeline.known);
+        timeline.up = merge(timeline.up);
+        timeline
+    }
+
+    fn push(&mut self, from: NaiveDateTime, to: NaiveDateTime, online: bool) {
+        if from < to {
+            self.known.push((from, to));
+            if online {
+                self.up.push((from, to));
+            }
+        }
+    }
+
+    /// 两个对象同时有记录、同时在线的时间线
+    fn both(&self, other: &Timeline) -> Timeline {
+        Timeline { known: intersect(&self.known, &other.known), up: intersect(&self.up, &other.up) }
+    }
+
+    /// 截取 [start, end) 并去掉维护时间后计算可用率
+    fn availability(&self, start: NaiveDateTime, end: NaiveDateTime, maintenance: &[Span]) -> Availability {
+        let period = [(start, end)];
+        let known = subtract(&intersect(&self.known, &period), maintenance);
+        let up = subtract(&intersect(&self.up, &period), maintenance);
+        let monitored_secs = total_secs(&known);
+        let uptime_secs = total_secs(&up);
+        Availability {
+            percent: (monitored_secs > 0).then(|| uptime_secs as f64 / monitored_secs as f64 * 100.0),
+            monitored_secs,
+            downtime_secs: monitored_secs - uptime_secs,
+        }
+    }
+}
+
+/// 排序并合并重叠的时间段
+fn merge(mut spans: Vec<Span>) -> Vec<Span> {
+    spans.retain(|(from, to)| from < to);
+    spans.sort();
+    let mut merged: Vec<Span> = Vec::with_capacity(spans.len());
+    for (from, to) in spans {
+        match merged.last_mut() {
+            Some(last) if from <= last.1 => last.1 = last.1.max(to),
+            _ => merged.push((from, to)),
+        }
+    }
+    merged
+}
+
+/// 两组已合并时间段的交集
+fn intersect(a: &[Span], b: &[Span]) -> Vec<Span> {
+    let (mut i, mut j) = (0, 0);
+    let mut result = Vec::new();
+    while i < a.len() && j < b.len() {
+        let from = a[i].0.max(b[j].0);
+        let to = a[i].1.min(b[j].1);
+        if from < to {
+            result.push((from, to));
+        }
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result
+}
+
+/// 从已合并的时间段中去掉另一组（已合并的）时间段
+fn subtract(a: &[Span], cut: &[Span]) -> Vec<Span> {
+    let mut result = Vec::new();
+    for &(mut from, to) in a {
+        for &(cut_from, cut_to) in cut {
+            if cut_to <= from || cut_from >= to {
+                continue;
+            }
+            if cut_from > from {
+                result.push((from, cut_from));
+            }
+            from = cut_to;
+        }
+        if from < to {
+            result.push((from, to));
+        }
+    }
+    result
+}
+
+fn total_secs(spans: &[Span]) -> i64 {
+    spans.iter().map(|(from, to)| (*to - *from).num_seconds()).sum()
+}
+
+/// 一个统计区间的可用率
+#[derive(Debug, Clone, Serialize)]
+pub struct Availability {
+    /// 可用率（%），区间内没有记录时为空
+    pub percent: Option<f64>,
+    /// 有记录且不在维护窗口内的时长（秒）
+    #[serde(rename = "monitoredSecs")]
+    pub monitored_secs: i64,
+    /// 离线时长（秒）
+    #[serde(rename = "downtimeSecs")]
+    pub downtime_secs: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PeriodAvailability {
+    /// `24h` / `7d` / `30d`
+    pub period: &'static str,
+    #[serde(flatten)]
+    pub availability: Availability,
+}
+
+/// 从数据库加载对象在 [start, end) 内的时间线
+async fn load_timeline(db: &DatabaseConnection, entity_type: &str, id: i64, start: NaiveDateTime, end: NaiveDateTime) -> Result<Timeline> {
+    let of_entity = Condition::all()
+        .add(status_history::Column::EntityType.eq(entity_type))
+        .add(status_history::Column::EntityId.eq(id));
+    let initial = StatusHistory::find()
+        .filter(of_entity.clone())
+        .filter(status_history::Column::ChangedAt.lt(start))
+        .order_by_desc(status_history::Column::ChangedAt)
+        .one(db)
+        .await?
+        .map(|r| r.online);
+    let changes: Vec<(NaiveDateTime, bool)> = StatusHistory::find()
+        .filter(of_entity)
+        .filter(status_history::Column::ChangedAt.gte(start))
+        .filter(status_history::Column::ChangedAt.lt(end))
+        .order_by_asc(status_history::Column::ChangedAt)
+        .order_by_asc(status_history::Column::Id)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|r| (r.changed_at, r.online))
+        .collect();
+    Ok(Timeline::from_changes(initial, &changes, start, end))
+}
+
+/// 维护窗口在 [start, end) 内的时间段
+async fn load_maintenance(db: &DatabaseConnection, scopes: &[(&str, i64)], start: NaiveDateTime, end: NaiveDateTime) -> Result<Vec<Span>> {
+    let mut scope = Condition::any();
+    for &(scope_type, scope_id) in scopes {
+        scope = scope.add(
+            Condition::all()
+                .add(maintenance_window::Column::ScopeType.eq(scope_type))
+                .add(maintenance_window::Column::ScopeId.eq(scope_id)),
+        );
+    }
+    let windows = MaintenanceWindow::find()
+        .filter(scope)
+        .filter(maintenance_window::Column::StartsAt.lt(end))
+        .filter(maintenance_window::Column::EndsAt.gt(start))
+        .all(db)
+        .await?;
+    Ok(merge(windows.into_iter().map(|w| (w.starts_at.max(start), w.ends_at.min(end))).collect()))
+}
+
+/// 对象最近 24 小时、7 天、30 天的可用率
+///
+/// `scope_type` 为 `node` / `client` / `proxy`。
+pub async fn report(db: &DatabaseConnection, scope_type: &str, id: i64) -> Result<Vec<PeriodAvailability>> {
+    let end = Utc::now().naive_utc();
+    let start = end - Duration::hours(PERIODS[PERIODS.len() - 1].1);
+
+    let (timeline, scopes) = match scope_type {
+        SCOPE_NODE => (load_timeline(db, ENTITY_NODE, id, start, end).await?, vec![(SCOPE_NODE, id)]),
+        SCOPE_CLIENT => (load_timeline(db, ENTITY_CLIENT, id, start, end).await?, vec![(SCOPE_CLIENT, id)]),
+        SCOPE_PROXY => {
+            let proxy = Proxy::find_by_id(id).one(db).await?.ok_or_else(|| anyhow!("代理不存在"))?;
+            let client_id: i64 = proxy.client_id.parse().map_err(|_| anyhow!("代理的客户端无效"))?;
+            let mut timeline = load_timeline(db, ENTITY_CLIENT, client_id, start, end).await?;
+            let mut scopes = vec![(SCOPE_PROXY, id), (SCOPE_CLIENT, client_id)];
+            if let Some(node_id) = proxy.node_id {
+                timeline = timeline.both(&load_timeline(db, ENTITY_NODE, node_id, start, end).await?);
+                scopes.push((SCOPE_NODE, node_id));
+            }
+            (timeline, scopes)
+        }
+        _ => return Err(anyhow!("无效的统计对象: {}", scope_type)),
+    };
+    let maintenance = load_maintenance(db, &scopes, start, end).await?;
+
+    Ok(PERIODS
+        .iter()
+        .map(|&(period, hours)| PeriodAvailability {
+            period,
+            availability: timeline.availability(end - Duration::hours(hours), end, &maintenance),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(minutes: i64) -> NaiveDateTime {
+        NaiveDateTime::default() + Duration::minutes(minutes)
+    }
+
+    #[test]
+    fn test_timeline_from_changes() {
+        // 起始在线，10 分钟时离线，30 分钟时恢复
+        let timeline = Timeline::from_changes(Some(true), &[(at(10), false), (at(30), true)], at(0), at(60));
+        assert_eq!(timeline.known, vec![(at(0), at(60))]);
+        assert_eq!(timeline.up, vec![(at(0), at(10)), (at(30), at(60))]);
+
+        let a = timeline.availability(at(0), at(60), &[]);
+        assert_eq!(a.monitored_secs, 3600);
+        assert_eq!(a.downtime_secs, 1200);
+
+        // 区间开始前没有记录：从第一条记录开始统计
+        let timeline = Timeline::from_changes(None, &[(at(20), true)], at(0), at(60));
+        assert_eq!(timeline.known, vec![(at(20), at(60))]);
+        assert_eq!(timeline.availability(at(0), at(60), &[]).percent, Some(100.0));
+
+        assert_eq!(Timeline::from_changes(None, &[], at(0), at(60)).availability(at(0), at(60), &[]).percent, None);
+    }
+
+    #[test]
+    fn test_maintenance_excluded() {
+        let timeline = Timeline::from_changes(Some(true), &[(at(10), false), (at(30), true)], at(0), at(60));
+        // 离线期间处于维护窗口
+        let a = timeline.availability(at(0), at(60), &[(at(5), at(35))]);
+        assert_eq!(a.monitored_secs, 30 * 60);
+        assert_eq!(a.downtime_secs, 0);
+        assert_eq!(a.percent, Some(100.0));
+    }
+
+    #[test]
+    fn test_proxy_needs_both() {
+        let client = Timeline::from_changes(Some(true), &[(at(10), false), (at(20), true)], at(0), at(60));
+        let node = Timeline::from_changes(Some(true), &[(at(15), false), (at(40), true)], at(0), at(60));
+        let proxy = client.both(&node);
+        assert_eq!(proxy.up, vec![(at(0), at(10)), (at(40), at(60))]);
+        assert_eq!(proxy.availability(at(0), at(60), &[]).downtime_secs, 30 * 60);
+    }
+
+    #[test]
+    fn test_span_ops() {
+        assert_eq!(merge(vec![(at(5), at(10)), (at(0), at(6)), (at(20), at(30))]), vec![(at(0), at(10)), (at(20), at(30))]);
+        assert_eq!(subtract(&[(at(0), at(60))], &[(at(10), at(20)), (at(50), at(70))]), vec![(at(0), at(10)), (at(20), at(50))]);
+    }
+}
@@ -0,0 +1,248 @@
+//! 带宽时间表
+//!
+//! 节点和用户的速度限制可以按时间段变化，例如 0 点到 8 点 100 Mbps、其余时间 20 Mbps：
+//!
+//! ```text
+//! speedLimit         = 2621440          （时间表之外使用，20 Mbps）
+//! speedLimitSchedule = 00:00-08:00=12.5M（100 Mbps）
+//! ```
+//!
+//! - 时间表由 `;` 分隔的「时间窗口=速率」组成，时间窗口的写法与代理定时启停相同，按 Controller 本地时间计算；
+//! - 速率单位为字节/秒，可带 `K` / `M` / `G` 后缀（1024 进制），`0` 表示不限速；
+//! - 窗口重叠时使用靠前的一个，不在任何窗口内时使用 `speedLimit`。
+//!
+//! 节点限制整个节点的总带宽；用户限制该用户所有客户端在每个节点上的总带宽（各节点分别计算）。
+//! 节点注册时下发当前生效的限制，之后每 30 秒检查一次，跨过窗口边界时通过 gRPC 推送更新。
+
+use anyhow::{anyhow, Result};
+use chrono::{Local, NaiveDateTime};
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use common::grpc::oxiproxy;
+
+use crate::entity::{client, node, user, Client, Node, User};
+use crate::migration::get_connection;
+use crate::node_manager::NodeManager;
+use crate::proxy_schedule::Schedule;
+
+/// 检查间隔
+const CHECK_INTERVAL_SECS: u64 = 30;
+
+/// 按时间窗口变化的速度限制
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BandwidthSchedule(Vec<(Schedule, i64)>);
+
+impl BandwidthSchedule {
+    /// 指定时间所在窗口的速率，不在任何窗口内时为 None
+    pub fn rate_at(&self, now: NaiveDateTime) -> Option<i64> {
+        self.0.iter().find(|(s, _)| s.is_active(now)).map(|(_, rate)| *rate)
+    }
+}
+
+/// 解析速率（字节/秒），支持 K / M / G 后缀
+fn parse_rate(s: &str) -> Result<i64> {
+    let s = s.trim();
+    let (number, unit) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => {
+            let unit: f64 = match c.to_ascii_uppercase() {
+                'K' => 1024.0,
+                'M' => 1024.0 * 1024.0,
+                'G' => 1024.0 * 1024.0 * 1024.0,
+                _ => return Err(anyhow!("无效的速率单位: {}", s)),
+            };
+            (&s[..i], unit)
+        }
+        _ => (s, 1.0),
+    };
+    let value: f64 = number.trim().parse().map_err(|_| anyhow!("无效的速率: {}", s))?;
+    if !value.is_finite() || value < 0.0 {
+        return Err(anyhow!("无效的速率: {}", s));
+    }
+    Ok((value * unit).round() as i64)
+}
+
+impl FromStr for BandwidthSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let entries = s
+            .split(';')
+            .filter(|e| !e.trim().is_empty())
+            .map(|entry| {
+                let (window, rate) = entry
+                    .rsplit_once('=')
+                    .ok_or_else(|| anyhow!("缺少速率（格式为 时间窗口=速率）: {}", entry.trim()))?;
+                Ok((window.parse::<Schedule>()?, parse_rate(rate)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if entries.is_empty() {
+            return Err(anyhow!("时间表不能为空"));
+        }
+        Ok(Self(entries))
+    }
+}
+
+/// 校验并规范化 API 传入的时间表，空字符串视为不设置
+pub fn normalize(schedule: Option<String>) -> Result<Option<String>, String> {
+    let Some(schedule) = schedule.filter(|s| !s.trim().is_empty()) else {
+        return Ok(None);
+    };
+    schedule
+        .parse::<BandwidthSchedule>()
+        .map_err(|e| format!("带宽时间表无效: {}", e))?;
+    Ok(Some(schedule.trim().to_string()))
+}
+
+/// 指定时间生效的速度限制（字节/秒，0 表示不限速）
+fn effective_at(base: Option<i64>, schedule: Option<&str>, now: NaiveDateTime) -> i64 {
+    schedule
+        .and_then(|s| s.parse::<BandwidthSchedule>().ok())
+        .and_then(|s| s.rate_at(now))
+        .or(base)
+        .unwrap_or(0)
+        .max(0)
+}
+
+fn effective_now(base: Option<i64>, schedule: Option<&str>) -> i64 {
+    effective_at(base, schedule, Local::now().naive_local())
+}
+
+/// 下发给节点的速度限制
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpeedLimits {
+    /// 节点总带宽（字节/秒，0 表示不限速）
+    pub node: i64,
+    /// 节点带宽饱和时是否按代理公平分配
+    pub fair_share: bool,
+    /// 当前限速的用户及其客户端
+    pub users: Vec<oxiproxy::UserSpeedLimit>,
+}
+
+/// 当前限速的用户（不限速的用户不下发）
+async fn user_limits(db: &DatabaseConnection) -> Result<Vec<oxiproxy::UserSpeedLimit>> {
+    let users = User::find()
+        .filter(
+            Condition::any()
+                .add(user::Column::SpeedLimit.gt(0))
+                .add(user::Column::SpeedLimitSchedule.is_not_null()),
+        )
+        .all(db)
+        .await?;
+
+    let mut limits = Vec::new();
+    for u in users {
+        let speed_limit = effective_now(u.speed_limit, u.speed_limit_schedule.as_deref());
+        if speed_limit == 0 {
+            continue;
+        }
+        let client_ids = Client::find()
+            .filter(client::Column::UserId.eq(u.id))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|c| c.id.to_string())
+            .collect();
+        limits.push(oxiproxy::UserSpeedLimit { user_id: u.id, speed_limit, client_ids });
+    }
+    Ok(limits)
+}
+
+/// 节点当前生效的速度限制（节点注册时下发）
+pub async fn limits_for(node: &node::Model, db: &DatabaseConnection) -> Result<SpeedLimits> {
+    Ok(SpeedLimits {
+        node: effective_now(node.speed_limit, node.speed_limit_schedule.as_deref()),
+        fair_share: node.fair_share,
+        users: user_limits(db).await?,
+    })
+}
+
+/// 计算在线节点（`only` 指定时只计算该节点）当前生效的限制，推送与 `pushed` 中记录不同的部分
+async fn sync(node_manager: &NodeManager, pushed: &mut HashMap<i64, SpeedLimits>, only: Option<i64>) -> Result<()> {
+    let mut online = node_manager.get_loaded_node_ids().await;
+    pushed.retain(|id, _| online.contains(id));
+    if let Some(id) = only {
+        online.retain(|n| *n == id);
+    }
+    if online.is_empty() {
+        return Ok(());
+    }
+
+    let db = get_connection().await;
+    let users = user_limits(db).await?;
+    for n in Node::find().filter(node::Column::Id.is_in(online)).all(db).await? {
+        let limits = SpeedLimits {
+            node: effective_now(n.speed_limit, n.speed_limit_schedule.as_deref()),
+            fair_share: n.fair_share,
+            users: users.clone(),
+        };
+        if pushed.get(&n.id) == Some(&limits) {
+            continue;
+        }
+        match node_manager.send_update_speed_limit(n.id, limits.node, limits.fair_share, limits.users.clone()).await {
+            Ok(()) => {
+                info!(
+                    "已推送速度限制到节点 #{}: {} bytes/s，限速用户 {} 个",
+                    n.id,
+                    limits.node,
+                    limits.users.len()
+                );
+                pushed.insert(n.id, limits);
+            }
+            Err(e) => warn!("推送速度限制到节点 #{} 失败: {}", n.id, e),
+        }
+    }
+    Ok(())
+}
+
+/// 立即向在线节点推送当前生效的限制（修改节点或用户的限速后调用），`only` 指定时只推送该节点
+pub async fn push_now(node_manager: &NodeManager, only: Option<i64>) {
+    if let Err(e) = sync(node_manager, &mut HashMap::new(), only).await {
+        error!("推送速度限制失败: {}", e);
+    }
+}
+
+/// 启动带宽时间表后台任务
+pub fn start_bandwidth_scheduler(node_manager: Arc<NodeManager>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(CHECK_INTERVAL_SECS));
+        // 上一次推送给各节点的限制，用于识别窗口边界
+        let mut pushed: HashMap<i64, SpeedLimits> = HashMap::new();
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = sync(&node_manager, &mut pushed, None).await {
+                error!("检查带宽时间表失败: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_bandwidth_schedule() {
+        let schedule = Some("00:00-08:00=12.5M; sat,sun 08:00-24:00=0");
+        let base = Some(2 * 1024 * 1024);
+        // 2026-03-09 是周一
+        assert_eq!(effective_at(base, schedule, at("2026-03-09 03:00")), 13_107_200);
+        assert_eq!(effective_at(base, schedule, at("2026-03-09 08:00")), 2 * 1024 * 1024);
+        assert_eq!(effective_at(base, schedule, at("2026-03-14 12:00")), 0);
+        assert_eq!(effective_at(None, schedule, at("2026-03-09 12:00")), 0);
+
+        assert_eq!(parse_rate("512k").unwrap(), 512 * 1024);
+        assert!("00:00-08:00".parse::<BandwidthSchedule>().is_err());
+        assert!("00:00-08:00=fast".parse::<BandwidthSchedule>().is_err());
+        assert!("00:00-08:00=-1".parse::<BandwidthSchedule>().is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! 代理抓包文件的接收与清理
+//!
+//! 管理员通过 `POST /api/proxies/{id}/captures` 在代理所在节点上发起限时抓包，节点结束后把
+//! pcapng 文件分块上传（`CaptureChunk`），这里按顺序写入 `./data/captures/{id}.pcapng`。
+//! 每次抓包在 `proxy_capture` 表中保留一条记录作为审计日志（发起人、原因、下载次数），
+//! 文件在 `capture_retention_hours` 小时后由后台任务删除，记录本身保留。
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, Set};
+use tokio::io::AsyncWriteExt;
+use tracing::{error, info, warn};
+
+use common::grpc::oxiproxy;
+
+use crate::entity::proxy_capture::{self, STATUS_COMPLETED, STATUS_DELETED, STATUS_FAILED, STATUS_RUNNING, STATUS_UPLOADING};
+use crate::entity::ProxyCapture;
+use crate::migration::get_connection;
+
+const CAPTURE_DIR: &str = "./data/captures";
+/// 清理间隔
+const CLEANUP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(600);
+/// 抓包结束后等待上传完成的时间，超过后记为失败
+const UPLOAD_GRACE: chrono::Duration = chrono::Duration::minutes(10);
+
+/// 抓包文件路径
+pub fn file_path(id: i64) -> PathBuf {
+    PathBuf::from(CAPTURE_DIR).join(format!("{}.pcapng", id))
+}
+
+/// 删除抓包文件（文件不存在时忽略）
+pub async fn remove_file(id: i64) {
+    if let Err(e) = tokio::fs::remove_file(file_path(id)).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            warn!("删除抓包文件 #{} 失败: {}", id, e);
+        }
+    }
+}
+
+async fn append(id: i64, offset: u64, data: &[u8]) -> Result<()> {
+    tokio::fs::create_dir_all(CAPTURE_DIR).await?;
+    let path = file_path(id);
+    let mut file = if offset == 0 {
+        tokio::fs::File::create(&path).await?
+    } else {
+        tokio::fs::OpenOptions::new().append(true).open(&path).await?
+    };
+    let len = file.metadata().await?.len();
+    if len != offset {
+        bail!("分块不连续：已接收 {} 字节，收到的分块偏移为 {}", len, offset);
+    }
+    file.write_all(data).await?;
+    file.flush().await?;
+    Ok(())
+}
+
+async fn mark_failed(record: proxy_capture::Model, error: String) {
+    let id = record.id;
+    warn!("抓包 #{} 失败: {}", id, error);
+    let mut active = record.into_active_model();
+    active.status = Set(STATUS_FAILED.to_string());
+    active.error = Set(Some(error));
+    active.completed_at = Set(Some(Utc::now().naive_utc()));
+    if let Err(e) = active.update(get_connection().await).await {
+        error!("更新抓包记录 #{} 失败: {}", id, e);
+    }
+    remove_file(id).await;
+}
+
+/// 接收节点上传的抓包文件分块（按到达顺序处理）
+pub async fn receive_chunk(node_id: i64, chunk: oxiproxy::CaptureChunk) {
+    let Ok(id) = chunk.capture_id.parse::<i64>() else {
+        warn!("节点 #{} 上传了未知的抓包: {}", node_id, chunk.capture_id);
+        return;
+    };
+    let db = get_connection().await;
+    let record = match ProxyCapture::find_by_id(id).one(db).await {
+        Ok(Some(r)) if r.node_id == node_id && (r.status == STATUS_RUNNING || r.status == STATUS_UPLOADING) => r,
+        Ok(_) => {
+            warn!("节点 #{} 上传的抓包 #{} 不存在或已结束，忽略", node_id, id);
+            return;
+        }
+        Err(e) => {
+            error!("查询抓包记录 #{} 失败: {}", id, e);
+            return;
+        }
+    };
+
+    if let Some(e) = chunk.error {
+        mark_failed(record, format!("节点抓包失败: {}", e)).await;
+        return;
+    }
+    if let Err(e) = append(id, chunk.offset, &chunk.data).await {
+        mark_failed(record, format!("保存抓包文件失败: {}", e)).await;
+        return;
+    }
+
+    let mut active = record.into_active_model();
+    active.size_bytes = Set((chunk.offset + chunk.data.len() as u64) as i64);
+    active.dropped_packets = Set(chunk.dropped_packets as i64);
+    if chunk.done {
+        active.status = Set(STATUS_COMPLETED.to_string());
+        active.completed_at = Set(Some(Utc::now().naive_utc()));
+    } else {
+        active.status = Set(STATUS_UPLOADING.to_string());
+    }
+    match active.update(db).await {
+        Ok(m) if chunk.done => info!("📦 抓包 #{} 已完成: {} 字节", id, m.size_bytes),
+        Ok(_) => {}
+        Err(e) => error!("更新抓包记录 #{} 失败: {}", id, e),
+    }
+}
+
+/// 删除到期的抓包文件，并把长时间未完成上传的抓包记为失败
+async fn run_cleanup() {
+    let db = get_connection().await;
+    let now = Utc::now().naive_utc();
+
+    match ProxyCapture::find()
+        .filter(proxy_capture::Column::Status.is_in([STATUS_RUNNING, STATUS_UPLOADING]))
+        .all(db)
+        .await
+    {
+        Ok(records) => {
+            for record in records {
+                let deadline = record.created_at + chrono::Duration::seconds(record.duration_secs as i64) + UPLOAD_GRACE;
+                if deadline < now {
+                    mark_failed(record, "超时未收到完整的抓包文件（节点可能已断开）".to_string()).await;
+                }
+            }
+        }
+        Err(e) => error!("查询进行中的抓包失败: {}", e),
+    }
+
+    let expired = match ProxyCapture::find()
+        .filter(proxy_capture::Column::Status.ne(STATUS_DELETED))
+        .filter(proxy_capture::Column::Status.ne(STATUS_RUNNING))
+        .filter(proxy_capture::Column::Status.ne(STATUS_UPLOADING))
+        .filter(proxy_capture::Column::ExpiresAt.lt(now))
+        .all(db)
+        .await
+    {
+        Ok(records) => records,
+        Err(e) => {
+            error!("查询到期的抓包失败: {}", e);
+            return;
+        }
+    };
+    for record in expired {
+        let id = record.id;
+        remove_file(id).await;
+        let mut active = record.into_active_model();
+        active.status = Set(STATUS_DELETED.to_string());
+        match active.update(db).await {
+            Ok(_) => info!("抓包 #{} 已到期，文件已删除", id),
+            Err(e) => error!("更新抓包记录 #{} 失败: {}", id, e),
+        }
+    }
+}
+
+/// 启动抓包文件清理任务
+pub fn start_cleanup_job() {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+        loop {
+            interval.tick().await;
+            run_cleanup().await;
+        }
+    });
+}
@@ -0,0 +1,185 @@
+//! `controller check`：启动前的配置自检
+//!
+//! 依次检查数据库连接与迁移状态、系统配置、Web 和 gRPC 端口、TLS 证书、JWT 密钥和 Web 界面目录，
+//! 有失败项时以非零状态退出。SQLite 文件不存在时不会创建，只检查默认值和环境变量中的配置。
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use base64::Engine;
+use sea_orm_migration::MigratorTrait;
+
+use common::check::{self, Report, Transport};
+
+use crate::config::{self, Config};
+use crate::config_manager::ConfigManager;
+use crate::migration;
+
+/// 连接数据库的超时时间
+const DB_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// JWT 密钥建议的最短长度
+const MIN_JWT_SECRET_LEN: usize = 32;
+
+/// 执行全部检查
+pub async fn run(ui_dir: Option<String>) -> Result<()> {
+    // 校验 TLS 证书需要 rustls 的默认加密实现
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let mut report = Report::default();
+
+    let db_ready = check_database(&mut report).await;
+    let mut config = if db_ready { config::init_config().await } else { Config::from_env() };
+    if ui_dir.is_some() {
+        config.ui_dir = ui_dir;
+    }
+    let config_manager = ConfigManager::new();
+    if db_ready {
+        match config_manager.load_from_db().await {
+            Ok(()) => report.ok("系统配置", "已加载"),
+            Err(e) => report.fail("系统配置", format!("加载失败: {}", e), "确认数据库迁移已执行（启动时自动执行）"),
+        }
+    }
+
+    check::check_port(&mut report, "Web 端口", config.web_port, Transport::Tcp);
+    if config.internal_port == config.web_port {
+        report.fail(
+            "gRPC 端口",
+            format!("与 Web 端口相同（{}）", config.web_port),
+            "修改 internal_port / OXIPROXY_INTERNAL_PORT",
+        );
+    } else {
+        check::check_port(&mut report, "gRPC 端口", config.internal_port, Transport::Tcp);
+    }
+
+    check_tls(&mut report, &config_manager, "Web TLS", "web_tls").await;
+    check_tls(&mut report, &config_manager, "gRPC TLS", "grpc_tls").await;
+    check_jwt_secret(&mut report, &config);
+
+    if let Some(dir) = &config.ui_dir {
+        if Path::new(dir).join("index.html").is_file() {
+            report.ok("Web 界面目录", dir);
+        } else {
+            report.fail(
+                "Web 界面目录",
+                format!("{} 中没有 index.html", dir),
+                "检查 --ui-dir / OXIPROXY_UI_DIR 或先构建前端；不设置时使用内嵌界面",
+            );
+        }
+    }
+
+    report.finish()
+}
+
+/// 检查数据库能否连接以及迁移状态，返回数据库是否可用
+async fn check_database(report: &mut Report) -> bool {
+    const ITEM: &str = "数据库";
+    let url = migration::database_url();
+
+    if let Some(path) = migration::sqlite_file(&url) {
+        if !path.exists() {
+            report.warn(ITEM, format!("{} 不存在，首次启动时自动创建", path.display()));
+            return false;
+        }
+    }
+
+    let db = match tokio::time::timeout(DB_TIMEOUT, migration::connect(&url)).await {
+        Ok(Ok(db)) => db,
+        Ok(Err(e)) => {
+            report.fail(
+                ITEM,
+                format!("连接 {} 失败: {}", url, e),
+                "检查 OXIPROXY_DATABASE_URL 以及数据库文件和所在目录的读写权限",
+            );
+            return false;
+        }
+        Err(_) => {
+            report.fail(
+                ITEM,
+                format!("连接 {} 超时（{} 秒）", url, DB_TIMEOUT.as_secs()),
+                "数据库可能被其他进程锁定，检查 OXIPROXY_DB_BUSY_TIMEOUT_MS",
+            );
+            return false;
+        }
+    };
+    if let Err(e) = db.ping().await {
+        report.fail(ITEM, format!("{} 无响应: {}", url, e), "检查数据库文件是否损坏");
+        return false;
+    }
+    report.ok(ITEM, format!("{} 连接正常", url));
+
+    match migration::Migrator::get_pending_migrations(&db).await {
+        Ok(pending) if pending.is_empty() => report.ok("数据库迁移", "已是最新"),
+        Ok(pending) => report.warn(
+            "数据库迁移",
+            format!("有 {} 个待执行的迁移，启动时自动执行，建议先备份数据库", pending.len()),
+        ),
+        Err(e) => report.fail("数据库迁移", format!("读取迁移状态失败: {}", e), "检查数据库文件是否损坏"),
+    }
+    true
+}
+
+/// 读取 TLS 证书和私钥：优先数据库中 base64 编码的内容，其次文件路径
+async fn read_tls_pem(config_manager: &ConfigManager, prefix: &str) -> Result<(Vec<u8>, Vec<u8>, String), String> {
+    let cert_content = config_manager.get_string(&format!("{}_cert_content", prefix), "").await;
+    let key_content = config_manager.get_string(&format!("{}_key_content", prefix), "").await;
+    if !cert_content.is_empty() && !key_content.is_empty() {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let cert = engine.decode(&cert_content).map_err(|e| format!("证书 base64 解码失败: {}", e))?;
+        let key = engine.decode(&key_content).map_err(|e| format!("私钥 base64 解码失败: {}", e))?;
+        return Ok((cert, key, "数据库".to_string()));
+    }
+
+    let cert_path = config_manager.get_string(&format!("{}_cert_path", prefix), "").await;
+    let key_path = config_manager.get_string(&format!("{}_key_path", prefix), "").await;
+    if cert_path.is_empty() || key_path.is_empty() {
+        return Err("已启用但未配置证书".to_string());
+    }
+    let cert = tokio::fs::read(&cert_path).await.map_err(|e| format!("读取证书文件 {} 失败: {}", cert_path, e))?;
+    let key = tokio::fs::read(&key_path).await.map_err(|e| format!("读取私钥文件 {} 失败: {}", key_path, e))?;
+    Ok((cert, key, format!("文件 {}", cert_path)))
+}
+
+async fn check_tls(report: &mut Report, config_manager: &ConfigManager, item: &str, prefix: &str) {
+    if !config_manager.get_bool(&format!("{}_enabled", prefix), false).await {
+        return;
+    }
+    let (cert, key, source) = match read_tls_pem(config_manager, prefix).await {
+        Ok(pem) => pem,
+        Err(e) => {
+            return report.fail(
+                item,
+                e,
+                format!(
+                    "设置 {p}_cert_content / {p}_key_content（base64 编码的 PEM）或 {p}_cert_path / {p}_key_path",
+                    p = prefix
+                ),
+            )
+        }
+    };
+    match crate::tls_apply::validate_pem(&cert, &key) {
+        Ok(_) => report.ok(item, format!("证书和私钥有效（{}）", source)),
+        Err(e) => report.fail(
+            item,
+            format!("{}: {}", source, e),
+            "证书和私钥需为 PEM 格式且相互匹配，证书文件应包含完整证书链",
+        ),
+    }
+}
+
+fn check_jwt_secret(report: &mut Report, config: &Config) {
+    const ITEM: &str = "JWT 密钥";
+    let configured = common::env::var("OXIPROXY_JWT_SECRET")
+        .or_else(|| common::env::var("JWT_SECRET"))
+        .or_else(|| config.jwt_secret.clone().filter(|s| !s.is_empty()));
+
+    match configured {
+        Some(secret) if secret.len() < MIN_JWT_SECRET_LEN => report.warn(
+            ITEM,
+            format!("长度只有 {} 个字符，建议至少 {} 个字符的随机密钥", secret.len(), MIN_JWT_SECRET_LEN),
+        ),
+        Some(_) => report.ok(ITEM, "已配置"),
+        None if Path::new("./data/jwt_secret.key").is_file() => report.ok(ITEM, "使用 ./data/jwt_secret.key"),
+        None => report.ok(ITEM, "首次启动时自动生成并保存到 ./data/jwt_secret.key"),
+    }
+}
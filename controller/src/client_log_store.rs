@@ -0,0 +1,134 @@
+//! 客户端上传日志缓存
+//!
+//! 客户端按级别过滤、限速后批量上传日志（见 client `log_shipper`），Controller 为每个客户端
+//! 在内存中保留最近的条目，客户端离线后仍可通过 `GET /api/clients/{id}/logs` 查看。
+//! 单批条数超出上限的部分直接丢弃，不信任客户端自身的限速。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+
+use chrono::{DateTime, Utc};
+
+use common::grpc::oxiproxy;
+use common::protocol::control::LogEntry;
+
+/// 每个客户端保留的日志条数
+const MAX_ENTRIES_PER_CLIENT: usize = 2000;
+/// 单批最多接受的条数
+const MAX_BATCH: usize = 500;
+
+fn store() -> &'static Mutex<HashMap<i64, VecDeque<LogEntry>>> {
+    static STORE: OnceLock<Mutex<HashMap<i64, VecDeque<LogEntry>>>> = OnceLock::new();
+    STORE.get_or_init(Default::default)
+}
+
+/// 记录客户端上传的一批日志，客户端报告的丢弃条数记为一条 WARN
+pub fn record(client_id: i64, upload: oxiproxy::ClientLogUpload) {
+    let received = upload.logs.len();
+    let mut entries: Vec<LogEntry> = upload
+        .logs
+        .into_iter()
+        .take(MAX_BATCH)
+        .map(|l| LogEntry { timestamp: l.timestamp, level: l.level, message: l.message })
+        .collect();
+    let dropped = upload.dropped + received.saturating_sub(MAX_BATCH) as u64;
+    if dropped > 0 {
+        entries.push(LogEntry {
+            timestamp: Utc::now().to_rfc3339(),
+            level: "WARN".to_string(),
+            message: format!("{} 条客户端日志因限速或缓冲区溢出未上传", dropped),
+        });
+    }
+
+    let mut store = store().lock().unwrap_or_else(|e| e.into_inner());
+    let logs = store.entry(client_id).or_default();
+    for entry in entries {
+        if logs.len() >= MAX_ENTRIES_PER_CLIENT {
+            logs.pop_front();
+        }
+        logs.push_back(entry);
+    }
+}
+
+/// 客户端已上传的日志（按上传顺序）
+pub fn get(client_id: i64) -> Vec<LogEntry> {
+    let store = store().lock().unwrap_or_else(|e| e.into_inner());
+    store.get(&client_id).map(|logs| logs.iter().cloned().collect()).unwrap_or_default()
+}
+
+/// 删除客户端时清除其日志
+pub fn remove(client_id: i64) {
+    store().lock().unwrap_or_else(|e| e.into_inner()).remove(&client_id);
+}
+
+/// 日志查询条件
+#[derive(Debug, Default)]
+pub struct LogFilter {
+    /// 只保留级别不低于该级别的日志
+    pub min_level: Option<tracing::Level>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// 最多返回最近的 N 条
+    pub limit: usize,
+}
+
+fn parse_timestamp(entry: &LogEntry) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(&entry.timestamp).ok().map(|t| t.with_timezone(&Utc))
+}
+
+/// 合并上传的日志和实时获取的日志：去除重复条目、按时间排序并应用过滤条件
+pub fn merge(uploaded: Vec<LogEntry>, live: Vec<LogEntry>, filter: &LogFilter) -> Vec<LogEntry> {
+    let mut merged: Vec<(DateTime<Utc>, LogEntry)> = uploaded
+        .into_iter()
+        .chain(live)
+        .filter_map(|entry| Some((parse_timestamp(&entry)?, entry)))
+        .filter(|(time, entry)| {
+            filter.min_level.is_none_or(|min| entry.level.parse::<tracing::Level>().is_ok_and(|level| level <= min))
+                && filter.since.is_none_or(|since| *time >= since)
+                && filter.until.is_none_or(|until| *time <= until)
+        })
+        .collect();
+    // 两边获取的同一条日志排序后相邻
+    merged.sort_by(|(ta, a), (tb, b)| (ta, &a.level, &a.message).cmp(&(tb, &b.level, &b.message)));
+    merged.dedup_by(|(ta, a), (tb, b)| ta == tb && a.level == b.level && a.message == b.message);
+
+    let start = merged.len().saturating_sub(filter.limit);
+    merged.into_iter().skip(start).map(|(_, entry)| entry).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: &str, level: &str, message: &str) -> LogEntry {
+        LogEntry { timestamp: timestamp.to_string(), level: level.to_string(), message: message.to_string() }
+    }
+
+    #[test]
+    fn test_merge() {
+        let uploaded = vec![
+            entry("2026-01-01T00:00:01+00:00", "WARN", "a"),
+            entry("2026-01-01T00:00:03+00:00", "ERROR", "c"),
+        ];
+        let live = vec![
+            entry("2026-01-01T00:00:02+00:00", "INFO", "b"),
+            entry("2026-01-01T00:00:03+00:00", "ERROR", "c"),
+            entry("2026-01-01T00:00:04+00:00", "DEBUG", "d"),
+        ];
+        let messages = |logs: Vec<LogEntry>| logs.into_iter().map(|e| e.message).collect::<Vec<_>>();
+
+        let all = LogFilter { limit: 100, ..Default::default() };
+        assert_eq!(messages(merge(uploaded.clone(), live.clone(), &all)), vec!["a", "b", "c", "d"]);
+
+        let filter = LogFilter { min_level: Some(tracing::Level::INFO), limit: 2, ..Default::default() };
+        assert_eq!(messages(merge(uploaded.clone(), live.clone(), &filter)), vec!["b", "c"]);
+
+        let filter = LogFilter {
+            since: Some("2026-01-01T00:00:02Z".parse().unwrap()),
+            until: Some("2026-01-01T00:00:03Z".parse().unwrap()),
+            limit: 100,
+            ..Default::default()
+        };
+        assert_eq!(messages(merge(uploaded, live, &filter)), vec!["b", "c"]);
+    }
+}
@@ -4,31 +4,99 @@
 //! 当代理配置变更时推送 ProxyListUpdate 通知。
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{mpsc, RwLock};
-use tracing::{debug, error, info};
+use tokio::sync::{mpsc, Notify, RwLock};
+use tracing::{debug, error, info, warn};
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 
 use common::grpc::oxiproxy;
 use common::grpc::pending_requests::PendingRequests;
-use common::KcpConfig;
+use common::{KcpConfig, QuicConfig};
 use common::protocol::control::LogEntry;
 
-use crate::entity::{Client, Node, Proxy, proxy, node};
+use crate::entity::{Client, Node, Proxy, Visitor, proxy, node, visitor};
+use crate::duplicate_login::Policy;
 use crate::migration::get_connection;
 
+type ClientSender = mpsc::Sender<Result<oxiproxy::ControllerToClientMessage, tonic::Status>>;
+
+/// 连接序号，区分同一客户端先后建立的连接
+static NEXT_CONN_ID: AtomicU64 = AtomicU64::new(1);
+
 /// 单个客户端的流连接
 struct ClientStream {
-    tx: mpsc::Sender<Result<oxiproxy::ControllerToClientMessage, tonic::Status>>,
+    tx: ClientSender,
     pending: PendingRequests<oxiproxy::AgentClientResponse>,
+    conn_id: u64,
+    /// 客户端实例 ID（首次启动时生成并保存在客户端本地），旧版客户端不上报
+    instance_id: Option<String>,
+    /// 被新连接取代或被踢下线时通知连接的处理任务退出
+    superseded: Arc<Notify>,
+}
+
+impl ClientStream {
+    /// 是否为同一实例（任一方未上报实例 ID 时视为同一实例）
+    fn same_instance(&self, instance_id: &Option<String>) -> bool {
+        self.instance_id.is_none() || instance_id.is_none() || self.instance_id == *instance_id
+    }
+
+    /// 通知客户端已被踢下线，并让连接的处理任务退出
+    fn kick(&self, message: String) {
+        let msg = oxiproxy::ControllerToClientMessage {
+            payload: Some(oxiproxy::controller_to_client_message::Payload::Error(oxiproxy::ErrorNotification {
+                code: KICKED.to_string(),
+                message,
+            })),
+        };
+        let _ = self.tx.try_send(Ok(msg));
+        self.superseded.notify_one();
+    }
+}
+
+/// 客户端的所有连接：主连接处理请求 / 响应；重复登录策略为副本模式时，其他实例的连接只接收配置推送，
+/// 主连接断开后由最早的副本接替
+struct ClientStreams {
+    primary: ClientStream,
+    replicas: Vec<ClientStream>,
+}
+
+impl ClientStreams {
+    fn all(&self) -> impl Iterator<Item = &ClientStream> {
+        std::iter::once(&self.primary).chain(self.replicas.iter())
+    }
+}
+
+/// 被踢下线时发给原实例的错误码，客户端收到后停止重连
+pub const KICKED: &str = "kicked";
+
+/// 连接的接入方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Admission {
+    /// 该客户端没有其他连接
+    Fresh,
+    /// 同一实例重连，取代了原来的连接
+    Reconnected,
+    /// 踢下线了其他实例（实例 ID）
+    Kicked(Vec<String>),
+    /// 作为副本加入
+    Replica,
+}
+
+/// 注册成功的连接
+pub struct Registration {
+    pub conn_id: u64,
+    pub admission: Admission,
+    /// 收到通知表示连接已被新连接取代或被踢下线，处理任务应直接退出
+    pub superseded: Arc<Notify>,
 }
 
 /// 管理已连接的 Agent Client 流
 #[derive(Clone)]
 pub struct ClientStreamManager {
-    /// client_id -> stream
-    streams: Arc<RwLock<HashMap<i64, ClientStream>>>,
+    /// client_id -> streams
+    streams: Arc<RwLock<HashMap<i64, ClientStreams>>>,
 }
 
 impl ClientStreamManager {
@@ -38,33 +106,168 @@ impl ClientStreamManager {
         }
     }
 
-    /// 注册一个 Agent Client 流
+    /// 注册一个 Agent Client 流，`auth_response` 为认证成功响应，在加入流表前写入，保证客户端先收到它
+    ///
+    /// 该客户端已有连接时：同一实例（或任一方未上报实例 ID）的新连接取代旧连接，旧连接多半是断线后
+    /// 尚未超时的半开连接；不同实例按重复登录策略处理，被拒绝时返回已在线的实例 ID 和错误信息。
     pub async fn register(
         &self,
         client_id: i64,
-        tx: mpsc::Sender<Result<oxiproxy::ControllerToClientMessage, tonic::Status>>,
-    ) {
-        info!("Agent Client #{} 已连接", client_id);
+        instance_id: Option<String>,
+        policy: Policy,
+        tx: ClientSender,
+        auth_response: oxiproxy::ControllerToClientMessage,
+    ) -> Result<Registration, (Option<String>, String)> {
+        let mut streams = self.streams.write().await;
+        let admission = match streams.get(&client_id) {
+            None => Admission::Fresh,
+            Some(existing) if existing.all().any(|s| s.same_instance(&instance_id)) => Admission::Reconnected,
+            Some(existing) => match policy {
+                Policy::RejectNew => {
+                    let online = existing.primary.instance_id.clone();
+                    let message = format!("该 token 已被另一个客户端实例 {} 使用", online.as_deref().unwrap_or_default());
+                    return Err((online, message));
+                }
+                Policy::KickOld => Admission::Kicked(existing.all().filter_map(|s| s.instance_id.clone()).collect()),
+                Policy::Replicas => Admission::Replica,
+            },
+        };
+        if tx.try_send(Ok(auth_response)).is_err() {
+            return Err((None, "连接已关闭".to_string()));
+        }
         let stream = ClientStream {
             tx,
             pending: PendingRequests::new(),
+            conn_id: NEXT_CONN_ID.fetch_add(1, Ordering::Relaxed),
+            instance_id,
+            superseded: Arc::new(Notify::new()),
         };
-        self.streams.write().await.insert(client_id, stream);
+        let registration = Registration {
+            conn_id: stream.conn_id,
+            admission: admission.clone(),
+            superseded: stream.superseded.clone(),
+        };
+        match admission {
+            Admission::Fresh => {
+                streams.insert(client_id, ClientStreams { primary: stream, replicas: Vec::new() });
+            }
+            Admission::Reconnected => {
+                let existing = streams.get_mut(&client_id).expect("已检查存在");
+                let old = if existing.primary.same_instance(&stream.instance_id) {
+                    std::mem::replace(&mut existing.primary, stream)
+                } else {
+                    let index = existing.replicas.iter().position(|s| s.same_instance(&stream.instance_id)).expect("已检查存在");
+                    std::mem::replace(&mut existing.replicas[index], stream)
+                };
+                warn!("Agent Client #{} 的新连接取代了旧连接", client_id);
+                old.superseded.notify_one();
+            }
+            Admission::Kicked(_) => {
+                let message = format!(
+                    "已被同一 token 的另一个客户端实例 {} 踢下线",
+                    stream.instance_id.as_deref().unwrap_or_default()
+                );
+                let old = streams.insert(client_id, ClientStreams { primary: stream, replicas: Vec::new() });
+                for kicked in old.iter().flat_map(|s| s.all()) {
+                    kicked.kick(message.clone());
+                }
+                warn!("Agent Client #{} 的新实例踢下线了原实例", client_id);
+            }
+            Admission::Replica => {
+                let existing = streams.get_mut(&client_id).expect("已检查存在");
+                existing.replicas.push(stream);
+                info!("Agent Client #{} 的另一个实例作为副本连接，当前 {} 个实例在线", client_id, existing.replicas.len() + 1);
+            }
+        }
+        info!("Agent Client #{} 已连接", client_id);
+        Ok(registration)
+    }
+
+    /// 向所有已连接客户端下发重连延迟（0 ~ `spread` 之间随机），Controller 关闭前调用，返回下发的客户端数
+    pub async fn broadcast_reconnect_hint(&self, spread: Duration) -> usize {
+        let streams = self.streams.read().await;
+        let mut sent = 0;
+        for stream in streams.values().flat_map(|s| s.all()) {
+            let delay = common::grpc::reconnect::jitter(spread);
+            let msg = oxiproxy::ControllerToClientMessage {
+                payload: Some(oxiproxy::controller_to_client_message::Payload::ReconnectHint(
+                    oxiproxy::ReconnectHint { delay_ms: delay.as_millis() as u32 },
+                )),
+            };
+            if stream.tx.try_send(Ok(msg)).is_ok() {
+                sent += 1;
+            }
+        }
+        sent
+    }
+
+    /// 移除连接 `conn_id` 注册的流，返回该客户端是否已没有连接（已被新连接取代或仍有其他实例在线时为 false）
+    pub async fn unregister(&self, client_id: i64, conn_id: u64) -> bool {
+        let mut streams = self.streams.write().await;
+        let Some(existing) = streams.get_mut(&client_id) else {
+            info!("Agent Client #{} 已断开", client_id);
+            return true;
+        };
+        if existing.primary.conn_id == conn_id {
+            if existing.replicas.is_empty() {
+                streams.remove(&client_id);
+                info!("Agent Client #{} 已断开", client_id);
+                return true;
+            }
+            existing.primary = existing.replicas.remove(0);
+            info!("Agent Client #{} 的主连接断开，由副本接替", client_id);
+        } else if let Some(index) = existing.replicas.iter().position(|s| s.conn_id == conn_id) {
+            existing.replicas.remove(index);
+            info!("Agent Client #{} 的一个副本断开", client_id);
+        }
+        false
+    }
+
+    /// 客户端当前是否已连接
+    pub async fn is_connected(&self, client_id: i64) -> bool {
+        self.streams.read().await.contains_key(&client_id)
     }
 
-    /// 移除一个 Agent Client 流
-    pub async fn unregister(&self, client_id: i64) {
-        info!("Agent Client #{} 已断开", client_id);
-        self.streams.write().await.remove(&client_id);
+    /// 向客户端发送错误通知后断开连接（例如客户端到期）
+    pub async fn disconnect(&self, client_id: i64, code: &str, message: String) {
+        if let Some(streams) = self.streams.write().await.remove(&client_id) {
+            for stream in streams.all() {
+                let msg = oxiproxy::ControllerToClientMessage {
+                    payload: Some(oxiproxy::controller_to_client_message::Payload::Error(oxiproxy::ErrorNotification {
+                        code: code.to_string(),
+                        message: message.clone(),
+                    })),
+                };
+                let _ = stream.tx.send(Ok(msg)).await;
+            }
+            info!("Agent Client #{} 已被断开", client_id);
+        }
     }
 
-    /// 通知指定客户端代理配置已变更
+    /// 通知指定客户端代理配置已变更（目标代理属于该客户端的访客客户端也一并刷新）
     pub async fn notify_proxy_change(&self, client_id_str: &str) {
         let client_id: i64 = match client_id_str.parse() {
             Ok(id) => id,
             Err(_) => return,
         };
 
+        self.push_proxy_update(client_id).await;
+        match self.visitor_client_ids(client_id_str).await {
+            Ok(visitor_clients) => {
+                for visitor_client in visitor_clients.into_iter().filter(|&id| id != client_id) {
+                    self.push_proxy_update(visitor_client).await;
+                }
+            }
+            Err(e) => error!("查询访客代理失败: {}", e),
+        }
+    }
+
+    /// 向指定客户端推送最新的代理列表
+    async fn push_proxy_update(&self, client_id: i64) {
+        if !self.is_connected(client_id).await {
+            return;
+        }
+
         let update = match self.build_proxy_list_update(client_id).await {
             Ok(u) => u,
             Err(e) => {
@@ -73,10 +276,11 @@ impl ClientStreamManager {
             }
         };
 
+        // 副本模式下每个实例都需要完整的代理列表
         let streams = self.streams.read().await;
-        if let Some(stream) = streams.get(&client_id) {
+        for stream in streams.get(&client_id).into_iter().flat_map(|s| s.all()) {
             let msg = oxiproxy::ControllerToClientMessage {
-                payload: Some(oxiproxy::controller_to_client_message::Payload::ProxyUpdate(update)),
+                payload: Some(oxiproxy::controller_to_client_message::Payload::ProxyUpdate(update.clone())),
             };
             if let Err(e) = stream.tx.send(Ok(msg)).await {
                 error!("推送代理更新到 Client #{} 失败: {}", client_id, e);
@@ -86,13 +290,46 @@ impl ClientStreamManager {
         }
     }
 
+    /// 访客代理指向该客户端代理的客户端；目标代理已删除的访客也包括在内，以便及时停止监听
+    async fn visitor_client_ids(&self, client_id_str: &str) -> anyhow::Result<Vec<i64>> {
+        let db = get_connection().await;
+        let visitors = Visitor::find().all(db).await?;
+        if visitors.is_empty() {
+            return Ok(Vec::new());
+        }
+        let proxy_ids: Vec<i64> = visitors.iter().map(|v| v.proxy_id).collect();
+        let targets: HashMap<i64, String> = Proxy::find()
+            .filter(proxy::Column::Id.is_in(proxy_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|p| (p.id, p.client_id))
+            .collect();
+
+        let mut ids: Vec<i64> = visitors
+            .into_iter()
+            .filter(|v| targets.get(&v.proxy_id).is_none_or(|owner| owner == client_id_str))
+            .filter_map(|v| v.client_id.parse().ok())
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        Ok(ids)
+    }
+
     /// 通知某个节点上的所有客户端刷新配置
     pub async fn notify_clients_for_node(&self, node_id: i64) {
+        for client_id_str in self.client_ids_for_node(node_id).await {
+            self.notify_proxy_change(&client_id_str).await;
+        }
+    }
+
+    /// 在该节点上有启用代理的客户端（不重复）
+    pub async fn client_ids_for_node(&self, node_id: i64) -> Vec<String> {
         let db = get_connection().await;
 
-        // 查询该节点上所有启用的代理
+        // 查询发布在该节点上的所有启用的代理
         let proxies = match Proxy::find()
-            .filter(proxy::Column::NodeId.eq(node_id))
+            .filter(crate::anycast::maybe_on_node(node_id))
             .filter(proxy::Column::Enabled.eq(true))
             .all(db)
             .await
@@ -100,19 +337,25 @@ impl ClientStreamManager {
             Ok(p) => p,
             Err(e) => {
                 error!("查询节点 #{} 的代理失败: {}", node_id, e);
-                return;
+                return Vec::new();
             }
         };
 
         // 收集所有不重复的 client_id
-        let mut client_ids = std::collections::HashSet::new();
-        for proxy in proxies {
-            client_ids.insert(proxy.client_id);
-        }
+        let mut seen = std::collections::HashSet::new();
+        proxies
+            .into_iter()
+            .filter(|proxy| proxy.is_published_on(node_id))
+            .map(|proxy| proxy.client_id)
+            .filter(|client_id| seen.insert(client_id.clone()))
+            .collect()
+    }
 
-        // 逐个通知客户端
-        for client_id_str in client_ids {
-            self.notify_proxy_change(&client_id_str).await;
+    /// 通知所有在线客户端刷新配置（例如端口黑名单变更后）
+    pub async fn notify_all_clients(&self) {
+        let client_ids: Vec<i64> = self.streams.read().await.keys().cloned().collect();
+        for client_id in client_ids {
+            self.notify_proxy_change(&client_id.to_string()).await;
         }
     }
 
@@ -141,8 +384,8 @@ impl ClientStreamManager {
     /// 完成一个待处理的请求（由 AgentClientResponse 触发）
     pub async fn complete_pending_request(&self, client_id: i64, response: &oxiproxy::AgentClientResponse) {
         let streams = self.streams.read().await;
-        if let Some(stream) = streams.get(&client_id) {
-            stream.pending.complete(&response.request_id, response.clone()).await;
+        if let Some(streams) = streams.get(&client_id) {
+            streams.primary.pending.complete(&response.request_id, response.clone()).await;
         }
     }
 
@@ -151,6 +394,7 @@ impl ClientStreamManager {
         let (request_id, rx, tx_clone) = {
             let streams = self.streams.read().await;
             let stream = streams.get(&client_id)
+                .map(|s| &s.primary)
                 .ok_or_else(|| anyhow::anyhow!("客户端 #{} 未连接", client_id))?;
 
             let (request_id, rx) = stream.pending.register().await;
@@ -183,11 +427,16 @@ impl ClientStreamManager {
         }
     }
 
-    /// 向客户端发送软件更新指令
-    pub async fn send_software_update(&self, client_id: i64) -> anyhow::Result<oxiproxy::SoftwareUpdateResponse> {
+    /// 向客户端发送软件更新指令（`target_version` 为空时更新到最新版本）
+    pub async fn send_software_update(
+        &self,
+        client_id: i64,
+        target_version: Option<String>,
+    ) -> anyhow::Result<oxiproxy::SoftwareUpdateResponse> {
         let (request_id, rx, tx_clone) = {
             let streams = self.streams.read().await;
             let stream = streams.get(&client_id)
+                .map(|s| &s.primary)
                 .ok_or_else(|| anyhow::anyhow!("客户端 #{} 未连接", client_id))?;
 
             let (request_id, rx) = stream.pending.register().await;
@@ -198,6 +447,7 @@ impl ClientStreamManager {
             payload: Some(oxiproxy::controller_to_client_message::Payload::SoftwareUpdate(
                 oxiproxy::SoftwareUpdateCommand {
                     request_id: request_id.clone(),
+                    target_version,
                 },
             )),
         };
@@ -232,25 +482,42 @@ impl ClientStreamManager {
             .all(db)
             .await?;
 
-        // 按 node_id 分组（只使用 proxy.node_id）
+        // 按节点分组，多节点发布的代理出现在每个发布节点的分组中（没有指定节点的代理跳过）
         let mut node_proxy_map: HashMap<i64, Vec<oxiproxy::ProxyInfo>> = HashMap::new();
         for p in &proxies {
-            let nid = match p.node_id {
-                Some(id) => id,
-                None => continue, // 跳过没有指定节点的代理
-            };
+            for nid in p.node_ids() {
+                node_proxy_map
+                    .entry(nid)
+                    .or_default()
+                    .push(oxiproxy::ProxyInfo {
+                        proxy_id: p.id,
+                        name: p.name.clone(),
+                        proxy_type: p.proxy_type.clone(),
+                        local_ip: p.local_ip.clone(),
+                        local_port: p.local_port as i32,
+                        remote_port: p.remote_port as i32,
+                        enabled: p.enabled,
+                        local_pool_size: p.local_pool_size.unwrap_or(0).max(0) as u32,
+                        local_source: p.local_source.clone(),
+                    });
+            }
+        }
 
+        // 临时隧道：客户端可能只通过临时隧道使用某个节点，同样需要建立连接
+        for t in crate::temporary_tunnel::active_tunnels(client_id, None, db).await? {
             node_proxy_map
-                .entry(nid)
+                .entry(t.node_id)
                 .or_default()
                 .push(oxiproxy::ProxyInfo {
-                    proxy_id: p.id,
-                    name: p.name.clone(),
-                    proxy_type: p.proxy_type.clone(),
-                    local_ip: p.local_ip.clone(),
-                    local_port: p.local_port as i32,
-                    remote_port: p.remote_port as i32,
-                    enabled: p.enabled,
+                    proxy_id: crate::temporary_tunnel::proxy_id(t.id),
+                    name: format!("temporary-{}", t.id),
+                    proxy_type: "tcp".to_string(),
+                    local_ip: t.local_ip,
+                    local_port: t.local_port as i32,
+                    remote_port: t.remote_port as i32,
+                    enabled: true,
+                    local_pool_size: 0,
+                    local_source: None,
                 });
         }
 
@@ -265,18 +532,24 @@ impl ClientStreamManager {
                 .await?
         };
 
+        let tenant_id = crate::port_blocklist::client_tenant_id(&client_id.to_string(), db).await?;
+        let allowed_targets: Vec<String> = client_model
+            .allowed_targets
+            .as_deref()
+            .map(|s| s.split(',').map(|r| r.trim().to_string()).filter(|r| !r.is_empty()).collect())
+            .unwrap_or_default();
+
         let mut server_groups = Vec::new();
         for n in nodes {
             if let Some(proxy_list) = node_proxy_map.remove(&n.id) {
                 let kcp = n.kcp_config
                     .as_deref()
                     .and_then(|s| serde_json::from_str::<KcpConfig>(s).ok())
-                    .map(|k| oxiproxy::GrpcKcpConfig {
-                        nodelay: k.nodelay,
-                        interval: k.interval,
-                        resend: k.resend,
-                        nc: k.nc,
-                    });
+                    .map(|k| oxiproxy::GrpcKcpConfig::from(&k));
+                let quic = n.quic_config
+                    .as_deref()
+                    .and_then(|s| serde_json::from_str::<QuicConfig>(s).ok())
+                    .map(|q| oxiproxy::GrpcQuicConfig::from(&q));
 
                 server_groups.push(oxiproxy::ServerProxyGroup {
                     node_id: n.id,
@@ -285,6 +558,10 @@ impl ClientStreamManager {
                     protocol: n.tunnel_protocol,
                     kcp,
                     proxies: proxy_list,
+                    quic,
+                    blocked_targets: crate::port_blocklist::local_target_patterns(n.id, tenant_id, db).await?,
+                    allowed_targets: allowed_targets.clone(),
+                    nat_probe_port: n.nat_probe_port.map(|p| p as u32),
                 });
             }
         }
@@ -293,6 +570,55 @@ impl ClientStreamManager {
             client_id: client_model.id,
             client_name: client_model.name,
             server_groups,
+            visitors: build_visitors(client_id, db).await?,
         })
     }
 }
+
+/// 客户端的访客代理，目标代理不存在、已禁用或无法确定访问地址的跳过
+async fn build_visitors(client_id: i64, db: &sea_orm::DatabaseConnection) -> anyhow::Result<Vec<oxiproxy::VisitorInfo>> {
+    let visitors = Visitor::find()
+        .filter(visitor::Column::ClientId.eq(client_id.to_string()))
+        .filter(visitor::Column::Enabled.eq(true))
+        .all(db)
+        .await?;
+    if visitors.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let proxy_ids: Vec<i64> = visitors.iter().map(|v| v.proxy_id).collect();
+    let targets: HashMap<i64, proxy::Model> = Proxy::find()
+        .filter(proxy::Column::Id.is_in(proxy_ids))
+        .filter(proxy::Column::Enabled.eq(true))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|p| (p.id, p))
+        .collect();
+    let node_ids: Vec<i64> = targets.values().filter_map(|p| p.node_id).collect();
+    let nodes: HashMap<i64, node::Model> = Node::find()
+        .filter(node::Column::Id.is_in(node_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|n| (n.id, n))
+        .collect();
+
+    Ok(visitors
+        .into_iter()
+        .filter_map(|v| {
+            let target = targets.get(&v.proxy_id)?;
+            let node = target.node_id.and_then(|id| nodes.get(&id));
+            let target_addr = crate::endpoint::visitor_host(target, node)?;
+            Some(oxiproxy::VisitorInfo {
+                visitor_id: v.id,
+                name: v.name,
+                proxy_type: target.proxy_type.clone(),
+                bind_ip: v.bind_ip,
+                bind_port: v.bind_port as u32,
+                target_addr,
+                target_port: target.remote_port as u32,
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,125 @@
+//! 阿里云 ECS 安全组（V3 签名 ACS3-HMAC-SHA256）
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::{FirewallProvider, PortRule, RULE_DESCRIPTION};
+use crate::dns::route53::{canonical_query, hmac_sha256};
+
+const API_VERSION: &str = "2014-05-26";
+
+pub struct EcsSecurityGroup {
+    http: reqwest::Client,
+    region_id: String,
+    group_id: String,
+    access_key_id: String,
+    access_key_secret: String,
+    source_ranges: Vec<String>,
+}
+
+impl EcsSecurityGroup {
+    pub fn new(
+        http: reqwest::Client,
+        region_id: String,
+        group_id: String,
+        access_key_id: String,
+        access_key_secret: String,
+        source_ranges: Vec<String>,
+    ) -> Self {
+        Self { http, region_id, group_id, access_key_id, access_key_secret, source_ranges }
+    }
+
+    async fn call(&self, action: &str, rule: PortRule) -> Result<()> {
+        let host = format!("ecs.{}.aliyuncs.com", self.region_id);
+        let mut params: Vec<(String, String)> = vec![
+            ("RegionId".to_string(), self.region_id.clone()),
+            ("SecurityGroupId".to_string(), self.group_id.clone()),
+        ];
+        // 每个来源网段一条授权规则
+        for (i, cidr) in self.source_ranges.iter().enumerate() {
+            let prefix = format!("Permissions.{}.", i + 1);
+            let source_field = if cidr.contains(':') { "Ipv6SourceCidrIp" } else { "SourceCidrIp" };
+            params.push((format!("{}IpProtocol", prefix), rule.protocol.as_str().to_uppercase()));
+            params.push((format!("{}PortRange", prefix), format!("{}/{}", rule.port, rule.port)));
+            params.push((format!("{}{}", prefix, source_field), cidr.clone()));
+            params.push((format!("{}Policy", prefix), "accept".to_string()));
+            params.push((format!("{}Description", prefix), RULE_DESCRIPTION.to_string()));
+        }
+        let params: Vec<(&str, &str)> = params.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        let query = canonical_query(&params);
+
+        let headers = sign(&self.access_key_id, &self.access_key_secret, &host, action, &query, Utc::now());
+        let mut req = self.http.post(format!("https://{}/?{}", host, query));
+        for (name, value) in headers {
+            req = req.header(name, value);
+        }
+        let resp = req.send().await?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let body: Value = resp.json().await.unwrap_or(Value::Null);
+        let code = body["Code"].as_str().unwrap_or_default();
+        let message = body["Message"].as_str().unwrap_or("未知错误");
+        Err(anyhow!("阿里云 API 错误（HTTP {}）{}: {}", status.as_u16(), code, message))
+    }
+}
+
+#[async_trait]
+impl FirewallProvider for EcsSecurityGroup {
+    fn name(&self) -> &'static str {
+        "阿里云安全组"
+    }
+
+    /// 添加已存在的规则、删除不存在的规则都会直接返回成功
+    async fn open(&self, rule: PortRule) -> Result<()> {
+        self.call("AuthorizeSecurityGroup", rule).await
+    }
+
+    async fn close(&self, rule: PortRule) -> Result<()> {
+        self.call("RevokeSecurityGroup", rule).await
+    }
+}
+
+/// ACS3-HMAC-SHA256 签名（RPC 风格，参数在查询字符串中，请求体为空），返回需要附加的请求头
+fn sign(
+    access_key_id: &str,
+    secret: &str,
+    host: &str,
+    action: &str,
+    query: &str,
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let payload_hash = hex::encode(Sha256::digest(b""));
+    let date = now.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let nonce = uuid::Uuid::new_v4().simple().to_string();
+    let headers = vec![
+        ("host", host.to_string()),
+        ("x-acs-action", action.to_string()),
+        ("x-acs-content-sha256", payload_hash.clone()),
+        ("x-acs-date", date),
+        ("x-acs-signature-nonce", nonce),
+        ("x-acs-version", API_VERSION.to_string()),
+    ];
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let canonical_request =
+        format!("POST\n/\n{}\n{}\n{}\n{}", query, canonical_headers, signed_headers, payload_hash);
+    let string_to_sign = format!(
+        "ACS3-HMAC-SHA256\n{}",
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signature = hex::encode(hmac_sha256(secret.as_bytes(), &string_to_sign));
+    let mut headers: Vec<(&'static str, String)> = headers.into_iter().filter(|(name, _)| *name != "host").collect();
+    headers.push((
+        "authorization",
+        format!(
+            "ACS3-HMAC-SHA256 Credential={},SignedHeaders={},Signature={}",
+            access_key_id, signed_headers, signature
+        ),
+    ));
+    headers
+}
@@ -0,0 +1,116 @@
+//! Amazon EC2 安全组
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+
+use super::{FirewallProvider, PortRule, RULE_DESCRIPTION};
+use crate::dns::route53::{canonical_query, sign, xml_values};
+
+const API_VERSION: &str = "2016-11-15";
+
+pub struct Ec2SecurityGroup {
+    http: reqwest::Client,
+    region: String,
+    group_id: String,
+    access_key_id: String,
+    secret_access_key: String,
+    source_ranges: Vec<String>,
+}
+
+impl Ec2SecurityGroup {
+    pub fn new(
+        http: reqwest::Client,
+        region: String,
+        group_id: String,
+        access_key_id: String,
+        secret_access_key: String,
+        source_ranges: Vec<String>,
+    ) -> Self {
+        Self { http, region, group_id, access_key_id, secret_access_key, source_ranges }
+    }
+
+    /// 调用安全组入站规则接口，返回错误码和错误信息（成功时为 `None`）
+    async fn call(&self, action: &str, rule: PortRule) -> Result<Option<(String, String)>> {
+        let host = format!("ec2.{}.amazonaws.com", self.region);
+        let port = rule.port.to_string();
+        // 规则描述只在添加时提交，删除按协议、端口和网段匹配
+        let authorize = action.starts_with("Authorize");
+        let mut params: Vec<(String, String)> = vec![
+            ("Action".to_string(), action.to_string()),
+            ("Version".to_string(), API_VERSION.to_string()),
+            ("GroupId".to_string(), self.group_id.clone()),
+            ("IpPermissions.1.IpProtocol".to_string(), rule.protocol.as_str().to_string()),
+            ("IpPermissions.1.FromPort".to_string(), port.clone()),
+            ("IpPermissions.1.ToPort".to_string(), port),
+        ];
+        let (v6, v4): (Vec<&String>, Vec<&String>) = self.source_ranges.iter().partition(|c| c.contains(':'));
+        for (i, cidr) in v4.iter().enumerate() {
+            params.push((format!("IpPermissions.1.IpRanges.{}.CidrIp", i + 1), cidr.to_string()));
+            if authorize {
+                params.push((format!("IpPermissions.1.IpRanges.{}.Description", i + 1), RULE_DESCRIPTION.to_string()));
+            }
+        }
+        for (i, cidr) in v6.iter().enumerate() {
+            params.push((format!("IpPermissions.1.Ipv6Ranges.{}.CidrIpv6", i + 1), cidr.to_string()));
+            if authorize {
+                params.push((format!("IpPermissions.1.Ipv6Ranges.{}.Description", i + 1), RULE_DESCRIPTION.to_string()));
+            }
+        }
+        let params: Vec<(&str, &str)> = params.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        let body = canonical_query(&params);
+
+        let headers = sign(
+            (&self.access_key_id, &self.secret_access_key),
+            (&host, &self.region, "ec2"),
+            "POST",
+            "/",
+            "",
+            &body,
+            Utc::now(),
+        );
+        let mut req = self
+            .http
+            .post(format!("https://{}/", host))
+            .header("content-type", "application/x-www-form-urlencoded; charset=utf-8")
+            .body(body);
+        for (name, value) in headers {
+            req = req.header(name, value);
+        }
+        let resp = req.send().await?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        if status.is_success() {
+            return Ok(None);
+        }
+        let code = xml_values(&text, "Code").into_iter().next().unwrap_or_default();
+        let message = xml_values(&text, "Message").into_iter().next().unwrap_or(text);
+        if code.is_empty() {
+            return Err(anyhow!("EC2 API 错误（HTTP {}）: {}", status.as_u16(), message));
+        }
+        Ok(Some((code, message)))
+    }
+}
+
+#[async_trait]
+impl FirewallProvider for Ec2SecurityGroup {
+    fn name(&self) -> &'static str {
+        "AWS 安全组"
+    }
+
+    async fn open(&self, rule: PortRule) -> Result<()> {
+        match self.call("AuthorizeSecurityGroupIngress", rule).await? {
+            None => Ok(()),
+            Some((code, _)) if code == "InvalidPermission.Duplicate" => Ok(()),
+            Some((code, message)) => Err(anyhow!("EC2 API 错误 {}: {}", code, message)),
+        }
+    }
+
+    async fn close(&self, rule: PortRule) -> Result<()> {
+        match self.call("RevokeSecurityGroupIngress", rule).await? {
+            None => Ok(()),
+            Some((code, _)) if code == "InvalidPermission.NotFound" => Ok(()),
+            Some((code, message)) => Err(anyhow!("EC2 API 错误 {}: {}", code, message)),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Google Cloud VPC 防火墙规则
+//!
+//! 每个端口创建一条名为 `oxiproxy-n<节点 ID>-<协议>-<端口>` 的规则。GCP 的一条规则不能同时包含 IPv4 和 IPv6
+//! 来源网段，两者都配置时 IPv6 另建一条名称以 `-v6` 结尾的规则。
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::OnceCell;
+
+use super::{FirewallProvider, PortRule, RULE_DESCRIPTION};
+
+const API_BASE: &str = "https://compute.googleapis.com/compute/v1";
+const SCOPE: &str = "https://www.googleapis.com/auth/compute";
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+
+/// 服务账号密钥文件中用到的字段
+#[derive(Clone, Deserialize)]
+pub struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+    #[serde(default)]
+    token_uri: Option<String>,
+}
+
+impl ServiceAccountKey {
+    pub fn parse(raw: &str) -> Result<ServiceAccountKey, String> {
+        let key: ServiceAccountKey =
+            serde_json::from_str(raw).map_err(|e| format!("serviceAccountKey 不是有效的服务账号密钥: {}", e))?;
+        EncodingKey::from_rsa_pem(key.private_key.as_bytes())
+            .map_err(|e| format!("serviceAccountKey 中的私钥无效: {}", e))?;
+        Ok(key)
+    }
+}
+
+#[derive(Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+pub struct VpcFirewall {
+    http: reqwest::Client,
+    key: ServiceAccountKey,
+    project: String,
+    network: String,
+    target_tags: Vec<String>,
+    node_id: i64,
+    source_ranges: Vec<String>,
+    access_token: OnceCell<String>,
+}
+
+impl VpcFirewall {
+    pub fn new(
+        http: reqwest::Client,
+        key: ServiceAccountKey,
+        project: String,
+        network: String,
+        target_tags: Vec<String>,
+        node_id: i64,
+        source_ranges: Vec<String>,
+    ) -> Self {
+        Self { http, key, project, network, target_tags, node_id, source_ranges, access_token: OnceCell::new() }
+    }
+
+    /// 用服务账号签发的 JWT 换取访问令牌（有效期 1 小时，客户端只在一次同步内使用）
+    async fn access_token(&self) -> Result<&str> {
+        let token = self
+            .access_token
+            .get_or_try_init(|| async {
+                let token_uri = self.key.token_uri.as_deref().unwrap_or(DEFAULT_TOKEN_URI);
+                let now = Utc::now().timestamp();
+                let claims =
+                    Claims { iss: &self.key.client_email, scope: SCOPE, aud: token_uri, iat: now, exp: now + 3600 };
+                let assertion = encode(
+                    &Header::new(Algorithm::RS256),
+                    &claims,
+                    &EncodingKey::from_rsa_pem(self.key.private_key.as_bytes())?,
+                )?;
+                let resp = self
+                    .http
+                    .post(token_uri)
+                    .form(&[("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"), ("assertion", assertion.as_str())])
+                    .send()
+                    .await?;
+                let status = resp.status();
+                let body: Value = resp.json().await.unwrap_or(Value::Null);
+                body["access_token"].as_str().map(str::to_string).ok_or_else(|| {
+                    let message = body["error_description"].as_str().unwrap_or("未返回访问令牌");
+                    anyhow!("获取 GCP 访问令牌失败（HTTP {}）: {}", status.as_u16(), message)
+                })
+            })
+            .await?;
+        Ok(token)
+    }
+
+    /// 发送请求，返回 HTTP 状态码和错误信息（成功时为 `None`）
+    async fn request(&self, method: reqwest::Method, path: &str, body: Option<Value>) -> Result<Option<(u16, String)>> {
+        let token = self.access_token().await?;
+        let mut req = self.http.request(method, format!("{}/projects/{}{}", API_BASE, self.project, path)).bearer_auth(token);
+        if let Some(body) = body {
+            req = req.json(&body);
+        }
+        let resp = req.send().await?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(None);
+        }
+        let body: Value = resp.json().await.unwrap_or(Value::Null);
+        let message = body["error"]["message"].as_str().unwrap_or("未知错误").to_string();
+        Ok(Some((status.as_u16(), message)))
+    }
+
+    /// 规则名称和对应的来源网段（IPv4 和 IPv6 分开）
+    fn rules(&self, rule: PortRule) -> Vec<(String, Vec<&String>)> {
+        let name = format!("oxiproxy-n{}-{}-{}", self.node_id, rule.protocol.as_str(), rule.port);
+        let (v6, v4): (Vec<&String>, Vec<&String>) = self.source_ranges.iter().partition(|c| c.contains(':'));
+        let mut rules = Vec::new();
+        if !v4.is_empty() {
+            rules.push((name.clone(), v4));
+        }
+        if !v6.is_empty() {
+            rules.push((format!("{}-v6", name), v6));
+        }
+        rules
+    }
+}
+
+#[async_trait]
+impl FirewallProvider for VpcFirewall {
+    fn name(&self) -> &'static str {
+        "GCP 防火墙"
+    }
+
+    async fn open(&self, rule: PortRule) -> Result<()> {
+        for (name, source_ranges) in self.rules(rule) {
+            let mut body = json!({
+                "name": name,
+                "network": format!("projects/{}/global/networks/{}", self.project, self.network),
+                "direction": "INGRESS",
+                "allowed": [{ "IPProtocol": rule.protocol.as_str(), "ports": [rule.port.to_string()] }],
+                "sourceRanges": source_ranges,
+                "description": RULE_DESCRIPTION,
+            });
+            if !self.target_tags.is_empty() {
+                body["targetTags"] = json!(self.target_tags);
+            }
+            match self.request(reqwest::Method::POST, "/global/firewalls", Some(body)).await? {
+                None | Some((409, _)) => {}
+                Some((status, message)) => return Err(anyhow!("GCP API 错误（HTTP {}）: {}", status, message)),
+            }
+        }
+        Ok(())
+    }
+
+    async fn close(&self, rule: PortRule) -> Result<()> {
+        for (name, _) in self.rules(rule) {
+            match self.request(reqwest::Method::DELETE, &format!("/global/firewalls/{}", name), None).await? {
+                None | Some((404, _)) => {}
+                Some((status, message)) => return Err(anyhow!("GCP API 错误（HTTP {}）: {}", status, message)),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+//! Hetzner Cloud 防火墙
+//!
+//! Hetzner 只能整体替换防火墙的规则列表，每次修改先读取当前规则，只增删描述为 `oxiproxy` 的规则。
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use super::{FirewallProvider, PortRule, RULE_DESCRIPTION};
+
+const API_BASE: &str = "https://api.hetzner.cloud/v1";
+
+pub struct CloudFirewall {
+    http: reqwest::Client,
+    firewall_id: u64,
+    token: String,
+    source_ranges: Vec<String>,
+}
+
+impl CloudFirewall {
+    pub fn new(http: reqwest::Client, firewall_id: u64, token: String, source_ranges: Vec<String>) -> Self {
+        Self { http, firewall_id, token, source_ranges }
+    }
+
+    async fn request(&self, method: reqwest::Method, path: &str, body: Option<Value>) -> Result<Value> {
+        let mut req = self.http.request(method, format!("{}{}", API_BASE, path)).bearer_auth(&self.token);
+        if let Some(body) = body {
+            req = req.json(&body);
+        }
+        let resp = req.send().await?;
+        let status = resp.status();
+        let body: Value = resp.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            let message = body["error"]["message"].as_str().unwrap_or("未知错误");
+            return Err(anyhow!("Hetzner API 错误（HTTP {}）: {}", status.as_u16(), message));
+        }
+        Ok(body)
+    }
+
+    async fn rules(&self) -> Result<Vec<Value>> {
+        let body = self.request(reqwest::Method::GET, &format!("/firewalls/{}", self.firewall_id), None).await?;
+        Ok(body["firewall"]["rules"].as_array().cloned().unwrap_or_default())
+    }
+
+    async fn set_rules(&self, rules: Vec<Value>) -> Result<()> {
+        self.request(
+            reqwest::Method::POST,
+            &format!("/firewalls/{}/actions/set_rules", self.firewall_id),
+            Some(json!({ "rules": rules })),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// 规则是否是 Controller 为该端口创建的
+fn is_own_rule(rule: &Value, port: PortRule) -> bool {
+    rule["direction"] == "in"
+        && rule["protocol"] == port.protocol.as_str()
+        && rule["port"].as_str().and_then(|p| p.parse::<u16>().ok()) == Some(port.port)
+        && rule["description"] == RULE_DESCRIPTION
+}
+
+#[async_trait]
+impl FirewallProvider for CloudFirewall {
+    fn name(&self) -> &'static str {
+        "Hetzner 防火墙"
+    }
+
+    async fn open(&self, rule: PortRule) -> Result<()> {
+        let mut rules = self.rules().await?;
+        if rules.iter().any(|r| is_own_rule(r, rule)) {
+            return Ok(());
+        }
+        rules.push(json!({
+            "direction": "in",
+            "protocol": rule.protocol.as_str(),
+            "port": rule.port.to_string(),
+            "source_ips": self.source_ranges,
+            "description": RULE_DESCRIPTION,
+        }));
+        self.set_rules(rules).await
+    }
+
+    async fn close(&self, rule: PortRule) -> Result<()> {
+        let rules = self.rules().await?;
+        let count = rules.len();
+        let rules: Vec<Value> = rules.into_iter().filter(|r| !is_own_rule(r, rule)).collect();
+        if rules.len() == count {
+            return Ok(());
+        }
+        self.set_rules(rules).await
+    }
+}
@@ -0,0 +1,550 @@
+//! 云服务商防火墙联动
+//!
+//! 节点设置了防火墙配置（`firewall_config`）时，Controller 在节点所在云服务商的安全组 / 防火墙中为该节点上
+//! 已启用代理的远程端口添加入站放行规则，代理删除、禁用或迁移到其他节点后关闭对应端口，用户无需为每个新端口
+//! 登录云控制台。
+//!
+//! 服务商由配置中的 `provider` 选择：`aws`（EC2 安全组）、`gcp`（VPC 防火墙规则）、`hetzner`（Hetzner Cloud
+//! 防火墙）、`aliyun`（ECS 安全组），新的服务商实现 [`FirewallProvider`] 即可接入。Controller 只增删自己创建的
+//! 规则，已放行的端口记录在 `data/firewall_rules.json` 中，与数据库中的代理比较后只提交有变化的端口。
+//! 代理增删改时立即同步，另外每 10 分钟同步一次以覆盖到期、时间表启停等其他路径。
+
+mod aliyun;
+mod aws;
+mod gcp;
+mod hetzner;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::IpAddr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use sea_orm::{ColumnTrait, Condition, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use tokio::sync::{Mutex, Notify};
+use tracing::{error, info, warn};
+
+use crate::entity::{node, proxy, Node, Proxy};
+use crate::migration::get_connection;
+
+/// 已放行端口的持久化文件
+const STATE_FILE: &str = "data/firewall_rules.json";
+/// 定期同步间隔
+const SYNC_INTERVAL: Duration = Duration::from_secs(600);
+/// 变更后等待合并的时间
+const DEBOUNCE: Duration = Duration::from_secs(2);
+/// 服务商 API 请求超时
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// Controller 创建的规则的描述，用于和用户手动添加的规则区分
+pub const RULE_DESCRIPTION: &str = "oxiproxy";
+/// 接口返回的配置中代替凭据的占位符，更新时提交占位符表示沿用原值
+const REDACTED: &str = "******";
+/// 配置中的凭据字段
+const SECRET_FIELDS: [&str; 4] = ["secretAccessKey", "accessKeySecret", "apiToken", "serviceAccountKey"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+impl Protocol {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+        }
+    }
+}
+
+/// 一条入站放行规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PortRule {
+    pub protocol: Protocol,
+    pub port: u16,
+}
+
+impl std::fmt::Display for PortRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.protocol.as_str().to_uppercase(), self.port)
+    }
+}
+
+/// 云服务商防火墙
+#[async_trait]
+pub trait FirewallProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// 放行入站端口，规则已存在时视为成功
+    async fn open(&self, rule: PortRule) -> Result<()>;
+
+    /// 关闭之前放行的端口，规则不存在时视为成功
+    async fn close(&self, rule: PortRule) -> Result<()>;
+}
+
+/// 节点的防火墙配置（JSON 保存在 `node.firewall_config`）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirewallConfig {
+    #[serde(flatten)]
+    pub provider: ProviderConfig,
+    /// 允许访问的来源网段，默认 `0.0.0.0/0`
+    #[serde(rename = "sourceRanges", default = "default_source_ranges")]
+    pub source_ranges: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum ProviderConfig {
+    /// EC2 安全组（IAM 用户需要 `ec2:AuthorizeSecurityGroupIngress` 和 `ec2:RevokeSecurityGroupIngress` 权限）
+    Aws {
+        region: String,
+        #[serde(rename = "securityGroupId")]
+        security_group_id: String,
+        #[serde(rename = "accessKeyId")]
+        access_key_id: String,
+        #[serde(rename = "secretAccessKey")]
+        secret_access_key: String,
+    },
+    /// VPC 防火墙规则（服务账号需要 `compute.firewalls.create` / `delete` 和 `compute.networks.updatePolicy` 权限）
+    Gcp {
+        project: String,
+        #[serde(default = "default_gcp_network")]
+        network: String,
+        /// 规则只作用于带有这些网络标记的实例，为空时作用于整个网络
+        #[serde(rename = "targetTags", default)]
+        target_tags: Vec<String>,
+        /// 服务账号密钥（下载的 JSON 文件内容）
+        #[serde(rename = "serviceAccountKey")]
+        service_account_key: String,
+    },
+    /// Hetzner Cloud 防火墙（API Token 需要读写权限）
+    Hetzner {
+        #[serde(rename = "firewallId")]
+        firewall_id: u64,
+        #[serde(rename = "apiToken")]
+        api_token: String,
+    },
+    /// ECS 安全组（RAM 用户需要 `ecs:AuthorizeSecurityGroup` 和 `ecs:RevokeSecurityGroup` 权限）
+    Aliyun {
+        #[serde(rename = "regionId")]
+        region_id: String,
+        #[serde(rename = "securityGroupId")]
+        security_group_id: String,
+        #[serde(rename = "accessKeyId")]
+        access_key_id: String,
+        #[serde(rename = "accessKeySecret")]
+        access_key_secret: String,
+    },
+}
+
+fn default_source_ranges() -> Vec<String> {
+    vec!["0.0.0.0/0".to_string()]
+}
+
+fn default_gcp_network() -> String {
+    "default".to_string()
+}
+
+/// 校验 CIDR 网段，如 `0.0.0.0/0`、`2001:db8::/32`
+fn valid_cidr(cidr: &str) -> bool {
+    let Some((ip, prefix)) = cidr.split_once('/') else {
+        return false;
+    };
+    match (ip.parse::<IpAddr>(), prefix.parse::<u8>()) {
+        (Ok(IpAddr::V4(_)), Ok(prefix)) => prefix <= 32,
+        (Ok(IpAddr::V6(_)), Ok(prefix)) => prefix <= 128,
+        _ => false,
+    }
+}
+
+impl FirewallConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let required = |value: &str, field: &str| {
+            if value.trim().is_empty() {
+                Err(format!("防火墙配置缺少 {}", field))
+            } else {
+                Ok(())
+            }
+        };
+        match &self.provider {
+            ProviderConfig::Aws { region, security_group_id, access_key_id, secret_access_key } => {
+                required(region, "region")?;
+                required(security_group_id, "securityGroupId")?;
+                required(access_key_id, "accessKeyId")?;
+                required(secret_access_key, "secretAccessKey")?;
+            }
+            ProviderConfig::Gcp { project, network, service_account_key, .. } => {
+                required(project, "project")?;
+                required(network, "network")?;
+                gcp::ServiceAccountKey::parse(service_account_key)?;
+            }
+            ProviderConfig::Hetzner { firewall_id, api_token } => {
+                if *firewall_id == 0 {
+                    return Err("防火墙配置缺少 firewallId".to_string());
+                }
+                required(api_token, "apiToken")?;
+            }
+            ProviderConfig::Aliyun { region_id, security_group_id, access_key_id, access_key_secret } => {
+                required(region_id, "regionId")?;
+                required(security_group_id, "securityGroupId")?;
+                required(access_key_id, "accessKeyId")?;
+                required(access_key_secret, "accessKeySecret")?;
+            }
+        }
+        if self.source_ranges.is_empty() {
+            return Err("sourceRanges 不能为空".to_string());
+        }
+        if let Some(cidr) = self.source_ranges.iter().find(|c| !valid_cidr(c)) {
+            return Err(format!("无效的来源网段: {}", cidr));
+        }
+        Ok(())
+    }
+
+    /// 创建服务商客户端，`node_id` 用于生成规则名称
+    fn provider(&self, node_id: i64) -> Result<Box<dyn FirewallProvider>> {
+        let http = crate::outbound::client(REQUEST_TIMEOUT)?;
+        let source_ranges = self.source_ranges.clone();
+        let provider: Box<dyn FirewallProvider> = match &self.provider {
+            ProviderConfig::Aws { region, security_group_id, access_key_id, secret_access_key } => {
+                Box::new(aws::Ec2SecurityGroup::new(
+                    http,
+                    region.clone(),
+                    security_group_id.clone(),
+                    access_key_id.clone(),
+                    secret_access_key.clone(),
+                    source_ranges,
+                ))
+            }
+            ProviderConfig::Gcp { project, network, target_tags, service_account_key } => Box::new(gcp::VpcFirewall::new(
+                http,
+                gcp::ServiceAccountKey::parse(service_account_key).map_err(anyhow::Error::msg)?,
+                project.clone(),
+                network.clone(),
+                target_tags.clone(),
+                node_id,
+                source_ranges,
+            )),
+            ProviderConfig::Hetzner { firewall_id, api_token } => {
+                Box::new(hetzner::CloudFirewall::new(http, *firewall_id, api_token.clone(), source_ranges))
+            }
+            ProviderConfig::Aliyun { region_id, security_group_id, access_key_id, access_key_secret } => {
+                Box::new(aliyun::EcsSecurityGroup::new(
+                    http,
+                    region_id.clone(),
+                    security_group_id.clone(),
+                    access_key_id.clone(),
+                    access_key_secret.clone(),
+                    source_ranges,
+                ))
+            }
+        };
+        Ok(provider)
+    }
+}
+
+/// 将配置中的凭据替换为占位符，无法解析时整体隐藏
+fn redact(raw: &str) -> String {
+    let Ok(mut value) = serde_json::from_str::<Value>(raw) else {
+        return REDACTED.to_string();
+    };
+    if let Some(object) = value.as_object_mut() {
+        for field in SECRET_FIELDS {
+            if let Some(secret) = object.get_mut(field) {
+                *secret = Value::String(REDACTED.to_string());
+            }
+        }
+    }
+    value.to_string()
+}
+
+/// 节点序列化时隐藏防火墙凭据（`#[serde(serialize_with)]`）
+pub fn serialize_redacted<S: Serializer>(raw: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    raw.as_deref().map(redact).serialize(serializer)
+}
+
+/// 校验并规范化防火墙配置，空字符串表示取消
+///
+/// `previous` 为节点当前的配置：凭据提交为占位符 `******` 时沿用其中的值，便于只修改其他字段。
+pub fn normalize_config(raw: Option<String>, previous: Option<&str>) -> Result<Option<String>, String> {
+    let raw = match raw.as_deref().map(str::trim) {
+        None | Some("") => return Ok(None),
+        Some(raw) => raw,
+    };
+    let mut value: Value = serde_json::from_str(raw).map_err(|e| format!("防火墙配置格式错误: {}", e))?;
+    let previous = previous
+        .and_then(|p| serde_json::from_str::<Value>(p).ok())
+        .filter(|p| p.get("provider") == value.get("provider"));
+    if let Some(object) = value.as_object_mut() {
+        for field in SECRET_FIELDS {
+            if object.get(field).and_then(Value::as_str) != Some(REDACTED) {
+                continue;
+            }
+            match previous.as_ref().and_then(|p| p.get(field)) {
+                Some(secret) => {
+                    object.insert(field.to_string(), secret.clone());
+                }
+                None => return Err(format!("防火墙配置缺少 {}", field)),
+            }
+        }
+    }
+    let config: FirewallConfig =
+        serde_json::from_value(value).map_err(|e| format!("防火墙配置格式错误: {}", e))?;
+    config.validate()?;
+    serde_json::to_string(&config).map(Some).map_err(|e| e.to_string())
+}
+
+/// 节点 ID → 已放行的端口
+type Published = BTreeMap<i64, BTreeSet<PortRule>>;
+
+#[derive(Serialize, Deserialize)]
+struct PublishedRule {
+    #[serde(rename = "nodeId")]
+    node_id: i64,
+    #[serde(flatten)]
+    rule: PortRule,
+}
+
+struct FirewallState {
+    published: Mutex<Published>,
+    changed: Notify,
+}
+
+fn state() -> &'static FirewallState {
+    static STATE: OnceLock<FirewallState> = OnceLock::new();
+    STATE.get_or_init(|| FirewallState { published: Mutex::new(load_published()), changed: Notify::new() })
+}
+
+fn load_published() -> Published {
+    let Ok(content) = std::fs::read_to_string(STATE_FILE) else {
+        return Published::new();
+    };
+    match serde_json::from_str::<Vec<PublishedRule>>(&content) {
+        Ok(rules) => {
+            let mut published = Published::new();
+            for r in rules {
+                published.entry(r.node_id).or_default().insert(r.rule);
+            }
+            published
+        }
+        Err(e) => {
+            warn!("读取 {} 失败，将重新放行全部端口: {}", STATE_FILE, e);
+            Published::new()
+        }
+    }
+}
+
+fn save_published(published: &Published) {
+    let rules: Vec<PublishedRule> = published
+        .iter()
+        .flat_map(|(node_id, rules)| rules.iter().map(|rule| PublishedRule { node_id: *node_id, rule: *rule }))
+        .collect();
+    let result = serde_json::to_string_pretty(&rules)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| Ok(std::fs::write(STATE_FILE, json)?));
+    if let Err(e) = result {
+        error!("保存 {} 失败: {}", STATE_FILE, e);
+    }
+}
+
+/// 代理的远程端口对应的放行规则
+fn proxy_rule(p: &proxy::Model) -> PortRule {
+    let protocol = if p.proxy_type == "udp" { Protocol::Udp } else { Protocol::Tcp };
+    PortRule { protocol, port: p.remote_port }
+}
+
+/// 根据已启用的代理计算各节点需要放行的端口
+fn desired_rules(proxies: &[proxy::Model], managed: impl Fn(i64) -> bool) -> Published {
+    let mut desired = Published::new();
+    for p in proxies.iter().filter(|p| p.enabled) {
+        // 多节点发布的代理在每个发布节点上都需要放行
+        for node_id in p.node_ids().into_iter().filter(|id| managed(*id)) {
+            desired.entry(node_id).or_default().insert(proxy_rule(p));
+        }
+    }
+    desired
+}
+
+async fn sync_once(state: &FirewallState) -> Result<()> {
+    let db = get_connection().await;
+    let nodes: HashMap<i64, (String, FirewallConfig)> = Node::find()
+        .filter(node::Column::FirewallConfig.is_not_null())
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|n| match serde_json::from_str::<FirewallConfig>(n.firewall_config.as_deref()?) {
+            Ok(config) => Some((n.id, (n.name, config))),
+            Err(e) => {
+                warn!("节点 {} 的防火墙配置无效: {}", n.name, e);
+                None
+            }
+        })
+        .collect();
+    let proxies = Proxy::find()
+        .filter(proxy::Column::Enabled.eq(true))
+        .filter(
+            Condition::any()
+                .add(proxy::Column::NodeId.is_in(nodes.keys().copied()))
+                .add(proxy::Column::AnycastNodeIds.is_not_null()),
+        )
+        .all(db)
+        .await?;
+    let desired = desired_rules(&proxies, |node_id| nodes.contains_key(&node_id));
+
+    let mut published = state.published.lock().await;
+    let node_ids: BTreeSet<i64> = desired.keys().chain(published.keys()).copied().collect();
+    let mut dirty = false;
+    for node_id in node_ids {
+        let want = desired.get(&node_id).cloned().unwrap_or_default();
+        let have = published.get(&node_id).cloned().unwrap_or_default();
+        if want == have {
+            continue;
+        }
+        let Some((name, config)) = nodes.get(&node_id) else {
+            // 正常情况下取消配置时已经用旧配置关闭（见 config_changed），这里只是兜底
+            warn!("节点 #{} 已删除或取消了防火墙配置，之前放行的 {} 个端口需要在云控制台手动关闭", node_id, have.len());
+            published.remove(&node_id);
+            dirty = true;
+            continue;
+        };
+        let provider = match config.provider(node_id) {
+            Ok(provider) => provider,
+            Err(e) => {
+                warn!("节点 {} 的防火墙服务商初始化失败: {}", name, e);
+                continue;
+            }
+        };
+        let rules = published.entry(node_id).or_default();
+        for rule in want.difference(&have) {
+            match provider.open(*rule).await {
+                Ok(()) => {
+                    info!("已在 {} 为节点 {} 放行 {}", provider.name(), name, rule);
+                    rules.insert(*rule);
+                    dirty = true;
+                }
+                Err(e) => warn!("在 {} 为节点 {} 放行 {} 失败: {}", provider.name(), name, rule, e),
+            }
+        }
+        for rule in have.difference(&want) {
+            match provider.close(*rule).await {
+                Ok(()) => {
+                    info!("已在 {} 为节点 {} 关闭 {}", provider.name(), name, rule);
+                    rules.remove(rule);
+                    dirty = true;
+                }
+                Err(e) => warn!("在 {} 为节点 {} 关闭 {} 失败: {}", provider.name(), name, rule, e),
+            }
+        }
+        if rules.is_empty() {
+            published.remove(&node_id);
+        }
+    }
+    if dirty {
+        save_published(&published);
+    }
+    Ok(())
+}
+
+/// 通知同步任务代理已变化
+pub fn request_sync() {
+    state().changed.notify_one();
+}
+
+/// 节点的防火墙配置变更或节点删除后调用
+///
+/// 放行规则所在的位置（服务商、安全组、来源网段等）变化时，先用旧配置关闭之前放行的端口，
+/// 再由同步任务按新配置重新放行；只轮换凭据时不需要关闭。
+pub fn config_changed(node_id: i64, previous: Option<String>, current: Option<&str>) {
+    if previous.as_deref().map(redact) == current.map(redact) {
+        request_sync();
+        return;
+    }
+    tokio::spawn(async move {
+        let state = state();
+        let mut published = state.published.lock().await;
+        if let Some(rules) = published.remove(&node_id) {
+            match previous.as_deref().map(serde_json::from_str::<FirewallConfig>) {
+                Some(Ok(config)) => match config.provider(node_id) {
+                    Ok(provider) => {
+                        for rule in rules {
+                            match provider.close(rule).await {
+                                Ok(()) => info!("已在 {} 为节点 #{} 关闭 {}", provider.name(), node_id, rule),
+                                Err(e) => {
+                                    warn!("在 {} 为节点 #{} 关闭 {} 失败，请手动关闭: {}", provider.name(), node_id, rule, e)
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => warn!("节点 #{} 的旧防火墙配置无法使用，之前放行的端口需要手动关闭: {}", node_id, e),
+                },
+                _ => warn!("节点 #{} 的旧防火墙配置无效，之前放行的 {} 个端口需要手动关闭", node_id, rules.len()),
+            }
+            save_published(&published);
+        }
+        drop(published);
+        request_sync();
+    });
+}
+
+/// 启动防火墙同步任务（没有节点配置防火墙时每次同步只查询一次数据库）
+pub fn start_firewall_sync() {
+    let state = state();
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = sync_once(state).await {
+                error!("同步云防火墙规则失败: {}", e);
+            }
+            tokio::select! {
+                _ = state.changed.notified() => tokio::time::sleep(DEBOUNCE).await,
+                _ = tokio::time::sleep(SYNC_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_config_keeps_redacted_secret() {
+        let previous = r#"{"provider":"hetzner","firewallId":42,"apiToken":"secret-token"}"#;
+        let normalized = normalize_config(
+            Some(r#"{"provider":"hetzner","firewallId":43,"apiToken":"******"}"#.to_string()),
+            Some(previous),
+        )
+        .unwrap()
+        .unwrap();
+        let value: Value = serde_json::from_str(&normalized).unwrap();
+        assert_eq!(value["apiToken"], "secret-token");
+        assert_eq!(value["firewallId"], 43);
+        assert_eq!(value["sourceRanges"], serde_json::json!(["0.0.0.0/0"]));
+
+        // 换了服务商时不能沿用旧凭据
+        assert!(normalize_config(
+            Some(r#"{"provider":"aliyun","regionId":"cn-hangzhou","securityGroupId":"sg-1","accessKeyId":"ak","accessKeySecret":"******"}"#.to_string()),
+            Some(previous),
+        )
+        .is_err());
+        assert_eq!(normalize_config(Some(" ".to_string()), Some(previous)), Ok(None));
+    }
+
+    #[test]
+    fn test_redact() {
+        let redacted = redact(r#"{"provider":"aws","accessKeyId":"AKID","secretAccessKey":"shh"}"#);
+        assert!(!redacted.contains("shh"));
+        assert!(redacted.contains("AKID"));
+        assert_eq!(redact("not json"), REDACTED);
+    }
+
+    #[test]
+    fn test_valid_cidr() {
+        assert!(valid_cidr("0.0.0.0/0"));
+        assert!(valid_cidr("2001:db8::/32"));
+        assert!(!valid_cidr("10.0.0.1"));
+        assert!(!valid_cidr("10.0.0.0/33"));
+    }
+}
@@ -1,6 +1,7 @@
 //! Controller 配置模块
 
 use anyhow::Context;
+use common::grpc::tuning::GrpcTuning;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
@@ -40,6 +41,14 @@ pub struct Config {
     /// (向后兼容) frps 内部 API 共享密钥
     #[serde(default)]
     pub frps_secret: Option<String>,
+
+    /// Web 管理界面静态文件目录（不设置时使用内嵌资源或 ./dist）
+    #[serde(default)]
+    pub ui_dir: Option<String>,
+
+    /// gRPC 消息大小上限、压缩和 keepalive（配置文件中的 `[grpc]` 段）
+    #[serde(default)]
+    pub grpc: GrpcTuning,
 }
 
 fn default_web_port() -> u16 {
@@ -58,7 +67,31 @@ fn default_db_path() -> String {
     "./data/oxiproxy.db".to_string()
 }
 
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            web_port: default_web_port(),
+            internal_port: default_internal_port(),
+            jwt_secret: None,
+            jwt_expiration_hours: default_jwt_expiration(),
+            db_path: default_db_path(),
+            internal_secret: None,
+            frps_url: None,
+            frps_secret: None,
+            ui_dir: None,
+            grpc: GrpcTuning::default(),
+        }
+    }
+}
+
 impl Config {
+    /// 只使用默认值和环境变量的配置（数据库尚不可用时）
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        config.apply_env_overrides();
+        config
+    }
+
     /// 获取内部 API 密钥（优先 internal_secret，回退 frps_secret）
     pub fn get_internal_secret(&self) -> String {
         if let Some(ref secret) = self.internal_secret {
@@ -71,11 +104,11 @@ impl Config {
 
     /// 获取 JWT 密钥（优先从环境变量读取，其次从配置文件，最后自动生成）
     pub fn get_jwt_secret(&self) -> anyhow::Result<String> {
-        // 优先从环境变量读取
-        if let Ok(secret) = std::env::var("JWT_SECRET") {
-            if !secret.is_empty() {
-                return Ok(secret);
-            }
+        // 优先从环境变量读取（支持 *_FILE 从文件读取）
+        if let Some(secret) = common::env::var("OXIPROXY_JWT_SECRET")
+            .or_else(|| common::env::var("JWT_SECRET"))
+        {
+            return Ok(secret);
         }
 
         // 其次从配置文件读取
@@ -89,6 +122,42 @@ impl Config {
         Self::get_or_generate_jwt_secret()
     }
 
+    /// 使用 `OXIPROXY_*` 环境变量覆盖配置
+    fn apply_env_overrides(&mut self) {
+        use common::env;
+
+        if let Some(port) = env::parse::<u16>("OXIPROXY_WEB_PORT") {
+            self.web_port = port;
+        }
+        if let Some(port) = env::parse::<u16>("OXIPROXY_INTERNAL_PORT") {
+            self.internal_port = port;
+        }
+        if let Some(hours) = env::parse::<i64>("OXIPROXY_JWT_EXPIRATION_HOURS") {
+            self.jwt_expiration_hours = hours;
+        }
+        if let Some(secret) = env::var("OXIPROXY_INTERNAL_SECRET") {
+            self.internal_secret = Some(secret);
+        }
+        if let Some(dir) = env::var("OXIPROXY_UI_DIR") {
+            self.ui_dir = Some(dir);
+        }
+        if let Some(size) = env::parse::<usize>("OXIPROXY_GRPC_MAX_MESSAGE_SIZE") {
+            self.grpc.max_message_size = size;
+        }
+        if let Some(compression) = env::parse("OXIPROXY_GRPC_COMPRESSION") {
+            self.grpc.compression = compression;
+        }
+        if let Some(secs) = env::parse::<u64>("OXIPROXY_GRPC_KEEPALIVE_INTERVAL_SECS") {
+            self.grpc.keepalive_interval_secs = secs;
+        }
+        if let Some(secs) = env::parse::<u64>("OXIPROXY_GRPC_KEEPALIVE_TIMEOUT_SECS") {
+            self.grpc.keepalive_timeout_secs = secs;
+        }
+        if let Some(url) = crate::migration::database_url_from_env() {
+            self.db_path = url;
+        }
+    }
+
     /// 从文件获取或生成新的 JWT 密钥
     fn get_or_generate_jwt_secret() -> anyhow::Result<String> {
         use std::path::PathBuf;
@@ -145,8 +214,15 @@ pub async fn get_config() -> &'static Config {
     CONFIG.get_or_init(init_config).await
 }
 
-/// 初始化配置
+/// 初始化配置（环境变量优先于数据库和配置文件）
 pub async fn init_config() -> Config {
+    let mut config = load_config().await;
+    config.apply_env_overrides();
+    config
+}
+
+/// 从数据库或配置文件加载配置
+async fn load_config() -> Config {
     use crate::entity::{SystemConfig, system_config};
     use sea_orm::{EntityTrait, ColumnTrait, QueryFilter};
 
@@ -156,16 +232,7 @@ pub async fn init_config() -> Config {
 
         // 读取所有配置项
         if let Ok(configs) = SystemConfig::find().all(db).await {
-            let mut config = Config {
-                web_port: default_web_port(),
-                internal_port: default_internal_port(),
-                jwt_secret: None,
-                jwt_expiration_hours: default_jwt_expiration(),
-                db_path: default_db_path(),
-                internal_secret: None,
-                frps_url: None,
-                frps_secret: None,
-            };
+            let mut config = Config::default();
 
             // 从数据库配置项中填充
             for item in configs {
@@ -190,6 +257,34 @@ pub async fn init_config() -> Config {
                             config.db_path = path;
                         }
                     }
+                    "ui_dir" => {
+                        if let Ok(dir) = serde_json::from_str::<String>(&item.value) {
+                            if !dir.is_empty() {
+                                config.ui_dir = Some(dir);
+                            }
+                        }
+                    }
+                    "grpc_max_message_size" => {
+                        if let Ok(size) = item.value.parse::<usize>() {
+                            config.grpc.max_message_size = size;
+                        }
+                    }
+                    "grpc_compression" => {
+                        let value = serde_json::from_str::<String>(&item.value).unwrap_or(item.value);
+                        if let Ok(compression) = value.parse() {
+                            config.grpc.compression = compression;
+                        }
+                    }
+                    "grpc_keepalive_interval_secs" => {
+                        if let Ok(secs) = item.value.parse::<u64>() {
+                            config.grpc.keepalive_interval_secs = secs;
+                        }
+                    }
+                    "grpc_keepalive_timeout_secs" => {
+                        if let Ok(secs) = item.value.parse::<u64>() {
+                            config.grpc.keepalive_timeout_secs = secs;
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -222,14 +317,5 @@ pub async fn init_config() -> Config {
     }
 
     tracing::warn!("未找到配置文件或数据库配置，使用默认配置");
-    Config {
-        web_port: default_web_port(),
-        internal_port: default_internal_port(),
-        jwt_secret: None,
-        jwt_expiration_hours: default_jwt_expiration(),
-        db_path: default_db_path(),
-        internal_secret: None,
-        frps_url: None,
-        frps_secret: None,
-    }
+    Config::default()
 }
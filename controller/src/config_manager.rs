@@ -12,7 +12,7 @@ pub struct ConfigManager {
     cache: Arc<RwLock<HashMap<String, ConfigValue>>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ConfigValue {
     Number(i64),
     Float(f64),
@@ -73,10 +73,21 @@ impl ConfigManager {
         Ok(())
     }
 
-    /// 获取配置值
+    /// 获取配置值（环境变量 `OXIPROXY_<KEY>` 优先于数据库）
     pub async fn get(&self, key: &str) -> Option<ConfigValue> {
         let cache = self.cache.read().await;
-        cache.get(key).cloned()
+        let cached = cache.get(key).cloned();
+
+        if let Some(raw) = common::env::var(&common::env::key_to_var(key)) {
+            let value_type = match cached {
+                Some(ConfigValue::Number(_)) | Some(ConfigValue::Float(_)) => "number",
+                Some(ConfigValue::Boolean(_)) => "boolean",
+                _ => "string",
+            };
+            return Some(self.parse_value(&raw, value_type));
+        }
+
+        cached
     }
 
     /// 获取数值配置（带默认值）
@@ -0,0 +1,264 @@
+//! 系统配置修订记录
+//!
+//! 每次修改系统配置都会生成一个修订，记录变更内容和修订后的完整配置快照，
+//! 以便查看历史并回滚到任意修订（包括容易导致失联的 Web TLS / gRPC TLS 配置）。
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, PaginatorTrait,
+    QueryFilter, Set, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::entity::{config_revision, system_config, ConfigRevision, SystemConfig};
+
+/// 配置快照：键 → 数据库中的原始值
+pub type ConfigSnapshot = BTreeMap<String, String>;
+
+/// 单个配置项的变更
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigChange {
+    pub key: String,
+    pub old: String,
+    pub new: String,
+}
+
+impl ConfigChange {
+    /// 用于展示的变更：证书/私钥内容等敏感值只显示长度
+    pub fn redacted(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            old: redact_value(&self.key, &self.old),
+            new: redact_value(&self.key, &self.new),
+        }
+    }
+}
+
+/// 证书 / 私钥内容、密钥、密码等不对外展示原值的配置项
+pub fn is_sensitive_key(key: &str) -> bool {
+    key.ends_with("_content") || key.contains("secret") || key.contains("password")
+}
+
+/// 隐藏后的占位值前缀
+const REDACTED_PREFIX: &str = "<已隐藏，";
+
+/// 敏感配置项的原始值替换为只显示长度的占位值，其他配置项原样返回
+pub fn redact_value(key: &str, value: &str) -> String {
+    // 字符串值以 JSON 形式存储，空字符串为 `""`
+    if !is_sensitive_key(key) || value.is_empty() || value == "\"\"" {
+        return value.to_string();
+    }
+    format!("{}{} 字节>", REDACTED_PREFIX, value.len())
+}
+
+/// 值是否为 [`redact_value`] 生成的占位值（客户端把读取到的占位值原样提交时不应写入）
+pub fn is_redacted(value: &str) -> bool {
+    value.starts_with(REDACTED_PREFIX)
+}
+
+/// 计算从 `from` 到 `to` 的变更（仅包含 `to` 中存在且值不同的键）
+pub fn diff(from: &ConfigSnapshot, to: &ConfigSnapshot) -> Vec<ConfigChange> {
+    to.iter()
+        .filter_map(|(key, new)| {
+            let old = from.get(key).cloned().unwrap_or_default();
+            (old != *new).then(|| ConfigChange {
+                key: key.clone(),
+                old,
+                new: new.clone(),
+            })
+        })
+        .collect()
+}
+
+/// 读取当前所有系统配置的快照
+pub async fn current_snapshot<C: ConnectionTrait>(db: &C) -> Result<ConfigSnapshot> {
+    Ok(SystemConfig::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|c| (c.key, c.value))
+        .collect())
+}
+
+/// 记录一次配置修订，没有变更时不记录
+///
+/// 首次记录时会先保存变更前的配置作为基线修订，确保可以回滚到最初的配置。
+pub async fn record_revision<C: ConnectionTrait>(
+    db: &C,
+    changes: Vec<ConfigChange>,
+    description: Option<String>,
+    created_by: Option<String>,
+) -> Result<Option<config_revision::Model>> {
+    if changes.is_empty() {
+        return Ok(None);
+    }
+
+    let now = Utc::now().naive_utc();
+    let after = current_snapshot(db).await?;
+
+    if ConfigRevision::find().count(db).await? == 0 {
+        let mut baseline = after.clone();
+        for change in &changes {
+            baseline.insert(change.key.clone(), change.old.clone());
+        }
+        config_revision::ActiveModel {
+            changes: Set("[]".to_string()),
+            snapshot: Set(serde_json::to_string(&baseline)?),
+            description: Set(Some("初始配置".to_string())),
+            created_by: Set(None),
+            created_at: Set(now),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+    }
+
+    let revision = config_revision::ActiveModel {
+        changes: Set(serde_json::to_string(&changes)?),
+        snapshot: Set(serde_json::to_string(&after)?),
+        description: Set(description),
+        created_by: Set(created_by),
+        created_at: Set(now),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    tracing::info!("记录配置修订 #{}（{} 项变更）", revision.id, changes.len());
+    Ok(Some(revision))
+}
+
+/// 将变更写入数据库（不记录修订），不存在的配置项会被忽略
+pub async fn write_changes<C: ConnectionTrait>(db: &C, changes: &[ConfigChange]) -> Result<()> {
+    let now = Utc::now().naive_utc();
+    for change in changes {
+        if let Some(config) = SystemConfig::find()
+            .filter(system_config::Column::Key.eq(&change.key))
+            .one(db)
+            .await?
+        {
+            let mut active: system_config::ActiveModel = config.into();
+            active.value = Set(change.new.clone());
+            active.updated_at = Set(now);
+            active.update(db).await?;
+        }
+    }
+    Ok(())
+}
+
+/// 将指定配置项设置为给定的原始值并记录修订，返回实际发生的变更
+pub async fn apply_values(
+    db: &DatabaseConnection,
+    values: &ConfigSnapshot,
+    description: Option<String>,
+    created_by: Option<String>,
+) -> Result<Vec<ConfigChange>> {
+    let txn = db.begin().await?;
+
+    let current = current_snapshot(&txn).await?;
+    let changes: Vec<ConfigChange> = diff(&current, values)
+        .into_iter()
+        .filter(|c| current.contains_key(&c.key))
+        .collect();
+
+    write_changes(&txn, &changes).await?;
+    record_revision(&txn, changes.clone(), description, created_by).await?;
+
+    txn.commit().await?;
+    Ok(changes)
+}
+
+/// 解析修订中记录的变更
+pub fn parse_changes(revision: &config_revision::Model) -> Vec<ConfigChange> {
+    serde_json::from_str(&revision.changes).unwrap_or_default()
+}
+
+/// 将系统配置回滚到指定修订时的状态，回滚本身也会生成新的修订
+///
+/// 返回 (新修订, 回滚应用的变更)。
+pub async fn rollback(
+    db: &DatabaseConnection,
+    revision_id: i64,
+    created_by: Option<String>,
+) -> Result<(config_revision::Model, Vec<ConfigChange>)> {
+    let revision = ConfigRevision::find_by_id(revision_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| anyhow!("配置修订 #{} 不存在", revision_id))?;
+    let target: ConfigSnapshot = serde_json::from_str(&revision.snapshot)
+        .map_err(|e| anyhow!("配置修订 #{} 的快照已损坏: {}", revision_id, e))?;
+
+    let txn = db.begin().await?;
+
+    let current = current_snapshot(&txn).await?;
+    // 修订之后新增的配置项不在快照中，保持不变
+    let changes: Vec<ConfigChange> = diff(&current, &target)
+        .into_iter()
+        .filter(|c| current.contains_key(&c.key))
+        .collect();
+
+    if changes.is_empty() {
+        return Err(anyhow!("当前配置已与修订 #{} 一致", revision_id));
+    }
+
+    write_changes(&txn, &changes).await?;
+
+    let new_revision = record_revision(
+        &txn,
+        changes.clone(),
+        Some(format!("回滚到修订 #{}", revision_id)),
+        created_by,
+    )
+    .await?
+    .ok_or_else(|| anyhow!("记录回滚修订失败"))?;
+
+    txn.commit().await?;
+    Ok((new_revision, changes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(pairs: &[(&str, &str)]) -> ConfigSnapshot {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_diff() {
+        let from = snapshot(&[("web_port", "3000"), ("web_tls_enabled", "false")]);
+        let to = snapshot(&[("web_port", "3000"), ("web_tls_enabled", "true")]);
+        assert_eq!(
+            diff(&from, &to),
+            vec![ConfigChange {
+                key: "web_tls_enabled".to_string(),
+                old: "false".to_string(),
+                new: "true".to_string(),
+            }]
+        );
+        assert!(diff(&to, &to).is_empty());
+    }
+
+    #[test]
+    fn test_redacted() {
+        let change = ConfigChange {
+            key: "web_tls_key_content".to_string(),
+            old: "\"\"".to_string(),
+            new: "\"LS0tLS1CRUdJTg==\"".to_string(),
+        };
+        let redacted = change.redacted();
+        assert_eq!(redacted.old, "\"\"");
+        assert_eq!(redacted.new, "<已隐藏，18 字节>");
+        assert!(is_redacted(&redacted.new));
+        assert!(!is_redacted(&change.new));
+
+        let plain = ConfigChange {
+            key: "web_tls_enabled".to_string(),
+            old: "false".to_string(),
+            new: "true".to_string(),
+        };
+        assert_eq!(plain.redacted(), plain);
+    }
+}
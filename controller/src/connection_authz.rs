@@ -0,0 +1,185 @@
+//! 访客连接授权钩子
+//!
+//! 配置 `OXIPROXY_CONNECTION_AUTHZ_URL` 后，节点在接受访客 TCP 连接前通过 gRPC 请求授权，
+//! Controller 补全代理、客户端和用户信息后以 JSON POST 调用该地址，由运营方自己的风控系统决定放行或拒绝。
+//!
+//! 请求体：`{"nodeId","proxyId","proxyName","proxyType","remotePort","clientId","clientName","userId","username","sourceIp"}`；
+//! 响应体：`{"allow": bool, "cacheSecs": 可选, "reason": 可选}`。节点按 `cacheSecs`（默认
+//! `OXIPROXY_CONNECTION_AUTHZ_CACHE_SECS`）缓存同一来源 IP 在同一代理上的决定。
+//!
+//! 钩子超时（`OXIPROXY_CONNECTION_AUTHZ_TIMEOUT_MS`）、返回非 2xx 或格式错误时按
+//! `OXIPROXY_CONNECTION_AUTHZ_FAIL_OPEN`（默认放行）处理，该结果只缓存几秒。
+//! 设置 `OXIPROXY_CONNECTION_AUTHZ_SECRET` 时以 `Authorization: Bearer <secret>` 发送。
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use common::grpc::oxiproxy;
+
+use crate::entity::{Client, Proxy, User};
+use crate::migration::get_connection;
+
+/// 默认的钩子超时
+const DEFAULT_TIMEOUT_MS: u32 = 500;
+/// 默认的决定缓存时间
+const DEFAULT_CACHE_SECS: u32 = 60;
+/// 钩子不可用时的决定缓存时间
+const FAILURE_CACHE_SECS: u32 = 5;
+
+pub struct AuthzConfig {
+    url: String,
+    secret: Option<String>,
+    timeout_ms: u32,
+    fail_open: bool,
+    cache_secs: u32,
+    http: reqwest::Client,
+}
+
+/// 钩子配置，未设置地址时为 None
+pub fn config() -> Option<&'static AuthzConfig> {
+    static CONFIG: OnceLock<Option<AuthzConfig>> = OnceLock::new();
+    CONFIG
+        .get_or_init(|| {
+            let url = common::env::var("OXIPROXY_CONNECTION_AUTHZ_URL")?;
+            if let Err(e) = crate::outbound::validate_url(&url) {
+                warn!("连接授权钩子地址不可用，已忽略: {}", e);
+                return None;
+            }
+            let timeout_ms = common::env::parse::<u32>("OXIPROXY_CONNECTION_AUTHZ_TIMEOUT_MS")
+                .unwrap_or(DEFAULT_TIMEOUT_MS)
+                .max(1);
+            let http = match crate::outbound::client(Duration::from_millis(timeout_ms as u64)) {
+                Ok(http) => http,
+                Err(e) => {
+                    warn!("创建连接授权钩子 HTTP 客户端失败: {}", e);
+                    return None;
+                }
+            };
+            info!("访客连接授权钩子: {}", url);
+            Some(AuthzConfig {
+                url,
+                secret: common::env::var("OXIPROXY_CONNECTION_AUTHZ_SECRET"),
+                timeout_ms,
+                fail_open: common::env::parse::<bool>("OXIPROXY_CONNECTION_AUTHZ_FAIL_OPEN").unwrap_or(true),
+                cache_secs: common::env::parse::<u32>("OXIPROXY_CONNECTION_AUTHZ_CACHE_SECS")
+                    .unwrap_or(DEFAULT_CACHE_SECS),
+                http,
+            })
+        })
+        .as_ref()
+}
+
+/// 随节点注册响应下发的钩子设置
+pub fn to_grpc() -> Option<oxiproxy::GrpcConnectionAuthz> {
+    config().map(|c| oxiproxy::GrpcConnectionAuthz { timeout_ms: c.timeout_ms, fail_open: c.fail_open })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HookRequest {
+    node_id: i64,
+    proxy_id: i64,
+    proxy_name: String,
+    proxy_type: String,
+    remote_port: u16,
+    client_id: i64,
+    client_name: String,
+    user_id: Option<i64>,
+    username: Option<String>,
+    source_ip: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HookResponse {
+    allow: bool,
+    cache_secs: Option<u32>,
+    reason: Option<String>,
+}
+
+/// 处理节点的授权请求
+pub async fn authorize(
+    node_id: i64,
+    req: oxiproxy::AuthorizeConnectionRequest,
+) -> oxiproxy::AuthorizeConnectionResponse {
+    let request_id = req.request_id.clone();
+    let Some(config) = config() else {
+        return oxiproxy::AuthorizeConnectionResponse { request_id, allowed: true, cache_secs: 0, reason: None };
+    };
+
+    match call_hook(config, node_id, &req).await {
+        Ok(resp) => {
+            if !resp.allow {
+                debug!(
+                    "连接授权钩子拒绝来源 {} 访问代理 #{}: {}",
+                    req.source_ip,
+                    req.proxy_id,
+                    resp.reason.as_deref().unwrap_or_default()
+                );
+            }
+            oxiproxy::AuthorizeConnectionResponse {
+                request_id,
+                allowed: resp.allow,
+                cache_secs: resp.cache_secs.unwrap_or(config.cache_secs),
+                reason: resp.reason,
+            }
+        }
+        Err(e) => {
+            warn!("调用连接授权钩子失败（{}）: {}", if config.fail_open { "放行" } else { "拒绝" }, e);
+            oxiproxy::AuthorizeConnectionResponse {
+                request_id,
+                allowed: config.fail_open,
+                cache_secs: FAILURE_CACHE_SECS,
+                reason: Some("授权服务不可用".to_string()),
+            }
+        }
+    }
+}
+
+async fn call_hook(
+    config: &AuthzConfig,
+    node_id: i64,
+    req: &oxiproxy::AuthorizeConnectionRequest,
+) -> Result<HookResponse> {
+    let db = get_connection().await;
+    let proxy = Proxy::find_by_id(req.proxy_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| anyhow!("代理 #{} 不存在", req.proxy_id))?;
+    let client = match proxy.client_id.parse::<i64>() {
+        Ok(id) => Client::find_by_id(id).one(db).await?,
+        Err(_) => None,
+    };
+    let user = match client.as_ref().and_then(|c| c.user_id) {
+        Some(user_id) => User::find_by_id(user_id).one(db).await?,
+        None => None,
+    };
+
+    let body = HookRequest {
+        node_id,
+        proxy_id: proxy.id,
+        proxy_name: proxy.name,
+        proxy_type: proxy.proxy_type,
+        remote_port: proxy.remote_port,
+        client_id: client.as_ref().map_or(0, |c| c.id),
+        client_name: client.map(|c| c.name).unwrap_or_default(),
+        user_id: user.as_ref().map(|u| u.id),
+        username: user.map(|u| u.username),
+        source_ip: req.source_ip.clone(),
+    };
+
+    let mut request = config.http.post(&config.url).json(&body);
+    if let Some(secret) = &config.secret {
+        request = request.bearer_auth(secret);
+    }
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("钩子返回状态 {}", response.status()));
+    }
+    Ok(response.json::<HookResponse>().await?)
+}
@@ -0,0 +1,122 @@
+//! Cloudflare DNS（API Token 需要区域的 DNS 编辑权限）
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tokio::sync::OnceCell;
+
+use super::{DnsProvider, RecordType, SrvValue};
+
+const API_BASE: &str = "https://api.cloudflare.com/client/v4";
+
+pub struct Cloudflare {
+    http: reqwest::Client,
+    token: String,
+    zone: String,
+    zone_id: OnceCell<String>,
+}
+
+impl Cloudflare {
+    pub fn new(http: reqwest::Client, token: String, zone: String) -> Self {
+        Self { http, token, zone, zone_id: OnceCell::new() }
+    }
+
+    async fn request(&self, method: reqwest::Method, path: &str, body: Option<Value>) -> Result<Value> {
+        let mut req = self.http.request(method, format!("{}{}", API_BASE, path)).bearer_auth(&self.token);
+        if let Some(body) = body {
+            req = req.json(&body);
+        }
+        let resp: Value = req.send().await?.json().await?;
+        if resp["success"].as_bool() != Some(true) {
+            let errors: Vec<String> = resp["errors"]
+                .as_array()
+                .map(|errors| errors.iter().filter_map(|e| e["message"].as_str().map(str::to_string)).collect())
+                .unwrap_or_default();
+            return Err(anyhow!("Cloudflare API 错误: {}", errors.join("; ")));
+        }
+        Ok(resp["result"].clone())
+    }
+
+    async fn zone_id(&self) -> Result<&str> {
+        let id = self
+            .zone_id
+            .get_or_try_init(|| async {
+                let zones = self.request(reqwest::Method::GET, &format!("/zones?name={}", self.zone), None).await?;
+                zones[0]["id"]
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow!("Cloudflare 中没有区域 {}", self.zone))
+            })
+            .await?;
+        Ok(id)
+    }
+}
+
+/// Cloudflare 返回的记录转为区域文件格式的值
+fn record_value(record: &Value) -> Option<String> {
+    if record["type"] == "SRV" {
+        let data = &record["data"];
+        let srv = SrvValue {
+            priority: data["priority"].as_u64()? as u16,
+            weight: data["weight"].as_u64()? as u16,
+            port: data["port"].as_u64()? as u16,
+            target: data["target"].as_str()?.trim_end_matches('.').to_string(),
+        };
+        return Some(srv.to_value());
+    }
+    record["content"].as_str().map(str::to_string)
+}
+
+fn record_body(name: &str, record_type: RecordType, value: &str, ttl: u32) -> Result<Value> {
+    match record_type {
+        RecordType::Srv => {
+            let srv = SrvValue::parse(value).ok_or_else(|| anyhow!("无效的 SRV 记录值: {}", value))?;
+            Ok(json!({
+                "type": "SRV",
+                "name": name,
+                "ttl": ttl,
+                "data": { "priority": srv.priority, "weight": srv.weight, "port": srv.port, "target": srv.target },
+            }))
+        }
+        _ => Ok(json!({ "type": record_type.as_str(), "name": name, "content": value, "ttl": ttl, "proxied": false })),
+    }
+}
+
+#[async_trait]
+impl DnsProvider for Cloudflare {
+    fn name(&self) -> &'static str {
+        "Cloudflare"
+    }
+
+    async fn set_records(&self, name: &str, record_type: RecordType, values: &[String], ttl: u32) -> Result<()> {
+        let zone_id = self.zone_id().await?;
+        let path = format!("/zones/{}/dns_records", zone_id);
+        let existing = self
+            .request(
+                reqwest::Method::GET,
+                &format!("{}?type={}&name={}&per_page=100", path, record_type.as_str(), name),
+                None,
+            )
+            .await?;
+        let existing: Vec<(String, Option<String>)> = existing
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|r| Some((r["id"].as_str()?.to_string(), record_value(r))))
+            .collect();
+
+        // 先添加再删除，避免更新期间解析不到
+        for value in values {
+            if !existing.iter().any(|(_, v)| v.as_ref() == Some(value)) {
+                self.request(reqwest::Method::POST, &path, Some(record_body(name, record_type, value, ttl)?))
+                    .await?;
+            }
+        }
+        for (id, value) in &existing {
+            if !value.as_ref().is_some_and(|v| values.contains(v)) {
+                self.request(reqwest::Method::DELETE, &format!("{}/{}", path, id), None).await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,430 @@
+//! DNS 自动发布
+//!
+//! 代理设置了 `dns_name` 时，Controller 在配置的 DNS 服务商处发布指向所在节点公网 IP 的记录：
+//!
+//! - `ssh.example.com`：发布 A / AAAA 记录；
+//! - `_minecraft._tcp.mc.example.com`：在 `mc.example.com` 发布 A / AAAA，并在完整名称上发布指向它和远程端口的 SRV 记录。
+//!
+//! 不含区域后缀的名称（如 `ssh`）自动补全为 `ssh.<区域>`。期望的记录由数据库中的代理和节点计算得出，
+//! 与 `data/dns_records.json` 中记录的已发布状态比较后只提交有变化的记录集，代理删除后其记录随之删除。
+//! 代理增删改、节点公网 IP 变化时立即同步，另外每 10 分钟同步一次以覆盖到期清理等其他路径。
+//!
+//! 通过 `OXIPROXY_DNS_PROVIDER` 选择服务商（`cloudflare`、`route53`、`rfc2136`），新的服务商实现 [`DnsProvider`] 即可接入。
+
+mod cloudflare;
+mod rfc2136;
+pub(crate) mod route53;
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+use crate::entity::{node, proxy, Node, Proxy};
+use crate::migration::get_connection;
+
+/// 已发布记录的持久化文件
+const STATE_FILE: &str = "data/dns_records.json";
+/// 定期同步间隔
+const SYNC_INTERVAL: Duration = Duration::from_secs(600);
+/// 变更后等待合并的时间
+const DEBOUNCE: Duration = Duration::from_secs(2);
+/// 默认 TTL（秒）
+const DEFAULT_TTL: u32 = 60;
+/// 服务商 API 请求超时
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum RecordType {
+    A,
+    Aaaa,
+    Srv,
+}
+
+impl RecordType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RecordType::A => "A",
+            RecordType::Aaaa => "AAAA",
+            RecordType::Srv => "SRV",
+        }
+    }
+}
+
+/// SRV 记录值（区域文件格式 `优先级 权重 端口 目标.`）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvValue {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    /// 不带末尾的点
+    pub target: String,
+}
+
+impl SrvValue {
+    pub fn parse(value: &str) -> Option<SrvValue> {
+        let mut parts = value.split_whitespace();
+        let srv = SrvValue {
+            priority: parts.next()?.parse().ok()?,
+            weight: parts.next()?.parse().ok()?,
+            port: parts.next()?.parse().ok()?,
+            target: parts.next()?.trim_end_matches('.').to_string(),
+        };
+        parts.next().is_none().then_some(srv)
+    }
+
+    pub fn to_value(&self) -> String {
+        format!("{} {} {} {}.", self.priority, self.weight, self.port, self.target)
+    }
+}
+
+/// DNS 服务商
+#[async_trait]
+pub trait DnsProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// 将 `name`（完整域名，不带末尾的点）的 `record_type` 记录集替换为 `values`，`values` 为空时删除
+    ///
+    /// 记录值为区域文件格式：A / AAAA 为 IP 地址，SRV 见 [`SrvValue`]。
+    async fn set_records(&self, name: &str, record_type: RecordType, values: &[String], ttl: u32) -> Result<()>;
+}
+
+/// (名称, 类型) → 记录值（已排序）
+type RecordSets = BTreeMap<(String, RecordType), Vec<String>>;
+
+#[derive(Serialize, Deserialize)]
+struct PublishedRecord {
+    name: String,
+    #[serde(rename = "type")]
+    record_type: RecordType,
+    values: Vec<String>,
+}
+
+struct DnsState {
+    provider: Box<dyn DnsProvider>,
+    zone: String,
+    ttl: u32,
+    changed: Notify,
+}
+
+fn state() -> &'static OnceLock<Arc<DnsState>> {
+    static STATE: OnceLock<Arc<DnsState>> = OnceLock::new();
+    &STATE
+}
+
+fn http_client() -> Result<reqwest::Client> {
+    Ok(crate::outbound::client(REQUEST_TIMEOUT)?)
+}
+
+/// 读取必需的环境变量
+fn required_var(name: &str) -> Result<String> {
+    common::env::var(name).ok_or_else(|| anyhow!("缺少环境变量 {}", name))
+}
+
+/// 按环境变量创建服务商，未设置 `OXIPROXY_DNS_PROVIDER` 时返回 `None`
+fn provider_from_env(zone: &str) -> Result<Option<Box<dyn DnsProvider>>> {
+    let Some(kind) = common::env::var("OXIPROXY_DNS_PROVIDER") else {
+        return Ok(None);
+    };
+    let provider: Box<dyn DnsProvider> = match kind.to_ascii_lowercase().as_str() {
+        "cloudflare" => Box::new(cloudflare::Cloudflare::new(
+            http_client()?,
+            required_var("OXIPROXY_DNS_CLOUDFLARE_API_TOKEN")?,
+            zone.to_string(),
+        )),
+        "route53" => Box::new(route53::Route53::new(
+            http_client()?,
+            required_var("OXIPROXY_DNS_ROUTE53_ZONE_ID")?,
+            required_var("OXIPROXY_DNS_ROUTE53_ACCESS_KEY_ID")?,
+            required_var("OXIPROXY_DNS_ROUTE53_SECRET_ACCESS_KEY")?,
+        )),
+        "rfc2136" => Box::new(rfc2136::Rfc2136::new(
+            required_var("OXIPROXY_DNS_RFC2136_SERVER")?,
+            zone.to_string(),
+            required_var("OXIPROXY_DNS_RFC2136_KEY_NAME")?,
+            &required_var("OXIPROXY_DNS_RFC2136_KEY_SECRET")?,
+        )?),
+        other => return Err(anyhow!("未知的 DNS 服务商: {}（可选 cloudflare、route53、rfc2136）", other)),
+    };
+    Ok(Some(provider))
+}
+
+/// 规范化并校验代理的 DNS 名称：小写、去掉末尾的点、补全区域后缀
+pub fn normalize_name(name: &str) -> Result<String, String> {
+    let Some(state) = state().get() else {
+        return Err("未配置 DNS 服务商（OXIPROXY_DNS_PROVIDER），无法发布 DNS 记录".to_string());
+    };
+    normalize_in_zone(name, &state.zone)
+}
+
+fn normalize_in_zone(name: &str, zone: &str) -> Result<String, String> {
+    let name = name.trim().trim_end_matches('.').to_ascii_lowercase();
+    let name = if name == zone || name.ends_with(&format!(".{}", zone)) {
+        name
+    } else {
+        format!("{}.{}", name, zone)
+    };
+    let host = split_srv(&name).1;
+    if host == zone {
+        return Err("不能在区域根域名上发布记录".to_string());
+    }
+    if name.len() > 253 {
+        return Err("DNS 名称过长".to_string());
+    }
+    for label in name.split('.') {
+        let valid = !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        if !valid {
+            return Err(format!("无效的 DNS 名称: {}", name));
+        }
+    }
+    // 下划线只允许出现在 SRV 的服务和协议标签中
+    if host.contains('_') {
+        return Err(format!("无效的 DNS 名称: {}", name));
+    }
+    Ok(name)
+}
+
+/// `_svc._tcp.host` → (Some(`_svc._tcp.host`), `host`)；其他名称 → (None, 名称)
+pub fn split_srv(name: &str) -> (Option<&str>, &str) {
+    let mut labels = name.splitn(3, '.');
+    match (labels.next(), labels.next(), labels.next()) {
+        (Some(service), Some(proto), Some(host)) if service.starts_with('_') && proto.starts_with('_') => {
+            (Some(name), host)
+        }
+        _ => (None, name),
+    }
+}
+
+/// 校验主机名没有被其他客户端或其他节点上的代理使用（A / AAAA 记录只能指向一个节点）
+pub async fn check_conflict(
+    name: &str,
+    node_id: Option<i64>,
+    client_id: &str,
+    exclude_proxy_id: Option<i64>,
+    db: &DatabaseConnection,
+) -> Result<(), String> {
+    let host = split_srv(name).1;
+    let proxies = Proxy::find()
+        .filter(proxy::Column::DnsName.is_not_null())
+        .all(db)
+        .await
+        .map_err(|e| format!("查询代理失败: {}", e))?;
+    let conflict = proxies.iter().find(|p| {
+        Some(p.id) != exclude_proxy_id
+            && p.dns_name.as_deref().is_some_and(|n| split_srv(n).1 == host)
+            && (p.node_id != node_id || p.client_id != client_id)
+    });
+    match conflict {
+        Some(p) => Err(format!("DNS 名称 {} 已被代理「{}」使用", host, p.name)),
+        None => Ok(()),
+    }
+}
+
+/// 节点的公网地址：注册时上报的公网 IP，其次为 `public_ip`、隧道地址
+fn node_addresses(node: &node::Model) -> Vec<IpAddr> {
+    if let Some(caps) = node.capabilities() {
+        let ips: Vec<IpAddr> = caps.public_ips.iter().filter_map(|ip| ip.parse().ok()).collect();
+        if !ips.is_empty() {
+            return ips;
+        }
+    }
+    [node.public_ip.as_deref(), Some(node.tunnel_addr.as_str())]
+        .into_iter()
+        .flatten()
+        .find_map(|addr| addr.parse().ok())
+        .into_iter()
+        .collect()
+}
+
+/// 根据代理和节点计算期望的记录
+fn desired_records(proxies: &[proxy::Model], nodes: &HashMap<i64, node::Model>) -> RecordSets {
+    let mut records = RecordSets::new();
+    // 主机名 → 节点，同一主机名只取第一个节点
+    let mut hosts: BTreeMap<String, i64> = BTreeMap::new();
+    for p in proxies {
+        let (Some(name), Some(node_id)) = (p.dns_name.as_deref(), p.node_id) else {
+            continue;
+        };
+        let Some(node) = nodes.get(&node_id) else {
+            continue;
+        };
+        let (srv, host) = split_srv(name);
+        match hosts.get(host) {
+            Some(existing) if *existing != node_id => {
+                warn!("DNS 名称 {} 同时被节点 #{} 和 #{} 上的代理使用，忽略代理「{}」", host, existing, node_id, p.name);
+                continue;
+            }
+            Some(_) => {}
+            None => {
+                hosts.insert(host.to_string(), node_id);
+                for ip in node_addresses(node) {
+                    let record_type = if ip.is_ipv4() { RecordType::A } else { RecordType::Aaaa };
+                    records.entry((host.to_string(), record_type)).or_default().push(ip.to_string());
+                }
+            }
+        }
+        if let Some(srv) = srv {
+            let value = SrvValue { priority: 0, weight: 0, port: p.remote_port, target: host.to_string() };
+            records.entry((srv.to_string(), RecordType::Srv)).or_default().push(value.to_value());
+        }
+    }
+    for values in records.values_mut() {
+        values.sort();
+        values.dedup();
+    }
+    records
+}
+
+fn load_published() -> RecordSets {
+    let Ok(content) = std::fs::read_to_string(STATE_FILE) else {
+        return RecordSets::new();
+    };
+    match serde_json::from_str::<Vec<PublishedRecord>>(&content) {
+        Ok(records) => records.into_iter().map(|r| ((r.name, r.record_type), r.values)).collect(),
+        Err(e) => {
+            warn!("读取 {} 失败，将重新发布全部记录: {}", STATE_FILE, e);
+            RecordSets::new()
+        }
+    }
+}
+
+fn save_published(records: &RecordSets) {
+    let records: Vec<PublishedRecord> = records
+        .iter()
+        .map(|((name, record_type), values)| PublishedRecord {
+            name: name.clone(),
+            record_type: *record_type,
+            values: values.clone(),
+        })
+        .collect();
+    let result = serde_json::to_string_pretty(&records)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| Ok(std::fs::write(STATE_FILE, json)?));
+    if let Err(e) = result {
+        error!("保存 {} 失败: {}", STATE_FILE, e);
+    }
+}
+
+async fn sync_once(state: &DnsState, published: &mut RecordSets) -> Result<()> {
+    let db = get_connection().await;
+    let proxies = Proxy::find().filter(proxy::Column::DnsName.is_not_null()).all(db).await?;
+    let nodes: HashMap<i64, node::Model> = Node::find().all(db).await?.into_iter().map(|n| (n.id, n)).collect();
+    let desired = desired_records(&proxies, &nodes);
+
+    let keys: Vec<(String, RecordType)> = desired.keys().chain(published.keys()).cloned().collect();
+    let mut dirty = false;
+    for key in keys {
+        let values = desired.get(&key).cloned().unwrap_or_default();
+        if published.get(&key).cloned().unwrap_or_default() == values {
+            continue;
+        }
+        let (name, record_type) = &key;
+        match state.provider.set_records(name, *record_type, &values, state.ttl).await {
+            Ok(()) => {
+                if values.is_empty() {
+                    info!("已删除 DNS 记录 {} {}", name, record_type.as_str());
+                    published.remove(&key);
+                } else {
+                    info!("已发布 DNS 记录 {} {} → {}", name, record_type.as_str(), values.join(", "));
+                    published.insert(key, values);
+                }
+                dirty = true;
+            }
+            Err(e) => warn!("更新 DNS 记录 {} {} 失败: {}", name, record_type.as_str(), e),
+        }
+    }
+    if dirty {
+        save_published(published);
+    }
+    Ok(())
+}
+
+/// 通知 DNS 同步任务代理或节点地址已变化
+pub fn request_sync() {
+    if let Some(state) = state().get() {
+        state.changed.notify_one();
+    }
+}
+
+/// 读取配置并启动 DNS 同步任务，未配置服务商时不做任何事
+pub fn start_dns_sync() {
+    let zone = common::env::var("OXIPROXY_DNS_ZONE")
+        .map(|z| z.trim().trim_end_matches('.').to_ascii_lowercase())
+        .unwrap_or_default();
+    let provider = match provider_from_env(&zone) {
+        Ok(Some(provider)) => provider,
+        Ok(None) => return,
+        Err(e) => {
+            error!("DNS 自动发布未启用: {}", e);
+            return;
+        }
+    };
+    if zone.is_empty() {
+        error!("DNS 自动发布未启用: 缺少环境变量 OXIPROXY_DNS_ZONE");
+        return;
+    }
+    let ttl = common::env::parse::<u32>("OXIPROXY_DNS_TTL").unwrap_or(DEFAULT_TTL);
+    info!("DNS 自动发布: {}，区域 {}，TTL {} 秒", provider.name(), zone, ttl);
+    let state = state()
+        .get_or_init(|| Arc::new(DnsState { provider, zone, ttl, changed: Notify::new() }))
+        .clone();
+
+    tokio::spawn(async move {
+        let mut published = load_published();
+        loop {
+            if let Err(e) = sync_once(&state, &mut published).await {
+                error!("同步 DNS 记录失败: {}", e);
+            }
+            tokio::select! {
+                _ = state.changed.notified() => tokio::time::sleep(DEBOUNCE).await,
+                _ = tokio::time::sleep(SYNC_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_in_zone() {
+        assert_eq!(normalize_in_zone("SSH", "example.com"), Ok("ssh.example.com".to_string()));
+        assert_eq!(normalize_in_zone("ssh.example.com.", "example.com"), Ok("ssh.example.com".to_string()));
+        assert_eq!(
+            normalize_in_zone("_minecraft._tcp.mc", "example.com"),
+            Ok("_minecraft._tcp.mc.example.com".to_string())
+        );
+        assert!(normalize_in_zone("example.com", "example.com").is_err());
+        assert!(normalize_in_zone("_minecraft._tcp", "example.com").is_err());
+        assert!(normalize_in_zone("bad_host", "example.com").is_err());
+        assert!(normalize_in_zone("-ssh", "example.com").is_err());
+        assert!(normalize_in_zone("a..b", "example.com").is_err());
+    }
+
+    #[test]
+    fn test_split_srv() {
+        assert_eq!(split_srv("_mc._tcp.mc.example.com"), (Some("_mc._tcp.mc.example.com"), "mc.example.com"));
+        assert_eq!(split_srv("ssh.example.com"), (None, "ssh.example.com"));
+    }
+
+    #[test]
+    fn test_srv_value() {
+        let srv = SrvValue::parse("0 5 25565 mc.example.com.").unwrap();
+        assert_eq!(srv, SrvValue { priority: 0, weight: 5, port: 25565, target: "mc.example.com".to_string() });
+        assert_eq!(srv.to_value(), "0 5 25565 mc.example.com.");
+        assert!(SrvValue::parse("0 0 mc.example.com.").is_none());
+    }
+}
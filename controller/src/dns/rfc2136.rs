@@ -0,0 +1,223 @@
+//! RFC 2136 动态更新（BIND、Knot、PowerDNS 等），使用 TSIG（hmac-sha256）认证，通过 TCP 发送
+
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use super::{DnsProvider, RecordType, SrvValue};
+
+const TYPE_SOA: u16 = 6;
+const TYPE_TSIG: u16 = 250;
+const CLASS_IN: u16 = 1;
+const CLASS_ANY: u16 = 255;
+const OPCODE_UPDATE: u16 = 5;
+const TSIG_ALGORITHM: &str = "hmac-sha256";
+const TSIG_FUDGE: u16 = 300;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Rfc2136 {
+    server: String,
+    zone: String,
+    key_name: String,
+    key: Vec<u8>,
+}
+
+impl Rfc2136 {
+    pub fn new(server: String, zone: String, key_name: String, key_secret: &str) -> Result<Self> {
+        let server = if server.contains(':') { server } else { format!("{}:53", server) };
+        let key = base64::engine::general_purpose::STANDARD
+            .decode(key_secret.trim())
+            .map_err(|_| anyhow!("OXIPROXY_DNS_RFC2136_KEY_SECRET 须为 base64 编码的 TSIG 密钥"))?;
+        Ok(Self { server, zone, key_name, key })
+    }
+}
+
+fn record_type_code(record_type: RecordType) -> u16 {
+    match record_type {
+        RecordType::A => 1,
+        RecordType::Aaaa => 28,
+        RecordType::Srv => 33,
+    }
+}
+
+/// 域名的线格式（不压缩）
+fn encode_name(buf: &mut Vec<u8>, name: &str) {
+    for label in name.trim_end_matches('.').split('.').filter(|l| !l.is_empty()) {
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.to_ascii_lowercase().as_bytes());
+    }
+    buf.push(0);
+}
+
+fn encode_rdata(record_type: RecordType, value: &str) -> Result<Vec<u8>> {
+    let invalid = || anyhow!("无效的 {} 记录值: {}", record_type.as_str(), value);
+    match record_type {
+        RecordType::A | RecordType::Aaaa => match value.parse::<IpAddr>().map_err(|_| invalid())? {
+            IpAddr::V4(ip) if record_type == RecordType::A => Ok(ip.octets().to_vec()),
+            IpAddr::V6(ip) if record_type == RecordType::Aaaa => Ok(ip.octets().to_vec()),
+            _ => Err(invalid()),
+        },
+        RecordType::Srv => {
+            let srv = SrvValue::parse(value).ok_or_else(invalid)?;
+            let mut rdata = Vec::new();
+            rdata.extend_from_slice(&srv.priority.to_be_bytes());
+            rdata.extend_from_slice(&srv.weight.to_be_bytes());
+            rdata.extend_from_slice(&srv.port.to_be_bytes());
+            encode_name(&mut rdata, &srv.target);
+            Ok(rdata)
+        }
+    }
+}
+
+/// 资源记录：名称、类型、类、TTL、RDATA
+fn encode_rr(buf: &mut Vec<u8>, name: &str, rtype: u16, class: u16, ttl: u32, rdata: &[u8]) {
+    encode_name(buf, name);
+    buf.extend_from_slice(&rtype.to_be_bytes());
+    buf.extend_from_slice(&class.to_be_bytes());
+    buf.extend_from_slice(&ttl.to_be_bytes());
+    buf.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    buf.extend_from_slice(rdata);
+}
+
+/// 构造 UPDATE 消息：删除整个记录集，再添加新值
+fn build_update(id: u16, zone: &str, name: &str, record_type: RecordType, values: &[String], ttl: u32) -> Result<Vec<u8>> {
+    let rtype = record_type_code(record_type);
+    let mut msg = Vec::with_capacity(512);
+    msg.extend_from_slice(&id.to_be_bytes());
+    msg.extend_from_slice(&(OPCODE_UPDATE << 11).to_be_bytes());
+    // ZOCOUNT, PRCOUNT, UPCOUNT, ADCOUNT
+    for count in [1, 0, 1 + values.len() as u16, 0] {
+        msg.extend_from_slice(&u16::to_be_bytes(count));
+    }
+    encode_name(&mut msg, zone);
+    msg.extend_from_slice(&TYPE_SOA.to_be_bytes());
+    msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+
+    encode_rr(&mut msg, name, rtype, CLASS_ANY, 0, &[]);
+    for value in values {
+        encode_rr(&mut msg, name, rtype, CLASS_IN, ttl, &encode_rdata(record_type, value)?);
+    }
+    Ok(msg)
+}
+
+/// 附加 TSIG 记录（RFC 8945）
+fn sign(msg: &mut Vec<u8>, key_name: &str, key: &[u8], time_signed: u64) {
+    let time_bytes = time_signed.to_be_bytes();
+    let time = &time_bytes[2..];
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC 接受任意长度的密钥");
+    mac.update(msg);
+    let mut vars = Vec::new();
+    encode_name(&mut vars, key_name);
+    vars.extend_from_slice(&CLASS_ANY.to_be_bytes());
+    vars.extend_from_slice(&0u32.to_be_bytes());
+    encode_name(&mut vars, TSIG_ALGORITHM);
+    vars.extend_from_slice(time);
+    vars.extend_from_slice(&TSIG_FUDGE.to_be_bytes());
+    // error, other len
+    vars.extend_from_slice(&[0, 0, 0, 0]);
+    mac.update(&vars);
+    let digest = mac.finalize().into_bytes();
+
+    let mut rdata = Vec::new();
+    encode_name(&mut rdata, TSIG_ALGORITHM);
+    rdata.extend_from_slice(time);
+    rdata.extend_from_slice(&TSIG_FUDGE.to_be_bytes());
+    rdata.extend_from_slice(&(digest.len() as u16).to_be_bytes());
+    rdata.extend_from_slice(&digest);
+    // original id, error, other len
+    rdata.extend_from_slice(&msg[..2]);
+    rdata.extend_from_slice(&[0, 0, 0, 0]);
+    encode_rr(msg, key_name, TYPE_TSIG, CLASS_ANY, 0, &rdata);
+
+    let adcount = u16::from_be_bytes([msg[10], msg[11]]) + 1;
+    msg[10..12].copy_from_slice(&adcount.to_be_bytes());
+}
+
+fn rcode_name(rcode: u16) -> &'static str {
+    match rcode {
+        1 => "FORMERR",
+        2 => "SERVFAIL",
+        3 => "NXDOMAIN",
+        4 => "NOTIMP",
+        5 => "REFUSED",
+        9 => "NOTAUTH",
+        10 => "NOTZONE",
+        _ => "未知错误",
+    }
+}
+
+#[async_trait]
+impl DnsProvider for Rfc2136 {
+    fn name(&self) -> &'static str {
+        "RFC 2136"
+    }
+
+    async fn set_records(&self, name: &str, record_type: RecordType, values: &[String], ttl: u32) -> Result<()> {
+        let id = rand::random::<u16>();
+        let mut msg = build_update(id, &self.zone, name, record_type, values, ttl)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        sign(&mut msg, &self.key_name, &self.key, now);
+
+        let exchange = async {
+            let mut stream = TcpStream::connect(&self.server).await?;
+            stream.write_all(&(msg.len() as u16).to_be_bytes()).await?;
+            stream.write_all(&msg).await?;
+            let len = stream.read_u16().await? as usize;
+            let mut resp = vec![0u8; len];
+            stream.read_exact(&mut resp).await?;
+            Ok::<_, std::io::Error>(resp)
+        };
+        let resp = tokio::time::timeout(TIMEOUT, exchange)
+            .await
+            .map_err(|_| anyhow!("DNS 服务器 {} 响应超时", self.server))??;
+        if resp.len() < 12 || resp[..2] != id.to_be_bytes() {
+            return Err(anyhow!("DNS 服务器 {} 响应无效", self.server));
+        }
+        let rcode = u16::from_be_bytes([resp[2], resp[3]]) & 0x0f;
+        if rcode != 0 {
+            return Err(anyhow!("DNS 服务器拒绝更新: {}（{}）", rcode_name(rcode), rcode));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_name() {
+        let mut buf = Vec::new();
+        encode_name(&mut buf, "SSH.example.com.");
+        assert_eq!(buf, b"\x03ssh\x07example\x03com\x00");
+    }
+
+    #[test]
+    fn test_encode_rdata() {
+        assert_eq!(encode_rdata(RecordType::A, "1.2.3.4").unwrap(), vec![1, 2, 3, 4]);
+        assert!(encode_rdata(RecordType::A, "::1").is_err());
+        assert_eq!(
+            encode_rdata(RecordType::Srv, "0 5 25565 mc.example.com.").unwrap(),
+            b"\x00\x00\x00\x05\x63\xdd\x02mc\x07example\x03com\x00"
+        );
+    }
+
+    #[test]
+    fn test_build_update_and_sign() {
+        let values = ["1.2.3.4".to_string()];
+        let mut msg = build_update(0x1234, "example.com", "ssh.example.com", RecordType::A, &values, 60).unwrap();
+        // 头部：ID、opcode UPDATE、区域 1、更新 2（删除 + 添加）
+        assert_eq!(&msg[..12], &[0x12, 0x34, 0x28, 0, 0, 1, 0, 0, 0, 2, 0, 0]);
+        sign(&mut msg, "oxiproxy", b"secret", 1_700_000_000);
+        assert_eq!(&msg[10..12], &[0, 1]);
+        assert!(msg.ends_with(&[0x12, 0x34, 0, 0, 0, 0]));
+    }
+}
@@ -0,0 +1,245 @@
+//! Amazon Route 53（IAM 用户需要 `route53:ChangeResourceRecordSets` 和 `route53:ListResourceRecordSets` 权限）
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use super::{DnsProvider, RecordType};
+
+const HOST: &str = "route53.amazonaws.com";
+const REGION: &str = "us-east-1";
+const SERVICE: &str = "route53";
+
+pub struct Route53 {
+    http: reqwest::Client,
+    zone_id: String,
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+impl Route53 {
+    pub fn new(http: reqwest::Client, zone_id: String, access_key_id: String, secret_access_key: String) -> Self {
+        let zone_id = zone_id.trim_start_matches("/hostedzone/").to_string();
+        Self { http, zone_id, access_key_id, secret_access_key }
+    }
+
+    /// 发送签名请求，返回响应正文
+    async fn request(&self, method: reqwest::Method, path: &str, query: &[(&str, &str)], body: String) -> Result<String> {
+        let query = canonical_query(query);
+        let headers = sign(
+            (&self.access_key_id, &self.secret_access_key),
+            (HOST, REGION, SERVICE),
+            method.as_str(),
+            path,
+            &query,
+            &body,
+            Utc::now(),
+        );
+        let url = if query.is_empty() {
+            format!("https://{}{}", HOST, path)
+        } else {
+            format!("https://{}{}?{}", HOST, path, query)
+        };
+        let mut req = self.http.request(method, url).body(body);
+        for (name, value) in headers {
+            req = req.header(name, value);
+        }
+        let resp = req.send().await?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        if !status.is_success() {
+            let message = xml_values(&text, "Message").into_iter().next().unwrap_or(text);
+            return Err(anyhow!("Route 53 API 错误（HTTP {}）: {}", status.as_u16(), message));
+        }
+        Ok(text)
+    }
+
+    /// 当前的记录集（TTL, 值）
+    async fn current(&self, name: &str, record_type: RecordType) -> Result<Option<(u32, Vec<String>)>> {
+        let fqdn = format!("{}.", name);
+        let body = self
+            .request(
+                reqwest::Method::GET,
+                &format!("/2013-04-01/hostedzone/{}/rrset", self.zone_id),
+                &[("name", &fqdn), ("type", record_type.as_str()), ("maxitems", "1")],
+                String::new(),
+            )
+            .await?;
+        let Some(set) = xml_blocks(&body, "ResourceRecordSet").into_iter().next() else {
+            return Ok(None);
+        };
+        let matches = xml_values(set, "Name").first().is_some_and(|n| n.eq_ignore_ascii_case(&fqdn))
+            && xml_values(set, "Type").first().is_some_and(|t| t == record_type.as_str());
+        if !matches {
+            return Ok(None);
+        }
+        let ttl = xml_values(set, "TTL").first().and_then(|t| t.parse().ok()).unwrap_or(0);
+        Ok(Some((ttl, xml_values(set, "Value"))))
+    }
+
+    async fn change(&self, action: &str, name: &str, record_type: RecordType, values: &[String], ttl: u32) -> Result<()> {
+        let records: String = values
+            .iter()
+            .map(|v| format!("<ResourceRecord><Value>{}</Value></ResourceRecord>", v))
+            .collect();
+        let body = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <ChangeResourceRecordSetsRequest xmlns=\"https://route53.amazonaws.com/doc/2013-04-01/\">\
+             <ChangeBatch><Changes><Change><Action>{}</Action><ResourceRecordSet>\
+             <Name>{}.</Name><Type>{}</Type><TTL>{}</TTL><ResourceRecords>{}</ResourceRecords>\
+             </ResourceRecordSet></Change></Changes></ChangeBatch></ChangeResourceRecordSetsRequest>",
+            action,
+            name,
+            record_type.as_str(),
+            ttl,
+            records
+        );
+        self.request(
+            reqwest::Method::POST,
+            &format!("/2013-04-01/hostedzone/{}/rrset", self.zone_id),
+            &[],
+            body,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DnsProvider for Route53 {
+    fn name(&self) -> &'static str {
+        "Route 53"
+    }
+
+    async fn set_records(&self, name: &str, record_type: RecordType, values: &[String], ttl: u32) -> Result<()> {
+        if !values.is_empty() {
+            return self.change("UPSERT", name, record_type, values, ttl).await;
+        }
+        // 删除须给出与现有记录完全一致的值和 TTL
+        match self.current(name, record_type).await? {
+            Some((ttl, values)) => self.change("DELETE", name, record_type, &values, ttl).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// AWS 规定的 URI 编码（只保留非保留字符）
+pub(crate) fn uri_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+pub(crate) fn canonical_query(query: &[(&str, &str)]) -> String {
+    let mut pairs: Vec<String> = query.iter().map(|(k, v)| format!("{}={}", uri_encode(k), uri_encode(v))).collect();
+    pairs.sort();
+    pairs.join("&")
+}
+
+pub(crate) fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC 接受任意长度的密钥");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// SigV4 签名密钥
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(format!("AWS4{}", secret).as_bytes(), date);
+    let key = hmac_sha256(&key, region);
+    let key = hmac_sha256(&key, service);
+    hmac_sha256(&key, "aws4_request")
+}
+
+/// SigV4 签名，返回需要附加的请求头；凭据为 (Access Key ID, Secret Access Key)，服务端点为 (主机, 区域, 服务)
+pub(crate) fn sign(
+    (access_key_id, secret): (&str, &str),
+    (host, region, service): (&str, &str, &str),
+    method: &str,
+    path: &str,
+    query: &str,
+    body: &str,
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let payload_hash = hex::encode(Sha256::digest(body.as_bytes()));
+    let canonical_request = format!(
+        "{}\n{}\n{}\nhost:{}\nx-amz-date:{}\n\nhost;x-amz-date\n{}",
+        method, path, query, host, amz_date, payload_hash
+    );
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signature = hex::encode(hmac_sha256(&signing_key(secret, &date, region, service), &string_to_sign));
+    vec![
+        ("x-amz-date", amz_date),
+        (
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-date, Signature={}",
+                access_key_id, scope, signature
+            ),
+        ),
+    ]
+}
+
+/// 所有 `<tag>...</tag>` 块的内容
+pub(crate) fn xml_blocks<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut blocks = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        let Some(end) = after.find(&close) else {
+            break;
+        };
+        blocks.push(&after[..end]);
+        rest = &after[end + close.len()..];
+    }
+    blocks
+}
+
+pub(crate) fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    xml_blocks(xml, tag).into_iter().map(|v| v.trim().to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_key() {
+        // AWS 文档中的派生签名密钥示例
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(hex::encode(key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+    }
+
+    #[test]
+    fn test_canonical_query() {
+        assert_eq!(
+            canonical_query(&[("type", "A"), ("name", "ssh.example.com."), ("maxitems", "1")]),
+            "maxitems=1&name=ssh.example.com.&type=A"
+        );
+        assert_eq!(uri_encode("a b/c"), "a%20b%2Fc");
+    }
+
+    #[test]
+    fn test_xml_values() {
+        let xml = "<ResourceRecordSets><ResourceRecordSet><Name>ssh.example.com.</Name><Type>A</Type><TTL>60</TTL>\
+            <ResourceRecords><ResourceRecord><Value>1.2.3.4</Value></ResourceRecord>\
+            <ResourceRecord><Value>5.6.7.8</Value></ResourceRecord></ResourceRecords></ResourceRecordSet></ResourceRecordSets>";
+        let set = xml_blocks(xml, "ResourceRecordSet")[0];
+        assert_eq!(xml_values(set, "Name"), vec!["ssh.example.com."]);
+        assert_eq!(xml_values(set, "Value"), vec!["1.2.3.4", "5.6.7.8"]);
+    }
+}
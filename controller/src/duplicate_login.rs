@@ -0,0 +1,128 @@
+//! 重复登录策略
+//!
+//! 同一 token 的另一个客户端实例在原实例在线时连接（token 被复制到多台主机）的处理方式，按客户端配置：
+//!
+//! - `reject_new`（默认）：拒绝新实例，原实例不受影响
+//! - `kick_old`：新实例上线，原实例被踢下线并停止重连，适合迁移主机
+//! - `replicas`：两个实例同时在线，各自建立隧道，节点在各实例间分配访客连接
+//!
+//! 同一实例重连（或旧版客户端未上报实例 ID）不受策略影响，新连接直接取代旧连接。
+//! 拒绝、踢下线、副本加入以及原实例离线后换了新实例都会记录审计事件。
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, NotSet, Set};
+use tracing::error;
+
+use crate::entity::client_login_event;
+use crate::migration::get_connection;
+
+/// 新实例被拒绝
+pub const EVENT_REJECTED: &str = "rejected";
+/// 原实例被新实例踢下线
+pub const EVENT_KICKED: &str = "kicked";
+/// 新实例作为副本加入
+pub const EVENT_REPLICA: &str = "replica";
+/// 原实例离线后由另一个实例连接（重新安装或迁移）
+pub const EVENT_REBOUND: &str = "rebound";
+
+/// 被拒绝的实例按正常间隔重试，同一事件在该时间内只记录一次
+const EVENT_DEDUP_WINDOW: Duration = Duration::from_secs(600);
+
+/// (客户端 ID, 事件类型, 实例 ID) -> 最近一次记录的时间
+type RecentEvents = HashMap<(i64, &'static str, String), Instant>;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Policy {
+    #[default]
+    RejectNew,
+    KickOld,
+    Replicas,
+}
+
+impl Policy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "reject_new" => Some(Self::RejectNew),
+            "kick_old" => Some(Self::KickOld),
+            "replicas" => Some(Self::Replicas),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RejectNew => "reject_new",
+            Self::KickOld => "kick_old",
+            Self::Replicas => "replicas",
+        }
+    }
+
+    /// 按客户端保存的值解析，无法识别时按默认策略处理
+    pub fn of(client: &crate::entity::client::Model) -> Self {
+        Self::parse(&client.duplicate_login_policy).unwrap_or_default()
+    }
+}
+
+/// 记录重复登录审计事件
+pub async fn record(
+    client_id: i64,
+    action: &'static str,
+    instance_id: Option<String>,
+    other_instance_id: Option<String>,
+    ip: Option<String>,
+) {
+    if !should_record(client_id, action, instance_id.as_deref()) {
+        return;
+    }
+    let event = client_login_event::ActiveModel {
+        id: NotSet,
+        client_id: Set(client_id),
+        action: Set(action.to_string()),
+        instance_id: Set(instance_id),
+        other_instance_id: Set(other_instance_id),
+        ip: Set(ip),
+        created_at: Set(Utc::now().naive_utc()),
+    };
+    if let Err(e) = event.insert(get_connection().await).await {
+        error!("记录客户端 #{} 的重复登录事件失败: {}", client_id, e);
+    }
+}
+
+/// 同一客户端、同一实例的同类事件在去重窗口内只记录一次
+fn should_record(client_id: i64, action: &'static str, instance_id: Option<&str>) -> bool {
+    static LAST: OnceLock<Mutex<RecentEvents>> = OnceLock::new();
+    let now = Instant::now();
+    let mut last = LAST.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    last.retain(|_, at| now.duration_since(*at) < EVENT_DEDUP_WINDOW);
+    let key = (client_id, action, instance_id.unwrap_or_default().to_string());
+    if last.contains_key(&key) {
+        return false;
+    }
+    last.insert(key, now);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_policies() {
+        for policy in [Policy::RejectNew, Policy::KickOld, Policy::Replicas] {
+            assert_eq!(Policy::parse(policy.as_str()), Some(policy));
+        }
+        assert_eq!(Policy::parse("both"), None);
+    }
+
+    #[test]
+    fn dedups_repeated_events() {
+        assert!(should_record(-1, EVENT_REJECTED, Some("a")));
+        assert!(!should_record(-1, EVENT_REJECTED, Some("a")));
+        assert!(should_record(-1, EVENT_REJECTED, Some("b")));
+        assert!(should_record(-1, EVENT_KICKED, Some("a")));
+    }
+}
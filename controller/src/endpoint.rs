@@ -0,0 +1,247 @@
+//! 访客连接字符串
+//!
+//! 按代理的服务类型标签（`service`，未设置时按本地端口推断）生成可直接复制的连接命令 / 地址，
+//! 例如 `ssh -p 20022 user@node.example.com`、`mysql://node.example.com:23306`。
+//! 地址优先使用代理发布的 DNS 名称，其次为代理监听的公网 IP、节点的隧道地址、公网 IP。
+//! 多节点发布的代理另外列出其他节点的地址。
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use serde::Serialize;
+
+use crate::entity::{node, proxy};
+
+/// 支持生成连接字符串的服务类型
+pub const SERVICES: [&str; 12] = [
+    "ssh", "http", "https", "mysql", "postgres", "redis", "mongodb", "rdp", "vnc", "smb", "minecraft", "ftp",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Endpoint {
+    /// 显示名称，如 `SSH`、`地址`
+    pub label: String,
+    pub value: String,
+}
+
+impl Endpoint {
+    fn new(label: &str, value: String) -> Self {
+        Self { label: label.to_string(), value }
+    }
+}
+
+/// 校验服务类型标签，空字符串视为不设置
+pub fn normalize_service(service: Option<String>) -> Result<Option<String>, String> {
+    match service.map(|s| s.trim().to_ascii_lowercase()) {
+        None => Ok(None),
+        Some(s) if s.is_empty() => Ok(None),
+        Some(s) if SERVICES.contains(&s.as_str()) => Ok(Some(s)),
+        Some(s) => Err(format!("未知的服务类型: {}（可选 {}）", s, SERVICES.join("、"))),
+    }
+}
+
+/// 按本地服务的常用端口推断服务类型
+fn guess_service(local_port: u16) -> Option<&'static str> {
+    Some(match local_port {
+        21 => "ftp",
+        22 => "ssh",
+        80 | 8080 => "http",
+        443 | 8443 => "https",
+        445 => "smb",
+        3306 => "mysql",
+        3389 => "rdp",
+        5432 => "postgres",
+        5900..=5909 => "vnc",
+        6379 => "redis",
+        25565 => "minecraft",
+        27017 => "mongodb",
+        _ => return None,
+    })
+}
+
+/// 代理的连接字符串，无法确定访客地址（如未指定节点）时为空
+pub fn for_proxy(proxy: &proxy::Model, node: Option<&node::Model>) -> Vec<Endpoint> {
+    visitor_host(proxy, node).map(|host| compose(proxy, &host)).unwrap_or_default()
+}
+
+/// 访客连接使用的主机名
+pub fn visitor_host(proxy: &proxy::Model, node: Option<&node::Model>) -> Option<String> {
+    if let Some(name) = &proxy.dns_name {
+        // SRV 名称取其指向的主机名
+        return Some(crate::dns::split_srv(name).1.to_string());
+    }
+    // 多网卡节点上代理只监听某个公网 IP 时，访客需连接该 IP
+    if let Some(ip) = proxy.bind_ip.as_deref().filter(|ip| is_public_ip(ip)) {
+        return Some(ip.to_string());
+    }
+    node_host(node?)
+}
+
+/// 节点对访客的地址：隧道地址，其次公网 IP
+fn node_host(node: &node::Model) -> Option<String> {
+    [Some(node.tunnel_addr.as_str()), node.public_ip.as_deref()]
+        .into_iter()
+        .flatten()
+        .find(|addr| !addr.is_empty())
+        .map(str::to_string)
+}
+
+/// 代理在所有发布节点上的连接字符串：`node_id` 节点的完整列表，多节点发布的其他节点各附一条地址
+pub fn for_published(proxy: &proxy::Model, nodes: &HashMap<i64, node::Model>) -> Vec<Endpoint> {
+    let mut endpoints = for_proxy(proxy, proxy.node_id.and_then(|id| nodes.get(&id)));
+    for node in proxy.anycast_ids().iter().filter_map(|id| nodes.get(id)) {
+        if let Some(addr) = node_host(node).and_then(|host| compose(proxy, &host).into_iter().next()) {
+            endpoints.push(Endpoint::new(&format!("地址（{}）", node.name), addr.value));
+        }
+    }
+    endpoints
+}
+
+/// 是否为访客可以直接连接的公网地址
+pub(crate) fn is_public_ip(ip: &str) -> bool {
+    match ip.parse::<IpAddr>() {
+        Ok(IpAddr::V4(v4)) => !(v4.is_private() || v4.is_loopback() || v4.is_link_local() || v4.is_unspecified()),
+        // 2000::/3 全球单播
+        Ok(IpAddr::V6(v6)) => v6.segments()[0] & 0xe000 == 0x2000,
+        Err(_) => false,
+    }
+}
+
+/// 生成代理的连接字符串，第一项始终为 `主机:端口` 地址
+fn compose(proxy: &proxy::Model, host: &str) -> Vec<Endpoint> {
+    let port = proxy.remote_port;
+    // IPv6 地址在 URL 和 host:port 中需要加方括号
+    let host = match host.parse::<IpAddr>() {
+        Ok(IpAddr::V6(ip)) => format!("[{}]", ip),
+        _ => host.to_string(),
+    };
+    let addr = format!("{}:{}", host, port);
+    let mut endpoints = vec![Endpoint::new("地址", addr.clone())];
+    if proxy.proxy_type.eq_ignore_ascii_case("udp") {
+        return endpoints;
+    }
+
+    let service = proxy.service.as_deref().or_else(|| guess_service(proxy.local_port));
+    match service {
+        Some("ssh") => {
+            endpoints.push(Endpoint::new("SSH", format!("ssh -p {} user@{}", port, host)));
+            endpoints.push(Endpoint::new("SCP", format!("scp -P {} file user@{}:", port, host)));
+        }
+        Some("http") if port == 80 => endpoints.push(Endpoint::new("URL", format!("http://{}", host))),
+        Some("http") => endpoints.push(Endpoint::new("URL", format!("http://{}", addr))),
+        Some("https") if port == 443 => endpoints.push(Endpoint::new("URL", format!("https://{}", host))),
+        Some("https") => endpoints.push(Endpoint::new("URL", format!("https://{}", addr))),
+        Some("mysql") => {
+            endpoints.push(Endpoint::new("MySQL", format!("mysql -h {} -P {} -u root -p", host, port)));
+            endpoints.push(Endpoint::new("URL", format!("mysql://{}", addr)));
+        }
+        Some("postgres") => {
+            endpoints.push(Endpoint::new("psql", format!("psql -h {} -p {} -U postgres", host, port)));
+            endpoints.push(Endpoint::new("URL", format!("postgresql://{}", addr)));
+        }
+        Some("redis") => {
+            endpoints.push(Endpoint::new("redis-cli", format!("redis-cli -h {} -p {}", host, port)));
+            endpoints.push(Endpoint::new("URL", format!("redis://{}", addr)));
+        }
+        Some("mongodb") => endpoints.push(Endpoint::new("URL", format!("mongodb://{}", addr))),
+        Some("rdp") => endpoints.push(Endpoint::new("RDP", format!("mstsc /v:{}", addr))),
+        Some("vnc") => endpoints.push(Endpoint::new("URL", format!("vnc://{}", addr))),
+        Some("smb") => endpoints.push(Endpoint::new("URL", format!("smb://{}", addr))),
+        Some("ftp") => endpoints.push(Endpoint::new("URL", format!("ftp://{}", addr))),
+        // 发布了 SRV 记录时只需填写主机名
+        Some("minecraft") if proxy.dns_name.as_deref().is_some_and(|n| n.starts_with("_minecraft._tcp.")) => {
+            endpoints.push(Endpoint::new("Minecraft", host))
+        }
+        _ => {}
+    }
+    endpoints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn proxy(proxy_type: &str, local_port: u16, remote_port: u16, service: Option<&str>, dns_name: Option<&str>) -> proxy::Model {
+        let now = Utc::now().naive_utc();
+        proxy::Model {
+            id: 1,
+            client_id: "1".to_string(),
+            name: "test".to_string(),
+            proxy_type: proxy_type.to_string(),
+            local_ip: "127.0.0.1".to_string(),
+            local_port,
+            remote_port,
+            bind_ip: None,
+            enabled: true,
+            anycast_node_ids: None,
+            node_id: Some(1),
+            group_id: None,
+            idle_timeout: None,
+            mitigation_config: None,
+            bandwidth_weight: None,
+            priority: None,
+            local_pool_size: None,
+            local_source: None,
+            dns_name: dns_name.map(str::to_string),
+            service: service.map(str::to_string),
+            schedule: None,
+            expires_at: None,
+            stale_at: None,
+            total_bytes_sent: 0,
+            total_bytes_received: 0,
+            lock_version: 0,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn values(endpoints: Vec<Endpoint>) -> Vec<String> {
+        endpoints.into_iter().map(|e| e.value).collect()
+    }
+
+    #[test]
+    fn test_compose() {
+        assert_eq!(
+            values(compose(&proxy("tcp", 22, 20022, None, None), "node.example.com")),
+            vec![
+                "node.example.com:20022",
+                "ssh -p 20022 user@node.example.com",
+                "scp -P 20022 file user@node.example.com:",
+            ]
+        );
+        assert_eq!(
+            values(compose(&proxy("tcp", 3307, 23306, Some("mysql"), None), "2001:db8::1")),
+            vec!["[2001:db8::1]:23306", "mysql -h [2001:db8::1] -P 23306 -u root -p", "mysql://[2001:db8::1]:23306"]
+        );
+        assert_eq!(values(compose(&proxy("tcp", 8080, 80, None, None), "1.2.3.4")), vec!["1.2.3.4:80", "http://1.2.3.4"]);
+        assert_eq!(values(compose(&proxy("udp", 22, 20022, Some("ssh"), None), "1.2.3.4")), vec!["1.2.3.4:20022"]);
+        assert_eq!(values(compose(&proxy("tcp", 9000, 20000, None, None), "1.2.3.4")), vec!["1.2.3.4:20000"]);
+    }
+
+    #[test]
+    fn test_visitor_host() {
+        let p = proxy("tcp", 25565, 30000, None, Some("_minecraft._tcp.mc.example.com"));
+        assert_eq!(visitor_host(&p, None).as_deref(), Some("mc.example.com"));
+        assert_eq!(
+            values(compose(&p, "mc.example.com")),
+            vec!["mc.example.com:30000", "mc.example.com"]
+        );
+        let p = proxy("tcp", 22, 20022, None, Some("ssh.example.com"));
+        assert_eq!(visitor_host(&p, None).as_deref(), Some("ssh.example.com"));
+        assert_eq!(visitor_host(&proxy("tcp", 22, 20022, None, None), None), None);
+
+        let mut p = proxy("tcp", 22, 20022, None, None);
+        p.bind_ip = Some("198.51.100.7".to_string());
+        assert_eq!(visitor_host(&p, None).as_deref(), Some("198.51.100.7"));
+        p.bind_ip = Some("10.0.0.7".to_string());
+        assert_eq!(visitor_host(&p, None), None);
+    }
+
+    #[test]
+    fn test_normalize_service() {
+        assert_eq!(normalize_service(Some(" SSH ".to_string())), Ok(Some("ssh".to_string())));
+        assert_eq!(normalize_service(Some(String::new())), Ok(None));
+        assert!(normalize_service(Some("telnet".to_string())).is_err());
+    }
+}
@@ -8,6 +8,28 @@ pub mod system_config;
 pub mod node;
 pub mod subscription;
 pub mod user_subscription;
+pub mod update_rollout;
+pub mod agent_update;
+pub mod config_revision;
+pub mod tenant;
+pub mod port_blocklist;
+pub mod temporary_tunnel;
+pub mod traffic_report;
+pub mod impersonation_log;
+pub mod mitigation_event;
+pub mod alert_rule;
+pub mod alert_event;
+pub mod maintenance_window;
+pub mod status_history;
+pub mod port_reservation;
+pub mod visitor;
+pub mod system_restart;
+pub mod proxy_capture;
+pub mod failover_group;
+pub mod proxy_port_probe;
+pub mod client_login_event;
+pub mod api_token;
+pub mod web_session;
 
 pub use client::Entity as Client;
 pub use proxy::Entity as Proxy;
@@ -19,3 +41,25 @@ pub use system_config::Entity as SystemConfig;
 pub use node::Entity as Node;
 pub use subscription::Entity as Subscription;
 pub use user_subscription::Entity as UserSubscription;
+pub use update_rollout::Entity as UpdateRollout;
+pub use agent_update::Entity as AgentUpdate;
+pub use config_revision::Entity as ConfigRevision;
+pub use tenant::Entity as Tenant;
+pub use port_blocklist::Entity as PortBlocklist;
+pub use temporary_tunnel::Entity as TemporaryTunnel;
+pub use traffic_report::Entity as TrafficReport;
+pub use impersonation_log::Entity as ImpersonationLog;
+pub use mitigation_event::Entity as MitigationEvent;
+pub use alert_rule::Entity as AlertRule;
+pub use alert_event::Entity as AlertEvent;
+pub use maintenance_window::Entity as MaintenanceWindow;
+pub use status_history::Entity as StatusHistory;
+pub use port_reservation::Entity as PortReservation;
+pub use visitor::Entity as Visitor;
+pub use system_restart::Entity as SystemRestart;
+pub use proxy_capture::Entity as ProxyCapture;
+pub use failover_group::Entity as FailoverGroup;
+pub use proxy_port_probe::Entity as ProxyPortProbe;
+pub use client_login_event::Entity as ClientLoginEvent;
+pub use api_token::Entity as ApiToken;
+pub use web_session::Entity as WebSession;
@@ -0,0 +1,43 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "agent_update")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[serde(rename = "rolloutId")]
+    pub rollout_id: i64,
+    #[serde(rename = "agentType")]
+    pub agent_type: String,
+    #[serde(rename = "agentId")]
+    pub agent_id: i64,
+    #[serde(rename = "fromVersion")]
+    pub from_version: Option<String>,
+    #[serde(rename = "targetVersion")]
+    pub target_version: String,
+    pub status: String, // dispatched, downloading, restarting, succeeded, failed
+    pub message: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::update_rollout::Entity",
+        from = "Column::RolloutId",
+        to = "super::update_rollout::Column::Id"
+    )]
+    UpdateRollout,
+}
+
+impl Related<super::update_rollout::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UpdateRollout.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,39 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const STATE_FIRING: &str = "firing";
+pub const STATE_RESOLVED: &str = "resolved";
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "alert_event")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[serde(rename = "ruleId")]
+    pub rule_id: i64,
+    #[serde(rename = "ruleName")]
+    pub rule_name: String,
+    pub kind: String,
+    pub severity: String,
+    /// `node` / `client` / `user` / `proxy`
+    #[serde(rename = "targetType")]
+    pub target_type: String,
+    #[serde(rename = "targetId")]
+    pub target_id: i64,
+    #[serde(rename = "targetName")]
+    pub target_name: String,
+    /// 触发时的指标值
+    pub value: f64,
+    pub message: String,
+    /// `firing` / `resolved`
+    pub state: String,
+    #[serde(rename = "firedAt")]
+    pub fired_at: DateTime,
+    #[serde(rename = "resolvedAt")]
+    pub resolved_at: Option<DateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
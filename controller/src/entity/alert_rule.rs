@@ -0,0 +1,32 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "alert_rule")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub name: String,
+    /// `node_offline` / `client_offline` / `user_traffic` / `proxy_error_rate`
+    pub kind: String,
+    /// 阈值：流量为配额百分比，错误率为每分钟次数，离线类规则不使用
+    pub threshold: f64,
+    /// 条件持续多少秒后触发
+    #[serde(rename = "durationSecs")]
+    pub duration_secs: i64,
+    /// `info` / `warning` / `critical`
+    pub severity: String,
+    /// 只检查指定的节点 / 客户端 / 用户 / 代理，为空时检查全部
+    #[serde(rename = "targetId")]
+    pub target_id: Option<i64>,
+    pub enabled: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,32 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// 用户的长期 API 令牌，请求时通过 `X-API-Key` 头携带
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "api_token")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[serde(rename = "userId")]
+    pub user_id: i64,
+    /// 用途说明，如 `rfrpctl@laptop`
+    pub name: String,
+    /// 令牌原文的 SHA-256（十六进制），不保存原文
+    #[sea_orm(unique)]
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    /// 令牌原文的前几位，用于在列表中辨认
+    pub prefix: String,
+    /// 过期时间，为空表示不过期
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<DateTime>,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: Option<DateTime>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -27,6 +27,32 @@ pub struct Model {
     #[serde(rename = "userId")]
     pub user_id: Option<i64>,
     pub version: Option<String>,
+    /// 允许转发的本地目标白名单（逗号分隔），为空表示不限制
+    #[serde(rename = "allowedTargets")]
+    pub allowed_targets: Option<String>,
+    /// 到期时间，为空表示永不过期
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<DateTime>,
+    /// 客户端检测到的 NAT 类型（open / full_cone / port_restricted_cone / symmetric / udp_blocked）
+    #[serde(rename = "natType")]
+    pub nat_type: Option<String>,
+    /// NAT 外部映射地址（ip:port）
+    #[serde(rename = "natMappedAddr")]
+    pub nat_mapped_addr: Option<String>,
+    #[serde(rename = "natDetectedAt")]
+    pub nat_detected_at: Option<DateTime>,
+    /// 客户端心跳上报的操作系统和 CPU 架构
+    pub os: Option<String>,
+    pub arch: Option<String>,
+    /// 客户端进程启动时间（按心跳上报的运行时长推算）
+    #[serde(rename = "startedAt")]
+    pub started_at: Option<DateTime>,
+    /// 客户端首次启动时生成的实例 ID，用于区分同一 token 下的不同主机
+    #[serde(rename = "instanceId")]
+    pub instance_id: Option<String>,
+    /// 重复登录策略：`reject_new` / `kick_old` / `replicas`
+    #[serde(rename = "duplicateLoginPolicy")]
+    pub duplicate_login_policy: String,
     pub created_at: DateTime,
     pub updated_at: DateTime,
 }
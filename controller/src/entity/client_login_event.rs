@@ -0,0 +1,27 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "client_login_event")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[serde(rename = "clientId")]
+    pub client_id: i64,
+    /// `rejected` / `kicked` / `replica` / `rebound`
+    pub action: String,
+    /// 发起连接的实例
+    #[serde(rename = "instanceId")]
+    pub instance_id: Option<String>,
+    /// 已在线（被拒绝时）、被踢下线或原先绑定的实例
+    #[serde(rename = "otherInstanceId")]
+    pub other_instance_id: Option<String>,
+    pub ip: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "config_revision")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// 本次修订的变更（JSON 数组）
+    pub changes: String,
+    /// 修订后的完整配置快照（JSON 对象，键 → 原始值）
+    pub snapshot: String,
+    pub description: Option<String>,
+    #[serde(rename = "createdBy")]
+    pub created_by: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
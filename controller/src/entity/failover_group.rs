@@ -0,0 +1,58 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "failover_group")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub name: String,
+    #[serde(rename = "primaryNodeId")]
+    pub primary_node_id: i64,
+    /// 备用节点 ID（逗号分隔，按优先级排列）
+    #[serde(rename = "standbyNodeIds")]
+    pub standby_node_ids: String,
+    /// 当前节点持续离线多少秒后转移
+    #[serde(rename = "offlineThresholdSecs")]
+    pub offline_threshold_secs: i32,
+    /// 主节点恢复后是否切回
+    pub failback: bool,
+    pub enabled: bool,
+    /// 组内代理当前所在的节点
+    #[serde(rename = "activeNodeId")]
+    pub active_node_id: i64,
+    /// 已从主节点转移走的代理 ID（逗号分隔），切回时只迁回这些代理
+    #[serde(rename = "movedProxyIds")]
+    pub moved_proxy_ids: Option<String>,
+    #[serde(rename = "failedOverAt")]
+    pub failed_over_at: Option<DateTime>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// 解析逗号分隔的 ID 列表
+pub fn parse_ids(raw: &str) -> Vec<i64> {
+    raw.split(',').filter_map(|s| s.trim().parse().ok()).collect()
+}
+
+/// 格式化为逗号分隔的 ID 列表
+pub fn format_ids(ids: &[i64]) -> String {
+    ids.iter().map(i64::to_string).collect::<Vec<_>>().join(",")
+}
+
+impl Model {
+    pub fn standby_ids(&self) -> Vec<i64> {
+        parse_ids(&self.standby_node_ids)
+    }
+
+    pub fn moved_ids(&self) -> Vec<i64> {
+        self.moved_proxy_ids.as_deref().map(parse_ids).unwrap_or_default()
+    }
+}
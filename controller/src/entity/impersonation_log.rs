@@ -0,0 +1,29 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "impersonation_log")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// 发起模拟登录的管理员
+    #[serde(rename = "adminId")]
+    pub admin_id: i64,
+    #[serde(rename = "adminUsername")]
+    pub admin_username: String,
+    /// 被模拟的用户
+    #[serde(rename = "userId")]
+    pub user_id: i64,
+    pub username: String,
+    pub reason: Option<String>,
+    /// 模拟令牌的过期时间
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,33 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const SCOPE_NODE: &str = "node";
+pub const SCOPE_CLIENT: &str = "client";
+pub const SCOPE_PROXY: &str = "proxy";
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "maintenance_window")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub name: String,
+    /// `node` / `client` / `proxy`
+    #[serde(rename = "scopeType")]
+    pub scope_type: String,
+    #[serde(rename = "scopeId")]
+    pub scope_id: i64,
+    #[serde(rename = "startsAt")]
+    pub starts_at: DateTime,
+    #[serde(rename = "endsAt")]
+    pub ends_at: DateTime,
+    pub description: Option<String>,
+    #[serde(rename = "createdBy")]
+    pub created_by: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,36 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "mitigation_event")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[serde(rename = "nodeId")]
+    pub node_id: i64,
+    #[serde(rename = "proxyId")]
+    pub proxy_id: i64,
+    #[serde(rename = "clientId")]
+    pub client_id: i64,
+    #[serde(rename = "sourceIp")]
+    pub source_ip: String,
+    /// `throttle` / `block`
+    pub action: String,
+    /// 触发原因（持续超限的指标）
+    pub reason: String,
+    /// 节点上的处置到期时间
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime,
+    /// 管理员提前解除的时间
+    #[serde(rename = "releasedAt")]
+    pub released_at: Option<DateTime>,
+    #[serde(rename = "releasedBy")]
+    pub released_by: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
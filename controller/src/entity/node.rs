@@ -23,6 +23,8 @@ pub struct Model {
     pub tunnel_protocol: String,
     #[serde(rename = "kcpConfig")]
     pub kcp_config: Option<String>,
+    #[serde(rename = "quicConfig")]
+    pub quic_config: Option<String>,
     #[serde(rename = "nodeType")]
     pub node_type: String,
     #[serde(rename = "maxProxyCount")]
@@ -43,7 +45,32 @@ pub struct Model {
     pub is_traffic_exceeded: bool,
     #[serde(rename = "speedLimit")]
     pub speed_limit: Option<i64>,
+    /// 带宽时间表，如 `00:00-08:00=12.5M`，不在任何窗口内时使用 speedLimit
+    #[serde(rename = "speedLimitSchedule")]
+    pub speed_limit_schedule: Option<String>,
+    /// 带宽饱和时是否按代理权重公平分配
+    #[serde(rename = "fairShare")]
+    pub fair_share: bool,
     pub version: Option<String>,
+    #[serde(rename = "tenantId")]
+    pub tenant_id: Option<i64>,
+    /// NAT 探测端口（节点注册时上报，客户端向该端口和下一个端口发送探测）
+    #[serde(rename = "natProbePort")]
+    pub nat_probe_port: Option<i32>,
+    /// 默认的来源 IP 处置规则（JSON），代理未单独设置时使用
+    #[serde(rename = "mitigationConfig")]
+    pub mitigation_config: Option<String>,
+    /// 云服务商防火墙配置（JSON），创建 / 删除代理时自动放行 / 关闭端口，序列化时隐藏凭据
+    #[serde(rename = "firewallConfig", serialize_with = "crate::cloud_firewall::serialize_redacted")]
+    pub firewall_config: Option<String>,
+    /// 节点能力（注册时上报的公网 IP、隧道协议、端口范围等，JSON）
+    pub capabilities: Option<String>,
+    /// 排空时禁用的代理 ID（逗号分隔），不为空表示节点处于排空状态，恢复时只重新启用这些代理
+    #[serde(rename = "drainedProxyIds")]
+    pub drained_proxy_ids: Option<String>,
+    /// 乐观锁版本号，每次通过 API 修改加一
+    #[serde(rename = "lockVersion")]
+    pub lock_version: i32,
     pub created_at: DateTime,
     pub updated_at: DateTime,
 }
@@ -61,3 +88,10 @@ impl Related<super::user_node::Entity> for Entity {
 }
 
 impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// 节点注册时上报的能力，旧版节点未上报时为 `None`
+    pub fn capabilities(&self) -> Option<common::protocol::node_register::NodeCapabilities> {
+        serde_json::from_str(self.capabilities.as_deref()?).ok()
+    }
+}
@@ -0,0 +1,32 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// 远程端口规则：禁止在节点上监听匹配的端口
+pub const KIND_REMOTE_PORT: &str = "remote_port";
+/// 本地目标规则：禁止客户端转发到匹配的地址 / 端口
+pub const KIND_LOCAL_TARGET: &str = "local_target";
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "port_blocklist")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// 规则类型：remote_port / local_target
+    pub kind: String,
+    /// 规则内容，格式见 `common::target_rule`
+    pub pattern: String,
+    /// 仅对指定节点生效，为空表示所有节点
+    #[serde(rename = "nodeId")]
+    pub node_id: Option<i64>,
+    /// 仅对指定租户生效，为空表示所有租户
+    #[serde(rename = "tenantId")]
+    pub tenant_id: Option<i64>,
+    pub description: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,41 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "port_reservation")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[serde(rename = "nodeId")]
+    pub node_id: i64,
+    /// 预留的端口范围（含两端）
+    #[serde(rename = "portStart")]
+    pub port_start: i32,
+    #[serde(rename = "portEnd")]
+    pub port_end: i32,
+    /// 预留给的用户，与 `tenant_id` 二选一
+    #[serde(rename = "userId")]
+    pub user_id: Option<i64>,
+    /// 预留给的租户（租户内所有用户可用）
+    #[serde(rename = "tenantId")]
+    pub tenant_id: Option<i64>,
+    /// 过期时间，为空表示一直有效
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<DateTime>,
+    pub description: Option<String>,
+    #[serde(rename = "createdBy")]
+    pub created_by: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+}
+
+impl Model {
+    pub fn contains(&self, port: u16) -> bool {
+        (self.port_start..=self.port_end).contains(&(port as i32))
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
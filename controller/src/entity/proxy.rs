@@ -16,15 +16,54 @@ pub struct Model {
     pub local_port: u16,
     #[serde(rename = "remotePort")]
     pub remote_port: u16,
+    /// 节点上监听的 IP，为空时使用节点默认的监听地址
+    #[serde(rename = "bindIp")]
+    pub bind_ip: Option<String>,
     pub enabled: bool,
     #[serde(rename = "nodeId")]
     pub node_id: Option<i64>,
+    /// 同时发布该代理的其他节点 ID（逗号分隔），各节点监听相同的远程端口
+    #[serde(rename = "anycastNodeIds")]
+    pub anycast_node_ids: Option<String>,
     #[serde(rename = "groupId")]
     pub group_id: Option<String>,
+    /// TCP 连接空闲超时（秒），0 表示不限制，为空时使用节点默认值
+    #[serde(rename = "idleTimeout")]
+    pub idle_timeout: Option<i32>,
+    /// 来源 IP 处置规则（JSON），为空时使用节点默认规则
+    #[serde(rename = "mitigationConfig")]
+    pub mitigation_config: Option<String>,
+    /// 节点公平分配带宽时的权重（1-100），为空时为 1
+    #[serde(rename = "bandwidthWeight")]
+    pub bandwidth_weight: Option<i32>,
+    /// 优先级（high / normal / low），为空时为 normal
+    pub priority: Option<String>,
+    /// 客户端预先建立并保持的本地服务连接数，为空或 0 表示不启用
+    #[serde(rename = "localPoolSize")]
+    pub local_pool_size: Option<i32>,
+    /// 客户端连接本地服务使用的源 IP 或网卡，为空时使用客户端的全局设置
+    #[serde(rename = "localSource")]
+    pub local_source: Option<String>,
+    /// 自动发布的 DNS 记录名（A/AAAA，`_service._proto.` 开头时同时发布 SRV），为空表示不发布
+    #[serde(rename = "dnsName")]
+    pub dns_name: Option<String>,
+    /// 访客侧服务类型标签（如 ssh、mysql），用于生成连接字符串，为空时按本地端口推断
+    pub service: Option<String>,
+    /// 启用时间表（每周时间窗口），为空表示不按时间自动启停
+    pub schedule: Option<String>,
+    /// 到期时间，为空表示永不过期
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<DateTime>,
+    /// 被标记为闲置（长时间无流量）的时间
+    #[serde(rename = "staleAt")]
+    pub stale_at: Option<DateTime>,
     #[serde(rename = "totalBytesSent")]
     pub total_bytes_sent: i64,
     #[serde(rename = "totalBytesReceived")]
     pub total_bytes_received: i64,
+    /// 乐观锁版本号，每次通过 API 修改加一
+    #[serde(rename = "lockVersion")]
+    pub lock_version: i32,
     pub created_at: DateTime,
     pub updated_at: DateTime,
 }
@@ -40,3 +79,21 @@ pub enum Relation {
 }
 
 impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// 除 `node_id` 外同时发布的节点
+    pub fn anycast_ids(&self) -> Vec<i64> {
+        let ids = self.anycast_node_ids.as_deref().map(super::failover_group::parse_ids).unwrap_or_default();
+        ids.into_iter().filter(|id| Some(*id) != self.node_id).collect()
+    }
+
+    /// 发布该代理的所有节点，`node_id` 在前
+    pub fn node_ids(&self) -> Vec<i64> {
+        self.node_id.into_iter().chain(self.anycast_ids()).collect()
+    }
+
+    /// 代理是否发布在该节点上
+    pub fn is_published_on(&self, node_id: i64) -> bool {
+        self.node_id == Some(node_id) || self.anycast_ids().contains(&node_id)
+    }
+}
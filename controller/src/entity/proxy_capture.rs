@@ -0,0 +1,52 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_UPLOADING: &str = "uploading";
+pub const STATUS_COMPLETED: &str = "completed";
+pub const STATUS_FAILED: &str = "failed";
+pub const STATUS_DELETED: &str = "deleted";
+
+/// 代理抓包记录（兼作审计日志，文件删除后记录保留）
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "proxy_capture")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[serde(rename = "proxyId")]
+    pub proxy_id: i64,
+    #[serde(rename = "proxyName")]
+    pub proxy_name: String,
+    #[serde(rename = "nodeId")]
+    pub node_id: i64,
+    /// 发起抓包的管理员
+    #[serde(rename = "userId")]
+    pub user_id: i64,
+    pub username: String,
+    pub reason: Option<String>,
+    #[serde(rename = "durationSecs")]
+    pub duration_secs: i32,
+    /// `running` / `uploading` / `completed` / `failed` / `deleted`（文件已到期或被手动删除）
+    pub status: String,
+    /// 已接收的文件大小
+    #[serde(rename = "sizeBytes")]
+    pub size_bytes: i64,
+    /// 节点写入不及时丢弃的数据段数
+    #[serde(rename = "droppedPackets")]
+    pub dropped_packets: i64,
+    pub error: Option<String>,
+    #[serde(rename = "downloadCount")]
+    pub download_count: i32,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+    #[serde(rename = "completedAt")]
+    pub completed_at: Option<DateTime>,
+    /// 到期后自动删除文件
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,39 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const STATUS_REACHABLE: &str = "reachable";
+pub const STATUS_UNREACHABLE: &str = "unreachable";
+pub const STATUS_SKIPPED: &str = "skipped";
+
+/// 代理公网端口的最近一次外部探测结果
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "proxy_port_probe")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(unique)]
+    #[serde(rename = "proxyId")]
+    pub proxy_id: i64,
+    #[serde(rename = "nodeId")]
+    pub node_id: i64,
+    /// 发起探测的一方：`controller` 或 `node:<id>`
+    pub source: String,
+    /// 探测的地址，如 `203.0.113.5:8080`
+    pub target: String,
+    /// `reachable` / `unreachable` / `skipped`（UDP 代理、未启用等无法探测的情况）
+    pub status: String,
+    #[serde(rename = "latencyMs")]
+    pub latency_ms: Option<f64>,
+    /// 外部不可达时节点本机能否连接该端口，用于区分监听器未运行和防火墙拦截；未检查时为空
+    #[serde(rename = "localReachable")]
+    pub local_reachable: Option<bool>,
+    /// 结果说明和排查建议
+    pub message: String,
+    #[serde(rename = "probedAt")]
+    pub probed_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,25 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const ENTITY_NODE: &str = "node";
+pub const ENTITY_CLIENT: &str = "client";
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "status_history")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// `node` / `client`
+    #[serde(rename = "entityType")]
+    pub entity_type: String,
+    #[serde(rename = "entityId")]
+    pub entity_id: i64,
+    pub online: bool,
+    #[serde(rename = "changedAt")]
+    pub changed_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,33 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// 系统重启的审计记录
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "system_restart")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// 发起重启的管理员
+    #[serde(rename = "userId")]
+    pub user_id: i64,
+    pub username: String,
+    pub reason: Option<String>,
+    /// 重启时待生效的配置项（JSON 数组）
+    #[serde(rename = "pendingChanges")]
+    pub pending_changes: String,
+    /// 重启时在线的节点 / 客户端数
+    #[serde(rename = "connectedNodes")]
+    pub connected_nodes: i32,
+    #[serde(rename = "connectedClients")]
+    pub connected_clients: i32,
+    #[serde(rename = "requestedAt")]
+    pub requested_at: DateTime,
+    /// 重启后服务就绪的时间，为空表示尚未完成（或重启失败）
+    #[serde(rename = "completedAt")]
+    pub completed_at: Option<DateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
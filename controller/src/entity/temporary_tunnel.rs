@@ -0,0 +1,38 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "temporary_tunnel")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[serde(rename = "clientId")]
+    pub client_id: i64,
+    #[serde(rename = "nodeId")]
+    pub node_id: i64,
+    #[serde(rename = "localIP")]
+    pub local_ip: String,
+    #[serde(rename = "localPort")]
+    pub local_port: u16,
+    #[serde(rename = "remotePort")]
+    pub remote_port: u16,
+    pub reason: Option<String>,
+    /// 开启隧道的管理员用户名
+    #[serde(rename = "createdBy")]
+    pub created_by: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime,
+    /// 关闭时间，为空表示仍在运行
+    #[serde(rename = "closedAt")]
+    pub closed_at: Option<DateTime>,
+    /// 手动关闭的管理员用户名，到期自动关闭时为空
+    #[serde(rename = "closedBy")]
+    pub closed_by: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,29 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "tenant")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    /// 租户内最大用户数，为空表示不限制
+    #[serde(rename = "maxUserCount")]
+    pub max_user_count: Option<i32>,
+    /// 租户内最大客户端数，为空表示不限制
+    #[serde(rename = "maxClientCount")]
+    pub max_client_count: Option<i32>,
+    /// 可分配给租户内用户的流量总配额（GB），为空表示不限制
+    #[serde(rename = "trafficQuotaGb")]
+    pub traffic_quota_gb: Option<f64>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,35 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "update_rollout")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[serde(rename = "agentType")]
+    pub agent_type: String, // node, client
+    #[serde(rename = "targetVersion")]
+    pub target_version: String,
+    pub percentage: i32,
+    #[serde(rename = "canaryIds")]
+    pub canary_ids: Option<String>, // JSON 数组
+    pub status: String, // running, paused, completed, cancelled
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::agent_update::Entity")]
+    AgentUpdates,
+}
+
+impl Related<super::agent_update::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AgentUpdates.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -29,6 +29,27 @@ pub struct Model {
     pub max_node_count: Option<i32>,
     #[serde(rename = "maxClientCount")]
     pub max_client_count: Option<i32>,
+    /// 用户在每个节点上的总带宽（字节/秒），为空或 0 不限速
+    #[serde(rename = "speedLimit")]
+    pub speed_limit: Option<i64>,
+    /// 带宽时间表，不在任何窗口内时使用 speedLimit
+    #[serde(rename = "speedLimitSchedule")]
+    pub speed_limit_schedule: Option<String>,
+    #[serde(rename = "tenantId")]
+    pub tenant_id: Option<i64>,
+    #[serde(rename = "isTenantAdmin")]
+    pub is_tenant_admin: bool,
+    /// 显示名称
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    /// 联系邮箱
+    pub email: Option<String>,
+    /// 邮箱验证时间，为空表示未验证
+    #[serde(rename = "emailVerifiedAt")]
+    pub email_verified_at: Option<DateTime>,
+    /// 乐观锁版本号，每次通过 API 修改加一
+    #[serde(rename = "lockVersion")]
+    pub lock_version: i32,
     pub created_at: DateTime,
     pub updated_at: DateTime,
 }
@@ -0,0 +1,31 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// 访客代理：在 `client_id` 所在机器上监听本地端口，连接经节点转发到目标代理
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "visitor")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// 监听本地端口的客户端
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    pub name: String,
+    /// 目标代理（可属于其他客户端）
+    #[serde(rename = "proxyId")]
+    pub proxy_id: i64,
+    #[serde(rename = "bindIP")]
+    pub bind_ip: String,
+    #[serde(rename = "bindPort")]
+    pub bind_port: u16,
+    pub enabled: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,22 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Web 登录会话的活动记录，用于在 Controller 重启后继续计算空闲超时和二次验证有效期
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "web_session")]
+pub struct Model {
+    /// 登录令牌的 SHA-256（十六进制），不保存原文
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub key_hash: String,
+    /// 令牌过期时间（Unix 秒），过期后记录可以清理
+    pub expires_at: i64,
+    pub last_seen_at: DateTime,
+    /// 已因空闲超时失效，在令牌过期前一直拒绝
+    pub idle_expired: bool,
+    pub elevated_until: Option<DateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,234 @@
+//! 代理 / 客户端到期与闲置清理
+//!
+//! - 代理到期后自动禁用并停止监听器（释放端口），禁用超过保留期后删除；
+//! - 客户端到期后禁用其所有代理、断开连接并拒绝再次认证；
+//! - 启用的代理连续 N 天没有流量时标记为闲置（`stale_at`），恢复流量后自动清除标记。
+
+use chrono::{Duration as ChronoDuration, NaiveDateTime, Utc};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use common::protocol::control::ProxyControl;
+
+use crate::client_stream_manager::ClientStreamManager;
+use crate::entity::{client, proxy, traffic_daily, Client, Proxy, TrafficDaily, User};
+use crate::migration::get_connection;
+
+/// 到期检查间隔
+const EXPIRATION_INTERVAL_SECS: u64 = 60;
+/// 闲置检查间隔
+const STALE_INTERVAL_SECS: u64 = 3600;
+
+/// 已到期代理保留多少天后删除（`OXIPROXY_EXPIRED_PROXY_RETENTION_DAYS`，默认 7，0 表示不删除）
+fn expired_retention_days() -> i64 {
+    common::env::var("OXIPROXY_EXPIRED_PROXY_RETENTION_DAYS")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(7)
+}
+
+/// 代理连续多少天无流量视为闲置（`OXIPROXY_STALE_PROXY_DAYS`，默认 30，0 表示不检查）
+fn stale_days() -> i64 {
+    common::env::var("OXIPROXY_STALE_PROXY_DAYS")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30)
+}
+
+/// 是否已到期
+pub fn is_expired(expires_at: Option<NaiveDateTime>) -> bool {
+    expires_at.is_some_and(|t| t <= Utc::now().naive_utc())
+}
+
+/// 客户端所有者的用户名，用于日志
+pub async fn owner_name(client_id: &str, db: &DatabaseConnection) -> String {
+    let Ok(client_id) = client_id.parse::<i64>() else {
+        return "-".to_string();
+    };
+    let Ok(Some(client)) = Client::find_by_id(client_id).one(db).await else {
+        return "-".to_string();
+    };
+    match client.user_id {
+        Some(user_id) => match User::find_by_id(user_id).one(db).await {
+            Ok(Some(user)) => user.username,
+            _ => "-".to_string(),
+        },
+        None => "-".to_string(),
+    }
+}
+
+/// 禁用代理并停止监听器
+pub async fn disable_proxy(
+    proxy_control: &dyn ProxyControl,
+    p: &proxy::Model,
+    db: &DatabaseConnection,
+) -> anyhow::Result<()> {
+    Proxy::update_many()
+        .col_expr(proxy::Column::Enabled, Expr::value(false))
+        .col_expr(proxy::Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+        .filter(proxy::Column::Id.eq(p.id))
+        .exec(db)
+        .await?;
+    if let Err(e) = proxy_control.stop_proxy(&p.client_id, p.id).await {
+        warn!("停止代理 {} (ID: {}) 监听器失败: {}", p.name, p.id, e);
+    }
+    Ok(())
+}
+
+async fn check_expired(
+    proxy_control: &dyn ProxyControl,
+    client_stream_manager: &ClientStreamManager,
+    db: &DatabaseConnection,
+) -> anyhow::Result<()> {
+    let now = Utc::now().naive_utc();
+    let mut notify: HashSet<String> = HashSet::new();
+
+    // 1. 到期的客户端：禁用其所有代理并断开连接
+    let expired_clients = Client::find()
+        .filter(client::Column::ExpiresAt.lte(now))
+        .all(db)
+        .await?;
+    let expired_client_ids: Vec<String> = expired_clients.iter().map(|c| c.id.to_string()).collect();
+    let proxies = Proxy::find()
+        .filter(proxy::Column::ClientId.is_in(expired_client_ids))
+        .filter(proxy::Column::Enabled.eq(true))
+        .all(db)
+        .await?;
+    for p in &proxies {
+        disable_proxy(proxy_control, p, db).await?;
+    }
+    for c in expired_clients {
+        if client_stream_manager.is_connected(c.id).await {
+            let owner = owner_name(&c.id.to_string(), db).await;
+            info!("客户端 {} (#{}) 已到期，断开连接（所有者: {}）", c.name, c.id, owner);
+            client_stream_manager
+                .disconnect(c.id, "client_expired", format!("客户端 '{}' 已到期", c.name))
+                .await;
+        }
+    }
+
+    // 2. 到期的代理：禁用并释放端口
+    let expired_proxies = Proxy::find()
+        .filter(proxy::Column::ExpiresAt.lte(now))
+        .filter(proxy::Column::Enabled.eq(true))
+        .all(db)
+        .await?;
+    for p in expired_proxies {
+        disable_proxy(proxy_control, &p, db).await?;
+        let owner = owner_name(&p.client_id, db).await;
+        info!(
+            "代理 {} (ID: {}) 已到期，已禁用并释放端口 {}（客户端 #{}，所有者: {}）",
+            p.name, p.id, p.remote_port, p.client_id, owner
+        );
+        notify.insert(p.client_id);
+    }
+
+    // 3. 到期并禁用超过保留期的代理：删除
+    let retention_days = expired_retention_days();
+    if retention_days > 0 {
+        let cutoff = now - ChronoDuration::days(retention_days);
+        let result = Proxy::delete_many()
+            .filter(proxy::Column::ExpiresAt.lte(cutoff))
+            .filter(proxy::Column::Enabled.eq(false))
+            .exec(db)
+            .await?;
+        if result.rows_affected > 0 {
+            info!("已删除 {} 个到期超过 {} 天的代理", result.rows_affected, retention_days);
+        }
+    }
+
+    for client_id in notify {
+        client_stream_manager.notify_proxy_change(&client_id).await;
+    }
+    Ok(())
+}
+
+async fn check_stale(db: &DatabaseConnection) -> anyhow::Result<()> {
+    let days = stale_days();
+    if days <= 0 {
+        return Ok(());
+    }
+    let now = Utc::now().naive_utc();
+    let cutoff = now - ChronoDuration::days(days);
+    let cutoff_date = cutoff.format("%Y-%m-%d").to_string();
+
+    // 统计期内有流量的代理
+    let active: HashSet<i64> = TrafficDaily::find()
+        .select_only()
+        .column(traffic_daily::Column::ProxyId)
+        .filter(traffic_daily::Column::Date.gte(cutoff_date))
+        .filter(
+            Condition::any()
+                .add(traffic_daily::Column::BytesSent.gt(0))
+                .add(traffic_daily::Column::BytesReceived.gt(0)),
+        )
+        .distinct()
+        .into_tuple::<i64>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+
+    let proxies = Proxy::find()
+        .filter(proxy::Column::Enabled.eq(true))
+        .filter(proxy::Column::CreatedAt.lte(cutoff))
+        .all(db)
+        .await?;
+    for p in proxies {
+        let is_stale = !active.contains(&p.id);
+        if is_stale == p.stale_at.is_some() {
+            continue;
+        }
+        Proxy::update_many()
+            .col_expr(proxy::Column::StaleAt, Expr::value(is_stale.then_some(now)))
+            .filter(proxy::Column::Id.eq(p.id))
+            .exec(db)
+            .await?;
+        if is_stale {
+            let owner = owner_name(&p.client_id, db).await;
+            warn!(
+                "代理 {} (ID: {}) 已连续 {} 天没有流量，标记为闲置（客户端 #{}，所有者: {}）",
+                p.name, p.id, days, p.client_id, owner
+            );
+        }
+    }
+
+    // 禁用的代理不再视为闲置
+    Proxy::update_many()
+        .col_expr(proxy::Column::StaleAt, Expr::value(Option::<NaiveDateTime>::None))
+        .filter(proxy::Column::Enabled.eq(false))
+        .filter(proxy::Column::StaleAt.is_not_null())
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// 启动到期与闲置检查后台任务
+pub fn start_expiration_monitor(
+    proxy_control: Arc<dyn ProxyControl>,
+    client_stream_manager: Arc<ClientStreamManager>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(EXPIRATION_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let db = get_connection().await;
+            if let Err(e) = check_expired(proxy_control.as_ref(), &client_stream_manager, db).await {
+                error!("到期检查失败: {}", e);
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(STALE_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let db = get_connection().await;
+            if let Err(e) = check_stale(db).await {
+                error!("闲置代理检查失败: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,380 @@
+//! 备用节点自动故障转移
+//!
+//! 管理员把一个主节点和若干备用节点（按优先级排列）定义为故障转移组。组内代理当前所在的节点
+//! 持续离线超过阈值后，把代理迁移到第一个在线的候选节点（主节点优先，其次按顺序的备用节点），
+//! 通知客户端重新拉取代理列表，并通过告警 Webhook 通知新的公网地址。开启切回时，主节点恢复
+//! 在线并稳定超过同样的阈值后，把迁走的代理迁回主节点。
+//!
+//! 处于维护窗口中的节点离线不会触发转移。节点在线状态以 gRPC 连接为准，记录在内存中，
+//! Controller 重启后重新计时。
+
+use anyhow::Result;
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::client_stream_manager::ClientStreamManager;
+use crate::config_manager::ConfigManager;
+use crate::entity::failover_group::{self, format_ids};
+use crate::entity::{proxy, FailoverGroup, Node, Proxy};
+use crate::migration::get_connection;
+use crate::node_manager::NodeManager;
+
+/// 检查间隔
+const CHECK_INTERVAL_SECS: u64 = 15;
+
+/// 节点在线状态及其持续时间
+#[derive(Default)]
+struct Tracker {
+    /// 节点 ID -> (是否在线, 进入该状态的时间)
+    states: HashMap<i64, (bool, Instant)>,
+}
+
+impl Tracker {
+    fn update(&mut self, nodes: impl IntoIterator<Item = i64>, online: &HashSet<i64>, now: Instant) {
+        for node_id in nodes {
+            let is_online = online.contains(&node_id);
+            match self.states.get_mut(&node_id) {
+                Some(state) if state.0 == is_online => {}
+                Some(state) => *state = (is_online, now),
+                None => {
+                    self.states.insert(node_id, (is_online, now));
+                }
+            }
+        }
+    }
+
+    /// 节点是否已处于 `online` 状态至少 `threshold`
+    fn held(&self, node_id: i64, online: bool, threshold: Duration, now: Instant) -> bool {
+        matches!(self.states.get(&node_id), Some((state, since)) if *state == online && now.duration_since(*since) >= threshold)
+    }
+}
+
+/// 对故障转移组要执行的操作
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    /// 当前节点离线，转移到 `to`
+    FailOver { to: i64 },
+    /// 主节点已恢复，迁回主节点
+    FailBack,
+}
+
+fn decide(
+    group: &failover_group::Model,
+    tracker: &Tracker,
+    online: &HashSet<i64>,
+    in_maintenance: impl Fn(i64) -> bool,
+    now: Instant,
+) -> Option<Action> {
+    let threshold = Duration::from_secs(group.offline_threshold_secs.max(0) as u64);
+    let active = group.active_node_id;
+    let primary = group.primary_node_id;
+
+    if active != primary && group.failback && tracker.held(primary, true, threshold, now) {
+        return Some(Action::FailBack);
+    }
+    if online.contains(&active) || in_maintenance(active) || !tracker.held(active, false, threshold, now) {
+        return None;
+    }
+    std::iter::once(primary)
+        .chain(group.standby_ids())
+        .find(|n| *n != active && online.contains(n))
+        .map(|to| Action::FailOver { to })
+}
+
+/// 迁移事件中的代理
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MovedProxy {
+    id: i64,
+    name: String,
+    client_id: String,
+    owner: String,
+    remote_port: u16,
+}
+
+/// 发送到告警 Webhook 的迁移事件
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FailoverEvent {
+    /// failover / failback
+    #[serde(rename = "type")]
+    kind: &'static str,
+    group_id: i64,
+    group_name: String,
+    from_node_id: i64,
+    to_node_id: i64,
+    to_node_name: String,
+    /// 新节点的公网地址，用户需改用此地址访问
+    public_addr: String,
+    proxies: Vec<MovedProxy>,
+    skipped: Vec<String>,
+    at: chrono::DateTime<Utc>,
+}
+
+/// 检查代理能否迁移到目标节点，不能时返回原因
+async fn check_target(
+    p: &proxy::Model,
+    target_id: i64,
+    config_manager: &ConfigManager,
+    db: &DatabaseConnection,
+) -> Result<Option<String>> {
+    if !p.enabled {
+        return Ok(None);
+    }
+    let existing = Proxy::find()
+        .filter(proxy::Column::NodeId.eq(target_id))
+        .filter(proxy::Column::RemotePort.eq(p.remote_port))
+        .filter(proxy::Column::Enabled.eq(true))
+        .filter(proxy::Column::Id.ne(p.id))
+        .one(db)
+        .await?;
+    if let Some(existing) = existing {
+        return Ok(Some(format!("远程端口 {} 已被代理「{}」占用", p.remote_port, existing.name)));
+    }
+    let (allowed, reason) = crate::node_limiter::validate_node_proxy_limit(target_id, p.remote_port, config_manager, db).await?;
+    if !allowed {
+        return Ok(Some(reason));
+    }
+    let (allowed, reason) =
+        crate::port_reservation::validate_proxy_port(Some(target_id), &p.client_id, p.remote_port, db).await?;
+    if !allowed {
+        return Ok(Some(reason));
+    }
+    Ok(None)
+}
+
+/// 把组内代理从 `from` 迁移到 `to`，并更新组的状态
+#[allow(clippy::too_many_arguments)]
+async fn rehome(
+    group: failover_group::Model,
+    from: i64,
+    to: i64,
+    online: &HashSet<i64>,
+    node_manager: &NodeManager,
+    client_stream_manager: &ClientStreamManager,
+    config_manager: &ConfigManager,
+    db: &DatabaseConnection,
+) -> Result<()> {
+    let primary = group.primary_node_id;
+    let prev_moved = group.moved_ids();
+    // 从主节点迁出时迁移主节点上的全部代理，否则只迁移此前迁走的代理
+    let mut select = Proxy::find().filter(proxy::Column::NodeId.eq(from));
+    if from != primary {
+        select = select.filter(proxy::Column::Id.is_in(prev_moved.clone()));
+    }
+    let proxies = select.order_by_asc(proxy::Column::Id).all(db).await?;
+
+    let mut moved = Vec::new();
+    let mut skipped = Vec::new();
+    let mut notify: HashSet<String> = HashSet::new();
+    let mut dns_changed = false;
+    for p in proxies {
+        if let Some(reason) = check_target(&p, to, config_manager, db).await? {
+            warn!("故障转移组 {} 的代理 {} (ID: {}) 无法迁移到节点 #{}: {}", group.name, p.name, p.id, to, reason);
+            skipped.push(format!("{}: {}", p.name, reason));
+            continue;
+        }
+        if p.enabled && online.contains(&from) {
+            if let Err(e) = node_manager.stop_proxy_on_node(from, &p.client_id, p.id).await {
+                warn!("停止节点 #{} 上的代理 {} 失败: {}", from, p.name, e);
+            }
+        }
+
+        let mut active: proxy::ActiveModel = p.clone().into();
+        active.node_id = Set(Some(to));
+        active.updated_at = Set(Utc::now().naive_utc());
+        active.update(db).await?;
+
+        if p.enabled {
+            if let Err(e) = node_manager.start_proxy_on_node(to, &p.client_id, p.id).await {
+                warn!("在节点 #{} 上启动代理 {} 失败: {}", to, p.name, e);
+            }
+        }
+        dns_changed |= p.dns_name.is_some();
+        notify.insert(p.client_id.clone());
+        moved.push(p);
+    }
+
+    let moved_ids: Vec<i64> = moved.iter().map(|p| p.id).collect();
+    let remaining: Vec<i64> = if from == primary {
+        prev_moved.into_iter().chain(moved_ids.iter().copied()).collect()
+    } else if to == primary {
+        prev_moved.into_iter().filter(|id| !moved_ids.contains(id)).collect()
+    } else {
+        prev_moved
+    };
+
+    let group_id = group.id;
+    let group_name = group.name.clone();
+    let now = Utc::now();
+    let mut active: failover_group::ActiveModel = group.into();
+    active.active_node_id = Set(to);
+    active.moved_proxy_ids = Set((!remaining.is_empty()).then(|| format_ids(&remaining)));
+    active.failed_over_at = Set((to != primary).then(|| now.naive_utc()));
+    active.updated_at = Set(now.naive_utc());
+    active.update(db).await?;
+
+    let kind = if to == primary { "failback" } else { "failover" };
+    let target = Node::find_by_id(to).one(db).await?;
+    let (to_node_name, public_addr) = target
+        .map(|n| (n.name, n.public_ip.unwrap_or(n.tunnel_addr)))
+        .unwrap_or_default();
+    warn!(
+        "故障转移组 {} ({}): 节点 #{} -> {} (#{}，{})，迁移代理 {} 个，跳过 {} 个",
+        group_name,
+        kind,
+        from,
+        to_node_name,
+        to,
+        public_addr,
+        moved.len(),
+        skipped.len()
+    );
+
+    if dns_changed {
+        crate::dns::request_sync();
+    }
+    crate::cloud_firewall::request_sync();
+    for client_id in &notify {
+        client_stream_manager.notify_proxy_change(client_id).await;
+    }
+
+    let mut proxies = Vec::with_capacity(moved.len());
+    for p in moved {
+        proxies.push(MovedProxy {
+            owner: crate::expiration::owner_name(&p.client_id, db).await,
+            id: p.id,
+            name: p.name,
+            client_id: p.client_id,
+            remote_port: p.remote_port,
+        });
+    }
+    crate::alerting::post_webhooks(FailoverEvent {
+        kind,
+        group_id,
+        group_name,
+        from_node_id: from,
+        to_node_id: to,
+        to_node_name,
+        public_addr,
+        proxies,
+        skipped,
+        at: now,
+    });
+    Ok(())
+}
+
+async fn check(
+    tracker: &mut Tracker,
+    node_manager: &NodeManager,
+    client_stream_manager: &ClientStreamManager,
+    config_manager: &ConfigManager,
+) -> Result<()> {
+    let db = get_connection().await;
+    let groups = FailoverGroup::find()
+        .filter(failover_group::Column::Enabled.eq(true))
+        .all(db)
+        .await?;
+    if groups.is_empty() {
+        return Ok(());
+    }
+
+    let online: HashSet<i64> = node_manager.get_loaded_node_ids().await.into_iter().collect();
+    let now = Instant::now();
+    let nodes: HashSet<i64> = groups
+        .iter()
+        .flat_map(|g| std::iter::once(g.primary_node_id).chain(g.standby_ids()))
+        .collect();
+    tracker.update(nodes, &online, now);
+
+    let windows = crate::maintenance::global();
+    for group in groups {
+        let from = group.active_node_id;
+        let action = decide(&group, tracker, &online, |n| windows.node(n), now);
+        let to = match action {
+            Some(Action::FailOver { to }) => to,
+            Some(Action::FailBack) => group.primary_node_id,
+            None => continue,
+        };
+        let name = group.name.clone();
+        if let Err(e) = rehome(group, from, to, &online, node_manager, client_stream_manager, config_manager, db).await {
+            error!("故障转移组 {} 迁移代理失败: {}", name, e);
+        }
+    }
+    Ok(())
+}
+
+/// 启动故障转移监控任务
+pub fn start_failover_monitor(
+    node_manager: Arc<NodeManager>,
+    client_stream_manager: Arc<ClientStreamManager>,
+    config_manager: Arc<ConfigManager>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(CHECK_INTERVAL_SECS));
+        let mut tracker = Tracker::default();
+        info!("故障转移监控已启动");
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = check(&mut tracker, &node_manager, &client_stream_manager, &config_manager).await {
+                error!("检查故障转移组失败: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(active: i64, failback: bool) -> failover_group::Model {
+        let now = Utc::now().naive_utc();
+        failover_group::Model {
+            id: 1,
+            name: "g".to_string(),
+            primary_node_id: 1,
+            standby_node_ids: "2,3".to_string(),
+            offline_threshold_secs: 60,
+            failback,
+            enabled: true,
+            active_node_id: active,
+            moved_proxy_ids: None,
+            failed_over_at: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn test_decide() {
+        let start = Instant::now();
+        let later = start + Duration::from_secs(61);
+        let online: HashSet<i64> = [3].into();
+        let mut tracker = Tracker::default();
+        tracker.update([1, 2, 3], &online, start);
+
+        // 离线未超过阈值
+        assert_eq!(decide(&group(1, true), &tracker, &online, |_| false, start), None);
+        // 跳过离线的备用节点 2
+        assert_eq!(
+            decide(&group(1, true), &tracker, &online, |_| false, later),
+            Some(Action::FailOver { to: 3 })
+        );
+        // 维护中的节点不转移
+        assert_eq!(decide(&group(1, true), &tracker, &online, |n| n == 1, later), None);
+
+        // 主节点恢复后需稳定超过阈值才切回
+        let online: HashSet<i64> = [1, 3].into();
+        tracker.update([1, 2, 3], &online, later);
+        assert_eq!(decide(&group(3, true), &tracker, &online, |_| false, later), None);
+        let recovered = later + Duration::from_secs(60);
+        assert_eq!(decide(&group(3, true), &tracker, &online, |_| false, recovered), Some(Action::FailBack));
+        assert_eq!(decide(&group(3, false), &tracker, &online, |_| false, recovered), None);
+    }
+}
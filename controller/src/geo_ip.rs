@@ -2,6 +2,8 @@
 //!
 //! 使用 ip.sb 免费 API 查询 IP 地址的地理位置信息
 
+use std::net::IpAddr;
+
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use tracing::{error, info};
@@ -25,11 +27,14 @@ struct IpSbResponse {
 /// 查询 IP 地址的地理位置信息
 /// 使用 ip.sb 免费服务（国内 IP 准确度高）
 pub async fn query_geo_ip(ip: &str) -> Result<GeoIpInfo> {
+    // IP 由节点上报，拼进地址前确认是公网 IP
+    let ip: IpAddr = ip.trim().parse().map_err(|_| anyhow!("无效的 IP 地址: {}", ip))?;
+    if crate::outbound::is_blocked_ip(ip, false) {
+        return Err(anyhow!("{} 不是公网 IP，跳过地理位置查询", ip));
+    }
     let url = format!("https://api.ip.sb/geoip/{}", ip);
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(5))
-        .build()?;
+    let client = crate::outbound::client(std::time::Duration::from_secs(5))?;
 
     let response = client
         .get(&url)
@@ -125,9 +130,7 @@ pub async fn query_node_geo_info(ip: Option<String>) -> Result<GeoIpInfo> {
 
 /// 获取本机公网 IP 地址
 async fn get_public_ip() -> Result<String> {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(5))
-        .build()?;
+    let client = crate::outbound::client(std::time::Duration::from_secs(5))?;
 
     // 使用多个服务作为备选
     let services = vec![
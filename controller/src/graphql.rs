@@ -0,0 +1,369 @@
+//! GraphQL 查询接口（可选功能 `graphql`）
+//!
+//! 在 REST API 之外提供 `POST /api/graphql`，前端可以一次请求取回嵌套数据
+//! （用户 → 客户端 → 代理 → 今日流量），无需逐级调用多个 REST 接口。
+//!
+//! 嵌套字段通过 DataLoader 批量加载：同一层级的所有父对象只产生一条 `IN (...)` 查询，
+//! 避免逐行查询的 N+1 问题。数据范围与 REST 接口一致（`UserScope`）。
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Error, Object, Result, Schema, SimpleObject};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::extract::Extension;
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder};
+
+use crate::entity::{client, proxy, traffic_daily, user, Client, Proxy, TrafficDaily, User};
+use crate::middleware::AuthUser;
+use crate::migration::get_connection;
+use crate::tenant::UserScope;
+
+/// 查询深度上限
+const MAX_DEPTH: usize = 8;
+/// 查询复杂度上限
+const MAX_COMPLEXITY: usize = 2000;
+
+pub type OxiproxySchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+fn schema() -> &'static OxiproxySchema {
+    static SCHEMA: OnceLock<OxiproxySchema> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+            .limit_depth(MAX_DEPTH)
+            .limit_complexity(MAX_COMPLEXITY)
+            .finish()
+    })
+}
+
+/// POST /api/graphql
+pub async fn graphql_handler(
+    Extension(auth_user): Extension<Option<AuthUser>>,
+    req: GraphQLRequest,
+) -> GraphQLResponse {
+    let Some(auth_user) = auth_user else {
+        return async_graphql::Response::from_errors(vec![async_graphql::ServerError::new("未认证", None)]).into();
+    };
+
+    let db = get_connection().await;
+    // DataLoader 按请求创建，缓存只在本次查询内有效
+    let req = req
+        .into_inner()
+        .data(UserScope::of(&auth_user))
+        .data(auth_user)
+        .data(DataLoader::new(ClientsByUser(db), tokio::spawn))
+        .data(DataLoader::new(ProxiesByClient(db), tokio::spawn))
+        .data(DataLoader::new(TodayTrafficByProxy(db), tokio::spawn));
+    schema().execute(req).await.into()
+}
+
+fn db_err(e: DbErr) -> Error {
+    Error::new(format!("数据库查询失败: {}", e))
+}
+
+/// 当前请求可访问的用户 ID，`None` 表示不限制
+async fn scope_user_ids(ctx: &Context<'_>) -> Result<Option<Vec<i64>>> {
+    let scope = *ctx.data::<UserScope>()?;
+    scope.user_ids(get_connection().await).await.map_err(|e| Error::new(e.to_string()))
+}
+
+fn in_scope(user_ids: &Option<Vec<i64>>, user_id: Option<i64>) -> bool {
+    match user_ids {
+        None => true,
+        Some(ids) => user_id.is_some_and(|id| ids.contains(&id)),
+    }
+}
+
+// ─── DataLoader ───────────────────────────────────────────
+
+/// 按用户批量加载客户端
+pub struct ClientsByUser(&'static DatabaseConnection);
+
+impl Loader<i64> for ClientsByUser {
+    type Value = Vec<client::Model>;
+    type Error = Arc<DbErr>;
+
+    async fn load(&self, keys: &[i64]) -> std::result::Result<HashMap<i64, Self::Value>, Self::Error> {
+        let clients = Client::find()
+            .filter(client::Column::UserId.is_in(keys.iter().copied()))
+            .order_by_asc(client::Column::Id)
+            .all(self.0)
+            .await?;
+        let mut map: HashMap<i64, Self::Value> = HashMap::new();
+        for c in clients {
+            if let Some(user_id) = c.user_id {
+                map.entry(user_id).or_default().push(c);
+            }
+        }
+        Ok(map)
+    }
+}
+
+/// 按客户端批量加载代理
+pub struct ProxiesByClient(&'static DatabaseConnection);
+
+impl Loader<i64> for ProxiesByClient {
+    type Value = Vec<proxy::Model>;
+    type Error = Arc<DbErr>;
+
+    async fn load(&self, keys: &[i64]) -> std::result::Result<HashMap<i64, Self::Value>, Self::Error> {
+        let proxies = Proxy::find()
+            .filter(proxy::Column::ClientId.is_in(keys.iter().map(|id| id.to_string())))
+            .order_by_asc(proxy::Column::Id)
+            .all(self.0)
+            .await?;
+        let mut map: HashMap<i64, Self::Value> = HashMap::new();
+        for p in proxies {
+            if let Ok(client_id) = p.client_id.parse::<i64>() {
+                map.entry(client_id).or_default().push(p);
+            }
+        }
+        Ok(map)
+    }
+}
+
+/// 按代理批量加载今日流量
+pub struct TodayTrafficByProxy(&'static DatabaseConnection);
+
+impl Loader<i64> for TodayTrafficByProxy {
+    type Value = Traffic;
+    type Error = Arc<DbErr>;
+
+    async fn load(&self, keys: &[i64]) -> std::result::Result<HashMap<i64, Self::Value>, Self::Error> {
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+        let rows = TrafficDaily::find()
+            .filter(traffic_daily::Column::ProxyId.is_in(keys.iter().copied()))
+            .filter(traffic_daily::Column::Date.eq(today))
+            .all(self.0)
+            .await?;
+        let mut map: HashMap<i64, Self::Value> = HashMap::new();
+        for r in rows {
+            let t = map.entry(r.proxy_id).or_default();
+            t.bytes_sent += r.bytes_sent;
+            t.bytes_received += r.bytes_received;
+        }
+        Ok(map)
+    }
+}
+
+// ─── 类型 ─────────────────────────────────────────────────
+
+/// 流量统计（字节）
+#[derive(SimpleObject, Clone, Copy, Default)]
+pub struct Traffic {
+    pub bytes_sent: i64,
+    pub bytes_received: i64,
+}
+
+pub struct UserObject(user::Model);
+
+#[Object(name = "User")]
+impl UserObject {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+    async fn username(&self) -> &str {
+        &self.0.username
+    }
+    async fn is_admin(&self) -> bool {
+        self.0.is_admin
+    }
+    async fn tenant_id(&self) -> Option<i64> {
+        self.0.tenant_id
+    }
+    async fn is_traffic_exceeded(&self) -> bool {
+        self.0.is_traffic_exceeded
+    }
+    async fn traffic_quota_gb(&self) -> Option<f64> {
+        self.0.traffic_quota_gb
+    }
+    async fn total_traffic(&self) -> Traffic {
+        Traffic { bytes_sent: self.0.total_bytes_sent, bytes_received: self.0.total_bytes_received }
+    }
+    async fn created_at(&self) -> NaiveDateTime {
+        self.0.created_at
+    }
+    async fn clients(&self, ctx: &Context<'_>) -> Result<Vec<ClientObject>> {
+        let loader = ctx.data::<DataLoader<ClientsByUser>>()?;
+        let clients = loader.load_one(self.0.id).await?.unwrap_or_default();
+        Ok(clients.into_iter().map(ClientObject).collect())
+    }
+}
+
+pub struct ClientObject(client::Model);
+
+#[Object(name = "Client")]
+impl ClientObject {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+    async fn is_online(&self) -> bool {
+        self.0.is_online
+    }
+    async fn public_ip(&self) -> Option<&str> {
+        self.0.public_ip.as_deref()
+    }
+    async fn region(&self) -> Option<&str> {
+        self.0.region.as_deref()
+    }
+    async fn nat_type(&self) -> Option<&str> {
+        self.0.nat_type.as_deref()
+    }
+    async fn version(&self) -> Option<&str> {
+        self.0.version.as_deref()
+    }
+    async fn user_id(&self) -> Option<i64> {
+        self.0.user_id
+    }
+    async fn is_traffic_exceeded(&self) -> bool {
+        self.0.is_traffic_exceeded
+    }
+    async fn expires_at(&self) -> Option<NaiveDateTime> {
+        self.0.expires_at
+    }
+    async fn total_traffic(&self) -> Traffic {
+        Traffic { bytes_sent: self.0.total_bytes_sent, bytes_received: self.0.total_bytes_received }
+    }
+    async fn proxies(&self, ctx: &Context<'_>) -> Result<Vec<ProxyObject>> {
+        let loader = ctx.data::<DataLoader<ProxiesByClient>>()?;
+        let proxies = loader.load_one(self.0.id).await?.unwrap_or_default();
+        Ok(proxies.into_iter().map(ProxyObject).collect())
+    }
+    /// 客户端所有代理的今日流量之和
+    async fn today_traffic(&self, ctx: &Context<'_>) -> Result<Traffic> {
+        let proxies = ctx.data::<DataLoader<ProxiesByClient>>()?.load_one(self.0.id).await?.unwrap_or_default();
+        let traffic = ctx
+            .data::<DataLoader<TodayTrafficByProxy>>()?
+            .load_many(proxies.iter().map(|p| p.id))
+            .await?;
+        Ok(traffic.values().fold(Traffic::default(), |acc, t| Traffic {
+            bytes_sent: acc.bytes_sent + t.bytes_sent,
+            bytes_received: acc.bytes_received + t.bytes_received,
+        }))
+    }
+}
+
+pub struct ProxyObject(proxy::Model);
+
+#[Object(name = "Proxy")]
+impl ProxyObject {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+    async fn client_id(&self) -> &str {
+        &self.0.client_id
+    }
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+    #[graphql(name = "type")]
+    async fn proxy_type(&self) -> &str {
+        &self.0.proxy_type
+    }
+    #[graphql(name = "localIP")]
+    async fn local_ip(&self) -> &str {
+        &self.0.local_ip
+    }
+    async fn local_port(&self) -> u16 {
+        self.0.local_port
+    }
+    async fn remote_port(&self) -> u16 {
+        self.0.remote_port
+    }
+    async fn enabled(&self) -> bool {
+        self.0.enabled
+    }
+    async fn node_id(&self) -> Option<i64> {
+        self.0.node_id
+    }
+    async fn group_id(&self) -> Option<&str> {
+        self.0.group_id.as_deref()
+    }
+    async fn schedule(&self) -> Option<&str> {
+        self.0.schedule.as_deref()
+    }
+    async fn expires_at(&self) -> Option<NaiveDateTime> {
+        self.0.expires_at
+    }
+    async fn stale_at(&self) -> Option<NaiveDateTime> {
+        self.0.stale_at
+    }
+    async fn total_traffic(&self) -> Traffic {
+        Traffic { bytes_sent: self.0.total_bytes_sent, bytes_received: self.0.total_bytes_received }
+    }
+    async fn today_traffic(&self, ctx: &Context<'_>) -> Result<Traffic> {
+        let loader = ctx.data::<DataLoader<TodayTrafficByProxy>>()?;
+        Ok(loader.load_one(self.0.id).await?.unwrap_or_default())
+    }
+}
+
+// ─── 查询入口 ─────────────────────────────────────────────
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// 当前登录用户
+    async fn me(&self, ctx: &Context<'_>) -> Result<Option<UserObject>> {
+        let auth_user = ctx.data::<AuthUser>()?;
+        let db = get_connection().await;
+        Ok(User::find_by_id(auth_user.id).one(db).await.map_err(db_err)?.map(UserObject))
+    }
+
+    /// 可访问范围内的用户
+    async fn users(&self, ctx: &Context<'_>) -> Result<Vec<UserObject>> {
+        let db = get_connection().await;
+        let mut select = User::find().order_by_asc(user::Column::Id);
+        if let Some(ids) = scope_user_ids(ctx).await? {
+            select = select.filter(user::Column::Id.is_in(ids));
+        }
+        Ok(select.all(db).await.map_err(db_err)?.into_iter().map(UserObject).collect())
+    }
+
+    /// 可访问范围内的客户端，可按用户过滤
+    async fn clients(&self, ctx: &Context<'_>, user_id: Option<i64>) -> Result<Vec<ClientObject>> {
+        let db = get_connection().await;
+        let mut select = Client::find().order_by_asc(client::Column::Id);
+        if let Some(ids) = scope_user_ids(ctx).await? {
+            select = select.filter(client::Column::UserId.is_in(ids));
+        }
+        if let Some(user_id) = user_id {
+            select = select.filter(client::Column::UserId.eq(user_id));
+        }
+        Ok(select.all(db).await.map_err(db_err)?.into_iter().map(ClientObject).collect())
+    }
+
+    /// 单个客户端
+    async fn client(&self, ctx: &Context<'_>, id: i64) -> Result<Option<ClientObject>> {
+        let db = get_connection().await;
+        let client = Client::find_by_id(id).one(db).await.map_err(db_err)?;
+        let user_ids = scope_user_ids(ctx).await?;
+        Ok(client.filter(|c| in_scope(&user_ids, c.user_id)).map(ClientObject))
+    }
+
+    /// 可访问范围内的代理，可按客户端过滤
+    async fn proxies(&self, ctx: &Context<'_>, client_id: Option<i64>) -> Result<Vec<ProxyObject>> {
+        let db = get_connection().await;
+        let mut select = Proxy::find().order_by_asc(proxy::Column::Id);
+        if let Some(ids) = scope_user_ids(ctx).await? {
+            let client_ids: Vec<String> = Client::find()
+                .filter(client::Column::UserId.is_in(ids))
+                .all(db)
+                .await
+                .map_err(db_err)?
+                .into_iter()
+                .map(|c| c.id.to_string())
+                .collect();
+            select = select.filter(proxy::Column::ClientId.is_in(client_ids));
+        }
+        if let Some(client_id) = client_id {
+            select = select.filter(proxy::Column::ClientId.eq(client_id.to_string()));
+        }
+        Ok(select.all(db).await.map_err(db_err)?.into_iter().map(ProxyObject).collect())
+    }
+}
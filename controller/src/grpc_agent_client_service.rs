@@ -9,14 +9,14 @@ use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
 use tonic::{Request, Response, Status, Streaming};
 use tracing::{debug, error, info, warn};
 use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
 
 use common::grpc::oxiproxy;
 use common::grpc::oxiproxy::agent_client_message::Payload as ClientPayload;
 use common::grpc::oxiproxy::controller_to_client_message::Payload as ControllerPayload;
 use common::grpc::AgentClientService;
 
-use crate::client_stream_manager::ClientStreamManager;
+use crate::client_stream_manager::{Admission, ClientStreamManager};
 use crate::entity::{Client, client};
 use crate::migration::get_connection;
 
@@ -41,6 +41,13 @@ impl AgentClientService for AgentClientServiceImpl {
         let client_stream_manager = self.client_stream_manager.clone();
 
         tokio::spawn(async move {
+            // 重连高峰时排队接入，排队过久直接拒绝，由客户端稍后重试
+            if !crate::accept_limiter::acquire().await {
+                warn!("Agent 连接过多，拒绝客户端连接");
+                let _ = tx.send(Err(Status::resource_exhausted("Controller 繁忙，请稍后重连"))).await;
+                return;
+            }
+
             // 1. 读取首条消息，必须是认证请求
             let first_msg = match in_stream.next().await {
                 Some(Ok(msg)) => msg,
@@ -62,6 +69,12 @@ impl AgentClientService for AgentClientServiceImpl {
                 }
             };
             let client_version = if auth_req.version.is_empty() { None } else { Some(auth_req.version.clone()) };
+            // 客户端实例 ID，格式不对时当作未上报
+            let instance_id = auth_req
+                .instance_id
+                .as_deref()
+                .and_then(|id| uuid::Uuid::parse_str(id).ok())
+                .map(|id| id.to_string());
 
             // 2. 验证 token
             let db = get_connection().await;
@@ -78,6 +91,7 @@ impl AgentClientService for AgentClientServiceImpl {
                             error_message: Some("无效的 token".to_string()),
                             client_id: 0,
                             client_name: String::new(),
+                            session_token: None,
                         })),
                     };
                     let _ = tx.send(Ok(resp)).await;
@@ -90,6 +104,7 @@ impl AgentClientService for AgentClientServiceImpl {
                             error_message: Some(format!("数据库错误: {}", e)),
                             client_id: 0,
                             client_name: String::new(),
+                            session_token: None,
                         })),
                     };
                     let _ = tx.send(Ok(resp)).await;
@@ -109,34 +124,161 @@ impl AgentClientService for AgentClientServiceImpl {
                 return;
             }
 
+            // 检查是否到期
+            if crate::expiration::is_expired(client_model.expires_at) {
+                let resp = oxiproxy::ControllerToClientMessage {
+                    payload: Some(ControllerPayload::Error(oxiproxy::ErrorNotification {
+                        code: "client_expired".to_string(),
+                        message: format!("客户端 '{}' 已到期", client_model.name),
+                    })),
+                };
+                let _ = tx.send(Ok(resp)).await;
+                return;
+            }
+
+            // 检查版本兼容性，过旧的客户端在注册时拒绝（可先下发自更新指令）
+            if let Err(message) = crate::agent_version::check_supported(client_version.as_deref()) {
+                warn!("拒绝客户端 #{} ({}) 连接: {}", client_model.id, client_model.name, message);
+                if crate::agent_version::auto_update_outdated() {
+                    update_outdated_client(&client_model, &tx, &mut in_stream).await;
+                }
+                let resp = oxiproxy::ControllerToClientMessage {
+                    payload: Some(ControllerPayload::Error(oxiproxy::ErrorNotification {
+                        code: "version_unsupported".to_string(),
+                        message,
+                    })),
+                };
+                let _ = tx.send(Ok(resp)).await;
+                return;
+            }
+
             let client_id = client_model.id;
             let client_name = client_model.name.clone();
 
-            // 发送认证成功响应
+            // 会话令牌有效、来源 IP 和版本未变且数据库中已是在线状态时，无需再更新客户端记录
+            let resumed = client_model.is_online
+                && crate::agent_session::is_resumable(
+                    auth_req.session_token.as_deref(),
+                    crate::agent_session::KIND_CLIENT,
+                    client_id,
+                    client_ip.as_deref(),
+                    client_version.as_deref(),
+                )
+                .await;
+            let session_token = crate::agent_session::issue(
+                crate::agent_session::KIND_CLIENT,
+                client_id,
+                client_ip.as_deref(),
+                client_version.as_deref(),
+            )
+            .await;
+
+            // 注册到 ClientStreamManager，注册时发送认证成功响应，之后推送的代理列表不会先于它到达
             let auth_resp = oxiproxy::ControllerToClientMessage {
                 payload: Some(ControllerPayload::AuthResponse(oxiproxy::ClientAuthResponse {
                     success: true,
                     error_message: None,
                     client_id,
                     client_name: client_name.clone(),
+                    session_token,
                 })),
             };
-            if tx.send(Ok(auth_resp)).await.is_err() {
-                return;
-            }
-
-            info!("Agent Client #{} ({}) 已通过 gRPC 认证", client_id, client_name);
+            let policy = crate::duplicate_login::Policy::of(&client_model);
+            let registration = match client_stream_manager
+                .register(client_id, instance_id.clone(), policy, tx.clone(), auth_resp)
+                .await
+            {
+                Ok(r) => r,
+                Err((online_instance, message)) => {
+                    warn!("拒绝客户端 #{} ({}) 连接: {}", client_id, client_name, message);
+                    crate::duplicate_login::record(
+                        client_id,
+                        crate::duplicate_login::EVENT_REJECTED,
+                        instance_id,
+                        online_instance,
+                        client_ip.clone(),
+                    )
+                    .await;
+                    let resp = oxiproxy::ControllerToClientMessage {
+                        payload: Some(ControllerPayload::Error(oxiproxy::ErrorNotification {
+                            code: "duplicate_instance".to_string(),
+                            message,
+                        })),
+                    };
+                    let _ = tx.send(Ok(resp)).await;
+                    return;
+                }
+            };
 
             // 更新客户端为在线状态
-            let mut client_active: client::ActiveModel = client_model.into();
-            client_active.is_online = Set(true);
-            client_active.version = Set(client_version);
-            if let Some(ref ip) = client_ip {
-                client_active.public_ip = Set(Some(ip.clone()));
+            crate::online_status::set_client(client_id, true).await;
+            // 副本不改变绑定的实例 ID
+            let instance_changed = registration.admission != Admission::Replica
+                && instance_id.is_some()
+                && instance_id != client_model.instance_id;
+            let event = match &registration.admission {
+                Admission::Kicked(kicked) => {
+                    for old in kicked {
+                        crate::duplicate_login::record(
+                            client_id,
+                            crate::duplicate_login::EVENT_KICKED,
+                            instance_id.clone(),
+                            Some(old.clone()),
+                            client_ip.clone(),
+                        )
+                        .await;
+                    }
+                    None
+                }
+                Admission::Replica => Some(crate::duplicate_login::EVENT_REPLICA),
+                _ if instance_changed && client_model.instance_id.is_some() => Some(crate::duplicate_login::EVENT_REBOUND),
+                _ => None,
+            };
+            if let Some(event) = event {
+                crate::duplicate_login::record(
+                    client_id,
+                    event,
+                    instance_id.clone(),
+                    client_model.instance_id.clone(),
+                    client_ip.clone(),
+                )
+                .await;
             }
-            client_active.updated_at = Set(Utc::now().naive_utc());
-            if let Err(e) = client_active.update(db).await {
-                error!("更新客户端 #{} 在线状态失败: {}", client_id, e);
+            if instance_changed {
+                match &client_model.instance_id {
+                    Some(old) => info!(
+                        "客户端 #{} ({}) 的实例 ID 由 {} 变为 {}",
+                        client_id,
+                        client_name,
+                        old,
+                        instance_id.as_deref().unwrap_or_default()
+                    ),
+                    None => info!(
+                        "客户端 #{} ({}) 绑定实例 ID {}",
+                        client_id,
+                        client_name,
+                        instance_id.as_deref().unwrap_or_default()
+                    ),
+                }
+            }
+            if resumed && !instance_changed {
+                info!("Agent Client #{} ({}) 已通过 gRPC 认证（复用会话）", client_id, client_name);
+            } else {
+                info!("Agent Client #{} ({}) 已通过 gRPC 认证", client_id, client_name);
+
+                let mut client_active: client::ActiveModel = client_model.into();
+                client_active.is_online = Set(true);
+                client_active.version = Set(client_version);
+                if instance_changed {
+                    client_active.instance_id = Set(instance_id);
+                }
+                if let Some(ref ip) = client_ip {
+                    client_active.public_ip = Set(Some(ip.clone()));
+                }
+                client_active.updated_at = Set(Utc::now().naive_utc());
+                if let Err(e) = client_active.update(db).await {
+                    error!("更新客户端 #{} 在线状态失败: {}", client_id, e);
+                }
             }
 
             // 3. 立即推送当前代理列表
@@ -154,17 +296,25 @@ impl AgentClientService for AgentClientServiceImpl {
                 }
             }
 
-            // 4. 注册到 ClientStreamManager
-            client_stream_manager.register(client_id, tx.clone()).await;
+            // 上次保存的系统信息，心跳中的信息变化时才写库
+            let mut system_info: Option<SystemInfo> = None;
 
-            // 5. 消息处理循环（主要处理心跳）
-            while let Some(result) = in_stream.next().await {
+            // 4. 消息处理循环（主要处理心跳），被同一实例的新连接取代时退出
+            loop {
+                let result = tokio::select! {
+                    result = in_stream.next() => result,
+                    _ = registration.superseded.notified() => {
+                        info!("Agent Client #{} ({}) 的旧连接已被新连接取代或被踢下线", client_id, client_name);
+                        return;
+                    }
+                };
                 let msg = match result {
-                    Ok(m) => m,
-                    Err(e) => {
+                    Some(Ok(m)) => m,
+                    Some(Err(e)) => {
                         warn!("Client #{} 流错误: {}", client_id, e);
                         break;
                     }
+                    None => break,
                 };
 
                 let payload = match msg.payload {
@@ -174,9 +324,14 @@ impl AgentClientService for AgentClientServiceImpl {
 
                 match payload {
                     ClientPayload::Heartbeat(hb) => {
+                        if let Some(info) = hb.system_info.clone() {
+                            record_system_info(client_id, info, &mut system_info).await;
+                        }
                         let resp = oxiproxy::ControllerToClientMessage {
                             payload: Some(ControllerPayload::HeartbeatResponse(oxiproxy::Heartbeat {
                                 timestamp: hb.timestamp,
+                                proxy_speeds: Vec::new(),
+                                system_info: None,
                             })),
                         };
                         let _ = tx.send(Ok(resp)).await;
@@ -184,17 +339,33 @@ impl AgentClientService for AgentClientServiceImpl {
                     ClientPayload::Response(resp) => {
                         client_stream_manager.complete_pending_request(client_id, &resp).await;
                     }
+                    ClientPayload::UpdateProgress(progress) => {
+                        crate::update_rollout::record_progress(
+                            crate::update_rollout::AGENT_CLIENT,
+                            client_id,
+                            &progress,
+                        ).await;
+                    }
+                    ClientPayload::NatReport(report) => {
+                        record_nat_report(client_id, report).await;
+                    }
+                    ClientPayload::LogUpload(upload) => {
+                        crate::client_log_store::record(client_id, upload);
+                    }
                     _ => {
                         debug!("Client #{} 收到未知消息类型", client_id);
                     }
                 }
             }
 
-            // 6. 清理
+            // 5. 清理（已有新连接时保持在线状态）
             info!("Agent Client #{} ({}) gRPC 连接断开", client_id, client_name);
-            client_stream_manager.unregister(client_id).await;
+            if !client_stream_manager.unregister(client_id, registration.conn_id).await {
+                return;
+            }
 
             // 更新客户端为离线状态
+            crate::online_status::set_client(client_id, false).await;
             let db = get_connection().await;
             if let Ok(Some(c)) = Client::find_by_id(client_id).one(db).await {
                 let mut client_active: client::ActiveModel = c.into();
@@ -207,4 +378,181 @@ impl AgentClientService for AgentClientServiceImpl {
         let output_stream = ReceiverStream::new(rx);
         Ok(Response::new(Box::pin(output_stream) as Self::AgentClientChannelStream))
     }
+
+    async fn client_diagnose(
+        &self,
+        request: Request<oxiproxy::ClientAuthRequest>,
+    ) -> Result<Response<oxiproxy::ClientDiagnoseResponse>, Status> {
+        let observed_ip = crate::geo_ip::extract_client_ip_from_request(&request);
+        let req = request.into_inner();
+        let rejected = |message: String| oxiproxy::ClientDiagnoseResponse {
+            success: false,
+            error_message: Some(message),
+            client_id: 0,
+            client_name: String::new(),
+            server_groups: Vec::new(),
+            observed_ip: observed_ip.clone(),
+        };
+
+        let db = get_connection().await;
+        let client_model = match Client::find()
+            .filter(client::Column::Token.eq(&req.token))
+            .one(db)
+            .await
+        {
+            Ok(Some(c)) => c,
+            Ok(None) => return Ok(Response::new(rejected("无效的 token".to_string()))),
+            Err(e) => return Ok(Response::new(rejected(format!("数据库错误: {}", e)))),
+        };
+
+        if client_model.is_traffic_exceeded {
+            return Ok(Response::new(rejected(format!("客户端 '{}' 流量已超限", client_model.name))));
+        }
+        if crate::expiration::is_expired(client_model.expires_at) {
+            return Ok(Response::new(rejected(format!("客户端 '{}' 已到期", client_model.name))));
+        }
+
+        // 与认证不同，这里只读取代理列表，不注册流也不更新在线状态，避免影响正在运行的客户端
+        let server_groups = match self.client_stream_manager.build_proxy_list_update(client_model.id).await {
+            Ok(update) => update.server_groups,
+            Err(e) => return Ok(Response::new(rejected(format!("构建代理列表失败: {}", e)))),
+        };
+
+        info!("Agent Client #{} ({}) 执行诊断", client_model.id, client_model.name);
+        Ok(Response::new(oxiproxy::ClientDiagnoseResponse {
+            success: true,
+            error_message: None,
+            client_id: client_model.id,
+            client_name: client_model.name,
+            server_groups,
+            observed_ip,
+        }))
+    }
+}
+
+/// 保存客户端上报的 NAT 类型
+/// 让版本过旧的客户端自更新：完成认证（不下发代理）后发送更新指令，等待其更新重启或超时
+async fn update_outdated_client(
+    client_model: &client::Model,
+    tx: &mpsc::Sender<Result<oxiproxy::ControllerToClientMessage, Status>>,
+    in_stream: &mut Streaming<oxiproxy::AgentClientMessage>,
+) {
+    let target_version = crate::agent_version::update_target();
+    let messages = [
+        ControllerPayload::AuthResponse(oxiproxy::ClientAuthResponse {
+            success: true,
+            error_message: None,
+            client_id: client_model.id,
+            client_name: client_model.name.clone(),
+            session_token: None,
+        }),
+        ControllerPayload::SoftwareUpdate(oxiproxy::SoftwareUpdateCommand {
+            request_id: uuid::Uuid::new_v4().to_string(),
+            target_version: target_version.clone(),
+        }),
+    ];
+    for payload in messages {
+        if tx.send(Ok(oxiproxy::ControllerToClientMessage { payload: Some(payload) })).await.is_err() {
+            return;
+        }
+    }
+    info!(
+        "已向版本过旧的客户端 #{} ({}) 下发自更新指令，目标版本 {}",
+        client_model.id,
+        client_model.name,
+        target_version.as_deref().unwrap_or("最新版本")
+    );
+
+    let wait = async {
+        while let Some(Ok(msg)) = in_stream.next().await {
+            match msg.payload {
+                Some(ClientPayload::UpdateProgress(progress)) => {
+                    info!("客户端 #{} 自更新进度: {}", client_model.id, progress.stage);
+                }
+                Some(ClientPayload::Response(oxiproxy::AgentClientResponse {
+                    result: Some(oxiproxy::agent_client_response::Result::SoftwareUpdate(resp)),
+                    ..
+                })) => {
+                    if resp.success {
+                        info!("客户端 #{} 自更新完成: {}", client_model.id, resp.new_version.unwrap_or_default());
+                    } else {
+                        warn!("客户端 #{} 自更新失败: {}", client_model.id, resp.error.unwrap_or_default());
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    };
+    if tokio::time::timeout(crate::agent_version::UPDATE_WAIT, wait).await.is_err() {
+        warn!("等待客户端 #{} 自更新超时", client_model.id);
+    }
+}
+
+/// 客户端的系统信息（启动时间由运行时长推算）
+#[derive(Debug, Clone, PartialEq)]
+struct SystemInfo {
+    os: String,
+    arch: String,
+    version: String,
+    started_at: NaiveDateTime,
+}
+
+impl SystemInfo {
+    /// 推算的启动时间随心跳间隔和时钟抖动，相差一分钟以内视为同一次启动
+    fn same_as(&self, other: &SystemInfo) -> bool {
+        self.os == other.os
+            && self.arch == other.arch
+            && self.version == other.version
+            && (self.started_at - other.started_at).num_seconds().abs() < 60
+    }
+}
+
+async fn record_system_info(client_id: i64, info: oxiproxy::AgentSystemInfo, last: &mut Option<SystemInfo>) {
+    let uptime = i64::try_from(info.uptime_secs)
+        .ok()
+        .and_then(chrono::Duration::try_seconds)
+        .unwrap_or_default();
+    let current = SystemInfo {
+        os: info.os,
+        arch: info.arch,
+        version: info.version,
+        started_at: Utc::now().naive_utc() - uptime,
+    };
+    if last.as_ref().is_some_and(|l| l.same_as(&current)) {
+        return;
+    }
+    let db = get_connection().await;
+    let Ok(Some(c)) = Client::find_by_id(client_id).one(db).await else {
+        return;
+    };
+    let mut client_active: client::ActiveModel = c.into();
+    client_active.os = Set(Some(current.os.clone()).filter(|s| !s.is_empty()));
+    client_active.arch = Set(Some(current.arch.clone()).filter(|s| !s.is_empty()));
+    if !current.version.is_empty() {
+        client_active.version = Set(Some(current.version.clone()));
+    }
+    client_active.started_at = Set(Some(current.started_at));
+    match client_active.update(db).await {
+        Ok(_) => *last = Some(current),
+        Err(e) => error!("保存客户端 #{} 系统信息失败: {}", client_id, e),
+    }
+}
+
+async fn record_nat_report(client_id: i64, report: oxiproxy::NatReport) {
+    info!(
+        "Client #{} NAT 类型: {}（映射地址 {}，探测节点 #{}）",
+        client_id, report.nat_type, report.mapped_addr, report.node_id
+    );
+    let db = get_connection().await;
+    let Ok(Some(c)) = Client::find_by_id(client_id).one(db).await else {
+        return;
+    };
+    let mut client_active: client::ActiveModel = c.into();
+    client_active.nat_type = Set(Some(report.nat_type).filter(|t| !t.is_empty()));
+    client_active.nat_mapped_addr = Set(Some(report.mapped_addr).filter(|a| !a.is_empty()));
+    client_active.nat_detected_at = Set(Some(Utc::now().naive_utc()));
+    if let Err(e) = client_active.update(db).await {
+        error!("保存客户端 #{} NAT 类型失败: {}", client_id, e);
+    }
 }
@@ -8,7 +8,7 @@ use tokio::sync::mpsc;
 use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
 use tonic::{Request, Response, Status, Streaming};
 use tracing::{error, info, warn};
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
 use chrono::Utc;
 
 use common::grpc::oxiproxy;
@@ -22,16 +22,140 @@ use crate::entity::{Node, Proxy, node, proxy};
 use crate::migration::get_connection;
 
 use common::protocol::auth::ClientAuthProvider;
+use common::protocol::handshake::AuthProof;
 
 pub struct AgentServerServiceImpl {
     pub node_manager: Arc<NodeManager>,
 }
 
 type ResponseStream = Pin<Box<dyn Stream<Item = Result<oxiproxy::ControllerToAgentMessage, Status>> + Send>>;
+type TrafficAckStream = Pin<Box<dyn Stream<Item = Result<oxiproxy::TrafficAck, Status>> + Send>>;
 
 #[tonic::async_trait]
 impl AgentServerService for AgentServerServiceImpl {
     type AgentServerChannelStream = ResponseStream;
+    type TrafficStreamStream = TrafficAckStream;
+
+    async fn traffic_stream(
+        &self,
+        request: Request<Streaming<oxiproxy::TrafficBatch>>,
+    ) -> Result<Response<Self::TrafficStreamStream>, Status> {
+        let token = request
+            .metadata()
+            .get(common::grpc::NODE_TOKEN_METADATA)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| Status::unauthenticated("缺少节点 token"))?
+            .to_string();
+
+        let db = get_connection().await;
+        let node_id = match Node::find()
+            .filter(node::Column::Secret.eq(&token))
+            .one(db)
+            .await
+        {
+            Ok(Some(n)) => n.id,
+            Ok(None) => return Err(Status::unauthenticated("无效的节点 token")),
+            Err(e) => return Err(Status::internal(format!("数据库错误: {}", e))),
+        };
+
+        let mut in_stream = request.into_inner();
+        let (tx, rx) = mpsc::channel::<Result<oxiproxy::TrafficAck, Status>>(
+            crate::traffic::TRAFFIC_STREAM_WINDOW as usize,
+        );
+
+        tokio::spawn(async move {
+            info!("节点 #{} 流量上报流已建立", node_id);
+            let traffic_manager = crate::traffic::TrafficManager::shared();
+
+            while let Some(result) = in_stream.next().await {
+                let batch = match result {
+                    Ok(batch) => batch,
+                    Err(e) => {
+                        warn!("节点 #{} 流量上报流错误: {}", node_id, e);
+                        break;
+                    }
+                };
+
+                // 重发的批次已处理过，只需再次确认；登记失败时不确认，断开后由节点重发
+                let first = match crate::traffic::claim_report(node_id, &batch.report_id).await {
+                    Ok(first) => first,
+                    Err(e) => {
+                        error!("节点 #{} 登记流量上报 {} 失败: {}", node_id, batch.report_id, e);
+                        break;
+                    }
+                };
+                if first {
+                    for record in batch.records {
+                        let cid = record.client_id.parse::<i64>().unwrap_or(0);
+                        // 写入队列满时在此等待，确认随之延后，形成背压
+                        traffic_manager
+                            .record_traffic(
+                                node_id,
+                                record.proxy_id,
+                                cid,
+                                record.user_id,
+                                record.bytes_sent,
+                                record.bytes_received,
+                            )
+                            .await;
+                    }
+                }
+
+                let ack = oxiproxy::TrafficAck {
+                    seq: batch.seq,
+                    window: traffic_manager.ack_window(),
+                    session: batch.session,
+                };
+                if tx.send(Ok(ack)).await.is_err() {
+                    break;
+                }
+            }
+            info!("节点 #{} 流量上报流已关闭", node_id);
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx)) as Self::TrafficStreamStream))
+    }
+
+    async fn node_dry_run(
+        &self,
+        request: Request<oxiproxy::NodeRegisterRequest>,
+    ) -> Result<Response<oxiproxy::NodeDryRunResponse>, Status> {
+        let req = request.into_inner();
+        let rejected = |message: String| oxiproxy::NodeDryRunResponse {
+            success: false,
+            error_message: Some(message),
+            ..Default::default()
+        };
+
+        let db = get_connection().await;
+        let node_model = match Node::find()
+            .filter(node::Column::Secret.eq(&req.token))
+            .one(db)
+            .await
+        {
+            Ok(Some(n)) => n,
+            Ok(None) => return Ok(Response::new(rejected("无效的节点 token".to_string()))),
+            Err(e) => return Ok(Response::new(rejected(format!("数据库错误: {}", e)))),
+        };
+
+        // 与注册不同，这里只读取配置，不注册流也不更新在线状态，避免影响正在运行的节点
+        let proxies = match enabled_proxy_configs(node_model.id, db).await {
+            Ok(proxies) => proxies,
+            Err(e) => return Ok(Response::new(rejected(e))),
+        };
+
+        let online = crate::online_status::nodes().get(node_model.id).unwrap_or(node_model.is_online);
+        info!("节点 #{} ({}) 执行预检（版本 {}）", node_model.id, node_model.name, req.version);
+        Ok(Response::new(oxiproxy::NodeDryRunResponse {
+            success: true,
+            error_message: None,
+            node_id: node_model.id,
+            node_name: node_model.name,
+            tunnel_protocol: node_model.tunnel_protocol,
+            proxies,
+            online,
+        }))
+    }
 
     async fn agent_server_channel(
         &self,
@@ -46,6 +170,13 @@ impl AgentServerService for AgentServerServiceImpl {
         let node_manager = self.node_manager.clone();
 
         tokio::spawn(async move {
+            // 重连高峰时排队接入，排队过久直接拒绝，由节点稍后重试
+            if !crate::accept_limiter::acquire().await {
+                warn!("Agent 连接过多，拒绝节点连接");
+                let _ = tx.send(Err(Status::resource_exhausted("Controller 繁忙，请稍后重连"))).await;
+                return;
+            }
+
             // 1. 读取首条消息，必须是认证请求
             let first_msg = match in_stream.next().await {
                 Some(Ok(msg)) => msg,
@@ -88,46 +219,131 @@ impl AgentServerService for AgentServerServiceImpl {
                 }
             };
 
+            // 检查版本兼容性，过旧的节点在注册时拒绝（可先下发自更新指令）
+            if let Err(message) = crate::agent_version::check_supported(Some(register_req.version.as_str())) {
+                warn!("拒绝节点 #{} ({}) 连接: {}", node_model.id, node_model.name, message);
+                if crate::agent_version::auto_update_outdated() {
+                    update_outdated_node(&node_model, &tx, &mut in_stream).await;
+                }
+                let _ = tx.send(Err(Status::failed_precondition(message))).await;
+                return;
+            }
+
             let node_id = node_model.id;
             let node_name = node_model.name.clone();
             let authoritative_protocol = node_model.tunnel_protocol.clone();
-            let node_speed_limit = node_model.speed_limit;
+            // 按带宽时间表计算当前生效的限制
+            let speed_limits = match crate::bandwidth_schedule::limits_for(&node_model, db).await {
+                Ok(limits) => limits,
+                Err(e) => {
+                    warn!("计算节点 #{} 的速度限制失败: {}", node_model.id, e);
+                    crate::bandwidth_schedule::SpeedLimits {
+                        node: node_model.speed_limit.unwrap_or(0),
+                        fair_share: node_model.fair_share,
+                        users: Vec::new(),
+                    }
+                }
+            };
+            let node_kcp = node_model.kcp_config
+                .as_deref()
+                .and_then(|s| serde_json::from_str::<common::KcpConfig>(s).ok())
+                .map(|k| oxiproxy::GrpcKcpConfig::from(&k));
+            let node_quic = node_model.quic_config
+                .as_deref()
+                .and_then(|s| serde_json::from_str::<common::QuicConfig>(s).ok())
+                .map(|q| oxiproxy::GrpcQuicConfig::from(&q));
+            let node_mitigation = crate::mitigation::to_grpc(node_model.mitigation_config.as_deref());
             let current_tunnel_addr = node_model.tunnel_addr.clone();
 
+            // 会话令牌有效且来源 IP、版本未变时沿用上次的公网 IP 和地理位置，跳过外部查询
+            let node_version = if register_req.version.is_empty() { None } else { Some(register_req.version.clone()) };
+            let resumed = node_model.public_ip.is_some()
+                && crate::agent_session::is_resumable(
+                    register_req.session_token.as_deref(),
+                    crate::agent_session::KIND_NODE,
+                    node_id,
+                    client_ip.as_deref(),
+                    node_version.as_deref(),
+                )
+                .await;
+
+            // 节点上报的能力；本机公网 IP 比连接来源地址更准确（Controller 与节点可能经内网或代理连接）
+            let capabilities = register_req.capabilities.map(common::protocol::node_register::NodeCapabilities::from);
+            let reported_ip = capabilities
+                .as_ref()
+                .and_then(|c| c.preferred_public_ip())
+                .map(str::to_string);
+            if capabilities.as_ref().is_some_and(|c| !c.supports_protocol(&authoritative_protocol)) {
+                warn!("节点 #{} 不支持配置的隧道协议 {}，请检查节点版本或修改隧道协议", node_id, authoritative_protocol);
+            }
+
             // 查询地理位置信息
-            let geo_info = if let Some(ref ip) = client_ip {
-                crate::geo_ip::query_geo_ip(ip).await.ok()
-            } else {
-                None
+            let geo_info = match reported_ip.as_ref().or(client_ip.as_ref()) {
+                Some(ip) if !resumed => crate::geo_ip::query_geo_ip(ip).await.ok(),
+                _ => None,
             };
 
             // 更新节点信息（不覆盖 tunnel_protocol，Controller DB 为权威来源）
+            crate::online_status::set_node(node_id, true).await;
             let mut active: crate::entity::node::ActiveModel = node_model.into();
             active.tunnel_port = Set(register_req.tunnel_port as i32);
             active.is_online = Set(true);
             active.updated_at = Set(Utc::now().naive_utc());
-            active.version = Set(if register_req.version.is_empty() { None } else { Some(register_req.version.clone()) });
+            active.version = Set(node_version.clone());
+            active.nat_probe_port = Set(register_req.nat_probe_port.map(|p| p as i32));
+            active.capabilities = Set(capabilities.as_ref().and_then(|c| serde_json::to_string(c).ok()));
 
-            // 更新公网IP和地理位置
-            if let Some(geo) = geo_info {
+            // 更新公网IP和地理位置（复用会话时沿用上次的结果）
+            if let Some(ip) = reported_ip {
+                if current_tunnel_addr.is_empty() {
+                    active.tunnel_addr = Set(ip.clone());
+                }
+                active.public_ip = Set(Some(ip));
+                if let Some(geo) = geo_info {
+                    active.region = Set(Some(geo.region));
+                }
+            } else if let Some(geo) = geo_info {
                 // 如果隧道地址为空，自动设置为公网IP
                 if current_tunnel_addr.is_empty() {
                     active.tunnel_addr = Set(geo.ip.clone());
                 }
                 active.public_ip = Set(Some(geo.ip));
                 active.region = Set(Some(geo.region));
-            } else if let Some(ip) = client_ip {
+            } else if let (false, Some(ip)) = (resumed, &client_ip) {
                 if current_tunnel_addr.is_empty() {
                     active.tunnel_addr = Set(ip.clone());
                 }
-                active.public_ip = Set(Some(ip));
+                active.public_ip = Set(Some(ip.clone()));
             }
 
-            if let Err(e) = active.update(db).await {
-                error!("更新节点 #{} 失败: {}", node_id, e);
+            match active.update(db).await {
+                // 公网 IP 可能变化，同步指向该节点的 DNS 记录
+                Ok(_) => crate::dns::request_sync(),
+                Err(e) => error!("更新节点 #{} 失败: {}", node_id, e),
             }
 
-            info!("节点 #{} ({}) 已通过 gRPC 连接认证", node_id, node_name);
+            if resumed {
+                info!("节点 #{} ({}) 已通过 gRPC 连接认证（复用会话）", node_id, node_name);
+            } else {
+                info!("节点 #{} ({}) 已通过 gRPC 连接认证", node_id, node_name);
+            }
+            let session_token = crate::agent_session::issue(
+                crate::agent_session::KIND_NODE,
+                node_id,
+                client_ip.as_deref(),
+                node_version.as_deref(),
+            )
+            .await;
+
+            // 节点启动时预绑定所有启用代理的远程端口，提前发现与主机上其他程序的端口冲突
+            let prebind_proxies = if register_req.prebind_ports {
+                enabled_proxy_configs(node_id, db).await.unwrap_or_else(|e| {
+                    warn!("查询节点 #{} 需要预绑定的代理失败: {}", node_id, e);
+                    Vec::new()
+                })
+            } else {
+                Vec::new()
+            };
 
             // 发送认证响应（包含权威隧道协议）
             let register_resp = oxiproxy::ControllerToAgentMessage {
@@ -135,7 +351,15 @@ impl AgentServerService for AgentServerServiceImpl {
                     node_id,
                     node_name: node_name.clone(),
                     tunnel_protocol: authoritative_protocol,
-                    speed_limit: node_speed_limit,
+                    speed_limit: Some(speed_limits.node),
+                    kcp: node_kcp,
+                    quic: node_quic,
+                    session_token,
+                    mitigation: node_mitigation,
+                    connection_authz: crate::connection_authz::to_grpc(),
+                    user_speed_limits: speed_limits.users,
+                    fair_share: speed_limits.fair_share,
+                    prebind_proxies,
                 })),
             };
             if tx.send(Ok(register_resp)).await.is_err() {
@@ -164,16 +388,31 @@ impl AgentServerService for AgentServerServiceImpl {
 
                 match payload {
                     AgentPayload::Heartbeat(hb) => {
+                        crate::live_speed::update(node_id, hb.proxy_speeds);
                         let resp = oxiproxy::ControllerToAgentMessage {
                             payload: Some(ControllerPayload::HeartbeatResponse(oxiproxy::Heartbeat {
                                 timestamp: hb.timestamp,
+                                proxy_speeds: Vec::new(),
+                                system_info: None,
                             })),
                         };
                         let _ = tx.send(Ok(resp)).await;
                     }
 
                     AgentPayload::ValidateToken(req) => {
-                        let result = auth_provider.validate_token(&req.token).await;
+                        let result = match req.proof {
+                            Some(p) => {
+                                let proof = AuthProof {
+                                    client_id: p.client_id,
+                                    client_nonce: p.client_nonce,
+                                    server_nonce: p.server_nonce,
+                                    metadata: p.metadata,
+                                    mac: p.mac,
+                                };
+                                auth_provider.validate_proof(&proof).await
+                            }
+                            None => auth_provider.validate_token(&req.token).await,
+                        };
                         let resp = match result {
                             Ok(r) => oxiproxy::ValidateTokenResponse {
                                 request_id: req.request_id,
@@ -181,6 +420,7 @@ impl AgentServerService for AgentServerServiceImpl {
                                 client_name: r.client_name,
                                 allowed: r.allowed,
                                 reject_reason: r.reject_reason,
+                                allow_replicas: r.allow_replicas,
                             },
                             Err(e) => oxiproxy::ValidateTokenResponse {
                                 request_id: req.request_id,
@@ -188,6 +428,7 @@ impl AgentServerService for AgentServerServiceImpl {
                                 client_name: String::new(),
                                 allowed: false,
                                 reject_reason: Some(e.to_string()),
+                                allow_replicas: false,
                             },
                         };
                         let msg = oxiproxy::ControllerToAgentMessage {
@@ -248,19 +489,29 @@ impl AgentServerService for AgentServerServiceImpl {
                     }
 
                     AgentPayload::TrafficReport(req) => {
-                        // 处理流量上报
-                        let traffic_manager = crate::traffic::TrafficManager::new();
-                        for record in req.records {
-                            let cid = record.client_id.parse::<i64>().unwrap_or(0);
-                            traffic_manager
-                                .record_traffic(
-                                    record.proxy_id,
-                                    cid,
-                                    record.user_id,
-                                    record.bytes_sent,
-                                    record.bytes_received,
-                                )
-                                .await;
+                        // 处理流量上报（重复的上报 ID 直接确认，不再入账）
+                        let first = crate::traffic::claim_report(node_id, &req.report_id)
+                            .await
+                            .unwrap_or_else(|e| {
+                                error!("节点 #{} 登记流量上报 {} 失败: {}", node_id, req.report_id, e);
+                                // 旧的上报方式不会重发，登记失败时照常入账
+                                true
+                            });
+                        let traffic_manager = crate::traffic::TrafficManager::shared();
+                        if first {
+                            for record in req.records {
+                                let cid = record.client_id.parse::<i64>().unwrap_or(0);
+                                traffic_manager
+                                    .record_traffic(
+                                        node_id,
+                                        record.proxy_id,
+                                        cid,
+                                        record.user_id,
+                                        record.bytes_sent,
+                                        record.bytes_received,
+                                    )
+                                    .await;
+                            }
                         }
                         let resp = oxiproxy::ControllerToAgentMessage {
                             payload: Some(ControllerPayload::TrafficReportResponse(
@@ -275,6 +526,46 @@ impl AgentServerService for AgentServerServiceImpl {
                         node_manager.complete_pending_request(node_id, &resp).await;
                     }
 
+                    AgentPayload::ProbeStep(step) => {
+                        node_manager.forward_probe_step(node_id, step).await;
+                    }
+
+                    AgentPayload::LogChunk(chunk) => {
+                        node_manager.forward_log_chunk(node_id, chunk).await;
+                    }
+
+                    AgentPayload::CaptureChunk(chunk) => {
+                        crate::capture::receive_chunk(node_id, chunk).await;
+                    }
+
+                    AgentPayload::MitigationEvent(event) => {
+                        crate::mitigation::record(node_id, event).await;
+                    }
+
+                    AgentPayload::PortConflicts(report) => {
+                        crate::port_conflict::report(node_id, &node_name, report);
+                    }
+
+                    AgentPayload::AuthorizeConnection(req) => {
+                        // 调用外部钩子可能较慢，不阻塞消息循环
+                        let tx = tx.clone();
+                        tokio::spawn(async move {
+                            let resp = crate::connection_authz::authorize(node_id, req).await;
+                            let msg = oxiproxy::ControllerToAgentMessage {
+                                payload: Some(ControllerPayload::AuthorizeConnectionResponse(resp)),
+                            };
+                            let _ = tx.send(Ok(msg)).await;
+                        });
+                    }
+
+                    AgentPayload::UpdateProgress(progress) => {
+                        crate::update_rollout::record_progress(
+                            crate::update_rollout::AGENT_NODE,
+                            node_id,
+                            &progress,
+                        ).await;
+                    }
+
                     _ => {
                         warn!("节点 #{} 收到未知消息类型", node_id);
                     }
@@ -284,6 +575,8 @@ impl AgentServerService for AgentServerServiceImpl {
             // 5. 清理：标记节点离线
             info!("节点 #{} ({}) gRPC 连接断开", node_id, node_name);
             node_manager.unregister_node_stream(node_id).await;
+            crate::online_status::set_node(node_id, false).await;
+            crate::live_speed::remove(node_id);
 
             let db = get_connection().await;
             if let Ok(Some(n)) = Node::find_by_id(node_id).one(db).await {
@@ -314,10 +607,10 @@ async fn get_client_proxies_filtered(client_id: i64, filter_node_id: i64) -> Vec
         Err(_) => return vec![],
     };
 
-    // 过滤出属于指定节点的代理
-    proxies
+    // 过滤出发布在指定节点上的代理（含多节点发布）
+    let mut configs: Vec<oxiproxy::ProxyConfig> = proxies
         .into_iter()
-        .filter(|p| p.node_id == Some(filter_node_id))
+        .filter(|p| p.is_published_on(filter_node_id))
         .map(|p| oxiproxy::ProxyConfig {
             proxy_id: p.id,
             client_id: p.client_id,
@@ -327,6 +620,139 @@ async fn get_client_proxies_filtered(client_id: i64, filter_node_id: i64) -> Vec
             local_port: p.local_port as u32,
             remote_port: p.remote_port as u32,
             enabled: p.enabled,
+            idle_timeout: p.idle_timeout.map(|t| t.max(0) as u32),
+            mitigation: crate::mitigation::to_grpc(p.mitigation_config.as_deref()),
+            bind_ip: p.bind_ip,
+            bandwidth_weight: p.bandwidth_weight.map(|w| w.max(1) as u32),
+            priority: p.priority,
         })
-        .collect()
+        .collect();
+
+    // 该节点上仍在有效期内的临时隧道
+    if let Ok(tunnels) = crate::temporary_tunnel::active_tunnels(client_id, Some(filter_node_id), db).await {
+        configs.extend(tunnels.into_iter().map(|t| oxiproxy::ProxyConfig {
+            proxy_id: crate::temporary_tunnel::proxy_id(t.id),
+            client_id: client_id_str.clone(),
+            name: format!("temporary-{}", t.id),
+            proxy_type: "tcp".to_string(),
+            local_ip: t.local_ip,
+            local_port: t.local_port as u32,
+            remote_port: t.remote_port as u32,
+            enabled: true,
+            idle_timeout: None,
+            mitigation: None,
+            bind_ip: None,
+            bandwidth_weight: None,
+            priority: None,
+        }));
+    }
+
+    configs
+}
+
+/// 让版本过旧的节点自更新：完成注册（不下发代理）后发送更新指令，等待其更新重启或超时
+/// 节点上启用的代理和有效期内的临时隧道（预检和启动时预绑定端口使用）
+async fn enabled_proxy_configs(node_id: i64, db: &DatabaseConnection) -> Result<Vec<oxiproxy::ProxyConfig>, String> {
+    let mut proxies: Vec<oxiproxy::ProxyConfig> = match Proxy::find()
+        .filter(crate::anycast::maybe_on_node(node_id))
+        .filter(proxy::Column::Enabled.eq(true))
+        .all(db)
+        .await
+    {
+        Ok(list) => list
+            .into_iter()
+            .filter(|p| p.is_published_on(node_id))
+            .map(|p| oxiproxy::ProxyConfig {
+                proxy_id: p.id,
+                client_id: p.client_id,
+                name: p.name,
+                proxy_type: p.proxy_type,
+                local_ip: p.local_ip,
+                local_port: p.local_port as u32,
+                remote_port: p.remote_port as u32,
+                enabled: p.enabled,
+                idle_timeout: p.idle_timeout.map(|t| t.max(0) as u32),
+                mitigation: crate::mitigation::to_grpc(p.mitigation_config.as_deref()),
+                bind_ip: p.bind_ip,
+                bandwidth_weight: p.bandwidth_weight.map(|w| w.max(1) as u32),
+                priority: p.priority,
+            })
+            .collect(),
+        Err(e) => return Err(format!("查询代理失败: {}", e)),
+    };
+    match crate::temporary_tunnel::active_tunnels_on_node(node_id, db).await {
+        Ok(tunnels) => proxies.extend(tunnels.into_iter().map(|t| oxiproxy::ProxyConfig {
+            proxy_id: crate::temporary_tunnel::proxy_id(t.id),
+            client_id: t.client_id.to_string(),
+            name: format!("temporary-{}", t.id),
+            proxy_type: "tcp".to_string(),
+            local_ip: t.local_ip,
+            local_port: t.local_port as u32,
+            remote_port: t.remote_port as u32,
+            enabled: true,
+            idle_timeout: None,
+            mitigation: None,
+            bind_ip: None,
+            bandwidth_weight: None,
+            priority: None,
+        })),
+        Err(e) => return Err(format!("查询临时隧道失败: {}", e)),
+    }
+    Ok(proxies)
+}
+
+async fn update_outdated_node(
+    node_model: &node::Model,
+    tx: &mpsc::Sender<Result<oxiproxy::ControllerToAgentMessage, Status>>,
+    in_stream: &mut Streaming<oxiproxy::AgentServerMessage>,
+) {
+    let target_version = crate::agent_version::update_target();
+    let messages = [
+        ControllerPayload::RegisterResponse(oxiproxy::NodeRegisterResponse {
+            node_id: node_model.id,
+            node_name: node_model.name.clone(),
+            tunnel_protocol: node_model.tunnel_protocol.clone(),
+            ..Default::default()
+        }),
+        ControllerPayload::SoftwareUpdate(oxiproxy::SoftwareUpdateCommand {
+            request_id: uuid::Uuid::new_v4().to_string(),
+            target_version: target_version.clone(),
+        }),
+    ];
+    for payload in messages {
+        if tx.send(Ok(oxiproxy::ControllerToAgentMessage { payload: Some(payload) })).await.is_err() {
+            return;
+        }
+    }
+    info!(
+        "已向版本过旧的节点 #{} ({}) 下发自更新指令，目标版本 {}",
+        node_model.id,
+        node_model.name,
+        target_version.as_deref().unwrap_or("最新版本")
+    );
+
+    let wait = async {
+        while let Some(Ok(msg)) = in_stream.next().await {
+            match msg.payload {
+                Some(AgentPayload::UpdateProgress(progress)) => {
+                    info!("节点 #{} 自更新进度: {}", node_model.id, progress.stage);
+                }
+                Some(AgentPayload::Response(oxiproxy::AgentServerResponse {
+                    result: Some(oxiproxy::agent_server_response::Result::SoftwareUpdate(resp)),
+                    ..
+                })) => {
+                    if resp.success {
+                        info!("节点 #{} 自更新完成: {}", node_model.id, resp.new_version.unwrap_or_default());
+                    } else {
+                        warn!("节点 #{} 自更新失败: {}", node_model.id, resp.error.unwrap_or_default());
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    };
+    if tokio::time::timeout(crate::agent_version::UPDATE_WAIT, wait).await.is_err() {
+        warn!("等待节点 #{} 自更新超时", node_model.id);
+    }
 }
@@ -1,20 +1,31 @@
 //! gRPC Server 启动
 //!
-//! 在 internal_port 上启动 gRPC Server，提供 AgentServerService 和 AgentClientService。
+//! 在 internal_port 上启动 gRPC Server，提供 AgentServerService 和 AgentClientService，
+//! 以及标准健康检查（grpc.health.v1）和服务反射，供 grpcurl、Kubernetes gRPC 探针、负载均衡器使用。
 //! 支持原生 TLS（从数据库或文件加载证书）。
 
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::server::Router;
 use tonic::transport::{Identity, Server, ServerTlsConfig};
+use tonic_health::pb::health_server::{Health, HealthServer};
+use tonic_health::server::HealthReporter;
+use tonic_health::ServingStatus;
 use tracing::{info, error, warn};
 use base64::Engine;
 
 use common::grpc::{AgentServerServiceServer, AgentClientServiceServer};
+use common::grpc::tuning::{self, Tuned};
 
 use crate::grpc_agent_server_service::AgentServerServiceImpl;
 use crate::grpc_agent_client_service::AgentClientServiceImpl;
 use crate::node_manager::NodeManager;
 use crate::client_stream_manager::ClientStreamManager;
 use crate::config_manager::ConfigManager;
+use crate::health::HealthState;
+use crate::migration::get_connection;
 
 /// 从 ConfigManager 加载 TLS 证书和私钥（PEM 格式）
 async fn load_tls_identity(config_manager: &ConfigManager) -> Result<Identity, String> {
@@ -49,15 +60,87 @@ async fn load_tls_identity(config_manager: &ConfigManager) -> Result<Identity, S
     Ok(Identity::from_pem(cert_pem, key_pem))
 }
 
+/// 标准健康检查状态的刷新间隔
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 注册所有服务：Agent 服务、标准健康检查（grpc.health.v1）和服务反射（grpcurl 等工具使用）
+fn add_services<H: Health>(
+    builder: &mut Server,
+    agent_server_service: AgentServerServiceImpl,
+    agent_client_service: AgentClientServiceImpl,
+    health_service: HealthServer<H>,
+) -> Router {
+    let mut router = builder
+        .add_service(health_service)
+        .add_service(AgentServerServiceServer::new(agent_server_service).tuned())
+        .add_service(AgentClientServiceServer::new(agent_client_service).tuned());
+
+    let reflection = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(common::grpc::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET);
+    match reflection.build_v1() {
+        Ok(service) => router = router.add_service(service),
+        Err(e) => warn!("gRPC 反射服务初始化失败: {}", e),
+    }
+    // 旧版 grpcurl 只支持 v1alpha
+    let reflection = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(common::grpc::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET);
+    match reflection.build_v1alpha() {
+        Ok(service) => router = router.add_service(service),
+        Err(e) => warn!("gRPC 反射服务 (v1alpha) 初始化失败: {}", e),
+    }
+    router
+}
+
+/// 按就绪条件（数据库可访问、系统配置已加载）定期更新标准健康检查状态
+fn start_health_reporter(mut reporter: HealthReporter, health: Arc<HealthState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+        let mut last = None;
+        loop {
+            interval.tick().await;
+            let ready = health.is_config_loaded() && get_connection().await.ping().await.is_ok();
+            if last == Some(ready) {
+                continue;
+            }
+            last = Some(ready);
+
+            let status = if ready { ServingStatus::Serving } else { ServingStatus::NotServing };
+            reporter.set_service_status("", status).await;
+            if ready {
+                reporter.set_serving::<AgentServerServiceServer<AgentServerServiceImpl>>().await;
+                reporter.set_serving::<AgentClientServiceServer<AgentClientServiceImpl>>().await;
+            } else {
+                warn!("gRPC 健康状态: NOT_SERVING");
+                reporter.set_not_serving::<AgentServerServiceServer<AgentServerServiceImpl>>().await;
+                reporter.set_not_serving::<AgentClientServiceServer<AgentClientServiceImpl>>().await;
+            }
+        }
+    });
+}
+
 /// 启动 gRPC Server
 pub fn start_grpc_server(
     port: u16,
     node_manager: Arc<NodeManager>,
     client_stream_manager: Arc<ClientStreamManager>,
     config_manager: Arc<ConfigManager>,
+    health: Arc<HealthState>,
 ) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
-        let addr = format!("0.0.0.0:{}", port).parse().unwrap();
+        let addr: SocketAddr = common::utils::wildcard_addr(port);
+
+        // 先绑定端口再启动服务，以便就绪探针能准确反映 gRPC 端口状态
+        let listener = match common::socket_activation::tcp_listener(addr) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("gRPC Server 绑定端口 {} 失败: {}", addr, e);
+                return;
+            }
+        };
+        let incoming = TcpListenerStream::new(listener);
+        health.set_grpc_bound(true);
 
         let agent_server_service = AgentServerServiceImpl {
             node_manager,
@@ -67,6 +150,9 @@ pub fn start_grpc_server(
             client_stream_manager,
         };
 
+        let (health_reporter, health_service) = tonic_health::server::health_reporter();
+        start_health_reporter(health_reporter, health.clone());
+
         let tls_enabled = config_manager.get_bool("grpc_tls_enabled", false).await;
 
         if tls_enabled {
@@ -75,27 +161,24 @@ pub fn start_grpc_server(
                     let tls_config = ServerTlsConfig::new().identity(identity);
                     info!("gRPC Server 启动 (TLS): {}", addr);
 
-                    let mut builder = match Server::builder().tls_config(tls_config) {
+                    let mut builder = match tuning::configure_server(Server::builder()).tls_config(tls_config) {
                         Ok(b) => b,
                         Err(e) => {
                             error!("gRPC TLS 配置失败: {}，回退到非 TLS 模式", e);
                             warn!("gRPC Server 启动 (非 TLS): {}", addr);
-                            if let Err(e) = Server::builder()
-                                .add_service(AgentServerServiceServer::new(agent_server_service))
-                                .add_service(AgentClientServiceServer::new(agent_client_service))
-                                .serve(addr)
+                            if let Err(e) = add_services(&mut tuning::configure_server(Server::builder()), agent_server_service, agent_client_service, health_service)
+                                .serve_with_incoming(incoming)
                                 .await
                             {
                                 error!("gRPC Server 错误: {}", e);
                             }
+                            health.set_grpc_bound(false);
                             return;
                         }
                     };
 
-                    if let Err(e) = builder
-                        .add_service(AgentServerServiceServer::new(agent_server_service))
-                        .add_service(AgentClientServiceServer::new(agent_client_service))
-                        .serve(addr)
+                    if let Err(e) = add_services(&mut builder, agent_server_service, agent_client_service, health_service)
+                        .serve_with_incoming(incoming)
                         .await
                     {
                         error!("gRPC Server 错误: {}", e);
@@ -104,10 +187,8 @@ pub fn start_grpc_server(
                 Err(e) => {
                     error!("加载 TLS 证书失败: {}，回退到非 TLS 模式", e);
                     warn!("gRPC Server 启动 (非 TLS): {}", addr);
-                    if let Err(e) = Server::builder()
-                        .add_service(AgentServerServiceServer::new(agent_server_service))
-                        .add_service(AgentClientServiceServer::new(agent_client_service))
-                        .serve(addr)
+                    if let Err(e) = add_services(&mut tuning::configure_server(Server::builder()), agent_server_service, agent_client_service, health_service)
+                        .serve_with_incoming(incoming)
                         .await
                     {
                         error!("gRPC Server 错误: {}", e);
@@ -117,14 +198,14 @@ pub fn start_grpc_server(
         } else {
             info!("gRPC Server 启动: {}", addr);
 
-            if let Err(e) = Server::builder()
-                .add_service(AgentServerServiceServer::new(agent_server_service))
-                .add_service(AgentClientServiceServer::new(agent_client_service))
-                .serve(addr)
+            if let Err(e) = add_services(&mut tuning::configure_server(Server::builder()), agent_server_service, agent_client_service, health_service)
+                .serve_with_incoming(incoming)
                 .await
             {
                 error!("gRPC Server 错误: {}", e);
             }
         }
+
+        health.set_grpc_bound(false);
     })
 }
@@ -0,0 +1,43 @@
+//! 健康检查状态
+//!
+//! 记录各子系统的就绪情况，供 `/healthz`（存活）和 `/readyz`（就绪）探针使用。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Default)]
+pub struct HealthState {
+    grpc_bound: AtomicBool,
+    config_loaded: AtomicBool,
+    draining: AtomicBool,
+}
+
+impl HealthState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_grpc_bound(&self, bound: bool) {
+        self.grpc_bound.store(bound, Ordering::Relaxed);
+    }
+
+    pub fn is_grpc_bound(&self) -> bool {
+        self.grpc_bound.load(Ordering::Relaxed)
+    }
+
+    pub fn set_config_loaded(&self, loaded: bool) {
+        self.config_loaded.store(loaded, Ordering::Relaxed);
+    }
+
+    pub fn is_config_loaded(&self) -> bool {
+        self.config_loaded.load(Ordering::Relaxed)
+    }
+
+    /// 正在排空（即将重启），就绪探针返回不可用
+    pub fn set_draining(&self, draining: bool) {
+        self.draining.store(draining, Ordering::Relaxed);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+}
@@ -8,19 +8,39 @@ pub struct Claims {
     pub sub: i64,    // user id
     pub username: String,
     pub is_admin: bool,
+    /// 所属租户（旧令牌没有该字段，视为不属于任何租户）
+    #[serde(default)]
+    pub tenant_id: Option<i64>,
+    #[serde(default)]
+    pub is_tenant_admin: bool,
+    /// 管理员模拟该用户登录时签发的令牌中记录发起的管理员
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonator: Option<Impersonator>,
     pub exp: i64,    // expiration time
     pub iat: i64,    // issued at
 }
 
+/// 模拟登录的发起者
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Impersonator {
+    pub id: i64,
+    pub username: String,
+    /// 对应的审计记录 ID
+    pub log_id: i64,
+}
+
 /// Generate a JWT token for a user
-pub fn generate_token(user_id: i64, username: &str, is_admin: bool, jwt_secret: &str, expiration_hours: i64) -> Result<String> {
+pub fn generate_token(user: &crate::entity::user::Model, jwt_secret: &str, expiration_hours: i64) -> Result<String> {
     let now = Utc::now();
     let expiration = now + Duration::hours(expiration_hours);
 
     let claims = Claims {
-        sub: user_id,
-        username: username.to_string(),
-        is_admin,
+        sub: user.id,
+        username: user.username.clone(),
+        is_admin: user.is_admin,
+        tenant_id: user.tenant_id,
+        is_tenant_admin: user.is_tenant_admin,
+        impersonator: None,
         iat: now.timestamp(),
         exp: expiration.timestamp(),
     };
@@ -33,6 +53,32 @@ pub fn generate_token(user_id: i64, username: &str, is_admin: bool, jwt_secret:
     .map_err(|e| anyhow!("Failed to generate token: {}", e))
 }
 
+/// Generate a short-lived JWT token that lets an admin act as `user`
+pub fn generate_impersonation_token(
+    user: &crate::entity::user::Model,
+    impersonator: Impersonator,
+    jwt_secret: &str,
+    expires_at: chrono::DateTime<Utc>,
+) -> Result<String> {
+    let claims = Claims {
+        sub: user.id,
+        username: user.username.clone(),
+        is_admin: user.is_admin,
+        tenant_id: user.tenant_id,
+        is_tenant_admin: user.is_tenant_admin,
+        impersonator: Some(impersonator),
+        iat: Utc::now().timestamp(),
+        exp: expires_at.timestamp(),
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(jwt_secret.as_ref()),
+    )
+    .map_err(|e| anyhow!("Failed to generate token: {}", e))
+}
+
 /// Verify and decode a JWT token
 pub fn verify_token(token: &str, jwt_secret: &str) -> Result<Claims> {
     decode::<Claims>(
@@ -0,0 +1,91 @@
+//! 代理实时速率
+//!
+//! 节点每 5 秒通过心跳上报各代理的当前速率（节点侧按 5 秒窗口做 EWMA 平滑后的字节/秒），
+//! 这里按节点保存最近一次上报，供代理和客户端列表展示。数据只保存在内存中，
+//! 节点断开或超过 20 秒没有上报时视为没有流量。心跳同时携带各代理的访客连接错误率，供告警规则使用。
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use common::grpc::oxiproxy;
+
+/// 上报超过该时长未更新时视为过期
+const STALE_AFTER: Duration = Duration::from_secs(20);
+
+/// 当前速率（字节/秒）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Speed {
+    #[serde(rename = "bytesSentPerSec")]
+    pub sent: u64,
+    #[serde(rename = "bytesReceivedPerSec")]
+    pub received: u64,
+}
+
+struct NodeSpeeds {
+    at: Instant,
+    proxies: Vec<oxiproxy::ProxySpeed>,
+}
+
+fn store() -> &'static RwLock<HashMap<i64, NodeSpeeds>> {
+    static STORE: OnceLock<RwLock<HashMap<i64, NodeSpeeds>>> = OnceLock::new();
+    STORE.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// 保存节点心跳中的速率（整体替换该节点上次的上报）
+pub fn update(node_id: i64, proxies: Vec<oxiproxy::ProxySpeed>) {
+    let mut store = store().write().unwrap();
+    if proxies.is_empty() {
+        store.remove(&node_id);
+    } else {
+        store.insert(node_id, NodeSpeeds { at: Instant::now(), proxies });
+    }
+}
+
+/// 节点断开时清除其速率
+pub fn remove(node_id: i64) {
+    store().write().unwrap().remove(&node_id);
+}
+
+/// 遍历所有未过期的代理速率
+fn for_each_fresh(mut f: impl FnMut(&oxiproxy::ProxySpeed)) {
+    let now = Instant::now();
+    let store = store().read().unwrap();
+    for node in store.values().filter(|n| now.duration_since(n.at) < STALE_AFTER) {
+        node.proxies.iter().for_each(&mut f);
+    }
+}
+
+/// 各代理的当前速率
+pub fn proxy_speeds() -> HashMap<i64, Speed> {
+    let mut speeds: HashMap<i64, Speed> = HashMap::new();
+    for_each_fresh(|p| {
+        let speed = speeds.entry(p.proxy_id).or_default();
+        speed.sent += p.bytes_sent_per_sec;
+        speed.received += p.bytes_received_per_sec;
+    });
+    speeds
+}
+
+/// 各客户端的当前速率（名下所有代理之和）
+pub fn client_speeds() -> HashMap<i64, Speed> {
+    let mut speeds: HashMap<i64, Speed> = HashMap::new();
+    for_each_fresh(|p| {
+        let speed = speeds.entry(p.client_id).or_default();
+        speed.sent += p.bytes_sent_per_sec;
+        speed.received += p.bytes_received_per_sec;
+    });
+    speeds
+}
+
+/// 各代理的访客连接错误率（次/分钟）
+pub fn proxy_error_rates() -> HashMap<i64, f64> {
+    let mut rates: HashMap<i64, f64> = HashMap::new();
+    for_each_fresh(|p| {
+        if p.errors_per_min > 0.0 {
+            *rates.entry(p.proxy_id).or_default() += p.errors_per_min;
+        }
+    });
+    rates
+}
@@ -8,7 +8,8 @@ use tracing::debug;
 use common::protocol::auth::{
     ClientAuthProvider, TrafficLimitResponse, ValidateTokenResponse,
 };
-use common::protocol::control::ProxyConfig;
+use common::protocol::control::{ProxyConfig, ProxyPriority};
+use common::protocol::handshake::AuthProof;
 
 use crate::entity::{Client, Proxy, User, client, proxy};
 use crate::migration::get_connection;
@@ -32,45 +33,26 @@ impl ClientAuthProvider for LocalControllerAuthProvider {
             .await?
         {
             Some(c) => c,
-            None => {
-                return Ok(ValidateTokenResponse {
-                    client_id: 0,
-                    client_name: String::new(),
-                    allowed: false,
-                    reject_reason: Some("无效的 token".to_string()),
-                });
-            }
+            None => return Ok(rejected("无效的 token")),
         };
 
-        let client_id = client.id;
-        let client_name = client.name.clone();
+        check_client(client).await
+    }
 
-        // 检查流量限制（通过 client.user_id → User）
-        if let Some(user_id) = client.user_id {
-            if let Ok(Some(user)) = User::find_by_id(user_id).one(db).await {
-                if user.is_traffic_exceeded {
-                    return Ok(ValidateTokenResponse {
-                        client_id,
-                        client_name,
-                        allowed: false,
-                        reject_reason: Some(format!(
-                            "用户 {} (#{}) 流量已超限",
-                            user.username, user.id
-                        )),
-                    });
-                }
-            }
-        }
+    async fn validate_proof(&self, proof: &AuthProof) -> Result<ValidateTokenResponse> {
+        let db = get_connection().await;
 
-        Ok(ValidateTokenResponse {
-            client_id,
-            client_name,
-            allowed: true,
-            reject_reason: None,
-        })
+        // 客户端不存在与应答错误返回相同的原因，避免探测客户端 ID
+        let client = match Client::find_by_id(proof.client_id).one(db).await? {
+            Some(c) if proof.verify(&c.token) => c,
+            _ => return Ok(rejected("认证失败")),
+        };
+
+        check_client(client).await
     }
 
     async fn set_client_online(&self, client_id: i64, online: bool) -> Result<()> {
+        crate::online_status::set_client(client_id, online).await;
         let db = get_connection().await;
         if let Some(client) = Client::find_by_id(client_id).one(db).await? {
             let mut client_active: client::ActiveModel = client.into();
@@ -125,7 +107,7 @@ impl ClientAuthProvider for LocalControllerAuthProvider {
             .all(db)
             .await?;
 
-        Ok(proxies
+        let mut configs: Vec<ProxyConfig> = proxies
             .into_iter()
             .map(|p| ProxyConfig {
                 proxy_id: p.id,
@@ -136,7 +118,90 @@ impl ClientAuthProvider for LocalControllerAuthProvider {
                 local_port: p.local_port,
                 remote_port: p.remote_port,
                 enabled: p.enabled,
+                idle_timeout: p.idle_timeout.map(|t| t.max(0) as u32),
+                mitigation: crate::mitigation::to_rule(p.mitigation_config.as_deref()),
+                bind_ip: p.bind_ip,
+                bandwidth_weight: p.bandwidth_weight.map(|w| w.max(1) as u32),
+                priority: p.priority.as_deref().and_then(ProxyPriority::parse),
             })
-            .collect())
+            .collect();
+
+        // 仍在有效期内的临时隧道
+        configs.extend(
+            crate::temporary_tunnel::active_tunnels(client_id, None, db)
+                .await?
+                .into_iter()
+                .map(|t| ProxyConfig {
+                    proxy_id: crate::temporary_tunnel::proxy_id(t.id),
+                    client_id: client_id_str.clone(),
+                    name: format!("temporary-{}", t.id),
+                    proxy_type: "tcp".to_string(),
+                    local_ip: t.local_ip,
+                    local_port: t.local_port,
+                    remote_port: t.remote_port,
+                    enabled: true,
+                    idle_timeout: None,
+                    mitigation: None,
+                    bind_ip: None,
+                    bandwidth_weight: None,
+                    priority: None,
+                }),
+        );
+
+        Ok(configs)
+    }
+}
+
+fn rejected(reason: &str) -> ValidateTokenResponse {
+    ValidateTokenResponse {
+        client_id: 0,
+        client_name: String::new(),
+        allowed: false,
+        reject_reason: Some(reason.to_string()),
+        allow_replicas: false,
     }
 }
+
+/// 检查已通过身份校验的客户端是否允许连接（到期、流量超限）
+async fn check_client(client: client::Model) -> Result<ValidateTokenResponse> {
+    let db = get_connection().await;
+    let client_id = client.id;
+    let client_name = client.name.clone();
+    let allow_replicas = crate::duplicate_login::Policy::of(&client) == crate::duplicate_login::Policy::Replicas;
+
+    if crate::expiration::is_expired(client.expires_at) {
+        return Ok(ValidateTokenResponse {
+            client_id,
+            client_name,
+            allowed: false,
+            reject_reason: Some(format!("客户端 #{} 已到期", client_id)),
+            allow_replicas,
+        });
+    }
+
+    // 检查流量限制（通过 client.user_id → User）
+    if let Some(user_id) = client.user_id {
+        if let Ok(Some(user)) = User::find_by_id(user_id).one(db).await {
+            if user.is_traffic_exceeded {
+                return Ok(ValidateTokenResponse {
+                    client_id,
+                    client_name,
+                    allowed: false,
+                    reject_reason: Some(format!(
+                        "用户 {} (#{}) 流量已超限",
+                        user.username, user.id
+                    )),
+                    allow_replicas,
+                });
+            }
+        }
+    }
+
+    Ok(ValidateTokenResponse {
+        client_id,
+        client_name,
+        allowed: true,
+        reject_reason: None,
+        allow_replicas,
+    })
+}
@@ -0,0 +1,86 @@
+//! 邮件通知
+//!
+//! Controller 不直接连接 SMTP 服务器，而是与告警通知一样以 JSON POST 到
+//! `OXIPROXY_MAIL_WEBHOOK_URL`（逗号分隔多个地址，依次尝试直到一个成功），由邮件服务商的
+//! HTTP 接口或自建的转发服务投递：
+//!
+//! ```json
+//! {"type": "email", "to": "user@example.com", "subject": "...", "text": "..."}
+//! ```
+//!
+//! 未配置时邮件功能关闭，需要发送邮件的操作（邮箱验证、找回密码）不可用。
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+/// 投递请求超时
+const MAIL_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize)]
+struct Mail {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    to: String,
+    subject: String,
+    text: String,
+}
+
+fn webhooks() -> &'static [String] {
+    static URLS: OnceLock<Vec<String>> = OnceLock::new();
+    URLS.get_or_init(|| {
+        common::env::var("OXIPROXY_MAIL_WEBHOOK_URL")
+            .map(|v| {
+                v.split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .filter(|url| match crate::outbound::validate_url(url) {
+                        Ok(_) => true,
+                        Err(e) => {
+                            warn!("邮件 Webhook 地址不可用，已忽略: {}", e);
+                            false
+                        }
+                    })
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    })
+}
+
+/// 是否配置了邮件发送
+pub fn enabled() -> bool {
+    !webhooks().is_empty()
+}
+
+/// 后台发送一封纯文本邮件，未配置时忽略
+pub fn send(to: &str, subject: &str, text: String) {
+    let urls = webhooks();
+    if urls.is_empty() {
+        warn!("未配置 OXIPROXY_MAIL_WEBHOOK_URL，邮件「{}」未发送", subject);
+        return;
+    }
+    let mail = Mail { kind: "email", to: to.to_string(), subject: subject.to_string(), text };
+    tokio::spawn(async move {
+        let http = match crate::outbound::client(MAIL_TIMEOUT) {
+            Ok(http) => http,
+            Err(e) => {
+                error!("创建邮件 HTTP 客户端失败: {}", e);
+                return;
+            }
+        };
+        for url in urls {
+            match http.post(url).json(&mail).send().await {
+                Ok(resp) if resp.status().is_success() => {
+                    info!("邮件「{}」已提交到 {}", mail.subject, url);
+                    return;
+                }
+                Ok(resp) => warn!("邮件 Webhook {} 返回状态 {}", url, resp.status()),
+                Err(e) => warn!("发送邮件到 {} 失败: {}", url, e),
+            }
+        }
+        error!("邮件「{}」发送失败，所有邮件 Webhook 均不可用", mail.subject);
+    });
+}
@@ -2,24 +2,72 @@ mod config;
 mod entity;
 mod migration;
 mod auth;
+mod password_policy;
+mod account_token;
+mod api_token;
+mod mailer;
 mod jwt;
 mod middleware;
 mod traffic;
 mod traffic_limiter;
+mod quota;
 mod port_limiter;
 mod node_limiter;
+mod node_clone;
+mod port_probe;
+mod failover;
+mod port_conflict;
 mod subscription_quota;
+mod quota_simulation;
 mod config_manager;
 mod api;
 mod node_manager;
 mod local_auth_provider;
 mod client_stream_manager;
+mod client_log_store;
 mod grpc_agent_server_service;
 mod grpc_agent_client_service;
 mod grpc_server;
 mod geo_ip;
-
-use crate::migration::{get_connection, init_sqlite};
+mod outbound;
+mod admin_cli;
+mod health;
+mod update_rollout;
+mod agent_version;
+mod config_revision;
+mod tls_apply;
+mod restart;
+mod capture;
+mod check;
+mod tenant;
+mod port_blocklist;
+mod temporary_tunnel;
+mod proxy_schedule;
+mod bandwidth_schedule;
+mod expiration;
+mod online_status;
+mod protocol_switch;
+mod agent_session;
+mod session_guard;
+mod accept_limiter;
+mod live_speed;
+mod mitigation;
+mod connection_authz;
+mod alerting;
+mod maintenance;
+mod availability;
+mod retention;
+mod port_reservation;
+mod optimistic_lock;
+mod dns;
+mod cloud_firewall;
+mod duplicate_login;
+mod endpoint;
+mod anycast;
+#[cfg(feature = "graphql")]
+mod graphql;
+
+use crate::migration::{get_connection, init_database};
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, QueryFilter, Set};
@@ -45,7 +93,11 @@ struct Cli {
 #[derive(Subcommand)]
 enum Command {
     /// 前台运行控制器
-    Start,
+    Start {
+        /// Web 管理界面静态文件目录（默认使用内嵌资源，未内嵌时为 ./dist 或程序所在目录下的 dist）
+        #[arg(long, env = "OXIPROXY_UI_DIR")]
+        ui_dir: Option<String>,
+    },
 
     /// 停止运行中的守护进程
     Stop {
@@ -62,6 +114,10 @@ enum Command {
 
     /// 以守护进程模式运行
     Daemon {
+        /// Web 管理界面静态文件目录（默认使用内嵌资源，未内嵌时为 ./dist 或程序所在目录下的 dist）
+        #[arg(long, env = "OXIPROXY_UI_DIR")]
+        ui_dir: Option<String>,
+
         /// PID 文件路径
         #[cfg(unix)]
         #[arg(long, default_value = "/var/run/oxiproxy-controller.pid")]
@@ -85,6 +141,25 @@ enum Command {
 
     /// 更新到最新版本
     Update,
+
+    /// 检查配置（数据库、端口、TLS 证书等），有问题时以非零状态退出，可用于 CI 或 systemd ExecStartPre
+    Check {
+        /// Web 管理界面静态文件目录（与 start 的 --ui-dir 相同）
+        #[arg(long, env = "OXIPROXY_UI_DIR")]
+        ui_dir: Option<String>,
+    },
+
+    /// 管理员运维命令（直接操作本地数据库）
+    Admin {
+        #[command(subcommand)]
+        command: admin_cli::AdminCommand,
+    },
+
+    /// 隧道管理命令（直接操作本地数据库）
+    Proxy {
+        #[command(subcommand)]
+        command: admin_cli::ProxyCommand,
+    },
 }
 
 /// 应用状态
@@ -96,6 +171,9 @@ pub struct AppState {
     pub config_manager: Arc<config_manager::ConfigManager>,
     pub client_stream_manager: Arc<client_stream_manager::ClientStreamManager>,
     pub config: Arc<config::Config>,
+    pub health: Arc<health::HealthState>,
+    pub update_rollout: Arc<update_rollout::UpdateRolloutManager>,
+    pub tls_apply: Arc<tls_apply::TlsApplyManager>,
 }
 
 // ─── Unix 入口 ───────────────────────────────────────────
@@ -107,9 +185,9 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Command::Start => {
+        Command::Start { ui_dir } => {
             let runtime = tokio::runtime::Runtime::new()?;
-            runtime.block_on(run_controller(None))?;
+            runtime.block_on(run_controller(None, ui_dir))?;
         }
 
         Command::Stop { pid_file } => {
@@ -117,6 +195,7 @@ fn main() -> Result<()> {
         }
 
         Command::Daemon {
+            ui_dir,
             pid_file,
             log_dir,
         } => {
@@ -150,12 +229,27 @@ fn main() -> Result<()> {
 
             // fork 完成后再创建 tokio runtime，确保 epoll fd 和线程池状态正确
             let runtime = tokio::runtime::Runtime::new()?;
-            runtime.block_on(run_controller(Some(log_dir)))?;
+            runtime.block_on(run_controller(Some(log_dir), ui_dir))?;
         }
 
         Command::Update => {
             update_binary()?;
         }
+
+        Command::Check { ui_dir } => {
+            let runtime = tokio::runtime::Runtime::new()?;
+            runtime.block_on(check::run(ui_dir))?;
+        }
+
+        Command::Admin { command } => {
+            let runtime = tokio::runtime::Runtime::new()?;
+            runtime.block_on(admin_cli::run_admin_command(command))?;
+        }
+
+        Command::Proxy { command } => {
+            let runtime = tokio::runtime::Runtime::new()?;
+            runtime.block_on(admin_cli::run_proxy_command(command))?;
+        }
     }
 
     Ok(())
@@ -193,24 +287,40 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Command::Start => {
+        Command::Start { ui_dir } => {
             let runtime = tokio::runtime::Runtime::new()?;
-            runtime.block_on(async { run_controller(None).await })
+            runtime.block_on(async { run_controller(None, ui_dir).await })
         }
 
         Command::Stop { pid_file } => stop_daemon_windows(&pid_file),
 
         Command::Daemon {
+            ui_dir,
             pid_file,
             log_dir,
-        } => start_daemon_windows(&pid_file, &log_dir),
+        } => start_daemon_windows(ui_dir.as_deref(), &pid_file, &log_dir),
 
         Command::Update => update_binary(),
+
+        Command::Check { ui_dir } => {
+            let runtime = tokio::runtime::Runtime::new()?;
+            runtime.block_on(check::run(ui_dir))
+        }
+
+        Command::Admin { command } => {
+            let runtime = tokio::runtime::Runtime::new()?;
+            runtime.block_on(admin_cli::run_admin_command(command))
+        }
+
+        Command::Proxy { command } => {
+            let runtime = tokio::runtime::Runtime::new()?;
+            runtime.block_on(admin_cli::run_proxy_command(command))
+        }
     }
 }
 
 #[cfg(windows)]
-fn start_daemon_windows(pid_file: &str, log_dir: &str) -> Result<()> {
+fn start_daemon_windows(ui_dir: Option<&str>, pid_file: &str, log_dir: &str) -> Result<()> {
     use std::os::windows::process::CommandExt;
 
     const DETACHED_PROCESS: u32 = 0x00000008;
@@ -226,8 +336,12 @@ fn start_daemon_windows(pid_file: &str, log_dir: &str) -> Result<()> {
         .map_err(|e| anyhow::anyhow!("无法创建错误日志文件: {}", e))?;
 
     let exe = std::env::current_exe()?;
+    let mut args = vec!["start"];
+    if let Some(dir) = ui_dir {
+        args.extend(["--ui-dir", dir]);
+    }
     let child = std::process::Command::new(&exe)
-        .args(["start"])
+        .args(&args)
         .stdout(stdout)
         .stderr(stderr)
         .creation_flags(DETACHED_PROCESS | CREATE_NO_WINDOW)
@@ -295,6 +409,7 @@ fn update_binary() -> Result<()> {
         .bin_name("controller")
         .identifier("controller")
         .bin_path_in_archive("{bin}{bin_ext}")
+        .verifying_keys(common::update::verifying_keys()?)
         .show_download_progress(true)
         .current_version(env!("CARGO_PKG_VERSION"))
         .no_confirm(true)
@@ -315,7 +430,7 @@ fn update_binary() -> Result<()> {
 }
 
 /// 运行控制器主逻辑
-async fn run_controller(log_dir: Option<String>) -> Result<()> {
+async fn run_controller(log_dir: Option<String>, ui_dir: Option<String>) -> Result<()> {
     // 安装 rustls CryptoProvider（TLS 需要）
     let _ = rustls::crypto::ring::default_provider().install_default();
 
@@ -337,26 +452,35 @@ async fn run_controller(log_dir: Option<String>) -> Result<()> {
             .init();
     }
 
+    // 初始化数据库（配置可能从数据库读取，需先连接）
+    let db = init_database().await.map_err(|e| anyhow::anyhow!("连接数据库失败: {}", e))?;
+
     // 读取配置
     let config = get_config().await;
     info!("📋 controller 启动");
     info!("🌐 Web管理端口: {}", config.web_port);
     info!("🔗 内部API端口: {}", config.internal_port);
 
-    // 初始化数据库
-    let db = init_sqlite().await;
     // 运行数据库迁移
-    migration::Migrator::up(&db, None).await?;
+    migration::Migrator::up(db, None).await?;
     info!("✅ 数据库初始化完成");
 
     // 初始化 admin 用户（如果不存在）
     initialize_admin_user().await;
 
+    // 健康检查状态
+    let health = Arc::new(health::HealthState::new());
+
+    // 恢复重启前未确认的 TLS 变更
+    tls_apply::recover_pending_on_startup().await;
+
     // 初始化配置管理器
     let config_manager = Arc::new(config_manager::ConfigManager::new());
-    if let Err(e) = config_manager.load_from_db().await {
-        tracing::error!("加载系统配置失败: {}", e);
+    match config_manager.load_from_db().await {
+        Ok(_) => health.set_config_loaded(true),
+        Err(e) => tracing::error!("加载系统配置失败: {}", e),
     }
+    restart::record_boot(&config_manager).await;
 
     // 创建多节点管理器
     let node_manager = Arc::new(node_manager::NodeManager::new());
@@ -375,7 +499,21 @@ async fn run_controller(log_dir: Option<String>) -> Result<()> {
     // 创建 Agent Client 流管理器
     let client_stream_manager = Arc::new(client_stream_manager::ClientStreamManager::new());
 
-    let config_arc = Arc::new(config.clone());
+    // 创建软件更新发布编排器
+    let rollout_manager = Arc::new(update_rollout::UpdateRolloutManager::new(
+        node_manager.clone(),
+        client_stream_manager.clone(),
+    ));
+
+    // 创建 TLS 两阶段应用管理器
+    let tls_apply_manager = Arc::new(tls_apply::TlsApplyManager::new(config_manager.clone()));
+
+    // 命令行参数优先于数据库 / 配置文件中的 ui_dir
+    let mut config = config.clone();
+    if ui_dir.is_some() {
+        config.ui_dir = ui_dir;
+    }
+    let config_arc = Arc::new(config);
 
     // 创建应用状态
     let app_state = AppState {
@@ -385,19 +523,40 @@ async fn run_controller(log_dir: Option<String>) -> Result<()> {
         config_manager: config_manager.clone(),
         client_stream_manager: client_stream_manager.clone(),
         config: config_arc.clone(),
+        health: health.clone(),
+        update_rollout: rollout_manager.clone(),
+        tls_apply: tls_apply_manager,
     };
 
+    // 启动 DNS 自动发布（需在 Web API 之前，创建代理时校验 DNS 名称）
+    dns::start_dns_sync();
+
+    // 启动云服务商防火墙同步
+    cloud_firewall::start_firewall_sync();
+
     // 启动 Web API 服务
     let _web_handle = api::start_web_server(app_state.clone());
 
     // 启动 gRPC Server（供 Agent Server 和 Agent Client 连接）
+    common::grpc::tuning::set(config_arc.grpc.clone());
     let _grpc_handle = grpc_server::start_grpc_server(
-        config.internal_port,
+        config_arc.internal_port,
         node_manager.clone(),
         client_stream_manager.clone(),
         config_manager.clone(),
+        health.clone(),
     );
 
+    // 加载上次记录的在线状态，健康监控只写回发生变化的记录
+    if let Err(e) = online_status::load(get_connection().await).await {
+        tracing::warn!("加载在线状态缓存失败: {}", e);
+    }
+
+    // 加载维护窗口
+    if let Err(e) = maintenance::reload(get_connection().await).await {
+        tracing::warn!("加载维护窗口失败: {}", e);
+    }
+
     // 启动节点健康监控
     start_node_health_monitor(node_manager.clone());
 
@@ -407,29 +566,67 @@ async fn run_controller(log_dir: Option<String>) -> Result<()> {
     // 启动订阅过期检查
     start_subscription_expiry_monitor();
 
+    // 启动软件更新发布调和
+    update_rollout::start_update_rollout_monitor(rollout_manager.clone());
+
+    // 启动临时隧道到期检查
+    temporary_tunnel::start_temporary_tunnel_reaper(node_manager.clone(), client_stream_manager.clone());
+
+    // 启动代理定时启停
+    proxy_schedule::start_proxy_scheduler(proxy_control.clone(), client_stream_manager.clone());
+
+    // 启动带宽时间表
+    bandwidth_schedule::start_bandwidth_scheduler(node_manager.clone());
+
+    // 启动代理 / 客户端到期与闲置检查
+    expiration::start_expiration_monitor(proxy_control.clone(), client_stream_manager.clone());
+
+    // 启动代理数量上限对账
+    node_limiter::start_proxy_cap_reconciler(proxy_control.clone(), client_stream_manager.clone(), config_manager.clone());
+
+    // 启动备用节点故障转移监控
+    failover::start_failover_monitor(node_manager.clone(), client_stream_manager.clone(), config_manager.clone());
+
+    // 启动流量上报 ID 清理
+    traffic::start_report_pruner();
+
+    // 启动配额对账
+    quota::start_quota_reconciler();
+
+    // 启动告警规则求值
+    alerting::start_alert_evaluator();
+
+    // 启动历史数据保留与降采样
+    retention::start_retention_job();
+
+    // 启动抓包文件到期清理
+    capture::start_cleanup_job();
+
+    // 通过 API 发起的重启在新进程就绪后记为完成
+    restart::mark_completed(get_connection().await).await;
+
     // 等待终止信号
     info!("✅ 所有服务已启动，等待终止信号...");
 
     tokio::select! {
-        _ = tokio::signal::ctrl_c() => {
-            info!("收到 Ctrl+C 信号，正在关闭服务...");
-        }
-        _ = async {
-            #[cfg(unix)]
-            {
-                use tokio::signal::unix::{signal, SignalKind};
-                let mut sigterm = signal(SignalKind::terminate()).expect("failed to listen for SIGTERM");
-                sigterm.recv().await;
-            }
-            #[cfg(not(unix))]
-            {
-                std::future::pending::<()>().await;
-            }
-        } => {
-            info!("收到 SIGTERM 信号，正在关闭服务...");
+        signal = common::health::shutdown_signal() => info!("收到 {} 信号，正在关闭服务...", signal),
+        _ = restart::requested() => {
+            // 节点和客户端已在请求重启时收到错开重连的通知
+            info!("收到重启请求，停止接收新流量...");
+            restart::drain_and_respawn().await;
         }
     }
 
+    // 通知已连接的节点和客户端错开重连，避免重启后同时涌入
+    let spread = restart::reconnect_spread();
+    let nodes = node_manager.broadcast_reconnect_hint(spread).await;
+    let clients = client_stream_manager.broadcast_reconnect_hint(spread).await;
+    if nodes + clients > 0 {
+        info!("已通知 {} 个节点、{} 个客户端在 {} 秒内错开重连", nodes, clients, spread.as_secs());
+        // 留出时间把消息发送出去
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
     Ok(())
 }
 
@@ -442,25 +639,7 @@ fn start_node_health_monitor(node_manager: Arc<node_manager::NodeManager>) {
             interval.tick().await;
 
             let results = node_manager.check_all_nodes().await;
-            let db = get_connection().await;
-
-            for (node_id, is_online) in results {
-                if let Ok(Some(node)) = entity::Node::find_by_id(node_id).one(db).await {
-                    let was_online = node.is_online;
-                    if was_online != is_online {
-                        if is_online {
-                            info!("节点 #{} ({}) 已上线", node_id, node.name);
-                        } else {
-                            tracing::warn!("节点 #{} ({}) 已离线", node_id, node.name);
-                        }
-                    }
-
-                    let mut active: entity::node::ActiveModel = node.into();
-                    active.is_online = Set(is_online);
-                    active.updated_at = Set(Utc::now().naive_utc());
-                    let _ = active.update(db).await;
-                }
-            }
+            online_status::sync_nodes(&results, get_connection().await).await;
         }
     });
 }
@@ -474,25 +653,7 @@ fn start_client_health_monitor(client_stream_manager: Arc<client_stream_manager:
             interval.tick().await;
 
             let results = client_stream_manager.check_all_clients().await;
-            let db = get_connection().await;
-
-            for (client_id, is_online) in results {
-                if let Ok(Some(client)) = entity::Client::find_by_id(client_id).one(db).await {
-                    let was_online = client.is_online;
-                    if was_online != is_online {
-                        if is_online {
-                            info!("客户端 #{} ({}) 已上线", client_id, client.name);
-                        } else {
-                            tracing::warn!("客户端 #{} ({}) 已离线", client_id, client.name);
-                        }
-                    }
-
-                    let mut active: entity::client::ActiveModel = client.into();
-                    active.is_online = Set(is_online);
-                    active.updated_at = Set(Utc::now().naive_utc());
-                    let _ = active.update(db).await;
-                }
-            }
+            online_status::sync_clients(&results, get_connection().await).await;
         }
     });
 }
@@ -537,8 +698,10 @@ async fn initialize_admin_user() {
             info!("🔐 Admin 用户已存在");
         }
         Ok(None) => {
-            // 生成随机密码
-            let password = auth::generate_random_password(16);
+            // 优先使用环境变量指定的初始密码（无状态部署时不落盘），否则随机生成
+            let env_password = common::env::var("OXIPROXY_ADMIN_PASSWORD");
+            let password_from_env = env_password.is_some();
+            let password = env_password.unwrap_or_else(|| auth::generate_random_password(16));
             let password_hash = match auth::hash_password(&password) {
                 Ok(hash) => hash,
                 Err(e) => {
@@ -563,11 +726,22 @@ async fn initialize_admin_user() {
                 allowed_port_range: Set(None),
                 max_node_count: Set(None),
                 max_client_count: Set(None),
+                speed_limit: Set(None),
+                speed_limit_schedule: Set(None),
+                tenant_id: Set(None),
+                is_tenant_admin: Set(false),
+                display_name: Set(None),
+                email: Set(None),
+                email_verified_at: Set(None),
+                lock_version: Set(0),
                 created_at: Set(now),
                 updated_at: Set(now),
             };
 
             match admin_user.insert(db).await {
+                Ok(_) if password_from_env => {
+                    info!("🔐 Admin 用户已创建（密码来自 OXIPROXY_ADMIN_PASSWORD）");
+                }
                 Ok(_) => {
                     info!("🔐 Admin 用户已创建");
                     info!("═══════════════════════════════════════════════════════════════");
@@ -0,0 +1,127 @@
+//! 维护窗口
+//!
+//! 管理员为节点、客户端或代理登记计划内的维护时间段。窗口生效期间：
+//!
+//! - 对象离线只记录为维护中，不按异常离线告警；
+//! - 告警规则跳过该对象（已触发的告警保持原状，窗口结束后继续求值）；
+//! - 列表接口返回 `inMaintenance: true`，管理界面显示"维护中"。
+//!
+//! 节点和客户端的窗口同时覆盖其下的代理。未结束的窗口缓存在内存中，增删后重新加载。
+
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use std::sync::{OnceLock, RwLock};
+
+use crate::entity::maintenance_window::{self, SCOPE_CLIENT, SCOPE_NODE, SCOPE_PROXY};
+use crate::entity::{proxy, MaintenanceWindow};
+
+/// 校验窗口参数
+pub fn validate_window(scope_type: &str, starts_at: NaiveDateTime, ends_at: NaiveDateTime) -> Result<(), String> {
+    if ![SCOPE_NODE, SCOPE_CLIENT, SCOPE_PROXY].contains(&scope_type) {
+        return Err(format!("无效的维护范围: {}（可选 node / client / proxy）", scope_type));
+    }
+    if ends_at <= starts_at {
+        return Err("结束时间须晚于开始时间".to_string());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+struct Window {
+    scope_type: String,
+    scope_id: i64,
+    starts_at: NaiveDateTime,
+    ends_at: NaiveDateTime,
+}
+
+/// 未结束的维护窗口
+#[derive(Default)]
+pub struct Windows {
+    windows: RwLock<Vec<Window>>,
+}
+
+impl Windows {
+    fn replace(&self, windows: Vec<Window>) {
+        *self.windows.write().unwrap() = windows;
+    }
+
+    /// 对象在 `at` 时是否处于维护窗口中
+    fn covers_at(&self, scope_type: &str, scope_id: i64, at: NaiveDateTime) -> bool {
+        self.windows
+            .read()
+            .unwrap()
+            .iter()
+            .any(|w| w.scope_type == scope_type && w.scope_id == scope_id && w.starts_at <= at && at < w.ends_at)
+    }
+
+    /// 对象当前是否处于维护中
+    pub fn covers(&self, scope_type: &str, scope_id: i64) -> bool {
+        self.covers_at(scope_type, scope_id, Utc::now().naive_utc())
+    }
+
+    pub fn node(&self, id: i64) -> bool {
+        self.covers(SCOPE_NODE, id)
+    }
+
+    pub fn client(&self, id: i64) -> bool {
+        self.covers(SCOPE_CLIENT, id)
+    }
+
+    /// 代理本身、所属客户端或所在节点处于维护中
+    pub fn proxy(&self, proxy: &proxy::Model) -> bool {
+        self.covers(SCOPE_PROXY, proxy.id)
+            || proxy.client_id.parse::<i64>().is_ok_and(|id| self.client(id))
+            || proxy.node_id.is_some_and(|id| self.node(id))
+    }
+}
+
+pub fn global() -> &'static Windows {
+    static WINDOWS: OnceLock<Windows> = OnceLock::new();
+    WINDOWS.get_or_init(Windows::default)
+}
+
+/// 从数据库重新加载未结束的窗口（启动时和增删窗口后调用）
+pub async fn reload(db: &DatabaseConnection) -> anyhow::Result<()> {
+    let windows = MaintenanceWindow::find()
+        .filter(maintenance_window::Column::EndsAt.gt(Utc::now().naive_utc()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|w| Window { scope_type: w.scope_type, scope_id: w.scope_id, starts_at: w.starts_at, ends_at: w.ends_at })
+        .collect();
+    global().replace(windows);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_covers_only_inside_window() {
+        let now = Utc::now().naive_utc();
+        let windows = Windows::default();
+        windows.replace(vec![Window {
+            scope_type: SCOPE_NODE.to_string(),
+            scope_id: 1,
+            starts_at: now,
+            ends_at: now + Duration::hours(1),
+        }]);
+
+        assert!(windows.covers_at(SCOPE_NODE, 1, now));
+        assert!(windows.covers_at(SCOPE_NODE, 1, now + Duration::minutes(59)));
+        assert!(!windows.covers_at(SCOPE_NODE, 1, now + Duration::hours(1)));
+        assert!(!windows.covers_at(SCOPE_NODE, 1, now - Duration::seconds(1)));
+        assert!(!windows.covers_at(SCOPE_NODE, 2, now));
+        assert!(!windows.covers_at(SCOPE_CLIENT, 1, now));
+    }
+
+    #[test]
+    fn test_validate_window() {
+        let now = Utc::now().naive_utc();
+        assert!(validate_window(SCOPE_PROXY, now, now + Duration::minutes(30)).is_ok());
+        assert!(validate_window(SCOPE_PROXY, now, now).is_err());
+        assert!(validate_window("user", now, now + Duration::minutes(30)).is_err());
+    }
+}
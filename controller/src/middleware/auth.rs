@@ -1,12 +1,15 @@
 use axum::{
     extract::{Request, Extension},
-    http::{HeaderMap, StatusCode},
+    http::{HeaderMap, Method, StatusCode},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Json, Response},
 };
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
 
-use crate::{jwt, AppState};
+use crate::api::handlers::ApiResponse;
+use crate::migration::get_connection;
+use crate::{api_token, jwt, session_guard, AppState};
 
 /// Current authenticated user information extracted from JWT
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -14,6 +17,10 @@ pub struct AuthUser {
     pub id: i64,
     pub username: String,
     pub is_admin: bool,
+    pub tenant_id: Option<i64>,
+    pub is_tenant_admin: bool,
+    /// 管理员模拟登录时为发起的管理员，此时只允许只读请求
+    pub impersonator: Option<jwt::Impersonator>,
 }
 
 /// Extract bearer token from Authorization header
@@ -31,18 +38,17 @@ fn extract_bearer_token(headers: &HeaderMap) -> Result<String, StatusCode> {
     Ok(auth_header[7..].to_string())
 }
 
-impl AuthUser {
-    /// Create AuthUser from headers
-    pub fn from_headers(headers: &HeaderMap, jwt_secret: &str) -> Result<Self, StatusCode> {
-        let token = extract_bearer_token(headers)?;
-        let claims = jwt::verify_token(&token, jwt_secret)
-            .map_err(|_| StatusCode::UNAUTHORIZED)?;
-
-        Ok(AuthUser {
+impl From<jwt::Claims> for AuthUser {
+    fn from(claims: jwt::Claims) -> Self {
+        AuthUser {
             id: claims.sub,
             username: claims.username,
-            is_admin: claims.is_admin,
-        })
+            // 租户内的用户不具备平台管理员权限
+            is_admin: claims.is_admin && claims.tenant_id.is_none(),
+            tenant_id: claims.tenant_id,
+            is_tenant_admin: claims.is_tenant_admin,
+            impersonator: claims.impersonator,
+        }
     }
 }
 
@@ -53,8 +59,83 @@ pub async fn auth_middleware(
     next: Next,
 ) -> Response {
     let jwt_secret = app_state.config.get_jwt_secret().unwrap_or_default();
-    let auth_user = AuthUser::from_headers(request.headers(), &jwt_secret).ok();
+    let claims = extract_bearer_token(request.headers())
+        .ok()
+        .and_then(|token| jwt::verify_token(&token, &jwt_secret).ok());
+
+    // 长时间未使用的会话需要重新登录
+    if let (Some(claims), Some(key)) = (claims.as_ref(), session_guard::session_key(request.headers())) {
+        let idle_minutes = app_state.config_manager.get_number("session_idle_timeout_minutes", 60).await;
+        let idle_timeout = (idle_minutes > 0).then(|| Duration::from_secs(idle_minutes as u64 * 60));
+        if !session_guard::touch(key, claims.exp, idle_timeout).await {
+            return (
+                StatusCode::UNAUTHORIZED,
+                ApiResponse::<()>::error("会话长时间未操作已失效，请重新登录".to_string()),
+            )
+                .into_response();
+        }
+    }
+    let mut auth_user = claims.map(AuthUser::from);
+
+    // 没有有效的登录令牌时尝试 API 令牌（不受会话空闲超时限制，可随时吊销）
+    if auth_user.is_none() {
+        if let Some(token) = api_token::from_headers(request.headers()).map(str::to_string) {
+            auth_user = api_token::authenticate(&token, get_connection().await).await;
+            if auth_user.is_none() {
+                return (
+                    StatusCode::UNAUTHORIZED,
+                    ApiResponse::<()>::error("API 令牌无效、已过期或已吊销".to_string()),
+                )
+                    .into_response();
+            }
+        }
+    }
+
+    // 模拟登录只用于查看用户看到的界面，拒绝一切修改操作
+    if let Some(impersonator) = auth_user.as_ref().and_then(|u| u.impersonator.as_ref()) {
+        if !is_read_only(&request) {
+            tracing::warn!(
+                "管理员 {} 模拟登录期间尝试修改操作 {} {}，已拒绝",
+                impersonator.username,
+                request.method(),
+                request.uri().path()
+            );
+            return (
+                StatusCode::FORBIDDEN,
+                ApiResponse::<()>::error("模拟登录为只读模式，请退出模拟后再修改".to_string()),
+            )
+                .into_response();
+        }
+    }
+
     let mut request = request;
     request.extensions_mut().insert(auth_user);
     next.run(request).await
 }
+
+/// 只读请求：GET / HEAD / OPTIONS，以及只支持查询的 GraphQL 接口
+fn is_read_only(request: &Request) -> bool {
+    matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS)
+        || request.uri().path().ends_with("/graphql")
+}
+
+/// 敏感操作要求当前会话在有效期内通过二次验证（`POST /api/auth/reauth`），
+/// 否则返回 403，响应的 `data.reauthRequired` 为 true，前端据此提示重新输入密码后重试。
+/// 未登录的请求交给处理函数返回 401。
+pub async fn require_reauth(request: Request, next: Next) -> Response {
+    let authenticated = request.extensions().get::<Option<AuthUser>>().is_some_and(Option::is_some);
+    let elevated = session_guard::session_key(request.headers()).is_some_and(|key| session_guard::is_elevated(&key));
+    if authenticated && !elevated {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(ApiResponse {
+                success: false,
+                data: Some(serde_json::json!({ "reauthRequired": true })),
+                message: "该操作需要重新验证身份".to_string(),
+                total: None,
+            }),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
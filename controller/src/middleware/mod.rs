@@ -1,3 +1,3 @@
 pub mod auth;
 
-pub use auth::{auth_middleware, AuthUser};
+pub use auth::{auth_middleware, require_reauth, AuthUser};
@@ -1,4 +1,5 @@
 use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DatabaseBackend;
 use sea_orm_migration::schema::*;
 
 #[derive(DeriveMigrationName)]
@@ -68,33 +69,34 @@ impl MigrationTrait for Migration {
             .await?;
 
         // 创建 proxy 表
-        manager
-            .create_table(
-                Table::create()
-                    .table(Proxy::Table)
-                    .if_not_exists()
-                    .col(big_integer(Proxy::Id).auto_increment().primary_key())
-                    .col(string(Proxy::Name))
-                    .col(string(Proxy::ProxyType))
-                    .col(string(Proxy::LocalIp))
-                    .col(integer(Proxy::LocalPort))
-                    .col(integer(Proxy::RemotePort))
-                    .col(boolean(Proxy::Enabled))
-                    .col(big_integer(Proxy::TotalBytesSent).default(0))
-                    .col(big_integer(Proxy::TotalBytesReceived).default(0))
-                    .col(timestamp(Proxy::CreatedAt))
-                    .col(timestamp(Proxy::UpdatedAt))
-                    .col(string(Proxy::ClientId))
-                    .foreign_key(
-                        ForeignKey::create()
-                            .name("fk_proxy_client")
-                            .from(Proxy::Table, Proxy::ClientId)
-                            .to(Client::Table, Client::Id)
-                            .on_delete(ForeignKeyAction::Cascade),
-                    )
-                    .to_owned(),
-            )
-            .await?;
+        let mut proxy_table = Table::create();
+        proxy_table
+            .table(Proxy::Table)
+            .if_not_exists()
+            .col(big_integer(Proxy::Id).auto_increment().primary_key())
+            .col(string(Proxy::Name))
+            .col(string(Proxy::ProxyType))
+            .col(string(Proxy::LocalIp))
+            .col(integer(Proxy::LocalPort))
+            .col(integer(Proxy::RemotePort))
+            .col(boolean(Proxy::Enabled))
+            .col(big_integer(Proxy::TotalBytesSent).default(0))
+            .col(big_integer(Proxy::TotalBytesReceived).default(0))
+            .col(timestamp(Proxy::CreatedAt))
+            .col(timestamp(Proxy::UpdatedAt))
+            .col(string(Proxy::ClientId));
+        // client_id 以字符串保存，SQLite 允许外键两端类型不同，PostgreSQL / MySQL 不允许，
+        // 删除客户端时由代码删除其代理
+        if manager.get_database_backend() == DatabaseBackend::Sqlite {
+            proxy_table.foreign_key(
+                ForeignKey::create()
+                    .name("fk_proxy_client")
+                    .from(Proxy::Table, Proxy::ClientId)
+                    .to(Client::Table, Client::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            );
+        }
+        manager.create_table(proxy_table).await?;
 
         // 创建 proxy 索引
         manager
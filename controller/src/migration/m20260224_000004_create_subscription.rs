@@ -18,8 +18,8 @@ impl MigrationTrait for Migration {
                     .col(string(Subscription::DurationType)) // daily, weekly, monthly, yearly
                     .col(integer(Subscription::DurationValue).default(1))
                     .col(double(Subscription::TrafficQuotaGb))
-                    .col(double(Subscription::Price).null())
-                    .col(string(Subscription::Description).null())
+                    .col(double_null(Subscription::Price))
+                    .col(string_null(Subscription::Description))
                     .col(boolean(Subscription::IsActive).default(true))
                     .col(timestamp(Subscription::CreatedAt))
                     .col(timestamp(Subscription::UpdatedAt))
@@ -1,4 +1,5 @@
 use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DatabaseBackend;
 
 #[derive(DeriveMigrationName)]
 pub struct Migration;
@@ -50,39 +51,44 @@ impl MigrationTrait for Migration {
 
         // 数据迁移：为已有的激活订阅填充快照字段
         let db = manager.get_connection();
+        // user 在 PostgreSQL/MySQL 中是保留字，需要按数据库加引号
+        let user = match manager.get_database_backend() {
+            DatabaseBackend::MySql => "`user`",
+            _ => "\"user\"",
+        };
         db.execute_unprepared(
             "UPDATE user_subscription SET \
              max_port_count_snapshot = (SELECT max_port_count FROM subscription WHERE subscription.id = user_subscription.subscription_id), \
              max_node_count_snapshot = (SELECT max_node_count FROM subscription WHERE subscription.id = user_subscription.subscription_id), \
              max_client_count_snapshot = (SELECT max_client_count FROM subscription WHERE subscription.id = user_subscription.subscription_id), \
-             quota_merged = 1 \
-             WHERE is_active = 1"
+             quota_merged = TRUE \
+             WHERE is_active = TRUE"
         ).await?;
 
         // 将已有激活订阅的端口额度合并到用户（traffic 已由旧代码合并，不重复处理）
-        db.execute_unprepared(
-            "UPDATE user SET max_port_count = COALESCE(max_port_count, 0) + COALESCE( \
+        db.execute_unprepared(&format!(
+            "UPDATE {user} SET max_port_count = COALESCE(max_port_count, 0) + COALESCE( \
              (SELECT SUM(max_port_count_snapshot) FROM user_subscription \
-              WHERE user_subscription.user_id = user.id AND user_subscription.is_active = 1 \
+              WHERE user_subscription.user_id = {user}.id AND user_subscription.is_active = TRUE \
               AND user_subscription.max_port_count_snapshot IS NOT NULL), 0) \
-             WHERE id IN (SELECT DISTINCT user_id FROM user_subscription WHERE is_active = 1 AND max_port_count_snapshot IS NOT NULL)"
-        ).await?;
+             WHERE id IN (SELECT DISTINCT user_id FROM user_subscription WHERE is_active = TRUE AND max_port_count_snapshot IS NOT NULL)"
+        )).await?;
 
-        db.execute_unprepared(
-            "UPDATE user SET max_node_count = COALESCE(max_node_count, 0) + COALESCE( \
+        db.execute_unprepared(&format!(
+            "UPDATE {user} SET max_node_count = COALESCE(max_node_count, 0) + COALESCE( \
              (SELECT SUM(max_node_count_snapshot) FROM user_subscription \
-              WHERE user_subscription.user_id = user.id AND user_subscription.is_active = 1 \
+              WHERE user_subscription.user_id = {user}.id AND user_subscription.is_active = TRUE \
               AND user_subscription.max_node_count_snapshot IS NOT NULL), 0) \
-             WHERE id IN (SELECT DISTINCT user_id FROM user_subscription WHERE is_active = 1 AND max_node_count_snapshot IS NOT NULL)"
-        ).await?;
+             WHERE id IN (SELECT DISTINCT user_id FROM user_subscription WHERE is_active = TRUE AND max_node_count_snapshot IS NOT NULL)"
+        )).await?;
 
-        db.execute_unprepared(
-            "UPDATE user SET max_client_count = COALESCE(max_client_count, 0) + COALESCE( \
+        db.execute_unprepared(&format!(
+            "UPDATE {user} SET max_client_count = COALESCE(max_client_count, 0) + COALESCE( \
              (SELECT SUM(max_client_count_snapshot) FROM user_subscription \
-              WHERE user_subscription.user_id = user.id AND user_subscription.is_active = 1 \
+              WHERE user_subscription.user_id = {user}.id AND user_subscription.is_active = TRUE \
               AND user_subscription.max_client_count_snapshot IS NOT NULL), 0) \
-             WHERE id IN (SELECT DISTINCT user_id FROM user_subscription WHERE is_active = 1 AND max_client_count_snapshot IS NOT NULL)"
-        ).await?;
+             WHERE id IN (SELECT DISTINCT user_id FROM user_subscription WHERE is_active = TRUE AND max_client_count_snapshot IS NOT NULL)"
+        )).await?;
 
         Ok(())
     }
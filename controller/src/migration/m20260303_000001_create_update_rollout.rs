@@ -0,0 +1,110 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建 update_rollout 表（软件更新发布计划）
+        manager
+            .create_table(
+                Table::create()
+                    .table(UpdateRollout::Table)
+                    .if_not_exists()
+                    .col(big_integer(UpdateRollout::Id).auto_increment().primary_key())
+                    .col(string(UpdateRollout::AgentType)) // node, client
+                    .col(string(UpdateRollout::TargetVersion))
+                    .col(integer(UpdateRollout::Percentage).default(0))
+                    .col(string_null(UpdateRollout::CanaryIds)) // JSON 数组
+                    .col(string(UpdateRollout::Status)) // running, paused, completed, cancelled
+                    .col(timestamp(UpdateRollout::CreatedAt))
+                    .col(timestamp(UpdateRollout::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // 创建 agent_update 表（单个节点/客户端的更新进度）
+        manager
+            .create_table(
+                Table::create()
+                    .table(AgentUpdate::Table)
+                    .if_not_exists()
+                    .col(big_integer(AgentUpdate::Id).auto_increment().primary_key())
+                    .col(big_integer(AgentUpdate::RolloutId))
+                    .col(string(AgentUpdate::AgentType))
+                    .col(big_integer(AgentUpdate::AgentId))
+                    .col(string_null(AgentUpdate::FromVersion))
+                    .col(string(AgentUpdate::TargetVersion))
+                    .col(string(AgentUpdate::Status)) // dispatched, downloading, restarting, succeeded, failed
+                    .col(string_null(AgentUpdate::Message))
+                    .col(timestamp(AgentUpdate::CreatedAt))
+                    .col(timestamp(AgentUpdate::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_agent_update_rollout")
+                            .from(AgentUpdate::Table, AgentUpdate::RolloutId)
+                            .to(UpdateRollout::Table, UpdateRollout::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_agent_update_rollout_agent")
+                    .table(AgentUpdate::Table)
+                    .col(AgentUpdate::RolloutId)
+                    .col(AgentUpdate::AgentType)
+                    .col(AgentUpdate::AgentId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AgentUpdate::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(UpdateRollout::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum UpdateRollout {
+    Table,
+    Id,
+    AgentType,
+    TargetVersion,
+    Percentage,
+    CanaryIds,
+    Status,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum AgentUpdate {
+    Table,
+    Id,
+    RolloutId,
+    AgentType,
+    AgentId,
+    FromVersion,
+    TargetVersion,
+    Status,
+    Message,
+    CreatedAt,
+    UpdatedAt,
+}
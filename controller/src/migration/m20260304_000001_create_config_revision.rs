@@ -0,0 +1,47 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建 config_revision 表（系统配置修订记录）
+        manager
+            .create_table(
+                Table::create()
+                    .table(ConfigRevision::Table)
+                    .if_not_exists()
+                    .col(big_integer(ConfigRevision::Id).auto_increment().primary_key())
+                    .col(text(ConfigRevision::Changes)) // JSON 数组：[{key, old, new}]
+                    .col(text(ConfigRevision::Snapshot)) // JSON 对象：修订后的完整配置
+                    .col(string_null(ConfigRevision::Description))
+                    .col(string_null(ConfigRevision::CreatedBy))
+                    .col(timestamp(ConfigRevision::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ConfigRevision::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum ConfigRevision {
+    Table,
+    Id,
+    Changes,
+    Snapshot,
+    Description,
+    CreatedBy,
+    CreatedAt,
+}
@@ -0,0 +1,101 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建 tenant 表（租户，位于用户之上）
+        manager
+            .create_table(
+                Table::create()
+                    .table(Tenant::Table)
+                    .if_not_exists()
+                    .col(big_integer(Tenant::Id).auto_increment().primary_key())
+                    .col(string(Tenant::Name).unique_key())
+                    .col(string_null(Tenant::Description))
+                    .col(integer_null(Tenant::MaxUserCount))
+                    .col(integer_null(Tenant::MaxClientCount))
+                    .col(double_null(Tenant::TrafficQuotaGb))
+                    .col(timestamp(Tenant::CreatedAt))
+                    .col(timestamp(Tenant::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // 用户所属租户（为空表示不属于任何租户）及租户管理员标记
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(User::TenantId).big_integer().null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(User::IsTenantAdmin).boolean().not_null().default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        // 节点分配给租户后仅该租户的用户可见
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Node::Table)
+                    .add_column(ColumnDef::new(Node::TenantId).big_integer().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(Table::alter().table(Node::Table).drop_column(Node::TenantId).to_owned())
+            .await?;
+        manager
+            .alter_table(Table::alter().table(User::Table).drop_column(User::IsTenantAdmin).to_owned())
+            .await?;
+        manager
+            .alter_table(Table::alter().table(User::Table).drop_column(User::TenantId).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Tenant::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Tenant {
+    Table,
+    Id,
+    Name,
+    Description,
+    MaxUserCount,
+    MaxClientCount,
+    TrafficQuotaGb,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    TenantId,
+    IsTenantAdmin,
+}
+
+#[derive(DeriveIden)]
+enum Node {
+    Table,
+    TenantId,
+}
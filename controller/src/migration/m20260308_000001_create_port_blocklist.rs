@@ -0,0 +1,85 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+/// 默认封禁的远程端口（SMTP 等常被滥用于发送垃圾邮件的端口）
+const DEFAULT_REMOTE_PORTS: &[(&str, &str)] = &[
+    ("25", "SMTP"),
+    ("465", "SMTPS"),
+    ("587", "SMTP Submission"),
+];
+
+/// 默认封禁的本地转发目标（云服务器元数据地址）
+const DEFAULT_LOCAL_TARGETS: &[(&str, &str)] = &[
+    ("169.254.0.0/16", "链路本地地址（含云服务器元数据 169.254.169.254）"),
+    ("fd00:ec2::254", "AWS IPv6 元数据地址"),
+];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 端口黑名单：node_id / tenant_id 均为空时为全局规则
+        manager
+            .create_table(
+                Table::create()
+                    .table(PortBlocklist::Table)
+                    .if_not_exists()
+                    .col(big_integer(PortBlocklist::Id).auto_increment().primary_key())
+                    .col(string(PortBlocklist::Kind))
+                    .col(string(PortBlocklist::Pattern))
+                    .col(big_integer_null(PortBlocklist::NodeId))
+                    .col(big_integer_null(PortBlocklist::TenantId))
+                    .col(string_null(PortBlocklist::Description))
+                    .col(timestamp(PortBlocklist::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        let defaults = DEFAULT_REMOTE_PORTS
+            .iter()
+            .map(|(pattern, desc)| ("remote_port", pattern, desc))
+            .chain(DEFAULT_LOCAL_TARGETS.iter().map(|(pattern, desc)| ("local_target", pattern, desc)));
+        for (kind, pattern, description) in defaults {
+            let insert = Query::insert()
+                .into_table(PortBlocklist::Table)
+                .columns([
+                    PortBlocklist::Kind,
+                    PortBlocklist::Pattern,
+                    PortBlocklist::Description,
+                    PortBlocklist::CreatedAt,
+                ])
+                .values_panic([
+                    kind.into(),
+                    (*pattern).into(),
+                    (*description).into(),
+                    Expr::current_timestamp().into(),
+                ])
+                .to_owned();
+            manager.exec_stmt(insert).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PortBlocklist::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum PortBlocklist {
+    Table,
+    Id,
+    Kind,
+    Pattern,
+    NodeId,
+    TenantId,
+    Description,
+    CreatedAt,
+}
@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 客户端允许转发的本地目标白名单（逗号分隔），为空表示不限制
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Client::Table)
+                    .add_column(ColumnDef::new(Client::AllowedTargets).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Client::Table)
+                    .drop_column(Client::AllowedTargets)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Client {
+    Table,
+    AllowedTargets,
+}
@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 临时隧道（到期自动关闭，关闭后保留记录用于审计）
+        manager
+            .create_table(
+                Table::create()
+                    .table(TemporaryTunnel::Table)
+                    .if_not_exists()
+                    .col(big_integer(TemporaryTunnel::Id).auto_increment().primary_key())
+                    .col(big_integer(TemporaryTunnel::ClientId))
+                    .col(big_integer(TemporaryTunnel::NodeId))
+                    .col(string(TemporaryTunnel::LocalIp))
+                    .col(integer(TemporaryTunnel::LocalPort))
+                    .col(integer(TemporaryTunnel::RemotePort))
+                    .col(string_null(TemporaryTunnel::Reason))
+                    .col(string(TemporaryTunnel::CreatedBy))
+                    .col(timestamp(TemporaryTunnel::ExpiresAt))
+                    .col(timestamp_null(TemporaryTunnel::ClosedAt))
+                    .col(string_null(TemporaryTunnel::ClosedBy))
+                    .col(timestamp(TemporaryTunnel::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TemporaryTunnel::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum TemporaryTunnel {
+    Table,
+    Id,
+    ClientId,
+    NodeId,
+    LocalIp,
+    LocalPort,
+    RemotePort,
+    Reason,
+    CreatedBy,
+    ExpiresAt,
+    ClosedAt,
+    ClosedBy,
+    CreatedAt,
+}
@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 代理的启用时间窗口，为空表示不按时间表自动启停
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Proxy::Table)
+                    .add_column(ColumnDef::new(Proxy::Schedule).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Proxy::Table)
+                    .drop_column(Proxy::Schedule)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Proxy {
+    Table,
+    Schedule,
+}
@@ -0,0 +1,85 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 代理到期时间，为空表示永不过期
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Proxy::Table)
+                    .add_column(ColumnDef::new(Proxy::ExpiresAt).timestamp().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // 代理被标记为闲置（长时间无流量）的时间
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Proxy::Table)
+                    .add_column(ColumnDef::new(Proxy::StaleAt).timestamp().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // 客户端到期时间，为空表示永不过期
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Client::Table)
+                    .add_column(ColumnDef::new(Client::ExpiresAt).timestamp().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Client::Table)
+                    .drop_column(Client::ExpiresAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Proxy::Table)
+                    .drop_column(Proxy::StaleAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Proxy::Table)
+                    .drop_column(Proxy::ExpiresAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Proxy {
+    Table,
+    ExpiresAt,
+    StaleAt,
+}
+
+#[derive(DeriveIden)]
+enum Client {
+    Table,
+    ExpiresAt,
+}
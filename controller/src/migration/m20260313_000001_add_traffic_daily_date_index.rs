@@ -0,0 +1,42 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 管理员流量总览按日期范围聚合全部记录，需要单独的日期索引
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_traffic_daily_date")
+                    .table(TrafficDaily::Table)
+                    .col(TrafficDaily::Date)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_traffic_daily_date")
+                    .table(TrafficDaily::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum TrafficDaily {
+    Table,
+    Date,
+}
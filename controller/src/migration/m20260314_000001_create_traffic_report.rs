@@ -0,0 +1,67 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 已处理的流量上报 ID，用于丢弃节点超时 / 重连后重发的批次
+        manager
+            .create_table(
+                Table::create()
+                    .table(TrafficReport::Table)
+                    .if_not_exists()
+                    .col(big_integer(TrafficReport::Id).auto_increment().primary_key())
+                    .col(big_integer(TrafficReport::NodeId))
+                    .col(string(TrafficReport::ReportId))
+                    .col(timestamp(TrafficReport::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_traffic_report_node_report")
+                    .table(TrafficReport::Table)
+                    .col(TrafficReport::NodeId)
+                    .col(TrafficReport::ReportId)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_traffic_report_created_at")
+                    .table(TrafficReport::Table)
+                    .col(TrafficReport::CreatedAt)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TrafficReport::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum TrafficReport {
+    Table,
+    Id,
+    NodeId,
+    ReportId,
+    CreatedAt,
+}
@@ -0,0 +1,89 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 节点 NAT 探测端口（节点注册时上报，未启用时为空）
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Node::Table)
+                    .add_column(ColumnDef::new(Node::NatProbePort).integer().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // 客户端上报的 NAT 类型
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Client::Table)
+                    .add_column(ColumnDef::new(Client::NatType).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // 客户端的外部映射地址
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Client::Table)
+                    .add_column(ColumnDef::new(Client::NatMappedAddr).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // NAT 检测时间
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Client::Table)
+                    .add_column(ColumnDef::new(Client::NatDetectedAt).timestamp().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [Client::NatDetectedAt, Client::NatMappedAddr, Client::NatType] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Client::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Node::Table)
+                    .drop_column(Node::NatProbePort)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Node {
+    Table,
+    NatProbePort,
+}
+
+#[derive(DeriveIden)]
+enum Client {
+    Table,
+    NatType,
+    NatMappedAddr,
+    NatDetectedAt,
+}
@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 管理员模拟用户登录的审计记录
+        manager
+            .create_table(
+                Table::create()
+                    .table(ImpersonationLog::Table)
+                    .if_not_exists()
+                    .col(big_integer(ImpersonationLog::Id).auto_increment().primary_key())
+                    .col(big_integer(ImpersonationLog::AdminId))
+                    .col(string(ImpersonationLog::AdminUsername))
+                    .col(big_integer(ImpersonationLog::UserId))
+                    .col(string(ImpersonationLog::Username))
+                    .col(string_null(ImpersonationLog::Reason))
+                    .col(timestamp(ImpersonationLog::ExpiresAt))
+                    .col(timestamp(ImpersonationLog::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ImpersonationLog::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum ImpersonationLog {
+    Table,
+    Id,
+    AdminId,
+    AdminUsername,
+    UserId,
+    Username,
+    Reason,
+    ExpiresAt,
+    CreatedAt,
+}
@@ -0,0 +1,117 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 节点默认的来源 IP 处置规则（JSON）
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Node::Table)
+                    .add_column(ColumnDef::new(Node::MitigationConfig).text().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // 代理的来源 IP 处置规则（JSON），为空时使用节点默认规则
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Proxy::Table)
+                    .add_column(ColumnDef::new(Proxy::MitigationConfig).text().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // 节点上报的处置记录
+        manager
+            .create_table(
+                Table::create()
+                    .table(MitigationEvent::Table)
+                    .if_not_exists()
+                    .col(big_integer(MitigationEvent::Id).auto_increment().primary_key())
+                    .col(big_integer(MitigationEvent::NodeId))
+                    .col(big_integer(MitigationEvent::ProxyId))
+                    .col(big_integer(MitigationEvent::ClientId))
+                    .col(string(MitigationEvent::SourceIp))
+                    .col(string(MitigationEvent::Action))
+                    .col(string(MitigationEvent::Reason))
+                    .col(timestamp(MitigationEvent::ExpiresAt))
+                    .col(timestamp_null(MitigationEvent::ReleasedAt))
+                    .col(string_null(MitigationEvent::ReleasedBy))
+                    .col(timestamp(MitigationEvent::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_mitigation_event_expires_at")
+                    .table(MitigationEvent::Table)
+                    .col(MitigationEvent::ExpiresAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MitigationEvent::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Proxy::Table)
+                    .drop_column(Proxy::MitigationConfig)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Node::Table)
+                    .drop_column(Node::MitigationConfig)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Node {
+    Table,
+    MitigationConfig,
+}
+
+#[derive(DeriveIden)]
+enum Proxy {
+    Table,
+    MitigationConfig,
+}
+
+#[derive(DeriveIden)]
+enum MitigationEvent {
+    Table,
+    Id,
+    NodeId,
+    ProxyId,
+    ClientId,
+    SourceIp,
+    Action,
+    Reason,
+    ExpiresAt,
+    ReleasedAt,
+    ReleasedBy,
+    CreatedAt,
+}
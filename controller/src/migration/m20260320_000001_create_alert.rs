@@ -0,0 +1,110 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 告警规则
+        manager
+            .create_table(
+                Table::create()
+                    .table(AlertRule::Table)
+                    .if_not_exists()
+                    .col(big_integer(AlertRule::Id).auto_increment().primary_key())
+                    .col(string(AlertRule::Name))
+                    .col(string(AlertRule::Kind))
+                    .col(double(AlertRule::Threshold).default(0.0))
+                    .col(big_integer(AlertRule::DurationSecs).default(0))
+                    .col(string(AlertRule::Severity))
+                    .col(big_integer_null(AlertRule::TargetId))
+                    .col(boolean(AlertRule::Enabled).default(true))
+                    .col(timestamp(AlertRule::CreatedAt))
+                    .col(timestamp(AlertRule::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // 告警事件（触发 / 恢复）
+        manager
+            .create_table(
+                Table::create()
+                    .table(AlertEvent::Table)
+                    .if_not_exists()
+                    .col(big_integer(AlertEvent::Id).auto_increment().primary_key())
+                    .col(big_integer(AlertEvent::RuleId))
+                    .col(string(AlertEvent::RuleName))
+                    .col(string(AlertEvent::Kind))
+                    .col(string(AlertEvent::Severity))
+                    .col(string(AlertEvent::TargetType))
+                    .col(big_integer(AlertEvent::TargetId))
+                    .col(string(AlertEvent::TargetName))
+                    .col(double(AlertEvent::Value))
+                    .col(string(AlertEvent::Message))
+                    .col(string(AlertEvent::State))
+                    .col(timestamp(AlertEvent::FiredAt))
+                    .col(timestamp_null(AlertEvent::ResolvedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_alert_event_state")
+                    .table(AlertEvent::Table)
+                    .col(AlertEvent::State)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AlertEvent::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(AlertRule::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum AlertRule {
+    Table,
+    Id,
+    Name,
+    Kind,
+    Threshold,
+    DurationSecs,
+    Severity,
+    TargetId,
+    Enabled,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum AlertEvent {
+    Table,
+    Id,
+    RuleId,
+    RuleName,
+    Kind,
+    Severity,
+    TargetType,
+    TargetId,
+    TargetName,
+    Value,
+    Message,
+    State,
+    FiredAt,
+    ResolvedAt,
+}
@@ -0,0 +1,62 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MaintenanceWindow::Table)
+                    .if_not_exists()
+                    .col(big_integer(MaintenanceWindow::Id).auto_increment().primary_key())
+                    .col(string(MaintenanceWindow::Name))
+                    .col(string(MaintenanceWindow::ScopeType))
+                    .col(big_integer(MaintenanceWindow::ScopeId))
+                    .col(timestamp(MaintenanceWindow::StartsAt))
+                    .col(timestamp(MaintenanceWindow::EndsAt))
+                    .col(string_null(MaintenanceWindow::Description))
+                    .col(string(MaintenanceWindow::CreatedBy))
+                    .col(timestamp(MaintenanceWindow::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_maintenance_window_ends_at")
+                    .table(MaintenanceWindow::Table)
+                    .col(MaintenanceWindow::EndsAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MaintenanceWindow::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum MaintenanceWindow {
+    Table,
+    Id,
+    Name,
+    ScopeType,
+    ScopeId,
+    StartsAt,
+    EndsAt,
+    Description,
+    CreatedBy,
+    CreatedAt,
+}
@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 节点 / 客户端的上线、离线记录
+        manager
+            .create_table(
+                Table::create()
+                    .table(StatusHistory::Table)
+                    .if_not_exists()
+                    .col(big_integer(StatusHistory::Id).auto_increment().primary_key())
+                    .col(string(StatusHistory::EntityType))
+                    .col(big_integer(StatusHistory::EntityId))
+                    .col(boolean(StatusHistory::Online))
+                    .col(timestamp(StatusHistory::ChangedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_status_history_entity")
+                    .table(StatusHistory::Table)
+                    .col(StatusHistory::EntityType)
+                    .col(StatusHistory::EntityId)
+                    .col(StatusHistory::ChangedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(StatusHistory::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum StatusHistory {
+    Table,
+    Id,
+    EntityType,
+    EntityId,
+    Online,
+    ChangedAt,
+}
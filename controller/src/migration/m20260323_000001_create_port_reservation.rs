@@ -0,0 +1,64 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PortReservation::Table)
+                    .if_not_exists()
+                    .col(big_integer(PortReservation::Id).auto_increment().primary_key())
+                    .col(big_integer(PortReservation::NodeId))
+                    .col(integer(PortReservation::PortStart))
+                    .col(integer(PortReservation::PortEnd))
+                    .col(big_integer_null(PortReservation::UserId))
+                    .col(big_integer_null(PortReservation::TenantId))
+                    .col(timestamp_null(PortReservation::ExpiresAt))
+                    .col(string_null(PortReservation::Description))
+                    .col(string(PortReservation::CreatedBy))
+                    .col(timestamp(PortReservation::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_port_reservation_node")
+                    .table(PortReservation::Table)
+                    .col(PortReservation::NodeId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PortReservation::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum PortReservation {
+    Table,
+    Id,
+    NodeId,
+    PortStart,
+    PortEnd,
+    UserId,
+    TenantId,
+    ExpiresAt,
+    Description,
+    CreatedBy,
+    CreatedAt,
+}
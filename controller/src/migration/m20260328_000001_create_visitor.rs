@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 访客代理：在客户端本机监听端口，经节点转发到目标代理
+        manager
+            .create_table(
+                Table::create()
+                    .table(Visitor::Table)
+                    .if_not_exists()
+                    .col(big_integer(Visitor::Id).auto_increment().primary_key())
+                    .col(string(Visitor::ClientId))
+                    .col(string(Visitor::Name))
+                    .col(big_integer(Visitor::ProxyId))
+                    .col(string(Visitor::BindIp))
+                    .col(integer(Visitor::BindPort))
+                    .col(boolean(Visitor::Enabled))
+                    .col(timestamp(Visitor::CreatedAt))
+                    .col(timestamp(Visitor::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_visitor_client")
+                    .table(Visitor::Table)
+                    .col(Visitor::ClientId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Visitor::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Visitor {
+    Table,
+    Id,
+    ClientId,
+    Name,
+    ProxyId,
+    BindIp,
+    BindPort,
+    Enabled,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,50 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const CONFIGS: [(&str, &str, &str, &str); 3] = [
+    ("password_min_length", "8", "密码最小长度", "number"),
+    ("password_min_char_classes", "2", "密码至少包含的字符类别数（小写、大写、数字、符号，1-4）", "number"),
+    ("password_breach_list_path", "\"\"", "泄露密码列表文件路径（每行一个密码），为空不检查", "string"),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut insert = Query::insert()
+            .into_table(SystemConfig::Table)
+            .columns([
+                SystemConfig::Key,
+                SystemConfig::Value,
+                SystemConfig::Description,
+                SystemConfig::ValueType,
+            ])
+            .to_owned();
+        for (key, value, description, value_type) in CONFIGS {
+            insert.values_panic([key.into(), value.into(), description.into(), value_type.into()]);
+        }
+
+        manager.exec_stmt(insert).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let delete = Query::delete()
+            .from_table(SystemConfig::Table)
+            .and_where(Expr::col(SystemConfig::Key).is_in(CONFIGS.map(|(key, ..)| key)))
+            .to_owned();
+        manager.exec_stmt(delete).await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum SystemConfig {
+    Table,
+    Key,
+    Value,
+    Description,
+    ValueType,
+}
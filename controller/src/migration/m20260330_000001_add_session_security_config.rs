@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const CONFIGS: [(&str, &str, &str, &str); 2] = [
+    ("session_idle_timeout_minutes", "60", "登录会话空闲超时（分钟），超过该时间未操作需重新登录，0 表示不限制", "number"),
+    ("elevated_action_window_minutes", "5", "敏感操作二次验证的有效时间（分钟）", "number"),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut insert = Query::insert()
+            .into_table(SystemConfig::Table)
+            .columns([
+                SystemConfig::Key,
+                SystemConfig::Value,
+                SystemConfig::Description,
+                SystemConfig::ValueType,
+            ])
+            .to_owned();
+        for (key, value, description, value_type) in CONFIGS {
+            insert.values_panic([key.into(), value.into(), description.into(), value_type.into()]);
+        }
+
+        manager.exec_stmt(insert).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let delete = Query::delete()
+            .from_table(SystemConfig::Table)
+            .and_where(Expr::col(SystemConfig::Key).is_in(CONFIGS.map(|(key, ..)| key)))
+            .to_owned();
+        manager.exec_stmt(delete).await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum SystemConfig {
+    Table,
+    Key,
+    Value,
+    Description,
+    ValueType,
+}
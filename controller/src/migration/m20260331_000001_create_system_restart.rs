@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 系统重启的审计记录
+        manager
+            .create_table(
+                Table::create()
+                    .table(SystemRestart::Table)
+                    .if_not_exists()
+                    .col(big_integer(SystemRestart::Id).auto_increment().primary_key())
+                    .col(big_integer(SystemRestart::UserId))
+                    .col(string(SystemRestart::Username))
+                    .col(string_null(SystemRestart::Reason))
+                    .col(text(SystemRestart::PendingChanges))
+                    .col(integer(SystemRestart::ConnectedNodes))
+                    .col(integer(SystemRestart::ConnectedClients))
+                    .col(timestamp(SystemRestart::RequestedAt))
+                    .col(timestamp_null(SystemRestart::CompletedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SystemRestart::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum SystemRestart {
+    Table,
+    Id,
+    UserId,
+    Username,
+    Reason,
+    PendingChanges,
+    ConnectedNodes,
+    ConnectedClients,
+    RequestedAt,
+    CompletedAt,
+}
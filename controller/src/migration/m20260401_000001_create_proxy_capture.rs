@@ -0,0 +1,112 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const CONFIGS: [(&str, &str, &str, &str); 3] = [
+    ("capture_retention_hours", "24", "抓包文件保留时间（小时），到期自动删除", "number"),
+    ("capture_max_duration_secs", "600", "单次抓包的最长时间（秒）", "number"),
+    ("capture_max_size_mb", "100", "单次抓包的文件大小上限（MB）", "number"),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 代理抓包记录
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProxyCapture::Table)
+                    .if_not_exists()
+                    .col(big_integer(ProxyCapture::Id).auto_increment().primary_key())
+                    .col(big_integer(ProxyCapture::ProxyId))
+                    .col(string(ProxyCapture::ProxyName))
+                    .col(big_integer(ProxyCapture::NodeId))
+                    .col(big_integer(ProxyCapture::UserId))
+                    .col(string(ProxyCapture::Username))
+                    .col(string_null(ProxyCapture::Reason))
+                    .col(integer(ProxyCapture::DurationSecs))
+                    .col(string(ProxyCapture::Status))
+                    .col(big_integer(ProxyCapture::SizeBytes).default(0))
+                    .col(big_integer(ProxyCapture::DroppedPackets).default(0))
+                    .col(text_null(ProxyCapture::Error))
+                    .col(integer(ProxyCapture::DownloadCount).default(0))
+                    .col(timestamp(ProxyCapture::CreatedAt))
+                    .col(timestamp_null(ProxyCapture::CompletedAt))
+                    .col(timestamp(ProxyCapture::ExpiresAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_proxy_capture_proxy_id")
+                    .table(ProxyCapture::Table)
+                    .col(ProxyCapture::ProxyId)
+                    .to_owned(),
+            )
+            .await?;
+
+        let mut insert = Query::insert()
+            .into_table(SystemConfig::Table)
+            .columns([
+                SystemConfig::Key,
+                SystemConfig::Value,
+                SystemConfig::Description,
+                SystemConfig::ValueType,
+            ])
+            .to_owned();
+        for (key, value, description, value_type) in CONFIGS {
+            insert.values_panic([key.into(), value.into(), description.into(), value_type.into()]);
+        }
+        manager.exec_stmt(insert).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let delete = Query::delete()
+            .from_table(SystemConfig::Table)
+            .and_where(Expr::col(SystemConfig::Key).is_in(CONFIGS.map(|(key, ..)| key)))
+            .to_owned();
+        manager.exec_stmt(delete).await?;
+
+        manager
+            .drop_table(Table::drop().table(ProxyCapture::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum ProxyCapture {
+    Table,
+    Id,
+    ProxyId,
+    ProxyName,
+    NodeId,
+    UserId,
+    Username,
+    Reason,
+    DurationSecs,
+    Status,
+    SizeBytes,
+    DroppedPackets,
+    Error,
+    DownloadCount,
+    CreatedAt,
+    CompletedAt,
+    ExpiresAt,
+}
+
+#[derive(DeriveIden)]
+enum SystemConfig {
+    Table,
+    Key,
+    Value,
+    Description,
+    ValueType,
+}
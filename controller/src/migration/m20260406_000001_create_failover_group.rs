@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 节点故障转移组：主节点 + 备用节点
+        manager
+            .create_table(
+                Table::create()
+                    .table(FailoverGroup::Table)
+                    .if_not_exists()
+                    .col(big_integer(FailoverGroup::Id).auto_increment().primary_key())
+                    .col(string(FailoverGroup::Name))
+                    .col(big_integer(FailoverGroup::PrimaryNodeId))
+                    .col(string(FailoverGroup::StandbyNodeIds))
+                    .col(integer(FailoverGroup::OfflineThresholdSecs).default(120))
+                    .col(boolean(FailoverGroup::Failback).default(true))
+                    .col(boolean(FailoverGroup::Enabled).default(true))
+                    .col(big_integer(FailoverGroup::ActiveNodeId))
+                    .col(text_null(FailoverGroup::MovedProxyIds))
+                    .col(timestamp_null(FailoverGroup::FailedOverAt))
+                    .col(timestamp(FailoverGroup::CreatedAt))
+                    .col(timestamp(FailoverGroup::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FailoverGroup::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum FailoverGroup {
+    Table,
+    Id,
+    Name,
+    PrimaryNodeId,
+    StandbyNodeIds,
+    OfflineThresholdSecs,
+    Failback,
+    Enabled,
+    ActiveNodeId,
+    MovedProxyIds,
+    FailedOverAt,
+    CreatedAt,
+    UpdatedAt,
+}
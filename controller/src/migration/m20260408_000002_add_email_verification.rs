@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const CONFIGS: [(&str, &str, &str, &str); 2] = [
+    ("public_url", "", "Web 界面的外部访问地址（如 https://frp.example.com），用于邮件中的验证和重置链接", "string"),
+    ("password_reset_token_minutes", "30", "邮箱验证和找回密码链接的有效时间（分钟）", "number"),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 邮箱验证时间，为空表示未验证；修改邮箱后清空
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(User::EmailVerifiedAt).timestamp().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        let mut insert = Query::insert()
+            .into_table(SystemConfig::Table)
+            .columns([
+                SystemConfig::Key,
+                SystemConfig::Value,
+                SystemConfig::Description,
+                SystemConfig::ValueType,
+            ])
+            .to_owned();
+        for (key, value, description, value_type) in CONFIGS {
+            insert.values_panic([key.into(), value.into(), description.into(), value_type.into()]);
+        }
+
+        manager.exec_stmt(insert).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let delete = Query::delete()
+            .from_table(SystemConfig::Table)
+            .and_where(Expr::col(SystemConfig::Key).is_in(CONFIGS.map(|(key, ..)| key)))
+            .to_owned();
+        manager.exec_stmt(delete).await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::EmailVerifiedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    EmailVerifiedAt,
+}
+
+#[derive(DeriveIden)]
+enum SystemConfig {
+    Table,
+    Key,
+    Value,
+    Description,
+    ValueType,
+}
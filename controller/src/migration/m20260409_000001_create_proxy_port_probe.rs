@@ -0,0 +1,91 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const CONFIGS: [(&str, &str, &str, &str); 1] = [(
+    "port_probe_node_id",
+    "0",
+    "代理端口外部探测的发起节点 ID，0 表示由 Controller 直接探测（Controller 与节点在同一内网时应指定其他节点）",
+    "number",
+)];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 代理公网端口的最近一次探测结果，每个代理一条
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProxyPortProbe::Table)
+                    .if_not_exists()
+                    .col(big_integer(ProxyPortProbe::Id).auto_increment().primary_key())
+                    .col(big_integer(ProxyPortProbe::ProxyId).unique_key())
+                    .col(big_integer(ProxyPortProbe::NodeId))
+                    .col(string(ProxyPortProbe::Source))
+                    .col(string(ProxyPortProbe::Target))
+                    .col(string(ProxyPortProbe::Status))
+                    .col(double_null(ProxyPortProbe::LatencyMs))
+                    .col(boolean_null(ProxyPortProbe::LocalReachable))
+                    .col(text(ProxyPortProbe::Message))
+                    .col(timestamp(ProxyPortProbe::ProbedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        let mut insert = Query::insert()
+            .into_table(SystemConfig::Table)
+            .columns([
+                SystemConfig::Key,
+                SystemConfig::Value,
+                SystemConfig::Description,
+                SystemConfig::ValueType,
+            ])
+            .to_owned();
+        for (key, value, description, value_type) in CONFIGS {
+            insert.values_panic([key.into(), value.into(), description.into(), value_type.into()]);
+        }
+        manager.exec_stmt(insert).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let delete = Query::delete()
+            .from_table(SystemConfig::Table)
+            .and_where(Expr::col(SystemConfig::Key).is_in(CONFIGS.map(|(key, ..)| key)))
+            .to_owned();
+        manager.exec_stmt(delete).await?;
+
+        manager
+            .drop_table(Table::drop().table(ProxyPortProbe::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum ProxyPortProbe {
+    Table,
+    Id,
+    ProxyId,
+    NodeId,
+    Source,
+    Target,
+    Status,
+    LatencyMs,
+    LocalReachable,
+    Message,
+    ProbedAt,
+}
+
+#[derive(DeriveIden)]
+enum SystemConfig {
+    Table,
+    Key,
+    Value,
+    Description,
+    ValueType,
+}
@@ -0,0 +1,89 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 客户端的重复登录策略（同一 token 的另一个实例在原实例在线时连接）
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Client::Table)
+                    .add_column(
+                        ColumnDef::new(Client::DuplicateLoginPolicy)
+                            .string()
+                            .not_null()
+                            .default("reject_new"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // 重复登录审计事件
+        manager
+            .create_table(
+                Table::create()
+                    .table(ClientLoginEvent::Table)
+                    .if_not_exists()
+                    .col(big_integer(ClientLoginEvent::Id).auto_increment().primary_key())
+                    .col(big_integer(ClientLoginEvent::ClientId))
+                    .col(string(ClientLoginEvent::Action))
+                    .col(string_null(ClientLoginEvent::InstanceId))
+                    .col(string_null(ClientLoginEvent::OtherInstanceId))
+                    .col(string_null(ClientLoginEvent::Ip))
+                    .col(timestamp(ClientLoginEvent::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_client_login_event_client_id")
+                    .table(ClientLoginEvent::Table)
+                    .col(ClientLoginEvent::ClientId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ClientLoginEvent::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Client::Table)
+                    .drop_column(Client::DuplicateLoginPolicy)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Client {
+    Table,
+    DuplicateLoginPolicy,
+}
+
+#[derive(DeriveIden)]
+enum ClientLoginEvent {
+    Table,
+    Id,
+    ClientId,
+    Action,
+    InstanceId,
+    OtherInstanceId,
+    Ip,
+    CreatedAt,
+}
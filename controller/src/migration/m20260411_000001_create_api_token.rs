@@ -0,0 +1,74 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 长期有效的 API 令牌（rfrpctl 等脚本使用），只保存 SHA-256 摘要
+        manager
+            .create_table(
+                Table::create()
+                    .table(ApiToken::Table)
+                    .if_not_exists()
+                    .col(big_integer(ApiToken::Id).auto_increment().primary_key())
+                    .col(big_integer(ApiToken::UserId))
+                    .col(string(ApiToken::Name))
+                    .col(string(ApiToken::TokenHash).unique_key())
+                    .col(string(ApiToken::Prefix))
+                    .col(timestamp_null(ApiToken::ExpiresAt))
+                    .col(timestamp_null(ApiToken::LastUsedAt))
+                    .col(timestamp(ApiToken::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_api_token_user")
+                            .from(ApiToken::Table, ApiToken::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_api_token_user_id")
+                    .table(ApiToken::Table)
+                    .col(ApiToken::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ApiToken::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum ApiToken {
+    Table,
+    Id,
+    UserId,
+    Name,
+    TokenHash,
+    Prefix,
+    ExpiresAt,
+    LastUsedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
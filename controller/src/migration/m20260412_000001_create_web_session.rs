@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Web 会话的最后活动时间和二次验证有效期，Controller 重启后继续生效
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebSession::Table)
+                    .if_not_exists()
+                    .col(string(WebSession::KeyHash).primary_key())
+                    .col(big_integer(WebSession::ExpiresAt))
+                    .col(timestamp(WebSession::LastSeenAt))
+                    .col(boolean(WebSession::IdleExpired).default(false))
+                    .col(timestamp_null(WebSession::ElevatedUntil))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_web_session_expires_at")
+                    .table(WebSession::Table)
+                    .col(WebSession::ExpiresAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WebSession::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum WebSession {
+    Table,
+    KeyHash,
+    ExpiresAt,
+    LastSeenAt,
+    IdleExpired,
+    ElevatedUntil,
+}
@@ -1,6 +1,8 @@
-use sea_orm::{Database, DatabaseConnection};
+use sea_orm::sqlx::sqlite::{SqliteJournalMode, SqliteSynchronous};
+use sea_orm::{ConnectOptions, Database, DatabaseConnection};
 use sea_orm_migration::prelude::*;
 use std::fs::create_dir_all;
+use std::time::Duration;
 use std::{fs, path};
 use tokio::sync::OnceCell;
 
@@ -39,6 +41,53 @@ mod m20260301_000003_add_user_quotas;
 mod m20260301_000004_add_subscription_quotas;
 mod m20260301_000005_add_subscription_quota_snapshots;
 mod m20260302_000001_add_version_fields;
+mod m20260303_000001_create_update_rollout;
+mod m20260304_000001_create_config_revision;
+mod m20260305_000001_add_node_quic_config;
+mod m20260306_000001_add_proxy_idle_timeout;
+mod m20260307_000001_create_tenant;
+mod m20260308_000001_create_port_blocklist;
+mod m20260309_000001_add_client_allowed_targets;
+mod m20260310_000001_create_temporary_tunnel;
+mod m20260311_000001_add_proxy_schedule;
+mod m20260312_000001_add_expiration;
+mod m20260313_000001_add_traffic_daily_date_index;
+mod m20260314_000001_create_traffic_report;
+mod m20260315_000001_add_user_profile;
+mod m20260316_000001_add_nat_detection;
+mod m20260317_000001_create_impersonation_log;
+mod m20260318_000001_add_mitigation;
+mod m20260319_000001_add_proxy_local_pool_size;
+mod m20260320_000001_create_alert;
+mod m20260321_000001_create_maintenance_window;
+mod m20260322_000001_create_status_history;
+mod m20260323_000001_create_port_reservation;
+mod m20260324_000001_add_lock_version;
+mod m20260325_000001_add_node_capabilities;
+mod m20260326_000001_add_proxy_dns_name;
+mod m20260327_000001_add_proxy_service;
+mod m20260328_000001_create_visitor;
+mod m20260329_000001_add_password_policy_config;
+mod m20260330_000001_add_session_security_config;
+mod m20260331_000001_create_system_restart;
+mod m20260401_000001_create_proxy_capture;
+mod m20260402_000001_add_proxy_bind_ip;
+mod m20260403_000001_add_proxy_local_source;
+mod m20260404_000001_add_speed_limit_schedule;
+mod m20260405_000001_add_client_system_info;
+mod m20260406_000001_create_failover_group;
+mod m20260407_000001_add_fair_share;
+mod m20260408_000001_add_proxy_priority;
+mod m20260408_000002_add_email_verification;
+mod m20260409_000001_create_proxy_port_probe;
+mod m20260410_000001_add_node_firewall_config;
+mod m20260410_000002_add_client_instance_id;
+mod m20260410_000003_add_client_duplicate_login;
+mod m20260410_000004_add_proxy_anycast_nodes;
+mod m20260411_000001_create_api_token;
+mod m20260411_000002_add_node_drained_proxies;
+mod m20260412_000001_create_web_session;
+mod m20260412_000002_add_max_enabled_proxies_config;
 
 pub struct Migrator;
 
@@ -81,27 +130,241 @@ impl MigratorTrait for Migrator {
             Box::new(m20260301_000004_add_subscription_quotas::Migration),
             Box::new(m20260301_000005_add_subscription_quota_snapshots::Migration),
             Box::new(m20260302_000001_add_version_fields::Migration),
+            Box::new(m20260303_000001_create_update_rollout::Migration),
+            Box::new(m20260304_000001_create_config_revision::Migration),
+            Box::new(m20260305_000001_add_node_quic_config::Migration),
+            Box::new(m20260306_000001_add_proxy_idle_timeout::Migration),
+            Box::new(m20260307_000001_create_tenant::Migration),
+            Box::new(m20260308_000001_create_port_blocklist::Migration),
+            Box::new(m20260309_000001_add_client_allowed_targets::Migration),
+            Box::new(m20260310_000001_create_temporary_tunnel::Migration),
+            Box::new(m20260311_000001_add_proxy_schedule::Migration),
+            Box::new(m20260312_000001_add_expiration::Migration),
+            Box::new(m20260313_000001_add_traffic_daily_date_index::Migration),
+            Box::new(m20260314_000001_create_traffic_report::Migration),
+            Box::new(m20260315_000001_add_user_profile::Migration),
+            Box::new(m20260316_000001_add_nat_detection::Migration),
+            Box::new(m20260317_000001_create_impersonation_log::Migration),
+            Box::new(m20260318_000001_add_mitigation::Migration),
+            Box::new(m20260319_000001_add_proxy_local_pool_size::Migration),
+            Box::new(m20260320_000001_create_alert::Migration),
+            Box::new(m20260321_000001_create_maintenance_window::Migration),
+            Box::new(m20260322_000001_create_status_history::Migration),
+            Box::new(m20260323_000001_create_port_reservation::Migration),
+            Box::new(m20260324_000001_add_lock_version::Migration),
+            Box::new(m20260325_000001_add_node_capabilities::Migration),
+            Box::new(m20260326_000001_add_proxy_dns_name::Migration),
+            Box::new(m20260327_000001_add_proxy_service::Migration),
+            Box::new(m20260328_000001_create_visitor::Migration),
+            Box::new(m20260329_000001_add_password_policy_config::Migration),
+            Box::new(m20260330_000001_add_session_security_config::Migration),
+            Box::new(m20260331_000001_create_system_restart::Migration),
+            Box::new(m20260401_000001_create_proxy_capture::Migration),
+            Box::new(m20260402_000001_add_proxy_bind_ip::Migration),
+            Box::new(m20260403_000001_add_proxy_local_source::Migration),
+            Box::new(m20260404_000001_add_speed_limit_schedule::Migration),
+            Box::new(m20260405_000001_add_client_system_info::Migration),
+            Box::new(m20260406_000001_create_failover_group::Migration),
+            Box::new(m20260407_000001_add_fair_share::Migration),
+            Box::new(m20260408_000001_add_proxy_priority::Migration),
+            Box::new(m20260408_000002_add_email_verification::Migration),
+            Box::new(m20260409_000001_create_proxy_port_probe::Migration),
+            Box::new(m20260410_000001_add_node_firewall_config::Migration),
+            Box::new(m20260410_000002_add_client_instance_id::Migration),
+            Box::new(m20260410_000003_add_client_duplicate_login::Migration),
+            Box::new(m20260410_000004_add_proxy_anycast_nodes::Migration),
+            Box::new(m20260411_000001_create_api_token::Migration),
+            Box::new(m20260411_000002_add_node_drained_proxies::Migration),
+            Box::new(m20260412_000001_create_web_session::Migration),
+            Box::new(m20260412_000002_add_max_enabled_proxies_config::Migration),
         ]
     }
 }
 
 static DATABASE_CONNECTION: OnceCell<DatabaseConnection> = OnceCell::const_new();
 
+/// 全局数据库连接
+///
+/// 启动时由 `main` 调用 [`init_database`] 建立连接并处理失败，之后的调用直接返回该连接。
 pub async fn get_connection() -> &'static DatabaseConnection {
-    DATABASE_CONNECTION.get_or_init(init_sqlite).await
+    match init_database().await {
+        Ok(db) => db,
+        Err(e) => panic!("数据库未连接: {}", e),
+    }
+}
+
+/// 默认 SQLite 数据库地址
+const DEFAULT_DATABASE_URL: &str = "sqlite://data/oxiproxy.db";
+
+/// 从环境变量读取数据库地址（`OXIPROXY_DATABASE_URL` 优先，兼容 `DATABASE_URL`）
+///
+/// 支持 SQLite、PostgreSQL 和 MySQL，其他数据库的地址由 [`check_database_url`] 拒绝。
+pub fn database_url_from_env() -> Option<String> {
+    common::env::var("OXIPROXY_DATABASE_URL").or_else(|| common::env::var("DATABASE_URL"))
+}
+
+/// 数据库连接与连接池参数
+///
+/// 数据库连接先于系统配置建立，因此这些参数只能通过环境变量设置（前三项只对 SQLite 生效）：
+/// - `OXIPROXY_DB_JOURNAL_MODE`：日志模式，默认 `wal`（读写互不阻塞）
+/// - `OXIPROXY_DB_SYNCHRONOUS`：同步级别，默认 `normal`（WAL 下兼顾安全与写入性能）
+/// - `OXIPROXY_DB_BUSY_TIMEOUT_MS`：数据库被锁定时的等待时间，默认 5000
+/// - `OXIPROXY_DB_MAX_CONNECTIONS` / `OXIPROXY_DB_MIN_CONNECTIONS`：连接池大小，默认 10 / 1
+/// - `OXIPROXY_DB_ACQUIRE_TIMEOUT_SECS`：从连接池获取连接的超时时间，默认 30
+#[derive(Debug, Clone)]
+pub struct DatabaseOptions {
+    pub journal_mode: SqliteJournalMode,
+    pub synchronous: SqliteSynchronous,
+    pub busy_timeout: Duration,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout: Duration,
+}
+
+impl Default for DatabaseOptions {
+    fn default() -> Self {
+        Self {
+            journal_mode: SqliteJournalMode::Wal,
+            synchronous: SqliteSynchronous::Normal,
+            busy_timeout: Duration::from_millis(5000),
+            max_connections: 10,
+            min_connections: 1,
+            acquire_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl DatabaseOptions {
+    pub fn from_env() -> Self {
+        use common::env;
+
+        let mut options = Self::default();
+        if let Some(mode) = env::parse("OXIPROXY_DB_JOURNAL_MODE") {
+            options.journal_mode = mode;
+        }
+        if let Some(synchronous) = env::parse("OXIPROXY_DB_SYNCHRONOUS") {
+            options.synchronous = synchronous;
+        }
+        if let Some(ms) = env::parse("OXIPROXY_DB_BUSY_TIMEOUT_MS") {
+            options.busy_timeout = Duration::from_millis(ms);
+        }
+        if let Some(max) = env::parse::<u32>("OXIPROXY_DB_MAX_CONNECTIONS") {
+            options.max_connections = max.max(1);
+        }
+        if let Some(min) = env::parse("OXIPROXY_DB_MIN_CONNECTIONS") {
+            options.min_connections = min;
+        }
+        if let Some(secs) = env::parse("OXIPROXY_DB_ACQUIRE_TIMEOUT_SECS") {
+            options.acquire_timeout = Duration::from_secs(secs);
+        }
+        options.min_connections = options.min_connections.min(options.max_connections);
+        options
+    }
+
+    /// 生成连接参数；内存数据库的每个连接都是独立的库，只能使用单个连接
+    fn connect_options(&self, url: &str) -> ConnectOptions {
+        let max_connections = if url.contains(":memory:") { 1 } else { self.max_connections };
+
+        let mut opt = ConnectOptions::new(url);
+        opt.max_connections(max_connections)
+            .min_connections(self.min_connections.min(max_connections))
+            .acquire_timeout(self.acquire_timeout);
+
+        let (journal_mode, synchronous, busy_timeout) = (self.journal_mode, self.synchronous, self.busy_timeout);
+        opt.map_sqlx_sqlite_opts(move |o| {
+            o.journal_mode(journal_mode)
+                .synchronous(synchronous)
+                .busy_timeout(busy_timeout)
+        });
+        opt
+    }
+}
+
+/// 当前使用的数据库地址（未设置环境变量时为默认 SQLite 文件）
+pub fn database_url() -> String {
+    database_url_from_env().unwrap_or_else(|| DEFAULT_DATABASE_URL.to_string())
+}
+
+/// SQLite 数据库文件路径（内存数据库返回 None）
+pub fn sqlite_file(url: &str) -> Option<&path::Path> {
+    let file = url.strip_prefix("sqlite://")?;
+    let file = file.split('?').next().unwrap_or(file);
+    (!file.is_empty() && file != ":memory:").then(|| path::Path::new(file))
+}
+
+/// 支持的数据库地址前缀
+const SUPPORTED_SCHEMES: [&str; 4] = ["sqlite:", "postgres://", "postgresql://", "mysql://"];
+
+/// 检查数据库地址是否为支持的数据库（SQLite、PostgreSQL、MySQL）
+pub fn check_database_url(url: &str) -> Result<(), String> {
+    if SUPPORTED_SCHEMES.iter().any(|scheme| url.starts_with(scheme)) {
+        return Ok(());
+    }
+    let scheme = url.split_once(':').map_or(url, |(scheme, _)| scheme);
+    Err(format!(
+        "不支持的数据库地址 {}://...：支持 sqlite://、postgres:// 和 mysql://，请检查 OXIPROXY_DATABASE_URL / DATABASE_URL",
+        scheme
+    ))
 }
 
-pub async fn init_sqlite() -> DatabaseConnection {
-    let path = path::Path::new("data/oxiproxy.db");
-    if !path.exists() {
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent).unwrap();
+/// 按环境变量中的连接参数连接数据库（不创建 SQLite 文件）
+pub async fn connect(url: &str) -> Result<DatabaseConnection, DbErr> {
+    check_database_url(url).map_err(DbErr::Custom)?;
+    Database::connect(DatabaseOptions::from_env().connect_options(url)).await
+}
+
+/// 建立全局数据库连接（只在第一次调用时连接），地址不受支持或无法连接时返回错误
+pub async fn init_database() -> Result<&'static DatabaseConnection, DbErr> {
+    DATABASE_CONNECTION.get_or_try_init(connect_from_env).await
+}
+
+async fn connect_from_env() -> Result<DatabaseConnection, DbErr> {
+    let url = database_url();
+    check_database_url(&url).map_err(DbErr::Custom)?;
+
+    // 本地 SQLite 文件不存在时先创建（连接外部数据库时无需本地文件）
+    if let Some(path) = sqlite_file(&url) {
+        if !path.exists() {
+            let create = || -> std::io::Result<()> {
+                if let Some(parent) = path.parent() {
+                    create_dir_all(parent)?;
+                }
+                fs::write(path, "")
+            };
+            create().map_err(|e| DbErr::Custom(format!("创建数据库文件 {} 失败: {}", path.display(), e)))?;
         }
-        fs::write(path, "").unwrap();
     }
-    let db = Database::connect("sqlite://data/oxiproxy.db")
-        .await
-        .expect("failed to connect sqlite");
 
-    db
+    let options = DatabaseOptions::from_env();
+    if url.starts_with("sqlite:") {
+        tracing::info!(
+            "数据库连接参数: journal_mode={:?}, synchronous={:?}, busy_timeout={}ms, 连接池 {}-{}",
+            options.journal_mode,
+            options.synchronous,
+            options.busy_timeout.as_millis(),
+            options.min_connections,
+            options.max_connections,
+        );
+    } else {
+        tracing::info!("数据库连接参数: 连接池 {}-{}", options.min_connections, options.max_connections);
+    }
+    Database::connect(options.connect_options(&url)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_database_url() {
+        assert!(check_database_url(DEFAULT_DATABASE_URL).is_ok());
+        assert!(check_database_url("sqlite::memory:").is_ok());
+        assert!(check_database_url("postgres://user:secret@db/oxiproxy").is_ok());
+        assert!(check_database_url("postgresql://db/oxiproxy").is_ok());
+        assert!(check_database_url("mysql://db/oxiproxy").is_ok());
+        let err = check_database_url("mssql://user:secret@db/oxiproxy").unwrap_err();
+        assert!(err.contains("mssql://"), "{}", err);
+        // 错误信息不带出连接地址中的密码
+        assert!(!err.contains("secret"), "{}", err);
+    }
 }
@@ -0,0 +1,63 @@
+//! 来源 IP 自动处置
+//!
+//! 规则以 JSON 保存在节点（默认规则）和代理上，分别随节点注册响应和代理配置下发；
+//! 节点对来源 IP 限速或封禁后上报事件，这里记录下来供管理员查看和提前解除。
+
+use chrono::{Duration, Utc};
+use sea_orm::{ActiveModelTrait, NotSet, Set};
+use tracing::{error, warn};
+
+use common::grpc::oxiproxy;
+use common::mitigation::MitigationRule;
+
+use crate::entity::mitigation_event;
+use crate::migration::get_connection;
+
+/// 校验并规范化规则 JSON，空字符串表示不设置
+pub fn normalize_config(raw: Option<String>) -> Result<Option<String>, String> {
+    match raw.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(raw) => {
+            let rule: MitigationRule =
+                serde_json::from_str(raw).map_err(|e| format!("来源 IP 处置规则格式错误: {}", e))?;
+            rule.validate().map_err(|e| e.to_string())?;
+            serde_json::to_string(&rule).map(Some).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// 将保存的规则转换为 gRPC 下发格式
+pub fn to_grpc(raw: Option<&str>) -> Option<oxiproxy::GrpcMitigationRule> {
+    raw.and_then(|s| serde_json::from_str::<MitigationRule>(s).ok())
+        .map(|r| oxiproxy::GrpcMitigationRule::from(&r))
+}
+
+/// 将保存的规则转换为代理配置中的规则
+pub fn to_rule(raw: Option<&str>) -> Option<MitigationRule> {
+    raw.and_then(|s| serde_json::from_str(s).ok())
+}
+
+/// 记录节点上报的处置
+pub async fn record(node_id: i64, event: oxiproxy::MitigationEvent) {
+    warn!(
+        "节点 #{} 对来源 {} 在代理 #{} 上执行 {}：{}",
+        node_id, event.source_ip, event.proxy_id, event.action, event.reason
+    );
+    let now = Utc::now().naive_utc();
+    let model = mitigation_event::ActiveModel {
+        id: NotSet,
+        node_id: Set(node_id),
+        proxy_id: Set(event.proxy_id),
+        client_id: Set(event.client_id),
+        source_ip: Set(event.source_ip),
+        action: Set(event.action),
+        reason: Set(event.reason),
+        expires_at: Set(now + Duration::seconds(event.duration_secs as i64)),
+        released_at: Set(None),
+        released_by: Set(None),
+        created_at: Set(now),
+    };
+    if let Err(e) = model.insert(get_connection().await).await {
+        error!("记录来源 IP 处置失败: {}", e);
+    }
+}
@@ -0,0 +1,281 @@
+//! 节点环境克隆（灾难恢复）
+//!
+//! 节点所在的服务器失效时，把其上的所有代理在备用节点上重新创建：逐个校验目标节点的限制、
+//! 端口黑名单、端口预留和端口占用，启用的代理先在目标节点上预留端口再写入并激活，无法创建的
+//! 代理记录原因后跳过。默认同时禁用源节点上的代理，并把 DNS 名称转移到新代理；完成后通知
+//! 相关客户端重新拉取代理列表，客户端随即连接到新节点。
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, NotSet, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+use serde::Serialize;
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+use common::protocol::control::{PortLease, ProxyControl};
+
+use crate::client_stream_manager::ClientStreamManager;
+use crate::config_manager::ConfigManager;
+use crate::entity::{proxy, Node, Proxy};
+
+/// 已克隆的代理
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClonedProxy {
+    pub source_id: i64,
+    pub proxy_id: i64,
+    pub name: String,
+    pub remote_port: u16,
+    pub enabled: bool,
+}
+
+/// 未能克隆的代理
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedProxy {
+    pub source_id: i64,
+    pub name: String,
+    pub remote_port: u16,
+    pub reason: String,
+}
+
+/// 克隆结果
+#[derive(Debug, Default, Serialize)]
+pub struct CloneReport {
+    pub cloned: Vec<ClonedProxy>,
+    pub skipped: Vec<SkippedProxy>,
+}
+
+/// 检查代理能否在目标节点上创建，不能时返回原因
+async fn check_target(
+    p: &proxy::Model,
+    target_id: i64,
+    config_manager: &ConfigManager,
+    db: &DatabaseConnection,
+) -> Result<Option<String>> {
+    // 禁用的代理不占用端口，也不计入代理数量
+    if p.enabled {
+        let (allowed, reason) = crate::node_limiter::validate_node_proxy_limit(target_id, p.remote_port, config_manager, db).await?;
+        if !allowed {
+            return Ok(Some(reason));
+        }
+    }
+
+    let tenant_id = crate::port_blocklist::client_tenant_id(&p.client_id, db).await?;
+    let (allowed, reason) = crate::port_blocklist::validate_proxy_target(
+        Some(target_id),
+        tenant_id,
+        p.remote_port,
+        &p.local_ip,
+        p.local_port,
+        db,
+    )
+    .await?;
+    if !allowed {
+        return Ok(Some(reason));
+    }
+
+    let (allowed, reason) =
+        crate::port_reservation::validate_proxy_port(Some(target_id), &p.client_id, p.remote_port, db).await?;
+    if !allowed {
+        return Ok(Some(reason));
+    }
+    Ok(None)
+}
+
+/// 在一个事务中检查目标节点的端口占用并写入新代理
+async fn insert_clone(
+    p: &proxy::Model,
+    target_id: i64,
+    db: &DatabaseConnection,
+) -> Result<std::result::Result<proxy::Model, String>> {
+    let txn = db.begin().await?;
+    if p.enabled {
+        let existing = crate::anycast::port_owner(Some(target_id), p.remote_port, None, &txn).await?;
+        if let Some(existing) = existing {
+            return Ok(Err(format!("远程端口 {} 已被代理「{}」占用", p.remote_port, existing.name)));
+        }
+    }
+
+    let now = Utc::now().naive_utc();
+    let new_proxy = proxy::ActiveModel {
+        id: NotSet,
+        client_id: Set(p.client_id.clone()),
+        name: Set(p.name.clone()),
+        proxy_type: Set(p.proxy_type.clone()),
+        local_ip: Set(p.local_ip.clone()),
+        local_port: Set(p.local_port),
+        remote_port: Set(p.remote_port),
+        bind_ip: Set(p.bind_ip.clone()),
+        enabled: Set(p.enabled),
+        node_id: Set(Some(target_id)),
+        group_id: Set(p.group_id.clone()),
+        idle_timeout: Set(p.idle_timeout),
+        mitigation_config: Set(p.mitigation_config.clone()),
+        bandwidth_weight: Set(p.bandwidth_weight),
+        priority: Set(p.priority.clone()),
+        local_pool_size: Set(p.local_pool_size),
+        local_source: Set(p.local_source.clone()),
+        anycast_node_ids: Set(None),
+        dns_name: Set(None),
+        service: Set(p.service.clone()),
+        schedule: Set(p.schedule.clone()),
+        expires_at: Set(p.expires_at),
+        stale_at: Set(None),
+        total_bytes_sent: Set(0),
+        total_bytes_received: Set(0),
+        lock_version: Set(0),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(&txn)
+    .await?;
+    txn.commit().await?;
+    Ok(Ok(new_proxy))
+}
+
+/// 禁用源代理并移除其 DNS 名称
+async fn retire_source(proxy_control: &dyn ProxyControl, p: &proxy::Model, db: &DatabaseConnection) -> Result<()> {
+    Proxy::update_many()
+        .col_expr(proxy::Column::Enabled, Expr::value(false))
+        .col_expr(proxy::Column::DnsName, Expr::value(Option::<String>::None))
+        .col_expr(proxy::Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+        .filter(proxy::Column::Id.eq(p.id))
+        .exec(db)
+        .await?;
+    if p.enabled {
+        // 源节点通常已离线，停止失败不影响克隆
+        if let Err(e) = proxy_control.stop_proxy(&p.client_id, p.id).await {
+            warn!("停止源代理 {} (ID: {}) 失败: {}", p.name, p.id, e);
+        }
+    }
+    Ok(())
+}
+
+/// 把 `source_id` 节点上的代理克隆到 `target_id` 节点
+pub async fn clone_node_proxies(
+    source_id: i64,
+    target_id: i64,
+    disable_source: bool,
+    proxy_control: &dyn ProxyControl,
+    client_stream_manager: &ClientStreamManager,
+    config_manager: &ConfigManager,
+    db: &DatabaseConnection,
+) -> Result<CloneReport> {
+    if source_id == target_id {
+        return Err(anyhow!("源节点和目标节点不能相同"));
+    }
+    let source = Node::find_by_id(source_id).one(db).await?.ok_or_else(|| anyhow!("源节点不存在"))?;
+    let target = Node::find_by_id(target_id).one(db).await?.ok_or_else(|| anyhow!("目标节点不存在"))?;
+
+    let proxies = Proxy::find()
+        .filter(proxy::Column::NodeId.eq(source_id))
+        .order_by_asc(proxy::Column::Id)
+        .all(db)
+        .await?;
+
+    let mut report = CloneReport::default();
+    let mut notify: HashSet<String> = HashSet::new();
+    let mut dns_moved = false;
+    for p in proxies {
+        let skip = |reason: String| SkippedProxy {
+            source_id: p.id,
+            name: p.name.clone(),
+            remote_port: p.remote_port,
+            reason,
+        };
+
+        if let Some(reason) = check_target(&p, target_id, config_manager, db).await? {
+            report.skipped.push(skip(reason));
+            continue;
+        }
+
+        // 与创建代理相同的两阶段流程：先预留端口，写入后再激活监听器
+        let lease = PortLease {
+            node_id: Some(target_id),
+            client_id: p.client_id.clone(),
+            proxy_type: p.proxy_type.clone(),
+            remote_port: p.remote_port,
+        };
+        if p.enabled {
+            if let Err(e) = proxy_control.reserve_port(&lease).await {
+                report.skipped.push(skip(format!("预留端口失败: {}", e)));
+                continue;
+            }
+        }
+
+        let cloned = match insert_clone(&p, target_id, db).await {
+            Ok(Ok(cloned)) => cloned,
+            Ok(Err(reason)) => {
+                if p.enabled {
+                    let _ = proxy_control.release_port(&lease).await;
+                }
+                report.skipped.push(skip(reason));
+                continue;
+            }
+            Err(e) => {
+                if p.enabled {
+                    let _ = proxy_control.release_port(&lease).await;
+                }
+                return Err(e);
+            }
+        };
+
+        if cloned.enabled {
+            if let Err(e) = proxy_control.activate_proxy(&lease, cloned.id).await {
+                warn!("激活克隆的代理 {} 失败，删除记录: {}", cloned.name, e);
+                let _ = Proxy::delete_by_id(cloned.id).exec(db).await;
+                let _ = proxy_control.release_port(&lease).await;
+                report.skipped.push(skip(format!("启动代理监听器失败: {}", e)));
+                continue;
+            }
+        }
+        if disable_source {
+            retire_source(proxy_control, &p, db).await?;
+            // DNS 名称唯一，从源代理上移除后再转移到新代理
+            if p.dns_name.is_some() {
+                Proxy::update_many()
+                    .col_expr(proxy::Column::DnsName, Expr::value(p.dns_name.clone()))
+                    .filter(proxy::Column::Id.eq(cloned.id))
+                    .exec(db)
+                    .await?;
+                dns_moved = true;
+            }
+        }
+
+        notify.insert(p.client_id.clone());
+        report.cloned.push(ClonedProxy {
+            source_id: p.id,
+            proxy_id: cloned.id,
+            name: cloned.name,
+            remote_port: cloned.remote_port,
+            enabled: cloned.enabled,
+        });
+    }
+
+    info!(
+        "节点 {} (#{}) 的代理已克隆到节点 {} (#{}): 成功 {} 个，跳过 {} 个",
+        source.name,
+        source_id,
+        target.name,
+        target_id,
+        report.cloned.len(),
+        report.skipped.len()
+    );
+    for s in &report.skipped {
+        warn!("克隆代理 {} (ID: {}，端口 {}) 已跳过: {}", s.name, s.source_id, s.remote_port, s.reason);
+    }
+
+    if dns_moved {
+        crate::dns::request_sync();
+    }
+    crate::cloud_firewall::request_sync();
+    for client_id in notify {
+        client_stream_manager.notify_proxy_change(&client_id).await;
+    }
+    Ok(report)
+}
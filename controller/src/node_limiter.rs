@@ -1,14 +1,65 @@
+//! 节点代理数量、端口范围和流量限制
+//!
+//! 创建代理时校验节点的限制；之后启用已禁用的代理、定时启停或导入配置仍可能让启用的代理数量
+//! 超过上限，因此后台每分钟对账一次：超过节点 `maxProxyCount` 或系统配置 `max_enabled_proxies`
+//! 的部分按创建时间从新到旧禁用，记录日志、通知客户端，并向告警 Webhook 发送
+//! `type` 为 `proxy_cap_exceeded` 的事件。
+
 use anyhow::Result;
+use chrono::Utc;
 use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use common::protocol::control::ProxyControl;
 
-use crate::entity::{proxy, Node, Proxy};
+use crate::client_stream_manager::ClientStreamManager;
+use crate::config_manager::ConfigManager;
+use crate::entity::{node, proxy, Node, Proxy};
+use crate::migration::get_connection;
 use crate::port_limiter::{is_port_in_ranges, parse_port_ranges};
 
+/// 对账间隔
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 全局启用代理数量上限（系统配置 `max_enabled_proxies`，0 表示不限制）
+async fn max_enabled_proxies(config_manager: &ConfigManager) -> u64 {
+    config_manager.get_number("max_enabled_proxies", 0).await.max(0) as u64
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DisabledProxy {
+    id: i64,
+    name: String,
+    client_id: String,
+    owner: String,
+    remote_port: u16,
+}
+
+/// 发送到告警 Webhook 的超限禁用事件
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProxyCapEvent {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// 超出的是节点上限时为节点 ID，全局上限时为空
+    node_id: Option<i64>,
+    node_name: Option<String>,
+    cap: u64,
+    proxies: Vec<DisabledProxy>,
+    at: chrono::DateTime<Utc>,
+}
+
 /// 验证节点的代理数量、端口范围和流量限制
 /// 返回 (是否允许, 错误信息)
 pub async fn validate_node_proxy_limit(
     node_id: i64,
     remote_port: u16,
+    config_manager: &ConfigManager,
     db: &DatabaseConnection,
 ) -> Result<(bool, String)> {
     let node = match Node::find_by_id(node_id).one(db).await? {
@@ -46,6 +97,49 @@ pub async fn validate_node_proxy_limit(
         }
     }
 
+    // 检查节点上报的可用端口范围（系统或防火墙限制）
+    if let Some(caps) = node.capabilities() {
+        if caps.lacks_permission_for(remote_port) {
+            return Ok((
+                false,
+                format!(
+                    "节点没有监听 1024 以下端口的权限（端口 {}）：以 root 运行节点、为节点程序授予 CAP_NET_BIND_SERVICE 能力，或以 root 启动并用 --user 降权，然后重启节点",
+                    remote_port
+                ),
+            ));
+        }
+        if !caps.allows_port(remote_port) {
+            return Ok((
+                false,
+                format!(
+                    "端口 {} 不在节点可用的端口范围内: {}-{}",
+                    remote_port,
+                    caps.port_range_start.unwrap_or(1),
+                    caps.port_range_end.unwrap_or(u16::MAX)
+                ),
+            ));
+        }
+    }
+
+    // 检查全局代理数量限制
+    let global_max = max_enabled_proxies(config_manager).await;
+    if global_max > 0 {
+        let proxy_count = Proxy::find()
+            .filter(proxy::Column::Enabled.eq(true))
+            .count(db)
+            .await?;
+
+        if proxy_count >= global_max {
+            return Ok((
+                false,
+                format!(
+                    "系统启用的代理数量已达上限: {} / {}",
+                    proxy_count, global_max
+                ),
+            ));
+        }
+    }
+
     // 检查代理数量限制
     if let Some(max_count) = node.max_proxy_count {
         let proxy_count = Proxy::find()
@@ -67,3 +161,185 @@ pub async fn validate_node_proxy_limit(
 
     Ok((true, String::new()))
 }
+
+/// 启用的代理中超出 `cap` 的部分（最新创建的在前），`cap` 为 0 时全部超出
+fn newest_excess(mut proxies: Vec<proxy::Model>, cap: u64) -> Vec<proxy::Model> {
+    proxies.retain(|p| p.enabled);
+    proxies.sort_by_key(|p| std::cmp::Reverse((p.created_at, p.id)));
+    let excess = proxies.len().saturating_sub(cap as usize);
+    proxies.truncate(excess);
+    proxies
+}
+
+/// 禁用超出上限的代理并发送通知
+async fn disable_excess(
+    proxy_control: &dyn ProxyControl,
+    node: Option<&node::Model>,
+    cap: u64,
+    excess: Vec<proxy::Model>,
+    db: &DatabaseConnection,
+) -> Result<Vec<proxy::Model>> {
+    if excess.is_empty() {
+        return Ok(excess);
+    }
+    let mut proxies = Vec::with_capacity(excess.len());
+    for p in &excess {
+        crate::expiration::disable_proxy(proxy_control, p, db).await?;
+        let owner = crate::expiration::owner_name(&p.client_id, db).await;
+        match node {
+            Some(n) => warn!(
+                "节点 {} (#{}) 启用的代理超过上限 {}，已禁用最新的代理 {} (ID: {}，端口 {}，客户端 #{}，所有者: {})",
+                n.name, n.id, cap, p.name, p.id, p.remote_port, p.client_id, owner
+            ),
+            None => warn!(
+                "启用的代理超过全局上限 {}，已禁用最新的代理 {} (ID: {}，端口 {}，客户端 #{}，所有者: {})",
+                cap, p.name, p.id, p.remote_port, p.client_id, owner
+            ),
+        }
+        proxies.push(DisabledProxy {
+            id: p.id,
+            name: p.name.clone(),
+            client_id: p.client_id.clone(),
+            owner,
+            remote_port: p.remote_port,
+        });
+    }
+    crate::alerting::post_webhooks(ProxyCapEvent {
+        kind: "proxy_cap_exceeded",
+        node_id: node.map(|n| n.id),
+        node_name: node.map(|n| n.name.clone()),
+        cap,
+        proxies,
+        at: Utc::now(),
+    });
+    Ok(excess)
+}
+
+/// 禁用超出节点上限和全局上限的代理，返回被禁用的代理
+async fn reconcile(
+    proxy_control: &dyn ProxyControl,
+    client_stream_manager: &ClientStreamManager,
+    config_manager: &ConfigManager,
+    db: &DatabaseConnection,
+) -> Result<Vec<proxy::Model>> {
+    let mut disabled = Vec::new();
+
+    // 1. 节点上限
+    let nodes = Node::find()
+        .filter(node::Column::MaxProxyCount.is_not_null())
+        .all(db)
+        .await?;
+    for n in nodes {
+        let max_count = n.max_proxy_count.unwrap_or(0).max(0) as u64;
+        let proxies = Proxy::find()
+            .filter(proxy::Column::NodeId.eq(n.id))
+            .filter(proxy::Column::Enabled.eq(true))
+            .all(db)
+            .await?;
+        let excess = newest_excess(proxies, max_count);
+        disabled.extend(disable_excess(proxy_control, Some(&n), max_count, excess, db).await?);
+    }
+
+    // 2. 全局上限
+    let global_max = max_enabled_proxies(config_manager).await;
+    if global_max > 0 {
+        let proxies = Proxy::find().filter(proxy::Column::Enabled.eq(true)).all(db).await?;
+        let excess = newest_excess(proxies, global_max);
+        disabled.extend(disable_excess(proxy_control, None, global_max, excess, db).await?);
+    }
+
+    let clients: HashSet<&str> = disabled.iter().map(|p| p.client_id.as_str()).collect();
+    for client_id in clients {
+        client_stream_manager.notify_proxy_change(client_id).await;
+    }
+    Ok(disabled)
+}
+
+/// 启动代理数量上限对账任务
+pub fn start_proxy_cap_reconciler(
+    proxy_control: Arc<dyn ProxyControl>,
+    client_stream_manager: Arc<ClientStreamManager>,
+    config_manager: Arc<ConfigManager>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RECONCILE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let db = get_connection().await;
+            match reconcile(proxy_control.as_ref(), &client_stream_manager, &config_manager, db).await {
+                Ok(disabled) if !disabled.is_empty() => {
+                    info!("代理数量上限对账完成: 禁用 {} 个超出上限的代理", disabled.len());
+                }
+                Ok(_) => {}
+                Err(e) => error!("代理数量上限对账失败: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy(id: i64, created_secs: i64, enabled: bool) -> proxy::Model {
+        let created_at = chrono::DateTime::from_timestamp(1_700_000_000 + created_secs, 0).unwrap().naive_utc();
+        proxy::Model {
+            id,
+            client_id: "1".to_string(),
+            name: format!("p{}", id),
+            proxy_type: "tcp".to_string(),
+            local_ip: "127.0.0.1".to_string(),
+            local_port: 22,
+            remote_port: 10000 + id as u16,
+            bind_ip: None,
+            enabled,
+            anycast_node_ids: None,
+            node_id: Some(1),
+            group_id: None,
+            idle_timeout: None,
+            mitigation_config: None,
+            bandwidth_weight: None,
+            priority: None,
+            local_pool_size: None,
+            local_source: None,
+            dns_name: None,
+            service: None,
+            schedule: None,
+            expires_at: None,
+            stale_at: None,
+            total_bytes_sent: 0,
+            total_bytes_received: 0,
+            lock_version: 0,
+            created_at,
+            updated_at: created_at,
+        }
+    }
+
+    fn ids(proxies: &[proxy::Model]) -> Vec<i64> {
+        proxies.iter().map(|p| p.id).collect()
+    }
+
+    #[test]
+    fn test_newest_excess() {
+        let proxies = vec![proxy(1, 0, true), proxy(2, 30, true), proxy(3, 10, true), proxy(4, 20, true)];
+
+        // 按创建时间从新到旧选出超出的部分
+        assert_eq!(ids(&newest_excess(proxies.clone(), 2)), [2, 4]);
+        assert_eq!(ids(&newest_excess(proxies.clone(), 3)), [2]);
+        assert!(newest_excess(proxies.clone(), 4).is_empty());
+        assert!(newest_excess(proxies.clone(), 10).is_empty());
+        // 上限为 0 时全部超出
+        assert_eq!(ids(&newest_excess(proxies, 0)), [2, 4, 3, 1]);
+    }
+
+    #[test]
+    fn test_newest_excess_ignores_disabled_and_breaks_ties_by_id() {
+        // 已禁用的代理不计入数量，也不会被再次禁用
+        let proxies = vec![proxy(1, 0, true), proxy(2, 50, false), proxy(3, 10, true)];
+        assert_eq!(ids(&newest_excess(proxies, 1)), [3]);
+
+        // 创建时间相同时 ID 大的视为更新
+        let proxies = vec![proxy(5, 10, true), proxy(7, 10, true), proxy(6, 10, true)];
+        assert_eq!(ids(&newest_excess(proxies, 1)), [7, 6]);
+    }
+}
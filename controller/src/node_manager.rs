@@ -16,7 +16,7 @@ use common::grpc::oxiproxy::controller_to_agent_message::Payload as ControllerPa
 use common::grpc::oxiproxy::agent_server_response::Result as AgentResult;
 use common::grpc::pending_requests::PendingRequests;
 use common::protocol::control::{
-    ConnectedClient, LogEntry, ProxyControl, ServerStatus,
+    ConnectedClient, LogEntry, PortLease, ProxyControl, ServerStatus,
 };
 
 use crate::entity::Node;
@@ -26,6 +26,10 @@ use crate::migration::get_connection;
 struct NodeStream {
     tx: mpsc::Sender<Result<oxiproxy::ControllerToAgentMessage, tonic::Status>>,
     pending: PendingRequests<oxiproxy::AgentServerResponse>,
+    /// 进行中的连通性探测：request_id -> 结果接收端（节点断开时随流一起丢弃）
+    probes: std::sync::Mutex<HashMap<String, mpsc::Sender<oxiproxy::ProbeStep>>>,
+    /// 进行中的实时日志跟随：request_id -> 日志接收端
+    log_follows: std::sync::Mutex<HashMap<String, mpsc::Sender<oxiproxy::NodeLogChunk>>>,
 }
 
 /// 多节点管理器
@@ -58,11 +62,31 @@ impl NodeManager {
         let stream = NodeStream {
             tx,
             pending: PendingRequests::new(),
+            probes: std::sync::Mutex::new(HashMap::new()),
+            log_follows: std::sync::Mutex::new(HashMap::new()),
         };
         self.streams.write().await.insert(node_id, stream);
         info!("节点 #{} gRPC 流已注册", node_id);
     }
 
+    /// 向所有已连接节点下发重连延迟（0 ~ `spread` 之间随机），Controller 关闭前调用，返回下发的节点数
+    pub async fn broadcast_reconnect_hint(&self, spread: Duration) -> usize {
+        let streams = self.streams.read().await;
+        let mut sent = 0;
+        for stream in streams.values() {
+            let delay = common::grpc::reconnect::jitter(spread);
+            let msg = oxiproxy::ControllerToAgentMessage {
+                payload: Some(ControllerPayload::ReconnectHint(oxiproxy::ReconnectHint {
+                    delay_ms: delay.as_millis() as u32,
+                })),
+            };
+            if stream.tx.try_send(Ok(msg)).is_ok() {
+                sent += 1;
+            }
+        }
+        sent
+    }
+
     /// 移除一个 Agent Server 的 gRPC 流
     pub async fn unregister_node_stream(&self, node_id: i64) {
         self.streams.write().await.remove(&node_id);
@@ -105,6 +129,72 @@ impl NodeManager {
         PendingRequests::wait(rx, Duration::from_secs(10)).await
     }
 
+    /// 在指定节点上启动代理监听器
+    pub async fn start_proxy_on_node(&self, node_id: i64, client_id: &str, proxy_id: i64) -> Result<()> {
+        let cmd = ControllerPayload::StartProxy(oxiproxy::StartProxyCommand {
+            request_id: String::new(),
+            client_id: client_id.to_string(),
+            proxy_id,
+        });
+
+        let resp = self.send_command_and_wait(node_id, cmd).await?;
+
+        match resp.result {
+            Some(AgentResult::CommandAck(ack)) => {
+                if ack.success {
+                    Ok(())
+                } else {
+                    Err(anyhow!("启动代理失败: {}", ack.error.unwrap_or_default()))
+                }
+            }
+            _ => Err(anyhow!("收到意外的响应类型")),
+        }
+    }
+
+    /// 在指定节点上停止代理监听器
+    pub async fn stop_proxy_on_node(&self, node_id: i64, client_id: &str, proxy_id: i64) -> Result<()> {
+        let cmd = ControllerPayload::StopProxy(oxiproxy::StopProxyCommand {
+            request_id: String::new(),
+            client_id: client_id.to_string(),
+            proxy_id,
+        });
+
+        let resp = self.send_command_and_wait(node_id, cmd).await?;
+
+        match resp.result {
+            Some(AgentResult::CommandAck(ack)) => {
+                if ack.success {
+                    Ok(())
+                } else {
+                    Err(anyhow!("停止代理失败: {}", ack.error.unwrap_or_default()))
+                }
+            }
+            _ => Err(anyhow!("收到意外的响应类型")),
+        }
+    }
+
+    /// 发送端口预留相关命令并检查确认
+    async fn send_lease_command(&self, lease: &PortLease, payload: ControllerPayload, action: &str) -> Result<()> {
+        let node_id = match lease.node_id {
+            Some(id) => id,
+            None => self.resolve_node_for_client(&lease.client_id).await?
+                .ok_or_else(|| anyhow!("客户端 {} 未关联任何节点", lease.client_id))?,
+        };
+
+        let resp = self.send_command_and_wait(node_id, payload).await?;
+
+        match resp.result {
+            Some(AgentResult::CommandAck(ack)) => {
+                if ack.success {
+                    Ok(())
+                } else {
+                    Err(anyhow!("{}失败: {}", action, ack.error.unwrap_or_default()))
+                }
+            }
+            _ => Err(anyhow!("收到意外的响应类型")),
+        }
+    }
+
     /// 根据 client_id 查找所属节点 ID
     async fn resolve_node_for_client(&self, client_id: &str) -> Result<Option<i64>> {
         let db = get_connection().await;
@@ -119,6 +209,55 @@ impl NodeManager {
         Ok(proxy.and_then(|p| p.node_id))
     }
 
+    /// 发布代理的所有节点（多节点发布时不止一个）；代理已删除或未指定节点时按客户端关联的节点处理
+    async fn resolve_nodes_for_proxy(&self, client_id: &str, proxy_id: i64) -> Result<Vec<i64>> {
+        let db = get_connection().await;
+        if let Some(p) = crate::entity::Proxy::find_by_id(proxy_id).one(db).await? {
+            if p.node_id.is_some() {
+                return Ok(p.node_ids());
+            }
+        }
+        let node_id = self.resolve_node_for_client(client_id).await?
+            .ok_or_else(|| anyhow!("客户端 {} 未关联任何节点", client_id))?;
+        Ok(vec![node_id])
+    }
+
+    /// 获取单个节点的实时状态
+    pub async fn get_node_server_status(&self, node_id: i64) -> Result<ServerStatus> {
+        let cmd = ControllerPayload::GetStatus(oxiproxy::GetStatusCommand {
+            request_id: String::new(),
+        });
+        let resp = self.send_command_and_wait(node_id, cmd).await?;
+        match resp.result {
+            Some(AgentResult::ServerStatus(status)) => Ok(ServerStatus {
+                connected_clients: status
+                    .connected_clients
+                    .into_iter()
+                    .map(|c| ConnectedClient {
+                        client_id: c.client_id,
+                        remote_address: c.remote_address,
+                        protocol: c.protocol,
+                    })
+                    .collect(),
+                active_proxy_count: status.active_proxy_count as usize,
+                reaped_connections: status.reaped_connections,
+                accept_rate_limited: status.accept_rate_limited,
+                accept_pending_rejected: status.accept_pending_rejected,
+                active_relays: status.active_relays,
+                relay_limit: status.relay_limit,
+                relay_rejected: status.relay_rejected,
+                open_fds: status.open_fds,
+                fd_limit: status.fd_limit,
+                // 旧版节点不上报
+                kcp: status.kcp.map(Into::into).unwrap_or_default(),
+            }),
+            Some(AgentResult::CommandAck(ack)) if !ack.success => {
+                Err(anyhow!("{}", ack.error.unwrap_or_else(|| "未知错误".to_string())))
+            }
+            _ => Err(anyhow!("收到意外的响应类型")),
+        }
+    }
+
     /// 健康检查所有节点
     pub async fn check_all_nodes(&self) -> Vec<(i64, bool)> {
         let db = get_connection().await;
@@ -168,11 +307,174 @@ impl NodeManager {
         }
     }
 
-    /// 向节点推送协议变更命令
-    pub async fn send_update_protocol(&self, node_id: i64, protocol: &str) -> Result<()> {
+    /// 获取节点记录的代理生命周期事件
+    pub async fn get_proxy_events(
+        &self,
+        node_id: i64,
+        proxy_id: Option<i64>,
+        client_id: Option<String>,
+        limit: u32,
+    ) -> Result<Vec<oxiproxy::ProxyEvent>> {
+        let cmd = ControllerPayload::GetProxyEvents(oxiproxy::GetProxyEventsCommand {
+            request_id: String::new(),
+            proxy_id,
+            client_id,
+            limit,
+        });
+
+        let resp = self.send_command_and_wait(node_id, cmd).await?;
+
+        match resp.result {
+            Some(AgentResult::ProxyEvents(events)) => Ok(events.events),
+            _ => Err(anyhow!("收到意外的响应类型")),
+        }
+    }
+
+    /// 在节点上发起连通性探测，返回逐条接收结果的通道（最后一条的 `done` 为 true）
+    pub async fn start_probe(
+        &self,
+        node_id: i64,
+        mut cmd: oxiproxy::ProbeCommand,
+    ) -> Result<mpsc::Receiver<oxiproxy::ProbeStep>> {
+        let (step_tx, step_rx) = mpsc::channel(64);
+        cmd.request_id = uuid::Uuid::new_v4().to_string();
+
+        let tx = {
+            let streams = self.streams.read().await;
+            let stream = streams.get(&node_id)
+                .ok_or_else(|| anyhow!("节点 #{} 未连接", node_id))?;
+            stream.probes.lock().unwrap().insert(cmd.request_id.clone(), step_tx);
+            stream.tx.clone()
+        };
+
+        let msg = oxiproxy::ControllerToAgentMessage {
+            payload: Some(ControllerPayload::Probe(cmd)),
+        };
+        tx.send(Ok(msg)).await
+            .map_err(|_| anyhow!("发送命令到节点 #{} 失败", node_id))?;
+
+        Ok(step_rx)
+    }
+
+    /// 转发节点上报的探测结果（由 ProbeStep 消息触发）
+    pub async fn forward_probe_step(&self, node_id: i64, step: oxiproxy::ProbeStep) {
+        let sender = {
+            let streams = self.streams.read().await;
+            let Some(stream) = streams.get(&node_id) else {
+                return;
+            };
+            let mut probes = stream.probes.lock().unwrap();
+            if step.done {
+                probes.remove(&step.request_id)
+            } else {
+                probes.get(&step.request_id).cloned()
+            }
+        };
+
+        // 不阻塞节点的控制流：接收方处理不过来时丢弃中间结果
+        if let Some(sender) = sender {
+            let request_id = step.request_id.clone();
+            match sender.try_send(step) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    warn!("节点 #{} 探测 {} 的结果积压，丢弃一条", node_id, request_id);
+                }
+                // 调用方已放弃等待（请求超时 / 连接断开）
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    if let Some(stream) = self.streams.read().await.get(&node_id) {
+                        stream.probes.lock().unwrap().remove(&request_id);
+                    }
+                }
+            }
+        }
+    }
+
+    /// 开始实时跟随节点日志，返回 request_id 和日志接收通道（节点断开时通道关闭）
+    pub async fn follow_logs(&self, node_id: i64, level: &str) -> Result<(String, mpsc::Receiver<oxiproxy::NodeLogChunk>)> {
+        let (chunk_tx, chunk_rx) = mpsc::channel(64);
+        let request_id = uuid::Uuid::new_v4().to_string();
+
+        let tx = {
+            let streams = self.streams.read().await;
+            let stream = streams.get(&node_id)
+                .ok_or_else(|| anyhow!("节点 #{} 未连接", node_id))?;
+            stream.log_follows.lock().unwrap().insert(request_id.clone(), chunk_tx);
+            stream.tx.clone()
+        };
+
+        let msg = oxiproxy::ControllerToAgentMessage {
+            payload: Some(ControllerPayload::FollowLogs(oxiproxy::FollowLogsCommand {
+                request_id: request_id.clone(),
+                level: level.to_string(),
+            })),
+        };
+        tx.send(Ok(msg)).await
+            .map_err(|_| anyhow!("发送命令到节点 #{} 失败", node_id))?;
+
+        Ok((request_id, chunk_rx))
+    }
+
+    /// 停止实时跟随节点日志
+    pub async fn stop_follow_logs(&self, node_id: i64, request_id: &str) {
+        let tx = {
+            let streams = self.streams.read().await;
+            let Some(stream) = streams.get(&node_id) else {
+                return;
+            };
+            stream.log_follows.lock().unwrap().remove(request_id);
+            stream.tx.clone()
+        };
+        let msg = oxiproxy::ControllerToAgentMessage {
+            payload: Some(ControllerPayload::StopFollowLogs(oxiproxy::StopFollowLogsCommand {
+                request_id: request_id.to_string(),
+            })),
+        };
+        let _ = tx.send(Ok(msg)).await;
+    }
+
+    /// 转发节点上报的日志（由 NodeLogChunk 消息触发），没有接收方的跟随（如 Controller 重启前发起的）通知节点停止
+    pub async fn forward_log_chunk(&self, node_id: i64, chunk: oxiproxy::NodeLogChunk) {
+        let sender = {
+            let streams = self.streams.read().await;
+            let Some(stream) = streams.get(&node_id) else {
+                return;
+            };
+            let follows = stream.log_follows.lock().unwrap();
+            follows.get(&chunk.request_id).cloned()
+        };
+
+        let request_id = chunk.request_id.clone();
+        let delivered = match sender {
+            // 不阻塞节点的控制流：接收方处理不过来时丢弃这一批
+            Some(sender) => match sender.try_send(chunk) {
+                Ok(()) => true,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    warn!("节点 #{} 日志跟随 {} 积压，丢弃一批", node_id, request_id);
+                    true
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => false,
+            },
+            None => false,
+        };
+        if !delivered {
+            self.stop_follow_logs(node_id, &request_id).await;
+        }
+    }
+
+    /// 向节点推送协议（及 KCP 参数）变更命令
+    pub async fn send_update_protocol(
+        &self,
+        node_id: i64,
+        protocol: &str,
+        kcp: Option<oxiproxy::GrpcKcpConfig>,
+        quic: Option<oxiproxy::GrpcQuicConfig>,
+    ) -> Result<()> {
         let cmd = ControllerPayload::UpdateProtocol(oxiproxy::UpdateProtocolCommand {
             request_id: String::new(),
             tunnel_protocol: protocol.to_string(),
+            kcp,
+            quic,
+            staged: false,
         });
 
         let resp = self.send_command_and_wait(node_id, cmd).await?;
@@ -189,10 +491,73 @@ impl NodeManager {
         }
     }
 
-    pub async fn send_update_speed_limit(&self, node_id: i64, speed_limit: i64) -> Result<()> {
+    /// 分阶段切换协议：节点在备用端口上启动新监听器并保留旧监听器
+    ///
+    /// 返回新监听器的端口；旧版本节点不支持分阶段切换，会直接原地切换并返回 `None`。
+    pub async fn stage_protocol(
+        &self,
+        node_id: i64,
+        protocol: &str,
+        kcp: Option<oxiproxy::GrpcKcpConfig>,
+        quic: Option<oxiproxy::GrpcQuicConfig>,
+    ) -> Result<Option<u16>> {
+        let cmd = ControllerPayload::UpdateProtocol(oxiproxy::UpdateProtocolCommand {
+            request_id: String::new(),
+            tunnel_protocol: protocol.to_string(),
+            kcp,
+            quic,
+            staged: true,
+        });
+
+        let resp = self.send_command_and_wait(node_id, cmd).await?;
+
+        match resp.result {
+            Some(AgentResult::ProtocolStaged(staged)) => u16::try_from(staged.port)
+                .map(Some)
+                .map_err(|_| anyhow!("节点返回的端口无效: {}", staged.port)),
+            Some(AgentResult::CommandAck(ack)) => {
+                if ack.success {
+                    Ok(None)
+                } else {
+                    Err(anyhow!("分阶段切换失败: {}", ack.error.unwrap_or_default()))
+                }
+            }
+            _ => Err(anyhow!("收到意外的响应类型")),
+        }
+    }
+
+    /// 停止节点在分阶段切换中保留的旧监听器
+    pub async fn retire_previous_listener(&self, node_id: i64) -> Result<()> {
+        let cmd = ControllerPayload::RetireListener(oxiproxy::RetireListenerCommand {
+            request_id: String::new(),
+        });
+
+        let resp = self.send_command_and_wait(node_id, cmd).await?;
+
+        match resp.result {
+            Some(AgentResult::CommandAck(ack)) => {
+                if ack.success {
+                    Ok(())
+                } else {
+                    Err(anyhow!("停止旧监听器失败: {}", ack.error.unwrap_or_default()))
+                }
+            }
+            _ => Err(anyhow!("收到意外的响应类型")),
+        }
+    }
+
+    pub async fn send_update_speed_limit(
+        &self,
+        node_id: i64,
+        speed_limit: i64,
+        fair_share: bool,
+        user_limits: Vec<oxiproxy::UserSpeedLimit>,
+    ) -> Result<()> {
         let cmd = ControllerPayload::UpdateSpeedLimit(oxiproxy::UpdateSpeedLimitCommand {
             request_id: String::new(),
             speed_limit,
+            user_limits,
+            fair_share,
         });
 
         let resp = self.send_command_and_wait(node_id, cmd).await?;
@@ -209,10 +574,106 @@ impl NodeManager {
         }
     }
 
-    /// 向节点发送软件更新指令
-    pub async fn send_software_update(&self, node_id: i64) -> Result<oxiproxy::SoftwareUpdateResponse> {
+    /// 推送节点默认的来源 IP 处置规则（`None` 取消）
+    pub async fn send_update_mitigation(&self, node_id: i64, rule: Option<oxiproxy::GrpcMitigationRule>) -> Result<()> {
+        let cmd = ControllerPayload::UpdateMitigation(oxiproxy::UpdateMitigationCommand {
+            request_id: String::new(),
+            rule,
+        });
+
+        let resp = self.send_command_and_wait(node_id, cmd).await?;
+
+        match resp.result {
+            Some(AgentResult::CommandAck(ack)) => {
+                if ack.success {
+                    Ok(())
+                } else {
+                    Err(anyhow!("处置规则更新失败: {}", ack.error.unwrap_or_default()))
+                }
+            }
+            _ => Err(anyhow!("收到意外的响应类型")),
+        }
+    }
+
+    /// 解除节点对来源 IP 的处置
+    pub async fn send_release_mitigation(&self, node_id: i64, proxy_id: i64, source_ip: &str) -> Result<()> {
+        let cmd = ControllerPayload::ReleaseMitigation(oxiproxy::ReleaseMitigationCommand {
+            request_id: String::new(),
+            proxy_id,
+            source_ip: source_ip.to_string(),
+        });
+
+        let resp = self.send_command_and_wait(node_id, cmd).await?;
+
+        match resp.result {
+            Some(AgentResult::CommandAck(ack)) => {
+                if ack.success {
+                    Ok(())
+                } else {
+                    Err(anyhow!("解除处置失败: {}", ack.error.unwrap_or_default()))
+                }
+            }
+            _ => Err(anyhow!("收到意外的响应类型")),
+        }
+    }
+
+    /// 在节点上开始抓包，节点确认后返回，抓包文件之后通过 CaptureChunk 上传
+    pub async fn start_capture(
+        &self,
+        node_id: i64,
+        capture_id: &str,
+        proxy_id: i64,
+        duration_secs: u32,
+        max_bytes: u64,
+    ) -> Result<()> {
+        let cmd = ControllerPayload::StartCapture(oxiproxy::StartCaptureCommand {
+            request_id: String::new(),
+            capture_id: capture_id.to_string(),
+            proxy_id,
+            duration_secs,
+            max_bytes,
+        });
+
+        let resp = self.send_command_and_wait(node_id, cmd).await?;
+
+        match resp.result {
+            Some(AgentResult::CommandAck(ack)) => {
+                if ack.success {
+                    Ok(())
+                } else {
+                    Err(anyhow!("开始抓包失败: {}", ack.error.unwrap_or_default()))
+                }
+            }
+            _ => Err(anyhow!("收到意外的响应类型")),
+        }
+    }
+
+    /// 提前结束节点上的抓包
+    pub async fn stop_capture(&self, node_id: i64, capture_id: &str) -> Result<()> {
+        let tx = {
+            let streams = self.streams.read().await;
+            let stream = streams.get(&node_id)
+                .ok_or_else(|| anyhow!("节点 #{} 未连接", node_id))?;
+            stream.tx.clone()
+        };
+        let msg = oxiproxy::ControllerToAgentMessage {
+            payload: Some(ControllerPayload::StopCapture(oxiproxy::StopCaptureCommand {
+                capture_id: capture_id.to_string(),
+            })),
+        };
+        tx.send(Ok(msg)).await
+            .map_err(|_| anyhow!("发送命令到节点 #{} 失败", node_id))
+    }
+
+    /// 向节点发送软件更新指令（`target_version` 为空时更新到最新版本）
+    pub async fn send_software_update(
+        &self,
+        node_id: i64,
+        target_version: Option<String>,
+    ) -> Result<oxiproxy::SoftwareUpdateResponse> {
         let cmd = ControllerPayload::SoftwareUpdate(oxiproxy::SoftwareUpdateCommand {
             request_id: String::new(),
+            target_version,
         });
 
         // 使用自定义超时（120秒，等待下载）
@@ -272,14 +733,46 @@ fn replace_request_id(payload: ControllerPayload, request_id: &str) -> Controlle
             cmd.request_id = request_id.to_string();
             ControllerPayload::UpdateProtocol(cmd)
         }
+        ControllerPayload::RetireListener(mut cmd) => {
+            cmd.request_id = request_id.to_string();
+            ControllerPayload::RetireListener(cmd)
+        }
         ControllerPayload::UpdateSpeedLimit(mut cmd) => {
             cmd.request_id = request_id.to_string();
             ControllerPayload::UpdateSpeedLimit(cmd)
         }
+        ControllerPayload::UpdateMitigation(mut cmd) => {
+            cmd.request_id = request_id.to_string();
+            ControllerPayload::UpdateMitigation(cmd)
+        }
+        ControllerPayload::ReleaseMitigation(mut cmd) => {
+            cmd.request_id = request_id.to_string();
+            ControllerPayload::ReleaseMitigation(cmd)
+        }
         ControllerPayload::SoftwareUpdate(mut cmd) => {
             cmd.request_id = request_id.to_string();
             ControllerPayload::SoftwareUpdate(cmd)
         }
+        ControllerPayload::ReservePort(mut cmd) => {
+            cmd.request_id = request_id.to_string();
+            ControllerPayload::ReservePort(cmd)
+        }
+        ControllerPayload::ActivateProxy(mut cmd) => {
+            cmd.request_id = request_id.to_string();
+            ControllerPayload::ActivateProxy(cmd)
+        }
+        ControllerPayload::ReleasePort(mut cmd) => {
+            cmd.request_id = request_id.to_string();
+            ControllerPayload::ReleasePort(cmd)
+        }
+        ControllerPayload::GetProxyEvents(mut cmd) => {
+            cmd.request_id = request_id.to_string();
+            ControllerPayload::GetProxyEvents(cmd)
+        }
+        ControllerPayload::StartCapture(mut cmd) => {
+            cmd.request_id = request_id.to_string();
+            ControllerPayload::StartCapture(cmd)
+        }
         other => other,
     }
 }
@@ -287,51 +780,46 @@ fn replace_request_id(payload: ControllerPayload, request_id: &str) -> Controlle
 #[async_trait]
 impl ProxyControl for NodeManager {
     async fn start_proxy(&self, client_id: &str, proxy_id: i64) -> Result<()> {
-        let node_id = self.resolve_node_for_client(client_id).await?
-            .ok_or_else(|| anyhow!("客户端 {} 未关联任何节点", client_id))?;
-
-        let cmd = ControllerPayload::StartProxy(oxiproxy::StartProxyCommand {
-            request_id: String::new(),
-            client_id: client_id.to_string(),
-            proxy_id,
-        });
-
-        let resp = self.send_command_and_wait(node_id, cmd).await?;
+        for node_id in self.resolve_nodes_for_proxy(client_id, proxy_id).await? {
+            self.start_proxy_on_node(node_id, client_id, proxy_id).await?;
+        }
+        Ok(())
+    }
 
-        match resp.result {
-            Some(AgentResult::CommandAck(ack)) => {
-                if ack.success {
-                    Ok(())
-                } else {
-                    Err(anyhow!("启动代理失败: {}", ack.error.unwrap_or_default()))
-                }
+    async fn stop_proxy(&self, client_id: &str, proxy_id: i64) -> Result<()> {
+        let mut result = Ok(());
+        for node_id in self.resolve_nodes_for_proxy(client_id, proxy_id).await? {
+            // 某个节点失败时仍停止其余节点上的监听器
+            if let Err(e) = self.stop_proxy_on_node(node_id, client_id, proxy_id).await {
+                result = Err(e);
             }
-            _ => Err(anyhow!("收到意外的响应类型")),
         }
+        result
     }
 
-    async fn stop_proxy(&self, client_id: &str, proxy_id: i64) -> Result<()> {
-        let node_id = self.resolve_node_for_client(client_id).await?
-            .ok_or_else(|| anyhow!("客户端 {} 未关联任何节点", client_id))?;
+    async fn reserve_port(&self, lease: &PortLease) -> Result<()> {
+        let cmd = ControllerPayload::ReservePort(oxiproxy::ReservePortCommand {
+            request_id: String::new(),
+            lease: Some(lease.into()),
+        });
+        self.send_lease_command(lease, cmd, "预留端口").await
+    }
 
-        let cmd = ControllerPayload::StopProxy(oxiproxy::StopProxyCommand {
+    async fn activate_proxy(&self, lease: &PortLease, proxy_id: i64) -> Result<()> {
+        let cmd = ControllerPayload::ActivateProxy(oxiproxy::ActivateProxyCommand {
             request_id: String::new(),
-            client_id: client_id.to_string(),
+            lease: Some(lease.into()),
             proxy_id,
         });
+        self.send_lease_command(lease, cmd, "启动代理").await
+    }
 
-        let resp = self.send_command_and_wait(node_id, cmd).await?;
-
-        match resp.result {
-            Some(AgentResult::CommandAck(ack)) => {
-                if ack.success {
-                    Ok(())
-                } else {
-                    Err(anyhow!("停止代理失败: {}", ack.error.unwrap_or_default()))
-                }
-            }
-            _ => Err(anyhow!("收到意外的响应类型")),
-        }
+    async fn release_port(&self, lease: &PortLease) -> Result<()> {
+        let cmd = ControllerPayload::ReleasePort(oxiproxy::ReleasePortCommand {
+            request_id: String::new(),
+            lease: Some(lease.into()),
+        });
+        self.send_lease_command(lease, cmd, "释放端口").await
     }
 
     async fn get_connected_clients(&self) -> Result<Vec<ConnectedClient>> {
@@ -392,27 +880,34 @@ impl ProxyControl for NodeManager {
     }
 
     async fn get_server_status(&self) -> Result<ServerStatus> {
-        let node_ids = self.get_loaded_node_ids().await;
-        let mut all_clients = Vec::new();
-        let mut total_proxy_count = 0;
-
-        for node_id in node_ids {
-            let cmd = ControllerPayload::GetStatus(oxiproxy::GetStatusCommand {
-                request_id: String::new(),
-            });
+        let mut total = ServerStatus {
+            connected_clients: Vec::new(),
+            active_proxy_count: 0,
+            reaped_connections: 0,
+            accept_rate_limited: 0,
+            accept_pending_rejected: 0,
+            active_relays: 0,
+            relay_limit: 0,
+            relay_rejected: 0,
+            open_fds: 0,
+            fd_limit: 0,
+            kcp: Default::default(),
+        };
 
-            match self.send_command_and_wait(node_id, cmd).await {
-                Ok(resp) => {
-                    if let Some(AgentResult::ServerStatus(status)) = resp.result {
-                        total_proxy_count += status.active_proxy_count as usize;
-                        for c in status.connected_clients {
-                            all_clients.push(ConnectedClient {
-                                client_id: c.client_id,
-                                remote_address: c.remote_address,
-                                protocol: c.protocol,
-                            });
-                        }
-                    }
+        for node_id in self.get_loaded_node_ids().await {
+            match self.get_node_server_status(node_id).await {
+                Ok(status) => {
+                    total.connected_clients.extend(status.connected_clients);
+                    total.active_proxy_count += status.active_proxy_count;
+                    total.reaped_connections += status.reaped_connections;
+                    total.accept_rate_limited += status.accept_rate_limited;
+                    total.accept_pending_rejected += status.accept_pending_rejected;
+                    total.active_relays += status.active_relays;
+                    total.relay_limit += status.relay_limit;
+                    total.relay_rejected += status.relay_rejected;
+                    total.open_fds += status.open_fds;
+                    total.fd_limit += status.fd_limit;
+                    total.kcp += status.kcp;
                 }
                 Err(e) => {
                     warn!("从节点 #{} 获取状态失败: {}", node_id, e);
@@ -420,9 +915,6 @@ impl ProxyControl for NodeManager {
             }
         }
 
-        Ok(ServerStatus {
-            connected_clients: all_clients,
-            active_proxy_count: total_proxy_count,
-        })
+        Ok(total)
     }
 }
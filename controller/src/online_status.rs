@@ -0,0 +1,251 @@
+//! 客户端 / 节点在线状态缓存
+//!
+//! 健康监控每 30 秒检查一次所有客户端和节点的连接状态。最近一次的状态保存在内存缓存中，
+//! 只有发生变化的记录才写回数据库，同一轮中所有上线 / 离线的 ID 各合并为一条 UPDATE。
+//! 连接建立和断开时也会立即更新缓存，API 可以直接读取缓存获取实时在线状态。
+//! 每次状态变化都追加到 `status_history`，用于计算可用率（见 `availability`）。
+
+use chrono::Utc;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use tracing::{error, info, warn};
+
+use crate::entity::status_history::{ENTITY_CLIENT, ENTITY_NODE};
+use crate::entity::{client, node, Client, Node};
+
+/// 一类实体（客户端或节点）的在线状态
+#[derive(Default)]
+pub struct StatusCache {
+    state: RwLock<HashMap<i64, bool>>,
+}
+
+/// 与缓存比较后发生变化的 ID
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct StatusChanges {
+    pub online: Vec<i64>,
+    pub offline: Vec<i64>,
+}
+
+impl StatusChanges {
+    pub fn is_empty(&self) -> bool {
+        self.online.is_empty() && self.offline.is_empty()
+    }
+}
+
+impl StatusCache {
+    /// 记录单个实体的状态（连接建立 / 断开时调用），返回状态是否发生变化
+    pub fn set(&self, id: i64, online: bool) -> bool {
+        self.state.write().unwrap().insert(id, online) != Some(online)
+    }
+
+    /// 缓存中的状态，未知时返回 None
+    pub fn get(&self, id: i64) -> Option<bool> {
+        self.state.read().unwrap().get(&id).copied()
+    }
+
+    /// 所有在线的 ID（升序）
+    pub fn online_ids(&self) -> Vec<i64> {
+        let mut ids: Vec<i64> = self
+            .state
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, online)| **online)
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// 用数据库中的状态初始化缓存（已有记录不覆盖）
+    pub fn seed(&self, rows: impl IntoIterator<Item = (i64, bool)>) {
+        let mut state = self.state.write().unwrap();
+        for (id, online) in rows {
+            state.entry(id).or_insert(online);
+        }
+    }
+
+    /// 用一轮完整的检查结果替换缓存，返回状态发生变化的 ID
+    ///
+    /// 缓存中没有的 ID 视为离线（新建的记录默认离线），不在结果中的 ID（已删除）从缓存中移除。
+    pub fn apply(&self, results: &[(i64, bool)]) -> StatusChanges {
+        let mut state = self.state.write().unwrap();
+        let mut changes = StatusChanges::default();
+        for &(id, online) in results {
+            if state.get(&id).copied().unwrap_or(false) != online {
+                if online {
+                    changes.online.push(id);
+                } else {
+                    changes.offline.push(id);
+                }
+            }
+        }
+        *state = results.iter().copied().collect();
+        changes
+    }
+}
+
+/// 客户端在线状态缓存
+pub fn clients() -> &'static StatusCache {
+    static CLIENTS: OnceLock<StatusCache> = OnceLock::new();
+    CLIENTS.get_or_init(StatusCache::default)
+}
+
+/// 节点在线状态缓存
+pub fn nodes() -> &'static StatusCache {
+    static NODES: OnceLock<StatusCache> = OnceLock::new();
+    NODES.get_or_init(StatusCache::default)
+}
+
+/// 客户端连接建立 / 断开时更新缓存，状态变化时写入历史
+pub async fn set_client(id: i64, online: bool) {
+    if clients().set(id, online) {
+        crate::availability::record(ENTITY_CLIENT, &[id], online).await;
+    }
+}
+
+/// 节点连接建立 / 断开时更新缓存，状态变化时写入历史
+pub async fn set_node(id: i64, online: bool) {
+    if nodes().set(id, online) {
+        crate::availability::record(ENTITY_NODE, &[id], online).await;
+    }
+}
+
+/// 启动时从数据库加载上次记录的在线状态
+pub async fn load(db: &DatabaseConnection) -> anyhow::Result<()> {
+    let client_rows = Client::find()
+        .select_only()
+        .column(client::Column::Id)
+        .column(client::Column::IsOnline)
+        .into_tuple::<(i64, bool)>()
+        .all(db)
+        .await?;
+    clients().seed(client_rows);
+
+    let node_rows = Node::find()
+        .select_only()
+        .column(node::Column::Id)
+        .column(node::Column::IsOnline)
+        .into_tuple::<(i64, bool)>()
+        .all(db)
+        .await?;
+    nodes().seed(node_rows);
+    Ok(())
+}
+
+/// 写回一轮检查中状态发生变化的客户端
+pub async fn sync_clients(results: &[(i64, bool)], db: &DatabaseConnection) {
+    let changes = clients().apply(results);
+    if changes.is_empty() {
+        return;
+    }
+
+    if let Ok(names) = Client::find()
+        .select_only()
+        .column(client::Column::Id)
+        .column(client::Column::Name)
+        .filter(client::Column::Id.is_in(changes.online.iter().chain(&changes.offline).copied()))
+        .into_tuple::<(i64, String)>()
+        .all(db)
+        .await
+    {
+        let names: HashMap<i64, String> = names.into_iter().collect();
+        let name = |id: &i64| names.get(id).map(String::as_str).unwrap_or("-");
+        for id in &changes.online {
+            info!("客户端 #{} ({}) 已上线", id, name(id));
+        }
+        for id in &changes.offline {
+            if crate::maintenance::global().client(*id) {
+                info!("客户端 #{} ({}) 已离线（维护中）", id, name(id));
+            } else {
+                warn!("客户端 #{} ({}) 已离线", id, name(id));
+            }
+        }
+    }
+
+    for (ids, online) in [(changes.online, true), (changes.offline, false)] {
+        if ids.is_empty() {
+            continue;
+        }
+        crate::availability::record(ENTITY_CLIENT, &ids, online).await;
+        if let Err(e) = Client::update_many()
+            .col_expr(client::Column::IsOnline, Expr::value(online))
+            .col_expr(client::Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+            .filter(client::Column::Id.is_in(ids))
+            .exec(db)
+            .await
+        {
+            error!("批量更新客户端在线状态失败: {}", e);
+        }
+    }
+}
+
+/// 写回一轮检查中状态发生变化的节点
+pub async fn sync_nodes(results: &[(i64, bool)], db: &DatabaseConnection) {
+    let changes = nodes().apply(results);
+    if changes.is_empty() {
+        return;
+    }
+
+    if let Ok(names) = Node::find()
+        .select_only()
+        .column(node::Column::Id)
+        .column(node::Column::Name)
+        .filter(node::Column::Id.is_in(changes.online.iter().chain(&changes.offline).copied()))
+        .into_tuple::<(i64, String)>()
+        .all(db)
+        .await
+    {
+        let names: HashMap<i64, String> = names.into_iter().collect();
+        let name = |id: &i64| names.get(id).map(String::as_str).unwrap_or("-");
+        for id in &changes.online {
+            info!("节点 #{} ({}) 已上线", id, name(id));
+        }
+        for id in &changes.offline {
+            if crate::maintenance::global().node(*id) {
+                info!("节点 #{} ({}) 已离线（维护中）", id, name(id));
+            } else {
+                warn!("节点 #{} ({}) 已离线", id, name(id));
+            }
+        }
+    }
+
+    for (ids, online) in [(changes.online, true), (changes.offline, false)] {
+        if ids.is_empty() {
+            continue;
+        }
+        crate::availability::record(ENTITY_NODE, &ids, online).await;
+        if let Err(e) = Node::update_many()
+            .col_expr(node::Column::IsOnline, Expr::value(online))
+            .col_expr(node::Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+            .filter(node::Column::Id.is_in(ids))
+            .exec(db)
+            .await
+        {
+            error!("批量更新节点在线状态失败: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_reports_only_changes() {
+        let cache = StatusCache::default();
+        cache.seed([(1, true), (2, false), (3, true)]);
+
+        let changes = cache.apply(&[(1, true), (2, true), (3, false), (4, false), (5, true)]);
+        assert_eq!(changes, StatusChanges { online: vec![2, 5], offline: vec![3] });
+        assert_eq!(cache.online_ids(), vec![1, 2, 5]);
+
+        assert!(cache.apply(&[(1, true), (2, true), (3, false), (4, false), (5, true)]).is_empty());
+
+        // 已删除的记录从缓存中移除
+        cache.apply(&[(1, true)]);
+        assert_eq!(cache.get(2), None);
+    }
+}
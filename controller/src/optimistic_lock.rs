@@ -0,0 +1,80 @@
+//! 乐观锁
+//!
+//! 代理、用户、节点带有 `lock_version` 版本号，每次通过 API 修改都会加一。
+//! 更新请求须通过 `If-Match` 请求头或请求体中的 `lockVersion` 给出读取时的版本号，
+//! 更新语句带上 `lock_version = 版本号` 条件；期间被他人修改过时不会更新任何行，返回 409，
+//! 避免两个管理员同时编辑同一对象时后保存的一方静默覆盖前者的修改。
+//! `If-Match: *` 表示有意直接覆盖（例如命令行启用 / 禁用），不校验版本号。
+
+use axum::http::{header::IF_MATCH, HeaderMap, StatusCode};
+use sea_orm::DbErr;
+
+/// 从 `If-Match`（`"3"`、`W/"3"` 或 `3`）或请求体中取出期望的版本号，`If-Match: *` 时返回 `None`
+///
+/// 两者都给出时必须一致；都没有时返回 428。
+pub fn expected_version(headers: &HeaderMap, body: Option<i32>) -> Result<Option<i32>, (StatusCode, String)> {
+    let header = match headers.get(IF_MATCH) {
+        Some(value) if value.to_str().is_ok_and(|v| v.trim() == "*") => return Ok(None),
+        Some(value) => {
+            let parsed = value.to_str().ok().and_then(parse_if_match);
+            match parsed {
+                Some(v) => Some(v),
+                None => return Err((StatusCode::BAD_REQUEST, "If-Match 须为数字版本号".to_string())),
+            }
+        }
+        None => None,
+    };
+
+    match (header, body) {
+        (Some(h), Some(b)) if h != b => {
+            Err((StatusCode::BAD_REQUEST, format!("If-Match（{}）与 lockVersion（{}）不一致", h, b)))
+        }
+        (Some(v), _) | (None, Some(v)) => Ok(Some(v)),
+        (None, None) => Err((
+            StatusCode::PRECONDITION_REQUIRED,
+            "缺少版本号，请通过 If-Match 请求头或 lockVersion 字段提供".to_string(),
+        )),
+    }
+}
+
+fn parse_if_match(value: &str) -> Option<i32> {
+    let value = value.trim();
+    let value = value.strip_prefix("W/").unwrap_or(value);
+    let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+    value.parse().ok()
+}
+
+/// 更新失败是否因为版本号不匹配（期间已被他人修改）
+pub fn is_conflict(e: &DbErr) -> bool {
+    matches!(e, DbErr::RecordNotUpdated)
+}
+
+/// 版本号不匹配时的提示
+pub fn conflict_message(expected: i32, current: i32) -> String {
+    format!("数据已被他人修改（版本 {} → {}），请刷新后重试", expected, current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(if_match: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_MATCH, HeaderValue::from_str(if_match).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_expected_version() {
+        assert_eq!(expected_version(&headers("\"3\""), None), Ok(Some(3)));
+        assert_eq!(expected_version(&headers("W/\"4\""), None), Ok(Some(4)));
+        assert_eq!(expected_version(&headers("5"), Some(5)), Ok(Some(5)));
+        assert_eq!(expected_version(&HeaderMap::new(), Some(6)), Ok(Some(6)));
+        assert_eq!(expected_version(&headers("*"), Some(6)), Ok(None));
+
+        assert_eq!(expected_version(&HeaderMap::new(), None).unwrap_err().0, StatusCode::PRECONDITION_REQUIRED);
+        assert_eq!(expected_version(&headers("\"3\""), Some(4)).unwrap_err().0, StatusCode::BAD_REQUEST);
+        assert_eq!(expected_version(&headers("abc"), None).unwrap_err().0, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,174 @@
+//! Controller 主动发起的 HTTP 请求的 SSRF 防护
+//!
+//! 连接授权钩子、告警 Webhook、IP 地理位置查询、DNS 服务商和版本检查统一通过 [`client`]
+//! 创建 HTTP 客户端：
+//!
+//! - 只允许 http / https 地址；
+//! - 拒绝链路本地地址和云厂商元数据地址（169.254.0.0/16、fe80::/10、100.100.100.200 等），
+//!   以及未指定、组播、广播地址；`OXIPROXY_OUTBOUND_ALLOW_PRIVATE=false` 时还拒绝回环、
+//!   私有网段和运营商级 NAT 地址；
+//! - 域名由自定义解析器解析并逐个过滤地址，实际连接的就是检查过的地址，防止 DNS 重绑定；
+//! - 重定向的每一跳都重新检查，最多 5 跳；
+//! - 统一设置连接超时和总超时。
+//!
+//! 配置的地址在加载时用 [`validate_url`] 检查，不合规的地址直接忽略并记录警告。
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{redirect, Url};
+
+/// 连接超时上限
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// 最多跟随的重定向次数
+const MAX_REDIRECTS: usize = 5;
+
+/// 云厂商元数据服务地址（链路本地网段之外的）
+const METADATA_V4: &[Ipv4Addr] = &[
+    Ipv4Addr::new(100, 100, 100, 200), // 阿里云
+    Ipv4Addr::new(168, 63, 129, 16),   // Azure
+];
+const METADATA_V6: &[Ipv6Addr] = &[
+    Ipv6Addr::new(0xfd00, 0xec2, 0, 0, 0, 0, 0, 0x254), // AWS
+];
+
+/// 是否允许访问回环、私有网段（授权钩子、Webhook 通常部署在内网，默认允许）
+fn allow_private() -> bool {
+    static ALLOW: OnceLock<bool> = OnceLock::new();
+    *ALLOW.get_or_init(|| common::env::parse::<bool>("OXIPROXY_OUTBOUND_ALLOW_PRIVATE").unwrap_or(true))
+}
+
+/// 地址是否禁止访问
+pub fn is_blocked_ip(ip: IpAddr, allow_private: bool) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            let private = v4.is_loopback() || v4.is_private() || (a == 100 && (64..128).contains(&b));
+            v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || a == 0
+                || METADATA_V4.contains(&v4)
+                || (private && !allow_private)
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_blocked_ip(IpAddr::V4(v4), allow_private);
+            }
+            let first = v6.segments()[0];
+            let link_local = first & 0xffc0 == 0xfe80;
+            let private = v6.is_loopback() || first & 0xfe00 == 0xfc00;
+            link_local
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || METADATA_V6.contains(&v6)
+                || (private && !allow_private)
+        }
+    }
+}
+
+fn check_url(url: &Url, allow_private: bool) -> Result<()> {
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("不支持的协议: {}", url.scheme());
+    }
+    let Some(host) = url.host_str() else {
+        bail!("地址缺少主机名");
+    };
+    // IP 字面量不经过解析器，在这里检查
+    if let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        if is_blocked_ip(ip, allow_private) {
+            bail!("禁止访问的地址: {}", ip);
+        }
+    }
+    Ok(())
+}
+
+/// 检查外部请求地址（协议、IP 字面量），返回解析后的地址
+pub fn validate_url(url: &str) -> Result<Url> {
+    let parsed = Url::parse(url).map_err(|e| anyhow!("无效的地址 {}: {}", url, e))?;
+    check_url(&parsed, allow_private())?;
+    Ok(parsed)
+}
+
+/// 过滤禁止访问地址的 DNS 解析器
+struct GuardedResolver;
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let allow_private = allow_private();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| !is_blocked_ip(addr.ip(), allow_private))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} 没有可访问的地址（解析结果为空或均被禁止）", host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// 创建带 SSRF 防护的 HTTP 客户端，`timeout` 为单个请求的总超时
+pub fn client(timeout: Duration) -> reqwest::Result<reqwest::Client> {
+    builder(timeout).build()
+}
+
+/// 带 SSRF 防护的 HTTP 客户端构造器，供需要额外设置（如 User-Agent）的调用方使用
+pub fn builder(timeout: Duration) -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .dns_resolver(Arc::new(GuardedResolver))
+        .redirect(redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error("重定向次数过多");
+            }
+            match check_url(attempt.url(), allow_private()) {
+                Ok(()) => attempt.follow(),
+                Err(e) => attempt.error(e.to_string()),
+            }
+        }))
+        .connect_timeout(CONNECT_TIMEOUT.min(timeout))
+        .timeout(timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocked_addresses() {
+        let blocked = |s: &str, allow_private| is_blocked_ip(s.parse().unwrap(), allow_private);
+        // 元数据、链路本地地址始终禁止
+        assert!(blocked("169.254.169.254", true));
+        assert!(blocked("100.100.100.200", true));
+        assert!(blocked("fe80::1", true));
+        assert!(blocked("fd00:ec2::254", true));
+        assert!(blocked("::ffff:169.254.169.254", true));
+        assert!(blocked("0.0.0.0", true));
+        // 私有网段按配置
+        assert!(!blocked("10.0.0.1", true));
+        assert!(blocked("10.0.0.1", false));
+        assert!(blocked("127.0.0.1", false));
+        assert!(blocked("100.64.1.1", false));
+        assert!(blocked("fd12::1", false));
+        // 公网地址
+        assert!(!blocked("1.1.1.1", false));
+        assert!(!blocked("2606:4700::1111", false));
+    }
+
+    #[test]
+    fn test_check_url() {
+        let check = |s: &str| check_url(&Url::parse(s).unwrap(), true);
+        assert!(check("https://hooks.example.com/alert").is_ok());
+        assert!(check("http://10.0.0.5:8080/authz").is_ok());
+        assert!(check("file:///etc/passwd").is_err());
+        assert!(check("gopher://example.com/").is_err());
+        assert!(check("http://169.254.169.254/latest/meta-data/").is_err());
+        assert!(check("http://[fe80::1]/").is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! 密码策略
+//!
+//! 注册、管理员创建 / 修改用户和用户修改自己的密码时校验：最小长度（`password_min_length`）、
+//! 至少包含几类字符（小写、大写、数字、符号，`password_min_char_classes`）、不能与用户名相同、
+//! 不在内置的常见弱密码列表中，以及不在泄露密码列表文件（`password_breach_list_path`，
+//! 每行一个密码）中。系统随机生成的密码不受策略限制。
+//!
+//! 泄露密码列表加载后缓存在内存中，文件的修改时间或大小变化后在下一次校验时重新加载。
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use crate::config_manager::ConfigManager;
+
+const DEFAULT_MIN_LENGTH: i64 = 8;
+const DEFAULT_MIN_CHAR_CLASSES: i64 = 2;
+/// 密码最大长度，避免超长输入消耗哈希计算资源
+const MAX_LENGTH: usize = 128;
+
+/// 内置的常见弱密码（比较时忽略大小写）
+const COMMON_PASSWORDS: &[&str] = &[
+    "password", "password1", "password123", "passw0rd", "p@ssw0rd", "12345678", "123456789",
+    "1234567890", "11111111", "00000000", "88888888", "66666666", "12341234", "87654321",
+    "qwertyui", "qwerty123", "qwertyuiop", "1q2w3e4r", "1qaz2wsx", "abc12345", "abcd1234",
+    "a1234567", "aa123456", "asdfghjk", "iloveyou", "admin123", "administrator", "welcome1",
+    "letmein1", "changeme", "sunshine", "football", "baseball", "superman", "trustno1",
+    "woaini1314", "oxiproxy",
+];
+
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub min_char_classes: usize,
+    pub breach_list_path: String,
+}
+
+impl PasswordPolicy {
+    pub async fn load(config_manager: &ConfigManager) -> Self {
+        Self {
+            min_length: config_manager.get_number("password_min_length", DEFAULT_MIN_LENGTH).await.clamp(1, MAX_LENGTH as i64)
+                as usize,
+            min_char_classes: config_manager.get_number("password_min_char_classes", DEFAULT_MIN_CHAR_CLASSES).await.clamp(1, 4)
+                as usize,
+            breach_list_path: config_manager.get_string("password_breach_list_path", "").await.trim().to_string(),
+        }
+    }
+
+    /// 校验密码，不符合时返回原因
+    pub async fn check(&self, password: &str, username: &str) -> Result<(), String> {
+        self.check_rules(password, username)?;
+        if !self.breach_list_path.is_empty() {
+            match breach_list(&self.breach_list_path).await {
+                Ok(list) if list.contains(password) => {
+                    return Err("该密码出现在已泄露的密码列表中，请更换".to_string());
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("读取泄露密码列表 {} 失败: {}", self.breach_list_path, e),
+            }
+        }
+        Ok(())
+    }
+
+    fn check_rules(&self, password: &str, username: &str) -> Result<(), String> {
+        let length = password.chars().count();
+        if length < self.min_length {
+            return Err(format!("密码长度不能少于 {} 个字符", self.min_length));
+        }
+        if length > MAX_LENGTH {
+            return Err(format!("密码长度不能超过 {} 个字符", MAX_LENGTH));
+        }
+
+        let classes = [
+            password.chars().any(|c| c.is_lowercase()),
+            password.chars().any(|c| c.is_uppercase()),
+            password.chars().any(|c| c.is_ascii_digit()),
+            password.chars().any(|c| !c.is_alphanumeric()),
+        ];
+        if classes.iter().filter(|&&present| present).count() < self.min_char_classes {
+            return Err(format!(
+                "密码需要至少包含小写字母、大写字母、数字、符号中的 {} 类",
+                self.min_char_classes
+            ));
+        }
+
+        if !username.is_empty() && password.eq_ignore_ascii_case(username.trim()) {
+            return Err("密码不能与用户名相同".to_string());
+        }
+        if COMMON_PASSWORDS.iter().any(|common| password.eq_ignore_ascii_case(common)) {
+            return Err("密码过于常见，请更换".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 已加载列表对应的文件版本：路径、修改时间和大小
+type ListVersion = (String, Option<SystemTime>, u64);
+type CachedList = Option<(ListVersion, Arc<HashSet<String>>)>;
+
+/// 加载泄露密码列表，文件未变化时复用已加载的内容
+async fn breach_list(path: &str) -> std::io::Result<Arc<HashSet<String>>> {
+    static LIST: OnceLock<Mutex<CachedList>> = OnceLock::new();
+    let cache = LIST.get_or_init(Default::default);
+    let metadata = tokio::fs::metadata(path).await?;
+    let version: ListVersion = (path.to_string(), metadata.modified().ok(), metadata.len());
+    if let Some((cached, list)) = cache.lock().unwrap().as_ref() {
+        if *cached == version {
+            return Ok(list.clone());
+        }
+    }
+
+    let content = tokio::fs::read_to_string(path).await?;
+    let list: Arc<HashSet<String>> = Arc::new(
+        content
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect(),
+    );
+    tracing::info!("已加载泄露密码列表 {}（{} 条）", path, list.len());
+    *cache.lock().unwrap() = Some((version, list.clone()));
+    Ok(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_rules() {
+        let policy = PasswordPolicy { min_length: 8, min_char_classes: 3, breach_list_path: String::new() };
+        assert!(policy.check_rules("Sh0rt!", "alice").is_err());
+        assert!(policy.check_rules("alllowercase", "alice").is_err());
+        assert!(policy.check_rules("Mixed-Case-Pass", "alice").is_ok());
+        assert!(policy.check_rules("Alice.Smith9", "alice.smith9").is_err());
+        assert!(policy.check_rules("P@ssw0rd", "alice").is_err());
+        assert!(policy.check_rules(&"Aa1!".repeat(40), "alice").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_breach_list_reloads_on_change() {
+        let path = std::env::temp_dir().join(format!("oxiproxy-breach-{}.txt", std::process::id()));
+        let policy = PasswordPolicy {
+            min_length: 8,
+            min_char_classes: 1,
+            breach_list_path: path.to_string_lossy().into_owned(),
+        };
+
+        std::fs::write(&path, "Leaked-Pass-1\n").unwrap();
+        assert!(policy.check("Leaked-Pass-1", "alice").await.is_err());
+        assert!(policy.check("Leaked-Pass-2", "alice").await.is_ok());
+
+        // 文件更新后无需重启即可生效（修改时间精度可能较粗，这里显式推后）
+        std::fs::write(&path, "Leaked-Pass-1\nLeaked-Pass-2\n").unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + std::time::Duration::from_secs(10)).unwrap();
+        assert!(policy.check("Leaked-Pass-2", "alice").await.is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
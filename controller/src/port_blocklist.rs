@@ -0,0 +1,129 @@
+//! 端口黑名单（防滥用）
+//!
+//! 规则分为两类：
+//! - `remote_port`：禁止在节点上监听的远程端口（如 25、465）；
+//! - `local_target`：禁止客户端转发的本地目标（如云服务器元数据地址 169.254.169.254）。
+//!
+//! 规则可以全局生效，也可以只对某个节点或某个租户生效。创建 / 修改代理时由 Controller 校验；
+//! 本地目标规则还会随代理列表下发给客户端，客户端在连接目标前按解析后的实际地址再检查一次，
+//! 防止通过域名绕过。
+
+use anyhow::Result;
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter};
+use std::net::{IpAddr, SocketAddr};
+use tracing::warn;
+
+use common::target_rule::{PortRange, TargetRule};
+
+use crate::entity::port_blocklist::{self, KIND_LOCAL_TARGET, KIND_REMOTE_PORT};
+use crate::entity::{Client, PortBlocklist, User};
+
+/// 校验规则内容是否合法
+pub fn validate_pattern(kind: &str, pattern: &str) -> Result<(), String> {
+    match kind {
+        KIND_REMOTE_PORT => pattern.parse::<PortRange>().map(|_| ()).map_err(|e| e.to_string()),
+        KIND_LOCAL_TARGET => pattern.parse::<TargetRule>().map(|_| ()).map_err(|e| e.to_string()),
+        _ => Err(format!("无效的规则类型: {}", kind)),
+    }
+}
+
+/// 对指定节点和租户生效的规则：全局规则 + 该节点的规则 + 该租户的规则
+pub async fn effective_rules(
+    kind: &str,
+    node_id: Option<i64>,
+    tenant_id: Option<i64>,
+    db: &DatabaseConnection,
+) -> Result<Vec<port_blocklist::Model>> {
+    let scope = |column: port_blocklist::Column, id: Option<i64>| {
+        let condition = Condition::any().add(column.is_null());
+        match id {
+            Some(id) => condition.add(column.eq(id)),
+            None => condition,
+        }
+    };
+
+    Ok(PortBlocklist::find()
+        .filter(port_blocklist::Column::Kind.eq(kind))
+        .filter(scope(port_blocklist::Column::NodeId, node_id))
+        .filter(scope(port_blocklist::Column::TenantId, tenant_id))
+        .all(db)
+        .await?)
+}
+
+/// 客户端所属用户的租户
+pub async fn client_tenant_id(client_id: &str, db: &DatabaseConnection) -> Result<Option<i64>> {
+    let Ok(client_id) = client_id.parse::<i64>() else {
+        return Ok(None);
+    };
+    let Some(user_id) = Client::find_by_id(client_id).one(db).await?.and_then(|c| c.user_id) else {
+        return Ok(None);
+    };
+    Ok(User::find_by_id(user_id).one(db).await?.and_then(|u| u.tenant_id))
+}
+
+/// 下发给客户端的本地目标规则
+pub async fn local_target_patterns(
+    node_id: i64,
+    tenant_id: Option<i64>,
+    db: &DatabaseConnection,
+) -> Result<Vec<String>> {
+    Ok(effective_rules(KIND_LOCAL_TARGET, Some(node_id), tenant_id, db)
+        .await?
+        .into_iter()
+        .map(|r| r.pattern)
+        .collect())
+}
+
+/// 验证代理的远程端口和本地目标是否命中黑名单
+/// 返回 (是否允许, 错误信息)
+///
+/// 本地地址为域名时无法在 Controller 判断，由客户端解析后检查。
+pub async fn validate_proxy_target(
+    node_id: Option<i64>,
+    tenant_id: Option<i64>,
+    remote_port: u16,
+    local_ip: &str,
+    local_port: u16,
+    db: &DatabaseConnection,
+) -> Result<(bool, String)> {
+    for rule in effective_rules(KIND_REMOTE_PORT, node_id, tenant_id, db).await? {
+        match rule.pattern.parse::<PortRange>() {
+            Ok(range) if range.contains(remote_port) => {
+                return Ok((false, format!("远程端口 {} 已被禁止使用（规则 {}）", remote_port, rule.pattern)));
+            }
+            Ok(_) => {}
+            Err(e) => warn!("端口黑名单规则 #{} 无效: {}", rule.id, e),
+        }
+    }
+
+    validate_local_target(node_id, tenant_id, local_ip, local_port, db).await
+}
+
+/// 验证本地目标是否命中黑名单
+/// 返回 (是否允许, 错误信息)
+pub async fn validate_local_target(
+    node_id: Option<i64>,
+    tenant_id: Option<i64>,
+    local_ip: &str,
+    local_port: u16,
+    db: &DatabaseConnection,
+) -> Result<(bool, String)> {
+    let Ok(ip) = common::utils::normalize_host(local_ip).parse::<IpAddr>() else {
+        return Ok((true, String::new()));
+    };
+    let target = SocketAddr::new(ip, local_port);
+    for rule in effective_rules(KIND_LOCAL_TARGET, node_id, tenant_id, db).await? {
+        match rule.pattern.parse::<TargetRule>() {
+            Ok(r) if r.matches(target) => {
+                return Ok((
+                    false,
+                    format!("不允许转发到本地目标 {}（规则 {}）", target, rule.pattern),
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => warn!("端口黑名单规则 #{} 无效: {}", rule.id, e),
+        }
+    }
+
+    Ok((true, String::new()))
+}
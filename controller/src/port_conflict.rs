@@ -0,0 +1,70 @@
+//! 节点启动时发现的代理端口冲突
+//!
+//! 节点启动时按注册响应中下发的启用代理绑定并持有所有远程端口，不等客户端连接；被主机上其他程序
+//! 占用或权限不足而无法绑定的端口通过 `PortConflictReport` 上报。Controller 记录日志并向告警
+//! Webhook 发送 `type` 为 `port_conflict` 的事件，便于在访客连接失败之前处理。
+
+use chrono::Utc;
+use serde::Serialize;
+use tracing::warn;
+
+use common::grpc::oxiproxy;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConflictedProxy {
+    id: i64,
+    name: String,
+    client_id: String,
+    proxy_type: String,
+    remote_port: u32,
+    error: String,
+}
+
+/// 发送到告警 Webhook 的端口冲突事件
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PortConflictEvent {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    node_id: i64,
+    node_name: String,
+    proxies: Vec<ConflictedProxy>,
+    at: chrono::DateTime<Utc>,
+}
+
+pub fn report(node_id: i64, node_name: &str, report: oxiproxy::PortConflictReport) {
+    if report.conflicts.is_empty() {
+        return;
+    }
+    for c in &report.conflicts {
+        warn!(
+            "节点 #{} ({}) 无法绑定代理「{}」(#{}) 的 {} 端口 {}: {}",
+            node_id,
+            node_name,
+            c.name,
+            c.proxy_id,
+            c.proxy_type.to_uppercase(),
+            c.remote_port,
+            c.error
+        );
+    }
+    crate::alerting::post_webhooks(PortConflictEvent {
+        kind: "port_conflict",
+        node_id,
+        node_name: node_name.to_string(),
+        proxies: report
+            .conflicts
+            .into_iter()
+            .map(|c| ConflictedProxy {
+                id: c.proxy_id,
+                name: c.name,
+                client_id: c.client_id,
+                proxy_type: c.proxy_type,
+                remote_port: c.remote_port,
+                error: c.error,
+            })
+            .collect(),
+        at: Utc::now(),
+    });
+}
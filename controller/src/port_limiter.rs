@@ -1,5 +1,7 @@
 use anyhow::{anyhow, Result};
-use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QuerySelect};
+use std::collections::HashMap;
 
 use crate::entity::{proxy, Proxy, User};
 
@@ -158,6 +160,47 @@ pub async fn get_user_port_count(user_id: i64, db: &DatabaseConnection) -> Resul
     Ok(count)
 }
 
+/// 批量获取多个用户的代理数量（固定两次查询），返回 用户ID -> 代理数，没有代理的用户不在结果中
+pub async fn get_user_port_counts(user_ids: &[i64], db: &DatabaseConnection) -> Result<HashMap<i64, u64>> {
+    if user_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let client_owner: HashMap<String, i64> = crate::entity::Client::find()
+        .select_only()
+        .column(crate::entity::client::Column::Id)
+        .column(crate::entity::client::Column::UserId)
+        .filter(crate::entity::client::Column::UserId.is_in(user_ids.iter().copied()))
+        .into_tuple::<(i64, i64)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|(client_id, user_id)| (client_id.to_string(), user_id))
+        .collect();
+
+    if client_owner.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let proxy_counts = Proxy::find()
+        .select_only()
+        .column(proxy::Column::ClientId)
+        .column_as(Expr::col(proxy::Column::Id).count(), "count")
+        .filter(proxy::Column::ClientId.is_in(client_owner.keys().cloned()))
+        .group_by(proxy::Column::ClientId)
+        .into_tuple::<(String, i64)>()
+        .all(db)
+        .await?;
+
+    let mut counts = HashMap::new();
+    for (client_id, count) in proxy_counts {
+        if let Some(user_id) = client_owner.get(&client_id) {
+            *counts.entry(*user_id).or_insert(0) += count as u64;
+        }
+    }
+    Ok(counts)
+}
+
 /// 获取用户端口限制信息
 pub async fn get_user_port_limit_info(user_id: i64, db: &DatabaseConnection) -> Result<UserPortLimitInfo> {
     let user = match User::find_by_id(user_id).one(db).await? {
@@ -0,0 +1,271 @@
+//! 代理公网端口探测
+//!
+//! 从外部检查代理的远程端口在节点公网地址上是否真正可达，用于发现云防火墙 / 安全组未放行端口
+//! 等节点自身无法察觉的问题。探测默认由 Controller 直接发起 TCP 连接；Controller 与节点位于
+//! 同一内网时，可以通过系统配置 `port_probe_node_id` 改由另一个节点发起。外部连接失败时再让
+//! 代理所在的节点连接本机端口，区分"监听器未运行"和"被防火墙拦截"。
+//!
+//! 只有 TCP 代理可以探测：UDP 没有握手，无法从外部确认端口是否放行。每个代理只保存最近一次结果。
+
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, NotSet, QueryFilter, Set};
+use tokio::net::TcpStream;
+use tracing::{info, warn};
+
+use common::grpc::oxiproxy;
+
+use crate::config_manager::ConfigManager;
+use crate::entity::proxy_port_probe::{self, STATUS_REACHABLE, STATUS_SKIPPED, STATUS_UNREACHABLE};
+use crate::entity::{node, proxy, Node, ProxyPortProbe};
+use crate::node_manager::NodeManager;
+
+/// 单次连接超时
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+/// 等待节点返回探测结果的时间上限
+const NODE_PROBE_WAIT: Duration = Duration::from_secs(10);
+
+/// 一次 TCP 连接的结果
+struct Attempt {
+    success: bool,
+    latency_ms: Option<f64>,
+    detail: String,
+}
+
+/// 探测使用的地址：优先代理监听的公网 IP，其次节点的公网 IP、隧道地址
+///
+/// 不使用代理发布的 DNS 名称，避免把 DNS 未生效误判为端口不可达。
+fn target_host(p: &proxy::Model, n: &node::Model) -> Option<String> {
+    if let Some(ip) = p.bind_ip.as_deref().filter(|ip| crate::endpoint::is_public_ip(ip)) {
+        return Some(ip.to_string());
+    }
+    [n.public_ip.as_deref(), Some(n.tunnel_addr.as_str())]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .find(|addr| !addr.is_empty())
+        .map(str::to_string)
+}
+
+/// 节点本机检查使用的地址：代理只监听某个 IP 时连接该 IP，否则连接回环地址
+fn local_host(p: &proxy::Model) -> String {
+    match p.bind_ip.as_deref().and_then(|ip| ip.parse::<IpAddr>().ok()) {
+        Some(ip) if !ip.is_unspecified() => ip.to_string(),
+        _ => "127.0.0.1".to_string(),
+    }
+}
+
+fn format_target(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// 由 Controller 直接连接
+async fn connect_from_controller(host: &str, port: u16) -> Attempt {
+    let start = Instant::now();
+    match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect((host, port))).await {
+        Ok(Ok(_)) => Attempt {
+            success: true,
+            latency_ms: Some(start.elapsed().as_secs_f64() * 1000.0),
+            detail: "连接成功".to_string(),
+        },
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::ConnectionRefused => Attempt {
+            success: false,
+            latency_ms: None,
+            detail: "连接被拒绝".to_string(),
+        },
+        Ok(Err(e)) => Attempt { success: false, latency_ms: None, detail: format!("连接失败: {}", e) },
+        Err(_) => Attempt {
+            success: false,
+            latency_ms: None,
+            detail: format!("连接超时（{} 秒）", CONNECT_TIMEOUT.as_secs()),
+        },
+    }
+}
+
+/// 让节点发起一次 TCP 连接
+async fn connect_from_node(node_manager: &NodeManager, node_id: i64, host: &str, port: u16) -> Result<Attempt> {
+    let cmd = oxiproxy::ProbeCommand {
+        request_id: String::new(),
+        kind: "tcp".to_string(),
+        host: host.to_string(),
+        port: port as u32,
+        count: 1,
+        timeout_ms: CONNECT_TIMEOUT.as_millis() as u32,
+    };
+    let mut steps = node_manager.start_probe(node_id, cmd).await?;
+    let collect = async {
+        let mut attempt = None;
+        while let Some(step) = steps.recv().await {
+            if step.done {
+                let mut attempt = attempt.unwrap_or(Attempt { success: false, latency_ms: None, detail: String::new() });
+                attempt.success = step.success;
+                if attempt.detail.is_empty() {
+                    attempt.detail = step.message;
+                }
+                return Some(attempt);
+            }
+            attempt = Some(Attempt { success: step.success, latency_ms: step.rtt_ms, detail: step.message });
+        }
+        None
+    };
+    match tokio::time::timeout(NODE_PROBE_WAIT, collect).await {
+        Ok(Some(attempt)) => Ok(attempt),
+        Ok(None) => Err(anyhow!("节点 #{} 未返回探测结果", node_id)),
+        Err(_) => Err(anyhow!("等待节点 #{} 返回探测结果超时", node_id)),
+    }
+}
+
+/// 保存探测结果（每个代理只保留一条）
+async fn save(proxy_id: i64, result: proxy_port_probe::ActiveModel, db: &DatabaseConnection) -> Result<proxy_port_probe::Model> {
+    ProxyPortProbe::insert(result)
+        .on_conflict(
+            OnConflict::column(proxy_port_probe::Column::ProxyId)
+                .update_columns([
+                    proxy_port_probe::Column::NodeId,
+                    proxy_port_probe::Column::Source,
+                    proxy_port_probe::Column::Target,
+                    proxy_port_probe::Column::Status,
+                    proxy_port_probe::Column::LatencyMs,
+                    proxy_port_probe::Column::LocalReachable,
+                    proxy_port_probe::Column::Message,
+                    proxy_port_probe::Column::ProbedAt,
+                ])
+                .to_owned(),
+        )
+        .exec(db)
+        .await?;
+    ProxyPortProbe::find()
+        .filter(proxy_port_probe::Column::ProxyId.eq(proxy_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| anyhow!("保存探测结果失败"))
+}
+
+/// 探测代理的远程端口并保存结果
+pub async fn probe_proxy(
+    p: &proxy::Model,
+    node_manager: &NodeManager,
+    config_manager: &ConfigManager,
+    db: &DatabaseConnection,
+) -> Result<proxy_port_probe::Model> {
+    let node_id = p.node_id.ok_or_else(|| anyhow!("代理未指定节点，无法探测"))?;
+    let n = Node::find_by_id(node_id).one(db).await?.ok_or_else(|| anyhow!("节点不存在"))?;
+
+    let configured = config_manager.get_number("port_probe_node_id", 0).await;
+    // 代理所在的节点探测自己的公网地址不能说明外部可达，此时仍由 Controller 探测
+    let source_node = (configured > 0 && configured != node_id).then_some(configured);
+    let source = match source_node {
+        Some(id) => format!("node:{}", id),
+        None => "controller".to_string(),
+    };
+    let source_label = match source_node {
+        Some(id) => format!("节点 #{}", id),
+        None => "Controller".to_string(),
+    };
+
+    let mut result = proxy_port_probe::ActiveModel {
+        id: NotSet,
+        proxy_id: Set(p.id),
+        node_id: Set(node_id),
+        source: Set(source),
+        target: Set(String::new()),
+        status: Set(STATUS_SKIPPED.to_string()),
+        latency_ms: Set(None),
+        local_reachable: Set(None),
+        message: Set(String::new()),
+        probed_at: Set(Utc::now().naive_utc()),
+    };
+
+    let host = target_host(p, &n);
+    let skip_reason = if p.proxy_type == "udp" {
+        Some("UDP 没有握手，无法从外部确认端口是否放行".to_string())
+    } else if !p.enabled {
+        Some("代理未启用，节点上没有监听该端口".to_string())
+    } else if host.is_none() {
+        Some(format!("节点 {} 未配置公网 IP 或隧道地址", n.name))
+    } else {
+        None
+    };
+    if let Some(host) = &host {
+        result.target = Set(format_target(host, p.remote_port));
+    }
+    if let Some(reason) = skip_reason {
+        result.message = Set(reason);
+        return save(p.id, result, db).await;
+    }
+    let host = host.unwrap_or_default();
+    let target = format_target(&host, p.remote_port);
+
+    let external = match source_node {
+        Some(id) => connect_from_node(node_manager, id, &host, p.remote_port).await?,
+        None => connect_from_controller(&host, p.remote_port).await,
+    };
+    if external.success {
+        result.status = Set(STATUS_REACHABLE.to_string());
+        result.latency_ms = Set(external.latency_ms);
+        result.message = Set(format!("从{}连接 {} 成功", source_label, target));
+    } else {
+        // 外部不可达时让节点连接本机端口，判断问题在监听器还是在网络路径上
+        let local = match connect_from_node(node_manager, node_id, &local_host(p), p.remote_port).await {
+            Ok(attempt) => Some(attempt.success),
+            Err(e) => {
+                warn!("代理 {} (ID: {}) 的本机端口检查失败: {}", p.name, p.id, e);
+                None
+            }
+        };
+        result.status = Set(STATUS_UNREACHABLE.to_string());
+        result.local_reachable = Set(local);
+        result.message = Set(match local {
+            Some(true) => format!(
+                "从{}无法访问 {}（{}），但节点本机可以连接：请检查云服务商的防火墙 / 安全组是否放行 TCP {} 端口",
+                source_label, target, external.detail, p.remote_port
+            ),
+            Some(false) => format!(
+                "从{}无法访问 {}（{}），节点本机也无法连接：监听器可能未运行，请查看代理事件和节点日志",
+                source_label, target, external.detail
+            ),
+            None => format!(
+                "从{}无法访问 {}（{}），节点未响应本机检查，无法确认监听器状态",
+                source_label, target, external.detail
+            ),
+        });
+    }
+
+    let saved = save(p.id, result, db).await?;
+    info!("代理 {} (ID: {}) 端口探测: {} - {}", p.name, p.id, saved.status, saved.message);
+    Ok(saved)
+}
+
+/// 后台探测新创建的代理，失败只记录日志
+pub fn spawn_probe(
+    p: proxy::Model,
+    node_manager: Arc<NodeManager>,
+    config_manager: Arc<ConfigManager>,
+    db: &'static DatabaseConnection,
+) {
+    tokio::spawn(async move {
+        if let Err(e) = probe_proxy(&p, &node_manager, &config_manager, db).await {
+            warn!("探测代理 {} (ID: {}) 的端口失败: {}", p.name, p.id, e);
+        }
+    });
+}
+
+/// 删除代理的探测结果
+pub async fn forget(proxy_ids: &[i64], db: &DatabaseConnection) {
+    if let Err(e) = ProxyPortProbe::delete_many()
+        .filter(proxy_port_probe::Column::ProxyId.is_in(proxy_ids.iter().copied()))
+        .exec(db)
+        .await
+    {
+        warn!("删除代理端口探测结果失败: {}", e);
+    }
+}
@@ -0,0 +1,120 @@
+//! 端口预留
+//!
+//! 管理员可以在节点上为某个用户或租户预留端口或端口范围，而无需先创建代理（例如迁移期间先占住端口）。
+//! 创建代理或修改远程端口时，预留范围内的端口只允许预留对象的客户端使用；临时隧道随机分配端口时跳过预留端口。
+//! 已过期的预留不再生效。同一节点上的预留范围不能重叠。
+
+use anyhow::Result;
+use chrono::Utc;
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter};
+
+use crate::entity::port_reservation::{self, Model as Reservation};
+use crate::entity::{Client, PortReservation, User};
+
+/// 校验预留范围
+pub fn validate_range(port_start: i32, port_end: i32) -> Result<(), String> {
+    if !(1..=65535).contains(&port_start) || !(1..=65535).contains(&port_end) {
+        return Err("端口须在 1-65535 之间".to_string());
+    }
+    if port_start > port_end {
+        return Err(format!("起始端口不能大于结束端口: {}-{}", port_start, port_end));
+    }
+    Ok(())
+}
+
+/// 节点上仍然有效的预留
+pub async fn active_reservations(node_id: i64, db: &DatabaseConnection) -> Result<Vec<Reservation>> {
+    Ok(PortReservation::find()
+        .filter(port_reservation::Column::NodeId.eq(node_id))
+        .filter(
+            Condition::any()
+                .add(port_reservation::Column::ExpiresAt.is_null())
+                .add(port_reservation::Column::ExpiresAt.gt(Utc::now().naive_utc())),
+        )
+        .all(db)
+        .await?)
+}
+
+/// 客户端所属的用户和租户
+pub async fn client_owner(client_id: &str, db: &DatabaseConnection) -> Result<(Option<i64>, Option<i64>)> {
+    let Ok(client_id) = client_id.parse::<i64>() else {
+        return Ok((None, None));
+    };
+    let Some(user_id) = Client::find_by_id(client_id).one(db).await?.and_then(|c| c.user_id) else {
+        return Ok((None, None));
+    };
+    let tenant_id = User::find_by_id(user_id).one(db).await?.and_then(|u| u.tenant_id);
+    Ok((Some(user_id), tenant_id))
+}
+
+/// 端口是否可以由该用户 / 租户使用
+fn check(reservations: &[Reservation], port: u16, user_id: Option<i64>, tenant_id: Option<i64>) -> Result<(), String> {
+    match reservations.iter().find(|r| r.contains(port)) {
+        None => Ok(()),
+        Some(r) if r.user_id.is_some() && r.user_id == user_id => Ok(()),
+        Some(r) if r.tenant_id.is_some() && r.tenant_id == tenant_id => Ok(()),
+        Some(r) => Err(format!("端口 {} 已被预留（{}-{}）", port, r.port_start, r.port_end)),
+    }
+}
+
+/// 验证代理的远程端口是否与其他用户 / 租户的预留冲突
+/// 返回 (是否允许, 错误信息)
+pub async fn validate_proxy_port(
+    node_id: Option<i64>,
+    client_id: &str,
+    remote_port: u16,
+    db: &DatabaseConnection,
+) -> Result<(bool, String)> {
+    let Some(node_id) = node_id else {
+        return Ok((true, String::new()));
+    };
+    let reservations = active_reservations(node_id, db).await?;
+    if reservations.is_empty() {
+        return Ok((true, String::new()));
+    }
+    let (user_id, tenant_id) = client_owner(client_id, db).await?;
+    match check(&reservations, remote_port, user_id, tenant_id) {
+        Ok(()) => Ok((true, String::new())),
+        Err(reason) => Ok((false, reason)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reservation(start: i32, end: i32, user_id: Option<i64>, tenant_id: Option<i64>) -> Reservation {
+        Reservation {
+            id: 1,
+            node_id: 1,
+            port_start: start,
+            port_end: end,
+            user_id,
+            tenant_id,
+            expires_at: None,
+            description: None,
+            created_by: "admin".to_string(),
+            created_at: Utc::now().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn test_check_reservation_owner() {
+        let reservations = [reservation(8000, 8010, Some(7), None), reservation(9000, 9000, None, Some(3))];
+
+        assert!(check(&reservations, 7999, Some(1), None).is_ok());
+        assert!(check(&reservations, 8005, Some(7), None).is_ok());
+        assert!(check(&reservations, 8010, Some(1), None).is_err());
+        assert!(check(&reservations, 8005, None, None).is_err());
+        assert!(check(&reservations, 9000, Some(1), Some(3)).is_ok());
+        assert!(check(&reservations, 9000, Some(1), Some(4)).is_err());
+    }
+
+    #[test]
+    fn test_validate_range() {
+        assert!(validate_range(8000, 8010).is_ok());
+        assert!(validate_range(8010, 8000).is_err());
+        assert!(validate_range(0, 10).is_err());
+        assert!(validate_range(1, 70000).is_err());
+    }
+}
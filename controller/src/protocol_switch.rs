@@ -0,0 +1,132 @@
+//! 节点隧道协议的分阶段切换
+//!
+//! 原地切换会让节点停止旧监听器再启动新监听器，所有客户端同时断线重连。分阶段切换时节点先在
+//! 备用端口上启动新协议的监听器（旧监听器继续工作），Controller 更新节点的隧道端口后逐个推送
+//! 客户端的代理列表，客户端收到新的地址 / 协议后自行重连到新监听器。全部推送完成并等待一段时间后，
+//! 通知节点停止旧监听器。节点未配置备用端口或版本过旧时回退为原地切换。
+
+use sea_orm::{ActiveModelTrait, EntityTrait, Set};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use common::grpc::oxiproxy;
+
+use crate::client_stream_manager::ClientStreamManager;
+use crate::entity::{node, Node};
+use crate::migration::get_connection;
+use crate::node_manager::NodeManager;
+
+/// 相邻两个客户端迁移之间的间隔，避免新监听器同时处理大量握手
+const MIGRATE_INTERVAL: Duration = Duration::from_secs(2);
+/// 全部客户端迁移后旧监听器的保留时间，等待重连中的客户端完成握手
+const DRAIN_GRACE: Duration = Duration::from_secs(30);
+
+/// 正在进行分阶段切换的节点
+fn in_progress() -> &'static Mutex<HashSet<i64>> {
+    static IN_PROGRESS: OnceLock<Mutex<HashSet<i64>>> = OnceLock::new();
+    IN_PROGRESS.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// 在后台切换节点的隧道协议（或当前协议的传输参数）
+///
+/// 同一节点已有分阶段切换在进行时直接原地切换。
+pub fn spawn(
+    node_manager: Arc<NodeManager>,
+    client_stream_manager: Arc<ClientStreamManager>,
+    node_id: i64,
+    protocol: String,
+    kcp: Option<oxiproxy::GrpcKcpConfig>,
+    quic: Option<oxiproxy::GrpcQuicConfig>,
+) {
+    tokio::spawn(async move {
+        let staged = in_progress().lock().unwrap().insert(node_id);
+        if !staged {
+            info!("节点 #{} 正在分阶段切换协议，本次改为原地切换", node_id);
+            switch_in_place(&node_manager, &client_stream_manager, node_id, &protocol, kcp, quic).await;
+            return;
+        }
+
+        run_staged(&node_manager, &client_stream_manager, node_id, &protocol, kcp, quic).await;
+        in_progress().lock().unwrap().remove(&node_id);
+    });
+}
+
+async fn run_staged(
+    node_manager: &NodeManager,
+    client_stream_manager: &ClientStreamManager,
+    node_id: i64,
+    protocol: &str,
+    kcp: Option<oxiproxy::GrpcKcpConfig>,
+    quic: Option<oxiproxy::GrpcQuicConfig>,
+) {
+    let port = match node_manager.stage_protocol(node_id, protocol, kcp, quic.clone()).await {
+        Ok(Some(port)) => port,
+        Ok(None) => {
+            // 旧版本节点忽略 staged 标志，已原地切换
+            info!("节点 #{} 不支持分阶段切换，已原地切换协议", node_id);
+            client_stream_manager.notify_clients_for_node(node_id).await;
+            return;
+        }
+        Err(e) => {
+            warn!("节点 #{} 分阶段切换失败，回退为原地切换: {}", node_id, e);
+            switch_in_place(node_manager, client_stream_manager, node_id, protocol, kcp, quic).await;
+            return;
+        }
+    };
+    info!("节点 #{} 新 {} 监听器已在端口 {} 上启动，开始迁移客户端", node_id, protocol.to_uppercase(), port);
+
+    if let Err(e) = update_tunnel_port(node_id, port).await {
+        // 端口未写入时客户端仍会连接旧端口，保留旧监听器，等待节点下次注册时更新端口
+        error!("更新节点 #{} 隧道端口失败，停止迁移: {}", node_id, e);
+        return;
+    }
+
+    let client_ids = client_stream_manager.client_ids_for_node(node_id).await;
+    let total = client_ids.len();
+    for (i, client_id) in client_ids.into_iter().enumerate() {
+        client_stream_manager.notify_proxy_change(&client_id).await;
+        if i + 1 < total {
+            tokio::time::sleep(MIGRATE_INTERVAL).await;
+        }
+    }
+    info!("节点 #{} 的 {} 个客户端已推送新配置，{} 秒后停止旧监听器", node_id, total, DRAIN_GRACE.as_secs());
+
+    tokio::time::sleep(DRAIN_GRACE).await;
+    match node_manager.retire_previous_listener(node_id).await {
+        Ok(()) => info!("节点 #{} 协议分阶段切换完成", node_id),
+        Err(e) => warn!("停止节点 #{} 的旧监听器失败: {}", node_id, e),
+    }
+}
+
+async fn switch_in_place(
+    node_manager: &NodeManager,
+    client_stream_manager: &ClientStreamManager,
+    node_id: i64,
+    protocol: &str,
+    kcp: Option<oxiproxy::GrpcKcpConfig>,
+    quic: Option<oxiproxy::GrpcQuicConfig>,
+) {
+    if let Err(e) = node_manager.send_update_protocol(node_id, protocol, kcp, quic).await {
+        warn!("推送协议更新到节点 #{} 失败: {}", node_id, e);
+    } else {
+        info!("已推送协议更新到节点 #{}", node_id);
+    }
+    client_stream_manager.notify_clients_for_node(node_id).await;
+}
+
+async fn update_tunnel_port(node_id: i64, port: u16) -> anyhow::Result<()> {
+    let db = get_connection().await;
+    let model = Node::find_by_id(node_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("节点不存在"))?;
+    if model.tunnel_port == port as i32 {
+        return Ok(());
+    }
+    let mut active: node::ActiveModel = model.into();
+    active.tunnel_port = Set(port as i32);
+    active.update(db).await?;
+    Ok(())
+}
@@ -0,0 +1,259 @@
+//! 代理定时启停
+//!
+//! 代理可以配置每周的启用时间窗口（按 Controller 本地时间），例如只在工作时间开放 RDP：
+//!
+//! ```text
+//! mon-fri 09:00-18:00; sat 10:00-12:00
+//! ```
+//!
+//! - 多个窗口用 `;` 分隔，每个窗口为「星期 + 时间段」，省略星期表示每天；
+//! - 星期支持 `mon`..`sun`、逗号列表和范围（`fri-mon` 可跨周）；
+//! - 结束时间不大于开始时间时表示跨过午夜（`22:00-06:00`），`24:00` 表示当天结束。
+//!
+//! 调度器只在时间窗口边界切换代理的 `enabled` 状态：窗口内手动禁用、窗口外手动启用的代理
+//! 保持手动设置，直到下一个边界。Controller 启动时按当前时间同步一次。
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Local, NaiveDateTime, Timelike};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use common::protocol::control::ProxyControl;
+
+use crate::client_stream_manager::ClientStreamManager;
+use crate::entity::{proxy, Proxy};
+use crate::migration::get_connection;
+
+/// 调度检查间隔
+const SCHEDULE_INTERVAL_SECS: u64 = 30;
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// 单个时间窗口
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Window {
+    /// 窗口开始的星期（周一为 0）
+    days: [bool; 7],
+    /// 开始时间（当天的分钟数）
+    start: u16,
+    /// 结束时间（分钟数，跨午夜时大于一天）
+    end: u16,
+}
+
+/// 代理的启用时间表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule(Vec<Window>);
+
+impl Schedule {
+    /// 指定时间是否处于任一启用窗口内
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        let day = now.weekday().num_days_from_monday() as usize;
+        let prev_day = (day + 6) % 7;
+        let minute = (now.hour() * 60 + now.minute()) as u16;
+
+        self.0.iter().any(|w| {
+            (w.days[day] && minute >= w.start && minute < w.end)
+                // 前一天开始、跨过午夜的窗口
+                || (w.days[prev_day] && w.end > MINUTES_PER_DAY && minute < w.end - MINUTES_PER_DAY)
+        })
+    }
+
+    /// 按 Controller 本地时间判断当前是否处于启用窗口内
+    pub fn is_active_now(&self) -> bool {
+        self.is_active(Local::now().naive_local())
+    }
+}
+
+fn parse_weekday(s: &str) -> Result<usize> {
+    let s = s.trim().to_ascii_lowercase();
+    WEEKDAYS
+        .iter()
+        .position(|d| s.starts_with(d))
+        .ok_or_else(|| anyhow!("无效的星期: {}", s))
+}
+
+fn parse_days(s: &str) -> Result<[bool; 7]> {
+    let mut days = [false; 7];
+    for part in s.split(',') {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let (from, to) = (parse_weekday(from)?, parse_weekday(to)?);
+                let mut d = from;
+                loop {
+                    days[d] = true;
+                    if d == to {
+                        break;
+                    }
+                    d = (d + 1) % 7;
+                }
+            }
+            None => days[parse_weekday(part)?] = true,
+        }
+    }
+    Ok(days)
+}
+
+fn parse_time(s: &str) -> Result<u16> {
+    let (h, m) = s.trim().split_once(':').ok_or_else(|| anyhow!("无效的时间: {}", s))?;
+    let h: u16 = h.parse().map_err(|_| anyhow!("无效的时间: {}", s))?;
+    let m: u16 = m.parse().map_err(|_| anyhow!("无效的时间: {}", s))?;
+    if m >= 60 || h > 24 || (h == 24 && m != 0) {
+        return Err(anyhow!("无效的时间: {}", s));
+    }
+    Ok(h * 60 + m)
+}
+
+impl FromStr for Window {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (days, times) = match s.rsplit_once(char::is_whitespace) {
+            Some((days, times)) => (parse_days(days.trim())?, times),
+            None => ([true; 7], s),
+        };
+        let (start, end) = times.split_once('-').ok_or_else(|| anyhow!("无效的时间段: {}", times))?;
+        let start = parse_time(start)?;
+        let mut end = parse_time(end)?;
+        if start >= MINUTES_PER_DAY {
+            return Err(anyhow!("无效的开始时间: {}", times));
+        }
+        if end <= start {
+            end += MINUTES_PER_DAY;
+        }
+        Ok(Self { days, start, end })
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let windows = s
+            .split(';')
+            .filter(|w| !w.trim().is_empty())
+            .map(|w| w.parse().map_err(|e| anyhow!("无效的时间窗口 {}: {}", w.trim(), e)))
+            .collect::<Result<Vec<Window>>>()?;
+        if windows.is_empty() {
+            return Err(anyhow!("时间表不能为空"));
+        }
+        Ok(Self(windows))
+    }
+}
+
+/// 启动代理定时启停后台任务
+pub fn start_proxy_scheduler(
+    proxy_control: Arc<dyn ProxyControl>,
+    client_stream_manager: Arc<ClientStreamManager>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(SCHEDULE_INTERVAL_SECS));
+        // 上一次检查时各代理是否处于窗口内，用于识别边界
+        let mut last_active: HashMap<i64, bool> = HashMap::new();
+
+        loop {
+            interval.tick().await;
+
+            let db = get_connection().await;
+            let proxies = match Proxy::find()
+                .filter(proxy::Column::Schedule.is_not_null())
+                .all(db)
+                .await
+            {
+                Ok(p) => p,
+                Err(e) => {
+                    error!("查询定时代理失败: {}", e);
+                    continue;
+                }
+            };
+
+            last_active.retain(|id, _| proxies.iter().any(|p| p.id == *id));
+
+            for p in proxies {
+                let Some(schedule) = p.schedule.as_deref() else { continue };
+                // 已到期的代理不再按时间表启用
+                if crate::expiration::is_expired(p.expires_at) {
+                    continue;
+                }
+                let active = match schedule.parse::<Schedule>() {
+                    Ok(s) => s.is_active_now(),
+                    Err(e) => {
+                        warn!("代理 {} (ID: {}) 的时间表无效: {}", p.name, p.id, e);
+                        continue;
+                    }
+                };
+
+                if last_active.insert(p.id, active) == Some(active) || p.enabled == active {
+                    continue;
+                }
+
+                let client_id = p.client_id.clone();
+                let (id, name) = (p.id, p.name.clone());
+                let mut model: proxy::ActiveModel = p.into();
+                model.enabled = Set(active);
+                model.updated_at = Set(chrono::Utc::now().naive_utc());
+                if let Err(e) = model.update(db).await {
+                    error!("更新定时代理 {} (ID: {}) 失败: {}", name, id, e);
+                    continue;
+                }
+
+                if active {
+                    if let Err(e) = proxy_control.start_proxy(&client_id, id).await {
+                        error!("定时启用代理 {} (ID: {}) 失败: {}", name, id, e);
+                        let _ = Proxy::update_many()
+                            .col_expr(proxy::Column::Enabled, sea_orm::sea_query::Expr::value(false))
+                            .filter(proxy::Column::Id.eq(id))
+                            .exec(db)
+                            .await;
+                        continue;
+                    }
+                    info!("代理 {} (ID: {}) 已按时间表启用", name, id);
+                } else {
+                    if let Err(e) = proxy_control.stop_proxy(&client_id, id).await {
+                        warn!("定时停止代理 {} (ID: {}) 监听器失败: {}", name, id, e);
+                    }
+                    info!("代理 {} (ID: {}) 已按时间表禁用", name, id);
+                }
+
+                client_stream_manager.notify_proxy_change(&client_id).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_schedule() {
+        // 2026-03-09 是周一
+        let s: Schedule = "mon-fri 09:00-18:00; sat 10:00-12:00".parse().unwrap();
+        assert!(s.is_active(at("2026-03-09 09:00")));
+        assert!(!s.is_active(at("2026-03-09 18:00")));
+        assert!(s.is_active(at("2026-03-14 11:00")));
+        assert!(!s.is_active(at("2026-03-15 11:00")));
+
+        let s: Schedule = "fri-sat 22:00-06:00".parse().unwrap();
+        assert!(s.is_active(at("2026-03-13 23:00")));
+        assert!(s.is_active(at("2026-03-15 05:59")));
+        assert!(!s.is_active(at("2026-03-16 01:00")));
+
+        let s: Schedule = "08:00-24:00".parse().unwrap();
+        assert!(s.is_active(at("2026-03-11 23:59")));
+        assert!(!s.is_active(at("2026-03-11 07:59")));
+
+        assert!("".parse::<Schedule>().is_err());
+        assert!("mon 25:00-26:00".parse::<Schedule>().is_err());
+        assert!("funday 09:00-10:00".parse::<Schedule>().is_err());
+    }
+}
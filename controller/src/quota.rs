@@ -0,0 +1,591 @@
+//! 流量配额服务
+//!
+//! 用户的流量配额可以再分配给自己的客户端。配额的分配、回收、调整以及流量扣减都经过这里：
+//! 同一时间只有一个配额操作在执行，检查和写入在同一个数据库事务内完成，
+//! 避免两个并发请求各自通过检查后共同超额分配。
+//!
+//! 不变量（用户设置了配额时）：已使用流量 + 已分配给客户端的配额 ≤ 用户配额。
+//! 缩减分配不受该约束；平台管理员可以直接设置客户端配额，由此产生的超额分配只在对账时记录警告。
+//!
+//! 对账任务每 10 分钟运行一次，修复两类偏差：
+//! - 用户累计流量小于其客户端累计流量之和（例如某次写入失败），按客户端之和补齐；
+//! - 超额标记与实际用量不一致（例如调整配额后标记未更新），按用量重新设置。
+
+use anyhow::Result;
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QuerySelect,
+    Set, TransactionTrait,
+};
+use std::fmt;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::entity::{client, user, Client, User};
+use crate::migration::get_connection;
+use crate::traffic_limiter::{bytes_to_gb, gb_to_bytes, should_reset_client_traffic, should_reset_traffic};
+
+/// 对账间隔
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(600);
+/// 比较配额时容许的浮点误差（GB）
+const EPSILON_GB: f64 = 1e-6;
+
+/// 配额操作失败的原因
+#[derive(Debug)]
+pub enum QuotaError {
+    NotFound(&'static str),
+    /// 违反配额约束，消息可直接返回给用户
+    Rejected(String),
+    Db(DbErr),
+}
+
+impl fmt::Display for QuotaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaError::NotFound(msg) => write!(f, "{}", msg),
+            QuotaError::Rejected(msg) => write!(f, "{}", msg),
+            QuotaError::Db(e) => write!(f, "数据库错误: {}", e),
+        }
+    }
+}
+
+impl From<DbErr> for QuotaError {
+    fn from(e: DbErr) -> Self {
+        QuotaError::Db(e)
+    }
+}
+
+/// 用户配额账目
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuotaLedger {
+    /// 用户配额，`None` 表示不限制
+    pub quota_gb: Option<f64>,
+    /// 已使用流量
+    pub used_gb: f64,
+    /// 已分配给客户端的配额之和
+    pub allocated_gb: f64,
+}
+
+impl QuotaLedger {
+    /// 还可以分配的配额，不限制时返回 `None`
+    pub fn available_gb(&self) -> Option<f64> {
+        self.quota_gb.map(|quota| (quota - self.used_gb - self.allocated_gb).max(0.0))
+    }
+
+    /// 超出用户配额的部分，未超出时为 0
+    pub fn overcommitted_gb(&self) -> f64 {
+        self.quota_gb
+            .map_or(0.0, |quota| (self.used_gb + self.allocated_gb - quota).max(0.0))
+    }
+
+    /// 把某个客户端的分配从 `current_gb` 调整为 `target_gb`，只在增加分配时检查可用配额
+    pub fn check_reallocate(&self, current_gb: f64, target_gb: f64) -> Result<(), String> {
+        let Some(quota) = self.quota_gb else {
+            return Ok(());
+        };
+        let additional = target_gb - current_gb;
+        if additional <= 0.0 || self.used_gb + self.allocated_gb + additional <= quota + EPSILON_GB {
+            return Ok(());
+        }
+        Err(format!(
+            "配额不足: 可用 {:.2} GB，需要 {:.2} GB (用户配额: {:.2} GB，已使用: {:.2} GB，已分配: {:.2} GB)",
+            self.available_gb().unwrap_or(0.0),
+            additional,
+            quota,
+            self.used_gb,
+            self.allocated_gb
+        ))
+    }
+
+    /// 把用户配额调整为 `new_quota_gb`，减少配额时新配额需覆盖已使用和已分配的部分
+    pub fn check_quota_change(&self, new_quota_gb: f64) -> Result<(), String> {
+        if new_quota_gb < 0.0 {
+            return Err("配额不能为负数".to_string());
+        }
+        let decreasing = self.quota_gb.is_some_and(|quota| new_quota_gb < quota);
+        let needed = self.used_gb + self.allocated_gb;
+        if decreasing && new_quota_gb + EPSILON_GB < needed {
+            return Err(format!(
+                "配额不足: 新配额 {:.2} GB 小于已使用 {:.2} GB + 已分配 {:.2} GB = {:.2} GB",
+                new_quota_gb, self.used_gb, self.allocated_gb, needed
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 累计流量及其统计起点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    pub sent: i64,
+    pub received: i64,
+    pub last_reset_at: Option<NaiveDateTime>,
+}
+
+impl From<&user::Model> for Usage {
+    fn from(u: &user::Model) -> Self {
+        Usage { sent: u.total_bytes_sent, received: u.total_bytes_received, last_reset_at: u.last_reset_at }
+    }
+}
+
+impl From<&client::Model> for Usage {
+    fn from(c: &client::Model) -> Self {
+        Usage { sent: c.total_bytes_sent, received: c.total_bytes_received, last_reset_at: c.last_reset_at }
+    }
+}
+
+/// 用户累计流量应不小于其客户端累计流量之和。只有用户的统计周期覆盖所有客户端的统计周期
+/// （用户上次重置不晚于每个客户端上次重置）时两者才可比较。
+///
+/// 存在偏差时返回用户应补齐到的 (发送, 接收)。
+pub fn usage_drift(user: Usage, clients: &[Usage]) -> Option<(i64, i64)> {
+    let user_reset = user.last_reset_at?;
+    if clients.iter().any(|c| c.last_reset_at.is_none_or(|t| t < user_reset)) {
+        return None;
+    }
+    let sent: i64 = clients.iter().map(|c| c.sent).sum();
+    let received: i64 = clients.iter().map(|c| c.received).sum();
+    (sent > user.sent || received > user.received).then(|| (sent.max(user.sent), received.max(user.received)))
+}
+
+/// 按用量应有的超额标记，未设置配额时返回 `None`（保持原状）
+pub fn expected_exceeded(quota_gb: Option<f64>, used_bytes: i64) -> Option<bool> {
+    quota_gb.map(|quota| used_bytes >= gb_to_bytes(quota))
+}
+
+/// 串行化所有配额操作
+fn lock() -> &'static Mutex<()> {
+    static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| Mutex::new(()))
+}
+
+/// 读取用户的配额账目，已分配配额按 `client.user_id` 汇总
+pub async fn ledger<C: ConnectionTrait>(db: &C, user: &user::Model) -> Result<QuotaLedger, DbErr> {
+    let allocations: Vec<Option<f64>> = Client::find()
+        .select_only()
+        .column(client::Column::TrafficQuotaGb)
+        .filter(client::Column::UserId.eq(user.id))
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    Ok(QuotaLedger {
+        quota_gb: user.traffic_quota_gb,
+        used_gb: bytes_to_gb(user.total_bytes_sent + user.total_bytes_received),
+        allocated_gb: allocations.into_iter().flatten().sum(),
+    })
+}
+
+/// 为客户端分配配额。`enforce` 为 false 时（平台管理员）不检查所属用户的可用配额
+pub async fn allocate(db: &DatabaseConnection, client_id: i64, quota_gb: f64, enforce: bool) -> Result<client::Model, QuotaError> {
+    if quota_gb < 0.0 {
+        return Err(QuotaError::Rejected("配额不能为负数".to_string()));
+    }
+    set_allocation(db, client_id, Some(quota_gb), enforce).await
+}
+
+/// 回收客户端的配额分配，客户端改为直接使用所属用户的配额
+pub async fn release(db: &DatabaseConnection, client_id: i64) -> Result<client::Model, QuotaError> {
+    set_allocation(db, client_id, None, false).await
+}
+
+async fn set_allocation(
+    db: &DatabaseConnection,
+    client_id: i64,
+    quota_gb: Option<f64>,
+    enforce: bool,
+) -> Result<client::Model, QuotaError> {
+    let _guard = lock().lock().await;
+    let txn = db.begin().await?;
+
+    let client = Client::find_by_id(client_id)
+        .one(&txn)
+        .await?
+        .ok_or(QuotaError::NotFound("客户端不存在"))?;
+
+    if enforce {
+        if let Some(user_id) = client.user_id {
+            if let Some(owner) = User::find_by_id(user_id).one(&txn).await? {
+                ledger(&txn, &owner)
+                    .await?
+                    .check_reallocate(client.traffic_quota_gb.unwrap_or(0.0), quota_gb.unwrap_or(0.0))
+                    .map_err(QuotaError::Rejected)?;
+            }
+        }
+    }
+
+    let mut client_active: client::ActiveModel = client.into();
+    client_active.traffic_quota_gb = Set(quota_gb);
+    client_active.updated_at = Set(Utc::now().naive_utc());
+    let updated = client_active.update(&txn).await?;
+
+    txn.commit().await?;
+    Ok(updated)
+}
+
+/// 调整用户配额，返回调整后的配额
+pub async fn adjust_user_quota(db: &DatabaseConnection, user_id: i64, change_gb: f64) -> Result<f64, QuotaError> {
+    let _guard = lock().lock().await;
+    let txn = db.begin().await?;
+
+    let user = User::find_by_id(user_id)
+        .one(&txn)
+        .await?
+        .ok_or(QuotaError::NotFound("用户不存在"))?;
+
+    let new_quota = user.traffic_quota_gb.unwrap_or(0.0) + change_gb;
+    ledger(&txn, &user)
+        .await?
+        .check_quota_change(new_quota)
+        .map_err(QuotaError::Rejected)?;
+
+    let mut user_active: user::ActiveModel = user.into();
+    user_active.traffic_quota_gb = Set(Some(new_quota));
+    user_active.updated_at = Set(Utc::now().naive_utc());
+    user_active.update(&txn).await?;
+
+    txn.commit().await?;
+    Ok(new_quota)
+}
+
+/// 计入客户端及其所属用户的流量：到达重置周期时先重置，用量达到配额时设置超额标记
+pub async fn consume(db: &DatabaseConnection, client_id: i64, bytes_sent: i64, bytes_received: i64) -> Result<(), QuotaError> {
+    let _guard = lock().lock().await;
+    let now = Utc::now().naive_utc();
+    let txn = db.begin().await?;
+
+    let Some(client) = Client::find_by_id(client_id).one(&txn).await? else {
+        return Ok(());
+    };
+    charge_client(&txn, &client, bytes_sent, bytes_received, now).await?;
+
+    if let Some(user_id) = client.user_id {
+        if let Some(user) = User::find_by_id(user_id).one(&txn).await? {
+            charge_user(&txn, &user, bytes_sent, bytes_received, now).await?;
+        }
+    }
+
+    txn.commit().await?;
+    Ok(())
+}
+
+async fn charge_client<C: ConnectionTrait>(
+    db: &C,
+    client: &client::Model,
+    bytes_sent: i64,
+    bytes_received: i64,
+    now: NaiveDateTime,
+) -> Result<(), DbErr> {
+    let mut update = Client::update_many()
+        .col_expr(client::Column::UpdatedAt, Expr::value(now))
+        .filter(client::Column::Id.eq(client.id));
+    if should_reset_client_traffic(client) {
+        update = update
+            .col_expr(client::Column::TotalBytesSent, Expr::value(bytes_sent))
+            .col_expr(client::Column::TotalBytesReceived, Expr::value(bytes_received))
+            .col_expr(client::Column::IsTrafficExceeded, Expr::value(false))
+            .col_expr(client::Column::LastResetAt, Expr::value(now));
+        info!("🔄 客户端 #{} ({}) 流量已自动重置", client.id, client.name);
+    } else {
+        update = update
+            .col_expr(client::Column::TotalBytesSent, Expr::col(client::Column::TotalBytesSent).add(bytes_sent))
+            .col_expr(
+                client::Column::TotalBytesReceived,
+                Expr::col(client::Column::TotalBytesReceived).add(bytes_received),
+            );
+    }
+    update.exec(db).await?;
+
+    let Some(quota_gb) = client.traffic_quota_gb else {
+        return Ok(());
+    };
+    let Some(updated) = Client::find_by_id(client.id).one(db).await? else {
+        return Ok(());
+    };
+    let total_used = updated.total_bytes_sent + updated.total_bytes_received;
+    if !updated.is_traffic_exceeded && total_used >= gb_to_bytes(quota_gb) {
+        set_client_exceeded(db, client.id, true, now).await?;
+        error!(
+            "⚠️ 客户端 #{} ({}) 流量配额已用尽: {:.2} GB / {:.2} GB",
+            client.id,
+            client.name,
+            bytes_to_gb(total_used),
+            quota_gb
+        );
+    }
+    Ok(())
+}
+
+async fn charge_user<C: ConnectionTrait>(
+    db: &C,
+    user: &user::Model,
+    bytes_sent: i64,
+    bytes_received: i64,
+    now: NaiveDateTime,
+) -> Result<(), DbErr> {
+    let mut update = User::update_many()
+        .col_expr(user::Column::UpdatedAt, Expr::value(now))
+        .filter(user::Column::Id.eq(user.id));
+    if should_reset_traffic(user) {
+        update = update
+            .col_expr(user::Column::TotalBytesSent, Expr::value(bytes_sent))
+            .col_expr(user::Column::TotalBytesReceived, Expr::value(bytes_received))
+            .col_expr(user::Column::IsTrafficExceeded, Expr::value(false))
+            .col_expr(user::Column::LastResetAt, Expr::value(now));
+        info!("🔄 用户 #{} ({}) 流量已自动重置", user.id, user.username);
+    } else {
+        update = update
+            .col_expr(user::Column::TotalBytesSent, Expr::col(user::Column::TotalBytesSent).add(bytes_sent))
+            .col_expr(
+                user::Column::TotalBytesReceived,
+                Expr::col(user::Column::TotalBytesReceived).add(bytes_received),
+            );
+    }
+    update.exec(db).await?;
+
+    let Some(quota_gb) = user.traffic_quota_gb else {
+        return Ok(());
+    };
+    let Some(updated) = User::find_by_id(user.id).one(db).await? else {
+        return Ok(());
+    };
+    let total_used = updated.total_bytes_sent + updated.total_bytes_received;
+    if !updated.is_traffic_exceeded && total_used >= gb_to_bytes(quota_gb) {
+        set_user_exceeded(db, user.id, true, now).await?;
+        error!(
+            "⚠️ 用户 #{} ({}) 流量配额已用尽: {:.2} GB / {:.2} GB",
+            user.id,
+            user.username,
+            bytes_to_gb(total_used),
+            quota_gb
+        );
+    }
+    Ok(())
+}
+
+async fn set_client_exceeded<C: ConnectionTrait>(db: &C, client_id: i64, exceeded: bool, now: NaiveDateTime) -> Result<(), DbErr> {
+    Client::update_many()
+        .col_expr(client::Column::IsTrafficExceeded, Expr::value(exceeded))
+        .col_expr(client::Column::UpdatedAt, Expr::value(now))
+        .filter(client::Column::Id.eq(client_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+async fn set_user_exceeded<C: ConnectionTrait>(db: &C, user_id: i64, exceeded: bool, now: NaiveDateTime) -> Result<(), DbErr> {
+    User::update_many()
+        .col_expr(user::Column::IsTrafficExceeded, Expr::value(exceeded))
+        .col_expr(user::Column::UpdatedAt, Expr::value(now))
+        .filter(user::Column::Id.eq(user_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// 一次对账的结果
+#[derive(Debug, Default)]
+struct ReconcileReport {
+    usage_repaired: usize,
+    flags_repaired: usize,
+    overcommitted: usize,
+}
+
+/// 修复客户端的超额标记，返回是否做了修改
+async fn reconcile_client_flag<C: ConnectionTrait>(db: &C, client: &client::Model, now: NaiveDateTime) -> Result<bool, DbErr> {
+    let used = client.total_bytes_sent + client.total_bytes_received;
+    match expected_exceeded(client.traffic_quota_gb, used) {
+        Some(expected) if expected != client.is_traffic_exceeded => {
+            set_client_exceeded(db, client.id, expected, now).await?;
+            warn!("配额对账: 客户端 #{} ({}) 超额标记修正为 {}", client.id, client.name, expected);
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// 对账单个用户及其客户端
+async fn reconcile_user(db: &DatabaseConnection, user_id: i64, report: &mut ReconcileReport) -> Result<(), DbErr> {
+    let _guard = lock().lock().await;
+    let now = Utc::now().naive_utc();
+    let txn = db.begin().await?;
+
+    let Some(mut user) = User::find_by_id(user_id).one(&txn).await? else {
+        return Ok(());
+    };
+    let clients = Client::find().filter(client::Column::UserId.eq(user_id)).all(&txn).await?;
+
+    let usages: Vec<Usage> = clients.iter().map(Usage::from).collect();
+    if let Some((sent, received)) = usage_drift(Usage::from(&user), &usages) {
+        User::update_many()
+            .col_expr(user::Column::TotalBytesSent, Expr::value(sent))
+            .col_expr(user::Column::TotalBytesReceived, Expr::value(received))
+            .col_expr(user::Column::UpdatedAt, Expr::value(now))
+            .filter(user::Column::Id.eq(user_id))
+            .exec(&txn)
+            .await?;
+        warn!(
+            "配额对账: 用户 #{} ({}) 累计流量小于客户端之和，已补齐 ({} / {} → {} / {})",
+            user.id, user.username, user.total_bytes_sent, user.total_bytes_received, sent, received
+        );
+        user.total_bytes_sent = sent;
+        user.total_bytes_received = received;
+        report.usage_repaired += 1;
+    }
+
+    let used = user.total_bytes_sent + user.total_bytes_received;
+    if let Some(expected) = expected_exceeded(user.traffic_quota_gb, used) {
+        if expected != user.is_traffic_exceeded {
+            set_user_exceeded(&txn, user.id, expected, now).await?;
+            warn!("配额对账: 用户 #{} ({}) 超额标记修正为 {}", user.id, user.username, expected);
+            report.flags_repaired += 1;
+        }
+    }
+
+    for client in &clients {
+        if reconcile_client_flag(&txn, client, now).await? {
+            report.flags_repaired += 1;
+        }
+    }
+
+    let ledger = ledger(&txn, &user).await?;
+    if ledger.overcommitted_gb() > EPSILON_GB {
+        warn!(
+            "配额对账: 用户 #{} ({}) 已使用 {:.2} GB + 已分配 {:.2} GB 超出配额 {:.2} GB",
+            user.id,
+            user.username,
+            ledger.used_gb,
+            ledger.allocated_gb,
+            ledger.quota_gb.unwrap_or(0.0)
+        );
+        report.overcommitted += 1;
+    }
+
+    txn.commit().await
+}
+
+/// 对账不属于任何用户的客户端（只检查超额标记）
+async fn reconcile_unowned_clients(db: &DatabaseConnection, report: &mut ReconcileReport) -> Result<(), DbErr> {
+    let _guard = lock().lock().await;
+    let now = Utc::now().naive_utc();
+    let txn = db.begin().await?;
+
+    let clients = Client::find().filter(client::Column::UserId.is_null()).all(&txn).await?;
+    for client in &clients {
+        if reconcile_client_flag(&txn, client, now).await? {
+            report.flags_repaired += 1;
+        }
+    }
+
+    txn.commit().await
+}
+
+/// 对账所有用户和客户端；每个用户单独持锁和开启事务，不长时间阻塞流量写入
+async fn reconcile(db: &DatabaseConnection) -> Result<ReconcileReport> {
+    let mut report = ReconcileReport::default();
+
+    let user_ids: Vec<i64> = User::find().select_only().column(user::Column::Id).into_tuple().all(db).await?;
+    for user_id in user_ids {
+        reconcile_user(db, user_id, &mut report).await?;
+    }
+    reconcile_unowned_clients(db, &mut report).await?;
+
+    Ok(report)
+}
+
+/// 启动配额对账任务
+pub fn start_quota_reconciler() {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RECONCILE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            match reconcile(get_connection().await).await {
+                Ok(report) if report.usage_repaired + report.flags_repaired + report.overcommitted > 0 => {
+                    info!(
+                        "配额对账完成: 修复流量 {} 个用户，修正超额标记 {} 处，超额分配 {} 个用户",
+                        report.usage_repaired, report.flags_repaired, report.overcommitted
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => error!("配额对账失败: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32) -> Option<NaiveDateTime> {
+        NaiveDate::from_ymd_opt(2026, 3, day).and_then(|d| d.and_hms_opt(0, 0, 0))
+    }
+
+    fn usage(sent: i64, received: i64, day: u32) -> Usage {
+        Usage { sent, received, last_reset_at: at(day) }
+    }
+
+    #[test]
+    fn test_reallocate_respects_invariant() {
+        let ledger = QuotaLedger { quota_gb: Some(100.0), used_gb: 30.0, allocated_gb: 50.0 };
+        assert_eq!(ledger.available_gb(), Some(20.0));
+        // 10 GB 的客户端增加到 30 GB，需要额外 20 GB，恰好可用
+        assert!(ledger.check_reallocate(10.0, 30.0).is_ok());
+        assert!(ledger.check_reallocate(10.0, 30.5).is_err());
+        // 缩减分配总是允许
+        assert!(ledger.check_reallocate(30.0, 0.0).is_ok());
+    }
+
+    #[test]
+    fn test_unlimited_user_allows_any_allocation() {
+        let ledger = QuotaLedger { quota_gb: None, used_gb: 500.0, allocated_gb: 500.0 };
+        assert_eq!(ledger.available_gb(), None);
+        assert_eq!(ledger.overcommitted_gb(), 0.0);
+        assert!(ledger.check_reallocate(0.0, 1000.0).is_ok());
+    }
+
+    #[test]
+    fn test_quota_change() {
+        let ledger = QuotaLedger { quota_gb: Some(100.0), used_gb: 30.0, allocated_gb: 50.0 };
+        assert!(ledger.check_quota_change(80.0).is_ok());
+        assert!(ledger.check_quota_change(79.0).is_err());
+        assert!(ledger.check_quota_change(-1.0).is_err());
+        assert!(ledger.check_quota_change(200.0).is_ok());
+
+        // 已经超额时仍然可以增加配额
+        let over = QuotaLedger { quota_gb: Some(50.0), used_gb: 30.0, allocated_gb: 50.0 };
+        assert_eq!(over.overcommitted_gb(), 30.0);
+        assert!(over.check_quota_change(60.0).is_ok());
+        assert!(over.check_quota_change(40.0).is_err());
+    }
+
+    #[test]
+    fn test_usage_drift_repairs_missing_user_traffic() {
+        let clients = [usage(100, 200, 2), usage(50, 10, 3)];
+        assert_eq!(usage_drift(usage(120, 250, 1), &clients), Some((150, 250)));
+        assert_eq!(usage_drift(usage(150, 210, 1), &clients), None);
+        assert_eq!(usage_drift(usage(0, 0, 2), &[]), None);
+    }
+
+    #[test]
+    fn test_usage_drift_skips_incomparable_periods() {
+        // 用户在客户端之后重置过，用户累计值小于客户端之和是正常的
+        assert_eq!(usage_drift(usage(0, 0, 5), &[usage(100, 100, 1)]), None);
+        // 从未重置过的客户端或用户无法确定统计起点
+        assert_eq!(usage_drift(usage(0, 0, 1), &[Usage { sent: 1, received: 1, last_reset_at: None }]), None);
+        assert_eq!(usage_drift(Usage { sent: 0, received: 0, last_reset_at: None }, &[usage(1, 1, 1)]), None);
+    }
+
+    #[test]
+    fn test_expected_exceeded() {
+        assert_eq!(expected_exceeded(None, i64::MAX), None);
+        assert_eq!(expected_exceeded(Some(1.0), gb_to_bytes(1.0)), Some(true));
+        assert_eq!(expected_exceeded(Some(1.0), gb_to_bytes(1.0) - 1), Some(false));
+    }
+}
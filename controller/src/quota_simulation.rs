@@ -0,0 +1,380 @@
+//! 配额变更模拟
+//!
+//! 管理员为用户分配套餐、停用订阅或直接修改配额前，先按与实际生效相同的合并/回退规则计算出
+//! 变更后的配额，再与用户当前的用量对比：哪些现有代理会超出端口范围或端口数量上限，流量、
+//! 节点和客户端数量是否超出，以及这些问题是变更导致的还是现在就已存在。只读取数据库，不做任何修改。
+
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+
+use crate::entity::{client, proxy, user, user_node, user_subscription, Client, Proxy, Subscription, UserNode, UserSubscription};
+use crate::port_limiter::{is_port_in_ranges, parse_port_ranges};
+use crate::quota::{QuotaError, QuotaLedger};
+
+/// 用户的配额限制，`None` 表示不限制
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaLimits {
+    pub traffic_quota_gb: Option<f64>,
+    pub max_port_count: Option<i32>,
+    pub max_node_count: Option<i32>,
+    pub max_client_count: Option<i32>,
+    pub allowed_port_range: Option<String>,
+}
+
+impl QuotaLimits {
+    fn of(user: &user::Model) -> Self {
+        QuotaLimits {
+            traffic_quota_gb: user.traffic_quota_gb,
+            max_port_count: user.max_port_count,
+            max_node_count: user.max_node_count,
+            max_client_count: user.max_client_count,
+            allowed_port_range: user.allowed_port_range.clone(),
+        }
+    }
+
+    /// 与 `merge_subscription_quota_to_user` 相同：流量总是累加，不限制的数量限制累加后变为有限
+    fn merge(&mut self, traffic_gb: f64, port: Option<i32>, node: Option<i32>, client: Option<i32>) {
+        self.traffic_quota_gb = Some(self.traffic_quota_gb.unwrap_or(0.0) + traffic_gb);
+        let add = |current: Option<i32>, n: Option<i32>| n.map(|n| current.unwrap_or(0) + n).or(current);
+        self.max_port_count = add(self.max_port_count, port);
+        self.max_node_count = add(self.max_node_count, node);
+        self.max_client_count = add(self.max_client_count, client);
+    }
+
+    /// 与 `rollback_subscription_quota_from_user` 相同，下限为 0
+    fn rollback(&mut self, traffic_gb: f64, port: Option<i32>, node: Option<i32>, client: Option<i32>) {
+        self.traffic_quota_gb = Some((self.traffic_quota_gb.unwrap_or(0.0) - traffic_gb).max(0.0));
+        let sub = |current: Option<i32>, n: Option<i32>| n.map(|n| (current.unwrap_or(0) - n).max(0)).or(current);
+        self.max_port_count = sub(self.max_port_count, port);
+        self.max_node_count = sub(self.max_node_count, node);
+        self.max_client_count = sub(self.max_client_count, client);
+    }
+}
+
+/// 要模拟的配额变更，依次应用：停用订阅、分配套餐、直接设置的新值
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaChange {
+    /// 将要分配的套餐
+    pub subscription_id: Option<i64>,
+    /// 将要停用的用户订阅
+    pub deactivate_user_subscription_id: Option<i64>,
+    pub traffic_quota_gb: Option<f64>,
+    pub max_port_count: Option<i32>,
+    pub max_node_count: Option<i32>,
+    pub max_client_count: Option<i32>,
+    /// 空字符串表示不限制端口范围
+    pub allowed_port_range: Option<String>,
+}
+
+/// 用户当前的用量
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrentUsage {
+    pub used_gb: f64,
+    /// 已分配给客户端的流量配额
+    pub allocated_gb: f64,
+    pub port_count: u64,
+    pub node_count: u64,
+    pub client_count: u64,
+}
+
+/// 配额被超出的一项
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Violation {
+    /// traffic / trafficAllocation / portRange / portCount / nodeCount / clientCount
+    pub kind: &'static str,
+    pub message: String,
+    /// 变更前就已超出
+    pub existing: bool,
+}
+
+/// 变更后不再符合配额的代理
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AffectedProxy {
+    pub id: i64,
+    pub name: String,
+    pub client_id: String,
+    pub remote_port: u16,
+    pub reasons: Vec<String>,
+}
+
+/// 模拟结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationReport {
+    pub user_id: i64,
+    pub username: String,
+    pub current: QuotaLimits,
+    pub projected: QuotaLimits,
+    pub usage: CurrentUsage,
+    pub violations: Vec<Violation>,
+    pub affected_proxies: Vec<AffectedProxy>,
+}
+
+/// 检查用量是否超出配额，`proxies` 按创建顺序排列
+fn check(limits: &QuotaLimits, usage: &CurrentUsage, proxies: &[proxy::Model]) -> (Vec<Violation>, Vec<AffectedProxy>) {
+    let mut violations = Vec::new();
+    let mut affected: Vec<AffectedProxy> = Vec::new();
+    let mut flag = |p: &proxy::Model, reason: String| match affected.iter_mut().find(|a| a.id == p.id) {
+        Some(a) => a.reasons.push(reason),
+        None => affected.push(AffectedProxy {
+            id: p.id,
+            name: p.name.clone(),
+            client_id: p.client_id.clone(),
+            remote_port: p.remote_port,
+            reasons: vec![reason],
+        }),
+    };
+    let mut violate = |kind: &'static str, message: String| {
+        violations.push(Violation { kind, message, existing: false });
+    };
+
+    if let Some(quota) = limits.traffic_quota_gb {
+        if usage.used_gb >= quota {
+            violate(
+                "traffic",
+                format!("已使用 {:.2} GB，达到流量配额 {:.2} GB，用户将被标记为流量超限", usage.used_gb, quota),
+            );
+        }
+        let ledger = QuotaLedger { quota_gb: Some(quota), used_gb: usage.used_gb, allocated_gb: usage.allocated_gb };
+        let over = ledger.overcommitted_gb();
+        if over > 1e-6 {
+            violate(
+                "trafficAllocation",
+                format!(
+                    "已使用 {:.2} GB + 已分配给客户端 {:.2} GB 超出流量配额 {:.2} GB（超出 {:.2} GB）",
+                    usage.used_gb, usage.allocated_gb, quota, over
+                ),
+            );
+        }
+    }
+
+    if let Some(range) = limits.allowed_port_range.as_deref().filter(|r| !r.trim().is_empty()) {
+        match parse_port_ranges(range) {
+            Ok(ranges) => {
+                let outside: Vec<&proxy::Model> =
+                    proxies.iter().filter(|p| !is_port_in_ranges(p.remote_port, &ranges)).collect();
+                if !outside.is_empty() {
+                    violate("portRange", format!("{} 个代理的端口不在允许的范围 {} 内", outside.len(), range));
+                }
+                for p in outside {
+                    flag(p, format!("端口 {} 不在允许的范围 {} 内", p.remote_port, range));
+                }
+            }
+            Err(e) => violate("portRange", format!("端口范围配置错误: {}", e)),
+        }
+    }
+
+    if let Some(max) = limits.max_port_count {
+        let max = max.max(0) as u64;
+        if usage.port_count > max {
+            violate(
+                "portCount",
+                format!("现有代理 {} 个，超出端口数量上限 {}，删除前无法创建新代理", usage.port_count, max),
+            );
+            // 最后创建的代理超出上限
+            for p in proxies.iter().skip(max as usize) {
+                flag(p, format!("超出端口数量上限 {}", max));
+            }
+        }
+    }
+
+    if let Some(max) = limits.max_node_count {
+        if usage.node_count > max.max(0) as u64 {
+            violate("nodeCount", format!("已分配节点 {} 个，超出节点数量上限 {}", usage.node_count, max));
+        }
+    }
+
+    if let Some(max) = limits.max_client_count {
+        if usage.client_count > max.max(0) as u64 {
+            violate("clientCount", format!("现有客户端 {} 个，超出客户端数量上限 {}", usage.client_count, max));
+        }
+    }
+
+    (violations, affected)
+}
+
+/// 模拟 `change` 应用到 `user` 后的配额及其影响
+pub async fn simulate(
+    user: &user::Model,
+    change: &QuotaChange,
+    db: &DatabaseConnection,
+) -> Result<SimulationReport, QuotaError> {
+    let current = QuotaLimits::of(user);
+    let mut projected = current.clone();
+
+    if let Some(id) = change.deactivate_user_subscription_id {
+        let sub = UserSubscription::find_by_id(id)
+            .filter(user_subscription::Column::UserId.eq(user.id))
+            .one(db)
+            .await?
+            .ok_or(QuotaError::NotFound("用户订阅不存在"))?;
+        // 只有激活且已合并的订阅停用时才会回退配额
+        if sub.is_active && sub.quota_merged {
+            projected.rollback(
+                sub.traffic_quota_gb,
+                sub.max_port_count_snapshot,
+                sub.max_node_count_snapshot,
+                sub.max_client_count_snapshot,
+            );
+        }
+    }
+    if let Some(id) = change.subscription_id {
+        let subscription = Subscription::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or(QuotaError::NotFound("订阅套餐不存在"))?;
+        projected.merge(
+            subscription.traffic_quota_gb,
+            subscription.max_port_count,
+            subscription.max_node_count,
+            subscription.max_client_count,
+        );
+    }
+    if let Some(quota) = change.traffic_quota_gb {
+        if quota < 0.0 {
+            return Err(QuotaError::Rejected("配额不能为负数".to_string()));
+        }
+        projected.traffic_quota_gb = Some(quota);
+    }
+    if let Some(n) = change.max_port_count {
+        projected.max_port_count = Some(n);
+    }
+    if let Some(n) = change.max_node_count {
+        projected.max_node_count = Some(n);
+    }
+    if let Some(n) = change.max_client_count {
+        projected.max_client_count = Some(n);
+    }
+    if let Some(range) = &change.allowed_port_range {
+        let range = range.trim();
+        if range.is_empty() {
+            projected.allowed_port_range = None;
+        } else {
+            parse_port_ranges(range).map_err(|e| QuotaError::Rejected(format!("端口范围配置错误: {}", e)))?;
+            projected.allowed_port_range = Some(range.to_string());
+        }
+    }
+
+    let ledger = crate::quota::ledger(db, user).await?;
+    let client_ids: Vec<String> = Client::find()
+        .filter(client::Column::UserId.eq(user.id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|c| c.id.to_string())
+        .collect();
+    let proxies = if client_ids.is_empty() {
+        Vec::new()
+    } else {
+        Proxy::find()
+            .filter(proxy::Column::ClientId.is_in(client_ids.clone()))
+            .order_by_asc(proxy::Column::Id)
+            .all(db)
+            .await?
+    };
+    let usage = CurrentUsage {
+        used_gb: ledger.used_gb,
+        allocated_gb: ledger.allocated_gb,
+        port_count: proxies.len() as u64,
+        node_count: UserNode::find().filter(user_node::Column::UserId.eq(user.id)).count(db).await?,
+        client_count: client_ids.len() as u64,
+    };
+
+    let (before, _) = check(&current, &usage, &proxies);
+    let (mut violations, affected_proxies) = check(&projected, &usage, &proxies);
+    for v in &mut violations {
+        v.existing = before.iter().any(|b| b.kind == v.kind);
+    }
+
+    Ok(SimulationReport {
+        user_id: user.id,
+        username: user.username.clone(),
+        current,
+        projected,
+        usage,
+        violations,
+        affected_proxies,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy(id: i64, remote_port: u16) -> proxy::Model {
+        let now = chrono::Utc::now().naive_utc();
+        proxy::Model {
+            id,
+            client_id: "1".to_string(),
+            name: format!("p{}", id),
+            proxy_type: "tcp".to_string(),
+            local_ip: "127.0.0.1".to_string(),
+            local_port: 22,
+            remote_port,
+            bind_ip: None,
+            enabled: true,
+            anycast_node_ids: None,
+            node_id: None,
+            group_id: None,
+            idle_timeout: None,
+            mitigation_config: None,
+            bandwidth_weight: None,
+            priority: None,
+            local_pool_size: None,
+            local_source: None,
+            dns_name: None,
+            service: None,
+            schedule: None,
+            expires_at: None,
+            stale_at: None,
+            total_bytes_sent: 0,
+            total_bytes_received: 0,
+            lock_version: 0,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn test_merge_limits_unlimited_becomes_finite() {
+        let mut limits = QuotaLimits {
+            traffic_quota_gb: None,
+            max_port_count: None,
+            max_node_count: Some(1),
+            max_client_count: None,
+            allowed_port_range: None,
+        };
+        limits.merge(10.0, Some(5), None, None);
+        assert_eq!(limits.traffic_quota_gb, Some(10.0));
+        assert_eq!(limits.max_port_count, Some(5));
+        assert_eq!(limits.max_node_count, Some(1));
+        limits.rollback(20.0, Some(8), Some(1), None);
+        assert_eq!(limits.traffic_quota_gb, Some(0.0));
+        assert_eq!(limits.max_port_count, Some(0));
+        assert_eq!(limits.max_node_count, Some(0));
+    }
+
+    #[test]
+    fn test_check() {
+        let proxies = vec![proxy(1, 8080), proxy(2, 9000), proxy(3, 22000)];
+        let usage = CurrentUsage { used_gb: 5.0, allocated_gb: 2.0, port_count: 3, node_count: 0, client_count: 1 };
+        let limits = QuotaLimits {
+            traffic_quota_gb: Some(6.0),
+            max_port_count: Some(2),
+            max_node_count: None,
+            max_client_count: Some(1),
+            allowed_port_range: Some("8000-9999".to_string()),
+        };
+        let (violations, affected) = check(&limits, &usage, &proxies);
+        let kinds: Vec<&str> = violations.iter().map(|v| v.kind).collect();
+        assert_eq!(kinds, ["trafficAllocation", "portRange", "portCount"]);
+        assert_eq!(affected.len(), 1);
+        assert_eq!(affected[0].id, 3);
+        assert_eq!(affected[0].reasons.len(), 2);
+    }
+}
@@ -0,0 +1,178 @@
+//! 系统重启编排
+//!
+//! `POST /api/system/restart` 通知已连接的节点和客户端错开重连、记录审计日志后触发重启：
+//! `/readyz` 立即返回 503 让负载均衡器摘除流量，等待 `OXIPROXY_RESTART_DRAIN_SECS` 秒
+//! 让进行中的请求完成，然后原地重新执行自身（Unix 上 exec，Windows 上延迟启动新进程后退出）。
+//! 新进程启动完成后把审计记录标记为已完成，前端通过 `GET /api/system/restart` 中
+//! `bootId` 的变化确认重启完成。
+//!
+//! Web / gRPC 的 TLS 开关和 gRPC 证书只在启动时读取，修改后需要重启才生效，
+//! 这些配置项与启动时不同时作为待生效变更列出。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use tokio::sync::Notify;
+use tracing::{error, info};
+
+use crate::config_manager::{ConfigManager, ConfigValue};
+use crate::entity::{system_restart, SystemRestart};
+
+/// 只在启动时读取、修改后需要重启才生效的配置项
+pub const RESTART_KEYS: &[&str] = &[
+    "web_tls_enabled",
+    "grpc_tls_enabled",
+    "grpc_tls_cert_content",
+    "grpc_tls_key_content",
+    "grpc_tls_cert_path",
+    "grpc_tls_key_path",
+];
+
+const DEFAULT_DRAIN_SECS: u64 = 3;
+/// 关闭前通知 Agent 错开重连的默认时间窗口（秒）
+const DEFAULT_RECONNECT_SPREAD_SECS: u64 = 30;
+
+struct Boot {
+    id: String,
+    started_at: DateTime<Utc>,
+    config: Vec<(&'static str, Option<ConfigValue>)>,
+}
+
+static BOOT: OnceLock<Boot> = OnceLock::new();
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+fn notify() -> &'static Notify {
+    static NOTIFY: OnceLock<Notify> = OnceLock::new();
+    NOTIFY.get_or_init(Notify::new)
+}
+
+/// 记录本次启动时需要重启才生效的配置（在加载系统配置之后调用）
+pub async fn record_boot(config_manager: &ConfigManager) {
+    let mut config = Vec::with_capacity(RESTART_KEYS.len());
+    for &key in RESTART_KEYS {
+        config.push((key, config_manager.get(key).await));
+    }
+    let _ = BOOT.set(Boot { id: uuid::Uuid::new_v4().to_string(), started_at: Utc::now(), config });
+}
+
+/// 本次启动的标识，重启后变化
+pub fn boot_id() -> &'static str {
+    BOOT.get().map(|b| b.id.as_str()).unwrap_or_default()
+}
+
+pub fn started_at() -> Option<DateTime<Utc>> {
+    BOOT.get().map(|b| b.started_at)
+}
+
+/// 与启动时相比发生变化、需要重启才生效的配置项
+pub async fn pending_changes(config_manager: &ConfigManager) -> Vec<String> {
+    let Some(boot) = BOOT.get() else {
+        return Vec::new();
+    };
+    let mut changed = Vec::new();
+    for (key, value) in &boot.config {
+        if config_manager.get(key).await != *value {
+            changed.push(key.to_string());
+        }
+    }
+    changed
+}
+
+/// 关闭或重启前通知 Agent 错开重连的时间窗口
+pub fn reconnect_spread() -> Duration {
+    Duration::from_secs(
+        common::env::parse::<u64>("OXIPROXY_RECONNECT_SPREAD_SECS").unwrap_or(DEFAULT_RECONNECT_SPREAD_SECS),
+    )
+}
+
+/// 是否已请求重启（正在排空）
+pub fn is_requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// 请求重启，已在重启中时返回 false
+pub fn request() -> bool {
+    if REQUESTED.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+    notify().notify_one();
+    true
+}
+
+/// 等待重启请求
+pub async fn requested() {
+    if !is_requested() {
+        notify().notified().await;
+    }
+}
+
+/// 等待进行中的请求完成后重新执行自身，不会返回
+pub async fn drain_and_respawn() -> ! {
+    let drain = Duration::from_secs(common::env::parse::<u64>("OXIPROXY_RESTART_DRAIN_SECS").unwrap_or(DEFAULT_DRAIN_SECS));
+    info!("等待 {} 秒让进行中的请求完成...", drain.as_secs());
+    tokio::time::sleep(drain).await;
+    respawn()
+}
+
+#[cfg(unix)]
+fn respawn() -> ! {
+    use std::os::unix::process::CommandExt;
+
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            error!("无法获取可执行文件路径，改为直接退出: {}", e);
+            std::process::exit(1);
+        }
+    };
+    info!("系统正在重启: {}", exe.display());
+    // 自行监听的套接字带有 CLOEXEC 标志，exec 后端口随旧进程映像一起释放；
+    // systemd 传入的套接字需要保留给新的进程映像，否则 LISTEN_FDS 指向的文件描述符已被关闭
+    if let Err(e) = common::socket_activation::keep_across_exec() {
+        error!("保留 systemd 传入的套接字失败，改为直接退出由 systemd 重新启动: {}", e);
+        std::process::exit(1);
+    }
+    let e = std::process::Command::new(&exe).args(std::env::args_os().skip(1)).exec();
+    error!("重新执行 {} 失败，改为直接退出: {}", exe.display(), e);
+    std::process::exit(1);
+}
+
+#[cfg(not(unix))]
+fn respawn() -> ! {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            error!("无法获取可执行文件路径，改为直接退出: {}", e);
+            std::process::exit(1);
+        }
+    };
+    info!("系统正在重启: {}", exe.display());
+    // 延迟启动新进程，让当前进程先退出并释放端口
+    let mut cmd = std::process::Command::new("cmd");
+    cmd.args(["/c", "timeout", "/t", "2", "/nobreak", ">nul", "&&"]).arg(&exe).args(std::env::args_os().skip(1));
+    if let Ok(dir) = std::env::current_dir() {
+        cmd.current_dir(dir);
+    }
+    if let Err(e) = cmd.spawn() {
+        error!("启动新进程失败: {}", e);
+    }
+    std::process::exit(0);
+}
+
+/// 服务就绪后把尚未完成的重启记录标记为已完成
+pub async fn mark_completed(db: &DatabaseConnection) {
+    match SystemRestart::update_many()
+        .col_expr(system_restart::Column::CompletedAt, Expr::value(Utc::now().naive_utc()))
+        .filter(system_restart::Column::CompletedAt.is_null())
+        .exec(db)
+        .await
+    {
+        Ok(res) if res.rows_affected > 0 => info!("✅ 系统重启完成，服务已就绪"),
+        Ok(_) => {}
+        Err(e) => error!("更新重启记录失败: {}", e),
+    }
+}
@@ -0,0 +1,255 @@
+//! 历史数据保留与降采样
+//!
+//! 后台任务每 6 小时整理一次历史数据，避免繁忙部署的数据库无限增长：
+//!
+//! | 数据 | 策略 | 环境变量（天，0 表示永久保留） | 默认 |
+//! |------|------|------|------|
+//! | 按日流量 `traffic_daily` | 超期的按日记录合并为按月记录（记在当月 1 日） | `OXIPROXY_RETENTION_TRAFFIC_DAILY_DAYS` | 90 |
+//! | 按月流量 | 超期删除 | `OXIPROXY_RETENTION_TRAFFIC_MONTHLY_DAYS` | 730 |
+//! | 在线状态历史 `status_history` | 超期删除，每个对象保留最后一条作为起始状态 | `OXIPROXY_RETENTION_STATUS_HISTORY_DAYS` | 90 |
+//! | 已恢复的告警、已结束的来源 IP 处置、客户端重复登录事件 | 超期删除 | `OXIPROXY_RETENTION_EVENTS_DAYS` | 180 |
+//!
+//! 按月合并只处理整月都已超期的月份；每轮最多合并 [`COMPACT_BATCH`] 个（代理, 月份），剩余的下一轮继续。
+
+use anyhow::Result;
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, NotSet, QueryFilter, QuerySelect, Set,
+    TransactionTrait,
+};
+use std::sync::OnceLock;
+use tracing::{error, info};
+
+use crate::entity::alert_event::{self, STATE_RESOLVED};
+use crate::entity::{
+    client_login_event, mitigation_event, status_history, traffic_daily, AlertEvent, ClientLoginEvent, MitigationEvent,
+    StatusHistory, TrafficDaily,
+};
+use crate::migration::get_connection;
+
+/// 整理间隔
+const RUN_INTERVAL: std::time::Duration = std::time::Duration::from_secs(6 * 3600);
+/// 每轮最多合并的（代理, 月份）数
+const COMPACT_BATCH: u64 = 500;
+
+/// 保留策略（天，0 表示永久保留）
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    pub traffic_daily_days: i64,
+    pub traffic_monthly_days: i64,
+    pub status_history_days: i64,
+    pub events_days: i64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self { traffic_daily_days: 90, traffic_monthly_days: 730, status_history_days: 90, events_days: 180 }
+    }
+}
+
+pub fn policy() -> &'static RetentionPolicy {
+    static POLICY: OnceLock<RetentionPolicy> = OnceLock::new();
+    POLICY.get_or_init(|| {
+        let default = RetentionPolicy::default();
+        let days = |name: &str, default: i64| common::env::parse::<i64>(name).unwrap_or(default).max(0);
+        RetentionPolicy {
+            traffic_daily_days: days("OXIPROXY_RETENTION_TRAFFIC_DAILY_DAYS", default.traffic_daily_days),
+            traffic_monthly_days: days("OXIPROXY_RETENTION_TRAFFIC_MONTHLY_DAYS", default.traffic_monthly_days),
+            // 可用率最长统计 30 天，状态历史至少保留 30 天
+            status_history_days: match days("OXIPROXY_RETENTION_STATUS_HISTORY_DAYS", default.status_history_days) {
+                0 => 0,
+                d => d.max(30),
+            },
+            events_days: days("OXIPROXY_RETENTION_EVENTS_DAYS", default.events_days),
+        }
+    })
+}
+
+/// 保留 `days` 天时需要合并的月份上限：早于返回日期（某月 1 日）的按日记录合并为按月记录
+fn compact_before(today: NaiveDate, days: i64) -> NaiveDate {
+    let oldest_kept = today - Duration::days(days);
+    oldest_kept.with_day(1).unwrap()
+}
+
+/// 月份（`YYYY-MM`）对应的按月记录日期及下一个月的 1 日
+fn month_range(month: &str) -> Option<(String, String)> {
+    let first = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").ok()?;
+    let next = if first.month() == 12 {
+        NaiveDate::from_ymd_opt(first.year() + 1, 1, 1)?
+    } else {
+        NaiveDate::from_ymd_opt(first.year(), first.month() + 1, 1)?
+    };
+    Some((first.format("%Y-%m-%d").to_string(), next.format("%Y-%m-%d").to_string()))
+}
+
+/// 把 `before` 之前的按日流量合并为按月记录，返回合并的（代理, 月份）数
+async fn compact_traffic(db: &DatabaseConnection, before: NaiveDate) -> Result<usize> {
+    let before = before.format("%Y-%m-%d").to_string();
+    // 还有非 1 日记录的（代理, 月份）
+    let groups: Vec<(i64, String)> = TrafficDaily::find()
+        .select_only()
+        .column(traffic_daily::Column::ProxyId)
+        .column_as(Expr::cust("substr(date, 1, 7)"), "month")
+        .filter(traffic_daily::Column::Date.lt(&before))
+        .filter(traffic_daily::Column::Date.not_like("%-01"))
+        .distinct()
+        .limit(COMPACT_BATCH)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let mut compacted = 0;
+    for (proxy_id, month) in groups {
+        let Some((first, next)) = month_range(&month) else {
+            continue;
+        };
+        let txn = db.begin().await?;
+        let rows = TrafficDaily::find()
+            .filter(traffic_daily::Column::ProxyId.eq(proxy_id))
+            .filter(traffic_daily::Column::Date.gte(&first))
+            .filter(traffic_daily::Column::Date.lt(&next))
+            .all(&txn)
+            .await?;
+        let Some(sample) = rows.first() else {
+            txn.commit().await?;
+            continue;
+        };
+        let monthly = traffic_daily::ActiveModel {
+            id: NotSet,
+            proxy_id: Set(proxy_id),
+            client_id: Set(sample.client_id),
+            bytes_sent: Set(rows.iter().map(|r| r.bytes_sent).sum()),
+            bytes_received: Set(rows.iter().map(|r| r.bytes_received).sum()),
+            date: Set(first.clone()),
+            created_at: Set(rows.iter().map(|r| r.created_at).min().unwrap_or(sample.created_at)),
+            updated_at: Set(Utc::now().naive_utc()),
+        };
+        TrafficDaily::delete_many()
+            .filter(traffic_daily::Column::Id.is_in(rows.iter().map(|r| r.id)))
+            .exec(&txn)
+            .await?;
+        monthly.insert(&txn).await?;
+        txn.commit().await?;
+        compacted += 1;
+    }
+    Ok(compacted)
+}
+
+/// 删除超期的按月流量（降采样后只剩每月 1 日的记录）
+async fn prune_traffic(db: &DatabaseConnection, before: NaiveDate) -> Result<u64> {
+    let res = TrafficDaily::delete_many()
+        .filter(traffic_daily::Column::Date.lt(before.format("%Y-%m-%d").to_string()))
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected)
+}
+
+/// 删除超期的在线状态历史，每个对象保留超期记录中的最后一条作为起始状态
+async fn prune_status_history(db: &DatabaseConnection, days: i64) -> Result<u64> {
+    let cutoff = Utc::now().naive_utc() - Duration::days(days);
+    let anchors: Vec<i64> = StatusHistory::find()
+        .select_only()
+        .column_as(status_history::Column::Id.max(), "id")
+        .filter(status_history::Column::ChangedAt.lt(cutoff))
+        .group_by(status_history::Column::EntityType)
+        .group_by(status_history::Column::EntityId)
+        .into_tuple()
+        .all(db)
+        .await?;
+    let res = StatusHistory::delete_many()
+        .filter(status_history::Column::ChangedAt.lt(cutoff))
+        .filter(status_history::Column::Id.is_not_in(anchors))
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected)
+}
+
+/// 删除超期的已恢复告警、已结束的处置记录和重复登录事件
+async fn prune_events(db: &DatabaseConnection, days: i64) -> Result<u64> {
+    let cutoff = Utc::now().naive_utc() - Duration::days(days);
+    let alerts = AlertEvent::delete_many()
+        .filter(alert_event::Column::State.eq(STATE_RESOLVED))
+        .filter(alert_event::Column::ResolvedAt.lt(cutoff))
+        .exec(db)
+        .await?;
+    let mitigations = MitigationEvent::delete_many()
+        .filter(mitigation_event::Column::ExpiresAt.lt(cutoff))
+        .exec(db)
+        .await?;
+    let logins = ClientLoginEvent::delete_many()
+        .filter(client_login_event::Column::CreatedAt.lt(cutoff))
+        .exec(db)
+        .await?;
+    Ok(alerts.rows_affected + mitigations.rows_affected + logins.rows_affected)
+}
+
+/// 按保留策略整理一轮
+async fn run_once(db: &DatabaseConnection, policy: &RetentionPolicy) {
+    let today = Utc::now().date_naive();
+
+    if policy.traffic_daily_days > 0 {
+        match compact_traffic(db, compact_before(today, policy.traffic_daily_days)).await {
+            Ok(0) => {}
+            Ok(n) => info!("已把 {} 个（代理, 月份）的按日流量合并为按月记录", n),
+            Err(e) => error!("合并按日流量失败: {}", e),
+        }
+    }
+    if policy.traffic_monthly_days > 0 {
+        match prune_traffic(db, compact_before(today, policy.traffic_monthly_days)).await {
+            Ok(0) => {}
+            Ok(n) => info!("已删除 {} 条超期的按月流量", n),
+            Err(e) => error!("清理按月流量失败: {}", e),
+        }
+    }
+    if policy.status_history_days > 0 {
+        match prune_status_history(db, policy.status_history_days).await {
+            Ok(0) => {}
+            Ok(n) => info!("已删除 {} 条超期的在线状态历史", n),
+            Err(e) => error!("清理在线状态历史失败: {}", e),
+        }
+    }
+    if policy.events_days > 0 {
+        match prune_events(db, policy.events_days).await {
+            Ok(0) => {}
+            Ok(n) => info!("已删除 {} 条超期的告警 / 处置 / 登录事件记录", n),
+            Err(e) => error!("清理告警 / 处置 / 登录事件记录失败: {}", e),
+        }
+    }
+}
+
+/// 启动历史数据整理任务
+pub fn start_retention_job() {
+    tokio::spawn(async move {
+        let policy = policy();
+        info!(
+            "历史数据保留: 按日流量 {} 天, 按月流量 {} 天, 在线状态历史 {} 天, 告警/处置记录 {} 天（0 为永久）",
+            policy.traffic_daily_days, policy.traffic_monthly_days, policy.status_history_days, policy.events_days
+        );
+        let mut interval = tokio::time::interval(RUN_INTERVAL);
+        loop {
+            interval.tick().await;
+            run_once(get_connection().await, policy).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_before_whole_months() {
+        let today = NaiveDate::from_ymd_opt(2026, 6, 15).unwrap();
+        // 保留 90 天：最早保留 3 月 17 日，3 月不完整，只合并 3 月之前
+        assert_eq!(compact_before(today, 90), NaiveDate::from_ymd_opt(2026, 3, 1).unwrap());
+        assert_eq!(compact_before(today, 14), NaiveDate::from_ymd_opt(2026, 6, 1).unwrap());
+    }
+
+    #[test]
+    fn test_month_range() {
+        assert_eq!(month_range("2025-12"), Some(("2025-12-01".to_string(), "2026-01-01".to_string())));
+        assert_eq!(month_range("2026-02"), Some(("2026-02-01".to_string(), "2026-03-01".to_string())));
+        assert_eq!(month_range("bad"), None);
+    }
+}
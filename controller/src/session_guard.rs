@@ -0,0 +1,303 @@
+//! Web 会话的空闲超时和敏感操作二次验证
+//!
+//! JWT 本身无状态，这里按令牌的 SHA-256 记录每个会话最后一次使用的时间：
+//! 超过 `session_idle_timeout_minutes` 分钟未使用的令牌即使未过期也不再接受（0 表示不限制），
+//! 需要重新登录。
+//!
+//! 删除节点 / 用户 / 租户、重启系统、轮换客户端 token 等敏感操作要求会话在最近
+//! `elevated_action_window_minutes` 分钟内通过 `POST /api/auth/reauth` 重新输入过密码。
+//!
+//! 记录缓存在内存中，并写入 `web_session` 表：Controller 重启后从数据库恢复空闲计时和二次验证状态，
+//! 重启不会让空闲的会话重新生效。为避免每个请求都写数据库，最后使用时间最多每
+//! `PERSIST_INTERVAL` 写入一次，重启后的空闲判断因此可能提前这么久。
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use axum::http::HeaderMap;
+use chrono::NaiveDateTime;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use sha2::{Digest, Sha256};
+
+use crate::entity::{web_session, WebSession};
+use crate::migration::get_connection;
+
+/// 清理过期记录的间隔
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+/// 最后使用时间写入数据库的最小间隔
+const PERSIST_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 会话标识（令牌的 SHA-256），不保留令牌原文
+pub type SessionKey = [u8; 32];
+
+struct Session {
+    last_seen: NaiveDateTime,
+    /// 令牌过期时间（Unix 秒），过期后记录可以清理
+    expires_at: i64,
+    /// 已因空闲超时失效，在令牌过期前一直拒绝
+    idle_expired: bool,
+    elevated_until: Option<NaiveDateTime>,
+    /// 最近一次写入数据库的 `last_seen`
+    persisted: Option<NaiveDateTime>,
+}
+
+impl Session {
+    fn from_model(model: web_session::Model) -> Self {
+        Self {
+            last_seen: model.last_seen_at,
+            expires_at: model.expires_at,
+            idle_expired: model.idle_expired,
+            elevated_until: model.elevated_until,
+            persisted: Some(model.last_seen_at),
+        }
+    }
+
+    /// 生成要写入数据库的记录
+    fn persist(&mut self, key: &SessionKey) -> web_session::Model {
+        self.persisted = Some(self.last_seen);
+        web_session::Model {
+            key_hash: hex::encode(key),
+            expires_at: self.expires_at,
+            last_seen_at: self.last_seen,
+            idle_expired: self.idle_expired,
+            elevated_until: self.elevated_until,
+        }
+    }
+}
+
+struct Sessions {
+    map: HashMap<SessionKey, Session>,
+    last_prune: NaiveDateTime,
+}
+
+impl Sessions {
+    fn new(now: NaiveDateTime) -> Self {
+        Self { map: HashMap::new(), last_prune: now }
+    }
+
+    /// 到了清理间隔时清理已过期的记录，返回清理时使用的 Unix 时间
+    fn prune(&mut self, now: NaiveDateTime) -> Option<i64> {
+        if (now - self.last_prune).to_std().unwrap_or_default() < PRUNE_INTERVAL {
+            return None;
+        }
+        let now_ts = now.and_utc().timestamp();
+        self.map.retain(|_, s| s.expires_at > now_ts);
+        self.last_prune = now;
+        Some(now_ts)
+    }
+
+    /// 记录会话的一次使用，返回是否接受以及需要写入数据库的记录
+    fn touch(
+        &mut self,
+        key: SessionKey,
+        expires_at: i64,
+        idle_timeout: Option<Duration>,
+        now: NaiveDateTime,
+    ) -> (bool, Option<web_session::Model>) {
+        let session = self.map.entry(key).or_insert(Session {
+            last_seen: now,
+            expires_at,
+            idle_expired: false,
+            elevated_until: None,
+            persisted: None,
+        });
+        if session.idle_expired {
+            return (false, None);
+        }
+        if idle_timeout.is_some_and(|idle| (now - session.last_seen).to_std().unwrap_or_default() > idle) {
+            session.idle_expired = true;
+            session.elevated_until = None;
+            return (false, Some(session.persist(&key)));
+        }
+        session.last_seen = now;
+        let due = session
+            .persisted
+            .is_none_or(|at| (now - at).to_std().unwrap_or_default() >= PERSIST_INTERVAL);
+        (true, due.then(|| session.persist(&key)))
+    }
+
+    /// 会话通过二次验证，返回需要写入数据库的记录；会话不存在或已失效时返回 None
+    fn elevate(&mut self, key: SessionKey, until: NaiveDateTime) -> Option<web_session::Model> {
+        match self.map.get_mut(&key) {
+            Some(session) if !session.idle_expired => {
+                session.elevated_until = Some(until);
+                Some(session.persist(&key))
+            }
+            _ => None,
+        }
+    }
+
+    fn is_elevated(&self, key: &SessionKey, now: NaiveDateTime) -> bool {
+        self.map
+            .get(key)
+            .and_then(|s| s.elevated_until)
+            .is_some_and(|until| now < until)
+    }
+}
+
+fn now() -> NaiveDateTime {
+    chrono::Utc::now().naive_utc()
+}
+
+fn sessions() -> &'static Mutex<Sessions> {
+    static SESSIONS: OnceLock<Mutex<Sessions>> = OnceLock::new();
+    SESSIONS.get_or_init(|| Mutex::new(Sessions::new(now())))
+}
+
+/// 请求所用令牌对应的会话标识，没有 Bearer 令牌时为 None
+pub fn session_key(headers: &HeaderMap) -> Option<SessionKey> {
+    let token = headers.get("authorization")?.to_str().ok()?.strip_prefix("Bearer ")?;
+    Some(Sha256::digest(token.as_bytes()).into())
+}
+
+/// 内存中没有记录时从数据库加载（Controller 重启后的第一次请求）
+async fn load(key: &SessionKey, db: &DatabaseConnection) {
+    if sessions().lock().unwrap_or_else(|e| e.into_inner()).map.contains_key(key) {
+        return;
+    }
+    match WebSession::find_by_id(hex::encode(key)).one(db).await {
+        Ok(Some(model)) => {
+            let mut sessions = sessions().lock().unwrap_or_else(|e| e.into_inner());
+            sessions.map.entry(*key).or_insert_with(|| Session::from_model(model));
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("读取会话记录失败: {}", e),
+    }
+}
+
+async fn save(model: web_session::Model, db: &DatabaseConnection) {
+    let result = WebSession::insert(web_session::ActiveModel::from(model))
+        .on_conflict(
+            OnConflict::column(web_session::Column::KeyHash)
+                .update_columns([
+                    web_session::Column::ExpiresAt,
+                    web_session::Column::LastSeenAt,
+                    web_session::Column::IdleExpired,
+                    web_session::Column::ElevatedUntil,
+                ])
+                .to_owned(),
+        )
+        .exec(db)
+        .await;
+    if let Err(e) = result {
+        tracing::warn!("保存会话记录失败: {}", e);
+    }
+}
+
+/// 记录会话的一次使用，会话已因空闲超时失效时返回 false
+pub async fn touch(key: SessionKey, expires_at: i64, idle_timeout: Option<Duration>) -> bool {
+    let db = get_connection().await;
+    load(&key, db).await;
+
+    let (accepted, record, pruned) = {
+        let mut sessions = sessions().lock().unwrap_or_else(|e| e.into_inner());
+        let now = now();
+        let pruned = sessions.prune(now);
+        let (accepted, record) = sessions.touch(key, expires_at, idle_timeout, now);
+        (accepted, record, pruned)
+    };
+    if let Some(now_ts) = pruned {
+        if let Err(e) = WebSession::delete_many()
+            .filter(web_session::Column::ExpiresAt.lte(now_ts))
+            .exec(db)
+            .await
+        {
+            tracing::warn!("清理过期会话记录失败: {}", e);
+        }
+    }
+    if let Some(record) = record {
+        save(record, db).await;
+    }
+    accepted
+}
+
+/// 会话通过二次验证，在 `window` 内可以执行敏感操作
+pub async fn elevate(key: SessionKey, window: Duration) -> bool {
+    let until = now() + chrono::Duration::from_std(window).unwrap_or(chrono::Duration::MAX);
+    let record = sessions().lock().unwrap_or_else(|e| e.into_inner()).elevate(key, until);
+    match record {
+        Some(record) => {
+            save(record, get_connection().await).await;
+            true
+        }
+        None => false,
+    }
+}
+
+/// 会话当前是否处于二次验证有效期内
+pub fn is_elevated(key: &SessionKey) -> bool {
+    sessions().lock().unwrap_or_else(|e| e.into_inner()).is_elevated(key, now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: i64) -> NaiveDateTime {
+        chrono::DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap().naive_utc()
+    }
+
+    #[test]
+    fn test_idle_timeout_and_elevation() {
+        let mut sessions = Sessions::new(at(0));
+        let expires_at = at(3600).and_utc().timestamp();
+        let key = [7u8; 32];
+        let idle = Some(Duration::from_secs(50));
+
+        let (accepted, record) = sessions.touch(key, expires_at, idle, at(0));
+        assert!(accepted);
+        // 新会话立即写入数据库
+        assert_eq!(record.map(|r| r.last_seen_at), Some(at(0)));
+        assert!(!sessions.is_elevated(&key, at(0)));
+        assert!(sessions.elevate(key, at(60)).is_some());
+        assert!(sessions.is_elevated(&key, at(10)));
+        assert!(!sessions.is_elevated(&key, at(60)));
+
+        // 写入间隔内的使用只更新内存
+        assert_eq!(sessions.touch(key, expires_at, idle, at(30)), (true, None));
+        assert!(sessions.touch(key, expires_at, idle, at(70)).1.is_some());
+
+        // 空闲超时后会话失效，二次验证状态一并清除，之后的请求也一直拒绝
+        assert!(sessions.elevate(key, at(200)).is_some());
+        let (accepted, record) = sessions.touch(key, expires_at, idle, at(130));
+        assert!(!accepted);
+        assert!(record.is_some_and(|r| r.idle_expired && r.elevated_until.is_none()));
+        assert!(!sessions.is_elevated(&key, at(130)));
+        assert_eq!(sessions.touch(key, expires_at, None, at(131)), (false, None));
+        assert!(sessions.elevate(key, at(200)).is_none());
+
+        // 未启用空闲超时
+        let other = [8u8; 32];
+        assert!(sessions.touch(other, expires_at, None, at(0)).0);
+        assert!(sessions.touch(other, expires_at, None, at(1000)).0);
+
+        // 令牌过期后清理记录
+        assert_eq!(sessions.prune(at(30)), None);
+        assert!(sessions.prune(at(3600)).is_some());
+        assert!(sessions.map.is_empty());
+    }
+
+    #[test]
+    fn test_restore_from_record() {
+        let expires_at = at(3600).and_utc().timestamp();
+        let key = [9u8; 32];
+        let idle = Some(Duration::from_secs(50));
+
+        let mut before = Sessions::new(at(0));
+        let record = before.touch(key, expires_at, idle, at(0)).1.unwrap();
+        let elevated = before.elevate(key, at(40)).unwrap();
+        assert_eq!(elevated.key_hash, record.key_hash);
+
+        // Controller 重启后从数据库恢复：二次验证仍然有效，空闲超时继续计时
+        let mut after = Sessions::new(at(20));
+        after.map.insert(key, Session::from_model(elevated.clone()));
+        assert!(after.is_elevated(&key, at(20)));
+        assert!(!after.touch(key, expires_at, idle, at(100)).0);
+
+        let mut after = Sessions::new(at(20));
+        after.map.insert(key, Session::from_model(elevated));
+        assert!(after.touch(key, expires_at, idle, at(30)).0);
+    }
+}
@@ -0,0 +1,235 @@
+//! 临时隧道（跳板式 TCP 调试通道）
+//!
+//! 管理员可以为客户端的某个本地端口临时开放节点上的一个随机高位端口，无需创建持久化的代理，
+//! 到期后自动关闭。每条临时隧道的开启人、原因和关闭时间都保留在 `temporary_tunnel` 表中用于审计。
+//!
+//! 节点侧复用普通代理的监听器，临时隧道使用负数的 proxy_id（`-tunnel.id`）与普通代理区分。
+
+use anyhow::{anyhow, Result};
+use chrono::{Duration as ChronoDuration, Utc};
+use rand::Rng;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, NotSet, QueryFilter, Set};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::client_stream_manager::ClientStreamManager;
+use crate::entity::{proxy, temporary_tunnel, Proxy, TemporaryTunnel};
+use crate::migration::get_connection;
+use crate::node_manager::NodeManager;
+
+/// 默认有效期（秒）
+pub const DEFAULT_TTL_SECS: u64 = 3600;
+/// 最短 / 最长有效期（秒）
+pub const MIN_TTL_SECS: u64 = 60;
+pub const MAX_TTL_SECS: u64 = 24 * 3600;
+
+/// 随机端口范围（IANA 动态端口）
+const PORT_RANGE: std::ops::RangeInclusive<u16> = 49152..=65535;
+/// 端口被占用时的最大重试次数
+const MAX_PORT_ATTEMPTS: usize = 5;
+
+/// 到期检查间隔
+const REAP_INTERVAL_SECS: u64 = 15;
+
+/// 临时隧道在节点上使用的 proxy_id
+pub fn proxy_id(tunnel_id: i64) -> i64 {
+    -tunnel_id
+}
+
+/// 开启临时隧道的参数
+pub struct OpenRequest {
+    pub client_id: i64,
+    pub node_id: i64,
+    pub local_ip: String,
+    pub local_port: u16,
+    pub ttl_secs: u64,
+    pub reason: Option<String>,
+    pub created_by: String,
+}
+
+/// 客户端仍在有效期内的临时隧道（可按节点过滤）
+pub async fn active_tunnels(
+    client_id: i64,
+    node_id: Option<i64>,
+    db: &DatabaseConnection,
+) -> Result<Vec<temporary_tunnel::Model>> {
+    let mut query = TemporaryTunnel::find()
+        .filter(temporary_tunnel::Column::ClientId.eq(client_id))
+        .filter(temporary_tunnel::Column::ClosedAt.is_null())
+        .filter(temporary_tunnel::Column::ExpiresAt.gt(Utc::now().naive_utc()));
+    if let Some(node_id) = node_id {
+        query = query.filter(temporary_tunnel::Column::NodeId.eq(node_id));
+    }
+    Ok(query.all(db).await?)
+}
+
+/// 节点上仍在有效期内的临时隧道（所有客户端）
+pub async fn active_tunnels_on_node(node_id: i64, db: &DatabaseConnection) -> Result<Vec<temporary_tunnel::Model>> {
+    Ok(TemporaryTunnel::find()
+        .filter(temporary_tunnel::Column::NodeId.eq(node_id))
+        .filter(temporary_tunnel::Column::ClosedAt.is_null())
+        .filter(temporary_tunnel::Column::ExpiresAt.gt(Utc::now().naive_utc()))
+        .all(db)
+        .await?)
+}
+
+/// 在节点上挑选一个未被代理或其他临时隧道占用、也未被预留的随机端口
+async fn pick_remote_port(node_id: i64, db: &DatabaseConnection) -> Result<u16> {
+    let mut used: Vec<u16> = Proxy::find()
+        .filter(proxy::Column::NodeId.eq(node_id))
+        .filter(proxy::Column::Enabled.eq(true))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|p| p.remote_port)
+        .collect();
+    used.extend(
+        TemporaryTunnel::find()
+            .filter(temporary_tunnel::Column::NodeId.eq(node_id))
+            .filter(temporary_tunnel::Column::ClosedAt.is_null())
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|t| t.remote_port),
+    );
+
+    let reserved = crate::port_reservation::active_reservations(node_id, db).await?;
+
+    let mut rng = rand::rng();
+    for _ in 0..100 {
+        let port = rng.random_range(PORT_RANGE);
+        if !used.contains(&port) && !reserved.iter().any(|r| r.contains(port)) {
+            return Ok(port);
+        }
+    }
+    Err(anyhow!("节点 #{} 没有可用的随机端口", node_id))
+}
+
+/// 开启临时隧道：分配随机端口并在节点上启动监听器，端口被占用时换一个端口重试
+pub async fn open(
+    node_manager: &NodeManager,
+    client_stream_manager: &ClientStreamManager,
+    req: OpenRequest,
+) -> Result<temporary_tunnel::Model> {
+    let db = get_connection().await;
+    let client_id_str = req.client_id.to_string();
+    let now = Utc::now().naive_utc();
+    let expires_at = now + ChronoDuration::seconds(req.ttl_secs as i64);
+
+    let mut last_err = None;
+    for _ in 0..MAX_PORT_ATTEMPTS {
+        let remote_port = pick_remote_port(req.node_id, db).await?;
+        let tunnel = temporary_tunnel::ActiveModel {
+            id: NotSet,
+            client_id: Set(req.client_id),
+            node_id: Set(req.node_id),
+            local_ip: Set(req.local_ip.clone()),
+            local_port: Set(req.local_port),
+            remote_port: Set(remote_port),
+            reason: Set(req.reason.clone()),
+            created_by: Set(req.created_by.clone()),
+            expires_at: Set(expires_at),
+            closed_at: Set(None),
+            closed_by: Set(None),
+            created_at: Set(now),
+        }
+        .insert(db)
+        .await?;
+
+        match node_manager
+            .start_proxy_on_node(req.node_id, &client_id_str, proxy_id(tunnel.id))
+            .await
+        {
+            Ok(()) => {
+                info!(
+                    "临时隧道 #{} 已开启: 节点 #{} 端口 {} → 客户端 #{} {}:{}，到期时间 {}，操作人 {}",
+                    tunnel.id,
+                    tunnel.node_id,
+                    tunnel.remote_port,
+                    tunnel.client_id,
+                    tunnel.local_ip,
+                    tunnel.local_port,
+                    tunnel.expires_at,
+                    tunnel.created_by
+                );
+                // 客户端可能尚未连接该节点，推送配置让其建立隧道
+                client_stream_manager.notify_proxy_change(&client_id_str).await;
+                return Ok(tunnel);
+            }
+            Err(e) => {
+                warn!("临时隧道在节点 #{} 端口 {} 启动失败: {}", req.node_id, remote_port, e);
+                let _ = TemporaryTunnel::delete_by_id(tunnel.id).exec(db).await;
+                last_err = Some(e);
+            }
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| anyhow!("启动临时隧道失败")))
+}
+
+/// 关闭临时隧道并记录关闭时间（`closed_by` 为空表示到期自动关闭）
+pub async fn close(
+    node_manager: &NodeManager,
+    client_stream_manager: &ClientStreamManager,
+    tunnel: temporary_tunnel::Model,
+    closed_by: Option<String>,
+) -> Result<temporary_tunnel::Model> {
+    let db = get_connection().await;
+    let client_id_str = tunnel.client_id.to_string();
+
+    if let Err(e) = node_manager
+        .stop_proxy_on_node(tunnel.node_id, &client_id_str, proxy_id(tunnel.id))
+        .await
+    {
+        // 节点离线时监听器已随节点消失，重连后同步代理列表也不会再包含该隧道
+        warn!("停止临时隧道 #{} 监听器失败: {}", tunnel.id, e);
+    }
+
+    let mut active: temporary_tunnel::ActiveModel = tunnel.into();
+    active.closed_at = Set(Some(Utc::now().naive_utc()));
+    active.closed_by = Set(closed_by);
+    let tunnel = active.update(db).await?;
+
+    match &tunnel.closed_by {
+        Some(user) => info!("临时隧道 #{} 已被 {} 关闭", tunnel.id, user),
+        None => info!("临时隧道 #{} 已到期关闭", tunnel.id),
+    }
+    client_stream_manager.notify_proxy_change(&client_id_str).await;
+    Ok(tunnel)
+}
+
+/// 启动临时隧道到期检查后台任务
+pub fn start_temporary_tunnel_reaper(
+    node_manager: Arc<NodeManager>,
+    client_stream_manager: Arc<ClientStreamManager>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(REAP_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+
+            let db = get_connection().await;
+            let expired = match TemporaryTunnel::find()
+                .filter(temporary_tunnel::Column::ClosedAt.is_null())
+                .filter(temporary_tunnel::Column::ExpiresAt.lte(Utc::now().naive_utc()))
+                .all(db)
+                .await
+            {
+                Ok(t) => t,
+                Err(e) => {
+                    error!("查询到期临时隧道失败: {}", e);
+                    continue;
+                }
+            };
+
+            for tunnel in expired {
+                let id = tunnel.id;
+                if let Err(e) = close(&node_manager, &client_stream_manager, tunnel, None).await {
+                    error!("关闭到期临时隧道 #{} 失败: {}", id, e);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,191 @@
+//! 多租户隔离
+//!
+//! 租户位于用户之上：每个租户拥有自己的用户（以及用户的客户端和代理），可以独占分配给它的节点，
+//! 并限制租户内的用户数、客户端数和可分配的流量总配额。
+//!
+//! - 平台管理员（`is_admin`，不属于任何租户）管理所有租户和数据；
+//! - 租户管理员（`is_tenant_admin`）只能管理本租户内的用户和客户端；
+//! - 分配给租户的节点（`node.tenant_id`）只对该租户的用户可见，未分配的节点对所有用户可见。
+
+use anyhow::Result;
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QuerySelect};
+
+use crate::entity::{client, node, user, Client, Tenant, User};
+use crate::middleware::AuthUser;
+
+/// 当前用户可访问的用户范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserScope {
+    /// 平台管理员：所有用户
+    All,
+    /// 租户管理员：本租户内的用户
+    Tenant(i64),
+    /// 普通用户：仅自己
+    Own(i64),
+}
+
+impl UserScope {
+    pub fn of(auth_user: &AuthUser) -> Self {
+        match auth_user.tenant_id {
+            Some(tenant_id) if auth_user.is_tenant_admin => UserScope::Tenant(tenant_id),
+            None if auth_user.is_admin => UserScope::All,
+            _ => UserScope::Own(auth_user.id),
+        }
+    }
+
+    /// 是否可以管理（创建、修改、删除）其他用户
+    pub fn can_manage_users(self) -> bool {
+        !matches!(self, UserScope::Own(_))
+    }
+
+    /// 目标用户是否在范围内
+    pub fn contains(self, target: &user::Model) -> bool {
+        match self {
+            UserScope::All => true,
+            UserScope::Tenant(tenant_id) => target.tenant_id == Some(tenant_id),
+            UserScope::Own(id) => target.id == id,
+        }
+    }
+
+    /// 范围内的用户 ID，`None` 表示不限制
+    pub async fn user_ids(self, db: &DatabaseConnection) -> Result<Option<Vec<i64>>> {
+        match self {
+            UserScope::All => Ok(None),
+            UserScope::Tenant(tenant_id) => Ok(Some(tenant_user_ids(tenant_id, db).await?)),
+            UserScope::Own(id) => Ok(Some(vec![id])),
+        }
+    }
+}
+
+/// 租户内所有用户的 ID
+pub async fn tenant_user_ids(tenant_id: i64, db: &DatabaseConnection) -> Result<Vec<i64>> {
+    Ok(User::find()
+        .select_only()
+        .column(user::Column::Id)
+        .filter(user::Column::TenantId.eq(tenant_id))
+        .into_tuple()
+        .all(db)
+        .await?)
+}
+
+/// 节点对指定租户是否可见：未分配租户的节点对所有用户可见
+pub fn node_visible_to(node: &node::Model, tenant_id: Option<i64>) -> bool {
+    node.tenant_id.is_none() || node.tenant_id == tenant_id
+}
+
+/// 节点查询条件：未分配租户的节点 + 分配给指定租户的节点
+pub fn node_tenant_condition(tenant_id: Option<i64>) -> Condition {
+    let condition = Condition::any().add(node::Column::TenantId.is_null());
+    match tenant_id {
+        Some(id) => condition.add(node::Column::TenantId.eq(id)),
+        None => condition,
+    }
+}
+
+/// 检查租户是否还能添加用户
+/// 返回 (是否允许, 错误信息)
+pub async fn validate_tenant_user_limit(tenant_id: i64, db: &DatabaseConnection) -> Result<(bool, String)> {
+    let tenant = match Tenant::find_by_id(tenant_id).one(db).await? {
+        Some(t) => t,
+        None => return Ok((false, "租户不存在".to_string())),
+    };
+
+    if let Some(max_count) = tenant.max_user_count {
+        let count = User::find()
+            .filter(user::Column::TenantId.eq(tenant_id))
+            .count(db)
+            .await?;
+        if count >= max_count as u64 {
+            return Ok((false, format!("租户用户数量已达上限: {} / {}", count, max_count)));
+        }
+    }
+
+    Ok((true, String::new()))
+}
+
+/// 检查用户所属租户是否还能添加客户端（用户不属于任何租户时不限制）
+/// 返回 (是否允许, 错误信息)
+pub async fn validate_tenant_client_limit(user_id: i64, db: &DatabaseConnection) -> Result<(bool, String)> {
+    let Some(tenant_id) = User::find_by_id(user_id).one(db).await?.and_then(|u| u.tenant_id) else {
+        return Ok((true, String::new()));
+    };
+    let Some(tenant) = Tenant::find_by_id(tenant_id).one(db).await? else {
+        return Ok((true, String::new()));
+    };
+
+    if let Some(max_count) = tenant.max_client_count {
+        let user_ids = tenant_user_ids(tenant_id, db).await?;
+        let count = Client::find()
+            .filter(client::Column::UserId.is_in(user_ids))
+            .count(db)
+            .await?;
+        if count >= max_count as u64 {
+            return Ok((false, format!("租户客户端数量已达上限: {} / {}", count, max_count)));
+        }
+    }
+
+    Ok((true, String::new()))
+}
+
+/// 检查把用户的流量配额设置为 `new_quota_gb` 后，租户内用户配额之和是否超出租户总配额
+/// 返回 (是否允许, 错误信息)
+pub async fn validate_tenant_traffic_quota(
+    tenant_id: i64,
+    user_id: Option<i64>,
+    new_quota_gb: f64,
+    db: &DatabaseConnection,
+) -> Result<(bool, String)> {
+    let Some(tenant) = Tenant::find_by_id(tenant_id).one(db).await? else {
+        return Ok((false, "租户不存在".to_string()));
+    };
+    let Some(tenant_quota) = tenant.traffic_quota_gb else {
+        return Ok((true, String::new()));
+    };
+
+    let allocated: f64 = User::find()
+        .filter(user::Column::TenantId.eq(tenant_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter(|u| Some(u.id) != user_id)
+        .filter_map(|u| u.traffic_quota_gb)
+        .sum();
+
+    if allocated + new_quota_gb > tenant_quota {
+        return Ok((
+            false,
+            format!(
+                "超出租户流量总配额: 已分配 {:.2} GB + {:.2} GB > {:.2} GB",
+                allocated, new_quota_gb, tenant_quota
+            ),
+        ));
+    }
+
+    Ok((true, String::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth(is_admin: bool, tenant_id: Option<i64>, is_tenant_admin: bool) -> AuthUser {
+        AuthUser {
+            id: 7,
+            username: "u".to_string(),
+            is_admin,
+            tenant_id,
+            is_tenant_admin,
+            impersonator: None,
+        }
+    }
+
+    #[test]
+    fn test_user_scope() {
+        assert_eq!(UserScope::of(&auth(true, None, false)), UserScope::All);
+        assert_eq!(UserScope::of(&auth(false, Some(3), true)), UserScope::Tenant(3));
+        assert_eq!(UserScope::of(&auth(false, Some(3), false)), UserScope::Own(7));
+        // 租户内的用户即使带有平台管理员标记也不能越过租户边界
+        assert_eq!(UserScope::of(&auth(true, Some(3), false)), UserScope::Own(7));
+        assert!(!UserScope::Own(7).can_manage_users());
+    }
+}
@@ -0,0 +1,402 @@
+//! Web / gRPC TLS 证书的两阶段应用
+//!
+//! 1. 校验证书链与私钥是否匹配；
+//! 2. 用新证书在临时端口启动 HTTPS 监听器，同时写入新配置；
+//! 3. 管理员通过临时监听器访问确认地址（证明浏览器可以用新证书建立连接）或在管理界面确认；
+//!    超时未确认则自动恢复之前的配置。
+//!
+//! 待确认的变更同时记录在 `data/tls_pending.json`，Controller 在确认前重启时会在启动阶段恢复旧配置。
+
+use anyhow::{anyhow, Result};
+use axum::{
+    extract::{Path, State},
+    response::Html,
+    routing::get,
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use base64::Engine;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::config_manager::ConfigManager;
+use crate::config_revision::{self, ConfigSnapshot};
+use crate::migration::get_connection;
+
+/// 待确认变更的持久化文件
+const PENDING_FILE: &str = "data/tls_pending.json";
+
+/// 默认确认超时（秒）
+pub const DEFAULT_CONFIRM_TIMEOUT_SECS: u64 = 300;
+
+/// 默认临时监听端口
+pub const DEFAULT_PROBE_PORT: u16 = 8443;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TlsTarget {
+    Web,
+    Grpc,
+}
+
+impl TlsTarget {
+    fn key_prefix(self) -> &'static str {
+        match self {
+            TlsTarget::Web => "web_tls",
+            TlsTarget::Grpc => "grpc_tls",
+        }
+    }
+}
+
+/// 待确认的 TLS 变更
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingTlsApply {
+    /// 确认令牌
+    pub token: String,
+    pub target: TlsTarget,
+    /// 确认截止时间（Unix 时间戳）
+    pub deadline: i64,
+    #[serde(rename = "probePort")]
+    pub probe_port: u16,
+    /// 变更前的原始配置值，用于恢复
+    #[serde(skip_serializing)]
+    pub previous: ConfigSnapshot,
+}
+
+struct ActiveApply {
+    info: PendingTlsApply,
+    probe_handle: axum_server::Handle,
+    timer: tokio::task::JoinHandle<()>,
+}
+
+pub struct TlsApplyManager {
+    config_manager: Arc<ConfigManager>,
+    active: Mutex<Option<ActiveApply>>,
+}
+
+/// 校验 PEM 格式的证书链和私钥，返回可直接使用的 rustls 服务端配置
+pub fn validate_pem(cert_pem: &[u8], key_pem: &[u8]) -> Result<rustls::ServerConfig> {
+    let certs = CertificateDer::pem_slice_iter(cert_pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow!("解析证书失败: {:?}", e))?;
+    if certs.is_empty() {
+        return Err(anyhow!("证书链为空"));
+    }
+
+    let key = PrivateKeyDer::from_pem_slice(key_pem).map_err(|e| anyhow!("解析私钥失败: {:?}", e))?;
+
+    let mut config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| anyhow!("证书与私钥不匹配: {}", e))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+/// 校验 base64 编码的 PEM 证书链和私钥
+pub fn validate_base64(cert_base64: &str, key_base64: &str) -> Result<rustls::ServerConfig> {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let cert_pem = engine
+        .decode(cert_base64.trim())
+        .map_err(|e| anyhow!("证书 base64 解码失败: {}", e))?;
+    let key_pem = engine
+        .decode(key_base64.trim())
+        .map_err(|e| anyhow!("私钥 base64 解码失败: {}", e))?;
+    validate_pem(&cert_pem, &key_pem)
+}
+
+/// 是否为 TLS 证书 / 私钥内容配置项
+pub fn is_content_key(key: &str) -> bool {
+    [TlsTarget::Web, TlsTarget::Grpc].iter().any(|t| {
+        let prefix = t.key_prefix();
+        key.strip_prefix(prefix)
+            .is_some_and(|rest| rest == "_cert_content" || rest == "_key_content")
+    })
+}
+
+/// 校验通过通用配置接口写入的证书 / 私钥内容
+///
+/// `updates` 为待写入的值，`current` 为当前配置，均为数据库中保存的 JSON 形式。只修改证书或私钥之一时
+/// 与另一项的当前值配对校验；两项都清空视为停用内容配置，只设置其中一项则拒绝。
+pub fn validate_content_updates(current: &ConfigSnapshot, updates: &ConfigSnapshot) -> Result<()> {
+    for target in [TlsTarget::Web, TlsTarget::Grpc] {
+        let prefix = target.key_prefix();
+        let cert_key = format!("{}_cert_content", prefix);
+        let key_key = format!("{}_key_content", prefix);
+        if !updates.contains_key(&cert_key) && !updates.contains_key(&key_key) {
+            continue;
+        }
+        let value = |k: &str| {
+            updates
+                .get(k)
+                .or_else(|| current.get(k))
+                .and_then(|v| serde_json::from_str::<String>(v).ok())
+                .unwrap_or_default()
+        };
+        let (cert, key) = (value(&cert_key), value(&key_key));
+        match (cert.trim().is_empty(), key.trim().is_empty()) {
+            (true, true) => {}
+            (false, false) => {
+                validate_base64(&cert, &key).map_err(|e| anyhow!("{} 证书校验失败: {}", prefix, e))?;
+            }
+            _ => return Err(anyhow!("{} 的证书和私钥需要同时设置", prefix)),
+        }
+    }
+    Ok(())
+}
+
+impl TlsApplyManager {
+    pub fn new(config_manager: Arc<ConfigManager>) -> Self {
+        Self {
+            config_manager,
+            active: Mutex::new(None),
+        }
+    }
+
+    /// 获取当前待确认的变更
+    pub async fn pending(&self) -> Option<PendingTlsApply> {
+        self.active.lock().await.as_ref().map(|a| a.info.clone())
+    }
+
+    /// 第一阶段：校验证书、启动临时监听器并写入新配置
+    pub async fn apply(
+        self: &Arc<Self>,
+        target: TlsTarget,
+        cert_base64: &str,
+        key_base64: &str,
+        probe_port: u16,
+        timeout_secs: u64,
+        created_by: Option<String>,
+    ) -> Result<PendingTlsApply> {
+        let mut active = self.active.lock().await;
+        if active.is_some() {
+            return Err(anyhow!("已有待确认的 TLS 变更，请先确认或撤销"));
+        }
+
+        let server_config = validate_base64(cert_base64, key_base64)?;
+
+        let token = uuid::Uuid::new_v4().to_string();
+
+        // 启动临时 HTTPS 监听器
+        let probe_handle = axum_server::Handle::new();
+        let addr: SocketAddr = common::utils::wildcard_addr(probe_port);
+        let app = Router::new()
+            .route("/tls-confirm/{token}", get(probe_confirm))
+            .with_state(self.clone());
+        let server = axum_server::bind_rustls(addr, RustlsConfig::from_config(Arc::new(server_config)))
+            .handle(probe_handle.clone())
+            .serve(app.into_make_service());
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                error!("TLS 确认监听器错误: {}", e);
+            }
+        });
+        if probe_handle.listening().await.is_none() {
+            return Err(anyhow!("TLS 确认监听器无法绑定端口 {}", probe_port));
+        }
+
+        // 写入新配置
+        let prefix = target.key_prefix();
+        let mut values = ConfigSnapshot::new();
+        values.insert(format!("{}_enabled", prefix), "true".to_string());
+        values.insert(format!("{}_cert_content", prefix), serde_json::to_string(cert_base64.trim())?);
+        values.insert(format!("{}_key_content", prefix), serde_json::to_string(key_base64.trim())?);
+
+        let db = get_connection().await;
+        let current = config_revision::current_snapshot(db).await?;
+        let previous: ConfigSnapshot = values
+            .keys()
+            .filter_map(|k| current.get(k).map(|v| (k.clone(), v.clone())))
+            .collect();
+
+        if let Err(e) = config_revision::apply_values(
+            db,
+            &values,
+            Some(format!("应用 {} TLS 证书（待确认）", prefix)),
+            created_by,
+        )
+        .await
+        {
+            probe_handle.shutdown();
+            return Err(e);
+        }
+        if let Err(e) = self.config_manager.reload().await {
+            error!("重新加载配置缓存失败: {}", e);
+        }
+
+        let info = PendingTlsApply {
+            token,
+            target,
+            deadline: chrono::Utc::now().timestamp() + timeout_secs as i64,
+            probe_port,
+            previous,
+        };
+        if let Err(e) = save_pending(&info) {
+            warn!("保存待确认 TLS 变更失败: {}", e);
+        }
+
+        // 超时未确认则自动恢复
+        let manager = self.clone();
+        let timer_token = info.token.clone();
+        let timer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(timeout_secs)).await;
+            // 不能在定时任务内 abort 自身，直接取出变更后恢复
+            let Ok(apply) = manager.take(Some(&timer_token)).await else {
+                return;
+            };
+            warn!("TLS 变更在 {} 秒内未确认，自动恢复之前的配置", timeout_secs);
+            if let Err(e) = manager.finish_revert(apply).await {
+                error!("自动恢复 TLS 配置失败: {}", e);
+            }
+        });
+
+        info!(
+            "{} TLS 证书已应用，等待确认（临时端口 {}，{} 秒内有效）",
+            prefix, probe_port, timeout_secs
+        );
+        *active = Some(ActiveApply {
+            info: info.clone(),
+            probe_handle,
+            timer,
+        });
+        Ok(info)
+    }
+
+    /// 取出待确认的变更（`token` 为空时跳过令牌校验，供管理员在管理界面操作）
+    async fn take(&self, token: Option<&str>) -> Result<ActiveApply> {
+        let mut active = self.active.lock().await;
+        let current = active.as_ref().ok_or_else(|| anyhow!("没有待确认的 TLS 变更"))?;
+        if token.is_some_and(|t| t != current.info.token) {
+            return Err(anyhow!("确认令牌无效"));
+        }
+        Ok(active.take().expect("checked above"))
+    }
+
+    /// 第二阶段：确认变更
+    pub async fn confirm(&self, token: Option<&str>) -> Result<PendingTlsApply> {
+        let apply = self.take(token).await?;
+        apply.timer.abort();
+        apply.probe_handle.graceful_shutdown(Some(Duration::from_secs(1)));
+        remove_pending();
+
+        info!("{} TLS 变更已确认", apply.info.target.key_prefix());
+        Ok(apply.info)
+    }
+
+    /// 撤销待确认的变更并恢复之前的配置
+    pub async fn revert(&self, token: Option<&str>) -> Result<PendingTlsApply> {
+        let apply = self.take(token).await?;
+        apply.timer.abort();
+        self.finish_revert(apply).await
+    }
+
+    async fn finish_revert(&self, apply: ActiveApply) -> Result<PendingTlsApply> {
+        apply.probe_handle.shutdown();
+
+        restore_previous(&apply.info).await?;
+        if let Err(e) = self.config_manager.reload().await {
+            error!("重新加载配置缓存失败: {}", e);
+        }
+        remove_pending();
+
+        Ok(apply.info)
+    }
+}
+
+/// 临时监听器上的确认地址：能够访问即说明新证书可以正常建立 TLS 连接
+async fn probe_confirm(
+    State(manager): State<Arc<TlsApplyManager>>,
+    Path(token): Path<String>,
+) -> Html<String> {
+    match manager.confirm(Some(&token)).await {
+        Ok(_) => Html("<h3>✓ TLS 证书已确认生效</h3>".to_string()),
+        Err(e) => Html(format!("<h3>确认失败: {}</h3>", e)),
+    }
+}
+
+async fn restore_previous(info: &PendingTlsApply) -> Result<()> {
+    let db = get_connection().await;
+    config_revision::apply_values(
+        db,
+        &info.previous,
+        Some(format!("{} TLS 变更未确认，已恢复之前的配置", info.target.key_prefix())),
+        None,
+    )
+    .await?;
+    info!("已恢复 {} TLS 之前的配置", info.target.key_prefix());
+    Ok(())
+}
+
+fn save_pending(info: &PendingTlsApply) -> Result<()> {
+    #[derive(Serialize)]
+    struct Persisted<'a> {
+        #[serde(flatten)]
+        info: &'a PendingTlsApply,
+        previous: &'a ConfigSnapshot,
+    }
+    let content = serde_json::to_string_pretty(&Persisted { info, previous: &info.previous })?;
+    std::fs::write(PENDING_FILE, content)?;
+    Ok(())
+}
+
+fn remove_pending() {
+    let _ = std::fs::remove_file(PENDING_FILE);
+}
+
+/// 启动时恢复未确认的 TLS 变更（需在加载配置缓存之前调用）
+pub async fn recover_pending_on_startup() {
+    let content = match std::fs::read_to_string(PENDING_FILE) {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+
+    match serde_json::from_str::<PendingTlsApply>(&content) {
+        Ok(info) => {
+            warn!("检测到重启前未确认的 {} TLS 变更，恢复之前的配置", info.target.key_prefix());
+            if let Err(e) = restore_previous(&info).await {
+                error!("恢复 TLS 配置失败: {}", e);
+                return;
+            }
+        }
+        Err(e) => error!("解析 {} 失败: {}", PENDING_FILE, e),
+    }
+    remove_pending();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(pairs: &[(&str, &str)]) -> ConfigSnapshot {
+        pairs.iter().map(|(k, v)| (k.to_string(), serde_json::to_string(v).unwrap())).collect()
+    }
+
+    #[test]
+    fn test_is_content_key() {
+        assert!(is_content_key("web_tls_cert_content"));
+        assert!(is_content_key("grpc_tls_key_content"));
+        assert!(!is_content_key("web_tls_cert_path"));
+        assert!(!is_content_key("smtp_key_content"));
+    }
+
+    #[test]
+    fn test_validate_content_updates() {
+        let current = snapshot(&[("web_tls_cert_content", "Y2VydA=="), ("web_tls_key_content", "")]);
+
+        // 未涉及证书内容的修改不校验
+        assert!(validate_content_updates(&current, &snapshot(&[("web_port", "3000")])).is_ok());
+        // 两项都清空
+        assert!(validate_content_updates(&current, &snapshot(&[("web_tls_cert_content", "")])).is_ok());
+        // 只有证书没有私钥
+        assert!(validate_content_updates(&current, &snapshot(&[("web_tls_cert_content", "Y2VydA==")])).is_err());
+        // 内容不是有效的证书
+        let updates = snapshot(&[("grpc_tls_cert_content", "Y2VydA=="), ("grpc_tls_key_content", "a2V5")]);
+        assert!(validate_content_updates(&current, &updates).is_err());
+    }
+}
@@ -1,16 +1,26 @@
 use anyhow::Result;
 use chrono::Utc;
-use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set, NotSet};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set, NotSet};
 use sea_orm::sea_query::{OnConflict, Expr};
 use std::collections::HashMap;
+use std::sync::OnceLock;
 use tracing::{debug, error, info};
 use tokio::sync::mpsc;
 use std::time::Duration;
 
-use crate::entity::{proxy, client, user, node, traffic_daily, Proxy, Client, User, Node, TrafficDaily};
+use crate::entity::{proxy, client, user, node, traffic_daily, traffic_report, Proxy, Client, User, Node, TrafficDaily, TrafficReport};
 use crate::migration::get_connection;
 
+/// 写入队列容量
+const TRAFFIC_CHANNEL_CAPACITY: usize = 10000;
+/// 流量上报流的默认确认窗口（允许同时在途的批次数）
+pub const TRAFFIC_STREAM_WINDOW: u32 = 8;
+/// 上报 ID 保留天数
+const REPORT_RETENTION_DAYS: i64 = 7;
+
 struct TrafficEvent {
+    /// 上报流量的节点（多节点发布的代理在每个节点上都有流量）
+    node_id: i64,
     proxy_id: i64,
     client_id: i64,
     user_id: Option<i64>,
@@ -18,6 +28,9 @@ struct TrafficEvent {
     bytes_received: i64,
 }
 
+/// 聚合键：(节点, 代理, 客户端, 用户)
+type TrafficKey = (i64, i64, i64, Option<i64>);
+
 /// 流量统计管理器
 #[derive(Clone)]
 pub struct TrafficManager {
@@ -26,17 +39,17 @@ pub struct TrafficManager {
 
 impl TrafficManager {
     pub fn new() -> Self {
-        let (tx, mut rx) = mpsc::channel::<TrafficEvent>(10000);
+        let (tx, mut rx) = mpsc::channel::<TrafficEvent>(TRAFFIC_CHANNEL_CAPACITY);
 
         tokio::spawn(async move {
-            let mut buffer: HashMap<(i64, i64, Option<i64>), (i64, i64)> = HashMap::new();
+            let mut buffer: HashMap<TrafficKey, (i64, i64)> = HashMap::new();
             let mut interval = tokio::time::interval(Duration::from_secs(5));
             interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
             loop {
                 tokio::select! {
                     Some(event) = rx.recv() => {
-                        let key = (event.proxy_id, event.client_id, event.user_id);
+                        let key = (event.node_id, event.proxy_id, event.client_id, event.user_id);
                         let entry = buffer.entry(key).or_insert((0, 0));
                         entry.0 += event.bytes_sent;
                         entry.1 += event.bytes_received;
@@ -58,7 +71,22 @@ impl TrafficManager {
         Self { sender: tx }
     }
 
-    async fn flush_buffer(buffer: &mut HashMap<(i64, i64, Option<i64>), (i64, i64)>) {
+    /// 全局共享的流量管理器，所有节点的上报合并后批量写入
+    pub fn shared() -> &'static TrafficManager {
+        static SHARED: OnceLock<TrafficManager> = OnceLock::new();
+        SHARED.get_or_init(TrafficManager::new)
+    }
+
+    /// 按写入队列的积压情况计算确认窗口：剩余容量不足四分之一时只允许一个在途批次
+    pub fn ack_window(&self) -> u32 {
+        if self.sender.capacity() < TRAFFIC_CHANNEL_CAPACITY / 4 {
+            1
+        } else {
+            TRAFFIC_STREAM_WINDOW
+        }
+    }
+
+    async fn flush_buffer(buffer: &mut HashMap<TrafficKey, (i64, i64)>) {
         let db = get_connection().await;
         let today = Utc::now().format("%Y-%m-%d").to_string();
         let now = Utc::now().naive_utc();
@@ -69,23 +97,21 @@ impl TrafficManager {
         // 用于聚合节点流量
         let mut node_traffic: HashMap<i64, (i64, i64)> = HashMap::new();
 
-        for ((proxy_id, client_id, _user_id), (bytes_sent, bytes_received)) in buffer.drain() {
+        for ((node_id, proxy_id, client_id, _user_id), (bytes_sent, bytes_received)) in buffer.drain() {
             if bytes_sent == 0 && bytes_received == 0 {
                 continue;
             }
 
-            // 1. 更新代理流量，同时收集 node_id（代理已删除则跳过，避免外键约束失败）
+            // 1. 更新代理流量（代理已删除则跳过，避免外键约束失败）
             let Ok(Some(proxy)) = Proxy::find_by_id(proxy_id).one(db).await else {
                 debug!("代理 #{} 已不存在，跳过流量记录", proxy_id);
                 continue;
             };
 
-            // 收集节点流量
-            if let Some(nid) = proxy.node_id {
-                let entry = node_traffic.entry(nid).or_insert((0, 0));
-                entry.0 += bytes_sent;
-                entry.1 += bytes_received;
-            }
+            // 收集节点流量，按实际上报的节点统计
+            let entry = node_traffic.entry(node_id).or_insert((0, 0));
+            entry.0 += bytes_sent;
+            entry.1 += bytes_received;
 
             let mut proxy_active: proxy::ActiveModel = proxy.into();
             proxy_active.total_bytes_sent = Set(proxy_active.total_bytes_sent.unwrap() + bytes_sent);
@@ -133,96 +159,10 @@ impl TrafficManager {
                 }
             }
 
-            // 4. 更新客户端流量
-            if let Some(client) = client_opt {
-                let client_user_id = client.user_id;
-                let needs_reset = crate::traffic_limiter::should_reset_client_traffic(&client);
-
-                let mut client_active: client::ActiveModel = client.clone().into();
-
-                if needs_reset {
-                    client_active.total_bytes_sent = Set(bytes_sent);
-                    client_active.total_bytes_received = Set(bytes_received);
-                    client_active.is_traffic_exceeded = Set(false);
-                    client_active.last_reset_at = Set(Some(now));
-                    info!("🔄 客户端 #{} ({}) 流量已自动重置", client_id, client.name);
-                } else {
-                    client_active.total_bytes_sent = Set(client_active.total_bytes_sent.unwrap() + bytes_sent);
-                    client_active.total_bytes_received = Set(client_active.total_bytes_received.unwrap() + bytes_received);
-                }
-
-                client_active.updated_at = Set(now);
-
-                if let Err(e) = client_active.update(db).await {
+            // 4. 更新客户端及其所属用户的流量
+            if client_opt.is_some() {
+                if let Err(e) = crate::quota::consume(db, client_id, bytes_sent, bytes_received).await {
                     error!("更新客户端流量失败: {}", e);
-                } else {
-                    let new_sent = if needs_reset { bytes_sent } else { client.total_bytes_sent + bytes_sent };
-                    let new_received = if needs_reset { bytes_received } else { client.total_bytes_received + bytes_received };
-
-                    // 检查客户端配额
-                    if let Some(quota_gb) = client.traffic_quota_gb {
-                        let total_used = new_sent + new_received;
-                        let quota_bytes = crate::traffic_limiter::gb_to_bytes(quota_gb);
-                        if total_used >= quota_bytes && !client.is_traffic_exceeded {
-                            if let Ok(Some(c)) = Client::find_by_id(client_id).one(db).await {
-                                let mut c_active: client::ActiveModel = c.into();
-                                c_active.is_traffic_exceeded = Set(true);
-                                c_active.updated_at = Set(now);
-                                let _ = c_active.update(db).await;
-                                error!("⚠️ 客户端 #{} ({}) 流量配额已用尽: {:.2} GB / {:.2} GB",
-                                    client_id, client.name,
-                                    crate::traffic_limiter::bytes_to_gb(total_used),
-                                    quota_gb);
-                            }
-                        }
-                    }
-                }
-
-                // 4. 如果客户端有 user_id，同时更新用户流量
-                if let Some(uid) = client_user_id {
-                    if let Ok(Some(user)) = User::find_by_id(uid).one(db).await {
-                        let needs_reset = crate::traffic_limiter::should_reset_traffic(&user);
-
-                        let mut user_active: user::ActiveModel = user.clone().into();
-
-                        if needs_reset {
-                            user_active.total_bytes_sent = Set(bytes_sent);
-                            user_active.total_bytes_received = Set(bytes_received);
-                            user_active.is_traffic_exceeded = Set(false);
-                            user_active.last_reset_at = Set(Some(now));
-                            info!("🔄 用户 #{} ({}) 流量已自动重置", uid, user.username);
-                        } else {
-                            user_active.total_bytes_sent = Set(user_active.total_bytes_sent.unwrap() + bytes_sent);
-                            user_active.total_bytes_received = Set(user_active.total_bytes_received.unwrap() + bytes_received);
-                        }
-
-                        user_active.updated_at = Set(now);
-
-                        if let Err(e) = user_active.update(db).await {
-                            error!("更新用户流量失败: {}", e);
-                        } else {
-                            let new_sent = if needs_reset { bytes_sent } else { user.total_bytes_sent + bytes_sent };
-                            let new_received = if needs_reset { bytes_received } else { user.total_bytes_received + bytes_received };
-
-                            // 检查用户配额
-                            if let Some(quota_gb) = user.traffic_quota_gb {
-                                let total_used = new_sent + new_received;
-                                let quota_bytes = crate::traffic_limiter::gb_to_bytes(quota_gb);
-                                if total_used >= quota_bytes && !user.is_traffic_exceeded {
-                                    if let Ok(Some(u)) = User::find_by_id(uid).one(db).await {
-                                        let mut u_active: user::ActiveModel = u.into();
-                                        u_active.is_traffic_exceeded = Set(true);
-                                        u_active.updated_at = Set(now);
-                                        let _ = u_active.update(db).await;
-                                        error!("⚠️ 用户 #{} ({}) 流量配额已用尽: {:.2} GB / {:.2} GB",
-                                            uid, user.username,
-                                            crate::traffic_limiter::bytes_to_gb(total_used),
-                                            quota_gb);
-                                    }
-                                }
-                            }
-                        }
-                    }
                 }
             }
         }
@@ -279,6 +219,7 @@ impl TrafficManager {
     /// 实时记录流量统计到数据库 (异步非阻塞)
     pub async fn record_traffic(
         &self,
+        node_id: i64,
         proxy_id: i64,
         client_id: i64,
         user_id: Option<i64>,
@@ -290,6 +231,7 @@ impl TrafficManager {
         }
 
         let event = TrafficEvent {
+            node_id,
             proxy_id,
             client_id,
             user_id,
@@ -367,81 +309,133 @@ pub struct DailyTraffic {
     pub total_bytes: i64,
 }
 
-/// 获取流量总览
-pub async fn get_traffic_overview(user_id: Option<i64>, days: i64) -> Result<TrafficOverview> {
-    let db = get_connection().await;
+/// 登记一次流量上报，返回是否为首次上报
+///
+/// 上报 ID 在入账前写入 `traffic_report` 表（节点 + 上报 ID 唯一），节点在超时或重连后重发的
+/// 批次会因冲突而被忽略。登记先于入账，Controller 在两者之间崩溃时该批次少计而不会多计。
+/// 上报 ID 为空（旧版节点）时不去重。
+pub async fn claim_report(node_id: i64, report_id: &str) -> Result<bool> {
+    if report_id.is_empty() {
+        return Ok(true);
+    }
 
-    let is_admin = if let Some(uid) = user_id {
-        if let Some(user) = User::find_by_id(uid).one(db).await? {
-            user.is_admin
-        } else {
-            false
-        }
-    } else {
-        false
+    let db = get_connection().await;
+    let report = traffic_report::ActiveModel {
+        id: NotSet,
+        node_id: Set(node_id),
+        report_id: Set(report_id.to_string()),
+        created_at: Set(Utc::now().naive_utc()),
     };
+    let inserted = TrafficReport::insert(report)
+        .on_conflict(
+            OnConflict::columns([traffic_report::Column::NodeId, traffic_report::Column::ReportId])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+    Ok(inserted > 0)
+}
 
-    // 获取所有用户流量
-    let mut users = Vec::new();
-    let mut total_sent = 0i64;
-    let mut total_received = 0i64;
-
-    if is_admin {
-        let all_users = User::find().all(db).await?;
-        for user in all_users {
-            let total = user.total_bytes_sent + user.total_bytes_received;
-            users.push(UserTraffic {
-                user_id: user.id,
-                username: user.username,
-                total_bytes_sent: user.total_bytes_sent,
-                total_bytes_received: user.total_bytes_received,
-                total_bytes: total,
-            });
-        }
-    } else if let Some(uid) = user_id {
-        if let Some(user) = User::find_by_id(uid).one(db).await? {
-            let total = user.total_bytes_sent + user.total_bytes_received;
-            total_sent += user.total_bytes_sent;
-            total_received += user.total_bytes_received;
-            users.push(UserTraffic {
-                user_id: user.id,
-                username: user.username,
-                total_bytes_sent: user.total_bytes_sent,
-                total_bytes_received: user.total_bytes_received,
-                total_bytes: total,
-            });
+/// 定期清理过期的上报 ID（节点暂存的批次超过保留期后不再重发）
+pub fn start_report_pruner() {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(3600));
+
+        loop {
+            interval.tick().await;
+
+            let cutoff = Utc::now().naive_utc() - chrono::Duration::days(REPORT_RETENTION_DAYS);
+            match TrafficReport::delete_many()
+                .filter(traffic_report::Column::CreatedAt.lt(cutoff))
+                .exec(get_connection().await)
+                .await
+            {
+                Ok(res) if res.rows_affected > 0 => {
+                    debug!("已清理 {} 条过期的流量上报 ID", res.rows_affected);
+                }
+                Ok(_) => {}
+                Err(e) => error!("清理流量上报 ID 失败: {}", e),
+            }
         }
+    });
+}
+
+/// 获取流量总览
+///
+/// `user_ids` 为统计范围内的用户（`None` 表示全部，仅平台管理员），客户端、代理和按日流量只统计这些用户的客户端。
+/// 每类数据只发出一条查询（按日流量在数据库中聚合），`top` 指定时客户端和代理只返回流量最高的前 N 个。
+pub async fn get_traffic_overview(user_ids: Option<&[i64]>, days: i64, top: Option<u64>) -> Result<TrafficOverview> {
+    let db = get_connection().await;
+
+    // 用户流量
+    let mut user_query = User::find();
+    if let Some(ids) = user_ids {
+        user_query = user_query.filter(user::Column::Id.is_in(ids.iter().copied()));
     }
+    let users: Vec<UserTraffic> = user_query
+        .order_by_asc(user::Column::Id)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|user| UserTraffic {
+            user_id: user.id,
+            username: user.username,
+            total_bytes_sent: user.total_bytes_sent,
+            total_bytes_received: user.total_bytes_received,
+            total_bytes: user.total_bytes_sent + user.total_bytes_received,
+        })
+        .collect();
+
+    // 客户端流量
+    let total_bytes = |sent: client::Column, received: client::Column| {
+        Expr::col(sent).add(Expr::col(received))
+    };
+    let mut client_query = Client::find();
+    if let Some(condition) = crate::api::access::client_owner_condition(user_ids) {
+        client_query = client_query.filter(condition);
+    }
+    let all_clients = client_query
+        .order_by_desc(total_bytes(client::Column::TotalBytesSent, client::Column::TotalBytesReceived))
+        .order_by_asc(client::Column::Id)
+        .all(db)
+        .await?;
 
-    // 获取客户端流量
-    let mut clients = Vec::new();
-    let all_clients = Client::find().all(db).await?;
-    for client in all_clients {
-        let total = client.total_bytes_sent + client.total_bytes_received;
-        if !is_admin {
-            // 如果不是管理员，只显示有权限的客户端
-            if user_id.is_some() && !has_client_access(db, user_id.unwrap(), client.id).await? {
-                continue;
-            }
-        }
-        // 管理员模式下从 client 表统计总流量（避免从 user 表统计导致遗漏无关联用户的流量）
-        if is_admin {
-            total_sent += client.total_bytes_sent;
-            total_received += client.total_bytes_received;
-        }
-        clients.push(ClientTraffic {
+    // 不限范围时从 client 表统计总流量（避免从 user 表统计导致遗漏无关联用户的流量）
+    let (total_sent, total_received) = if user_ids.is_none() {
+        all_clients.iter().fold((0, 0), |(s, r), c| (s + c.total_bytes_sent, r + c.total_bytes_received))
+    } else {
+        users.iter().fold((0, 0), |(s, r), u| (s + u.total_bytes_sent, r + u.total_bytes_received))
+    };
+
+    let client_names: HashMap<i64, String> = all_clients.iter().map(|c| (c.id, c.name.clone())).collect();
+    let clients: Vec<ClientTraffic> = all_clients
+        .into_iter()
+        .take(top.map_or(usize::MAX, |n| n as usize))
+        .map(|client| ClientTraffic {
             client_id: client.id,
             client_name: client.name,
             total_bytes_sent: client.total_bytes_sent,
             total_bytes_received: client.total_bytes_received,
-            total_bytes: total,
-        });
+            total_bytes: client.total_bytes_sent + client.total_bytes_received,
+        })
+        .collect();
+
+    // 代理流量
+    let mut proxy_query = Proxy::find();
+    if user_ids.is_some() {
+        let client_ids: Vec<String> = client_names.keys().map(|id| id.to_string()).collect();
+        proxy_query = proxy_query.filter(proxy::Column::ClientId.is_in(client_ids));
+    }
+    proxy_query = proxy_query
+        .order_by_desc(Expr::col(proxy::Column::TotalBytesSent).add(Expr::col(proxy::Column::TotalBytesReceived)))
+        .order_by_asc(proxy::Column::Id);
+    if let Some(n) = top {
+        proxy_query = proxy_query.limit(n);
     }
 
-    // 获取代理流量
     let mut proxies = Vec::new();
-    let all_proxies = Proxy::find().all(db).await?;
-    for proxy in all_proxies {
+    for proxy in proxy_query.all(db).await? {
         let proxy_client_id = match proxy.client_id.parse::<i64>() {
             Ok(id) => id,
             Err(_) => {
@@ -450,63 +444,44 @@ pub async fn get_traffic_overview(user_id: Option<i64>, days: i64) -> Result<Tra
             }
         };
 
-        let total = proxy.total_bytes_sent + proxy.total_bytes_received;
-        if !is_admin {
-            // 如果不是管理员，只显示有权限的代理
-            if user_id.is_some() && !has_client_access(db, user_id.unwrap(), proxy_client_id).await? {
-                continue;
-            }
-        }
-
-        let client_name = if let Some(client) = Client::find_by_id(proxy_client_id).one(db).await? {
-            client.name
-        } else {
-            String::from("Unknown")
-        };
-
         proxies.push(ProxyTraffic {
             proxy_id: proxy.id,
             proxy_name: proxy.name,
             client_id: proxy_client_id,
-            client_name,
+            client_name: client_names.get(&proxy_client_id).cloned().unwrap_or_else(|| String::from("Unknown")),
             total_bytes_sent: proxy.total_bytes_sent,
             total_bytes_received: proxy.total_bytes_received,
-            total_bytes: total,
+            total_bytes: proxy.total_bytes_sent + proxy.total_bytes_received,
         });
     }
 
-    // 获取每日流量统计
-    let mut daily = Vec::new();
+    // 每日流量统计（按日期在数据库中聚合，走 (client_id, date) 索引）
     let start_date = Utc::now() - chrono::Duration::days(days);
     let start_date_str = start_date.format("%Y-%m-%d").to_string();
 
-    let all_daily = TrafficDaily::find()
-        .filter(traffic_daily::Column::Date.gte(&start_date_str))
-        .all(db)
-        .await?;
-
-    let mut daily_map: HashMap<String, (i64, i64)> = HashMap::new();
-    for d in all_daily {
-        if !is_admin && user_id.is_some() {
-            // 如果不是管理员，只显示有权限的代理的流量
-            if !has_client_access(db, user_id.unwrap(), d.client_id).await? {
-                continue;
-            }
-        }
-        let entry = daily_map.entry(d.date.clone()).or_insert((0, 0));
-        entry.0 += d.bytes_sent;
-        entry.1 += d.bytes_received;
+    let mut daily_query = TrafficDaily::find()
+        .select_only()
+        .column(traffic_daily::Column::Date)
+        .column_as(Expr::col(traffic_daily::Column::BytesSent).sum(), "bytes_sent")
+        .column_as(Expr::col(traffic_daily::Column::BytesReceived).sum(), "bytes_received")
+        .filter(traffic_daily::Column::Date.gte(&start_date_str));
+    if user_ids.is_some() {
+        daily_query = daily_query.filter(traffic_daily::Column::ClientId.is_in(client_names.keys().copied()));
     }
-
-    for (date, (sent, received)) in daily_map {
-        daily.push(DailyTraffic {
+    let daily: Vec<DailyTraffic> = daily_query
+        .group_by(traffic_daily::Column::Date)
+        .order_by_asc(traffic_daily::Column::Date)
+        .into_tuple::<(String, i64, i64)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|(date, sent, received)| DailyTraffic {
             date,
             total_bytes_sent: sent,
             total_bytes_received: received,
             total_bytes: sent + received,
-        });
-    }
-    daily.sort_by(|a, b| a.date.cmp(&b.date));
+        })
+        .collect();
 
     Ok(TrafficOverview {
         total_traffic: TotalTraffic {
@@ -520,17 +495,3 @@ pub async fn get_traffic_overview(user_id: Option<i64>, days: i64) -> Result<Tra
         daily_traffic: daily,
     })
 }
-
-/// 检查用户是否有访问客户端的权限（通过 client.user_id）
-async fn has_client_access(db: &DatabaseConnection, user_id: i64, client_id: i64) -> Result<bool> {
-    use crate::entity::client::Entity as Client;
-
-    // 查找 client 并检查 user_id
-    let client = match Client::find_by_id(client_id).one(db).await? {
-        Some(c) => c,
-        None => return Ok(false),
-    };
-
-    // 检查客户端是否属于该用户
-    Ok(client.user_id == Some(user_id))
-}
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use chrono::{Datelike, Utc};
-use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set};
 use tracing::{info};
 
 use crate::entity::{client, node, user, Client, User};
@@ -187,64 +187,6 @@ pub async fn check_client_traffic_limit(client_id: i64, db: &DatabaseConnection)
     Ok((false, String::new()))
 }
 
-/// 检查用户是否有足够的配额分配给客户端
-pub async fn check_user_quota_allocation(user_id: i64, additional_quota_gb: f64, db: &DatabaseConnection) -> Result<(bool, String)> {
-    let user = match User::find_by_id(user_id).one(db).await? {
-        Some(u) => u,
-        None => return Ok((false, "用户不存在".to_string())),
-    };
-
-    // 获取最终配额（套餐配额 + 用户直接配额）
-    let (final_traffic_quota_gb, _, _, _) = crate::subscription_quota::get_user_final_quota(
-        user_id,
-        user.traffic_quota_gb,
-        user.max_port_count,
-        user.max_node_count,
-        user.max_client_count,
-        db,
-    ).await?;
-
-    // 如果用户没有配额限制，允许分配
-    let user_quota_gb = match final_traffic_quota_gb {
-        Some(q) => q,
-        None => return Ok((true, String::new())),
-    };
-
-    // 计算用户已使用的流量
-    let user_used_gb = bytes_to_gb(user.total_bytes_sent + user.total_bytes_received);
-
-    // 查询用户所有客户端已分配的配额总和（直接通过 client.user_id）
-    let user_clients = Client::find()
-        .filter(client::Column::UserId.eq(user_id))
-        .all(db)
-        .await?;
-
-    let mut total_allocated_gb = 0.0;
-    for client in user_clients {
-        if let Some(quota) = client.traffic_quota_gb {
-            total_allocated_gb += quota;
-        }
-    }
-
-    // 检查：已使用 + 已分配 + 新分配 <= 用户配额
-    let total_needed = user_used_gb + total_allocated_gb + additional_quota_gb;
-
-    if total_needed > user_quota_gb {
-        let available = (user_quota_gb - user_used_gb - total_allocated_gb).max(0.0);
-        let reason = format!(
-            "配额不足: 可用 {:.2} GB，需要 {:.2} GB (用户配额: {:.2} GB，已使用: {:.2} GB，已分配: {:.2} GB)",
-            available,
-            additional_quota_gb,
-            user_quota_gb,
-            user_used_gb,
-            total_allocated_gb
-        );
-        return Ok((false, reason));
-    }
-
-    Ok((true, String::new()))
-}
-
 /// 检查节点流量是否需要重置
 pub fn should_reset_node_traffic(node: &node::Model) -> bool {
     let now = Utc::now().naive_utc();
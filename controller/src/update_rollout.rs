@@ -0,0 +1,507 @@
+//! 软件更新发布编排
+//!
+//! Controller 为节点/客户端维护期望版本（发布计划），先更新金丝雀列表中的 Agent，
+//! 再按百分比逐步扩大范围下发 `SoftwareUpdateCommand`，并记录每个 Agent 上报的更新进度。
+//! 任一 Agent 更新失败时自动暂停发布计划，等待管理员处理。
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use common::grpc::oxiproxy;
+
+use crate::client_stream_manager::ClientStreamManager;
+use crate::entity::{agent_update, update_rollout, AgentUpdate, Client, Node, UpdateRollout};
+use crate::migration::get_connection;
+use crate::node_manager::NodeManager;
+
+pub const AGENT_NODE: &str = "node";
+pub const AGENT_CLIENT: &str = "client";
+
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_PAUSED: &str = "paused";
+pub const STATUS_COMPLETED: &str = "completed";
+pub const STATUS_CANCELLED: &str = "cancelled";
+
+/// 已下发但在该时间内未完成的更新视为超时，允许重新下发
+const IN_FLIGHT_TIMEOUT_SECS: i64 = 600;
+
+/// Agent 在发布计划中的当前版本与在线状态
+struct AgentInfo {
+    version: Option<String>,
+    online: bool,
+}
+
+pub struct UpdateRolloutManager {
+    node_manager: Arc<NodeManager>,
+    client_stream_manager: Arc<ClientStreamManager>,
+}
+
+impl UpdateRolloutManager {
+    pub fn new(node_manager: Arc<NodeManager>, client_stream_manager: Arc<ClientStreamManager>) -> Self {
+        Self {
+            node_manager,
+            client_stream_manager,
+        }
+    }
+
+    /// 创建发布计划，同类型的其他进行中计划会被取消
+    pub async fn create_rollout(
+        &self,
+        agent_type: &str,
+        target_version: &str,
+        percentage: i32,
+        canary_ids: &[i64],
+    ) -> Result<update_rollout::Model> {
+        if agent_type != AGENT_NODE && agent_type != AGENT_CLIENT {
+            return Err(anyhow!("无效的 Agent 类型: {}", agent_type));
+        }
+        if !(0..=100).contains(&percentage) {
+            return Err(anyhow!("发布百分比必须在 0-100 之间"));
+        }
+        let target_version = normalize_version(target_version);
+        if target_version.is_empty() {
+            return Err(anyhow!("目标版本不能为空"));
+        }
+
+        let db = get_connection().await;
+        let now = Utc::now().naive_utc();
+
+        let superseded = UpdateRollout::find()
+            .filter(update_rollout::Column::AgentType.eq(agent_type))
+            .filter(update_rollout::Column::Status.is_in([STATUS_RUNNING, STATUS_PAUSED]))
+            .all(db)
+            .await?;
+        for rollout in superseded {
+            info!("发布计划 #{} 已被新的发布计划取代", rollout.id);
+            let mut active: update_rollout::ActiveModel = rollout.into();
+            active.status = Set(STATUS_CANCELLED.to_string());
+            active.updated_at = Set(now);
+            active.update(db).await?;
+        }
+
+        let canary_ids = if canary_ids.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(canary_ids)?)
+        };
+
+        let rollout = update_rollout::ActiveModel {
+            agent_type: Set(agent_type.to_string()),
+            target_version: Set(target_version.to_string()),
+            percentage: Set(percentage),
+            canary_ids: Set(canary_ids),
+            status: Set(STATUS_RUNNING.to_string()),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        info!(
+            "创建发布计划 #{}: {} → {} ({}%)",
+            rollout.id, agent_type, rollout.target_version, percentage
+        );
+        Ok(rollout)
+    }
+
+    /// 调整发布百分比或状态（running / paused / cancelled）
+    ///
+    /// 从暂停恢复为进行中时，清除失败记录以便重新下发。
+    pub async fn update_rollout(
+        &self,
+        id: i64,
+        percentage: Option<i32>,
+        status: Option<&str>,
+    ) -> Result<update_rollout::Model> {
+        let db = get_connection().await;
+        let rollout = UpdateRollout::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| anyhow!("发布计划不存在"))?;
+
+        if rollout.status == STATUS_COMPLETED || rollout.status == STATUS_CANCELLED {
+            return Err(anyhow!("发布计划已结束，无法修改"));
+        }
+
+        let mut active: update_rollout::ActiveModel = rollout.clone().into();
+
+        if let Some(percentage) = percentage {
+            if !(0..=100).contains(&percentage) {
+                return Err(anyhow!("发布百分比必须在 0-100 之间"));
+            }
+            if percentage < rollout.percentage {
+                return Err(anyhow!("发布百分比不能缩小"));
+            }
+            active.percentage = Set(percentage);
+        }
+
+        if let Some(status) = status {
+            if ![STATUS_RUNNING, STATUS_PAUSED, STATUS_CANCELLED].contains(&status) {
+                return Err(anyhow!("无效的发布状态: {}", status));
+            }
+            if status == STATUS_RUNNING && rollout.status == STATUS_PAUSED {
+                AgentUpdate::delete_many()
+                    .filter(agent_update::Column::RolloutId.eq(id))
+                    .filter(agent_update::Column::Status.eq("failed"))
+                    .exec(db)
+                    .await?;
+            }
+            active.status = Set(status.to_string());
+        }
+
+        active.updated_at = Set(Utc::now().naive_utc());
+        Ok(active.update(db).await?)
+    }
+
+    /// 对所有进行中的发布计划执行一次调和
+    pub async fn reconcile_all(&self) {
+        let db = get_connection().await;
+        let rollouts = match UpdateRollout::find()
+            .filter(update_rollout::Column::Status.eq(STATUS_RUNNING))
+            .order_by_asc(update_rollout::Column::Id)
+            .all(db)
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                error!("查询发布计划失败: {}", e);
+                return;
+            }
+        };
+
+        for rollout in rollouts {
+            if let Err(e) = self.reconcile(&rollout).await {
+                error!("调和发布计划 #{} 失败: {}", rollout.id, e);
+            }
+        }
+    }
+
+    /// 调和单个发布计划：向未达到目标版本的在线 Agent 下发更新，全部完成后标记计划完成
+    async fn reconcile(&self, rollout: &update_rollout::Model) -> Result<()> {
+        let db = get_connection().await;
+        let agents = load_agents(&rollout.agent_type).await?;
+
+        let mut agent_ids: Vec<i64> = agents.keys().copied().collect();
+        agent_ids.sort_unstable();
+        let canary_ids: Vec<i64> = rollout
+            .canary_ids
+            .as_deref()
+            .and_then(|s| serde_json::from_str(s).ok())
+            .unwrap_or_default();
+        let targets = select_targets(rollout.id, &agent_ids, &canary_ids, rollout.percentage);
+
+        let records: HashMap<i64, agent_update::Model> = AgentUpdate::find()
+            .filter(agent_update::Column::RolloutId.eq(rollout.id))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|r| (r.agent_id, r))
+            .collect();
+
+        let now = Utc::now().naive_utc();
+        let mut all_done = true;
+
+        for agent_id in targets {
+            let Some(agent) = agents.get(&agent_id) else {
+                continue;
+            };
+            let record = records.get(&agent_id);
+
+            if agent.version.as_deref().map(normalize_version) == Some(rollout.target_version.as_str()) {
+                if record.map(|r| r.status != "succeeded").unwrap_or(true) {
+                    upsert_record(rollout, agent_id, record, None, "succeeded", None).await?;
+                }
+                continue;
+            }
+
+            all_done = false;
+            if !agent.online {
+                continue;
+            }
+
+            if let Some(record) = record {
+                if record.status == "failed" {
+                    continue;
+                }
+                let in_flight = record.status != "succeeded"
+                    && (now - record.updated_at).num_seconds() < IN_FLIGHT_TIMEOUT_SECS;
+                if in_flight {
+                    continue;
+                }
+            }
+
+            upsert_record(rollout, agent_id, record, agent.version.clone(), "dispatched", None).await?;
+            self.dispatch(rollout.clone(), agent_id);
+        }
+
+        if all_done && rollout.percentage >= 100 {
+            info!("发布计划 #{} 已完成", rollout.id);
+            let mut active: update_rollout::ActiveModel = rollout.clone().into();
+            active.status = Set(STATUS_COMPLETED.to_string());
+            active.updated_at = Set(now);
+            active.update(db).await?;
+        }
+
+        Ok(())
+    }
+
+    /// 后台下发更新指令并记录结果
+    fn dispatch(&self, rollout: update_rollout::Model, agent_id: i64) {
+        let node_manager = self.node_manager.clone();
+        let client_stream_manager = self.client_stream_manager.clone();
+
+        tokio::spawn(async move {
+            info!(
+                "发布计划 #{}: 向 {} #{} 下发更新到 {}",
+                rollout.id, rollout.agent_type, agent_id, rollout.target_version
+            );
+
+            let target = Some(rollout.target_version.clone());
+            let result = if rollout.agent_type == AGENT_NODE {
+                node_manager.send_software_update(agent_id, target).await
+            } else {
+                client_stream_manager.send_software_update(agent_id, target).await
+            };
+
+            let (status, message) = match result {
+                Ok(resp) if resp.success => ("restarting", None),
+                Ok(resp) => ("failed", Some(resp.error.unwrap_or_else(|| "未知错误".to_string()))),
+                Err(e) => ("failed", Some(e.to_string())),
+            };
+
+            if let Err(e) = set_record_status(rollout.id, agent_id, status, message.clone()).await {
+                error!("更新发布记录失败: {}", e);
+            }
+
+            if status == "failed" {
+                warn!(
+                    "发布计划 #{}: {} #{} 更新失败，已暂停发布: {}",
+                    rollout.id,
+                    rollout.agent_type,
+                    agent_id,
+                    message.unwrap_or_default()
+                );
+                let db = get_connection().await;
+                if let Ok(Some(current)) = UpdateRollout::find_by_id(rollout.id).one(db).await {
+                    if current.status == STATUS_RUNNING {
+                        let mut active: update_rollout::ActiveModel = current.into();
+                        active.status = Set(STATUS_PAUSED.to_string());
+                        active.updated_at = Set(Utc::now().naive_utc());
+                        let _ = active.update(db).await;
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// 记录 Agent 上报的更新进度（更新该 Agent 最近一条未结束的记录）
+pub async fn record_progress(agent_type: &str, agent_id: i64, progress: &oxiproxy::UpdateProgress) {
+    let status = match progress.stage.as_str() {
+        "downloading" | "installing" | "restarting" | "failed" => progress.stage.as_str(),
+        other => {
+            warn!("{} #{} 上报未知的更新阶段: {}", agent_type, agent_id, other);
+            return;
+        }
+    };
+
+    let db = get_connection().await;
+    let record = AgentUpdate::find()
+        .filter(agent_update::Column::AgentType.eq(agent_type))
+        .filter(agent_update::Column::AgentId.eq(agent_id))
+        .filter(agent_update::Column::Status.is_not_in(["succeeded", "failed"]))
+        .order_by_desc(agent_update::Column::UpdatedAt)
+        .one(db)
+        .await;
+
+    match record {
+        Ok(Some(record)) => {
+            let mut active: agent_update::ActiveModel = record.into();
+            active.status = Set(status.to_string());
+            if progress.message.is_some() {
+                active.message = Set(progress.message.clone());
+            }
+            active.updated_at = Set(Utc::now().naive_utc());
+            if let Err(e) = active.update(db).await {
+                error!("记录 {} #{} 更新进度失败: {}", agent_type, agent_id, e);
+            }
+        }
+        Ok(None) => {
+            // 手动触发的更新不属于任何发布计划
+            info!("{} #{} 更新进度: {}", agent_type, agent_id, status);
+        }
+        Err(e) => error!("查询 {} #{} 更新记录失败: {}", agent_type, agent_id, e),
+    }
+}
+
+/// 启动发布计划调和后台任务
+pub fn start_update_rollout_monitor(manager: Arc<UpdateRolloutManager>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(30));
+
+        loop {
+            interval.tick().await;
+            manager.reconcile_all().await;
+        }
+    });
+}
+
+/// 根据金丝雀列表和百分比选择本次发布的目标 Agent
+///
+/// 金丝雀始终包含在内，其余 Agent 按与发布计划相关的稳定顺序排列，
+/// 因此扩大百分比时已选中的 Agent 保持不变。
+pub fn select_targets(rollout_id: i64, agent_ids: &[i64], canary_ids: &[i64], percentage: i32) -> Vec<i64> {
+    let percentage = percentage.clamp(0, 100) as usize;
+    let quota = (agent_ids.len() * percentage).div_ceil(100);
+
+    let mut targets: Vec<i64> = canary_ids
+        .iter()
+        .copied()
+        .filter(|id| agent_ids.contains(id))
+        .collect();
+
+    let mut rest: Vec<i64> = agent_ids
+        .iter()
+        .copied()
+        .filter(|id| !targets.contains(id))
+        .collect();
+    rest.sort_by_key(|id| rollout_hash(rollout_id, *id));
+
+    let remaining = quota.saturating_sub(targets.len());
+    targets.extend(rest.into_iter().take(remaining));
+    targets
+}
+
+/// 稳定的 (发布计划, Agent) 排序键（splitmix64）
+fn rollout_hash(rollout_id: i64, agent_id: i64) -> u64 {
+    let mut x = (rollout_id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ agent_id as u64;
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+fn normalize_version(version: &str) -> &str {
+    version.trim().trim_start_matches('v')
+}
+
+/// 查询指定类型的所有 Agent 的版本与在线状态
+async fn load_agents(agent_type: &str) -> Result<HashMap<i64, AgentInfo>> {
+    let db = get_connection().await;
+    let agents = if agent_type == AGENT_NODE {
+        Node::find()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|n| (n.id, AgentInfo { version: n.version, online: n.is_online }))
+            .collect()
+    } else {
+        Client::find()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|c| (c.id, AgentInfo { version: c.version, online: c.is_online }))
+            .collect()
+    };
+    Ok(agents)
+}
+
+async fn upsert_record(
+    rollout: &update_rollout::Model,
+    agent_id: i64,
+    existing: Option<&agent_update::Model>,
+    from_version: Option<String>,
+    status: &str,
+    message: Option<String>,
+) -> Result<()> {
+    let db = get_connection().await;
+    let now = Utc::now().naive_utc();
+
+    match existing {
+        Some(record) => {
+            let mut active: agent_update::ActiveModel = record.clone().into();
+            if from_version.is_some() {
+                active.from_version = Set(from_version);
+            }
+            active.status = Set(status.to_string());
+            active.message = Set(message);
+            active.updated_at = Set(now);
+            active.update(db).await?;
+        }
+        None => {
+            agent_update::ActiveModel {
+                rollout_id: Set(rollout.id),
+                agent_type: Set(rollout.agent_type.clone()),
+                agent_id: Set(agent_id),
+                from_version: Set(from_version),
+                target_version: Set(rollout.target_version.clone()),
+                status: Set(status.to_string()),
+                message: Set(message),
+                created_at: Set(now),
+                updated_at: Set(now),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn set_record_status(rollout_id: i64, agent_id: i64, status: &str, message: Option<String>) -> Result<()> {
+    let db = get_connection().await;
+    if let Some(record) = AgentUpdate::find()
+        .filter(agent_update::Column::RolloutId.eq(rollout_id))
+        .filter(agent_update::Column::AgentId.eq(agent_id))
+        .one(db)
+        .await?
+    {
+        // Agent 已重连并上报目标版本时不再覆盖
+        if record.status == "succeeded" {
+            return Ok(());
+        }
+        let mut active: agent_update::ActiveModel = record.into();
+        active.status = Set(status.to_string());
+        active.message = Set(message);
+        active.updated_at = Set(Utc::now().naive_utc());
+        active.update(db).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_targets_includes_canaries() {
+        let ids: Vec<i64> = (1..=10).collect();
+        assert_eq!(select_targets(1, &ids, &[3, 7], 0), vec![3, 7]);
+        // 不存在的金丝雀被忽略
+        assert_eq!(select_targets(1, &ids, &[42], 0), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn test_select_targets_percentage() {
+        let ids: Vec<i64> = (1..=10).collect();
+        assert_eq!(select_targets(1, &ids, &[], 25).len(), 3);
+        assert_eq!(select_targets(1, &ids, &[1, 2], 10).len(), 2);
+
+        let mut all = select_targets(1, &ids, &[], 100);
+        all.sort_unstable();
+        assert_eq!(all, ids);
+    }
+
+    #[test]
+    fn test_select_targets_widening_is_superset() {
+        let ids: Vec<i64> = (1..=50).collect();
+        let small = select_targets(7, &ids, &[5], 20);
+        let large = select_targets(7, &ids, &[5], 60);
+        assert!(small.iter().all(|id| large.contains(id)));
+    }
+}
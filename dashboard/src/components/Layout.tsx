@@ -7,7 +7,7 @@ interface LayoutProps {
 }
 
 export default function Layout({ children }: LayoutProps) {
-  const { user, logout, isAdmin } = useAuth();
+  const { user, logout, isAdmin, stopImpersonation } = useAuth();
   const location = useLocation();
   const [sidebarCollapsed, setSidebarCollapsed] = useState(false);
 
@@ -214,6 +214,24 @@ export default function Layout({ children }: LayoutProps) {
 
       {/* 主内容区 */}
       <div className="flex-1 flex flex-col min-h-screen overflow-hidden">
+        {/* 模拟登录提示 */}
+        {user?.impersonator && (
+          <div className="flex items-center justify-between gap-4 px-8 py-2 bg-amber-100 border-b border-amber-300 text-amber-900 text-sm">
+            <span>
+              管理员 <span className="font-semibold">{user.impersonator}</span> 正在以用户 <span className="font-semibold">{user.username}</span> 的身份查看（只读）
+            </span>
+            <button
+              onClick={() => {
+                stopImpersonation();
+                window.location.href = '/users';
+              }}
+              className="px-3 py-1 text-xs font-medium rounded-md border border-amber-400 hover:bg-amber-200 transition-colors"
+            >
+              退出模拟
+            </button>
+          </div>
+        )}
+
         {/* 顶部栏 - 现代化设计 */}
         <header className="h-16 bg-card border-b border-border flex items-center justify-between px-8">
           <div className="flex items-center gap-4">
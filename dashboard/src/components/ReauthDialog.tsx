@@ -0,0 +1,90 @@
+import { useEffect, useRef, useState } from 'react';
+import { isAxiosError } from 'axios';
+import { setReauthHandler } from '../lib/api';
+import { authService } from '../lib/services';
+
+/** 敏感操作前重新输入密码的对话框，由 API 拦截器在服务端要求二次验证时弹出 */
+export default function ReauthDialog() {
+  const [open, setOpen] = useState(false);
+  const [password, setPassword] = useState('');
+  const [error, setError] = useState('');
+  const [submitting, setSubmitting] = useState(false);
+  const resolveRef = useRef<((ok: boolean) => void) | null>(null);
+
+  useEffect(() => {
+    setReauthHandler(
+      () =>
+        new Promise<boolean>((resolve) => {
+          resolveRef.current?.(false);
+          resolveRef.current = resolve;
+          setPassword('');
+          setError('');
+          setOpen(true);
+        })
+    );
+    return () => setReauthHandler(null);
+  }, []);
+
+  const finish = (ok: boolean) => {
+    resolveRef.current?.(ok);
+    resolveRef.current = null;
+    setOpen(false);
+    setPassword('');
+  };
+
+  const handleSubmit = async (e: React.FormEvent) => {
+    e.preventDefault();
+    if (!password) return;
+    setSubmitting(true);
+    setError('');
+    try {
+      await authService.reauth(password);
+      finish(true);
+    } catch (err) {
+      setError((isAxiosError(err) && err.response?.data?.message) || '验证失败');
+    } finally {
+      setSubmitting(false);
+    }
+  };
+
+  if (!open) return null;
+
+  return (
+    <div className="fixed inset-0 bg-black/50 backdrop-blur-sm flex items-center justify-center z-[60]" onClick={() => finish(false)}>
+      <form
+        className="relative bg-card rounded-2xl shadow-2xl w-full max-w-sm mx-4 p-6"
+        onClick={e => e.stopPropagation()}
+        onSubmit={handleSubmit}
+      >
+        <h3 className="text-lg font-bold text-foreground">验证身份</h3>
+        <p className="mt-2 text-sm text-muted-foreground">这是敏感操作，请重新输入当前账号的密码。</p>
+        <input
+          type="password"
+          autoFocus
+          autoComplete="current-password"
+          value={password}
+          onChange={e => setPassword(e.target.value)}
+          placeholder="当前密码"
+          className="mt-4 flex h-10 w-full rounded-xl border border-input bg-background px-3 py-2 text-sm focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring"
+        />
+        {error && <p className="mt-2 text-sm text-destructive">{error}</p>}
+        <div className="mt-6 flex gap-3">
+          <button
+            type="button"
+            onClick={() => finish(false)}
+            className="flex-1 px-4 py-2.5 bg-muted text-foreground font-medium rounded-xl hover:bg-accent transition-colors"
+          >
+            取消
+          </button>
+          <button
+            type="submit"
+            disabled={submitting || !password}
+            className="flex-1 px-4 py-2.5 bg-primary text-primary-foreground font-medium rounded-xl hover:bg-primary/90 transition-colors disabled:opacity-50"
+          >
+            {submitting ? '验证中...' : '确认'}
+          </button>
+        </div>
+      </form>
+    </div>
+  );
+}
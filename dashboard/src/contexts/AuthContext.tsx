@@ -7,11 +7,17 @@ interface AuthContextType {
   token: string | null;
   login: (token: string, user: User) => void;
   logout: () => void;
+  startImpersonation: (token: string, user: User) => void;
+  stopImpersonation: () => void;
   isAuthenticated: boolean;
   isAdmin: boolean;
   isLoading: boolean;
 }
 
+// 模拟登录期间保存管理员自己的登录状态，退出模拟时恢复
+const ADMIN_TOKEN_KEY = 'impersonator_token';
+const ADMIN_USER_KEY = 'impersonator_user';
+
 const AuthContext = createContext<AuthContextType | undefined>(undefined);
 
 export function AuthProvider({ children }: { children: ReactNode }) {
@@ -67,10 +73,32 @@ export function AuthProvider({ children }: { children: ReactNode }) {
   const logout = () => {
     localStorage.removeItem('token');
     localStorage.removeItem('user');
+    localStorage.removeItem(ADMIN_TOKEN_KEY);
+    localStorage.removeItem(ADMIN_USER_KEY);
     setToken(null);
     setUser(null);
   };
 
+  const startImpersonation = (newToken: string, newUser: User) => {
+    if (token && user) {
+      localStorage.setItem(ADMIN_TOKEN_KEY, token);
+      localStorage.setItem(ADMIN_USER_KEY, JSON.stringify(user));
+    }
+    login(newToken, newUser);
+  };
+
+  const stopImpersonation = () => {
+    const adminToken = localStorage.getItem(ADMIN_TOKEN_KEY);
+    const adminUser = localStorage.getItem(ADMIN_USER_KEY);
+    localStorage.removeItem(ADMIN_TOKEN_KEY);
+    localStorage.removeItem(ADMIN_USER_KEY);
+    if (adminToken && adminUser) {
+      login(adminToken, JSON.parse(adminUser));
+    } else {
+      logout();
+    }
+  };
+
   return (
     <AuthContext.Provider
       value={{
@@ -78,6 +106,8 @@ export function AuthProvider({ children }: { children: ReactNode }) {
         token,
         login,
         logout,
+        startImpersonation,
+        stopImpersonation,
         isAuthenticated: !!user,
         isAdmin: user?.is_admin || false,
         isLoading,
@@ -23,10 +23,34 @@ api.interceptors.request.use(
   }
 );
 
+// 敏感操作需要二次验证时，由 ReauthDialog 注册的处理函数提示输入密码，验证通过返回 true
+type ReauthHandler = () => Promise<boolean>;
+let reauthHandler: ReauthHandler | null = null;
+
+export function setReauthHandler(handler: ReauthHandler | null) {
+  reauthHandler = handler;
+}
+
 // 响应拦截器 - 处理错误
 api.interceptors.response.use(
   (response) => response,
-  (error) => {
+  async (error) => {
+    // 需要二次验证：输入密码后自动重试一次原请求
+    const config = error.config;
+    if (
+      error.response?.status === 403 &&
+      error.response?.data?.data?.reauthRequired &&
+      reauthHandler &&
+      config &&
+      !config._reauthRetried
+    ) {
+      if (await reauthHandler()) {
+        config._reauthRetried = true;
+        return api(config);
+      }
+      return Promise.reject(error);
+    }
+
     if (error.response?.status === 401) {
       const url = error.config?.url || '';
       const errorMessage = error.response?.data?.message || '';
@@ -43,7 +67,8 @@ api.interceptors.response.use(
         url.includes('/auth/') ||
         errorMessage.toLowerCase().includes('token') ||
         errorMessage.toLowerCase().includes('unauthorized') ||
-        errorMessage.toLowerCase().includes('not authenticated');
+        errorMessage.toLowerCase().includes('not authenticated') ||
+        errorMessage.includes('重新登录');
 
       if (isAuthError) {
         console.warn('认证失败，正在跳转到登录页...');
@@ -4,17 +4,59 @@ import type {
   UserWithNodeCount,
   Client,
   ClientTrafficInfo,
+  ClientLoginEvent,
+  DuplicateLoginPolicy,
   Proxy,
+  ProxyEndpoint,
+  ProxyEvent,
+  PortProbe,
+  ProxyCapture,
   TrafficOverview,
   DashboardStats,
+  OnlineStatus,
   LoginRequest,
   LoginResponse,
   LogEntry,
+  LogLevel,
+  ClientLogQuery,
   Node,
   Subscription,
   UserSubscription,
+  QuotaChange,
+  QuotaSimulationReport,
   LatestVersionInfo,
+  RestartStatus,
+  RestartResult,
   BatchUpdateResult,
+  ListParams,
+  UpdateRollout,
+  UpdateRolloutDetail,
+  ConfigRevision,
+  ApplyTlsRequest,
+  PendingTlsApply,
+  ConfigChange,
+  Tenant,
+  TenantWithUsage,
+  BlocklistRule,
+  TemporaryTunnel,
+  Profile,
+  ProbeKind,
+  ProbeResult,
+  NodeCloneReport,
+  ImpersonateResponse,
+  MitigationEvent,
+  AlertRule,
+  AlertRuleRequest,
+  AlertEvent,
+  MaintenanceWindow,
+  MaintenanceWindowRequest,
+  FailoverGroup,
+  FailoverGroupRequest,
+  PeriodAvailability,
+  PortReservation,
+  CreatePortReservationRequest,
+  Visitor,
+  CreateVisitorRequest,
 } from './types';
 
 // ============ 认证服务 ============
@@ -33,12 +75,57 @@ export const authService = {
     const response = await api.get<ApiResponse<{ enabled: boolean }>>('/auth/register-status');
     return response.data;
   },
+
+  async getProfile(): Promise<ApiResponse<Profile>> {
+    const response = await api.get<ApiResponse<Profile>>('/auth/me/profile');
+    return response.data;
+  },
+
+  async updateProfile(data: { displayName?: string | null; email?: string | null }): Promise<ApiResponse<string>> {
+    const response = await api.put<ApiResponse<string>>('/auth/me/profile', data);
+    return response.data;
+  },
+
+  async changePassword(data: { currentPassword: string; newPassword: string }): Promise<ApiResponse<string>> {
+    const response = await api.put<ApiResponse<string>>('/auth/me/password', data);
+    return response.data;
+  },
+
+  async reauth(password: string): Promise<ApiResponse<{ expiresInSecs: number }>> {
+    const response = await api.post<ApiResponse<{ expiresInSecs: number }>>('/auth/reauth', { password });
+    return response.data;
+  },
+
+  async resendEmailVerification(): Promise<ApiResponse<string>> {
+    const response = await api.post<ApiResponse<string>>('/auth/me/email/verify');
+    return response.data;
+  },
+
+  async verifyEmail(token: string): Promise<ApiResponse<string>> {
+    const response = await api.post<ApiResponse<string>>('/auth/verify-email', { token });
+    return response.data;
+  },
+
+  async requestPasswordReset(account: string): Promise<ApiResponse<string>> {
+    const response = await api.post<ApiResponse<string>>('/auth/password-reset/request', { account });
+    return response.data;
+  },
+
+  async confirmPasswordReset(data: { token: string; newPassword: string }): Promise<ApiResponse<string>> {
+    const response = await api.post<ApiResponse<string>>('/auth/password-reset/confirm', data);
+    return response.data;
+  },
+
+  async rotateClientToken(clientId: number): Promise<ApiResponse<{ token: string }>> {
+    const response = await api.post<ApiResponse<{ token: string }>>(`/auth/me/clients/${clientId}/rotate-token`);
+    return response.data;
+  },
 };
 
 // ============ 用户服务 ============
 export const userService = {
-  async getUsers(): Promise<ApiResponse<UserWithNodeCount[]>> {
-    const response = await api.get<ApiResponse<UserWithNodeCount[]>>('/users');
+  async getUsers(params?: ListParams): Promise<ApiResponse<UserWithNodeCount[]>> {
+    const response = await api.get<ApiResponse<UserWithNodeCount[]>>('/users', { params });
     return response.data;
   },
 
@@ -47,6 +134,8 @@ export const userService = {
     password?: string;
     is_admin?: boolean;
     traffic_quota_gb?: number | null;
+    tenant_id?: number | null;
+    is_tenant_admin?: boolean;
   }): Promise<ApiResponse<any>> {
     const response = await api.post<ApiResponse<any>>('/users', data);
     return response.data;
@@ -67,9 +156,15 @@ export const userService = {
       allowed_port_range?: string | null;
       max_node_count?: number | null;
       max_client_count?: number | null;
+      speed_limit?: number | null;
+      speed_limit_schedule?: string;
+      tenant_id?: number;
+      is_tenant_admin?: boolean;
+      lockVersion?: number;
     }
   ): Promise<ApiResponse<any>> {
-    const response = await api.put<ApiResponse<any>>(`/users/${id}`, data);
+    // 409 表示期间已被他人修改，按普通失败返回提示信息
+    const response = await api.put<ApiResponse<any>>(`/users/${id}`, data, { validateStatus: (status) => (status >= 200 && status < 300) || status === 409 });
     return response.data;
   },
 
@@ -100,6 +195,16 @@ export const userService = {
     return response.data;
   },
 
+  async impersonate(userId: number, data: { reason?: string; ttlMinutes?: number }): Promise<ApiResponse<ImpersonateResponse>> {
+    const response = await api.post<ApiResponse<ImpersonateResponse>>(`/users/${userId}/impersonate`, data);
+    return response.data;
+  },
+
+  async sendPasswordReset(userId: number): Promise<ApiResponse<string>> {
+    const response = await api.post<ApiResponse<string>>(`/users/${userId}/password-reset`);
+    return response.data;
+  },
+
   async getQuotaInfo(userId: number): Promise<ApiResponse<any>> {
     const response = await api.get<ApiResponse<any>>(`/users/${userId}/quota-info`);
     return response.data;
@@ -108,8 +213,8 @@ export const userService = {
 
 // ============ 客户端服务 ============
 export const clientService = {
-  async getClients(): Promise<ApiResponse<Client[]>> {
-    const response = await api.get<ApiResponse<Client[]>>('/clients');
+  async getClients(params?: ListParams): Promise<ApiResponse<Client[]>> {
+    const response = await api.get<ApiResponse<Client[]>>('/clients', { params });
     return response.data;
   },
 
@@ -118,7 +223,7 @@ export const clientService = {
     return response.data;
   },
 
-  async createClient(data: { name: string; token?: string; region?: string }): Promise<ApiResponse<Client>> {
+  async createClient(data: { name: string; token?: string; region?: string; expires_at?: string }): Promise<ApiResponse<Client>> {
     const response = await api.post<ApiResponse<Client>>('/clients', data);
     return response.data;
   },
@@ -128,8 +233,8 @@ export const clientService = {
     return response.data;
   },
 
-  async getClientLogs(id: number): Promise<ApiResponse<LogEntry[]>> {
-    const response = await api.get<ApiResponse<LogEntry[]>>(`/clients/${id}/logs`);
+  async getClientLogs(id: number, params?: ClientLogQuery): Promise<ApiResponse<LogEntry[]>> {
+    const response = await api.get<ApiResponse<LogEntry[]>>(`/clients/${id}/logs`, { params });
     return response.data;
   },
 
@@ -142,19 +247,35 @@ export const clientService = {
       traffic_quota_gb?: number | null;
       traffic_reset_cycle?: string;
       is_traffic_exceeded?: boolean;
+      expires_at?: string | null;
     }
   ): Promise<ApiResponse<Client>> {
     const response = await api.put<ApiResponse<Client>>(`/clients/${id}`, data);
     return response.data;
   },
 
-  async allocateQuota(id: number, quotaGb: number): Promise<ApiResponse<string>> {
+  async allocateQuota(id: number, quotaGb: number | null): Promise<ApiResponse<string>> {
     const response = await api.post<ApiResponse<string>>(`/clients/${id}/allocate-quota`, {
       quota_gb: quotaGb,
     });
     return response.data;
   },
 
+  async updateTargetPolicy(id: number, allowedTargets: string[]): Promise<ApiResponse<Client>> {
+    const response = await api.put<ApiResponse<Client>>(`/clients/${id}/target-policy`, { allowedTargets });
+    return response.data;
+  },
+
+  async updateLoginPolicy(id: number, duplicateLoginPolicy: DuplicateLoginPolicy): Promise<ApiResponse<Client>> {
+    const response = await api.put<ApiResponse<Client>>(`/clients/${id}/login-policy`, { duplicateLoginPolicy });
+    return response.data;
+  },
+
+  async getLoginEvents(id: number): Promise<ApiResponse<ClientLoginEvent[]>> {
+    const response = await api.get<ApiResponse<ClientLoginEvent[]>>(`/clients/${id}/login-events`);
+    return response.data;
+  },
+
   async getClientTraffic(id: number): Promise<ApiResponse<ClientTrafficInfo>> {
     const response = await api.get<ApiResponse<ClientTrafficInfo>>(`/clients/${id}/traffic`);
     return response.data;
@@ -173,8 +294,8 @@ export const clientService = {
 
 // ============ 代理服务 ============
 export const proxyService = {
-  async getProxies(): Promise<ApiResponse<Proxy[]>> {
-    const response = await api.get<ApiResponse<Proxy[]>>('/proxies');
+  async getProxies(params?: ListParams): Promise<ApiResponse<Proxy[]>> {
+    const response = await api.get<ApiResponse<Proxy[]>>('/proxies', { params });
     return response.data;
   },
 
@@ -190,7 +311,16 @@ export const proxyService = {
     localIP: string;
     localPort: number;
     remotePort: number;
+    bindIp?: string;
     nodeId?: number;
+    idleTimeout?: number;
+    schedule?: string;
+    expiresAt?: string;
+    mitigationConfig?: string;
+    localPoolSize?: number;
+    localSource?: string;
+    dnsName?: string;
+    service?: string;
   }): Promise<ApiResponse<Proxy>> {
     const response = await api.post<ApiResponse<Proxy>>('/proxies', data);
     return response.data;
@@ -204,10 +334,45 @@ export const proxyService = {
       localIP?: string;
       localPort?: number;
       remotePort?: number;
+      bindIp?: string | null;
       enabled?: boolean;
+      idleTimeout?: number | null;
+      schedule?: string | null;
+      expiresAt?: string | null;
+      mitigationConfig?: string | null;
+      localPoolSize?: number | null;
+      localSource?: string | null;
+      dnsName?: string | null;
+      service?: string | null;
+      lockVersion?: number;
     }
   ): Promise<ApiResponse<Proxy>> {
-    const response = await api.put<ApiResponse<Proxy>>(`/proxies/${id}`, data);
+    const response = await api.put<ApiResponse<Proxy>>(`/proxies/${id}`, data, { validateStatus: (status) => (status >= 200 && status < 300) || status === 409 });
+    return response.data;
+  },
+
+  async updateAnycastNodes(id: number, nodeIds: number[]): Promise<ApiResponse<Proxy>> {
+    const response = await api.put<ApiResponse<Proxy>>(`/proxies/${id}/anycast-nodes`, { nodeIds });
+    return response.data;
+  },
+
+  async getProxyEndpoints(id: number): Promise<ApiResponse<ProxyEndpoint[]>> {
+    const response = await api.get<ApiResponse<ProxyEndpoint[]>>(`/proxies/${id}/endpoints`);
+    return response.data;
+  },
+
+  async getPortProbe(id: number): Promise<ApiResponse<PortProbe | null>> {
+    const response = await api.get<ApiResponse<PortProbe | null>>(`/proxies/${id}/probe`);
+    return response.data;
+  },
+
+  async probePort(id: number): Promise<ApiResponse<PortProbe>> {
+    const response = await api.post<ApiResponse<PortProbe>>(`/proxies/${id}/probe`);
+    return response.data;
+  },
+
+  async getProxyEvents(id: number, limit?: number): Promise<ApiResponse<ProxyEvent[]>> {
+    const response = await api.get<ApiResponse<ProxyEvent[]>>(`/proxies/${id}/events`, { params: { limit } });
     return response.data;
   },
 
@@ -224,6 +389,7 @@ export const proxyService = {
     localPorts: number[];
     remotePorts: number[];
     nodeId?: number;
+    idleTimeout?: number;
   }): Promise<ApiResponse<Proxy[]>> {
     const response = await api.post<ApiResponse<Proxy[]>>('/proxies/batch', data);
     return response.data;
@@ -252,16 +418,16 @@ export const proxyService = {
 
 // ============ 流量服务 ============
 export const trafficService = {
-  async getTrafficOverview(days?: number): Promise<ApiResponse<TrafficOverview>> {
+  async getTrafficOverview(days?: number, top?: number): Promise<ApiResponse<TrafficOverview>> {
     const response = await api.get<ApiResponse<TrafficOverview>>('/traffic/overview', {
-      params: { days },
+      params: { days, top },
     });
     return response.data;
   },
 
-  async getUserTraffic(userId: number, days?: number): Promise<ApiResponse<TrafficOverview>> {
+  async getUserTraffic(userId: number, days?: number, top?: number): Promise<ApiResponse<TrafficOverview>> {
     const response = await api.get<ApiResponse<TrafficOverview>>(`/traffic/users/${userId}`, {
-      params: { days },
+      params: { days, top },
     });
     return response.data;
   },
@@ -273,6 +439,11 @@ export const dashboardService = {
     const response = await api.get<ApiResponse<DashboardStats>>(`/dashboard/stats/${userId}`);
     return response.data;
   },
+
+  async getOnlineStatus(): Promise<ApiResponse<OnlineStatus>> {
+    const response = await api.get<ApiResponse<OnlineStatus>>('/status/online');
+    return response.data;
+  },
 };
 
 // ============ 系统配置服务 ============
@@ -287,8 +458,43 @@ export const systemService = {
     return response.data;
   },
 
-  async restart(): Promise<ApiResponse<{ message: string }>> {
-    const response = await api.post('/system/restart');
+  async getConfigRevisions(params?: ListParams): Promise<ApiResponse<ConfigRevision[]>> {
+    const response = await api.get<ApiResponse<ConfigRevision[]>>('/system/configs/revisions', { params });
+    return response.data;
+  },
+
+  async rollbackConfigs(revisionId: number): Promise<ApiResponse<{ revision: ConfigRevision; changes: ConfigChange[] }>> {
+    const response = await api.post(`/system/configs/rollback/${revisionId}`);
+    return response.data;
+  },
+
+  async applyTls(data: ApplyTlsRequest): Promise<ApiResponse<{ pending: PendingTlsApply; confirmPath: string }>> {
+    const response = await api.post('/system/tls/apply', data);
+    return response.data;
+  },
+
+  async getPendingTls(): Promise<ApiResponse<PendingTlsApply | null>> {
+    const response = await api.get('/system/tls/pending');
+    return response.data;
+  },
+
+  async confirmTls(token?: string): Promise<ApiResponse<PendingTlsApply>> {
+    const response = await api.post('/system/tls/confirm', { token });
+    return response.data;
+  },
+
+  async revertTls(): Promise<ApiResponse<PendingTlsApply>> {
+    const response = await api.post('/system/tls/revert');
+    return response.data;
+  },
+
+  async getRestartStatus(): Promise<ApiResponse<RestartStatus>> {
+    const response = await api.get<ApiResponse<RestartStatus>>('/system/restart');
+    return response.data;
+  },
+
+  async restart(reason?: string): Promise<ApiResponse<RestartResult>> {
+    const response = await api.post<ApiResponse<RestartResult>>('/system/restart', { reason });
     return response.data;
   },
 
@@ -298,24 +504,19 @@ export const systemService = {
   },
 
   async getGrpcTlsStatus(): Promise<{ enabled: boolean; domain: string }> {
-    const response = await api.get('/system/configs');
-    const data = response.data as ApiResponse<{ configs: Array<{ key: string; value: any }> }>;
-    let enabled = false;
-    let domain = '';
-    if (data.success && data.data?.configs) {
-      for (const c of data.data.configs) {
-        if (c.key === 'grpc_tls_enabled') enabled = c.value === true || c.value === 'true';
-        if (c.key === 'grpc_domain') domain = String(c.value || '');
-      }
+    const response = await api.get<ApiResponse<{ enabled: boolean; domain: string }>>('/system/grpc-tls');
+    const data = response.data;
+    if (data.success && data.data) {
+      return data.data;
     }
-    return { enabled, domain };
+    return { enabled: false, domain: '' };
   },
 };
 
 // ============ 节点服务 ============
 export const nodeService = {
-  async getNodes(): Promise<ApiResponse<Node[]>> {
-    const response = await api.get<ApiResponse<Node[]>>('/nodes');
+  async getNodes(params?: ListParams): Promise<ApiResponse<Node[]>> {
+    const response = await api.get<ApiResponse<Node[]>>('/nodes', { params });
     return response.data;
   },
 
@@ -334,12 +535,16 @@ export const nodeService = {
     tunnelPort?: number;
     tunnelProtocol?: string;
     kcpConfig?: string;
+    quicConfig?: string;
     nodeType?: string;
     maxProxyCount?: number | null;
     allowedPortRange?: string | null;
     trafficQuotaGb?: number | null;
     trafficResetCycle?: string;
     speedLimit?: number | null;
+    speedLimitSchedule?: string;
+    mitigationConfig?: string;
+    firewallConfig?: string;
   }): Promise<ApiResponse<Node>> {
     const response = await api.post<ApiResponse<Node>>('/nodes', data);
     return response.data;
@@ -357,15 +562,20 @@ export const nodeService = {
       tunnelPort?: number;
       tunnelProtocol?: string;
       kcpConfig?: string;
+      quicConfig?: string;
       nodeType?: string;
       maxProxyCount?: number | null;
       allowedPortRange?: string | null;
       trafficQuotaGb?: number | null;
       trafficResetCycle?: string;
       speedLimit?: number | null;
+      speedLimitSchedule?: string;
+      mitigationConfig?: string;  // 空字符串取消默认规则
+      firewallConfig?: string;  // 空字符串取消防火墙联动，凭据为 ****** 时沿用原值
+      lockVersion?: number;
     }
   ): Promise<ApiResponse<Node>> {
-    const response = await api.put<ApiResponse<Node>>(`/nodes/${id}`, data);
+    const response = await api.put<ApiResponse<Node>>(`/nodes/${id}`, data, { validateStatus: (status) => (status >= 200 && status < 300) || status === 409 });
     return response.data;
   },
 
@@ -389,6 +599,62 @@ export const nodeService = {
     return response.data;
   },
 
+  // 实时跟随节点日志（SSE），返回停止函数；节点断开或出错时调用 onEnd
+  followNodeLogs(
+    id: number,
+    level: LogLevel | undefined,
+    onLog: (entry: LogEntry) => void,
+    onSkipped?: (count: number) => void,
+    onEnd?: (error?: unknown) => void,
+  ): () => void {
+    const controller = new AbortController();
+    const query = level ? `?level=${level}` : '';
+    const token = localStorage.getItem('token');
+    (async () => {
+      const response = await fetch(`${api.defaults.baseURL}/nodes/${id}/logs/stream${query}`, {
+        headers: token ? { Authorization: `Bearer ${token}` } : {},
+        signal: controller.signal,
+      });
+      if (!response.ok || !response.body) {
+        throw new Error(`HTTP ${response.status}`);
+      }
+      const reader = response.body.pipeThrough(new TextDecoderStream()).getReader();
+      let buffer = '';
+      for (;;) {
+        const { value, done } = await reader.read();
+        if (done) break;
+        buffer += value;
+        const events = buffer.split('\n\n');
+        buffer = events.pop() ?? '';
+        for (const raw of events) {
+          let event = 'message';
+          let data = '';
+          for (const line of raw.split('\n')) {
+            if (line.startsWith('event:')) event = line.slice(6).trim();
+            else if (line.startsWith('data:')) data += line.slice(5).trim();
+          }
+          if (event === 'log') onLog(JSON.parse(data));
+          else if (event === 'skipped') onSkipped?.(Number(data));
+        }
+      }
+      onEnd?.();
+    })().catch((error) => {
+      if (!controller.signal.aborted) onEnd?.(error);
+    });
+    return () => controller.abort();
+  },
+
+  async probe(id: number, data: {
+    kind: ProbeKind;
+    host: string;
+    port?: number;
+    count?: number;
+    timeoutMs?: number;
+  }): Promise<ApiResponse<ProbeResult>> {
+    const response = await api.post<ApiResponse<ProbeResult>>(`/nodes/${id}/probe`, data);
+    return response.data;
+  },
+
   async triggerUpdate(id: number): Promise<ApiResponse<{ success: boolean; error?: string; newVersion?: string }>> {
     const response = await api.post<ApiResponse<any>>(`/nodes/${id}/update`);
     return response.data;
@@ -398,6 +664,11 @@ export const nodeService = {
     const response = await api.post<ApiResponse<any>>('/nodes/batch-update');
     return response.data;
   },
+
+  async cloneTo(id: number, targetId: number, disableSource = true): Promise<ApiResponse<NodeCloneReport>> {
+    const response = await api.post<ApiResponse<NodeCloneReport>>(`/nodes/${id}/clone-to/${targetId}?disable_source=${disableSource}`);
+    return response.data;
+  },
 };
 
 // ============ 订阅服务 ============
@@ -499,4 +770,273 @@ export const userSubscriptionService = {
     const response = await api.delete<ApiResponse<string>>(`/user-subscriptions/${id}`);
     return response.data;
   },
+
+  async simulateQuota(userId: number, change: QuotaChange): Promise<ApiResponse<QuotaSimulationReport>> {
+    const response = await api.post<ApiResponse<QuotaSimulationReport>>(`/users/${userId}/quota-simulation`, change);
+    return response.data;
+  },
+};
+
+// ============ 软件更新发布服务 ============
+export const updateRolloutService = {
+  async getRollouts(): Promise<ApiResponse<UpdateRollout[]>> {
+    const response = await api.get<ApiResponse<UpdateRollout[]>>('/updates/rollouts');
+    return response.data;
+  },
+
+  async getRollout(id: number): Promise<ApiResponse<UpdateRolloutDetail>> {
+    const response = await api.get<ApiResponse<UpdateRolloutDetail>>(`/updates/rollouts/${id}`);
+    return response.data;
+  },
+
+  async createRollout(data: {
+    agent_type: 'node' | 'client';
+    target_version: string;
+    percentage?: number;
+    canary_ids?: number[];
+  }): Promise<ApiResponse<UpdateRollout>> {
+    const response = await api.post<ApiResponse<UpdateRollout>>('/updates/rollouts', data);
+    return response.data;
+  },
+
+  async updateRollout(
+    id: number,
+    data: { percentage?: number; status?: 'running' | 'paused' | 'cancelled' }
+  ): Promise<ApiResponse<UpdateRollout>> {
+    const response = await api.put<ApiResponse<UpdateRollout>>(`/updates/rollouts/${id}`, data);
+    return response.data;
+  },
+};
+
+// ============ 租户服务 ============
+type TenantInput = Partial<Pick<Tenant, 'name' | 'description' | 'maxUserCount' | 'maxClientCount' | 'trafficQuotaGb'>>;
+
+export const tenantService = {
+  async getTenants(): Promise<ApiResponse<TenantWithUsage[]>> {
+    const response = await api.get<ApiResponse<TenantWithUsage[]>>('/tenants');
+    return response.data;
+  },
+
+  async createTenant(data: TenantInput & { name: string }): Promise<ApiResponse<Tenant>> {
+    const response = await api.post<ApiResponse<Tenant>>('/tenants', data);
+    return response.data;
+  },
+
+  async updateTenant(id: number, data: TenantInput): Promise<ApiResponse<Tenant>> {
+    const response = await api.put<ApiResponse<Tenant>>(`/tenants/${id}`, data);
+    return response.data;
+  },
+
+  async deleteTenant(id: number): Promise<ApiResponse<null>> {
+    const response = await api.delete<ApiResponse<null>>(`/tenants/${id}`);
+    return response.data;
+  },
+};
+
+// ============ 端口黑名单服务 ============
+export const portBlocklistService = {
+  async getRules(): Promise<ApiResponse<BlocklistRule[]>> {
+    const response = await api.get<ApiResponse<BlocklistRule[]>>('/port-blocklist');
+    return response.data;
+  },
+
+  async createRule(data: Pick<BlocklistRule, 'kind' | 'pattern'> & Partial<Pick<BlocklistRule, 'nodeId' | 'tenantId' | 'description'>>): Promise<ApiResponse<BlocklistRule>> {
+    const response = await api.post<ApiResponse<BlocklistRule>>('/port-blocklist', data);
+    return response.data;
+  },
+
+  async deleteRule(id: number): Promise<ApiResponse<null>> {
+    const response = await api.delete<ApiResponse<null>>(`/port-blocklist/${id}`);
+    return response.data;
+  },
+};
+
+// ============ 端口预留服务 ============
+export const portReservationService = {
+  async getReservations(params?: { nodeId?: number }): Promise<ApiResponse<PortReservation[]>> {
+    const response = await api.get<ApiResponse<PortReservation[]>>('/port-reservations', { params });
+    return response.data;
+  },
+
+  async createReservation(data: CreatePortReservationRequest): Promise<ApiResponse<PortReservation>> {
+    const response = await api.post<ApiResponse<PortReservation>>('/port-reservations', data);
+    return response.data;
+  },
+
+  async deleteReservation(id: number): Promise<ApiResponse<null>> {
+    const response = await api.delete<ApiResponse<null>>(`/port-reservations/${id}`);
+    return response.data;
+  },
+};
+
+// ============ 访客代理服务 ============
+export const visitorService = {
+  async getVisitors(params?: { clientId?: string }): Promise<ApiResponse<Visitor[]>> {
+    const response = await api.get<ApiResponse<Visitor[]>>('/visitors', { params });
+    return response.data;
+  },
+
+  async createVisitor(data: CreateVisitorRequest): Promise<ApiResponse<Visitor>> {
+    const response = await api.post<ApiResponse<Visitor>>('/visitors', data);
+    return response.data;
+  },
+
+  async updateVisitor(id: number, data: Partial<Pick<Visitor, 'name' | 'bindIP' | 'bindPort' | 'enabled'>>): Promise<ApiResponse<Visitor>> {
+    const response = await api.put<ApiResponse<Visitor>>(`/visitors/${id}`, data);
+    return response.data;
+  },
+
+  async deleteVisitor(id: number): Promise<ApiResponse<null>> {
+    const response = await api.delete<ApiResponse<null>>(`/visitors/${id}`);
+    return response.data;
+  },
+};
+
+// ============ 来源 IP 处置服务 ============
+export const mitigationService = {
+  async getMitigations(params?: { active?: boolean; nodeId?: number }): Promise<ApiResponse<MitigationEvent[]>> {
+    const response = await api.get<ApiResponse<MitigationEvent[]>>('/mitigations', { params });
+    return response.data;
+  },
+
+  async release(id: number): Promise<ApiResponse<MitigationEvent>> {
+    const response = await api.post<ApiResponse<MitigationEvent>>(`/mitigations/${id}/release`);
+    return response.data;
+  },
+};
+
+// ============ 告警服务 ============
+export const alertService = {
+  async getRules(): Promise<ApiResponse<AlertRule[]>> {
+    const response = await api.get<ApiResponse<AlertRule[]>>('/alerts/rules');
+    return response.data;
+  },
+
+  async createRule(data: AlertRuleRequest): Promise<ApiResponse<AlertRule>> {
+    const response = await api.post<ApiResponse<AlertRule>>('/alerts/rules', data);
+    return response.data;
+  },
+
+  async updateRule(id: number, data: AlertRuleRequest): Promise<ApiResponse<AlertRule>> {
+    const response = await api.put<ApiResponse<AlertRule>>(`/alerts/rules/${id}`, data);
+    return response.data;
+  },
+
+  async deleteRule(id: number): Promise<ApiResponse<null>> {
+    const response = await api.delete<ApiResponse<null>>(`/alerts/rules/${id}`);
+    return response.data;
+  },
+
+  async getEvents(params?: { state?: 'firing' | 'resolved'; ruleId?: number }): Promise<ApiResponse<AlertEvent[]>> {
+    const response = await api.get<ApiResponse<AlertEvent[]>>('/alerts/events', { params });
+    return response.data;
+  },
+};
+
+// ============ 维护窗口服务 ============
+export const maintenanceService = {
+  async getWindows(params?: { upcoming?: boolean; scopeType?: string; scopeId?: number }): Promise<ApiResponse<MaintenanceWindow[]>> {
+    const response = await api.get<ApiResponse<MaintenanceWindow[]>>('/maintenance-windows', { params });
+    return response.data;
+  },
+
+  async createWindow(data: MaintenanceWindowRequest): Promise<ApiResponse<MaintenanceWindow>> {
+    const response = await api.post<ApiResponse<MaintenanceWindow>>('/maintenance-windows', data);
+    return response.data;
+  },
+
+  async updateWindow(id: number, data: MaintenanceWindowRequest): Promise<ApiResponse<MaintenanceWindow>> {
+    const response = await api.put<ApiResponse<MaintenanceWindow>>(`/maintenance-windows/${id}`, data);
+    return response.data;
+  },
+
+  async deleteWindow(id: number): Promise<ApiResponse<null>> {
+    const response = await api.delete<ApiResponse<null>>(`/maintenance-windows/${id}`);
+    return response.data;
+  },
+};
+
+// ============ 故障转移组服务 ============
+export const failoverService = {
+  async getGroups(): Promise<ApiResponse<FailoverGroup[]>> {
+    const response = await api.get<ApiResponse<FailoverGroup[]>>('/failover-groups');
+    return response.data;
+  },
+
+  async createGroup(data: FailoverGroupRequest): Promise<ApiResponse<FailoverGroup>> {
+    const response = await api.post<ApiResponse<FailoverGroup>>('/failover-groups', data);
+    return response.data;
+  },
+
+  async updateGroup(id: number, data: FailoverGroupRequest): Promise<ApiResponse<FailoverGroup>> {
+    const response = await api.put<ApiResponse<FailoverGroup>>(`/failover-groups/${id}`, data);
+    return response.data;
+  },
+
+  async deleteGroup(id: number): Promise<ApiResponse<null>> {
+    const response = await api.delete<ApiResponse<null>>(`/failover-groups/${id}`);
+    return response.data;
+  },
+};
+
+// ============ 可用率服务 ============
+export const availabilityService = {
+  async getAvailability(scope: 'node' | 'client' | 'proxy', id: number): Promise<ApiResponse<PeriodAvailability[]>> {
+    const response = await api.get<ApiResponse<PeriodAvailability[]>>(`/availability/${scope}/${id}`);
+    return response.data;
+  },
+};
+
+// ============ 临时隧道服务 ============
+export const temporaryTunnelService = {
+  async getTunnels(): Promise<ApiResponse<TemporaryTunnel[]>> {
+    const response = await api.get<ApiResponse<TemporaryTunnel[]>>('/temporary-tunnels');
+    return response.data;
+  },
+
+  async openTunnel(clientId: number, data: { localIP?: string; localPort: number; ttlSecs?: number; nodeId?: number; reason?: string }): Promise<ApiResponse<TemporaryTunnel>> {
+    const response = await api.post<ApiResponse<TemporaryTunnel>>(`/clients/${clientId}/temporary-tunnel`, data);
+    return response.data;
+  },
+
+  async closeTunnel(id: number): Promise<ApiResponse<TemporaryTunnel>> {
+    const response = await api.delete<ApiResponse<TemporaryTunnel>>(`/temporary-tunnels/${id}`);
+    return response.data;
+  },
+};
+
+// ============ 代理抓包服务（仅平台管理员） ============
+export const captureService = {
+  async getCaptures(proxyId?: number): Promise<ApiResponse<ProxyCapture[]>> {
+    const response = await api.get<ApiResponse<ProxyCapture[]>>('/captures', { params: { proxyId } });
+    return response.data;
+  },
+
+  async startCapture(proxyId: number, data: { durationSecs?: number; reason?: string }): Promise<ApiResponse<ProxyCapture>> {
+    const response = await api.post<ApiResponse<ProxyCapture>>(`/proxies/${proxyId}/captures`, data);
+    return response.data;
+  },
+
+  async stopCapture(id: number): Promise<ApiResponse<null>> {
+    const response = await api.post<ApiResponse<null>>(`/captures/${id}/stop`);
+    return response.data;
+  },
+
+  async downloadCapture(id: number): Promise<Blob> {
+    const response = await api.get<Blob>(`/captures/${id}/download`, { responseType: 'blob' });
+    return response.data;
+  },
+
+  async deleteCapture(id: number): Promise<ApiResponse<null>> {
+    const response = await api.delete<ApiResponse<null>>(`/captures/${id}`);
+    return response.data;
+  },
+};
+
+// ============ GraphQL 服务（Controller 需以 graphql 功能编译） ============
+export const graphqlService = {
+  async query<T>(query: string, variables?: Record<string, unknown>): Promise<{ data?: T; errors?: { message: string }[] }> {
+    const response = await api.post<{ data?: T; errors?: { message: string }[] }>('/graphql', { query, variables });
+    return response.data;
+  },
 };
@@ -3,6 +3,20 @@ export interface ApiResponse<T> {
   success: boolean;
   data?: T;
   message: string;
+  // 列表接口的总条数（分页时返回）
+  total?: number;
+}
+
+// 列表接口通用查询参数
+export interface ListParams {
+  page?: number;
+  page_size?: number;
+  // 排序字段，前缀 - 表示降序，例如 -created_at
+  sort?: string;
+  q?: string;
+  // 游标分页：上一页最后一条的 ID（仅按 ID 升序时生效）
+  after?: number;
+  [filter: string]: string | number | boolean | undefined;
 }
 
 // 用户类型
@@ -10,6 +24,9 @@ export interface User {
   id: number;
   username: string;
   is_admin: boolean;
+  tenantId: number | null;
+  isTenantAdmin: boolean;
+  lockVersion?: number;  // 乐观锁版本号，更新时原样带回
   created_at: string;
   updated_at: string;
   totalBytesSent: number;
@@ -23,8 +40,12 @@ export interface User {
   allowedPortRange: string | null;
   maxNodeCount: number | null;
   maxClientCount: number | null;
+  speedLimit?: number | null;  // 每个节点上的总带宽（字节/秒），空为不限速
+  speedLimitSchedule?: string | null;  // 带宽时间表，如 00:00-08:00=12.5M
   currentPortCount?: number;
   currentClientCount?: number;
+  // 管理员模拟该用户登录时为发起的管理员用户名
+  impersonator?: string;
 }
 
 export interface UserWithNodeCount extends User {
@@ -52,16 +73,48 @@ export interface Client {
   region: string | null;
   userId: number | null;
   version: string | null;
+  allowedTargets: string | null;
+  expiresAt: string | null;  // 到期时间，空为永不过期
+  natType: NatType | null;  // 客户端上报的 NAT 类型，未检测时为空
+  natMappedAddr: string | null;
+  natDetectedAt: string | null;
+  os: string | null;  // 客户端心跳上报的操作系统和 CPU 架构
+  arch: string | null;
+  startedAt: string | null;  // 客户端进程启动时间
+  instanceId: string | null;  // 客户端实例 ID，旧版客户端为空
+  duplicateLoginPolicy: DuplicateLoginPolicy;  // 同一 token 多个实例同时连接时的处理策略
   totalBytesSent: number;
   totalBytesReceived: number;
   trafficQuotaGb: number | null;
   trafficResetCycle: string;
   lastResetAt: string | null;
   isTrafficExceeded: boolean;
+  bytesSentPerSec?: number;  // 名下代理的当前速率之和（字节/秒），仅列表接口返回
+  bytesReceivedPerSec?: number;
+  inMaintenance?: boolean;  // 处于维护窗口中，仅列表接口返回
+  outdated?: boolean;  // 版本低于 Controller，仅列表接口返回
+  uptimeSecs?: number | null;  // 在线客户端的运行时长（秒），仅列表接口返回
   created_at: string;
   updated_at: string;
 }
 
+// 重复登录策略
+export type DuplicateLoginPolicy = 'reject_new' | 'kick_old' | 'replicas';
+
+// 重复登录审计事件
+export interface ClientLoginEvent {
+  id: number;
+  clientId: number;
+  action: 'rejected' | 'kicked' | 'replica' | 'rebound';
+  instanceId: string | null;  // 发起连接的实例
+  otherInstanceId: string | null;  // 原在线（或原绑定）的实例
+  ip: string | null;
+  createdAt: string;
+}
+
+// NAT 类型
+export type NatType = 'open' | 'full_cone' | 'port_restricted_cone' | 'symmetric' | 'udp_blocked';
+
 // 客户端流量详情
 export interface ClientTrafficInfo {
   client_id: number;
@@ -84,11 +137,30 @@ export interface Proxy {
   localIP: string;  // 后端返回驼峰命名
   localPort: number;  // 后端返回驼峰命名
   remotePort: number;  // 后端返回驼峰命名
+  bindIp: string | null;  // 节点上监听的 IP（多网卡服务器），空为节点默认的监听地址
   enabled: boolean;
   nodeId: number | null;
+  anycastNodeIds: string | null;  // 同时发布该代理的其他节点 ID（逗号分隔），各节点监听相同的远程端口
   groupId: string | null;  // 代理分组 ID，同组代理共享
+  idleTimeout: number | null;  // TCP 连接空闲超时（秒），0 不限制，空为节点默认值
+  mitigationConfig: string | null;  // 来源 IP 处置规则（MitigationRule 的 JSON），空为节点默认规则
+  bandwidthWeight: number | null;  // 节点公平分配带宽时的权重（1-100），空为 1
+  priority: 'high' | 'normal' | 'low' | null;  // 优先级（QoS），空为 normal
+  localPoolSize: number | null;  // 客户端到本地服务的预连接数（0-16），空或 0 不启用
+  localSource: string | null;  // 客户端连接本地服务使用的源 IP 或网卡，空为客户端的全局设置
+  dnsName: string | null;  // 自动发布的 DNS 记录名，如 "ssh.example.com" 或 "_minecraft._tcp.mc.example.com"
+  service: string | null;  // 访客侧服务类型（ssh、mysql 等），用于生成连接字符串，空为按本地端口推断
+  schedule: string | null;  // 启用时间表，如 "mon-fri 09:00-18:00"，空为不自动启停
+  expiresAt: string | null;  // 到期时间，到期后自动禁用，空为永不过期
+  staleAt: string | null;  // 长时间无流量被标记为闲置的时间
   totalBytesSent: number;  // 后端返回驼峰命名
   totalBytesReceived: number;  // 后端返回驼峰命名
+  bytesSentPerSec?: number;  // 当前速率（字节/秒），仅列表接口返回
+  bytesReceivedPerSec?: number;
+  inMaintenance?: boolean;  // 代理、所属客户端或节点处于维护窗口中，仅列表接口返回
+  endpoints?: ProxyEndpoint[];  // 访客连接字符串，第一项为 主机:端口，仅列表接口返回
+  portProbe?: PortProbe | null;  // 最近一次公网端口探测结果，仅列表接口返回
+  lockVersion: number;  // 乐观锁版本号，更新时原样带回
   created_at: string;
   updated_at: string;
 }
@@ -105,6 +177,8 @@ export interface ProxyGroup {
   enabled: boolean;
   totalBytesSent: number;
   totalBytesReceived: number;
+  bytesSentPerSec: number;
+  bytesReceivedPerSec: number;
 }
 
 export type ProxyDisplayRow =
@@ -175,6 +249,12 @@ export interface DashboardStats {
   };
 }
 
+// 实时在线状态（Controller 内存缓存）
+export interface OnlineStatus {
+  clientIds: number[];
+  nodeIds: number[];
+}
+
 // 节点类型
 export interface Node {
   id: number;
@@ -189,6 +269,7 @@ export interface Node {
   tunnelPort: number;
   tunnelProtocol: string;
   kcpConfig: string | null;
+  quicConfig: string | null;
   nodeType: string;
   maxProxyCount: number | null;
   allowedPortRange: string | null;
@@ -199,7 +280,17 @@ export interface Node {
   lastResetAt: string | null;
   isTrafficExceeded: boolean;
   speedLimit: number | null;
+  speedLimitSchedule: string | null;  // 带宽时间表，如 00:00-08:00=12.5M，窗口外使用 speedLimit
+  fairShare: boolean;  // 带宽饱和时按代理权重公平分配
   version: string | null;
+  tenantId: number | null;
+  natProbePort: number | null;  // NAT 探测端口，未启用时为空
+  mitigationConfig: string | null;  // 默认的来源 IP 处置规则（MitigationRule 的 JSON）
+  firewallConfig: string | null;  // 云服务商防火墙配置（JSON），凭据显示为 ******
+  capabilities: string | null;  // 节点注册时上报的能力（NodeCapabilities 的 JSON），旧版节点为空
+  drainedProxyIds: string | null;  // 排空时禁用的代理 ID（逗号分隔），不为空表示节点处于排空状态
+  inMaintenance?: boolean;  // 处于维护窗口中，仅列表接口返回
+  lockVersion: number;  // 乐观锁版本号，更新时原样带回
   created_at: string;
   updated_at: string;
 }
@@ -211,15 +302,45 @@ export interface LoginRequest {
 }
 
 // 登录响应
+export interface Profile {
+  id: number;
+  username: string;
+  displayName: string | null;
+  email: string | null;
+  emailVerified: boolean;
+  is_admin: boolean;
+  tenantId: number | null;
+  isTenantAdmin: boolean;
+  created_at: string;
+  totalBytesSent: number;
+  totalBytesReceived: number;
+  trafficQuotaGb: number | null;
+  isTrafficExceeded: boolean;
+  maxPortCount: number | null;
+  currentPortCount: number;
+  maxNodeCount: number | null;
+  currentNodeCount: number;
+  maxClientCount: number | null;
+  currentClientCount: number;
+}
+
 export interface LoginResponse {
   token: string;
   user: {
     id: number;
     username: string;
     is_admin: boolean;
+    tenant_id: number | null;
+    is_tenant_admin: boolean;
+    impersonator?: string;
   };
 }
 
+// 管理员模拟用户登录
+export interface ImpersonateResponse extends LoginResponse {
+  expiresAt: string;
+}
+
 // 日志条目
 export interface LogEntry {
   timestamp: string;
@@ -227,6 +348,43 @@ export interface LogEntry {
   message: string;
 }
 
+export type LogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+// 客户端日志查询条件（since / until 为 RFC 3339 时间）
+export interface ClientLogQuery {
+  level?: LogLevel;
+  since?: string;
+  until?: string;
+  limit?: number;
+}
+
+// 节点连通性探测
+export type ProbeKind = 'tcp' | 'ping' | 'traceroute';
+
+export interface ProbeStep {
+  seq: number;
+  success: boolean;
+  rttMs: number | null;
+  message: string;
+  done: boolean;
+}
+
+// 节点克隆结果
+export interface NodeCloneReport {
+  cloned: { sourceId: number; proxyId: number; name: string; remotePort: number; enabled: boolean }[];
+  skipped: { sourceId: number; name: string; remotePort: number; reason: string }[];
+}
+
+export interface ProbeResult {
+  nodeId: number;
+  kind: ProbeKind;
+  host: string;
+  port: number | null;
+  success: boolean;
+  summary: string;
+  steps: ProbeStep[];
+}
+
 // 最新版本信息
 export interface LatestVersionInfo {
   latestVersion: string;
@@ -235,6 +393,35 @@ export interface LatestVersionInfo {
   controllerVersion: string;
 }
 
+// 系统重启审计记录
+export interface SystemRestartRecord {
+  id: number;
+  userId: number;
+  username: string;
+  reason: string | null;
+  pendingChanges: string;
+  connectedNodes: number;
+  connectedClients: number;
+  requestedAt: string;
+  completedAt: string | null;
+}
+
+// 系统重启状态
+export interface RestartStatus {
+  bootId: string;
+  startedAt: string | null;
+  restarting: boolean;
+  pendingChanges: string[];
+  lastRestart: SystemRestartRecord | null;
+}
+
+export interface RestartResult {
+  message: string;
+  pendingChanges: string[];
+  notifiedNodes: number;
+  notifiedClients: number;
+}
+
 // 批量更新结果
 export interface BatchUpdateResult {
   id?: number;
@@ -244,6 +431,38 @@ export interface BatchUpdateResult {
   newVersion?: string;
 }
 
+// 软件更新发布计划
+export interface UpdateRollout {
+  id: number;
+  agentType: 'node' | 'client';
+  targetVersion: string;
+  percentage: number;
+  canaryIds: string | null;
+  status: 'running' | 'paused' | 'completed' | 'cancelled';
+  createdAt: string;
+  updatedAt: string;
+}
+
+// 单个节点/客户端的更新进度
+export interface AgentUpdate {
+  id: number;
+  rolloutId: number;
+  agentType: 'node' | 'client';
+  agentId: number;
+  fromVersion: string | null;
+  targetVersion: string;
+  status: 'dispatched' | 'downloading' | 'installing' | 'restarting' | 'succeeded' | 'failed';
+  message: string | null;
+  createdAt: string;
+  updatedAt: string;
+}
+
+export interface UpdateRolloutDetail {
+  rollout: UpdateRollout;
+  agents: AgentUpdate[];
+  summary: { succeeded: number; failed: number; inProgress: number };
+}
+
 // 订阅套餐类型
 export interface Subscription {
   id: number;
@@ -277,3 +496,361 @@ export interface UserSubscription {
   createdAt: string;
   updatedAt: string;
 }
+
+// 配额变更模拟
+export interface QuotaChange {
+  subscriptionId?: number;  // 将要分配的套餐
+  deactivateUserSubscriptionId?: number;  // 将要停用的用户订阅
+  trafficQuotaGb?: number;
+  maxPortCount?: number;
+  maxNodeCount?: number;
+  maxClientCount?: number;
+  allowedPortRange?: string;  // 空字符串表示不限制
+}
+
+export interface QuotaLimits {
+  trafficQuotaGb: number | null;
+  maxPortCount: number | null;
+  maxNodeCount: number | null;
+  maxClientCount: number | null;
+  allowedPortRange: string | null;
+}
+
+export interface QuotaSimulationReport {
+  userId: number;
+  username: string;
+  current: QuotaLimits;
+  projected: QuotaLimits;
+  usage: {
+    usedGb: number;
+    allocatedGb: number;
+    portCount: number;
+    nodeCount: number;
+    clientCount: number;
+  };
+  violations: {
+    kind: 'traffic' | 'trafficAllocation' | 'portRange' | 'portCount' | 'nodeCount' | 'clientCount';
+    message: string;
+    existing: boolean;  // 变更前就已超出
+  }[];
+  affectedProxies: {
+    id: number;
+    name: string;
+    clientId: string;
+    remotePort: number;
+    reasons: string[];
+  }[];
+}
+
+// 系统配置修订
+export interface ConfigChange {
+  key: string;
+  old: string;
+  new: string;
+}
+
+export interface ConfigRevision {
+  id: number;
+  description: string | null;
+  createdBy: string | null;
+  createdAt: string;
+  changes: ConfigChange[];
+}
+
+export interface ApplyTlsRequest {
+  target: 'web' | 'grpc';
+  cert_content: string;
+  key_content: string;
+  confirm_timeout_secs?: number;
+  probe_port?: number;
+}
+
+export interface PendingTlsApply {
+  token: string;
+  target: 'web' | 'grpc';
+  deadline: number;
+  probePort: number;
+}
+
+// 租户
+export interface Tenant {
+  id: number;
+  name: string;
+  description: string | null;
+  maxUserCount: number | null;
+  maxClientCount: number | null;
+  trafficQuotaGb: number | null;
+  createdAt: string;
+  updatedAt: string;
+}
+
+// 租户列表项（含用户数、节点数）
+export interface TenantWithUsage extends Tenant {
+  userCount: number;
+  nodeCount: number;
+}
+
+// 来源 IP 处置规则（单个 IP 持续超过阈值时限速或封禁）
+export interface MitigationRule {
+  maxMbps?: number | null;
+  maxConnections?: number | null;
+  sustainSecs: number;
+  action: 'throttle' | 'block';
+  throttleMbps?: number | null;  // 仅 throttle 使用
+  penaltySecs: number;
+}
+
+// 节点上报的来源 IP 处置记录
+export interface MitigationEvent {
+  id: number;
+  nodeId: number;
+  proxyId: number;
+  clientId: number;
+  sourceIp: string;
+  action: 'throttle' | 'block';
+  reason: string;
+  expiresAt: string;
+  releasedAt: string | null;
+  releasedBy: string | null;
+  createdAt: string;
+}
+
+// 告警规则
+export type AlertKind = 'node_offline' | 'client_offline' | 'user_traffic' | 'proxy_error_rate';
+export type AlertSeverity = 'info' | 'warning' | 'critical';
+
+export interface AlertRule {
+  id: number;
+  name: string;
+  kind: AlertKind;
+  threshold: number;  // user_traffic 为配额百分比，proxy_error_rate 为每分钟错误次数
+  durationSecs: number;  // 条件持续多少秒后触发
+  severity: AlertSeverity;
+  targetId: number | null;  // 为空时检查全部对象
+  enabled: boolean;
+  createdAt: string;
+  updatedAt: string;
+}
+
+export interface AlertRuleRequest {
+  name?: string;
+  kind?: AlertKind;  // 创建后不可修改
+  threshold?: number;
+  durationSecs?: number;
+  severity?: AlertSeverity;
+  targetId?: number | null;
+  enabled?: boolean;
+}
+
+// 告警事件
+export interface AlertEvent {
+  id: number;
+  ruleId: number;
+  ruleName: string;
+  kind: AlertKind;
+  severity: AlertSeverity;
+  targetType: 'node' | 'client' | 'user' | 'proxy';
+  targetId: number;
+  targetName: string;
+  value: number;
+  message: string;
+  state: 'firing' | 'resolved';
+  firedAt: string;
+  resolvedAt: string | null;
+}
+
+// 维护窗口（期间离线不告警，界面显示"维护中"）
+export interface MaintenanceWindow {
+  id: number;
+  name: string;
+  scopeType: 'node' | 'client' | 'proxy';
+  scopeId: number;
+  startsAt: string;
+  endsAt: string;
+  description: string | null;
+  createdBy: string;
+  createdAt: string;
+}
+
+export interface MaintenanceWindowRequest {
+  name?: string;
+  scopeType?: 'node' | 'client' | 'proxy';  // 创建后不可修改
+  scopeId?: number;
+  startsAt?: string;
+  endsAt?: string;
+  description?: string | null;
+}
+
+export interface FailoverGroup {
+  id: number;
+  name: string;
+  primaryNodeId: number;
+  standbyNodeIds: string;  // 逗号分隔，按优先级排列
+  offlineThresholdSecs: number;
+  failback: boolean;
+  enabled: boolean;
+  activeNodeId: number;
+  movedProxyIds: string | null;
+  failedOverAt: string | null;
+  createdAt: string;
+  updatedAt: string;
+}
+
+export interface FailoverGroupRequest {
+  name?: string;
+  primaryNodeId?: number;  // 创建后不可修改
+  standbyNodeIds?: number[];
+  offlineThresholdSecs?: number;
+  failback?: boolean;
+  enabled?: boolean;
+}
+
+// 可用率（维护窗口内的时间不计入）
+export interface PeriodAvailability {
+  period: '24h' | '7d' | '30d';
+  percent: number | null;  // 区间内没有记录时为空
+  monitoredSecs: number;
+  downtimeSecs: number;
+}
+
+// 端口预留（预留给用户或租户，其他人的代理不能使用）
+export interface PortReservation {
+  id: number;
+  nodeId: number;
+  portStart: number;
+  portEnd: number;
+  userId: number | null;
+  tenantId: number | null;
+  expiresAt: string | null;
+  description: string | null;
+  createdBy: string;
+  createdAt: string;
+}
+
+export interface CreatePortReservationRequest {
+  nodeId: number;
+  portStart: number;
+  portEnd?: number;
+  userId?: number;  // 与 tenantId 二选一
+  tenantId?: number;
+  expiresAt?: string;
+  description?: string;
+}
+
+// 节点能力（节点注册时上报）
+export interface NodeCapabilities {
+  publicIps: string[];
+  tunnelProtocols: string[];
+  portRangeStart: number | null;
+  portRangeEnd: number | null;
+  maxThroughput: number | null;  // 字节/秒
+  privilegedPorts?: boolean | null;  // 能否监听 1024 以下的端口（旧版本节点不上报）
+}
+
+// 代理公网端口探测结果
+export interface PortProbe {
+  proxyId: number;
+  nodeId: number;
+  source: string;  // "controller" 或 "node:<id>"
+  target: string;  // 探测的地址，如 "203.0.113.5:8080"
+  status: 'reachable' | 'unreachable' | 'skipped';
+  latencyMs: number | null;
+  localReachable: boolean | null;  // 外部不可达时节点本机能否连接，未检查时为空
+  message: string;
+  probedAt: string;
+}
+
+// 访客连接字符串
+export interface ProxyEndpoint {
+  label: string;  // 如 "地址"、"SSH"、"URL"
+  value: string;  // 可直接复制的命令或地址
+}
+
+export type ProxyEventKind =
+  | 'listener_started'
+  | 'listener_stopped'
+  | 'bind_failed'
+  | 'client_attached'
+  | 'client_detached'
+  | 'protocol_switched';
+
+export interface ProxyEvent {
+  timestamp: string;
+  kind: ProxyEventKind;
+  nodeId: number;
+  proxyId: number | null;  // 客户端和节点级事件为 null
+  clientId: string | null;  // 节点级事件（协议切换）为 null
+  port: number | null;
+  detail: string;
+}
+
+// 访客代理：在客户端本机监听端口，经节点转发到目标代理
+export interface Visitor {
+  id: number;
+  clientId: string;
+  name: string;
+  proxyId: number;
+  bindIP: string;
+  bindPort: number;
+  enabled: boolean;
+  createdAt: string;
+  updatedAt: string;
+}
+
+export interface CreateVisitorRequest {
+  clientId: string;
+  name: string;
+  proxyId: number;
+  bindIP?: string;  // 默认 127.0.0.1
+  bindPort: number;
+  enabled?: boolean;
+}
+
+// 代理抓包记录（兼作审计日志）
+export type ProxyCaptureStatus = 'running' | 'uploading' | 'completed' | 'failed' | 'deleted';
+
+export interface ProxyCapture {
+  id: number;
+  proxyId: number;
+  proxyName: string;
+  nodeId: number;
+  userId: number;
+  username: string;
+  reason: string | null;
+  durationSecs: number;
+  status: ProxyCaptureStatus;
+  sizeBytes: number;
+  droppedPackets: number;
+  error: string | null;
+  downloadCount: number;
+  createdAt: string;
+  completedAt: string | null;
+  expiresAt: string;  // 到期后文件自动删除
+}
+
+// 临时隧道
+export interface TemporaryTunnel {
+  id: number;
+  clientId: number;
+  nodeId: number;
+  localIP: string;
+  localPort: number;
+  remotePort: number;
+  reason: string | null;
+  createdBy: string;
+  expiresAt: string;
+  closedAt: string | null;
+  closedBy: string | null;
+  createdAt: string;
+}
+
+// 端口黑名单规则
+export interface BlocklistRule {
+  id: number;
+  kind: 'remote_port' | 'local_target';
+  pattern: string;
+  nodeId: number | null;
+  tenantId: number | null;
+  description: string | null;
+  createdAt: string;
+}
@@ -15,6 +15,11 @@ export function formatBytes(bytes: number | undefined | null): string {
   return parseFloat((bytes / Math.pow(k, i)).toFixed(2)) + ' ' + sizes[i];
 }
 
+// 格式化速率（字节/秒）
+export function formatSpeed(bytesPerSec: number | undefined | null): string {
+  return formatBytes(bytesPerSec) + '/s';
+}
+
 // 格式化日期
 export function formatDate(dateString: string): string {
   const date = new Date(dateString);
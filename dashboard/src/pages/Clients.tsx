@@ -1,7 +1,7 @@
 import { useEffect, useState } from 'react';
 import { clientService, userService, systemService } from '../lib/services';
-import type { Client, LogEntry } from '../lib/types';
-import { formatBytes, formatDate, copyToClipboard } from '../lib/utils';
+import type { Client, ClientLoginEvent, DuplicateLoginPolicy, LogEntry, NatType } from '../lib/types';
+import { formatBytes, formatSpeed, formatDate, copyToClipboard } from '../lib/utils';
 import { useToast } from '../contexts/ToastContext';
 import ConfirmDialog from '../components/ConfirmDialog';
 import { TableSkeleton } from '../components/Skeleton';
@@ -15,6 +15,29 @@ import {
   TableCell,
 } from '../components/ui/table';
 
+// NAT 类型的显示名称和样式（对称型 NAT 下 KCP / QUIC 连接容易中断，也无法 P2P 打洞）
+const NAT_TYPE_LABELS: Record<NatType, { label: string; className: string }> = {
+  open: { label: '无 NAT', className: 'bg-emerald-50 text-emerald-700' },
+  full_cone: { label: '完全锥形', className: 'bg-emerald-50 text-emerald-700' },
+  port_restricted_cone: { label: '端口受限锥形', className: 'bg-amber-50 text-amber-700' },
+  symmetric: { label: '对称型', className: 'bg-red-50 text-red-700' },
+  udp_blocked: { label: 'UDP 不通', className: 'bg-red-50 text-red-700' },
+};
+
+// 重复登录策略和审计事件的显示名称
+const LOGIN_POLICY_OPTIONS: { value: DuplicateLoginPolicy; label: string; description: string }[] = [
+  { value: 'reject_new', label: '拒绝新实例', description: '原实例在线时拒绝其他实例连接，原实例离线后新实例可接替' },
+  { value: 'kick_old', label: '踢下线原实例', description: '新实例上线，原实例断开所有隧道并停止重连，适合迁移主机' },
+  { value: 'replicas', label: '多实例同时在线', description: '所有实例同时建立隧道，节点在各实例间轮流分配访客连接' },
+];
+
+const LOGIN_EVENT_LABELS: Record<ClientLoginEvent['action'], { label: string; className: string }> = {
+  rejected: { label: '拒绝新实例', className: 'bg-red-50 text-red-700' },
+  kicked: { label: '踢下线', className: 'bg-amber-50 text-amber-700' },
+  replica: { label: '副本加入', className: 'bg-blue-50 text-blue-700' },
+  rebound: { label: '换绑实例', className: 'bg-muted text-muted-foreground' },
+};
+
 export default function Clients() {
   const { showToast } = useToast();
   const [clients, setClients] = useState<Client[]>([]);
@@ -35,6 +58,13 @@ export default function Clients() {
   const [quotaSaving, setQuotaSaving] = useState(false);
   const [userQuotaInfo, setUserQuotaInfo] = useState<any>(null);
 
+  // 重复登录策略相关状态
+  const [loginClient, setLoginClient] = useState<Client | null>(null);
+  const [loginPolicy, setLoginPolicy] = useState<DuplicateLoginPolicy>('reject_new');
+  const [loginEvents, setLoginEvents] = useState<ClientLoginEvent[]>([]);
+  const [loginEventsLoading, setLoginEventsLoading] = useState(false);
+  const [loginSaving, setLoginSaving] = useState(false);
+
   // 命令生成相关状态
   const [showCommandModal, setShowCommandModal] = useState(false);
   const [commandClient, setCommandClient] = useState<Client | null>(null);
@@ -216,6 +246,46 @@ export default function Clients() {
     }
   };
 
+  const handleShowLoginPolicy = async (client: Client) => {
+    setLoginClient(client);
+    setLoginPolicy(client.duplicateLoginPolicy);
+    setLoginEvents([]);
+    setLoginEventsLoading(true);
+    try {
+      const response = await clientService.getLoginEvents(client.id);
+      if (response.success && response.data) {
+        setLoginEvents(response.data);
+      } else {
+        showToast(response.message || '获取重复登录事件失败', 'error');
+      }
+    } catch (error) {
+      console.error('获取重复登录事件失败:', error);
+      showToast('获取重复登录事件失败', 'error');
+    } finally {
+      setLoginEventsLoading(false);
+    }
+  };
+
+  const handleSaveLoginPolicy = async () => {
+    if (!loginClient) return;
+    setLoginSaving(true);
+    try {
+      const response = await clientService.updateLoginPolicy(loginClient.id, loginPolicy);
+      if (response.success) {
+        showToast('重复登录策略已更新', 'success');
+        setLoginClient(null);
+        loadClients();
+      } else {
+        showToast(response.message || '更新失败', 'error');
+      }
+    } catch (error) {
+      console.error('更新重复登录策略失败:', error);
+      showToast('更新失败', 'error');
+    } finally {
+      setLoginSaving(false);
+    }
+  };
+
   const handleResetTrafficExceeded = (client: Client) => {
     setConfirmDialog({
       open: true,
@@ -448,6 +518,11 @@ export default function Clients() {
                         <span className={`text-sm font-medium`} style={{ color: client.is_online ? 'hsl(142 71% 45%)' : 'hsl(0 84.2% 60.2%)' }}>
                           {client.is_online ? '在线' : '离线'}
                         </span>
+                        {client.inMaintenance && (
+                          <span className="inline-flex items-center px-2 py-0.5 rounded-lg text-xs font-semibold" style={{ background: 'hsl(38 92% 50% / 0.12)', color: 'hsl(38 92% 50%)' }}>
+                            维护中
+                          </span>
+                        )}
                       </div>
                     </TableCell>
                     <TableCell className="whitespace-nowrap">
@@ -461,6 +536,14 @@ export default function Clients() {
                       ) : (
                         <span className="text-xs text-muted-foreground">-</span>
                       )}
+                      {client.natType && NAT_TYPE_LABELS[client.natType] && (
+                        <span
+                          className={`ml-1.5 inline-flex items-center px-2 py-0.5 text-xs font-medium rounded-lg ${NAT_TYPE_LABELS[client.natType].className}`}
+                          title={client.natMappedAddr ? `映射地址 ${client.natMappedAddr}` : undefined}
+                        >
+                          {NAT_TYPE_LABELS[client.natType].label}
+                        </span>
+                      )}
                     </TableCell>
                     <TableCell className="whitespace-nowrap">
                       <div className="flex items-center gap-3">
@@ -490,12 +573,14 @@ export default function Clients() {
                             <path strokeLinecap="round" strokeLinejoin="round" d="M4.5 10.5L12 3m0 0l7.5 7.5M12 3v18" />
                           </svg>
                           <span className="text-muted-foreground">{formatBytes(client.totalBytesSent)}</span>
+                          {!!client.bytesSentPerSec && <span className="text-primary">{formatSpeed(client.bytesSentPerSec)}</span>}
                         </div>
                         <div className="flex items-center gap-1.5 text-xs">
                           <svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" strokeWidth={2} stroke="currentColor" className="w-3.5 h-3.5" style={{ color: 'hsl(142 71% 45%)' }}>
                             <path strokeLinecap="round" strokeLinejoin="round" d="M19.5 13.5L12 21m0 0l-7.5-7.5M12 21V3" />
                           </svg>
                           <span className="text-muted-foreground">{formatBytes(client.totalBytesReceived)}</span>
+                          {!!client.bytesReceivedPerSec && <span className="text-primary">{formatSpeed(client.bytesReceivedPerSec)}</span>}
                         </div>
                       </div>
                     </TableCell>
@@ -610,6 +695,16 @@ export default function Clients() {
                           </svg>
                           配额
                         </button>
+                        <button
+                          onClick={() => handleShowLoginPolicy(client)}
+                          className="inline-flex items-center gap-1.5 px-3 py-1.5 text-xs font-medium text-primary hover:bg-accent rounded-lg transition-colors"
+                          title="同一 token 多个实例同时连接时的处理策略"
+                        >
+                          <svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" strokeWidth={1.5} stroke="currentColor" className="w-4 h-4">
+                            <path strokeLinecap="round" strokeLinejoin="round" d="M15.75 17.25v3.375c0 .621-.504 1.125-1.125 1.125h-9.75a1.125 1.125 0 01-1.125-1.125V7.875c0-.621.504-1.125 1.125-1.125H6.75a9.06 9.06 0 011.5.124m7.5 10.376h3.375c.621 0 1.125-.504 1.125-1.125V11.25c0-4.46-3.243-8.161-7.5-8.876a9.06 9.06 0 00-1.5-.124H9.375c-.621 0-1.125.504-1.125 1.125v3.5m7.5 10.375H9.375a1.125 1.125 0 01-1.125-1.125v-9.25m12 6.625v-1.875a3.375 3.375 0 00-3.375-3.375h-1.5a1.125 1.125 0 01-1.125-1.125v-1.5a3.375 3.375 0 00-3.375-3.375H9.75" />
+                          </svg>
+                          重复登录
+                        </button>
                         {client.isTrafficExceeded && (
                           <button
                             onClick={() => handleResetTrafficExceeded(client)}
@@ -932,6 +1027,95 @@ export default function Clients() {
         </div>
       )}
 
+      {/* 重复登录策略模态框 */}
+      {loginClient && (
+        <div className="fixed inset-0 bg-black/50 backdrop-blur-sm overflow-y-auto h-full w-full flex items-center justify-center z-50">
+          <div className="relative bg-card rounded-2xl shadow-2xl w-full max-w-2xl mx-4 transform transition-all">
+            <div className="p-6">
+              <div className="mb-6">
+                <h3 className="text-lg font-bold text-foreground">重复登录</h3>
+                <p className="text-sm text-muted-foreground">
+                  {loginClient.name} · 当前实例 <span className="font-mono">{loginClient.instanceId || '未知'}</span>
+                </p>
+              </div>
+
+              <div className="space-y-4">
+                <div>
+                  <label className="block text-sm font-medium text-foreground mb-1.5">同一 token 的另一个实例在原实例在线时连接</label>
+                  <select
+                    value={loginPolicy}
+                    onChange={(e) => setLoginPolicy(e.target.value as DuplicateLoginPolicy)}
+                    className="w-full px-4 py-3 border border-border rounded-xl text-foreground focus:outline-none focus:ring-2 focus:ring-primary/20 focus:border-primary transition-all bg-muted/50 hover:bg-card"
+                  >
+                    {LOGIN_POLICY_OPTIONS.map((option) => (
+                      <option key={option.value} value={option.value}>{option.label}</option>
+                    ))}
+                  </select>
+                  <p className="mt-2 text-xs text-muted-foreground">
+                    {LOGIN_POLICY_OPTIONS.find((option) => option.value === loginPolicy)?.description}。只影响之后的连接。
+                  </p>
+                </div>
+
+                <div>
+                  <p className="text-sm font-medium text-foreground mb-1.5">最近事件</p>
+                  <div className="max-h-72 overflow-y-auto border border-border rounded-xl">
+                    {loginEventsLoading ? (
+                      <p className="p-4 text-sm text-muted-foreground">加载中...</p>
+                    ) : loginEvents.length === 0 ? (
+                      <p className="p-4 text-sm text-muted-foreground">暂无重复登录事件</p>
+                    ) : (
+                      <Table>
+                        <TableHeader>
+                          <TableRow>
+                            <TableHead>时间</TableHead>
+                            <TableHead>事件</TableHead>
+                            <TableHead>实例</TableHead>
+                            <TableHead>原实例</TableHead>
+                            <TableHead>IP</TableHead>
+                          </TableRow>
+                        </TableHeader>
+                        <TableBody>
+                          {loginEvents.map((event) => (
+                            <TableRow key={event.id}>
+                              <TableCell className="text-xs whitespace-nowrap">{formatDate(event.createdAt)}</TableCell>
+                              <TableCell>
+                                <span className={`px-2 py-0.5 rounded text-xs font-medium ${LOGIN_EVENT_LABELS[event.action]?.className ?? ''}`}>
+                                  {LOGIN_EVENT_LABELS[event.action]?.label ?? event.action}
+                                </span>
+                              </TableCell>
+                              <TableCell className="font-mono text-xs" title={event.instanceId || undefined}>{event.instanceId?.slice(0, 8) || '-'}</TableCell>
+                              <TableCell className="font-mono text-xs" title={event.otherInstanceId || undefined}>{event.otherInstanceId?.slice(0, 8) || '-'}</TableCell>
+                              <TableCell className="font-mono text-xs">{event.ip || '-'}</TableCell>
+                            </TableRow>
+                          ))}
+                        </TableBody>
+                      </Table>
+                    )}
+                  </div>
+                </div>
+              </div>
+
+              <div className="mt-6 flex gap-3">
+                <button
+                  onClick={() => setLoginClient(null)}
+                  className="flex-1 px-4 py-2.5 bg-muted text-foreground font-medium rounded-xl hover:bg-accent transition-colors"
+                  disabled={loginSaving}
+                >
+                  取消
+                </button>
+                <button
+                  onClick={handleSaveLoginPolicy}
+                  disabled={loginSaving || loginPolicy === loginClient.duplicateLoginPolicy}
+                  className="flex-1 px-4 py-2.5 bg-primary text-primary-foreground font-medium rounded-xl hover:bg-primary/90 shadow-sm transition-all disabled:opacity-50"
+                >
+                  {loginSaving ? '保存中...' : '保存'}
+                </button>
+              </div>
+            </div>
+          </div>
+        </div>
+      )}
+
       {/* 启动命令教程模态框 */}
       {showCommandModal && commandClient && (
         <div className="fixed inset-0 bg-black/50 backdrop-blur-sm overflow-y-auto h-full w-full flex items-center justify-center z-50">
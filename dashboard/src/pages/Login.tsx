@@ -71,6 +71,8 @@ export default function Login() {
           id: user.id,
           username: user.username,
           is_admin: user.is_admin,
+          tenantId: user.tenant_id,
+          isTenantAdmin: user.is_tenant_admin,
           created_at: '',
           updated_at: '',
           totalBytesSent: 0,
@@ -293,6 +295,9 @@ export default function Login() {
               <label htmlFor="remember" className="text-xs text-white/30 cursor-pointer select-none">
                 记住用户名
               </label>
+              <Link to="/reset-password" className="ml-auto text-xs text-white/30 hover:text-cyan-400 transition-colors">
+                忘记密码？
+              </Link>
             </div>
 
             {/* 登录按钮 */}
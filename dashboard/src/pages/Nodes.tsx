@@ -1,7 +1,7 @@
 import { useEffect, useState } from 'react';
 import { nodeService, systemService } from '../lib/services';
-import type { Node } from '../lib/types';
-import { formatDate } from '../lib/utils';
+import type { Node, NodeCapabilities } from '../lib/types';
+import { formatDate, formatSpeed } from '../lib/utils';
 import { useToast } from '../contexts/ToastContext';
 import ConfirmDialog from '../components/ConfirmDialog';
 import { TableSkeleton } from '../components/Skeleton';
@@ -15,6 +15,24 @@ import {
   TableCell,
 } from '../components/ui/table';
 
+// 节点上报能力的摘要，作为公网 IP 的悬停提示
+function capabilitiesSummary(node: Node): string | undefined {
+  if (!node.capabilities) return undefined;
+  try {
+    const caps = JSON.parse(node.capabilities) as NodeCapabilities;
+    const lines = [
+      `公网 IP: ${caps.publicIps.length > 0 ? caps.publicIps.join(', ') : '未探测到'}`,
+      `隧道协议: ${caps.tunnelProtocols.join(', ').toUpperCase()}`,
+      `可用端口: ${caps.portRangeStart ?? 1}-${caps.portRangeEnd ?? 65535}`,
+    ];
+    if (caps.privilegedPorts === false) lines.push('低端口: 无权限监听 1024 以下的端口');
+    if (caps.maxThroughput) lines.push(`吞吐量: ${formatSpeed(caps.maxThroughput)}`);
+    return lines.join('\n');
+  } catch {
+    return undefined;
+  }
+}
+
 export default function Nodes() {
   const { showToast } = useToast();
   const [nodes, setNodes] = useState<Node[]>([]);
@@ -150,6 +168,7 @@ export default function Nodes() {
         trafficQuotaGb: formData.trafficQuotaGb ? parseFloat(formData.trafficQuotaGb) : null,
         trafficResetCycle: formData.trafficResetCycle || 'none',
         speedLimit: formData.speedLimit ? Math.round(parseFloat(formData.speedLimit) * 1024 * 1024) : null,
+        lockVersion: editingNode.lockVersion,
       });
       if (response.success) {
         showToast('节点更新成功', 'success');
@@ -597,7 +616,7 @@ export default function Nodes() {
                     </TableCell>
                     <TableCell className="whitespace-nowrap">
                       {node.publicIp ? (
-                        <span className="inline-flex items-center gap-1.5 px-2.5 py-1 text-xs font-medium rounded-lg bg-emerald-50 text-emerald-700 font-mono">
+                        <span title={capabilitiesSummary(node)} className="inline-flex items-center gap-1.5 px-2.5 py-1 text-xs font-medium rounded-lg bg-emerald-50 text-emerald-700 font-mono">
                           <svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" strokeWidth={2} stroke="currentColor" className="w-3.5 h-3.5">
                             <path strokeLinecap="round" strokeLinejoin="round" d="M12 21a9.004 9.004 0 008.716-6.747M12 21a9.004 9.004 0 01-8.716-6.747M12 21c2.485 0 4.5-4.03 4.5-9S14.485 3 12 3m0 18c-2.485 0-4.5-4.03-4.5-9S9.515 3 12 3m0 0a8.997 8.997 0 017.843 4.582M12 3a8.997 8.997 0 00-7.843 4.582m15.686 0A11.953 11.953 0 0112 10.5c-2.998 0-5.74-1.1-7.843-2.918m15.686 0A8.959 8.959 0 0121 12c0 .778-.099 1.533-.284 2.253m0 0A17.919 17.919 0 0112 16.5c-3.162 0-6.133-.815-8.716-2.247m0 0A9.015 9.015 0 013 12c0-1.605.42-3.113 1.157-4.418" />
                           </svg>
@@ -627,6 +646,11 @@ export default function Nodes() {
                         <span className="w-1.5 h-1.5 rounded-full" style={{ background: node.isOnline ? 'hsl(142 71% 45%)' : 'hsl(0 84.2% 60.2%)' }}></span>
                         {node.isOnline ? '在线' : '离线'}
                       </span>
+                      {node.inMaintenance && (
+                        <span className="ml-1.5 inline-flex items-center px-2 py-1 rounded-lg text-xs font-semibold" style={{ background: 'hsl(38 92% 50% / 0.12)', color: 'hsl(38 92% 50%)' }}>
+                          维护中
+                        </span>
+                      )}
                     </TableCell>
                     <TableCell className="whitespace-nowrap">
                       {!node.version ? (
@@ -1,7 +1,7 @@
 import { useEffect, useState, Fragment } from 'react';
 import { proxyService, clientService, nodeService, userService } from '../lib/services';
 import type { Proxy, Client, Node, ProxyGroup, ProxyDisplayRow } from '../lib/types';
-import { formatBytes } from '../lib/utils';
+import { formatBytes, formatSpeed, copyToClipboard } from '../lib/utils';
 import { useToast } from '../contexts/ToastContext';
 import ConfirmDialog from '../components/ConfirmDialog';
 import { TableSkeleton } from '../components/Skeleton';
@@ -24,6 +24,7 @@ export default function Proxies() {
   const [loading, setLoading] = useState(true);
   const [showCreateModal, setShowCreateModal] = useState(false);
   const [editingProxy, setEditingProxy] = useState<Proxy | null>(null);
+  const [probingId, setProbingId] = useState<number | null>(null);
   const [confirmDialog, setConfirmDialog] = useState<{ open: boolean; title: string; message: string; onConfirm: () => void }>({ open: false, title: '', message: '', onConfirm: () => {} });
   const [nodeSearchQuery, setNodeSearchQuery] = useState('');
   const [nodeTypeFilter, setNodeTypeFilter] = useState<'all' | 'shared' | 'dedicated'>('all');
@@ -49,6 +50,7 @@ export default function Proxies() {
   const [portParseError, setPortParseError] = useState<string>('');
   const [expandedGroups, setExpandedGroups] = useState<Set<string>>(new Set());
   const [editingGroupId, setEditingGroupId] = useState<string | null>(null);
+  const [anycastNodeIds, setAnycastNodeIds] = useState<number[]>([]);
   useEffect(() => {
     loadData();
   }, []);
@@ -220,7 +222,16 @@ export default function Proxies() {
         localPort: formData.localPort ? parseInt(formData.localPort) : undefined,
         remotePort: formData.remotePort ? parseInt(formData.remotePort) : undefined,
         enabled: formData.enabled,
+        lockVersion: editingProxy.lockVersion,
       });
+      if (response.success && anycastNodeIds.join(',') !== (editingProxy.anycastNodeIds || '')) {
+        const anycastResponse = await proxyService.updateAnycastNodes(editingProxy.id, anycastNodeIds);
+        if (!anycastResponse.success) {
+          showToast(anycastResponse.message || '设置多节点发布失败', 'error');
+          loadData();
+          return;
+        }
+      }
       if (response.success) {
         showToast('代理更新成功', 'success');
         resetForm();
@@ -248,6 +259,7 @@ export default function Proxies() {
       remotePort: proxy.remotePort.toString(),
       enabled: proxy.enabled,
     });
+    setAnycastNodeIds(proxy.anycastNodeIds ? proxy.anycastNodeIds.split(',').map(Number) : []);
     setShowCreateModal(true);
   };
 
@@ -277,10 +289,13 @@ export default function Proxies() {
     try {
       const response = await proxyService.updateProxy(proxy.id, {
         enabled: !proxy.enabled,
+        lockVersion: proxy.lockVersion,
       });
       if (response.success) {
         showToast(`代理已${proxy.enabled ? '禁用' : '启用'}`, 'success');
         loadData();
+      } else {
+        showToast(response.message || '操作失败', 'error');
       }
     } catch (error) {
       console.error('切换状态失败:', error);
@@ -299,6 +314,36 @@ export default function Proxies() {
     return node?.name || String(nodeId);
   };
 
+  // 复制访客连接命令（有服务命令时优先复制命令，否则复制地址）
+  const copyEndpoint = async (proxy: Proxy) => {
+    const endpoint = proxy.endpoints?.[1] ?? proxy.endpoints?.[0];
+    if (!endpoint) return;
+    if (await copyToClipboard(endpoint.value)) {
+      showToast(`已复制${endpoint.label}：${endpoint.value}`, 'success');
+    } else {
+      showToast('复制失败', 'error');
+    }
+  };
+
+  // 重新探测代理的公网端口
+  const handleProbePort = async (proxy: Proxy) => {
+    setProbingId(proxy.id);
+    try {
+      const response = await proxyService.probePort(proxy.id);
+      if (response.success && response.data) {
+        showToast(response.data.message, response.data.status === 'unreachable' ? 'error' : 'success');
+        loadData();
+      } else {
+        showToast(response.message || '探测失败', 'error');
+      }
+    } catch (error) {
+      console.error('端口探测失败:', error);
+      showToast('探测失败', 'error');
+    } finally {
+      setProbingId(null);
+    }
+  };
+
   const getNodeIp = (nodeId: number | null) => {
     if (!nodeId) return null;
     const node = nodes.find((n) => n.id === nodeId);
@@ -505,6 +550,8 @@ export default function Proxies() {
             enabled: sorted.every(p => p.enabled),
             totalBytesSent: sorted.reduce((sum, p) => sum + p.totalBytesSent, 0),
             totalBytesReceived: sorted.reduce((sum, p) => sum + p.totalBytesReceived, 0),
+            bytesSentPerSec: sorted.reduce((sum, p) => sum + (p.bytesSentPerSec ?? 0), 0),
+            bytesReceivedPerSec: sorted.reduce((sum, p) => sum + (p.bytesReceivedPerSec ?? 0), 0),
           },
         });
       }
@@ -717,7 +764,11 @@ export default function Proxies() {
                         </TableCell>
                         <TableCell className="whitespace-nowrap">
                           <div className="flex items-center gap-2 text-sm">
-                            <span className="px-2 py-1 bg-muted text-primary rounded-lg font-mono text-xs">
+                            <span
+                              className={`px-2 py-1 bg-muted text-primary rounded-lg font-mono text-xs${proxy.endpoints?.length ? ' cursor-pointer hover:bg-muted/70' : ''}`}
+                              title={proxy.endpoints?.map(e => `${e.label}: ${e.value}`).join('\n')}
+                              onClick={() => copyEndpoint(proxy)}
+                            >
                               {getNodeIp(proxy.nodeId) ? `${getNodeIp(proxy.nodeId)}:${proxy.remotePort}` : `:${proxy.remotePort}`}
                             </span>
                             <svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" strokeWidth={2} stroke="currentColor" className="w-4 h-4 text-muted-foreground">
@@ -738,6 +789,33 @@ export default function Proxies() {
                             <span className="w-1.5 h-1.5 rounded-full" style={{ background: proxy.enabled ? 'hsl(142 71% 45%)' : 'hsl(0 0% 60%)' }}></span>
                             {proxy.enabled ? '启用' : '禁用'}
                           </span>
+                          {proxy.inMaintenance && (
+                            <span className="ml-1.5 inline-flex items-center px-2 py-0.5 rounded-lg text-xs font-semibold" style={{ background: 'hsl(38 92% 50% / 0.12)', color: 'hsl(38 92% 50%)' }}>
+                              维护中
+                            </span>
+                          )}
+                          {(proxy.type || 'tcp').toLowerCase() === 'tcp' && proxy.enabled && (
+                            <span
+                              className="ml-1.5 inline-flex items-center px-2 py-0.5 rounded-lg text-xs font-semibold cursor-pointer hover:opacity-80"
+                              style={
+                                proxy.portProbe?.status === 'reachable'
+                                  ? { background: 'hsl(142 71% 45% / 0.12)', color: 'hsl(142 71% 45%)' }
+                                  : proxy.portProbe?.status === 'unreachable'
+                                    ? { background: 'hsl(0 84% 60% / 0.12)', color: 'hsl(0 84% 60%)' }
+                                    : { background: 'hsl(0 0% 50% / 0.1)', color: 'hsl(0 0% 45%)' }
+                              }
+                              title={`${proxy.portProbe?.message || '尚未探测公网端口'}\n点击重新探测`}
+                              onClick={() => probingId === null && handleProbePort(proxy)}
+                            >
+                              {probingId === proxy.id
+                                ? '探测中…'
+                                : proxy.portProbe?.status === 'reachable'
+                                  ? '公网可达'
+                                  : proxy.portProbe?.status === 'unreachable'
+                                    ? '公网不可达'
+                                    : '未探测'}
+                            </span>
+                          )}
                         </TableCell>
                         <TableCell className="whitespace-nowrap">
                           <div className="flex flex-col gap-1">
@@ -746,12 +824,14 @@ export default function Proxies() {
                                 <path strokeLinecap="round" strokeLinejoin="round" d="M4.5 10.5L12 3m0 0l7.5 7.5M12 3v18" />
                               </svg>
                               <span className="text-muted-foreground">{formatBytes(proxy.totalBytesSent)}</span>
+                              {!!proxy.bytesSentPerSec && <span className="text-primary">{formatSpeed(proxy.bytesSentPerSec)}</span>}
                             </div>
                             <div className="flex items-center gap-1.5 text-xs">
                               <svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" strokeWidth={2} stroke="currentColor" className="w-3.5 h-3.5" style={{ color: 'hsl(142 71% 45%)' }}>
                                 <path strokeLinecap="round" strokeLinejoin="round" d="M19.5 13.5L12 21m0 0l-7.5-7.5M12 21V3" />
                               </svg>
                               <span className="text-muted-foreground">{formatBytes(proxy.totalBytesReceived)}</span>
+                              {!!proxy.bytesReceivedPerSec && <span className="text-primary">{formatSpeed(proxy.bytesReceivedPerSec)}</span>}
                             </div>
                           </div>
                         </TableCell>
@@ -862,12 +942,14 @@ export default function Proxies() {
                                 <path strokeLinecap="round" strokeLinejoin="round" d="M4.5 10.5L12 3m0 0l7.5 7.5M12 3v18" />
                               </svg>
                               <span className="text-muted-foreground">{formatBytes(group.totalBytesSent)}</span>
+                              {!!group.bytesSentPerSec && <span className="text-primary">{formatSpeed(group.bytesSentPerSec)}</span>}
                             </div>
                             <div className="flex items-center gap-1.5 text-xs">
                               <svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" strokeWidth={2} stroke="currentColor" className="w-3.5 h-3.5" style={{ color: 'hsl(142 71% 45%)' }}>
                                 <path strokeLinecap="round" strokeLinejoin="round" d="M19.5 13.5L12 21m0 0l-7.5-7.5M12 21V3" />
                               </svg>
                               <span className="text-muted-foreground">{formatBytes(group.totalBytesReceived)}</span>
+                              {!!group.bytesReceivedPerSec && <span className="text-primary">{formatSpeed(group.bytesReceivedPerSec)}</span>}
                             </div>
                           </div>
                         </TableCell>
@@ -938,12 +1020,14 @@ export default function Proxies() {
                                   <path strokeLinecap="round" strokeLinejoin="round" d="M4.5 10.5L12 3m0 0l7.5 7.5M12 3v18" />
                                 </svg>
                                 <span className="text-muted-foreground">{formatBytes(proxy.totalBytesSent)}</span>
+                                {!!proxy.bytesSentPerSec && <span className="text-primary">{formatSpeed(proxy.bytesSentPerSec)}</span>}
                               </div>
                               <div className="flex items-center gap-1.5 text-xs">
                                 <svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" strokeWidth={2} stroke="currentColor" className="w-3 h-3" style={{ color: 'hsl(142 71% 45%)' }}>
                                   <path strokeLinecap="round" strokeLinejoin="round" d="M19.5 13.5L12 21m0 0l-7.5-7.5M12 21V3" />
                                 </svg>
                                 <span className="text-muted-foreground">{formatBytes(proxy.totalBytesReceived)}</span>
+                                {!!proxy.bytesReceivedPerSec && <span className="text-primary">{formatSpeed(proxy.bytesReceivedPerSec)}</span>}
                               </div>
                             </div>
                           </TableCell>
@@ -1440,6 +1524,27 @@ export default function Proxies() {
                     </label>
                   </div>
                 )}
+                {editingProxy && editingProxy.nodeId !== null && (
+                  <div className="p-3 bg-muted rounded-xl">
+                    <p className="text-sm text-foreground font-medium mb-1">同时发布到其他节点</p>
+                    <p className="text-xs text-muted-foreground mb-2">各节点监听相同的远程端口，端口在每个节点上都必须空闲</p>
+                    <div className="flex flex-wrap gap-2">
+                      {availableNodes.filter((node) => node.id !== editingProxy.nodeId).map((node) => (
+                        <label key={node.id} className="flex items-center gap-1.5 text-sm text-foreground">
+                          <input
+                            type="checkbox"
+                            checked={anycastNodeIds.includes(node.id)}
+                            onChange={(e) => setAnycastNodeIds(e.target.checked
+                              ? [...anycastNodeIds, node.id]
+                              : anycastNodeIds.filter((id) => id !== node.id))}
+                            className="h-4 w-4 text-primary focus:ring-primary border-border rounded"
+                          />
+                          {node.name}
+                        </label>
+                      ))}
+                    </div>
+                  </div>
+                )}
               </div>
             </div>
 
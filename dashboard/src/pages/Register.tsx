@@ -63,12 +63,6 @@ export default function Register() {
       return;
     }
 
-    if (password.length < 6) {
-      setError('密码长度不能少于 6 个字符');
-      triggerShake();
-      return;
-    }
-
     if (password !== confirmPassword) {
       setError('两次输入的密码不一致');
       triggerShake();
@@ -85,6 +79,8 @@ export default function Register() {
           id: user.id,
           username: user.username,
           is_admin: user.is_admin,
+          tenantId: user.tenant_id,
+          isTenantAdmin: user.is_tenant_admin,
           created_at: '',
           updated_at: '',
           totalBytesSent: 0,
@@ -212,7 +208,7 @@ export default function Register() {
                     value={password}
                     onChange={(e) => setPassword(e.target.value)}
                     className="h-11 pl-10 pr-12 rounded-lg bg-white/[0.06] border-white/[0.08] text-white placeholder:text-white/20 hover:bg-white/[0.09] focus-visible:bg-white/[0.09] focus-visible:ring-0 focus-visible:border-transparent transition-all"
-                    placeholder="至少 6 个字符"
+                    placeholder="至少 8 个字符，包含字母和数字等"
                     disabled={loading}
                     autoComplete="new-password"
                   />
@@ -0,0 +1,192 @@
+import { useState } from 'react';
+import { Link, useSearchParams } from 'react-router-dom';
+import { authService } from '../lib/services';
+import { User, Lock, CheckCircle2, ArrowRight, Loader2, AlertCircle } from 'lucide-react';
+import { Button } from '../components/ui/button';
+import { Input } from '../components/ui/input';
+import { Label } from '../components/ui/label';
+import { Alert, AlertDescription } from '../components/ui/alert';
+
+const inputClass =
+  'h-11 pl-10 rounded-lg bg-white/[0.06] border-white/[0.08] text-white placeholder:text-white/20 hover:bg-white/[0.09] focus-visible:bg-white/[0.09] focus-visible:ring-0 focus-visible:border-transparent transition-all';
+
+/**
+ * 找回密码：没有 token 时输入用户名或邮箱申请重置链接，
+ * 从邮件中的链接打开（带 token）时设置新密码。
+ */
+export default function ResetPassword() {
+  const [searchParams] = useSearchParams();
+  const token = searchParams.get('token');
+
+  const [account, setAccount] = useState('');
+  const [password, setPassword] = useState('');
+  const [confirmPassword, setConfirmPassword] = useState('');
+  const [error, setError] = useState('');
+  const [message, setMessage] = useState('');
+  const [loading, setLoading] = useState(false);
+
+  const handleRequest = async (e: React.FormEvent) => {
+    e.preventDefault();
+    setError('');
+    setLoading(true);
+    try {
+      const response = await authService.requestPasswordReset(account.trim());
+      if (response.success) {
+        setMessage(response.data || '重置链接已发送');
+      } else {
+        setError(response.message || '申请失败');
+      }
+    } catch (err) {
+      console.error('申请重置密码失败:', err);
+      setError('申请失败，请稍后重试');
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  const handleConfirm = async (e: React.FormEvent) => {
+    e.preventDefault();
+    setError('');
+    if (password !== confirmPassword) {
+      setError('两次输入的密码不一致');
+      return;
+    }
+    setLoading(true);
+    try {
+      const response = await authService.confirmPasswordReset({ token: token!, newPassword: password });
+      if (response.success) {
+        setMessage(response.data || '密码已重置');
+      } else {
+        setError(response.message || '重置失败');
+      }
+    } catch (err) {
+      console.error('重置密码失败:', err);
+      setError('重置失败，请稍后重试');
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  return (
+    <div className="min-h-screen flex items-center justify-center py-12 px-4 sm:px-6 lg:px-8 relative overflow-hidden login-bg-animated" style={{ background: 'linear-gradient(135deg, hsl(222 60% 8%), hsl(210 100% 14%), hsl(210 100% 22%), hsl(189 80% 18%), hsl(210 100% 14%), hsl(222 60% 8%))' }}>
+      <div className="relative w-full max-w-[400px]">
+        <div className="mb-8">
+          <h1 className="text-3xl font-bold text-white tracking-tight mb-6">OxiProxy</h1>
+          <h2 className="text-2xl font-semibold text-white/95 mb-1.5">{token ? '设置新密码' : '找回密码'}</h2>
+          <p className="text-white/40 text-sm">
+            {token ? '请输入新密码，链接只能使用一次' : '重置链接将发送到账户已验证的邮箱'}
+          </p>
+        </div>
+
+        {error && (
+          <Alert variant="destructive" className="flex items-center gap-3 mb-6 bg-red-500/10 border-red-500/30 text-red-300 login-fade-in">
+            <AlertCircle className="w-5 h-5 shrink-0" />
+            <AlertDescription>{error}</AlertDescription>
+          </Alert>
+        )}
+
+        {message ? (
+          <Alert className="flex items-center gap-3 bg-emerald-500/10 border-emerald-500/30 text-emerald-300">
+            <CheckCircle2 className="w-5 h-5 shrink-0" />
+            <AlertDescription>{message}</AlertDescription>
+          </Alert>
+        ) : token ? (
+          <form className="space-y-5" onSubmit={handleConfirm}>
+            <div className="space-y-2">
+              <Label htmlFor="password" className="text-white/50 text-xs font-medium uppercase tracking-wider">
+                新密码
+              </Label>
+              <div className="relative group login-input-glow rounded-lg">
+                <div className="absolute inset-y-0 left-0 pl-3.5 flex items-center pointer-events-none">
+                  <Lock className="w-4 h-4 text-white/25" />
+                </div>
+                <Input
+                  id="password"
+                  type="password"
+                  required
+                  value={password}
+                  onChange={(e) => setPassword(e.target.value)}
+                  className={inputClass}
+                  disabled={loading}
+                  autoComplete="new-password"
+                />
+              </div>
+            </div>
+            <div className="space-y-2">
+              <Label htmlFor="confirmPassword" className="text-white/50 text-xs font-medium uppercase tracking-wider">
+                确认密码
+              </Label>
+              <div className="relative group login-input-glow rounded-lg">
+                <div className="absolute inset-y-0 left-0 pl-3.5 flex items-center pointer-events-none">
+                  <CheckCircle2 className="w-4 h-4 text-white/25" />
+                </div>
+                <Input
+                  id="confirmPassword"
+                  type="password"
+                  required
+                  value={confirmPassword}
+                  onChange={(e) => setConfirmPassword(e.target.value)}
+                  className={inputClass}
+                  disabled={loading}
+                  autoComplete="new-password"
+                />
+              </div>
+            </div>
+            <SubmitButton loading={loading} label="重置密码" />
+          </form>
+        ) : (
+          <form className="space-y-5" onSubmit={handleRequest}>
+            <div className="space-y-2">
+              <Label htmlFor="account" className="text-white/50 text-xs font-medium uppercase tracking-wider">
+                用户名或邮箱
+              </Label>
+              <div className="relative group login-input-glow rounded-lg">
+                <div className="absolute inset-y-0 left-0 pl-3.5 flex items-center pointer-events-none">
+                  <User className="w-4 h-4 text-white/25" />
+                </div>
+                <Input
+                  id="account"
+                  type="text"
+                  required
+                  value={account}
+                  onChange={(e) => setAccount(e.target.value)}
+                  className={inputClass}
+                  disabled={loading}
+                  autoComplete="username"
+                />
+              </div>
+            </div>
+            <SubmitButton loading={loading} label="发送重置链接" />
+          </form>
+        )}
+
+        <div className="mt-8 space-y-4">
+          <div className="h-px bg-gradient-to-r from-transparent via-white/[0.08] to-transparent" />
+          <p className="text-center text-sm text-white/35">
+            <Link to="/login" className="text-cyan-400/70 hover:text-cyan-400 font-medium transition-colors">
+              返回登录
+            </Link>
+          </p>
+        </div>
+      </div>
+    </div>
+  );
+}
+
+function SubmitButton({ loading, label }: { loading: boolean; label: string }) {
+  return (
+    <Button
+      type="submit"
+      disabled={loading}
+      className="w-full h-11 text-white hover:opacity-90 shadow-lg transition-all duration-300 login-btn-glow rounded-lg font-medium"
+      style={{ background: 'linear-gradient(135deg, hsl(210 100% 45%), hsl(189 94% 43%))' }}
+    >
+      {loading ? <Loader2 className="w-5 h-5 animate-spin" /> : (
+        <>
+          <span>{label}</span>
+          <ArrowRight className="w-4 h-4" />
+        </>
+      )}
+    </Button>
+  );
+}
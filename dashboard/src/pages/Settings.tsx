@@ -1,6 +1,7 @@
 import { useState, useEffect } from 'react';
 import { useAuth } from '../contexts/AuthContext';
 import { systemService } from '../lib/services';
+import type { RestartStatus } from '../lib/types';
 import { useToast } from '../contexts/ToastContext';
 import ConfirmDialog from '../components/ConfirmDialog';
 import SkeletonBlock from '../components/Skeleton';
@@ -64,11 +65,46 @@ export default function Settings() {
     loadConfigs();
   }, []);
 
-  const restartSystem = () => {
+  // 轮询重启状态，bootId 变化说明新进程已就绪
+  const waitForRestart = (previousBootId: string) => {
+    const deadline = Date.now() + 120_000;
+    const poll = async () => {
+      try {
+        const response = await systemService.getRestartStatus();
+        if (response.success && response.data && response.data.bootId !== previousBootId) {
+          showToast('系统重启完成', 'success');
+          setTimeout(() => window.location.reload(), 1000);
+          return;
+        }
+      } catch {
+        // 重启期间接口不可用，继续等待
+      }
+      if (Date.now() > deadline) {
+        showToast('等待系统重启超时，请检查服务状态', 'error');
+        setRestarting(false);
+        return;
+      }
+      setTimeout(poll, 2000);
+    };
+    setTimeout(poll, 2000);
+  };
+
+  const restartSystem = async () => {
+    let status: RestartStatus | undefined;
+    try {
+      const response = await systemService.getRestartStatus();
+      status = response.data;
+    } catch {
+      showToast('获取重启状态失败', 'error');
+      return;
+    }
+    const pending = status?.pendingChanges ?? [];
     setConfirmDialog({
       open: true,
       title: '重启系统',
-      message: '确定要重启系统吗？重启期间服务将暂时不可用。',
+      message:
+        (pending.length > 0 ? `以下配置将在重启后生效：${pending.join('、')}。` : '') +
+        '重启前会通知已连接的节点和客户端错开重连，重启期间服务将暂时不可用。确定要重启系统吗？',
       variant: 'warning',
       confirmText: '重启',
       onConfirm: async () => {
@@ -77,9 +113,7 @@ export default function Settings() {
           const response = await systemService.restart();
           if (response.success) {
             showToast('系统正在重启，请稍候...', 'success');
-            setTimeout(() => {
-              window.location.reload();
-            }, 5000);
+            waitForRestart(status?.bootId ?? '');
           } else {
             showToast(response.message || '重启失败', 'error');
             setRestarting(false);
@@ -3,6 +3,7 @@ import { userService, nodeService } from '../lib/services';
 import type { UserWithNodeCount, Node } from '../lib/types';
 import { formatDate, formatBytes } from '../lib/utils';
 import { useToast } from '../contexts/ToastContext';
+import { useAuth } from '../contexts/AuthContext';
 import ConfirmDialog from '../components/ConfirmDialog';
 import { TableSkeleton } from '../components/Skeleton';
 import {
@@ -17,6 +18,11 @@ import {
 
 export default function Users() {
   const { showToast } = useToast();
+  const { user: currentUser, startImpersonation } = useAuth();
+  const isPlatformAdmin = !!currentUser?.is_admin && currentUser.tenantId == null;
+  const [impersonateTarget, setImpersonateTarget] = useState<UserWithNodeCount | null>(null);
+  const [impersonateReason, setImpersonateReason] = useState('');
+  const [impersonating, setImpersonating] = useState(false);
   const [users, setUsers] = useState<UserWithNodeCount[]>([]);
   const [nodes, setNodes] = useState<Node[]>([]);
   const [loading, setLoading] = useState(true);
@@ -98,6 +104,29 @@ export default function Users() {
     }
   };
 
+  const handleSendPasswordReset = (user: UserWithNodeCount) => {
+    setConfirmDialog({
+      open: true,
+      title: '发送重置链接',
+      message: `向用户 ${user.username} 已验证的邮箱发送密码重置链接？`,
+      variant: 'warning',
+      confirmText: '发送',
+      onConfirm: async () => {
+        try {
+          const response = await userService.sendPasswordReset(user.id);
+          if (response.success) {
+            showToast('重置链接已发送', 'success');
+          } else {
+            showToast(response.message || '发送失败', 'error');
+          }
+        } catch (error) {
+          console.error('发送重置链接失败:', error);
+          showToast('发送失败', 'error');
+        }
+      },
+    });
+  };
+
   const handleDeleteUser = (id: number) => {
     setConfirmDialog({
       open: true,
@@ -126,6 +155,7 @@ export default function Users() {
     try {
       const response = await userService.updateUser(user.id, {
         is_admin: !user.is_admin,
+        lockVersion: user.lockVersion,
       });
       if (response.success) {
         showToast(`用户已${user.is_admin ? '取消管理员权限' : '设为管理员'}`, 'success');
@@ -206,6 +236,7 @@ export default function Users() {
         try {
           const response = await userService.updateUser(user.id, {
             is_traffic_exceeded: false,
+            lockVersion: user.lockVersion,
           });
           if (response.success) {
             showToast('超限状态已重置', 'success');
@@ -261,6 +292,34 @@ export default function Users() {
     }
   };
 
+  const handleImpersonate = async () => {
+    if (!impersonateTarget) return;
+
+    try {
+      setImpersonating(true);
+      const response = await userService.impersonate(impersonateTarget.id, {
+        reason: impersonateReason || undefined,
+      });
+      if (response.success && response.data) {
+        const { token, user } = response.data;
+        startImpersonation(token, {
+          ...impersonateTarget,
+          tenantId: user.tenant_id,
+          isTenantAdmin: user.is_tenant_admin,
+          impersonator: user.impersonator,
+        });
+        window.location.href = '/';
+      } else {
+        showToast(response.message || '模拟登录失败', 'error');
+      }
+    } catch (error) {
+      console.error('模拟登录失败:', error);
+      showToast('模拟登录失败', 'error');
+    } finally {
+      setImpersonating(false);
+    }
+  };
+
   const handleManagePortLimit = (user: UserWithNodeCount) => {
     setSelectedUser(user);
     setPortLimitData({
@@ -282,6 +341,7 @@ export default function Users() {
         allowed_port_range: portLimitData.allowedPortRange || null,
         max_node_count: portLimitData.maxNodeCount ? parseInt(portLimitData.maxNodeCount) : null,
         max_client_count: portLimitData.maxClientCount ? parseInt(portLimitData.maxClientCount) : null,
+        lockVersion: selectedUser.lockVersion,
       });
 
       if (response.success) {
@@ -493,6 +553,30 @@ export default function Users() {
                             重置超限
                           </button>
                         )}
+                        <button
+                          onClick={() => handleSendPasswordReset(user)}
+                          className="inline-flex items-center gap-1.5 px-3 py-1.5 text-xs font-medium text-primary hover:bg-accent rounded-lg transition-colors"
+                        >
+                          <svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" strokeWidth={2} stroke="currentColor" className="w-3.5 h-3.5">
+                            <path strokeLinecap="round" strokeLinejoin="round" d="M21.75 6.75v10.5a2.25 2.25 0 01-2.25 2.25h-15a2.25 2.25 0 01-2.25-2.25V6.75m19.5 0A2.25 2.25 0 0019.5 4.5h-15a2.25 2.25 0 00-2.25 2.25m19.5 0v.243a2.25 2.25 0 01-1.07 1.916l-7.5 4.615a2.25 2.25 0 01-2.36 0L3.32 8.91a2.25 2.25 0 01-1.07-1.916V6.75" />
+                          </svg>
+                          重置密码
+                        </button>
+                        {isPlatformAdmin && !user.is_admin && (
+                          <button
+                            onClick={() => {
+                              setImpersonateTarget(user);
+                              setImpersonateReason('');
+                            }}
+                            className="inline-flex items-center gap-1.5 px-3 py-1.5 text-xs font-medium text-amber-600 hover:bg-amber-50 rounded-lg transition-colors"
+                          >
+                            <svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" strokeWidth={2} stroke="currentColor" className="w-3.5 h-3.5">
+                              <path strokeLinecap="round" strokeLinejoin="round" d="M2.036 12.322a1.012 1.012 0 010-.639C3.423 7.51 7.36 4.5 12 4.5c4.638 0 8.573 3.007 9.963 7.178.07.207.07.431 0 .639C20.577 16.49 16.64 19.5 12 19.5c-4.638 0-8.573-3.007-9.963-7.178z" />
+                              <path strokeLinecap="round" strokeLinejoin="round" d="M15 12a3 3 0 11-6 0 3 3 0 016 0z" />
+                            </svg>
+                            模拟登录
+                          </button>
+                        )}
                         <button
                           onClick={() => handleToggleAdmin(user)}
                           className="inline-flex items-center gap-1.5 px-3 py-1.5 text-xs font-medium text-purple-600 hover:bg-purple-50 rounded-lg transition-colors"
@@ -722,6 +806,48 @@ export default function Users() {
         </div>
       )}
 
+      {/* 模拟登录模态框 */}
+      {impersonateTarget && (
+        <div className="fixed inset-0 bg-black/50 backdrop-blur-sm overflow-y-auto h-full w-full flex items-center justify-center z-50">
+          <div className="relative bg-card rounded-2xl shadow-2xl w-full max-w-md mx-4 transform transition-all">
+            <div className="p-6">
+              <div className="mb-6">
+                <h3 className="text-lg font-bold text-foreground">模拟登录</h3>
+                <p className="text-sm text-muted-foreground">
+                  以 {impersonateTarget.username} 的身份只读查看管理界面，令牌 15 分钟后失效，操作记录在审计日志中
+                </p>
+              </div>
+              <div>
+                <label className="block text-sm font-medium text-foreground mb-1.5">原因</label>
+                <input
+                  type="text"
+                  value={impersonateReason}
+                  onChange={(e) => setImpersonateReason(e.target.value)}
+                  placeholder="例如：排查配额问题"
+                  className="w-full px-4 py-3 border border-border rounded-xl text-foreground placeholder-muted-foreground focus:outline-none focus:ring-2 focus:ring-amber-500/20 focus:border-amber-500 transition-all bg-muted/50 hover:bg-card"
+                />
+              </div>
+              <div className="mt-6 flex gap-3">
+                <button
+                  onClick={() => setImpersonateTarget(null)}
+                  className="flex-1 px-4 py-2.5 bg-muted text-foreground font-medium rounded-xl hover:bg-accent transition-colors"
+                  disabled={impersonating}
+                >
+                  取消
+                </button>
+                <button
+                  onClick={handleImpersonate}
+                  disabled={impersonating}
+                  className="flex-1 px-4 py-2.5 bg-gradient-to-r from-amber-500 to-orange-600 text-primary-foreground font-medium rounded-xl hover:from-amber-600 hover:to-orange-700 shadow-lg shadow-amber-500/25 transition-all disabled:opacity-50 disabled:cursor-not-allowed"
+                >
+                  {impersonating ? '处理中...' : '开始模拟'}
+                </button>
+              </div>
+            </div>
+          </div>
+        </div>
+      )}
+
       {/* 配额管理模态框 */}
       {showQuotaModal && selectedUser && (
         <div className="fixed inset-0 bg-black/50 backdrop-blur-sm overflow-y-auto h-full w-full flex items-center justify-center z-50">
@@ -0,0 +1,61 @@
+import { useEffect, useRef, useState } from 'react';
+import { Link, useSearchParams } from 'react-router-dom';
+import { authService } from '../lib/services';
+import { CheckCircle2, Loader2, AlertCircle } from 'lucide-react';
+import { Alert, AlertDescription } from '../components/ui/alert';
+
+/** 邮件中的验证链接打开后自动提交 token */
+export default function VerifyEmail() {
+  const [searchParams] = useSearchParams();
+  const token = searchParams.get('token');
+  const submitted = useRef(false);
+  const [result, setResult] = useState<{ success: boolean; message: string } | null>(null);
+
+  useEffect(() => {
+    if (submitted.current) return;
+    submitted.current = true;
+    if (!token) {
+      setResult({ success: false, message: '链接无效或已过期' });
+      return;
+    }
+    authService.verifyEmail(token)
+      .then(res => setResult({ success: res.success, message: res.success ? (res.data || '邮箱已验证') : (res.message || '验证失败') }))
+      .catch(() => setResult({ success: false, message: '验证失败，请稍后重试' }));
+  }, [token]);
+
+  return (
+    <div className="min-h-screen flex items-center justify-center py-12 px-4 sm:px-6 lg:px-8 relative overflow-hidden login-bg-animated" style={{ background: 'linear-gradient(135deg, hsl(222 60% 8%), hsl(210 100% 14%), hsl(210 100% 22%), hsl(189 80% 18%), hsl(210 100% 14%), hsl(222 60% 8%))' }}>
+      <div className="relative w-full max-w-[400px]">
+        <div className="mb-8">
+          <h1 className="text-3xl font-bold text-white tracking-tight mb-6">OxiProxy</h1>
+          <h2 className="text-2xl font-semibold text-white/95 mb-1.5">验证邮箱</h2>
+        </div>
+
+        {result === null ? (
+          <div className="flex items-center justify-center py-12">
+            <Loader2 className="w-6 h-6 animate-spin text-white/50" />
+          </div>
+        ) : result.success ? (
+          <Alert className="flex items-center gap-3 bg-emerald-500/10 border-emerald-500/30 text-emerald-300">
+            <CheckCircle2 className="w-5 h-5 shrink-0" />
+            <AlertDescription>{result.message}</AlertDescription>
+          </Alert>
+        ) : (
+          <Alert variant="destructive" className="flex items-center gap-3 bg-red-500/10 border-red-500/30 text-red-300">
+            <AlertCircle className="w-5 h-5 shrink-0" />
+            <AlertDescription>{result.message}</AlertDescription>
+          </Alert>
+        )}
+
+        <div className="mt-8 space-y-4">
+          <div className="h-px bg-gradient-to-r from-transparent via-white/[0.08] to-transparent" />
+          <p className="text-center text-sm text-white/35">
+            <Link to="/" className="text-cyan-400/70 hover:text-cyan-400 font-medium transition-colors">
+              返回首页
+            </Link>
+          </p>
+        </div>
+      </div>
+    </div>
+  );
+}
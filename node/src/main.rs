@@ -1,6 +1,7 @@
 mod server;
 
 use clap::{Parser, Subcommand};
+use common::grpc::tuning::GrpcTuning;
 use std::fs;
 
 #[cfg(unix)]
@@ -20,28 +21,45 @@ enum Command {
     /// 前台运行节点服务器
     Start {
         /// Controller gRPC 地址（例如 http://controller:3100）
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_CONTROLLER_URL")]
         controller_url: String,
 
         /// 节点密钥
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_TOKEN", hide_env_values = true)]
         token: String,
 
         /// 隧道监听端口（默认 7000）
-        #[arg(long, default_value = "7000")]
+        #[arg(long, env = "OXIPROXY_BIND_PORT", default_value = "7000")]
         bind_port: u16,
 
         /// 隧道协议：quic 或 kcp（默认 quic）
-        #[arg(long, default_value = "quic")]
+        #[arg(long, env = "OXIPROXY_PROTOCOL", default_value = "quic")]
         protocol: String,
 
         /// 自定义 CA 证书文件路径（PEM 格式，用于验证 Controller 的 TLS 证书）
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_TLS_CA_CERT")]
         tls_ca_cert: Option<String>,
 
         /// 日志目录路径（按天自动分割，不指定则输出到控制台）
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_LOG_DIR")]
         log_dir: Option<String>,
+
+        /// 健康检查 HTTP 端口（提供 /healthz 和 /readyz，不指定则不启动）
+        #[arg(long, env = "OXIPROXY_HEALTH_PORT")]
+        health_port: Option<u16>,
+
+        /// 预检模式：从 Controller 只读获取节点配置并检查所有需要的端口能否监听，不注册节点、不转发流量
+        #[arg(long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        firewall: FirewallArgs,
+
+        #[command(flatten)]
+        privileges: server::privileges::PrivilegeArgs,
+
+        #[command(flatten)]
+        grpc: GrpcTuning,
     },
 
     /// 停止运行中的守护进程
@@ -60,25 +78,38 @@ enum Command {
     /// 以守护进程模式运行
     Daemon {
         /// Controller gRPC 地址（例如 http://controller:3100）
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_CONTROLLER_URL")]
         controller_url: String,
 
         /// 节点密钥
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_TOKEN", hide_env_values = true)]
         token: String,
 
         /// 隧道监听端口（默认 7000）
-        #[arg(long, default_value = "7000")]
+        #[arg(long, env = "OXIPROXY_BIND_PORT", default_value = "7000")]
         bind_port: u16,
 
         /// 隧道协议：quic 或 kcp（默认 quic）
-        #[arg(long, default_value = "quic")]
+        #[arg(long, env = "OXIPROXY_PROTOCOL", default_value = "quic")]
         protocol: String,
 
         /// 自定义 CA 证书文件路径（PEM 格式，用于验证 Controller 的 TLS 证书）
-        #[arg(long)]
+        #[arg(long, env = "OXIPROXY_TLS_CA_CERT")]
         tls_ca_cert: Option<String>,
 
+        /// 健康检查 HTTP 端口（提供 /healthz 和 /readyz，不指定则不启动）
+        #[arg(long, env = "OXIPROXY_HEALTH_PORT")]
+        health_port: Option<u16>,
+
+        #[command(flatten)]
+        firewall: FirewallArgs,
+
+        #[command(flatten)]
+        privileges: server::privileges::PrivilegeArgs,
+
+        #[command(flatten)]
+        grpc: GrpcTuning,
+
         /// PID 文件路径
         #[cfg(unix)]
         #[arg(long, default_value = "/var/run/oxiproxy-node.pid")]
@@ -86,7 +117,7 @@ enum Command {
 
         /// 日志目录路径（按天自动分割）
         #[cfg(unix)]
-        #[arg(long, default_value = "./logs")]
+        #[arg(long, env = "OXIPROXY_LOG_DIR", default_value = "./logs")]
         log_dir: String,
 
         /// PID 文件路径
@@ -96,12 +127,65 @@ enum Command {
 
         /// 日志目录路径（按天自动分割）
         #[cfg(windows)]
-        #[arg(long, default_value = "./logs")]
+        #[arg(long, env = "OXIPROXY_LOG_DIR", default_value = "./logs")]
         log_dir: String,
     },
 
     /// 更新到最新版本
     Update,
+
+    /// 检查运行中节点的就绪状态（请求健康检查服务的 /readyz），未就绪时以非零状态退出，可用作 Docker HEALTHCHECK
+    Health {
+        /// 健康检查 HTTP 端口（与运行中节点的 --health-port 相同）
+        #[arg(long, env = "OXIPROXY_HEALTH_PORT")]
+        health_port: u16,
+
+        /// 健康检查服务地址
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+    },
+
+    /// 检查配置（Controller 地址、token、端口、CA 证书等），有问题时以非零状态退出，可用于 CI 或 systemd ExecStartPre
+    Check {
+        /// Controller gRPC 地址（例如 http://controller:3100）
+        #[arg(long, env = "OXIPROXY_CONTROLLER_URL")]
+        controller_url: String,
+
+        /// 节点密钥
+        #[arg(long, env = "OXIPROXY_TOKEN", hide_env_values = true)]
+        token: String,
+
+        /// 隧道监听端口（默认 7000）
+        #[arg(long, env = "OXIPROXY_BIND_PORT", default_value = "7000")]
+        bind_port: u16,
+
+        /// 隧道协议：quic 或 kcp（默认 quic）
+        #[arg(long, env = "OXIPROXY_PROTOCOL", default_value = "quic")]
+        protocol: String,
+
+        /// 自定义 CA 证书文件路径（PEM 格式，用于验证 Controller 的 TLS 证书）
+        #[arg(long, env = "OXIPROXY_TLS_CA_CERT")]
+        tls_ca_cert: Option<String>,
+
+        /// 健康检查 HTTP 端口
+        #[arg(long, env = "OXIPROXY_HEALTH_PORT")]
+        health_port: Option<u16>,
+
+        #[command(flatten)]
+        firewall: FirewallArgs,
+    },
+}
+
+/// 防火墙管理参数
+#[derive(clap::Args)]
+struct FirewallArgs {
+    /// 由节点管理代理端口的防火墙规则：只放行运行中代理的端口，并在内核层面丢弃被封禁的来源（仅 Linux，需要 root）
+    #[arg(long, env = "OXIPROXY_MANAGE_FIREWALL")]
+    manage_firewall: bool,
+
+    /// 防火墙后端：auto（优先 nftables）、nftables 或 iptables
+    #[arg(long, env = "OXIPROXY_FIREWALL_BACKEND", default_value = "auto")]
+    firewall_backend: String,
 }
 
 /// 加载 CA 证书文件内容
@@ -116,8 +200,74 @@ fn load_tls_ca_cert(path: &Option<String>) -> anyhow::Result<Option<Vec<u8>>> {
     }
 }
 
-async fn run_node(controller_url: String, token: String, bind_port: u16, protocol: String, tls_ca_cert: Option<Vec<u8>>, log_dir: Option<String>) -> anyhow::Result<()> {
-    server::run_server_controller_mode(controller_url, token, bind_port, protocol, tls_ca_cert, log_dir).await
+/// 请求健康检查服务，未就绪时返回错误（进程以非零状态退出）
+fn run_health_check(host: &str, port: u16) -> anyhow::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let body = runtime.block_on(common::health::check_ready(host, port))?;
+    println!("{}", body);
+    Ok(())
+}
+
+/// 启动前的配置自检，有失败项时返回错误（进程以非零状态退出）
+fn run_config_check(
+    controller_url: &str,
+    token: &str,
+    bind_port: u16,
+    protocol: &str,
+    tls_ca_cert: Option<&str>,
+    health_port: Option<u16>,
+    firewall: &FirewallArgs,
+) -> anyhow::Result<()> {
+    use common::check::{self, Report, Transport};
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(async {
+        let mut report = Report::default();
+        check::check_token(&mut report, token);
+        check::check_controller_url(&mut report, controller_url).await;
+        check::check_ca_cert(&mut report, tls_ca_cert, controller_url);
+
+        // 实际使用的隧道协议以 Controller 下发的为准
+        let transport = match protocol {
+            "quic" | "kcp" => Transport::Udp,
+            "tcp" => Transport::Tcp,
+            other => {
+                report.fail("隧道协议", format!("未知的协议: {}", other), "可选 quic、kcp 或 tcp");
+                Transport::Udp
+            }
+        };
+        check::check_port(&mut report, "隧道端口", bind_port, transport);
+        if let Some(port) = health_port {
+            check::check_port(&mut report, "健康检查端口", port, Transport::Tcp);
+        }
+
+        if firewall.manage_firewall {
+            match server::firewall::configure(true, &firewall.firewall_backend) {
+                Ok(()) if !is_root() => report.warn(
+                    "防火墙管理",
+                    "未以 root 运行，需要为节点授予 CAP_NET_ADMIN 能力，否则规则无法生效",
+                ),
+                Ok(()) => report.ok("防火墙管理", format!("后端 {}", firewall.firewall_backend)),
+                Err(e) => report.fail("防火墙管理", e, "检查 --firewall-backend，或去掉 --manage-firewall"),
+            }
+        }
+
+        report.finish()
+    })
+}
+
+#[cfg(unix)]
+fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_root() -> bool {
+    true
+}
+
+async fn run_node(controller_url: String, token: String, bind_port: u16, protocol: String, tls_ca_cert: Option<Vec<u8>>, log_dir: Option<String>, health_port: Option<u16>) -> anyhow::Result<()> {
+    server::run_server_controller_mode(controller_url, token, bind_port, protocol, tls_ca_cert, log_dir, health_port).await
 }
 
 // ─── Unix 入口 ───────────────────────────────────────────
@@ -130,6 +280,8 @@ fn main() -> anyhow::Result<()> {
         .install_default()
         .expect("Failed to install rustls crypto provider");
 
+    // 展开 OXIPROXY_*_FILE 环境变量（Kubernetes Secret 挂载），需在解析参数前执行
+    common::env::expand_file_vars();
     let cli = Cli::parse();
 
     match cli.command {
@@ -140,13 +292,27 @@ fn main() -> anyhow::Result<()> {
             protocol,
             tls_ca_cert,
             log_dir,
+            health_port,
+            dry_run,
+            firewall,
+            privileges,
+            grpc,
         } => {
+            common::grpc::tuning::set(grpc);
             let ca_cert = load_tls_ca_cert(&tls_ca_cert)?;
+            if dry_run {
+                let runtime = tokio::runtime::Runtime::new()?;
+                runtime.block_on(server::dry_run::run(&controller_url, &token, bind_port, &protocol, ca_cert.as_deref(), health_port))?;
+                return Ok(());
+            }
+            server::firewall::configure(firewall.manage_firewall, &firewall.firewall_backend)?;
             if let Some(ref dir) = log_dir {
                 fs::create_dir_all(dir).expect("无法创建日志目录");
             }
+            // 降权需在创建 runtime 之前进行：能力是线程属性，worker 线程从主线程继承
+            server::privileges::drop_privileges(&privileges, firewall.manage_firewall, log_dir.as_deref().as_slice())?;
             let runtime = tokio::runtime::Runtime::new()?;
-            runtime.block_on(run_node(controller_url, token, bind_port, protocol, ca_cert, log_dir))?;
+            runtime.block_on(run_node(controller_url, token, bind_port, protocol, ca_cert, log_dir, health_port))?;
         }
 
         Command::Stop { pid_file } => {
@@ -159,9 +325,16 @@ fn main() -> anyhow::Result<()> {
             bind_port,
             protocol,
             tls_ca_cert,
+            health_port,
             pid_file,
             log_dir,
+            firewall,
+            privileges,
+            grpc,
         } => {
+            server::firewall::configure(firewall.manage_firewall, &firewall.firewall_backend)?;
+            common::grpc::tuning::set(grpc);
+
             // 确保日志目录存在
             fs::create_dir_all(&log_dir).expect("无法创建日志目录");
 
@@ -190,13 +363,30 @@ fn main() -> anyhow::Result<()> {
 
             // fork 完成后再创建 tokio runtime，确保 epoll fd 和线程池状态正确
             let ca_cert = load_tls_ca_cert(&tls_ca_cert)?;
+            server::privileges::drop_privileges(&privileges, firewall.manage_firewall, &[log_dir.as_str()])?;
             let runtime = tokio::runtime::Runtime::new()?;
-            runtime.block_on(run_node(controller_url, token, bind_port, protocol, ca_cert, Some(log_dir)))?;
+            runtime.block_on(run_node(controller_url, token, bind_port, protocol, ca_cert, Some(log_dir), health_port))?;
         }
 
         Command::Update => {
             update_binary()?;
         }
+
+        Command::Health { health_port, host } => {
+            run_health_check(&host, health_port)?;
+        }
+
+        Command::Check {
+            controller_url,
+            token,
+            bind_port,
+            protocol,
+            tls_ca_cert,
+            health_port,
+            firewall,
+        } => {
+            run_config_check(&controller_url, &token, bind_port, &protocol, tls_ca_cert.as_deref(), health_port, &firewall)?;
+        }
     }
 
     Ok(())
@@ -235,6 +425,8 @@ fn main() -> anyhow::Result<()> {
         .install_default()
         .expect("Failed to install rustls crypto provider");
 
+    // 展开 OXIPROXY_*_FILE 环境变量（Kubernetes Secret 挂载），需在解析参数前执行
+    common::env::expand_file_vars();
     let cli = Cli::parse();
 
     match cli.command {
@@ -245,13 +437,24 @@ fn main() -> anyhow::Result<()> {
             protocol,
             tls_ca_cert,
             log_dir,
+            health_port,
+            dry_run,
+            firewall,
+            privileges,
+            grpc,
         } => {
+            common::grpc::tuning::set(grpc);
             let ca_cert = load_tls_ca_cert(&tls_ca_cert)?;
+            server::privileges::drop_privileges(&privileges, firewall.manage_firewall, &[])?;
+            let runtime = tokio::runtime::Runtime::new()?;
+            if dry_run {
+                return runtime.block_on(server::dry_run::run(&controller_url, &token, bind_port, &protocol, ca_cert.as_deref(), health_port));
+            }
+            server::firewall::configure(firewall.manage_firewall, &firewall.firewall_backend)?;
             if let Some(ref dir) = log_dir {
                 fs::create_dir_all(dir).expect("无法创建日志目录");
             }
-            let runtime = tokio::runtime::Runtime::new()?;
-            runtime.block_on(async { run_node(controller_url, token, bind_port, protocol, ca_cert, log_dir).await })
+            runtime.block_on(async { run_node(controller_url, token, bind_port, protocol, ca_cert, log_dir, health_port).await })
         }
 
         Command::Stop { pid_file } => stop_daemon_windows(&pid_file),
@@ -262,29 +465,52 @@ fn main() -> anyhow::Result<()> {
             bind_port,
             protocol,
             tls_ca_cert,
+            health_port,
             pid_file,
             log_dir,
-        } => start_daemon_windows(
+            firewall,
+            privileges,
+            grpc,
+        } => server::firewall::configure(firewall.manage_firewall, &firewall.firewall_backend)
+            .and_then(|_| server::privileges::drop_privileges(&privileges, false, &[]))
+            .and_then(|_| start_daemon_windows(
             &controller_url,
             &token,
             bind_port,
             &protocol,
             &tls_ca_cert,
+            health_port,
+            &grpc,
             &pid_file,
             &log_dir,
-        ),
+        )),
 
         Command::Update => update_binary(),
+
+        Command::Health { health_port, host } => run_health_check(&host, health_port),
+
+        Command::Check {
+            controller_url,
+            token,
+            bind_port,
+            protocol,
+            tls_ca_cert,
+            health_port,
+            firewall,
+        } => run_config_check(&controller_url, &token, bind_port, &protocol, tls_ca_cert.as_deref(), health_port, &firewall),
     }
 }
 
 #[cfg(windows)]
+#[allow(clippy::too_many_arguments)]
 fn start_daemon_windows(
     controller_url: &str,
     token: &str,
     bind_port: u16,
     protocol: &str,
     tls_ca_cert: &Option<String>,
+    health_port: Option<u16>,
+    grpc: &GrpcTuning,
     pid_file: &str,
     log_dir: &str,
 ) -> anyhow::Result<()> {
@@ -322,6 +548,12 @@ fn start_daemon_windows(
         args.push(ca_path.to_string());
     }
 
+    if let Some(port) = health_port {
+        args.push("--health-port".to_string());
+        args.push(port.to_string());
+    }
+    args.extend(grpc.to_args());
+
     let child = std::process::Command::new(&exe)
         .args(&args)
         .stdout(stdout)
@@ -391,6 +623,7 @@ fn update_binary() -> anyhow::Result<()> {
         .bin_name("node")
         .identifier("node")
         .bin_path_in_archive("{bin}{bin_ext}")
+        .verifying_keys(common::update::verifying_keys()?)
         .show_download_progress(true)
         .current_version(env!("CARGO_PKG_VERSION"))
         .no_confirm(true)
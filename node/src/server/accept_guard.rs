@@ -0,0 +1,193 @@
+//! 代理监听器接入保护
+//!
+//! 单个暴露端口遭到连接洪泛时，每个新连接都会生成一个任务并等待打开隧道流（客户端离线时还要等待重连），
+//! 大量这样的任务会挤占所有代理共享的 tokio 运行时。每个 TCP 监听器因此有两道限制：
+//!
+//! - 接入速率：按 GCRA 限制每秒接受的连接数（突发量与速率相同），超出的连接立即关闭；
+//! - 待处理连接上限：已接受但隧道流尚未打开的连接数达到上限时，新连接立即关闭。
+//!
+//! 速率由 `OXIPROXY_LISTENER_ACCEPT_RATE`（每秒连接数，默认 200，0 表示不限速）、
+//! 上限由 `OXIPROXY_LISTENER_MAX_PENDING`（默认 128，0 表示不限制）控制。
+//! 被拒绝的连接数计入节点状态，并按监听器每 10 秒最多汇总一条警告日志。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::warn;
+
+/// 默认每秒接受的连接数
+const DEFAULT_ACCEPT_RATE: u32 = 200;
+/// 默认待处理连接上限
+const DEFAULT_MAX_PENDING: usize = 128;
+/// 同一监听器拒绝连接时汇总日志的间隔
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 节点上所有监听器被拒绝的连接累计数
+#[derive(Default)]
+pub struct AcceptStats {
+    rate_limited: AtomicU64,
+    pending_rejected: AtomicU64,
+}
+
+impl AcceptStats {
+    /// 因超过接入速率被关闭的连接数
+    pub fn rate_limited(&self) -> u64 {
+        self.rate_limited.load(Ordering::Relaxed)
+    }
+
+    /// 因待处理连接达到上限被关闭的连接数
+    pub fn pending_rejected(&self) -> u64 {
+        self.pending_rejected.load(Ordering::Relaxed)
+    }
+}
+
+pub fn stats() -> &'static AcceptStats {
+    static STATS: OnceLock<AcceptStats> = OnceLock::new();
+    STATS.get_or_init(AcceptStats::default)
+}
+
+/// 按 GCRA 计算的接入速率
+struct RateGate {
+    /// 相邻两个连接的间隔
+    interval: Duration,
+    /// 允许的突发量对应的时间
+    tolerance: Duration,
+    /// 理论上下一个连接的到达时间
+    tat: Option<Instant>,
+}
+
+impl RateGate {
+    fn new(rate: u32) -> Self {
+        let interval = Duration::from_secs(1) / rate.max(1);
+        Self { interval, tolerance: interval * rate.max(1), tat: None }
+    }
+
+    /// `now` 到达的连接是否在速率之内（超出的连接不占用额度）
+    fn try_pass_at(&mut self, now: Instant) -> bool {
+        let start = self.tat.map_or(now, |t| t.max(now));
+        let next = start + self.interval;
+        if next.saturating_duration_since(now) > self.tolerance {
+            return false;
+        }
+        self.tat = Some(next);
+        true
+    }
+}
+
+/// 拒绝原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    RateLimited,
+    TooManyPending,
+}
+
+struct RejectLog {
+    rate_limited: u64,
+    pending_rejected: u64,
+    last: Option<Instant>,
+}
+
+/// 单个监听器的接入保护
+pub struct AcceptGuard {
+    rate: Option<Mutex<RateGate>>,
+    pending: Option<Arc<Semaphore>>,
+    log: Mutex<RejectLog>,
+}
+
+/// 待处理连接的占位，隧道流打开后（或连接结束时）释放
+pub struct PendingPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl AcceptGuard {
+    pub fn new(rate: u32, max_pending: usize) -> Self {
+        Self {
+            rate: (rate > 0).then(|| Mutex::new(RateGate::new(rate))),
+            pending: (max_pending > 0).then(|| Arc::new(Semaphore::new(max_pending))),
+            log: Mutex::new(RejectLog { rate_limited: 0, pending_rejected: 0, last: None }),
+        }
+    }
+
+    /// 按环境变量创建
+    pub fn from_env() -> Self {
+        Self::new(
+            common::env::parse::<u32>("OXIPROXY_LISTENER_ACCEPT_RATE").unwrap_or(DEFAULT_ACCEPT_RATE),
+            common::env::parse::<usize>("OXIPROXY_LISTENER_MAX_PENDING").unwrap_or(DEFAULT_MAX_PENDING),
+        )
+    }
+
+    /// 新连接是否可以接入
+    pub fn admit(&self) -> Result<PendingPermit, Rejection> {
+        self.admit_at(Instant::now())
+    }
+
+    fn admit_at(&self, now: Instant) -> Result<PendingPermit, Rejection> {
+        if let Some(rate) = &self.rate {
+            if !rate.lock().unwrap().try_pass_at(now) {
+                stats().rate_limited.fetch_add(1, Ordering::Relaxed);
+                return Err(Rejection::RateLimited);
+            }
+        }
+        match &self.pending {
+            Some(pending) => match pending.clone().try_acquire_owned() {
+                Ok(permit) => Ok(PendingPermit { _permit: Some(permit) }),
+                Err(_) => {
+                    stats().pending_rejected.fetch_add(1, Ordering::Relaxed);
+                    Err(Rejection::TooManyPending)
+                }
+            },
+            None => Ok(PendingPermit { _permit: None }),
+        }
+    }
+
+    /// 记录一次拒绝，距上次汇总超过 10 秒时输出警告
+    pub fn report(&self, proxy_name: &str, rejection: Rejection) {
+        let mut log = self.log.lock().unwrap();
+        match rejection {
+            Rejection::RateLimited => log.rate_limited += 1,
+            Rejection::TooManyPending => log.pending_rejected += 1,
+        }
+        let now = Instant::now();
+        if log.last.is_some_and(|last| now.duration_since(last) < LOG_INTERVAL) {
+            return;
+        }
+        warn!(
+            "[{}] ⚠️ 连接过多，已关闭 {} 个超过接入速率的连接、{} 个超过待处理上限的连接",
+            proxy_name, log.rate_limited, log.pending_rejected
+        );
+        log.rate_limited = 0;
+        log.pending_rejected = 0;
+        log.last = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_burst_then_rejects() {
+        let guard = AcceptGuard::new(10, 0);
+        let now = Instant::now();
+        for _ in 0..10 {
+            assert!(guard.admit_at(now).is_ok());
+        }
+        assert_eq!(guard.admit_at(now).err(), Some(Rejection::RateLimited));
+        // 被拒绝的连接不占用额度，100ms 后恢复一个
+        assert!(guard.admit_at(now + Duration::from_millis(100)).is_ok());
+        assert!(guard.admit_at(now + Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn test_pending_cap_released_on_drop() {
+        let guard = AcceptGuard::new(0, 2);
+        let now = Instant::now();
+        let a = guard.admit_at(now).unwrap();
+        let _b = guard.admit_at(now).unwrap();
+        assert_eq!(guard.admit_at(now).err(), Some(Rejection::TooManyPending));
+        drop(a);
+        assert!(guard.admit_at(now).is_ok());
+    }
+}
@@ -0,0 +1,172 @@
+//! 节点能力上报
+//!
+//! 注册时向 Controller 上报本机公网 IP、支持的隧道协议、可用端口范围和吞吐量提示，
+//! Controller 据此校验代理和节点配置，并生成访客连接地址，无需手动填写公网 IP。
+//!
+//! | 能力 | 自动探测 | 覆盖 |
+//! |------|------|------|
+//! | 公网 IP | 默认路由的出口地址（IPv4 / IPv6 各一个，仅公网地址） | `OXIPROXY_PUBLIC_IPS`（逗号分隔） |
+//! | 端口范围 | 非 root 且没有 `CAP_NET_BIND_SERVICE` 时从 `ip_unprivileged_port_start` 开始，到 65535 | `OXIPROXY_PORT_RANGE`（如 `10000-20000`，按防火墙放行的范围设置） |
+//! | 低端口权限 | 能否监听 1024 以下的端口，Controller 据此拒绝节点无权监听的代理端口 | - |
+//! | 吞吐量 | 已启用网卡的最大协商速率 | `OXIPROXY_MAX_THROUGHPUT_MBPS` |
+
+use std::net::{IpAddr, UdpSocket};
+
+use common::protocol::node_register::NodeCapabilities;
+use tracing::warn;
+
+/// 节点支持的隧道协议
+const TUNNEL_PROTOCOLS: [&str; 3] = ["quic", "kcp", "tcp"];
+
+/// 探测出口地址时“连接”的目标，UDP connect 只选路由，不发送数据
+const ROUTE_TARGETS: [&str; 2] = ["8.8.8.8:53", "[2001:4860:4860::8888]:53"];
+
+/// 收集本机能力
+pub fn collect() -> NodeCapabilities {
+    let (start, end) = port_range();
+    NodeCapabilities {
+        public_ips: public_ips(),
+        tunnel_protocols: TUNNEL_PROTOCOLS.iter().map(|p| p.to_string()).collect(),
+        port_range_start: Some(start),
+        port_range_end: Some(end),
+        max_throughput: max_throughput(),
+        privileged_ports: Some(lowest_bindable_port() < 1024),
+    }
+}
+
+fn public_ips() -> Vec<String> {
+    if let Some(list) = common::env::var("OXIPROXY_PUBLIC_IPS") {
+        return list
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .filter(|s| {
+                let valid = s.parse::<IpAddr>().is_ok();
+                if !valid {
+                    warn!("OXIPROXY_PUBLIC_IPS 中的地址无效: {}", s);
+                }
+                valid
+            })
+            .map(String::from)
+            .collect();
+    }
+    ROUTE_TARGETS
+        .iter()
+        .filter_map(|target| outbound_ip(target))
+        .filter(is_public)
+        .map(|ip| ip.to_string())
+        .collect()
+}
+
+/// 默认路由的出口地址
+fn outbound_ip(target: &str) -> Option<IpAddr> {
+    let bind = if target.starts_with('[') { "[::]:0" } else { "0.0.0.0:0" };
+    let socket = UdpSocket::bind(bind).ok()?;
+    socket.connect(target).ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+/// 是否为公网地址（NAT 后的内网地址不上报，由 Controller 按连接来源地址推断）
+fn is_public(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_documentation()
+                || v4.is_broadcast()
+                // 100.64.0.0/10 运营商级 NAT
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        // 2000::/3 全球单播
+        IpAddr::V6(v6) => v6.segments()[0] & 0xe000 == 0x2000,
+    }
+}
+
+/// 解析 `起始-结束` 格式的端口范围
+fn parse_port_range(value: &str) -> Option<(u16, u16)> {
+    let (start, end) = value.split_once('-')?;
+    let (start, end) = (start.trim().parse::<u16>().ok()?, end.trim().parse::<u16>().ok()?);
+    (start > 0 && start <= end).then_some((start, end))
+}
+
+/// `OXIPROXY_PORT_RANGE` 指定的端口范围（防火墙管理也只在该范围内关闭未使用的端口）
+pub fn configured_port_range() -> Option<(u16, u16)> {
+    let value = common::env::var("OXIPROXY_PORT_RANGE")?;
+    let range = parse_port_range(&value);
+    if range.is_none() {
+        warn!("环境变量 OXIPROXY_PORT_RANGE 的值无效: {}", value);
+    }
+    range
+}
+
+fn port_range() -> (u16, u16) {
+    configured_port_range().unwrap_or_else(|| (lowest_bindable_port(), u16::MAX))
+}
+
+/// 当前进程可以绑定的最小端口
+#[cfg(unix)]
+fn lowest_bindable_port() -> u16 {
+    if super::privileges::can_bind_privileged_ports() {
+        return 1;
+    }
+    std::fs::read_to_string("/proc/sys/net/ipv4/ip_unprivileged_port_start")
+        .ok()
+        .and_then(|s| s.trim().parse::<u16>().ok())
+        .unwrap_or(1024)
+        .max(1)
+}
+
+#[cfg(not(unix))]
+fn lowest_bindable_port() -> u16 {
+    1
+}
+
+/// 吞吐量提示（字节/秒）
+fn max_throughput() -> Option<u64> {
+    let mbps = common::env::parse::<u64>("OXIPROXY_MAX_THROUGHPUT_MBPS").or_else(link_speed_mbps)?;
+    (mbps > 0).then(|| mbps * 1_000_000 / 8)
+}
+
+/// 已启用网卡（不含回环）的最大协商速率（Mbps）
+fn link_speed_mbps() -> Option<u64> {
+    let entries = std::fs::read_dir("/sys/class/net").ok()?;
+    entries
+        .flatten()
+        .filter(|e| e.file_name() != "lo")
+        .filter(|e| {
+            std::fs::read_to_string(e.path().join("operstate")).is_ok_and(|s| s.trim() == "up")
+        })
+        // 虚拟网卡读取 speed 会失败或返回 -1
+        .filter_map(|e| std::fs::read_to_string(e.path().join("speed")).ok()?.trim().parse::<i64>().ok())
+        .filter(|&speed| speed > 0)
+        .max()
+        .map(|speed| speed as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_public() {
+        assert!(!is_public(&"203.0.113.5".parse::<IpAddr>().unwrap()));
+        assert!(is_public(&"1.1.1.1".parse().unwrap()));
+        assert!(!is_public(&"192.168.1.2".parse().unwrap()));
+        assert!(!is_public(&"100.72.0.1".parse().unwrap()));
+        assert!(is_public(&"2606:4700::1111".parse().unwrap()));
+        assert!(!is_public(&"fd00::1".parse().unwrap()));
+        assert!(!is_public(&"fe80::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_parse_port_range() {
+        assert_eq!(parse_port_range("10000-20000"), Some((10000, 20000)));
+        assert_eq!(parse_port_range(" 80 - 443 "), Some((80, 443)));
+        assert_eq!(parse_port_range("20000-10000"), None);
+        assert_eq!(parse_port_range("0-100"), None);
+        assert_eq!(parse_port_range("8080"), None);
+    }
+}
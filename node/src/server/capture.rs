@@ -0,0 +1,483 @@
+//! 按需抓包
+//!
+//! 按 Controller 的指令记录指定代理的转发数据，写成 pcapng 文件：记录的是隧道解密后、
+//! 访客与客户端之间实际转发的字节流，每个访客连接合成一条 TCP 流（访客地址 → 代理监听地址），
+//! 附带三次握手和 FIN，Wireshark 中可以直接“追踪流”。只记录抓包开始后建立的 TCP 连接。
+//!
+//! 抓包到时、文件达到大小上限或收到停止指令后结束，文件分块通过 `CaptureChunk` 上传到
+//! Controller，上传完成后删除本地临时文件。写文件来不及时丢弃数据段并计数，不阻塞转发。
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use common::grpc::oxiproxy;
+use common::grpc::oxiproxy::agent_server_message::Payload as AgentPayload;
+
+use super::grpc_client::AgentGrpcClient;
+use super::plugin::Direction;
+
+/// 写文件队列长度，积压超过时丢弃数据段
+const QUEUE_SIZE: usize = 4096;
+/// 上传分块大小
+const CHUNK_SIZE: usize = 1024 * 1024;
+/// pcapng 链路类型：裸 IP
+const LINKTYPE_RAW: u16 = 101;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_PSH: u8 = 0x08;
+const TCP_ACK: u8 = 0x10;
+
+enum Event {
+    Open { client: SocketAddr, server: SocketAddr },
+    Data(Direction, Vec<u8>),
+    Close,
+}
+
+struct Record {
+    conn: u32,
+    /// Unix 微秒时间戳
+    ts: u64,
+    event: Event,
+}
+
+struct Capture {
+    id: String,
+    tx: mpsc::Sender<Record>,
+    cancel: CancellationToken,
+    next_conn: AtomicU32,
+    dropped: AtomicU64,
+}
+
+impl Capture {
+    fn send(&self, conn: u32, event: Event) {
+        let ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_micros() as u64;
+        if self.tx.try_send(Record { conn, ts, event }).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// 进行中的抓包数，为 0 时转发路径不查表
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+fn captures() -> &'static Mutex<HashMap<i64, Arc<Capture>>> {
+    static CAPTURES: OnceLock<Mutex<HashMap<i64, Arc<Capture>>>> = OnceLock::new();
+    CAPTURES.get_or_init(Default::default)
+}
+
+/// 一个访客连接的抓包记录，连接结束（drop）时写入 FIN
+pub struct CaptureSession {
+    capture: Arc<Capture>,
+    conn: u32,
+}
+
+impl CaptureSession {
+    pub fn record(&self, direction: Direction, data: &[u8]) {
+        self.capture.send(self.conn, Event::Data(direction, data.to_vec()));
+    }
+}
+
+impl Drop for CaptureSession {
+    fn drop(&mut self) {
+        self.capture.send(self.conn, Event::Close);
+    }
+}
+
+/// 代理正在抓包时为新连接创建抓包记录
+pub fn open(proxy_id: i64, client: SocketAddr, server: SocketAddr) -> Option<CaptureSession> {
+    if ACTIVE.load(Ordering::Relaxed) == 0 {
+        return None;
+    }
+    let capture = captures().lock().unwrap_or_else(|e| e.into_inner()).get(&proxy_id)?.clone();
+    let conn = capture.next_conn.fetch_add(1, Ordering::Relaxed);
+    capture.send(conn, Event::Open { client, server });
+    Some(CaptureSession { capture, conn })
+}
+
+/// 提前结束抓包（已结束的忽略）
+pub fn stop(capture_id: &str) {
+    let captures = captures().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(capture) = captures.values().find(|c| c.id == capture_id) {
+        capture.cancel.cancel();
+    }
+}
+
+/// 已开始、尚未写完的抓包
+pub struct CaptureTask {
+    proxy_id: i64,
+    capture: Arc<Capture>,
+    rx: mpsc::Receiver<Record>,
+    duration: Duration,
+    max_bytes: u64,
+    path: PathBuf,
+}
+
+/// 开始抓包，同一代理同时只能有一个抓包
+pub fn start(cmd: &oxiproxy::StartCaptureCommand) -> Result<CaptureTask> {
+    if cmd.duration_secs == 0 || cmd.max_bytes == 0 {
+        bail!("抓包时长和大小上限必须大于 0");
+    }
+    // 文件名只使用 capture_id 中的安全字符
+    if cmd.capture_id.is_empty() || !cmd.capture_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        bail!("无效的抓包 ID: {}", cmd.capture_id);
+    }
+
+    let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+    let capture = Arc::new(Capture {
+        id: cmd.capture_id.clone(),
+        tx,
+        cancel: CancellationToken::new(),
+        next_conn: AtomicU32::new(0),
+        dropped: AtomicU64::new(0),
+    });
+    {
+        let mut captures = captures().lock().unwrap_or_else(|e| e.into_inner());
+        if captures.contains_key(&cmd.proxy_id) {
+            bail!("代理 #{} 正在抓包", cmd.proxy_id);
+        }
+        captures.insert(cmd.proxy_id, capture.clone());
+        ACTIVE.fetch_add(1, Ordering::Relaxed);
+    }
+
+    Ok(CaptureTask {
+        proxy_id: cmd.proxy_id,
+        capture,
+        rx,
+        duration: Duration::from_secs(cmd.duration_secs as u64),
+        max_bytes: cmd.max_bytes,
+        path: std::env::temp_dir().join(format!("oxiproxy-capture-{}.pcapng", cmd.capture_id)),
+    })
+}
+
+impl CaptureTask {
+    /// 写入抓包文件直到结束，然后上传并删除本地文件
+    pub async fn run(mut self, grpc: Arc<AgentGrpcClient>) {
+        let id = self.capture.id.clone();
+        info!("📦 开始抓包 {}: 代理 #{}，最长 {} 秒", id, self.proxy_id, self.duration.as_secs());
+        let written = self.write().await;
+
+        {
+            let mut captures = captures().lock().unwrap_or_else(|e| e.into_inner());
+            if captures.remove(&self.proxy_id).is_some() {
+                ACTIVE.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+        let dropped = self.capture.dropped.load(Ordering::Relaxed);
+
+        let result = match written {
+            Ok(size) => {
+                info!("📦 抓包 {} 结束: {} 字节，丢弃 {} 个数据段，开始上传", id, size, dropped);
+                upload(&grpc, &id, &self.path, dropped).await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            error!("抓包 {} 失败: {}", id, e);
+            let _ = send_chunk(&grpc, oxiproxy::CaptureChunk {
+                capture_id: id.clone(),
+                error: Some(e.to_string()),
+                dropped_packets: dropped,
+                ..Default::default()
+            })
+            .await;
+        }
+        if let Err(e) = tokio::fs::remove_file(&self.path).await {
+            warn!("删除抓包临时文件 {} 失败: {}", self.path.display(), e);
+        }
+    }
+
+    async fn write(&mut self) -> Result<u64> {
+        let mut file = tokio::io::BufWriter::new(tokio::fs::File::create(&self.path).await?);
+        let mut writer = PcapWriter::default();
+        let header = PcapWriter::header();
+        file.write_all(&header).await?;
+        let mut written = header.len() as u64;
+
+        let deadline = tokio::time::sleep(self.duration);
+        tokio::pin!(deadline);
+        loop {
+            let record = tokio::select! {
+                _ = &mut deadline => break,
+                _ = self.capture.cancel.cancelled() => break,
+                record = self.rx.recv() => match record {
+                    Some(record) => record,
+                    None => break,
+                },
+            };
+            let packets = writer.encode(record);
+            if written + packets.len() as u64 > self.max_bytes {
+                info!("抓包 {} 达到大小上限，提前结束", self.capture.id);
+                break;
+            }
+            file.write_all(&packets).await?;
+            written += packets.len() as u64;
+        }
+        file.flush().await?;
+        Ok(written)
+    }
+}
+
+async fn send_chunk(grpc: &AgentGrpcClient, chunk: oxiproxy::CaptureChunk) -> Result<()> {
+    let msg = oxiproxy::AgentServerMessage { payload: Some(AgentPayload::CaptureChunk(chunk)) };
+    if grpc.shared_sender().send(msg).await.is_err() {
+        bail!("与 Controller 的连接已断开");
+    }
+    Ok(())
+}
+
+async fn upload(grpc: &AgentGrpcClient, id: &str, path: &Path, dropped: u64) -> Result<()> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let total = file.metadata().await?.len();
+    let mut offset = 0u64;
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = file.read(&mut buf).await?;
+        let done = n == 0 || offset + n as u64 >= total;
+        send_chunk(grpc, oxiproxy::CaptureChunk {
+            capture_id: id.to_string(),
+            offset,
+            data: buf[..n].to_vec(),
+            done,
+            error: None,
+            dropped_packets: dropped,
+        })
+        .await?;
+        offset += n as u64;
+        if done {
+            return Ok(());
+        }
+    }
+}
+
+/// 合成的 TCP 连接状态
+struct Conn {
+    client: SocketAddr,
+    server: SocketAddr,
+    client_seq: u32,
+    server_seq: u32,
+}
+
+/// 把转发记录编码为 pcapng 数据块
+#[derive(Default)]
+struct PcapWriter {
+    conns: HashMap<u32, Conn>,
+}
+
+impl PcapWriter {
+    /// Section Header Block + Interface Description Block
+    fn header() -> Vec<u8> {
+        let mut out = Vec::with_capacity(48);
+        // SHB
+        out.extend_from_slice(&0x0A0D0D0Au32.to_le_bytes());
+        out.extend_from_slice(&28u32.to_le_bytes());
+        out.extend_from_slice(&0x1A2B3C4Du32.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(&(-1i64).to_le_bytes());
+        out.extend_from_slice(&28u32.to_le_bytes());
+        // IDB（时间戳精度默认为微秒）
+        out.extend_from_slice(&1u32.to_le_bytes());
+        out.extend_from_slice(&20u32.to_le_bytes());
+        out.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out.extend_from_slice(&20u32.to_le_bytes());
+        out
+    }
+
+    fn encode(&mut self, record: Record) -> Vec<u8> {
+        let mut out = Vec::new();
+        let ts = record.ts;
+        match record.event {
+            Event::Open { client, server } => {
+                let (client, server) = same_family(client, server);
+                epb(&mut out, ts, &tcp_packet(client, server, 0, 0, TCP_SYN, &[]));
+                epb(&mut out, ts, &tcp_packet(server, client, 0, 1, TCP_SYN | TCP_ACK, &[]));
+                epb(&mut out, ts, &tcp_packet(client, server, 1, 1, TCP_ACK, &[]));
+                self.conns.insert(record.conn, Conn { client, server, client_seq: 1, server_seq: 1 });
+            }
+            Event::Data(direction, data) => {
+                // 打开记录被丢弃的连接无法还原，跳过
+                let Some(conn) = self.conns.get_mut(&record.conn) else {
+                    return out;
+                };
+                let flags = TCP_PSH | TCP_ACK;
+                let packet = match direction {
+                    Direction::Inbound => tcp_packet(conn.client, conn.server, conn.client_seq, conn.server_seq, flags, &data),
+                    Direction::Outbound => tcp_packet(conn.server, conn.client, conn.server_seq, conn.client_seq, flags, &data),
+                };
+                match direction {
+                    Direction::Inbound => conn.client_seq = conn.client_seq.wrapping_add(data.len() as u32),
+                    Direction::Outbound => conn.server_seq = conn.server_seq.wrapping_add(data.len() as u32),
+                }
+                epb(&mut out, ts, &packet);
+            }
+            Event::Close => {
+                let Some(conn) = self.conns.remove(&record.conn) else {
+                    return out;
+                };
+                let flags = TCP_FIN | TCP_ACK;
+                epb(&mut out, ts, &tcp_packet(conn.client, conn.server, conn.client_seq, conn.server_seq, flags, &[]));
+                epb(&mut out, ts, &tcp_packet(conn.server, conn.client, conn.server_seq, conn.client_seq.wrapping_add(1), flags, &[]));
+            }
+        }
+        out
+    }
+}
+
+/// Enhanced Packet Block
+fn epb(out: &mut Vec<u8>, ts: u64, packet: &[u8]) {
+    let padded = packet.len().next_multiple_of(4);
+    let total = (32 + padded) as u32;
+    out.extend_from_slice(&6u32.to_le_bytes());
+    out.extend_from_slice(&total.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&((ts >> 32) as u32).to_le_bytes());
+    out.extend_from_slice(&(ts as u32).to_le_bytes());
+    out.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+    out.extend_from_slice(packet);
+    out.resize(out.len() + padded - packet.len(), 0);
+    out.extend_from_slice(&total.to_le_bytes());
+}
+
+/// 两端地址族不同时统一映射为 IPv6
+fn same_family(a: SocketAddr, b: SocketAddr) -> (SocketAddr, SocketAddr) {
+    let to_v6 = |addr: SocketAddr| match addr.ip() {
+        IpAddr::V4(v4) => SocketAddr::new(IpAddr::V6(v4.to_ipv6_mapped()), addr.port()),
+        IpAddr::V6(_) => addr,
+    };
+    if a.is_ipv4() == b.is_ipv4() {
+        (a, b)
+    } else {
+        (to_v6(a), to_v6(b))
+    }
+}
+
+fn checksum(data: &[u8], mut sum: u32) -> u16 {
+    for chunk in data.chunks(2) {
+        let word = if chunk.len() == 2 { u16::from_be_bytes([chunk[0], chunk[1]]) } else { (chunk[0] as u16) << 8 };
+        sum += word as u32;
+    }
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// 构造 IP + TCP 数据包（`src` 和 `dst` 地址族相同）
+fn tcp_packet(src: SocketAddr, dst: SocketAddr, seq: u32, ack: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+    let tcp_len = 20 + payload.len();
+    let mut tcp = Vec::with_capacity(tcp_len);
+    tcp.extend_from_slice(&src.port().to_be_bytes());
+    tcp.extend_from_slice(&dst.port().to_be_bytes());
+    tcp.extend_from_slice(&seq.to_be_bytes());
+    tcp.extend_from_slice(&ack.to_be_bytes());
+    tcp.push(5 << 4);
+    tcp.push(flags);
+    tcp.extend_from_slice(&u16::MAX.to_be_bytes());
+    tcp.extend_from_slice(&[0, 0, 0, 0]);
+    tcp.extend_from_slice(payload);
+
+    // 伪首部
+    let mut pseudo = Vec::with_capacity(40);
+    match (src.ip(), dst.ip()) {
+        (IpAddr::V4(s), IpAddr::V4(d)) => {
+            pseudo.extend_from_slice(&s.octets());
+            pseudo.extend_from_slice(&d.octets());
+            pseudo.extend_from_slice(&[0, 6]);
+            pseudo.extend_from_slice(&(tcp_len as u16).to_be_bytes());
+        }
+        (s, d) => {
+            pseudo.extend_from_slice(&ip6_octets(s));
+            pseudo.extend_from_slice(&ip6_octets(d));
+            pseudo.extend_from_slice(&(tcp_len as u32).to_be_bytes());
+            pseudo.extend_from_slice(&[0, 0, 0, 6]);
+        }
+    }
+    let pseudo_sum = pseudo.chunks(2).map(|c| u16::from_be_bytes([c[0], c[1]]) as u32).sum();
+    let sum = checksum(&tcp, pseudo_sum);
+    tcp[16..18].copy_from_slice(&sum.to_be_bytes());
+
+    let mut packet = Vec::with_capacity(40 + tcp_len);
+    match (src.ip(), dst.ip()) {
+        (IpAddr::V4(s), IpAddr::V4(d)) => {
+            packet.extend_from_slice(&[0x45, 0]);
+            packet.extend_from_slice(&((20 + tcp_len) as u16).to_be_bytes());
+            packet.extend_from_slice(&[0, 0, 0x40, 0, 64, 6, 0, 0]);
+            packet.extend_from_slice(&s.octets());
+            packet.extend_from_slice(&d.octets());
+            let sum = checksum(&packet, 0);
+            packet[10..12].copy_from_slice(&sum.to_be_bytes());
+        }
+        (s, d) => {
+            packet.extend_from_slice(&[0x60, 0, 0, 0]);
+            packet.extend_from_slice(&(tcp_len as u16).to_be_bytes());
+            packet.extend_from_slice(&[6, 64]);
+            packet.extend_from_slice(&ip6_octets(s));
+            packet.extend_from_slice(&ip6_octets(d));
+        }
+    }
+    packet.extend_from_slice(&tcp);
+    packet
+}
+
+fn ip6_octets(ip: IpAddr) -> [u8; 16] {
+    match ip {
+        IpAddr::V4(v4) => v4.to_ipv6_mapped().octets(),
+        IpAddr::V6(v6) => v6.octets(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(conn: u32, event: Event) -> Record {
+        Record { conn, ts: 1_700_000_000_000_000, event }
+    }
+
+    #[test]
+    fn test_pcapng_encoding() {
+        let header = PcapWriter::header();
+        assert_eq!(header.len(), 48);
+        assert_eq!(&header[..4], &[0x0A, 0x0D, 0x0D, 0x0A]);
+
+        let mut writer = PcapWriter::default();
+        let client: SocketAddr = "203.0.113.7:50000".parse().unwrap();
+        let server: SocketAddr = "198.51.100.1:8080".parse().unwrap();
+        // 三次握手
+        let open = writer.encode(record(1, Event::Open { client, server }));
+        assert_eq!(open.len(), 3 * (32 + 40));
+
+        let data = writer.encode(record(1, Event::Data(Direction::Inbound, b"hello".to_vec())));
+        assert_eq!(data.len() % 4, 0);
+        let packet = &data[28..28 + 45];
+        // IPv4 首部校验和正确时整个首部求和为 0xffff
+        assert_eq!(checksum(&packet[..20], 0), 0);
+        assert_eq!(&packet[40..], b"hello");
+        // 序号从握手后的 1 开始，下一段从 6 开始
+        assert_eq!(u32::from_be_bytes(packet[24..28].try_into().unwrap()), 1);
+        assert_eq!(writer.conns[&1].client_seq, 6);
+
+        assert_eq!(writer.encode(record(1, Event::Close)).len(), 2 * (32 + 40));
+        // 未知连接的数据跳过
+        assert!(writer.encode(record(2, Event::Data(Direction::Outbound, b"x".to_vec()))).is_empty());
+
+        // 地址族不同时映射为 IPv6
+        let v6: SocketAddr = "[2001:db8::1]:443".parse().unwrap();
+        let open = writer.encode(record(3, Event::Open { client, server: v6 }));
+        assert_eq!(open[28] >> 4, 6);
+    }
+}
@@ -0,0 +1,94 @@
+//! 同一客户端的隧道连接
+//!
+//! 通常每个客户端只有一条隧道连接，新连接直接取代旧连接（旧连接多半是断线后尚未超时的半开连接）。
+//! Controller 将客户端的重复登录策略设为副本模式时，不同实例的连接同时保留，访客连接在各实例间
+//! 轮流分配；同一实例重连仍取代它原来的连接。
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+pub struct ClientConnections<C> {
+    /// (实例 ID, 连接)，旧版客户端的实例 ID 为空
+    conns: Vec<(Option<String>, Arc<C>)>,
+    next: AtomicUsize,
+}
+
+impl<C> ClientConnections<C> {
+    pub fn new() -> Self {
+        Self { conns: Vec::new(), next: AtomicUsize::new(0) }
+    }
+
+    /// 加入连接：不允许副本时取代所有已有连接，否则只取代同一实例（或未上报实例 ID）的连接
+    pub fn insert(&mut self, instance_id: Option<String>, conn: Arc<C>, allow_replicas: bool) {
+        if allow_replicas && instance_id.is_some() {
+            self.conns.retain(|(id, _)| id.is_some() && *id != instance_id);
+        } else {
+            self.conns.clear();
+        }
+        self.conns.push((instance_id, conn));
+    }
+
+    /// 移除连接，返回 `None` 表示该连接已被取代，`Some(true)` 表示已没有其他连接
+    pub fn remove(&mut self, conn: &Arc<C>) -> Option<bool> {
+        let index = self.conns.iter().position(|(_, c)| Arc::ptr_eq(c, conn))?;
+        self.conns.remove(index);
+        Some(self.conns.is_empty())
+    }
+
+    /// 轮流选择一条连接
+    pub fn pick(&self) -> Option<Arc<C>> {
+        if self.conns.is_empty() {
+            return None;
+        }
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.conns.len();
+        Some(self.conns[index].1.clone())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<C>> {
+        self.conns.iter().map(|(_, c)| c)
+    }
+}
+
+impl<C> Default for ClientConnections<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(s: &str) -> Option<String> {
+        Some(s.to_string())
+    }
+
+    #[test]
+    fn replaces_without_replicas() {
+        let mut conns = ClientConnections::new();
+        let (a, b) = (Arc::new(1), Arc::new(2));
+        conns.insert(id("a"), a.clone(), false);
+        conns.insert(id("b"), b.clone(), false);
+        assert_eq!(conns.iter().count(), 1);
+        assert_eq!(conns.remove(&a), None);
+        assert_eq!(conns.remove(&b), Some(true));
+    }
+
+    #[test]
+    fn keeps_other_instances_as_replicas() {
+        let mut conns = ClientConnections::new();
+        let (a1, b, a2) = (Arc::new(1), Arc::new(2), Arc::new(3));
+        conns.insert(id("a"), a1.clone(), true);
+        conns.insert(id("b"), b.clone(), true);
+        conns.insert(id("a"), a2.clone(), true);
+        assert_eq!(conns.iter().count(), 2);
+        assert_eq!(conns.remove(&a1), None);
+
+        let picked: Vec<i32> = (0..4).map(|_| *conns.pick().unwrap()).collect();
+        assert_eq!(picked, vec![2, 3, 2, 3]);
+
+        assert_eq!(conns.remove(&b), Some(false));
+        assert_eq!(conns.remove(&a2), Some(true));
+        assert!(conns.pick().is_none());
+    }
+}
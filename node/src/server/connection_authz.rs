@@ -0,0 +1,111 @@
+//! 访客连接授权
+//!
+//! Controller 配置了连接授权钩子时（随节点注册响应下发），节点在把访客 TCP 连接接入隧道前
+//! 向 Controller 请求授权，由 Controller 调用外部钩子决定放行或拒绝。
+//! 决定按（代理, 来源 IP）缓存，缓存时间由 Controller 返回；同一来源的后续连接直接使用缓存。
+//!
+//! 等待 Controller 超时或 Controller 不可用时按下发的 `fail_open` 处理。UDP 代理不经过授权。
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use common::grpc::oxiproxy;
+use common::grpc::oxiproxy::agent_server_message::Payload as AgentPayload;
+use common::grpc::pending_requests::PendingRequests;
+use tracing::{debug, info};
+
+use super::grpc_client::{ControllerResponse, SharedGrpcSender, SharedPendingRequests};
+
+/// 缓存条目超过该数量时清理过期条目
+const CACHE_PRUNE_THRESHOLD: usize = 10_000;
+/// 在钩子超时之外额外等待 Controller 的时间
+const CONTROLLER_MARGIN: Duration = Duration::from_secs(1);
+
+struct Channel {
+    sender: SharedGrpcSender,
+    pending: SharedPendingRequests,
+}
+
+pub struct ConnectionAuthz {
+    settings: RwLock<Option<oxiproxy::GrpcConnectionAuthz>>,
+    channel: OnceLock<Channel>,
+    /// (代理, 来源 IP) → (是否放行, 过期时间)
+    cache: Mutex<HashMap<(i64, IpAddr), (bool, Instant)>>,
+}
+
+pub fn global() -> &'static ConnectionAuthz {
+    static AUTHZ: OnceLock<ConnectionAuthz> = OnceLock::new();
+    AUTHZ.get_or_init(|| ConnectionAuthz {
+        settings: RwLock::new(None),
+        channel: OnceLock::new(),
+        cache: Mutex::new(HashMap::new()),
+    })
+}
+
+/// 设置向 Controller 发送授权请求的通道
+pub fn start(sender: SharedGrpcSender, pending: SharedPendingRequests) {
+    let _ = global().channel.set(Channel { sender, pending });
+}
+
+impl ConnectionAuthz {
+    /// 更新 Controller 下发的设置（注册和重连时调用），设置变化时清空缓存
+    pub fn configure(&self, settings: Option<oxiproxy::GrpcConnectionAuthz>) {
+        let mut current = self.settings.write().unwrap();
+        if *current != settings {
+            match &settings {
+                Some(s) => info!("访客连接授权已启用（钩子不可用时{}）", if s.fail_open { "放行" } else { "拒绝" }),
+                None if current.is_some() => info!("访客连接授权已关闭"),
+                None => {}
+            }
+            self.cache.lock().unwrap().clear();
+            *current = settings;
+        }
+    }
+
+    /// 来源是否可以连接该代理，未启用授权时直接放行
+    pub async fn authorize(&self, proxy_id: i64, ip: IpAddr) -> bool {
+        let Some(settings) = *self.settings.read().unwrap() else {
+            return true;
+        };
+        let now = Instant::now();
+        if let Some(&(allowed, until)) = self.cache.lock().unwrap().get(&(proxy_id, ip)) {
+            if until > now {
+                return allowed;
+            }
+        }
+
+        let wait = Duration::from_millis(settings.timeout_ms as u64) + CONTROLLER_MARGIN;
+        let Some(resp) = self.request(proxy_id, ip, wait).await else {
+            debug!("请求连接授权失败，按设置{}: 代理 #{} 来源 {}", if settings.fail_open { "放行" } else { "拒绝" }, proxy_id, ip);
+            return settings.fail_open;
+        };
+
+        if resp.cache_secs > 0 {
+            let mut cache = self.cache.lock().unwrap();
+            if cache.len() >= CACHE_PRUNE_THRESHOLD {
+                cache.retain(|_, (_, until)| *until > now);
+            }
+            cache.insert((proxy_id, ip), (resp.allowed, now + Duration::from_secs(resp.cache_secs as u64)));
+        }
+        resp.allowed
+    }
+
+    async fn request(&self, proxy_id: i64, ip: IpAddr, wait: Duration) -> Option<oxiproxy::AuthorizeConnectionResponse> {
+        let channel = self.channel.get()?;
+        let (request_id, rx) = channel.pending.register().await;
+        let msg = oxiproxy::AgentServerMessage {
+            payload: Some(AgentPayload::AuthorizeConnection(oxiproxy::AuthorizeConnectionRequest {
+                request_id,
+                proxy_id,
+                source_ip: ip.to_string(),
+            })),
+        };
+        channel.sender.send(msg).await.ok()?;
+        match PendingRequests::wait(rx, wait).await.ok()? {
+            ControllerResponse::AuthorizeConnection(resp) => Some(resp),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! 预检模式（`node start --dry-run`）
+//!
+//! 用节点 token 向 Controller 只读地获取节点配置（不注册连接，不影响正在运行的同一节点），
+//! 然后在本机逐个尝试监听隧道端口、健康检查端口和所有代理的远程端口，报告端口被占用、
+//! 低端口权限不足以及配置中的端口重复，不转发任何流量。适合在替换生产节点前在新机器上执行。
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use common::check::{self, Report, Transport};
+
+use super::grpc_client::AgentGrpcClient;
+use super::proxy_server::ProxyProtocol;
+
+pub async fn run(
+    controller_url: &str,
+    token: &str,
+    bind_port: u16,
+    protocol: &str,
+    tls_ca_cert: Option<&[u8]>,
+    health_port: Option<u16>,
+) -> Result<()> {
+    let mut report = Report::default();
+
+    let response = match AgentGrpcClient::dry_run(controller_url, token, bind_port, protocol, tls_ca_cert).await {
+        Ok(response) => response,
+        Err(e) => {
+            report.fail("Controller", e, "检查 --controller-url、网络连通性和 CA 证书，可先运行 node check");
+            return report.finish();
+        }
+    };
+    if !response.success {
+        report.fail(
+            "节点认证",
+            response.error_message.unwrap_or_default(),
+            "检查 --token 是否与管理界面中节点的密钥一致",
+        );
+        return report.finish();
+    }
+    report.ok(
+        "节点认证",
+        format!(
+            "节点 #{} ({})，隧道协议 {}，{} 个代理",
+            response.node_id,
+            response.node_name,
+            response.tunnel_protocol,
+            response.proxies.len()
+        ),
+    );
+    if response.online {
+        report.warn("节点状态", "该节点当前在线，在正在运行的节点所在机器上预检时，其端口会显示为被占用");
+    }
+
+    // 隧道协议以 Controller 配置为准
+    let tunnel_transport = if response.tunnel_protocol == "tcp" { Transport::Tcp } else { Transport::Udp };
+    check::check_port(&mut report, "隧道端口", bind_port, tunnel_transport);
+    if let Some(port) = health_port {
+        check::check_port(&mut report, "健康检查端口", port, Transport::Tcp);
+    }
+
+    let mut seen: HashMap<(ProxyProtocol, u32), String> = HashMap::new();
+    for proxy in &response.proxies {
+        let item = format!("代理 {} (#{})", proxy.name, proxy.proxy_id);
+        let proxy_protocol = ProxyProtocol::from(proxy.proxy_type.as_str());
+        let transport = match proxy_protocol {
+            ProxyProtocol::Tcp => Transport::Tcp,
+            ProxyProtocol::Udp => Transport::Udp,
+        };
+        let port = proxy.remote_port;
+
+        if let Some(other) = seen.insert((proxy_protocol.clone(), port), proxy.name.clone()) {
+            report.fail(
+                &item,
+                format!("远程端口 {}/{} 与代理 {} 重复", port, proxy_protocol.as_str(), other),
+                "在管理界面修改其中一个代理的远程端口",
+            );
+            continue;
+        }
+        if port == bind_port as u32 && transport == tunnel_transport {
+            report.fail(&item, format!("远程端口 {} 与隧道端口相同", port), "修改代理的远程端口或 --bind-port");
+            continue;
+        }
+        match u16::try_from(port) {
+            Ok(port) => check::check_port(&mut report, &item, port, transport),
+            Err(_) => report.fail(&item, format!("远程端口 {} 无效", port), "在管理界面修改代理的远程端口"),
+        }
+    }
+
+    report.finish()
+}
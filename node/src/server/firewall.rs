@@ -0,0 +1,434 @@
+//! 防火墙管理（`--manage-firewall`，仅 Linux）
+//!
+//! 节点在独立的表 / 链中维护代理端口的规则，不改动系统已有的规则：
+//!
+//! 1. 被来源 IP 自动处置封禁的来源，在内核层面丢弃其发往对应代理端口的包，处置到期或解除后删除；
+//! 2. 放行已建立的连接、运行中代理的远程端口，以及隧道端口、备用端口、NAT 探测端口和健康检查端口；
+//! 3. 设置了 `OXIPROXY_PORT_RANGE` 时，丢弃该范围内其余端口的包，范围内只有运行中的代理端口是开放的。
+//!
+//! 代理监听器启动或停止后规则随之更新（短暂合并后整体重写），节点退出时删除整个表 / 链。
+//!
+//! | 后端 | 规则位置 |
+//! |------|------|
+//! | nftables | `inet oxiproxy` 表的 `input` 链（优先级 -10）。nftables 中 accept 只结束本表的处理，其他表中的 drop 仍会生效 |
+//! | iptables | `filter` 表的 `OXIPROXY` 链，插入到 `INPUT` 链首位；IPv6 使用 ip6tables |
+
+use std::collections::{BTreeSet, HashMap};
+use std::net::IpAddr;
+use std::process::Stdio;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+use super::proxy_server::ProxyProtocol;
+
+/// 规则变更后等待合并的时间（批量启动代理时只重写一次）
+const DEBOUNCE: Duration = Duration::from_millis(200);
+/// nftables 表名 / iptables 链名
+const NFT_TABLE: &str = "oxiproxy";
+const IPT_CHAIN: &str = "OXIPROXY";
+/// iptables multiport 每条规则最多 15 个端口
+const MULTIPORT_MAX: usize = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Nftables,
+    Iptables,
+}
+
+impl Backend {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Backend::Nftables => "nftables",
+            Backend::Iptables => "iptables",
+        }
+    }
+}
+
+/// 命令行选择的后端，`None` 表示自动检测
+fn requested() -> &'static OnceLock<Option<Backend>> {
+    static REQUESTED: OnceLock<Option<Backend>> = OnceLock::new();
+    &REQUESTED
+}
+
+/// 启用防火墙管理（启动参数解析后、创建 runtime 之前调用）
+///
+/// `backend` 为 `auto`、`nftables` 或 `iptables`；不启用时不做任何事。
+pub fn configure(enabled: bool, backend: &str) -> Result<()> {
+    if !enabled {
+        return Ok(());
+    }
+    if !cfg!(target_os = "linux") {
+        return Err(anyhow!("防火墙管理仅支持 Linux"));
+    }
+    let backend = match backend {
+        "auto" => None,
+        "nftables" | "nft" => Some(Backend::Nftables),
+        "iptables" => Some(Backend::Iptables),
+        other => return Err(anyhow!("未知的防火墙后端: {}（可选 auto、nftables、iptables）", other)),
+    };
+    let _ = requested().set(backend);
+    Ok(())
+}
+
+/// 一次完整的规则集
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Ruleset {
+    /// 封禁的来源：(来源 IP, 协议, 代理端口)
+    blocks: BTreeSet<(IpAddr, &'static str, u16)>,
+    tcp: BTreeSet<u16>,
+    udp: BTreeSet<u16>,
+    /// 只开放运行中代理端口的范围
+    range: Option<(u16, u16)>,
+}
+
+fn proto_name(protocol: &ProxyProtocol) -> &'static str {
+    match protocol {
+        ProxyProtocol::Tcp => "tcp",
+        ProxyProtocol::Udp => "udp",
+    }
+}
+
+/// nftables 规则脚本（`nft -f -` 原子替换整张表）
+fn render_nft(rules: &Ruleset) -> String {
+    let mut chain = vec![
+        "type filter hook input priority -10; policy accept;".to_string(),
+    ];
+    for (ip, proto, port) in &rules.blocks {
+        let family = if ip.is_ipv4() { "ip" } else { "ip6" };
+        chain.push(format!("{} saddr {} {} dport {} drop", family, ip, proto, port));
+    }
+    chain.push("ct state established,related accept".to_string());
+    for (proto, ports) in [("tcp", &rules.tcp), ("udp", &rules.udp)] {
+        if !ports.is_empty() {
+            let list: Vec<String> = ports.iter().map(u16::to_string).collect();
+            chain.push(format!("{} dport {{ {} }} accept", proto, list.join(", ")));
+        }
+    }
+    if let Some((start, end)) = rules.range {
+        chain.push(format!("tcp dport {}-{} drop", start, end));
+        chain.push(format!("udp dport {}-{} drop", start, end));
+    }
+
+    let body: String = chain.iter().map(|line| format!("    {}\n", line)).collect();
+    format!(
+        "table inet {t}\ndelete table inet {t}\ntable inet {t} {{\n  chain input {{\n{body}  }}\n}}\n",
+        t = NFT_TABLE
+    )
+}
+
+/// iptables-restore 脚本（`--noflush` 下声明链会清空并重写该链）
+fn render_iptables(rules: &Ruleset, v6: bool) -> String {
+    let mut lines = vec!["*filter".to_string(), format!(":{} - [0:0]", IPT_CHAIN)];
+    for (ip, proto, port) in rules.blocks.iter().filter(|(ip, _, _)| ip.is_ipv6() == v6) {
+        lines.push(format!("-A {} -s {} -p {} --dport {} -j DROP", IPT_CHAIN, ip, proto, port));
+    }
+    lines.push(format!("-A {} -m conntrack --ctstate ESTABLISHED,RELATED -j ACCEPT", IPT_CHAIN));
+    for (proto, ports) in [("tcp", &rules.tcp), ("udp", &rules.udp)] {
+        let ports: Vec<String> = ports.iter().map(u16::to_string).collect();
+        for chunk in ports.chunks(MULTIPORT_MAX) {
+            lines.push(format!(
+                "-A {} -p {} -m multiport --dports {} -j ACCEPT",
+                IPT_CHAIN,
+                proto,
+                chunk.join(",")
+            ));
+        }
+    }
+    if let Some((start, end)) = rules.range {
+        for proto in ["tcp", "udp"] {
+            lines.push(format!("-A {} -p {} --dport {}:{} -j DROP", IPT_CHAIN, proto, start, end));
+        }
+    }
+    lines.push("COMMIT".to_string());
+    lines.join("\n") + "\n"
+}
+
+/// 执行命令，`input` 写入标准输入
+async fn run(program: &str, args: &[&str], input: Option<&str>) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("执行 {} 失败: {}", program, e))?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes()).await?;
+    }
+    let output = child.wait_with_output().await?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} {} 失败: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+impl Backend {
+    async fn detect() -> Result<Backend> {
+        if run("nft", &["--version"], None).await.is_ok() {
+            Ok(Backend::Nftables)
+        } else if run("iptables", &["--version"], None).await.is_ok() {
+            Ok(Backend::Iptables)
+        } else {
+            Err(anyhow!("未找到 nft 或 iptables 命令"))
+        }
+    }
+
+    async fn apply(&self, rules: &Ruleset) -> Result<()> {
+        match self {
+            Backend::Nftables => run("nft", &["-f", "-"], Some(&render_nft(rules))).await,
+            Backend::Iptables => {
+                for (restore, cmd, v6) in [("iptables-restore", "iptables", false), ("ip6tables-restore", "ip6tables", true)] {
+                    run(restore, &["--noflush"], Some(&render_iptables(rules, v6))).await?;
+                    if run(cmd, &["-C", "INPUT", "-j", IPT_CHAIN], None).await.is_err() {
+                        run(cmd, &["-I", "INPUT", "1", "-j", IPT_CHAIN], None).await?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn cleanup(&self) -> Result<()> {
+        match self {
+            Backend::Nftables => run("nft", &["delete", "table", "inet", NFT_TABLE], None).await,
+            Backend::Iptables => {
+                for cmd in ["iptables", "ip6tables"] {
+                    let _ = run(cmd, &["-D", "INPUT", "-j", IPT_CHAIN], None).await;
+                    let _ = run(cmd, &["-F", IPT_CHAIN], None).await;
+                    run(cmd, &["-X", IPT_CHAIN], None).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// 运行中的代理：proxy_id -> (协议, 远程端口)
+    proxies: HashMap<i64, (&'static str, u16)>,
+    /// 封禁的来源：(proxy_id, 来源 IP) -> 到期时间
+    blocks: HashMap<(i64, IpAddr), Instant>,
+}
+
+struct Firewall {
+    backend: Backend,
+    /// 始终放行的节点自身端口
+    exempt: Vec<(&'static str, u16)>,
+    range: Option<(u16, u16)>,
+    state: Mutex<State>,
+    changed: Notify,
+}
+
+impl Firewall {
+    /// 当前规则集，同时清理已到期的封禁
+    fn snapshot(&self, now: Instant) -> Ruleset {
+        let mut state = self.state.lock().unwrap();
+        state.blocks.retain(|_, until| *until > now);
+
+        let mut rules = Ruleset { range: self.range, ..Default::default() };
+        for &(proto, port) in self.exempt.iter().chain(state.proxies.values()) {
+            match proto {
+                "tcp" => rules.tcp.insert(port),
+                _ => rules.udp.insert(port),
+            };
+        }
+        for (proxy_id, ip) in state.blocks.keys() {
+            if let Some(&(proto, port)) = state.proxies.get(proxy_id) {
+                rules.blocks.insert((*ip, proto, port));
+            }
+        }
+        rules
+    }
+
+    fn next_expiry(&self) -> Option<Instant> {
+        self.state.lock().unwrap().blocks.values().min().copied()
+    }
+}
+
+fn firewall() -> &'static OnceLock<Firewall> {
+    static FIREWALL: OnceLock<Firewall> = OnceLock::new();
+    &FIREWALL
+}
+
+/// 节点自身需要始终放行的端口
+pub struct ExemptPorts {
+    pub tunnel_ports: Vec<u16>,
+    pub health_port: Option<u16>,
+    pub nat_probe_port: Option<u16>,
+}
+
+/// 启用了防火墙管理时检测后端、写入初始规则并启动同步任务
+///
+/// 需在恢复代理监听器之前调用，后端不可用时返回错误。
+pub async fn start(exempt: ExemptPorts) -> Result<()> {
+    let Some(requested) = requested().get().copied() else {
+        return Ok(());
+    };
+    let backend = match requested {
+        Some(b) => b,
+        None => Backend::detect().await?,
+    };
+
+    let mut ports = Vec::new();
+    for port in exempt.tunnel_ports {
+        ports.push(("tcp", port));
+        ports.push(("udp", port));
+    }
+    if let Some(port) = exempt.health_port {
+        ports.push(("tcp", port));
+    }
+    if let Some(port) = exempt.nat_probe_port {
+        ports.push(("udp", port));
+        ports.push(("udp", port.saturating_add(1)));
+    }
+
+    let fw = Firewall {
+        backend,
+        exempt: ports,
+        range: super::capabilities::configured_port_range(),
+        state: Mutex::new(State::default()),
+        changed: Notify::new(),
+    };
+    let initial = fw.snapshot(Instant::now());
+    backend
+        .apply(&initial)
+        .await
+        .map_err(|e| anyhow!("写入防火墙规则失败（需要 root 或 CAP_NET_ADMIN）: {}", e))?;
+    let _ = firewall().set(fw);
+    match initial.range {
+        Some((start, end)) => info!("防火墙管理已启用（{}），端口 {}-{} 只开放运行中的代理", backend.as_str(), start, end),
+        None => info!("防火墙管理已启用（{}）", backend.as_str()),
+    }
+
+    tokio::spawn(sync_loop(initial));
+    Ok(())
+}
+
+async fn sync_loop(mut applied: Ruleset) {
+    let Some(fw) = firewall().get() else {
+        return;
+    };
+    loop {
+        match fw.next_expiry() {
+            Some(at) => {
+                tokio::select! {
+                    _ = fw.changed.notified() => {}
+                    _ = tokio::time::sleep_until(at) => {}
+                }
+            }
+            None => fw.changed.notified().await,
+        }
+        tokio::time::sleep(DEBOUNCE).await;
+
+        let rules = fw.snapshot(Instant::now());
+        if rules == applied {
+            continue;
+        }
+        match fw.backend.apply(&rules).await {
+            Ok(()) => applied = rules,
+            Err(e) => error!("更新防火墙规则失败: {}", e),
+        }
+    }
+}
+
+/// 代理监听器变化后同步开放的端口：(proxy_id, 协议, 远程端口)
+pub fn sync_proxies(proxies: impl Iterator<Item = (i64, ProxyProtocol, u16)>) {
+    let Some(fw) = firewall().get() else {
+        return;
+    };
+    let proxies = proxies.map(|(id, protocol, port)| (id, (proto_name(&protocol), port))).collect();
+    let mut state = fw.state.lock().unwrap();
+    if state.proxies != proxies {
+        state.proxies = proxies;
+        fw.changed.notify_one();
+    }
+}
+
+/// 来源在代理上被封禁时在内核层面丢弃其流量
+pub fn block_source(proxy_id: i64, ip: IpAddr, duration: Duration) {
+    let Some(fw) = firewall().get() else {
+        return;
+    };
+    fw.state.lock().unwrap().blocks.insert((proxy_id, ip), Instant::now() + duration);
+    fw.changed.notify_one();
+}
+
+/// 解除封禁
+pub fn unblock_source(proxy_id: i64, ip: IpAddr) {
+    let Some(fw) = firewall().get() else {
+        return;
+    };
+    if fw.state.lock().unwrap().blocks.remove(&(proxy_id, ip)).is_some() {
+        fw.changed.notify_one();
+    }
+}
+
+/// 节点退出时删除全部规则
+pub async fn cleanup() {
+    let Some(fw) = firewall().get() else {
+        return;
+    };
+    match fw.backend.cleanup().await {
+        Ok(()) => info!("已删除防火墙规则"),
+        Err(e) => warn!("删除防火墙规则失败: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> Ruleset {
+        Ruleset {
+            blocks: BTreeSet::from([("203.0.113.9".parse().unwrap(), "tcp", 8080), ("2001:db8::9".parse().unwrap(), "udp", 5353)]),
+            tcp: BTreeSet::from([7000, 8080]),
+            udp: BTreeSet::from([7000, 5353]),
+            range: Some((10000, 20000)),
+        }
+    }
+
+    #[test]
+    fn test_render_nft() {
+        let script = render_nft(&rules());
+        assert!(script.starts_with("table inet oxiproxy\ndelete table inet oxiproxy\n"));
+        assert!(script.contains("ip saddr 203.0.113.9 tcp dport 8080 drop"));
+        assert!(script.contains("ip6 saddr 2001:db8::9 udp dport 5353 drop"));
+        assert!(script.contains("tcp dport { 7000, 8080 } accept"));
+        assert!(script.contains("udp dport 10000-20000 drop"));
+        // 封禁必须在放行已建立连接之前
+        assert!(script.find("drop").unwrap() < script.find("ct state").unwrap());
+
+        let empty = render_nft(&Ruleset::default());
+        assert!(!empty.contains("dport"));
+    }
+
+    #[test]
+    fn test_render_iptables() {
+        let v4 = render_iptables(&rules(), false);
+        assert!(v4.contains("-A OXIPROXY -s 203.0.113.9 -p tcp --dport 8080 -j DROP"));
+        assert!(!v4.contains("2001:db8::9"));
+        assert!(v4.contains("-A OXIPROXY -p udp -m multiport --dports 5353,7000 -j ACCEPT"));
+        assert!(v4.contains("-A OXIPROXY -p tcp --dport 10000:20000 -j DROP"));
+        assert!(v4.ends_with("COMMIT\n"));
+
+        let v6 = render_iptables(&rules(), true);
+        assert!(v6.contains("-s 2001:db8::9 -p udp --dport 5353 -j DROP"));
+
+        let many = Ruleset { tcp: (1..=20).collect(), ..Default::default() };
+        assert_eq!(render_iptables(&many, false).matches("multiport").count(), 2);
+    }
+}
@@ -16,6 +16,7 @@ use common::protocol::auth::{
     ClientAuthProvider, TrafficLimitResponse, ValidateTokenResponse,
 };
 use common::protocol::control::ProxyConfig;
+use common::protocol::handshake::AuthProof;
 
 use super::grpc_client::{AgentGrpcClient, ControllerResponse, SharedGrpcSender, SharedPendingRequests};
 
@@ -34,18 +35,19 @@ impl GrpcAuthProvider {
             node_id,
         }
     }
-}
 
-#[async_trait]
-impl ClientAuthProvider for GrpcAuthProvider {
-    async fn validate_token(&self, token: &str) -> Result<ValidateTokenResponse> {
+    async fn request_validation(
+        &self,
+        token: String,
+        proof: Option<oxiproxy::TunnelAuthProof>,
+    ) -> Result<ValidateTokenResponse> {
         let (request_id, rx) = self.pending.register().await;
-        debug!("gRPC 验证 token (request_id={})", request_id);
 
         let msg = oxiproxy::AgentServerMessage {
             payload: Some(AgentPayload::ValidateToken(oxiproxy::ValidateTokenRequest {
                 request_id: request_id.clone(),
-                token: token.to_string(),
+                token,
+                proof,
             })),
         };
 
@@ -61,11 +63,32 @@ impl ClientAuthProvider for GrpcAuthProvider {
                     client_name: r.client_name,
                     allowed: r.allowed,
                     reject_reason: r.reject_reason,
+                    allow_replicas: r.allow_replicas,
                 })
             }
             _ => Err(anyhow::anyhow!("收到意外的响应类型")),
         }
     }
+}
+
+#[async_trait]
+impl ClientAuthProvider for GrpcAuthProvider {
+    async fn validate_token(&self, token: &str) -> Result<ValidateTokenResponse> {
+        debug!("gRPC 验证 token");
+        self.request_validation(token.to_string(), None).await
+    }
+
+    async fn validate_proof(&self, proof: &AuthProof) -> Result<ValidateTokenResponse> {
+        debug!("gRPC 验证客户端 #{} 的握手应答", proof.client_id);
+        let proof = oxiproxy::TunnelAuthProof {
+            client_id: proof.client_id,
+            client_nonce: proof.client_nonce.clone(),
+            server_nonce: proof.server_nonce.clone(),
+            metadata: proof.metadata.clone(),
+            mac: proof.mac.clone(),
+        };
+        self.request_validation(String::new(), Some(proof)).await
+    }
 
     async fn set_client_online(&self, client_id: i64, online: bool) -> Result<()> {
         let (request_id, rx) = self.pending.register().await;
@@ -142,6 +165,11 @@ impl ClientAuthProvider for GrpcAuthProvider {
                     local_port: p.local_port as u16,
                     remote_port: p.remote_port as u16,
                     enabled: p.enabled,
+                    idle_timeout: p.idle_timeout,
+                    mitigation: super::mitigation::rule_from_grpc(p.mitigation),
+                    bind_ip: p.bind_ip,
+                    bandwidth_weight: p.bandwidth_weight,
+                    priority: p.priority.as_deref().and_then(common::protocol::control::ProxyPriority::parse),
                 }).collect())
             }
             _ => Err(anyhow::anyhow!("收到意外的响应类型")),
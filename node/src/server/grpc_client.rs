@@ -16,7 +16,11 @@ use common::grpc::oxiproxy::controller_to_agent_message::Payload as ControllerPa
 use common::grpc::oxiproxy::agent_server_response::Result as AgentResult;
 use common::grpc::AgentServerServiceClient;
 use common::grpc::pending_requests::PendingRequests;
-use common::protocol::control::{ProxyControl, LogEntry};
+use common::grpc::reconnect;
+use common::grpc::tuning::{self, Tuned};
+use common::protocol::control::{ProxyControl, LogEntry, PortLease};
+use common::{KcpConfig, QuicConfig};
+use super::tunnel_manager::TransportSettings;
 
 /// gRPC 流发送器类型
 pub type GrpcSender = mpsc::Sender<oxiproxy::AgentServerMessage>;
@@ -78,6 +82,26 @@ impl SharedPendingRequests {
     }
 }
 
+/// Controller 在认证响应中下发的节点配置
+pub struct NodeRegistration {
+    /// 权威隧道协议
+    pub tunnel_protocol: String,
+    /// 速度限制（字节/秒）
+    pub speed_limit: Option<i64>,
+    /// 当前限速的用户
+    pub user_speed_limits: Vec<oxiproxy::UserSpeedLimit>,
+    /// 带宽饱和时是否按代理公平分配
+    pub fair_share: bool,
+    /// 隧道传输参数
+    pub transport: TransportSettings,
+    /// 节点默认的来源 IP 处置规则
+    pub mitigation: Option<common::mitigation::MitigationRule>,
+    /// 访客连接授权设置，未启用时为 None
+    pub connection_authz: Option<oxiproxy::GrpcConnectionAuthz>,
+    /// 需要预绑定远程端口的代理，只在节点启动时请求
+    pub prebind_proxies: Vec<oxiproxy::ProxyConfig>,
+}
+
 /// Agent Server gRPC 客户端
 pub struct AgentGrpcClient {
     /// 可热替换的发送器
@@ -86,6 +110,10 @@ pub struct AgentGrpcClient {
     shared_pending: SharedPendingRequests,
     /// 节点 ID（连接认证后获得）
     node_id: RwLock<i64>,
+    /// 当前连接的 gRPC 客户端（重连后替换，供流量上报流使用）
+    service: RwLock<AgentServerServiceClient<Channel>>,
+    /// 节点 token
+    token: String,
 }
 
 /// Controller 响应的包装类型
@@ -96,25 +124,26 @@ pub enum ControllerResponse {
     TrafficLimit(oxiproxy::TrafficLimitResponse),
     GetClientProxies(oxiproxy::GetClientProxiesResponse),
     TrafficReport(oxiproxy::TrafficReportResponse),
+    AuthorizeConnection(oxiproxy::AuthorizeConnectionResponse),
 }
 
 impl AgentGrpcClient {
     /// 连接 Controller 并认证节点
     ///
-    /// 返回 (gRPC 客户端, 命令接收器, Controller 下发的权威隧道协议)
+    /// 返回 (gRPC 客户端, 命令接收器, Controller 下发的节点配置)
     pub async fn connect_and_authenticate(
         controller_url: &str,
         token: &str,
         tunnel_port: u16,
         tunnel_protocol: &str,
         tls_ca_cert: Option<&[u8]>,
-    ) -> Result<(Arc<Self>, mpsc::Receiver<ControllerCommand>, String, Option<i64>)> {
-        let mut endpoint = Channel::from_shared(controller_url.to_string())?
-            .timeout(Duration::from_secs(30))
-            .connect_timeout(Duration::from_secs(10))
-            .tcp_keepalive(Some(Duration::from_secs(60)))
-            .http2_keep_alive_interval(Duration::from_secs(30))
-            .keep_alive_timeout(Duration::from_secs(10));
+    ) -> Result<(Arc<Self>, mpsc::Receiver<ControllerCommand>, NodeRegistration)> {
+        let mut endpoint = tuning::configure_endpoint(
+            Channel::from_shared(controller_url.to_string())?
+                .timeout(Duration::from_secs(30))
+                .connect_timeout(Duration::from_secs(10))
+                .tcp_keepalive(Some(Duration::from_secs(60))),
+        );
 
         if controller_url.starts_with("https://") {
             // 从 URL 中提取域名用于 SNI
@@ -143,7 +172,7 @@ impl AgentGrpcClient {
             .await
             .map_err(|e| anyhow!("连接 Controller gRPC 失败: {}", e))?;
 
-        let mut client = AgentServerServiceClient::new(channel);
+        let mut client = AgentServerServiceClient::new(channel).tuned();
 
         // 创建双向流
         let (tx, rx) = mpsc::channel::<oxiproxy::AgentServerMessage>(256);
@@ -158,6 +187,10 @@ impl AgentGrpcClient {
                 tunnel_port: tunnel_port as u32,
                 tunnel_protocol: tunnel_protocol.to_string(),
                 version: env!("CARGO_PKG_VERSION").to_string(),
+                nat_probe_port: super::nat_probe::active_port().map(u32::from),
+                session_token: reconnect::session_token(),
+                capabilities: Some((&super::capabilities::collect()).into()),
+                prebind_ports: super::prebind::enabled(),
             })),
         };
         tx.send(register_msg).await
@@ -181,13 +214,26 @@ impl AgentGrpcClient {
         };
 
         let node_id = register_resp.node_id;
+        reconnect::set_session_token(register_resp.session_token.clone());
         let authoritative_protocol = if register_resp.tunnel_protocol.is_empty() {
             tunnel_protocol.to_string()
         } else {
             register_resp.tunnel_protocol.clone()
         };
         info!("gRPC 连接认证成功: 节点 #{} ({}), 隧道协议: {}", node_id, register_resp.node_name, authoritative_protocol);
-        let speed_limit = register_resp.speed_limit;
+        let registration = NodeRegistration {
+            tunnel_protocol: authoritative_protocol,
+            speed_limit: register_resp.speed_limit,
+            user_speed_limits: register_resp.user_speed_limits,
+            fair_share: register_resp.fair_share,
+            transport: TransportSettings {
+                kcp: register_resp.kcp.map(KcpConfig::from),
+                quic: register_resp.quic.map(QuicConfig::from),
+            },
+            mitigation: super::mitigation::rule_from_grpc(register_resp.mitigation),
+            connection_authz: register_resp.connection_authz,
+            prebind_proxies: register_resp.prebind_proxies,
+        };
 
         let shared_sender = SharedGrpcSender::new(tx.clone());
         let shared_pending = SharedPendingRequests::new(pending.clone());
@@ -196,6 +242,8 @@ impl AgentGrpcClient {
             shared_sender,
             shared_pending,
             node_id: RwLock::new(node_id),
+            service: RwLock::new(client),
+            token: token.to_string(),
         });
 
         // 启动消息接收循环
@@ -212,12 +260,69 @@ impl AgentGrpcClient {
             Self::shared_heartbeat_loop(heartbeat_sender).await;
         });
 
-        Ok((grpc_client, cmd_rx, authoritative_protocol, speed_limit))
+        Ok((grpc_client, cmd_rx, registration))
+    }
+
+    /// 只读预检：校验 token 并获取节点需要监听的端口，不注册连接、不影响正在运行的同一节点
+    pub async fn dry_run(
+        controller_url: &str,
+        token: &str,
+        tunnel_port: u16,
+        tunnel_protocol: &str,
+        tls_ca_cert: Option<&[u8]>,
+    ) -> Result<oxiproxy::NodeDryRunResponse> {
+        let mut endpoint = tuning::configure_endpoint(
+            Channel::from_shared(controller_url.to_string())?
+                .timeout(Duration::from_secs(30))
+                .connect_timeout(Duration::from_secs(10)),
+        );
+
+        if controller_url.starts_with("https://") {
+            // 从 URL 中提取域名用于 SNI
+            let domain = controller_url
+                .trim_start_matches("https://")
+                .split(':')
+                .next()
+                .ok_or_else(|| anyhow!("无法从 URL 提取域名"))?;
+
+            let mut tls_config = ClientTlsConfig::new()
+                .domain_name(domain)
+                .with_webpki_roots();
+
+            if let Some(ca_pem) = tls_ca_cert {
+                tls_config = tls_config.ca_certificate(
+                    tonic::transport::Certificate::from_pem(ca_pem)
+                );
+            }
+
+            endpoint = endpoint.tls_config(tls_config)
+                .map_err(|e| anyhow!("TLS 配置失败: {}", e))?;
+        }
+
+        let channel = endpoint.connect()
+            .await
+            .map_err(|e| anyhow!("连接 Controller gRPC 失败: {}", e))?;
+
+        let mut client = AgentServerServiceClient::new(channel).tuned();
+        let response = client
+            .node_dry_run(oxiproxy::NodeRegisterRequest {
+                token: token.to_string(),
+                tunnel_port: tunnel_port as u32,
+                tunnel_protocol: tunnel_protocol.to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| match e.code() {
+                tonic::Code::Unimplemented => anyhow!("Controller 版本过旧，不支持预检"),
+                _ => anyhow!("预检请求失败: {}", e),
+            })?;
+        Ok(response.into_inner())
     }
 
     /// 重连 Controller（复用已有的 SharedGrpcSender 和 SharedPendingRequests）
     ///
-    /// 返回 (命令接收器, Controller 下发的权威隧道协议)
+    /// 返回 (命令接收器, Controller 下发的节点配置)
     pub async fn reconnect(
         self: &Arc<Self>,
         controller_url: &str,
@@ -225,8 +330,12 @@ impl AgentGrpcClient {
         tunnel_port: u16,
         tunnel_protocol: &str,
         tls_ca_cert: Option<&[u8]>,
-    ) -> Result<(mpsc::Receiver<ControllerCommand>, String, Option<i64>)> {
-        let mut endpoint = Channel::from_shared(controller_url.to_string())?;
+    ) -> Result<(mpsc::Receiver<ControllerCommand>, NodeRegistration)> {
+        let mut endpoint = tuning::configure_endpoint(
+            Channel::from_shared(controller_url.to_string())?
+                .connect_timeout(Duration::from_secs(10))
+                .tcp_keepalive(Some(Duration::from_secs(60))),
+        );
 
         if controller_url.starts_with("https://") {
             // 从 URL 中提取域名用于 SNI
@@ -254,7 +363,7 @@ impl AgentGrpcClient {
             .await
             .map_err(|e| anyhow!("重连 Controller gRPC 失败: {}", e))?;
 
-        let mut client = AgentServerServiceClient::new(channel);
+        let mut client = AgentServerServiceClient::new(channel).tuned();
 
         // 创建新的双向流
         let (tx, rx) = mpsc::channel::<oxiproxy::AgentServerMessage>(256);
@@ -269,6 +378,11 @@ impl AgentGrpcClient {
                 tunnel_port: tunnel_port as u32,
                 tunnel_protocol: tunnel_protocol.to_string(),
                 version: env!("CARGO_PKG_VERSION").to_string(),
+                nat_probe_port: super::nat_probe::active_port().map(u32::from),
+                session_token: reconnect::session_token(),
+                capabilities: Some((&super::capabilities::collect()).into()),
+                // 只在节点启动时预绑定端口
+                prebind_ports: false,
             })),
         };
         tx.send(register_msg).await
@@ -292,18 +406,32 @@ impl AgentGrpcClient {
         };
 
         let node_id = register_resp.node_id;
+        reconnect::set_session_token(register_resp.session_token.clone());
         let authoritative_protocol = if register_resp.tunnel_protocol.is_empty() {
             tunnel_protocol.to_string()
         } else {
             register_resp.tunnel_protocol.clone()
         };
         info!("gRPC 重连认证成功: 节点 #{} ({}), 隧道协议: {}", node_id, register_resp.node_name, authoritative_protocol);
-        let speed_limit = register_resp.speed_limit;
+        let registration = NodeRegistration {
+            tunnel_protocol: authoritative_protocol,
+            speed_limit: register_resp.speed_limit,
+            user_speed_limits: register_resp.user_speed_limits,
+            fair_share: register_resp.fair_share,
+            transport: TransportSettings {
+                kcp: register_resp.kcp.map(KcpConfig::from),
+                quic: register_resp.quic.map(QuicConfig::from),
+            },
+            mitigation: super::mitigation::rule_from_grpc(register_resp.mitigation),
+            connection_authz: register_resp.connection_authz,
+            prebind_proxies: register_resp.prebind_proxies,
+        };
 
         // 热替换 sender 和 pending
         self.shared_sender.replace(tx.clone()).await;
         self.shared_pending.replace(pending.clone()).await;
         *self.node_id.write().await = node_id;
+        *self.service.write().await = client;
 
         // 启动新的消息接收循环
         let pending_clone = pending.clone();
@@ -319,7 +447,7 @@ impl AgentGrpcClient {
             Self::shared_heartbeat_loop(heartbeat_sender).await;
         });
 
-        Ok((cmd_rx, authoritative_protocol, speed_limit))
+        Ok((cmd_rx, registration))
     }
 
     /// 消息接收循环
@@ -369,6 +497,11 @@ impl AgentGrpcClient {
                     pending.complete(&rid, ControllerResponse::GetClientProxies(resp)).await;
                 }
 
+                ControllerPayload::AuthorizeConnectionResponse(resp) => {
+                    let rid = resp.request_id.clone();
+                    pending.complete(&rid, ControllerResponse::AuthorizeConnection(resp)).await;
+                }
+
                 ControllerPayload::TrafficReportResponse(_resp) => {
                     // 流量上报是 fire-and-forget，无需关联响应
                 }
@@ -390,6 +523,30 @@ impl AgentGrpcClient {
                     }).await;
                 }
 
+                ControllerPayload::ReservePort(cmd) => {
+                    let _ = cmd_tx.send(ControllerCommand::PortLease {
+                        request_id: cmd.request_id,
+                        lease: cmd.lease.map(PortLease::from),
+                        action: LeaseAction::Reserve,
+                    }).await;
+                }
+
+                ControllerPayload::ActivateProxy(cmd) => {
+                    let _ = cmd_tx.send(ControllerCommand::PortLease {
+                        request_id: cmd.request_id,
+                        lease: cmd.lease.map(PortLease::from),
+                        action: LeaseAction::Activate(cmd.proxy_id),
+                    }).await;
+                }
+
+                ControllerPayload::ReleasePort(cmd) => {
+                    let _ = cmd_tx.send(ControllerCommand::PortLease {
+                        request_id: cmd.request_id,
+                        lease: cmd.lease.map(PortLease::from),
+                        action: LeaseAction::Release,
+                    }).await;
+                }
+
                 ControllerPayload::GetStatus(cmd) => {
                     let _ = cmd_tx.send(ControllerCommand::GetStatus {
                         request_id: cmd.request_id,
@@ -415,6 +572,17 @@ impl AgentGrpcClient {
                     let _ = cmd_tx.send(ControllerCommand::UpdateProtocol {
                         request_id: cmd.request_id,
                         tunnel_protocol: cmd.tunnel_protocol,
+                        transport: TransportSettings {
+                            kcp: cmd.kcp.map(KcpConfig::from),
+                            quic: cmd.quic.map(QuicConfig::from),
+                        },
+                        staged: cmd.staged,
+                    }).await;
+                }
+
+                ControllerPayload::RetireListener(cmd) => {
+                    let _ = cmd_tx.send(ControllerCommand::RetireListener {
+                        request_id: cmd.request_id,
                     }).await;
                 }
 
@@ -422,15 +590,62 @@ impl AgentGrpcClient {
                     let _ = cmd_tx.send(ControllerCommand::UpdateSpeedLimit {
                         request_id: cmd.request_id,
                         speed_limit: cmd.speed_limit,
+                        user_limits: cmd.user_limits,
+                        fair_share: cmd.fair_share,
+                    }).await;
+                }
+
+                ControllerPayload::UpdateMitigation(cmd) => {
+                    let _ = cmd_tx.send(ControllerCommand::UpdateMitigation {
+                        request_id: cmd.request_id,
+                        rule: cmd.rule,
+                    }).await;
+                }
+
+                ControllerPayload::ReleaseMitigation(cmd) => {
+                    let _ = cmd_tx.send(ControllerCommand::ReleaseMitigation {
+                        request_id: cmd.request_id,
+                        proxy_id: cmd.proxy_id,
+                        source_ip: cmd.source_ip,
                     }).await;
                 }
 
                 ControllerPayload::SoftwareUpdate(cmd) => {
                     let _ = cmd_tx.send(ControllerCommand::SoftwareUpdate {
                         request_id: cmd.request_id,
+                        target_version: cmd.target_version,
                     }).await;
                 }
 
+                ControllerPayload::Probe(cmd) => {
+                    let _ = cmd_tx.send(ControllerCommand::Probe(cmd)).await;
+                }
+
+                ControllerPayload::FollowLogs(cmd) => {
+                    let _ = cmd_tx.send(ControllerCommand::FollowLogs(cmd)).await;
+                }
+
+                ControllerPayload::StopFollowLogs(cmd) => {
+                    super::log_follow::stop(&cmd.request_id);
+                }
+
+                ControllerPayload::GetProxyEvents(cmd) => {
+                    let _ = cmd_tx.send(ControllerCommand::GetProxyEvents(cmd)).await;
+                }
+
+                ControllerPayload::StartCapture(cmd) => {
+                    let _ = cmd_tx.send(ControllerCommand::StartCapture(cmd)).await;
+                }
+
+                ControllerPayload::StopCapture(cmd) => {
+                    super::capture::stop(&cmd.capture_id);
+                }
+
+                ControllerPayload::ReconnectHint(hint) => {
+                    info!("Controller 即将关闭，断线后等待 {} ms 再重连", hint.delay_ms);
+                    reconnect::set_hint(Duration::from_millis(hint.delay_ms as u64));
+                }
+
                 _ => {
                     warn!("收到未知的 Controller 消息类型");
                 }
@@ -449,9 +664,7 @@ impl AgentGrpcClient {
             interval.tick().await;
 
             let msg = oxiproxy::AgentServerMessage {
-                payload: Some(AgentPayload::Heartbeat(oxiproxy::Heartbeat {
-                    timestamp: chrono::Utc::now().timestamp(),
-                })),
+                payload: Some(AgentPayload::Heartbeat(super::speed_meter::heartbeat())),
             };
 
             if sender.send(msg).await.is_err() {
@@ -471,6 +684,19 @@ impl AgentGrpcClient {
         &self.shared_sender
     }
 
+    /// 在当前连接上建立流量上报专用流，返回 Controller 的确认流
+    pub async fn open_traffic_stream(
+        &self,
+        outbound: mpsc::Receiver<oxiproxy::TrafficBatch>,
+    ) -> Result<tonic::Streaming<oxiproxy::TrafficAck>, tonic::Status> {
+        let mut client = self.service.read().await.clone();
+        let mut request = tonic::Request::new(tokio_stream::wrappers::ReceiverStream::new(outbound));
+        let token = self.token.parse()
+            .map_err(|_| tonic::Status::invalid_argument("节点 token 含有无效字符"))?;
+        request.metadata_mut().insert(common::grpc::NODE_TOKEN_METADATA, token);
+        Ok(client.traffic_stream(request).await?.into_inner())
+    }
+
     /// 获取共享 pending requests（供 auth provider 使用）
     pub fn shared_pending(&self) -> &SharedPendingRequests {
         &self.shared_pending
@@ -485,6 +711,27 @@ impl AgentGrpcClient {
             .map_err(|_| anyhow!("发送响应失败"))?;
         Ok(())
     }
+
+    /// 上报软件更新进度到 Controller
+    pub async fn send_update_progress(
+        &self,
+        request_id: &str,
+        stage: &str,
+        target_version: Option<String>,
+        message: Option<String>,
+    ) {
+        let msg = oxiproxy::AgentServerMessage {
+            payload: Some(AgentPayload::UpdateProgress(oxiproxy::UpdateProgress {
+                request_id: request_id.to_string(),
+                stage: stage.to_string(),
+                target_version,
+                message,
+            })),
+        };
+        if self.shared_sender.send(msg).await.is_err() {
+            warn!("上报更新进度失败: {}", stage);
+        }
+    }
 }
 
 /// Controller 下发的命令
@@ -499,6 +746,12 @@ pub enum ControllerCommand {
         client_id: String,
         proxy_id: i64,
     },
+    /// 两阶段创建代理的端口预留 / 激活 / 释放
+    PortLease {
+        request_id: String,
+        lease: Option<PortLease>,
+        action: LeaseAction,
+    },
     GetStatus {
         request_id: String,
     },
@@ -514,14 +767,50 @@ pub enum ControllerCommand {
     UpdateProtocol {
         request_id: String,
         tunnel_protocol: String,
+        transport: TransportSettings,
+        /// 在备用端口上启动新监听器并保留旧监听器
+        staged: bool,
+    },
+    /// 停止分阶段切换中保留的旧监听器
+    RetireListener {
+        request_id: String,
     },
     UpdateSpeedLimit {
         request_id: String,
         speed_limit: i64,
+        user_limits: Vec<oxiproxy::UserSpeedLimit>,
+        fair_share: bool,
+    },
+    /// 更新节点默认的来源 IP 处置规则
+    UpdateMitigation {
+        request_id: String,
+        rule: Option<oxiproxy::GrpcMitigationRule>,
+    },
+    /// 解除对来源 IP 的处置
+    ReleaseMitigation {
+        request_id: String,
+        proxy_id: i64,
+        source_ip: String,
     },
     SoftwareUpdate {
         request_id: String,
+        target_version: Option<String>,
     },
+    /// 连通性探测（结果通过 ProbeStep 逐条上报，不发送 AgentServerResponse）
+    Probe(oxiproxy::ProbeCommand),
+    /// 实时跟随日志（新日志通过 NodeLogChunk 分批上报，不发送 AgentServerResponse）
+    FollowLogs(oxiproxy::FollowLogsCommand),
+    /// 查询代理生命周期事件
+    GetProxyEvents(oxiproxy::GetProxyEventsCommand),
+    /// 开始抓包（确认后继续写文件，结束后通过 CaptureChunk 上传）
+    StartCapture(oxiproxy::StartCaptureCommand),
+}
+
+/// 对预留端口的操作
+pub enum LeaseAction {
+    Reserve,
+    Activate(i64),
+    Release,
 }
 
 /// 命令处理器：处理 Controller 下发的命令并发送响应
@@ -566,6 +855,26 @@ pub async fn handle_controller_commands(
                     let _ = grpc.send_response(resp).await;
                 }
 
+                ControllerCommand::PortLease { request_id, lease, action } => {
+                    let result = match lease {
+                        Some(lease) => match action {
+                            LeaseAction::Reserve => control.reserve_port(&lease).await,
+                            LeaseAction::Activate(proxy_id) => control.activate_proxy(&lease, proxy_id).await,
+                            LeaseAction::Release => control.release_port(&lease).await,
+                        },
+                        None => Err(anyhow!("缺少端口预留信息")),
+                    };
+                    let ack = match result {
+                        Ok(()) => oxiproxy::CommandAck { success: true, error: None },
+                        Err(e) => oxiproxy::CommandAck { success: false, error: Some(e.to_string()) },
+                    };
+                    let resp = oxiproxy::AgentServerResponse {
+                        request_id,
+                        result: Some(AgentResult::CommandAck(ack)),
+                    };
+                    let _ = grpc.send_response(resp).await;
+                }
+
                 ControllerCommand::GetStatus { request_id } => {
                     let result = control.get_server_status().await;
                     let resp = match result {
@@ -583,6 +892,15 @@ pub async fn handle_controller_commands(
                                 result: Some(AgentResult::ServerStatus(oxiproxy::ServerStatus {
                                     connected_clients: clients,
                                     active_proxy_count: status.active_proxy_count as u32,
+                                    reaped_connections: status.reaped_connections,
+                                    accept_rate_limited: status.accept_rate_limited,
+                                    accept_pending_rejected: status.accept_pending_rejected,
+                                    active_relays: status.active_relays,
+                                    relay_limit: status.relay_limit,
+                                    relay_rejected: status.relay_rejected,
+                                    open_fds: status.open_fds,
+                                    fd_limit: status.fd_limit,
+                                    kcp: Some((&status.kcp).into()),
                                 })),
                             }
                         }
@@ -658,22 +976,46 @@ pub async fn handle_controller_commands(
                     let _ = grpc.send_response(resp).await;
                 }
 
-                ControllerCommand::UpdateProtocol { request_id, tunnel_protocol } => {
-                    let result = tm.switch_protocol(&tunnel_protocol).await;
-                    let ack = match result {
-                        Ok(()) => oxiproxy::CommandAck { success: true, error: None },
-                        Err(e) => oxiproxy::CommandAck { success: false, error: Some(e.to_string()) },
+                ControllerCommand::UpdateProtocol { request_id, tunnel_protocol, transport, staged } => {
+                    let result = if staged {
+                        match tm.stage(&tunnel_protocol, transport).await {
+                            Ok(port) => AgentResult::ProtocolStaged(oxiproxy::ProtocolStaged { port: port as u32 }),
+                            Err(e) => {
+                                warn!("分阶段切换协议失败: {}", e);
+                                AgentResult::CommandAck(oxiproxy::CommandAck { success: false, error: Some(e.to_string()) })
+                            }
+                        }
+                    } else {
+                        let ack = match tm.switch_protocol(&tunnel_protocol, transport).await {
+                            Ok(()) => oxiproxy::CommandAck { success: true, error: None },
+                            Err(e) => oxiproxy::CommandAck { success: false, error: Some(e.to_string()) },
+                        };
+                        AgentResult::CommandAck(ack)
                     };
                     let resp = oxiproxy::AgentServerResponse {
                         request_id,
-                        result: Some(AgentResult::CommandAck(ack)),
+                        result: Some(result),
                     };
                     let _ = grpc.send_response(resp).await;
                 }
 
-                ControllerCommand::UpdateSpeedLimit { request_id, speed_limit } => {
+                ControllerCommand::RetireListener { request_id } => {
+                    tm.retire_previous().await;
+                    let resp = oxiproxy::AgentServerResponse {
+                        request_id,
+                        result: Some(AgentResult::CommandAck(oxiproxy::CommandAck {
+                            success: true,
+                            error: None,
+                        })),
+                    };
+                    let _ = grpc.send_response(resp).await;
+                }
+
+                ControllerCommand::UpdateSpeedLimit { request_id, speed_limit, user_limits, fair_share } => {
                     sl.update_rate(speed_limit as u64);
+                    sl.set_fair(fair_share);
                     info!("速度限制已更新: {} bytes/s", speed_limit);
+                    super::speed_limiter::users().set(&user_limits);
                     let resp = oxiproxy::AgentServerResponse {
                         request_id,
                         result: Some(AgentResult::CommandAck(oxiproxy::CommandAck {
@@ -684,14 +1026,76 @@ pub async fn handle_controller_commands(
                     let _ = grpc.send_response(resp).await;
                 }
 
-                ControllerCommand::SoftwareUpdate { request_id } => {
-                    info!("收到远程软件更新指令，开始更新...");
-                    let update_result = tokio::task::spawn_blocking(perform_node_self_update).await;
+                ControllerCommand::UpdateMitigation { request_id, rule } => {
+                    let rule = super::mitigation::rule_from_grpc(rule);
+                    info!("节点默认的来源 IP 处置规则已{}", if rule.is_some() { "更新" } else { "取消" });
+                    super::mitigation::global().set_node_rule(rule);
+                    let resp = oxiproxy::AgentServerResponse {
+                        request_id,
+                        result: Some(AgentResult::CommandAck(oxiproxy::CommandAck {
+                            success: true,
+                            error: None,
+                        })),
+                    };
+                    let _ = grpc.send_response(resp).await;
+                }
+
+                ControllerCommand::ReleaseMitigation { request_id, proxy_id, source_ip } => {
+                    let ack = match source_ip.parse() {
+                        Ok(ip) => {
+                            if super::mitigation::global().release(proxy_id, ip) {
+                                info!("已解除来源 {} 在代理 #{} 上的处置", ip, proxy_id);
+                            }
+                            oxiproxy::CommandAck { success: true, error: None }
+                        }
+                        Err(_) => oxiproxy::CommandAck {
+                            success: false,
+                            error: Some(format!("无效的来源 IP: {}", source_ip)),
+                        },
+                    };
+                    let resp = oxiproxy::AgentServerResponse {
+                        request_id,
+                        result: Some(AgentResult::CommandAck(ack)),
+                    };
+                    let _ = grpc.send_response(resp).await;
+                }
+
+                ControllerCommand::SoftwareUpdate { request_id, target_version } => {
+                    let current = env!("CARGO_PKG_VERSION");
+                    if target_version.as_deref() == Some(current) {
+                        info!("当前已是目标版本 {}，跳过更新", current);
+                        let resp = oxiproxy::AgentServerResponse {
+                            request_id,
+                            result: Some(AgentResult::SoftwareUpdate(oxiproxy::SoftwareUpdateResponse {
+                                success: true,
+                                error: None,
+                                new_version: Some(current.to_string()),
+                            })),
+                        };
+                        let _ = grpc.send_response(resp).await;
+                        return;
+                    }
+
+                    info!(
+                        "收到远程软件更新指令，开始更新到 {}...",
+                        target_version.as_deref().unwrap_or("最新版本")
+                    );
+                    grpc.send_update_progress(&request_id, "downloading", target_version.clone(), None).await;
+
+                    let tv = target_version.clone();
+                    let update_result = tokio::task::spawn_blocking(move || perform_node_self_update(tv.as_deref())).await;
                     let (success, error_msg, new_ver) = match update_result {
                         Ok(Ok(v)) => (true, None, Some(v)),
                         Ok(Err(e)) => (false, Some(e.to_string()), None),
                         Err(e) => (false, Some(e.to_string()), None),
                     };
+
+                    if success {
+                        grpc.send_update_progress(&request_id, "restarting", new_ver.clone(), None).await;
+                    } else {
+                        grpc.send_update_progress(&request_id, "failed", target_version, error_msg.clone()).await;
+                    }
+
                     let resp = oxiproxy::AgentServerResponse {
                         request_id,
                         result: Some(AgentResult::SoftwareUpdate(oxiproxy::SoftwareUpdateResponse {
@@ -707,6 +1111,40 @@ pub async fn handle_controller_commands(
                         std::process::exit(0);
                     }
                 }
+
+                ControllerCommand::Probe(cmd) => {
+                    super::probe::run(cmd, grpc).await;
+                }
+
+                ControllerCommand::FollowLogs(cmd) => {
+                    super::log_follow::run(cmd, grpc).await;
+                }
+
+                ControllerCommand::GetProxyEvents(cmd) => {
+                    let events = super::proxy_events::query(cmd.proxy_id, cmd.client_id.as_deref(), cmd.limit as usize);
+                    let resp = oxiproxy::AgentServerResponse {
+                        request_id: cmd.request_id,
+                        result: Some(AgentResult::ProxyEvents(oxiproxy::ProxyEventsResponse {
+                            events: events.into_iter().map(Into::into).collect(),
+                        })),
+                    };
+                    let _ = grpc.send_response(resp).await;
+                }
+
+                ControllerCommand::StartCapture(cmd) => {
+                    let (ack, task) = match super::capture::start(&cmd) {
+                        Ok(task) => (oxiproxy::CommandAck { success: true, error: None }, Some(task)),
+                        Err(e) => (oxiproxy::CommandAck { success: false, error: Some(e.to_string()) }, None),
+                    };
+                    let resp = oxiproxy::AgentServerResponse {
+                        request_id: cmd.request_id,
+                        result: Some(AgentResult::CommandAck(ack)),
+                    };
+                    let _ = grpc.send_response(resp).await;
+                    if let Some(task) = task {
+                        task.run(grpc).await;
+                    }
+                }
             }
         });
     }
@@ -775,13 +1213,21 @@ async fn read_node_logs(lines: u32) -> Result<Vec<LogEntry>> {
 }
 
 /// 执行节点自更新（阻塞操作，需在 spawn_blocking 中调用）
-fn perform_node_self_update() -> anyhow::Result<String> {
-    let status = self_update::backends::github::Update::configure()
+///
+/// `target_version` 为空时更新到最新版本
+fn perform_node_self_update(target_version: Option<&str>) -> anyhow::Result<String> {
+    let mut builder = self_update::backends::github::Update::configure();
+    if let Some(version) = target_version {
+        builder.target_version_tag(&format!("v{}", version.trim_start_matches('v')));
+    }
+
+    let status = builder
         .repo_owner("oxiproxy")
         .repo_name("oxiproxy")
         .bin_name("node")
         .identifier("node")
         .bin_path_in_archive("{bin}{bin_ext}")
+        .verifying_keys(common::update::verifying_keys()?)
         .show_download_progress(false)
         .current_version(env!("CARGO_PKG_VERSION"))
         .no_confirm(true)
@@ -0,0 +1,89 @@
+//! 节点健康检查 HTTP 服务
+//!
+//! - `GET /healthz`：存活探针，进程能响应即返回 200
+//! - `GET /readyz`：就绪探针，已连接 Controller 且隧道监听器已启动时返回 200，否则 503
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::get, Json, Router};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::{error, info};
+
+#[derive(Default)]
+pub struct HealthState {
+    controller_connected: AtomicBool,
+    tunnel_ready: AtomicBool,
+}
+
+impl HealthState {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    pub fn set_controller_connected(&self, connected: bool) {
+        self.controller_connected.store(connected, Ordering::Relaxed);
+    }
+
+    pub fn set_tunnel_ready(&self, ready: bool) {
+        self.tunnel_ready.store(ready, Ordering::Relaxed);
+    }
+
+    fn checks(&self) -> ReadinessChecks {
+        ReadinessChecks {
+            controller: self.controller_connected.load(Ordering::Relaxed),
+            tunnel: self.tunnel_ready.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ReadinessChecks {
+    controller: bool,
+    tunnel: bool,
+}
+
+#[derive(Serialize)]
+struct HealthStatus {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checks: Option<ReadinessChecks>,
+}
+
+async fn healthz() -> impl IntoResponse {
+    (StatusCode::OK, Json(HealthStatus { status: "ok", checks: None }))
+}
+
+async fn readyz(State(state): State<Arc<HealthState>>) -> impl IntoResponse {
+    let checks = state.checks();
+    if checks.controller && checks.tunnel {
+        (StatusCode::OK, Json(HealthStatus { status: "ok", checks: Some(checks) }))
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(HealthStatus { status: "unavailable", checks: Some(checks) }),
+        )
+    }
+}
+
+/// 启动健康检查 HTTP 服务
+pub fn start_health_server(port: u16, state: Arc<HealthState>) {
+    tokio::spawn(async move {
+        let app = Router::new()
+            .route("/healthz", get(healthz))
+            .route("/readyz", get(readyz))
+            .with_state(state);
+
+        let addr = common::utils::wildcard_addr(port);
+        match common::utils::bind_tcp_listener(addr) {
+            Ok(listener) => {
+                info!("健康检查服务: http://{}", addr);
+                if let Err(e) = axum::serve(listener, app).await {
+                    error!("健康检查服务错误: {}", e);
+                }
+            }
+            Err(e) => {
+                error!("健康检查服务启动失败 ({}): {}", addr, e);
+            }
+        }
+    });
+}
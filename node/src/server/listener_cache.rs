@@ -0,0 +1,72 @@
+//! 代理监听器状态缓存
+//!
+//! 将各客户端正在运行的代理配置写入本地文件。节点重启后先按缓存恢复监听器，
+//! 无需等待客户端重连和 Controller 重新下发配置；客户端重连前到达的访客连接
+//! 会排队等待客户端恢复，超时后关闭。
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use tracing::warn;
+
+use common::protocol::control::ProxyConfig;
+
+/// 默认缓存文件路径（相对于工作目录）
+const DEFAULT_CACHE_FILE: &str = "listener_cache.json";
+
+/// 缓存内容：client_id → 代理配置列表
+pub type CachedListeners = HashMap<String, Vec<ProxyConfig>>;
+
+pub struct ListenerCache {
+    /// 为 `None` 时不启用缓存
+    path: Option<PathBuf>,
+}
+
+impl ListenerCache {
+    /// 从环境变量 `OXIPROXY_LISTENER_CACHE` 读取缓存文件路径，设置为 `off` 时禁用
+    pub fn from_env() -> Self {
+        let path = match common::env::var("OXIPROXY_LISTENER_CACHE") {
+            Some(v) if v.eq_ignore_ascii_case("off") => None,
+            Some(v) => Some(PathBuf::from(v)),
+            None => Some(PathBuf::from(DEFAULT_CACHE_FILE)),
+        };
+        Self { path }
+    }
+
+    /// 读取缓存，文件不存在或解析失败时返回空
+    pub fn load(&self) -> CachedListeners {
+        let Some(path) = &self.path else {
+            return CachedListeners::new();
+        };
+        let content = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(_) => return CachedListeners::new(),
+        };
+        match serde_json::from_str(&content) {
+            Ok(cached) => cached,
+            Err(e) => {
+                warn!("解析监听器缓存 {} 失败: {}", path.display(), e);
+                CachedListeners::new()
+            }
+        }
+    }
+
+    /// 写入缓存（先写临时文件再重命名，避免进程中途退出留下损坏的文件）
+    pub fn save(&self, cached: &CachedListeners) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = write_atomic(path, cached) {
+            warn!("写入监听器缓存 {} 失败: {}", path.display(), e);
+        }
+    }
+}
+
+fn write_atomic(path: &PathBuf, cached: &CachedListeners) -> Result<()> {
+    let content = serde_json::to_string_pretty(cached)?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
@@ -8,14 +8,16 @@ use anyhow::Result;
 use async_trait::async_trait;
 use tokio::sync::RwLock;
 use std::collections::HashMap;
-use tracing::info;
+use tracing::{info, warn};
 
 use common::protocol::auth::ClientAuthProvider;
 use common::protocol::control::{
-    ConnectedClient, LogEntry, ProxyControl, ServerStatus,
+    ConnectedClient, LogEntry, PortLease, ProxyControl, ServerStatus,
 };
 use common::TunnelConnection;
+use common::utils::display_addr;
 
+use crate::server::client_connections::ClientConnections;
 use crate::server::proxy_server::{ConnectionProvider, ProxyListenerManager};
 use crate::server::client_logs;
 
@@ -24,16 +26,16 @@ use crate::server::client_logs;
 /// 直接调用 ProxyServer 内部组件，无需网络通信。
 pub struct LocalProxyControl {
     listener_manager: Arc<ProxyListenerManager>,
-    quic_connections: Arc<RwLock<HashMap<String, Arc<quinn::Connection>>>>,
-    tunnel_connections: Arc<RwLock<HashMap<String, Arc<Box<dyn TunnelConnection>>>>>,
+    quic_connections: Arc<RwLock<HashMap<String, ClientConnections<quinn::Connection>>>>,
+    tunnel_connections: Arc<RwLock<HashMap<String, ClientConnections<Box<dyn TunnelConnection>>>>>,
     auth_provider: Arc<dyn ClientAuthProvider>,
 }
 
 impl LocalProxyControl {
     pub fn new(
         listener_manager: Arc<ProxyListenerManager>,
-        quic_connections: Arc<RwLock<HashMap<String, Arc<quinn::Connection>>>>,
-        tunnel_connections: Arc<RwLock<HashMap<String, Arc<Box<dyn TunnelConnection>>>>>,
+        quic_connections: Arc<RwLock<HashMap<String, ClientConnections<quinn::Connection>>>>,
+        tunnel_connections: Arc<RwLock<HashMap<String, ClientConnections<Box<dyn TunnelConnection>>>>>,
         auth_provider: Arc<dyn ClientAuthProvider>,
     ) -> Self {
         Self {
@@ -91,16 +93,35 @@ impl ProxyControl for LocalProxyControl {
         Ok(())
     }
 
+    async fn reserve_port(&self, lease: &PortLease) -> Result<()> {
+        self.listener_manager
+            .hold_port(lease.proxy_type.as_str().into(), lease.remote_port)
+            .await
+    }
+
+    async fn activate_proxy(&self, lease: &PortLease, proxy_id: i64) -> Result<()> {
+        // 关闭预留的 socket 后由监听器立即重新绑定；预留已超时释放时直接启动
+        if !self.listener_manager.release_port(lease.proxy_type.as_str().into(), lease.remote_port) {
+            warn!("代理 #{} 的预留端口 {} 已超时释放，直接启动", proxy_id, lease.remote_port);
+        }
+        self.start_proxy(&lease.client_id, proxy_id).await
+    }
+
+    async fn release_port(&self, lease: &PortLease) -> Result<()> {
+        self.listener_manager.release_port(lease.proxy_type.as_str().into(), lease.remote_port);
+        Ok(())
+    }
+
     async fn get_connected_clients(&self) -> Result<Vec<ConnectedClient>> {
         let mut clients = Vec::new();
 
         // QUIC connections
         {
             let conns = self.quic_connections.read().await;
-            for (client_id, conn) in conns.iter() {
+            for (client_id, conn) in conns.iter().flat_map(|(id, set)| set.iter().map(move |c| (id, c))) {
                 clients.push(ConnectedClient {
                     client_id: client_id.clone(),
-                    remote_address: conn.remote_address().to_string(),
+                    remote_address: display_addr(conn.remote_address()).to_string(),
                     protocol: "quic".to_string(),
                 });
             }
@@ -109,10 +130,10 @@ impl ProxyControl for LocalProxyControl {
         // KCP/Tunnel connections
         {
             let conns = self.tunnel_connections.read().await;
-            for (client_id, conn) in conns.iter() {
+            for (client_id, conn) in conns.iter().flat_map(|(id, set)| set.iter().map(move |c| (id, c))) {
                 clients.push(ConnectedClient {
                     client_id: client_id.clone(),
-                    remote_address: conn.remote_address().to_string(),
+                    remote_address: display_addr(conn.remote_address()).to_string(),
                     protocol: "kcp".to_string(),
                 });
             }
@@ -125,7 +146,7 @@ impl ProxyControl for LocalProxyControl {
         // 目前只支持 QUIC 连接获取日志
         let conn = {
             let conns = self.quic_connections.read().await;
-            conns.get(client_id).cloned()
+            conns.get(client_id).and_then(|c| c.pick())
         };
 
         let conn = match conn {
@@ -147,9 +168,19 @@ impl ProxyControl for LocalProxyControl {
     async fn get_server_status(&self) -> Result<ServerStatus> {
         let clients = self.get_connected_clients().await?;
         let active_proxy_count = clients.len(); // 简化：用连接数近似
+        let resources = super::resource_guard::global();
         Ok(ServerStatus {
             connected_clients: clients,
             active_proxy_count,
+            reaped_connections: self.listener_manager.reaped_connections(),
+            accept_rate_limited: super::accept_guard::stats().rate_limited(),
+            accept_pending_rejected: super::accept_guard::stats().pending_rejected(),
+            active_relays: resources.active_relays(),
+            relay_limit: resources.relay_limit(),
+            relay_rejected: resources.rejected(),
+            open_fds: super::resource_guard::open_fds().unwrap_or(0),
+            fd_limit: resources.fd_limit(),
+            kcp: common::tunnel::kcp_stats(),
         })
     }
 }
@@ -0,0 +1,110 @@
+//! 实时日志跟随
+//!
+//! 按 Controller 的指令订阅节点日志缓冲区，每 500 毫秒（或攒满 100 条）把新日志通过
+//! `NodeLogChunk` 上报一次，直到收到停止指令。管理员关闭页面后 Controller 会下发停止指令；
+//! Controller 重启后不认识的跟随请求同样会被停止。
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, Level};
+
+use common::grpc::oxiproxy;
+use common::grpc::oxiproxy::agent_server_message::Payload as AgentPayload;
+use common::protocol::control::LogEntry;
+
+use super::grpc_client::AgentGrpcClient;
+
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_BATCH: usize = 100;
+
+fn follows() -> &'static Mutex<HashMap<String, CancellationToken>> {
+    static FOLLOWS: OnceLock<Mutex<HashMap<String, CancellationToken>>> = OnceLock::new();
+    FOLLOWS.get_or_init(Default::default)
+}
+
+/// 停止跟随
+pub fn stop(request_id: &str) {
+    if let Some(token) = follows().lock().unwrap_or_else(|e| e.into_inner()).remove(request_id) {
+        token.cancel();
+    }
+}
+
+/// 日志级别是否不低于 `min_level`（未指定时不过滤）
+fn level_matches(entry: &LogEntry, min_level: Option<Level>) -> bool {
+    min_level.is_none_or(|min| entry.level.parse::<Level>().is_ok_and(|level| level <= min))
+}
+
+/// 跟随日志并上报，直到被停止或连接不可用
+pub async fn run(cmd: oxiproxy::FollowLogsCommand, grpc: Arc<AgentGrpcClient>) {
+    let Some(buffer) = super::node_logs::get_global_log_buffer() else {
+        return;
+    };
+    let min_level = cmd.level.parse::<Level>().ok();
+    let request_id = cmd.request_id;
+
+    let cancel = CancellationToken::new();
+    follows().lock().unwrap_or_else(|e| e.into_inner()).insert(request_id.clone(), cancel.clone());
+    // 订阅前记录，本条日志不会出现在跟随结果中
+    info!("开始实时跟随日志: {}", request_id);
+    let mut rx = buffer.subscribe();
+
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    let mut batch: Vec<LogEntry> = Vec::new();
+    let mut skipped = 0u64;
+    loop {
+        let flush = tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = interval.tick() => true,
+            received = rx.recv() => match received {
+                Ok(entry) => {
+                    if level_matches(&entry, min_level) {
+                        batch.push(entry);
+                    }
+                    batch.len() >= MAX_BATCH
+                }
+                Err(RecvError::Lagged(n)) => {
+                    skipped += n;
+                    false
+                }
+                Err(RecvError::Closed) => break,
+            },
+        };
+        if !flush || (batch.is_empty() && skipped == 0) {
+            continue;
+        }
+
+        let msg = oxiproxy::AgentServerMessage {
+            payload: Some(AgentPayload::LogChunk(oxiproxy::NodeLogChunk {
+                request_id: request_id.clone(),
+                logs: batch
+                    .drain(..)
+                    .map(|l| oxiproxy::LogEntry { timestamp: l.timestamp, level: l.level, message: l.message })
+                    .collect(),
+                skipped: std::mem::take(&mut skipped),
+            })),
+        };
+        if grpc.shared_sender().send(msg).await.is_err() {
+            break;
+        }
+    }
+
+    follows().lock().unwrap_or_else(|e| e.into_inner()).remove(&request_id);
+    info!("停止实时跟随日志: {}", request_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_matches() {
+        let entry = |level: &str| LogEntry { timestamp: String::new(), level: level.to_string(), message: String::new() };
+        assert!(level_matches(&entry("ERROR"), Some(Level::WARN)));
+        assert!(!level_matches(&entry("INFO"), Some(Level::WARN)));
+        assert!(level_matches(&entry("INFO"), None));
+    }
+}
@@ -0,0 +1,409 @@
+//! 来源 IP 自动处置
+//!
+//! 按（代理, 来源 IP）统计并发连接数和收发速率，每秒结算一次。某个 IP 持续超过规则阈值
+//! `sustain_secs` 秒后，在 `penalty_secs` 内对其限速（该 IP 在此代理上的所有连接共享一个限速桶）
+//! 或封禁（拒绝新连接并断开已有连接），同时向 Controller 上报处置事件。
+//!
+//! 代理规则随代理配置下发，未设置时使用节点默认规则；两者都没有时不跟踪该代理。
+//! UDP 代理只统计来源发出的数据报，限速时丢弃超出速率的数据报。
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use common::grpc::oxiproxy;
+use common::grpc::oxiproxy::agent_server_message::Payload as AgentPayload;
+use common::mitigation::{MitigationAction, MitigationRule};
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use super::grpc_client::SharedGrpcSender;
+
+/// 结算间隔
+const TICK: Duration = Duration::from_secs(1);
+/// 限速时 UDP 数据报允许的突发时长，超出部分直接丢弃
+const UDP_BURST: Duration = Duration::from_secs(1);
+
+/// 正在生效的处置
+struct Penalty {
+    action: MitigationAction,
+    until: Instant,
+    /// 限速速率（字节/秒）
+    rate: u64,
+    /// 限速桶：下一个字节可以发送的时间
+    next_free: Instant,
+}
+
+impl Penalty {
+    /// 为 `bytes` 字节预留发送时间并返回需要等待的时长，等待超过 `max_wait` 时不预留
+    fn reserve(&mut self, bytes: usize, now: Instant, max_wait: Duration) -> Option<Duration> {
+        let start = self.next_free.max(now);
+        let wait = start - now;
+        if wait > max_wait {
+            return None;
+        }
+        self.next_free = start + Duration::from_secs_f64(bytes as f64 / self.rate.max(1) as f64);
+        Some(wait)
+    }
+}
+
+/// 单个来源 IP 在某个代理上的状态
+#[derive(Default)]
+struct Source {
+    bytes: AtomicU64,
+    connections: AtomicU32,
+    penalty: Mutex<Option<Penalty>>,
+    kick: Notify,
+}
+
+impl Source {
+    fn is_blocked(&self, now: Instant) -> bool {
+        self.penalty
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|p| p.action == MitigationAction::Block && p.until > now)
+    }
+
+    /// 来源被限速时按限速桶预留发送时间，见 [`Penalty::reserve`]
+    fn throttle(&self, bytes: usize, now: Instant, max_wait: Duration) -> Option<Duration> {
+        match self.penalty.lock().unwrap().as_mut() {
+            Some(p) if p.action == MitigationAction::Throttle && p.until > now => p.reserve(bytes, now, max_wait),
+            _ => Some(Duration::ZERO),
+        }
+    }
+}
+
+/// 一条 TCP 连接持有的来源状态，连接结束时释放并发计数
+pub struct SourceGuard {
+    source: Arc<Source>,
+}
+
+impl SourceGuard {
+    /// 记录转发的字节数，来源被限速时等待
+    pub async fn record(&self, bytes: usize) {
+        self.source.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        let delay = self.source.throttle(bytes, Instant::now(), Duration::MAX).unwrap_or_default();
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// 来源被封禁时返回
+    pub async fn blocked(&self) {
+        loop {
+            let notified = self.source.kick.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.source.is_blocked(Instant::now()) {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl Drop for SourceGuard {
+    fn drop(&mut self) {
+        self.source.connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+struct Entry {
+    source: Arc<Source>,
+    /// 开始持续超限的时间
+    over_since: Option<Instant>,
+}
+
+struct ProxyRule {
+    client_id: i64,
+    rule: Option<MitigationRule>,
+}
+
+#[derive(Default)]
+struct Inner {
+    node_rule: Option<MitigationRule>,
+    proxies: HashMap<i64, ProxyRule>,
+    sources: HashMap<(i64, IpAddr), Entry>,
+    last_sample: Option<Instant>,
+}
+
+impl Inner {
+    fn rule_for(&self, proxy_id: i64) -> Option<&MitigationRule> {
+        self.proxies
+            .get(&proxy_id)
+            .and_then(|p| p.rule.as_ref())
+            .or(self.node_rule.as_ref())
+    }
+
+    fn source(&mut self, proxy_id: i64, ip: IpAddr) -> Option<Arc<Source>> {
+        self.rule_for(proxy_id)?;
+        let entry = self.sources.entry((proxy_id, ip)).or_insert_with(|| Entry {
+            source: Arc::default(),
+            over_since: None,
+        });
+        Some(entry.source.clone())
+    }
+}
+
+#[derive(Default)]
+pub struct Mitigation {
+    inner: Mutex<Inner>,
+}
+
+impl Mitigation {
+    /// 设置节点默认规则
+    pub fn set_node_rule(&self, rule: Option<MitigationRule>) {
+        self.inner.lock().unwrap().node_rule = rule;
+    }
+
+    /// 代理监听器启动时登记其规则
+    pub fn set_proxy_rule(&self, proxy_id: i64, client_id: i64, rule: Option<MitigationRule>) {
+        self.inner.lock().unwrap().proxies.insert(proxy_id, ProxyRule { client_id, rule });
+    }
+
+    /// 新 TCP 连接到达：来源被封禁时返回 `None`
+    pub fn admit(&self, proxy_id: i64, ip: IpAddr) -> Option<SourceGuard> {
+        let source = self.inner.lock().unwrap().source(proxy_id, ip).unwrap_or_default();
+        if source.is_blocked(Instant::now()) {
+            return None;
+        }
+        source.connections.fetch_add(1, Ordering::Relaxed);
+        Some(SourceGuard { source })
+    }
+
+    /// UDP 数据报到达：来源被封禁或超出限速时返回 `false`，数据报应丢弃
+    pub fn admit_datagram(&self, proxy_id: i64, ip: IpAddr, len: usize) -> bool {
+        self.admit_datagram_at(proxy_id, ip, len, Instant::now())
+    }
+
+    fn admit_datagram_at(&self, proxy_id: i64, ip: IpAddr, len: usize, now: Instant) -> bool {
+        let Some(source) = self.inner.lock().unwrap().source(proxy_id, ip) else {
+            return true;
+        };
+        if source.is_blocked(now) || source.throttle(len, now, UDP_BURST).is_none() {
+            return false;
+        }
+        source.bytes.fetch_add(len as u64, Ordering::Relaxed);
+        true
+    }
+
+    /// 解除对来源的处置，返回此前是否有生效的处置
+    pub fn release(&self, proxy_id: i64, ip: IpAddr) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(entry) = inner.sources.get_mut(&(proxy_id, ip)) else {
+            return false;
+        };
+        entry.over_since = None;
+        super::firewall::unblock_source(proxy_id, ip);
+        let released = entry.source.penalty.lock().unwrap().take().is_some();
+        released
+    }
+
+    /// 结算一次，返回本次新触发的处置
+    pub fn sample_at(&self, now: Instant) -> Vec<oxiproxy::MitigationEvent> {
+        let mut guard = self.inner.lock().unwrap();
+        let elapsed = guard.last_sample.map_or(TICK, |last| now.saturating_duration_since(last));
+        if elapsed.is_zero() {
+            return Vec::new();
+        }
+        guard.last_sample = Some(now);
+        let secs = elapsed.as_secs_f64();
+
+        let inner = &mut *guard;
+        let mut events = Vec::new();
+        inner.sources.retain(|&(proxy_id, ip), entry| {
+            let rate = entry.source.bytes.swap(0, Ordering::Relaxed) as f64 / secs;
+            let connections = entry.source.connections.load(Ordering::Relaxed);
+            let rule = inner
+                .proxies
+                .get(&proxy_id)
+                .and_then(|p| p.rule.as_ref())
+                .or(inner.node_rule.as_ref());
+
+            let mut penalty = entry.source.penalty.lock().unwrap();
+            if penalty.as_ref().is_some_and(|p| p.until <= now) {
+                info!("来源 {} 在代理 #{} 上的处置已到期", ip, proxy_id);
+                *penalty = None;
+            }
+
+            match rule {
+                Some(rule) if penalty.is_none() && rule.exceeded_by(rate, connections) => {
+                    let since = *entry.over_since.get_or_insert(now);
+                    if now.duration_since(since) >= Duration::from_secs(rule.sustain_secs as u64) {
+                        let reason = describe(rule, rate, connections);
+                        warn!("来源 {} 在代理 #{} 上{}，{} {} 秒", ip, proxy_id, reason, action_label(rule.action), rule.penalty_secs);
+                        *penalty = Some(Penalty {
+                            action: rule.action,
+                            until: now + Duration::from_secs(rule.penalty_secs as u64),
+                            rate: rule.throttle_rate(),
+                            next_free: now,
+                        });
+                        entry.over_since = None;
+                        if rule.action == MitigationAction::Block {
+                            entry.source.kick.notify_waiters();
+                            super::firewall::block_source(proxy_id, ip, Duration::from_secs(rule.penalty_secs as u64));
+                        }
+                        events.push(oxiproxy::MitigationEvent {
+                            proxy_id,
+                            client_id: inner.proxies.get(&proxy_id).map_or(0, |p| p.client_id),
+                            source_ip: ip.to_string(),
+                            action: rule.action.as_str().to_string(),
+                            reason,
+                            duration_secs: rule.penalty_secs,
+                        });
+                    }
+                }
+                _ => entry.over_since = None,
+            }
+
+            connections > 0 || penalty.is_some() || entry.over_since.is_some()
+        });
+        events
+    }
+}
+
+fn action_label(action: MitigationAction) -> &'static str {
+    match action {
+        MitigationAction::Throttle => "限速",
+        MitigationAction::Block => "封禁",
+    }
+}
+
+/// 触发原因（持续超限的指标）
+fn describe(rule: &MitigationRule, rate: f64, connections: u32) -> String {
+    let mbps = rate * 8.0 / 1_000_000.0;
+    match (rule.max_mbps, rule.max_connections) {
+        (Some(max), _) if mbps > max => {
+            format!("速率 {:.1} Mbps 超过 {} Mbps 持续 {} 秒", mbps, max, rule.sustain_secs)
+        }
+        (_, Some(max)) => {
+            format!("并发连接 {} 超过 {} 持续 {} 秒", connections, max, rule.sustain_secs)
+        }
+        _ => format!("超过阈值持续 {} 秒", rule.sustain_secs),
+    }
+}
+
+/// 转换 Controller 下发的规则，无法识别的规则视为未设置
+pub fn rule_from_grpc(rule: Option<oxiproxy::GrpcMitigationRule>) -> Option<MitigationRule> {
+    MitigationRule::try_from(rule?)
+        .map_err(|e| warn!("忽略无效的来源 IP 处置规则: {}", e))
+        .ok()
+}
+
+/// 节点进程内的处置状态
+pub fn global() -> &'static Mitigation {
+    static MITIGATION: OnceLock<Mitigation> = OnceLock::new();
+    MITIGATION.get_or_init(Mitigation::default)
+}
+
+/// 启动结算任务，触发的处置通过控制流上报 Controller
+pub fn start(sender: SharedGrpcSender) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(TICK);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            for event in global().sample_at(Instant::now()) {
+                let msg = oxiproxy::AgentServerMessage {
+                    payload: Some(AgentPayload::MitigationEvent(event)),
+                };
+                if sender.send(msg).await.is_err() {
+                    warn!("上报来源 IP 处置事件失败");
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip() -> IpAddr {
+        "203.0.113.7".parse().unwrap()
+    }
+
+    fn rule(action: MitigationAction) -> MitigationRule {
+        MitigationRule {
+            max_mbps: Some(8.0),
+            max_connections: Some(2),
+            sustain_secs: 2,
+            action,
+            throttle_mbps: Some(1.0),
+            penalty_secs: 10,
+        }
+    }
+
+    #[test]
+    fn test_no_rule_is_not_tracked() {
+        let m = Mitigation::default();
+        let _guard = m.admit(1, ip()).unwrap();
+        assert!(m.admit_datagram(1, ip(), 100));
+        assert!(m.inner.lock().unwrap().sources.is_empty());
+    }
+
+    #[test]
+    fn test_block_after_sustained_connections() {
+        let m = Mitigation::default();
+        m.set_proxy_rule(1, 10, Some(rule(MitigationAction::Block)));
+        let start = Instant::now();
+        m.sample_at(start);
+
+        let guards: Vec<_> = (0..3).map(|_| m.admit(1, ip()).unwrap()).collect();
+        assert!(m.sample_at(start + TICK).is_empty());
+        assert!(m.sample_at(start + TICK * 2).is_empty());
+        let events = m.sample_at(start + TICK * 3);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].action, "block");
+        assert_eq!(events[0].client_id, 10);
+        assert!(m.admit(1, ip()).is_none());
+        // 其他代理不受影响
+        assert!(m.admit(2, ip()).is_some());
+
+        drop(guards);
+        assert!(m.release(1, ip()));
+        assert!(m.admit(1, ip()).is_some());
+    }
+
+    #[test]
+    fn test_short_burst_is_ignored() {
+        let m = Mitigation::default();
+        m.set_node_rule(Some(rule(MitigationAction::Block)));
+        let start = Instant::now();
+        m.sample_at(start);
+
+        assert!(m.admit_datagram_at(1, ip(), 2_000_000, start));
+        m.sample_at(start + TICK);
+        // 下一个窗口恢复正常，持续计时清零
+        m.sample_at(start + TICK * 2);
+        assert!(m.admit_datagram_at(1, ip(), 2_000_000, start + TICK * 2));
+        assert!(m.sample_at(start + TICK * 3).is_empty());
+        assert!(m.admit_datagram_at(1, ip(), 100, start + TICK * 3));
+    }
+
+    #[test]
+    fn test_throttle_expires() {
+        let m = Mitigation::default();
+        m.set_proxy_rule(1, 10, Some(rule(MitigationAction::Throttle)));
+        let start = Instant::now();
+        m.sample_at(start);
+
+        for i in 1..=3 {
+            assert!(m.admit_datagram_at(1, ip(), 2_000_000, start + TICK * (i - 1)));
+            let events = m.sample_at(start + TICK * i);
+            assert_eq!(events.len(), usize::from(i == 3));
+        }
+        // 限速 1 Mbps（125000 字节/秒）：排队超过 1 秒的数据报直接丢弃，丢弃的数据报不占用限速桶
+        let now = start + TICK * 3;
+        assert!(m.admit_datagram_at(1, ip(), 100_000, now));
+        assert!(m.admit_datagram_at(1, ip(), 100_000, now));
+        assert!(!m.admit_datagram_at(1, ip(), 100_000, now));
+        assert!(m.admit_datagram_at(1, ip(), 100_000, now + Duration::from_millis(700)));
+
+        m.sample_at(start + TICK * 13);
+        assert!(m.inner.lock().unwrap().sources.is_empty());
+    }
+}
@@ -1,13 +1,35 @@
 pub mod proxy_server;
 pub mod traffic;
+pub mod traffic_spool;
 pub mod client_logs;
 pub mod config_manager;
 pub mod local_proxy_control;
+pub mod listener_cache;
 pub mod grpc_client;
 pub mod grpc_auth_provider;
 pub mod node_logs;
+pub mod log_follow;
 pub mod tunnel_manager;
 pub mod speed_limiter;
+pub mod speed_meter;
+pub mod mitigation;
+pub mod connection_authz;
+pub mod accept_guard;
+pub mod resource_guard;
+pub mod health;
+pub mod probe;
+pub mod proxy_events;
+pub mod nat_probe;
+pub mod capabilities;
+pub mod firewall;
+pub mod port_mapping;
+pub mod tunnel_auth;
+pub mod client_connections;
+pub mod plugin;
+pub mod capture;
+pub mod dry_run;
+pub mod prebind;
+pub mod privileges;
 
 use anyhow::Result;
 use std::sync::Arc;
@@ -27,6 +49,7 @@ pub async fn run_server_controller_mode(
     protocol: String,
     tls_ca_cert: Option<Vec<u8>>,
     log_dir: Option<String>,
+    health_port: Option<u16>,
 ) -> Result<()> {
     // 初始化内存日志缓冲区（保存最近 1000 条日志）
     let log_buffer = node_logs::init_global_log_buffer(1000);
@@ -57,8 +80,41 @@ pub async fn run_server_controller_mode(
     info!("隧道端口: {}", bind_port);
     info!("隧道协议: {}", protocol);
 
+    // 提高文件描述符限制并开始检查资源使用
+    resource_guard::start();
+
+    // 启动连接插件
+    plugin::start_from_env();
+
+    // 健康检查服务（尽早启动，便于探针观察启动过程）
+    let health = health::HealthState::new();
+    if let Some(port) = health_port {
+        health::start_health_server(port, health.clone());
+    }
+
+    // NAT 探测应答（需在注册前启动，注册时上报端口）
+    if let Err(e) = nat_probe::start_from_env().await {
+        warn!("启动 NAT 探测失败: {}", e);
+    }
+
+    let tunnel_ports: Vec<u16> = [Some(bind_port), common::env::parse::<u16>("OXIPROXY_STAGING_PORT").filter(|p| *p != 0)]
+        .into_iter()
+        .flatten()
+        .collect();
+
+    // 防火墙管理（需在恢复代理监听器前启动）
+    firewall::start(firewall::ExemptPorts {
+        tunnel_ports: tunnel_ports.clone(),
+        health_port,
+        nat_probe_port: nat_probe::active_port(),
+    })
+    .await?;
+
+    // 路由器端口映射（家用网络部署，默认不启用）
+    port_mapping::start_from_env(tunnel_ports);
+
     // 首次连接 Controller 并认证（protocol 作为回退值，最终以 Controller 返回为准）
-    let (grpc_client, cmd_rx, authoritative_protocol, initial_speed_limit) = grpc_client::AgentGrpcClient::connect_and_authenticate(
+    let (grpc_client, cmd_rx, registration) = grpc_client::AgentGrpcClient::connect_and_authenticate(
         &controller_url,
         &token,
         bind_port,
@@ -67,24 +123,35 @@ pub async fn run_server_controller_mode(
     ).await?;
 
     let node_id = grpc_client.node_id().await;
-    info!("连接认证成功: 节点 #{}, Controller 协议: {}", node_id, authoritative_protocol);
+    health.set_controller_connected(true);
+    info!("连接认证成功: 节点 #{}, Controller 协议: {}", node_id, registration.tunnel_protocol);
 
     // 创建速度限制器（0 表示不限速）
-    let speed_limiter = speed_limiter::SpeedLimiter::new(initial_speed_limit.unwrap_or(0) as u64);
-    if let Some(limit) = initial_speed_limit {
+    let speed_limiter = speed_limiter::SpeedLimiter::new(registration.speed_limit.unwrap_or(0) as u64);
+    if let Some(limit) = registration.speed_limit {
         if limit > 0 {
             info!("速度限制: {} bytes/sec", limit);
         }
     }
+    speed_limiter.set_fair(registration.fair_share);
+    speed_limiter::users().set(&registration.user_speed_limits);
+
+    // 来源 IP 自动处置（节点默认规则，代理规则随代理配置下发）
+    mitigation::global().set_node_rule(registration.mitigation.clone());
+    mitigation::start(grpc_client.shared_sender().clone());
+
+    // 访客连接授权钩子（由 Controller 配置）
+    connection_authz::global().configure(registration.connection_authz);
+    connection_authz::start(grpc_client.shared_sender().clone(), grpc_client.shared_pending().clone());
 
     // 创建 gRPC 认证提供者（使用 SharedGrpcSender，重连后自动使用新 sender）
     let auth_provider: Arc<dyn ClientAuthProvider> = Arc::new(
         grpc_auth_provider::GrpcAuthProvider::new(&grpc_client, node_id)
     );
 
-    // 创建 gRPC 流量管理器（使用 SharedGrpcSender，重连后自动使用新 sender）
+    // 创建 gRPC 流量管理器（使用专用的流量上报流，重连后自动使用新连接）
     let traffic_manager = Arc::new(
-        traffic::TrafficManager::new(grpc_client.shared_sender().clone())
+        traffic::TrafficManager::new(grpc_client.clone())
     );
 
     // 创建配置管理器（使用默认值）
@@ -100,6 +167,12 @@ pub async fn run_server_controller_mode(
         )?
     );
 
+    // 按缓存恢复重启前的代理监听器，客户端重连前访客连接排队等待
+    proxy_server.restore_cached_listeners().await;
+
+    // 在隧道启动（客户端可以连接）之前预绑定启用代理的远程端口，提前发现端口冲突
+    prebind::run(&proxy_server.get_listener_manager(), registration.prebind_proxies, grpc_client.shared_sender()).await;
+
     // 创建本地代理控制实例
     let proxy_control: Arc<dyn ProxyControl> = Arc::new(local_proxy_control::LocalProxyControl::new(
         proxy_server.get_listener_manager(),
@@ -110,7 +183,8 @@ pub async fn run_server_controller_mode(
 
     // 创建并启动隧道管理器（使用 Controller 下发的权威协议）
     let tunnel_manager = Arc::new(tunnel_manager::TunnelManager::new(proxy_server.clone(), bind_port));
-    tunnel_manager.start(&authoritative_protocol, None).await?;
+    tunnel_manager.start(&registration.tunnel_protocol, registration.transport).await?;
+    health.set_tunnel_ready(true);
 
     // 启动首次 Controller 命令处理器
     let grpc_client_clone = grpc_client.clone();
@@ -133,6 +207,7 @@ pub async fn run_server_controller_mode(
     let protocol_clone = protocol.clone();
 
     let tls_ca_cert_clone = tls_ca_cert.clone();
+    let health_reconnect = health.clone();
 
     tokio::spawn(async move {
         // 等待首次连接的心跳/消息循环结束（通过检测 sender 是否可用）
@@ -143,34 +218,50 @@ pub async fn run_server_controller_mode(
             // 尝试发送一个心跳来检测连接是否存活
             let test_msg = common::grpc::oxiproxy::AgentServerMessage {
                 payload: Some(common::grpc::oxiproxy::agent_server_message::Payload::Heartbeat(
-                    common::grpc::oxiproxy::Heartbeat {
-                        timestamp: chrono::Utc::now().timestamp(),
-                    },
+                    speed_meter::heartbeat(),
                 )),
             };
 
             if grpc_client_reconnect.shared_sender().send(test_msg).await.is_err() {
                 warn!("检测到 gRPC 连接断开，开始重连...");
+                health_reconnect.set_controller_connected(false);
+
+                // Controller 关闭前下发了重连延迟时先等待，错开各节点的重连时间
+                let delay = common::grpc::reconnect::next_delay(Duration::ZERO);
+                if !delay.is_zero() {
+                    info!("{} ms 后重连", delay.as_millis());
+                    tokio::time::sleep(delay).await;
+                }
 
                 loop {
+                    // 分阶段切换协议后监听器可能位于备用端口，注册时上报实际端口
+                    let tunnel_port = tunnel_manager_reconnect.active_port().await;
                     match grpc_client_reconnect.reconnect(
                         &controller_url_clone,
                         &token_clone,
-                        bind_port,
+                        tunnel_port,
                         &protocol_clone,
                         tls_ca_cert_clone.as_deref(),
                     ).await {
-                        Ok((new_cmd_rx, new_protocol, new_speed_limit)) => {
+                        Ok((new_cmd_rx, new_registration)) => {
                             info!("gRPC 重连成功");
+                            health_reconnect.set_controller_connected(true);
 
                             // 更新速度限制
-                            if let Some(limit) = new_speed_limit {
+                            if let Some(limit) = new_registration.speed_limit {
                                 speed_limiter_reconnect.update_rate(limit as u64);
                             }
+                            speed_limiter_reconnect.set_fair(new_registration.fair_share);
+                            speed_limiter::users().set(&new_registration.user_speed_limits);
+                            mitigation::global().set_node_rule(new_registration.mitigation.clone());
+                            connection_authz::global().configure(new_registration.connection_authz);
 
-                            // 如果协议变更，切换隧道协议
-                            if !new_protocol.is_empty() {
-                                if let Err(e) = tunnel_manager_reconnect.switch_protocol(&new_protocol).await {
+                            // 如果协议或 KCP 参数变更，重启隧道监听器
+                            if !new_registration.tunnel_protocol.is_empty() {
+                                if let Err(e) = tunnel_manager_reconnect
+                                    .switch_protocol(&new_registration.tunnel_protocol, new_registration.transport)
+                                    .await
+                                {
                                     error!("重连后切换协议失败: {}", e);
                                 }
                             }
@@ -190,8 +281,9 @@ pub async fn run_server_controller_mode(
                         }
                         Err(e) => {
                             error!("gRPC 重连失败: {}", e);
-                            warn!("5秒后重试...");
-                            tokio::time::sleep(Duration::from_secs(5)).await;
+                            let delay = common::grpc::reconnect::next_delay(Duration::from_secs(5));
+                            warn!("{} ms 后重试...", delay.as_millis());
+                            tokio::time::sleep(delay).await;
                         }
                     }
                 }
@@ -199,26 +291,14 @@ pub async fn run_server_controller_mode(
         }
     });
 
-    // 等待终止信号
-    tokio::select! {
-        _ = tokio::signal::ctrl_c() => {
-            info!("收到 Ctrl+C 信号，正在关闭服务...");
-        }
-        _ = async {
-            #[cfg(unix)]
-            {
-                use tokio::signal::unix::{signal, SignalKind};
-                let mut sigterm = signal(SignalKind::terminate()).expect("failed to listen for SIGTERM");
-                sigterm.recv().await;
-            }
-            #[cfg(not(unix))]
-            {
-                std::future::pending::<()>().await;
-            }
-        } => {
-            info!("收到 SIGTERM 信号，正在关闭服务...");
-        }
-    }
+    // 等待终止信号（容器停止时发送 SIGTERM）
+    let signal = common::health::shutdown_signal().await;
+    info!("收到 {} 信号，正在关闭服务...", signal);
+    health.set_controller_connected(false);
+    tunnel_manager.stop().await;
+    firewall::cleanup().await;
+    port_mapping::cleanup().await;
+    plugin::stop_all();
 
     Ok(())
 }
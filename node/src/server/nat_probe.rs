@@ -0,0 +1,84 @@
+//! NAT 探测应答
+//!
+//! 在 `OXIPROXY_NAT_PROBE_PORT` 指定的端口 `P` 和 `P + 1` 上监听 UDP，回复探测请求的来源地址，
+//! 供客户端判断自身的 NAT 类型（协议见 `common::nat_probe`）。端口在节点注册时上报给 Controller，
+//! 再随代理列表下发给客户端。未设置该环境变量时不启用。
+
+use anyhow::{anyhow, Result};
+use std::sync::{Arc, OnceLock};
+use tokio::net::UdpSocket;
+use tracing::{debug, info};
+
+use common::nat_probe::{ProbeRequest, ProbeResponse, FLAG_ALT_PORT, FLAG_PAD_REPLY, MAX_REQUEST_LEN, REQUEST_LEN};
+
+/// 已启用的探测端口
+fn active() -> &'static OnceLock<u16> {
+    static ACTIVE: OnceLock<u16> = OnceLock::new();
+    &ACTIVE
+}
+
+/// 当前启用的探测端口（注册时上报），未启用时为 `None`
+pub fn active_port() -> Option<u16> {
+    active().get().copied()
+}
+
+/// 读取 `OXIPROXY_NAT_PROBE_PORT` 并启动应答，未设置时不做任何事
+pub async fn start_from_env() -> Result<()> {
+    let Some(port) = common::env::parse::<u16>("OXIPROXY_NAT_PROBE_PORT") else {
+        return Ok(());
+    };
+    if port == 0 || port == u16::MAX {
+        return Err(anyhow!("NAT 探测端口 {} 无效（需要使用该端口和下一个端口）", port));
+    }
+    let alt_port = port + 1;
+
+    let primary = Arc::new(
+        UdpSocket::bind(("0.0.0.0", port))
+            .await
+            .map_err(|e| anyhow!("绑定 NAT 探测端口 {} 失败: {}", port, e))?,
+    );
+    let alternate = Arc::new(
+        UdpSocket::bind(("0.0.0.0", alt_port))
+            .await
+            .map_err(|e| anyhow!("绑定 NAT 探测端口 {} 失败: {}", alt_port, e))?,
+    );
+
+    tokio::spawn(serve(primary.clone(), alternate.clone()));
+    tokio::spawn(serve(alternate, primary));
+    let _ = active().set(port);
+    info!("NAT 探测已启用: UDP {} / {}", port, alt_port);
+    Ok(())
+}
+
+/// 处理 `socket` 上收到的请求；请求带 `FLAG_ALT_PORT` 时从 `other` 回复，
+/// 带 `FLAG_PAD_REPLY` 时回复填充到与请求相同的长度
+async fn serve(socket: Arc<UdpSocket>, other: Arc<UdpSocket>) {
+    let mut buf = vec![0u8; MAX_REQUEST_LEN];
+    loop {
+        let (len, from) = match socket.recv_from(&mut buf).await {
+            Ok(v) => v,
+            // Windows 上对端端口不可达会使 recv_from 返回错误，忽略即可
+            Err(e) => {
+                debug!("NAT 探测接收失败: {}", e);
+                continue;
+            }
+        };
+        if len < REQUEST_LEN {
+            continue;
+        }
+        let Some(req) = ProbeRequest::decode(&buf[..len]) else {
+            continue;
+        };
+
+        let resp = ProbeResponse {
+            flags: req.flags,
+            txn: req.txn,
+            mapped: from,
+        };
+        let resp = if req.flags & FLAG_PAD_REPLY != 0 { resp.encode_padded(len) } else { resp.encode() };
+        let sender = if req.flags & FLAG_ALT_PORT != 0 { &other } else { &socket };
+        if let Err(e) = sender.send_to(&resp, from).await {
+            debug!("NAT 探测回复 {} 失败: {}", from, e);
+        }
+    }
+}
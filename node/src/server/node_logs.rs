@@ -1,9 +1,10 @@
 //! 节点日志缓冲区
 //!
-//! 提供内存中的日志缓冲区，用于跨平台日志查询。
+//! 提供内存中的日志缓冲区，用于跨平台日志查询；新日志同时广播给实时跟随的订阅者。
 
 use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
 use tracing::{Level, Subscriber};
 use tracing_subscriber::layer::{Context, Layer};
 use common::protocol::control::LogEntry;
@@ -13,6 +14,7 @@ use common::protocol::control::LogEntry;
 pub struct NodeLogBuffer {
     inner: Arc<Mutex<VecDeque<LogEntry>>>,
     max_size: usize,
+    live: broadcast::Sender<LogEntry>,
 }
 
 impl NodeLogBuffer {
@@ -21,6 +23,7 @@ impl NodeLogBuffer {
         Self {
             inner: Arc::new(Mutex::new(VecDeque::with_capacity(max_size))),
             max_size,
+            live: broadcast::channel(max_size.max(1)).0,
         }
     }
 
@@ -30,9 +33,17 @@ impl NodeLogBuffer {
         if buffer.len() >= self.max_size {
             buffer.pop_front();
         }
+        if self.live.receiver_count() > 0 {
+            let _ = self.live.send(entry.clone());
+        }
         buffer.push_back(entry);
     }
 
+    /// 订阅之后新增的日志
+    pub fn subscribe(&self) -> broadcast::Receiver<LogEntry> {
+        self.live.subscribe()
+    }
+
     /// 获取最后 N 条日志
     pub fn get_last(&self, count: usize) -> Vec<LogEntry> {
         let buffer = self.inner.lock().unwrap();
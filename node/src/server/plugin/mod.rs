@@ -0,0 +1,195 @@
+//! 连接插件
+//!
+//! 插件在节点上挂接 TCP 代理的访客连接：连接接入时（[`Plugin::on_open`]）可以拒绝连接或
+//! 返回一个连接级的 [`ConnectionHook`]，之后两个方向的每一段数据都会交给它检查，
+//! 连接结束时收到最终的流量统计。可以在不修改转发逻辑的情况下实现协议白名单、DLP 扫描、
+//! 自定义计费等功能。
+//!
+//! 插件编译在节点程序中，通过 `OXIPROXY_PLUGINS`（逗号分隔的插件名）启用，每个插件的配置
+//! 从 `OXIPROXY_PLUGIN_<插件名>` 读取（插件名转为大写、`-` 换成 `_`）。节点启动时按顺序
+//! 启动插件，启动失败的插件不会加载；节点退出时按相反顺序停止。新的插件实现 [`Plugin`]
+//! 并在 [`create`] 中登记即可。
+//!
+//! 钩子在转发路径上同步调用，不应执行阻塞操作，耗时的处理应交给插件自己的后台任务。
+//! 未启用任何插件时转发路径没有额外开销。UDP 代理不经过插件。
+
+mod protocol_allowlist;
+
+use std::net::SocketAddr;
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{anyhow, Result};
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+/// 数据方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// 访客 -> 客户端
+    Inbound,
+    /// 客户端 -> 访客
+    Outbound,
+}
+
+/// 插件对连接的处理结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Continue,
+    /// 断开连接，附带原因
+    Close(String),
+}
+
+/// 访客连接信息
+///
+/// 字段供插件读取，内置插件不一定全部用到
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    pub proxy_id: i64,
+    pub proxy_name: String,
+    pub client_id: String,
+    pub source: SocketAddr,
+    /// 客户端侧的目标地址
+    pub target_addr: String,
+}
+
+/// 连接结束时的统计
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConnectionStats {
+    pub bytes_inbound: u64,
+    pub bytes_outbound: u64,
+    pub duration_ms: u64,
+}
+
+/// 节点插件
+pub trait Plugin: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// 节点启动时调用，`config` 为 `OXIPROXY_PLUGIN_<插件名>` 的值（未设置时为空）
+    fn start(&mut self, _config: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// 节点退出时调用
+    fn stop(&self) {}
+
+    /// 访客连接接入、打开隧道流之前调用；返回 `Err` 拒绝连接，
+    /// 返回 `Ok(None)` 表示不关心该连接的数据
+    fn on_open(&self, conn: &ConnectionInfo) -> Result<Option<Box<dyn ConnectionHook>>, String>;
+}
+
+/// 连接级钩子，由 [`Plugin::on_open`] 为每个连接创建
+pub trait ConnectionHook: Send {
+    /// 每段转发的数据（转发前调用）
+    fn on_data(&mut self, _direction: Direction, _data: &[u8]) -> Verdict {
+        Verdict::Continue
+    }
+
+    /// 连接结束
+    fn on_close(&mut self, _stats: &ConnectionStats) {}
+}
+
+/// 按名称创建编译在节点中的插件
+fn create(name: &str) -> Option<Box<dyn Plugin>> {
+    match name {
+        protocol_allowlist::NAME => Some(Box::new(protocol_allowlist::ProtocolAllowlist::default())),
+        _ => None,
+    }
+}
+
+fn plugins() -> &'static OnceLock<Vec<Box<dyn Plugin>>> {
+    static PLUGINS: OnceLock<Vec<Box<dyn Plugin>>> = OnceLock::new();
+    &PLUGINS
+}
+
+fn config_var(name: &str) -> String {
+    format!("OXIPROXY_PLUGIN_{}", name.to_ascii_uppercase().replace('-', "_"))
+}
+
+/// 按 `OXIPROXY_PLUGINS` 启动插件
+pub fn start_from_env() {
+    let mut loaded: Vec<Box<dyn Plugin>> = Vec::new();
+    let names = common::env::var("OXIPROXY_PLUGINS").unwrap_or_default();
+    for name in names.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let Some(mut plugin) = create(name) else {
+            error!("未知的插件: {}", name);
+            continue;
+        };
+        if loaded.iter().any(|p| p.name() == plugin.name()) {
+            warn!("插件 {} 重复配置，已忽略", name);
+            continue;
+        }
+        let config = common::env::var(&config_var(name)).unwrap_or_default();
+        match plugin.start(&config) {
+            Ok(()) => {
+                info!("🧩 插件已启动: {}", plugin.name());
+                loaded.push(plugin);
+            }
+            Err(e) => error!("插件 {} 启动失败，未加载: {}", name, e),
+        }
+    }
+    if plugins().set(loaded).is_err() {
+        warn!("插件已经启动过，忽略重复启动");
+    }
+}
+
+/// 停止所有插件
+pub fn stop_all() {
+    for plugin in plugins().get().into_iter().flatten().rev() {
+        plugin.stop();
+        info!("插件已停止: {}", plugin.name());
+    }
+}
+
+/// 一个访客连接上所有插件的钩子
+pub struct Pipeline {
+    hooks: Mutex<Vec<(&'static str, Box<dyn ConnectionHook>)>>,
+    closed: Notify,
+}
+
+impl Pipeline {
+    /// 依次询问插件是否接受连接；没有插件关心该连接时返回 `Ok(None)`
+    pub fn open(conn: &ConnectionInfo) -> Result<Option<Pipeline>> {
+        let Some(plugins) = plugins().get().filter(|p| !p.is_empty()) else {
+            return Ok(None);
+        };
+        let mut hooks = Vec::new();
+        for plugin in plugins {
+            match plugin.on_open(conn) {
+                Ok(Some(hook)) => hooks.push((plugin.name(), hook)),
+                Ok(None) => {}
+                Err(reason) => return Err(anyhow!("插件 {} 拒绝连接: {}", plugin.name(), reason)),
+            }
+        }
+        if hooks.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Pipeline { hooks: Mutex::new(hooks), closed: Notify::new() }))
+    }
+
+    /// 把一段数据交给各插件检查，需要断开时返回原因并唤醒 [`Pipeline::closed`]
+    pub fn inspect(&self, direction: Direction, data: &[u8]) -> Result<()> {
+        let mut hooks = self.hooks.lock().unwrap_or_else(|e| e.into_inner());
+        for (name, hook) in hooks.iter_mut() {
+            if let Verdict::Close(reason) = hook.on_data(direction, data) {
+                self.closed.notify_one();
+                return Err(anyhow!("插件 {} 断开连接: {}", name, reason));
+            }
+        }
+        Ok(())
+    }
+
+    /// 等待插件要求断开连接
+    pub async fn closed(&self) {
+        self.closed.notified().await
+    }
+
+    /// 连接结束
+    pub fn close(&self, stats: &ConnectionStats) {
+        let mut hooks = self.hooks.lock().unwrap_or_else(|e| e.into_inner());
+        for (_, hook) in hooks.iter_mut() {
+            hook.on_close(stats);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! 示例插件：协议白名单
+//!
+//! 根据访客发送的第一段数据识别应用层协议，不在白名单中的连接直接断开。
+//! 配置为逗号分隔的协议名（`tls`、`http`、`ssh`），可以在前面加上 `<代理ID>:` 只对指定代理生效，
+//! 如 `OXIPROXY_PLUGIN_PROTOCOL_ALLOWLIST="tls,http"` 或 `"12:ssh,12:tls"`。
+//! 服务端先发数据的协议（如 SSH 的版本横幅由服务端先发送）以访客的第一段数据为准。
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Result};
+
+use super::{ConnectionHook, ConnectionInfo, Direction, Plugin, Verdict};
+
+pub const NAME: &str = "protocol-allowlist";
+
+const PROTOCOLS: &[&str] = &["tls", "http", "ssh"];
+
+const HTTP_METHODS: &[&[u8]] = &[
+    b"GET ", b"POST ", b"PUT ", b"HEAD ", b"DELETE ", b"OPTIONS ", b"PATCH ", b"CONNECT ", b"TRACE ", b"PRI * HTTP/2",
+];
+
+/// 识别第一段数据的协议
+fn detect(data: &[u8]) -> Option<&'static str> {
+    if data.len() >= 3 && data[0] == 0x16 && data[1] == 0x03 && data[2] <= 0x04 {
+        Some("tls")
+    } else if HTTP_METHODS.iter().any(|m| data.starts_with(m)) {
+        Some("http")
+    } else if data.starts_with(b"SSH-") {
+        Some("ssh")
+    } else {
+        None
+    }
+}
+
+#[derive(Default)]
+pub struct ProtocolAllowlist {
+    /// 对所有代理生效的白名单
+    global: HashSet<&'static str>,
+    /// 按代理设置的白名单，优先于全局白名单
+    per_proxy: HashMap<i64, HashSet<&'static str>>,
+}
+
+impl ProtocolAllowlist {
+    fn allowed(&self, proxy_id: i64) -> Option<&HashSet<&'static str>> {
+        self.per_proxy.get(&proxy_id).or((!self.global.is_empty()).then_some(&self.global))
+    }
+}
+
+impl Plugin for ProtocolAllowlist {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn start(&mut self, config: &str) -> Result<()> {
+        for item in config.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (proxy_id, protocol) = match item.split_once(':') {
+                Some((id, protocol)) => (Some(id.trim().parse::<i64>()?), protocol.trim()),
+                None => (None, item),
+            };
+            let protocol = protocol.to_ascii_lowercase();
+            let Some(&protocol) = PROTOCOLS.iter().find(|p| **p == protocol) else {
+                bail!("不支持的协议 {}（可选 {}）", protocol, PROTOCOLS.join("、"));
+            };
+            match proxy_id {
+                Some(id) => self.per_proxy.entry(id).or_default().insert(protocol),
+                None => self.global.insert(protocol),
+            };
+        }
+        if self.global.is_empty() && self.per_proxy.is_empty() {
+            bail!("未配置允许的协议");
+        }
+        Ok(())
+    }
+
+    fn on_open(&self, conn: &ConnectionInfo) -> Result<Option<Box<dyn ConnectionHook>>, String> {
+        Ok(self
+            .allowed(conn.proxy_id)
+            .map(|allowed| Box::new(FirstPacket { allowed: allowed.clone(), checked: false }) as Box<dyn ConnectionHook>))
+    }
+}
+
+struct FirstPacket {
+    allowed: HashSet<&'static str>,
+    checked: bool,
+}
+
+impl ConnectionHook for FirstPacket {
+    fn on_data(&mut self, direction: Direction, data: &[u8]) -> Verdict {
+        if self.checked || direction != Direction::Inbound {
+            return Verdict::Continue;
+        }
+        self.checked = true;
+        match detect(data) {
+            Some(protocol) if self.allowed.contains(protocol) => Verdict::Continue,
+            Some(protocol) => Verdict::Close(format!("协议 {} 不在白名单中", protocol)),
+            None => Verdict::Close("无法识别的协议".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conn(proxy_id: i64) -> ConnectionInfo {
+        ConnectionInfo {
+            proxy_id,
+            proxy_name: "web".to_string(),
+            client_id: "1".to_string(),
+            source: "203.0.113.7:50000".parse().unwrap(),
+            target_addr: "127.0.0.1:80".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_protocol_allowlist() {
+        let mut plugin = ProtocolAllowlist::default();
+        plugin.start("tls, http, 7:ssh").unwrap();
+
+        let mut hook = plugin.on_open(&conn(1)).unwrap().unwrap();
+        // 只检查访客发送的第一段数据
+        assert_eq!(hook.on_data(Direction::Outbound, b"\x00\x01"), Verdict::Continue);
+        assert_eq!(hook.on_data(Direction::Inbound, b"GET / HTTP/1.1\r\n"), Verdict::Continue);
+        assert_eq!(hook.on_data(Direction::Inbound, b"\x00\x01"), Verdict::Continue);
+
+        let mut hook = plugin.on_open(&conn(1)).unwrap().unwrap();
+        assert!(matches!(hook.on_data(Direction::Inbound, b"SSH-2.0-OpenSSH"), Verdict::Close(_)));
+
+        // 按代理设置的白名单优先
+        let mut hook = plugin.on_open(&conn(7)).unwrap().unwrap();
+        assert_eq!(hook.on_data(Direction::Inbound, b"SSH-2.0-OpenSSH"), Verdict::Continue);
+        let mut hook = plugin.on_open(&conn(7)).unwrap().unwrap();
+        assert!(matches!(hook.on_data(Direction::Inbound, &[0x16, 0x03, 0x01, 0x00]), Verdict::Close(_)));
+
+        assert!(ProtocolAllowlist::default().start("ftp").is_err());
+        assert!(ProtocolAllowlist::default().start("").is_err());
+    }
+}
@@ -0,0 +1,499 @@
+//! 路由器端口映射（UPnP IGD / NAT-PMP）
+//!
+//! 部署在家用路由器后的节点可以让路由器自动转发隧道端口和运行中代理的远程端口，无需手动配置端口转发。
+//! 通过 `OXIPROXY_PORT_MAPPING` 启用（`upnp`、`natpmp` 或 `auto`，默认不启用）：
+//!
+//! - 启动时映射隧道端口（TCP + UDP）和备用端口，代理监听器启动 / 停止后添加 / 删除对应端口的映射；
+//! - 映射带租期（`OXIPROXY_PORT_MAPPING_LEASE_SECS`，默认 3600 秒），每过半个租期续期一次；
+//! - 节点退出时删除全部映射。
+//!
+//! `auto` 先尝试 NAT-PMP（网关由默认路由得出，可用 `OXIPROXY_PORT_MAPPING_GATEWAY` 指定），失败再通过 SSDP 发现 UPnP 网关。
+
+use std::collections::BTreeSet;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use super::proxy_server::ProxyProtocol;
+
+/// 默认租期
+const DEFAULT_LEASE_SECS: u32 = 3600;
+/// 变更后等待合并的时间
+const DEBOUNCE: Duration = Duration::from_millis(500);
+/// NAT-PMP 网关端口
+const NATPMP_PORT: u16 = 5351;
+/// SSDP 组播地址
+const SSDP_ADDR: &str = "239.255.255.250:1900";
+/// 发现网关和单次请求的超时
+const DISCOVER_TIMEOUT: Duration = Duration::from_secs(3);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// 映射描述
+const DESCRIPTION: &str = "oxiproxy";
+/// 按优先级尝试的 UPnP 服务类型
+const UPNP_SERVICES: [&str; 3] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:2",
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+type Mapping = (&'static str, u16);
+
+/// 已发现的网关
+enum Gateway {
+    NatPmp { addr: SocketAddr },
+    Upnp { host: String, control_path: String, service: &'static str, local_ip: IpAddr },
+}
+
+impl Gateway {
+    async fn discover(method: &str) -> Result<Gateway> {
+        match method {
+            "natpmp" => Self::discover_natpmp().await,
+            "upnp" => Self::discover_upnp().await,
+            _ => match Self::discover_natpmp().await {
+                Ok(gw) => Ok(gw),
+                Err(e) => {
+                    info!("NAT-PMP 不可用（{}），尝试 UPnP", e);
+                    Self::discover_upnp().await
+                }
+            },
+        }
+    }
+
+    async fn discover_natpmp() -> Result<Gateway> {
+        let gateway = match common::env::parse::<Ipv4Addr>("OXIPROXY_PORT_MAPPING_GATEWAY") {
+            Some(ip) => ip,
+            None => {
+                let routes = std::fs::read_to_string("/proc/net/route")
+                    .map_err(|_| anyhow!("无法确定默认网关，请设置 OXIPROXY_PORT_MAPPING_GATEWAY"))?;
+                default_gateway(&routes).ok_or_else(|| anyhow!("未找到默认网关"))?
+            }
+        };
+        let addr = SocketAddr::from((gateway, NATPMP_PORT));
+        // 查询公网地址，确认网关支持 NAT-PMP
+        let resp = natpmp_request(addr, &[0, 0]).await?;
+        if resp.len() < 12 {
+            return Err(anyhow!("NAT-PMP 网关响应无效"));
+        }
+        let external = Ipv4Addr::new(resp[8], resp[9], resp[10], resp[11]);
+        info!("NAT-PMP 网关 {}，公网地址 {}", gateway, external);
+        Ok(Gateway::NatPmp { addr })
+    }
+
+    async fn discover_upnp() -> Result<Gateway> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        let search = format!(
+            "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\r\n",
+            SSDP_ADDR
+        );
+        socket.send_to(search.as_bytes(), SSDP_ADDR).await?;
+
+        let mut buf = [0u8; 2048];
+        let location = tokio::time::timeout(DISCOVER_TIMEOUT, async {
+            loop {
+                let (n, _) = socket.recv_from(&mut buf).await?;
+                if let Some(location) = ssdp_location(&String::from_utf8_lossy(&buf[..n])) {
+                    return Ok::<_, std::io::Error>(location);
+                }
+            }
+        })
+        .await
+        .map_err(|_| anyhow!("未发现 UPnP 网关"))??;
+
+        let (host, path) = split_url(&location).ok_or_else(|| anyhow!("无效的 UPnP 描述地址: {}", location))?;
+        let ((_, description), local_ip) = http_request(&host, &format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            path, host
+        ))
+        .await?;
+        let (service, control_url) = find_control_url(&description)
+            .ok_or_else(|| anyhow!("UPnP 网关不支持 WANIPConnection / WANPPPConnection"))?;
+        let control_path = match split_url(&control_url) {
+            Some((_, path)) => path,
+            None if control_url.starts_with('/') => control_url,
+            None => format!("/{}", control_url),
+        };
+        info!("UPnP 网关 {}（{}）", host, service);
+        Ok(Gateway::Upnp { host, control_path, service, local_ip })
+    }
+
+    async fn add(&self, (proto, port): Mapping, lease: u32) -> Result<()> {
+        match self {
+            Gateway::NatPmp { addr } => {
+                natpmp_request(*addr, &natpmp_map_request(proto, port, lease)).await?;
+                Ok(())
+            }
+            Gateway::Upnp { local_ip, .. } => {
+                let args = format!(
+                    "<NewRemoteHost></NewRemoteHost><NewExternalPort>{port}</NewExternalPort><NewProtocol>{proto}</NewProtocol>\
+                     <NewInternalPort>{port}</NewInternalPort><NewInternalClient>{ip}</NewInternalClient><NewEnabled>1</NewEnabled>\
+                     <NewPortMappingDescription>{DESCRIPTION}</NewPortMappingDescription>",
+                    proto = proto.to_uppercase(),
+                    ip = local_ip,
+                );
+                match self.soap("AddPortMapping", &format!("{}<NewLeaseDuration>{}</NewLeaseDuration>", args, lease)).await {
+                    // 725 OnlyPermanentLeasesSupported：部分路由器只支持永久映射
+                    Err(e) if e.to_string().contains("725") => {
+                        self.soap("AddPortMapping", &format!("{}<NewLeaseDuration>0</NewLeaseDuration>", args)).await
+                    }
+                    other => other,
+                }
+            }
+        }
+    }
+
+    async fn remove(&self, (proto, port): Mapping) -> Result<()> {
+        match self {
+            Gateway::NatPmp { addr } => {
+                natpmp_request(*addr, &natpmp_map_request(proto, port, 0)).await?;
+                Ok(())
+            }
+            Gateway::Upnp { .. } => {
+                let args = format!(
+                    "<NewRemoteHost></NewRemoteHost><NewExternalPort>{}</NewExternalPort><NewProtocol>{}</NewProtocol>",
+                    port,
+                    proto.to_uppercase()
+                );
+                self.soap("DeletePortMapping", &args).await
+            }
+        }
+    }
+
+    async fn soap(&self, action: &str, args: &str) -> Result<()> {
+        let Gateway::Upnp { host, control_path, service, .. } = self else {
+            return Ok(());
+        };
+        let body = format!(
+            "<?xml version=\"1.0\"?><s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+             s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body>\
+             <u:{action} xmlns:u=\"{service}\">{args}</u:{action}></s:Body></s:Envelope>"
+        );
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: text/xml; charset=\"utf-8\"\r\nSOAPAction: \"{}#{}\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            control_path,
+            host,
+            service,
+            action,
+            body.len(),
+            body
+        );
+        let ((status, resp), _) = http_request(host, &request).await?;
+        if status == 200 {
+            Ok(())
+        } else {
+            let code = xml_text(&resp, "errorCode").unwrap_or_default();
+            let desc = xml_text(&resp, "errorDescription").unwrap_or_default();
+            Err(anyhow!("{} 失败: HTTP {} {} {}", action, status, code, desc))
+        }
+    }
+}
+
+/// 从 `/proc/net/route` 中取默认路由的网关
+fn default_gateway(routes: &str) -> Option<Ipv4Addr> {
+    routes.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 3 || fields[1] != "00000000" {
+            return None;
+        }
+        // 网关为小端序十六进制
+        let raw = u32::from_str_radix(fields[2], 16).ok()?;
+        (raw != 0).then_some(Ipv4Addr::from(raw.swap_bytes()))
+    })
+}
+
+/// NAT-PMP 映射请求（RFC 6886），租期为 0 时删除映射
+fn natpmp_map_request(proto: &str, port: u16, lease: u32) -> Vec<u8> {
+    let opcode = if proto == "udp" { 1 } else { 2 };
+    let external = if lease == 0 { 0 } else { port };
+    let mut req = vec![0, opcode, 0, 0];
+    req.extend_from_slice(&port.to_be_bytes());
+    req.extend_from_slice(&external.to_be_bytes());
+    req.extend_from_slice(&lease.to_be_bytes());
+    req
+}
+
+/// 发送 NAT-PMP 请求并校验结果码，按 RFC 建议从 250ms 开始加倍重试
+async fn natpmp_request(addr: SocketAddr, req: &[u8]) -> Result<Vec<u8>> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.connect(addr).await?;
+    let mut buf = [0u8; 16];
+    let mut wait = Duration::from_millis(250);
+    for _ in 0..4 {
+        socket.send(req).await?;
+        if let Ok(Ok(n)) = tokio::time::timeout(wait, socket.recv(&mut buf)).await {
+            if n >= 8 && buf[1] == req[1] + 128 {
+                let code = u16::from_be_bytes([buf[2], buf[3]]);
+                if code != 0 {
+                    return Err(anyhow!("NAT-PMP 网关返回错误码 {}", code));
+                }
+                return Ok(buf[..n].to_vec());
+            }
+        }
+        wait *= 2;
+    }
+    Err(anyhow!("NAT-PMP 网关 {} 无响应", addr))
+}
+
+/// SSDP 响应中的 LOCATION
+fn ssdp_location(response: &str) -> Option<String> {
+    response.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim().eq_ignore_ascii_case("location").then(|| value.trim().to_string())
+    })
+}
+
+/// `http://host:port/path` → (`host:port`, `/path`)
+fn split_url(url: &str) -> Option<(String, String)> {
+    let rest = url.strip_prefix("http://")?;
+    let (host, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let host = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+    Some((host, path.to_string()))
+}
+
+/// 设备描述中第一个支持的 WAN 连接服务及其 controlURL
+fn find_control_url(description: &str) -> Option<(&'static str, String)> {
+    UPNP_SERVICES.iter().find_map(|service| {
+        let start = description.find(&format!("<serviceType>{}</serviceType>", service))?;
+        let service_xml = &description[start..];
+        let end = service_xml.find("</service>").unwrap_or(service_xml.len());
+        Some((*service, xml_text(&service_xml[..end], "controlURL")?))
+    })
+}
+
+/// 取第一个 `<tag>...</tag>` 的文本（忽略命名空间前缀）
+fn xml_text(xml: &str, tag: &str) -> Option<String> {
+    let open = xml.find(&format!("{}>", tag))? + tag.len() + 1;
+    let close = xml[open..].find("</")? + open;
+    Some(xml[open..close].trim().to_string())
+}
+
+/// 发送 HTTP/1.1 请求，返回 ((状态码, 正文), 本机地址)
+async fn http_request(host: &str, request: &str) -> Result<((u16, String), IpAddr)> {
+    let mut stream = tokio::time::timeout(REQUEST_TIMEOUT, TcpStream::connect(host))
+        .await
+        .map_err(|_| anyhow!("连接 {} 超时", host))??;
+    let local_ip = stream.local_addr()?.ip();
+    stream.write_all(request.as_bytes()).await?;
+    let mut raw = Vec::new();
+    tokio::time::timeout(REQUEST_TIMEOUT, stream.read_to_end(&mut raw))
+        .await
+        .map_err(|_| anyhow!("读取 {} 的响应超时", host))??;
+    let raw = String::from_utf8_lossy(&raw);
+    let (head, body) = raw.split_once("\r\n\r\n").ok_or_else(|| anyhow!("无效的 HTTP 响应"))?;
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| anyhow!("无效的 HTTP 响应"))?;
+    let chunked = head.lines().any(|l| {
+        l.split_once(':')
+            .is_some_and(|(n, v)| n.trim().eq_ignore_ascii_case("transfer-encoding") && v.trim().eq_ignore_ascii_case("chunked"))
+    });
+    let body = if chunked { dechunk(body) } else { body.to_string() };
+    Ok(((status, body), local_ip))
+}
+
+/// 解码 chunked 正文
+fn dechunk(body: &str) -> String {
+    let mut out = String::new();
+    let mut rest = body;
+    while let Some((size, after)) = rest.split_once("\r\n") {
+        let Ok(size) = usize::from_str_radix(size.split(';').next().unwrap_or("").trim(), 16) else {
+            break;
+        };
+        if size == 0 || after.len() < size {
+            break;
+        }
+        out.push_str(&after[..size]);
+        rest = after[size..].trim_start_matches("\r\n");
+    }
+    out
+}
+
+struct PortMapper {
+    method: String,
+    /// 后台发现网关后设置
+    gateway: OnceLock<Gateway>,
+    lease: u32,
+    /// 始终映射的端口（隧道端口）
+    fixed: Vec<Mapping>,
+    /// 运行中代理的端口
+    proxies: Mutex<BTreeSet<Mapping>>,
+    changed: Notify,
+}
+
+impl PortMapper {
+    fn desired(&self) -> BTreeSet<Mapping> {
+        let mut ports = self.proxies.lock().unwrap().clone();
+        ports.extend(self.fixed.iter().copied());
+        ports
+    }
+}
+
+fn mapper() -> &'static OnceLock<PortMapper> {
+    static MAPPER: OnceLock<PortMapper> = OnceLock::new();
+    &MAPPER
+}
+
+/// 读取 `OXIPROXY_PORT_MAPPING`，启用时在后台发现网关并映射隧道端口，未设置时不做任何事
+///
+/// 发现网关失败只记录警告，节点照常运行。
+pub fn start_from_env(tunnel_ports: Vec<u16>) {
+    let Some(method) = common::env::var("OXIPROXY_PORT_MAPPING") else {
+        return;
+    };
+    let method = method.to_ascii_lowercase();
+    if !matches!(method.as_str(), "upnp" | "natpmp" | "auto") {
+        warn!("环境变量 OXIPROXY_PORT_MAPPING 的值无效: {}（可选 upnp、natpmp、auto）", method);
+        return;
+    }
+    let lease = common::env::parse::<u32>("OXIPROXY_PORT_MAPPING_LEASE_SECS")
+        .filter(|l| *l >= 120)
+        .unwrap_or(DEFAULT_LEASE_SECS);
+    let fixed = tunnel_ports.into_iter().flat_map(|p| [("tcp", p), ("udp", p)]).collect();
+    let _ = mapper().set(PortMapper {
+        method,
+        gateway: OnceLock::new(),
+        lease,
+        fixed,
+        proxies: Mutex::new(BTreeSet::new()),
+        changed: Notify::new(),
+    });
+    tokio::spawn(sync_loop());
+}
+
+async fn sync_loop() {
+    let Some(m) = mapper().get() else {
+        return;
+    };
+    let gateway = match Gateway::discover(&m.method).await {
+        Ok(gw) => m.gateway.get_or_init(|| gw),
+        Err(e) => {
+            warn!("路由器端口映射不可用: {}", e);
+            return;
+        }
+    };
+    let renew_every = Duration::from_secs(m.lease as u64 / 2);
+    let mut mapped: BTreeSet<Mapping> = BTreeSet::new();
+    let mut renew_at = tokio::time::Instant::now();
+    loop {
+        let desired = m.desired();
+        let renew = tokio::time::Instant::now() >= renew_at;
+        let to_add: Vec<Mapping> = if renew {
+            desired.iter().copied().collect()
+        } else {
+            desired.difference(&mapped).copied().collect()
+        };
+        for mapping in to_add {
+            match gateway.add(mapping, m.lease).await {
+                Ok(()) => {
+                    if mapped.insert(mapping) {
+                        info!("已在路由器上映射 {} 端口 {}", mapping.0.to_uppercase(), mapping.1);
+                    }
+                }
+                Err(e) => warn!("映射 {} 端口 {} 失败: {}", mapping.0.to_uppercase(), mapping.1, e),
+            }
+        }
+        let stale: Vec<Mapping> = mapped.difference(&desired).copied().collect();
+        for mapping in stale {
+            if let Err(e) = gateway.remove(mapping).await {
+                warn!("删除 {} 端口 {} 的映射失败: {}", mapping.0.to_uppercase(), mapping.1, e);
+            }
+            mapped.remove(&mapping);
+        }
+        if renew {
+            renew_at = tokio::time::Instant::now() + renew_every;
+        }
+
+        tokio::select! {
+            _ = m.changed.notified() => tokio::time::sleep(DEBOUNCE).await,
+            _ = tokio::time::sleep_until(renew_at) => {}
+        }
+    }
+}
+
+/// 代理监听器变化后同步需要映射的端口
+pub fn sync_proxies(proxies: impl Iterator<Item = (i64, ProxyProtocol, u16)>) {
+    let Some(m) = mapper().get() else {
+        return;
+    };
+    let ports: BTreeSet<Mapping> = proxies
+        .map(|(_, protocol, port)| (if protocol == ProxyProtocol::Udp { "udp" } else { "tcp" }, port))
+        .collect();
+    let mut current = m.proxies.lock().unwrap();
+    if *current != ports {
+        *current = ports;
+        m.changed.notify_one();
+    }
+}
+
+/// 节点退出时删除全部映射
+pub async fn cleanup() {
+    let Some((m, gateway)) = mapper().get().and_then(|m| Some((m, m.gateway.get()?))) else {
+        return;
+    };
+    for mapping in m.desired() {
+        let _ = gateway.remove(mapping).await;
+    }
+    info!("已删除路由器端口映射");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_gateway() {
+        let routes = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+                      eth0\t0001A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\n\
+                      eth0\t00000000\t0101A8C0\t0003\t0\t0\t0\t00000000\n";
+        assert_eq!(default_gateway(routes), Some(Ipv4Addr::new(192, 168, 1, 1)));
+        assert_eq!(default_gateway("Iface\tDestination\tGateway\n"), None);
+    }
+
+    #[test]
+    fn test_natpmp_map_request() {
+        assert_eq!(
+            natpmp_map_request("tcp", 7000, 3600),
+            vec![0, 2, 0, 0, 0x1b, 0x58, 0x1b, 0x58, 0, 0, 0x0e, 0x10]
+        );
+        // 删除映射：外部端口和租期为 0
+        assert_eq!(natpmp_map_request("udp", 7000, 0), vec![0, 1, 0, 0, 0x1b, 0x58, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_upnp_parsing() {
+        let ssdp = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=120\r\nLocation: http://192.168.1.1:5000/rootDesc.xml\r\n\r\n";
+        assert_eq!(ssdp_location(ssdp).as_deref(), Some("http://192.168.1.1:5000/rootDesc.xml"));
+        assert_eq!(
+            split_url("http://192.168.1.1:5000/rootDesc.xml"),
+            Some(("192.168.1.1:5000".to_string(), "/rootDesc.xml".to_string()))
+        );
+        assert_eq!(split_url("http://router"), Some(("router:80".to_string(), "/".to_string())));
+
+        let description = "<root><service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>\
+            <controlURL>/ctl/L3F</controlURL></service><service>\
+            <serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>\
+            <controlURL>/ctl/IPConn</controlURL></service></root>";
+        assert_eq!(
+            find_control_url(description),
+            Some(("urn:schemas-upnp-org:service:WANIPConnection:1", "/ctl/IPConn".to_string()))
+        );
+
+        let fault = "<s:Fault><detail><UPnPError><errorCode>725</errorCode><errorDescription>OnlyPermanentLeasesSupported</errorDescription></UPnPError></detail></s:Fault>";
+        assert_eq!(xml_text(fault, "errorCode").as_deref(), Some("725"));
+    }
+
+    #[test]
+    fn test_dechunk() {
+        assert_eq!(dechunk("4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n"), "Wikipedia");
+    }
+}
@@ -0,0 +1,59 @@
+//! 启动时预绑定代理端口
+//!
+//! 节点启动时在注册请求中要求 Controller 下发节点上启用的代理，在客户端连接之前就绑定并持有它们的
+//! 远程端口。被主机上其他程序占用（或权限不足）的端口在启动时即可发现：记录 `bind_failed` 事件并
+//! 通过 `PortConflictReport` 上报 Controller，而不是等到访客首次连接才失败。客户端连接后监听器
+//! 接管持有的端口，代理被停止时释放。设置 `OXIPROXY_PREBIND_PORTS=false` 关闭。
+
+use common::grpc::oxiproxy;
+use common::grpc::oxiproxy::agent_server_message::Payload as AgentPayload;
+use tracing::{info, warn};
+
+use super::grpc_client::SharedGrpcSender;
+use super::proxy_events;
+use super::proxy_server::{ProxyListenerManager, ProxyProtocol};
+
+/// 是否在启动时预绑定代理端口（默认启用）
+pub fn enabled() -> bool {
+    common::env::parse::<bool>("OXIPROXY_PREBIND_PORTS").unwrap_or(true)
+}
+
+/// 预绑定所有代理的远程端口，无法绑定的上报 Controller
+pub async fn run(listener_manager: &ProxyListenerManager, proxies: Vec<oxiproxy::ProxyConfig>, sender: &SharedGrpcSender) {
+    if proxies.is_empty() {
+        return;
+    }
+    let mut conflicts = Vec::new();
+    for proxy in &proxies {
+        let protocol = ProxyProtocol::from(proxy.proxy_type.as_str());
+        let result = match u16::try_from(proxy.remote_port) {
+            Ok(port) => listener_manager
+                .prebind_port(&proxy.client_id, proxy.proxy_id, protocol, proxy.bind_ip.as_deref(), port)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(_) => Err(format!("远程端口 {} 无效", proxy.remote_port)),
+        };
+        if let Err(error) = result {
+            warn!("代理「{}」(#{}) 的 {} 端口 {} 无法绑定: {}", proxy.name, proxy.proxy_id, proxy.proxy_type.to_uppercase(), proxy.remote_port, error);
+            proxy_events::bind_failed(&proxy.client_id, proxy.proxy_id, proxy.remote_port as u16, &error);
+            conflicts.push(oxiproxy::PortConflict {
+                proxy_id: proxy.proxy_id,
+                client_id: proxy.client_id.clone(),
+                name: proxy.name.clone(),
+                proxy_type: proxy.proxy_type.clone(),
+                remote_port: proxy.remote_port,
+                error,
+            });
+        }
+    }
+    info!("已预绑定 {} 个代理端口，{} 个冲突", proxies.len() - conflicts.len(), conflicts.len());
+    if conflicts.is_empty() {
+        return;
+    }
+    let msg = oxiproxy::AgentServerMessage {
+        payload: Some(AgentPayload::PortConflicts(oxiproxy::PortConflictReport { conflicts })),
+    };
+    if sender.send(msg).await.is_err() {
+        warn!("上报端口冲突失败：与 Controller 的连接已断开");
+    }
+}
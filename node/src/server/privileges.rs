@@ -0,0 +1,210 @@
+//! 降权运行与低端口权限
+//!
+//! 代理的远程端口为 80 / 443 等 1024 以下的端口时，节点需要 root 权限或 `CAP_NET_BIND_SERVICE`
+//! 能力。以 root 启动并指定 `--user` 时，节点在创建 tokio runtime 之前（此时只有主线程）切换到
+//! 该用户，并只保留监听低端口所需的 `CAP_NET_BIND_SERVICE`（启用防火墙管理时另外保留
+//! `CAP_NET_ADMIN` / `CAP_NET_RAW`，并设为 ambient 能力供 nft / iptables 子进程继承），
+//! 之后 Controller 下发的低端口代理仍可正常监听。
+//!
+//! 能力是线程属性，必须在创建 worker 线程之前降权，新线程会继承主线程的能力集。
+//! 非 Linux 的 Unix 系统不支持保留能力，降权后只能监听非特权端口。
+
+use anyhow::Result;
+
+/// 降权参数
+#[derive(clap::Args)]
+pub struct PrivilegeArgs {
+    /// 以 root 启动时，在启动后切换到该用户运行（用户名或 UID），只保留监听低端口的能力
+    #[arg(long, env = "OXIPROXY_RUN_AS_USER")]
+    pub user: Option<String>,
+
+    /// 切换到的用户组（组名或 GID，默认为用户的主组）
+    #[arg(long, env = "OXIPROXY_RUN_AS_GROUP", requires = "user")]
+    pub group: Option<String>,
+}
+
+/// 按参数降权，`owned_paths`（如日志目录）会先交给目标用户，未指定 `--user` 时不做任何事
+///
+/// 必须在创建 tokio runtime 之前调用。
+pub fn drop_privileges(args: &PrivilegeArgs, manage_firewall: bool, owned_paths: &[&str]) -> Result<()> {
+    let Some(user) = args.user.as_deref() else {
+        return Ok(());
+    };
+    imp::drop_privileges(user, args.group.as_deref(), manage_firewall, owned_paths)
+}
+
+/// 当前进程能否监听 1024 以下的端口
+pub fn can_bind_privileged_ports() -> bool {
+    imp::can_bind_privileged_ports()
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::ffi::CString;
+
+    use anyhow::{anyhow, bail, Result};
+
+    /// 解析用户名或 UID，返回 (UID, 主组 GID)
+    fn lookup_user(user: &str) -> Result<(libc::uid_t, libc::gid_t)> {
+        let passwd = match user.parse::<libc::uid_t>() {
+            Ok(uid) => unsafe { libc::getpwuid(uid) },
+            Err(_) => {
+                let name = CString::new(user).map_err(|_| anyhow!("用户名无效: {}", user))?;
+                unsafe { libc::getpwnam(name.as_ptr()) }
+            }
+        };
+        if passwd.is_null() {
+            bail!("用户 {} 不存在", user);
+        }
+        let passwd = unsafe { &*passwd };
+        Ok((passwd.pw_uid, passwd.pw_gid))
+    }
+
+    /// 解析组名或 GID
+    fn lookup_group(group: &str) -> Result<libc::gid_t> {
+        if let Ok(gid) = group.parse::<libc::gid_t>() {
+            return Ok(gid);
+        }
+        let name = CString::new(group).map_err(|_| anyhow!("组名无效: {}", group))?;
+        let entry = unsafe { libc::getgrnam(name.as_ptr()) };
+        if entry.is_null() {
+            bail!("用户组 {} 不存在", group);
+        }
+        Ok(unsafe { (*entry).gr_gid })
+    }
+
+    fn check(ret: libc::c_int, action: &str) -> Result<()> {
+        if ret != 0 {
+            bail!("{}失败: {}", action, std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn drop_privileges(user: &str, group: Option<&str>, manage_firewall: bool, owned_paths: &[&str]) -> Result<()> {
+        let (uid, primary_gid) = lookup_user(user)?;
+        let gid = group.map(lookup_group).transpose()?.unwrap_or(primary_gid);
+
+        if unsafe { libc::geteuid() } != 0 {
+            if unsafe { libc::getuid() } == uid {
+                return Ok(());
+            }
+            bail!("切换到用户 {} 需要以 root 启动节点", user);
+        }
+
+        for path in owned_paths {
+            std::os::unix::fs::chown(path, Some(uid), Some(gid))
+                .map_err(|e| anyhow!("修改 {} 的所有者失败: {}", path, e))?;
+        }
+
+        #[cfg(target_os = "linux")]
+        check(unsafe { libc::prctl(libc::PR_SET_KEEPCAPS, 1 as libc::c_ulong, 0 as libc::c_ulong, 0 as libc::c_ulong, 0 as libc::c_ulong) }, "保留能力")?;
+        check(unsafe { libc::setgroups(1, &gid) }, "设置附加组")?;
+        check(unsafe { libc::setgid(gid) }, "切换用户组")?;
+        check(unsafe { libc::setuid(uid) }, "切换用户")?;
+
+        #[cfg(target_os = "linux")]
+        caps::retain(manage_firewall)?;
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = manage_firewall;
+            eprintln!("当前系统不支持保留能力，降权后无法监听 1024 以下的端口");
+        }
+
+        // 日志系统此时还未初始化，直接输出到标准输出
+        println!("已切换到用户 {} (uid={}, gid={})", user, uid, gid);
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn can_bind_privileged_ports() -> bool {
+        (unsafe { libc::geteuid() == 0 })
+            || caps::effective().is_some_and(|caps| caps & caps::mask(caps::CAP_NET_BIND_SERVICE) != 0)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn can_bind_privileged_ports() -> bool {
+        unsafe { libc::geteuid() == 0 }
+    }
+
+    #[cfg(target_os = "linux")]
+    mod caps {
+        use anyhow::{bail, Result};
+
+        pub const CAP_NET_BIND_SERVICE: u32 = 10;
+        const CAP_NET_ADMIN: u32 = 12;
+        const CAP_NET_RAW: u32 = 13;
+        const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+        #[repr(C)]
+        struct CapHeader {
+            version: u32,
+            pid: libc::c_int,
+        }
+
+        #[repr(C)]
+        #[derive(Default, Clone, Copy)]
+        struct CapData {
+            effective: u32,
+            permitted: u32,
+            inheritable: u32,
+        }
+
+        pub fn mask(cap: u32) -> u64 {
+            1 << cap
+        }
+
+        /// 当前线程的有效能力集（`/proc/self/status` 中的 CapEff）
+        pub fn effective() -> Option<u64> {
+            let status = std::fs::read_to_string("/proc/self/status").ok()?;
+            let value = status.lines().find_map(|line| line.strip_prefix("CapEff:"))?;
+            u64::from_str_radix(value.trim(), 16).ok()
+        }
+
+        /// setuid 之后只保留需要的能力（setuid 会清空有效能力集，需要重新设置）
+        pub fn retain(manage_firewall: bool) -> Result<()> {
+            let mut caps = vec![CAP_NET_BIND_SERVICE];
+            if manage_firewall {
+                caps.extend([CAP_NET_ADMIN, CAP_NET_RAW]);
+            }
+            let bits = caps.iter().fold(0u32, |bits, &cap| bits | mask(cap) as u32);
+            let header = CapHeader { version: LINUX_CAPABILITY_VERSION_3, pid: 0 };
+            let data = [CapData { effective: bits, permitted: bits, inheritable: bits }, CapData::default()];
+            if unsafe { libc::syscall(libc::SYS_capset, &header, data.as_ptr()) } != 0 {
+                bail!("设置能力失败: {}", std::io::Error::last_os_error());
+            }
+
+            // 防火墙规则由 nft / iptables 子进程写入，需要 ambient 能力才能在 exec 后保留
+            if manage_firewall {
+                for cap in [CAP_NET_ADMIN, CAP_NET_RAW] {
+                    // prctl 是变参函数，参数需按 unsigned long 传递
+                    let ret = unsafe {
+                        libc::prctl(
+                            libc::PR_CAP_AMBIENT,
+                            libc::PR_CAP_AMBIENT_RAISE as libc::c_ulong,
+                            cap as libc::c_ulong,
+                            0 as libc::c_ulong,
+                            0 as libc::c_ulong,
+                        )
+                    };
+                    if ret != 0 {
+                        eprintln!("设置 ambient 能力 {} 失败（需要 Linux 4.3+）: {}", cap, std::io::Error::last_os_error());
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use anyhow::{bail, Result};
+
+    pub fn drop_privileges(_user: &str, _group: Option<&str>, _manage_firewall: bool, _owned_paths: &[&str]) -> Result<()> {
+        bail!("--user 仅支持 Unix 系统")
+    }
+
+    pub fn can_bind_privileged_ports() -> bool {
+        true
+    }
+}
@@ -0,0 +1,209 @@
+//! 连通性探测
+//!
+//! 按 Controller 的指令从节点向目标发起 TCP 连接、ping 或 traceroute，每得到一条结果就通过
+//! `ProbeStep` 上报，最后一条（`done`）携带汇总结果。ping 和 traceroute 调用系统命令，
+//! 不需要节点具备原始套接字权限。
+
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+use common::grpc::oxiproxy;
+use common::grpc::oxiproxy::agent_server_message::Payload as AgentPayload;
+
+use super::grpc_client::AgentGrpcClient;
+
+/// 单次探测的默认超时
+const DEFAULT_TIMEOUT_MS: u32 = 3000;
+/// 探测次数上限
+const MAX_COUNT: u32 = 10;
+/// traceroute 的最大跳数
+const MAX_HOPS: u32 = 20;
+/// 整个探测的时间上限（traceroute 可能较慢）
+const PROBE_DEADLINE: Duration = Duration::from_secs(90);
+
+/// 按顺序上报探测结果
+struct StepSender {
+    grpc: Arc<AgentGrpcClient>,
+    request_id: String,
+    seq: u32,
+}
+
+impl StepSender {
+    async fn send(&mut self, success: bool, rtt_ms: Option<f64>, message: String, done: bool) {
+        self.seq += 1;
+        let msg = oxiproxy::AgentServerMessage {
+            payload: Some(AgentPayload::ProbeStep(oxiproxy::ProbeStep {
+                request_id: self.request_id.clone(),
+                seq: self.seq,
+                success,
+                rtt_ms,
+                message,
+                done,
+            })),
+        };
+        if self.grpc.shared_sender().send(msg).await.is_err() {
+            warn!("上报探测结果失败");
+        }
+    }
+}
+
+/// 执行一次探测并上报结果
+pub async fn run(cmd: oxiproxy::ProbeCommand, grpc: Arc<AgentGrpcClient>) {
+    let mut steps = StepSender {
+        grpc,
+        request_id: cmd.request_id.clone(),
+        seq: 0,
+    };
+    let count = cmd.count.clamp(1, MAX_COUNT);
+    let timeout = Duration::from_millis(if cmd.timeout_ms == 0 { DEFAULT_TIMEOUT_MS } else { cmd.timeout_ms } as u64);
+    info!("执行连通性探测: {} {}:{}", cmd.kind, cmd.host, cmd.port);
+
+    // 主机名会作为系统命令的参数，拒绝以 '-' 开头等可能被解析为选项的值
+    if !valid_host(&cmd.host) {
+        steps.send(false, None, format!("无效的目标主机: {}", cmd.host), true).await;
+        return;
+    }
+
+    let probe = async {
+        match cmd.kind.as_str() {
+            "tcp" => probe_tcp(&mut steps, &cmd.host, cmd.port, count, timeout).await,
+            "ping" => probe_ping(&mut steps, &cmd.host, count, timeout).await,
+            "traceroute" => probe_traceroute(&mut steps, &cmd.host, timeout).await,
+            other => (false, format!("不支持的探测类型: {}", other)),
+        }
+    };
+    let (success, summary) = match tokio::time::timeout(PROBE_DEADLINE, probe).await {
+        Ok(result) => result,
+        Err(_) => (false, format!("探测超过 {} 秒未完成", PROBE_DEADLINE.as_secs())),
+    };
+    steps.send(success, None, summary, true).await;
+}
+
+/// 依次建立 `count` 次 TCP 连接，记录每次的连接耗时
+async fn probe_tcp(steps: &mut StepSender, host: &str, port: u32, count: u32, timeout: Duration) -> (bool, String) {
+    let Ok(port) = u16::try_from(port) else {
+        return (false, format!("无效的端口: {}", port));
+    };
+    if port == 0 {
+        return (false, "TCP 探测需要指定目标端口".to_string());
+    }
+
+    let mut rtts = Vec::new();
+    for i in 0..count {
+        if i > 0 {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        let start = Instant::now();
+        match tokio::time::timeout(timeout, TcpStream::connect((host, port))).await {
+            Ok(Ok(stream)) => {
+                let rtt = start.elapsed().as_secs_f64() * 1000.0;
+                let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or_else(|_| format!("{}:{}", host, port));
+                rtts.push(rtt);
+                steps.send(true, Some(rtt), format!("已连接 {}", peer), false).await;
+            }
+            Ok(Err(e)) => steps.send(false, None, format!("连接失败: {}", e), false).await,
+            Err(_) => steps.send(false, None, format!("连接超时（{} ms）", timeout.as_millis()), false).await,
+        }
+    }
+
+    let summary = if rtts.is_empty() {
+        format!("{}:{} 不可达（0/{} 次成功）", host, port, count)
+    } else {
+        format!(
+            "{}:{} 可达（{}/{} 次成功，平均 {:.1} ms）",
+            host,
+            port,
+            rtts.len(),
+            count,
+            rtts.iter().sum::<f64>() / rtts.len() as f64
+        )
+    };
+    (!rtts.is_empty(), summary)
+}
+
+/// 调用系统 ping 命令
+async fn probe_ping(steps: &mut StepSender, host: &str, count: u32, timeout: Duration) -> (bool, String) {
+    let mut command = Command::new("ping");
+    #[cfg(windows)]
+    command.args(["-n", &count.to_string(), "-w", &timeout.as_millis().to_string(), host]);
+    #[cfg(not(windows))]
+    command.args(["-c", &count.to_string(), "-W", &timeout.as_secs().max(1).to_string(), host]);
+    run_command(steps, command, "ping").await
+}
+
+/// 调用系统 traceroute（Windows 上为 tracert）命令，逐跳上报
+async fn probe_traceroute(steps: &mut StepSender, host: &str, timeout: Duration) -> (bool, String) {
+    #[cfg(windows)]
+    let command = {
+        let mut command = Command::new("tracert");
+        command.args(["-d", "-h", &MAX_HOPS.to_string(), "-w", &timeout.as_millis().to_string(), host]);
+        command
+    };
+    #[cfg(not(windows))]
+    let command = {
+        let mut command = Command::new("traceroute");
+        command.args(["-n", "-q", "1", "-m", &MAX_HOPS.to_string(), "-w", &timeout.as_secs().max(1).to_string(), host]);
+        command
+    };
+    run_command(steps, command, "traceroute").await
+}
+
+/// 运行探测命令，将标准输出的每一行作为一条结果上报
+async fn run_command(steps: &mut StepSender, mut command: Command, name: &str) -> (bool, String) {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return (false, format!("无法执行 {}: {}", name, e)),
+    };
+
+    if let Some(stdout) = child.stdout.take() {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let line = line.trim_end().to_string();
+            if line.is_empty() {
+                continue;
+            }
+            let rtt = parse_rtt_ms(&line);
+            steps.send(rtt.is_some(), rtt, line, false).await;
+        }
+    }
+
+    match child.wait_with_output().await {
+        Ok(output) if output.status.success() => (true, format!("{} 完成", name)),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            if stderr.is_empty() {
+                (false, format!("{} 失败（{}）", name, output.status))
+            } else {
+                (false, format!("{} 失败: {}", name, stderr))
+            }
+        }
+        Err(e) => (false, format!("{} 执行失败: {}", name, e)),
+    }
+}
+
+/// 从 ping / traceroute 的输出行中提取第一个 "<数字> ms" 形式的耗时
+fn parse_rtt_ms(line: &str) -> Option<f64> {
+    line.match_indices("ms").find_map(|(pos, _)| {
+        let before = line[..pos].trim_end();
+        let digits = before.chars().rev().take_while(|c| c.is_ascii_digit() || *c == '.').count();
+        before[before.len() - digits..].parse().ok()
+    })
+}
+
+/// 目标主机只允许域名 / IP 地址中会出现的字符
+fn valid_host(host: &str) -> bool {
+    !host.is_empty()
+        && host.len() <= 253
+        && !host.starts_with('-')
+        && host.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '_'))
+}
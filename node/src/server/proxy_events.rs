@@ -0,0 +1,169 @@
+//! 代理生命周期事件
+//!
+//! 监听器启停、端口绑定失败、客户端接入 / 断开和隧道协议切换作为带类型字段的结构化事件
+//! 保存在内存中（最多 2000 条），Controller 通过 `GetProxyEventsCommand` 拉取，
+//! 汇总各节点的事件生成代理的时间线。
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+
+use chrono::{DateTime, Utc};
+
+use common::grpc::oxiproxy;
+
+const MAX_EVENTS: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyEventKind {
+    ListenerStarted,
+    ListenerStopped,
+    BindFailed,
+    ClientAttached,
+    ClientDetached,
+    ProtocolSwitched,
+}
+
+impl ProxyEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProxyEventKind::ListenerStarted => "listener_started",
+            ProxyEventKind::ListenerStopped => "listener_stopped",
+            ProxyEventKind::BindFailed => "bind_failed",
+            ProxyEventKind::ClientAttached => "client_attached",
+            ProxyEventKind::ClientDetached => "client_detached",
+            ProxyEventKind::ProtocolSwitched => "protocol_switched",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProxyEvent {
+    pub timestamp: DateTime<Utc>,
+    pub kind: ProxyEventKind,
+    /// 客户端和节点级事件为 None
+    pub proxy_id: Option<i64>,
+    /// 节点级事件为 None
+    pub client_id: Option<String>,
+    pub port: Option<u16>,
+    /// 补充说明（失败原因、停止原因、协议变化等）
+    pub detail: String,
+}
+
+impl ProxyEvent {
+    /// 是否属于指定代理的时间线
+    fn concerns(&self, proxy_id: Option<i64>, client_id: Option<&str>) -> bool {
+        match (self.proxy_id, proxy_id) {
+            (Some(own), Some(wanted)) => own == wanted,
+            (None, Some(_)) => self.client_id.is_none() || self.client_id.as_deref() == client_id,
+            (_, None) => client_id.is_none() || self.client_id.as_deref() == client_id,
+        }
+    }
+}
+
+impl From<ProxyEvent> for oxiproxy::ProxyEvent {
+    fn from(event: ProxyEvent) -> Self {
+        Self {
+            timestamp: event.timestamp.to_rfc3339(),
+            kind: event.kind.as_str().to_string(),
+            proxy_id: event.proxy_id,
+            client_id: event.client_id,
+            port: event.port.map(u32::from),
+            detail: event.detail,
+        }
+    }
+}
+
+fn events() -> &'static Mutex<VecDeque<ProxyEvent>> {
+    static EVENTS: OnceLock<Mutex<VecDeque<ProxyEvent>>> = OnceLock::new();
+    EVENTS.get_or_init(|| Mutex::new(VecDeque::with_capacity(MAX_EVENTS)))
+}
+
+fn record(kind: ProxyEventKind, proxy_id: Option<i64>, client_id: Option<&str>, port: Option<u16>, detail: String) {
+    let mut events = events().lock().unwrap_or_else(|e| e.into_inner());
+    if events.len() >= MAX_EVENTS {
+        events.pop_front();
+    }
+    events.push_back(ProxyEvent {
+        timestamp: Utc::now(),
+        kind,
+        proxy_id,
+        client_id: client_id.map(str::to_string),
+        port,
+        detail,
+    });
+}
+
+pub fn listener_started(client_id: &str, proxy_id: i64, port: u16, detail: String) {
+    record(ProxyEventKind::ListenerStarted, Some(proxy_id), Some(client_id), Some(port), detail);
+}
+
+pub fn listener_stopped(client_id: &str, proxy_id: i64, reason: &str) {
+    record(ProxyEventKind::ListenerStopped, Some(proxy_id), Some(client_id), None, reason.to_string());
+}
+
+pub fn bind_failed(client_id: &str, proxy_id: i64, port: u16, error: impl std::fmt::Display) {
+    record(ProxyEventKind::BindFailed, Some(proxy_id), Some(client_id), Some(port), error.to_string());
+}
+
+pub fn client_attached(client_id: i64, remote_addr: impl std::fmt::Display) {
+    record(ProxyEventKind::ClientAttached, None, Some(&client_id.to_string()), None, format!("来自 {}", remote_addr));
+}
+
+pub fn client_detached(client_id: &str, reason: &str) {
+    record(ProxyEventKind::ClientDetached, None, Some(client_id), None, reason.to_string());
+}
+
+/// 隧道协议切换（协议不变时为传输参数变更）
+pub fn protocol_switched(from: &str, to: &str, port: u16) {
+    let detail = if from == to {
+        format!("{} 参数变更", to.to_uppercase())
+    } else {
+        format!("{} -> {}", from.to_uppercase(), to.to_uppercase())
+    };
+    record(ProxyEventKind::ProtocolSwitched, None, None, Some(port), detail);
+}
+
+/// 查询事件（按时间顺序），`limit` 为 0 时返回全部
+pub fn query(proxy_id: Option<i64>, client_id: Option<&str>, limit: usize) -> Vec<ProxyEvent> {
+    let events = events().lock().unwrap_or_else(|e| e.into_inner());
+    let mut matched: Vec<ProxyEvent> = events
+        .iter()
+        .rev()
+        .filter(|e| e.concerns(proxy_id, client_id))
+        .take(if limit == 0 { usize::MAX } else { limit })
+        .cloned()
+        .collect();
+    matched.reverse();
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(proxy_id: Option<i64>, client_id: Option<&str>) -> ProxyEvent {
+        ProxyEvent {
+            timestamp: Utc::now(),
+            kind: ProxyEventKind::ListenerStarted,
+            proxy_id,
+            client_id: client_id.map(str::to_string),
+            port: None,
+            detail: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_concerns() {
+        // 代理自身的事件
+        assert!(event(Some(1), Some("7")).concerns(Some(1), Some("7")));
+        assert!(!event(Some(2), Some("7")).concerns(Some(1), Some("7")));
+        // 所属客户端的接入 / 断开
+        assert!(event(None, Some("7")).concerns(Some(1), Some("7")));
+        assert!(!event(None, Some("8")).concerns(Some(1), Some("7")));
+        // 节点级事件
+        assert!(event(None, None).concerns(Some(1), Some("7")));
+        // 不指定代理时按客户端过滤
+        assert!(event(Some(2), Some("7")).concerns(None, Some("7")));
+        assert!(event(None, None).concerns(None, None));
+    }
+}
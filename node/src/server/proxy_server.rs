@@ -1,11 +1,12 @@
 use anyhow::Result;
-use quinn::{Endpoint, ServerConfig, TransportConfig, VarInt};
+use quinn::{Endpoint, ServerConfig, VarInt};
 use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::net::{TcpStream, UdpSocket};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
@@ -14,16 +15,24 @@ use serde::{Serialize, Deserialize};
 
 use crate::server::traffic::TrafficManager;
 use crate::server::config_manager::ConfigManager;
-use common::KcpConfig;
+use crate::server::listener_cache::ListenerCache;
+use crate::server::proxy_events;
+use crate::server::tunnel_auth;
+use crate::server::client_connections::ClientConnections;
+use common::{KcpConfig, QuicConfig};
+use common::tunnel::{build_transport_config, read_datagram, write_datagram, MAX_DATAGRAM_SIZE};
 
 // 从共享库导入隧道模块
 use common::{
     TunnelConnection, TunnelSendStream, TunnelRecvStream,
     TunnelListener, KcpListener, TcpTunnelListener, QuicSendStream, QuicRecvStream
 };
-use common::utils::create_configured_udp_socket;
+use common::utils::{
+    bind_tcp_listener, create_configured_udp_socket, display_addr, join_host_port,
+    wildcard_addr,
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum ProxyProtocol {
     Tcp,
@@ -57,20 +66,68 @@ impl ProxyProtocol {
     }
 }
 
-// UDP会话信息
-#[allow(dead_code)]
+// UDP会话信息（每个来源地址复用一条分帧的隧道流）
 struct UdpSession {
-    target_addr: SocketAddr,
+    sender: tokio::sync::mpsc::Sender<Vec<u8>>,
     last_activity: tokio::time::Instant,
 }
 
+/// 单个 UDP 会话排队等待发往隧道的数据报上限，超出时丢弃（与 UDP 语义一致）
+const UDP_SESSION_QUEUE: usize = 256;
+
+/// TCP 转发连接的默认空闲超时（代理未单独设置时使用）
+pub const DEFAULT_TCP_IDLE_TIMEOUT_SECS: u32 = 600;
+
+/// 访客连接到达时客户端不在线，最多等待客户端重连的时间（秒）
+const DEFAULT_CLIENT_WAIT_SECS: u64 = 10;
+
+/// 等待客户端重连的时间（环境变量 `OXIPROXY_CLIENT_WAIT_SECS`，0 表示不等待）
+fn client_wait_timeout() -> Duration {
+    static WAIT: std::sync::OnceLock<Duration> = std::sync::OnceLock::new();
+    *WAIT.get_or_init(|| {
+        Duration::from_secs(
+            common::env::parse::<u64>("OXIPROXY_CLIENT_WAIT_SECS").unwrap_or(DEFAULT_CLIENT_WAIT_SECS),
+        )
+    })
+}
+
+/// 代理的监听地址：代理配置的 IP 优先，其次节点默认的 `OXIPROXY_PROXY_BIND_ADDR`，都未设置时监听所有地址
+fn proxy_bind_addr(bind_ip: Option<&str>, port: u16) -> SocketAddr {
+    static DEFAULT_IP: std::sync::OnceLock<Option<IpAddr>> = std::sync::OnceLock::new();
+    let configured = bind_ip.and_then(|ip| match ip.parse::<IpAddr>() {
+        Ok(ip) => Some(ip),
+        Err(_) => {
+            warn!("代理监听地址无效，使用默认监听地址: {}", ip);
+            None
+        }
+    });
+    match configured.or(*DEFAULT_IP.get_or_init(|| common::env::parse::<IpAddr>("OXIPROXY_PROXY_BIND_ADDR"))) {
+        Some(ip) => SocketAddr::new(ip, port),
+        None => wildcard_addr(port),
+    }
+}
+
+/// 监听失败的原因，权限不足时附带处理建议（该错误会原样显示在管理界面）
+fn describe_bind_error(e: &anyhow::Error) -> String {
+    match e.downcast_ref::<std::io::Error>().map(|e| e.kind()) {
+        Some(std::io::ErrorKind::PermissionDenied) => format!("{}（{}）", e, common::check::LOW_PORT_HINT),
+        _ => e.to_string(),
+    }
+}
+
+/// 单个 UDP 代理同时保持的分帧会话上限（yamux 默认最多 512 条流），超出时淘汰最久未活动的会话
+const UDP_MAX_SESSIONS_PER_PROXY: usize = 128;
+
+/// accept 失败后的等待时间
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
 pub struct ProxyServer {
     cert: CertificateDer<'static>,
     key: PrivateKeyDer<'static>,
     traffic_manager: Arc<TrafficManager>,
     listener_manager: Arc<ProxyListenerManager>,
-    client_connections: Arc<RwLock<HashMap<String, Arc<quinn::Connection>>>>,
-    tunnel_connections: Arc<RwLock<HashMap<String, Arc<Box<dyn TunnelConnection>>>>>,
+    client_connections: Arc<RwLock<HashMap<String, ClientConnections<quinn::Connection>>>>,
+    tunnel_connections: Arc<RwLock<HashMap<String, ClientConnections<Box<dyn TunnelConnection>>>>>,
     config_manager: Arc<ConfigManager>,
     auth_provider: Arc<dyn common::protocol::auth::ClientAuthProvider>,
 }
@@ -83,10 +140,15 @@ pub enum UnifiedConnection {
 }
 
 impl UnifiedConnection {
-    pub async fn open_bi(&self) -> Result<(Box<dyn TunnelSendStream>, Box<dyn TunnelRecvStream>)> {
+    /// 打开双向流，QUIC 连接上按代理优先级设置流的发送优先级（其他隧道不支持，忽略）
+    pub async fn open_bi(
+        &self,
+        priority: common::protocol::control::ProxyPriority,
+    ) -> Result<(Box<dyn TunnelSendStream>, Box<dyn TunnelRecvStream>)> {
         match self {
             UnifiedConnection::Quic(conn) => {
                 let (send, recv) = conn.open_bi().await?;
+                let _ = send.set_priority(priority.stream_priority());
                 Ok((
                     Box::new(QuicSendStream::new(send)) as Box<dyn TunnelSendStream>,
                     Box::new(QuicRecvStream::new(recv)) as Box<dyn TunnelRecvStream>,
@@ -99,27 +161,54 @@ impl UnifiedConnection {
     }
 }
 
+/// 节点重启后按缓存恢复的监听器等待客户端重连的时间，超时仍未重连则停止
+const RESTORED_CLIENT_GRACE_SECS: u64 = 120;
+
+/// 预留端口的最长持有时间，超时未激活则自动释放
+const PORT_HOLD_TTL: Duration = Duration::from_secs(30);
+
+/// 两阶段创建代理时预留或节点启动时预绑定的端口：持有已绑定的 socket，激活或释放时关闭
+struct HeldPort {
+    seq: u64,
+    /// 预绑定端口所属的代理，监听器启动或代理停止时释放；两阶段创建的预留为 `None`
+    owner: Option<i64>,
+    _socket: Box<dyn std::any::Any + Send + Sync>,
+}
+
+// 运行中的代理监听器
+struct ActiveListener {
+    config: common::protocol::control::ProxyConfig,
+    handle: JoinHandle<()>,
+}
+
 // 代理监听器管理器
 pub struct ProxyListenerManager {
-    // client_id -> (proxy_id, JoinHandle)
-    listeners: Arc<RwLock<HashMap<String, HashMap<i64, JoinHandle<()>>>>>,
+    // client_id -> (proxy_id, ActiveListener)
+    listeners: Arc<RwLock<HashMap<String, HashMap<i64, ActiveListener>>>>,
+    /// 运行中的代理配置缓存，用于节点重启后恢复监听器
+    cache: ListenerCache,
     // UDP会话管理: (client_id, proxy_id) -> (source_addr -> UdpSession)
     udp_sessions: Arc<RwLock<HashMap<(String, i64), HashMap<SocketAddr, UdpSession>>>>,
     traffic_manager: Arc<TrafficManager>,
     speed_limiter: Arc<super::speed_limiter::SpeedLimiter>,
+    /// 因空闲超时被回收的 TCP 连接累计数
+    reaped_connections: Arc<AtomicU64>,
+    /// 预留的端口：(协议, 端口) -> 持有的 socket
+    held_ports: Arc<std::sync::Mutex<HashMap<(ProxyProtocol, u16), HeldPort>>>,
+    hold_seq: AtomicU64,
 }
 
 /// Connection provider for proxy listeners
 #[derive(Clone)]
 pub struct ConnectionProvider {
-    quic_connections: Arc<RwLock<HashMap<String, Arc<quinn::Connection>>>>,
-    tunnel_connections: Arc<RwLock<HashMap<String, Arc<Box<dyn TunnelConnection>>>>>,
+    quic_connections: Arc<RwLock<HashMap<String, ClientConnections<quinn::Connection>>>>,
+    tunnel_connections: Arc<RwLock<HashMap<String, ClientConnections<Box<dyn TunnelConnection>>>>>,
 }
 
 impl ConnectionProvider {
     pub fn new(
-        quic_connections: Arc<RwLock<HashMap<String, Arc<quinn::Connection>>>>,
-        tunnel_connections: Arc<RwLock<HashMap<String, Arc<Box<dyn TunnelConnection>>>>>,
+        quic_connections: Arc<RwLock<HashMap<String, ClientConnections<quinn::Connection>>>>,
+        tunnel_connections: Arc<RwLock<HashMap<String, ClientConnections<Box<dyn TunnelConnection>>>>>,
     ) -> Self {
         Self {
             quic_connections,
@@ -132,20 +221,39 @@ impl ConnectionProvider {
         // First check QUIC connections
         {
             let quic_conns = self.quic_connections.read().await;
-            if let Some(conn) = quic_conns.get(client_id) {
-                return Some(UnifiedConnection::Quic(conn.clone()));
+            if let Some(conn) = quic_conns.get(client_id).and_then(|c| c.pick()) {
+                return Some(UnifiedConnection::Quic(conn));
             }
         }
         // Then check tunnel (KCP) connections
         {
             let tunnel_conns = self.tunnel_connections.read().await;
-            if let Some(conn) = tunnel_conns.get(client_id) {
-                return Some(UnifiedConnection::Tunnel(conn.clone()));
+            if let Some(conn) = tunnel_conns.get(client_id).and_then(|c| c.pick()) {
+                return Some(UnifiedConnection::Tunnel(conn));
             }
         }
         None
     }
 
+    /// 获取客户端连接，客户端暂时不在线时按指数退避重试，最多等待 `max_wait`
+    ///
+    /// 用于吸收客户端短暂断线重连的窗口，避免访客直接收到连接失败。
+    pub async fn wait_for_connection(&self, client_id: &str, max_wait: Duration) -> Option<UnifiedConnection> {
+        let deadline = tokio::time::Instant::now() + max_wait;
+        let mut backoff = Duration::from_millis(100);
+        loop {
+            if let Some(conn) = self.get_connection(client_id).await {
+                return Some(conn);
+            }
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            tokio::time::sleep(backoff.min(deadline - now)).await;
+            backoff = (backoff * 2).min(Duration::from_secs(2));
+        }
+    }
+
     /// Check if a client is online
     pub async fn is_online(&self, client_id: &str) -> bool {
         self.get_connection(client_id).await.is_some()
@@ -156,10 +264,76 @@ impl ProxyListenerManager {
     pub fn new(traffic_manager: Arc<TrafficManager>, speed_limiter: Arc<super::speed_limiter::SpeedLimiter>) -> Self {
         Self {
             listeners: Arc::new(RwLock::new(HashMap::new())),
+            cache: ListenerCache::from_env(),
             udp_sessions: Arc::new(RwLock::new(HashMap::new())),
             traffic_manager,
             speed_limiter,
+            reaped_connections: Arc::new(AtomicU64::new(0)),
+            held_ports: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            hold_seq: AtomicU64::new(0),
+        }
+    }
+
+    /// 预留端口：绑定并持有，直到 [`Self::release_port`] 或超过 [`PORT_HOLD_TTL`]
+    ///
+    /// 端口已被监听器或其他预留占用时绑定失败，并发创建同一端口的代理只有一个能成功预留。
+    pub async fn hold_port(&self, protocol: ProxyProtocol, port: u16) -> Result<()> {
+        let addr = wildcard_addr(port);
+        let socket = match protocol {
+            ProxyProtocol::Tcp => bind_tcp_listener(addr).map(|l| Box::new(l) as Box<dyn std::any::Any + Send + Sync>),
+            ProxyProtocol::Udp => create_configured_udp_socket(addr).await.map(|s| Box::new(s) as Box<dyn std::any::Any + Send + Sync>),
+        }
+        .map_err(|e| anyhow::anyhow!("无法预留 {} 端口 {}：{}", protocol.as_str().to_uppercase(), port, e))?;
+
+        let seq = self.hold_seq.fetch_add(1, Ordering::Relaxed);
+        let key = (protocol, port);
+        self.held_ports.lock().unwrap().insert(key.clone(), HeldPort { seq, owner: None, _socket: socket });
+        debug!("已预留 {} 端口 {}", key.0.as_str().to_uppercase(), port);
+
+        let held_ports = self.held_ports.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(PORT_HOLD_TTL).await;
+            let mut held = held_ports.lock().unwrap();
+            if held.get(&key).is_some_and(|h| h.seq == seq) {
+                held.remove(&key);
+                warn!("预留的 {} 端口 {} 超时未激活，已释放", key.0.as_str().to_uppercase(), key.1);
+            }
+        });
+        Ok(())
+    }
+
+    /// 释放预留的端口，返回该端口是否处于预留状态
+    pub fn release_port(&self, protocol: ProxyProtocol, port: u16) -> bool {
+        self.held_ports.lock().unwrap().remove(&(protocol, port)).is_some()
+    }
+
+    /// 预绑定代理的远程端口：按代理的监听地址绑定并持有，直到该代理的监听器启动或代理被停止
+    ///
+    /// 代理的监听器已在运行（例如按缓存恢复）时无需预绑定，直接返回成功。
+    pub async fn prebind_port(&self, client_id: &str, proxy_id: i64, protocol: ProxyProtocol, bind_ip: Option<&str>, port: u16) -> Result<()> {
+        if self.listeners.read().await.get(client_id).is_some_and(|l| l.contains_key(&proxy_id)) {
+            return Ok(());
+        }
+        let addr = proxy_bind_addr(bind_ip, port);
+        let socket = match protocol {
+            ProxyProtocol::Tcp => bind_tcp_listener(addr).map(|l| Box::new(l) as Box<dyn std::any::Any + Send + Sync>),
+            ProxyProtocol::Udp => create_configured_udp_socket(addr).await.map(|s| Box::new(s) as Box<dyn std::any::Any + Send + Sync>),
         }
+        .map_err(|e| anyhow::anyhow!("{}", describe_bind_error(&e)))?;
+
+        let seq = self.hold_seq.fetch_add(1, Ordering::Relaxed);
+        self.held_ports.lock().unwrap().insert((protocol, port), HeldPort { seq, owner: Some(proxy_id), _socket: socket });
+        Ok(())
+    }
+
+    /// 释放代理预绑定的端口（监听器接管前或代理停止时）
+    fn release_prebound(&self, proxy_id: i64) {
+        self.held_ports.lock().unwrap().retain(|_, held| held.owner != Some(proxy_id));
+    }
+
+    /// 因空闲超时被回收的 TCP 连接累计数
+    pub fn reaped_connections(&self) -> u64 {
+        self.reaped_connections.load(Ordering::Relaxed)
     }
 
     // 从代理配置列表启动代理监听器
@@ -176,55 +350,85 @@ impl ProxyListenerManager {
 
         let mut listeners = self.listeners.write().await;
         let client_listeners = listeners.entry(client_id.clone()).or_insert_with(HashMap::new);
+        let mut result = Ok(());
 
         for proxy in proxies {
-            // 如果该代理的监听器已经运行，跳过
-            if client_listeners.contains_key(&proxy.proxy_id) {
-                continue;
+            // 如果该代理的监听器已经以相同配置运行，跳过；配置有变化（例如按缓存恢复的旧配置）则重启
+            if let Some(active) = client_listeners.get(&proxy.proxy_id) {
+                if active.config == proxy {
+                    continue;
+                }
+                if let Some(active) = client_listeners.remove(&proxy.proxy_id) {
+                    active.handle.abort();
+                    proxy_events::listener_stopped(&client_id, proxy.proxy_id, "配置变更，重启监听器");
+                }
             }
 
             let proxy_name = proxy.name.clone();
             let proxy_protocol: ProxyProtocol = proxy.proxy_type.clone().into();
             let proxy_protocol_str = proxy_protocol.as_str().to_uppercase();
             let client_id_clone = client_id.clone();
-            let listen_addr = format!("0.0.0.0:{}", proxy.remote_port);
-            let target_addr = format!("{}:{}", proxy.local_ip, proxy.local_port);
+            let bind_addr = proxy_bind_addr(proxy.bind_ip.as_deref(), proxy.remote_port);
+            let listen_addr = bind_addr.to_string();
+            let target_addr = join_host_port(&proxy.local_ip, proxy.local_port);
             let proxy_id = proxy.proxy_id;
             let conn_provider_clone = conn_provider.clone();
             let traffic_manager = self.traffic_manager.clone();
+            // 0 表示不限制空闲时间
+            let idle_timeout = match proxy.idle_timeout.unwrap_or(DEFAULT_TCP_IDLE_TIMEOUT_SECS) {
+                0 => None,
+                secs => Some(Duration::from_secs(secs as u64)),
+            };
+            let reaped_connections = self.reaped_connections.clone();
+            super::mitigation::global().set_proxy_rule(
+                proxy_id,
+                client_id.parse::<i64>().unwrap_or(0),
+                proxy.mitigation.clone(),
+            );
+
+            // 节点启动时预绑定的端口交给监听器
+            self.release_prebound(proxy_id);
 
             // 预检端口是否可用：尝试绑定后立即释放
             match proxy_protocol {
                 ProxyProtocol::Tcp => {
-                    match TcpListener::bind(&listen_addr).await {
+                    match bind_tcp_listener(bind_addr) {
                         Ok(_listener) => {
                             // 绑定成功，drop 释放端口，后续 spawn 任务会重新绑定
                         }
                         Err(e) => {
-                            return Err(anyhow::anyhow!(
+                            proxy_events::bind_failed(&client_id, proxy_id, proxy.remote_port, &e);
+                            result = Err(anyhow::anyhow!(
                                 "代理「{}」无法监听 {} 端口 {}：{}",
-                                proxy_name, proxy_protocol_str, proxy.remote_port, e
+                                proxy_name, proxy_protocol_str, proxy.remote_port, describe_bind_error(&e)
                             ));
+                            break;
                         }
                     }
                 }
                 ProxyProtocol::Udp => {
-                    match UdpSocket::bind(&listen_addr).await {
+                    match create_configured_udp_socket(bind_addr).await {
                         Ok(_socket) => {
                             // 绑定成功，drop 释放端口
                         }
                         Err(e) => {
-                            return Err(anyhow::anyhow!(
+                            proxy_events::bind_failed(&client_id, proxy_id, proxy.remote_port, &e);
+                            result = Err(anyhow::anyhow!(
                                 "代理「{}」无法监听 {} 端口 {}：{}",
-                                proxy_name, proxy_protocol_str, proxy.remote_port, e
+                                proxy_name, proxy_protocol_str, proxy.remote_port, describe_bind_error(&e)
                             ));
+                            break;
                         }
                     }
                 }
             }
 
             let udp_sessions = self.udp_sessions.clone();
-            let speed_limiter = self.speed_limiter.clone();
+            let speed_limiter = self.speed_limiter.share(
+                proxy_id,
+                proxy.bandwidth_weight.unwrap_or(1),
+                proxy.priority.unwrap_or_default(),
+            );
 
             let handle = tokio::spawn(async move {
                 loop {
@@ -239,6 +443,8 @@ impl ProxyListenerManager {
                                 proxy_id,
                                 traffic_manager.clone(),
                                 speed_limiter.clone(),
+                                idle_timeout,
+                                reaped_connections.clone(),
                             ).await
                         }
                         ProxyProtocol::Udp => {
@@ -268,17 +474,89 @@ impl ProxyListenerManager {
                     // 检查客户端是否仍在连接
                     if !conn_provider_clone.is_online(&client_id_clone).await {
                         warn!("[{}] 客户端已离线，停止代理监听", proxy_name);
+                        proxy_events::listener_stopped(&client_id_clone, proxy_id, "客户端已离线");
                         break;
                     }
                 }
             });
 
-            client_listeners.insert(proxy_id, handle);
             info!("  [客户端 {}] 启动{}代理: {} 端口: {}",
                   client_id, proxy_protocol_str, proxy.name, proxy.remote_port);
+            client_listeners.insert(proxy_id, ActiveListener { config: proxy, handle });
         }
 
-        Ok(())
+        self.save_cache(&listeners);
+        result
+    }
+
+    /// 按客户端上报的完整代理列表同步监听器：停止不在列表中的监听器，启动新增或配置有变化的监听器
+    pub async fn sync_client_proxies(
+        &self,
+        client_id: String,
+        proxies: Vec<common::protocol::control::ProxyConfig>,
+        conn_provider: ConnectionProvider,
+    ) -> Result<()> {
+        {
+            let mut listeners = self.listeners.write().await;
+            if let Some(client_listeners) = listeners.get_mut(&client_id) {
+                client_listeners.retain(|proxy_id, active| {
+                    let keep = proxies.iter().any(|p| p.proxy_id == *proxy_id);
+                    if !keep {
+                        active.handle.abort();
+                        info!("  [客户端 {}] 停止已移除的代理 #{}", client_id, proxy_id);
+                        proxy_events::listener_stopped(&client_id, *proxy_id, "代理已移除");
+                    }
+                    keep
+                });
+            }
+            self.save_cache(&listeners);
+        }
+        self.start_client_proxies_from_configs(client_id, proxies, conn_provider).await
+    }
+
+    /// 按缓存恢复节点重启前运行的代理监听器
+    ///
+    /// 恢复的监听器处于等待客户端状态：访客连接会等待客户端重连，
+    /// 客户端在 [`RESTORED_CLIENT_GRACE_SECS`] 内没有重连则停止。
+    pub async fn restore_cached_listeners(self: &Arc<Self>, conn_provider: ConnectionProvider) {
+        let cached = self.cache.load();
+        if cached.is_empty() {
+            return;
+        }
+
+        info!("按缓存恢复 {} 个客户端的代理监听器", cached.len());
+        for (client_id, proxies) in cached {
+            if let Err(e) = self
+                .start_client_proxies_from_configs(client_id.clone(), proxies, conn_provider.clone())
+                .await
+            {
+                warn!("恢复客户端 {} 的代理监听器失败: {}", client_id, e);
+            }
+            self.stop_client_proxies_after(client_id, conn_provider.clone(), Duration::from_secs(RESTORED_CLIENT_GRACE_SECS));
+        }
+    }
+
+    fn save_cache(&self, listeners: &HashMap<String, HashMap<i64, ActiveListener>>) {
+        let cached = listeners
+            .iter()
+            .filter(|(_, client_listeners)| !client_listeners.is_empty())
+            .map(|(client_id, client_listeners)| {
+                (client_id.clone(), client_listeners.values().map(|a| a.config.clone()).collect())
+            })
+            .collect();
+        self.cache.save(&cached);
+
+        // 防火墙只开放运行中代理的端口，路由器端口映射随之增删
+        let ports: Vec<_> = listeners
+            .values()
+            .flat_map(|client_listeners| {
+                client_listeners
+                    .values()
+                    .map(|a| (a.config.proxy_id, ProxyProtocol::from(a.config.proxy_type.as_str()), a.config.remote_port))
+            })
+            .collect();
+        super::firewall::sync_proxies(ports.iter().cloned());
+        super::port_mapping::sync_proxies(ports.into_iter());
     }
 
     // 停止客户端的所有代理监听器
@@ -286,20 +564,47 @@ impl ProxyListenerManager {
         let mut listeners = self.listeners.write().await;
         if let Some(client_listeners) = listeners.remove(client_id) {
             info!("  [客户端 {}] 停止 {} 个代理监听器", client_id, client_listeners.len());
-            for (proxy_id, handle) in client_listeners {
-                handle.abort();
+            for (proxy_id, active) in client_listeners {
+                active.handle.abort();
                 debug!("    代理 #{} 已停止", proxy_id);
+                proxy_events::listener_stopped(client_id, proxy_id, "停止客户端的所有代理");
             }
+            self.save_cache(&listeners);
         }
     }
 
+    /// 客户端断开后延迟停止其代理监听器
+    ///
+    /// 宽限期与等待客户端重连的时间相同：期间到达的访客连接会等待客户端恢复，
+    /// 客户端在宽限期内重连则保留现有监听器。
+    pub fn stop_client_proxies_after_grace(self: &Arc<Self>, client_id: String, conn_provider: ConnectionProvider) {
+        self.stop_client_proxies_after(client_id, conn_provider, client_wait_timeout());
+    }
+
+    fn stop_client_proxies_after(self: &Arc<Self>, client_id: String, conn_provider: ConnectionProvider, grace: Duration) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            if !grace.is_zero() {
+                tokio::time::sleep(grace).await;
+            }
+            if conn_provider.is_online(&client_id).await {
+                debug!("客户端 {} 已在宽限期内重连，保留代理监听器", client_id);
+                return;
+            }
+            manager.stop_client_proxies(&client_id).await;
+        });
+    }
+
     // 停止单个代理监听器（用于删除或禁用代理时）
     pub async fn stop_single_proxy(&self, client_id: &str, proxy_id: i64) {
+        self.release_prebound(proxy_id);
         let mut listeners = self.listeners.write().await;
         if let Some(client_listeners) = listeners.get_mut(client_id) {
-            if let Some(handle) = client_listeners.remove(&proxy_id) {
-                handle.abort();
+            if let Some(active) = client_listeners.remove(&proxy_id) {
+                active.handle.abort();
                 info!("  [客户端 {}] 停止代理 #{}", client_id, proxy_id);
+                proxy_events::listener_stopped(client_id, proxy_id, "代理被删除或禁用");
+                self.save_cache(&listeners);
             }
         }
     }
@@ -329,15 +634,21 @@ impl ProxyServer {
         })
     }
 
+    /// 按缓存恢复节点重启前运行的代理监听器
+    pub async fn restore_cached_listeners(&self) {
+        let conn_provider = ConnectionProvider::new(self.client_connections.clone(), self.tunnel_connections.clone());
+        self.listener_manager.restore_cached_listeners(conn_provider).await;
+    }
+
     pub fn get_listener_manager(&self) -> Arc<ProxyListenerManager> {
         self.listener_manager.clone()
     }
 
-    pub fn get_client_connections(&self) -> Arc<RwLock<HashMap<String, Arc<quinn::Connection>>>> {
+    pub fn get_client_connections(&self) -> Arc<RwLock<HashMap<String, ClientConnections<quinn::Connection>>>> {
         self.client_connections.clone()
     }
 
-    pub fn get_tunnel_connections(&self) -> Arc<RwLock<HashMap<String, Arc<Box<dyn TunnelConnection>>>>> {
+    pub fn get_tunnel_connections(&self) -> Arc<RwLock<HashMap<String, ClientConnections<Box<dyn TunnelConnection>>>>> {
         self.tunnel_connections.clone()
     }
 
@@ -346,15 +657,15 @@ impl ProxyServer {
         // First check QUIC connections
         {
             let quic_conns = self.client_connections.read().await;
-            if let Some(conn) = quic_conns.get(client_id) {
-                return Some(UnifiedConnection::Quic(conn.clone()));
+            if let Some(conn) = quic_conns.get(client_id).and_then(|c| c.pick()) {
+                return Some(UnifiedConnection::Quic(conn));
             }
         }
         // Then check tunnel (KCP) connections
         {
             let tunnel_conns = self.tunnel_connections.read().await;
-            if let Some(conn) = tunnel_conns.get(client_id) {
-                return Some(UnifiedConnection::Tunnel(conn.clone()));
+            if let Some(conn) = tunnel_conns.get(client_id).and_then(|c| c.pick()) {
+                return Some(UnifiedConnection::Tunnel(conn));
             }
         }
         None
@@ -376,17 +687,14 @@ impl ProxyServer {
         tunnel_online
     }
 
-    pub async fn run(&self, bind_addr: String) -> Result<()> {
-        // 从配置管理器获取配置
-        let idle_timeout = self.config_manager.get_number("idle_timeout", 60).await as u64;
-        let max_streams = self.config_manager.get_number("max_concurrent_streams", 100).await as u32;
-        let keep_alive_interval = self.config_manager.get_number("keep_alive_interval", 5).await as u64;
+    pub async fn run(&self, bind_addr: String, quic_config: Option<QuicConfig>) -> Result<()> {
+        // Controller 未下发 QUIC 参数时使用默认参数
+        let quic_config = quic_config.unwrap_or_default();
+        let idle_timeout = quic_config.idle_timeout;
+        let max_streams = quic_config.max_concurrent_streams;
 
-        let mut transport_config = TransportConfig::default();
+        let mut transport_config = build_transport_config(&quic_config)?;
         transport_config.max_concurrent_uni_streams(VarInt::from_u32(max_streams));
-        // 服务器也发送心跳，确保连接稳定
-        transport_config.keep_alive_interval(Some(Duration::from_secs(keep_alive_interval)));
-        transport_config.max_idle_timeout(Some(Duration::from_secs(idle_timeout).try_into()?));
 
         let mut server_config = ServerConfig::with_single_cert(
             vec![self.cert.clone()],
@@ -394,23 +702,29 @@ impl ProxyServer {
         )?;
         server_config.transport_config(Arc::new(transport_config));
 
-        let endpoint = Endpoint::server(server_config, bind_addr.parse()?)?;
+        // 自行创建套接字，通配 IPv6 地址时启用双栈
+        let socket = common::socket_activation::std_udp_socket(bind_addr.parse()?)?;
+        let runtime = quinn::default_runtime().ok_or_else(|| anyhow::anyhow!("未找到 QUIC 异步运行时"))?;
+        let endpoint = Endpoint::new(quinn::EndpointConfig::default(), Some(server_config), socket, runtime)?;
 
         info!("🚀 QUIC服务器启动成功!");
         info!("📡 监听地址: {}", bind_addr);
         info!("⏱️  空闲超时: {}秒 (心跳由客户端主动发送)", idle_timeout);
         info!("🔢 最大并发流: {}", max_streams);
+        info!("📶 拥塞控制: {}", quic_config.congestion_controller.as_str());
 
         info!("⏳ 等待客户端连接...");
 
         // 接受客户端连接
         while let Some(connecting) = endpoint.accept().await {
+            // 等待握手完整结束后再处理认证：客户端可能以 0-RTT 发送握手问候，
+            // 被重放的 0-RTT 数据包无法完成握手，因此不会被处理
             match connecting.await {
                 Ok(conn) => {
-                    let remote_addr = conn.remote_address();
+                    let remote_addr = display_addr(conn.remote_address());
                     info!("📡 新连接来自: {}", remote_addr);
 
-                    // 等待客户端发送 token 认证
+                    // 等待客户端完成认证握手
                     let conn_clone = Arc::new(conn);
                     let connections = self.client_connections.clone();
                     let tunnel_connections = self.tunnel_connections.clone();
@@ -446,7 +760,7 @@ impl ProxyServer {
         loop {
             match listener.accept().await {
                 Ok(conn) => {
-                    let remote_addr = conn.remote_address();
+                    let remote_addr = display_addr(conn.remote_address());
                     info!("New KCP connection from: {}", remote_addr);
 
                     let conn = Arc::new(conn);
@@ -489,7 +803,7 @@ impl ProxyServer {
         loop {
             match listener.accept().await {
                 Ok(conn) => {
-                    let remote_addr = conn.remote_address();
+                    let remote_addr = display_addr(conn.remote_address());
                     info!("New TCP tunnel connection from: {}", remote_addr);
 
                     let conn = Arc::new(conn);
@@ -515,6 +829,7 @@ impl ProxyServer {
                 }
                 Err(e) => {
                     error!("TCP tunnel connection accept failed: {}", e);
+                    tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
                 }
             }
         }
@@ -523,30 +838,29 @@ impl ProxyServer {
 
 async fn handle_client_auth(
     conn: Arc<quinn::Connection>,
-    connections: Arc<RwLock<HashMap<String, Arc<quinn::Connection>>>>,
-    tunnel_connections: Arc<RwLock<HashMap<String, Arc<Box<dyn TunnelConnection>>>>>,
+    connections: Arc<RwLock<HashMap<String, ClientConnections<quinn::Connection>>>>,
+    tunnel_connections: Arc<RwLock<HashMap<String, ClientConnections<Box<dyn TunnelConnection>>>>>,
     listener_manager: Arc<ProxyListenerManager>,
     config_manager: Arc<ConfigManager>,
     auth_provider: Arc<dyn common::protocol::auth::ClientAuthProvider>,
 ) -> Result<()> {
-    // 等待客户端发送 token (格式: 2字节长度 + 内容)
-    let mut recv_stream = match conn.accept_uni().await {
-        Ok(s) => s,
-        Err(_) => return Ok(()),
+    // 第一个流用于认证握手（旧版客户端使用单向流发送 token）
+    let first_stream = async {
+        tokio::select! {
+            bi = conn.accept_bi() => bi.ok().map(|(send, recv)| {
+                (
+                    Some(Box::new(QuicSendStream::new(send)) as Box<dyn TunnelSendStream>),
+                    Box::new(QuicRecvStream::new(recv)) as Box<dyn TunnelRecvStream>,
+                )
+            }),
+            uni = conn.accept_uni(), if tunnel_auth::legacy_allowed() => uni.ok().map(|recv| {
+                (None, Box::new(QuicRecvStream::new(recv)) as Box<dyn TunnelRecvStream>)
+            }),
+        }
+    };
+    let Some((auth_result, instance_id)) = tunnel_auth::authenticate(first_stream, auth_provider.as_ref()).await? else {
+        return Ok(());
     };
-
-    let mut len_buf = [0u8; 2];
-    recv_stream.read_exact(&mut len_buf).await?;
-    let len = u16::from_be_bytes(len_buf) as usize;
-    debug!("接收token长度: {}", len);
-
-    let mut token_buf = vec![0u8; len];
-    recv_stream.read_exact(&mut token_buf).await?;
-    let token = String::from_utf8(token_buf)?;
-    debug!("接收token: {}", token);
-
-    // 通过 auth_provider 验证 token
-    let auth_result = auth_provider.validate_token(&token).await?;
     if !auth_result.allowed {
         error!("❌ 客户端认证失败: {}", auth_result.reject_reason.unwrap_or_default());
         return Ok(());
@@ -554,6 +868,7 @@ async fn handle_client_auth(
 
     let client_id = auth_result.client_id;
     let client_name = auth_result.client_name;
+    let allow_replicas = auth_result.allow_replicas;
 
     // 更新客户端为在线状态
     if let Err(e) = auth_provider.set_client_online(client_id, true).await {
@@ -561,10 +876,11 @@ async fn handle_client_auth(
     }
 
     info!("✅ 客户端认证成功: {} (ID: {}, 在线: {})", client_name, client_id, conn.remote_address());
+    proxy_events::client_attached(client_id, conn.remote_address());
 
     // 保存连接（先保存，再启动代理，这样代理监听器能找到连接）
     let mut conns = connections.write().await;
-    conns.insert(format!("{}", client_id), conn.clone());
+    conns.entry(format!("{}", client_id)).or_default().insert(instance_id, conn.clone(), allow_replicas);
     drop(conns);
 
     // 启动该客户端的所有代理监听器（使用统一连接提供器）
@@ -572,7 +888,7 @@ async fn handle_client_auth(
     // 从 auth_provider 获取代理配置（兼容本地和远程模式）
     match auth_provider.get_client_proxies(client_id).await {
         Ok(proxies) => {
-            if let Err(e) = listener_manager.start_client_proxies_from_configs(format!("{}", client_id), proxies, conn_provider).await {
+            if let Err(e) = listener_manager.sync_client_proxies(format!("{}", client_id), proxies, conn_provider.clone()).await {
                 error!("❌ 启动代理监听器失败: {}", e);
             }
         }
@@ -588,6 +904,7 @@ async fn handle_client_auth(
     let connections_health = connections.clone();
     let listener_manager_health = listener_manager.clone();
     let auth_provider_health = auth_provider.clone();
+    let conn_provider_health = conn_provider.clone();
 
     // 从配置获取健康检查间隔
     let health_check_interval = config_manager.get_number("health_check_interval", 15).await as u64;
@@ -605,20 +922,18 @@ async fn handle_client_auth(
                 let client_id_str = format!("{}", client_id_health);
                 let mut conns = connections_health.write().await;
 
-                // 检查当前存储的连接是否与我们监控的连接是同一个
-                let should_cleanup = if let Some(current_conn) = conns.get(&client_id_str) {
-                    // 比较连接的远程地址来判断是否是同一个连接
-                    Arc::ptr_eq(current_conn, &conn_health_check)
-                } else {
-                    false
-                };
-
-                if should_cleanup {
+                // 只移除该连接本身（已被重连取代时跳过），副本模式下其他实例仍在线时不清理
+                let removed = conns.get_mut(&client_id_str).and_then(|set| set.remove(&conn_health_check));
+                if removed == Some(false) {
+                    drop(conns);
+                    info!("客户端 {} 的一条副本连接断开，其他实例仍在线", client_id_str);
+                } else if removed == Some(true) {
                     conns.remove(&client_id_str);
                     drop(conns);
+                    proxy_events::client_detached(&client_id_str, "隧道连接断开");
 
-                    // 停止该客户端的所有代理监听器
-                    listener_manager_health.stop_client_proxies(&client_id_str).await;
+                    // 宽限期内客户端未重连则停止其所有代理监听器
+                    listener_manager_health.stop_client_proxies_after_grace(client_id_str, conn_provider_health.clone());
 
                     // 更新客户端为离线状态
                     if let Err(e) = auth_provider_health.set_client_online(client_id_health, false).await {
@@ -669,19 +984,18 @@ async fn handle_client_auth(
                 let client_id_str = format!("{}", client_id);
                 let mut conns = connections.write().await;
 
-                // 检查当前存储的连接是否与我们监控的连接是同一个（防止误删重连后的新连接）
-                let should_cleanup = if let Some(current_conn) = conns.get(&client_id_str) {
-                    Arc::ptr_eq(current_conn, &conn)
-                } else {
-                    false
-                };
-
-                if should_cleanup {
+                // 只移除该连接本身（已被重连取代时跳过），副本模式下其他实例仍在线时不清理
+                let removed = conns.get_mut(&client_id_str).and_then(|set| set.remove(&conn));
+                if removed == Some(false) {
+                    drop(conns);
+                    info!("客户端 {} 的一条副本连接断开，其他实例仍在线", client_id_str);
+                } else if removed == Some(true) {
                     conns.remove(&client_id_str);
                     drop(conns);
+                    proxy_events::client_detached(&client_id_str, "隧道连接断开");
 
-                    // 停止该客户端的所有代理监听器
-                    listener_manager.stop_client_proxies(&client_id_str).await;
+                    // 宽限期内客户端未重连则停止其所有代理监听器
+                    listener_manager.stop_client_proxies_after_grace(client_id_str, conn_provider.clone());
 
                     // 更新客户端为离线状态
                     if let Err(e) = auth_provider.set_client_online(client_id, false).await {
@@ -702,30 +1016,17 @@ async fn handle_client_auth(
 /// Handle client authentication for tunnel connections (KCP)
 async fn handle_tunnel_client_auth(
     conn: Arc<Box<dyn TunnelConnection>>,
-    tunnel_connections: Arc<RwLock<HashMap<String, Arc<Box<dyn TunnelConnection>>>>>,
-    quic_connections: Arc<RwLock<HashMap<String, Arc<quinn::Connection>>>>,
+    tunnel_connections: Arc<RwLock<HashMap<String, ClientConnections<Box<dyn TunnelConnection>>>>>,
+    quic_connections: Arc<RwLock<HashMap<String, ClientConnections<quinn::Connection>>>>,
     listener_manager: Arc<ProxyListenerManager>,
     config_manager: Arc<ConfigManager>,
     auth_provider: Arc<dyn common::protocol::auth::ClientAuthProvider>,
 ) -> Result<()> {
-    // Wait for client to send token (format: 2 byte length + content)
-    let mut recv_stream = match conn.accept_uni().await {
-        Ok(s) => s,
-        Err(_) => return Ok(()),
+    // The first stream carries the auth handshake
+    let first_stream = async { conn.accept_bi().await.ok().map(|(send, recv)| (Some(send), recv)) };
+    let Some((auth_result, instance_id)) = tunnel_auth::authenticate(first_stream, auth_provider.as_ref()).await? else {
+        return Ok(());
     };
-
-    let mut len_buf = [0u8; 2];
-    recv_stream.read_exact(&mut len_buf).await?;
-    let len = u16::from_be_bytes(len_buf) as usize;
-    debug!("Received token length: {}", len);
-
-    let mut token_buf = vec![0u8; len];
-    recv_stream.read_exact(&mut token_buf).await?;
-    let token = String::from_utf8(token_buf)?;
-    debug!("Received token: {}", token);
-
-    // 通过 auth_provider 验证 token
-    let auth_result = auth_provider.validate_token(&token).await?;
     if !auth_result.allowed {
         error!("KCP client auth failed: {}", auth_result.reject_reason.unwrap_or_default());
         return Ok(());
@@ -733,6 +1034,7 @@ async fn handle_tunnel_client_auth(
 
     let client_id = auth_result.client_id;
     let client_name = auth_result.client_name;
+    let allow_replicas = auth_result.allow_replicas;
 
     // 更新客户端为在线状态
     if let Err(e) = auth_provider.set_client_online(client_id, true).await {
@@ -740,10 +1042,11 @@ async fn handle_tunnel_client_auth(
     }
 
     info!("KCP client authenticated: {} (ID: {}, Online: {})", client_name, client_id, conn.remote_address());
+    proxy_events::client_attached(client_id, conn.remote_address());
 
     // Save tunnel connection first (so proxy listeners can find it)
     let mut conns = tunnel_connections.write().await;
-    conns.insert(format!("{}", client_id), conn.clone());
+    conns.entry(format!("{}", client_id)).or_default().insert(instance_id, conn.clone(), allow_replicas);
     drop(conns);
 
     // Start all proxy listeners for this client (using unified connection provider)
@@ -751,7 +1054,7 @@ async fn handle_tunnel_client_auth(
     // 从 auth_provider 获取代理配置（兼容本地和远程模式）
     match auth_provider.get_client_proxies(client_id).await {
         Ok(proxies) => {
-            if let Err(e) = listener_manager.start_client_proxies_from_configs(format!("{}", client_id), proxies, conn_provider).await {
+            if let Err(e) = listener_manager.sync_client_proxies(format!("{}", client_id), proxies, conn_provider.clone()).await {
                 error!("Failed to start proxy listeners: {}", e);
             }
         }
@@ -767,6 +1070,7 @@ async fn handle_tunnel_client_auth(
     let tunnel_connections_health = tunnel_connections.clone();
     let listener_manager_health = listener_manager.clone();
     let auth_provider_health = auth_provider.clone();
+    let conn_provider_health = conn_provider.clone();
 
     let health_check_interval = config_manager.get_number("health_check_interval", 15).await as u64;
 
@@ -781,17 +1085,16 @@ async fn handle_tunnel_client_auth(
                 let client_id_str = format!("{}", client_id_health);
                 let mut conns = tunnel_connections_health.write().await;
 
-                let should_cleanup = if let Some(current_conn) = conns.get(&client_id_str) {
-                    Arc::ptr_eq(current_conn, &conn_health_check)
-                } else {
-                    false
-                };
-
-                if should_cleanup {
+                let removed = conns.get_mut(&client_id_str).and_then(|set| set.remove(&conn_health_check));
+                if removed == Some(false) {
+                    drop(conns);
+                    info!("客户端 {} 的一条副本连接断开，其他实例仍在线", client_id_str);
+                } else if removed == Some(true) {
                     conns.remove(&client_id_str);
                     drop(conns);
+                    proxy_events::client_detached(&client_id_str, "隧道连接断开");
 
-                    listener_manager_health.stop_client_proxies(&client_id_str).await;
+                    listener_manager_health.stop_client_proxies_after_grace(client_id_str, conn_provider_health.clone());
 
                     if let Err(e) = auth_provider_health.set_client_online(client_id_health, false).await {
                         error!("Failed to update client offline status: {}", e);
@@ -840,18 +1143,17 @@ async fn handle_tunnel_client_auth(
                 let client_id_str = format!("{}", client_id);
                 let mut conns = tunnel_connections.write().await;
 
-                // 检查当前存储的连接是否与我们监控的连接是同一个（防止误删重连后的新连接）
-                let should_cleanup = if let Some(current_conn) = conns.get(&client_id_str) {
-                    Arc::ptr_eq(current_conn, &conn)
-                } else {
-                    false
-                };
-
-                if should_cleanup {
+                // 只移除该连接本身（已被重连取代时跳过），副本模式下其他实例仍在线时不清理
+                let removed = conns.get_mut(&client_id_str).and_then(|set| set.remove(&conn));
+                if removed == Some(false) {
+                    drop(conns);
+                    info!("客户端 {} 的一条副本连接断开，其他实例仍在线", client_id_str);
+                } else if removed == Some(true) {
                     conns.remove(&client_id_str);
                     drop(conns);
+                    proxy_events::client_detached(&client_id_str, "隧道连接断开");
 
-                    listener_manager.stop_client_proxies(&client_id_str).await;
+                    listener_manager.stop_client_proxies_after_grace(client_id_str, conn_provider.clone());
 
                     if let Err(e) = auth_provider.set_client_online(client_id, false).await {
                         error!("Failed to update client offline status: {}", e);
@@ -880,7 +1182,7 @@ async fn handle_tunnel_proxy_stream(
     mut tunnel_send: Box<dyn TunnelSendStream>,
     mut tunnel_recv: Box<dyn TunnelRecvStream>,
     _conn: Arc<Box<dyn TunnelConnection>>,
-    _connections: Arc<RwLock<HashMap<String, Arc<Box<dyn TunnelConnection>>>>>,
+    _connections: Arc<RwLock<HashMap<String, ClientConnections<Box<dyn TunnelConnection>>>>>,
 ) -> Result<()> {
     // Read target address
     let mut len_buf = [0u8; 2];
@@ -956,7 +1258,7 @@ async fn handle_proxy_stream(
     mut quic_send: quinn::SendStream,
     mut quic_recv: quinn::RecvStream,
     _conn: Arc<quinn::Connection>,
-    _connections: Arc<RwLock<HashMap<String, Arc<quinn::Connection>>>>,
+    _connections: Arc<RwLock<HashMap<String, ClientConnections<quinn::Connection>>>>,
 ) -> Result<()> {
     // 读取目标地址（客户端已连接）
     let mut len_buf = [0u8; 2];
@@ -1030,14 +1332,37 @@ async fn run_tcp_proxy_listener_unified(
     conn_provider: ConnectionProvider,
     proxy_id: i64,
     traffic_manager: Arc<TrafficManager>,
-    speed_limiter: Arc<super::speed_limiter::SpeedLimiter>,
+    speed_limiter: super::speed_limiter::ProxyShare,
+    idle_timeout: Option<Duration>,
+    reaped_connections: Arc<AtomicU64>,
 ) -> Result<()> {
-    let listener = TcpListener::bind(&listen_addr).await?;
+    let bind_addr: SocketAddr = listen_addr.parse()?;
+    let listener = bind_tcp_listener(bind_addr)
+        .inspect_err(|e| proxy_events::bind_failed(&client_id, proxy_id, bind_addr.port(), e))?;
     info!("[{}] 🔌 TCP监听端口: {} -> {}", proxy_name, listen_addr, target_addr);
+    proxy_events::listener_started(&client_id, proxy_id, bind_addr.port(), format!("TCP -> {}", target_addr));
+    let accept_guard = super::accept_guard::AcceptGuard::from_env();
 
     loop {
         match listener.accept().await {
             Ok((tcp_stream, addr)) => {
+                // 超过接入速率或待处理上限的连接直接关闭，不生成任务
+                let pending = match accept_guard.admit() {
+                    Ok(permit) => permit,
+                    Err(rejection) => {
+                        accept_guard.report(&proxy_name, rejection);
+                        continue;
+                    }
+                };
+                // 节点转发任务数达到上限时直接关闭
+                let Some(relay) = super::resource_guard::global().try_acquire() else {
+                    continue;
+                };
+                let addr = display_addr(addr);
+                let Some(source) = super::mitigation::global().admit(proxy_id, addr.ip()) else {
+                    debug!("[{}] 🚫 来源已被封禁，拒绝连接: {}", proxy_name, addr);
+                    continue;
+                };
                 info!("[{}] 📥 新连接来自: {}", proxy_name, addr);
 
                 let conn_provider_clone = conn_provider.clone();
@@ -1046,11 +1371,33 @@ async fn run_tcp_proxy_listener_unified(
                 let proxy_name = proxy_name.clone();
                 let traffic_manager = traffic_manager.clone();
                 let speed_limiter = speed_limiter.clone();
+                let reaped_connections = reaped_connections.clone();
 
                 tokio::spawn(async move {
+                    let _relay = relay;
+                    if !super::connection_authz::global().authorize(proxy_id, addr.ip()).await {
+                        debug!("[{}] 🚫 连接授权被拒绝: {}", proxy_name, addr);
+                        return;
+                    }
+                    let plugins = match super::plugin::Pipeline::open(&super::plugin::ConnectionInfo {
+                        proxy_id,
+                        proxy_name: proxy_name.clone(),
+                        client_id: client_id.clone(),
+                        source: addr,
+                        target_addr: target_addr.clone(),
+                    }) {
+                        Ok(plugins) => plugins,
+                        Err(e) => {
+                            info!("[{}] 🚫 {}: {}", proxy_name, e, addr);
+                            return;
+                        }
+                    };
+                    let owner_id = client_id.parse::<i64>().unwrap_or(0);
                     if let Err(e) = handle_tcp_to_tunnel_unified(
                         tcp_stream,
                         addr,
+                        source,
+                        pending,
                         target_addr,
                         proxy_name,
                         client_id,
@@ -1058,13 +1405,19 @@ async fn run_tcp_proxy_listener_unified(
                         proxy_id,
                         traffic_manager,
                         speed_limiter,
+                        idle_timeout,
+                        reaped_connections,
+                        plugins,
                     ).await {
                         error!("❌ 处理连接错误: {}", e);
+                        super::speed_meter::global().add_error(proxy_id, owner_id);
                     }
                 });
             }
             Err(e) => {
                 error!("[{}] ❌ 接受连接失败: {}", proxy_name, e);
+                // 文件描述符耗尽（EMFILE）时 accept 会立即失败，稍等再试避免空转
+                tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
             }
         }
     }
@@ -1079,20 +1432,26 @@ async fn run_udp_proxy_listener_unified(
     proxy_id: i64,
     udp_sessions: Arc<RwLock<HashMap<(String, i64), HashMap<SocketAddr, UdpSession>>>>,
     traffic_manager: Arc<TrafficManager>,
-    speed_limiter: Arc<super::speed_limiter::SpeedLimiter>,
+    speed_limiter: super::speed_limiter::ProxyShare,
 ) -> Result<()> {
-    let socket = Arc::new(create_configured_udp_socket(listen_addr.parse()?).await?);
+    let bind_addr: SocketAddr = listen_addr.parse()?;
+    let socket = Arc::new(
+        create_configured_udp_socket(bind_addr)
+            .await
+            .inspect_err(|e| proxy_events::bind_failed(&client_id, proxy_id, bind_addr.port(), e))?,
+    );
     info!("[{}] 🔌 UDP监听端口: {} -> {}", proxy_name, listen_addr, target_addr);
+    proxy_events::listener_started(&client_id, proxy_id, bind_addr.port(), format!("UDP -> {}", target_addr));
 
     let mut buf = vec![0u8; 65535];
-    let session_timeout = Duration::from_secs(300);
+    let session_timeout = Duration::from_secs(60);
 
     // 启动会话清理任务
     let udp_sessions_cleanup = udp_sessions.clone();
     let client_id_clone = client_id.clone();
     let proxy_name_clone = proxy_name.clone();
     tokio::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        let mut interval = tokio::time::interval(Duration::from_secs(15));
         loop {
             interval.tick().await;
             let mut sessions = udp_sessions_cleanup.write().await;
@@ -1111,32 +1470,80 @@ async fn run_udp_proxy_listener_unified(
         }
     });
 
+    let session_key = (client_id.clone(), proxy_id);
     loop {
         match socket.recv_from(&mut buf).await {
             Ok((len, src_addr)) => {
+                if !super::mitigation::global().admit_datagram(proxy_id, src_addr.ip().to_canonical(), len) {
+                    continue;
+                }
                 let data = buf[..len].to_vec();
-                let conn_provider_clone = conn_provider.clone();
-                let client_id = client_id.clone();
+
+                // 已有分帧会话：直接交给会话任务发送
+                {
+                    let mut sessions = udp_sessions.write().await;
+                    if let Some(session) = sessions.get_mut(&session_key).and_then(|m| m.get_mut(&src_addr)) {
+                        if !session.sender.is_closed() {
+                            session.last_activity = tokio::time::Instant::now();
+                            if session.sender.try_send(data).is_err() {
+                                debug!("[{}] UDP会话队列已满，丢弃数据报: {}", proxy_name, src_addr);
+                            }
+                            continue;
+                        }
+                    }
+                }
+
+                // 每个来源地址一条分帧的流：隧道流是字节流，数据报带长度前缀才能保持边界，
+                // 同一来源复用流使客户端侧对应固定的本地 UDP 套接字（WireGuard 等协议依赖稳定的对端地址）
+                let Some(relay) = super::resource_guard::global().try_acquire() else {
+                    continue;
+                };
+                let Some(conn) = conn_provider.get_connection(&client_id).await else {
+                    debug!("[{}] 客户端未连接，丢弃 UDP 数据报: {}", proxy_name, src_addr);
+                    continue;
+                };
+                let (tx, rx) = tokio::sync::mpsc::channel(UDP_SESSION_QUEUE);
+                let _ = tx.try_send(data);
+                {
+                    let mut sessions = udp_sessions.write().await;
+                    let session_map = sessions.entry(session_key.clone()).or_default();
+                    if session_map.len() >= UDP_MAX_SESSIONS_PER_PROXY {
+                        let oldest = session_map
+                            .iter()
+                            .min_by_key(|(_, session)| session.last_activity)
+                            .map(|(addr, _)| *addr);
+                        if let Some(oldest) = oldest {
+                            debug!("[{}] UDP会话数达到上限，淘汰会话: {}", proxy_name, oldest);
+                            session_map.remove(&oldest);
+                        }
+                    }
+                    session_map.insert(
+                        src_addr,
+                        UdpSession { sender: tx, last_activity: tokio::time::Instant::now() },
+                    );
+                }
+
+                let socket = socket.clone();
                 let target_addr = target_addr.clone();
                 let proxy_name = proxy_name.clone();
-                let udp_sessions = udp_sessions.clone();
-                let socket = socket.clone();
+                let client_id = client_id.clone();
                 let traffic_manager = traffic_manager.clone();
-
+                let speed_limiter = speed_limiter.clone();
                 tokio::spawn(async move {
-                    if let Err(e) = handle_udp_to_tunnel_unified(
+                    let _relay = relay;
+                    if let Err(e) = run_framed_udp_session(
+                        conn,
+                        rx,
                         socket,
                         src_addr,
-                        data,
                         target_addr,
-                        proxy_name,
+                        proxy_name.clone(),
                         client_id,
-                        conn_provider_clone,
                         proxy_id,
-                        udp_sessions,
                         traffic_manager,
+                        speed_limiter,
                     ).await {
-                        error!("❌ 处理UDP错误: {}", e);
+                        debug!("[{}] UDP会话结束: {}", proxy_name, e);
                     }
                 });
             }
@@ -1150,28 +1557,49 @@ async fn run_udp_proxy_listener_unified(
 async fn handle_tcp_to_tunnel_unified(
     mut tcp_stream: TcpStream,
     addr: std::net::SocketAddr,
+    source: super::mitigation::SourceGuard,
+    pending: super::accept_guard::PendingPermit,
     target_addr: String,
     proxy_name: String,
     client_id: String,
     conn_provider: ConnectionProvider,
     proxy_id: i64,
     traffic_manager: Arc<TrafficManager>,
-    speed_limiter: Arc<super::speed_limiter::SpeedLimiter>,
+    speed_limiter: super::speed_limiter::ProxyShare,
+    idle_timeout: Option<Duration>,
+    reaped_connections: Arc<AtomicU64>,
+    plugins: Option<super::plugin::Pipeline>,
 ) -> Result<()> {
-    // 获取统一连接
+    // 获取统一连接（客户端短暂断线时保持访客连接，等待其重连）
     let conn = match conn_provider.get_connection(&client_id).await {
         Some(c) => c,
         None => {
-            error!("[{}] ❌ 客户端未连接", proxy_name);
-            return Ok(());
+            let max_wait = client_wait_timeout();
+            if !max_wait.is_zero() {
+                info!("[{}] ⏳ 客户端暂未连接，最多等待 {} 秒: {}", proxy_name, max_wait.as_secs(), addr);
+            }
+            match conn_provider.wait_for_connection(&client_id, max_wait).await {
+                Some(c) => c,
+                None => {
+                    error!("[{}] ❌ 客户端未连接", proxy_name);
+                    super::speed_meter::global().add_error(proxy_id, client_id.parse::<i64>().unwrap_or(0));
+                    return Ok(());
+                }
+            }
         }
     };
 
     // 打开双向流
-    let (mut tunnel_send, mut tunnel_recv) = conn.open_bi().await?;
+    let (mut tunnel_send, mut tunnel_recv) = conn.open_bi(speed_limiter.priority()).await?;
 
     info!("[{}] 🔗 隧道流已打开: {}", proxy_name, addr);
 
+    // 最近一次收发数据的时间（相对连接建立时刻的毫秒数），用于空闲超时检测
+    let started = tokio::time::Instant::now();
+    let last_activity = Arc::new(AtomicU64::new(0));
+    let last_activity_t2t = last_activity.clone();
+    let last_activity_t2c = last_activity.clone();
+
     // 发送消息类型 + 协议类型 + 目标地址 (格式: 1字节消息类型'p' + 1字节协议类型 + 2字节长度 + 地址)
     tunnel_send.write_all(&[b'p']).await?; // 'p' 表示代理请求
     tunnel_send.write_all(&[b't']).await?; // 't' 表示TCP
@@ -1181,9 +1609,28 @@ async fn handle_tcp_to_tunnel_unified(
     tunnel_send.write_all(&len.to_be_bytes()).await?;
     tunnel_send.write_all(target_bytes).await?;
     tunnel_send.flush().await?;
+    // 隧道流已建立，不再计入待处理连接
+    drop(pending);
+
+    // 代理正在抓包时记录本连接转发的数据
+    let capture = match tcp_stream.local_addr() {
+        Ok(local) => super::capture::open(proxy_id, addr, display_addr(local)),
+        Err(_) => None,
+    };
+    let capture_t2t = capture.as_ref();
+    let capture_t2c = capture.as_ref();
 
     let (mut tcp_read, mut tcp_write) = tcp_stream.split();
 
+    // 实时速率计数器
+    let meter = super::speed_meter::global().counter(proxy_id, client_id.parse::<i64>().unwrap_or(0));
+    let meter_t2t = meter.clone();
+    let meter_t2c = meter.clone();
+    let source_t2t = &source;
+    let source_t2c = &source;
+    let plugins_t2t = plugins.as_ref();
+    let plugins_t2c = plugins.as_ref();
+
     // 使用 AtomicI64 在两个方向上统计流量（无锁，性能更好）
     let sent_stats = Arc::new(std::sync::atomic::AtomicI64::new(0));
     let received_stats = Arc::new(std::sync::atomic::AtomicI64::new(0));
@@ -1191,6 +1638,11 @@ async fn handle_tcp_to_tunnel_unified(
     let sent_stats_clone = sent_stats.clone();
     let received_stats_clone = received_stats.clone();
 
+    // 客户端所属用户的速度限制（与节点总限制同时生效）
+    let user_limiter = super::speed_limiter::users().for_client(&client_id);
+    let user_limiter_t2t = user_limiter.clone();
+    let user_limiter_t2c = user_limiter;
+
     // TCP -> Tunnel
     let proxy_name_t2t = proxy_name.clone();
     let speed_limiter_t2t = speed_limiter.clone();
@@ -1201,9 +1653,21 @@ async fn handle_tcp_to_tunnel_unified(
             if n == 0 {
                 break;
             }
+            if let Some(plugins) = plugins_t2t {
+                plugins.inspect(super::plugin::Direction::Inbound, &buf[..n])?;
+            }
+            if let Some(capture) = capture_t2t {
+                capture.record(super::plugin::Direction::Inbound, &buf[..n]);
+            }
             speed_limiter_t2t.consume(n).await;
+            if let Some(limiter) = &user_limiter_t2t {
+                limiter.consume(n).await;
+            }
+            source_t2t.record(n).await;
             tunnel_send.write_all(&buf[..n]).await?;
             sent_stats_clone.fetch_add(n as i64, std::sync::atomic::Ordering::Relaxed);
+            meter_t2t.add_sent(n);
+            last_activity_t2t.store(started.elapsed().as_millis() as u64, Ordering::Relaxed);
         }
         // 关闭发送端，通知对端不再有数据
         let _ = tunnel_send.finish().await;
@@ -1221,9 +1685,21 @@ async fn handle_tcp_to_tunnel_unified(
                     if n == 0 {
                         break;
                     }
+                    if let Some(plugins) = plugins_t2c {
+                        plugins.inspect(super::plugin::Direction::Outbound, &buf[..n])?;
+                    }
+                    if let Some(capture) = capture_t2c {
+                        capture.record(super::plugin::Direction::Outbound, &buf[..n]);
+                    }
                     speed_limiter_t2c.consume(n).await;
+                    if let Some(limiter) = &user_limiter_t2c {
+                        limiter.consume(n).await;
+                    }
+                    source_t2c.record(n).await;
                     tcp_write.write_all(&buf[..n]).await?;
                     received_stats_clone.fetch_add(n as i64, std::sync::atomic::Ordering::Relaxed);
+                    meter_t2c.add_received(n);
+                    last_activity_t2c.store(started.elapsed().as_millis() as u64, Ordering::Relaxed);
                 }
                 None => break,
             }
@@ -1231,13 +1707,49 @@ async fn handle_tcp_to_tunnel_unified(
         Ok::<_, anyhow::Error>(())
     };
 
-    // 使用 join! 确保两个方向都完成，避免 select! 取消导致流量统计丢失
-    let (res_t2t, res_t2c) = tokio::join!(tcp_to_tunnel, tunnel_to_tcp);
-    if let Err(e) = res_t2t {
-        debug!("[{}] TCP->Tunnel结束: {}", proxy_name_t2t, e);
-    }
-    if let Err(e) = res_t2c {
-        debug!("[{}] Tunnel->TCP结束: {}", proxy_name_t2c, e);
+    // 空闲检测：超过 idle_timeout 没有任何方向的数据时结束转发
+    let idle_watchdog = async {
+        let Some(timeout) = idle_timeout else {
+            return std::future::pending::<()>().await;
+        };
+        loop {
+            let idle_since = Duration::from_millis(last_activity.load(Ordering::Relaxed));
+            let deadline = started + idle_since + timeout;
+            if tokio::time::Instant::now() >= deadline {
+                return;
+            }
+            tokio::time::sleep_until(deadline).await;
+        }
+    };
+
+    // 使用 join! 确保两个方向都完成；流量统计使用原子计数，空闲回收时中断转发也不会丢失
+    tokio::select! {
+        (res_t2t, res_t2c) = async { tokio::join!(tcp_to_tunnel, tunnel_to_tcp) } => {
+            if let Err(e) = res_t2t {
+                debug!("[{}] TCP->Tunnel结束: {}", proxy_name_t2t, e);
+            }
+            if let Err(e) = res_t2c {
+                debug!("[{}] Tunnel->TCP结束: {}", proxy_name_t2c, e);
+            }
+        }
+        _ = idle_watchdog => {
+            reaped_connections.fetch_add(1, Ordering::Relaxed);
+            info!(
+                "[{}] ⏱️ 连接空闲超过 {} 秒，已回收: {}",
+                proxy_name, idle_timeout.map(|t| t.as_secs()).unwrap_or_default(), addr
+            );
+        }
+        _ = source.blocked() => {
+            info!("[{}] 🚫 来源已被封禁，断开连接: {}", proxy_name, addr);
+        }
+        _ = async {
+            match &plugins {
+                Some(plugins) => plugins.closed().await,
+                None => std::future::pending().await,
+            }
+        } => {
+            info!("[{}] 🧩 插件要求断开连接: {}", proxy_name, addr);
+        }
     }
 
     info!("[{}] 🔚 连接已关闭: {}", proxy_name, addr);
@@ -1246,6 +1758,14 @@ async fn handle_tcp_to_tunnel_unified(
     let bytes_sent = sent_stats.load(std::sync::atomic::Ordering::Relaxed);
     let bytes_received = received_stats.load(std::sync::atomic::Ordering::Relaxed);
 
+    if let Some(plugins) = &plugins {
+        plugins.close(&super::plugin::ConnectionStats {
+            bytes_inbound: bytes_sent as u64,
+            bytes_outbound: bytes_received as u64,
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+    }
+
     // 记录流量统计
     if bytes_sent > 0 || bytes_received > 0 {
         let client_id_num = client_id.parse::<i64>().unwrap_or(0);
@@ -1266,68 +1786,62 @@ async fn handle_tcp_to_tunnel_unified(
     Ok(())
 }
 
-async fn handle_udp_to_tunnel_unified(
+/// 运行一个 UDP 会话：同一来源地址的数据报复用一条隧道流，按帧收发
+///
+/// 会话在空闲超时后从会话表移除，发送端随之关闭，流结束后客户端也会释放对应的 UDP 套接字。
+async fn run_framed_udp_session(
+    conn: UnifiedConnection,
+    mut datagrams: tokio::sync::mpsc::Receiver<Vec<u8>>,
     socket: Arc<UdpSocket>,
     src_addr: SocketAddr,
-    data: Vec<u8>,
     target_addr: String,
     proxy_name: String,
     client_id: String,
-    conn_provider: ConnectionProvider,
     proxy_id: i64,
-    _udp_sessions: Arc<RwLock<HashMap<(String, i64), HashMap<SocketAddr, UdpSession>>>>,
     traffic_manager: Arc<TrafficManager>,
+    speed_limiter: super::speed_limiter::ProxyShare,
 ) -> Result<()> {
-    // 获取统一连接
-    let conn = match conn_provider.get_connection(&client_id).await {
-        Some(c) => c,
-        None => {
-            error!("[{}] ❌ 客户端未连接", proxy_name);
-            return Ok(());
-        }
-    };
+    let (mut tunnel_send, mut tunnel_recv) = conn.open_bi(speed_limiter.priority()).await?;
+    info!("[{}] 🔗 UDP隧道流已打开（分帧）: {}", proxy_name, src_addr);
 
-    // 打开双向流
-    let (mut tunnel_send, mut tunnel_recv) = conn.open_bi().await?;
-
-    info!("[{}] 🔗 UDP隧道流已打开: {}", proxy_name, src_addr);
-
-    // 发送消息类型 + 协议类型 + 目标地址 (格式: 1字节消息类型'p' + 1字节协议类型 + 2字节长度 + 地址)
-    tunnel_send.write_all(&[b'p']).await?; // 'p' 表示代理请求
-    tunnel_send.write_all(&[b'u']).await?; // 'u' 表示UDP
+    // 'p' 表示代理请求，'U' 表示分帧 UDP
+    tunnel_send.write_all(&[b'p', b'U']).await?;
     let target_bytes = target_addr.as_bytes();
-    let len = target_bytes.len() as u16;
-    tunnel_send.write_all(&len.to_be_bytes()).await?;
+    tunnel_send.write_all(&(target_bytes.len() as u16).to_be_bytes()).await?;
     tunnel_send.write_all(target_bytes).await?;
-    tunnel_send.write_all(&data).await?;
-    tunnel_send.flush().await?;
-
-    let bytes_sent = data.len() as i64;
 
-    // 读取响应并转发回源
-    let mut recv_buf = vec![0u8; 65535];
+    let mut bytes_sent = 0i64;
     let mut bytes_received = 0i64;
+    let meter = super::speed_meter::global().counter(proxy_id, client_id.parse::<i64>().unwrap_or(0));
+
+    let to_tunnel = async {
+        while let Some(data) = datagrams.recv().await {
+            speed_limiter.consume(data.len()).await;
+            bytes_sent += data.len() as i64;
+            meter.add_sent(data.len());
+            write_datagram(tunnel_send.as_mut(), &data).await?;
+        }
+        tunnel_send.finish().await
+    };
 
-    loop {
-        match tunnel_recv.read(&mut recv_buf).await? {
-            Some(n) => {
-                if n == 0 {
-                    break;
-                }
-                bytes_received += n as i64;
-                socket.send_to(&recv_buf[..n], src_addr).await?;
-            }
-            None => break,
+    let from_tunnel = async {
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+        while let Some(n) = read_datagram(tunnel_recv.as_mut(), &mut buf).await? {
+            speed_limiter.consume(n).await;
+            bytes_received += n as i64;
+            meter.add_received(n);
+            socket.send_to(&buf[..n], src_addr).await?;
         }
-    }
+        Ok::<_, anyhow::Error>(())
+    };
 
-    tunnel_send.finish().await?;
+    let result = tokio::select! {
+        res = to_tunnel => res,
+        res = from_tunnel => res,
+    };
 
-    // 统一记录流量
     if bytes_sent > 0 || bytes_received > 0 {
         let client_id_num = client_id.parse::<i64>().unwrap_or(0);
-
-        // 1. 记录 proxy/client/daily 维度的流量
         traffic_manager.record_traffic(
             proxy_id,
             client_id_num,
@@ -1337,5 +1851,5 @@ async fn handle_udp_to_tunnel_unified(
         ).await;
     }
 
-    Ok(())
+    result
 }
@@ -0,0 +1,213 @@
+//! 节点资源保护
+//!
+//! 每条 TCP 转发连接都会占用一个访客套接字和一个任务，热门代理的连接数可能把整个进程的文件描述符耗尽，
+//! 之后所有代理的 accept 都会以 EMFILE 失败。这里限制节点上同时进行的转发任务总数（TCP 连接和 UDP 会话），
+//! 超出的新连接直接关闭；并定期检查打开的文件描述符数，接近系统限制时输出警告。
+//!
+//! - 启动时把文件描述符软限制提高到硬限制（Unix）；
+//! - 转发任务上限由 `OXIPROXY_NODE_MAX_RELAYS` 控制，默认为文件描述符软限制的 80%，0 表示不限制。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{info, warn};
+
+/// 转发任务默认上限占文件描述符软限制的比例
+const DEFAULT_RELAY_RATIO: f64 = 0.8;
+/// 文件描述符使用率超过该比例时警告
+const FD_WARN_RATIO: f64 = 0.8;
+/// 转发任务数超过上限的该比例时警告
+const RELAY_WARN_RATIO: f64 = 0.9;
+/// 检查间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// 同一类警告的最小间隔
+const WARN_INTERVAL: Duration = Duration::from_secs(300);
+/// 拒绝连接时汇总日志的间隔
+const REJECT_LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 节点转发任务的占位，连接或会话结束时释放
+pub struct RelayPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+pub struct ResourceGuard {
+    relays: Option<Arc<Semaphore>>,
+    relay_limit: usize,
+    fd_limit: Option<u64>,
+    rejected: AtomicU64,
+    /// 上次汇总日志的时间和期间拒绝的数量
+    reject_log: Mutex<(Option<Instant>, u64)>,
+}
+
+impl ResourceGuard {
+    fn new(relay_limit: usize, fd_limit: Option<u64>) -> Self {
+        Self {
+            relays: (relay_limit > 0).then(|| Arc::new(Semaphore::new(relay_limit))),
+            relay_limit,
+            fd_limit,
+            rejected: AtomicU64::new(0),
+            reject_log: Mutex::new((None, 0)),
+        }
+    }
+
+    /// 开始一个转发任务，达到上限时返回 None
+    pub fn try_acquire(&self) -> Option<RelayPermit> {
+        let Some(relays) = &self.relays else {
+            return Some(RelayPermit { _permit: None });
+        };
+        match relays.clone().try_acquire_owned() {
+            Ok(permit) => Some(RelayPermit { _permit: Some(permit) }),
+            Err(_) => {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                self.report_rejected();
+                None
+            }
+        }
+    }
+
+    fn report_rejected(&self) {
+        let mut log = self.reject_log.lock().unwrap();
+        log.1 += 1;
+        let now = Instant::now();
+        if log.0.is_some_and(|last| now.duration_since(last) < REJECT_LOG_INTERVAL) {
+            return;
+        }
+        warn!(
+            "⚠️ 节点转发任务数达到上限 {}，已拒绝 {} 个新连接（OXIPROXY_NODE_MAX_RELAYS）",
+            self.relay_limit, log.1
+        );
+        *log = (Some(now), 0);
+    }
+
+    /// 正在进行的转发任务数
+    pub fn active_relays(&self) -> u64 {
+        self.relays
+            .as_ref()
+            .map_or(0, |s| (self.relay_limit - s.available_permits()) as u64)
+    }
+
+    /// 转发任务上限，0 表示不限制
+    pub fn relay_limit(&self) -> u64 {
+        self.relay_limit as u64
+    }
+
+    /// 因达到上限被拒绝的连接累计数
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    /// 文件描述符软限制，无法获取时为 0
+    pub fn fd_limit(&self) -> u64 {
+        self.fd_limit.unwrap_or(0)
+    }
+}
+
+pub fn global() -> &'static ResourceGuard {
+    static GUARD: OnceLock<ResourceGuard> = OnceLock::new();
+    GUARD.get_or_init(|| {
+        let fd_limit = raise_fd_limit();
+        let relay_limit = common::env::parse::<usize>("OXIPROXY_NODE_MAX_RELAYS")
+            .unwrap_or_else(|| default_relay_limit(fd_limit));
+        ResourceGuard::new(relay_limit, fd_limit)
+    })
+}
+
+fn default_relay_limit(fd_limit: Option<u64>) -> usize {
+    fd_limit.map_or(0, |l| (l as f64 * DEFAULT_RELAY_RATIO) as usize)
+}
+
+/// 把文件描述符软限制提高到硬限制，返回最终的软限制
+#[cfg(unix)]
+fn raise_fd_limit() -> Option<u64> {
+    let mut rl = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut rl) } != 0 {
+        warn!("获取文件描述符限制失败: {}", std::io::Error::last_os_error());
+        return None;
+    }
+    if rl.rlim_cur < rl.rlim_max {
+        // 硬限制可能为 RLIM_INFINITY（macOS 不接受），此时只提高到一个较大的固定值
+        let target = rl.rlim_max.min(1 << 20);
+        let raised = libc::rlimit { rlim_cur: target, rlim_max: rl.rlim_max };
+        if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raised) } == 0 {
+            info!("文件描述符限制: {} -> {}", rl.rlim_cur, target);
+            rl.rlim_cur = target;
+        } else {
+            warn!("提高文件描述符限制失败: {}", std::io::Error::last_os_error());
+        }
+    }
+    Some(rl.rlim_cur)
+}
+
+#[cfg(not(unix))]
+fn raise_fd_limit() -> Option<u64> {
+    None
+}
+
+/// 当前进程打开的文件描述符数（仅 Linux / macOS）
+pub fn open_fds() -> Option<u64> {
+    let dir = std::fs::read_dir("/proc/self/fd").or_else(|_| std::fs::read_dir("/dev/fd")).ok()?;
+    // 减去 read_dir 自身占用的描述符
+    Some((dir.count() as u64).saturating_sub(1))
+}
+
+/// 启动资源检查任务
+pub fn start() {
+    let guard = global();
+    if guard.relay_limit > 0 {
+        info!("节点转发任务上限: {}", guard.relay_limit);
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        let mut last_fd_warn: Option<Instant> = None;
+        let mut last_relay_warn: Option<Instant> = None;
+        loop {
+            interval.tick().await;
+            let now = Instant::now();
+            let can_warn = |last: Option<Instant>| last.is_none_or(|t| now.duration_since(t) >= WARN_INTERVAL);
+
+            if let (Some(open), Some(limit)) = (open_fds(), guard.fd_limit) {
+                if open as f64 >= limit as f64 * FD_WARN_RATIO && can_warn(last_fd_warn) {
+                    warn!("⚠️ 打开的文件描述符接近系统限制: {}/{}", open, limit);
+                    last_fd_warn = Some(now);
+                }
+            }
+
+            let active = guard.active_relays();
+            if guard.relay_limit > 0
+                && active as f64 >= guard.relay_limit as f64 * RELAY_WARN_RATIO
+                && can_warn(last_relay_warn)
+            {
+                warn!("⚠️ 节点转发任务数接近上限: {}/{}", active, guard.relay_limit);
+                last_relay_warn = Some(now);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relay_limit() {
+        let guard = ResourceGuard::new(2, Some(1024));
+        let a = guard.try_acquire().unwrap();
+        let _b = guard.try_acquire().unwrap();
+        assert!(guard.try_acquire().is_none());
+        assert_eq!(guard.active_relays(), 2);
+        assert_eq!(guard.rejected(), 1);
+        drop(a);
+        assert!(guard.try_acquire().is_some());
+    }
+
+    #[test]
+    fn test_unlimited() {
+        let guard = ResourceGuard::new(0, None);
+        assert!(guard.try_acquire().is_some());
+        assert_eq!(guard.active_relays(), 0);
+        assert_eq!(default_relay_limit(None), 0);
+        assert_eq!(default_relay_limit(Some(1000)), 800);
+    }
+}
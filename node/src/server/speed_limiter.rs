@@ -1,10 +1,109 @@
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
-use tokio::sync::Notify;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use tokio::sync::{oneshot, Notify};
 use tokio::time::{Duration, Instant};
+use tracing::info;
+
+use common::grpc::oxiproxy;
+use common::protocol::control::ProxyPriority;
+
+/// 公平分配时每轮每单位权重可发送的字节数（一次读取的缓冲区大小）
+const FAIR_QUANTUM: f64 = 8192.0;
+
+/// 等待 token 的一次消费
+struct Waiter {
+    remaining: f64,
+    done: oneshot::Sender<()>,
+}
+
+/// 一个代理的等待队列
+struct Flow {
+    weight: u32,
+    /// 本轮剩余的配额（字节）
+    deficit: f64,
+    waiters: VecDeque<Waiter>,
+}
+
+/// 按代理公平分配 token 的 DRR（差额轮询）队列
+///
+/// 有等待者的代理轮流获得 `FAIR_QUANTUM * 权重` 的配额，单个大流量代理无法占满带宽，
+/// 交互式代理的少量数据总能在一轮内发出。
+#[derive(Default)]
+struct FairQueue {
+    flows: HashMap<i64, Flow>,
+    /// 有等待者的代理，按轮询顺序排列
+    active: VecDeque<i64>,
+}
+
+impl FairQueue {
+    fn push(&mut self, proxy_id: i64, weight: u32, bytes: f64) -> oneshot::Receiver<()> {
+        let (done, rx) = oneshot::channel();
+        let flow = self.flows.entry(proxy_id).or_insert_with(|| Flow {
+            weight,
+            deficit: 0.0,
+            waiters: VecDeque::new(),
+        });
+        flow.weight = weight.max(1);
+        if flow.waiters.is_empty() {
+            self.active.push_back(proxy_id);
+        }
+        flow.waiters.push_back(Waiter { remaining: bytes, done });
+        rx
+    }
+
+    /// 用 `available` 个 token 按轮询放行等待者
+    fn dispatch(&mut self, available: &mut f64) {
+        while *available >= 1.0 {
+            let Some(proxy_id) = self.active.pop_front() else {
+                break;
+            };
+            let Some(flow) = self.flows.get_mut(&proxy_id) else {
+                continue;
+            };
+            let quantum = FAIR_QUANTUM * flow.weight as f64;
+            flow.deficit += quantum;
+            while let Some(waiter) = flow.waiters.front_mut() {
+                // 连接已关闭的等待者不再分配
+                if waiter.done.is_closed() {
+                    flow.waiters.pop_front();
+                    continue;
+                }
+                let grant = waiter.remaining.min(flow.deficit).min(*available);
+                waiter.remaining -= grant;
+                flow.deficit -= grant;
+                *available -= grant;
+                if waiter.remaining > 0.0 {
+                    break;
+                }
+                if let Some(waiter) = flow.waiters.pop_front() {
+                    let _ = waiter.done.send(());
+                }
+            }
+            if flow.waiters.is_empty() {
+                self.flows.remove(&proxy_id);
+            } else {
+                flow.deficit = flow.deficit.min(quantum);
+                self.active.push_back(proxy_id);
+            }
+        }
+    }
+
+    /// 放行所有等待者（切换为不限速或关闭公平分配时）
+    fn release_all(&mut self) {
+        for (_, flow) in self.flows.drain() {
+            for waiter in flow.waiters {
+                let _ = waiter.done.send(());
+            }
+        }
+        self.active.clear();
+    }
+}
 
 /// 基于 token bucket 的速度限制器
-/// 所有代理连接共享同一个实例，限制整个节点的总带宽
+/// 节点级限制器由所有代理连接共享，限制整个节点的总带宽；用户级限制器由该用户的客户端共享
+///
+/// 节点开启公平分配后，带宽饱和时各代理按权重轮流获得 token（见 [`FairQueue`]）。
 pub struct SpeedLimiter {
     /// 速率限制(bytes/sec)，0 = 不限速
     rate: AtomicU64,
@@ -14,6 +113,10 @@ pub struct SpeedLimiter {
     last_refill: std::sync::Mutex<Instant>,
     /// 通知等待中的消费者有新 token
     notify: Notify,
+    /// 是否按代理公平分配
+    fair: AtomicBool,
+    /// 公平分配时的等待队列（加锁顺序：先 queue 后 available）
+    queue: std::sync::Mutex<FairQueue>,
 }
 
 impl SpeedLimiter {
@@ -23,17 +126,22 @@ impl SpeedLimiter {
             available: std::sync::Mutex::new(rate as f64),
             last_refill: std::sync::Mutex::new(Instant::now()),
             notify: Notify::new(),
+            fair: AtomicBool::new(false),
+            queue: std::sync::Mutex::new(FairQueue::default()),
         });
 
-        // 启动后台补充 token 任务
-        let limiter_clone = limiter.clone();
+        // 启动后台补充 token 任务，限制器释放后退出
+        let weak = Arc::downgrade(&limiter);
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(Duration::from_millis(10));
             interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
             loop {
                 interval.tick().await;
-                limiter_clone.refill();
+                match weak.upgrade() {
+                    Some(limiter) => limiter.refill(),
+                    None => return,
+                }
             }
         });
 
@@ -59,13 +167,56 @@ impl SpeedLimiter {
         let max_tokens = rate as f64; // 最多积攒 1 秒的量
 
         {
+            let mut queue = self.queue.lock().unwrap();
             let mut available = self.available.lock().unwrap();
             *available = (*available + tokens_to_add).min(max_tokens);
+            queue.dispatch(&mut available);
         }
 
         self.notify.notify_waiters();
     }
 
+    /// 代理连接使用的限制器句柄，调度权重为带宽权重乘以优先级系数
+    pub fn share(self: &Arc<Self>, proxy_id: i64, weight: u32, priority: ProxyPriority) -> ProxyShare {
+        ProxyShare {
+            limiter: self.clone(),
+            proxy_id,
+            weight: weight.max(1).saturating_mul(priority.weight_factor()),
+            priority,
+        }
+    }
+
+    /// 以代理 `proxy_id` 的身份消费 token，开启公平分配时按权重排队
+    async fn consume_fair(&self, proxy_id: i64, weight: u32, bytes: usize) {
+        if self.rate.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        if !self.fair.load(Ordering::Relaxed) {
+            return self.consume(bytes).await;
+        }
+
+        let rx = {
+            let mut queue = self.queue.lock().unwrap();
+            let mut available = self.available.lock().unwrap();
+            // 没有代理在排队时直接消费
+            if queue.active.is_empty() && *available >= bytes as f64 {
+                *available -= bytes as f64;
+                return;
+            }
+            queue.push(proxy_id, weight, bytes as f64)
+        };
+        let _ = rx.await;
+    }
+
+    /// 开启或关闭按代理公平分配
+    pub fn set_fair(&self, fair: bool) {
+        if !self.fair.swap(fair, Ordering::Relaxed) && fair {
+            info!("节点带宽按代理公平分配已开启");
+        } else if !fair {
+            self.queue.lock().unwrap().release_all();
+        }
+    }
+
     /// 消费指定字节数的 token，如果不够则等待
     pub async fn consume(&self, bytes: usize) {
         let rate = self.rate.load(Ordering::Relaxed);
@@ -89,6 +240,10 @@ impl SpeedLimiter {
 
             // 等待补充
             self.notify.notified().await;
+            // 等待期间切换为不限速（如带宽时间表进入不限速时段）
+            if self.rate.load(Ordering::Relaxed) == 0 {
+                return;
+            }
         }
     }
 
@@ -97,6 +252,7 @@ impl SpeedLimiter {
         self.rate.store(new_rate, Ordering::Relaxed);
         if new_rate == 0 {
             // 切换到不限速时，唤醒所有等待者
+            self.queue.lock().unwrap().release_all();
             self.notify.notify_waiters();
         }
     }
@@ -106,3 +262,115 @@ impl SpeedLimiter {
         self.rate.load(Ordering::Relaxed)
     }
 }
+
+/// 单个代理使用的节点限制器：开启公平分配时以代理为单位按权重排队
+#[derive(Clone)]
+pub struct ProxyShare {
+    limiter: Arc<SpeedLimiter>,
+    proxy_id: i64,
+    weight: u32,
+    priority: ProxyPriority,
+}
+
+impl ProxyShare {
+    pub async fn consume(&self, bytes: usize) {
+        self.limiter.consume_fair(self.proxy_id, self.weight, bytes).await;
+    }
+
+    pub fn priority(&self) -> ProxyPriority {
+        self.priority
+    }
+}
+
+/// 用户级速度限制：同一用户的所有客户端共享一个限制器
+#[derive(Default)]
+pub struct UserLimits {
+    inner: RwLock<UserLimitsInner>,
+}
+
+#[derive(Default)]
+struct UserLimitsInner {
+    /// 用户 ID → 限制器
+    users: HashMap<i64, Arc<SpeedLimiter>>,
+    /// 客户端 ID → 所属用户的限制器
+    by_client: HashMap<String, Arc<SpeedLimiter>>,
+}
+
+impl UserLimits {
+    /// 按 Controller 下发的列表替换用户限制，列表中没有的用户不再限速
+    ///
+    /// 仍在列表中的用户沿用原来的限制器，只更新速率，已建立的连接随之生效。
+    pub fn set(&self, limits: &[oxiproxy::UserSpeedLimit]) {
+        let mut inner = self.inner.write().unwrap();
+        let mut previous = std::mem::take(&mut inner.users);
+        inner.by_client.clear();
+
+        for limit in limits.iter().filter(|l| l.speed_limit > 0) {
+            let rate = limit.speed_limit as u64;
+            let limiter = match previous.remove(&limit.user_id) {
+                Some(limiter) => {
+                    limiter.update_rate(rate);
+                    limiter
+                }
+                None => SpeedLimiter::new(rate),
+            };
+            for client_id in &limit.client_ids {
+                inner.by_client.insert(client_id.clone(), limiter.clone());
+            }
+            inner.users.insert(limit.user_id, limiter);
+        }
+
+        // 不再限速的用户恢复为不限速，唤醒仍在等待的连接
+        for limiter in previous.values() {
+            limiter.update_rate(0);
+        }
+        if !limits.is_empty() || !previous.is_empty() {
+            info!("用户速度限制已更新: {} 个用户限速", inner.users.len());
+        }
+    }
+
+    /// 客户端所属用户的限制器，用户不限速时为 None
+    pub fn for_client(&self, client_id: &str) -> Option<Arc<SpeedLimiter>> {
+        self.inner.read().unwrap().by_client.get(client_id).cloned()
+    }
+}
+
+/// 节点进程内的用户速度限制
+pub fn users() -> &'static UserLimits {
+    static LIMITS: OnceLock<UserLimits> = OnceLock::new();
+    LIMITS.get_or_init(UserLimits::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fair_queue_weights() {
+        let mut queue = FairQueue::default();
+        // 大流量代理 1 排队 10 个块，交互式代理 2（权重 3）排队 3 个块
+        let bulk: Vec<_> = (0..10).map(|_| queue.push(1, 1, FAIR_QUANTUM)).collect();
+        let mut interactive: Vec<_> = (0..3).map(|_| queue.push(2, 3, FAIR_QUANTUM)).collect();
+
+        // 一轮 4 个块的 token：代理 1 得 1 块，代理 2 得 3 块
+        let mut available = FAIR_QUANTUM * 4.0;
+        queue.dispatch(&mut available);
+        assert_eq!(available, 0.0);
+        assert!(interactive.iter_mut().all(|rx| rx.try_recv().is_ok()));
+        let granted = |bulk: &mut Vec<oneshot::Receiver<()>>| bulk.iter_mut().filter_map(|rx| rx.try_recv().ok()).count();
+        let mut bulk = bulk;
+        assert_eq!(granted(&mut bulk), 1);
+
+        // 只剩代理 1 时独占带宽；不足一块的 token 先部分扣减
+        let mut available = FAIR_QUANTUM * 1.5;
+        queue.dispatch(&mut available);
+        assert_eq!(granted(&mut bulk), 1);
+        let mut available = FAIR_QUANTUM * 0.5;
+        queue.dispatch(&mut available);
+        assert_eq!(granted(&mut bulk), 1);
+
+        queue.release_all();
+        assert_eq!(granted(&mut bulk), 7);
+        assert!(queue.active.is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+//! 代理实时速率
+//!
+//! 转发连接每次读写数据时累加到所属代理的计数器（原子操作，连接建立时取得计数器），
+//! 流量管理器每 5 秒结算一次：窗口内的平均速率与之前的速率做指数加权移动平均（EWMA），
+//! 平滑短时突发。结果随节点心跳上报给 Controller，供管理界面展示每个代理和客户端的当前速度。
+//!
+//! 累计流量仍由连接结束时的流量上报负责，这里只用于展示实时速率。
+//! 访客连接处理失败（客户端未连接、隧道流打开失败等）的次数同样按窗口结算为每分钟错误数，供告警规则使用。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use common::grpc::oxiproxy;
+
+/// 结算窗口长度（与流量上报的刷新间隔一致）
+pub const WINDOW: Duration = Duration::from_secs(5);
+/// 新窗口速率的权重
+const ALPHA: f64 = 0.5;
+/// 速率衰减到该值（字节/秒）以下且没有连接在使用时不再上报
+const IDLE_RATE: f64 = 1.0;
+/// 错误率衰减到该值（次/分钟）以下时视为没有错误
+const IDLE_ERROR_RATE: f64 = 0.01;
+
+/// 单个代理当前窗口内的字节数
+#[derive(Default)]
+pub struct ProxyCounter {
+    sent: AtomicU64,
+    received: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl ProxyCounter {
+    pub fn add_sent(&self, bytes: usize) {
+        self.sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_received(&self, bytes: usize) {
+        self.received.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+struct Entry {
+    client_id: i64,
+    counter: Arc<ProxyCounter>,
+    sent: f64,
+    received: f64,
+    /// 每分钟错误数
+    errors: f64,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<i64, Entry>,
+    last_sample: Option<Instant>,
+}
+
+#[derive(Default)]
+pub struct SpeedMeter {
+    inner: Mutex<Inner>,
+}
+
+impl SpeedMeter {
+    /// 取得代理的计数器，连接在整个生命周期内使用同一个计数器
+    pub fn counter(&self, proxy_id: i64, client_id: i64) -> Arc<ProxyCounter> {
+        let mut inner = self.inner.lock().unwrap();
+        let entry = inner.entries.entry(proxy_id).or_insert_with(|| Entry {
+            client_id,
+            counter: Arc::default(),
+            sent: 0.0,
+            received: 0.0,
+            errors: 0.0,
+        });
+        entry.client_id = client_id;
+        entry.counter.clone()
+    }
+
+    /// 记录一次访客连接处理失败
+    pub fn add_error(&self, proxy_id: i64, client_id: i64) {
+        self.counter(proxy_id, client_id).add_error();
+    }
+
+    /// 结算当前窗口，窗口长度按上次结算到 `now` 的实际时间计算
+    pub fn sample_at(&self, now: Instant) {
+        let mut inner = self.inner.lock().unwrap();
+        let elapsed = inner.last_sample.map_or(WINDOW, |last| now.saturating_duration_since(last));
+        if elapsed.is_zero() {
+            return;
+        }
+        inner.last_sample = Some(now);
+
+        let secs = elapsed.as_secs_f64();
+        inner.entries.retain(|_, entry| {
+            let sent = entry.counter.sent.swap(0, Ordering::Relaxed) as f64;
+            let received = entry.counter.received.swap(0, Ordering::Relaxed) as f64;
+            let errors = entry.counter.errors.swap(0, Ordering::Relaxed) as f64;
+            entry.sent = ALPHA * (sent / secs) + (1.0 - ALPHA) * entry.sent;
+            entry.received = ALPHA * (received / secs) + (1.0 - ALPHA) * entry.received;
+            entry.errors = ALPHA * (errors / secs * 60.0) + (1.0 - ALPHA) * entry.errors;
+            // 仍有连接持有计数器时保留，否则等速率衰减后移除
+            Arc::strong_count(&entry.counter) > 1
+                || entry.sent >= IDLE_RATE
+                || entry.received >= IDLE_RATE
+                || entry.errors >= IDLE_ERROR_RATE
+        });
+    }
+
+    /// 各代理的当前速率（不含速率为 0 的代理）
+    pub fn snapshot(&self) -> Vec<oxiproxy::ProxySpeed> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .entries
+            .iter()
+            .map(|(&proxy_id, entry)| oxiproxy::ProxySpeed {
+                proxy_id,
+                client_id: entry.client_id,
+                bytes_sent_per_sec: entry.sent.round() as u64,
+                bytes_received_per_sec: entry.received.round() as u64,
+                errors_per_min: if entry.errors >= IDLE_ERROR_RATE { entry.errors } else { 0.0 },
+            })
+            .filter(|s| s.bytes_sent_per_sec > 0 || s.bytes_received_per_sec > 0 || s.errors_per_min > 0.0)
+            .collect()
+    }
+}
+
+/// 节点进程内的速率统计
+pub fn global() -> &'static SpeedMeter {
+    static METER: OnceLock<SpeedMeter> = OnceLock::new();
+    METER.get_or_init(SpeedMeter::default)
+}
+
+/// 携带当前速率的心跳
+pub fn heartbeat() -> oxiproxy::Heartbeat {
+    oxiproxy::Heartbeat {
+        timestamp: chrono::Utc::now().timestamp(),
+        proxy_speeds: global().snapshot(),
+        system_info: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn speed_of(meter: &SpeedMeter, proxy_id: i64) -> Option<(u64, u64)> {
+        meter
+            .snapshot()
+            .into_iter()
+            .find(|s| s.proxy_id == proxy_id)
+            .map(|s| (s.bytes_sent_per_sec, s.bytes_received_per_sec))
+    }
+
+    #[test]
+    fn test_ewma_converges() {
+        let meter = SpeedMeter::default();
+        let start = Instant::now();
+        meter.sample_at(start);
+
+        let counter = meter.counter(1, 10);
+        counter.add_sent(5000);
+        counter.add_received(10000);
+        meter.sample_at(start + WINDOW);
+        assert_eq!(speed_of(&meter, 1), Some((500, 1000)));
+
+        counter.add_sent(5000);
+        counter.add_received(10000);
+        meter.sample_at(start + WINDOW * 2);
+        assert_eq!(speed_of(&meter, 1), Some((750, 1500)));
+    }
+
+    #[test]
+    fn test_uses_actual_elapsed_time() {
+        let meter = SpeedMeter::default();
+        let start = Instant::now();
+        meter.sample_at(start);
+
+        meter.counter(1, 10).add_sent(10000);
+        meter.sample_at(start + Duration::from_secs(10));
+        assert_eq!(speed_of(&meter, 1), Some((500, 0)));
+    }
+
+    #[test]
+    fn test_error_rate_per_minute() {
+        let meter = SpeedMeter::default();
+        let start = Instant::now();
+        meter.sample_at(start);
+
+        for _ in 0..2 {
+            meter.add_error(1, 10);
+        }
+        meter.sample_at(start + WINDOW);
+        let errors = meter.snapshot().into_iter().find(|s| s.proxy_id == 1).map(|s| s.errors_per_min);
+        // 5 秒内 2 次 = 24 次/分钟，EWMA 取一半
+        assert_eq!(errors, Some(12.0));
+    }
+
+    #[test]
+    fn test_idle_proxy_decays_and_is_dropped() {
+        let meter = SpeedMeter::default();
+        let start = Instant::now();
+        meter.sample_at(start);
+
+        meter.counter(1, 10).add_sent(40);
+        let mut now = start + WINDOW;
+        meter.sample_at(now);
+        assert_eq!(speed_of(&meter, 1), Some((4, 0)));
+
+        for _ in 0..3 {
+            now += WINDOW;
+            meter.sample_at(now);
+        }
+        assert_eq!(speed_of(&meter, 1), None);
+        assert!(meter.inner.lock().unwrap().entries.is_empty());
+    }
+}
@@ -1,12 +1,20 @@
-use std::collections::HashMap;
-use tracing::{debug, error};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
 use tokio::sync::mpsc;
-use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use common::grpc::oxiproxy;
 use common::grpc::oxiproxy::agent_server_message::Payload as AgentPayload;
 
-use super::grpc_client::SharedGrpcSender;
+use super::grpc_client::AgentGrpcClient;
+use super::speed_meter;
+use super::traffic_spool::{self, SpooledBatch, TrafficSpool};
+
+/// 默认确认窗口（同时在途的批次数），Controller 会在确认中调整
+const DEFAULT_WINDOW: usize = 8;
+
+type TrafficBuffer = HashMap<(i64, i64, Option<i64>), (i64, i64)>;
 
 struct TrafficEvent {
     proxy_id: i64,
@@ -16,20 +24,53 @@ struct TrafficEvent {
     bytes_received: i64,
 }
 
+/// 流量上报流上的事件
+enum StreamEvent {
+    Ack(oxiproxy::TrafficAck),
+    /// 流已断开（携带流的代次，避免旧流的关闭影响新流）
+    Closed(u64),
+}
+
 /// 流量统计管理器（通过 gRPC 流上报到 Controller）
 #[derive(Clone)]
 pub struct TrafficManager {
     sender: mpsc::Sender<TrafficEvent>,
 }
 
+/// 流量上报器
+///
+/// 优先使用专用的 `TrafficStream` 流：每个批次带会话和序号，先写入本地暂存文件再发送，
+/// Controller 累计确认后从暂存中移除。在途批次达到窗口上限时暂停发送（背压），
+/// Controller 不可达期间批次在暂存中排队，流重建或节点重启后按原序号重发。
+/// Controller 不支持该流时回退到共享控制流上的 `TrafficReport` 消息。
+struct TrafficUploader {
+    grpc_client: Arc<AgentGrpcClient>,
+    /// 本次进程的会话 ID
+    session: u64,
+    next_seq: u64,
+    /// 所有未确认的批次（按发送顺序）
+    pending: VecDeque<SpooledBatch>,
+    /// `pending` 开头已在当前流上发送的批次数
+    sent: usize,
+    window: usize,
+    stream: Option<mpsc::Sender<oxiproxy::TrafficBatch>>,
+    generation: u64,
+    events: mpsc::Sender<StreamEvent>,
+    spool: TrafficSpool,
+    /// Controller 不支持流量上报流，使用旧的上报方式
+    legacy: bool,
+}
+
 impl TrafficManager {
     /// 创建 gRPC 模式的 TrafficManager
-    pub fn new(grpc_sender: SharedGrpcSender) -> Self {
+    pub fn new(grpc_client: Arc<AgentGrpcClient>) -> Self {
         let (tx, mut rx) = mpsc::channel::<TrafficEvent>(10000);
+        let (event_tx, mut event_rx) = mpsc::channel::<StreamEvent>(64);
 
         tokio::spawn(async move {
-            let mut buffer: HashMap<(i64, i64, Option<i64>), (i64, i64)> = HashMap::new();
-            let mut interval = tokio::time::interval(Duration::from_secs(5));
+            let mut uploader = TrafficUploader::new(grpc_client, event_tx);
+            let mut buffer: TrafficBuffer = HashMap::new();
+            let mut interval = tokio::time::interval(speed_meter::WINDOW);
             interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
             loop {
@@ -41,14 +82,19 @@ impl TrafficManager {
                         entry.1 += event.bytes_received;
 
                         if buffer.len() > 100 {
-                            Self::flush_buffer_grpc(&grpc_sender, &mut buffer).await;
+                            uploader.flush(&mut buffer).await;
                         }
                     }
-                    _ = interval.tick() => {
-                        if !buffer.is_empty() {
-                            Self::flush_buffer_grpc(&grpc_sender, &mut buffer).await;
+                    Some(event) = event_rx.recv() => {
+                        match event {
+                            StreamEvent::Ack(ack) => uploader.on_ack(ack).await,
+                            StreamEvent::Closed(generation) => uploader.on_closed(generation),
                         }
                     }
+                    _ = interval.tick() => {
+                        speed_meter::global().sample_at(std::time::Instant::now());
+                        uploader.flush(&mut buffer).await;
+                    }
                 }
             }
         });
@@ -56,46 +102,6 @@ impl TrafficManager {
         Self { sender: tx }
     }
 
-    /// 通过 gRPC 流发送流量上报
-    async fn flush_buffer_grpc(
-        grpc_sender: &SharedGrpcSender,
-        buffer: &mut HashMap<(i64, i64, Option<i64>), (i64, i64)>,
-    ) {
-        let records: Vec<oxiproxy::TrafficRecord> = buffer
-            .drain()
-            .filter(|(_, (sent, recv))| *sent > 0 || *recv > 0)
-            .map(|((proxy_id, client_id, user_id), (bytes_sent, bytes_received))| {
-                oxiproxy::TrafficRecord {
-                    proxy_id,
-                    client_id: client_id.to_string(),
-                    user_id,
-                    bytes_sent,
-                    bytes_received,
-                }
-            })
-            .collect();
-
-        if records.is_empty() {
-            return;
-        }
-
-        let count = records.len();
-        let msg = oxiproxy::AgentServerMessage {
-            payload: Some(AgentPayload::TrafficReport(oxiproxy::TrafficReportRequest {
-                records,
-            })),
-        };
-
-        match grpc_sender.send(msg).await {
-            Ok(()) => {
-                debug!("gRPC 上报流量: {} 条记录", count);
-            }
-            Err(e) => {
-                error!("gRPC 上报流量失败: {}", e);
-            }
-        }
-    }
-
     /// 实时记录流量统计 (异步非阻塞)
     pub async fn record_traffic(
         &self,
@@ -122,3 +128,206 @@ impl TrafficManager {
         }
     }
 }
+
+impl TrafficUploader {
+    fn new(grpc_client: Arc<AgentGrpcClient>, events: mpsc::Sender<StreamEvent>) -> Self {
+        let session = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        let mut spool = TrafficSpool::from_env();
+        let pending = spool.load(traffic_spool::unix_now());
+        if !pending.is_empty() {
+            info!("读取到 {} 个未确认的流量批次，将在连接 Controller 后重发", pending.len());
+        }
+        Self {
+            grpc_client,
+            session,
+            next_seq: 1,
+            pending,
+            sent: 0,
+            window: DEFAULT_WINDOW,
+            stream: None,
+            generation: 0,
+            events,
+            spool,
+            legacy: false,
+        }
+    }
+
+    /// 将缓冲区中的流量打包为批次并发送窗口内的批次
+    async fn flush(&mut self, buffer: &mut TrafficBuffer) {
+        if self.legacy {
+            self.flush_legacy(take_records(buffer)).await;
+            return;
+        }
+
+        let records = take_records(buffer);
+        if !records.is_empty() {
+            let batch = oxiproxy::TrafficBatch {
+                session: self.session,
+                seq: self.next_seq,
+                records,
+                report_id: common::grpc::traffic_report_id(self.session, self.next_seq),
+            };
+            self.next_seq += 1;
+            let batch = SpooledBatch { batch, created_at: traffic_spool::unix_now() };
+            self.spool.append_batch(&batch);
+            self.pending.push_back(batch);
+        }
+
+        // 暂存过大或最早的批次已无法去重时压缩，丢弃的批次中可能有已发送的
+        let now = traffic_spool::unix_now();
+        if self.spool.needs_compaction(&self.pending, now) {
+            let dropped = self.spool.compact(&mut self.pending, now);
+            self.sent = self.sent.saturating_sub(dropped);
+        }
+
+        if self.stream.is_none() && !self.pending.is_empty() {
+            self.open_stream().await;
+        }
+        self.pump().await;
+    }
+
+    /// 在窗口允许的范围内发送尚未发送的批次
+    async fn pump(&mut self) {
+        let Some(stream) = &self.stream else {
+            return;
+        };
+        while self.sent < self.pending.len() && self.sent < self.window {
+            let batch = self.pending[self.sent].batch.clone();
+            let count = batch.records.len();
+            if stream.send(batch).await.is_err() {
+                self.stream = None;
+                self.sent = 0;
+                return;
+            }
+            debug!("gRPC 上报流量: {} 条记录", count);
+            self.sent += 1;
+        }
+        if self.sent < self.pending.len() {
+            debug!("流量上报窗口已满，{} 个批次排队等待确认", self.pending.len() - self.sent);
+        }
+    }
+
+    /// 建立流量上报流，未确认的批次随后从头重发
+    async fn open_stream(&mut self) {
+        let (tx, rx) = mpsc::channel::<oxiproxy::TrafficBatch>(DEFAULT_WINDOW * 2);
+        let mut acks = match self.grpc_client.open_traffic_stream(rx).await {
+            Ok(acks) => acks,
+            Err(status) if status.code() == tonic::Code::Unimplemented => {
+                warn!("Controller 不支持流量上报流，使用旧的上报方式");
+                self.legacy = true;
+                let records = self.pending.drain(..).flat_map(|b| b.batch.records).collect();
+                self.spool.clear();
+                self.flush_legacy(records).await;
+                return;
+            }
+            Err(status) => {
+                warn!("建立流量上报流失败（{} 个批次待上报）: {}", self.pending.len(), status.message());
+                return;
+            }
+        };
+
+        self.generation += 1;
+        let generation = self.generation;
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            loop {
+                match acks.message().await {
+                    Ok(Some(ack)) => {
+                        if events.send(StreamEvent::Ack(ack)).await.is_err() {
+                            return;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        warn!("流量上报流错误: {}", e);
+                        break;
+                    }
+                }
+            }
+            let _ = events.send(StreamEvent::Closed(generation)).await;
+        });
+
+        if self.pending.len() > 1 {
+            info!("流量上报流已建立，重发 {} 个未确认的批次", self.pending.len());
+        }
+        self.stream = Some(tx);
+        self.sent = 0;
+    }
+
+    async fn on_ack(&mut self, ack: oxiproxy::TrafficAck) {
+        let mut acked = false;
+        while self
+            .pending
+            .front()
+            .is_some_and(|b| b.batch.session == ack.session && b.batch.seq <= ack.seq)
+        {
+            self.pending.pop_front();
+            self.sent = self.sent.saturating_sub(1);
+            acked = true;
+        }
+        if acked {
+            if self.pending.is_empty() {
+                self.spool.clear();
+            } else {
+                self.spool.append_ack(ack.session, ack.seq);
+            }
+        }
+        if ack.window > 0 {
+            self.window = ack.window as usize;
+        }
+        self.pump().await;
+    }
+
+    fn on_closed(&mut self, generation: u64) {
+        if generation == self.generation {
+            self.stream = None;
+            self.sent = 0;
+        }
+    }
+
+    /// 通过共享控制流发送流量上报（旧版 Controller）
+    async fn flush_legacy(&mut self, records: Vec<oxiproxy::TrafficRecord>) {
+        if records.is_empty() {
+            return;
+        }
+
+        let count = records.len();
+        let report_id = common::grpc::traffic_report_id(self.session, self.next_seq);
+        self.next_seq += 1;
+        let msg = oxiproxy::AgentServerMessage {
+            payload: Some(AgentPayload::TrafficReport(oxiproxy::TrafficReportRequest {
+                records,
+                report_id,
+            })),
+        };
+
+        match self.grpc_client.shared_sender().send(msg).await {
+            Ok(()) => {
+                debug!("gRPC 上报流量: {} 条记录", count);
+            }
+            Err(e) => {
+                error!("gRPC 上报流量失败: {}", e);
+            }
+        }
+    }
+}
+
+/// 取出缓冲区中的流量记录（跳过为零的记录）
+fn take_records(buffer: &mut TrafficBuffer) -> Vec<oxiproxy::TrafficRecord> {
+    buffer
+        .drain()
+        .filter(|(_, (sent, recv))| *sent > 0 || *recv > 0)
+        .map(|((proxy_id, client_id, user_id), (bytes_sent, bytes_received))| {
+            oxiproxy::TrafficRecord {
+                proxy_id,
+                client_id: client_id.to_string(),
+                user_id,
+                bytes_sent,
+                bytes_received,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,354 @@
+//! 流量上报本地暂存
+//!
+//! 每个流量批次在发送前追加写入本地文件，收到 Controller 确认后追加一条确认记录，
+//! 全部确认后清空文件。Controller 不可达期间批次持续累积在文件中，节点重启后读取
+//! 未确认的批次并按原会话和序号重发，由 Controller 去重，避免计费漏计或重复计算。
+//!
+//! Controller 只保留 7 天内的上报 ID，更早的批次重发时无法去重，因此超过 `MAX_AGE_SECS`
+//! 的批次直接丢弃。文件大小超过上限（`OXIPROXY_TRAFFIC_SPOOL_MAX_MB`，默认 64）时压缩为
+//! 只包含未确认的批次，仍然超出时从最早的批次开始丢弃；丢弃的批次会记录警告。
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use common::grpc::oxiproxy;
+
+/// 默认暂存文件路径（相对于工作目录）
+const DEFAULT_SPOOL_FILE: &str = "traffic_spool.jsonl";
+/// 默认暂存文件大小上限
+const DEFAULT_MAX_BYTES: u64 = 64 * 1024 * 1024;
+/// 批次的最长保留时间：Controller 的上报 ID 保留 7 天，这里留出 1 天余量应对时钟偏差
+pub const MAX_AGE_SECS: u64 = 6 * 24 * 3600;
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SpoolEntry {
+    Batch {
+        session: u64,
+        seq: u64,
+        /// 批次生成时间（Unix 秒），旧版本写入的记录没有该字段
+        #[serde(default)]
+        created_at: u64,
+        /// (proxy_id, client_id, user_id, bytes_sent, bytes_received)
+        records: Vec<(i64, String, Option<i64>, i64, i64)>,
+    },
+    Ack {
+        session: u64,
+        seq: u64,
+    },
+}
+
+/// 暂存中的批次
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpooledBatch {
+    pub batch: oxiproxy::TrafficBatch,
+    /// 批次生成时间（Unix 秒）
+    pub created_at: u64,
+}
+
+impl SpooledBatch {
+    fn expired(&self, now: u64) -> bool {
+        now.saturating_sub(self.created_at) > MAX_AGE_SECS
+    }
+}
+
+pub struct TrafficSpool {
+    /// 为 `None` 时不启用暂存
+    path: Option<PathBuf>,
+    file: Option<File>,
+    /// 文件当前大小（未启用暂存时为内存中批次的编码大小）
+    size: u64,
+    max_bytes: u64,
+}
+
+impl TrafficSpool {
+    /// 从环境变量 `OXIPROXY_TRAFFIC_SPOOL` 读取暂存文件路径，设置为 `off` 时禁用；
+    /// `OXIPROXY_TRAFFIC_SPOOL_MAX_MB` 设置文件大小上限
+    pub fn from_env() -> Self {
+        let path = match common::env::var("OXIPROXY_TRAFFIC_SPOOL") {
+            Some(v) if v.eq_ignore_ascii_case("off") => None,
+            Some(v) => Some(PathBuf::from(v)),
+            None => Some(PathBuf::from(DEFAULT_SPOOL_FILE)),
+        };
+        let max_bytes = common::env::parse::<u64>("OXIPROXY_TRAFFIC_SPOOL_MAX_MB")
+            .map_or(DEFAULT_MAX_BYTES, |mb| mb.max(1) * 1024 * 1024);
+        Self::new(path, max_bytes)
+    }
+
+    fn new(path: Option<PathBuf>, max_bytes: u64) -> Self {
+        Self { path, file: None, size: 0, max_bytes }
+    }
+
+    /// 读取上次运行时未确认的批次，丢弃过期和超出上限的批次，并将文件压缩为只包含剩余批次
+    pub fn load(&mut self, now: u64) -> VecDeque<SpooledBatch> {
+        let Some(path) = &self.path else {
+            return VecDeque::new();
+        };
+        let Ok(file) = File::open(path) else {
+            return VecDeque::new();
+        };
+
+        let mut batches: VecDeque<SpooledBatch> = VecDeque::new();
+        for line in BufReader::new(file).lines() {
+            let Ok(line) = line else { break };
+            match serde_json::from_str::<SpoolEntry>(&line) {
+                Ok(SpoolEntry::Batch { session, seq, created_at, records }) => batches.push_back(SpooledBatch {
+                    batch: oxiproxy::TrafficBatch {
+                        session,
+                        seq,
+                        report_id: common::grpc::traffic_report_id(session, seq),
+                        records: records
+                            .into_iter()
+                            .map(|(proxy_id, client_id, user_id, bytes_sent, bytes_received)| oxiproxy::TrafficRecord {
+                                proxy_id,
+                                client_id,
+                                user_id,
+                                bytes_sent,
+                                bytes_received,
+                            })
+                            .collect(),
+                    },
+                    // 旧记录按会话开始时间（纳秒）估算，不会晚于实际生成时间
+                    created_at: if created_at > 0 { created_at } else { session / 1_000_000_000 },
+                }),
+                Ok(SpoolEntry::Ack { session, seq }) => {
+                    batches.retain(|b| b.batch.session != session || b.batch.seq > seq);
+                }
+                // 进程中途退出时最后一行可能不完整
+                Err(e) => warn!("流量暂存文件 {} 中有无法解析的记录: {}", path.display(), e),
+            }
+        }
+
+        self.compact(&mut batches, now);
+        batches
+    }
+
+    /// 追加一个待发送的批次
+    pub fn append_batch(&mut self, batch: &SpooledBatch) {
+        self.append(&batch_entry(batch));
+    }
+
+    /// 记录确认：该会话中序号不大于 `seq` 的批次已被 Controller 接收
+    pub fn append_ack(&mut self, session: u64, seq: u64) {
+        self.append(&SpoolEntry::Ack { session, seq });
+    }
+
+    /// 所有批次均已确认，清空文件
+    pub fn clear(&mut self) {
+        self.rewrite(&VecDeque::new());
+    }
+
+    /// 文件超过大小上限，或最早的未确认批次已超过保留时间
+    pub fn needs_compaction(&self, pending: &VecDeque<SpooledBatch>, now: u64) -> bool {
+        self.size > self.max_bytes || pending.front().is_some_and(|b| b.expired(now))
+    }
+
+    /// 丢弃过期的批次，剩余批次仍超过上限的一半时从最早的开始丢弃，然后重写文件。
+    /// 返回从 `pending` 开头丢弃的批次数
+    pub fn compact(&mut self, pending: &mut VecDeque<SpooledBatch>, now: u64) -> usize {
+        let mut expired = 0;
+        while pending.front().is_some_and(|b| b.expired(now)) {
+            pending.pop_front();
+            expired += 1;
+        }
+
+        // 压缩到上限的一半，避免之后每次追加都触发重写
+        let mut size: u64 = pending.iter().map(|b| encode(&batch_entry(b)).len() as u64).sum();
+        let mut overflow = 0;
+        while size > self.max_bytes / 2 {
+            let Some(b) = pending.pop_front() else { break };
+            size -= encode(&batch_entry(&b)).len() as u64;
+            overflow += 1;
+        }
+
+        if expired > 0 {
+            warn!("丢弃 {} 个超过 {} 天仍未确认的流量批次（Controller 已无法去重）", expired, MAX_AGE_SECS / 86400);
+        }
+        if overflow > 0 {
+            warn!(
+                "流量暂存超过上限 {} MB，丢弃最早的 {} 个未确认批次",
+                self.max_bytes / 1024 / 1024,
+                overflow
+            );
+        }
+        self.rewrite(pending);
+        expired + overflow
+    }
+
+    fn append(&mut self, entry: &SpoolEntry) {
+        let line = encode(entry);
+        self.size += line.len() as u64;
+        let Some(path) = &self.path else {
+            return;
+        };
+        if self.file.is_none() {
+            match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(f) => self.file = Some(f),
+                Err(e) => {
+                    warn!("打开流量暂存文件 {} 失败: {}", path.display(), e);
+                    return;
+                }
+            }
+        }
+        if let Some(file) = self.file.as_mut() {
+            if let Err(e) = file.write_all(line.as_bytes()) {
+                warn!("写入流量暂存文件 {} 失败: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// 用指定批次重写文件（先写临时文件再重命名）
+    fn rewrite(&mut self, batches: &VecDeque<SpooledBatch>) {
+        let content: String = batches.iter().map(|b| encode(&batch_entry(b))).collect();
+        self.size = content.len() as u64;
+        let Some(path) = &self.path else {
+            return;
+        };
+        self.file = None;
+        if batches.is_empty() && !path.exists() {
+            return;
+        }
+
+        let tmp = path.with_extension("jsonl.tmp");
+        if let Err(e) = std::fs::write(&tmp, content).and_then(|_| std::fs::rename(&tmp, path)) {
+            warn!("写入流量暂存文件 {} 失败: {}", path.display(), e);
+        }
+    }
+}
+
+fn batch_entry(spooled: &SpooledBatch) -> SpoolEntry {
+    let batch = &spooled.batch;
+    SpoolEntry::Batch {
+        session: batch.session,
+        seq: batch.seq,
+        created_at: spooled.created_at,
+        records: batch
+            .records
+            .iter()
+            .map(|r| (r.proxy_id, r.client_id.clone(), r.user_id, r.bytes_sent, r.bytes_received))
+            .collect(),
+    }
+}
+
+/// 编码为一行 JSON
+fn encode(entry: &SpoolEntry) -> String {
+    let mut line = serde_json::to_string(entry).unwrap_or_default();
+    line.push('\n');
+    line
+}
+
+/// 当前 Unix 时间（秒）
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn spool_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("oxiproxy-spool-{}-{}.jsonl", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn batch(seq: u64, created_at: u64) -> SpooledBatch {
+        SpooledBatch {
+            batch: oxiproxy::TrafficBatch {
+                session: 1,
+                seq,
+                report_id: common::grpc::traffic_report_id(1, seq),
+                records: vec![oxiproxy::TrafficRecord {
+                    proxy_id: 7,
+                    client_id: "3".to_string(),
+                    user_id: Some(2),
+                    bytes_sent: 100 * seq as i64,
+                    bytes_received: 10,
+                }],
+            },
+            created_at,
+        }
+    }
+
+    #[test]
+    fn test_append_and_replay() {
+        let path = spool_path("replay");
+        let mut spool = TrafficSpool::new(Some(path.clone()), DEFAULT_MAX_BYTES);
+        for seq in 1..=4 {
+            spool.append_batch(&batch(seq, NOW));
+        }
+        spool.append_ack(1, 2);
+
+        // 重启后只重发未确认的批次，文件压缩为只包含这些批次
+        let mut restarted = TrafficSpool::new(Some(path.clone()), DEFAULT_MAX_BYTES);
+        let pending = restarted.load(NOW + 60);
+        assert_eq!(pending, VecDeque::from([batch(3, NOW), batch(4, NOW)]));
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+        restarted.clear();
+        assert!(TrafficSpool::new(Some(path.clone()), DEFAULT_MAX_BYTES).load(NOW).is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_replay_drops_expired() {
+        let path = spool_path("expired");
+        let old = NOW - MAX_AGE_SECS - 1;
+        std::fs::write(
+            &path,
+            // 旧版本的记录没有 created_at，按会话开始时间估算
+            format!(
+                "{{\"type\":\"batch\",\"session\":{},\"seq\":1,\"records\":[]}}\n{}{}",
+                old * 1_000_000_000,
+                encode(&batch_entry(&batch(2, old))),
+                encode(&batch_entry(&batch(3, NOW - 60))),
+            ),
+        )
+        .unwrap();
+
+        let mut spool = TrafficSpool::new(Some(path.clone()), DEFAULT_MAX_BYTES);
+        let pending = spool.load(NOW);
+        assert_eq!(pending, VecDeque::from([batch(3, NOW - 60)]));
+        assert!(!spool.needs_compaction(&pending, NOW));
+        assert!(spool.needs_compaction(&pending, NOW + MAX_AGE_SECS));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_truncate_over_limit() {
+        let path = spool_path("truncate");
+        let line = encode(&batch_entry(&batch(1, NOW))).len() as u64;
+        let mut spool = TrafficSpool::new(Some(path.clone()), line * 4);
+
+        let mut pending = VecDeque::new();
+        for seq in 1..=5 {
+            let b = batch(seq, NOW);
+            spool.append_batch(&b);
+            pending.push_back(b);
+        }
+        assert!(spool.needs_compaction(&pending, NOW));
+
+        // 压缩到上限的一半，从最早的批次开始丢弃
+        assert_eq!(spool.compact(&mut pending, NOW), 3);
+        assert_eq!(pending.iter().map(|b| b.batch.seq).collect::<Vec<_>>(), [4, 5]);
+        assert!(!spool.needs_compaction(&pending, NOW));
+        assert_eq!(TrafficSpool::new(Some(path.clone()), line * 4).load(NOW), pending);
+
+        // 确认记录也会增大文件，压缩后只保留未确认的批次
+        for _ in 0..20 {
+            spool.append_ack(1, 3);
+        }
+        assert!(spool.needs_compaction(&pending, NOW));
+        assert_eq!(spool.compact(&mut pending, NOW), 0);
+        assert_eq!(pending.len(), 2);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,115 @@
+//! 隧道客户端认证
+//!
+//! 客户端连接后打开的第一个流用于认证握手（协议见 `common::protocol::handshake`）：
+//! 节点下发挑战，把客户端的应答交给认证提供者（Controller）校验，再把结果回复客户端。
+//! 从接受连接到收齐客户端应答须在 10 秒内完成，魔数不符的流立即断开。
+//!
+//! 旧版客户端在第一个流上直接发送 token（2 字节长度 + 内容），抓包即可重放，默认拒绝；
+//! 升级过渡期间可设置 `OXIPROXY_ALLOW_LEGACY_TUNNEL_AUTH=true` 临时接受。
+
+use std::future::Future;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, bail, Result};
+use tracing::{debug, warn};
+
+use common::protocol::auth::{ClientAuthProvider, ValidateTokenResponse};
+use common::protocol::handshake::{AuthProof, AuthResult, ClientHello, ServerChallenge, HANDSHAKE_TIMEOUT, MAGIC};
+use common::{TunnelRecvStream, TunnelSendStream};
+
+/// 旧版 token 的最大长度
+const MAX_LEGACY_TOKEN_LEN: usize = 1024;
+
+/// 客户端打开的第一个流（旧版 QUIC 客户端使用单向流）
+pub type FirstStream = (Option<Box<dyn TunnelSendStream>>, Box<dyn TunnelRecvStream>);
+
+enum Credential {
+    /// 握手应答、回复认证结果的流和客户端上报的实例 ID
+    Proof(AuthProof, Box<dyn TunnelSendStream>, Option<String>),
+    Token(String),
+}
+
+/// 是否接受旧版 token 认证
+pub fn legacy_allowed() -> bool {
+    static ALLOWED: OnceLock<bool> = OnceLock::new();
+    *ALLOWED.get_or_init(|| common::env::parse::<bool>("OXIPROXY_ALLOW_LEGACY_TUNNEL_AUTH").unwrap_or(false))
+}
+
+/// 在客户端的第一个流上完成认证
+///
+/// `first_stream` 返回 `None` 表示客户端未打开流就断开了，此时返回 `Ok(None)`。
+/// 认证被拒绝时返回 `allowed = false` 的响应，由调用方记录日志并断开连接。
+/// 同时返回客户端握手时上报的实例 ID（旧版客户端为 `None`）。
+pub async fn authenticate<F>(
+    first_stream: F,
+    auth_provider: &dyn ClientAuthProvider,
+) -> Result<Option<(ValidateTokenResponse, Option<String>)>>
+where
+    F: Future<Output = Option<FirstStream>>,
+{
+    let credential = tokio::time::timeout(HANDSHAKE_TIMEOUT, read_credential(first_stream))
+        .await
+        .map_err(|_| anyhow!("认证握手超时"))??;
+
+    match credential {
+        None => Ok(None),
+        Some(Credential::Token(token)) => {
+            warn!("⚠️ 客户端使用旧版 token 认证，请尽快升级客户端");
+            auth_provider.validate_token(&token).await.map(|resp| Some((resp, None)))
+        }
+        Some(Credential::Proof(proof, mut send, instance_id)) => {
+            let resp = match auth_provider.validate_proof(&proof).await {
+                Ok(resp) => resp,
+                Err(e) => {
+                    let result = AuthResult { allowed: false, reason: Some("认证服务暂不可用".to_string()) };
+                    let _ = send.write_all(&result.encode()).await;
+                    return Err(e);
+                }
+            };
+            let result = AuthResult { allowed: resp.allowed, reason: resp.reject_reason.clone() };
+            send.write_all(&result.encode()).await?;
+            send.finish().await?;
+            Ok(Some((resp, instance_id)))
+        }
+    }
+}
+
+async fn read_credential<F>(first_stream: F) -> Result<Option<Credential>>
+where
+    F: Future<Output = Option<FirstStream>>,
+{
+    let Some((send, mut recv)) = first_stream.await else {
+        return Ok(None);
+    };
+
+    let mut magic = [0u8; 4];
+    recv.read_exact(&mut magic).await?;
+    if magic == MAGIC {
+        let mut send = send.ok_or_else(|| anyhow!("认证握手须使用双向流"))?;
+        let hello = ClientHello::read_after_magic(recv.as_mut()).await?;
+        let metadata = hello.metadata();
+        debug!(
+            "客户端 #{} 握手: 版本 {}, {}/{}",
+            hello.client_id, metadata.version, metadata.os, metadata.arch
+        );
+
+        let challenge = ServerChallenge::new();
+        send.write_all(&challenge.encode()).await?;
+        send.flush().await?;
+        let mac = AuthProof::read_mac(recv.as_mut()).await?;
+        return Ok(Some(Credential::Proof(AuthProof::new(hello, &challenge, mac), send, metadata.instance_id)));
+    }
+
+    if !legacy_allowed() {
+        bail!("不是认证握手（旧版客户端请升级，或设置 OXIPROXY_ALLOW_LEGACY_TUNNEL_AUTH=true）");
+    }
+    // 旧版格式：2 字节长度 + token，已读取的后两个字节是 token 的开头
+    let len = u16::from_be_bytes([magic[0], magic[1]]) as usize;
+    if !(2..=MAX_LEGACY_TOKEN_LEN).contains(&len) {
+        bail!("token 长度无效: {}", len);
+    }
+    let mut token = vec![0u8; len];
+    token[..2].copy_from_slice(&magic[2..]);
+    recv.read_exact(&mut token[2..]).await?;
+    Ok(Some(Credential::Token(String::from_utf8(token)?)))
+}
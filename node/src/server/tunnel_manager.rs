@@ -2,111 +2,260 @@
 //!
 //! 管理隧道监听器的启动、停止和协议切换。
 //! 通过 CancellationToken 实现可取消的监听循环。
+//!
+//! 协议切换有两种方式：`switch_protocol` 在原端口上重启监听器，会断开所有客户端；
+//! `stage` 在备用端口（`OXIPROXY_STAGING_PORT`）上启动新监听器并保留旧监听器，
+//! Controller 逐个迁移客户端后调用 `retire_previous` 停止旧监听器。
+//! 分阶段切换后监听器留在备用端口，下一次分阶段切换再切回主端口。
+//!
+//! 多网卡服务器可以用 `OXIPROXY_TUNNEL_BIND_ADDRS`（逗号分隔的 IP）限定隧道监听的地址，
+//! 每个地址各启动一个监听器，未设置时监听所有地址。
 
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 use tracing::{info, error, warn};
 
+use crate::server::proxy_events;
 use crate::server::proxy_server::ProxyServer;
-use common::KcpConfig;
+use common::{KcpConfig, QuicConfig};
+
+/// Controller 下发的隧道传输参数（未设置时使用默认参数）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransportSettings {
+    pub kcp: Option<KcpConfig>,
+    pub quic: Option<QuicConfig>,
+}
+
+/// 运行中的监听器任务
+struct ListenerTask {
+    cancel: CancellationToken,
+    handle: JoinHandle<()>,
+}
+
+impl ListenerTask {
+    /// 取消并等待任务结束，超时 5 秒
+    async fn stop(self) {
+        self.cancel.cancel();
+        if tokio::time::timeout(std::time::Duration::from_secs(5), self.handle).await.is_err() {
+            warn!("隧道监听器停止超时，强制终止");
+        }
+    }
+}
 
 pub struct TunnelManager {
     proxy_server: Arc<ProxyServer>,
+    /// 隧道监听的 IP，为空时监听所有地址
+    bind_ips: Vec<IpAddr>,
     bind_port: u16,
+    /// 分阶段切换使用的备用端口，未配置时只能原地切换
+    staging_port: Option<u16>,
+    /// 当前监听器所在端口
+    active_port: RwLock<u16>,
     current_protocol: RwLock<String>,
-    cancel_token: RwLock<Option<CancellationToken>>,
-    listener_handle: RwLock<Option<JoinHandle<()>>>,
+    current_settings: RwLock<TransportSettings>,
+    current: RwLock<Option<ListenerTask>>,
+    /// 分阶段切换中等待停止的旧监听器
+    previous: RwLock<Option<ListenerTask>>,
 }
 
 impl TunnelManager {
     pub fn new(proxy_server: Arc<ProxyServer>, bind_port: u16) -> Self {
+        let staging_port = common::env::parse::<u16>("OXIPROXY_STAGING_PORT").filter(|p| *p != 0 && *p != bind_port);
         Self {
             proxy_server,
+            bind_ips: tunnel_bind_ips(),
             bind_port,
+            staging_port,
+            active_port: RwLock::new(bind_port),
             current_protocol: RwLock::new(String::new()),
-            cancel_token: RwLock::new(None),
-            listener_handle: RwLock::new(None),
+            current_settings: RwLock::new(TransportSettings::default()),
+            current: RwLock::new(None),
+            previous: RwLock::new(None),
         }
     }
 
+    /// 当前监听器所在端口
+    pub async fn active_port(&self) -> u16 {
+        *self.active_port.read().await
+    }
+
     /// 启动隧道监听器
-    pub async fn start(&self, protocol: &str, kcp_config: Option<KcpConfig>) -> anyhow::Result<()> {
+    pub async fn start(&self, protocol: &str, settings: TransportSettings) -> anyhow::Result<()> {
         self.stop().await;
 
-        let bind_addr = format!("0.0.0.0:{}", self.bind_port);
-        let cancel = CancellationToken::new();
-        let cancel_clone = cancel.clone();
-        let proxy_server = self.proxy_server.clone();
-        let proto = protocol.to_string();
-
-        let handle = tokio::spawn(async move {
-            tokio::select! {
-                result = async {
-                    match proto.as_str() {
-                        "kcp" => {
-                            info!("启动 KCP 隧道服务: {}", bind_addr);
-                            proxy_server.run_kcp(bind_addr, kcp_config).await
-                        }
-                        "tcp" => {
-                            info!("启动 TCP 隧道服务: {}", bind_addr);
-                            proxy_server.run_tcp(bind_addr).await
-                        }
-                        _ => {
-                            info!("启动 QUIC 隧道服务: {}", bind_addr);
-                            proxy_server.run(bind_addr).await
-                        }
-                    }
-                } => {
-                    if let Err(e) = result {
-                        error!("隧道服务错误: {}", e);
-                    }
-                }
-                _ = cancel_clone.cancelled() => {
-                    info!("隧道服务已停止");
-                }
-            }
-        });
+        let port = self.active_port().await;
+        let task = self.spawn_listener(protocol, settings.clone(), port);
 
         *self.current_protocol.write().await = protocol.to_string();
-        *self.cancel_token.write().await = Some(cancel);
-        *self.listener_handle.write().await = Some(handle);
+        *self.current_settings.write().await = settings;
+        *self.current.write().await = Some(task);
 
         Ok(())
     }
 
-    /// 停止当前隧道监听器
+    /// 停止当前隧道监听器（以及分阶段切换中的旧监听器）
     pub async fn stop(&self) {
-        if let Some(cancel) = self.cancel_token.write().await.take() {
+        self.retire_previous().await;
+        if let Some(task) = self.current.write().await.take() {
             info!("正在停止当前隧道监听器...");
-            cancel.cancel();
-        }
-        if let Some(handle) = self.listener_handle.write().await.take() {
-            // 等待任务结束，超时 5 秒
-            match tokio::time::timeout(std::time::Duration::from_secs(5), handle).await {
-                Ok(_) => {}
-                Err(_) => {
-                    warn!("隧道监听器停止超时，强制终止");
-                }
-            }
+            task.stop().await;
         }
     }
 
-    /// 切换协议
-    pub async fn switch_protocol(&self, new_protocol: &str) -> anyhow::Result<()> {
-        let current = self.current_protocol.read().await.clone();
-        if current == new_protocol {
+    /// 切换协议（当前协议的传输参数变更同样会重启监听器）
+    pub async fn switch_protocol(&self, new_protocol: &str, settings: TransportSettings) -> anyhow::Result<()> {
+        if !self.needs_switch(new_protocol, &settings).await {
             info!("协议未变更 ({}), 无需切换", new_protocol);
             return Ok(());
         }
 
-        info!("切换隧道协议: {} -> {}", current, new_protocol);
+        let previous = self.current_protocol.read().await.clone();
 
         // 停止后短暂等待端口释放
         self.stop().await;
         tokio::time::sleep(std::time::Duration::from_secs(1)).await;
 
-        self.start(new_protocol, None).await
+        self.start(new_protocol, settings).await?;
+        proxy_events::protocol_switched(&previous, new_protocol, self.active_port().await);
+        Ok(())
     }
+
+    /// 分阶段切换：在备用端口上启动新协议的监听器，旧监听器继续服务尚未迁移的客户端
+    ///
+    /// 返回新监听器的端口。未配置备用端口时返回错误，由 Controller 回退为原地切换。
+    pub async fn stage(&self, new_protocol: &str, settings: TransportSettings) -> anyhow::Result<u16> {
+        let current_port = self.active_port().await;
+        if !self.needs_switch(new_protocol, &settings).await {
+            info!("协议未变更 ({}), 无需切换", new_protocol);
+            return Ok(current_port);
+        }
+        let port = if current_port == self.bind_port {
+            self.staging_port
+                .ok_or_else(|| anyhow::anyhow!("未配置备用隧道端口（OXIPROXY_STAGING_PORT），无法分阶段切换"))?
+        } else {
+            self.bind_port
+        };
+
+        // 上一次分阶段切换遗留的旧监听器占用着目标端口
+        self.retire_previous().await;
+
+        let task = self.spawn_listener(new_protocol, settings.clone(), port);
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        if task.handle.is_finished() {
+            return Err(anyhow::anyhow!("在备用端口 {} 上启动 {} 监听器失败", port, new_protocol.to_uppercase()));
+        }
+
+        let previous = self.current_protocol.read().await.clone();
+        let old = self.current.write().await.replace(task);
+        *self.previous.write().await = old;
+        *self.active_port.write().await = port;
+        *self.current_protocol.write().await = new_protocol.to_string();
+        *self.current_settings.write().await = settings;
+        info!("新监听器已在端口 {} 上启动，旧监听器 (端口 {}) 等待客户端迁移", port, current_port);
+        proxy_events::protocol_switched(&previous, new_protocol, port);
+
+        Ok(port)
+    }
+
+    /// 停止分阶段切换中保留的旧监听器
+    pub async fn retire_previous(&self) {
+        if let Some(task) = self.previous.write().await.take() {
+            info!("停止旧隧道监听器");
+            task.stop().await;
+        }
+    }
+
+    /// 协议或当前协议的传输参数是否变化
+    async fn needs_switch(&self, new_protocol: &str, settings: &TransportSettings) -> bool {
+        let current = self.current_protocol.read().await.clone();
+        let params_changed = {
+            let current_settings = self.current_settings.read().await;
+            match new_protocol {
+                "kcp" => current_settings.kcp != settings.kcp,
+                "tcp" => false,
+                _ => current_settings.quic != settings.quic,
+            }
+        };
+        if current == new_protocol && !params_changed {
+            return false;
+        }
+
+        if current == new_protocol {
+            info!("{} 参数变更，重启隧道监听器", new_protocol.to_uppercase());
+        } else {
+            info!("切换隧道协议: {} -> {}", current, new_protocol);
+        }
+        true
+    }
+
+    fn spawn_listener(&self, protocol: &str, settings: TransportSettings, port: u16) -> ListenerTask {
+        let bind_addrs: Vec<String> = if self.bind_ips.is_empty() {
+            vec![common::utils::wildcard_addr(port).to_string()]
+        } else {
+            self.bind_ips.iter().map(|ip| SocketAddr::new(*ip, port).to_string()).collect()
+        };
+        let cancel = CancellationToken::new();
+        let cancel_clone = cancel.clone();
+
+        // 每个监听地址一个任务，任一地址监听失败不影响其他地址
+        let mut runs = tokio::task::JoinSet::new();
+        for bind_addr in bind_addrs {
+            let proxy_server = self.proxy_server.clone();
+            let proto = protocol.to_string();
+            let settings = settings.clone();
+            runs.spawn(async move {
+                let result = match proto.as_str() {
+                    "kcp" => {
+                        info!("启动 KCP 隧道服务: {}", bind_addr);
+                        proxy_server.run_kcp(bind_addr.clone(), settings.kcp).await
+                    }
+                    "tcp" => {
+                        info!("启动 TCP 隧道服务: {}", bind_addr);
+                        proxy_server.run_tcp(bind_addr.clone()).await
+                    }
+                    _ => {
+                        info!("启动 QUIC 隧道服务: {}", bind_addr);
+                        proxy_server.run(bind_addr.clone(), settings.quic).await
+                    }
+                };
+                if let Err(e) = result {
+                    error!("隧道服务错误 ({}): {}", bind_addr, e);
+                }
+            });
+        }
+
+        let handle = tokio::spawn(async move {
+            tokio::select! {
+                _ = async { while runs.join_next().await.is_some() {} } => {}
+                _ = cancel_clone.cancelled() => {
+                    // 等待所有监听器释放端口后再返回，以便立即在同一端口重新监听
+                    runs.shutdown().await;
+                    info!("隧道服务已停止");
+                }
+            }
+        });
+
+        ListenerTask { cancel, handle }
+    }
+}
+
+/// 解析 `OXIPROXY_TUNNEL_BIND_ADDRS`，忽略无效地址
+fn tunnel_bind_ips() -> Vec<IpAddr> {
+    let Some(list) = common::env::var("OXIPROXY_TUNNEL_BIND_ADDRS") else {
+        return Vec::new();
+    };
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .filter_map(|s| match common::utils::normalize_host(s).parse::<IpAddr>() {
+            Ok(ip) => Some(ip),
+            Err(_) => {
+                warn!("OXIPROXY_TUNNEL_BIND_ADDRS 中的地址无效: {}", s);
+                None
+            }
+        })
+        .collect()
 }
@@ -0,0 +1,91 @@
+//! Controller REST API 客户端
+
+use anyhow::{anyhow, bail, Result};
+use reqwest::{Method, RequestBuilder};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Controller 统一响应格式
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    success: bool,
+    data: Option<Value>,
+    message: String,
+}
+
+pub struct ApiClient {
+    http: reqwest::Client,
+    base_url: String,
+    auth: Option<Auth>,
+}
+
+/// 请求携带的凭据
+pub enum Auth {
+    /// 登录令牌（JWT），只用于创建 API 令牌等需要二次验证的操作
+    Bearer(String),
+    /// 长期有效的 API 令牌
+    ApiKey(String),
+}
+
+impl ApiClient {
+    pub fn new(base_url: &str, auth: Option<Auth>) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .build()?;
+
+        Ok(Self {
+            http,
+            base_url: format!("{}/api", base_url.trim_end_matches('/')),
+            auth,
+        })
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let builder = self.http.request(method, format!("{}{}", self.base_url, path));
+        match &self.auth {
+            Some(Auth::Bearer(token)) => builder.bearer_auth(token),
+            Some(Auth::ApiKey(key)) => builder.header("X-API-Key", key),
+            None => builder,
+        }
+    }
+
+    async fn send(&self, builder: RequestBuilder) -> Result<Value> {
+        let response = builder.send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+
+        let parsed: ApiResponse = serde_json::from_str(&body).map_err(|_| {
+            if status == reqwest::StatusCode::UNAUTHORIZED {
+                anyhow!("未认证或 API 令牌已吊销，请重新执行 rfrpctl login")
+            } else {
+                anyhow!("Controller 返回了无法解析的响应 (HTTP {})", status)
+            }
+        })?;
+
+        if !parsed.success {
+            if status == reqwest::StatusCode::UNAUTHORIZED {
+                bail!("{}（请重新执行 rfrpctl login）", parsed.message);
+            }
+            bail!("{}", parsed.message);
+        }
+
+        Ok(parsed.data.unwrap_or(Value::Null))
+    }
+
+    pub async fn get(&self, path: &str, query: &[(&str, String)]) -> Result<Value> {
+        self.send(self.request(Method::GET, path).query(query)).await
+    }
+
+    pub async fn post(&self, path: &str, body: &Value) -> Result<Value> {
+        self.send(self.request(Method::POST, path).json(body)).await
+    }
+
+    /// 不校验乐观锁版本号的更新（`If-Match: *`），用于启用 / 禁用这类直接覆盖的操作
+    pub async fn put_overwrite(&self, path: &str, body: &Value) -> Result<Value> {
+        self.send(self.request(Method::PUT, path).header(reqwest::header::IF_MATCH, "*").json(body)).await
+    }
+
+    pub async fn delete(&self, path: &str) -> Result<Value> {
+        self.send(self.request(Method::DELETE, path)).await
+    }
+}
@@ -0,0 +1,78 @@
+//! 本地凭据存储
+//!
+//! 登录成功后创建一个长期有效的 API 令牌，与 Controller 地址一起保存到配置文件，后续命令无需重复登录。
+//! 登录令牌（JWT）会过期，也会因会话空闲超时失效，因此不保存。
+//! 默认路径：Unix 为 `~/.config/rfrpctl/config.json`，Windows 为 `%APPDATA%\rfrpctl\config.json`，
+//! 可通过环境变量 `RFRPCTL_CONFIG` 覆盖。
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CtlConfig {
+    /// Controller Web API 地址，例如 http://127.0.0.1:3000
+    pub url: Option<String>,
+    /// API 令牌，请求时通过 `X-API-Key` 头携带
+    pub api_key: Option<String>,
+    /// API 令牌在 Controller 上的 ID，`logout` 时用于吊销
+    pub api_key_id: Option<i64>,
+    /// 登录用户名，需要二次验证的操作用它重新登录
+    pub username: Option<String>,
+}
+
+pub fn config_path() -> Result<PathBuf> {
+    if let Ok(path) = std::env::var("RFRPCTL_CONFIG") {
+        if !path.is_empty() {
+            return Ok(PathBuf::from(path));
+        }
+    }
+
+    #[cfg(windows)]
+    let base = std::env::var("APPDATA").map(PathBuf::from);
+    #[cfg(not(windows))]
+    let base = std::env::var("HOME").map(|home| PathBuf::from(home).join(".config"));
+
+    let base = base.map_err(|_| anyhow!("无法确定配置目录，请设置 RFRPCTL_CONFIG"))?;
+    Ok(base.join("rfrpctl").join("config.json"))
+}
+
+impl CtlConfig {
+    pub fn load() -> Result<Self> {
+        let path = config_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("读取配置文件失败: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("解析配置文件失败: {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = config_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("写入配置文件失败: {}", path.display()))?;
+
+        // 令牌等同于密码，仅允许当前用户读取
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        }
+
+        Ok(path)
+    }
+
+    pub fn clear() -> Result<()> {
+        let path = config_path()?;
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        Ok(())
+    }
+}
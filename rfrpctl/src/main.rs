@@ -0,0 +1,459 @@
+mod api;
+mod config;
+mod output;
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+
+use crate::api::{ApiClient, Auth};
+use crate::config::CtlConfig;
+use crate::output::{Column, OutputFormat};
+
+#[derive(Parser)]
+#[command(name = "rfrpctl", version, about = "OxiProxy 命令行管理工具")]
+struct Cli {
+    /// Controller Web API 地址（默认使用 login 时保存的地址）
+    #[arg(long, global = true, env = "RFRPCTL_URL")]
+    url: Option<String>,
+
+    /// API 令牌（默认使用 login 时创建并保存的令牌）
+    #[arg(long, global = true, env = "RFRPCTL_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    /// 输出格式
+    #[arg(short, long, global = true, value_enum, default_value = "table")]
+    output: OutputFormat,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 登录并创建、保存 API 令牌
+    Login {
+        /// 用户名
+        #[arg(long)]
+        username: String,
+
+        /// 密码（未指定时从 RFRPCTL_PASSWORD 或标准输入读取）
+        #[arg(long, env = "RFRPCTL_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+
+        /// API 令牌的有效天数（默认不过期）
+        #[arg(long)]
+        expires_in_days: Option<u32>,
+    },
+
+    /// 吊销并清除保存的 API 令牌
+    Logout,
+
+    /// 当前用户的 API 令牌
+    Token {
+        #[command(subcommand)]
+        command: TokenCommand,
+    },
+
+    /// 隧道管理
+    Proxy {
+        #[command(subcommand)]
+        command: ProxyCommand,
+    },
+
+    /// 客户端管理
+    Client {
+        #[command(subcommand)]
+        command: ClientCommand,
+    },
+
+    /// 节点管理
+    Node {
+        #[command(subcommand)]
+        command: NodeCommand,
+    },
+
+    /// 用户管理（管理员）
+    User {
+        #[command(subcommand)]
+        command: UserCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum TokenCommand {
+    /// 列出 API 令牌
+    List,
+    /// 吊销 API 令牌
+    Revoke { id: i64 },
+}
+
+#[derive(Subcommand)]
+enum ProxyCommand {
+    /// 列出隧道
+    List {
+        #[arg(long)]
+        client_id: Option<i64>,
+        #[arg(long)]
+        node_id: Option<i64>,
+        /// 关键字搜索
+        #[arg(long)]
+        search: Option<String>,
+    },
+    /// 启用隧道
+    Enable { id: i64 },
+    /// 禁用隧道
+    Disable { id: i64 },
+    /// 删除隧道
+    Delete { id: i64 },
+    /// 显示隧道的访客连接命令 / 地址
+    Endpoints { id: i64 },
+}
+
+#[derive(Subcommand)]
+enum ClientCommand {
+    /// 列出客户端
+    List {
+        /// 仅显示在线客户端
+        #[arg(long)]
+        online: bool,
+    },
+    /// 客户端令牌
+    Token {
+        #[command(subcommand)]
+        command: ClientTokenCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ClientTokenCommand {
+    /// 显示客户端令牌
+    Show { id: i64 },
+}
+
+#[derive(Subcommand)]
+enum NodeCommand {
+    /// 列出节点
+    List,
+    /// 禁用节点上的所有隧道，以便对节点进行维护（Controller 记录被禁用的隧道）
+    Drain {
+        id: i64,
+        /// 二次验证密码（未指定时从 RFRPCTL_PASSWORD 或标准输入读取）
+        #[arg(long, env = "RFRPCTL_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+    },
+    /// 结束排空：只重新启用排空时禁用的隧道
+    Undrain {
+        id: i64,
+        /// 二次验证密码（未指定时从 RFRPCTL_PASSWORD 或标准输入读取）
+        #[arg(long, env = "RFRPCTL_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum UserCommand {
+    /// 列出用户
+    List,
+}
+
+const TOKEN_COLUMNS: &[Column] = &[
+    ("ID", "id"),
+    ("NAME", "name"),
+    ("PREFIX", "prefix"),
+    ("EXPIRES", "expiresAt"),
+    ("LAST USED", "lastUsedAt"),
+    ("CREATED", "createdAt"),
+];
+
+const ENDPOINT_COLUMNS: &[Column] = &[("类型", "label"), ("连接", "value")];
+
+const PROXY_COLUMNS: &[Column] = &[
+    ("ID", "id"),
+    ("NAME", "name"),
+    ("CLIENT", "client_id"),
+    ("TYPE", "type"),
+    ("LOCAL IP", "localIP"),
+    ("LOCAL PORT", "localPort"),
+    ("REMOTE PORT", "remotePort"),
+    ("NODE", "nodeId"),
+    ("ENABLED", "enabled"),
+];
+
+const CLIENT_COLUMNS: &[Column] = &[
+    ("ID", "id"),
+    ("NAME", "name"),
+    ("ONLINE", "is_online"),
+    ("PUBLIC IP", "publicIp"),
+    ("REGION", "region"),
+    ("USER", "userId"),
+    ("VERSION", "version"),
+];
+
+const NODE_COLUMNS: &[Column] = &[
+    ("ID", "id"),
+    ("NAME", "name"),
+    ("ONLINE", "isOnline"),
+    ("TYPE", "nodeType"),
+    ("TUNNEL ADDR", "tunnelAddr"),
+    ("TUNNEL PORT", "tunnelPort"),
+    ("PROTOCOL", "tunnelProtocol"),
+    ("REGION", "region"),
+];
+
+const USER_COLUMNS: &[Column] = &[
+    ("ID", "id"),
+    ("USERNAME", "username"),
+    ("ADMIN", "is_admin"),
+    ("NODES", "node_count"),
+    ("CLIENTS", "currentClientCount"),
+    ("QUOTA(GB)", "trafficQuotaGb"),
+];
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        eprintln!("错误: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    if let Command::Logout = cli.command {
+        // 配置文件损坏时仍然清除
+        if let Ok(stored) = CtlConfig::load() {
+            revoke_stored(&stored).await;
+        }
+        CtlConfig::clear()?;
+        println!("✓ 已清除保存的凭据");
+        return Ok(());
+    }
+
+    let stored = CtlConfig::load()?;
+    let url = cli
+        .url
+        .or_else(|| stored.url.clone())
+        .ok_or_else(|| anyhow!("未指定 Controller 地址，请使用 --url 或先执行 rfrpctl login --url <URL>"))?;
+    let format = cli.output;
+
+    let command = match cli.command {
+        Command::Login { username, password, expires_in_days } => {
+            return login(&url, username, password, expires_in_days, &stored).await
+        }
+        other => other,
+    };
+
+    let Some(api_key) = cli.api_key.or(stored.api_key) else {
+        return Err(anyhow!("未登录，请先执行 rfrpctl login 或通过 --api-key 指定 API 令牌"));
+    };
+    let client = ApiClient::new(&url, Some(Auth::ApiKey(api_key)))?;
+
+    match command {
+        Command::Login { .. } | Command::Logout => unreachable!(),
+
+        Command::Token { command } => match command {
+            TokenCommand::List => {
+                let data = client.get("/auth/me/api-tokens", &[]).await?;
+                output::print(format, &data, TOKEN_COLUMNS);
+            }
+            TokenCommand::Revoke { id } => {
+                client.delete(&format!("/auth/me/api-tokens/{}", id)).await?;
+                println!("✓ API 令牌 #{} 已吊销", id);
+            }
+        },
+
+        Command::Proxy { command } => match command {
+            ProxyCommand::List { client_id, node_id, search } => {
+                let mut query = Vec::new();
+                if let Some(id) = client_id {
+                    query.push(("client_id", id.to_string()));
+                }
+                if let Some(id) = node_id {
+                    query.push(("node_id", id.to_string()));
+                }
+                if let Some(q) = search {
+                    query.push(("q", q));
+                }
+                let data = client.get("/proxies", &query).await?;
+                output::print(format, &data, PROXY_COLUMNS);
+            }
+            ProxyCommand::Enable { id } => {
+                client.put_overwrite(&format!("/proxies/{}", id), &json!({ "enabled": true })).await?;
+                println!("✓ 隧道 #{} 已启用", id);
+            }
+            ProxyCommand::Disable { id } => {
+                client.put_overwrite(&format!("/proxies/{}", id), &json!({ "enabled": false })).await?;
+                println!("✓ 隧道 #{} 已禁用", id);
+            }
+            ProxyCommand::Delete { id } => {
+                client.delete(&format!("/proxies/{}", id)).await?;
+                println!("✓ 隧道 #{} 已删除", id);
+            }
+            ProxyCommand::Endpoints { id } => {
+                let data = client.get(&format!("/proxies/{}/endpoints", id), &[]).await?;
+                output::print(format, &data, ENDPOINT_COLUMNS);
+            }
+        },
+
+        Command::Client { command } => match command {
+            ClientCommand::List { online } => {
+                let query = if online { vec![("online", "true".to_string())] } else { vec![] };
+                let data = client.get("/clients", &query).await?;
+                output::print(format, &data, CLIENT_COLUMNS);
+            }
+            ClientCommand::Token { command: ClientTokenCommand::Show { id } } => {
+                let data = client.get(&format!("/clients/{}", id), &[]).await?;
+                match format {
+                    OutputFormat::Json => {
+                        output::print(format, &json!({ "id": id, "token": data.get("token") }), &[])
+                    }
+                    OutputFormat::Table => println!(
+                        "{}",
+                        data.get("token").and_then(Value::as_str).unwrap_or_default()
+                    ),
+                }
+            }
+        },
+
+        Command::Node { command } => match command {
+            NodeCommand::List => {
+                let data = client.get("/nodes", &[]).await?;
+                output::print(format, &data, NODE_COLUMNS);
+            }
+            NodeCommand::Drain { id, password } => {
+                let client = elevated_session(&url, stored.username.as_deref(), password).await?;
+                let data = client.post(&format!("/nodes/{}/drain", id), &json!({})).await?;
+                print_drain_result(format, &data, |changed, failed| {
+                    format!("✓ 节点 #{} 已排空: 禁用 {} 个隧道，失败 {} 个（rfrpctl node undrain {} 恢复）", id, changed, failed, id)
+                });
+            }
+            NodeCommand::Undrain { id, password } => {
+                let client = elevated_session(&url, stored.username.as_deref(), password).await?;
+                let data = client.post(&format!("/nodes/{}/undrain", id), &json!({})).await?;
+                print_drain_result(format, &data, |changed, failed| {
+                    format!("✓ 节点 #{} 已结束排空: 重新启用 {} 个隧道，失败 {} 个", id, changed, failed)
+                });
+            }
+        },
+
+        Command::User { command } => match command {
+            UserCommand::List => {
+                let data = client.get("/users", &[]).await?;
+                output::print(format, &data, USER_COLUMNS);
+            }
+        },
+    }
+
+    Ok(())
+}
+
+/// 打印排空 / 恢复结果，`summary` 接收成功和失败的数量
+fn print_drain_result(format: OutputFormat, data: &Value, summary: impl Fn(usize, usize) -> String) {
+    if let OutputFormat::Json = format {
+        output::print(format, data, &[]);
+        return;
+    }
+    let ids = |key: &str| -> Vec<i64> {
+        data.get(key).and_then(Value::as_array).map(|a| a.iter().filter_map(Value::as_i64).collect()).unwrap_or_default()
+    };
+    let (changed, skipped, failed) = (ids("changed"), ids("skipped"), ids("failed"));
+    for id in &changed {
+        println!("  隧道 #{}", id);
+    }
+    if !skipped.is_empty() {
+        println!("  跳过（已删除、已启用或已迁移到其他节点）: {:?}", skipped);
+    }
+    if !failed.is_empty() {
+        eprintln!("  失败: {:?}", failed);
+    }
+    println!("{}", summary(changed.len(), failed.len()));
+}
+
+/// 用密码登录，再创建长期有效的 API 令牌保存到配置文件（登录令牌会过期，不保存）
+async fn login(
+    url: &str,
+    username: String,
+    password: Option<String>,
+    expires_in_days: Option<u32>,
+    stored: &CtlConfig,
+) -> Result<()> {
+    // 创建 API 令牌需要二次验证
+    let client = elevated_session(url, Some(&username), password).await?;
+    let host = std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string());
+    let data = client
+        .post(
+            "/auth/me/api-tokens",
+            &json!({ "name": format!("rfrpctl@{}", host), "expiresInDays": expires_in_days }),
+        )
+        .await?;
+    let api_key = data
+        .get("token")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("创建 API 令牌的响应中缺少 token"))?;
+
+    // 重新登录时吊销之前保存的令牌，避免遗留无人使用的令牌
+    if stored.url.as_deref() == Some(url) {
+        revoke_stored(stored).await;
+    }
+
+    let path = CtlConfig {
+        url: Some(url.to_string()),
+        api_key: Some(api_key.to_string()),
+        api_key_id: data.get("id").and_then(Value::as_i64),
+        username: Some(username.clone()),
+    }
+    .save()?;
+    println!("✓ 已登录 {} ({})", url, username);
+    println!("API 令牌已保存到: {}", path.display());
+    Ok(())
+}
+
+/// 用密码登录并通过二次验证，返回使用登录令牌的客户端
+///
+/// API 令牌无法通过二次验证，创建令牌、排空节点等敏感操作需要临时使用登录会话。
+async fn elevated_session(url: &str, username: Option<&str>, password: Option<String>) -> Result<ApiClient> {
+    let username = username.ok_or_else(|| anyhow!("该操作需要二次验证，请先执行 rfrpctl login --username <用户名>"))?;
+    let password = match password {
+        Some(p) => p,
+        None => prompt(&format!("{} 的密码: ", username))?,
+    };
+
+    let client = ApiClient::new(url, None)?;
+    let data = client
+        .post("/auth/login", &json!({ "username": username, "password": password }))
+        .await?;
+    let session = data
+        .get("token")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("登录响应中缺少 token"))?;
+
+    let client = ApiClient::new(url, Some(Auth::Bearer(session.to_string())))?;
+    client.post("/auth/reauth", &json!({ "password": password })).await?;
+    Ok(client)
+}
+
+/// 吊销配置文件中保存的 API 令牌，失败时只提示（令牌可能已被吊销或 Controller 不可达）
+async fn revoke_stored(stored: &CtlConfig) {
+    let (Some(url), Some(api_key), Some(id)) = (&stored.url, &stored.api_key, stored.api_key_id) else {
+        return;
+    };
+    let result = match ApiClient::new(url, Some(Auth::ApiKey(api_key.clone()))) {
+        Ok(client) => client.delete(&format!("/auth/me/api-tokens/{}", id)).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        eprintln!("吊销 API 令牌 #{} 失败: {:#}", id, e);
+    }
+}
+
+fn prompt(label: &str) -> Result<String> {
+    print!("{}", label);
+    std::io::stdout().flush()?;
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
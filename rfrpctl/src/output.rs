@@ -0,0 +1,73 @@
+//! 命令输出格式（表格 / JSON）
+
+use clap::ValueEnum;
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+/// 表格列定义：(表头, JSON 字段名)
+pub type Column = (&'static str, &'static str);
+
+pub fn print(format: OutputFormat, data: &Value, columns: &[Column]) {
+    match format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(data).unwrap_or_default());
+        }
+        OutputFormat::Table => print_table(data, columns),
+    }
+}
+
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => "-".to_string(),
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Bool(b)) => if *b { "yes" } else { "no" }.to_string(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn print_table(data: &Value, columns: &[Column]) {
+    let rows: Vec<&Value> = match data {
+        Value::Array(items) => items.iter().collect(),
+        Value::Null => Vec::new(),
+        other => vec![other],
+    };
+
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| columns.iter().map(|(_, key)| cell(row.get(*key))).collect())
+        .collect();
+
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, (header, _))| {
+            cells
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain(std::iter::once(header.len()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let format_row = |values: Vec<String>| {
+        values
+            .iter()
+            .zip(&widths)
+            .map(|(value, width)| format!("{:<width$}", value, width = *width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    println!("{}", format_row(columns.iter().map(|(header, _)| header.to_string()).collect()));
+    for row in cells {
+        println!("{}", format_row(row));
+    }
+}